@@ -3,15 +3,17 @@ use lazy_static::lazy_static;
 
 // Platform-specific encoding detection
 #[cfg(windows)]
-use {codepage, windows_sys::Win32::Globalization::GetACP};
+use {codepage, windows_sys::Win32::Globalization::GetOEMCP};
 
 lazy_static! {
     static ref SYSTEM_ENCODING: &'static encoding_rs::Encoding = {
         #[cfg(windows)]
         {
-            // On Windows, we use the GetACP function to get the system's active code page.
-            let acp = unsafe { GetACP() };
-            codepage::to_encoding(acp.try_into().unwrap())
+            // PowerShell itself is switched to UTF-8, but native console programs
+            // it runs still write in the OEM code page (e.g. 437 or 936), not the
+            // ANSI one.
+            let oemcp = unsafe { GetOEMCP() };
+            codepage::to_encoding(oemcp.try_into().unwrap())
                 .unwrap_or(encoding_rs::UTF_8) // Fallback to UTF-8 if the code page is not recognized
         }
         #[cfg(not(windows))]
@@ -1,13 +1,14 @@
 use chrono::Utc;
 use std::process::Stdio;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::fs;
 use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command as TokioCommand;
 use tokio::sync::{mpsc, oneshot};
 use tracing::{error, info, warn};
 
 use crate::agent_modules::command::encoding::decode_chunk;
+use crate::agent_modules::command::shell::{SCRIPT_EXTENSION, script_command};
 use crate::agent_modules::command::tracker::RunningCommandsTracker;
 use nodenexus_common::agent_service::{
     BatchAgentCommandRequest, BatchCommandOutputStream, BatchCommandResult, CommandStatus,
@@ -25,12 +26,15 @@ pub(super) async fn manage_command_lifecycle(
     mut term_rx: oneshot::Receiver<()>, // Termination signal receiver
 ) {
     let child_command_id = request.command_id.clone();
+    let trace_id = request.trace_id.clone();
+    let timeout = (request.timeout_seconds > 0).then(|| Duration::from_secs(u64::from(request.timeout_seconds)));
     let command_to_run = request.content;
     // --- Command Pre-flight Checks ---
     if command_to_run.is_empty() {
         send_error_result(
             "Command content was empty.",
             &child_command_id,
+            &trace_id,
             &tx_to_server,
             vps_db_id,
             &agent_secret,
@@ -44,14 +48,14 @@ pub(super) async fn manage_command_lifecycle(
     info!(command_id = %child_command_id, "Executing script content:\n{}", command_to_run);
 
     // --- Temporary Script File Creation ---
-    let script_extension = if cfg!(windows) { ".ps1" } else { ".sh" };
-    let temp_file = match tempfile::Builder::new().suffix(script_extension).tempfile() {
+    let temp_file = match tempfile::Builder::new().suffix(SCRIPT_EXTENSION).tempfile() {
         Ok(file) => file,
         Err(e) => {
             let error_msg = format!("Failed to create temporary script file: {e}");
             send_error_result(
                 &error_msg,
                 &child_command_id,
+                &trace_id,
                 &tx_to_server,
                 vps_db_id,
                 &agent_secret,
@@ -78,6 +82,7 @@ pub(super) async fn manage_command_lifecycle(
         send_error_result(
             &error_msg,
             &child_command_id,
+            &trace_id,
             &tx_to_server,
             vps_db_id,
             &agent_secret,
@@ -95,24 +100,7 @@ pub(super) async fn manage_command_lifecycle(
     info!("Temporary script file created at: {:?}", temp_path);
 
     // --- Command Spawning ---
-    #[cfg(windows)]
-    let mut command = {
-        let mut cmd = TokioCommand::new("powershell.exe");
-        cmd.args([
-            "-NoProfile",
-            "-NonInteractive",
-            "-File",
-            temp_path.to_str().unwrap(),
-        ]);
-        cmd
-    };
-
-    #[cfg(not(windows))]
-    let mut command = {
-        let mut cmd = TokioCommand::new("/bin/bash");
-        cmd.arg(temp_path.to_str().unwrap());
-        cmd
-    };
+    let mut command = script_command(&temp_path);
 
     info!("Executing command: {:?}", command);
 
@@ -129,6 +117,7 @@ pub(super) async fn manage_command_lifecycle(
             send_error_result(
                 &error_msg,
                 &child_command_id,
+                &trace_id,
                 &tx_to_server,
                 vps_db_id,
                 &agent_secret,
@@ -155,6 +144,7 @@ pub(super) async fn manage_command_lifecycle(
                 info!("Kill signal sent successfully.");
                 BatchCommandResult {
                     command_id: child_command_id.clone(),
+                    trace_id: trace_id.clone(),
                     status: CommandStatus::Terminated.into(),
                     exit_code: -1, // Convention for terminated process
                     error_message: "Command terminated by user request.".to_string(),
@@ -165,6 +155,7 @@ pub(super) async fn manage_command_lifecycle(
                 error!(error = %error_msg);
                 BatchCommandResult {
                     command_id: child_command_id.clone(),
+                    trace_id: trace_id.clone(),
                     status: CommandStatus::Failure.into(),
                     exit_code: -1,
                     error_message: error_msg,
@@ -173,7 +164,32 @@ pub(super) async fn manage_command_lifecycle(
         }
     }
 
-    // Case 2: The command runs to completion
+    // Case 2: The command ran longer than its timeout
+    _ = async {
+        match timeout {
+            Some(timeout) => tokio::time::sleep(timeout).await,
+            None => std::future::pending().await,
+        }
+    } => {
+        let timeout_secs = timeout.map(|timeout| timeout.as_secs()).unwrap_or_default();
+        warn!(timeout_secs, "Command timed out.");
+        let error_message = match child_process.kill().await {
+            Ok(_) => format!("Command timed out after {timeout_secs}s and was killed."),
+            Err(e) => {
+                error!(error = %e, "Failed to kill timed out command.");
+                format!("Command timed out after {timeout_secs}s, but could not be killed: {e}")
+            }
+        };
+        BatchCommandResult {
+            command_id: child_command_id.clone(),
+            trace_id: trace_id.clone(),
+            status: CommandStatus::TimedOut.into(),
+            exit_code: -1,
+            error_message,
+        }
+    }
+
+    // Case 3: The command runs to completion
     result = async {
         let stdout_task = stream_output(stdout, OutputType::Stdout, child_command_id.clone(), tx_to_server.clone(), vps_db_id, agent_secret.clone(), id_provider.clone());
         let stderr_task = stream_output(stderr, OutputType::Stderr, child_command_id.clone(), tx_to_server.clone(), vps_db_id, agent_secret.clone(), id_provider.clone());
@@ -188,6 +204,7 @@ pub(super) async fn manage_command_lifecycle(
                     let final_status_enum = if status.success() { CommandStatus::Success } else { CommandStatus::Failure };
                     BatchCommandResult {
                         command_id: child_command_id.clone(),
+                        trace_id: trace_id.clone(),
                         status: final_status_enum.into(),
                         exit_code: status.code().unwrap_or(-1),
                         error_message: if status.success() { String::new() } else { format!("Exited with status {status}") },
@@ -198,6 +215,7 @@ pub(super) async fn manage_command_lifecycle(
                     error!(error = %error_msg);
                     BatchCommandResult {
                         command_id: child_command_id.clone(),
+                        trace_id: trace_id.clone(),
                         status: CommandStatus::Failure.into(),
                         exit_code: -1,
                         error_message: error_msg,
@@ -272,6 +290,7 @@ async fn stream_output(
 async fn send_error_result(
     error_message: &str,
     command_id: &str,
+    trace_id: &str,
     tx: &mpsc::Sender<MessageToServer>,
     vps_db_id: i32,
     agent_secret: &str,
@@ -283,6 +302,7 @@ async fn send_error_result(
         status: CommandStatus::Failure.into(),
         exit_code: -1,
         error_message: error_message.to_string(),
+        trace_id: trace_id.to_string(),
     };
     let client_msg_id = id_provider();
     if tx
@@ -1,4 +1,5 @@
 pub mod encoding;
 pub mod execution;
 pub mod service;
+pub mod shell;
 pub mod tracker;
@@ -1,6 +1,6 @@
 use std::sync::Arc;
 use tokio::sync::{mpsc, oneshot};
-use tracing::{error, info, warn};
+use tracing::{Instrument, error, info, info_span, warn};
 
 use crate::agent_modules::command::execution::manage_command_lifecycle;
 use crate::agent_modules::command::tracker::RunningCommandsTracker;
@@ -20,7 +20,9 @@ pub async fn handle_batch_agent_command(
     agent_secret: String,
     id_provider: impl Fn() -> u64 + Send + Sync + Clone + 'static,
 ) {
-    info!("Received command request.");
+    // Everything logged for this command carries the server's trace ID.
+    let span = info_span!("batch_command", command_id = %request.command_id, trace_id = %request.trace_id);
+    span.in_scope(|| info!("Received command request."));
 
     // Create a one-shot channel for termination signaling.
     let (term_tx, term_rx) = oneshot::channel();
@@ -41,7 +43,7 @@ pub async fn handle_batch_agent_command(
             term_rx, // Pass the receiver to the lifecycle manager
         )
         .await;
-    });
+    }.instrument(span));
 }
 
 /// This is the handler for termination requests. It's now much simpler.
@@ -70,6 +72,8 @@ pub async fn handle_batch_terminate_command(
             error_message: format!(
                 "Termination signal sent, but command was already completed or terminated: {e}"
             ),
+            // The finished command's trace isn't kept; the server still has it.
+            trace_id: String::new(),
         };
         let client_msg_id = id_provider();
         if tx_to_server
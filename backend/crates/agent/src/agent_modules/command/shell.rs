@@ -0,0 +1,54 @@
+//! The shell that scripts and one-off commands run in: PowerShell on Windows,
+//! bash or sh elsewhere.
+
+use std::path::Path;
+
+/// PowerShell writes redirected output in the OEM code page unless told otherwise.
+#[cfg(windows)]
+const UTF8_OUTPUT: &str = "[Console]::OutputEncoding = [System.Text.Encoding]::UTF8";
+/// The default execution policy on Windows clients refuses to run script files.
+#[cfg(windows)]
+const POWERSHELL_ARGS: [&str; 5] = ["-NoLogo", "-NoProfile", "-NonInteractive", "-ExecutionPolicy", "Bypass"];
+
+/// Extension the temporary script file needs for the shell to run it.
+pub const SCRIPT_EXTENSION: &str = if cfg!(windows) { ".ps1" } else { ".sh" };
+
+/// Runs the script at `path`. On Windows the exit code is the one the script
+/// exits with, or that of the last native command it ran.
+pub fn script_command(path: &Path) -> tokio::process::Command {
+    #[cfg(windows)]
+    {
+        let path = path.to_string_lossy().replace('\'', "''");
+        let mut command = tokio::process::Command::new("powershell.exe");
+        command
+            .args(POWERSHELL_ARGS)
+            .arg("-Command")
+            .arg(format!("{UTF8_OUTPUT}; & '{path}'; exit $LASTEXITCODE"));
+        command
+    }
+    #[cfg(not(windows))]
+    {
+        let mut command = tokio::process::Command::new("/bin/bash");
+        command.arg(path);
+        command
+    }
+}
+
+/// Runs a single command line, e.g. a watchdog restart command.
+pub fn inline_command(command_line: &str) -> std::process::Command {
+    #[cfg(windows)]
+    {
+        let mut command = std::process::Command::new("powershell.exe");
+        command
+            .args(POWERSHELL_ARGS)
+            .arg("-Command")
+            .arg(format!("{UTF8_OUTPUT}; {command_line}"));
+        command
+    }
+    #[cfg(not(windows))]
+    {
+        let mut command = std::process::Command::new("sh");
+        command.args(["-c", command_line]);
+        command
+    }
+}
@@ -0,0 +1,86 @@
+//! TLS settings for the connection to the server: an extra CA to trust and
+//! the client certificate the agent authenticates with (mutual TLS). The files
+//! are read on every connection attempt, so replacing them takes effect at the
+//! next reconnect.
+
+use crate::agent_modules::config::AgentCliConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::error::Error;
+use std::fs;
+use std::sync::Arc;
+use tokio_tungstenite::Connector;
+use tonic::transport::{Certificate, ClientTlsConfig, Identity};
+use tracing::warn;
+
+type BoxError = Box<dyn Error + Send + Sync>;
+
+struct TlsFiles {
+    /// Certificate and private key, in PEM.
+    client_identity: Option<(Vec<u8>, Vec<u8>)>,
+    server_ca: Option<Vec<u8>>,
+}
+
+fn read(path: &str, what: &str) -> Result<Vec<u8>, BoxError> {
+    fs::read(path).map_err(|e| format!("Failed to read {what} {path}: {e}").into())
+}
+
+fn load(config: &AgentCliConfig) -> Result<TlsFiles, BoxError> {
+    let client_identity = match (&config.client_cert_path, &config.client_key_path) {
+        (Some(cert), Some(key)) => Some((read(cert, "client certificate")?, read(key, "client key")?)),
+        (None, None) => None,
+        _ => return Err("client_cert_path and client_key_path must be set together".into()),
+    };
+    let server_ca = match &config.server_ca_cert_path {
+        Some(path) => Some(read(path, "server CA certificate")?),
+        None => None,
+    };
+    Ok(TlsFiles {
+        client_identity,
+        server_ca,
+    })
+}
+
+pub fn grpc_tls_config(config: &AgentCliConfig) -> Result<ClientTlsConfig, BoxError> {
+    let files = load(config)?;
+    let mut tls = ClientTlsConfig::new().with_native_roots();
+    if let Some(ca) = files.server_ca {
+        tls = tls.ca_certificate(Certificate::from_pem(ca));
+    }
+    if let Some((cert, key)) = files.client_identity {
+        tls = tls.identity(Identity::from_pem(cert, key));
+    }
+    Ok(tls)
+}
+
+/// The connector for `wss://` addresses, or `None` when nothing is configured
+/// and tokio-tungstenite's default (native roots, no client certificate) will do.
+pub fn websocket_connector(config: &AgentCliConfig) -> Result<Option<Connector>, BoxError> {
+    let files = load(config)?;
+    if files.client_identity.is_none() && files.server_ca.is_none() {
+        return Ok(None);
+    }
+
+    let mut roots = rustls::RootCertStore::empty();
+    let native = rustls_native_certs::load_native_certs();
+    if !native.errors.is_empty() {
+        warn!(errors = ?native.errors, "Some native root certificates could not be loaded.");
+    }
+    roots.add_parsable_certificates(native.certs);
+    if let Some(ca) = &files.server_ca {
+        for cert in CertificateDer::pem_slice_iter(ca) {
+            roots.add(cert?)?;
+        }
+    }
+
+    let builder = rustls::ClientConfig::builder().with_root_certificates(roots);
+    let client_config = match files.client_identity {
+        Some((cert, key)) => {
+            let certs = CertificateDer::pem_slice_iter(&cert).collect::<Result<Vec<_>, _>>()?;
+            let key = PrivateKeyDer::from_pem_slice(&key)?;
+            builder.with_client_auth_cert(certs, key)?
+        }
+        None => builder.with_no_client_auth(),
+    };
+    Ok(Some(Connector::Rustls(Arc::new(client_config))))
+}
@@ -1,6 +1,6 @@
 use crate::agent_modules::config::AgentCliConfig;
 use nodenexus_common::agent_service::{
-    AgentConfig, MessageToAgent, MessageToServer, message_to_agent::Payload as AgentPayload,
+    AgentConfig, MessageToAgent, MessageToServer, ServerHandshakeAck, message_to_agent::Payload as AgentPayload,
     message_to_server::Payload as ServerPayload,
 };
 use futures_util::{Sink, SinkExt, Stream, StreamExt as FuturesStreamExt};
@@ -11,7 +11,8 @@ use std::sync::atomic::{AtomicU64, Ordering};
 use tokio::sync::Mutex;
 use tokio::sync::mpsc;
 use tonic::Status;
-use tracing::{error, info};
+use tonic::transport::Uri;
+use tracing::{error, info, warn};
 
 // 重新导出子模块
 pub mod grpc;
@@ -20,12 +21,31 @@ pub mod websocket;
 // 使用绝对路径导入子模块内容
 use self::grpc::GrpcSink;
 use self::websocket::WebSocketStreamAdapter;
+use super::{client_tls, resolver};
 
 pub struct ConnectionHandler {
     pub in_stream: Pin<Box<dyn Stream<Item = Result<MessageToAgent, Status>> + Send + Unpin>>,
     pub tx_to_server: Pin<Box<dyn Sink<MessageToServer, Error = Status> + Send + Unpin>>,
     pub initial_agent_config: AgentConfig,
     pub client_message_id_counter: Arc<AtomicU64>,
+    /// VPS ID and secret issued when the handshake registered a new VPS
+    /// with a provisioning token.
+    pub registered_credentials: Option<(i32, String)>,
+}
+
+fn registered_credentials(assigned_vps_id: i32, new_agent_secret: &str) -> Option<(i32, String)> {
+    (assigned_vps_id > 0 && !new_agent_secret.is_empty())
+        .then(|| (assigned_vps_id, new_agent_secret.to_string()))
+}
+
+fn warn_if_outdated(ack: &ServerHandshakeAck) {
+    if ack.agent_outdated {
+        warn!(
+            current_version = crate::version::VERSION,
+            latest_version = %ack.latest_agent_version,
+            "A newer agent release is available from the server."
+        );
+    }
 }
 
 impl ConnectionHandler {
@@ -53,14 +73,24 @@ impl ConnectionHandler {
         };
 
         info!(url = %full_url, "Connecting to WebSocket URL");
-        let (ws_stream, _) = tokio_tungstenite::connect_async(&full_url).await?;
+        let uri: Uri = full_url.parse()?;
+        let host = uri.host().ok_or("WebSocket URL has no host")?;
+        let port = uri
+            .port_u16()
+            .unwrap_or(if uri.scheme_str() == Some("wss") { 443 } else { 80 });
+        let tcp_stream = resolver::connect_to_server(agent_cli_config, host, port).await?;
+        let connector = client_tls::websocket_connector(agent_cli_config)?;
+        let (ws_stream, _) =
+            tokio_tungstenite::client_async_tls_with_config(full_url.as_str(), tcp_stream, None, connector).await?;
         info!("Successfully connected to WebSocket endpoint.");
 
         let mut adapter = WebSocketStreamAdapter {
             ws_stream: Arc::new(Mutex::new(ws_stream)),
         };
 
-        let handshake_payload = super::handshake::create_handshake_payload().await;
+        let mut handshake_payload = super::handshake::create_handshake_payload().await;
+        handshake_payload.provisioning_token =
+            agent_cli_config.pending_provisioning_token().map(str::to_string);
         let client_message_id_counter = Arc::new(AtomicU64::new(initial_message_id_counter_val));
         let handshake_msg_id = client_message_id_counter.fetch_add(1, Ordering::SeqCst);
 
@@ -81,15 +111,17 @@ impl ConnectionHandler {
             if let Some(AgentPayload::ServerHandshakeAck(ack)) = response_msg.payload {
                 if ack.authentication_successful {
                     info!("Authenticated successfully via WebSocket.");
+                    warn_if_outdated(&ack);
                     Ok(Self {
                         in_stream: Box::pin(adapter.clone()),
                         tx_to_server: Box::pin(adapter),
                         initial_agent_config: ack.initial_config.unwrap_or_default(),
                         client_message_id_counter,
+                        registered_credentials: registered_credentials(ack.assigned_vps_id, &ack.new_agent_secret),
                     })
                 } else {
                     let err_msg = format!(
-                        "Authentication failed: {}. This is a critical error. Agent will not retry automatically for auth failures.",
+                        "Authentication failed: {}. The agent will re-read its credentials and retry after a long delay.",
                         ack.error_message
                     );
                     error!(error_message = %err_msg, "Handshake authentication failed.");
@@ -120,7 +152,7 @@ impl ConnectionHandler {
     ) -> Result<Self, Box<dyn Error + Send + Sync>> {
         info!("Attempting to connect to gRPC server");
 
-        let tls = tonic::transport::ClientTlsConfig::new().with_native_roots();
+        let tls = client_tls::grpc_tls_config(agent_cli_config)?;
 
         let channel =
             tonic::transport::Endpoint::from_shared(agent_cli_config.server_address.clone())?
@@ -128,7 +160,7 @@ impl ConnectionHandler {
                 .http2_keep_alive_interval(std::time::Duration::from_secs(10))
                 .keep_alive_timeout(std::time::Duration::from_secs(30))
                 .keep_alive_while_idle(true)
-                .connect()
+                .connect_with_connector(resolver::ServerConnector::new(agent_cli_config))
                 .await
                 .map_err(|e| {
                     error!(error = %e, "Failed to connect to gRPC endpoint with TLS.");
@@ -145,7 +177,9 @@ impl ConnectionHandler {
 
         info!("Continue without wait for establish_communication_stream result.");
 
-        let handshake_payload = super::handshake::create_handshake_payload().await;
+        let mut handshake_payload = super::handshake::create_handshake_payload().await;
+        handshake_payload.provisioning_token =
+            agent_cli_config.pending_provisioning_token().map(str::to_string);
 
         let client_message_id_counter = Arc::new(AtomicU64::new(initial_message_id_counter_val));
         let handshake_msg_id = client_message_id_counter.fetch_add(1, Ordering::SeqCst);
@@ -175,16 +209,21 @@ impl ConnectionHandler {
                 if let Some(AgentPayload::ServerHandshakeAck(ack)) = response_msg.payload {
                     if ack.authentication_successful {
                         info!("Authenticated successfully via gRPC.");
+                        warn_if_outdated(&ack);
                         let grpc_sink = GrpcSink { tx: tx_to_server };
                         Ok(Self {
                             in_stream: Box::pin(in_stream),
                             tx_to_server: Box::pin(grpc_sink),
                             initial_agent_config: ack.initial_config.unwrap_or_default(),
                             client_message_id_counter,
+                            registered_credentials: registered_credentials(
+                                ack.assigned_vps_id,
+                                &ack.new_agent_secret,
+                            ),
                         })
                     } else {
                         let err_msg = format!(
-                            "Authentication failed: {}. This is a critical error. Agent will not retry automatically for auth failures.",
+                            "Authentication failed: {}. The agent will re-read its credentials and retry after a long delay.",
                             ack.error_message
                         );
                         error!(error_message = %err_msg, "Handshake authentication failed.");
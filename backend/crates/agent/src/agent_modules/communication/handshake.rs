@@ -48,5 +48,6 @@ pub async fn create_handshake_payload() -> AgentHandshake {
         total_swap_bytes: Some(sys.total_swap()),
         cpu_static_info: cpu_static_info_opt,
         country_code: country_opt,
+        provisioning_token: None,
     }
 }
@@ -3,11 +3,13 @@ use crate::agent_modules::{
         service::{handle_batch_agent_command, handle_batch_terminate_command},
         tracker::RunningCommandsTracker,
     },
-    config, updater,
+    config, docker, files,
+    terminal::{OutputSink, TerminalWorker},
+    updater,
 };
 use nodenexus_common::agent_service::{
-    AgentConfig, MessageToAgent, MessageToServer, message_to_agent::Payload as AgentPayload,
-    message_to_server::Payload as ServerPayload,
+    AgentConfig, CommandRequest, CommandResponse, MessageToAgent, MessageToServer, command_request, command_response,
+    message_to_agent::Payload as AgentPayload, message_to_server::Payload as ServerPayload,
 };
 use futures_util::Stream;
 use futures_util::StreamExt;
@@ -30,6 +32,13 @@ pub async fn server_message_handler_loop(
     mut shutdown_rx: tokio::sync::watch::Receiver<()>,
 ) {
     info!("Listening for messages from server...");
+    // Dropped when this loop ends, which closes any open terminal sessions.
+    let terminal_worker = TerminalWorker::spawn(OutputSink {
+        tx_to_server: tx_to_server.clone(),
+        id_provider: id_provider.clone(),
+        vps_db_id,
+        agent_secret: agent_secret.clone(),
+    });
 
     loop {
         tokio::select! {
@@ -92,6 +101,82 @@ pub async fn server_message_handler_loop(
                                         error!(error = %e, "Failed to send config update response.");
                                     }
                                 }
+                                AgentPayload::CommandRequest(CommandRequest {
+                                    request_id,
+                                    payload: Some(command_request::Payload::FileOperation(op)),
+                                    ..
+                                }) => {
+                                    let tx_clone = tx_to_server.clone();
+                                    let agent_secret_clone = agent_secret.clone();
+                                    let id_provider_clone = id_provider.clone();
+
+                                    tokio::spawn(async move {
+                                        let response = match files::handle_file_operation(op).await {
+                                            Ok(result) => CommandResponse {
+                                                request_id,
+                                                success: true,
+                                                error_message: String::new(),
+                                                result_payload: Some(command_response::ResultPayload::FileResult(result)),
+                                            },
+                                            Err(error_message) => CommandResponse {
+                                                request_id,
+                                                success: false,
+                                                error_message,
+                                                result_payload: None,
+                                            },
+                                        };
+                                        if tx_clone
+                                            .send(MessageToServer {
+                                                client_message_id: id_provider_clone(),
+                                                payload: Some(ServerPayload::CommandResponse(response)),
+                                                vps_db_id,
+                                                agent_secret: agent_secret_clone,
+                                            })
+                                            .await
+                                            .is_err()
+                                        {
+                                            error!("Failed to send file operation response.");
+                                        }
+                                    });
+                                }
+                                AgentPayload::CommandRequest(CommandRequest {
+                                    request_id,
+                                    payload: Some(command_request::Payload::DockerCommand(cmd)),
+                                    ..
+                                }) => {
+                                    let tx_clone = tx_to_server.clone();
+                                    let agent_secret_clone = agent_secret.clone();
+                                    let id_provider_clone = id_provider.clone();
+
+                                    tokio::spawn(async move {
+                                        let response = match docker::handle_docker_command(cmd).await {
+                                            Ok(result) => CommandResponse {
+                                                request_id,
+                                                success: true,
+                                                error_message: String::new(),
+                                                result_payload: Some(command_response::ResultPayload::DockerResult(result)),
+                                            },
+                                            Err(error_message) => CommandResponse {
+                                                request_id,
+                                                success: false,
+                                                error_message,
+                                                result_payload: None,
+                                            },
+                                        };
+                                        if tx_clone
+                                            .send(MessageToServer {
+                                                client_message_id: id_provider_clone(),
+                                                payload: Some(ServerPayload::CommandResponse(response)),
+                                                vps_db_id,
+                                                agent_secret: agent_secret_clone,
+                                            })
+                                            .await
+                                            .is_err()
+                                        {
+                                            error!("Failed to send docker action response.");
+                                        }
+                                    });
+                                }
                                 AgentPayload::CommandRequest(cmd_req) => {
                                     warn!(request = ?cmd_req, "Received general CommandRequest. This is not currently handled for batch processing.");
                                     let error_result = nodenexus_common::agent_service::CommandResponse {
@@ -160,6 +245,9 @@ pub async fn server_message_handler_loop(
                                         .await;
                                     });
                                 }
+                                AgentPayload::PtyDataToAgent(pty_data) => {
+                                    terminal_worker.submit(pty_data);
+                                }
                                 AgentPayload::TriggerUpdateCheck(_cmd) => {
                                     info!(
                                         "Received TriggerUpdateCheck command from server. Spawning update task."
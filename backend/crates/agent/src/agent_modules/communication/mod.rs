@@ -1,8 +1,12 @@
 // 主模块入口
+pub mod client_tls;
 pub mod connection;
 pub mod handshake;
 pub mod message_handler;
+pub mod resolver;
+pub mod supervisor;
 
 // 重新导出公共接口
 pub use connection::ConnectionHandler;
 pub use message_handler::server_message_handler_loop;
+pub use supervisor::ConnectionSupervisor;
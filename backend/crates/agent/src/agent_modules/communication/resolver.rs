@@ -0,0 +1,234 @@
+//! Resilient resolution of and connection to the server address.
+//!
+//! Resolved addresses are cached for `dns_cache_ttl_seconds`, so reconnects don't
+//! depend on the resolver being healthy. When resolution fails, the last known
+//! addresses are used even if stale, and after that the static `server_fallback_ips`.
+//! Candidate addresses are raced happy-eyeballs style (RFC 8305), alternating
+//! between IPv6 and IPv4 with a short delay between attempts.
+
+use crate::agent_modules::config::AgentCliConfig;
+use futures::stream::{FuturesUnordered, StreamExt};
+use hyper_util::rt::TokioIo;
+use once_cell::sync::Lazy;
+use std::collections::HashMap;
+use std::future::Future;
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tonic::transport::Uri;
+use tokio::net::TcpStream;
+use tracing::{debug, info, warn};
+
+pub const DEFAULT_DNS_CACHE_TTL_SECONDS: u64 = 300;
+/// Delay before starting the next attempt while earlier ones are still pending.
+const CONNECTION_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct CachedResolution {
+    addrs: Vec<SocketAddr>,
+    expires_at: Instant,
+}
+
+static DNS_CACHE: Lazy<Mutex<HashMap<(String, u16), CachedResolution>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+async fn resolve(host: &str, port: u16, ttl: Duration) -> Vec<SocketAddr> {
+    let key = (host.to_string(), port);
+    if let Some(cached) = DNS_CACHE.lock().unwrap().get(&key) {
+        if cached.expires_at > Instant::now() {
+            debug!(host, "Using cached server addresses.");
+            return cached.addrs.clone();
+        }
+    }
+
+    match tokio::net::lookup_host((host, port)).await {
+        Ok(addrs) => {
+            let addrs: Vec<SocketAddr> = addrs.collect();
+            if !addrs.is_empty() {
+                DNS_CACHE.lock().unwrap().insert(
+                    key,
+                    CachedResolution {
+                        addrs: addrs.clone(),
+                        expires_at: Instant::now() + ttl,
+                    },
+                );
+                return addrs;
+            }
+            warn!(host, "DNS lookup returned no addresses.");
+        }
+        Err(e) => warn!(host, error = %e, "DNS lookup failed."),
+    }
+
+    match DNS_CACHE.lock().unwrap().get(&key) {
+        Some(cached) => {
+            warn!(host, "Falling back to stale cached server addresses.");
+            cached.addrs.clone()
+        }
+        None => Vec::new(),
+    }
+}
+
+/// Forces the next connection attempt to resolve again, keeping the entry as a stale fallback.
+fn expire_cached(host: &str, port: u16) {
+    if let Some(cached) = DNS_CACHE.lock().unwrap().get_mut(&(host.to_string(), port)) {
+        cached.expires_at = Instant::now();
+    }
+}
+
+/// Deduplicates and alternates address families, starting with the family of the first address.
+fn interleave_families(addrs: Vec<SocketAddr>) -> Vec<SocketAddr> {
+    let mut unique = Vec::with_capacity(addrs.len());
+    for addr in addrs {
+        if !unique.contains(&addr) {
+            unique.push(addr);
+        }
+    }
+    let prefer_v6 = unique.first().is_some_and(|a| a.is_ipv6());
+    let (preferred, other): (Vec<_>, Vec<_>) =
+        unique.into_iter().partition(|a| a.is_ipv6() == prefer_v6);
+
+    let mut ordered = Vec::with_capacity(preferred.len() + other.len());
+    let (mut preferred, mut other) = (preferred.into_iter(), other.into_iter());
+    loop {
+        match (preferred.next(), other.next()) {
+            (None, None) => break,
+            (a, b) => ordered.extend(a.into_iter().chain(b)),
+        }
+    }
+    ordered
+}
+
+async fn attempt(addr: SocketAddr) -> (SocketAddr, io::Result<TcpStream>) {
+    let result = match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(addr)).await {
+        Ok(result) => result,
+        Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "connection attempt timed out")),
+    };
+    (addr, result)
+}
+
+/// Races connection attempts to `addrs` in order, starting a new one every
+/// `CONNECTION_ATTEMPT_DELAY` or as soon as the previous attempt fails.
+pub async fn connect_happy_eyeballs(addrs: &[SocketAddr]) -> io::Result<TcpStream> {
+    let mut remaining = addrs.iter().copied();
+    let mut pending = FuturesUnordered::new();
+    let mut last_error = None;
+
+    loop {
+        if pending.is_empty() {
+            match remaining.next() {
+                Some(addr) => pending.push(attempt(addr)),
+                None => break,
+            }
+        }
+        let has_more = remaining.len() > 0;
+
+        tokio::select! {
+            Some((addr, result)) = pending.next() => match result {
+                Ok(stream) => {
+                    debug!(%addr, "Connected to server address.");
+                    return Ok(stream);
+                }
+                Err(e) => {
+                    debug!(%addr, error = %e, "Connection attempt failed.");
+                    last_error = Some(e);
+                }
+            },
+            _ = tokio::time::sleep(CONNECTION_ATTEMPT_DELAY), if has_more => {
+                if let Some(addr) = remaining.next() {
+                    pending.push(attempt(addr));
+                }
+            }
+        }
+    }
+
+    Err(last_error.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::NotFound, "no addresses to connect to")
+    }))
+}
+
+/// Opens a TCP connection to `host:port` using the DNS cache, the static fallback
+/// list from the config and happy-eyeballs connection racing.
+pub async fn connect_to_server(
+    config: &AgentCliConfig,
+    host: &str,
+    port: u16,
+) -> io::Result<TcpStream> {
+    if let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        return connect_happy_eyeballs(&[SocketAddr::new(ip, port)]).await;
+    }
+
+    let ttl = Duration::from_secs(
+        config
+            .dns_cache_ttl_seconds
+            .unwrap_or(DEFAULT_DNS_CACHE_TTL_SECONDS),
+    );
+    let resolved = interleave_families(resolve(host, port, ttl).await);
+    if !resolved.is_empty() {
+        match connect_happy_eyeballs(&resolved).await {
+            Ok(stream) => return Ok(stream),
+            Err(e) => {
+                warn!(host, error = %e, "Failed to connect to any resolved server address.");
+                expire_cached(host, port);
+                if config.server_fallback_ips.is_empty() {
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    if config.server_fallback_ips.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("could not resolve server host {host}"),
+        ));
+    }
+    info!(host, ips = ?config.server_fallback_ips, "Connecting via static fallback IPs.");
+    let fallback = config
+        .server_fallback_ips
+        .iter()
+        .map(|ip| SocketAddr::new(*ip, port))
+        .collect();
+    connect_happy_eyeballs(&interleave_families(fallback)).await
+}
+
+/// TCP connector for the gRPC endpoint that goes through [`connect_to_server`].
+/// TLS is still layered on top by tonic, using the host from the URI for SNI.
+#[derive(Clone)]
+pub struct ServerConnector {
+    config: Arc<AgentCliConfig>,
+}
+
+impl ServerConnector {
+    pub fn new(config: &AgentCliConfig) -> Self {
+        Self {
+            config: Arc::new(config.clone()),
+        }
+    }
+}
+
+impl tower::Service<Uri> for ServerConnector {
+    type Response = TokioIo<TcpStream>;
+    type Error = io::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, uri: Uri) -> Self::Future {
+        let config = self.config.clone();
+        Box::pin(async move {
+            let host = uri
+                .host()
+                .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "server URI has no host"))?;
+            let port = uri
+                .port_u16()
+                .unwrap_or(if uri.scheme_str() == Some("http") { 80 } else { 443 });
+            let stream = connect_to_server(&config, host, port).await?;
+            Ok(TokioIo::new(stream))
+        })
+    }
+}
@@ -0,0 +1,226 @@
+//! Decides when and where the agent reconnects. Network errors are retried
+//! with exponential backoff and jitter, moving on to the next configured
+//! server address each time. Authentication failures won't go away by
+//! retrying, so they wait much longer and the credentials are re-read first.
+//!
+//! After each successful connection the agent reports how it got there as
+//! `agent.connection.*` metrics.
+
+use crate::agent_modules::config::AgentCliConfig;
+use chrono::Utc;
+use nodenexus_common::agent_service::{
+    GenericMetric, GenericMetricValue, GenericMetricsBatch, generic_metric_value::ValueType,
+};
+use nodenexus_common::metric_catalog::{
+    CONNECTION_FAILED_ATTEMPTS, CONNECTION_OUTAGE_SECONDS, CONNECTION_RECONNECTS, CONNECTION_STATE,
+};
+use std::collections::HashMap;
+use std::error::Error;
+use std::time::Duration;
+use tracing::info;
+
+pub const DEFAULT_INITIAL_DELAY_SECONDS: u64 = 5;
+pub const DEFAULT_MAX_DELAY_SECONDS: u64 = 300;
+/// Wait after the server rejected the agent's credentials.
+pub const AUTH_FAILURE_DELAY: Duration = Duration::from_secs(600);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureKind {
+    /// The server rejected the credentials or provisioning token.
+    Auth,
+    /// Anything else: DNS, TCP, TLS, a dropped stream.
+    Transient,
+}
+
+/// Tells rejected credentials apart from network trouble.
+pub fn classify(error: &(dyn Error + Send + Sync + 'static)) -> FailureKind {
+    if let Some(io) = error.downcast_ref::<std::io::Error>() {
+        if io.kind() == std::io::ErrorKind::PermissionDenied {
+            return FailureKind::Auth;
+        }
+    }
+    if let Some(status) = error.downcast_ref::<tonic::Status>() {
+        if matches!(status.code(), tonic::Code::Unauthenticated | tonic::Code::PermissionDenied) {
+            return FailureKind::Auth;
+        }
+    }
+    FailureKind::Transient
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    /// Waiting before the next attempt.
+    Backoff,
+    /// Waiting after the server rejected the credentials.
+    AuthFailed,
+}
+
+impl ConnectionState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConnectionState::Connecting => "connecting",
+            ConnectionState::Connected => "connected",
+            ConnectionState::Backoff => "backoff",
+            ConnectionState::AuthFailed => "auth_failed",
+        }
+    }
+}
+
+/// Doubling delays from `initial` up to `max`. Each delay is drawn from its
+/// upper half, so agents that lost the server together don't return together.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    initial: Duration,
+    max: Duration,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(initial: Duration, max: Duration) -> Self {
+        Self {
+            initial,
+            max: max.max(initial),
+            attempt: 0,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+
+    /// The delay before the next attempt; `jitter` is between 0 and 1.
+    pub fn next_delay(&mut self, jitter: f64) -> Duration {
+        let ceiling = self
+            .initial
+            .saturating_mul(2u32.saturating_pow(self.attempt))
+            .min(self.max);
+        self.attempt = self.attempt.saturating_add(1);
+        ceiling.div_f64(2.0) + ceiling.div_f64(2.0).mul_f64(jitter.clamp(0.0, 1.0))
+    }
+}
+
+pub struct ConnectionSupervisor {
+    addresses: Vec<String>,
+    current: usize,
+    backoff: Backoff,
+    state: ConnectionState,
+    /// Failed attempts since the last successful connection.
+    failed_attempts: u32,
+    /// Successful connections after the first one.
+    reconnects: u64,
+    connected_once: bool,
+    /// When the last connection was lost, in Unix milliseconds.
+    disconnected_at_ms: Option<i64>,
+}
+
+impl ConnectionSupervisor {
+    pub fn new(config: &AgentCliConfig) -> Self {
+        let mut addresses = vec![config.server_address.clone()];
+        for address in &config.server_addresses {
+            if !address.is_empty() && !addresses.contains(address) {
+                addresses.push(address.clone());
+            }
+        }
+        let initial = config.reconnect_initial_delay_seconds.unwrap_or(DEFAULT_INITIAL_DELAY_SECONDS).max(1);
+        let max = config.reconnect_max_delay_seconds.unwrap_or(DEFAULT_MAX_DELAY_SECONDS);
+        Self {
+            addresses,
+            current: 0,
+            backoff: Backoff::new(Duration::from_secs(initial), Duration::from_secs(max)),
+            state: ConnectionState::Connecting,
+            failed_attempts: 0,
+            reconnects: 0,
+            connected_once: false,
+            disconnected_at_ms: None,
+        }
+    }
+
+    /// The server address for the next attempt.
+    pub fn address(&self) -> &str {
+        &self.addresses[self.current]
+    }
+
+    fn set_state(&mut self, state: ConnectionState) {
+        if self.state != state {
+            info!(
+                metric = CONNECTION_STATE,
+                state = state.as_str(),
+                server_address = %self.address(),
+                failed_attempts = self.failed_attempts,
+                "Connection state changed."
+            );
+            self.state = state;
+        }
+    }
+
+    pub fn connecting(&mut self) {
+        self.set_state(ConnectionState::Connecting);
+    }
+
+    /// Records a successful handshake and returns the metrics describing it.
+    /// The agent stays on this address until it fails.
+    pub fn connected(&mut self) -> GenericMetricsBatch {
+        self.set_state(ConnectionState::Connected);
+        let now_ms = Utc::now().timestamp_millis();
+        let mut metrics = Vec::new();
+        if let Some(disconnected_at_ms) = self.disconnected_at_ms.take() {
+            metrics.push(self.metric(CONNECTION_STATE, disconnected_at_ms, 0.0));
+            metrics.push(self.metric(
+                CONNECTION_OUTAGE_SECONDS,
+                now_ms,
+                (now_ms - disconnected_at_ms) as f64 / 1000.0,
+            ));
+        }
+        metrics.push(self.metric(CONNECTION_STATE, now_ms, 1.0));
+        metrics.push(self.metric(CONNECTION_FAILED_ATTEMPTS, now_ms, f64::from(self.failed_attempts)));
+        if self.connected_once {
+            self.reconnects += 1;
+        }
+        self.connected_once = true;
+        metrics.push(self.metric(CONNECTION_RECONNECTS, now_ms, self.reconnects as f64));
+        self.failed_attempts = 0;
+        self.backoff.reset();
+        GenericMetricsBatch { metrics }
+    }
+
+    /// Records the end of a connection and returns how long to wait before
+    /// reconnecting.
+    pub fn disconnected(&mut self) -> Duration {
+        self.disconnected_at_ms = Some(Utc::now().timestamp_millis());
+        self.set_state(ConnectionState::Backoff);
+        self.backoff.next_delay(rand::random())
+    }
+
+    /// Records a failed attempt and returns how long to wait before the next
+    /// one. Network failures move on to the next address.
+    pub fn failed(&mut self, kind: FailureKind) -> Duration {
+        self.failed_attempts = self.failed_attempts.saturating_add(1);
+        if self.disconnected_at_ms.is_none() {
+            self.disconnected_at_ms = Some(Utc::now().timestamp_millis());
+        }
+        match kind {
+            FailureKind::Auth => {
+                self.set_state(ConnectionState::AuthFailed);
+                AUTH_FAILURE_DELAY
+            }
+            FailureKind::Transient => {
+                self.current = (self.current + 1) % self.addresses.len();
+                self.set_state(ConnectionState::Backoff);
+                self.backoff.next_delay(rand::random())
+            }
+        }
+    }
+
+    fn metric(&self, name: &str, timestamp_unix_ms: i64, value: f64) -> GenericMetric {
+        GenericMetric {
+            name: name.to_string(),
+            timestamp_unix_ms,
+            value: Some(GenericMetricValue {
+                value_type: Some(ValueType::DoubleValue(value)),
+            }),
+            tags: HashMap::from([("server_address".to_string(), self.address().to_string())]),
+        }
+    }
+}
@@ -1,18 +1,134 @@
 use nodenexus_common::agent_service::AgentConfig;
 use serde::{Deserialize, Serialize};
-use std::{error::Error, fs, path::Path};
+use std::{error::Error, fs, net::IpAddr, path::Path};
 use tracing::{error, info};
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct AgentCliConfig {
     pub server_address: String,
+    #[serde(default)]
     pub vps_id: i32,
+    #[serde(default)]
     pub agent_secret: String,
+    /// Registers a new VPS on first connect when `vps_id`/`agent_secret` are
+    /// unset. Replaced by the issued credentials once registration succeeds.
+    #[serde(default)]
+    pub provisioning_token: Option<String>,
     pub agent_grpc_listen_address: Option<String>, // Address for the agent's own gRPC service
+    /// More server addresses, tried in turn after `server_address` when the
+    /// current one can't be reached.
+    #[serde(default)]
+    pub server_addresses: Vec<String>,
+    /// First delay before reconnecting; it doubles with each failed attempt. Defaults to 5.
+    #[serde(default)]
+    pub reconnect_initial_delay_seconds: Option<u64>,
+    /// Longest delay between reconnect attempts. Defaults to 300.
+    #[serde(default)]
+    pub reconnect_max_delay_seconds: Option<u64>,
+    /// How long resolved server addresses are reused before resolving again. Defaults to 300.
+    #[serde(default)]
+    pub dns_cache_ttl_seconds: Option<u64>,
+    /// Addresses to connect to when the server host can't be resolved or reached.
+    #[serde(default)]
+    pub server_fallback_ips: Vec<IpAddr>,
+    /// Processes or systemd units to watch, as `[[watchdog]]` tables.
+    #[serde(default)]
+    pub watchdog: Vec<WatchdogTarget>,
+    /// How often watched targets are checked. Defaults to 15.
+    #[serde(default)]
+    pub watchdog_interval_seconds: Option<u64>,
+    /// Files whose new lines are sent to the server for log alert rules, as
+    /// `[[log_tail]]` tables.
+    #[serde(default)]
+    pub log_tail: Vec<LogTailSource>,
+    /// How often tailed files are read. Defaults to 5.
+    #[serde(default)]
+    pub log_tail_interval_seconds: Option<u64>,
+    /// Commands whose output is reported as custom metrics, as `[[metric_scripts]]` tables.
+    #[serde(default)]
+    pub metric_scripts: Vec<MetricScript>,
+    /// Size limit of the on-disk buffer for metrics collected while
+    /// disconnected. Defaults to 64; 0 turns buffering off.
+    #[serde(default)]
+    pub metric_buffer_max_mb: Option<u64>,
+    /// Where buffered metrics are kept. Defaults to `metric_buffer` next to the config file.
+    #[serde(default)]
+    pub metric_buffer_dir: Option<String>,
+    /// Client certificate (PEM) for servers that require mutual TLS, issued
+    /// from the VPS's settings in the dashboard. Needs `client_key_path`.
+    #[serde(default)]
+    pub client_cert_path: Option<String>,
+    #[serde(default)]
+    pub client_key_path: Option<String>,
+    /// CA certificate (PEM) to trust for the server's certificate in addition
+    /// to the system roots, for servers with a private certificate.
+    #[serde(default)]
+    pub server_ca_cert_path: Option<String>,
     #[serde(skip)]
     pub config_path: String,
 }
 
+impl AgentCliConfig {
+    /// The provisioning token to register with, if the agent has no credentials yet.
+    pub fn pending_provisioning_token(&self) -> Option<&str> {
+        let unregistered = self.vps_id <= 0 || self.agent_secret.is_empty();
+        self.provisioning_token
+            .as_deref()
+            .filter(|token| unregistered && !token.is_empty())
+    }
+}
+
+/// A process (matched by exact name) or systemd unit kept running by the watchdog.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct WatchdogTarget {
+    pub name: String,
+    #[serde(default)]
+    pub process_name: Option<String>,
+    #[serde(default)]
+    pub systemd_unit: Option<String>,
+    /// Run through the shell (PowerShell on Windows). Defaults to
+    /// `systemctl restart <unit>` for units.
+    #[serde(default)]
+    pub restart_command: Option<String>,
+    #[serde(default)]
+    pub auto_restart: bool,
+    /// At most this many restarts per `restart_window_seconds`.
+    #[serde(default = "default_watchdog_max_restarts")]
+    pub max_restarts: u32,
+    #[serde(default = "default_watchdog_restart_window_seconds")]
+    pub restart_window_seconds: u64,
+}
+
+/// A log file followed like `tail -F`: reading starts at its end and
+/// continues from the start when it is rotated or truncated.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct LogTailSource {
+    pub name: String,
+    pub path: String,
+}
+
+/// A command run through the shell (PowerShell on Windows) on an interval; each
+/// line it prints is a sample, `<name> <value> [label=value ...]`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct MetricScript {
+    pub name: String,
+    pub command: String,
+    /// Defaults to 60; at least 10.
+    #[serde(default)]
+    pub interval_seconds: Option<u64>,
+    /// The run is killed and reports nothing after this long. Defaults to 30.
+    #[serde(default)]
+    pub timeout_seconds: Option<u64>,
+}
+
+fn default_watchdog_max_restarts() -> u32 {
+    3
+}
+
+fn default_watchdog_restart_window_seconds() -> u64 {
+    600
+}
+
 pub fn load_cli_config(config_path_str: &str) -> Result<AgentCliConfig, Box<dyn Error>> {
     let config_path = Path::new(config_path_str);
     // Attempt to get absolute path for logging, but don't fail if it can't be canonicalized (e.g. if file doesn't exist yet)
@@ -73,3 +189,26 @@ pub fn save_agent_config(
     info!(path = ?config_path, "Successfully merged and saved configuration.");
     Ok(())
 }
+
+/// Stores the credentials issued by a provisioning-token registration and
+/// drops the token, keeping the rest of the file as is.
+pub fn save_registered_credentials(
+    config_path_str: &str,
+    vps_id: i32,
+    agent_secret: &str,
+) -> Result<(), Box<dyn Error>> {
+    let config_path = Path::new(config_path_str);
+    let existing_content = fs::read_to_string(config_path).unwrap_or_default();
+    let mut existing_toml: toml::Value = toml::from_str(&existing_content)?;
+    let table = existing_toml
+        .as_table_mut()
+        .ok_or("Agent config file is not a TOML table")?;
+
+    table.insert("vps_id".to_string(), toml::Value::Integer(vps_id.into()));
+    table.insert("agent_secret".to_string(), toml::Value::String(agent_secret.to_string()));
+    table.remove("provisioning_token");
+
+    fs::write(config_path, toml::to_string_pretty(&existing_toml)?)?;
+    info!(path = ?config_path, vps_id, "Saved credentials issued at registration.");
+    Ok(())
+}
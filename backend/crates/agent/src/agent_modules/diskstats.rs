@@ -0,0 +1,208 @@
+//! Per block device throughput, IOPS and latency from `/proc/diskstats`, or
+//! from the disk performance counters of each physical drive on Windows.
+//! Rates are computed from the counters of two consecutive samples; other
+//! platforms report no devices.
+
+use nodenexus_common::agent_service::DiskIoStats;
+use std::collections::HashMap;
+use std::time::Instant;
+
+/// Sector size used by /proc/diskstats, independent of the device's real sector size.
+#[cfg(target_os = "linux")]
+const SECTOR_BYTES: u64 = 512;
+/// Virtual devices that only add noise.
+#[cfg(target_os = "linux")]
+const IGNORED_PREFIXES: [&str; 3] = ["loop", "ram", "zram"];
+/// `\\.\PhysicalDriveN` numbers probed; numbering can have gaps after a drive is removed.
+#[cfg(windows)]
+const MAX_PHYSICAL_DRIVES: u32 = 32;
+
+#[derive(Debug, Clone, Copy)]
+struct Counters {
+    reads: u64,
+    bytes_read: u64,
+    ms_reading: u64,
+    writes: u64,
+    bytes_written: u64,
+    ms_writing: u64,
+    ms_doing_io: u64,
+}
+
+/// Keeps the previous sample so each call reports the rates since the last one.
+#[derive(Default)]
+pub struct DiskStatsSampler {
+    previous: Option<(Instant, HashMap<String, Counters>)>,
+}
+
+impl DiskStatsSampler {
+    pub fn new() -> Self {
+        Self { previous: None }
+    }
+
+    /// Returns per-device stats since the previous call; empty on the first call.
+    pub fn sample(&mut self) -> Vec<DiskIoStats> {
+        let Some(current) = read_counters() else {
+            return Vec::new();
+        };
+        let now = Instant::now();
+        let stats = match &self.previous {
+            Some((previous_time, previous)) => {
+                let elapsed_secs = now.duration_since(*previous_time).as_secs_f64();
+                let mut stats: Vec<DiskIoStats> = current
+                    .iter()
+                    .filter_map(|(device, counters)| {
+                        let before = previous.get(device)?;
+                        compute_stats(device, before, counters, elapsed_secs)
+                    })
+                    .collect();
+                stats.sort_by(|a, b| a.device.cmp(&b.device));
+                stats
+            }
+            None => Vec::new(),
+        };
+        self.previous = Some((now, current));
+        stats
+    }
+}
+
+fn compute_stats(device: &str, before: &Counters, after: &Counters, elapsed_secs: f64) -> Option<DiskIoStats> {
+    if elapsed_secs <= 0.0 {
+        return None;
+    }
+    // Counters wrap or reset when a device is re-attached; skip that interval.
+    let delta = |a: u64, b: u64| b.checked_sub(a);
+    let reads = delta(before.reads, after.reads)?;
+    let writes = delta(before.writes, after.writes)?;
+    let bytes_read = delta(before.bytes_read, after.bytes_read)?;
+    let bytes_written = delta(before.bytes_written, after.bytes_written)?;
+    let ms_waiting = delta(before.ms_reading, after.ms_reading)? + delta(before.ms_writing, after.ms_writing)?;
+    let ms_doing_io = delta(before.ms_doing_io, after.ms_doing_io)?;
+
+    let ios = reads + writes;
+    Some(DiskIoStats {
+        device: device.to_string(),
+        read_bytes_per_sec: (bytes_read as f64 / elapsed_secs) as u64,
+        write_bytes_per_sec: (bytes_written as f64 / elapsed_secs) as u64,
+        read_iops: reads as f64 / elapsed_secs,
+        write_iops: writes as f64 / elapsed_secs,
+        await_ms: if ios > 0 { ms_waiting as f64 / ios as f64 } else { 0.0 },
+        utilization_percent: (ms_doing_io as f64 / (elapsed_secs * 1000.0) * 100.0).min(100.0),
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn read_counters() -> Option<HashMap<String, Counters>> {
+    let content = std::fs::read_to_string("/proc/diskstats").ok()?;
+    Some(
+        content
+            .lines()
+            .filter_map(parse_diskstats_line)
+            .filter(|(device, _)| is_whole_device(device))
+            .collect(),
+    )
+}
+
+/// `IOCTL_DISK_PERFORMANCE` needs no access rights on the drive, so this works
+/// without administrator rights. Times are reported in 100 ns units.
+#[cfg(windows)]
+fn read_counters() -> Option<HashMap<String, Counters>> {
+    use windows_sys::Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::Storage::FileSystem::{
+        CreateFileW, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+    };
+    use windows_sys::Win32::System::IO::DeviceIoControl;
+    use windows_sys::Win32::System::Ioctl::{DISK_PERFORMANCE, IOCTL_DISK_PERFORMANCE};
+
+    let ms = |ticks: i64| ticks.max(0) as u64 / 10_000;
+    let mut counters = HashMap::new();
+    for index in 0..MAX_PHYSICAL_DRIVES {
+        let device = format!("PhysicalDrive{index}");
+        let path: Vec<u16> = format!(r"\\.\{device}").encode_utf16().chain(Some(0)).collect();
+        // SAFETY: `path` is NUL terminated and outlives the call.
+        let handle = unsafe {
+            CreateFileW(
+                path.as_ptr(),
+                0,
+                FILE_SHARE_READ | FILE_SHARE_WRITE,
+                std::ptr::null(),
+                OPEN_EXISTING,
+                0,
+                std::ptr::null_mut(),
+            )
+        };
+        if handle == INVALID_HANDLE_VALUE {
+            continue;
+        }
+        // SAFETY: DISK_PERFORMANCE is plain data, and the output buffer is sized for it.
+        let (ok, performance) = unsafe {
+            let mut performance: DISK_PERFORMANCE = std::mem::zeroed();
+            let mut returned = 0u32;
+            let ok = DeviceIoControl(
+                handle,
+                IOCTL_DISK_PERFORMANCE,
+                std::ptr::null(),
+                0,
+                (&mut performance as *mut DISK_PERFORMANCE).cast(),
+                std::mem::size_of::<DISK_PERFORMANCE>() as u32,
+                &mut returned,
+                std::ptr::null_mut(),
+            );
+            CloseHandle(handle);
+            (ok != 0, performance)
+        };
+        if !ok {
+            continue;
+        }
+        counters.insert(
+            device,
+            Counters {
+                reads: u64::from(performance.ReadCount),
+                bytes_read: performance.BytesRead.max(0) as u64,
+                ms_reading: ms(performance.ReadTime),
+                writes: u64::from(performance.WriteCount),
+                bytes_written: performance.BytesWritten.max(0) as u64,
+                ms_writing: ms(performance.WriteTime),
+                // QueryTime is a timestamp and IdleTime a running total, so the
+                // difference grows by exactly the busy time between two samples.
+                ms_doing_io: ms(performance.QueryTime - performance.IdleTime),
+            },
+        );
+    }
+    Some(counters)
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn read_counters() -> Option<HashMap<String, Counters>> {
+    None
+}
+
+/// Partitions would double count their parent device; only devices listed in
+/// /sys/block are whole devices.
+#[cfg(target_os = "linux")]
+fn is_whole_device(device: &str) -> bool {
+    !IGNORED_PREFIXES.iter().any(|prefix| device.starts_with(prefix))
+        && std::path::Path::new("/sys/block").join(device).exists()
+}
+
+/// Fields: major minor name reads merged sectors ms_reading writes merged
+/// sectors ms_writing in_flight ms_doing_io weighted_ms ...
+#[cfg(target_os = "linux")]
+fn parse_diskstats_line(line: &str) -> Option<(String, Counters)> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 14 {
+        return None;
+    }
+    let number = |i: usize| fields[i].parse::<u64>().ok();
+    Some((
+        fields[2].to_string(),
+        Counters {
+            reads: number(3)?,
+            bytes_read: number(5)? * SECTOR_BYTES,
+            ms_reading: number(6)?,
+            writes: number(7)?,
+            bytes_written: number(9)? * SECTOR_BYTES,
+            ms_writing: number(10)?,
+            ms_doing_io: number(12)?,
+        },
+    ))
+}
@@ -0,0 +1,105 @@
+//! Container lifecycle actions for the dashboard, driven by `CommandRequest`s
+//! carrying a `DockerCommandPayload`. Talks to the Docker Engine API over the
+//! local socket, so no docker CLI is needed on the host.
+
+use nodenexus_common::agent_service::{
+    DockerActionResult, DockerCommandPayload, docker_command_payload::DockerAction,
+};
+use std::time::Duration;
+
+const DOCKER_SOCKET: &str = "/var/run/docker.sock";
+/// Seconds Docker waits for a graceful stop before killing the container.
+const DEFAULT_STOP_TIMEOUT_SECONDS: u32 = 10;
+const MAX_STOP_TIMEOUT_SECONDS: u32 = 300;
+/// Slack on top of the stop timeout for the API round trip itself.
+const REQUEST_GRACE: Duration = Duration::from_secs(30);
+
+pub async fn handle_docker_command(cmd: DockerCommandPayload) -> Result<DockerActionResult, String> {
+    let container_id = cmd.target_id.trim();
+    if !is_valid_container_ref(container_id) {
+        return Err("A valid container ID or name is required.".to_string());
+    }
+    let stop_timeout = match cmd.arguments.get("timeout_seconds") {
+        Some(value) => value
+            .parse::<u32>()
+            .ok()
+            .filter(|t| *t <= MAX_STOP_TIMEOUT_SECONDS)
+            .ok_or_else(|| format!("timeout_seconds must be between 0 and {MAX_STOP_TIMEOUT_SECONDS}."))?,
+        None => DEFAULT_STOP_TIMEOUT_SECONDS,
+    };
+
+    let path = match cmd.action() {
+        DockerAction::StartContainer => format!("/containers/{container_id}/start"),
+        DockerAction::StopContainer => format!("/containers/{container_id}/stop?t={stop_timeout}"),
+        DockerAction::RestartContainer => format!("/containers/{container_id}/restart?t={stop_timeout}"),
+        other => return Err(format!("Unsupported docker action: {}", other.as_str_name())),
+    };
+
+    let deadline = Duration::from_secs(u64::from(stop_timeout)) + REQUEST_GRACE;
+    let (status, body) = tokio::time::timeout(deadline, post(&path))
+        .await
+        .map_err(|_| "Timed out waiting for the Docker daemon.".to_string())??;
+
+    match status {
+        // 304: start/stop on a container already in that state.
+        204 | 304 => Ok(DockerActionResult {
+            container_id: container_id.to_string(),
+            state_changed: status == 204,
+        }),
+        404 => Err(format!("No such container: {container_id}")),
+        _ => Err(format!("Docker daemon returned {status}: {}", error_message(&body))),
+    }
+}
+
+/// Container IDs and names only; anything else could alter the request path.
+fn is_valid_container_ref(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 128
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+/// The daemon reports errors as `{"message": "..."}`.
+fn error_message(body: &str) -> String {
+    serde_json::from_str::<serde_json::Value>(body)
+        .ok()
+        .and_then(|v| v.get("message").and_then(|m| m.as_str()).map(str::to_string))
+        .unwrap_or_else(|| body.trim().to_string())
+}
+
+/// Issues a body-less POST and returns the status code and response body.
+/// HTTP/1.0 makes the daemon close the connection after responding, so the
+/// response can be read to EOF without chunked decoding.
+#[cfg(unix)]
+async fn post(path: &str) -> Result<(u16, String), String> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let mut stream = tokio::net::UnixStream::connect(DOCKER_SOCKET)
+        .await
+        .map_err(|e| format!("Cannot connect to Docker at {DOCKER_SOCKET}: {e}"))?;
+    let request = format!("POST {path} HTTP/1.0\r\nHost: docker\r\nContent-Length: 0\r\n\r\n");
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|e| format!("Failed to send request to Docker: {e}"))?;
+
+    let mut response = Vec::new();
+    stream
+        .read_to_end(&mut response)
+        .await
+        .map_err(|e| format!("Failed to read Docker response: {e}"))?;
+    let response = String::from_utf8_lossy(&response);
+
+    let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+    let status = head
+        .lines()
+        .next()
+        .and_then(|status_line| status_line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| "Malformed response from Docker daemon.".to_string())?;
+    Ok((status, body.to_string()))
+}
+
+#[cfg(not(unix))]
+async fn post(_path: &str) -> Result<(u16, String), String> {
+    Err("Docker actions are only supported on Unix hosts.".to_string())
+}
@@ -0,0 +1,397 @@
+//! `nodenexus-agent doctor`: checks the things that usually keep an agent
+//! from connecting (config, DNS, proxies, TLS, clock, transports,
+//! authentication) plus collector permissions, and prints a report.
+
+use crate::agent_modules::communication::{ConnectionHandler, client_tls, resolver};
+use crate::agent_modules::config::{AgentCliConfig, load_cli_config};
+use crate::version::VERSION;
+use futures_util::SinkExt;
+use std::error::Error;
+use std::future::Future;
+use std::path::Path;
+use std::time::Duration;
+use tonic::transport::Uri;
+
+const CHECK_TIMEOUT: Duration = Duration::from_secs(15);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Clock differences above this are reported.
+const MAX_CLOCK_SKEW_SECONDS: i64 = 30;
+const PROXY_VARS: [&str; 6] = ["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"];
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+    Skip,
+}
+
+struct Report {
+    checks: Vec<(Status, &'static str, String)>,
+}
+
+impl Report {
+    fn add(&mut self, status: Status, name: &'static str, detail: impl Into<String>) {
+        let detail = detail.into();
+        let label = match status {
+            Status::Ok => " OK ",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+            Status::Skip => "SKIP",
+        };
+        println!("[{label}] {name:<18} {detail}");
+        self.checks.push((status, name, detail));
+    }
+
+    fn count(&self, status: Status) -> usize {
+        self.checks.iter().filter(|(s, _, _)| *s == status).count()
+    }
+}
+
+/// Where the server listens. gRPC and the agent WebSocket share one port.
+struct ServerEndpoint {
+    host: String,
+    port: u16,
+    tls: bool,
+}
+
+impl ServerEndpoint {
+    fn parse(server_address: &str) -> Result<Self, String> {
+        let uri: Uri = server_address
+            .parse()
+            .map_err(|e| format!("server_address {server_address:?} is not a valid URL: {e}"))?;
+        let tls = matches!(uri.scheme_str(), Some("https") | Some("wss"));
+        let host = uri
+            .host()
+            .ok_or_else(|| format!("server_address {server_address:?} has no host"))?
+            .to_string();
+        let port = uri.port_u16().unwrap_or(if tls { 443 } else { 80 });
+        Ok(Self { host, port, tls })
+    }
+
+    fn url(&self, plain_scheme: &str, tls_scheme: &str, path: &str) -> String {
+        let scheme = if self.tls { tls_scheme } else { plain_scheme };
+        format!("{scheme}://{}:{}{path}", self.host, self.port)
+    }
+}
+
+/// Joins an error with its sources, which is where TLS and DNS details end up.
+fn error_chain(e: &(dyn Error + 'static)) -> String {
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        let text = cause.to_string();
+        if !message.contains(&text) {
+            message.push_str(": ");
+            message.push_str(&text);
+        }
+        source = cause.source();
+    }
+    message
+}
+
+async fn with_timeout<T>(timeout: Duration, fut: impl Future<Output = Result<T, String>>) -> Result<T, String> {
+    tokio::time::timeout(timeout, fut)
+        .await
+        .unwrap_or_else(|_| Err(format!("timed out after {}s", timeout.as_secs())))
+}
+
+/// Runs all checks and prints the report. Returns `false` if any check failed.
+pub async fn run_doctor(config_path: &str) -> bool {
+    println!("NodeNexus agent doctor (version {VERSION})\n");
+    let mut report = Report { checks: Vec::new() };
+
+    let config = match load_cli_config(config_path) {
+        Ok(mut config) => {
+            config.config_path = config_path.to_string();
+            report.add(Status::Ok, "Configuration", format!("loaded {config_path} (VPS {})", config.vps_id));
+            config
+        }
+        Err(e) => {
+            report.add(Status::Fail, "Configuration", format!("cannot load {config_path}: {e}"));
+            return summarize(&report);
+        }
+    };
+    if config.pending_provisioning_token().is_some() {
+        report.add(Status::Ok, "Credentials", "not registered yet; a provisioning token is set");
+    } else if config.vps_id <= 0 || config.agent_secret.is_empty() {
+        report.add(Status::Fail, "Credentials", "vps_id and agent_secret (or provisioning_token) must be set");
+    }
+
+    let endpoint = match ServerEndpoint::parse(&config.server_address) {
+        Ok(endpoint) => endpoint,
+        Err(e) => {
+            report.add(Status::Fail, "Server address", e);
+            return summarize(&report);
+        }
+    };
+
+    check_proxy(&mut report);
+    check_dns(&mut report, &config, &endpoint).await;
+    check_tcp(&mut report, &config, &endpoint).await;
+    check_http_and_clock(&mut report, &endpoint).await;
+    check_grpc(&mut report, &config, &endpoint).await;
+    check_websocket(&mut report, &config, &endpoint).await;
+    check_handshake(&mut report, &config).await;
+
+    check_writable(&mut report, "Config file", Path::new(config_path), false);
+    if let Some(log_dir) = std::env::current_exe().ok().and_then(|exe| exe.parent().map(|dir| dir.join("logs"))) {
+        check_writable(&mut report, "Log directory", &log_dir, true);
+    }
+    check_docker_socket(&mut report);
+    check_smartctl(&mut report);
+
+    summarize(&report)
+}
+
+fn summarize(report: &Report) -> bool {
+    let failed = report.count(Status::Fail);
+    let warned = report.count(Status::Warn);
+    println!();
+    if failed == 0 && warned == 0 {
+        println!("All checks passed.");
+    } else {
+        println!("{failed} check(s) failed, {warned} warning(s).");
+    }
+    failed == 0
+}
+
+fn check_proxy(report: &mut Report) {
+    let set: Vec<&str> = PROXY_VARS
+        .iter()
+        .copied()
+        .filter(|var| std::env::var_os(var).is_some_and(|v| !v.is_empty()))
+        .collect();
+    if set.is_empty() {
+        report.add(Status::Ok, "Proxy", "no proxy environment variables set");
+    } else {
+        report.add(
+            Status::Warn,
+            "Proxy",
+            format!(
+                "{} set, but the agent connects to the server directly; make sure direct outbound access is allowed",
+                set.join(", ")
+            ),
+        );
+    }
+}
+
+async fn check_dns(report: &mut Report, config: &AgentCliConfig, endpoint: &ServerEndpoint) {
+    let host = endpoint.host.trim_start_matches('[').trim_end_matches(']');
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        report.add(Status::Skip, "DNS", format!("{host} is an IP address"));
+        return;
+    }
+    let lookup = with_timeout(CHECK_TIMEOUT, async {
+        tokio::net::lookup_host((host, endpoint.port))
+            .await
+            .map(|addrs| addrs.map(|a| a.ip().to_string()).collect::<Vec<_>>())
+            .map_err(|e| e.to_string())
+    })
+    .await;
+    match lookup {
+        Ok(ips) if !ips.is_empty() => report.add(Status::Ok, "DNS", format!("{host} -> {}", ips.join(", "))),
+        Ok(_) | Err(_) if !config.server_fallback_ips.is_empty() => report.add(
+            Status::Warn,
+            "DNS",
+            format!("cannot resolve {host}; the agent will use server_fallback_ips"),
+        ),
+        Ok(_) => report.add(Status::Fail, "DNS", format!("{host} resolved to no addresses")),
+        Err(e) => report.add(Status::Fail, "DNS", format!("cannot resolve {host}: {e}")),
+    }
+}
+
+async fn check_tcp(report: &mut Report, config: &AgentCliConfig, endpoint: &ServerEndpoint) {
+    let result = with_timeout(CHECK_TIMEOUT, async {
+        resolver::connect_to_server(config, &endpoint.host, endpoint.port)
+            .await
+            .and_then(|stream| stream.peer_addr())
+            .map_err(|e| e.to_string())
+    })
+    .await;
+    match result {
+        Ok(addr) => report.add(Status::Ok, "TCP", format!("connected to {addr}")),
+        Err(e) => report.add(
+            Status::Fail,
+            "TCP",
+            format!("cannot connect to {}:{}: {e} (check firewalls and that the server is running)", endpoint.host, endpoint.port),
+        ),
+    }
+}
+
+/// Fetches the health endpoint, which exercises TLS the same way a browser
+/// would, and compares the server's `Date` header with the local clock.
+async fn check_http_and_clock(report: &mut Report, endpoint: &ServerEndpoint) {
+    let url = endpoint.url("http", "https", "/api/health");
+    let client = match reqwest::Client::builder().timeout(CHECK_TIMEOUT).no_proxy().build() {
+        Ok(client) => client,
+        Err(e) => {
+            report.add(Status::Fail, "HTTP/TLS", error_chain(&e));
+            return;
+        }
+    };
+    let response = match client.get(&url).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            let hint = if e.is_connect() && endpoint.tls {
+                " (TLS handshake failed; check the certificate chain and that the host name matches)"
+            } else {
+                ""
+            };
+            report.add(Status::Fail, "HTTP/TLS", format!("GET {url}: {}{hint}", error_chain(&e)));
+            report.add(Status::Skip, "Clock", "server time unavailable");
+            return;
+        }
+    };
+    let status = response.status();
+    let detail = if endpoint.tls { format!("GET {url} -> {status} over TLS") } else { format!("GET {url} -> {status} (no TLS)") };
+    report.add(if status.is_success() { Status::Ok } else { Status::Warn }, "HTTP/TLS", detail);
+
+    let server_time = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok());
+    match server_time {
+        Some(server_time) => {
+            let skew = (chrono::Utc::now() - server_time.with_timezone(&chrono::Utc)).num_seconds();
+            if skew.abs() > MAX_CLOCK_SKEW_SECONDS {
+                report.add(
+                    Status::Warn,
+                    "Clock",
+                    format!("local clock is {skew:+}s off the server; metric timestamps will be skewed (enable NTP)"),
+                );
+            } else {
+                report.add(Status::Ok, "Clock", format!("within {}s of the server", skew.abs()));
+            }
+        }
+        None => report.add(Status::Skip, "Clock", "server sent no Date header"),
+    }
+}
+
+/// Same rule as `ConnectionHandler::connect_and_handshake`.
+fn uses_websocket(config: &AgentCliConfig) -> bool {
+    config.server_address.starts_with("ws")
+}
+
+async fn check_grpc(report: &mut Report, config: &AgentCliConfig, endpoint: &ServerEndpoint) {
+    let url = endpoint.url("http", "https", "");
+    let result = with_timeout(CHECK_TIMEOUT, async {
+        let mut channel = tonic::transport::Endpoint::from_shared(url.clone()).map_err(|e| error_chain(&e))?;
+        if endpoint.tls {
+            channel = channel
+                .tls_config(client_tls::grpc_tls_config(config).map_err(|e| e.to_string())?)
+                .map_err(|e| error_chain(&e))?;
+        }
+        channel
+            .connect_with_connector(resolver::ServerConnector::new(config))
+            .await
+            .map(|_| ())
+            .map_err(|e| error_chain(&e))
+    })
+    .await;
+    match result {
+        Ok(()) => report.add(Status::Ok, "gRPC", format!("HTTP/2 channel to {url} established")),
+        // The transport that isn't configured is only informational.
+        Err(e) if uses_websocket(config) => report.add(Status::Warn, "gRPC", format!("{url}: {e}")),
+        Err(e) => report.add(Status::Fail, "gRPC", format!("{url}: {e}")),
+    }
+}
+
+async fn check_websocket(report: &mut Report, config: &AgentCliConfig, endpoint: &ServerEndpoint) {
+    let url = endpoint.url("ws", "wss", "/ws/agent");
+    let result = with_timeout(CHECK_TIMEOUT, async {
+        let stream = resolver::connect_to_server(config, &endpoint.host, endpoint.port)
+            .await
+            .map_err(|e| e.to_string())?;
+        let connector = client_tls::websocket_connector(config).map_err(|e| e.to_string())?;
+        let (mut ws, _) = tokio_tungstenite::client_async_tls_with_config(url.as_str(), stream, None, connector)
+            .await
+            .map_err(|e| error_chain(&e))?;
+        let _ = SinkExt::close(&mut ws).await;
+        Ok(())
+    })
+    .await;
+    match result {
+        Ok(()) => report.add(Status::Ok, "WebSocket", format!("upgrade to {url} succeeded")),
+        Err(e) if !uses_websocket(config) => report.add(Status::Warn, "WebSocket", format!("{url}: {e}")),
+        Err(e) => report.add(Status::Fail, "WebSocket", format!("{url}: {e}")),
+    }
+}
+
+/// Performs the real handshake. A running agent for the same VPS is
+/// disconnected by the server and reconnects on its own.
+async fn check_handshake(report: &mut Report, config: &AgentCliConfig) {
+    if config.pending_provisioning_token().is_some() {
+        // Handshaking with the token would register a VPS.
+        report.add(Status::Skip, "Authentication", "registration with the provisioning token happens on first start");
+        return;
+    }
+    let result = with_timeout(HANDSHAKE_TIMEOUT, async {
+        ConnectionHandler::connect_and_handshake(config, 1)
+            .await
+            .map(drop)
+            .map_err(|e| error_chain(e.as_ref()))
+    })
+    .await;
+    match result {
+        Ok(()) => report.add(Status::Ok, "Authentication", format!("handshake with {} accepted", config.server_address)),
+        Err(e) => report.add(Status::Fail, "Authentication", e),
+    }
+}
+
+fn check_writable(report: &mut Report, name: &'static str, path: &Path, is_dir: bool) {
+    let result = if is_dir {
+        std::fs::create_dir_all(path).and_then(|_| tempfile::tempfile_in(path).map(drop))
+    } else {
+        std::fs::OpenOptions::new().append(true).open(path).map(drop)
+    };
+    match result {
+        Ok(()) => report.add(Status::Ok, name, format!("{} is writable", path.display())),
+        Err(e) => report.add(Status::Warn, name, format!("{} is not writable: {e}", path.display())),
+    }
+}
+
+#[cfg(unix)]
+fn check_docker_socket(report: &mut Report) {
+    let socket = Path::new("/var/run/docker.sock");
+    if !socket.exists() {
+        report.add(Status::Skip, "Docker socket", "not found; container metrics are unavailable");
+        return;
+    }
+    match std::os::unix::net::UnixStream::connect(socket) {
+        Ok(_) => report.add(Status::Ok, "Docker socket", format!("{} is accessible", socket.display())),
+        Err(e) => report.add(
+            Status::Warn,
+            "Docker socket",
+            format!("{}: {e} (add the agent user to the docker group)", socket.display()),
+        ),
+    }
+}
+
+#[cfg(not(unix))]
+fn check_docker_socket(report: &mut Report) {
+    report.add(Status::Skip, "Docker socket", "not checked on this platform");
+}
+
+fn check_smartctl(report: &mut Report) {
+    match std::process::Command::new("smartctl").arg("--version").output() {
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            report.add(Status::Skip, "smartctl", "not installed; disk health is unavailable")
+        }
+        Err(e) => report.add(Status::Warn, "smartctl", format!("cannot run smartctl: {e}")),
+        Ok(_) if is_root() == Some(false) => report.add(
+            Status::Warn,
+            "smartctl",
+            "installed, but reading SMART data needs root; run the agent as root",
+        ),
+        Ok(_) => report.add(Status::Ok, "smartctl", "installed"),
+    }
+}
+
+/// Effective user is root; `None` where that can't be determined.
+fn is_root() -> Option<bool> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let uids = status.lines().find_map(|line| line.strip_prefix("Uid:"))?;
+    uids.split_whitespace().nth(1).map(|euid| euid == "0")
+}
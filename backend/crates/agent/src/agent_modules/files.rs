@@ -0,0 +1,186 @@
+//! File browsing and transfer for the dashboard, driven by `CommandRequest`s
+//! carrying a `FileManagementOperation`. Transfers are chunked so no single
+//! message exceeds the gRPC message limit.
+
+use nodenexus_common::agent_service::{
+    FileManagementOperation, FileManagementResult, FileStat, file_management_operation::FileAction,
+    file_stat::ItemType,
+};
+use std::fs::{self, Metadata, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::Path;
+use std::time::SystemTime;
+
+/// Upper bound on a single download chunk.
+const MAX_CHUNK_SIZE: usize = 1024 * 1024;
+const DEFAULT_CHUNK_SIZE: usize = 256 * 1024;
+
+/// Runs the operation on a blocking thread.
+pub async fn handle_file_operation(op: FileManagementOperation) -> Result<FileManagementResult, String> {
+    tokio::task::spawn_blocking(move || run(op))
+        .await
+        .map_err(|e| format!("File operation task failed: {e}"))?
+}
+
+fn run(op: FileManagementOperation) -> Result<FileManagementResult, String> {
+    if op.path.is_empty() {
+        return Err("A path is required.".to_string());
+    }
+    let path = Path::new(&op.path);
+    match op.action() {
+        FileAction::ListDirectory => list_directory(path),
+        FileAction::StatItem => Ok(FileManagementResult {
+            item_stat: Some(stat(path)?),
+            ..Default::default()
+        }),
+        FileAction::GetFileChunk => read_chunk(path, op.offset, op.chunk_size_request),
+        FileAction::PutFileChunk => write_chunk(path, op.offset, &op.data_chunk, op.create_parents_if_needed),
+        FileAction::DeleteItem => delete(path, op.recursive_delete),
+        other => Err(format!("Unsupported file action: {other:?}")),
+    }
+}
+
+fn unix_ms(time: std::io::Result<SystemTime>) -> i64 {
+    time.ok()
+        .and_then(|t| t.duration_since(SystemTime::UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+fn to_file_stat(path: &Path, metadata: &Metadata) -> FileStat {
+    let file_type = metadata.file_type();
+    let item_type = if file_type.is_symlink() {
+        ItemType::Symlink
+    } else if file_type.is_dir() {
+        ItemType::Directory
+    } else if file_type.is_file() {
+        ItemType::File
+    } else {
+        ItemType::Other
+    };
+
+    #[cfg(unix)]
+    let (mode_permissions, owner_user, owner_group) = {
+        use std::os::unix::fs::MetadataExt;
+        (metadata.mode() & 0o7777, metadata.uid().to_string(), metadata.gid().to_string())
+    };
+    #[cfg(not(unix))]
+    let (mode_permissions, owner_user, owner_group) = (0, String::new(), String::new());
+
+    FileStat {
+        name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().into_owned())
+            .unwrap_or_else(|| path.to_string_lossy().into_owned()),
+        path: path.to_string_lossy().into_owned(),
+        item_type: item_type.into(),
+        size_bytes: metadata.len() as i64,
+        mode_permissions,
+        modified_time_unix_ms: unix_ms(metadata.modified()),
+        access_time_unix_ms: unix_ms(metadata.accessed()),
+        owner_user,
+        owner_group,
+    }
+}
+
+/// Symlinks are reported as such rather than followed.
+fn stat(path: &Path) -> Result<FileStat, String> {
+    let metadata = fs::symlink_metadata(path).map_err(|e| format!("Cannot stat {}: {e}", path.display()))?;
+    Ok(to_file_stat(path, &metadata))
+}
+
+fn list_directory(path: &Path) -> Result<FileManagementResult, String> {
+    let entries = fs::read_dir(path).map_err(|e| format!("Cannot list {}: {e}", path.display()))?;
+    let mut listing: Vec<FileStat> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let entry_path = entry.path();
+            fs::symlink_metadata(&entry_path)
+                .ok()
+                .map(|metadata| to_file_stat(&entry_path, &metadata))
+        })
+        .collect();
+    // Directories first, then by name.
+    listing.sort_by(|a, b| {
+        let a_dir = a.item_type() == ItemType::Directory;
+        let b_dir = b.item_type() == ItemType::Directory;
+        b_dir.cmp(&a_dir).then_with(|| a.name.cmp(&b.name))
+    });
+    Ok(FileManagementResult {
+        item_stat: Some(stat(path)?),
+        directory_listing: listing,
+        ..Default::default()
+    })
+}
+
+fn read_chunk(path: &Path, offset: i64, chunk_size: u32) -> Result<FileManagementResult, String> {
+    if offset < 0 {
+        return Err("Offset must not be negative.".to_string());
+    }
+    let chunk_size = match chunk_size as usize {
+        0 => DEFAULT_CHUNK_SIZE,
+        n => n.min(MAX_CHUNK_SIZE),
+    };
+    let mut file = fs::File::open(path).map_err(|e| format!("Cannot open {}: {e}", path.display()))?;
+    let len = file.metadata().map_err(|e| e.to_string())?.len();
+    file.seek(SeekFrom::Start(offset as u64)).map_err(|e| e.to_string())?;
+
+    let mut data = Vec::with_capacity(chunk_size);
+    file.by_ref()
+        .take(chunk_size as u64)
+        .read_to_end(&mut data)
+        .map_err(|e| format!("Cannot read {}: {e}", path.display()))?;
+    let is_eof = offset as u64 + data.len() as u64 >= len;
+    Ok(FileManagementResult {
+        data_chunk: data,
+        offset_returned: offset,
+        is_eof,
+        ..Default::default()
+    })
+}
+
+/// A chunk at offset 0 creates or truncates the file; later chunks must
+/// continue where the previous one ended.
+fn write_chunk(path: &Path, offset: i64, data: &[u8], create_parents: bool) -> Result<FileManagementResult, String> {
+    if offset < 0 {
+        return Err("Offset must not be negative.".to_string());
+    }
+    if create_parents {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| format!("Cannot create {}: {e}", parent.display()))?;
+        }
+    }
+    let mut file = if offset == 0 {
+        fs::File::create(path)
+    } else {
+        OpenOptions::new().write(true).open(path)
+    }
+    .map_err(|e| format!("Cannot open {} for writing: {e}", path.display()))?;
+
+    let len = file.metadata().map_err(|e| e.to_string())?.len();
+    if len != offset as u64 {
+        return Err(format!("Upload out of order: file has {len} bytes, chunk starts at {offset}."));
+    }
+    file.seek(SeekFrom::Start(offset as u64)).map_err(|e| e.to_string())?;
+    file.write_all(data)
+        .and_then(|_| file.flush())
+        .map_err(|e| format!("Cannot write {}: {e}", path.display()))?;
+    Ok(FileManagementResult {
+        bytes_written_cumulative: offset + data.len() as i64,
+        ..Default::default()
+    })
+}
+
+fn delete(path: &Path, recursive: bool) -> Result<FileManagementResult, String> {
+    let metadata = fs::symlink_metadata(path).map_err(|e| format!("Cannot stat {}: {e}", path.display()))?;
+    let result = if metadata.is_dir() {
+        if recursive { fs::remove_dir_all(path) } else { fs::remove_dir(path) }
+    } else {
+        fs::remove_file(path)
+    };
+    result.map_err(|e| format!("Cannot delete {}: {e}", path.display()))?;
+    Ok(FileManagementResult {
+        item_stat: Some(to_file_stat(path, &metadata)),
+        ..Default::default()
+    })
+}
@@ -0,0 +1,139 @@
+//! Per GPU utilization, memory, temperature and power from `nvidia-smi`
+//! (NVIDIA) and `rocm-smi` (AMD). Hosts without either tool report no GPUs.
+
+use nodenexus_common::agent_service::GpuStats;
+use std::io::ErrorKind;
+use std::process::Command;
+use tracing::{debug, info};
+
+const NVIDIA_QUERY: &str =
+    "--query-gpu=index,name,uuid,utilization.gpu,memory.used,memory.total,temperature.gpu,power.draw";
+const MIB: f64 = 1024.0 * 1024.0;
+
+/// Remembers which vendor tools exist so missing ones aren't spawned on every sample.
+pub struct GpuSampler {
+    nvidia: bool,
+    amd: bool,
+}
+
+impl Default for GpuSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GpuSampler {
+    pub fn new() -> Self {
+        Self { nvidia: true, amd: true }
+    }
+
+    pub fn sample(&mut self) -> Vec<GpuStats> {
+        let mut gpus = Vec::new();
+        if self.nvidia {
+            match run("nvidia-smi", &[NVIDIA_QUERY, "--format=csv,noheader,nounits"]) {
+                Ok(Some(output)) => gpus.extend(parse_nvidia_smi(&output)),
+                Ok(None) => {}
+                Err(()) => self.nvidia = false,
+            }
+        }
+        if self.amd {
+            let args = [
+                "--showuse",
+                "--showtemp",
+                "--showpower",
+                "--showmeminfo",
+                "vram",
+                "--showproductname",
+                "--showuniqueid",
+                "--json",
+            ];
+            match run("rocm-smi", &args) {
+                Ok(Some(output)) => gpus.extend(parse_rocm_smi(&output)),
+                Ok(None) => {}
+                Err(()) => self.amd = false,
+            }
+        }
+        gpus
+    }
+}
+
+/// `Err` when the tool isn't installed, `Ok(None)` when it failed this time.
+fn run(program: &str, args: &[&str]) -> Result<Option<String>, ()> {
+    match Command::new(program).args(args).output() {
+        Ok(output) if output.status.success() => Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned())),
+        Ok(output) => {
+            debug!(program, status = ?output.status, "GPU query exited with failure.");
+            Ok(None)
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            info!(program, "GPU tool not found; not collecting its metrics.");
+            Err(())
+        }
+        Err(e) => {
+            debug!(program, error = %e, "Failed to run GPU query.");
+            Ok(None)
+        }
+    }
+}
+
+/// Numbers as printed by the tools; "[N/A]", "N/A" and the like become `None`.
+fn number(value: &str) -> Option<f64> {
+    value.trim().parse::<f64>().ok().filter(|v| v.is_finite())
+}
+
+fn parse_nvidia_smi(output: &str) -> Vec<GpuStats> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+            if fields.len() < 8 {
+                return None;
+            }
+            Some(GpuStats {
+                index: fields[0].parse().ok()?,
+                name: fields[1].to_string(),
+                vendor: "nvidia".to_string(),
+                uuid: fields[2].to_string(),
+                utilization_percent: number(fields[3]).unwrap_or(0.0),
+                memory_used_bytes: (number(fields[4]).unwrap_or(0.0) * MIB) as u64,
+                memory_total_bytes: (number(fields[5]).unwrap_or(0.0) * MIB) as u64,
+                temperature_celsius: number(fields[6]),
+                power_draw_watts: number(fields[7]),
+            })
+        })
+        .collect()
+}
+
+/// rocm-smi keys differ between releases, so fields are matched by key fragments.
+fn parse_rocm_smi(output: &str) -> Vec<GpuStats> {
+    let Ok(serde_json::Value::Object(cards)) = serde_json::from_str::<serde_json::Value>(output) else {
+        return Vec::new();
+    };
+    let mut gpus: Vec<GpuStats> = cards
+        .iter()
+        .filter_map(|(card, fields)| {
+            let index = card.strip_prefix("card")?.parse().ok()?;
+            let fields = fields.as_object()?;
+            let find = |fragments: &[&str]| {
+                fields
+                    .iter()
+                    .find(|(key, _)| fragments.iter().all(|f| key.contains(f)))
+                    .and_then(|(_, value)| value.as_str())
+            };
+            let find_number = |fragments: &[&str]| find(fragments).and_then(number);
+            Some(GpuStats {
+                index,
+                name: find(&["Card series"]).or_else(|| find(&["Card model"])).unwrap_or_default().to_string(),
+                vendor: "amd".to_string(),
+                uuid: find(&["Unique ID"]).unwrap_or_default().to_string(),
+                utilization_percent: find_number(&["GPU use"]).unwrap_or(0.0),
+                memory_used_bytes: find_number(&["VRAM", "Used Memory"]).unwrap_or(0.0) as u64,
+                memory_total_bytes: find_number(&["VRAM", "Total Memory"]).unwrap_or(0.0) as u64,
+                temperature_celsius: find_number(&["Temperature", "edge"]).or_else(|| find_number(&["Temperature"])),
+                power_draw_watts: find_number(&["Package Power"]),
+            })
+        })
+        .collect();
+    gpus.sort_by_key(|gpu| gpu.index);
+    gpus
+}
@@ -0,0 +1,213 @@
+//! Optional inventory facts collector: installed packages, kernel parameters
+//! and enabled services. Enabled through the `inventory_facts` feature flag;
+//! the interval can be tuned with `inventory_interval_seconds`.
+
+use nodenexus_common::agent_service::{
+    AgentConfig, InstalledPackage, InventoryFacts, MessageToServer, message_to_server::Payload,
+};
+use std::collections::HashMap;
+use std::process::Command;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::mpsc;
+use tracing::{debug, error, info, warn};
+
+const FEATURE_FLAG: &str = "inventory_facts";
+const INTERVAL_FLAG: &str = "inventory_interval_seconds";
+const DEFAULT_INTERVAL_SECONDS: u64 = 6 * 60 * 60;
+/// How often a disabled collector re-checks the feature flag.
+const DISABLED_POLL_SECONDS: u64 = 300;
+
+fn run(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    if !output.status.success() {
+        debug!(program, status = ?output.status, "Inventory command exited with failure.");
+        return None;
+    }
+    Some(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+fn parse_tab_separated_packages(output: &str) -> Vec<InstalledPackage> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.split('\t');
+            let name = fields.next()?.trim();
+            let version = fields.next()?.trim();
+            if name.is_empty() || version.is_empty() {
+                return None;
+            }
+            Some(InstalledPackage {
+                name: name.to_string(),
+                version: version.to_string(),
+                arch: fields.next().unwrap_or_default().trim().to_string(),
+                source_name: fields.next().unwrap_or_default().trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// `apk info -v` prints `name-version-rN`; the version starts at the second-to-last dash.
+fn parse_apk_packages(output: &str) -> Vec<InstalledPackage> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+            let release_dash = line.rfind('-')?;
+            let version_dash = line[..release_dash].rfind('-')?;
+            Some(InstalledPackage {
+                name: line[..version_dash].to_string(),
+                version: line[version_dash + 1..].to_string(),
+                arch: String::new(),
+                source_name: String::new(),
+            })
+        })
+        .collect()
+}
+
+fn collect_packages() -> (String, Vec<InstalledPackage>) {
+    if let Some(out) = run(
+        "dpkg-query",
+        &["-W", "-f", "${db:Status-Abbrev}\t${Package}\t${Version}\t${Architecture}\t${source:Package}\n"],
+    ) {
+        // Keep only installed packages ("ii"), then drop the status column.
+        let installed: String = out
+            .lines()
+            .filter(|l| l.starts_with("ii"))
+            .filter_map(|l| l.split_once('\t').map(|(_, rest)| format!("{rest}\n")))
+            .collect();
+        return ("dpkg".to_string(), parse_tab_separated_packages(&installed));
+    }
+    if let Some(out) = run(
+        "rpm",
+        &["-qa", "--queryformat", "%{NAME}\t%|EPOCH?{%{EPOCH}:}:{}|%{VERSION}-%{RELEASE}\t%{ARCH}\n"],
+    ) {
+        return ("rpm".to_string(), parse_tab_separated_packages(&out));
+    }
+    if let Some(out) = run("apk", &["info", "-v"]) {
+        return ("apk".to_string(), parse_apk_packages(&out));
+    }
+    (String::new(), Vec::new())
+}
+
+fn collect_sysctl() -> HashMap<String, String> {
+    run("sysctl", &["-a"])
+        .map(|out| {
+            out.lines()
+                .filter_map(|line| {
+                    let (key, value) = line.split_once('=')?;
+                    Some((key.trim().to_string(), value.trim().to_string()))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+fn collect_enabled_services() -> Vec<String> {
+    if let Some(out) = run(
+        "systemctl",
+        &["list-unit-files", "--type=service", "--state=enabled", "--no-legend", "--no-pager"],
+    ) {
+        return out
+            .lines()
+            .filter_map(|l| l.split_whitespace().next())
+            .map(str::to_string)
+            .collect();
+    }
+    if let Some(out) = run("rc-update", &["show", "default"]) {
+        return out
+            .lines()
+            .filter_map(|l| l.split('|').next())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect();
+    }
+    Vec::new()
+}
+
+/// Gathers all facts. Blocking; run it on a blocking thread.
+pub fn collect_inventory_facts() -> InventoryFacts {
+    let (package_manager, mut packages) = collect_packages();
+    packages.sort_by(|a, b| a.name.cmp(&b.name).then(a.arch.cmp(&b.arch)));
+    let mut enabled_services = collect_enabled_services();
+    enabled_services.sort();
+
+    InventoryFacts {
+        collected_at_unix_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or_default(),
+        package_manager,
+        packages,
+        sysctl: collect_sysctl(),
+        enabled_services,
+    }
+}
+
+fn read_schedule(shared_agent_config: &Arc<RwLock<AgentConfig>>) -> Option<u64> {
+    let config = shared_agent_config.read().unwrap();
+    let enabled = config
+        .feature_flags
+        .get(FEATURE_FLAG)
+        .is_some_and(|v| v.eq_ignore_ascii_case("true"));
+    if !enabled {
+        return None;
+    }
+    Some(
+        config
+            .feature_flags
+            .get(INTERVAL_FLAG)
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_INTERVAL_SECONDS),
+    )
+}
+
+pub async fn inventory_collection_loop(
+    tx_to_server: mpsc::Sender<MessageToServer>,
+    shared_agent_config: Arc<RwLock<AgentConfig>>,
+    mut id_provider: impl FnMut() -> u64 + Send + 'static,
+    vps_db_id: i32,
+    agent_secret: String,
+    mut shutdown_rx: tokio::sync::watch::Receiver<()>,
+) {
+    loop {
+        let wait_seconds = match read_schedule(&shared_agent_config) {
+            Some(interval_seconds) => {
+                let facts = match tokio::task::spawn_blocking(collect_inventory_facts).await {
+                    Ok(facts) => facts,
+                    Err(e) => {
+                        error!(error = %e, "Inventory collection task failed.");
+                        return;
+                    }
+                };
+                info!(
+                    packages = facts.packages.len(),
+                    sysctl = facts.sysctl.len(),
+                    services = facts.enabled_services.len(),
+                    "Collected inventory facts."
+                );
+                let message = MessageToServer {
+                    client_message_id: id_provider(),
+                    payload: Some(Payload::InventoryFacts(facts)),
+                    vps_db_id,
+                    agent_secret: agent_secret.clone(),
+                };
+                if tx_to_server.send(message).await.is_err() {
+                    warn!("Failed to send inventory facts. Channel closed.");
+                    return;
+                }
+                interval_seconds
+            }
+            None => DISABLED_POLL_SECONDS,
+        };
+
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(wait_seconds)) => {}
+            _ = shutdown_rx.changed() => {
+                info!("Inventory collection loop received shutdown signal.");
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Log tailing: follows the `[[log_tail]]` files from the local config file
+//! and sends their new lines to the server, which matches them against the
+//! log alert rules.
+//!
+//! Like the watchdog, reading runs for the whole lifetime of the agent. Lines
+//! are queued in batches and forwarded once connected.
+
+use crate::agent_modules::config::LogTailSource;
+use nodenexus_common::agent_service::{LogLine, LogLineBatch, MessageToServer, message_to_server::Payload};
+use std::fs::{File, Metadata};
+use std::io::{Read, Seek, SeekFrom};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::{Mutex, mpsc};
+use tracing::{error, info, warn};
+
+const DEFAULT_INTERVAL_SECONDS: u64 = 5;
+/// Batches queued while disconnected; further batches are dropped once full.
+pub const BATCH_QUEUE_SIZE: usize = 64;
+/// Most bytes read from one file per interval; the rest is read on the next ones.
+const MAX_READ_BYTES: u64 = 1024 * 1024;
+/// Longer lines are cut, and so is a partial line that grows past this.
+const MAX_LINE_BYTES: usize = 4096;
+
+struct TailState {
+    source: LogTailSource,
+    file: Option<File>,
+    file_id: Option<u64>,
+    /// Whether the file was looked for yet.
+    started: bool,
+    offset: u64,
+    /// Bytes after the last newline, waiting for the rest of their line.
+    partial: Vec<u8>,
+    /// Whether a missing or unreadable file was already logged.
+    error_reported: bool,
+}
+
+fn now_unix_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+/// Identifies the file behind a path, so a rotated file is noticed even when
+/// the new one already grew past the old offset.
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<u64> {
+    use std::os::unix::fs::MetadataExt;
+    Some(metadata.ino())
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &Metadata) -> Option<u64> {
+    None
+}
+
+fn truncate_line(mut line: Vec<u8>) -> String {
+    line.truncate(MAX_LINE_BYTES);
+    let text = String::from_utf8_lossy(&line);
+    text.trim_end_matches(['\r', '\n']).to_string()
+}
+
+/// Splits `data` into complete lines, keeping a trailing partial line in
+/// `partial` for the next read.
+fn split_lines(partial: &mut Vec<u8>, data: &[u8]) -> Vec<String> {
+    let mut lines = Vec::new();
+    for chunk in data.split_inclusive(|b| *b == b'\n') {
+        if partial.len() < MAX_LINE_BYTES {
+            let room = MAX_LINE_BYTES - partial.len();
+            partial.extend_from_slice(&chunk[..chunk.len().min(room)]);
+        }
+        if chunk.ends_with(b"\n") {
+            lines.push(truncate_line(std::mem::take(partial)));
+        }
+    }
+    lines
+}
+
+impl TailState {
+    /// Opens the file if needed and reopens it from the start after rotation or truncation.
+    fn sync_file(&mut self) -> std::io::Result<()> {
+        let first = !self.started;
+        self.started = true;
+        let metadata = std::fs::metadata(&self.source.path)?;
+        let id = file_id(&metadata);
+        let replaced = id != self.file_id;
+        let truncated = metadata.len() < self.offset;
+        if self.file.is_some() && !replaced && !truncated {
+            return Ok(());
+        }
+        let mut file = File::open(&self.source.path)?;
+        if first {
+            // What is already there when the agent starts is not reported.
+            self.offset = metadata.len();
+        } else if replaced || truncated {
+            if self.file_id.is_some() || truncated {
+                info!(log_source = %self.source.name, "Log file was rotated or truncated. Reading it from the start.");
+            }
+            self.offset = 0;
+            self.partial.clear();
+        }
+        file.seek(SeekFrom::Start(self.offset))?;
+        self.file = Some(file);
+        self.file_id = id;
+        Ok(())
+    }
+
+    /// Reads the lines appended since the last call. Blocking.
+    fn read_new_lines(&mut self) -> Vec<LogLine> {
+        if let Err(e) = self.sync_file() {
+            if !self.error_reported {
+                self.error_reported = true;
+                warn!(log_source = %self.source.name, path = %self.source.path, error = %e, "Cannot open tailed log file.");
+            }
+            self.file = None;
+            return Vec::new();
+        }
+        self.error_reported = false;
+        let Some(file) = self.file.as_mut() else {
+            return Vec::new();
+        };
+        let mut data = Vec::new();
+        if let Err(e) = file.by_ref().take(MAX_READ_BYTES).read_to_end(&mut data) {
+            warn!(log_source = %self.source.name, error = %e, "Failed to read tailed log file.");
+            self.file = None;
+            return Vec::new();
+        }
+        self.offset += data.len() as u64;
+        let timestamp_unix_ms = now_unix_ms();
+        split_lines(&mut self.partial, &data)
+            .into_iter()
+            .map(|line| LogLine {
+                timestamp_unix_ms,
+                source: self.source.name.clone(),
+                line,
+            })
+            .collect()
+    }
+}
+
+/// Reads the files until the batch channel is closed.
+pub async fn log_tail_loop(
+    sources: Vec<LogTailSource>,
+    interval_seconds: Option<u64>,
+    batches_tx: mpsc::Sender<LogLineBatch>,
+) {
+    let interval = Duration::from_secs(interval_seconds.unwrap_or(DEFAULT_INTERVAL_SECONDS).max(1));
+    let mut states: Vec<TailState> = sources
+        .into_iter()
+        .map(|source| TailState {
+            source,
+            file: None,
+            file_id: None,
+            started: false,
+            offset: 0,
+            partial: Vec::new(),
+            error_reported: false,
+        })
+        .collect();
+    if states.is_empty() {
+        return;
+    }
+    info!(files = states.len(), interval_seconds = interval.as_secs(), "Log tailing started.");
+
+    loop {
+        let result = tokio::task::spawn_blocking(move || {
+            let lines: Vec<LogLine> = states.iter_mut().flat_map(TailState::read_new_lines).collect();
+            (states, lines)
+        })
+        .await;
+        let lines;
+        (states, lines) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                error!(error = %e, "Log tailing panicked. Stopping it.");
+                return;
+            }
+        };
+
+        if !lines.is_empty() {
+            match batches_tx.try_send(LogLineBatch { lines }) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    warn!("Log line queue is full. Dropping lines.");
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => return,
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Forwards queued log lines over the current server connection.
+pub async fn log_tail_forwarder(
+    batches_rx: Arc<Mutex<mpsc::Receiver<LogLineBatch>>>,
+    tx_to_server: mpsc::Sender<MessageToServer>,
+    mut id_provider: impl FnMut() -> u64 + Send + 'static,
+    vps_db_id: i32,
+    agent_secret: String,
+    mut shutdown_rx: tokio::sync::watch::Receiver<()>,
+) {
+    let mut batches_rx = batches_rx.lock().await;
+    loop {
+        tokio::select! {
+            batch = batches_rx.recv() => {
+                let Some(batch) = batch else {
+                    // Tailing stopped. Finishing here would look like a
+                    // connection failure, so wait for the regular shutdown.
+                    let _ = shutdown_rx.changed().await;
+                    return;
+                };
+                let message = MessageToServer {
+                    client_message_id: id_provider(),
+                    payload: Some(Payload::LogLineBatch(batch)),
+                    vps_db_id,
+                    agent_secret: agent_secret.clone(),
+                };
+                if tx_to_server.send(message).await.is_err() {
+                    warn!("Failed to send log lines. Channel closed.");
+                    return;
+                }
+            }
+            _ = shutdown_rx.changed() => {
+                info!("Log tail forwarder received shutdown signal.");
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,135 @@
+//! Latency mesh: pings the peers the server assigns and reports the
+//! round-trip time and loss towards each as generic metrics tagged with
+//! `peer_vps_id`. Off unless `mesh_ping_interval_seconds` is set in the agent
+//! config; the peer list comes with every config update.
+
+use futures::future::join_all;
+use nodenexus_common::agent_service::{
+    AgentConfig, GenericMetric, GenericMetricValue, GenericMetricsBatch, MeshPeer, MessageToServer,
+    generic_metric_value::ValueType, message_to_server::Payload,
+};
+use nodenexus_common::metric_catalog::{MESH_LOSS_PERCENT, MESH_RTT_MS};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::agent_modules::service_monitor::Pinger;
+
+const PACKETS_PER_PEER: u32 = 3;
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+/// How often a disabled mesh re-checks the config.
+const DISABLED_POLL_SECONDS: u64 = 60;
+/// The shortest interval honoured, whatever the config says.
+const MIN_INTERVAL_SECONDS: u64 = 10;
+
+fn read_schedule(shared_agent_config: &Arc<RwLock<AgentConfig>>) -> Option<(u64, Vec<MeshPeer>)> {
+    let config = shared_agent_config.read().unwrap();
+    if config.mesh_ping_interval_seconds == 0 || config.mesh_peers.is_empty() {
+        return None;
+    }
+    Some((
+        u64::from(config.mesh_ping_interval_seconds).max(MIN_INTERVAL_SECONDS),
+        config.mesh_peers.clone(),
+    ))
+}
+
+fn metric(name: &str, timestamp_unix_ms: i64, value: f64, peer: &MeshPeer) -> GenericMetric {
+    GenericMetric {
+        name: name.to_string(),
+        timestamp_unix_ms,
+        value: Some(GenericMetricValue {
+            value_type: Some(ValueType::DoubleValue(value)),
+        }),
+        tags: HashMap::from([
+            ("peer_vps_id".to_string(), peer.vps_id.to_string()),
+            ("peer_address".to_string(), peer.address.clone()),
+        ]),
+    }
+}
+
+/// Pings every peer at once and returns the metrics of the round.
+async fn ping_peers(peers: &[MeshPeer], pingers: &mut HashMap<bool, Pinger>) -> Vec<GenericMetric> {
+    let targets: Vec<(&MeshPeer, IpAddr)> = peers
+        .iter()
+        .filter_map(|peer| match peer.address.parse::<IpAddr>() {
+            Ok(address) => Some((peer, address)),
+            Err(_) => {
+                warn!(peer_vps_id = peer.vps_id, address = %peer.address, "Ignoring mesh peer with an invalid address.");
+                None
+            }
+        })
+        .collect();
+    for (_, address) in &targets {
+        pingers.entry(address.is_ipv6()).or_insert_with(|| Pinger::open(*address));
+    }
+    let pingers = &*pingers;
+    let timestamp_unix_ms = chrono::Utc::now().timestamp_millis();
+    let results = join_all(targets.iter().map(|(peer, address)| async move {
+        let stats = pingers[&address.is_ipv6()]
+            .ping(*address, PACKETS_PER_PEER, PING_TIMEOUT)
+            .await;
+        (*peer, stats)
+    }))
+    .await;
+
+    let mut metrics = Vec::new();
+    for (peer, stats) in results {
+        match stats {
+            Ok(stats) if stats.packets_sent > 0 => {
+                let lost = stats.packets_sent.saturating_sub(stats.packets_received);
+                let loss_percent = f64::from(lost) * 100.0 / f64::from(stats.packets_sent);
+                metrics.push(metric(MESH_LOSS_PERCENT, timestamp_unix_ms, loss_percent, peer));
+                if stats.packets_received > 0 {
+                    metrics.push(metric(MESH_RTT_MS, timestamp_unix_ms, stats.rtt_avg_ms, peer));
+                }
+            }
+            Ok(_) => {}
+            Err(e) => debug!(peer_vps_id = peer.vps_id, error = %e, "Mesh ping failed."),
+        }
+    }
+    metrics
+}
+
+pub async fn mesh_ping_loop(
+    tx_to_server: mpsc::Sender<MessageToServer>,
+    shared_agent_config: Arc<RwLock<AgentConfig>>,
+    mut id_provider: impl FnMut() -> u64 + Send + 'static,
+    vps_db_id: i32,
+    agent_secret: String,
+    mut shutdown_rx: tokio::sync::watch::Receiver<()>,
+) {
+    // One pinger per address family, opened on first use.
+    let mut pingers: HashMap<bool, Pinger> = HashMap::new();
+    loop {
+        let wait_seconds = match read_schedule(&shared_agent_config) {
+            Some((interval_seconds, peers)) => {
+                let metrics = ping_peers(&peers, &mut pingers).await;
+                debug!(peers = peers.len(), metrics = metrics.len(), "Finished mesh ping round.");
+                if !metrics.is_empty() {
+                    let message = MessageToServer {
+                        client_message_id: id_provider(),
+                        payload: Some(Payload::GenericMetricsBatch(GenericMetricsBatch { metrics })),
+                        vps_db_id,
+                        agent_secret: agent_secret.clone(),
+                    };
+                    if tx_to_server.send(message).await.is_err() {
+                        warn!("Failed to send mesh metrics. Channel closed.");
+                        return;
+                    }
+                }
+                interval_seconds
+            }
+            None => DISABLED_POLL_SECONDS,
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(wait_seconds)) => {}
+            _ = shutdown_rx.changed() => {
+                info!("Mesh ping loop received shutdown signal.");
+                return;
+            }
+        }
+    }
+}
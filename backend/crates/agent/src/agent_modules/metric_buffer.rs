@@ -0,0 +1,254 @@
+//! Bounded on-disk queue for performance batches and monitor results that
+//! couldn't be sent. They are kept while the agent is disconnected and
+//! replayed with their original timestamps once it reconnects; the server
+//! drops anything it already stored.
+//!
+//! Messages are appended as length-prefixed protobuf records to segment files
+//! in the buffer directory. Past the size limit the oldest segments are
+//! deleted, so a long outage keeps its most recent data.
+
+use crate::agent_modules::config::AgentCliConfig;
+use nodenexus_common::agent_service::{MessageToServer, message_to_server::Payload};
+use once_cell::sync::OnceCell;
+use prost::Message;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+pub const DEFAULT_MAX_MB: u64 = 64;
+const DEFAULT_DIR_NAME: &str = "metric_buffer";
+/// New messages go to a fresh segment once the current one is this large.
+const SEGMENT_BYTES: u64 = 1024 * 1024;
+const SEGMENT_EXTENSION: &str = "seg";
+
+static BUFFER: OnceCell<MetricBuffer> = OnceCell::new();
+
+struct State {
+    /// Sequence number of the segment new messages are appended to.
+    active: u64,
+    /// Size of all segments together.
+    total_bytes: u64,
+}
+
+struct MetricBuffer {
+    dir: PathBuf,
+    max_bytes: u64,
+    state: Mutex<State>,
+    /// Held by the connection that is replaying the buffer.
+    replaying: tokio::sync::Mutex<()>,
+}
+
+fn segment_path(dir: &Path, seq: u64) -> PathBuf {
+    dir.join(format!("{seq:016}.{SEGMENT_EXTENSION}"))
+}
+
+/// Sequence numbers and sizes of the segments in `dir`, oldest first.
+fn list_segments(dir: &Path) -> io::Result<Vec<(u64, u64)>> {
+    let mut segments = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(SEGMENT_EXTENSION) {
+            continue;
+        }
+        let Some(seq) = path.file_stem().and_then(|stem| stem.to_str()).and_then(|stem| stem.parse().ok()) else {
+            continue;
+        };
+        segments.push((seq, entry.metadata()?.len()));
+    }
+    segments.sort_unstable();
+    Ok(segments)
+}
+
+fn encode_record(message: &MessageToServer) -> Vec<u8> {
+    let body = message.encode_to_vec();
+    let mut record = Vec::with_capacity(4 + body.len());
+    record.extend_from_slice(&(body.len() as u32).to_le_bytes());
+    record.extend_from_slice(&body);
+    record
+}
+
+/// Reads the records of a segment. A record cut short (the agent stopped
+/// mid-write) ends the segment.
+fn decode_records(mut bytes: &[u8]) -> Vec<MessageToServer> {
+    let mut messages = Vec::new();
+    while let Some((len, rest)) = bytes.split_first_chunk::<4>() {
+        let len = u32::from_le_bytes(*len) as usize;
+        let Some(record) = rest.get(..len) else {
+            break;
+        };
+        match MessageToServer::decode(record) {
+            Ok(message) => messages.push(message),
+            Err(e) => warn!(error = %e, "Skipping unreadable buffered message."),
+        }
+        bytes = &rest[len..];
+    }
+    messages
+}
+
+impl MetricBuffer {
+    fn open(dir: PathBuf, max_bytes: u64) -> io::Result<Self> {
+        fs::create_dir_all(&dir)?;
+        let segments = list_segments(&dir)?;
+        let state = State {
+            active: segments.last().map_or(0, |(seq, _)| seq + 1),
+            total_bytes: segments.iter().map(|(_, size)| size).sum(),
+        };
+        Ok(Self {
+            dir,
+            max_bytes,
+            state: Mutex::new(state),
+            replaying: tokio::sync::Mutex::new(()),
+        })
+    }
+
+    fn push(&self, message: &MessageToServer) -> io::Result<()> {
+        let record = encode_record(message);
+        let mut state = self.state.lock().unwrap();
+        let mut path = segment_path(&self.dir, state.active);
+        if fs::metadata(&path).is_ok_and(|meta| meta.len() >= SEGMENT_BYTES) {
+            state.active += 1;
+            path = segment_path(&self.dir, state.active);
+        }
+        OpenOptions::new().create(true).append(true).open(&path)?.write_all(&record)?;
+        state.total_bytes += record.len() as u64;
+        if state.total_bytes > self.max_bytes {
+            self.trim(&mut state)?;
+        }
+        Ok(())
+    }
+
+    /// Deletes the oldest segments until the buffer fits its limit again.
+    fn trim(&self, state: &mut State) -> io::Result<()> {
+        let segments = list_segments(&self.dir)?;
+        let mut total: u64 = segments.iter().map(|(_, size)| size).sum();
+        let mut dropped = 0;
+        for (seq, size) in segments {
+            if total <= self.max_bytes || seq == state.active {
+                break;
+            }
+            fs::remove_file(segment_path(&self.dir, seq))?;
+            total -= size;
+            dropped += size;
+        }
+        state.total_bytes = total;
+        if dropped > 0 {
+            warn!(dropped_bytes = dropped, "Metric buffer is full. Dropped the oldest buffered data.");
+        }
+        Ok(())
+    }
+
+    /// The oldest segment and its messages. The segment being appended to is
+    /// sealed first, so later messages go to a new one.
+    fn take_oldest(&self) -> io::Result<Option<(PathBuf, Vec<MessageToServer>)>> {
+        let mut state = self.state.lock().unwrap();
+        let Some(&(seq, _)) = list_segments(&self.dir)?.first() else {
+            return Ok(None);
+        };
+        if seq == state.active {
+            state.active += 1;
+        }
+        let path = segment_path(&self.dir, seq);
+        let messages = decode_records(&fs::read(&path)?);
+        Ok(Some((path, messages)))
+    }
+
+    fn remove(&self, path: &Path) -> io::Result<()> {
+        let mut state = self.state.lock().unwrap();
+        let size = fs::metadata(path)?.len();
+        fs::remove_file(path)?;
+        state.total_bytes = state.total_bytes.saturating_sub(size);
+        Ok(())
+    }
+}
+
+/// Opens the buffer configured in the agent config. `metric_buffer_max_mb = 0`
+/// turns buffering off.
+pub fn init(config: &AgentCliConfig) {
+    let max_mb = config.metric_buffer_max_mb.unwrap_or(DEFAULT_MAX_MB);
+    if max_mb == 0 {
+        info!("Metric buffering is disabled.");
+        return;
+    }
+    let dir = match &config.metric_buffer_dir {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(&config.config_path)
+            .parent()
+            .unwrap_or_else(|| Path::new("."))
+            .join(DEFAULT_DIR_NAME),
+    };
+    match MetricBuffer::open(dir.clone(), max_mb * 1024 * 1024) {
+        Ok(buffer) => {
+            let buffered_bytes = buffer.state.lock().unwrap().total_bytes;
+            info!(dir = ?dir, max_mb, buffered_bytes, "Metric buffer opened.");
+            let _ = BUFFER.set(buffer);
+        }
+        Err(e) => error!(dir = ?dir, error = %e, "Failed to open the metric buffer. Metrics collected while disconnected will be lost."),
+    }
+}
+
+/// Keeps a performance batch or monitor result that couldn't be sent, to be
+/// replayed after reconnecting. Other messages are dropped.
+pub fn keep(mut message: MessageToServer) {
+    let Some(buffer) = BUFFER.get() else {
+        return;
+    };
+    match &mut message.payload {
+        Some(Payload::PerformanceBatch(batch)) => batch.replayed = true,
+        Some(Payload::ServiceMonitorResult(result)) => result.replayed = true,
+        _ => return,
+    }
+    // Filled in again when replayed.
+    message.agent_secret.clear();
+    if let Err(e) = buffer.push(&message) {
+        error!(error = %e, "Failed to write to the metric buffer.");
+    }
+}
+
+/// Sends everything buffered over the current connection, oldest first. A
+/// segment is deleted once all of its messages were handed over; if the
+/// connection drops midway, the segment is sent again after the next reconnect.
+pub async fn replay(
+    tx_to_server: mpsc::Sender<MessageToServer>,
+    id_provider: impl Fn() -> u64,
+    vps_db_id: i32,
+    agent_secret: String,
+) {
+    let Some(buffer) = BUFFER.get() else {
+        return;
+    };
+    let Ok(_replaying) = buffer.replaying.try_lock() else {
+        return;
+    };
+    let mut replayed = 0;
+    loop {
+        let (path, messages) = match buffer.take_oldest() {
+            Ok(Some(segment)) => segment,
+            Ok(None) => break,
+            Err(e) => {
+                error!(error = %e, "Failed to read the metric buffer.");
+                return;
+            }
+        };
+        for mut message in messages {
+            message.client_message_id = id_provider();
+            message.vps_db_id = vps_db_id;
+            message.agent_secret = agent_secret.clone();
+            if tx_to_server.send(message).await.is_err() {
+                warn!(replayed, "Connection closed while replaying buffered metrics.");
+                return;
+            }
+            replayed += 1;
+        }
+        if let Err(e) = buffer.remove(&path) {
+            error!(path = ?path, error = %e, "Failed to delete a replayed metric buffer segment.");
+            return;
+        }
+    }
+    if replayed > 0 {
+        info!(replayed, "Replayed buffered metrics.");
+    }
+}
@@ -0,0 +1,142 @@
+//! Metric scripts: runs the `[[metric_scripts]]` commands from the local config
+//! file on their interval and reports what they print as generic metrics, so
+//! any script can feed custom metrics without credentials of its own.
+//!
+//! Each output line is one sample, `<name> <value> [label=value ...]`, e.g.
+//! `myapp.queue_depth 42 queue=emails`. Empty lines and lines starting with
+//! `#` are ignored, as are lines that don't parse.
+
+use nodenexus_common::agent_service::{
+    GenericMetric, GenericMetricValue, GenericMetricsBatch, MessageToServer,
+    generic_metric_value::ValueType, message_to_server::Payload,
+};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+use crate::agent_modules::command::shell::inline_command;
+use crate::agent_modules::config::MetricScript;
+
+const DEFAULT_INTERVAL_SECONDS: u64 = 60;
+const MIN_INTERVAL_SECONDS: u64 = 10;
+const DEFAULT_TIMEOUT_SECONDS: u64 = 30;
+/// Samples taken from one run; the rest of the output is ignored.
+const MAX_SAMPLES_PER_RUN: usize = 1000;
+
+/// Parses one output line; `None` for comments, blank and malformed lines.
+fn parse_line(line: &str, timestamp_unix_ms: i64) -> Option<GenericMetric> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return None;
+    }
+    let mut fields = line.split_whitespace();
+    let name = fields.next()?;
+    let value = fields.next()?.parse::<f64>().ok().filter(|v| v.is_finite())?;
+    let tags = fields
+        .map(|field| {
+            let (key, value) = field.split_once('=')?;
+            (!key.is_empty()).then(|| (key.to_string(), value.to_string()))
+        })
+        .collect::<Option<HashMap<_, _>>>()?;
+    Some(GenericMetric {
+        name: name.to_string(),
+        timestamp_unix_ms,
+        value: Some(GenericMetricValue {
+            value_type: Some(ValueType::DoubleValue(value)),
+        }),
+        tags,
+    })
+}
+
+/// Runs the script once and returns its samples; failures are logged and yield none.
+async fn run_script(script: &MetricScript) -> Vec<GenericMetric> {
+    let timeout = Duration::from_secs(script.timeout_seconds.unwrap_or(DEFAULT_TIMEOUT_SECONDS).max(1));
+    let mut command = tokio::process::Command::from(inline_command(&script.command));
+    command.kill_on_drop(true);
+    let output = match tokio::time::timeout(timeout, command.output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => {
+            warn!(script = %script.name, error = %e, "Failed to run metric script.");
+            return Vec::new();
+        }
+        Err(_) => {
+            warn!(script = %script.name, timeout_secs = timeout.as_secs(), "Metric script timed out.");
+            return Vec::new();
+        }
+    };
+    if !output.status.success() {
+        warn!(
+            script = %script.name,
+            status = %output.status,
+            stderr = %String::from_utf8_lossy(&output.stderr).trim(),
+            "Metric script failed."
+        );
+        return Vec::new();
+    }
+    let timestamp_unix_ms = chrono::Utc::now().timestamp_millis();
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| parse_line(line, timestamp_unix_ms))
+        .take(MAX_SAMPLES_PER_RUN)
+        .collect()
+}
+
+/// Runs one script on its interval until the connection closes or the agent shuts down.
+async fn script_loop(
+    script: MetricScript,
+    tx_to_server: mpsc::Sender<MessageToServer>,
+    id_provider: impl Fn() -> u64,
+    vps_db_id: i32,
+    agent_secret: String,
+    mut shutdown_rx: tokio::sync::watch::Receiver<()>,
+) {
+    let interval = Duration::from_secs(
+        script
+            .interval_seconds
+            .unwrap_or(DEFAULT_INTERVAL_SECONDS)
+            .max(MIN_INTERVAL_SECONDS),
+    );
+    loop {
+        let metrics = run_script(&script).await;
+        debug!(script = %script.name, metrics = metrics.len(), "Ran metric script.");
+        if !metrics.is_empty() {
+            let message = MessageToServer {
+                client_message_id: id_provider(),
+                payload: Some(Payload::GenericMetricsBatch(GenericMetricsBatch { metrics })),
+                vps_db_id,
+                agent_secret: agent_secret.clone(),
+            };
+            if tx_to_server.send(message).await.is_err() {
+                warn!("Failed to send metric script output. Channel closed.");
+                return;
+            }
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = shutdown_rx.changed() => return,
+        }
+    }
+}
+
+pub async fn metric_scripts_loop(
+    scripts: Vec<MetricScript>,
+    tx_to_server: mpsc::Sender<MessageToServer>,
+    id_provider: impl Fn() -> u64 + Send + Sync + Clone + 'static,
+    vps_db_id: i32,
+    agent_secret: String,
+    shutdown_rx: tokio::sync::watch::Receiver<()>,
+) {
+    info!(scripts = scripts.len(), "Starting metric scripts.");
+    futures::future::join_all(scripts.into_iter().map(|script| {
+        script_loop(
+            script,
+            tx_to_server.clone(),
+            id_provider.clone(),
+            vps_db_id,
+            agent_secret.clone(),
+            shutdown_rx.clone(),
+        )
+    }))
+    .await;
+}
@@ -1,3 +1,8 @@
+use crate::agent_modules::diskstats::DiskStatsSampler;
+use crate::agent_modules::gpu::GpuSampler;
+use crate::agent_modules::metric_buffer;
+use crate::agent_modules::netstats::NetStatsSampler;
+use crate::agent_modules::smart::{self, SmartSampler};
 use nodenexus_common::agent_service::{
     AgentConfig, MessageToServer, PerformanceSnapshot, PerformanceSnapshotBatch,
     message_to_server::Payload,
@@ -6,8 +11,8 @@ use netdev::interface::InterfaceType;
 use std::collections::HashSet;
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
-use sysinfo::{DiskKind, Disks, Networks, System};
-use tokio::sync::mpsc;
+use sysinfo::{Components, DiskKind, Disks, Networks, System};
+use tokio::sync::{Mutex, mpsc};
 use tracing::{debug, error, info, warn};
 
 // PreviousNetworkState struct is no longer needed
@@ -15,10 +20,16 @@ use tracing::{debug, error, info, warn};
 
 /// Collects a performance snapshot focusing ONLY on the default network interface
 /// for both cumulative and instantaneous network data, and total disk I/O rates.
+#[allow(clippy::too_many_arguments)]
 fn collect_performance_snapshot(
     sys: &System,
     disks: &mut Disks,
     networks: &mut Networks,
+    disk_stats_sampler: &mut DiskStatsSampler,
+    net_stats_sampler: &mut NetStatsSampler,
+    gpu_sampler: &mut GpuSampler,
+    smart_sampler: &mut SmartSampler,
+    components: &mut Components,
     prev_collection_time_opt: &Option<Instant>,
     current_time: Instant,
     excluded_fs_types: &HashSet<&str>,
@@ -193,20 +204,49 @@ fn collect_performance_snapshot(
         // Instantaneous network speed (Default Interface Only)
         network_rx_bytes_per_sec: network_rx_bps, // Renumbered field 16
         network_tx_bytes_per_sec: network_tx_bps, // Renumbered field 17
+        disk_io_stats: disk_stats_sampler.sample(),
+        network_interface_stats: net_stats_sampler.sample(),
+        gpu_stats: gpu_sampler.sample(),
+        disk_health_stats: smart_sampler.sample(),
+        temperature_sensor_stats: smart::sample_temperatures(components),
     }
 }
 
+/// Batches waiting for the forwarder. While disconnected the queue fills up
+/// and further batches go to the metric buffer.
+pub const BATCH_QUEUE_SIZE: usize = 4;
+
+/// Queues a finished batch for the current connection, or buffers it on disk
+/// when there is none.
+fn queue_batch(batches_tx: &mpsc::Sender<PerformanceSnapshotBatch>, batch: PerformanceSnapshotBatch) {
+    match batches_tx.try_send(batch) {
+        Ok(()) => {}
+        Err(mpsc::error::TrySendError::Full(batch) | mpsc::error::TrySendError::Closed(batch)) => {
+            debug!(batch_size = batch.snapshots.len(), "No connection to send metrics batch. Buffering it.");
+            metric_buffer::keep(MessageToServer {
+                client_message_id: 0,
+                payload: Some(Payload::PerformanceBatch(batch)),
+                vps_db_id: 0,
+                agent_secret: String::new(),
+            });
+        }
+    }
+}
+
+/// Collects snapshots for the whole lifetime of the agent, independent of the
+/// server connection, and hands finished batches to `metrics_forwarder`.
 pub async fn metrics_collection_loop(
-    tx_to_server: mpsc::Sender<MessageToServer>,
+    batches_tx: mpsc::Sender<PerformanceSnapshotBatch>,
     shared_agent_config: Arc<RwLock<AgentConfig>>,
-    mut id_provider: impl FnMut() -> u64 + Send + 'static,
-    vps_db_id: i32,
-    agent_secret: String,
     mut sys: System,
-    mut shutdown_rx: tokio::sync::watch::Receiver<()>,
 ) {
     let mut disks = Disks::new_with_refreshed_list();
     let mut networks = Networks::new_with_refreshed_list();
+    let mut disk_stats_sampler = DiskStatsSampler::new();
+    let mut net_stats_sampler = NetStatsSampler::new();
+    let mut gpu_sampler = GpuSampler::new();
+    let mut smart_sampler = SmartSampler::new();
+    let mut components = Components::new_with_refreshed_list();
     let mut snapshot_batch_vec = Vec::new();
 
     // Define a set of file system types to exclude.
@@ -277,6 +317,8 @@ pub async fn metrics_collection_loop(
     // Initial refresh to set the baseline for the *next* delta calculation by sysinfo
     disks.refresh(true);
     networks.refresh(true);
+    disk_stats_sampler.sample();
+    net_stats_sampler.sample();
     let mut prev_collection_time: Option<Instant> = Some(Instant::now());
 
     loop {
@@ -342,65 +384,81 @@ pub async fn metrics_collection_loop(
         // --- End Check ---
 
         tokio::select! {
-            biased;
-
-            _ = shutdown_rx.changed() => {
-                info!("Shutdown signal received, terminating metrics collection loop.");
-                break;
-            }
-
             _ = collect_interval.tick() => {
                 let current_time = Instant::now();
                 let snapshot = collect_performance_snapshot(
                     &sys,
                     &mut disks,
                     &mut networks,
+                    &mut disk_stats_sampler,
+                    &mut net_stats_sampler,
+                    &mut gpu_sampler,
+                    &mut smart_sampler,
+                    &mut components,
                     &prev_collection_time,
                     current_time,
                     &excluded_fs_types,
                     &active_interface_name, // Pass the cached interface name
                 );
-                snapshot_batch_vec.push(snapshot.clone());
+                snapshot_batch_vec.push(snapshot);
                 prev_collection_time = Some(current_time); // Update prev_collection_time for the next iteration
 
                 if snapshot_batch_vec.len() >= batch_max_size as usize {
-                    let batch_to_send_vec = std::mem::take(&mut snapshot_batch_vec);
-                    if !batch_to_send_vec.is_empty() {
-                        let batch_len = batch_to_send_vec.len();
-                        let batch_payload = PerformanceSnapshotBatch { snapshots: batch_to_send_vec };
-                        let msg_id = id_provider();
-                        if let Err(e) = tx_to_server.send(MessageToServer {
-                            client_message_id: msg_id,
-                            payload: Some(Payload::PerformanceBatch(batch_payload)),
-                            vps_db_id,
-                            agent_secret: agent_secret.clone(),
-                        }).await {
-                            error!(error = %e, "Failed to send metrics batch (size trigger).");
-                        } else {
-                            debug!(msg_id = msg_id, batch_size = batch_len, "Sent metrics batch (size trigger).");
-                        }
-                    }
+                    let snapshots = std::mem::take(&mut snapshot_batch_vec);
+                    queue_batch(&batches_tx, PerformanceSnapshotBatch { snapshots, replayed: false });
                 }
             }
             _ = upload_interval.tick() => {
-                let batch_to_send_vec = std::mem::take(&mut snapshot_batch_vec);
-                if !batch_to_send_vec.is_empty() {
-                    let batch_len = batch_to_send_vec.len();
-                    let batch_payload = PerformanceSnapshotBatch { snapshots: batch_to_send_vec };
-                    let msg_id = id_provider();
-                     if let Err(e) = tx_to_server.send(MessageToServer {
-                        client_message_id: msg_id,
-                        payload: Some(Payload::PerformanceBatch(batch_payload)),
-                        vps_db_id,
-                        agent_secret: agent_secret.clone(),
-                    }).await {
-                        error!(error = %e, "Failed to send metrics batch (interval trigger).");
-                    } else {
-                        debug!(msg_id = msg_id, batch_size = batch_len, "Sent metrics batch (interval trigger).");
-                    }
+                let snapshots = std::mem::take(&mut snapshot_batch_vec);
+                if !snapshots.is_empty() {
+                    queue_batch(&batches_tx, PerformanceSnapshotBatch { snapshots, replayed: false });
+                }
+            }
+        }
+    }
+}
+
+/// Forwards collected batches over the current server connection. A batch
+/// that can't be sent goes to the metric buffer.
+pub async fn metrics_forwarder(
+    batches_rx: Arc<Mutex<mpsc::Receiver<PerformanceSnapshotBatch>>>,
+    tx_to_server: mpsc::Sender<MessageToServer>,
+    mut id_provider: impl FnMut() -> u64 + Send + 'static,
+    vps_db_id: i32,
+    agent_secret: String,
+    mut shutdown_rx: tokio::sync::watch::Receiver<()>,
+) {
+    let mut batches_rx = batches_rx.lock().await;
+    loop {
+        tokio::select! {
+            biased;
+
+            _ = shutdown_rx.changed() => {
+                info!("Shutdown signal received, terminating metrics forwarder.");
+                return;
+            }
+
+            batch = batches_rx.recv() => {
+                let Some(batch) = batch else {
+                    // The collector stopped. Finishing here would look like a
+                    // connection failure, so wait for the regular shutdown.
+                    let _ = shutdown_rx.changed().await;
+                    return;
+                };
+                let batch_size = batch.snapshots.len();
+                let msg_id = id_provider();
+                if let Err(e) = tx_to_server.send(MessageToServer {
+                    client_message_id: msg_id,
+                    payload: Some(Payload::PerformanceBatch(batch)),
+                    vps_db_id,
+                    agent_secret: agent_secret.clone(),
+                }).await {
+                    error!("Failed to send metrics batch. Buffering it.");
+                    metric_buffer::keep(e.0);
+                    return;
                 }
+                debug!(msg_id, batch_size, "Sent metrics batch.");
             }
         }
     }
-    info!("Metrics collection loop gracefully shut down.");
 }
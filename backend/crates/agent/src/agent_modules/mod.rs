@@ -1,7 +1,24 @@
 pub mod command;
 pub mod communication;
 pub mod config;
+pub mod diskstats;
+pub mod docker;
+pub mod doctor;
+pub mod files;
+pub mod gpu;
+pub mod inventory;
+pub mod log_tail;
+pub mod mesh;
+pub mod metric_buffer;
+pub mod metric_scripts;
 pub mod metrics;
+pub mod netstats;
+pub mod processes;
+#[cfg(windows)]
+pub mod service_install;
 pub mod service_monitor;
+pub mod smart;
+pub mod terminal;
 pub mod updater;
 pub mod utils;
+pub mod watchdog;
@@ -0,0 +1,183 @@
+//! Per physical network interface error/drop counters and link state from
+//! `/sys/class/net`, or from the IP Helper interface table on Windows. Rates
+//! are computed from the counters of two consecutive samples; other platforms
+//! report no interfaces.
+
+use nodenexus_common::agent_service::NetworkInterfaceStats;
+use std::collections::HashMap;
+use std::time::Instant;
+
+#[derive(Debug, Clone)]
+struct Counters {
+    rx_errors: u64,
+    tx_errors: u64,
+    rx_dropped: u64,
+    tx_dropped: u64,
+    collisions: u64,
+    carrier_changes: u64,
+    link_up: bool,
+    speed_mbps: u32,
+    duplex: String,
+}
+
+/// Keeps the previous sample so each call reports the rates since the last one.
+#[derive(Default)]
+pub struct NetStatsSampler {
+    previous: Option<(Instant, HashMap<String, Counters>)>,
+}
+
+impl NetStatsSampler {
+    pub fn new() -> Self {
+        Self { previous: None }
+    }
+
+    /// Returns per-interface stats since the previous call; empty on the first call.
+    pub fn sample(&mut self) -> Vec<NetworkInterfaceStats> {
+        let Some(current) = read_counters() else {
+            return Vec::new();
+        };
+        let now = Instant::now();
+        let stats = match &self.previous {
+            Some((previous_time, previous)) => {
+                let elapsed_secs = now.duration_since(*previous_time).as_secs_f64();
+                let mut stats: Vec<NetworkInterfaceStats> = current
+                    .iter()
+                    .filter_map(|(name, counters)| {
+                        let before = previous.get(name)?;
+                        compute_stats(name, before, counters, elapsed_secs)
+                    })
+                    .collect();
+                stats.sort_by(|a, b| a.name.cmp(&b.name));
+                stats
+            }
+            None => Vec::new(),
+        };
+        self.previous = Some((now, current));
+        stats
+    }
+}
+
+fn compute_stats(
+    name: &str,
+    before: &Counters,
+    after: &Counters,
+    elapsed_secs: f64,
+) -> Option<NetworkInterfaceStats> {
+    if elapsed_secs <= 0.0 {
+        return None;
+    }
+    // Counters reset when a driver is reloaded; report zero rather than a bogus spike.
+    let rate = |a: u64, b: u64| b.saturating_sub(a) as f64 / elapsed_secs;
+    Some(NetworkInterfaceStats {
+        name: name.to_string(),
+        rx_errors_per_sec: rate(before.rx_errors, after.rx_errors),
+        tx_errors_per_sec: rate(before.tx_errors, after.tx_errors),
+        rx_dropped_per_sec: rate(before.rx_dropped, after.rx_dropped),
+        tx_dropped_per_sec: rate(before.tx_dropped, after.tx_dropped),
+        collisions_per_sec: rate(before.collisions, after.collisions),
+        // Windows has no carrier change counter, so a changed link state counts as one.
+        carrier_changes: after
+            .carrier_changes
+            .saturating_sub(before.carrier_changes)
+            .max(u64::from(before.link_up != after.link_up)),
+        link_up: after.link_up,
+        speed_mbps: after.speed_mbps,
+        duplex: after.duplex.clone(),
+    })
+}
+
+#[cfg(target_os = "linux")]
+fn read_counters() -> Option<HashMap<String, Counters>> {
+    use std::path::Path;
+
+    let read = |path: &Path| std::fs::read_to_string(path).ok().map(|s| s.trim().to_string());
+    let number = |path: &Path| read(path).and_then(|s| s.parse::<u64>().ok()).unwrap_or(0);
+
+    let entries = std::fs::read_dir("/sys/class/net").ok()?;
+    let mut counters = HashMap::new();
+    for entry in entries.flatten() {
+        let dir = entry.path();
+        // Only interfaces backed by a device; skips lo, bridges, veths and tunnels.
+        if !dir.join("device").exists() {
+            continue;
+        }
+        let stats = dir.join("statistics");
+        let name = entry.file_name().to_string_lossy().into_owned();
+        counters.insert(
+            name,
+            Counters {
+                rx_errors: number(&stats.join("rx_errors")),
+                tx_errors: number(&stats.join("tx_errors")),
+                rx_dropped: number(&stats.join("rx_dropped")),
+                tx_dropped: number(&stats.join("tx_dropped")),
+                collisions: number(&stats.join("collisions")),
+                carrier_changes: number(&dir.join("carrier_changes")),
+                // Reading `carrier` fails with EINVAL while the interface is down.
+                link_up: read(&dir.join("carrier")).as_deref() == Some("1"),
+                // `speed` is -1 or unreadable when unknown.
+                speed_mbps: read(&dir.join("speed"))
+                    .and_then(|s| s.parse::<u32>().ok())
+                    .unwrap_or(0),
+                duplex: read(&dir.join("duplex"))
+                    .filter(|d| d == "full" || d == "half")
+                    .unwrap_or_default(),
+            },
+        );
+    }
+    Some(counters)
+}
+
+/// Windows exposes neither collisions, carrier changes nor duplex; those stay
+/// zero/empty, and link flaps are only seen as a changed link state.
+#[cfg(windows)]
+fn read_counters() -> Option<HashMap<String, Counters>> {
+    use windows_sys::Win32::Foundation::NO_ERROR;
+    use windows_sys::Win32::NetworkManagement::IpHelper::{FreeMibTable, GetIfTable2, MIB_IF_TABLE2};
+    use windows_sys::Win32::NetworkManagement::Ndis::MediaConnectStateConnected;
+
+    // Bits of MIB_IF_ROW2::InterfaceAndOperStatusFlags.
+    const HARDWARE_INTERFACE: u8 = 1 << 0;
+    const FILTER_INTERFACE: u8 = 1 << 1;
+    const CONNECTOR_PRESENT: u8 = 1 << 2;
+
+    let mut table: *mut MIB_IF_TABLE2 = std::ptr::null_mut();
+    // SAFETY: on success GetIfTable2 hands over a table that stays valid until FreeMibTable.
+    if unsafe { GetIfTable2(&mut table) } != NO_ERROR {
+        return None;
+    }
+    let rows = unsafe { std::slice::from_raw_parts((*table).Table.as_ptr(), (*table).NumEntries as usize) };
+
+    let mut counters = HashMap::new();
+    for row in rows {
+        // Only adapters with a physical connector; skips loopback, tunnels,
+        // virtual switches and the filter driver rows stacked on each adapter.
+        let flags = row.InterfaceAndOperStatusFlags._bitfield;
+        if flags & HARDWARE_INTERFACE == 0 || flags & CONNECTOR_PRESENT == 0 || flags & FILTER_INTERFACE != 0 {
+            continue;
+        }
+        let alias_len = row.Alias.iter().position(|&c| c == 0).unwrap_or(row.Alias.len());
+        counters.insert(
+            String::from_utf16_lossy(&row.Alias[..alias_len]),
+            Counters {
+                rx_errors: row.InErrors,
+                tx_errors: row.OutErrors,
+                rx_dropped: row.InDiscards,
+                tx_dropped: row.OutDiscards,
+                collisions: 0,
+                carrier_changes: 0,
+                link_up: row.MediaConnectState == MediaConnectStateConnected,
+                // u64::MAX when unknown.
+                speed_mbps: u32::try_from(row.ReceiveLinkSpeed / 1_000_000).unwrap_or(0),
+                duplex: String::new(),
+            },
+        );
+    }
+    // SAFETY: `table` came from GetIfTable2 and `rows` is not used past this point.
+    unsafe { FreeMibTable(table.cast()) };
+    Some(counters)
+}
+
+#[cfg(not(any(target_os = "linux", windows)))]
+fn read_counters() -> Option<HashMap<String, Counters>> {
+    None
+}
@@ -0,0 +1,192 @@
+//! Per-process metrics: periodically reports the top processes by CPU and by
+//! memory. On by default; set the `process_metrics` feature flag to "false" to
+//! disable it. `process_metrics_interval_seconds` and `process_metrics_top_n`
+//! tune how often and how many processes are reported.
+//!
+//! On Windows sysinfo only knows local user accounts, so the owners of service
+//! and domain user processes are looked up by SID instead.
+
+use nodenexus_common::agent_service::{
+    AgentConfig, MessageToServer, ProcessInfo, ProcessSnapshot, message_to_server::Payload,
+};
+use std::collections::HashSet;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, Uid, UpdateKind, Users};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+const FEATURE_FLAG: &str = "process_metrics";
+const INTERVAL_FLAG: &str = "process_metrics_interval_seconds";
+const TOP_N_FLAG: &str = "process_metrics_top_n";
+const DEFAULT_INTERVAL_SECONDS: u64 = 60;
+const DEFAULT_TOP_N: usize = 10;
+const MAX_TOP_N: usize = 50;
+/// How often a disabled collector re-checks the feature flag.
+const DISABLED_POLL_SECONDS: u64 = 300;
+
+struct Schedule {
+    interval_seconds: u64,
+    top_n: usize,
+}
+
+fn read_schedule(shared_agent_config: &Arc<RwLock<AgentConfig>>) -> Option<Schedule> {
+    let config = shared_agent_config.read().unwrap();
+    let disabled = config
+        .feature_flags
+        .get(FEATURE_FLAG)
+        .is_some_and(|v| v.eq_ignore_ascii_case("false"));
+    if disabled {
+        return None;
+    }
+    Some(Schedule {
+        interval_seconds: config
+            .feature_flags
+            .get(INTERVAL_FLAG)
+            .and_then(|v| v.parse::<u64>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_INTERVAL_SECONDS),
+        top_n: config
+            .feature_flags
+            .get(TOP_N_FLAG)
+            .and_then(|v| v.parse::<usize>().ok())
+            .filter(|v| *v > 0)
+            .unwrap_or(DEFAULT_TOP_N)
+            .min(MAX_TOP_N),
+    })
+}
+
+fn refresh(sys: &mut System) {
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing()
+            .with_cpu()
+            .with_memory()
+            .with_user(UpdateKind::OnlyIfNotSet),
+    );
+}
+
+/// The union of the `top_n` processes by CPU and the `top_n` by resident memory.
+fn collect_process_snapshot(sys: &System, users: &Users, top_n: usize) -> ProcessSnapshot {
+    let mut by_cpu: Vec<_> = sys.processes().values().collect();
+    by_cpu.sort_by(|a, b| b.cpu_usage().total_cmp(&a.cpu_usage()));
+    let mut by_memory = by_cpu.clone();
+    by_memory.sort_by_key(|p| std::cmp::Reverse(p.memory()));
+
+    let mut seen: HashSet<Pid> = HashSet::new();
+    let processes = by_cpu
+        .into_iter()
+        .take(top_n)
+        .chain(by_memory.into_iter().take(top_n))
+        .filter(|p| seen.insert(p.pid()))
+        .map(|p| ProcessInfo {
+            pid: p.pid().as_u32(),
+            name: p.name().to_string_lossy().into_owned(),
+            cpu_usage_percent: p.cpu_usage(),
+            memory_rss_bytes: p.memory(),
+            user: p.user_id().and_then(|uid| user_name(users, uid)).unwrap_or_default(),
+        })
+        .collect();
+
+    ProcessSnapshot {
+        timestamp_unix_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i64,
+        processes,
+    }
+}
+
+#[cfg(not(windows))]
+fn user_name(users: &Users, uid: &Uid) -> Option<String> {
+    users.get_user_by_id(uid).map(|u| u.name().to_string())
+}
+
+#[cfg(windows)]
+fn user_name(users: &Users, uid: &Uid) -> Option<String> {
+    users
+        .get_user_by_id(uid)
+        .map(|u| u.name().to_string())
+        .or_else(|| account_name(&uid.to_string()))
+}
+
+/// Account name for a string SID such as `S-1-5-18` (SYSTEM).
+#[cfg(windows)]
+fn account_name(sid: &str) -> Option<String> {
+    use windows_sys::Win32::Foundation::LocalFree;
+    use windows_sys::Win32::Security::Authorization::ConvertStringSidToSidW;
+    use windows_sys::Win32::Security::LookupAccountSidW;
+
+    let sid: Vec<u16> = sid.encode_utf16().chain(Some(0)).collect();
+    let mut name = [0u16; 256];
+    let mut domain = [0u16; 256];
+    let mut name_len = name.len() as u32;
+    let mut domain_len = domain.len() as u32;
+    let mut name_use = 0;
+    // SAFETY: the buffers and their lengths match, and the converted SID is
+    // freed with LocalFree as ConvertStringSidToSidW requires.
+    let found = unsafe {
+        let mut psid = std::ptr::null_mut();
+        if ConvertStringSidToSidW(sid.as_ptr(), &mut psid) == 0 {
+            return None;
+        }
+        let found = LookupAccountSidW(
+            std::ptr::null(),
+            psid,
+            name.as_mut_ptr(),
+            &mut name_len,
+            domain.as_mut_ptr(),
+            &mut domain_len,
+            &mut name_use,
+        );
+        LocalFree(psid);
+        found != 0
+    };
+    found.then(|| String::from_utf16_lossy(&name[..name_len as usize]))
+}
+
+pub async fn process_collection_loop(
+    tx_to_server: mpsc::Sender<MessageToServer>,
+    shared_agent_config: Arc<RwLock<AgentConfig>>,
+    mut id_provider: impl FnMut() -> u64 + Send + 'static,
+    vps_db_id: i32,
+    agent_secret: String,
+    mut shutdown_rx: tokio::sync::watch::Receiver<()>,
+) {
+    let mut sys = System::new();
+    let mut users = Users::new_with_refreshed_list();
+    // CPU usage is measured between two refreshes, so take the baseline now.
+    refresh(&mut sys);
+
+    loop {
+        let wait_seconds = match read_schedule(&shared_agent_config) {
+            Some(schedule) => {
+                refresh(&mut sys);
+                users.refresh();
+                let snapshot = collect_process_snapshot(&sys, &users, schedule.top_n);
+                debug!(processes = snapshot.processes.len(), "Collected process snapshot.");
+                let message = MessageToServer {
+                    client_message_id: id_provider(),
+                    payload: Some(Payload::ProcessSnapshot(snapshot)),
+                    vps_db_id,
+                    agent_secret: agent_secret.clone(),
+                };
+                if tx_to_server.send(message).await.is_err() {
+                    warn!("Failed to send process snapshot. Channel closed.");
+                    return;
+                }
+                schedule.interval_seconds
+            }
+            None => DISABLED_POLL_SECONDS,
+        };
+
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_secs(wait_seconds)) => {}
+            _ = shutdown_rx.changed() => {
+                info!("Process collection loop received shutdown signal.");
+                return;
+            }
+        }
+    }
+}
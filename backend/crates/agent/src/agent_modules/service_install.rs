@@ -0,0 +1,104 @@
+//! `--install-service` / `--uninstall-service`: registers the agent with the
+//! Windows service control manager so it starts at boot and is restarted when
+//! it crashes.
+
+use std::ffi::{OsStr, OsString};
+use std::path::Path;
+use std::time::Duration;
+use windows_service::service::{
+    ServiceAccess, ServiceAction, ServiceActionType, ServiceErrorControl, ServiceFailureActions,
+    ServiceFailureResetPeriod, ServiceInfo, ServiceStartType, ServiceState, ServiceType,
+};
+use windows_service::service_manager::{ServiceManager, ServiceManagerAccess};
+
+pub const SERVICE_NAME: &str = "NodeNexusAgent";
+const DISPLAY_NAME: &str = "NodeNexus Agent";
+const DESCRIPTION: &str = "Reports metrics to the NodeNexus server and runs its commands.";
+/// Restarts after the first three crashes; the count resets after a day without one.
+const RESTART_ATTEMPTS: usize = 3;
+const RESTART_DELAY: Duration = Duration::from_secs(60);
+const FAILURE_RESET_PERIOD: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Installs and starts the service, running this executable with `config_path`
+/// as LocalSystem. Needs an elevated prompt.
+pub fn install_service(config_path: &str) -> Result<(), String> {
+    let config_path = std::path::absolute(Path::new(config_path))
+        .map_err(|e| format!("Invalid config path '{config_path}': {e}"))?;
+    if !config_path.is_file() {
+        return Err(format!("Config file {} not found.", config_path.display()));
+    }
+    let executable_path =
+        std::env::current_exe().map_err(|e| format!("Cannot locate the agent executable: {e}"))?;
+
+    let manager = ServiceManager::local_computer(
+        None::<&str>,
+        ServiceManagerAccess::CONNECT | ServiceManagerAccess::CREATE_SERVICE,
+    )
+    .map_err(|e| format!("Cannot open the service manager (run as administrator): {e}"))?;
+    let service_info = ServiceInfo {
+        name: OsString::from(SERVICE_NAME),
+        display_name: OsString::from(DISPLAY_NAME),
+        service_type: ServiceType::OWN_PROCESS,
+        start_type: ServiceStartType::AutoStart,
+        error_control: ServiceErrorControl::Normal,
+        executable_path,
+        launch_arguments: vec![OsString::from("--config"), config_path.clone().into_os_string()],
+        dependencies: Vec::new(),
+        account_name: None,
+        account_password: None,
+    };
+    let service = manager
+        .create_service(&service_info, ServiceAccess::CHANGE_CONFIG | ServiceAccess::START)
+        .map_err(|e| format!("Failed to create service {SERVICE_NAME}: {e}"))?;
+    service
+        .set_description(DESCRIPTION)
+        .map_err(|e| format!("Failed to set the service description: {e}"))?;
+    service
+        .update_failure_actions(ServiceFailureActions {
+            reset_period: ServiceFailureResetPeriod::After(FAILURE_RESET_PERIOD),
+            reboot_msg: None,
+            command: None,
+            actions: Some(
+                (0..RESTART_ATTEMPTS)
+                    .map(|_| ServiceAction {
+                        action_type: ServiceActionType::Restart,
+                        delay: RESTART_DELAY,
+                    })
+                    .collect(),
+            ),
+        })
+        .map_err(|e| format!("Failed to set the service recovery actions: {e}"))?;
+    println!("Installed service {SERVICE_NAME} using {}.", config_path.display());
+
+    service
+        .start::<&OsStr>(&[])
+        .map_err(|e| format!("Installed service {SERVICE_NAME}, but it failed to start: {e}"))?;
+    println!("Started service {SERVICE_NAME}.");
+    Ok(())
+}
+
+/// Stops the service if it is running and removes it. Needs an elevated prompt.
+pub fn uninstall_service() -> Result<(), String> {
+    let manager = ServiceManager::local_computer(None::<&str>, ServiceManagerAccess::CONNECT)
+        .map_err(|e| format!("Cannot open the service manager (run as administrator): {e}"))?;
+    let service = manager
+        .open_service(
+            SERVICE_NAME,
+            ServiceAccess::QUERY_STATUS | ServiceAccess::STOP | ServiceAccess::DELETE,
+        )
+        .map_err(|e| format!("Cannot open service {SERVICE_NAME}: {e}"))?;
+    // The service manager removes it once it has stopped and all handles are closed.
+    service
+        .delete()
+        .map_err(|e| format!("Failed to delete service {SERVICE_NAME}: {e}"))?;
+    let status = service
+        .query_status()
+        .map_err(|e| format!("Failed to query service {SERVICE_NAME}: {e}"))?;
+    if status.current_state != ServiceState::Stopped {
+        service
+            .stop()
+            .map_err(|e| format!("Failed to stop service {SERVICE_NAME}: {e}"))?;
+    }
+    println!("Removed service {SERVICE_NAME}.");
+    Ok(())
+}
@@ -5,10 +5,12 @@ use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 use tokio::sync::{mpsc, oneshot};
 use tokio::task::JoinHandle;
+use tokio::time::MissedTickBehavior;
 use tracing::{error, info, warn};
 
+use crate::agent_modules::metric_buffer;
 use nodenexus_common::agent_service::{
-    AgentConfig, MessageToServer, ServiceMonitorResult, ServiceMonitorTask,
+    AgentConfig, MessageToServer, PingStats, ServiceMonitorResult, ServiceMonitorTask, TlsCertificateInfo,
     message_to_server::Payload as ServerPayload,
 };
 
@@ -188,6 +190,17 @@ where
                 )
                 .await
             }
+            "dns" => {
+                run_dns_check(
+                    task,
+                    tx_to_server,
+                    vps_db_id,
+                    agent_secret,
+                    id_provider_clone,
+                    shutdown_rx,
+                )
+                .await
+            }
             _ => {
                 error!("Unknown monitor type. Task will not run.");
             }
@@ -197,6 +210,19 @@ where
     (handle, shutdown_tx, monitor_id)
 }
 
+/// Ticks every `frequency_seconds`, starting at a random point within the
+/// first period so that checks don't all line up on the same second across
+/// monitors and agents. Ticks missed while the agent was suspended or its
+/// connection stalled are skipped, not replayed, so a recovering agent runs
+/// each check once instead of a backlog burst.
+fn check_interval(task: &ServiceMonitorTask) -> tokio::time::Interval {
+    let period = Duration::from_secs(task.frequency_seconds.max(1) as u64);
+    let jitter = period.mul_f64(random::<f64>());
+    let mut interval = tokio::time::interval_at(tokio::time::Instant::now() + jitter, period);
+    interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+    interval
+}
+
 // --- Placeholder Implementations for Checkers ---
 async fn run_http_check<F>(
     task: ServiceMonitorTask,
@@ -208,10 +234,10 @@ async fn run_http_check<F>(
 ) where
     F: Fn() -> u64 + Send + Sync + 'static,
 {
-    let interval_duration = Duration::from_secs(task.frequency_seconds.max(1) as u64);
-    let mut interval = tokio::time::interval(interval_duration);
+    let mut interval = check_interval(&task);
     let client = reqwest::Client::builder()
         .timeout(Duration::from_secs(task.timeout_seconds.max(1) as u64))
+        .tls_info(true)
         .build()
         .unwrap(); // Should not fail with default settings
 
@@ -227,8 +253,14 @@ async fn run_http_check<F>(
                 let result = client.get(&task.target).send().await;
                 let response_time_ms = start_time.elapsed().as_millis() as i32;
 
+                let mut certificate = None;
                 let (successful, details, latency) = match result {
                     Ok(response) => {
+                        certificate = response
+                            .extensions()
+                            .get::<reqwest::tls::TlsInfo>()
+                            .and_then(|info| info.peer_certificate())
+                            .and_then(parse_certificate);
                         let status = response.status();
                         let details_str = status.to_string();
                         (status.is_success(), details_str, Some(response_time_ms))
@@ -249,6 +281,9 @@ async fn run_http_check<F>(
                     successful,
                     response_time_ms: latency,
                     details,
+                    certificate,
+                    ping: None,
+                    replayed: false,
                 };
 
                 let msg = MessageToServer {
@@ -259,7 +294,8 @@ async fn run_http_check<F>(
                 };
 
                 if let Err(e) = tx.send(msg).await {
-                    error!(error = %e, "Failed to send result to server. Terminating task.");
+                    error!(error = %e, "Failed to send result to server. Buffering it and terminating task.");
+                    metric_buffer::keep(e.0);
                     break;
                 }
             }
@@ -267,6 +303,164 @@ async fn run_http_check<F>(
     }
 }
 
+/// Extracts validity, issuer, subject and SANs from a DER certificate.
+fn parse_certificate(der: &[u8]) -> Option<TlsCertificateInfo> {
+    use x509_parser::extensions::GeneralName;
+
+    let (_, cert) = match x509_parser::parse_x509_certificate(der) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            warn!(error = %e, "Failed to parse peer certificate.");
+            return None;
+        }
+    };
+    let subject_alt_names = cert
+        .subject_alternative_name()
+        .ok()
+        .flatten()
+        .map(|ext| {
+            ext.value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(dns) => Some(dns.to_string()),
+                    GeneralName::IPAddress(bytes) => match bytes.len() {
+                        4 => <[u8; 4]>::try_from(*bytes).ok().map(|ip| std::net::IpAddr::from(ip).to_string()),
+                        16 => <[u8; 16]>::try_from(*bytes).ok().map(|ip| std::net::IpAddr::from(ip).to_string()),
+                        _ => None,
+                    },
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    let validity = cert.validity();
+    Some(TlsCertificateInfo {
+        not_before_unix_ms: validity.not_before.timestamp() * 1000,
+        not_after_unix_ms: validity.not_after.timestamp() * 1000,
+        issuer: cert.issuer().to_string(),
+        subject: cert.subject().to_string(),
+        subject_alt_names,
+    })
+}
+
+const DEFAULT_PING_PACKET_COUNT: u32 = 4;
+const MAX_PING_PACKET_COUNT: u32 = 20;
+const PING_PACKET_GAP: Duration = Duration::from_millis(200);
+
+/// `monitor_config_json` of a "ping" monitor.
+#[derive(serde::Deserialize, Default)]
+struct PingMonitorConfig {
+    packet_count: Option<u32>,
+}
+
+/// How echo requests are sent: an ICMP socket, or the system `ping` binary
+/// when the agent is not allowed to open one.
+pub(crate) enum Pinger {
+    Socket(surge_ping::Client),
+    Command,
+}
+
+impl Pinger {
+    /// A pinger for addresses of `target`'s family.
+    pub(crate) fn open(target: std::net::IpAddr) -> Self {
+        match open_icmp_client(target) {
+            Some(client) => Pinger::Socket(client),
+            None => Pinger::Command,
+        }
+    }
+
+    pub(crate) async fn ping(&self, target: std::net::IpAddr, count: u32, timeout: Duration) -> Result<PingStats, String> {
+        match self {
+            Pinger::Socket(client) => Ok(ping_with_socket(client, target, count, timeout).await),
+            Pinger::Command => ping_with_command(target, count, timeout).await,
+        }
+    }
+}
+
+/// Tries a raw ICMP socket, then an unprivileged datagram one.
+fn open_icmp_client(target: std::net::IpAddr) -> Option<surge_ping::Client> {
+    let kind = if target.is_ipv6() { surge_ping::ICMP::V6 } else { surge_ping::ICMP::V4 };
+    for sock_type in [socket2::Type::RAW, socket2::Type::DGRAM] {
+        let config = surge_ping::Config::builder().kind(kind).sock_type_hint(sock_type).build();
+        match surge_ping::Client::new(&config) {
+            Ok(client) => return Some(client),
+            Err(e) => warn!(?sock_type, error = %e, "Failed to open ICMP socket."),
+        }
+    }
+    None
+}
+
+fn ping_stats(packets_sent: u32, rtts_ms: &[f64]) -> PingStats {
+    let received = rtts_ms.len() as u32;
+    if rtts_ms.is_empty() {
+        return PingStats { packets_sent, packets_received: 0, ..Default::default() };
+    }
+    PingStats {
+        packets_sent,
+        packets_received: received,
+        rtt_min_ms: rtts_ms.iter().copied().fold(f64::INFINITY, f64::min),
+        rtt_avg_ms: rtts_ms.iter().sum::<f64>() / rtts_ms.len() as f64,
+        rtt_max_ms: rtts_ms.iter().copied().fold(0.0, f64::max),
+    }
+}
+
+async fn ping_with_socket(
+    client: &surge_ping::Client,
+    target: std::net::IpAddr,
+    count: u32,
+    timeout: Duration,
+) -> PingStats {
+    let mut pinger = client.pinger(target, surge_ping::PingIdentifier(random())).await;
+    pinger.timeout(timeout);
+    let payload = [0u8; 56];
+    let mut rtts_ms = Vec::with_capacity(count as usize);
+    for seq in 0..count {
+        if let Ok((_reply, rtt)) = pinger.ping(surge_ping::PingSequence(seq as u16), &payload).await {
+            rtts_ms.push(rtt.as_secs_f64() * 1000.0);
+        }
+        if seq + 1 < count {
+            tokio::time::sleep(PING_PACKET_GAP).await;
+        }
+    }
+    ping_stats(count, &rtts_ms)
+}
+
+/// Reads the `time=1.23 ms` (or Windows `time<1ms`) of every reply line.
+fn parse_ping_output(output: &str) -> Vec<f64> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let idx = line.find("time=").or_else(|| line.find("time<"))?;
+            let value: String = line[idx + 5..]
+                .chars()
+                .take_while(|c| c.is_ascii_digit() || *c == '.')
+                .collect();
+            value.parse().ok()
+        })
+        .collect()
+}
+
+async fn ping_with_command(target: std::net::IpAddr, count: u32, timeout: Duration) -> Result<PingStats, String> {
+    let mut command = tokio::process::Command::new("ping");
+    if cfg!(windows) {
+        command.args(["-n", &count.to_string(), "-w", &timeout.as_millis().to_string()]);
+    } else {
+        command.args(["-n", "-c", &count.to_string(), "-W", &timeout.as_secs().max(1).to_string()]);
+        if target.is_ipv6() {
+            command.arg("-6");
+        }
+    }
+    command.arg(target.to_string()).kill_on_drop(true);
+    let deadline = timeout * count + PING_PACKET_GAP * count + Duration::from_secs(2);
+    let output = tokio::time::timeout(deadline, command.output())
+        .await
+        .map_err(|_| "ping command timed out".to_string())?
+        .map_err(|e| format!("Failed to run ping: {e}"))?;
+    let rtts_ms = parse_ping_output(&String::from_utf8_lossy(&output.stdout));
+    Ok(ping_stats(count, &rtts_ms))
+}
+
 async fn run_ping_check<F: Fn() -> u64 + Send + Sync + 'static>(
     task: ServiceMonitorTask,
     tx: mpsc::Sender<MessageToServer>,
@@ -275,8 +469,14 @@ async fn run_ping_check<F: Fn() -> u64 + Send + Sync + 'static>(
     id_provider: F,
     mut shutdown_rx: oneshot::Receiver<()>,
 ) {
-    let interval_duration = Duration::from_secs(task.frequency_seconds.max(1) as u64);
-    let mut interval = tokio::time::interval(interval_duration);
+    let mut interval = check_interval(&task);
+    let timeout_duration = Duration::from_secs(task.timeout_seconds.max(1) as u64);
+    let config: PingMonitorConfig = serde_json::from_str(&task.monitor_config_json).unwrap_or_default();
+    let packet_count = config
+        .packet_count
+        .unwrap_or(DEFAULT_PING_PACKET_COUNT)
+        .clamp(1, MAX_PING_PACKET_COUNT);
+
     // Resolve the target, which could be a domain name or an IP address.
     let target_clone = task.target.clone();
     let resolved_addr_result = tokio::task::spawn_blocking(move || {
@@ -301,7 +501,10 @@ async fn run_ping_check<F: Fn() -> u64 + Send + Sync + 'static>(
         }
     };
 
-    let client = surge_ping::Client::new(&surge_ping::Config::default()).unwrap();
+    let pinger = Pinger::open(target_addr);
+    if matches!(pinger, Pinger::Command) {
+        warn!(monitor_id = task.monitor_id, "No ICMP socket available. Falling back to the ping command.");
+    }
 
     loop {
         tokio::select! {
@@ -311,17 +514,22 @@ async fn run_ping_check<F: Fn() -> u64 + Send + Sync + 'static>(
                 break;
             }
             _ = interval.tick() => {
-                let mut pinger = client
-                    .pinger(target_addr, surge_ping::PingIdentifier(random()))
-                    .await;
-                let (successful, details, latency) =
-                    match pinger.ping(surge_ping::PingSequence(0), &[]).await {
-                        Ok((_reply, duration)) => {
-                            let rtt = duration.as_millis() as i32;
-                            (true, format!("{rtt} ms"), Some(rtt))
-                        }
-                        Err(e) => (false, format!("Error: {e}"), None),
-                    };
+                let stats = pinger.ping(target_addr, packet_count, timeout_duration).await;
+                let (successful, details, latency, ping) = match stats {
+                    Ok(stats) if stats.packets_received > 0 => {
+                        let details = format!(
+                            "{}/{} replies, rtt min/avg/max {:.1}/{:.1}/{:.1} ms",
+                            stats.packets_received,
+                            stats.packets_sent,
+                            stats.rtt_min_ms,
+                            stats.rtt_avg_ms,
+                            stats.rtt_max_ms
+                        );
+                        (true, details, Some(stats.rtt_avg_ms.round() as i32), Some(stats))
+                    }
+                    Ok(stats) => (false, "Error: 100% packet loss".to_string(), None, Some(stats)),
+                    Err(e) => (false, format!("Error: {e}"), None, None),
+                };
 
                 let monitor_result = ServiceMonitorResult {
                     monitor_id: task.monitor_id,
@@ -329,6 +537,9 @@ async fn run_ping_check<F: Fn() -> u64 + Send + Sync + 'static>(
                     successful,
                     response_time_ms: latency,
                     details,
+                    certificate: None,
+                    ping,
+                    replayed: false,
                 };
 
                 let msg = MessageToServer {
@@ -339,7 +550,8 @@ async fn run_ping_check<F: Fn() -> u64 + Send + Sync + 'static>(
                 };
 
                 if let Err(e) = tx.send(msg).await {
-                    error!(error = %e, "Failed to send result to server. Terminating task.");
+                    error!(error = %e, "Failed to send result to server. Buffering it and terminating task.");
+                    metric_buffer::keep(e.0);
                     break;
                 }
             }
@@ -355,8 +567,7 @@ async fn run_tcp_check<F: Fn() -> u64 + Send + Sync + 'static>(
     id_provider: F,
     mut shutdown_rx: oneshot::Receiver<()>,
 ) {
-    let interval_duration = Duration::from_secs(task.frequency_seconds.max(1) as u64);
-    let mut interval = tokio::time::interval(interval_duration);
+    let mut interval = check_interval(&task);
     let timeout_duration = Duration::from_secs(task.timeout_seconds.max(1) as u64);
 
     loop {
@@ -391,6 +602,167 @@ async fn run_tcp_check<F: Fn() -> u64 + Send + Sync + 'static>(
                     successful,
                     response_time_ms: latency,
                     details,
+                    certificate: None,
+                    ping: None,
+                    replayed: false,
+                };
+
+                let msg = MessageToServer {
+                    client_message_id: id_provider(),
+                    payload: Some(ServerPayload::ServiceMonitorResult(monitor_result)),
+                    vps_db_id,
+                    agent_secret: agent_secret.clone(),
+                };
+
+                if let Err(e) = tx.send(msg).await {
+                    error!(error = %e, "Failed to send result to server. Buffering it and terminating task.");
+                    metric_buffer::keep(e.0);
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// `monitor_config_json` of a "dns" monitor.
+#[derive(serde::Deserialize, Default)]
+struct DnsMonitorConfig {
+    record_type: Option<String>,
+    #[serde(default)]
+    expected_values: Vec<String>,
+    resolver: Option<String>,
+}
+
+/// Lower-cases and strips the trailing root dot, so "Mail.Example.com." and
+/// "mail.example.com" compare equal.
+fn normalize_dns_value(value: &str) -> String {
+    value.trim().trim_end_matches('.').to_ascii_lowercase()
+}
+
+fn build_dns_resolver(
+    resolver: Option<&str>,
+    timeout: Duration,
+) -> Result<hickory_resolver::TokioAsyncResolver, String> {
+    use hickory_resolver::config::{NameServerConfigGroup, ResolverConfig, ResolverOpts};
+
+    let mut opts = ResolverOpts::default();
+    opts.timeout = timeout;
+    opts.attempts = 1;
+    opts.cache_size = 0;
+    match resolver.filter(|r| !r.is_empty()) {
+        Some(addr) => {
+            let socket_addr = addr
+                .parse::<std::net::SocketAddr>()
+                .or_else(|_| addr.parse::<std::net::IpAddr>().map(|ip| std::net::SocketAddr::new(ip, 53)))
+                .map_err(|_| format!("Invalid resolver address '{addr}'"))?;
+            let servers = NameServerConfigGroup::from_ips_clear(&[socket_addr.ip()], socket_addr.port(), true);
+            Ok(hickory_resolver::TokioAsyncResolver::tokio(
+                ResolverConfig::from_parts(None, vec![], servers),
+                opts,
+            ))
+        }
+        None => {
+            let (config, _) = hickory_resolver::system_conf::read_system_conf()
+                .map_err(|e| format!("Failed to read system resolver config: {e}"))?;
+            Ok(hickory_resolver::TokioAsyncResolver::tokio(config, opts))
+        }
+    }
+}
+
+async fn run_dns_check<F: Fn() -> u64 + Send + Sync + 'static>(
+    task: ServiceMonitorTask,
+    tx: mpsc::Sender<MessageToServer>,
+    vps_db_id: i32,
+    agent_secret: String,
+    id_provider: F,
+    mut shutdown_rx: oneshot::Receiver<()>,
+) {
+    use hickory_resolver::proto::rr::RecordType;
+
+    let mut interval = check_interval(&task);
+    let timeout_duration = Duration::from_secs(task.timeout_seconds.max(1) as u64);
+
+    let config: DnsMonitorConfig = if task.monitor_config_json.trim().is_empty() {
+        DnsMonitorConfig::default()
+    } else {
+        serde_json::from_str(&task.monitor_config_json).unwrap_or_else(|e| {
+            warn!(monitor_id = task.monitor_id, error = %e, "Invalid DNS monitor config. Using defaults.");
+            DnsMonitorConfig::default()
+        })
+    };
+    let record_type = match config.record_type.as_deref().map(str::to_ascii_uppercase).as_deref() {
+        None | Some("A") => RecordType::A,
+        Some("AAAA") => RecordType::AAAA,
+        Some("CNAME") => RecordType::CNAME,
+        Some("MX") => RecordType::MX,
+        Some("TXT") => RecordType::TXT,
+        Some(other) => {
+            error!(monitor_id = task.monitor_id, record_type = other, "Unsupported DNS record type. Task will not run.");
+            return;
+        }
+    };
+    let expected: Vec<String> = config.expected_values.iter().map(|v| normalize_dns_value(v)).collect();
+    let resolver = match build_dns_resolver(config.resolver.as_deref(), timeout_duration) {
+        Ok(resolver) => Some(resolver),
+        Err(e) => {
+            error!(monitor_id = task.monitor_id, error = %e, "Failed to set up DNS resolver.");
+            None
+        }
+    };
+
+    loop {
+        tokio::select! {
+            biased;
+            _ = &mut shutdown_rx => {
+                info!(monitor_id = task.monitor_id, "DNS check task received shutdown signal.");
+                break;
+            }
+            _ = interval.tick() => {
+                let start_time = Instant::now();
+                let (successful, details, latency) = match &resolver {
+                    None => (false, "Error: DNS resolver is not available".to_string(), None),
+                    Some(resolver) => {
+                        let result = tokio::time::timeout(
+                            timeout_duration,
+                            resolver.lookup(task.target.as_str(), record_type),
+                        )
+                        .await;
+                        let response_time_ms = start_time.elapsed().as_millis() as i32;
+                        match result {
+                            Ok(Ok(lookup)) => {
+                                let answers: Vec<String> = lookup.iter().map(|rdata| rdata.to_string()).collect();
+                                let normalized: HashSet<String> = answers.iter().map(|a| normalize_dns_value(a)).collect();
+                                let missing: Vec<&String> = expected.iter().filter(|v| !normalized.contains(*v)).collect();
+                                if missing.is_empty() {
+                                    (true, format!("{record_type}: {}", answers.join(", ")), Some(response_time_ms))
+                                } else {
+                                    let missing: Vec<&str> = missing.iter().map(|v| v.as_str()).collect();
+                                    (
+                                        false,
+                                        format!(
+                                            "Mismatch: expected {} not in {record_type} answer [{}]",
+                                            missing.join(", "),
+                                            answers.join(", ")
+                                        ),
+                                        Some(response_time_ms),
+                                    )
+                                }
+                            }
+                            Ok(Err(e)) => (false, format!("Error: {e}"), None),
+                            Err(_) => (false, "Error: DNS lookup timed out".to_string(), None),
+                        }
+                    }
+                };
+
+                let monitor_result = ServiceMonitorResult {
+                    monitor_id: task.monitor_id,
+                    timestamp_unix_ms: chrono::Utc::now().timestamp_millis(),
+                    successful,
+                    response_time_ms: latency,
+                    details,
+                    certificate: None,
+                    ping: None,
+                    replayed: false,
                 };
 
                 let msg = MessageToServer {
@@ -401,7 +773,8 @@ async fn run_tcp_check<F: Fn() -> u64 + Send + Sync + 'static>(
                 };
 
                 if let Err(e) = tx.send(msg).await {
-                    error!(error = %e, "Failed to send result to server. Terminating task.");
+                    error!(error = %e, "Failed to send result to server. Buffering it and terminating task.");
+                    metric_buffer::keep(e.0);
                     break;
                 }
             }
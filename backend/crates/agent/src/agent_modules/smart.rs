@@ -0,0 +1,152 @@
+//! Drive SMART health from `smartctl` (smartmontools 7+, which prints JSON)
+//! and hardware temperature sensors. SMART data changes slowly and reading it
+//! can wake sleeping drives, so drives are only re-read every few minutes.
+
+use nodenexus_common::agent_service::{DiskHealthStats, TemperatureSensorStats};
+use serde_json::Value;
+use std::io::ErrorKind;
+use std::process::Command;
+use std::time::{Duration, Instant};
+use sysinfo::Components;
+use tracing::{debug, info};
+
+const SMART_REFRESH_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// smartctl exit status bits meaning the device couldn't be queried at all;
+/// the other bits report drive problems and still come with usable output.
+const SMARTCTL_FATAL_BITS: i32 = 0b11;
+
+/// Reads every drive at most once per [`SMART_REFRESH_INTERVAL`].
+pub struct SmartSampler {
+    available: bool,
+    last_read: Option<Instant>,
+}
+
+impl Default for SmartSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SmartSampler {
+    pub fn new() -> Self {
+        Self { available: true, last_read: None }
+    }
+
+    /// Health of every drive when a refresh is due; empty otherwise.
+    pub fn sample(&mut self) -> Vec<DiskHealthStats> {
+        if !self.available || self.last_read.is_some_and(|t| t.elapsed() < SMART_REFRESH_INTERVAL) {
+            return Vec::new();
+        }
+        self.last_read = Some(Instant::now());
+
+        let devices = match run_smartctl(&["--scan", "--json"]) {
+            Ok(Some(scan)) => scanned_devices(&scan),
+            Ok(None) => return Vec::new(),
+            Err(()) => {
+                self.available = false;
+                return Vec::new();
+            }
+        };
+        devices
+            .iter()
+            .filter_map(|(device, device_type)| {
+                let output = run_smartctl(&["--all", "--json", "--device", device_type, device]).ok()??;
+                parse_smartctl(device, &output)
+            })
+            .collect()
+    }
+}
+
+/// `Err` when smartctl isn't installed, `Ok(None)` when the query failed this time.
+fn run_smartctl(args: &[&str]) -> Result<Option<Value>, ()> {
+    match Command::new("smartctl").args(args).output() {
+        Ok(output) => {
+            if output.status.code().is_some_and(|code| code & SMARTCTL_FATAL_BITS != 0) {
+                debug!(?args, status = ?output.status, "smartctl could not query the device.");
+                return Ok(None);
+            }
+            Ok(serde_json::from_slice(&output.stdout).ok())
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            info!("smartctl not found; not collecting drive health.");
+            Err(())
+        }
+        Err(e) => {
+            debug!(error = %e, "Failed to run smartctl.");
+            Ok(None)
+        }
+    }
+}
+
+/// `(device, type)` pairs from `smartctl --scan --json`.
+fn scanned_devices(scan: &Value) -> Vec<(String, String)> {
+    scan["devices"]
+        .as_array()
+        .map(|devices| {
+            devices
+                .iter()
+                .filter_map(|d| Some((d["name"].as_str()?.to_string(), d["type"].as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// The raw value of an ATA attribute, by attribute id.
+fn ata_raw(report: &Value, id: u64) -> Option<u64> {
+    ata_attribute(report, id)?["raw"]["value"].as_u64()
+}
+
+fn ata_attribute(report: &Value, id: u64) -> Option<&Value> {
+    report["ata_smart_attributes"]["table"]
+        .as_array()?
+        .iter()
+        .find(|attribute| attribute["id"].as_u64() == Some(id))
+}
+
+/// NVMe reports the endurance used directly. ATA SSDs report the life left as
+/// the normalized value of one of several vendor attributes.
+fn wear_percent(report: &Value) -> Option<f64> {
+    let nvme_log = &report["nvme_smart_health_information_log"];
+    if let Some(used) = nvme_log["percentage_used"].as_f64() {
+        return Some(used);
+    }
+    // Wear_Leveling_Count, SSD_Life_Left, Media_Wearout_Indicator, Percent_Lifetime_Remain.
+    [177, 231, 233, 202]
+        .into_iter()
+        .find_map(|id| ata_attribute(report, id)?["value"].as_f64())
+        .map(|life_left| (100.0 - life_left).clamp(0.0, 100.0))
+}
+
+fn parse_smartctl(device: &str, report: &Value) -> Option<DiskHealthStats> {
+    let model = report["model_name"].as_str()?;
+    let nvme_log = &report["nvme_smart_health_information_log"];
+    Some(DiskHealthStats {
+        device: device.to_string(),
+        model: model.to_string(),
+        serial: report["serial_number"].as_str().unwrap_or_default().to_string(),
+        smart_passed: report["smart_status"]["passed"].as_bool(),
+        // NVMe drives have no reallocation counter; media errors are the closest equivalent.
+        reallocated_sectors: ata_raw(report, 5).or_else(|| nvme_log["media_errors"].as_u64()),
+        pending_sectors: ata_raw(report, 197),
+        wear_percent: wear_percent(report),
+        temperature_celsius: report["temperature"]["current"].as_f64(),
+        power_on_hours: report["power_on_time"]["hours"].as_u64(),
+    })
+}
+
+/// Current readings of the chassis, CPU and other sensors the OS exposes.
+pub fn sample_temperatures(components: &mut Components) -> Vec<TemperatureSensorStats> {
+    components.refresh(false);
+    components
+        .list()
+        .iter()
+        .filter_map(|component| {
+            let temperature = component.temperature().filter(|t| t.is_finite())?;
+            Some(TemperatureSensorStats {
+                label: component.label().to_string(),
+                temperature_celsius: temperature as f64,
+                critical_celsius: component.critical().filter(|t| t.is_finite()).map(f64::from),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,260 @@
+//! Interactive shell sessions on a pseudo terminal, driven by `PtyDataToAgent`
+//! messages. Output is streamed back as `PtyDataToServer`. Sessions belong to
+//! one server connection and are closed when it ends.
+
+use nodenexus_common::agent_service::{
+    MessageToServer, PtyDataToAgent, PtyDataToServer, PtyResize, PtyStartCommand,
+    message_to_server::Payload, pty_data_to_agent::ControlEvent,
+};
+use portable_pty::{Child, ChildKiller, CommandBuilder, MasterPty, PtySize, native_pty_system};
+use std::collections::HashMap;
+use std::io::{Read, Write};
+use std::sync::{Arc, Mutex, mpsc as std_mpsc};
+use tokio::sync::mpsc;
+use tracing::{info, warn};
+
+/// Upper bound on concurrent sessions per connection.
+const MAX_SESSIONS: usize = 8;
+const READ_BUFFER_SIZE: usize = 8192;
+
+struct Session {
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    killer: Box<dyn ChildKiller + Send + Sync>,
+}
+
+/// Where a session's output goes.
+#[derive(Clone)]
+pub struct OutputSink<F> {
+    pub tx_to_server: mpsc::Sender<MessageToServer>,
+    pub id_provider: F,
+    pub vps_db_id: i32,
+    pub agent_secret: String,
+}
+
+impl<F: Fn() -> u64> OutputSink<F> {
+    fn send_blocking(&self, data: PtyDataToServer) -> bool {
+        self.tx_to_server
+            .blocking_send(MessageToServer {
+                client_message_id: (self.id_provider)(),
+                payload: Some(Payload::PtyDataToServer(data)),
+                vps_db_id: self.vps_db_id,
+                agent_secret: self.agent_secret.clone(),
+            })
+            .is_ok()
+    }
+}
+
+#[derive(Default)]
+struct TerminalManager {
+    sessions: Mutex<HashMap<String, Session>>,
+}
+
+/// Applies terminal messages in arrival order on a dedicated thread, so input
+/// isn't reordered. Dropping it kills all of its sessions.
+pub struct TerminalWorker {
+    tx: std_mpsc::Sender<PtyDataToAgent>,
+    manager: Arc<TerminalManager>,
+}
+
+impl TerminalWorker {
+    pub fn spawn<F>(sink: OutputSink<F>) -> Self
+    where
+        F: Fn() -> u64 + Send + Sync + Clone + 'static,
+    {
+        let manager = Arc::new(TerminalManager::default());
+        let (tx, rx) = std_mpsc::channel::<PtyDataToAgent>();
+        let worker_manager = manager.clone();
+        std::thread::spawn(move || {
+            for message in rx {
+                worker_manager.handle(message, sink.clone());
+            }
+        });
+        Self { tx, manager }
+    }
+
+    pub fn submit(&self, message: PtyDataToAgent) {
+        if self.tx.send(message).is_err() {
+            warn!("Terminal worker has stopped. Dropping terminal message.");
+        }
+    }
+}
+
+impl Drop for TerminalWorker {
+    fn drop(&mut self) {
+        self.manager.close_all();
+    }
+}
+
+fn pty_size(size: &PtyResize) -> PtySize {
+    PtySize {
+        rows: size.rows.clamp(1, u16::MAX as u32) as u16,
+        cols: size.cols.clamp(1, u16::MAX as u32) as u16,
+        pixel_width: 0,
+        pixel_height: 0,
+    }
+}
+
+fn default_shell() -> String {
+    #[cfg(windows)]
+    {
+        "powershell.exe".to_string()
+    }
+    #[cfg(not(windows))]
+    {
+        std::env::var("SHELL").unwrap_or_else(|_| "/bin/sh".to_string())
+    }
+}
+
+fn closed(session_id: &str, error_message: String) -> PtyDataToServer {
+    PtyDataToServer {
+        session_id: session_id.to_string(),
+        output_data: Vec::new(),
+        stream_closed_by_agent: true,
+        error_message,
+    }
+}
+
+impl TerminalManager {
+    fn handle<F>(self: &Arc<Self>, message: PtyDataToAgent, sink: OutputSink<F>)
+    where
+        F: Fn() -> u64 + Send + Sync + Clone + 'static,
+    {
+        let session_id = message.session_id;
+        match message.control_event {
+            Some(ControlEvent::StartCommand(start)) => {
+                if let Err(e) = self.start(&session_id, start, sink.clone()) {
+                    warn!(session_id = %session_id, error = %e, "Failed to start terminal session.");
+                    sink.send_blocking(closed(&session_id, e));
+                }
+            }
+            Some(ControlEvent::InputData(data)) => {
+                let mut sessions = self.sessions.lock().unwrap();
+                if let Some(session) = sessions.get_mut(&session_id) {
+                    if let Err(e) = session.writer.write_all(&data).and_then(|_| session.writer.flush()) {
+                        warn!(session_id = %session_id, error = %e, "Failed to write terminal input.");
+                    }
+                }
+            }
+            Some(ControlEvent::ResizeEvent(size)) => {
+                let sessions = self.sessions.lock().unwrap();
+                if let Some(session) = sessions.get(&session_id) {
+                    if let Err(e) = session.master.resize(pty_size(&size)) {
+                        warn!(session_id = %session_id, error = %e, "Failed to resize terminal.");
+                    }
+                }
+            }
+            Some(ControlEvent::CloseSignalFromServer(_)) => self.close(&session_id),
+            None => {}
+        }
+    }
+
+    fn start<F>(
+        self: &Arc<Self>,
+        session_id: &str,
+        start: PtyStartCommand,
+        sink: OutputSink<F>,
+    ) -> Result<(), String>
+    where
+        F: Fn() -> u64 + Send + Sync + Clone + 'static,
+    {
+        let mut sessions = self.sessions.lock().unwrap();
+        if sessions.contains_key(session_id) {
+            return Err("Session already exists.".to_string());
+        }
+        if sessions.len() >= MAX_SESSIONS {
+            return Err(format!("Too many open terminal sessions (limit {MAX_SESSIONS})."));
+        }
+
+        let size = start.initial_size.as_ref().map(pty_size).unwrap_or_default();
+        let pair = native_pty_system()
+            .openpty(size)
+            .map_err(|e| format!("Failed to open PTY: {e}"))?;
+
+        let shell = if start.shell_to_use.is_empty() { default_shell() } else { start.shell_to_use };
+        let mut command = CommandBuilder::new(shell);
+        #[cfg(not(windows))]
+        command.env("TERM", "xterm-256color");
+        for (key, value) in &start.env_variables {
+            command.env(key, value);
+        }
+        if !start.working_directory.is_empty() {
+            command.cwd(&start.working_directory);
+        }
+
+        let mut child = pair
+            .slave
+            .spawn_command(command)
+            .map_err(|e| format!("Failed to spawn shell: {e}"))?;
+        // Only the child should hold the slave end, so reads see EOF when it exits.
+        drop(pair.slave);
+        let (reader, writer) = match (pair.master.try_clone_reader(), pair.master.take_writer()) {
+            (Ok(reader), Ok(writer)) => (reader, writer),
+            (Err(e), _) | (_, Err(e)) => {
+                let _ = child.kill();
+                return Err(format!("Failed to attach to PTY: {e}"));
+            }
+        };
+        let killer = child.clone_killer();
+
+        sessions.insert(
+            session_id.to_string(),
+            Session { master: pair.master, writer, killer },
+        );
+        drop(sessions);
+        info!(session_id = %session_id, "Terminal session started.");
+
+        let manager = self.clone();
+        let session_id = session_id.to_string();
+        std::thread::spawn(move || {
+            pump_output(&session_id, reader, &sink);
+            let message = exit_message(&mut *child);
+            manager.sessions.lock().unwrap().remove(&session_id);
+            sink.send_blocking(closed(&session_id, message));
+            info!(session_id = %session_id, "Terminal session finished.");
+        });
+        Ok(())
+    }
+
+    fn close(&self, session_id: &str) {
+        if let Some(mut session) = self.sessions.lock().unwrap().remove(session_id) {
+            let _ = session.killer.kill();
+            info!(session_id = %session_id, "Terminal session closed by server.");
+        }
+    }
+
+    fn close_all(&self) {
+        let sessions: Vec<Session> = self.sessions.lock().unwrap().drain().map(|(_, s)| s).collect();
+        for mut session in sessions {
+            let _ = session.killer.kill();
+        }
+    }
+}
+
+fn pump_output<F: Fn() -> u64>(session_id: &str, mut reader: Box<dyn Read + Send>, sink: &OutputSink<F>) {
+    let mut buf = vec![0u8; READ_BUFFER_SIZE];
+    loop {
+        match reader.read(&mut buf) {
+            Ok(0) | Err(_) => return,
+            Ok(n) => {
+                let data = PtyDataToServer {
+                    session_id: session_id.to_string(),
+                    output_data: buf[..n].to_vec(),
+                    stream_closed_by_agent: false,
+                    error_message: String::new(),
+                };
+                if !sink.send_blocking(data) {
+                    return;
+                }
+            }
+        }
+    }
+}
+
+fn exit_message(child: &mut dyn Child) -> String {
+    match child.wait() {
+        Ok(status) if status.success() => String::new(),
+        Ok(status) => format!("Shell exited with code {}.", status.exit_code()),
+        Err(e) => format!("Failed to wait for shell: {e}"),
+    }
+}
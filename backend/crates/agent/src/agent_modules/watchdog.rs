@@ -0,0 +1,251 @@
+//! Process watchdog: checks the `[[watchdog]]` targets from the local config
+//! file, optionally restarts them when they stop (rate limited per target) and
+//! reports what happened to the server.
+//!
+//! The checks run for the whole lifetime of the agent, independent of the
+//! server connection. Events are queued and forwarded once connected.
+
+use crate::agent_modules::command::shell::inline_command;
+use crate::agent_modules::config::WatchdogTarget;
+use nodenexus_common::agent_service::{
+    MessageToServer, WatchdogEvent, WatchdogEventKind, message_to_server::Payload,
+};
+use std::collections::VecDeque;
+use std::ffi::OsStr;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System};
+use tokio::sync::{Mutex, mpsc};
+use tracing::{error, info, warn};
+
+const DEFAULT_INTERVAL_SECONDS: u64 = 15;
+/// Events queued while disconnected; further events are dropped once full.
+pub const EVENT_QUEUE_SIZE: usize = 256;
+
+struct TargetState {
+    target: WatchdogTarget,
+    /// `None` until the first check.
+    was_running: Option<bool>,
+    restarts: VecDeque<Instant>,
+    rate_limit_reported: bool,
+}
+
+impl TargetState {
+    fn restart_allowed(&mut self, now: Instant) -> bool {
+        let window = Duration::from_secs(self.target.restart_window_seconds);
+        while self.restarts.front().is_some_and(|t| now.duration_since(*t) >= window) {
+            self.restarts.pop_front();
+        }
+        self.restarts.len() < self.target.max_restarts as usize
+    }
+
+    fn restart_command(&self) -> Option<String> {
+        self.target.restart_command.clone().or_else(|| {
+            self.target
+                .systemd_unit
+                .as_ref()
+                .map(|unit| format!("systemctl restart {unit}"))
+        })
+    }
+}
+
+fn now_unix_ms() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or_default()
+}
+
+fn is_running(sys: &System, target: &WatchdogTarget) -> bool {
+    if let Some(unit) = &target.systemd_unit {
+        return Command::new("systemctl")
+            .args(["is-active", "--quiet", unit])
+            .status()
+            .map(|status| status.success())
+            .unwrap_or(false);
+    }
+    match &target.process_name {
+        Some(name) => sys.processes_by_exact_name(OsStr::new(name)).next().is_some(),
+        None => false,
+    }
+}
+
+fn run_restart_command(command: &str) -> Result<String, String> {
+    let output = inline_command(command).output().map_err(|e| format!("Failed to run '{command}': {e}"))?;
+    let mut text = String::from_utf8_lossy(&output.stdout).into_owned();
+    text.push_str(&String::from_utf8_lossy(&output.stderr));
+    let text = text.trim().to_string();
+    if output.status.success() {
+        Ok(text)
+    } else {
+        Err(format!("'{command}' exited with {}: {text}", output.status))
+    }
+}
+
+fn event(target: &WatchdogTarget, kind: WatchdogEventKind, details: String) -> WatchdogEvent {
+    WatchdogEvent {
+        timestamp_unix_ms: now_unix_ms(),
+        target_name: target.name.clone(),
+        kind: kind as i32,
+        details,
+    }
+}
+
+/// Checks one target and restarts it if needed. Blocking; runs on the blocking pool.
+fn check_target(sys: &System, state: &mut TargetState) -> Vec<WatchdogEvent> {
+    let mut events = Vec::new();
+    let running = is_running(sys, &state.target);
+    match (state.was_running, running) {
+        (Some(false), true) => {
+            events.push(event(&state.target, WatchdogEventKind::ProcessRecovered, String::new()));
+        }
+        (Some(true) | None, false) => {
+            events.push(event(&state.target, WatchdogEventKind::ProcessExited, String::new()));
+        }
+        _ => {}
+    }
+    state.was_running = Some(running);
+    if running {
+        state.rate_limit_reported = false;
+        return events;
+    }
+    if !state.target.auto_restart {
+        return events;
+    }
+    let Some(command) = state.restart_command() else {
+        return events;
+    };
+
+    let now = Instant::now();
+    if !state.restart_allowed(now) {
+        if !state.rate_limit_reported {
+            state.rate_limit_reported = true;
+            warn!(watchdog_target = %state.target.name, "Watchdog restart limit reached.");
+            events.push(event(
+                &state.target,
+                WatchdogEventKind::RestartRateLimited,
+                format!(
+                    "{} restarts within {} seconds",
+                    state.target.max_restarts, state.target.restart_window_seconds
+                ),
+            ));
+        }
+        return events;
+    }
+
+    state.restarts.push_back(now);
+    state.rate_limit_reported = false;
+    match run_restart_command(&command) {
+        Ok(output) => {
+            info!(watchdog_target = %state.target.name, "Watchdog restarted target.");
+            // Counted as running so the next check reports a new exit if it dies again.
+            state.was_running = Some(true);
+            events.push(event(&state.target, WatchdogEventKind::ProcessRestarted, output));
+        }
+        Err(e) => {
+            error!(watchdog_target = %state.target.name, error = %e, "Watchdog restart failed.");
+            events.push(event(&state.target, WatchdogEventKind::RestartFailed, e));
+        }
+    }
+    events
+}
+
+/// Runs the checks until the event channel is closed.
+pub async fn watchdog_loop(
+    targets: Vec<WatchdogTarget>,
+    interval_seconds: Option<u64>,
+    events_tx: mpsc::Sender<WatchdogEvent>,
+) {
+    let interval = Duration::from_secs(interval_seconds.unwrap_or(DEFAULT_INTERVAL_SECONDS).max(1));
+    let mut states: Vec<TargetState> = targets
+        .into_iter()
+        .filter(|target| {
+            let valid = target.process_name.is_some() || target.systemd_unit.is_some();
+            if !valid {
+                warn!(watchdog_target = %target.name, "Watchdog target has neither process_name nor systemd_unit. Ignoring.");
+            }
+            valid
+        })
+        .map(|target| TargetState {
+            target,
+            was_running: None,
+            restarts: VecDeque::new(),
+            rate_limit_reported: false,
+        })
+        .collect();
+    if states.is_empty() {
+        return;
+    }
+    info!(targets = states.len(), interval_seconds = interval.as_secs(), "Process watchdog started.");
+
+    let mut sys = System::new();
+    loop {
+        let result = tokio::task::spawn_blocking(move || {
+            sys.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::nothing());
+            let events: Vec<WatchdogEvent> = states
+                .iter_mut()
+                .flat_map(|state| check_target(&sys, state))
+                .collect();
+            (sys, states, events)
+        })
+        .await;
+        let events;
+        (sys, states, events) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                error!(error = %e, "Watchdog check panicked. Stopping watchdog.");
+                return;
+            }
+        };
+
+        for event in events {
+            match events_tx.try_send(event) {
+                Ok(()) => {}
+                Err(mpsc::error::TrySendError::Full(_)) => {
+                    warn!("Watchdog event queue is full. Dropping event.");
+                }
+                Err(mpsc::error::TrySendError::Closed(_)) => return,
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+/// Forwards queued watchdog events over the current server connection.
+pub async fn watchdog_event_forwarder(
+    events_rx: Arc<Mutex<mpsc::Receiver<WatchdogEvent>>>,
+    tx_to_server: mpsc::Sender<MessageToServer>,
+    mut id_provider: impl FnMut() -> u64 + Send + 'static,
+    vps_db_id: i32,
+    agent_secret: String,
+    mut shutdown_rx: tokio::sync::watch::Receiver<()>,
+) {
+    let mut events_rx = events_rx.lock().await;
+    loop {
+        tokio::select! {
+            event = events_rx.recv() => {
+                let Some(event) = event else {
+                    // The watchdog stopped. Finishing here would look like a
+                    // connection failure, so wait for the regular shutdown.
+                    let _ = shutdown_rx.changed().await;
+                    return;
+                };
+                let message = MessageToServer {
+                    client_message_id: id_provider(),
+                    payload: Some(Payload::WatchdogEvent(event)),
+                    vps_db_id,
+                    agent_secret: agent_secret.clone(),
+                };
+                if tx_to_server.send(message).await.is_err() {
+                    warn!("Failed to send watchdog event. Channel closed.");
+                    return;
+                }
+            }
+            _ = shutdown_rx.changed() => {
+                info!("Watchdog event forwarder received shutdown signal.");
+                return;
+            }
+        }
+    }
+}
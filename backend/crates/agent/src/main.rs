@@ -4,6 +4,7 @@ mod version;
 use std::error::Error;
 use std::sync::atomic::AtomicU64;
 use std::sync::{Arc, RwLock};
+#[cfg(windows)]
 use std::time::Duration;
 use sysinfo::{CpuRefreshKind, MemoryRefreshKind, ProcessRefreshKind, RefreshKind};
 use tokio::task::JoinHandle; // For task handles
@@ -15,13 +16,24 @@ use tokio::task::JoinHandle; // For task handles
 
 // Let's proceed assuming agent_modules are part of the backend crate library.
 use crate::agent_modules::command::tracker::RunningCommandsTracker;
+use crate::agent_modules::communication::supervisor::{FailureKind, classify};
 use crate::agent_modules::communication::{
-    ConnectionHandler, server_message_handler_loop,
+    ConnectionHandler, ConnectionSupervisor, server_message_handler_loop,
 };
-use crate::agent_modules::config::{AgentCliConfig, load_cli_config};
-use crate::agent_modules::metrics::metrics_collection_loop;
+use crate::agent_modules::config::{AgentCliConfig, load_cli_config, save_registered_credentials};
+use crate::agent_modules::inventory::inventory_collection_loop;
+use crate::agent_modules::log_tail::{self, log_tail_forwarder, log_tail_loop};
+use crate::agent_modules::mesh::mesh_ping_loop;
+use crate::agent_modules::metric_buffer;
+use crate::agent_modules::metric_scripts::metric_scripts_loop;
+use crate::agent_modules::metrics::{BATCH_QUEUE_SIZE, metrics_collection_loop, metrics_forwarder};
+use crate::agent_modules::processes::process_collection_loop;
 use crate::agent_modules::service_monitor::ServiceMonitorManager;
-use nodenexus_common::agent_service::AgentConfig;
+use crate::agent_modules::watchdog::{EVENT_QUEUE_SIZE, watchdog_event_forwarder, watchdog_loop};
+use nodenexus_common::agent_service::{
+    AgentConfig, GenericMetricsBatch, LogLineBatch, MessageToServer, PerformanceSnapshotBatch,
+    WatchdogEvent, message_to_server::Payload,
+};
 use crate::version::VERSION;
 use clap::{Parser, arg, command};
 use tracing::{error, info, warn};
@@ -44,7 +56,7 @@ use windows_service::{
 };
 
 #[cfg(windows)]
-const SERVICE_NAME: &str = "NodeNexusAgent";
+use crate::agent_modules::service_install::{SERVICE_NAME, install_service, uninstall_service};
 #[cfg(windows)]
 define_windows_service!(ffi_service_main, service_main);
 
@@ -52,13 +64,30 @@ define_windows_service!(ffi_service_main, service_main);
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Path to the configuration file
-    #[arg(short, long, default_value = "agent_config.toml")]
+    #[arg(short, long, default_value = "agent_config.toml", global = true)]
     config: String,
+
+    /// Install and start the agent as a Windows service using this config file, then exit
+    #[cfg(windows)]
+    #[arg(long, conflicts_with = "uninstall_service")]
+    install_service: bool,
+
+    /// Stop and remove the agent's Windows service, then exit
+    #[cfg(windows)]
+    #[arg(long)]
+    uninstall_service: bool,
+
+    #[command(subcommand)]
+    command: Option<AgentCommand>,
+}
+
+#[derive(clap::Subcommand, Debug)]
+enum AgentCommand {
+    /// Check connectivity to the server and collector permissions, then exit
+    Doctor,
 }
 
 const INITIAL_CLIENT_MESSAGE_ID: AtomicU64 = AtomicU64::new(1);
-const MAX_RECONNECT_DELAY_SECONDS: u64 = 60 * 5;
-const DEFAULT_RECONNECT_DELAY_SECONDS: u64 = 5;
 
 async fn spawn_and_monitor_core_tasks(
     handler: ConnectionHandler,
@@ -66,6 +95,10 @@ async fn spawn_and_monitor_core_tasks(
     shared_agent_config: Arc<RwLock<AgentConfig>>,
     command_tracker: Arc<RunningCommandsTracker>,
     update_lock: Arc<tokio::sync::Mutex<()>>,
+    metric_batches_rx: Arc<tokio::sync::Mutex<tokio::sync::mpsc::Receiver<PerformanceSnapshotBatch>>>,
+    watchdog_events_rx: Option<Arc<tokio::sync::Mutex<tokio::sync::mpsc::Receiver<WatchdogEvent>>>>,
+    log_batches_rx: Option<Arc<tokio::sync::Mutex<tokio::sync::mpsc::Receiver<LogLineBatch>>>>,
+    connection_metrics: GenericMetricsBatch,
     shutdown_rx: tokio::sync::watch::Receiver<()>,
 ) -> Vec<JoinHandle<()>> {
     let (
@@ -81,38 +114,57 @@ async fn spawn_and_monitor_core_tasks(
     let shutdown_rx_metrics = shutdown_rx.clone();
     let shutdown_rx_listener = shutdown_rx.clone();
     let shutdown_rx_monitor = shutdown_rx.clone();
+    let shutdown_rx_inventory = shutdown_rx.clone();
+    let shutdown_rx_processes = shutdown_rx.clone();
+    let shutdown_rx_mesh = shutdown_rx.clone();
+
+    // Connection Metrics (sent once, not monitored)
+    let connection_metrics_tx = tx_to_server.clone();
+    let connection_metrics_msg = MessageToServer {
+        client_message_id: client_message_id_counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst),
+        payload: Some(Payload::GenericMetricsBatch(connection_metrics)),
+        vps_db_id: agent_cli_config.vps_id,
+        agent_secret: agent_cli_config.agent_secret.clone(),
+    };
+    tokio::spawn(async move {
+        if connection_metrics_tx.send(connection_metrics_msg).await.is_err() {
+            warn!("Failed to send connection metrics.");
+        }
+    });
 
-    // Metrics Task
+    // Metrics Forwarder Task
     let metrics_tx = tx_to_server.clone();
-    let metrics_agent_config = Arc::clone(&shared_agent_config);
-    let metrics_id_provider_counter = client_message_id_counter.clone();
     let metrics_vps_id = agent_cli_config.vps_id;
     let metrics_agent_secret = agent_cli_config.agent_secret.clone();
     // Get the closure for ID generation
     let metrics_id_provider =
         crate::agent_modules::communication::ConnectionHandler::get_id_provider_closure(
-            metrics_id_provider_counter,
+            client_message_id_counter.clone(),
         );
-
-    let sys = sysinfo::System::new_with_specifics(
-        RefreshKind::nothing()
-        .with_cpu(CpuRefreshKind::nothing().with_cpu_usage())
-        .with_memory(MemoryRefreshKind::nothing().with_ram().with_swap())
-        .with_processes(ProcessRefreshKind::nothing()));
     tasks.push(tokio::spawn(async move {
-        metrics_collection_loop(
+        metrics_forwarder(
+            metric_batches_rx,
             metrics_tx,
-            metrics_agent_config,
-            metrics_id_provider, // Pass the closure
+            metrics_id_provider,
             metrics_vps_id,
             metrics_agent_secret,
-            sys,
             shutdown_rx_metrics,
         )
         .await;
-        info!("Metrics collection loop ended.");
+        info!("Metrics forwarder ended.");
     }));
 
+    // Buffered Metrics Replay (not monitored: it finishes once the buffer is empty)
+    let replay_id_provider =
+        crate::agent_modules::communication::ConnectionHandler::get_id_provider_closure(
+            client_message_id_counter.clone(),
+        );
+    tokio::spawn(metric_buffer::replay(
+        tx_to_server.clone(),
+        replay_id_provider,
+        agent_cli_config.vps_id,
+        agent_cli_config.agent_secret.clone(),
+    ));
 
     // Server Listener Task
     let listener_tx = tx_to_server.clone();
@@ -169,6 +221,145 @@ async fn spawn_and_monitor_core_tasks(
             .await;
         info!("Service monitor loop ended.");
     }));
+
+    // Inventory Facts Task (idles unless enabled via feature flag)
+    let inventory_tx = tx_to_server.clone();
+    let inventory_agent_config = Arc::clone(&shared_agent_config);
+    let inventory_vps_id = agent_cli_config.vps_id;
+    let inventory_agent_secret = agent_cli_config.agent_secret.clone();
+    let inventory_id_provider =
+        crate::agent_modules::communication::ConnectionHandler::get_id_provider_closure(
+            client_message_id_counter.clone(),
+        );
+    tasks.push(tokio::spawn(async move {
+        inventory_collection_loop(
+            inventory_tx,
+            inventory_agent_config,
+            inventory_id_provider,
+            inventory_vps_id,
+            inventory_agent_secret,
+            shutdown_rx_inventory,
+        )
+        .await;
+        info!("Inventory collection loop ended.");
+    }));
+
+    // Process Metrics Task (can be disabled via feature flag)
+    let processes_tx = tx_to_server.clone();
+    let processes_agent_config = Arc::clone(&shared_agent_config);
+    let processes_vps_id = agent_cli_config.vps_id;
+    let processes_agent_secret = agent_cli_config.agent_secret.clone();
+    let processes_id_provider =
+        crate::agent_modules::communication::ConnectionHandler::get_id_provider_closure(
+            client_message_id_counter.clone(),
+        );
+    tasks.push(tokio::spawn(async move {
+        process_collection_loop(
+            processes_tx,
+            processes_agent_config,
+            processes_id_provider,
+            processes_vps_id,
+            processes_agent_secret,
+            shutdown_rx_processes,
+        )
+        .await;
+        info!("Process collection loop ended.");
+    }));
+
+    // Latency Mesh Task (idles unless the config sets a mesh interval)
+    let mesh_tx = tx_to_server.clone();
+    let mesh_agent_config = Arc::clone(&shared_agent_config);
+    let mesh_vps_id = agent_cli_config.vps_id;
+    let mesh_agent_secret = agent_cli_config.agent_secret.clone();
+    let mesh_id_provider =
+        crate::agent_modules::communication::ConnectionHandler::get_id_provider_closure(
+            client_message_id_counter.clone(),
+        );
+    tasks.push(tokio::spawn(async move {
+        mesh_ping_loop(
+            mesh_tx,
+            mesh_agent_config,
+            mesh_id_provider,
+            mesh_vps_id,
+            mesh_agent_secret,
+            shutdown_rx_mesh,
+        )
+        .await;
+        info!("Mesh ping loop ended.");
+    }));
+
+    // Watchdog Event Forwarder Task (only when watchdog targets are configured)
+    if let Some(events_rx) = watchdog_events_rx {
+        let watchdog_tx = tx_to_server.clone();
+        let watchdog_vps_id = agent_cli_config.vps_id;
+        let watchdog_agent_secret = agent_cli_config.agent_secret.clone();
+        let watchdog_id_provider =
+            crate::agent_modules::communication::ConnectionHandler::get_id_provider_closure(
+                client_message_id_counter.clone(),
+            );
+        let shutdown_rx_watchdog = shutdown_rx.clone();
+        tasks.push(tokio::spawn(async move {
+            watchdog_event_forwarder(
+                events_rx,
+                watchdog_tx,
+                watchdog_id_provider,
+                watchdog_vps_id,
+                watchdog_agent_secret,
+                shutdown_rx_watchdog,
+            )
+            .await;
+            info!("Watchdog event forwarder ended.");
+        }));
+    }
+
+    // Log Tail Forwarder Task (only when log files are configured)
+    if let Some(batches_rx) = log_batches_rx {
+        let log_tail_tx = tx_to_server.clone();
+        let log_tail_vps_id = agent_cli_config.vps_id;
+        let log_tail_agent_secret = agent_cli_config.agent_secret.clone();
+        let log_tail_id_provider =
+            crate::agent_modules::communication::ConnectionHandler::get_id_provider_closure(
+                client_message_id_counter.clone(),
+            );
+        let shutdown_rx_log_tail = shutdown_rx.clone();
+        tasks.push(tokio::spawn(async move {
+            log_tail_forwarder(
+                batches_rx,
+                log_tail_tx,
+                log_tail_id_provider,
+                log_tail_vps_id,
+                log_tail_agent_secret,
+                shutdown_rx_log_tail,
+            )
+            .await;
+            info!("Log tail forwarder ended.");
+        }));
+    }
+
+    // Metric Scripts Task (only when metric scripts are configured)
+    if !agent_cli_config.metric_scripts.is_empty() {
+        let scripts = agent_cli_config.metric_scripts.clone();
+        let scripts_tx = tx_to_server.clone();
+        let scripts_vps_id = agent_cli_config.vps_id;
+        let scripts_agent_secret = agent_cli_config.agent_secret.clone();
+        let scripts_id_provider =
+            crate::agent_modules::communication::ConnectionHandler::get_id_provider_closure(
+                client_message_id_counter.clone(),
+            );
+        let shutdown_rx_scripts = shutdown_rx.clone();
+        tasks.push(tokio::spawn(async move {
+            metric_scripts_loop(
+                scripts,
+                scripts_tx,
+                scripts_id_provider,
+                scripts_vps_id,
+                scripts_agent_secret,
+                shutdown_rx_scripts,
+            )
+            .await;
+            info!("Metric scripts loop ended.");
+        }));
+    }
     info!("All core tasks spawned.");
     tasks
 }
@@ -381,13 +572,33 @@ async fn run_agent_logic() -> Result<(), Box<dyn Error + Send + Sync>> {
 
     let cli_args = Args::parse();
 
+    #[cfg(windows)]
+    if cli_args.install_service || cli_args.uninstall_service {
+        let result = if cli_args.install_service {
+            install_service(&cli_args.config)
+        } else {
+            uninstall_service()
+        };
+        if let Err(e) = result {
+            eprintln!("{e}");
+            std::process::exit(1);
+        }
+        std::process::exit(0);
+    }
+
     rustls::crypto::ring::default_provider()
         .install_default()
         .expect("Failed to install default crypto provider");
+
+    if let Some(AgentCommand::Doctor) = cli_args.command {
+        let healthy = crate::agent_modules::doctor::run_doctor(&cli_args.config).await;
+        std::process::exit(if healthy { 0 } else { 1 });
+    }
+
     init_logging();
     info!(version = VERSION, "Starting agent...");
 
-    let agent_cli_config = match load_cli_config(&cli_args.config) {
+    let mut agent_cli_config = match load_cli_config(&cli_args.config) {
         Ok(mut config) => {
             config.config_path = cli_args.config; // Store the config path
             config
@@ -404,32 +615,89 @@ async fn run_agent_logic() -> Result<(), Box<dyn Error + Send + Sync>> {
     let command_tracker = Arc::new(RunningCommandsTracker::new());
     let update_lock = Arc::new(tokio::sync::Mutex::new(()));
 
+    // The watchdog keeps checking while disconnected; its events wait in the queue.
+    let watchdog_events_rx = if agent_cli_config.watchdog.is_empty() {
+        None
+    } else {
+        let (events_tx, events_rx) = tokio::sync::mpsc::channel(EVENT_QUEUE_SIZE);
+        tokio::spawn(watchdog_loop(
+            agent_cli_config.watchdog.clone(),
+            agent_cli_config.watchdog_interval_seconds,
+            events_tx,
+        ));
+        Some(Arc::new(tokio::sync::Mutex::new(events_rx)))
+    };
+
+    // Log files are read while disconnected too; their lines wait in the queue.
+    let log_batches_rx = if agent_cli_config.log_tail.is_empty() {
+        None
+    } else {
+        let (batches_tx, batches_rx) = tokio::sync::mpsc::channel(log_tail::BATCH_QUEUE_SIZE);
+        tokio::spawn(log_tail_loop(
+            agent_cli_config.log_tail.clone(),
+            agent_cli_config.log_tail_interval_seconds,
+            batches_tx,
+        ));
+        Some(Arc::new(tokio::sync::Mutex::new(batches_rx)))
+    };
+
+    // Metrics are collected for the whole lifetime of the agent. While it is
+    // disconnected they go to the metric buffer and are replayed on reconnect.
+    metric_buffer::init(&agent_cli_config);
+    // Holds the last config received from the server, so collection keeps its
+    // intervals between connections.
+    let shared_agent_config = Arc::new(RwLock::new(AgentConfig::default()));
+    let (metric_batches_tx, metric_batches_rx) = tokio::sync::mpsc::channel(BATCH_QUEUE_SIZE);
+    let metric_batches_rx = Arc::new(tokio::sync::Mutex::new(metric_batches_rx));
+    let sys = sysinfo::System::new_with_specifics(
+        RefreshKind::nothing()
+        .with_cpu(CpuRefreshKind::nothing().with_cpu_usage())
+        .with_memory(MemoryRefreshKind::nothing().with_ram().with_swap())
+        .with_processes(ProcessRefreshKind::nothing()));
+    tokio::spawn(metrics_collection_loop(
+        metric_batches_tx,
+        Arc::clone(&shared_agent_config),
+        sys,
+    ));
+
     // --- Removed setup for Agent's own gRPC Command Service ---
     // The agent will handle commands received over the main communication stream.
 
-    let mut reconnect_delay_seconds = DEFAULT_RECONNECT_DELAY_SECONDS;
+    let mut supervisor = ConnectionSupervisor::new(&agent_cli_config);
 
-    // Main loop for connecting to the primary server
+    // Main loop for connecting to the server, rotating through the configured addresses
     loop {
+        supervisor.connecting();
         info!(
-            server_address = %agent_cli_config.server_address,
-            delay_seconds = reconnect_delay_seconds,
+            server_address = %supervisor.address(),
             "Main client loop: Attempting connection to server."
         );
+        let attempt_config = AgentCliConfig {
+            server_address: supervisor.address().to_string(),
+            ..agent_cli_config.clone()
+        };
 
         // Attempt to connect and handshake (client role)
         // Load the initial value from AtomicU64
         let initial_id = INITIAL_CLIENT_MESSAGE_ID.load(std::sync::atomic::Ordering::SeqCst);
-        match ConnectionHandler::connect_and_handshake(&agent_cli_config, initial_id).await {
-            Ok(handler) => {
+        let (delay, failure) = match ConnectionHandler::connect_and_handshake(&attempt_config, initial_id).await {
+            Ok(mut handler) => {
                 info!("Connection and handshake successful. Spawning tasks.");
+                if let Some((vps_id, agent_secret)) = handler.registered_credentials.take() {
+                    info!(vps_id, "Registered with the provisioning token.");
+                    if let Err(e) = save_registered_credentials(&agent_cli_config.config_path, vps_id, &agent_secret) {
+                        error!(error = %e, "Failed to save issued credentials. The agent will register again after a restart.");
+                    }
+                    agent_cli_config.vps_id = vps_id;
+                    agent_cli_config.agent_secret = agent_secret;
+                    agent_cli_config.provisioning_token = None;
+                }
                 // Log the received config
                 info!(config = ?handler.initial_agent_config, "Received initial config from server.");
-                reconnect_delay_seconds = DEFAULT_RECONNECT_DELAY_SECONDS; // Reset delay on successful connection
+                let connection_metrics = supervisor.connected(); // Also resets the backoff
 
-                // Create the shared, mutable configuration state
-                let shared_agent_config =
-                    Arc::new(RwLock::new(handler.initial_agent_config.clone()));
+                // Update the shared, mutable configuration state
+                *shared_agent_config.write().unwrap() = handler.initial_agent_config.clone();
 
                 let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(());
 
@@ -437,9 +705,13 @@ async fn run_agent_logic() -> Result<(), Box<dyn Error + Send + Sync>> {
                 let task_handles = spawn_and_monitor_core_tasks(
                     handler,
                     &agent_cli_config,
-                    shared_agent_config,
+                    Arc::clone(&shared_agent_config),
                     command_tracker.clone(),
                     update_lock.clone(),
+                    metric_batches_rx.clone(),
+                    watchdog_events_rx.clone(),
+                    log_batches_rx.clone(),
+                    connection_metrics,
                     shutdown_rx,
                 )
                 .await;
@@ -484,20 +756,40 @@ async fn run_agent_logic() -> Result<(), Box<dyn Error + Send + Sync>> {
                 }
 
                 warn!("A task ended or an issue occurred. Preparing to reconnect...");
+                (supervisor.disconnected(), None)
             }
             Err(e) => {
-                error!(error = %e, "Failed to connect or handshake. Will retry.");
-                // Error already logged by connect_and_handshake or load_cli_config
+                let kind = classify(e.as_ref());
+                match kind {
+                    FailureKind::Auth => error!(
+                        error = %e,
+                        "The server rejected the agent's credentials. Will re-read the config file and retry later."
+                    ),
+                    FailureKind::Transient => error!(error = %e, "Failed to connect or handshake. Will retry."),
+                }
+                (supervisor.failed(kind), Some(kind))
             }
-        }
+        };
 
-        // Exponential backoff for retrying connection
+        // Exponential backoff with jitter for retrying connection
         info!(
-            delay_seconds = reconnect_delay_seconds,
+            delay_seconds = delay.as_secs_f64(),
+            next_server_address = %supervisor.address(),
             "Sleeping before next connection attempt."
         );
-        tokio::time::sleep(Duration::from_secs(reconnect_delay_seconds)).await;
-        reconnect_delay_seconds = (reconnect_delay_seconds * 2).min(MAX_RECONNECT_DELAY_SECONDS);
+        tokio::time::sleep(delay).await;
+
+        // Fixed credentials are picked up without restarting the agent.
+        if failure == Some(FailureKind::Auth) {
+            match load_cli_config(&agent_cli_config.config_path) {
+                Ok(reloaded) => {
+                    agent_cli_config.vps_id = reloaded.vps_id;
+                    agent_cli_config.agent_secret = reloaded.agent_secret;
+                    agent_cli_config.provisioning_token = reloaded.provisioning_token;
+                }
+                Err(e) => warn!(error = %e, "Failed to re-read the config file. Retrying with the current credentials."),
+            }
+        }
     }
     // Loop is infinite, so Ok(()) is effectively unreachable but satisfies function signature.
     // For a real application, you might have a shutdown signal (e.g., Ctrl-C handler)
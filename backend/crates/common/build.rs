@@ -15,12 +15,19 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         "./proto/messages.proto",
         "./proto/service.proto",
         "./proto/batch_command.proto",
+        "./proto/inventory.proto",
+        "./proto/watchdog.proto",
+        "./proto/processes.proto",
+        "./proto/log_tail.proto",
     ];
     let out_dir = PathBuf::from(env::var("OUT_DIR").expect("OUT_DIR not set"));
 
     tonic_build::configure()
         .out_dir(out_dir.clone())
         .type_attribute(".", "#[derive(serde::Serialize, serde::Deserialize)]")
+        // Stored configs predate these fields.
+        .field_attribute("AgentConfig.mesh_ping_interval_seconds", "#[serde(default)]")
+        .field_attribute("AgentConfig.mesh_peers", "#[serde(default)]")
         .compile_protos(&proto_files, &["./proto"])?;
 
     // Tell cargo to re-run this build script if any proto file changes.
@@ -1,3 +1,5 @@
 pub mod agent_service {
     tonic::include_proto!("agent_service");
 }
+
+pub mod metric_catalog;
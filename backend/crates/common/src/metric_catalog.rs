@@ -0,0 +1,215 @@
+//! Every metric the agent collects or the server computes from what it
+//! collects: name, unit, type, where it comes from and what it can be used
+//! for. The agent names the metrics it reports after these entries, the server
+//! validates alert rules and expressions against them, and the frontend reads
+//! them from `/api/metrics/catalog` instead of keeping its own lists.
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Unit {
+    Percent,
+    Bytes,
+    BytesPerSecond,
+    Count,
+    PerSecond,
+    Seconds,
+    Milliseconds,
+    Days,
+    Celsius,
+    Watts,
+    /// 0 or 1.
+    Boolean,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricKind {
+    /// A value at a point in time.
+    Gauge,
+    /// Only goes up; rates and increases are what matter.
+    Counter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Os {
+    Linux,
+    Windows,
+    Macos,
+}
+
+const ALL_OS: &[Os] = &[Os::Linux, Os::Windows, Os::Macos];
+const LINUX_AND_WINDOWS: &[Os] = &[Os::Linux, Os::Windows];
+const NO_OS: &[Os] = &[];
+
+/// The part of the agent (or server) a metric comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Source {
+    /// The performance snapshot every agent sends.
+    System,
+    DiskIo,
+    NetworkInterfaces,
+    /// Needs `smartctl` (smartmontools 7+).
+    DiskHealth,
+    Temperature,
+    /// Needs `nvidia-smi` or `rocm-smi`.
+    Gpu,
+    /// Needs `[[watchdog]]` targets in the agent config.
+    Watchdog,
+    /// Needs `[[log_tail]]` files in the agent config.
+    LogTail,
+    ServiceMonitor,
+    /// How the agent's connection to the server is doing.
+    Connection,
+    /// Pings to the peers the server assigns; needs `meshPingIntervalSeconds`
+    /// in the agent config.
+    Mesh,
+    /// Computed by the server.
+    Server,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricUsage {
+    /// Can be the `metricType` of an alert rule.
+    pub alert_rule: bool,
+    /// Can appear in an alert rule's condition expression.
+    pub condition_expression: bool,
+    /// Is a column derived-metric expressions can reference.
+    pub derived_metric: bool,
+}
+
+const ALERTS_ONLY: MetricUsage = MetricUsage {
+    alert_rule: true,
+    condition_expression: false,
+    derived_metric: false,
+};
+const ALERTS_AND_CONDITIONS: MetricUsage = MetricUsage {
+    alert_rule: true,
+    condition_expression: true,
+    derived_metric: false,
+};
+const EVERYWHERE: MetricUsage = MetricUsage {
+    alert_rule: true,
+    condition_expression: true,
+    derived_metric: true,
+};
+const DERIVED_ONLY: MetricUsage = MetricUsage {
+    alert_rule: false,
+    condition_expression: false,
+    derived_metric: true,
+};
+const DISPLAY_ONLY: MetricUsage = MetricUsage {
+    alert_rule: false,
+    condition_expression: false,
+    derived_metric: false,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricDescriptor {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub unit: Unit,
+    #[serde(rename = "type")]
+    pub kind: MetricKind,
+    pub source: Source,
+    /// Operating systems the agent collects it on; empty when it isn't
+    /// collected yet.
+    pub os: &'static [Os],
+    pub usage: MetricUsage,
+}
+
+pub const CONNECTION_STATE: &str = "agent.connection.state";
+pub const CONNECTION_FAILED_ATTEMPTS: &str = "agent.connection.failed_attempts";
+pub const CONNECTION_RECONNECTS: &str = "agent.connection.reconnects";
+pub const CONNECTION_OUTAGE_SECONDS: &str = "agent.connection.outage_seconds";
+pub const MESH_RTT_MS: &str = "agent.mesh.rtt_ms";
+pub const MESH_LOSS_PERCENT: &str = "agent.mesh.loss_percent";
+
+const fn metric(
+    name: &'static str,
+    description: &'static str,
+    unit: Unit,
+    kind: MetricKind,
+    source: Source,
+    os: &'static [Os],
+    usage: MetricUsage,
+) -> MetricDescriptor {
+    MetricDescriptor {
+        name,
+        description,
+        unit,
+        kind,
+        source,
+        os,
+        usage,
+    }
+}
+
+use MetricKind::{Counter, Gauge};
+
+pub const METRICS: &[MetricDescriptor] = &[
+    // System snapshot
+    metric("cpu_usage_percent", "CPU usage across all cores.", Unit::Percent, Gauge, Source::System, ALL_OS, EVERYWHERE),
+    metric("memory_usage_percent", "Used share of physical memory.", Unit::Percent, Gauge, Source::System, ALL_OS, ALERTS_AND_CONDITIONS),
+    metric("memory_usage_bytes", "Used physical memory.", Unit::Bytes, Gauge, Source::System, ALL_OS, DERIVED_ONLY),
+    metric("memory_total_bytes", "Installed physical memory.", Unit::Bytes, Gauge, Source::System, ALL_OS, DERIVED_ONLY),
+    metric("swap_usage_percent", "Used share of swap.", Unit::Percent, Gauge, Source::System, ALL_OS, ALERTS_AND_CONDITIONS),
+    metric("swap_usage_bytes", "Used swap.", Unit::Bytes, Gauge, Source::System, ALL_OS, DERIVED_ONLY),
+    metric("swap_total_bytes", "Swap size.", Unit::Bytes, Gauge, Source::System, ALL_OS, DERIVED_ONLY),
+    metric("disk_usage_percent", "Used share of all mounted disks.", Unit::Percent, Gauge, Source::System, ALL_OS, ALERTS_AND_CONDITIONS),
+    metric("used_disk_space_bytes", "Used space of all mounted disks.", Unit::Bytes, Gauge, Source::System, ALL_OS, DERIVED_ONLY),
+    metric("total_disk_space_bytes", "Size of all mounted disks.", Unit::Bytes, Gauge, Source::System, ALL_OS, DERIVED_ONLY),
+    metric("disk_io_read_bps", "Bytes read from all disks per second.", Unit::BytesPerSecond, Gauge, Source::System, ALL_OS, EVERYWHERE),
+    metric("disk_io_write_bps", "Bytes written to all disks per second.", Unit::BytesPerSecond, Gauge, Source::System, ALL_OS, EVERYWHERE),
+    metric("network_rx_instant_bps", "Bytes received per second on all interfaces.", Unit::BytesPerSecond, Gauge, Source::System, ALL_OS, EVERYWHERE),
+    metric("network_tx_instant_bps", "Bytes sent per second on all interfaces.", Unit::BytesPerSecond, Gauge, Source::System, ALL_OS, EVERYWHERE),
+    metric("uptime_seconds", "Time since the system booted.", Unit::Seconds, Gauge, Source::System, ALL_OS, DERIVED_ONLY),
+    metric("total_processes_count", "Processes on the system.", Unit::Count, Gauge, Source::System, ALL_OS, DERIVED_ONLY),
+    metric("running_processes_count", "Processes currently running.", Unit::Count, Gauge, Source::System, ALL_OS, DERIVED_ONLY),
+    metric("tcp_established_connection_count", "Established TCP connections. Not collected by the agent yet.", Unit::Count, Gauge, Source::System, NO_OS, EVERYWHERE),
+    // Per device and interface
+    metric("disk_await_ms", "Highest average I/O latency of any block device.", Unit::Milliseconds, Gauge, Source::DiskIo, LINUX_AND_WINDOWS, ALERTS_ONLY),
+    metric("nic_link_flaps", "Link state changes of any physical interface.", Unit::Count, Counter, Source::NetworkInterfaces, LINUX_AND_WINDOWS, ALERTS_ONLY),
+    metric("nic_errors_per_sec", "Receive and transmit errors of any physical interface per second.", Unit::PerSecond, Gauge, Source::NetworkInterfaces, LINUX_AND_WINDOWS, ALERTS_ONLY),
+    metric("disk_reallocated_sectors_increase", "New reallocated sectors on any drive.", Unit::Count, Counter, Source::DiskHealth, ALL_OS, ALERTS_ONLY),
+    metric("disk_wear_percent", "Highest wear of any SSD.", Unit::Percent, Gauge, Source::DiskHealth, ALL_OS, ALERTS_ONLY),
+    metric("disk_smart_failed", "Whether any drive fails its SMART self-assessment.", Unit::Boolean, Gauge, Source::DiskHealth, ALL_OS, ALERTS_ONLY),
+    metric("temperature_celsius", "Highest hardware sensor temperature.", Unit::Celsius, Gauge, Source::Temperature, ALL_OS, ALERTS_ONLY),
+    metric("gpu_utilization_percent", "Utilization of each GPU.", Unit::Percent, Gauge, Source::Gpu, ALL_OS, DISPLAY_ONLY),
+    metric("gpu_memory_used_bytes", "Memory used on each GPU.", Unit::Bytes, Gauge, Source::Gpu, ALL_OS, DISPLAY_ONLY),
+    metric("gpu_temperature_celsius", "Temperature of each GPU.", Unit::Celsius, Gauge, Source::Gpu, ALL_OS, DISPLAY_ONLY),
+    metric("gpu_power_draw_watts", "Power draw of each GPU.", Unit::Watts, Gauge, Source::Gpu, ALL_OS, DISPLAY_ONLY),
+    // Agent features
+    metric("watchdog_exits", "Exits of watched processes and units.", Unit::Count, Counter, Source::Watchdog, ALL_OS, ALERTS_ONLY),
+    metric("log_matches", "Tailed log lines matching the rule's `logPattern`.", Unit::Count, Counter, Source::LogTail, ALL_OS, ALERTS_ONLY),
+    metric("cert_expiry_days", "Days until the TLS certificate of a monitored endpoint expires.", Unit::Days, Gauge, Source::ServiceMonitor, ALL_OS, ALERTS_ONLY),
+    metric("monitor_down", "Consecutive failed checks of a service monitor from one agent; the threshold is how many fire the alert.", Unit::Count, Gauge, Source::ServiceMonitor, ALL_OS, ALERTS_ONLY),
+    metric(CONNECTION_STATE, "1 while the agent is connected, 0 from when it lost the connection.", Unit::Boolean, Gauge, Source::Connection, ALL_OS, DISPLAY_ONLY),
+    metric(CONNECTION_FAILED_ATTEMPTS, "Failed connection attempts before the agent got through.", Unit::Count, Gauge, Source::Connection, ALL_OS, DISPLAY_ONLY),
+    metric(CONNECTION_RECONNECTS, "Times the agent reconnected since it started.", Unit::Count, Counter, Source::Connection, ALL_OS, DISPLAY_ONLY),
+    metric(CONNECTION_OUTAGE_SECONDS, "How long the agent was disconnected before it got through.", Unit::Seconds, Gauge, Source::Connection, ALL_OS, DISPLAY_ONLY),
+    metric(MESH_RTT_MS, "Average round-trip time to a mesh peer, per `peer_vps_id`. Missing when every ping was lost.", Unit::Milliseconds, Gauge, Source::Mesh, ALL_OS, DISPLAY_ONLY),
+    metric(MESH_LOSS_PERCENT, "Share of pings to a mesh peer that got no reply, per `peer_vps_id`.", Unit::Percent, Gauge, Source::Mesh, ALL_OS, DISPLAY_ONLY),
+    // Server side
+    metric("traffic_usage_percent", "Used share of the VPS's monthly traffic allowance.", Unit::Percent, Gauge, Source::Server, ALL_OS, ALERTS_ONLY),
+    metric("no_data_metrics", "Seconds since the agent last sent metrics.", Unit::Seconds, Gauge, Source::Server, ALL_OS, ALERTS_ONLY),
+    metric("no_data_monitors", "Seconds since any service monitor of the VPS last reported.", Unit::Seconds, Gauge, Source::Server, ALL_OS, ALERTS_ONLY),
+];
+
+pub fn find(name: &str) -> Option<&'static MetricDescriptor> {
+    METRICS.iter().find(|metric| metric.name == name)
+}
+
+/// Names of the metrics allowed for one use, in catalog order.
+pub fn names_for(usage: impl Fn(&MetricUsage) -> bool) -> Vec<&'static str> {
+    METRICS
+        .iter()
+        .filter(|metric| usage(&metric.usage))
+        .map(|metric| metric.name)
+        .collect()
+}
@@ -1,15 +1,43 @@
 use crate::{
+    alerting::expression::{self, ConditionExpr},
+    alerting::monitor_state::{self, Transition},
+    alerting::schedule::{self, EvaluationSchedule},
     db::{
-        duckdb_service::{self, alert_evaluation_service, alert_service, vps_service, DuckDbPool},
-        entities::{alert_rule, performance_metric},
+        duckdb_service::{
+            self, alert_correlation_service::{self, ActivityEntry}, alert_evaluation_service, alert_event_service,
+            custom_metric_service, derived_metric_service, disk_health_service, disk_io_service, log_match_service,
+            maintenance_service,
+            network_interface_service, no_data_service, service_monitor_service,
+            service_monitor_slo_service, temperature_service, virtual_group_service,
+            watchdog_service,
+            DuckDbPool,
+        },
+        entities::{alert_event, alert_rule, performance_metric},
+        storage::SharedStorage,
     },
-    notifications::encryption::EncryptionService,
+    notifications::{encryption::EncryptionService, models::NotificationAction},
+    server::config::ServerConfig,
+    services::alert_actions,
+    web::models::websocket_models::{AlertEventChange, WsMessage},
 };
 use chrono::{Duration as ChronoDuration, Utc};
+use std::collections::HashSet;
 use std::sync::Arc;
-use tokio::time::{interval, Duration as TokioDuration};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
 use tracing::{debug, error, info, warn};
 
+/// Minimum time between two burn-rate notifications for the same SLO.
+const SLO_ALERT_COOLDOWN_SECONDS: i64 = 3600;
+
+/// How far back `monitor_down` rules look for checks. A monitor that stopped
+/// reporting keeps the state its last checks left it in.
+const MONITOR_CHECK_LOOKBACK_SECONDS: i64 = 86_400;
+
+/// Longest time between reads of the rules, so edits to rules and their
+/// intervals apply without a restart.
+const RULE_REFRESH_INTERVAL: Duration = Duration::from_secs(10);
+
 #[derive(Debug, thiserror::Error)]
 pub enum EvaluationError {
     #[error("Database query error: {0}")]
@@ -22,102 +50,396 @@ pub enum EvaluationError {
 
 pub struct EvaluationService {
     pool: DuckDbPool,
+    storage: SharedStorage,
     encryption_service: Arc<EncryptionService>,
+    config: Arc<ServerConfig>,
+    /// Fired and resolved events are pushed to the dashboards.
+    ws_data_broadcaster_tx: broadcast::Sender<WsMessage>,
 }
 
 impl EvaluationService {
-    pub fn new(pool: DuckDbPool, encryption_service: Arc<EncryptionService>) -> Self {
+    pub fn new(
+        pool: DuckDbPool,
+        storage: SharedStorage,
+        encryption_service: Arc<EncryptionService>,
+        config: Arc<ServerConfig>,
+        ws_data_broadcaster_tx: broadcast::Sender<WsMessage>,
+    ) -> Self {
         Self {
             pool,
+            storage,
             encryption_service,
+            config,
+            ws_data_broadcaster_tx,
         }
     }
 
-    pub async fn start_periodic_evaluation(self: Arc<Self>, period_seconds: u64) {
+    /// Evaluates each active rule at its own interval, or the configured default,
+    /// and SLO burn rates at the default interval.
+    pub async fn start_scheduled_evaluation(self: Arc<Self>) {
+        let default_interval = Duration::from_secs(self.config.alert_evaluation_interval_seconds);
         info!(
-            interval_seconds = period_seconds,
+            default_interval_seconds = default_interval.as_secs(),
             "Alert evaluation service started."
         );
-        let mut interval = interval(TokioDuration::from_secs(period_seconds));
+        let mut schedule = EvaluationSchedule::default();
+        let mut next_slo_run = Instant::now();
         loop {
-            interval.tick().await;
-            debug!("Running alert evaluation cycle...");
-            if let Err(e) = self.run_evaluation_cycle().await {
-                error!(error = %e, "Error during alert evaluation cycle.");
+            let now = Instant::now();
+            match self.storage.get_all_active_rules_for_evaluation().await {
+                Ok(rules) => {
+                    schedule.sync(
+                        rules.iter().map(|rule| {
+                            (rule.id, schedule::rule_interval(rule.evaluation_interval_seconds, default_interval))
+                        }),
+                        now,
+                    );
+                    let due: HashSet<i32> = schedule.take_due(now).into_iter().collect();
+                    if !due.is_empty() {
+                        debug!(count = due.len(), "Evaluating due alert rules.");
+                    }
+                    for rule in rules.iter().filter(|rule| due.contains(&rule.id)) {
+                        self.evaluate_and_notify(rule).await;
+                    }
+                }
+                Err(e) => error!(error = %e, "Failed to fetch active alert rules."),
+            }
+
+            if now >= next_slo_run {
+                next_slo_run = now + default_interval;
+                if let Err(e) = self.evaluate_slos().await {
+                    error!(error = %e, "Error evaluating SLO burn rates.");
+                }
             }
+
+            let wake = schedule
+                .next_due()
+                .unwrap_or(next_slo_run)
+                .min(next_slo_run)
+                .min(Instant::now() + RULE_REFRESH_INTERVAL);
+            tokio::time::sleep_until(wake.into()).await;
         }
     }
 
-    async fn run_evaluation_cycle(&self) -> Result<(), EvaluationError> {
-        debug!("Fetching active alert rules...");
-        let active_rules =
-            alert_service::get_all_active_rules_for_evaluation(self.pool.clone()).await?;
-
-        info!(count = active_rules.len(), "Active rules to evaluate.");
-
-        for rule in active_rules {
-            match self.evaluate_rule(&rule).await {
-                Ok(Some(notification_message)) => {
-                    info!(rule_name = %rule.name, rule_id = rule.id, "Alert rule triggered. Sending notifications.");
-                    match duckdb_service::notification_service::send_notifications_for_alert_rule(
-                        self.pool.clone(),
-                        self.encryption_service.clone(),
-                        rule.id,
-                        notification_message,
-                    )
-                    .await
+    /// Evaluates one rule and sends its notifications if it fired.
+    async fn evaluate_and_notify(&self, rule: &alert_rule::Model) {
+        if rule.metric_type == service_monitor_service::MONITOR_DOWN_METRIC_TYPE {
+            if let Err(e) = self.evaluate_monitor_rule(rule).await {
+                error!(rule_name = %rule.name, rule_id = rule.id, error = %e, "Error evaluating monitor rule.");
+            }
+            return;
+        }
+        match self.evaluate_rule(rule).await {
+            Ok(Some((vps_id, mut notification_message))) => {
+                info!(rule_name = %rule.name, rule_id = rule.id, "Alert rule triggered. Sending notifications.");
+                let recent_activity = self.recent_activity(vps_id).await;
+                let actions = self
+                    .record_alert_event(rule, vps_id, &notification_message, &recent_activity)
+                    .await;
+                if let Some(activity) = alert_correlation_service::format_for_notification(&recent_activity) {
+                    notification_message = format!("{notification_message}\n\n{activity}");
+                }
+                if self.send_notifications(rule, vps_id, notification_message, actions).await {
+                    self.mark_triggered(rule).await;
+                }
+            }
+            Ok(None) => {}
+            Err(e) => {
+                error!(rule_name = %rule.name, rule_id = rule.id, error = %e, "Error evaluating rule.");
+            }
+        }
+    }
+
+    /// Sends a rule's notifications; returns whether that worked.
+    async fn send_notifications(
+        &self,
+        rule: &alert_rule::Model,
+        vps_id: i32,
+        message: String,
+        actions: Vec<NotificationAction>,
+    ) -> bool {
+        match duckdb_service::notification_service::send_notifications_for_alert_rule(
+            self.pool.clone(),
+            self.encryption_service.clone(),
+            rule.id,
+            Some(vps_id),
+            message,
+            actions,
+        )
+        .await
+        {
+            Ok(_) => {
+                info!(
+                    rule_id = rule.id,
+                    "Successfully sent notifications for alert rule."
+                );
+                true
+            }
+            Err(e) => {
+                error!(rule_id = rule.id, error = %e, "Failed to send notifications for alert rule.");
+                false
+            }
+        }
+    }
+
+    async fn mark_triggered(&self, rule: &alert_rule::Model) {
+        if let Err(e_update) = self.storage.update_alert_rule_last_triggered(rule.id, rule.user_id).await {
+            error!(rule_id = rule.id, error = %e_update, "Failed to update last_triggered_at for rule.");
+        }
+    }
+
+    /// Steps the alert state of every monitor and agent a `monitor_down` rule
+    /// covers. The open events are the state, so it survives restarts, and
+    /// they take the place of the cooldown.
+    async fn evaluate_monitor_rule(&self, rule: &alert_rule::Model) -> Result<(), EvaluationError> {
+        let failure_threshold = rule.threshold.max(1.0) as u32;
+        let recovery_threshold = rule
+            .recovery_threshold
+            .map_or(monitor_state::DEFAULT_RECOVERY_THRESHOLD, |n| n.max(1) as u32);
+        let runs = service_monitor_service::get_recent_checks_for_rule(
+            self.pool.clone(),
+            rule.id,
+            Utc::now() - ChronoDuration::seconds(MONITOR_CHECK_LOOKBACK_SECONDS),
+            failure_threshold.max(recovery_threshold) as usize,
+        )
+        .await?;
+        let mut open_events = alert_event_service::get_open_monitor_events(self.pool.clone(), rule.id).await?;
+
+        for run in runs {
+            let open_event = open_events.remove(&(run.monitor_id, run.agent_id));
+            let outcome = match (
+                monitor_state::transition(open_event.is_some(), &run.results, failure_threshold, recovery_threshold),
+                open_event,
+            ) {
+                (Some(Transition::Fire { consecutive_failures }), _) => {
+                    self.fire_monitor_alert(rule, &run, consecutive_failures).await
+                }
+                (Some(Transition::Resolve { consecutive_successes }), Some(event)) => {
+                    self.resolve_monitor_alert(rule, &run, &event, consecutive_successes).await
+                }
+                _ => Ok(()),
+            };
+            if let Err(e) = outcome {
+                error!(
+                    rule_id = rule.id,
+                    monitor_id = run.monitor_id,
+                    vps_id = run.agent_id,
+                    error = %e,
+                    "Error updating monitor alert state."
+                );
+            }
+        }
+        Ok(())
+    }
+
+    async fn fire_monitor_alert(
+        &self,
+        rule: &alert_rule::Model,
+        run: &service_monitor_service::MonitorCheckRun,
+        consecutive_failures: usize,
+    ) -> Result<(), EvaluationError> {
+        if self.storage.is_silenced(rule.id, run.agent_id).await? {
+            debug!(rule_id = rule.id, vps_id = run.agent_id, "Rule is silenced for this VPS.");
+            return Ok(());
+        }
+        if maintenance_service::is_in_maintenance(self.pool.clone(), run.agent_id).await? {
+            debug!(rule_id = rule.id, vps_id = run.agent_id, "VPS is in maintenance. Skipping rule.");
+            return Ok(());
+        }
+
+        let last_message = run
+            .last_message
+            .as_deref()
+            .filter(|message| !message.is_empty())
+            .map(|message| format!(" (last: {message})"))
+            .unwrap_or_default();
+        let mut message = format!(
+            "ALERT! Rule '{}' triggered for VPS '{}' (ID: {}): Monitor '{}' failed {} consecutive checks{}.",
+            rule.name, run.vps_name, run.agent_id, run.monitor_name, consecutive_failures, last_message
+        );
+        info!(rule_id = rule.id, monitor_id = run.monitor_id, vps_id = run.agent_id, "Monitor alert fired.");
+        let recent_activity = self.recent_activity(run.agent_id).await;
+        // Without its event the alert would fire again on the next evaluation,
+        // so nothing is sent when it can't be stored.
+        let event = alert_event_service::create_monitor_alert_event(
+            self.pool.clone(),
+            rule.id,
+            run.agent_id,
+            run.monitor_id,
+            &message,
+            &recent_activity,
+        )
+        .await?;
+        self.push_alert_event(event.id, AlertEventChange::Fired).await;
+        let actions = self.notification_actions(rule, event.id);
+        if let Some(activity) = alert_correlation_service::format_for_notification(&recent_activity) {
+            message = format!("{message}\n\n{activity}");
+        }
+        if self.send_notifications(rule, run.agent_id, message, actions).await {
+            self.mark_triggered(rule).await;
+        }
+        Ok(())
+    }
+
+    async fn resolve_monitor_alert(
+        &self,
+        rule: &alert_rule::Model,
+        run: &service_monitor_service::MonitorCheckRun,
+        event: &alert_event::Model,
+        consecutive_successes: usize,
+    ) -> Result<(), EvaluationError> {
+        alert_event_service::resolve_alert_event(self.pool.clone(), event.id).await?;
+        self.push_alert_event(event.id, AlertEventChange::Resolved).await;
+        info!(rule_id = rule.id, monitor_id = run.monitor_id, vps_id = run.agent_id, "Monitor alert resolved.");
+        if event.silenced_until.is_some_and(|until| until > Utc::now()) {
+            return Ok(());
+        }
+        let message = format!(
+            "RESOLVED: Rule '{}' for VPS '{}' (ID: {}): Monitor '{}' is up again after {} consecutive successful checks.",
+            rule.name, run.vps_name, run.agent_id, run.monitor_name, consecutive_successes
+        );
+        self.send_notifications(rule, run.agent_id, message, Vec::new()).await;
+        Ok(())
+    }
+
+    /// Checks every active SLO against its fast and slow burn-rate thresholds.
+    async fn evaluate_slos(&self) -> Result<(), EvaluationError> {
+        let slos = service_monitor_slo_service::get_active_slos_for_evaluation(self.pool.clone()).await?;
+        let now = Utc::now();
+
+        for entry in slos {
+            let slo = &entry.slo;
+            if let Some(last_alerted) = slo.last_alerted_at {
+                if now < last_alerted + ChronoDuration::seconds(SLO_ALERT_COOLDOWN_SECONDS) {
+                    continue;
+                }
+            }
+            if entry.channel_ids.is_empty() {
+                continue;
+            }
+
+            let (fast, slow) =
+                service_monitor_slo_service::get_burn_rates(self.pool.clone(), slo.clone()).await?;
+            let (severity, rates, threshold) = if fast.exceeds(slo.fast_burn_rate) {
+                ("Fast", fast, slo.fast_burn_rate)
+            } else if slow.exceeds(slo.slow_burn_rate) {
+                ("Slow", slow, slo.slow_burn_rate)
+            } else {
+                continue;
+            };
+
+            let message = format!(
+                "ALERT! {} error budget burn for SLO '{}' on monitor '{}' (target {}% over {} days): burn rate {:.1}x (short window {:.1}x), threshold {}x.",
+                severity,
+                slo.name,
+                entry.monitor_name,
+                slo.target_percent,
+                slo.window_days,
+                rates.long.unwrap_or_default(),
+                rates.short.unwrap_or_default(),
+                threshold
+            );
+            info!(slo_id = slo.id, monitor_id = slo.monitor_id, "SLO burn-rate alert triggered. Sending notifications.");
+
+            match duckdb_service::notification_service::send_notifications_to_channels(
+                self.pool.clone(),
+                self.encryption_service.clone(),
+                entry.channel_ids.clone(),
+                message,
+            )
+            .await
+            {
+                Ok(_) => {
+                    if let Err(e) =
+                        service_monitor_slo_service::mark_slo_alerted(self.pool.clone(), slo.id, now).await
                     {
-                        Ok(_) => {
-                            info!(
-                                rule_id = rule.id,
-                                "Successfully sent notifications for alert rule."
-                            );
-                            if let Err(e_update) =
-                                alert_service::update_alert_rule_last_triggered(
-                                    self.pool.clone(),
-                                    rule.id,
-                                    rule.user_id,
-                                )
-                                .await
-                            {
-                                error!(rule_id = rule.id, error = %e_update, "Failed to update last_triggered_at for rule.");
-                            }
-                        }
-                        Err(e) => {
-                            error!(rule_id = rule.id, error = %e, "Failed to send notifications for alert rule.")
-                        }
+                        error!(slo_id = slo.id, error = %e, "Failed to update last_alerted_at for SLO.");
                     }
                 }
-                Ok(None) => {}
-                Err(e) => {
-                    error!(rule_name = %rule.name, rule_id = rule.id, error = %e, "Error evaluating rule.");
-                }
+                Err(e) => error!(slo_id = slo.id, error = %e, "Failed to send SLO burn-rate notifications."),
             }
         }
         Ok(())
     }
 
+    /// Activity on the VPS just before the alert fired. Errors only cost the
+    /// notification its context.
+    async fn recent_activity(&self, vps_id: i32) -> Vec<ActivityEntry> {
+        alert_correlation_service::recent_activity(self.pool.clone(), vps_id, Utc::now())
+            .await
+            .unwrap_or_else(|e| {
+                warn!(vps_id, error = %e, "Failed to collect recent activity for alert.");
+                Vec::new()
+            })
+    }
+
+    /// Stores the triggered alert and returns the action links for its notification.
+    /// Failing to record the event must not suppress the notification itself.
+    async fn record_alert_event(
+        &self,
+        rule: &alert_rule::Model,
+        vps_id: i32,
+        message: &str,
+        recent_activity: &[ActivityEntry],
+    ) -> Vec<NotificationAction> {
+        let event = match self
+            .storage
+            .create_alert_event(rule.id, vps_id, message, recent_activity)
+            .await
+        {
+            Ok(event) => event,
+            Err(e) => {
+                error!(rule_id = rule.id, vps_id, error = %e, "Failed to record alert event.");
+                return Vec::new();
+            }
+        };
+        self.push_alert_event(event.id, AlertEventChange::Fired).await;
+        self.notification_actions(rule, event.id)
+    }
+
+    async fn push_alert_event(&self, event_id: i32, change: AlertEventChange) {
+        alert_event_service::broadcast_alert_event(self.pool.clone(), &self.ws_data_broadcaster_tx, event_id, change)
+            .await;
+    }
+
+    fn notification_actions(&self, rule: &alert_rule::Model, event_id: i32) -> Vec<NotificationAction> {
+        alert_actions::build_notification_actions(
+            event_id,
+            rule.remediation_script_id.is_some(),
+            &self.config.frontend_url,
+            &self.config.jwt_secret,
+        )
+        .unwrap_or_else(|e| {
+            error!(event_id, error = %e, "Failed to build notification actions.");
+            Vec::new()
+        })
+    }
+
+    /// Returns the VPS the rule fired for and the notification message.
     async fn evaluate_rule(
         &self,
         rule: &alert_rule::Model,
-    ) -> Result<Option<String>, EvaluationError> {
+    ) -> Result<Option<(i32, String)>, EvaluationError> {
         if let Some(specific_vps_id) = rule.vps_id {
-            let vps_name =
-                vps_service::get_vps_by_id(self.pool.clone(), specific_vps_id)
-                    .await?
-                    .map(|v| v.name)
-                    .unwrap_or_else(|| format!("VPS_ID_{specific_vps_id}"));
-
-            self.evaluate_rule_for_single_vps(rule, specific_vps_id, &vps_name)
-                .await
+            let vps_name = self
+                .storage
+                .get_vps_by_id(specific_vps_id)
+                .await?
+                .map(|v| v.name)
+                .unwrap_or_else(|| format!("VPS_ID_{specific_vps_id}"));
+
+            Ok(self
+                .evaluate_rule_for_single_vps(rule, specific_vps_id, &vps_name)
+                .await?
+                .map(|message| (specific_vps_id, message)))
         } else {
             debug!(rule_name = %rule.name, rule_id = rule.id, user_id = rule.user_id, "Evaluating global rule.");
             let user_vps_list =
-                alert_evaluation_service::get_all_vps_for_user(self.pool.clone(), rule.user_id)
+                alert_evaluation_service::get_all_vps_for_rule(self.pool.clone(), rule.id)
                     .await?;
 
             if user_vps_list.is_empty() {
-                warn!(user_id = rule.user_id, rule_name = %rule.name, "No VPS found for user to evaluate global rule.");
+                warn!(user_id = rule.user_id, rule_name = %rule.name, "No VPS found in the rule's organization to evaluate global rule.");
                 return Ok(None);
             }
 
@@ -127,7 +449,7 @@ impl EvaluationService {
                     .await
                 {
                     Ok(Some(message)) => {
-                        return Ok(Some(message));
+                        return Ok(Some((vps_instance.id, message)));
                     }
                     Ok(None) => {}
                     Err(e) => {
@@ -165,8 +487,83 @@ impl EvaluationService {
             }
         }
 
+        if self.storage.is_silenced(rule.id, vps_id).await? {
+            debug!(rule_id = rule.id, vps_id, "Rule is silenced for this VPS.");
+            return Ok(None);
+        }
+
+        if maintenance_service::is_in_maintenance(self.pool.clone(), vps_id).await? {
+            debug!(rule_id = rule.id, vps_id, "VPS is in maintenance. Skipping rule.");
+            return Ok(None);
+        }
+
         let start_time = now - ChronoDuration::seconds(rule.duration_seconds as i64);
 
+        if let Some(expression) = &rule.condition_expression {
+            return self
+                .evaluate_composite_rule(rule, expression, vps_id, vps_name, start_time, now)
+                .await;
+        }
+
+        if rule.metric_type == watchdog_service::WATCHDOG_EXITS_METRIC_TYPE {
+            return self.evaluate_watchdog_rule(rule, vps_id, vps_name, start_time).await;
+        }
+
+        if rule.metric_type == log_match_service::LOG_MATCHES_METRIC_TYPE {
+            return self.evaluate_log_rule(rule, vps_id, vps_name, start_time).await;
+        }
+
+        if rule.metric_type == disk_io_service::DISK_AWAIT_METRIC_TYPE {
+            return self
+                .evaluate_disk_await_rule(rule, vps_id, vps_name, start_time, now)
+                .await;
+        }
+
+        if rule.metric_type == network_interface_service::NIC_LINK_FLAPS_METRIC_TYPE
+            || rule.metric_type == network_interface_service::NIC_ERROR_RATE_METRIC_TYPE
+        {
+            return self
+                .evaluate_network_interface_rule(rule, vps_id, vps_name, start_time, now)
+                .await;
+        }
+
+        if rule.metric_type == no_data_service::NO_DATA_METRICS_METRIC_TYPE
+            || rule.metric_type == no_data_service::NO_DATA_MONITORS_METRIC_TYPE
+        {
+            return self.evaluate_no_data_rule(rule, vps_id, vps_name, now).await;
+        }
+
+        if rule.metric_type == disk_health_service::DISK_REALLOCATED_INCREASE_METRIC_TYPE
+            || rule.metric_type == disk_health_service::DISK_WEAR_METRIC_TYPE
+            || rule.metric_type == disk_health_service::DISK_SMART_FAILED_METRIC_TYPE
+        {
+            return self
+                .evaluate_disk_health_rule(rule, vps_id, vps_name, start_time, now)
+                .await;
+        }
+
+        if rule.metric_type == temperature_service::MAX_TEMPERATURE_METRIC_TYPE {
+            return self
+                .evaluate_temperature_rule(rule, vps_id, vps_name, start_time, now)
+                .await;
+        }
+
+        if rule.metric_type == service_monitor_service::CERT_EXPIRY_DAYS_METRIC_TYPE {
+            return self.evaluate_cert_expiry_rule(rule, vps_id, vps_name, now).await;
+        }
+
+        if let Some(name) = custom_metric_service::parse_custom_metric_type(&rule.metric_type) {
+            return self
+                .evaluate_custom_metric_rule(rule, name, vps_id, vps_name, start_time, now)
+                .await;
+        }
+
+        if let Some(derived_id) = derived_metric_service::parse_derived_metric_type(&rule.metric_type) {
+            return self
+                .evaluate_derived_metric_rule(rule, derived_id, vps_id, vps_name, start_time, now)
+                .await;
+        }
+
         let metrics: Vec<performance_metric::Model> =
             alert_evaluation_service::get_performance_metrics(
                 self.pool.clone(),
@@ -184,29 +581,20 @@ impl EvaluationService {
         let mut last_metric_value_str = "N/A".to_string();
 
         for metric_point in &metrics {
-            let current_value: f64;
-            match rule.metric_type.as_str() {
-                "cpu_usage_percent" => {
-                    current_value = metric_point.cpu_usage_percent;
-                }
-                "memory_usage_percent" => {
-                    if metric_point.memory_total_bytes == 0 {
-                        all_match = false;
-                        break;
-                    }
-                    current_value = (metric_point.memory_usage_bytes as f64
-                        / metric_point.memory_total_bytes as f64)
-                        * 100.0;
-                }
+            // Any metric read from a single sample, as in condition expressions.
+            let current_value = match rule.metric_type.as_str() {
                 "traffic_usage_percent" => {
                     all_match = false;
                     break;
                 }
-                _ => {
-                    all_match = false;
-                    break;
-                }
-            }
+                metric => match expression::metric_value(metric, metric_point) {
+                    Some(value) => value,
+                    None => {
+                        all_match = false;
+                        break;
+                    }
+                },
+            };
             if !rule.metric_type.eq("traffic_usage_percent") {
                 last_metric_value_str = format!("{current_value:.2}");
             }
@@ -233,18 +621,19 @@ impl EvaluationService {
         }
 
         if rule.metric_type.eq("traffic_usage_percent") {
-            let vps_model_option =
-                vps_service::get_vps_by_id(self.pool.clone(), vps_id).await?;
+            let vps_model_option = self.storage.get_vps_by_id(vps_id).await?;
             if let Some(vps_model) = vps_model_option {
                 if let Some(limit_bytes) = vps_model.traffic_limit_bytes {
                     if limit_bytes > 0 {
                         let current_rx = vps_model.traffic_current_cycle_rx_bytes.unwrap_or(0);
                         let current_tx = vps_model.traffic_current_cycle_tx_bytes.unwrap_or(0);
-                        let total_used = match vps_model.traffic_billing_rule.as_deref() {
-                            Some("sum_in_out") => current_rx + current_tx,
-                            Some("out_only") => current_tx,
-                            Some("max_in_out") => std::cmp::max(current_rx, current_tx),
-                            _ => {
+                        let total_used = match virtual_group_service::billed_traffic_bytes(
+                            vps_model.traffic_billing_rule.as_deref(),
+                            current_rx,
+                            current_tx,
+                        ) {
+                            Some(total_used) => total_used,
+                            None => {
                                 warn!(
                                     vps_id = vps_id,
                                     "Unsupported or missing traffic_billing_rule."
@@ -319,4 +708,506 @@ impl EvaluationService {
         }
         Ok(None)
     }
+    /// Composite rules fire when every sample within the rule's duration
+    /// satisfies the condition expression.
+    async fn evaluate_composite_rule(
+        &self,
+        rule: &alert_rule::Model,
+        expression: &serde_json::Value,
+        vps_id: i32,
+        vps_name: &str,
+        start_time: chrono::DateTime<Utc>,
+        now: chrono::DateTime<Utc>,
+    ) -> Result<Option<String>, EvaluationError> {
+        let expr = match ConditionExpr::parse(expression) {
+            Ok(expr) => expr,
+            Err(e) => {
+                warn!(rule_id = rule.id, error = %e, "Alert rule has an invalid condition expression.");
+                return Ok(None);
+            }
+        };
+        let metrics =
+            alert_evaluation_service::get_performance_metrics(self.pool.clone(), vps_id, start_time, now).await?;
+        let Some(latest) = metrics.last() else {
+            return Ok(None);
+        };
+        if !metrics.iter().all(|sample| expr.evaluate(sample)) {
+            return Ok(None);
+        }
+        let message = format!(
+            "ALERT! Rule '{}' triggered for VPS '{}' (ID: {}): {} (current: {}) for {} seconds.",
+            rule.name,
+            vps_name,
+            vps_id,
+            expr.describe(),
+            expr.describe_values(latest),
+            rule.duration_seconds
+        );
+        Ok(Some(message))
+    }
+
+    /// Derived metrics are evaluated in DuckDB. Like the built-in metrics, every
+    /// sample in the window must satisfy the condition; a sample where the
+    /// expression is undefined (e.g. division by zero) never does.
+    async fn evaluate_derived_metric_rule(
+        &self,
+        rule: &alert_rule::Model,
+        derived_id: i32,
+        vps_id: i32,
+        vps_name: &str,
+        start_time: chrono::DateTime<Utc>,
+        now: chrono::DateTime<Utc>,
+    ) -> Result<Option<String>, EvaluationError> {
+        let Some((metric, values)) = derived_metric_service::get_derived_metric_values(
+            self.pool.clone(),
+            rule.user_id,
+            derived_id,
+            vps_id,
+            start_time,
+            now,
+            &self.config.retention_policy(),
+        )
+        .await?
+        else {
+            warn!(rule_id = rule.id, derived_metric_id = derived_id, "Alert rule references a missing derived metric.");
+            return Ok(None);
+        };
+        if values.is_empty() {
+            return Ok(None);
+        }
+
+        let mut last_value = None;
+        for value in values {
+            let Some(current_value) = value else {
+                return Ok(None);
+            };
+            let condition_met = match rule.comparison_operator.as_str() {
+                ">" => current_value > rule.threshold,
+                "<" => current_value < rule.threshold,
+                ">=" => current_value >= rule.threshold,
+                "<=" => current_value <= rule.threshold,
+                "=" | "==" => (current_value - rule.threshold).abs() < f64::EPSILON,
+                "!=" => (current_value - rule.threshold).abs() > f64::EPSILON,
+                _ => false,
+            };
+            if !condition_met {
+                return Ok(None);
+            }
+            last_value = Some(current_value);
+        }
+
+        let unit = metric.unit.as_deref().map(|u| format!(" {u}")).unwrap_or_default();
+        let message = format!(
+            "ALERT! Rule '{}' triggered for VPS '{}' (ID: {}): Metric {} {} {} (current: {:.2}{}) for {} seconds.",
+            rule.name,
+            vps_name,
+            vps_id,
+            metric.name,
+            rule.comparison_operator,
+            rule.threshold,
+            last_value.unwrap_or_default(),
+            unit,
+            rule.duration_seconds
+        );
+        Ok(Some(message))
+    }
+
+    /// Custom metrics are pushed at whatever rate their scripts run, so every
+    /// sample within the rule's duration, of any label set, must satisfy the
+    /// condition.
+    async fn evaluate_custom_metric_rule(
+        &self,
+        rule: &alert_rule::Model,
+        name: &str,
+        vps_id: i32,
+        vps_name: &str,
+        start_time: chrono::DateTime<Utc>,
+        now: chrono::DateTime<Utc>,
+    ) -> Result<Option<String>, EvaluationError> {
+        let values =
+            custom_metric_service::get_custom_metric_values(self.pool.clone(), vps_id, name, start_time, now).await?;
+        if values.is_empty() {
+            return Ok(None);
+        }
+        let all_match = values.iter().all(|&current_value| match rule.comparison_operator.as_str() {
+            ">" => current_value > rule.threshold,
+            "<" => current_value < rule.threshold,
+            ">=" => current_value >= rule.threshold,
+            "<=" => current_value <= rule.threshold,
+            "=" | "==" => (current_value - rule.threshold).abs() < f64::EPSILON,
+            "!=" => (current_value - rule.threshold).abs() > f64::EPSILON,
+            _ => false,
+        });
+        if !all_match {
+            return Ok(None);
+        }
+        let message = format!(
+            "ALERT! Rule '{}' triggered for VPS '{}' (ID: {}): Metric {} {} {} (current: {:.2}) for {} seconds.",
+            rule.name,
+            vps_name,
+            vps_id,
+            name,
+            rule.comparison_operator,
+            rule.threshold,
+            values.last().copied().unwrap_or_default(),
+            rule.duration_seconds
+        );
+        Ok(Some(message))
+    }
+
+    /// Compares the number of process exits the agent's watchdog reported within
+    /// the rule's duration against the threshold.
+    async fn evaluate_watchdog_rule(
+        &self,
+        rule: &alert_rule::Model,
+        vps_id: i32,
+        vps_name: &str,
+        start_time: chrono::DateTime<Utc>,
+    ) -> Result<Option<String>, EvaluationError> {
+        let exits = watchdog_service::count_watchdog_exits(self.pool.clone(), vps_id, start_time).await? as f64;
+        let condition_met = match rule.comparison_operator.as_str() {
+            ">" => exits > rule.threshold,
+            "<" => exits < rule.threshold,
+            ">=" => exits >= rule.threshold,
+            "<=" => exits <= rule.threshold,
+            "=" | "==" => (exits - rule.threshold).abs() < f64::EPSILON,
+            "!=" => (exits - rule.threshold).abs() > f64::EPSILON,
+            _ => false,
+        };
+        if !condition_met {
+            return Ok(None);
+        }
+        let message = format!(
+            "ALERT! Rule '{}' triggered for VPS '{}' (ID: {}): {} watched process exit(s) in the last {} seconds ({} {}).",
+            rule.name,
+            vps_name,
+            vps_id,
+            exits,
+            rule.duration_seconds,
+            rule.comparison_operator,
+            rule.threshold
+        );
+        Ok(Some(message))
+    }
+
+    /// Compares the number of tailed log lines that matched the rule's pattern
+    /// within its duration against the threshold, e.g. more than 5 in 10 minutes.
+    async fn evaluate_log_rule(
+        &self,
+        rule: &alert_rule::Model,
+        vps_id: i32,
+        vps_name: &str,
+        start_time: chrono::DateTime<Utc>,
+    ) -> Result<Option<String>, EvaluationError> {
+        let (count, last_line) =
+            log_match_service::count_log_matches(self.pool.clone(), rule.id, vps_id, start_time).await?;
+        let matches = count as f64;
+        let condition_met = match rule.comparison_operator.as_str() {
+            ">" => matches > rule.threshold,
+            "<" => matches < rule.threshold,
+            ">=" => matches >= rule.threshold,
+            "<=" => matches <= rule.threshold,
+            "=" | "==" => (matches - rule.threshold).abs() < f64::EPSILON,
+            "!=" => (matches - rule.threshold).abs() > f64::EPSILON,
+            _ => false,
+        };
+        if !condition_met {
+            return Ok(None);
+        }
+        let mut message = format!(
+            "ALERT! Rule '{}' triggered for VPS '{}' (ID: {}): {} log line(s) matched '{}' in the last {} seconds ({} {}).",
+            rule.name,
+            vps_name,
+            vps_id,
+            count,
+            rule.log_pattern.as_deref().unwrap_or_default(),
+            rule.duration_seconds,
+            rule.comparison_operator,
+            rule.threshold
+        );
+        if let Some(line) = last_line {
+            message.push_str(&format!("\nLatest: {line}"));
+        }
+        Ok(Some(message))
+    }
+
+    /// Disk latency is sustained when the slowest device's await satisfies the
+    /// condition in every sample within the rule's duration.
+    async fn evaluate_disk_await_rule(
+        &self,
+        rule: &alert_rule::Model,
+        vps_id: i32,
+        vps_name: &str,
+        start_time: chrono::DateTime<Utc>,
+        now: chrono::DateTime<Utc>,
+    ) -> Result<Option<String>, EvaluationError> {
+        let values = disk_io_service::get_max_await_values(self.pool.clone(), vps_id, start_time, now).await?;
+        if values.is_empty() {
+            return Ok(None);
+        }
+        let all_match = values.iter().all(|&current_value| match rule.comparison_operator.as_str() {
+            ">" => current_value > rule.threshold,
+            "<" => current_value < rule.threshold,
+            ">=" => current_value >= rule.threshold,
+            "<=" => current_value <= rule.threshold,
+            "=" | "==" => (current_value - rule.threshold).abs() < f64::EPSILON,
+            "!=" => (current_value - rule.threshold).abs() > f64::EPSILON,
+            _ => false,
+        });
+        if !all_match {
+            return Ok(None);
+        }
+        let message = format!(
+            "ALERT! Rule '{}' triggered for VPS '{}' (ID: {}): Disk await {} {} ms (current: {:.2} ms) for {} seconds.",
+            rule.name,
+            vps_name,
+            vps_id,
+            rule.comparison_operator,
+            rule.threshold,
+            values.last().copied().unwrap_or_default(),
+            rule.duration_seconds
+        );
+        Ok(Some(message))
+    }
+
+    /// Link flaps are counted over the rule's duration; the error rate uses the
+    /// peak within it, since error bursts rarely last a whole window.
+    async fn evaluate_network_interface_rule(
+        &self,
+        rule: &alert_rule::Model,
+        vps_id: i32,
+        vps_name: &str,
+        start_time: chrono::DateTime<Utc>,
+        now: chrono::DateTime<Utc>,
+    ) -> Result<Option<String>, EvaluationError> {
+        let (value, description) = if rule.metric_type == network_interface_service::NIC_LINK_FLAPS_METRIC_TYPE {
+            let flaps = network_interface_service::count_link_flaps(self.pool.clone(), vps_id, start_time).await?;
+            (flaps as f64, "network link state change(s)")
+        } else {
+            match network_interface_service::get_peak_error_rate(self.pool.clone(), vps_id, start_time, now).await? {
+                Some(peak) => (peak, "peak network errors+drops per second"),
+                None => return Ok(None),
+            }
+        };
+        let condition_met = match rule.comparison_operator.as_str() {
+            ">" => value > rule.threshold,
+            "<" => value < rule.threshold,
+            ">=" => value >= rule.threshold,
+            "<=" => value <= rule.threshold,
+            "=" | "==" => (value - rule.threshold).abs() < f64::EPSILON,
+            "!=" => (value - rule.threshold).abs() > f64::EPSILON,
+            _ => false,
+        };
+        if !condition_met {
+            return Ok(None);
+        }
+        let message = format!(
+            "ALERT! Rule '{}' triggered for VPS '{}' (ID: {}): {:.2} {} in the last {} seconds ({} {}).",
+            rule.name,
+            vps_name,
+            vps_id,
+            value,
+            description,
+            rule.duration_seconds,
+            rule.comparison_operator,
+            rule.threshold
+        );
+        Ok(Some(message))
+    }
+
+    /// Reallocated sectors are compared as growth within the rule's duration, so
+    /// a drive that has always had a few doesn't alert forever; wear and failed
+    /// self-assessments use the newest reading of each drive.
+    async fn evaluate_disk_health_rule(
+        &self,
+        rule: &alert_rule::Model,
+        vps_id: i32,
+        vps_name: &str,
+        start_time: chrono::DateTime<Utc>,
+        now: chrono::DateTime<Utc>,
+    ) -> Result<Option<String>, EvaluationError> {
+        let (value, description) = if rule.metric_type == disk_health_service::DISK_REALLOCATED_INCREASE_METRIC_TYPE {
+            match disk_health_service::get_max_reallocated_increase(self.pool.clone(), vps_id, start_time, now).await? {
+                Some(increase) => (
+                    increase as f64,
+                    format!("new reallocated sector(s) in the last {} seconds", rule.duration_seconds),
+                ),
+                None => return Ok(None),
+            }
+        } else {
+            let drives = disk_health_service::get_current_disk_health(self.pool.clone(), vps_id, now).await?;
+            if rule.metric_type == disk_health_service::DISK_WEAR_METRIC_TYPE {
+                let most_worn = drives
+                    .iter()
+                    .filter_map(|d| d.wear_percent.map(|wear| (wear, d.device.as_str())))
+                    .max_by(|a, b| a.0.total_cmp(&b.0));
+                match most_worn {
+                    Some((wear, device)) => (wear, format!("% wear on {device}")),
+                    None => return Ok(None),
+                }
+            } else {
+                let failing: Vec<&str> = drives
+                    .iter()
+                    .filter(|d| d.smart_passed == Some(false))
+                    .map(|d| d.device.as_str())
+                    .collect();
+                let description = if failing.is_empty() {
+                    "drive(s) failing SMART self-assessment".to_string()
+                } else {
+                    format!("drive(s) failing SMART self-assessment ({})", failing.join(", "))
+                };
+                (failing.len() as f64, description)
+            }
+        };
+        if !expression::compare(value, &rule.comparison_operator, rule.threshold).unwrap_or(false) {
+            return Ok(None);
+        }
+        let message = format!(
+            "ALERT! Rule '{}' triggered for VPS '{}' (ID: {}): {} {} ({} {}).",
+            rule.name,
+            vps_name,
+            vps_id,
+            value,
+            description,
+            rule.comparison_operator,
+            rule.threshold
+        );
+        Ok(Some(message))
+    }
+
+    /// The hottest sensor must satisfy the condition in every sample within the
+    /// rule's duration, so brief load spikes don't alert.
+    async fn evaluate_temperature_rule(
+        &self,
+        rule: &alert_rule::Model,
+        vps_id: i32,
+        vps_name: &str,
+        start_time: chrono::DateTime<Utc>,
+        now: chrono::DateTime<Utc>,
+    ) -> Result<Option<String>, EvaluationError> {
+        let values = temperature_service::get_max_temperature_values(self.pool.clone(), vps_id, start_time, now).await?;
+        if values.is_empty() {
+            return Ok(None);
+        }
+        let all_match = values.iter().all(|&current_value| {
+            expression::compare(current_value, &rule.comparison_operator, rule.threshold).unwrap_or(false)
+        });
+        if !all_match {
+            return Ok(None);
+        }
+        let message = format!(
+            "ALERT! Rule '{}' triggered for VPS '{}' (ID: {}): Temperature {} {} °C (current: {:.1} °C) for {} seconds.",
+            rule.name,
+            vps_name,
+            vps_id,
+            rule.comparison_operator,
+            rule.threshold,
+            values.last().copied().unwrap_or_default(),
+            rule.duration_seconds
+        );
+        Ok(Some(message))
+    }
+
+    /// Fires when an online VPS stops delivering data for the rule's duration:
+    /// performance metrics, or results of any monitor it runs. Offline VPS
+    /// never match, so this stays distinct from offline alerts.
+    async fn evaluate_no_data_rule(
+        &self,
+        rule: &alert_rule::Model,
+        vps_id: i32,
+        vps_name: &str,
+        now: chrono::DateTime<Utc>,
+    ) -> Result<Option<String>, EvaluationError> {
+        let threshold_seconds = rule.duration_seconds as i64;
+        let description = if rule.metric_type == no_data_service::NO_DATA_METRICS_METRIC_TYPE {
+            match no_data_service::get_metrics_gap_seconds(self.pool.clone(), vps_id, now).await? {
+                Some(gap) if gap >= threshold_seconds => {
+                    format!("no performance metrics received for {gap} seconds while online")
+                }
+                _ => return Ok(None),
+            }
+        } else {
+            let silent =
+                no_data_service::get_silent_monitors(self.pool.clone(), vps_id, threshold_seconds, now).await?;
+            if silent.is_empty() {
+                return Ok(None);
+            }
+            format!("no results from monitor(s) {}", silent.join(", "))
+        };
+        let message = format!(
+            "ALERT! Rule '{}' triggered for VPS '{}' (ID: {}): {} (limit {} seconds).",
+            rule.name, vps_name, vps_id, description, rule.duration_seconds
+        );
+        Ok(Some(message))
+    }
+
+    /// Compares the days left on the certificates seen by the VPS's HTTPS
+    /// monitors against the threshold, e.g. `< 14`. Fires once for all
+    /// matching monitors.
+    async fn evaluate_cert_expiry_rule(
+        &self,
+        rule: &alert_rule::Model,
+        vps_id: i32,
+        vps_name: &str,
+        now: chrono::DateTime<Utc>,
+    ) -> Result<Option<String>, EvaluationError> {
+        let expiries = service_monitor_service::get_certificate_expiries(self.pool.clone(), vps_id, now).await?;
+        let matching: Vec<String> = expiries
+            .into_iter()
+            .filter(|(_, days)| match rule.comparison_operator.as_str() {
+                ">" => *days > rule.threshold,
+                "<" => *days < rule.threshold,
+                ">=" => *days >= rule.threshold,
+                "<=" => *days <= rule.threshold,
+                "=" | "==" => (*days - rule.threshold).abs() < f64::EPSILON,
+                "!=" => (*days - rule.threshold).abs() > f64::EPSILON,
+                _ => false,
+            })
+            .map(|(name, days)| format!("{name} ({days:.1} days)"))
+            .collect();
+        if matching.is_empty() {
+            return Ok(None);
+        }
+        let message = format!(
+            "ALERT! Rule '{}' triggered for VPS '{}' (ID: {}): TLS certificate expiry {} {} days for monitor(s) {}.",
+            rule.name,
+            vps_name,
+            vps_id,
+            rule.comparison_operator,
+            rule.threshold,
+            matching.join(", ")
+        );
+        Ok(Some(message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nodenexus_common::metric_catalog;
+
+    #[test]
+    fn evaluated_metric_types_are_catalogued() {
+        for metric_type in [
+            watchdog_service::WATCHDOG_EXITS_METRIC_TYPE,
+            log_match_service::LOG_MATCHES_METRIC_TYPE,
+            disk_io_service::DISK_AWAIT_METRIC_TYPE,
+            network_interface_service::NIC_LINK_FLAPS_METRIC_TYPE,
+            network_interface_service::NIC_ERROR_RATE_METRIC_TYPE,
+            no_data_service::NO_DATA_METRICS_METRIC_TYPE,
+            no_data_service::NO_DATA_MONITORS_METRIC_TYPE,
+            disk_health_service::DISK_REALLOCATED_INCREASE_METRIC_TYPE,
+            disk_health_service::DISK_WEAR_METRIC_TYPE,
+            disk_health_service::DISK_SMART_FAILED_METRIC_TYPE,
+            temperature_service::MAX_TEMPERATURE_METRIC_TYPE,
+            service_monitor_service::CERT_EXPIRY_DAYS_METRIC_TYPE,
+            service_monitor_service::MONITOR_DOWN_METRIC_TYPE,
+            "traffic_usage_percent",
+        ] {
+            assert!(
+                metric_catalog::find(metric_type).is_some_and(|m| m.usage.alert_rule),
+                "{metric_type} is not catalogued as an alert metric"
+            );
+        }
+    }
 }
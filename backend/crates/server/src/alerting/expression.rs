@@ -0,0 +1,255 @@
+//! Composite alert conditions: a JSON tree of AND/OR groups over metric
+//! thresholds, evaluated against each performance sample, e.g.
+//!
+//! ```json
+//! {"type": "and", "conditions": [
+//!   {"type": "metric", "metric": "cpu_usage_percent", "operator": ">", "threshold": 90},
+//!   {"type": "metric", "metric": "memory_usage_percent", "operator": ">", "threshold": 80}
+//! ]}
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+use nodenexus_common::metric_catalog;
+
+use crate::db::entities::performance_metric;
+
+/// `metric_type` stored for rules that use a condition expression.
+pub const COMPOSITE_METRIC_TYPE: &str = "composite";
+const MAX_DEPTH: usize = 8;
+const MAX_CONDITIONS: usize = 32;
+const OPERATORS: [&str; 7] = [">", "<", ">=", "<=", "=", "==", "!="];
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ConditionExpr {
+    And { conditions: Vec<ConditionExpr> },
+    Or { conditions: Vec<ConditionExpr> },
+    Metric { metric: String, operator: String, threshold: f64 },
+}
+
+/// Applies a rule comparison operator; `None` for unknown operators.
+pub fn compare(value: f64, operator: &str, threshold: f64) -> Option<bool> {
+    Some(match operator {
+        ">" => value > threshold,
+        "<" => value < threshold,
+        ">=" => value >= threshold,
+        "<=" => value <= threshold,
+        "=" | "==" => (value - threshold).abs() < f64::EPSILON,
+        "!=" => (value - threshold).abs() > f64::EPSILON,
+        _ => return None,
+    })
+}
+
+fn percent(used: i64, total: i64) -> Option<f64> {
+    (total > 0).then(|| used as f64 / total as f64 * 100.0)
+}
+
+/// Whether the catalog allows `metric` in condition expressions.
+pub fn is_expression_metric(metric: &str) -> bool {
+    metric_catalog::find(metric).is_some_and(|m| m.usage.condition_expression)
+}
+
+/// Value of an expression metric in a sample; `None` where it is undefined
+/// (e.g. swap usage without swap).
+pub fn metric_value(metric: &str, sample: &performance_metric::Model) -> Option<f64> {
+    match metric {
+        "cpu_usage_percent" => Some(sample.cpu_usage_percent),
+        "memory_usage_percent" => percent(sample.memory_usage_bytes, sample.memory_total_bytes),
+        "swap_usage_percent" => percent(sample.swap_usage_bytes, sample.swap_total_bytes),
+        "disk_usage_percent" => percent(sample.used_disk_space_bytes, sample.total_disk_space_bytes),
+        "network_rx_instant_bps" => Some(sample.network_rx_instant_bps as f64),
+        "network_tx_instant_bps" => Some(sample.network_tx_instant_bps as f64),
+        "disk_io_read_bps" => Some(sample.disk_io_read_bps as f64),
+        "disk_io_write_bps" => Some(sample.disk_io_write_bps as f64),
+        "tcp_established_connection_count" => Some(sample.tcp_established_connection_count as f64),
+        _ => None,
+    }
+}
+
+impl ConditionExpr {
+    /// Parses and validates an expression as submitted through the API.
+    pub fn parse(value: &serde_json::Value) -> Result<Self, String> {
+        let expr: ConditionExpr =
+            serde_json::from_value(value.clone()).map_err(|e| format!("Invalid condition expression: {e}"))?;
+        expr.validate(1)?;
+        if expr.condition_count() > MAX_CONDITIONS {
+            return Err(format!("Condition expression has more than {MAX_CONDITIONS} conditions."));
+        }
+        Ok(expr)
+    }
+
+    fn validate(&self, depth: usize) -> Result<(), String> {
+        if depth > MAX_DEPTH {
+            return Err(format!("Condition expression is nested deeper than {MAX_DEPTH} levels."));
+        }
+        match self {
+            ConditionExpr::And { conditions } | ConditionExpr::Or { conditions } => {
+                if conditions.is_empty() {
+                    return Err("AND/OR groups need at least one condition.".to_string());
+                }
+                conditions.iter().try_for_each(|c| c.validate(depth + 1))
+            }
+            ConditionExpr::Metric { metric, operator, threshold } => {
+                if !is_expression_metric(metric) {
+                    return Err(format!(
+                        "Unsupported metric '{metric}' in condition expression. Supported: {}.",
+                        metric_catalog::names_for(|usage| usage.condition_expression).join(", ")
+                    ));
+                }
+                if !OPERATORS.contains(&operator.as_str()) {
+                    return Err(format!("Unsupported operator '{operator}' in condition expression."));
+                }
+                if !threshold.is_finite() {
+                    return Err("Thresholds must be finite numbers.".to_string());
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn condition_count(&self) -> usize {
+        match self {
+            ConditionExpr::And { conditions } | ConditionExpr::Or { conditions } => {
+                conditions.iter().map(ConditionExpr::condition_count).sum()
+            }
+            ConditionExpr::Metric { .. } => 1,
+        }
+    }
+
+    /// Whether the sample satisfies the expression. A condition on an
+    /// undefined metric is never satisfied.
+    pub fn evaluate(&self, sample: &performance_metric::Model) -> bool {
+        match self {
+            ConditionExpr::And { conditions } => conditions.iter().all(|c| c.evaluate(sample)),
+            ConditionExpr::Or { conditions } => conditions.iter().any(|c| c.evaluate(sample)),
+            ConditionExpr::Metric { metric, operator, threshold } => metric_value(metric, sample)
+                .and_then(|value| compare(value, operator, *threshold))
+                .unwrap_or(false),
+        }
+    }
+
+    /// Human-readable form for notifications, e.g. `cpu_usage_percent > 90 AND memory_usage_percent > 80`.
+    pub fn describe(&self) -> String {
+        let group = |conditions: &[ConditionExpr], joiner: &str| {
+            let parts: Vec<String> = conditions
+                .iter()
+                .map(|c| match c {
+                    ConditionExpr::Metric { .. } => c.describe(),
+                    _ => format!("({})", c.describe()),
+                })
+                .collect();
+            parts.join(joiner)
+        };
+        match self {
+            ConditionExpr::And { conditions } => group(conditions, " AND "),
+            ConditionExpr::Or { conditions } => group(conditions, " OR "),
+            ConditionExpr::Metric { metric, operator, threshold } => format!("{metric} {operator} {threshold}"),
+        }
+    }
+
+    /// Current values of the metrics referenced by the expression.
+    pub fn describe_values(&self, sample: &performance_metric::Model) -> String {
+        let mut metrics = Vec::new();
+        self.collect_metrics(&mut metrics);
+        metrics
+            .into_iter()
+            .map(|metric| match metric_value(metric, sample) {
+                Some(value) => format!("{metric}={value:.2}"),
+                None => format!("{metric}=N/A"),
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn collect_metrics<'a>(&'a self, out: &mut Vec<&'a str>) {
+        match self {
+            ConditionExpr::And { conditions } | ConditionExpr::Or { conditions } => {
+                conditions.iter().for_each(|c| c.collect_metrics(out))
+            }
+            ConditionExpr::Metric { metric, .. } => {
+                if !out.contains(&metric.as_str()) {
+                    out.push(metric);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample(cpu: f64, memory_used: i64) -> performance_metric::Model {
+        performance_metric::Model {
+            time: chrono::Utc::now(),
+            vps_id: 1,
+            cpu_usage_percent: cpu,
+            memory_usage_bytes: memory_used,
+            memory_total_bytes: 100,
+            swap_usage_bytes: 0,
+            swap_total_bytes: 0,
+            disk_io_read_bps: 0,
+            disk_io_write_bps: 0,
+            total_disk_space_bytes: 0,
+            used_disk_space_bytes: 0,
+            network_rx_cumulative: 0,
+            network_tx_cumulative: 0,
+            network_rx_instant_bps: 0,
+            network_tx_instant_bps: 0,
+            uptime_seconds: 0,
+            total_processes_count: 0,
+            running_processes_count: 0,
+            tcp_established_connection_count: 0,
+        }
+    }
+
+    fn cpu_and_memory() -> ConditionExpr {
+        ConditionExpr::parse(&json!({
+            "type": "and",
+            "conditions": [
+                {"type": "metric", "metric": "cpu_usage_percent", "operator": ">", "threshold": 90},
+                {"type": "or", "conditions": [
+                    {"type": "metric", "metric": "memory_usage_percent", "operator": ">", "threshold": 80},
+                    {"type": "metric", "metric": "swap_usage_percent", "operator": ">", "threshold": 50}
+                ]}
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn evaluates_nested_groups() {
+        let expr = cpu_and_memory();
+        assert!(expr.evaluate(&sample(95.0, 85)));
+        assert!(!expr.evaluate(&sample(95.0, 50)));
+        assert!(!expr.evaluate(&sample(50.0, 85)));
+    }
+
+    #[test]
+    fn describes_expression() {
+        assert_eq!(
+            cpu_and_memory().describe(),
+            "cpu_usage_percent > 90 AND (memory_usage_percent > 80 OR swap_usage_percent > 50)"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(ConditionExpr::parse(&json!({"type": "and", "conditions": []})).is_err());
+        assert!(ConditionExpr::parse(&json!({"type": "metric", "metric": "nope", "operator": ">", "threshold": 1})).is_err());
+        assert!(ConditionExpr::parse(&json!({"type": "metric", "metric": "cpu_usage_percent", "operator": "~", "threshold": 1})).is_err());
+        assert!(ConditionExpr::parse(&json!({"type": "xor", "conditions": []})).is_err());
+    }
+
+    #[test]
+    fn every_catalog_expression_metric_has_a_value() {
+        let mut sample = sample(10.0, 50);
+        sample.swap_total_bytes = 100;
+        sample.total_disk_space_bytes = 100;
+        for name in metric_catalog::names_for(|usage| usage.condition_expression) {
+            assert!(metric_value(name, &sample).is_some(), "{name} has no value");
+        }
+    }
+}
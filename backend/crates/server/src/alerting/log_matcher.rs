@@ -0,0 +1,144 @@
+//! Streaming matcher for `log_matches` rules: each batch of tailed log lines
+//! is matched against the patterns of the rules that apply to the VPS as it
+//! arrives, and the matches are stored for the evaluation service to count
+//! over each rule's duration.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{TimeZone, Utc};
+use nodenexus_common::agent_service::LogLine;
+use regex::{Regex, RegexBuilder};
+use tracing::warn;
+
+use crate::db::duckdb_service::{log_match_service, DuckDbPool};
+use crate::db::entities::log_match;
+use crate::web::error::AppError;
+
+/// Longest pattern a log rule may have.
+pub const MAX_PATTERN_LENGTH: usize = 512;
+/// Upper bound of a compiled pattern's size, so one rule can't make matching slow.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+/// How long the rules of a VPS are reused before they are read again, so rule
+/// edits apply without a restart.
+const RULE_CACHE_TTL: Duration = Duration::from_secs(30);
+
+/// Compiles a log rule's pattern, or explains why it is not usable.
+pub fn compile_pattern(pattern: &str) -> Result<Regex, String> {
+    if pattern.is_empty() {
+        return Err("logPattern must not be empty.".to_string());
+    }
+    if pattern.len() > MAX_PATTERN_LENGTH {
+        return Err(format!("logPattern must be at most {MAX_PATTERN_LENGTH} characters."));
+    }
+    RegexBuilder::new(pattern)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .build()
+        .map_err(|e| format!("logPattern is not a valid regular expression: {e}"))
+}
+
+pub struct LogRule {
+    pub rule_id: i32,
+    pub regex: Regex,
+}
+
+/// The matches of every rule against the lines, in line order.
+pub fn match_lines(vps_id: i32, rules: &[LogRule], lines: &[LogLine]) -> Vec<log_match::Model> {
+    let mut matches = Vec::new();
+    for line in lines {
+        for rule in rules.iter().filter(|rule| rule.regex.is_match(&line.line)) {
+            matches.push(log_match::Model {
+                time: Utc
+                    .timestamp_millis_opt(line.timestamp_unix_ms)
+                    .single()
+                    .unwrap_or_else(Utc::now),
+                vps_id,
+                alert_rule_id: rule.rule_id,
+                source: line.source.clone(),
+                line: line.line.clone(),
+            });
+        }
+    }
+    matches
+}
+
+/// Compiled rules per VPS, read again after [`RULE_CACHE_TTL`].
+#[derive(Default)]
+pub struct LogMatcher {
+    rules: Mutex<HashMap<i32, (Instant, Arc<Vec<LogRule>>)>>,
+}
+
+impl LogMatcher {
+    async fn rules_for_vps(&self, pool: DuckDbPool, vps_id: i32) -> Result<Arc<Vec<LogRule>>, AppError> {
+        if let Some((loaded_at, rules)) = self.rules.lock().unwrap().get(&vps_id) {
+            if loaded_at.elapsed() < RULE_CACHE_TTL {
+                return Ok(rules.clone());
+            }
+        }
+        let rules: Vec<LogRule> = log_match_service::get_log_rules_for_vps(pool, vps_id)
+            .await?
+            .into_iter()
+            .filter_map(|(rule_id, pattern)| match compile_pattern(&pattern) {
+                Ok(regex) => Some(LogRule { rule_id, regex }),
+                Err(e) => {
+                    warn!(rule_id, error = %e, "Skipping log rule with an unusable pattern.");
+                    None
+                }
+            })
+            .collect();
+        let rules = Arc::new(rules);
+        self.rules.lock().unwrap().insert(vps_id, (Instant::now(), rules.clone()));
+        Ok(rules)
+    }
+
+    /// Matches a batch from the VPS's agent and stores the matches; returns how many there were.
+    pub async fn process_lines(&self, pool: DuckDbPool, vps_id: i32, lines: &[LogLine]) -> Result<usize, AppError> {
+        let rules = self.rules_for_vps(pool.clone(), vps_id).await?;
+        if rules.is_empty() {
+            return Ok(0);
+        }
+        let matches = match_lines(vps_id, &rules, lines);
+        log_match_service::record_log_matches(pool, &matches).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn line(source: &str, text: &str) -> LogLine {
+        LogLine {
+            timestamp_unix_ms: 1_750_000_000_000,
+            source: source.to_string(),
+            line: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn rejects_unusable_patterns() {
+        assert!(compile_pattern("").is_err());
+        assert!(compile_pattern("(unclosed").is_err());
+        assert!(compile_pattern(&"a".repeat(MAX_PATTERN_LENGTH + 1)).is_err());
+        assert!(compile_pattern(r"Failed password for \w+").is_ok());
+    }
+
+    #[test]
+    fn each_matching_rule_records_the_line() {
+        let rules = vec![
+            LogRule { rule_id: 1, regex: compile_pattern("Failed password").unwrap() },
+            LogRule { rule_id: 2, regex: compile_pattern("(?i)error").unwrap() },
+        ];
+        let lines = vec![
+            line("auth", "Failed password for root from 10.0.0.1"),
+            line("nginx", "upstream ERROR: connection refused"),
+            line("nginx", "GET / 200"),
+            line("auth", "Failed password for admin: error"),
+        ];
+        let matches = match_lines(7, &rules, &lines);
+        let found: Vec<(i32, &str)> = matches.iter().map(|m| (m.alert_rule_id, m.source.as_str())).collect();
+        assert_eq!(found, vec![(1, "auth"), (2, "nginx"), (1, "auth"), (2, "auth")]);
+        assert!(matches.iter().all(|m| m.vps_id == 7));
+        assert_eq!(matches[0].time.timestamp_millis(), 1_750_000_000_000);
+    }
+}
@@ -1,3 +1,7 @@
 pub mod evaluation_service;
+pub mod expression;
+pub mod log_matcher;
+pub mod monitor_state;
+pub mod schedule;
 
 // Potentially other alerting related modules in the future
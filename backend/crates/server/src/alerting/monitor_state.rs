@@ -0,0 +1,71 @@
+//! Alert state of a service monitor as seen from one agent. A check that
+//! fails once in a while shouldn't page anyone, and a monitor flapping between
+//! up and down shouldn't fire and resolve on every check, so an alert fires
+//! only after a run of failed checks and resolves only after a run of
+//! successful ones.
+
+/// Consecutive successful checks that resolve an alert when the rule sets none.
+pub const DEFAULT_RECOVERY_THRESHOLD: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    Fire { consecutive_failures: usize },
+    Resolve { consecutive_successes: usize },
+}
+
+/// Length of the run of results equal to `is_up` at the end of `results`.
+pub fn trailing_run(results: &[bool], is_up: bool) -> usize {
+    results.iter().rev().take_while(|result| **result == is_up).count()
+}
+
+/// Whether the latest results, oldest first, move a monitor that is `firing`
+/// (or not) to the other state. Thresholds below one count as one.
+pub fn transition(
+    firing: bool,
+    results: &[bool],
+    failure_threshold: u32,
+    recovery_threshold: u32,
+) -> Option<Transition> {
+    if firing {
+        let consecutive_successes = trailing_run(results, true);
+        (consecutive_successes >= recovery_threshold.max(1) as usize)
+            .then_some(Transition::Resolve { consecutive_successes })
+    } else {
+        let consecutive_failures = trailing_run(results, false);
+        (consecutive_failures >= failure_threshold.max(1) as usize)
+            .then_some(Transition::Fire { consecutive_failures })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fires_only_after_enough_consecutive_failures() {
+        assert_eq!(transition(false, &[true, false, false], 3, 2), None);
+        assert_eq!(transition(false, &[false, false, true], 3, 2), None);
+        assert_eq!(
+            transition(false, &[true, false, false, false], 3, 2),
+            Some(Transition::Fire { consecutive_failures: 3 })
+        );
+        assert_eq!(transition(false, &[], 3, 2), None);
+    }
+
+    #[test]
+    fn flapping_monitor_stays_firing_until_enough_successes() {
+        // Down, then up once, then down again: no resolve in between.
+        assert_eq!(transition(true, &[false, false, true], 3, 2), None);
+        assert_eq!(transition(true, &[false, true, false], 3, 2), None);
+        assert_eq!(
+            transition(true, &[false, true, true], 3, 2),
+            Some(Transition::Resolve { consecutive_successes: 2 })
+        );
+    }
+
+    #[test]
+    fn zero_thresholds_count_as_one() {
+        assert_eq!(transition(false, &[false], 0, 0), Some(Transition::Fire { consecutive_failures: 1 }));
+        assert_eq!(transition(true, &[true], 0, 0), Some(Transition::Resolve { consecutive_successes: 1 }));
+    }
+}
@@ -0,0 +1,149 @@
+//! When each alert rule is evaluated next. Rules have their own intervals, so
+//! the evaluation loop sleeps until the earliest due rule instead of sweeping
+//! every rule on one fixed period.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::time::{Duration, Instant};
+
+/// Shortest interval a rule may be evaluated at.
+pub const MIN_INTERVAL_SECONDS: u64 = 10;
+/// Longest interval a rule may be evaluated at.
+pub const MAX_INTERVAL_SECONDS: u64 = 86_400;
+
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    interval: Duration,
+    next_due: Instant,
+    last_run: Option<Instant>,
+}
+
+/// Due times of the active rules, ordered in a heap. Entries replaced by a
+/// reschedule stay in the heap and are skipped when popped.
+#[derive(Debug, Default)]
+pub struct EvaluationSchedule {
+    entries: HashMap<i32, Entry>,
+    queue: BinaryHeap<Reverse<(Instant, i32)>>,
+}
+
+impl EvaluationSchedule {
+    /// Brings the schedule in line with the active rules and their intervals.
+    /// New rules are due at once; a changed interval counts from the rule's
+    /// last run, so shortening it takes effect without waiting out the old one.
+    pub fn sync(&mut self, rules: impl IntoIterator<Item = (i32, Duration)>, now: Instant) {
+        let mut active = HashMap::new();
+        for (rule_id, interval) in rules {
+            active.insert(rule_id, interval);
+        }
+        self.entries.retain(|rule_id, _| active.contains_key(rule_id));
+
+        for (rule_id, interval) in active {
+            match self.entries.get_mut(&rule_id) {
+                Some(entry) if entry.interval == interval => {}
+                Some(entry) => {
+                    entry.interval = interval;
+                    entry.next_due = entry.last_run.map_or(now, |last_run| last_run + interval);
+                    self.queue.push(Reverse((entry.next_due, rule_id)));
+                }
+                None => {
+                    self.entries.insert(rule_id, Entry { interval, next_due: now, last_run: None });
+                    self.queue.push(Reverse((now, rule_id)));
+                }
+            }
+        }
+
+        // Stale heap entries pile up when intervals change often; rebuild then.
+        if self.queue.len() > 2 * self.entries.len() + 16 {
+            self.queue = self.entries.iter().map(|(id, entry)| Reverse((entry.next_due, *id))).collect();
+        }
+    }
+
+    /// Removes and returns the rules due at `now`, scheduling each one
+    /// interval from now. Missed runs aren't caught up.
+    pub fn take_due(&mut self, now: Instant) -> Vec<i32> {
+        let mut due = Vec::new();
+        while let Some(Reverse((at, rule_id))) = self.queue.peek().copied() {
+            if at > now {
+                break;
+            }
+            self.queue.pop();
+            let Some(entry) = self.entries.get_mut(&rule_id) else {
+                continue;
+            };
+            if entry.next_due != at {
+                continue;
+            }
+            entry.last_run = Some(now);
+            entry.next_due = now + entry.interval;
+            self.queue.push(Reverse((entry.next_due, rule_id)));
+            due.push(rule_id);
+        }
+        due
+    }
+
+    /// When the earliest rule is due, if any rule is scheduled.
+    pub fn next_due(&self) -> Option<Instant> {
+        self.entries.values().map(|entry| entry.next_due).min()
+    }
+}
+
+/// A rule's own interval, or `default` when it has none.
+pub fn rule_interval(interval_seconds: Option<i32>, default: Duration) -> Duration {
+    match interval_seconds {
+        Some(seconds) if seconds > 0 => {
+            Duration::from_secs((seconds as u64).clamp(MIN_INTERVAL_SECONDS, MAX_INTERVAL_SECONDS))
+        }
+        _ => default,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECOND: Duration = Duration::from_secs(1);
+
+    #[test]
+    fn rules_run_at_their_own_intervals() {
+        let start = Instant::now();
+        let mut schedule = EvaluationSchedule::default();
+        schedule.sync([(1, 30 * SECOND), (2, 300 * SECOND)], start);
+
+        let mut first = schedule.take_due(start);
+        first.sort_unstable();
+        assert_eq!(first, vec![1, 2]);
+        assert_eq!(schedule.next_due(), Some(start + 30 * SECOND));
+        assert!(schedule.take_due(start + 29 * SECOND).is_empty());
+        assert_eq!(schedule.take_due(start + 30 * SECOND), vec![1]);
+        assert_eq!(schedule.take_due(start + 60 * SECOND), vec![1]);
+        let mut later = schedule.take_due(start + 300 * SECOND);
+        later.sort_unstable();
+        assert_eq!(later, vec![1, 2]);
+    }
+
+    #[test]
+    fn sync_reschedules_changed_and_removed_rules() {
+        let start = Instant::now();
+        let mut schedule = EvaluationSchedule::default();
+        schedule.sync([(1, 300 * SECOND), (2, 60 * SECOND)], start);
+        schedule.take_due(start);
+
+        // Shortening the interval counts from the last run, not the old due time.
+        schedule.sync([(1, 30 * SECOND)], start + 10 * SECOND);
+        assert_eq!(schedule.next_due(), Some(start + 30 * SECOND));
+        assert_eq!(schedule.take_due(start + 60 * SECOND), vec![1]);
+
+        // A rule added later is due at once.
+        schedule.sync([(1, 30 * SECOND), (3, 60 * SECOND)], start + 61 * SECOND);
+        assert_eq!(schedule.take_due(start + 61 * SECOND), vec![3]);
+    }
+
+    #[test]
+    fn intervals_fall_back_and_are_clamped() {
+        let default = 60 * SECOND;
+        assert_eq!(rule_interval(None, default), default);
+        assert_eq!(rule_interval(Some(0), default), default);
+        assert_eq!(rule_interval(Some(1), default), 10 * SECOND);
+        assert_eq!(rule_interval(Some(300), default), 300 * SECOND);
+    }
+}
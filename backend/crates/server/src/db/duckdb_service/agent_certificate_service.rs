@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, OptionalExt, Result as DuckDbResult, Row};
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::agent_client_certificate;
+use crate::web::error::AppError;
+
+fn row_to_certificate_model(row: &Row) -> DuckDbResult<agent_client_certificate::Model> {
+    Ok(agent_client_certificate::Model {
+        vps_id: row.get("vps_id")?,
+        fingerprint: row.get("fingerprint")?,
+        issued_at: row.get("issued_at")?,
+        expires_at: row.get("expires_at")?,
+    })
+}
+
+/// Records a newly issued certificate, replacing the VPS's previous one.
+pub async fn save_certificate(
+    pool: DuckDbPool,
+    vps_id: i32,
+    fingerprint: &str,
+    expires_at: DateTime<Utc>,
+) -> Result<agent_client_certificate::Model, AppError> {
+    let conn = pool.get()?;
+    let model = conn.query_row(
+        "INSERT INTO agent_client_certificates (vps_id, fingerprint, issued_at, expires_at)
+         VALUES (?, ?, ?, ?)
+         ON CONFLICT (vps_id) DO UPDATE SET
+             fingerprint = excluded.fingerprint,
+             issued_at = excluded.issued_at,
+             expires_at = excluded.expires_at
+         RETURNING *",
+        params![vps_id, fingerprint, Utc::now(), expires_at],
+        row_to_certificate_model,
+    )?;
+    Ok(model)
+}
+
+pub async fn get_certificate(
+    pool: DuckDbPool,
+    vps_id: i32,
+) -> Result<Option<agent_client_certificate::Model>, AppError> {
+    let conn = pool.get()?;
+    let model = conn
+        .query_row(
+            "SELECT * FROM agent_client_certificates WHERE vps_id = ?",
+            params![vps_id],
+            row_to_certificate_model,
+        )
+        .optional()?;
+    Ok(model)
+}
+
+/// Revokes the VPS's certificate; its agent authenticates with the secret
+/// alone again. Returns whether there was one.
+pub async fn delete_certificate(pool: DuckDbPool, vps_id: i32) -> Result<bool, AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute(
+        "DELETE FROM agent_client_certificates WHERE vps_id = ?",
+        params![vps_id],
+    )?;
+    Ok(deleted > 0)
+}
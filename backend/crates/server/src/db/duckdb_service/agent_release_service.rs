@@ -0,0 +1,143 @@
+use duckdb::{params, Connection, OptionalExt, Result as DuckDbResult, Row};
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::agent_release;
+use crate::web::error::AppError;
+
+fn row_to_release_model(row: &Row) -> DuckDbResult<agent_release::Model> {
+    Ok(agent_release::Model {
+        version: row.get("version")?,
+        target: row.get("target")?,
+        file_name: row.get("file_name")?,
+        url: row.get("url")?,
+        size_bytes: row.get("size_bytes")?,
+        sha256: row.get("sha256")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+fn parse_version(version: &str) -> Option<semver::Version> {
+    semver::Version::parse(version.trim().trim_start_matches('v')).ok()
+}
+
+/// The highest semver among `versions`; ones that don't parse are skipped.
+pub fn newest_version<'a>(versions: impl IntoIterator<Item = &'a str>) -> Option<&'a str> {
+    versions
+        .into_iter()
+        .filter_map(|v| parse_version(v).map(|parsed| (parsed, v)))
+        .max_by(|(a, _), (b, _)| a.cmp(b))
+        .map(|(_, v)| v)
+}
+
+/// The version agents are compared against: the newest release the server
+/// knows about, or the server's own version when none is newer.
+pub fn latest_agent_version(conn: &Connection) -> Result<String, AppError> {
+    let versions = conn
+        .prepare("SELECT DISTINCT version FROM agent_releases")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let server_version = crate::version::VERSION;
+    Ok(newest_version(versions.iter().map(String::as_str).chain([server_version]))
+        .unwrap_or(server_version)
+        .to_string())
+}
+
+pub async fn get_latest_agent_version(pool: DuckDbPool) -> Result<String, AppError> {
+    let conn = pool.get()?;
+    latest_agent_version(&conn)
+}
+
+/// Records a release, replacing any previous one for the same version and target.
+pub async fn save_release(pool: DuckDbPool, release: &agent_release::Model) -> Result<agent_release::Model, AppError> {
+    let conn = pool.get()?;
+    let model = conn.query_row(
+        "INSERT INTO agent_releases (version, target, file_name, url, size_bytes, sha256, created_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?)
+         ON CONFLICT (version, target) DO UPDATE SET
+             file_name = excluded.file_name,
+             url = excluded.url,
+             size_bytes = excluded.size_bytes,
+             sha256 = excluded.sha256,
+             created_at = excluded.created_at
+         RETURNING *",
+        params![
+            release.version,
+            release.target,
+            release.file_name,
+            release.url,
+            release.size_bytes,
+            release.sha256,
+            release.created_at,
+        ],
+        row_to_release_model,
+    )?;
+    Ok(model)
+}
+
+/// All releases, newest first.
+pub async fn list_releases(pool: DuckDbPool) -> Result<Vec<agent_release::Model>, AppError> {
+    let conn = pool.get()?;
+    let mut releases = conn
+        .prepare("SELECT * FROM agent_releases ORDER BY target ASC")?
+        .query_map([], row_to_release_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    // Semver order, which SQL can't do; unparseable versions go last.
+    releases.sort_by(|a, b| parse_version(&b.version).cmp(&parse_version(&a.version)));
+    Ok(releases)
+}
+
+/// The release for `target`; `version` may be `latest` for the newest one
+/// that has a build for it.
+pub async fn get_release(
+    pool: DuckDbPool,
+    version: &str,
+    target: &str,
+) -> Result<Option<agent_release::Model>, AppError> {
+    let conn = pool.get()?;
+    if version != "latest" {
+        let model = conn
+            .query_row(
+                "SELECT * FROM agent_releases WHERE version = ? AND target = ?",
+                params![version, target],
+                row_to_release_model,
+            )
+            .optional()?;
+        return Ok(model);
+    }
+    let releases = conn
+        .prepare("SELECT * FROM agent_releases WHERE target = ?")?
+        .query_map(params![target], row_to_release_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    let newest = newest_version(releases.iter().map(|r| r.version.as_str())).map(str::to_string);
+    Ok(newest.and_then(|newest| releases.into_iter().find(|r| r.version == newest)))
+}
+
+/// Returns the deleted release, if there was one.
+pub async fn delete_release(
+    pool: DuckDbPool,
+    version: &str,
+    target: &str,
+) -> Result<Option<agent_release::Model>, AppError> {
+    let conn = pool.get()?;
+    let model = conn
+        .query_row(
+            "DELETE FROM agent_releases WHERE version = ? AND target = ? RETURNING *",
+            params![version, target],
+            row_to_release_model,
+        )
+        .optional()?;
+    Ok(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newest_version_compares_as_semver() {
+        assert_eq!(newest_version(["1.9.0", "1.10.0", "v1.2.3"]), Some("1.10.0"));
+        assert_eq!(newest_version(["nightly", "0.1.0"]), Some("0.1.0"));
+        assert_eq!(newest_version(["1.0.0-rc.1", "1.0.0"]), Some("1.0.0"));
+        assert_eq!(newest_version(["nightly"]), None);
+    }
+}
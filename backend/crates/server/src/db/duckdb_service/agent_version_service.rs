@@ -0,0 +1,175 @@
+use chrono::{DateTime, Duration, Utc};
+use duckdb::{params, Connection, OptionalExt, Row};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::BTreeMap;
+
+use crate::db::duckdb_service::virtual_group_service::is_agent_outdated;
+use crate::db::duckdb_service::{organization_service, webhook_service, DuckDbPool};
+use crate::db::entities::agent_version_event;
+use crate::web::error::AppError;
+
+/// How far back the report's upgrade count looks.
+const RECENT_CHANGES_DAYS: i64 = 7;
+
+fn row_to_agent_version_event(row: &Row) -> duckdb::Result<agent_version_event::Model> {
+    Ok(agent_version_event::Model {
+        id: row.get("id")?,
+        vps_id: row.get("vps_id")?,
+        time: row.get("time")?,
+        previous_version: row.get("previous_version")?,
+        version: row.get("version")?,
+    })
+}
+
+/// Records a version change if `version` differs from the one stored for the
+/// VPS. Called on handshake, before the stored version is updated. Upgrades
+/// of an agent that had reported before are published as `agent.updated`.
+pub fn record_version_change(
+    conn: &Connection,
+    vps_id: i32,
+    version: &str,
+    time: DateTime<Utc>,
+) -> Result<(), AppError> {
+    if version.is_empty() {
+        return Ok(());
+    }
+    let previous_version: Option<Option<String>> = conn
+        .query_row(
+            "INSERT INTO agent_version_events (vps_id, time, previous_version, version)
+             SELECT id, ?, agent_version, ? FROM vps
+             WHERE id = ? AND agent_version IS DISTINCT FROM ?
+             RETURNING previous_version",
+            params![time, version, vps_id, version],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if let Some(Some(previous_version)) = previous_version {
+        webhook_service::publish_vps_event(
+            conn,
+            vps_id,
+            webhook_service::EVENT_AGENT_UPDATED,
+            json!({ "previousVersion": previous_version, "version": version, "time": time }),
+        )?;
+    }
+    Ok(())
+}
+
+/// Version changes of one VPS, newest first.
+pub async fn get_version_history(
+    pool: DuckDbPool,
+    vps_id: i32,
+) -> Result<Vec<agent_version_event::Model>, AppError> {
+    let conn = pool.get()?;
+    let events = conn
+        .prepare("SELECT * FROM agent_version_events WHERE vps_id = ? ORDER BY time DESC, id DESC")?
+        .query_map(params![vps_id], row_to_agent_version_event)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(events)
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentVersionCount {
+    pub version: String,
+    pub vps_count: i64,
+    pub outdated: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentVersionReport {
+    /// Version agents are compared against: the server's own.
+    pub latest_version: String,
+    pub total_vps: i64,
+    pub outdated_count: i64,
+    /// VPS whose agent never reported a version.
+    pub unknown_count: i64,
+    /// Newest version first.
+    pub versions: Vec<AgentVersionCount>,
+    /// Version changes across the fleet in the last week.
+    pub recent_changes: Vec<agent_version_event::Model>,
+}
+
+/// Counts agents per version, newest first. Versions that don't parse as
+/// semver sort last, by name.
+pub fn summarize_versions(versions: &[Option<String>], latest_version: &str) -> Vec<AgentVersionCount> {
+    let mut counts: BTreeMap<&str, i64> = BTreeMap::new();
+    for version in versions.iter().flatten().filter(|v| !v.is_empty()) {
+        *counts.entry(version.as_str()).or_default() += 1;
+    }
+    let parse = |v: &str| semver::Version::parse(v.trim_start_matches('v')).ok();
+    let mut summary: Vec<AgentVersionCount> = counts
+        .into_iter()
+        .map(|(version, vps_count)| AgentVersionCount {
+            version: version.to_string(),
+            vps_count,
+            outdated: is_agent_outdated(version, latest_version),
+        })
+        .collect();
+    summary.sort_by(|a, b| match (parse(&a.version), parse(&b.version)) {
+        (Some(a), Some(b)) => b.cmp(&a),
+        (Some(_), None) => std::cmp::Ordering::Less,
+        (None, Some(_)) => std::cmp::Ordering::Greater,
+        (None, None) => a.version.cmp(&b.version),
+    });
+    summary
+}
+
+/// Version distribution across a user's non-archived VPS.
+pub async fn get_agent_version_report(
+    pool: DuckDbPool,
+    user_id: i32,
+    latest_version: &str,
+) -> Result<AgentVersionReport, AppError> {
+    let conn = pool.get()?;
+    let versions = conn
+        .prepare(&format!(
+            "SELECT agent_version FROM vps WHERE {} AND archived_at IS NULL",
+            organization_service::org_scope("organization_id")
+        ))?
+        .query_map(params![user_id], |row| row.get::<_, Option<String>>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let recent_changes = conn
+        .prepare(&format!(
+            "SELECT e.* FROM agent_version_events e JOIN vps v ON v.id = e.vps_id
+             WHERE {} AND e.time >= ?
+             ORDER BY e.time DESC, e.id DESC",
+            organization_service::org_scope("v.organization_id")
+        ))?
+        .query_map(
+            params![user_id, Utc::now() - Duration::days(RECENT_CHANGES_DAYS)],
+            row_to_agent_version_event,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let summary = summarize_versions(&versions, latest_version);
+    let known: i64 = summary.iter().map(|v| v.vps_count).sum();
+    Ok(AgentVersionReport {
+        latest_version: latest_version.to_string(),
+        total_vps: versions.len() as i64,
+        outdated_count: summary.iter().filter(|v| v.outdated).map(|v| v.vps_count).sum(),
+        unknown_count: versions.len() as i64 - known,
+        versions: summary,
+        recent_changes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_counted_newest_first() {
+        let versions = [
+            Some("0.1.0".to_string()),
+            Some("0.10.0".to_string()),
+            Some("0.1.0".to_string()),
+            Some("dev".to_string()),
+            None,
+        ];
+        let summary = summarize_versions(&versions, "0.10.0");
+        let order: Vec<_> = summary.iter().map(|v| (v.version.as_str(), v.vps_count, v.outdated)).collect();
+        assert_eq!(order, vec![("0.10.0", 1, false), ("0.1.0", 2, true), ("dev", 1, false)]);
+    }
+}
@@ -0,0 +1,258 @@
+//! "What changed?" context for alerts: commands, config pushes, maintenance
+//! toggles and audited edits on the VPS shortly before an alert fired.
+
+use chrono::{DateTime, Duration, Utc};
+use duckdb::{params, Connection};
+use serde::{Deserialize, Serialize};
+
+use crate::db::duckdb_service::{settings_service, DuckDbPool};
+use crate::web::error::AppError;
+
+pub const CORRELATION_SETTING_KEY: &str = "alert_correlation";
+/// At most this many entries are attached to one alert.
+const MAX_ENTRIES: usize = 20;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct CorrelationSettings {
+    /// How far before the alert activity is collected; 0 disables correlation.
+    pub window_minutes: i64,
+}
+
+impl Default for CorrelationSettings {
+    fn default() -> Self {
+        Self { window_minutes: 30 }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ActivityKind {
+    Command,
+    ConfigPush,
+    Maintenance,
+    Change,
+}
+
+impl ActivityKind {
+    fn label(self) -> &'static str {
+        match self {
+            ActivityKind::Command => "command",
+            ActivityKind::ConfigPush => "config push",
+            ActivityKind::Maintenance => "maintenance",
+            ActivityKind::Change => "change",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ActivityEntry {
+    pub kind: ActivityKind,
+    pub time: DateTime<Utc>,
+    pub summary: String,
+    pub actor: Option<String>,
+    pub status: Option<String>,
+}
+
+pub async fn get_correlation_settings(pool: DuckDbPool) -> Result<CorrelationSettings, AppError> {
+    let settings = settings_service::get_setting(pool, CORRELATION_SETTING_KEY)
+        .await?
+        .map(|s| serde_json::from_value(s.value))
+        .transpose()?
+        .unwrap_or_default();
+    Ok(settings)
+}
+
+pub async fn update_correlation_settings(pool: DuckDbPool, settings: &CorrelationSettings) -> Result<(), AppError> {
+    let value = serde_json::to_value(settings)?;
+    settings_service::update_setting(pool, CORRELATION_SETTING_KEY, &value).await?;
+    Ok(())
+}
+
+/// Activity on `vps_id` within the configured window before `until`, newest first.
+pub async fn recent_activity(pool: DuckDbPool, vps_id: i32, until: DateTime<Utc>) -> Result<Vec<ActivityEntry>, AppError> {
+    let settings = get_correlation_settings(pool.clone()).await?;
+    if settings.window_minutes <= 0 {
+        return Ok(Vec::new());
+    }
+    let since = until - Duration::minutes(settings.window_minutes);
+    let conn = pool.get()?;
+    let mut entries = Vec::new();
+    entries.extend(commands(&conn, vps_id, since, until)?);
+    entries.extend(config_pushes(&conn, vps_id, since, until)?);
+    entries.extend(maintenance(&conn, vps_id, since, until)?);
+    entries.extend(changes(&conn, vps_id, since, until)?);
+    Ok(newest_first(entries))
+}
+
+fn newest_first(mut entries: Vec<ActivityEntry>) -> Vec<ActivityEntry> {
+    entries.sort_by(|a, b| b.time.cmp(&a.time));
+    entries.truncate(MAX_ENTRIES);
+    entries
+}
+
+fn commands(conn: &Connection, vps_id: i32, since: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<ActivityEntry>, AppError> {
+    Ok(conn
+        .prepare(
+            "SELECT c.created_at, c.status,
+                    COALESCE(b.execution_alias, b.original_request_payload->>'command_content', 'script ' || (b.original_request_payload->>'script_id')),
+                    u.username
+             FROM child_command_tasks c
+             JOIN batch_command_tasks b ON b.batch_command_id = c.batch_command_id
+             LEFT JOIN users u ON u.id = b.user_id
+             WHERE c.vps_id = ? AND c.created_at BETWEEN ? AND ?",
+        )?
+        .query_map(params![vps_id, since, until], |row| {
+            Ok(ActivityEntry {
+                kind: ActivityKind::Command,
+                time: row.get(0)?,
+                status: row.get(1)?,
+                summary: row.get::<_, Option<String>>(2)?.unwrap_or_else(|| "command".to_string()),
+                actor: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+fn config_pushes(conn: &Connection, vps_id: i32, since: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<ActivityEntry>, AppError> {
+    let mut entries = conn
+        .prepare(
+            "SELECT t.updated_at, t.status, r.id, u.username
+             FROM config_rollout_targets t
+             JOIN config_rollouts r ON r.id = t.rollout_id
+             LEFT JOIN users u ON u.id = r.user_id
+             WHERE t.vps_id = ? AND t.updated_at BETWEEN ? AND ?",
+        )?
+        .query_map(params![vps_id, since, until], |row| {
+            Ok(ActivityEntry {
+                kind: ActivityKind::ConfigPush,
+                time: row.get(0)?,
+                status: row.get(1)?,
+                summary: format!("config rollout #{}", row.get::<_, i32>(2)?),
+                actor: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    // Direct pushes only leave their mark on the VPS row.
+    let direct = conn
+        .prepare(
+            "SELECT last_config_update_at, config_status FROM vps
+             WHERE id = ? AND last_config_update_at BETWEEN ? AND ?",
+        )?
+        .query_map(params![vps_id, since, until], |row| {
+            Ok(ActivityEntry {
+                kind: ActivityKind::ConfigPush,
+                time: row.get(0)?,
+                status: row.get(1)?,
+                summary: "agent config updated".to_string(),
+                actor: None,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    if !direct.iter().any(|d| entries.iter().any(|e| e.time == d.time)) {
+        entries.extend(direct);
+    }
+    Ok(entries)
+}
+
+fn maintenance(conn: &Connection, vps_id: i32, since: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<ActivityEntry>, AppError> {
+    Ok(conn
+        .prepare(
+            "SELECT time, action, reason, actor FROM vps_maintenance_events
+             WHERE vps_id = ? AND time BETWEEN ? AND ?",
+        )?
+        .query_map(params![vps_id, since, until], |row| {
+            let action: String = row.get(1)?;
+            let reason: Option<String> = row.get(2)?;
+            Ok(ActivityEntry {
+                kind: ActivityKind::Maintenance,
+                time: row.get(0)?,
+                summary: match reason {
+                    Some(reason) => format!("maintenance {action}: {reason}"),
+                    None => format!("maintenance {action}"),
+                },
+                actor: row.get(3)?,
+                status: None,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+fn changes(conn: &Connection, vps_id: i32, since: DateTime<Utc>, until: DateTime<Utc>) -> Result<Vec<ActivityEntry>, AppError> {
+    Ok(conn
+        .prepare(
+            "SELECT created_at, method, route, username, status_code FROM audit_log
+             WHERE entity_type = 'vps' AND entity_id = ? AND created_at BETWEEN ? AND ?",
+        )?
+        .query_map(params![vps_id.to_string(), since, until], |row| {
+            Ok(ActivityEntry {
+                kind: ActivityKind::Change,
+                time: row.get(0)?,
+                summary: format!("{} {}", row.get::<_, String>(1)?, row.get::<_, String>(2)?),
+                actor: row.get(3)?,
+                status: row.get::<_, Option<i32>>(4)?.map(|code| code.to_string()),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Plain-text section appended to alert notifications.
+pub fn format_for_notification(entries: &[ActivityEntry]) -> Option<String> {
+    if entries.is_empty() {
+        return None;
+    }
+    let mut text = String::from("Recent activity on this VPS:");
+    for entry in entries {
+        text.push_str(&format!(
+            "\n- {} {}: {}",
+            entry.time.format("%H:%M UTC"),
+            entry.kind.label(),
+            entry.summary
+        ));
+        if let Some(status) = &entry.status {
+            text.push_str(&format!(" [{status}]"));
+        }
+        if let Some(actor) = &entry.actor {
+            text.push_str(&format!(" by {actor}"));
+        }
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn entry(kind: ActivityKind, minute: u32, summary: &str) -> ActivityEntry {
+        ActivityEntry {
+            kind,
+            time: Utc.with_ymd_and_hms(2025, 8, 1, 12, minute, 0).unwrap(),
+            summary: summary.to_string(),
+            actor: None,
+            status: None,
+        }
+    }
+
+    #[test]
+    fn entries_are_sorted_newest_first_and_capped() {
+        let entries: Vec<_> = (0..30).map(|m| entry(ActivityKind::Change, m, "PUT /api/vps/1")).collect();
+        let sorted = newest_first(entries);
+        assert_eq!(sorted.len(), MAX_ENTRIES);
+        assert_eq!(sorted[0].time.format("%M").to_string(), "29");
+    }
+
+    #[test]
+    fn notification_text_lists_each_entry() {
+        assert_eq!(format_for_notification(&[]), None);
+        let mut command = entry(ActivityKind::Command, 5, "systemctl restart nginx");
+        command.status = Some("completed".to_string());
+        command.actor = Some("alice".to_string());
+        let text = format_for_notification(&[command, entry(ActivityKind::ConfigPush, 1, "config rollout #4")]).unwrap();
+        assert_eq!(
+            text,
+            "Recent activity on this VPS:\n- 12:05 UTC command: systemctl restart nginx [completed] by alice\n- 12:01 UTC config push: config rollout #4"
+        );
+    }
+}
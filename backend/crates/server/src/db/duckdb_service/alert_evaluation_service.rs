@@ -60,10 +60,23 @@ pub async fn get_performance_metrics(
     .await?
 }
 
-pub async fn get_all_vps_for_user(
+/// Active VPS a global rule applies to: those in the rule's organization.
+pub async fn get_all_vps_for_rule(
     pool: DuckDbPool,
-    user_id: i32,
+    rule_id: i32,
 ) -> Result<Vec<vps::Model>, AlertEvaluationDbError> {
-    let vps_list = vps_service::get_vps_by_user_id(pool, user_id).await?;
-    Ok(vps_list)
-}
\ No newline at end of file
+    task::spawn_blocking(move || {
+        let conn = pool.get()?;
+        let mut stmt = conn.prepare(
+            "SELECT * FROM vps
+             WHERE organization_id = (SELECT organization_id FROM alert_rules WHERE id = ?)
+               AND archived_at IS NULL
+             ORDER BY created_at DESC",
+        )?;
+        let vps_list = stmt
+            .query_map(params![rule_id], vps_service::row_to_vps_model)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(vps_list)
+    })
+    .await?
+}
@@ -0,0 +1,489 @@
+use chrono::{DateTime, Duration, Utc};
+use duckdb::{params, OptionalExt, Result as DuckDbResult, Row};
+use std::collections::HashMap;
+use tokio::sync::broadcast;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::db::duckdb_service::alert_correlation_service::ActivityEntry;
+use crate::db::duckdb_service::{json_from_row, organization_service, DuckDbPool};
+use crate::db::entities::alert_event;
+use crate::db::enums::AlertSeverity;
+use crate::web::error::AppError;
+use crate::web::models::alert_models::{AlertEventCount, AlertEventDetail, AlertEventState, AlertEventSummary};
+use crate::web::models::websocket_models::{AlertEventChange, AlertEventPush, WsMessage};
+
+/// VPS and rules listed in the summary.
+const SUMMARY_TOP_COUNT: usize = 10;
+
+/// An event together with the rule fields needed to act on it.
+#[derive(Debug, Clone)]
+pub struct AlertEventContext {
+    pub event: alert_event::Model,
+    pub user_id: i32,
+    /// The rule's organization, whose VPS its remediation may run on.
+    pub organization_id: Option<i32>,
+    pub rule_name: String,
+    pub remediation_script_id: Option<i32>,
+    pub vps_name: String,
+}
+
+#[derive(Debug, Default)]
+pub struct AlertEventFilter {
+    pub vps_id: Option<i32>,
+    pub rule_id: Option<i32>,
+    pub state: Option<AlertEventState>,
+    pub severity: Option<AlertSeverity>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+/// SQL condition on `alert_events e` matching events in `state`.
+fn state_condition(state: AlertEventState) -> &'static str {
+    match state {
+        AlertEventState::Open => "e.resolve_time IS NULL AND e.acknowledged_at IS NULL",
+        AlertEventState::Acknowledged => "e.resolve_time IS NULL AND e.acknowledged_at IS NOT NULL",
+        AlertEventState::Resolved => "e.resolve_time IS NOT NULL",
+    }
+}
+
+fn row_to_alert_event_model(row: &Row) -> DuckDbResult<alert_event::Model> {
+    Ok(alert_event::Model {
+        id: row.get("id")?,
+        rule_id: row.get("rule_id")?,
+        vps_id: row.get("vps_id")?,
+        trigger_time: row.get("trigger_time")?,
+        resolve_time: row.get("resolve_time")?,
+        details: row.get("details")?,
+        acknowledged_at: row.get("acknowledged_at")?,
+        acknowledged_by: row.get("acknowledged_by")?,
+        silenced_until: row.get("silenced_until")?,
+        remediation_batch_command_id: row.get("remediation_batch_command_id")?,
+        recent_activity: json_from_row(row, "recent_activity")?,
+        monitor_id: row.get("monitor_id")?,
+        severity: row.get("severity")?,
+    })
+}
+
+fn row_to_alert_event_detail(row: &Row) -> DuckDbResult<AlertEventDetail> {
+    let event = row_to_alert_event_model(row)?;
+    Ok(AlertEventDetail {
+        state: AlertEventState::of(&event),
+        event,
+        rule_name: row.get("rule_name")?,
+        vps_name: row.get("vps_name")?,
+    })
+}
+
+pub async fn create_alert_event(
+    pool: DuckDbPool,
+    rule_id: i32,
+    vps_id: i32,
+    details: &str,
+    recent_activity: &[ActivityEntry],
+) -> Result<alert_event::Model, AppError> {
+    let conn = pool.get()?;
+    let recent_activity = serde_json::to_string(recent_activity)?;
+    let event = conn.query_row(
+        "INSERT INTO alert_events (rule_id, vps_id, trigger_time, details, recent_activity, severity)
+         VALUES (?, ?, ?, ?, ?, (SELECT severity FROM alert_rules WHERE id = ?)) RETURNING *",
+        params![rule_id, vps_id, Utc::now(), details, recent_activity, rule_id],
+        row_to_alert_event_model,
+    )?;
+    Ok(event)
+}
+
+/// Records a `monitor_down` alert for a monitor on a VPS; it stays open until
+/// resolved with [`resolve_alert_event`].
+pub async fn create_monitor_alert_event(
+    pool: DuckDbPool,
+    rule_id: i32,
+    vps_id: i32,
+    monitor_id: i32,
+    details: &str,
+    recent_activity: &[ActivityEntry],
+) -> Result<alert_event::Model, AppError> {
+    let conn = pool.get()?;
+    let recent_activity = serde_json::to_string(recent_activity)?;
+    let event = conn.query_row(
+        "INSERT INTO alert_events (rule_id, vps_id, monitor_id, trigger_time, details, recent_activity, severity)
+         VALUES (?, ?, ?, ?, ?, ?, (SELECT severity FROM alert_rules WHERE id = ?)) RETURNING *",
+        params![rule_id, vps_id, monitor_id, Utc::now(), details, recent_activity, rule_id],
+        row_to_alert_event_model,
+    )?;
+    Ok(event)
+}
+
+/// Unresolved `monitor_down` events of a rule by monitor and VPS.
+pub async fn get_open_monitor_events(
+    pool: DuckDbPool,
+    rule_id: i32,
+) -> Result<HashMap<(i32, i32), alert_event::Model>, AppError> {
+    let conn = pool.get()?;
+    let events = conn
+        .prepare(
+            "SELECT * FROM alert_events
+             WHERE rule_id = ? AND monitor_id IS NOT NULL AND resolve_time IS NULL
+             ORDER BY trigger_time",
+        )?
+        .query_map(params![rule_id], row_to_alert_event_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(events
+        .into_iter()
+        .filter_map(|event| Some(((event.monitor_id?, event.vps_id), event)))
+        .collect())
+}
+
+pub async fn resolve_alert_event(pool: DuckDbPool, event_id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE alert_events SET resolve_time = ? WHERE id = ? AND resolve_time IS NULL",
+        params![Utc::now(), event_id],
+    )?;
+    Ok(())
+}
+
+/// One page of the events of the user's organization, newest first, plus the
+/// number of events matching the filter.
+pub async fn get_alert_event_page(
+    pool: DuckDbPool,
+    user_id: i32,
+    filter: &AlertEventFilter,
+    page: u32,
+    per_page: u32,
+) -> Result<(Vec<AlertEventDetail>, u64), AppError> {
+    let mut conditions = vec![organization_service::org_scope("r.organization_id")];
+    let mut values: Vec<Box<dyn duckdb::ToSql>> = vec![Box::new(user_id)];
+    if let Some(vps_id) = filter.vps_id {
+        conditions.push("e.vps_id = ?".to_string());
+        values.push(Box::new(vps_id));
+    }
+    if let Some(rule_id) = filter.rule_id {
+        conditions.push("e.rule_id = ?".to_string());
+        values.push(Box::new(rule_id));
+    }
+    if let Some(state) = filter.state {
+        conditions.push(state_condition(state).to_string());
+    }
+    if let Some(severity) = filter.severity {
+        conditions.push("e.severity = ?".to_string());
+        values.push(Box::new(severity));
+    }
+    if let Some(since) = filter.since {
+        conditions.push("e.trigger_time >= ?".to_string());
+        values.push(Box::new(since));
+    }
+    if let Some(until) = filter.until {
+        conditions.push("e.trigger_time < ?".to_string());
+        values.push(Box::new(until));
+    }
+    let where_clause = conditions.join(" AND ");
+
+    let conn = pool.get()?;
+    let total: i64 = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FROM alert_events e JOIN alert_rules r ON r.id = e.rule_id WHERE {where_clause}"
+        ),
+        duckdb::params_from_iter(values.iter()),
+        |row| row.get(0),
+    )?;
+
+    values.push(Box::new(per_page as i64));
+    values.push(Box::new(page.saturating_sub(1) as i64 * per_page as i64));
+    let items = conn
+        .prepare(&format!(
+            "SELECT e.*, r.name AS rule_name, v.name AS vps_name
+             FROM alert_events e
+             JOIN alert_rules r ON r.id = e.rule_id
+             JOIN vps v ON v.id = e.vps_id
+             WHERE {where_clause}
+             ORDER BY e.trigger_time DESC, e.id DESC LIMIT ? OFFSET ?"
+        ))?
+        .query_map(duckdb::params_from_iter(values.iter()), row_to_alert_event_detail)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((items, total as u64))
+}
+
+/// Unresolved events of the user's organization grouped by VPS (`v`) or by
+/// rule (`r`), most first.
+fn count_unresolved_by(
+    conn: &duckdb::Connection,
+    group_alias: &str,
+    user_id: i32,
+) -> Result<Vec<AlertEventCount>, AppError> {
+    let counts = conn
+        .prepare(&format!(
+            "SELECT {group_alias}.id, {group_alias}.name,
+                    COUNT(*) FILTER (WHERE e.acknowledged_at IS NULL),
+                    COUNT(*) FILTER (WHERE e.acknowledged_at IS NOT NULL)
+             FROM alert_events e
+             JOIN alert_rules r ON r.id = e.rule_id
+             JOIN vps v ON v.id = e.vps_id
+             WHERE e.resolve_time IS NULL AND {}
+             GROUP BY {group_alias}.id, {group_alias}.name
+             ORDER BY COUNT(*) DESC, {group_alias}.name
+             LIMIT {SUMMARY_TOP_COUNT}",
+            organization_service::org_scope("r.organization_id")
+        ))?
+        .query_map(params![user_id], |row| {
+            Ok(AlertEventCount {
+                id: row.get(0)?,
+                name: row.get(1)?,
+                open: row.get::<_, i64>(2)? as u64,
+                acknowledged: row.get::<_, i64>(3)? as u64,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(counts)
+}
+
+/// Counts of the events of the user's organization by state, and of the
+/// unresolved ones by VPS and by rule.
+pub async fn get_alert_event_summary(
+    pool: DuckDbPool,
+    user_id: i32,
+    now: DateTime<Utc>,
+) -> Result<AlertEventSummary, AppError> {
+    let conn = pool.get()?;
+    let (open, acknowledged, resolved_last_24h) = conn.query_row(
+        &format!(
+            "SELECT COUNT(*) FILTER (WHERE {}),
+                    COUNT(*) FILTER (WHERE {}),
+                    COUNT(*) FILTER (WHERE e.resolve_time >= ?)
+             FROM alert_events e
+             JOIN alert_rules r ON r.id = e.rule_id
+             WHERE {}",
+            state_condition(AlertEventState::Open),
+            state_condition(AlertEventState::Acknowledged),
+            organization_service::org_scope("r.organization_id")
+        ),
+        params![now - Duration::hours(24), user_id],
+        |row| Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, i64>(2)?)),
+    )?;
+    Ok(AlertEventSummary {
+        open: open as u64,
+        acknowledged: acknowledged as u64,
+        resolved_last_24h: resolved_last_24h as u64,
+        by_vps: count_unresolved_by(&conn, "v", user_id)?,
+        by_rule: count_unresolved_by(&conn, "r", user_id)?,
+    })
+}
+
+pub fn alert_event_push(change: AlertEventChange, context: &AlertEventContext) -> AlertEventPush {
+    let event = &context.event;
+    AlertEventPush {
+        change,
+        id: event.id,
+        rule_id: event.rule_id,
+        rule_name: context.rule_name.clone(),
+        vps_id: event.vps_id,
+        vps_name: context.vps_name.clone(),
+        monitor_id: event.monitor_id,
+        severity: event.severity,
+        trigger_time: event.trigger_time,
+        resolve_time: event.resolve_time,
+        acknowledged_at: event.acknowledged_at,
+        acknowledged_by: event.acknowledged_by.clone(),
+        silenced_until: event.silenced_until,
+        details: event.details.clone(),
+    }
+}
+
+/// Pushes an event that fired or changed to the dashboards.
+pub fn send_alert_event(
+    ws_data_broadcaster_tx: &broadcast::Sender<WsMessage>,
+    change: AlertEventChange,
+    context: &AlertEventContext,
+) {
+    // Without connected dashboards there is nobody to tell.
+    let _ = ws_data_broadcaster_tx.send(WsMessage::AlertEvent(alert_event_push(change, context)));
+}
+
+/// [`send_alert_event`] for an event that still has to be loaded. Errors only
+/// cost the push.
+pub async fn broadcast_alert_event(
+    pool: DuckDbPool,
+    ws_data_broadcaster_tx: &broadcast::Sender<WsMessage>,
+    event_id: i32,
+    change: AlertEventChange,
+) {
+    if ws_data_broadcaster_tx.receiver_count() == 0 {
+        return;
+    }
+    match get_alert_event_context(pool, event_id).await {
+        Ok(Some(context)) => send_alert_event(ws_data_broadcaster_tx, change, &context),
+        Ok(None) => {}
+        Err(e) => warn!(event_id, error = %e, "Failed to load alert event for broadcast."),
+    }
+}
+
+/// Whether a notification action silenced this rule for this VPS.
+pub async fn is_silenced(pool: DuckDbPool, rule_id: i32, vps_id: i32) -> Result<bool, AppError> {
+    let conn = pool.get()?;
+    let silenced = conn
+        .query_row(
+            "SELECT 1 FROM alert_events WHERE rule_id = ? AND vps_id = ? AND silenced_until > ? LIMIT 1",
+            params![rule_id, vps_id, Utc::now()],
+            |_| Ok(()),
+        )
+        .optional()?
+        .is_some();
+    Ok(silenced)
+}
+
+pub async fn get_alert_event_context(
+    pool: DuckDbPool,
+    event_id: i32,
+) -> Result<Option<AlertEventContext>, AppError> {
+    let conn = pool.get()?;
+    let context = conn
+        .query_row(
+            "SELECT e.*, r.user_id AS rule_user_id, r.organization_id AS rule_organization_id, r.name AS rule_name,
+                    r.remediation_script_id AS rule_remediation_script_id, v.name AS vps_name
+             FROM alert_events e
+             JOIN alert_rules r ON r.id = e.rule_id
+             JOIN vps v ON v.id = e.vps_id
+             WHERE e.id = ?",
+            params![event_id],
+            |row| {
+                Ok(AlertEventContext {
+                    event: row_to_alert_event_model(row)?,
+                    user_id: row.get("rule_user_id")?,
+                    organization_id: row.get("rule_organization_id")?,
+                    rule_name: row.get("rule_name")?,
+                    remediation_script_id: row.get("rule_remediation_script_id")?,
+                    vps_name: row.get("vps_name")?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(context)
+}
+
+/// Records the first acknowledgment; later ones leave it unchanged.
+pub async fn acknowledge_alert_event(
+    pool: DuckDbPool,
+    event_id: i32,
+    acknowledged_by: &str,
+) -> Result<alert_event::Model, AppError> {
+    let conn = pool.get()?;
+    let event = conn.query_row(
+        "UPDATE alert_events
+         SET acknowledged_at = COALESCE(acknowledged_at, ?), acknowledged_by = COALESCE(acknowledged_by, ?)
+         WHERE id = ? RETURNING *",
+        params![Utc::now(), acknowledged_by, event_id],
+        row_to_alert_event_model,
+    )?;
+    Ok(event)
+}
+
+/// Silencing also acknowledges the event.
+pub async fn silence_alert_event(
+    pool: DuckDbPool,
+    event_id: i32,
+    until: DateTime<Utc>,
+    acknowledged_by: &str,
+) -> Result<alert_event::Model, AppError> {
+    let conn = pool.get()?;
+    let event = conn.query_row(
+        "UPDATE alert_events
+         SET silenced_until = ?, acknowledged_at = COALESCE(acknowledged_at, ?),
+             acknowledged_by = COALESCE(acknowledged_by, ?)
+         WHERE id = ? RETURNING *",
+        params![until, Utc::now(), acknowledged_by, event_id],
+        row_to_alert_event_model,
+    )?;
+    Ok(event)
+}
+
+/// Claims the event for a remediation run. Only the first caller gets `true`,
+/// so concurrent clicks on the action link can't run the script twice.
+pub async fn claim_remediation(pool: DuckDbPool, event_id: i32) -> Result<bool, AppError> {
+    let conn = pool.get()?;
+    let claimed = conn.execute(
+        "UPDATE alert_events SET remediation_claimed_at = ?
+         WHERE id = ? AND remediation_claimed_at IS NULL AND remediation_batch_command_id IS NULL",
+        params![Utc::now(), event_id],
+    )?;
+    Ok(claimed == 1)
+}
+
+/// Releases a claim whose remediation could not be started, so it can be retried.
+pub async fn release_remediation(pool: DuckDbPool, event_id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE alert_events SET remediation_claimed_at = NULL WHERE id = ? AND remediation_batch_command_id IS NULL",
+        params![event_id],
+    )?;
+    Ok(())
+}
+
+pub async fn set_remediation_batch(
+    pool: DuckDbPool,
+    event_id: i32,
+    batch_command_id: Uuid,
+) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE alert_events SET remediation_batch_command_id = ? WHERE id = ?",
+        params![batch_command_id, event_id],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event() -> alert_event::Model {
+        alert_event::Model {
+            id: 1,
+            rule_id: 2,
+            vps_id: 3,
+            trigger_time: Utc::now(),
+            resolve_time: None,
+            details: Some("CPU above 90%".to_string()),
+            acknowledged_at: None,
+            acknowledged_by: None,
+            silenced_until: None,
+            remediation_batch_command_id: None,
+            recent_activity: None,
+            monitor_id: None,
+            severity: AlertSeverity::Critical,
+        }
+    }
+
+    #[test]
+    fn state_follows_resolve_then_acknowledge() {
+        let mut event = event();
+        assert_eq!(AlertEventState::of(&event), AlertEventState::Open);
+        event.acknowledged_at = Some(Utc::now());
+        assert_eq!(AlertEventState::of(&event), AlertEventState::Acknowledged);
+        event.resolve_time = Some(Utc::now());
+        assert_eq!(AlertEventState::of(&event), AlertEventState::Resolved);
+        // Unacknowledged events that resolved are resolved too.
+        event.acknowledged_at = None;
+        assert_eq!(AlertEventState::of(&event), AlertEventState::Resolved);
+    }
+
+    #[test]
+    fn push_carries_the_names_of_rule_and_vps() {
+        let context = AlertEventContext {
+            event: event(),
+            user_id: 1,
+            organization_id: Some(1),
+            rule_name: "CPU".to_string(),
+            remediation_script_id: None,
+            vps_name: "web-01".to_string(),
+        };
+        let push = alert_event_push(AlertEventChange::Fired, &context);
+        assert_eq!((push.id, push.rule_id, push.vps_id), (1, 2, 3));
+        assert_eq!(push.rule_name, "CPU");
+        assert_eq!(push.vps_name, "web-01");
+        let json = serde_json::to_value(WsMessage::AlertEvent(push)).unwrap();
+        assert_eq!(json["type"], "alert_event");
+        assert_eq!(json["data"]["change"], "fired");
+        assert_eq!(json["data"]["vpsName"], "web-01");
+        assert_eq!(json["data"]["severity"], "critical");
+    }
+}
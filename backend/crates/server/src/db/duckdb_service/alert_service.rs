@@ -1,33 +1,53 @@
 use chrono::Utc;
+use duckdb::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
 use duckdb::{params, Connection, Result as DuckDbResult, ToSql};
 use std::collections::HashMap;
+use std::str::FromStr;
 use tokio::task;
 
-use crate::db::duckdb_service::DuckDbPool;
+use crate::db::duckdb_service::{json_from_row, organization_service, DuckDbPool};
 use crate::db::entities::alert_rule;
+use crate::db::enums::AlertSeverity;
 use crate::db::models::AlertRule;
 use crate::web::error::AppError;
 use crate::web::models::alert_models::{CreateAlertRuleRequest, UpdateAlertRuleRequest};
 
+impl ToSql for AlertSeverity {
+    fn to_sql(&self) -> DuckDbResult<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for AlertSeverity {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        AlertSeverity::from_str(s).map_err(|_| FromSqlError::InvalidType)
+    }
+}
+
 pub async fn create_alert_rule(
     pool: DuckDbPool,
     user_id: i32,
     payload: CreateAlertRuleRequest,
 ) -> Result<AlertRule, AppError> {
     task::spawn_blocking(move || {
-        let mut conn = pool.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
-        let tx = conn.transaction().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let mut conn = pool.get().map_err(AppError::from)?;
+        let tx = conn.transaction().map_err(AppError::from)?;
 
         let cooldown_seconds = payload.cooldown_seconds.unwrap_or(300);
+        let evaluation_interval_seconds = payload.evaluation_interval_seconds.filter(|seconds| *seconds > 0);
         let now = Utc::now();
 
+        let organization_id = organization_service::active_organization_id(&tx, user_id)?;
+
         let new_rule_model = {
             let vps_id_val = payload.vps_id;
             let id: i32 = tx.query_row(
-                "INSERT INTO alert_rules (user_id, name, vps_id, metric_type, threshold, comparison_operator, duration_seconds, cooldown_seconds, is_active, created_at, updated_at)
-                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
+                "INSERT INTO alert_rules (user_id, organization_id, name, vps_id, metric_type, threshold, comparison_operator, duration_seconds, cooldown_seconds, is_active, created_at, updated_at, remediation_script_id, condition_expression, evaluation_interval_seconds, monitor_id, recovery_threshold, log_pattern, severity)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
                 params![
                     user_id,
+                    organization_id,
                     payload.name,
                     vps_id_val,
                     payload.metric_type,
@@ -38,9 +58,16 @@ pub async fn create_alert_rule(
                     true, // is_active
                     now,
                     now,
+                    payload.remediation_script_id,
+                    payload.condition_expression.as_ref().map(|e| e.to_string()),
+                    evaluation_interval_seconds,
+                    payload.monitor_id,
+                    payload.recovery_threshold,
+                    payload.log_pattern,
+                    payload.severity,
                 ],
                 |row| row.get(0)
-            ).map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            ).map_err(AppError::from)?;
 
             alert_rule::Model {
                 id,
@@ -56,6 +83,13 @@ pub async fn create_alert_rule(
                 cooldown_seconds,
                 created_at: now,
                 updated_at: now,
+                remediation_script_id: payload.remediation_script_id,
+                condition_expression: payload.condition_expression,
+                evaluation_interval_seconds,
+                monitor_id: payload.monitor_id,
+                recovery_threshold: payload.recovery_threshold,
+                log_pattern: payload.log_pattern,
+                severity: payload.severity,
             }
         };
 
@@ -67,7 +101,7 @@ pub async fn create_alert_rule(
             }
         }
 
-        tx.commit().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        tx.commit().map_err(AppError::from)?;
 
         Ok(AlertRule {
             id: new_rule_model.id,
@@ -84,6 +118,13 @@ pub async fn create_alert_rule(
             cooldown_seconds: new_rule_model.cooldown_seconds,
             created_at: new_rule_model.created_at,
             updated_at: new_rule_model.updated_at,
+            remediation_script_id: new_rule_model.remediation_script_id,
+            condition_expression: new_rule_model.condition_expression,
+            evaluation_interval_seconds: new_rule_model.evaluation_interval_seconds,
+            monitor_id: new_rule_model.monitor_id,
+            recovery_threshold: new_rule_model.recovery_threshold,
+            log_pattern: new_rule_model.log_pattern,
+            severity: new_rule_model.severity,
         })
     })
     .await
@@ -100,29 +141,36 @@ fn link_channels_to_rule(
     }
     let mut stmt = tx.prepare(
         "INSERT OR IGNORE INTO alert_rule_channels (alert_rule_id, channel_id) VALUES (?, ?)",
-    ).map_err(|e| AppError::DatabaseError(e.to_string()))?;
+    ).map_err(AppError::from)?;
 
     for &channel_id in channel_ids {
-        stmt.execute(params![rule_id, channel_id]).map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        stmt.execute(params![rule_id, channel_id]).map_err(AppError::from)?;
     }
     Ok(())
 }
 
 fn row_to_alert_rule_model(row: &duckdb::Row<'_>) -> DuckDbResult<alert_rule::Model> {
     Ok(alert_rule::Model {
-        id: row.get(0)?,
-        user_id: row.get(1)?,
-        name: row.get(2)?,
-        vps_id: row.get(3)?,
-        metric_type: row.get(4)?,
-        threshold: row.get(5)?,
-        comparison_operator: row.get(6)?,
-        duration_seconds: row.get(7)?,
-        is_active: row.get(8)?,
-        last_triggered_at: row.get(9)?,
-        cooldown_seconds: row.get(10)?,
-        created_at: row.get(11)?,
-        updated_at: row.get(12)?,
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        name: row.get("name")?,
+        vps_id: row.get("vps_id")?,
+        metric_type: row.get("metric_type")?,
+        threshold: row.get("threshold")?,
+        comparison_operator: row.get("comparison_operator")?,
+        duration_seconds: row.get("duration_seconds")?,
+        is_active: row.get("is_active")?,
+        last_triggered_at: row.get("last_triggered_at")?,
+        cooldown_seconds: row.get("cooldown_seconds")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+        remediation_script_id: row.get("remediation_script_id")?,
+        condition_expression: json_from_row(row, "condition_expression")?,
+        evaluation_interval_seconds: row.get("evaluation_interval_seconds")?,
+        monitor_id: row.get("monitor_id")?,
+        recovery_threshold: row.get("recovery_threshold")?,
+        log_pattern: row.get("log_pattern")?,
+        severity: row.get("severity")?,
     })
 }
 
@@ -131,16 +179,16 @@ pub async fn get_all_alert_rules_for_user(
     user_id: i32,
 ) -> Result<Vec<AlertRule>, AppError> {
     task::spawn_blocking(move || {
-        let conn = pool.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let conn = pool.get().map_err(AppError::from)?;
         let mut stmt = conn
-            .prepare("SELECT * FROM alert_rules WHERE user_id = ? ORDER BY name ASC")
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            .prepare(&format!("SELECT * FROM alert_rules WHERE {} ORDER BY name ASC", organization_service::org_scope("organization_id")))
+            .map_err(AppError::from)?;
 
         let rule_models = stmt
             .query_map(params![user_id], row_to_alert_rule_model)
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?
+            .map_err(AppError::from)?
             .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            .map_err(AppError::from)?;
 
         if rule_models.is_empty() {
             return Ok(Vec::new());
@@ -166,6 +214,14 @@ pub async fn get_all_alert_rules_for_user(
                 cooldown_seconds: rule_model.cooldown_seconds,
                 created_at: rule_model.created_at,
                 updated_at: rule_model.updated_at,
+                remediation_script_id: rule_model.remediation_script_id,
+                condition_expression: rule_model.condition_expression,
+                evaluation_interval_seconds: rule_model.evaluation_interval_seconds,
+                monitor_id: rule_model.monitor_id,
+                recovery_threshold: rule_model.recovery_threshold,
+            log_pattern: rule_model.log_pattern,
+                log_pattern: rule_model.log_pattern,
+                severity: rule_model.severity,
             })
             .collect();
 
@@ -181,10 +237,10 @@ pub async fn get_alert_rule_by_id_for_user(
     user_id: i32,
 ) -> Result<AlertRule, AppError> {
     task::spawn_blocking(move || {
-        let conn = pool.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let conn = pool.get().map_err(AppError::from)?;
         let mut stmt = conn
-            .prepare("SELECT * FROM alert_rules WHERE id = ? AND user_id = ?")
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            .prepare(&format!("SELECT * FROM alert_rules WHERE id = ? AND {}", organization_service::org_scope("organization_id")))
+            .map_err(AppError::from)?;
 
         let rule_model = stmt
             .query_row(params![rule_id, user_id], row_to_alert_rule_model)
@@ -212,6 +268,12 @@ pub async fn get_alert_rule_by_id_for_user(
             cooldown_seconds: rule_model.cooldown_seconds,
             created_at: rule_model.created_at,
             updated_at: rule_model.updated_at,
+            remediation_script_id: rule_model.remediation_script_id,
+            condition_expression: rule_model.condition_expression,
+            evaluation_interval_seconds: rule_model.evaluation_interval_seconds,
+            monitor_id: rule_model.monitor_id,
+            recovery_threshold: rule_model.recovery_threshold,
+            severity: rule_model.severity,
         })
     })
     .await
@@ -221,13 +283,13 @@ pub async fn get_alert_rule_by_id_for_user(
 fn get_linked_channel_ids_sync(conn: &Connection, rule_id: i32) -> Result<Vec<i32>, AppError> {
     let mut stmt = conn
         .prepare("SELECT channel_id FROM alert_rule_channels WHERE alert_rule_id = ?")
-        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        .map_err(AppError::from)?;
 
     let channel_ids = stmt
         .query_map(params![rule_id], |row| row.get(0))
-        .map_err(|e| AppError::DatabaseError(e.to_string()))?
+        .map_err(AppError::from)?
         .collect::<Result<Vec<i32>, _>>()
-        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        .map_err(AppError::from)?;
 
     Ok(channel_ids)
 }
@@ -253,16 +315,16 @@ fn get_linked_channels_for_rules_sync(
 
     let mut stmt = conn
         .prepare(&sql)
-        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        .map_err(AppError::from)?;
 
     let mut map: HashMap<i32, Vec<i32>> = HashMap::new();
     let rows = stmt
         .query_map(&params_vec[..], |row| Ok((row.get(0)?, row.get(1)?)))
-        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        .map_err(AppError::from)?;
 
     for row in rows {
         let (rule_id, channel_id): (i32, i32) =
-            row.map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            row.map_err(AppError::from)?;
         map.entry(rule_id).or_default().push(channel_id);
     }
 
@@ -277,8 +339,8 @@ pub async fn update_alert_rule(
 ) -> Result<AlertRule, AppError> {
     let pool_clone = pool.clone();
     task::spawn_blocking(move || {
-        let mut conn = pool_clone.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
-        let tx = conn.transaction().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let mut conn = pool_clone.get().map_err(AppError::from)?;
+        let tx = conn.transaction().map_err(AppError::from)?;
 
         let mut set_clauses: Vec<String> = Vec::new();
         let mut params_vec: Vec<&dyn ToSql> = Vec::new();
@@ -311,6 +373,41 @@ pub async fn update_alert_rule(
             set_clauses.push("cooldown_seconds = ?".to_string());
             params_vec.push(cooldown_seconds);
         }
+        if let Some(remediation_script_id) = &payload.remediation_script_id {
+            set_clauses.push("remediation_script_id = ?".to_string());
+            params_vec.push(remediation_script_id);
+        }
+        let evaluation_interval_seconds = payload.evaluation_interval_seconds.filter(|seconds| *seconds > 0);
+        if let Some(seconds) = &evaluation_interval_seconds {
+            set_clauses.push("evaluation_interval_seconds = ?".to_string());
+            params_vec.push(seconds);
+        } else if payload.evaluation_interval_seconds.is_some() {
+            set_clauses.push("evaluation_interval_seconds = NULL".to_string());
+        }
+        if let Some(monitor_id) = &payload.monitor_id {
+            set_clauses.push("monitor_id = ?".to_string());
+            params_vec.push(monitor_id);
+        }
+        if let Some(recovery_threshold) = &payload.recovery_threshold {
+            set_clauses.push("recovery_threshold = ?".to_string());
+            params_vec.push(recovery_threshold);
+        }
+        if let Some(log_pattern) = &payload.log_pattern {
+            set_clauses.push("log_pattern = ?".to_string());
+            params_vec.push(log_pattern);
+        }
+        if let Some(severity) = &payload.severity {
+            set_clauses.push("severity = ?".to_string());
+            params_vec.push(severity);
+        }
+        // Switching to a plain metric clears the expression.
+        let condition_expression = payload.condition_expression.as_ref().map(|e| e.to_string());
+        if let Some(expression) = &condition_expression {
+            set_clauses.push("condition_expression = ?".to_string());
+            params_vec.push(expression);
+        } else if payload.metric_type.is_some() {
+            set_clauses.push("condition_expression = NULL".to_string());
+        }
 
         if !set_clauses.is_empty() {
             let now = Utc::now();
@@ -318,30 +415,31 @@ pub async fn update_alert_rule(
             params_vec.push(&now);
 
             let sql = format!(
-                "UPDATE alert_rules SET {} WHERE id = ? AND user_id = ?",
-                set_clauses.join(", ")
+                "UPDATE alert_rules SET {} WHERE id = ? AND {}",
+                set_clauses.join(", "),
+                organization_service::org_scope("organization_id")
             );
             
             let mut final_params = params_vec;
             final_params.push(&rule_id);
             final_params.push(&user_id);
 
-            let num_updated = tx.execute(&sql, &final_params[..]).map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            let num_updated = tx.execute(&sql, &final_params[..]).map_err(AppError::from)?;
 
             if num_updated == 0 {
-                return Err(AppError::NotFound("Alert rule not found or not owned by user".to_string()));
+                return Err(AppError::NotFound("Alert rule not found".to_string()));
             }
         }
 
         if let Some(channel_ids) = &payload.notification_channel_ids {
             tx.execute("DELETE FROM alert_rule_channels WHERE alert_rule_id = ?", params![rule_id])
-                .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+                .map_err(AppError::from)?;
             if !channel_ids.is_empty() {
                 link_channels_to_rule(&tx, rule_id, channel_ids)?;
             }
         }
 
-        tx.commit().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        tx.commit().map_err(AppError::from)?;
         Ok(())
     })
     .await
@@ -352,15 +450,15 @@ pub async fn update_alert_rule(
 
 pub async fn delete_alert_rule(pool: DuckDbPool, rule_id: i32, user_id: i32) -> Result<(), AppError> {
     task::spawn_blocking(move || {
-        let conn = pool.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let conn = pool.get().map_err(AppError::from)?;
         let rows_affected = conn.execute(
-            "DELETE FROM alert_rules WHERE id = ? AND user_id = ?",
+            &format!("DELETE FROM alert_rules WHERE id = ? AND {}", organization_service::org_scope("organization_id")),
             params![rule_id, user_id],
-        ).map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        ).map_err(AppError::from)?;
 
         if rows_affected == 0 {
             Err(AppError::NotFound(
-                "Alert rule not found or not owned by user".to_string(),
+                "Alert rule not found".to_string(),
             ))
         } else {
             Ok(())
@@ -374,15 +472,15 @@ pub async fn get_all_active_rules_for_evaluation(
     pool: DuckDbPool,
 ) -> Result<Vec<alert_rule::Model>, AppError> {
     task::spawn_blocking(move || {
-        let conn = pool.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let conn = pool.get().map_err(AppError::from)?;
         let mut stmt = conn
             .prepare("SELECT * FROM alert_rules WHERE is_active = true ORDER BY id ASC")
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            .map_err(AppError::from)?;
 
         stmt.query_map([], row_to_alert_rule_model)
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?
+            .map_err(AppError::from)?
             .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| AppError::DatabaseError(e.to_string()))
+            .map_err(AppError::from)
     })
     .await
     .map_err(|e| AppError::InternalServerError(e.to_string()))?
@@ -394,15 +492,15 @@ pub async fn update_alert_rule_last_triggered(
     user_id: i32,
 ) -> Result<(), AppError> {
     task::spawn_blocking(move || {
-        let conn = pool.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let conn = pool.get().map_err(AppError::from)?;
         let rows_affected = conn.execute(
             "UPDATE alert_rules SET last_triggered_at = ?, updated_at = ? WHERE id = ? AND user_id = ?",
             params![Utc::now(), Utc::now(), rule_id, user_id],
         )
-        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        .map_err(AppError::from)?;
 
         if rows_affected == 0 {
-            Err(AppError::NotFound("Alert rule not found or not owned by user".to_string()))
+            Err(AppError::NotFound("Alert rule not found".to_string()))
         } else {
             Ok(())
         }
@@ -419,15 +517,15 @@ pub async fn update_alert_rule_status(
 ) -> Result<AlertRule, AppError> {
     let pool_clone = pool.clone();
     task::spawn_blocking(move || {
-        let conn = pool_clone.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let conn = pool_clone.get().map_err(AppError::from)?;
         let rows_affected = conn.execute(
-            "UPDATE alert_rules SET is_active = ?, updated_at = ? WHERE id = ? AND user_id = ?",
+            &format!("UPDATE alert_rules SET is_active = ?, updated_at = ? WHERE id = ? AND {}", organization_service::org_scope("organization_id")),
             params![is_active, Utc::now(), rule_id, user_id],
-        ).map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        ).map_err(AppError::from)?;
 
         if rows_affected == 0 {
             return Err(AppError::NotFound(
-                "Alert rule not found or not owned by user".to_string(),
+                "Alert rule not found".to_string(),
             ));
         }
         Ok(())
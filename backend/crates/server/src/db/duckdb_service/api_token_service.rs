@@ -0,0 +1,159 @@
+use axum::http::Method;
+use chrono::{DateTime, Utc};
+use duckdb::{params, OptionalExt, Result as DuckDbResult, Row};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::db::duckdb_service::{json_from_row, DuckDbPool};
+use crate::db::entities::api_token;
+use crate::web::error::AppError;
+
+/// Prefix of personal access tokens, told apart from JWTs in `Authorization: Bearer`.
+pub const TOKEN_PREFIX: &str = "nn_";
+pub const SCOPE_READ: &str = "read";
+pub const SCOPE_WRITE: &str = "write";
+
+/// Characters of the token kept in plain text for display.
+const DISPLAY_PREFIX_LEN: usize = 10;
+
+fn row_to_api_token_model(row: &Row) -> DuckDbResult<api_token::Model> {
+    Ok(api_token::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        name: row.get("name")?,
+        token_prefix: row.get("token_prefix")?,
+        scopes: json_from_row(row, "scopes")?
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default(),
+        expires_at: row.get("expires_at")?,
+        created_at: row.get("created_at")?,
+        last_used_at: row.get("last_used_at")?,
+    })
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
+
+/// Whether a token with `scopes` may make a request with `method`. "write"
+/// implies "read".
+pub fn scopes_allow(scopes: &[String], method: &Method) -> bool {
+    let has = |scope: &str| scopes.iter().any(|s| s == scope);
+    if has(SCOPE_WRITE) {
+        return true;
+    }
+    has(SCOPE_READ) && matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+fn validate_scopes(scopes: &[String]) -> Result<(), AppError> {
+    if scopes.is_empty() {
+        return Err(AppError::InvalidInput("At least one scope is required".to_string()));
+    }
+    if let Some(unknown) = scopes.iter().find(|s| *s != SCOPE_READ && *s != SCOPE_WRITE) {
+        return Err(AppError::InvalidInput(format!(
+            "Unknown scope '{unknown}'; expected '{SCOPE_READ}' or '{SCOPE_WRITE}'"
+        )));
+    }
+    Ok(())
+}
+
+/// Creates a token and returns it along with its plain text value, which is
+/// never available again.
+pub async fn create_api_token(
+    pool: DuckDbPool,
+    user_id: i32,
+    name: &str,
+    scopes: &[String],
+    expires_at: Option<DateTime<Utc>>,
+) -> Result<(api_token::Model, String), AppError> {
+    validate_scopes(scopes)?;
+    let token = format!("{TOKEN_PREFIX}{}", hex::encode(rand::random::<[u8; 24]>()));
+    let conn = pool.get()?;
+    let model = conn.query_row(
+        "INSERT INTO api_tokens (user_id, name, token_hash, token_prefix, scopes, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?) RETURNING *",
+        params![
+            user_id,
+            name,
+            hash_token(&token),
+            &token[..DISPLAY_PREFIX_LEN],
+            serde_json::to_string(scopes)?,
+            expires_at,
+        ],
+        row_to_api_token_model,
+    )?;
+    Ok((model, token))
+}
+
+pub async fn get_api_tokens_for_user(pool: DuckDbPool, user_id: i32) -> Result<Vec<api_token::Model>, AppError> {
+    let conn = pool.get()?;
+    let tokens = conn
+        .prepare("SELECT * FROM api_tokens WHERE user_id = ? ORDER BY created_at DESC")?
+        .query_map(params![user_id], row_to_api_token_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tokens)
+}
+
+pub async fn delete_api_token(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute("DELETE FROM api_tokens WHERE id = ? AND user_id = ?", params![id, user_id])?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("API token not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Looks up a presented, unexpired token and records its use.
+pub async fn authenticate_api_token(pool: DuckDbPool, token: &str) -> Result<Option<api_token::Model>, AppError> {
+    if !token.starts_with(TOKEN_PREFIX) {
+        return Ok(None);
+    }
+    let conn = pool.get()?;
+    let now = Utc::now();
+    let model = conn
+        .query_row(
+            "SELECT * FROM api_tokens WHERE token_hash = ? AND (expires_at IS NULL OR expires_at > ?)",
+            params![hash_token(token), now],
+            row_to_api_token_model,
+        )
+        .optional()?;
+    if let Some(model) = &model {
+        // Best effort: a read-only database must not lock scripts out.
+        if let Err(e) = conn.execute("UPDATE api_tokens SET last_used_at = ? WHERE id = ?", params![now, model.id]) {
+            warn!(token_id = model.id, error = %e, "Failed to record API token use.");
+        }
+    }
+    Ok(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scopes(values: &[&str]) -> Vec<String> {
+        values.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn read_scope_only_allows_safe_methods() {
+        let read = scopes(&[SCOPE_READ]);
+        assert!(scopes_allow(&read, &Method::GET));
+        assert!(!scopes_allow(&read, &Method::POST));
+        assert!(!scopes_allow(&read, &Method::DELETE));
+    }
+
+    #[test]
+    fn write_scope_implies_read() {
+        let write = scopes(&[SCOPE_WRITE]);
+        assert!(scopes_allow(&write, &Method::GET));
+        assert!(scopes_allow(&write, &Method::POST));
+        assert!(!scopes_allow(&[], &Method::GET));
+    }
+
+    #[test]
+    fn unknown_scopes_are_rejected() {
+        assert!(validate_scopes(&scopes(&["read", "admin"])).is_err());
+        assert!(validate_scopes(&[]).is_err());
+        assert!(validate_scopes(&scopes(&["read", "write"])).is_ok());
+    }
+}
@@ -0,0 +1,339 @@
+//! Archival of aged event rows to Parquet.
+//!
+//! Rows older than the configured retention window are exported with DuckDB's
+//! `COPY ... TO ... (FORMAT PARQUET)` and then removed from the live tables.
+//! Files are laid out as `<archive_dir>/<table>/<from>_<to>_<archived_at>.parquet`,
+//! so range queries only have to open the files that overlap the requested window.
+//! `archive_dir` may be a local directory or an `s3://bucket/prefix` URL.
+
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
+use crate::web::error::AppError;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use duckdb::{params, Connection, OptionalExt, ToSql};
+use serde::Serialize;
+use tracing::{debug, info};
+
+const FILE_TIME_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// How rows of an archived table are attributed to a user.
+#[derive(Debug, Clone, Copy)]
+pub enum ArchiveOwner {
+    /// The table carries the owning user in the given column.
+    User(&'static str),
+    /// The table references a VPS in the given column; the rows belong to the
+    /// organization the VPS is in now (`vps.organization_id`).
+    Vps(&'static str),
+    /// Rows aren't owned by a user; only admins may read the archive.
+    Admin,
+}
+
+/// A table whose aged rows are moved to Parquet.
+#[derive(Debug, Clone, Copy)]
+pub struct ArchiveTarget {
+    pub table: &'static str,
+    pub time_column: &'static str,
+    pub owner: ArchiveOwner,
+    /// Eligibility condition. `{cutoff}` is replaced with the cutoff timestamp literal.
+    pub filter: &'static str,
+}
+
+/// Tables are processed in order. Child command tasks go before their parent
+/// batches because their eligibility is derived from the batch rows.
+/// Tables that don't exist in the current schema are skipped.
+pub const ARCHIVE_TARGETS: &[ArchiveTarget] = &[
+    ArchiveTarget {
+        table: "alert_events",
+        time_column: "trigger_time",
+        owner: ArchiveOwner::Vps("vps_id"),
+        filter: "trigger_time < {cutoff}",
+    },
+    ArchiveTarget {
+        table: "child_command_tasks",
+        time_column: "created_at",
+        owner: ArchiveOwner::Vps("vps_id"),
+        filter: "batch_command_id IN (SELECT batch_command_id FROM batch_command_tasks WHERE completed_at < {cutoff})",
+    },
+    ArchiveTarget {
+        table: "batch_command_tasks",
+        time_column: "completed_at",
+        owner: ArchiveOwner::User("user_id"),
+        filter: "completed_at < {cutoff}",
+    },
+    ArchiveTarget {
+        table: "audit_log",
+        time_column: "created_at",
+        owner: ArchiveOwner::Admin,
+        filter: "created_at < {cutoff}",
+    },
+];
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchivedRange {
+    pub table: String,
+    /// Location of the file; internal, never sent to clients.
+    #[serde(skip)]
+    pub path: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+fn find_target(table: &str) -> Option<&'static ArchiveTarget> {
+    ARCHIVE_TARGETS.iter().find(|t| t.table == table)
+}
+
+fn is_remote(archive_dir: &str) -> bool {
+    archive_dir.starts_with("s3://")
+}
+
+fn sql_string(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn archive_file_name(from: DateTime<Utc>, to: DateTime<Utc>, archived_at: DateTime<Utc>) -> String {
+    format!(
+        "{}_{}_{}.parquet",
+        from.format(FILE_TIME_FORMAT),
+        to.format(FILE_TIME_FORMAT),
+        archived_at.format(FILE_TIME_FORMAT)
+    )
+}
+
+/// Extracts the `(from, to)` range encoded in an archive file name.
+fn parse_archive_file_name(path: &str) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+    let file_name = path.rsplit(['/', '\\']).next()?;
+    let stem = file_name.strip_suffix(".parquet")?;
+    let mut parts = stem.split('_');
+    let from = NaiveDateTime::parse_from_str(parts.next()?, FILE_TIME_FORMAT).ok()?;
+    let to = NaiveDateTime::parse_from_str(parts.next()?, FILE_TIME_FORMAT).ok()?;
+    Some((from.and_utc(), to.and_utc()))
+}
+
+/// Installs and loads the extensions and credentials needed for remote archive
+/// locations. Connections of a pool share one database instance, so this runs
+/// once at startup on the main pool, and again before each archival run in
+/// case that failed.
+pub fn prepare_archive_location(conn: &Connection, archive_dir: &str) -> Result<(), duckdb::Error> {
+    if is_remote(archive_dir) {
+        conn.execute_batch(
+            "INSTALL httpfs; LOAD httpfs; INSTALL aws; LOAD aws;
+             CREATE SECRET IF NOT EXISTS nodenexus_archive (TYPE S3, PROVIDER CREDENTIAL_CHAIN);",
+        )?;
+    }
+    Ok(())
+}
+
+fn table_exists(conn: &Connection, table: &str) -> Result<bool, duckdb::Error> {
+    let exists = conn
+        .query_row(
+            "SELECT 1 FROM duckdb_tables() WHERE table_name = ?",
+            params![table],
+            |_| Ok(()),
+        )
+        .optional()?;
+    Ok(exists.is_some())
+}
+
+/// Exports rows older than `retention_days` to Parquet and deletes them afterwards.
+/// Each table is handled in its own transaction so a failed export never drops rows.
+/// Returns the number of rows archived.
+pub fn archive_expired_rows(
+    conn: &Connection,
+    archive_dir: &str,
+    retention_days: u32,
+) -> Result<usize, AppError> {
+    if retention_days == 0 {
+        debug!("Event archival is disabled (retention_days = 0).");
+        return Ok(0);
+    }
+
+    let now = Utc::now();
+    let cutoff = now - Duration::days(retention_days as i64);
+    let cutoff_sql = format!("TIMESTAMPTZ {}", sql_string(&cutoff.to_rfc3339()));
+    prepare_archive_location(conn, archive_dir)?;
+
+    let mut total = 0;
+    for target in ARCHIVE_TARGETS {
+        if !table_exists(conn, target.table)? {
+            continue;
+        }
+
+        let filter = target.filter.replace("{cutoff}", &cutoff_sql);
+        let (count, from, to): (i64, Option<DateTime<Utc>>, Option<DateTime<Utc>>) = conn.query_row(
+            &format!(
+                "SELECT COUNT(*), MIN({tc}), MAX({tc}) FROM {table} WHERE {filter}",
+                tc = target.time_column,
+                table = target.table,
+            ),
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        if count == 0 {
+            continue;
+        }
+
+        let table_dir = format!("{}/{}", archive_dir.trim_end_matches('/'), target.table);
+        if !is_remote(archive_dir) {
+            std::fs::create_dir_all(&table_dir).map_err(|e| {
+                AppError::InternalServerError(format!("Failed to create archive directory {table_dir}: {e}"))
+            })?;
+        }
+        let path = format!(
+            "{table_dir}/{}",
+            archive_file_name(from.unwrap_or(cutoff), to.unwrap_or(cutoff), now)
+        );
+
+        conn.execute_batch("BEGIN TRANSACTION;")?;
+        let result = (|| {
+            conn.execute(
+                &format!(
+                    "COPY (SELECT * FROM {table} WHERE {filter}) TO {path} (FORMAT PARQUET)",
+                    table = target.table,
+                    path = sql_string(&path),
+                ),
+                [],
+            )?;
+            conn.execute(&format!("DELETE FROM {} WHERE {filter}", target.table), [])
+        })();
+
+        match result {
+            Ok(deleted) => {
+                conn.execute_batch("COMMIT;")?;
+                info!(table = target.table, rows = deleted, path = %path, "Archived aged rows to Parquet.");
+                total += deleted;
+            }
+            Err(e) => {
+                conn.execute_batch("ROLLBACK;")?;
+                return Err(e.into());
+            }
+        }
+    }
+
+    Ok(total)
+}
+
+fn list_ranges_for_table(
+    conn: &Connection,
+    archive_dir: &str,
+    table: &str,
+) -> Result<Vec<ArchivedRange>, duckdb::Error> {
+    let pattern = format!("{}/{}/*.parquet", archive_dir.trim_end_matches('/'), table);
+    let files: Vec<String> = conn
+        .prepare(&format!("SELECT file FROM glob({})", sql_string(&pattern)))?
+        .query_map([], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut ranges: Vec<ArchivedRange> = files
+        .into_iter()
+        .filter_map(|path| {
+            parse_archive_file_name(&path).map(|(from, to)| ArchivedRange {
+                table: table.to_string(),
+                path,
+                from,
+                to,
+            })
+        })
+        .collect();
+    ranges.sort_by_key(|r| r.from);
+    Ok(ranges)
+}
+
+/// Lists archived files and the time range each one covers.
+pub async fn list_archived_ranges(
+    pool: DuckDbPool,
+    archive_dir: String,
+) -> Result<Vec<ArchivedRange>, AppError> {
+    tokio::task::spawn_blocking(move || -> Result<_, AppError> {
+        let conn = pool.get()?;
+        let mut ranges = Vec::new();
+        for target in ARCHIVE_TARGETS {
+            ranges.extend(list_ranges_for_table(&conn, &archive_dir, target.table)?);
+        }
+        Ok(ranges)
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+}
+
+/// Reads archived rows of `table` within `[start_time, end_time]` that belong to `user_id`.
+/// Only the files whose encoded range overlaps the window are opened.
+///
+/// `pool` must be the main pool: ownership is resolved against its `vps` rows,
+/// and it is the one prepared for remote archive locations at startup.
+#[allow(clippy::too_many_arguments)]
+pub async fn query_archived_rows(
+    pool: DuckDbPool,
+    archive_dir: String,
+    table: String,
+    user_id: i32,
+    is_admin: bool,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    limit: u32,
+) -> Result<Vec<serde_json::Value>, AppError> {
+    let target = find_target(&table)
+        .ok_or_else(|| AppError::NotFound(format!("No archive for table '{table}'")))?;
+    if matches!(target.owner, ArchiveOwner::Admin) && !is_admin {
+        return Err(AppError::Forbidden(format!("Only admins can read the '{table}' archive")));
+    }
+
+    tokio::task::spawn_blocking(move || -> Result<_, AppError> {
+        let conn = pool.get()?;
+        let files: Vec<String> = list_ranges_for_table(&conn, &archive_dir, target.table)?
+            .into_iter()
+            .filter(|r| r.from <= end_time && r.to >= start_time)
+            .map(|r| sql_string(&r.path))
+            .collect();
+        if files.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let owner_filter = match target.owner {
+            ArchiveOwner::User(column) => format!("{column} = ?"),
+            ArchiveOwner::Vps(column) => {
+                format!("{column} IN (SELECT id FROM vps WHERE {})", organization_service::org_scope("organization_id"))
+            }
+            ArchiveOwner::Admin => "TRUE".to_string(),
+        };
+        let sql = format!(
+            "SELECT to_json(a)::VARCHAR FROM read_parquet([{files}], union_by_name = true) a
+             WHERE {tc} >= ? AND {tc} <= ? AND {owner_filter}
+             ORDER BY {tc} DESC
+             LIMIT ?",
+            files = files.join(", "),
+            tc = target.time_column,
+        );
+
+        let mut values: Vec<&dyn ToSql> = vec![&start_time, &end_time];
+        if !matches!(target.owner, ArchiveOwner::Admin) {
+            values.push(&user_id);
+        }
+        values.push(&limit);
+        let rows: Vec<String> = conn
+            .prepare(&sql)?
+            .query_map(values.as_slice(), |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+
+        rows.iter()
+            .map(|s| serde_json::from_str(s).map_err(AppError::from))
+            .collect()
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn archive_file_name_round_trips() {
+        let from = Utc.with_ymd_and_hms(2025, 1, 2, 3, 4, 5).unwrap();
+        let to = Utc.with_ymd_and_hms(2025, 2, 3, 4, 5, 6).unwrap();
+        let name = archive_file_name(from, to, Utc::now());
+        let path = format!("s3://bucket/archive/alert_events/{name}");
+        assert_eq!(parse_archive_file_name(&path), Some((from, to)));
+        assert_eq!(parse_archive_file_name("archive/alert_events/notes.txt"), None);
+    }
+}
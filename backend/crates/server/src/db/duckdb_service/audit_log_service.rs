@@ -0,0 +1,196 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, Result as DuckDbResult, Row};
+
+use crate::db::duckdb_service::{json_from_row, DuckDbPool};
+use crate::db::entities::audit_log;
+use crate::web::error::AppError;
+
+/// Summaries longer than this are stored as a truncated preview.
+const MAX_SUMMARY_CHARS: usize = 4000;
+
+/// Object keys whose values never reach the audit log.
+const SENSITIVE_KEY_PARTS: &[&str] = &["password", "secret", "token", "key", "code", "credential"];
+
+/// A mutation to record; see [`crate::web::middleware::audit`].
+#[derive(Debug)]
+pub struct NewAuditEntry {
+    pub user_id: i32,
+    pub username: String,
+    pub organization_id: Option<i32>,
+    pub method: String,
+    pub route: String,
+    pub entity_type: String,
+    pub entity_id: Option<String>,
+    pub status_code: u16,
+    pub before_summary: Option<serde_json::Value>,
+    pub after_summary: Option<serde_json::Value>,
+    pub ip_address: Option<String>,
+    pub request_id: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct AuditLogFilter {
+    pub user_id: Option<i32>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub method: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+}
+
+fn row_to_audit_log_model(row: &Row) -> DuckDbResult<audit_log::Model> {
+    Ok(audit_log::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        username: row.get("username")?,
+        organization_id: row.get("organization_id")?,
+        method: row.get("method")?,
+        route: row.get("route")?,
+        entity_type: row.get("entity_type")?,
+        entity_id: row.get("entity_id")?,
+        status_code: row.get("status_code")?,
+        before_summary: json_from_row(row, "before_summary")?,
+        after_summary: json_from_row(row, "after_summary")?,
+        ip_address: row.get("ip_address")?,
+        request_id: row.get("request_id")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+/// Replaces the values of credential-like keys, at any depth.
+pub fn redact(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(map) => {
+            for (key, child) in map.iter_mut() {
+                let key = key.to_ascii_lowercase();
+                if SENSITIVE_KEY_PARTS.iter().any(|part| key.contains(part)) {
+                    *child = serde_json::Value::String("[redacted]".to_string());
+                } else {
+                    redact(child);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// Serializes a summary for storage. Oversized summaries become a preview
+/// object so the column always holds valid JSON.
+fn summary_to_column(value: Option<serde_json::Value>) -> Result<Option<String>, AppError> {
+    let Some(value) = value else {
+        return Ok(None);
+    };
+    let text = serde_json::to_string(&value)?;
+    if text.chars().count() <= MAX_SUMMARY_CHARS {
+        return Ok(Some(text));
+    }
+    let preview: String = text.chars().take(MAX_SUMMARY_CHARS).collect();
+    Ok(Some(serde_json::to_string(&serde_json::json!({
+        "truncated": true,
+        "preview": preview,
+    }))?))
+}
+
+pub async fn record(pool: DuckDbPool, entry: NewAuditEntry) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO audit_log (user_id, username, organization_id, method, route, entity_type, entity_id,
+                                status_code, before_summary, after_summary, ip_address, request_id)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+        params![
+            entry.user_id,
+            entry.username,
+            entry.organization_id,
+            entry.method,
+            entry.route,
+            entry.entity_type,
+            entry.entity_id,
+            entry.status_code as i32,
+            summary_to_column(entry.before_summary)?,
+            summary_to_column(entry.after_summary)?,
+            entry.ip_address,
+            entry.request_id,
+        ],
+    )?;
+    Ok(())
+}
+
+/// One page of audit entries, newest first, plus the number of entries matching the filter.
+pub async fn get_audit_page(
+    pool: DuckDbPool,
+    filter: &AuditLogFilter,
+    page: u32,
+    per_page: u32,
+) -> Result<(Vec<audit_log::Model>, u64), AppError> {
+    let mut conditions = vec!["1 = 1".to_string()];
+    let mut values: Vec<Box<dyn duckdb::ToSql>> = Vec::new();
+    if let Some(user_id) = filter.user_id {
+        conditions.push("user_id = ?".to_string());
+        values.push(Box::new(user_id));
+    }
+    if let Some(entity_type) = &filter.entity_type {
+        conditions.push("entity_type = ?".to_string());
+        values.push(Box::new(entity_type.clone()));
+    }
+    if let Some(entity_id) = &filter.entity_id {
+        conditions.push("entity_id = ?".to_string());
+        values.push(Box::new(entity_id.clone()));
+    }
+    if let Some(method) = &filter.method {
+        conditions.push("method = ?".to_string());
+        values.push(Box::new(method.to_ascii_uppercase()));
+    }
+    if let Some(since) = filter.since {
+        conditions.push("created_at >= ?".to_string());
+        values.push(Box::new(since));
+    }
+    if let Some(until) = filter.until {
+        conditions.push("created_at < ?".to_string());
+        values.push(Box::new(until));
+    }
+    let where_clause = conditions.join(" AND ");
+
+    let conn = pool.get()?;
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM audit_log WHERE {where_clause}"),
+        duckdb::params_from_iter(values.iter()),
+        |row| row.get(0),
+    )?;
+
+    values.push(Box::new(per_page as i64));
+    values.push(Box::new(page.saturating_sub(1) as i64 * per_page as i64));
+    let items = conn
+        .prepare(&format!(
+            "SELECT * FROM audit_log WHERE {where_clause} ORDER BY created_at DESC, id DESC LIMIT ? OFFSET ?"
+        ))?
+        .query_map(duckdb::params_from_iter(values.iter()), row_to_audit_log_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((items, total as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn redacts_credentials_at_any_depth() {
+        let mut value = json!({
+            "name": "web-1",
+            "config": { "apiKey": "abc", "servers": [{ "password": "hunter2", "host": "h" }] },
+        });
+        redact(&mut value);
+        assert_eq!(value["name"], "web-1");
+        assert_eq!(value["config"]["apiKey"], "[redacted]");
+        assert_eq!(value["config"]["servers"][0]["password"], "[redacted]");
+        assert_eq!(value["config"]["servers"][0]["host"], "h");
+    }
+
+    #[test]
+    fn oversized_summaries_stay_valid_json() {
+        let stored = summary_to_column(Some(json!({ "notes": "x".repeat(10_000) }))).unwrap().unwrap();
+        let parsed: serde_json::Value = serde_json::from_str(&stored).unwrap();
+        assert_eq!(parsed["truncated"], true);
+    }
+}
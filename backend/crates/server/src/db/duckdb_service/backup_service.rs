@@ -0,0 +1,346 @@
+//! Backup archives of the whole instance.
+//!
+//! An archive is a `.tar.gz` holding a DuckDB `EXPORT DATABASE` of every
+//! table, the settings and notification channels as JSON (channel configs
+//! stay encrypted), the agent CA and branding assets, and a manifest. All of
+//! it is read in one transaction, so the archive is a consistent snapshot.
+//!
+//! Channel configs and other secrets are encrypted with keys wrapped by
+//! `NOTIFICATION_ENCRYPTION_KEY`, so an archive can only be restored by a
+//! server configured with the same key; the manifest records its fingerprint
+//! to catch a mismatch before anything is replaced. Archives also contain the
+//! agent CA private key and should be stored accordingly.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use duckdb::Connection;
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+use super::DuckDbPool;
+use crate::version::VERSION;
+
+const ARCHIVE_PREFIX: &str = "nodenexus-backup-";
+const ARCHIVE_SUFFIX: &str = ".tar.gz";
+const TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+/// Bumped when the archive layout changes in a way older servers can't restore.
+const FORMAT_VERSION: u32 = 1;
+
+const MANIFEST_FILE: &str = "manifest.json";
+const DATABASE_DIR: &str = "database";
+const SETTINGS_FILE: &str = "settings.json";
+const CHANNELS_FILE: &str = "notification_channels.json";
+/// Directories under the data dir holding state outside the database. Agent
+/// release binaries are left out; they can be uploaded again.
+const DATA_DIRS: &[&str] = &["agent_ca", "branding"];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupManifest {
+    pub format_version: u32,
+    pub server_version: String,
+    pub created_at: DateTime<Utc>,
+    /// See [`key_fingerprint`].
+    pub key_fingerprint: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupArchive {
+    pub file_name: String,
+    pub size_bytes: u64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// When the background task writes archives and how many it keeps.
+#[derive(Debug, Clone)]
+pub struct BackupSchedule {
+    pub data_dir: PathBuf,
+    /// `0` disables scheduled backups.
+    pub interval_hours: u32,
+    pub keep: usize,
+    pub key_fingerprint: String,
+}
+
+/// Identifies the master encryption key without revealing it: the first 16
+/// hex characters of its SHA-256.
+pub fn key_fingerprint(master_key_hex: &str) -> String {
+    let digest = Sha256::digest(master_key_hex.trim().to_ascii_lowercase().as_bytes());
+    hex::encode(&digest[..8])
+}
+
+/// Where archives are written: `<data_dir>/backups/archives`, next to the
+/// startup backups.
+pub fn archive_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join("backups").join("archives")
+}
+
+pub fn archive_name(created_at: DateTime<Utc>) -> String {
+    format!("{ARCHIVE_PREFIX}{}{ARCHIVE_SUFFIX}", created_at.format(TIMESTAMP_FORMAT))
+}
+
+/// The creation time encoded in an archive name, or `None` if `name` isn't
+/// one. Download requests are checked with this, so it also rules out paths.
+pub fn parse_archive_name(name: &str) -> Option<DateTime<Utc>> {
+    let timestamp = name.strip_prefix(ARCHIVE_PREFIX)?.strip_suffix(ARCHIVE_SUFFIX)?;
+    NaiveDateTime::parse_from_str(timestamp, TIMESTAMP_FORMAT)
+        .ok()
+        .map(|t| t.and_utc())
+}
+
+/// Whether a scheduled backup is due, given when the newest archive was made.
+pub fn is_backup_due(newest: Option<DateTime<Utc>>, interval_hours: u32, now: DateTime<Utc>) -> bool {
+    interval_hours > 0
+        && newest.is_none_or(|newest| now - newest >= chrono::Duration::hours(i64::from(interval_hours)))
+}
+
+/// Archives in the archive dir, newest first.
+pub fn list_backups(data_dir: &Path) -> Vec<BackupArchive> {
+    let mut archives: Vec<BackupArchive> = fs::read_dir(archive_dir(data_dir))
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let file_name = entry.file_name().into_string().ok()?;
+                    let created_at = parse_archive_name(&file_name)?;
+                    let size_bytes = entry.metadata().ok()?.len();
+                    Some(BackupArchive { file_name, size_bytes, created_at })
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    archives.sort_by_key(|a| std::cmp::Reverse(a.created_at));
+    archives
+}
+
+/// Deletes all but the `keep` newest archives. Returns how many were deleted.
+pub fn prune_backups(data_dir: &Path, keep: usize) -> usize {
+    let dir = archive_dir(data_dir);
+    let mut deleted = 0;
+    for old in list_backups(data_dir).iter().skip(keep) {
+        match fs::remove_file(dir.join(&old.file_name)) {
+            Ok(()) => deleted += 1,
+            Err(e) => warn!(file = %old.file_name, error = %e, "Failed to remove old backup archive."),
+        }
+    }
+    deleted
+}
+
+fn sql_path(path: &Path) -> String {
+    path.to_string_lossy().replace('\'', "''")
+}
+
+/// Exports the database, settings and channels into `dir` in one transaction.
+fn export_database(conn: &Connection, dir: &Path) -> Result<(), duckdb::Error> {
+    conn.execute_batch("BEGIN TRANSACTION;")?;
+    let result = conn.execute_batch(&format!(
+        "EXPORT DATABASE '{}' (FORMAT PARQUET);
+         COPY (SELECT key, value, updated_at FROM settings ORDER BY key)
+             TO '{}' (FORMAT JSON, ARRAY true);
+         COPY (SELECT id, user_id, name, channel_type, base64(config) AS config, created_at, updated_at
+               FROM notification_channels ORDER BY id)
+             TO '{}' (FORMAT JSON, ARRAY true);",
+        sql_path(&dir.join(DATABASE_DIR)),
+        sql_path(&dir.join(SETTINGS_FILE)),
+        sql_path(&dir.join(CHANNELS_FILE)),
+    ));
+    // Nothing was written, so the transaction only has to end.
+    conn.execute_batch("ROLLBACK;")?;
+    result
+}
+
+/// Writes a new archive into the archive dir. Blocking; the database stays
+/// writable while it runs.
+pub fn create_backup(pool: &DuckDbPool, data_dir: &Path, key_fingerprint: &str) -> Result<BackupArchive, String> {
+    let dir = archive_dir(data_dir);
+    fs::create_dir_all(&dir).map_err(|e| format!("failed to create {}: {e}", dir.display()))?;
+    let staging = tempfile::Builder::new()
+        .prefix(".staging-")
+        .tempdir_in(&dir)
+        .map_err(|e| e.to_string())?;
+
+    let created_at = Utc::now();
+    export_database(&pool.get().map_err(|e| e.to_string())?, staging.path())
+        .map_err(|e| format!("database export failed: {e}"))?;
+
+    let manifest = BackupManifest {
+        format_version: FORMAT_VERSION,
+        server_version: VERSION.to_string(),
+        created_at,
+        key_fingerprint: key_fingerprint.to_string(),
+    };
+    let manifest = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    fs::write(staging.path().join(MANIFEST_FILE), manifest).map_err(|e| e.to_string())?;
+
+    let file_name = archive_name(created_at);
+    // Written inside the staging dir first, so a half-written archive is never listed.
+    let partial = staging.path().join("archive.partial");
+    let write = || -> std::io::Result<()> {
+        let mut tar = tar::Builder::new(GzEncoder::new(File::create(&partial)?, Compression::default()));
+        tar.append_path_with_name(staging.path().join(MANIFEST_FILE), MANIFEST_FILE)?;
+        tar.append_dir_all(DATABASE_DIR, staging.path().join(DATABASE_DIR))?;
+        tar.append_path_with_name(staging.path().join(SETTINGS_FILE), SETTINGS_FILE)?;
+        tar.append_path_with_name(staging.path().join(CHANNELS_FILE), CHANNELS_FILE)?;
+        for name in DATA_DIRS {
+            let path = data_dir.join(name);
+            if path.is_dir() {
+                tar.append_dir_all(name, path)?;
+            }
+        }
+        tar.into_inner()?.finish()?.sync_all()?;
+        fs::rename(&partial, dir.join(&file_name))
+    };
+    write().map_err(|e| format!("failed to write archive: {e}"))?;
+
+    let size_bytes = fs::metadata(dir.join(&file_name)).map(|m| m.len()).unwrap_or_default();
+    info!(file = %file_name, size_bytes, "Created backup archive.");
+    Ok(BackupArchive { file_name, size_bytes, created_at })
+}
+
+/// Runs a backup if the schedule says one is due, then prunes old archives.
+pub fn run_scheduled_backup(pool: &DuckDbPool, schedule: &BackupSchedule) -> Result<Option<BackupArchive>, String> {
+    let newest = list_backups(&schedule.data_dir).first().map(|a| a.created_at);
+    if !is_backup_due(newest, schedule.interval_hours, Utc::now()) {
+        return Ok(None);
+    }
+    let archive = create_backup(pool, &schedule.data_dir, &schedule.key_fingerprint)?;
+    let deleted = prune_backups(&schedule.data_dir, schedule.keep);
+    if deleted > 0 {
+        info!(deleted, "Pruned old backup archives.");
+    }
+    Ok(Some(archive))
+}
+
+/// Renames `path` to `<path>.<suffix>` if it exists.
+fn move_aside(path: &Path, suffix: &str) -> Result<Option<PathBuf>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let mut aside = path.as_os_str().to_owned();
+    aside.push(format!(".{suffix}"));
+    let aside = PathBuf::from(aside);
+    fs::rename(path, &aside).map_err(|e| format!("failed to move {} aside: {e}", path.display()))?;
+    Ok(Some(aside))
+}
+
+/// Replaces the database and data directories with the contents of an
+/// archive. Must run before the database is opened. What is replaced is kept
+/// next to it with a `.pre-restore-<timestamp>` suffix.
+pub fn restore_backup(archive: &Path, db_path: &Path, data_dir: &Path, key_fingerprint: &str) -> Result<(), String> {
+    fs::create_dir_all(data_dir).map_err(|e| e.to_string())?;
+    // Unpacked inside the data dir so everything can be moved into place by renaming.
+    let staging = tempfile::Builder::new()
+        .prefix(".restore-")
+        .tempdir_in(data_dir)
+        .map_err(|e| e.to_string())?;
+    let file = File::open(archive).map_err(|e| format!("failed to open {}: {e}", archive.display()))?;
+    tar::Archive::new(GzDecoder::new(file))
+        .unpack(staging.path())
+        .map_err(|e| format!("failed to unpack {}: {e}", archive.display()))?;
+
+    let manifest = fs::read(staging.path().join(MANIFEST_FILE))
+        .map_err(|e| format!("not a NodeNexus backup archive (no {MANIFEST_FILE}): {e}"))?;
+    let manifest: BackupManifest =
+        serde_json::from_slice(&manifest).map_err(|e| format!("invalid {MANIFEST_FILE}: {e}"))?;
+    if manifest.format_version > FORMAT_VERSION {
+        return Err(format!(
+            "the archive was made by server {} in a newer format; upgrade before restoring it",
+            manifest.server_version
+        ));
+    }
+    if manifest.key_fingerprint != key_fingerprint {
+        return Err(format!(
+            "the archive was made with a different NOTIFICATION_ENCRYPTION_KEY (fingerprint {}, configured {}); \
+             configure the key it was made with, or its encrypted secrets can't be read",
+            manifest.key_fingerprint, key_fingerprint
+        ));
+    }
+
+    // Imported into a new file first, so a failed import leaves the current database alone.
+    let imported = staging.path().join("restored.db");
+    {
+        let conn = Connection::open(&imported).map_err(|e| e.to_string())?;
+        conn.execute_batch(&format!(
+            "IMPORT DATABASE '{}';",
+            sql_path(&staging.path().join(DATABASE_DIR))
+        ))
+        .map_err(|e| format!("database import failed: {e}"))?;
+    }
+
+    let suffix = format!("pre-restore-{}", Utc::now().format(TIMESTAMP_FORMAT));
+    let previous = move_aside(db_path, &suffix)?;
+    move_aside(&db_path.with_extension("db.wal"), &suffix)?;
+    fs::rename(&imported, db_path).map_err(|e| format!("failed to move the restored database into place: {e}"))?;
+    for name in DATA_DIRS {
+        let restored = staging.path().join(name);
+        if restored.is_dir() {
+            let target = data_dir.join(name);
+            move_aside(&target, &suffix)?;
+            fs::rename(&restored, &target).map_err(|e| format!("failed to restore {name}: {e}"))?;
+        }
+    }
+
+    warn!(
+        archive = %archive.display(),
+        created_at = %manifest.created_at,
+        server_version = %manifest.server_version,
+        previous = ?previous,
+        "Restored the database from a backup archive."
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn archive_names_round_trip() {
+        let created_at = Utc.with_ymd_and_hms(2025, 7, 1, 3, 4, 5).unwrap();
+        let name = archive_name(created_at);
+        assert_eq!(name, "nodenexus-backup-20250701T030405Z.tar.gz");
+        assert_eq!(parse_archive_name(&name), Some(created_at));
+        assert_eq!(parse_archive_name("nodenexus-20250701T030405Z.db"), None);
+        assert_eq!(parse_archive_name("nodenexus-backup-../../etc/passwd.tar.gz"), None);
+    }
+
+    #[test]
+    fn backups_are_due_after_the_interval() {
+        let now = Utc.with_ymd_and_hms(2025, 7, 2, 12, 0, 0).unwrap();
+        assert!(is_backup_due(None, 24, now));
+        assert!(!is_backup_due(None, 0, now));
+        assert!(!is_backup_due(Some(now - chrono::Duration::hours(23)), 24, now));
+        assert!(is_backup_due(Some(now - chrono::Duration::hours(24)), 24, now));
+    }
+
+    #[test]
+    fn prunes_the_oldest_archives() {
+        let data_dir = tempfile::tempdir().unwrap();
+        let dir = archive_dir(data_dir.path());
+        fs::create_dir_all(&dir).unwrap();
+        for day in 1..=4 {
+            let created_at = Utc.with_ymd_and_hms(2025, 7, day, 0, 0, 0).unwrap();
+            fs::write(dir.join(archive_name(created_at)), b"x").unwrap();
+        }
+        fs::write(dir.join("notes.txt"), b"kept").unwrap();
+
+        assert_eq!(prune_backups(data_dir.path(), 2), 2);
+        let names: Vec<String> = list_backups(data_dir.path()).into_iter().map(|a| a.file_name).collect();
+        assert_eq!(
+            names,
+            ["nodenexus-backup-20250704T000000Z.tar.gz", "nodenexus-backup-20250703T000000Z.tar.gz"]
+        );
+        assert!(dir.join("notes.txt").exists());
+    }
+
+    #[test]
+    fn fingerprints_ignore_case_and_whitespace() {
+        assert_eq!(key_fingerprint("ABCD\n"), key_fingerprint("abcd"));
+        assert_ne!(key_fingerprint("abcd"), key_fingerprint("abce"));
+        assert_eq!(key_fingerprint("abcd").len(), 16);
+    }
+}
@@ -1,21 +1,27 @@
 use crate::server::result_broadcaster::ResultBroadcaster;
-use crate::db::duckdb_service::DuckDbPool;
+use crate::db::duckdb_service::{command_policy_service, webhook_service, DuckDbPool};
 use chrono::Utc;
-use duckdb::{params, types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef}, Result as DuckDbResult, Row};
+use duckdb::{params, OptionalExt, types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef}, Result as DuckDbResult, Row};
+use serde_json::json;
 use std::fmt;
 use std::sync::Arc;
 use std::fs::OpenOptions;
-use std::io::Write;
+use std::io::{Read, Write};
 use std::path::PathBuf;
 use std::str::FromStr;
-use tracing::error;
+use std::time::SystemTime;
+use tracing::{error, info, warn};
 use uuid::Uuid;
 
 use crate::db::entities::{batch_command_task, child_command_task};
 use crate::db::enums::{BatchCommandStatus, ChildCommandStatus};
 use crate::web::error::AppError;
+use crate::services::batch_execution;
+use crate::services::batch_output::{self as output_search, escape_like};
+use crate::services::batch_output_diff::{self, OutputSample};
 use crate::web::models::batch_command_models::{
-    BatchCommandTaskDetailResponse, ChildCommandTaskDetail, CreateBatchCommandRequest,
+    BatchCommandTaskDetailResponse, BatchOutputComparisonResponse, BatchOutputSearchHit,
+    ChildCommandTaskDetail, CreateBatchCommandRequest, ResolvedTarget,
 };
 use nodenexus_common::agent_service::OutputType as GrpcOutputType;
 
@@ -46,8 +52,12 @@ pub enum BatchCommandServiceError {
     NotFound(Uuid),
     #[error("Unauthorized to access this resource")]
     Unauthorized,
+    #[error("Blocked by command policy: {0}")]
+    PolicyDenied(String),
     #[error("Child task is not in an active state and cannot be terminated")]
     TaskNotTerminable,
+    #[error("Batch command {0} has already finished")]
+    BatchFinished(Uuid),
     #[error("JSON serialization error: {0}")]
     JsonError(#[from] serde_json::Error),
     #[error("Tokio join error: {0}")]
@@ -59,13 +69,15 @@ pub enum BatchCommandServiceError {
 impl From<BatchCommandServiceError> for AppError {
     fn from(err: BatchCommandServiceError) -> Self {
         match err {
-            BatchCommandServiceError::DbErr(e) => AppError::DatabaseError(e.to_string()),
-            BatchCommandServiceError::PoolError(e) => AppError::DatabaseError(e.to_string()),
+            BatchCommandServiceError::DbErr(e) => e.into(),
+            BatchCommandServiceError::PoolError(e) => e.into(),
             BatchCommandServiceError::ValidationError(s) => AppError::InvalidInput(s),
             BatchCommandServiceError::CreationFailed(s) => AppError::InternalServerError(s),
             BatchCommandServiceError::NotFound(id) => AppError::NotFound(format!("Batch command {id} not found")),
             BatchCommandServiceError::Unauthorized => AppError::Unauthorized("Unauthorized".to_string()),
+            BatchCommandServiceError::PolicyDenied(s) => AppError::Forbidden(s),
             BatchCommandServiceError::TaskNotTerminable => AppError::Conflict("Task not terminable".to_string()),
+            BatchCommandServiceError::BatchFinished(id) => AppError::Conflict(format!("Batch command {id} has already finished")),
             BatchCommandServiceError::JsonError(e) => AppError::InternalServerError(e.to_string()),
             BatchCommandServiceError::JoinError(e) => AppError::InternalServerError(e.to_string()),
             BatchCommandServiceError::IoError(e) => AppError::InternalServerError(e.to_string()),
@@ -112,6 +124,10 @@ fn row_to_batch_command_task(row: &Row) -> DuckDbResult<batch_command_task::Mode
         created_at: row.get("created_at")?,
         updated_at: row.get("updated_at")?,
         completed_at: row.get("completed_at")?,
+        trace_id: row.get("trace_id")?,
+        timeout_seconds: row.get("timeout_seconds")?,
+        max_attempts: row.get::<_, Option<i32>>("max_attempts")?.unwrap_or(1),
+        retry_backoff_seconds: row.get::<_, Option<i32>>("retry_backoff_seconds")?.unwrap_or_default(),
     })
 }
 
@@ -130,13 +146,25 @@ fn row_to_child_command_task(row: &Row) -> DuckDbResult<child_command_task::Mode
         updated_at: row.get("updated_at")?,
         agent_started_at: row.get("agent_started_at")?,
         agent_completed_at: row.get("agent_completed_at")?,
+        matched_selector: row.get("matched_selector")?,
+        wave: row.get::<_, Option<i32>>("wave")?.unwrap_or_default(),
+        attempt: row.get::<_, Option<i32>>("attempt")?.unwrap_or(1),
+        next_attempt_at: row.get("next_attempt_at")?,
     })
 }
 
+/// Records a batch command and a child task per resolved target, see
+/// `CommandDispatcher::resolve_targets`, each in the wave the request's
+/// execution strategy plans for it. `script_id` is the saved script being
+/// run, if any; every target has to pass the command policies before anything
+/// is created.
 pub async fn create_batch_command(
     db_pool: DuckDbPool,
     user_id: i32,
     request: CreateBatchCommandRequest,
+    targets: Vec<ResolvedTarget>,
+    script_id: Option<i32>,
+    trace_id: String,
 ) -> Result<(batch_command_task::Model, Vec<child_command_task::Model>), BatchCommandServiceError> {
     if request.command_content.is_none() && request.script_id.is_none() {
         return Err(BatchCommandServiceError::ValidationError("Either command_content or script_id must be provided.".to_string()));
@@ -144,21 +172,36 @@ pub async fn create_batch_command(
     if request.command_content.is_some() && request.script_id.is_some() {
         return Err(BatchCommandServiceError::ValidationError("Provide either command_content or script_id, not both.".to_string()));
     }
-    if request.target_vps_ids.is_empty() {
-        return Err(BatchCommandServiceError::ValidationError("At least one target_vps_id must be provided.".to_string()));
+    if targets.is_empty() {
+        return Err(BatchCommandServiceError::ValidationError("No VPS matched the batch command's targets.".to_string()));
     }
+    batch_execution::validate_strategy(&request.strategy).map_err(BatchCommandServiceError::ValidationError)?;
+    batch_execution::validate_limits(request.timeout_seconds, request.retry.as_ref())
+        .map_err(BatchCommandServiceError::ValidationError)?;
+    let waves = batch_execution::plan_waves(&request.strategy, targets.len());
 
     let db_pool_clone = db_pool.clone();
     let task = tokio::task::spawn_blocking(move || -> Result<_, BatchCommandServiceError> {
         let mut conn = db_pool_clone.get()?;
+        let vps_ids: Vec<i32> = targets.iter().map(|target| target.vps_id).collect();
+        let denied: Vec<String> =
+            command_policy_service::evaluate_targets(&conn, user_id, script_id, &vps_ids)?
+                .into_iter()
+                .filter(|decision| !decision.allowed)
+                .map(|decision| format!("VPS {}: {}", decision.vps_id, decision.reason))
+                .collect();
+        if !denied.is_empty() {
+            return Err(BatchCommandServiceError::PolicyDenied(denied.join("; ")));
+        }
+
         let tx = conn.transaction()?;
         let batch_command_id = Uuid::new_v4();
         let now = Utc::now();
         let original_request_payload = serde_json::to_string(&request)?;
 
         tx.execute(
-            "INSERT INTO batch_command_tasks (batch_command_id, original_request_payload, status, execution_alias, user_id, created_at, updated_at)
-             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            "INSERT INTO batch_command_tasks (batch_command_id, original_request_payload, status, execution_alias, user_id, created_at, updated_at, trace_id, timeout_seconds, max_attempts, retry_backoff_seconds)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
             params![
                 batch_command_id,
                 original_request_payload,
@@ -167,28 +210,29 @@ pub async fn create_batch_command(
                 user_id,
                 now,
                 now,
+                trace_id,
+                request.timeout_seconds,
+                request.retry.as_ref().map_or(1, |retry| retry.max_attempts),
+                request.retry.as_ref().map_or(0, |retry| retry.backoff_seconds),
             ],
         )?;
 
-        let mut child_tasks_to_create = Vec::new();
-        for vps_id in request.target_vps_ids {
-            child_tasks_to_create.push((
-                Uuid::new_v4(),
-                batch_command_id,
-                vps_id,
-                ChildCommandStatus::Pending,
-                now,
-                now,
-            ));
-        }
-
-        if !child_tasks_to_create.is_empty() {
+        {
             let mut stmt = tx.prepare(
-                "INSERT INTO child_command_tasks (child_command_id, batch_command_id, vps_id, status, created_at, updated_at)
-                 VALUES (?, ?, ?, ?, ?, ?)",
+                "INSERT INTO child_command_tasks (child_command_id, batch_command_id, vps_id, status, created_at, updated_at, matched_selector, wave)
+                 VALUES (?, ?, ?, ?, ?, ?, ?, ?)",
             )?;
-            for task in child_tasks_to_create {
-                stmt.execute(params![task.0, task.1, task.2, task.3, task.4, task.5])?;
+            for (target, wave) in targets.into_iter().zip(waves) {
+                stmt.execute(params![
+                    Uuid::new_v4(),
+                    batch_command_id,
+                    target.vps_id,
+                    ChildCommandStatus::Pending,
+                    now,
+                    now,
+                    target.matched_selector,
+                    wave as i32,
+                ])?;
             }
         }
 
@@ -219,7 +263,7 @@ pub async fn get_child_tasks_for_batch(db_pool: DuckDbPool, batch_command_id: &U
     let id = *batch_command_id;
     tokio::task::spawn_blocking(move || -> Result<_, BatchCommandServiceError> {
         let conn = db_pool.get()?;
-        let mut stmt = conn.prepare("SELECT * FROM child_command_tasks WHERE batch_command_id = ?")?;
+        let mut stmt = conn.prepare("SELECT * FROM child_command_tasks WHERE batch_command_id = ? ORDER BY wave, created_at")?;
         let rows = stmt.query_map(params![id], row_to_child_command_task)?;
         let tasks = rows.collect::<DuckDbResult<Vec<_>>>()?;
         Ok(tasks)
@@ -252,6 +296,10 @@ pub async fn get_batch_command_detail_dto(
             agent_started_at: ct.agent_started_at,
             agent_completed_at: ct.agent_completed_at,
             last_output_at: ct.last_output_at,
+            matched_selector: ct.matched_selector,
+            wave: ct.wave,
+            attempt: ct.attempt,
+            next_attempt_at: ct.next_attempt_at,
         })
         .collect();
 
@@ -269,7 +317,104 @@ pub async fn get_batch_command_detail_dto(
     Ok(Some(response_dto))
 }
 
+/// Only the head of each log is compared, so a runaway output can't exhaust memory.
+const MAX_COMPARED_OUTPUT_BYTES: u64 = 1024 * 1024;
+/// Only the head of each log is copied into the search index.
+const MAX_INDEXED_OUTPUT_BYTES: u64 = 256 * 1024;
+/// Where child command output is written, one directory per batch.
+const BATCH_OUTPUT_LOG_DIR: &str = "logs/batch_commands";
+
+fn read_log_head(path: Option<&str>, limit: u64) -> std::io::Result<String> {
+    let Some(path) = path else {
+        return Ok(String::new());
+    };
+    let file = match std::fs::File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(String::new()),
+        Err(e) => return Err(e),
+    };
+    let mut buf = Vec::new();
+    file.take(limit).read_to_end(&mut buf)?;
+    Ok(String::from_utf8_lossy(&buf).into_owned())
+}
+
+/// Clusters the outputs of all child tasks of a batch and reports the outliers.
+pub async fn compare_child_outputs(
+    db_pool: DuckDbPool,
+    batch_command_id: Uuid,
+    requesting_user_id: i32,
+    stream: &str,
+    similarity_threshold: f64,
+) -> Result<BatchOutputComparisonResponse, BatchCommandServiceError> {
+    if !matches!(stream, "stdout" | "stderr" | "combined") {
+        return Err(BatchCommandServiceError::ValidationError(format!(
+            "Unknown stream '{stream}', expected stdout, stderr or combined"
+        )));
+    }
+    if !(0.0..=1.0).contains(&similarity_threshold) {
+        return Err(BatchCommandServiceError::ValidationError(
+            "similarity_threshold must be between 0 and 1".to_string(),
+        ));
+    }
+
+    let batch_task = get_batch_task(db_pool.clone(), &batch_command_id).await?;
+    if batch_task.user_id != requesting_user_id {
+        return Err(BatchCommandServiceError::Unauthorized);
+    }
+    let child_tasks = get_child_tasks_for_batch(db_pool, &batch_command_id).await?;
+
+    let stream_name = stream.to_string();
+    let samples = tokio::task::spawn_blocking(move || -> Result<Vec<OutputSample>, BatchCommandServiceError> {
+        child_tasks
+            .into_iter()
+            .map(|task| {
+                let output = match stream_name.as_str() {
+                    "stdout" => read_log_head(task.stdout_log_path.as_deref(), MAX_COMPARED_OUTPUT_BYTES)?,
+                    "stderr" => read_log_head(task.stderr_log_path.as_deref(), MAX_COMPARED_OUTPUT_BYTES)?,
+                    _ => format!(
+                        "{}\n--- stderr ---\n{}",
+                        read_log_head(task.stdout_log_path.as_deref(), MAX_COMPARED_OUTPUT_BYTES)?,
+                        read_log_head(task.stderr_log_path.as_deref(), MAX_COMPARED_OUTPUT_BYTES)?
+                    ),
+                };
+                Ok(OutputSample {
+                    child_command_id: task.child_command_id,
+                    vps_id: task.vps_id,
+                    exit_code: task.exit_code,
+                    output,
+                })
+            })
+            .collect()
+    })
+    .await??;
+
+    Ok(batch_output_diff::cluster_outputs(
+        batch_command_id,
+        stream,
+        samples,
+        similarity_threshold,
+    ))
+}
+
 pub async fn terminate_batch_command(db_pool: DuckDbPool, batch_command_id: Uuid, user_id: i32) -> Result<Vec<(Uuid, i32)>, BatchCommandServiceError> {
+    stop_batch_command(db_pool, batch_command_id, user_id, BatchCommandStatus::Terminating).await
+}
+
+/// Like `terminate_batch_command`, but its child tasks end up `Aborted`
+/// rather than `Terminated`, and tasks waiting for a retry or a later wave
+/// never run. Fails for a batch that has already finished.
+pub async fn abort_batch_command(db_pool: DuckDbPool, batch_command_id: Uuid, user_id: i32) -> Result<Vec<(Uuid, i32)>, BatchCommandServiceError> {
+    stop_batch_command(db_pool, batch_command_id, user_id, BatchCommandStatus::Aborting).await
+}
+
+/// Marks the batch `status` and its active child tasks `Terminating`, and
+/// returns those tasks so their agents can be told to kill them.
+async fn stop_batch_command(
+    db_pool: DuckDbPool,
+    batch_command_id: Uuid,
+    user_id: i32,
+    status: BatchCommandStatus,
+) -> Result<Vec<(Uuid, i32)>, BatchCommandServiceError> {
     tokio::task::spawn_blocking(move || -> Result<_, BatchCommandServiceError> {
         let mut conn = db_pool.get()?;
         let tx = conn.transaction()?;
@@ -284,10 +429,13 @@ pub async fn terminate_batch_command(db_pool: DuckDbPool, batch_command_id: Uuid
         if batch_task.user_id != user_id {
             return Err(BatchCommandServiceError::Unauthorized);
         }
+        if status == BatchCommandStatus::Aborting && batch_task.status.is_final() {
+            return Err(BatchCommandServiceError::BatchFinished(batch_command_id));
+        }
 
         tx.execute(
             "UPDATE batch_command_tasks SET status = ?, updated_at = ? WHERE batch_command_id = ?",
-            params![BatchCommandStatus::Terminating, Utc::now(), batch_command_id],
+            params![status, Utc::now(), batch_command_id],
         )?;
 
         let mut stmt = tx.prepare("SELECT * FROM child_command_tasks WHERE batch_command_id = ?")?;
@@ -295,14 +443,14 @@ pub async fn terminate_batch_command(db_pool: DuckDbPool, batch_command_id: Uuid
             .collect::<Result<Vec<_>, _>>()?;
 
         let active_child_tasks: Vec<(Uuid, i32)> = child_tasks.into_iter()
-            .filter(|task| matches!(task.status, ChildCommandStatus::Pending | ChildCommandStatus::Executing | ChildCommandStatus::SentToAgent | ChildCommandStatus::AgentAccepted))
+            .filter(|task| matches!(task.status, ChildCommandStatus::Pending | ChildCommandStatus::Executing | ChildCommandStatus::SentToAgent | ChildCommandStatus::AgentAccepted | ChildCommandStatus::Retrying))
             .map(|task| (task.child_command_id, task.vps_id))
             .collect();
 
         if !active_child_tasks.is_empty() {
             let active_child_task_ids: Vec<Uuid> = active_child_tasks.iter().map(|(id, _)| *id).collect();
             let params_sql = active_child_task_ids.iter().map(|_| "?").collect::<Vec<_>>().join(", ");
-            let sql = format!("UPDATE child_command_tasks SET status = ?, updated_at = ? WHERE child_command_id IN ({params_sql})");
+            let sql = format!("UPDATE child_command_tasks SET status = ?, updated_at = ?, next_attempt_at = NULL WHERE child_command_id IN ({params_sql})");
             
             let status = ChildCommandStatus::Terminating;
             let now = Utc::now();
@@ -318,6 +466,183 @@ pub async fn terminate_batch_command(db_pool: DuckDbPool, batch_command_id: Uuid
     }).await?
 }
 
+/// Starts the next attempt of a `Retrying` child task. Returns false if the
+/// task is no longer waiting for one, e.g. because the batch was stopped.
+pub async fn begin_retry(db_pool: DuckDbPool, child_command_id: Uuid) -> Result<bool, BatchCommandServiceError> {
+    tokio::task::spawn_blocking(move || -> Result<_, BatchCommandServiceError> {
+        let conn = db_pool.get()?;
+        let updated = conn.execute(
+            "UPDATE child_command_tasks
+             SET status = ?, attempt = attempt + 1, next_attempt_at = NULL, exit_code = NULL, error_message = NULL,
+                 agent_completed_at = NULL, updated_at = ?
+             WHERE child_command_id = ? AND status = ?",
+            params![ChildCommandStatus::Pending, Utc::now(), child_command_id, ChildCommandStatus::Retrying],
+        )?;
+        Ok(updated > 0)
+    }).await?
+}
+
+/// Copies the head of a finished child's output into the search index.
+fn index_child_output(conn: &duckdb::Connection, task: &child_command_task::Model) -> Result<(), BatchCommandServiceError> {
+    let stdout = read_log_head(task.stdout_log_path.as_deref(), MAX_INDEXED_OUTPUT_BYTES)?;
+    let stderr = read_log_head(task.stderr_log_path.as_deref(), MAX_INDEXED_OUTPUT_BYTES)?;
+    if stdout.is_empty() && stderr.is_empty() {
+        return Ok(());
+    }
+    conn.execute(
+        "INSERT OR REPLACE INTO child_command_outputs (child_command_id, batch_command_id, user_id, vps_id, stdout, stderr, indexed_at)
+         SELECT ?, batch_command_id, user_id, ?, ?, ?, ? FROM batch_command_tasks WHERE batch_command_id = ?",
+        params![task.child_command_id, task.vps_id, stdout, stderr, Utc::now(), task.batch_command_id],
+    )?;
+    Ok(())
+}
+
+/// The log file holding `stream` ("stdout" or "stderr") of a child command,
+/// `None` when it produced no output on that stream (or it has expired).
+pub async fn get_child_output_path(
+    db_pool: DuckDbPool,
+    batch_command_id: Uuid,
+    child_command_id: Uuid,
+    requesting_user_id: i32,
+    stream: &str,
+) -> Result<Option<PathBuf>, BatchCommandServiceError> {
+    if !matches!(stream, "stdout" | "stderr") {
+        return Err(BatchCommandServiceError::ValidationError(format!(
+            "Unknown stream '{stream}', expected stdout or stderr"
+        )));
+    }
+    let batch_task = get_batch_task(db_pool.clone(), &batch_command_id).await?;
+    if batch_task.user_id != requesting_user_id {
+        return Err(BatchCommandServiceError::Unauthorized);
+    }
+    let child_tasks = get_child_tasks_for_batch(db_pool, &batch_command_id).await?;
+    let task = child_tasks
+        .into_iter()
+        .find(|task| task.child_command_id == child_command_id)
+        .ok_or(BatchCommandServiceError::NotFound(child_command_id))?;
+    let path = match stream {
+        "stdout" => task.stdout_log_path,
+        _ => task.stderr_log_path,
+    };
+    Ok(path.map(PathBuf::from).filter(|path| path.is_file()))
+}
+
+/// Finished child commands of the user whose output contains `query`
+/// (case-insensitive), newest first.
+pub async fn search_child_outputs(
+    db_pool: DuckDbPool,
+    requesting_user_id: i32,
+    query: String,
+    limit: u32,
+) -> Result<Vec<BatchOutputSearchHit>, BatchCommandServiceError> {
+    let query = query.trim().to_string();
+    if query.chars().count() < 2 {
+        return Err(BatchCommandServiceError::ValidationError(
+            "Search terms must be at least 2 characters".to_string(),
+        ));
+    }
+    tokio::task::spawn_blocking(move || -> Result<_, BatchCommandServiceError> {
+        let conn = db_pool.get()?;
+        let pattern = format!("%{}%", escape_like(&query));
+        let mut stmt = conn.prepare(
+            "SELECT o.child_command_id, o.batch_command_id, o.vps_id, o.stdout, o.stderr, o.indexed_at, b.execution_alias
+             FROM child_command_outputs o
+             LEFT JOIN batch_command_tasks b ON b.batch_command_id = o.batch_command_id
+             WHERE o.user_id = ? AND (o.stdout ILIKE ? ESCAPE '\\' OR o.stderr ILIKE ? ESCAPE '\\')
+             ORDER BY o.indexed_at DESC
+             LIMIT ?",
+        )?;
+        let rows = stmt.query_map(params![requesting_user_id, pattern, pattern, limit], |row| {
+            Ok((
+                row.get::<_, Uuid>(0)?,
+                row.get::<_, Uuid>(1)?,
+                row.get::<_, i32>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, String>(4)?,
+                row.get::<_, chrono::DateTime<Utc>>(5)?,
+                row.get::<_, Option<String>>(6)?,
+            ))
+        })?;
+
+        let mut hits = Vec::new();
+        for row in rows {
+            let (child_command_id, batch_command_id, vps_id, stdout, stderr, finished_at, execution_alias) = row?;
+            let (stream, snippet) = match output_search::snippet(&stdout, &query) {
+                Some(snippet) => ("stdout", snippet),
+                None => ("stderr", output_search::snippet(&stderr, &query).unwrap_or_default()),
+            };
+            hits.push(BatchOutputSearchHit {
+                batch_command_id,
+                child_command_id,
+                vps_id,
+                execution_alias,
+                stream: stream.to_string(),
+                snippet,
+                finished_at,
+            });
+        }
+        Ok(hits)
+    })
+    .await?
+}
+
+/// Deletes the output of batches that last wrote output more than
+/// `retention_days` ago, along with its search index rows. Returns the number
+/// of batches pruned.
+pub fn prune_expired_outputs(db_pool: &DuckDbPool, retention_days: u32) -> Result<usize, BatchCommandServiceError> {
+    if retention_days == 0 {
+        return Ok(0);
+    }
+    let cutoff = SystemTime::now() - std::time::Duration::from_secs(u64::from(retention_days) * 24 * 60 * 60);
+    let entries = match std::fs::read_dir(BATCH_OUTPUT_LOG_DIR) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+        Err(e) => return Err(e.into()),
+    };
+
+    let conn = db_pool.get()?;
+    let mut pruned = 0;
+    for entry in entries {
+        let batch_dir = entry?.path();
+        let Some(batch_command_id) = batch_dir
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| Uuid::parse_str(name).ok())
+        else {
+            continue;
+        };
+        if newest_modification(&batch_dir)? >= cutoff {
+            continue;
+        }
+        std::fs::remove_dir_all(&batch_dir)?;
+        conn.execute(
+            "UPDATE child_command_tasks SET stdout_log_path = NULL, stderr_log_path = NULL WHERE batch_command_id = ?",
+            params![batch_command_id],
+        )?;
+        conn.execute("DELETE FROM child_command_outputs WHERE batch_command_id = ?", params![batch_command_id])?;
+        pruned += 1;
+    }
+    if pruned > 0 {
+        info!(pruned, retention_days, "Pruned expired batch command output.");
+    }
+    Ok(pruned)
+}
+
+/// Latest modification time of any file below `dir`.
+fn newest_modification(dir: &std::path::Path) -> std::io::Result<SystemTime> {
+    let mut newest = std::fs::metadata(dir)?.modified()?;
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let modified = if entry.file_type()?.is_dir() {
+            newest_modification(&entry.path())?
+        } else {
+            entry.metadata()?.modified()?
+        };
+        newest = newest.max(modified);
+    }
+    Ok(newest)
+}
+
 pub async fn terminate_single_child_task(db_pool: DuckDbPool, child_command_id: Uuid, user_id: i32) -> Result<Option<(Uuid, i32)>, BatchCommandServiceError> {
     tokio::task::spawn_blocking(move || -> Result<_, BatchCommandServiceError> {
         let mut conn = db_pool.get()?;
@@ -340,12 +665,12 @@ pub async fn terminate_single_child_task(db_pool: DuckDbPool, child_command_id:
             return Err(BatchCommandServiceError::Unauthorized);
         }
 
-        if !matches!(child_task.status, ChildCommandStatus::Pending | ChildCommandStatus::Executing | ChildCommandStatus::SentToAgent | ChildCommandStatus::AgentAccepted) {
+        if !matches!(child_task.status, ChildCommandStatus::Pending | ChildCommandStatus::Executing | ChildCommandStatus::SentToAgent | ChildCommandStatus::AgentAccepted | ChildCommandStatus::Retrying) {
             return Err(BatchCommandServiceError::TaskNotTerminable);
         }
 
         tx.execute(
-            "UPDATE child_command_tasks SET status = ?, updated_at = ? WHERE child_command_id = ?",
+            "UPDATE child_command_tasks SET status = ?, updated_at = ?, next_attempt_at = NULL WHERE child_command_id = ?",
             params![ChildCommandStatus::Terminating, Utc::now(), child_command_id],
         )?;
 
@@ -373,8 +698,24 @@ pub async fn update_child_task_status(
             row_to_child_command_task,
         )?;
 
-        task.status = new_status;
+        let (batch_status, max_attempts, retry_backoff_seconds): (BatchCommandStatus, Option<i32>, Option<i32>) = tx.query_row(
+            "SELECT status, max_attempts, retry_backoff_seconds FROM batch_command_tasks WHERE batch_command_id = ?",
+            params![task.batch_command_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        let stopping = matches!(batch_status, BatchCommandStatus::Terminating | BatchCommandStatus::Aborting) || batch_status.is_final();
+
         task.updated_at = Utc::now();
+        task.next_attempt_at = None;
+        task.status = if batch_status == BatchCommandStatus::Aborting && new_status == ChildCommandStatus::Terminated {
+            ChildCommandStatus::Aborted
+        } else if new_status.is_retryable() && task.attempt < max_attempts.unwrap_or(1) && !stopping {
+            let delay = batch_execution::retry_delay(retry_backoff_seconds.unwrap_or_default(), task.attempt);
+            task.next_attempt_at = Some(task.updated_at + delay);
+            ChildCommandStatus::Retrying
+        } else {
+            new_status
+        };
         if let Some(code) = exit_code {
             task.exit_code = Some(code);
         }
@@ -382,33 +723,31 @@ pub async fn update_child_task_status(
             task.error_message = Some(msg);
         }
 
-        match task.status {
-            ChildCommandStatus::CompletedSuccessfully
-            | ChildCommandStatus::CompletedWithFailure
-            | ChildCommandStatus::Terminated
-            | ChildCommandStatus::AgentUnreachable
-            | ChildCommandStatus::TimedOut
-            | ChildCommandStatus::AgentError => {
-                if task.agent_completed_at.is_none() {
-                    task.agent_completed_at = Some(Utc::now());
-                }
-            }
-            _ => {}
+        let finished = task.status.is_final();
+        if finished && task.agent_completed_at.is_none() {
+            task.agent_completed_at = Some(Utc::now());
         }
 
         tx.execute(
-            "UPDATE child_command_tasks SET status = ?, updated_at = ?, exit_code = ?, error_message = ?, agent_completed_at = ? WHERE child_command_id = ?",
+            "UPDATE child_command_tasks SET status = ?, updated_at = ?, exit_code = ?, error_message = ?, agent_completed_at = ?, next_attempt_at = ? WHERE child_command_id = ?",
             params![
                 task.status,
                 task.updated_at,
                 task.exit_code,
                 task.error_message,
                 task.agent_completed_at,
+                task.next_attempt_at,
                 child_task_id
             ],
         )?;
 
         tx.commit()?;
+        if finished {
+            // The status update stands even if the output can't be indexed.
+            if let Err(e) = index_child_output(&conn, &task) {
+                warn!(child_task_id = %child_task_id, error = %e, "Failed to index child command output.");
+            }
+        }
         Ok(task)
     }).await??;
 
@@ -446,8 +785,7 @@ pub async fn record_child_task_output(
             row_to_child_command_task,
         )?;
 
-        let base_log_dir = PathBuf::from("logs")
-            .join("batch_commands")
+        let base_log_dir = PathBuf::from(BATCH_OUTPUT_LOG_DIR)
             .join(task.batch_command_id.to_string())
             .join(task.child_command_id.to_string());
         
@@ -530,7 +868,7 @@ pub async fn check_and_update_batch_task_status(
             return Ok(None);
         }
 
-        let any_failed = child_statuses.iter().any(|s| matches!(s, ChildCommandStatus::CompletedWithFailure | ChildCommandStatus::AgentError | ChildCommandStatus::AgentUnreachable | ChildCommandStatus::TimedOut));
+        let any_failed = child_statuses.iter().any(|s| matches!(s, ChildCommandStatus::CompletedWithFailure | ChildCommandStatus::AgentError | ChildCommandStatus::AgentUnreachable | ChildCommandStatus::TimedOut | ChildCommandStatus::Skipped));
         let any_terminated = child_statuses.iter().any(|s| *s == ChildCommandStatus::Terminated);
 
         let parent_task = tx.query_row("SELECT * FROM batch_command_tasks WHERE batch_command_id = ?", params![batch_command_id], row_to_batch_command_task)?;
@@ -539,7 +877,9 @@ pub async fn check_and_update_batch_task_status(
             return Ok(None);
         }
 
-        let new_status = if any_terminated && !any_failed {
+        let new_status = if parent_task.status == BatchCommandStatus::Aborting {
+            BatchCommandStatus::Aborted
+        } else if any_terminated && !any_failed {
             BatchCommandStatus::Terminated
         } else if any_failed {
             BatchCommandStatus::CompletedWithErrors
@@ -553,6 +893,29 @@ pub async fn check_and_update_batch_task_status(
             params![new_status, completed_at, completed_at, batch_command_id],
         )?;
 
+        // Published to the organization the user who started the batch is working in.
+        let organization_id: Option<i32> = tx
+            .query_row("SELECT active_organization_id FROM users WHERE id = ?", params![parent_task.user_id], |row| row.get(0))
+            .optional()?
+            .flatten();
+        if let Some(organization_id) = organization_id {
+            let failed = child_statuses.iter().filter(|s| !matches!(s, ChildCommandStatus::CompletedSuccessfully)).count();
+            webhook_service::publish_event(
+                &tx,
+                organization_id,
+                webhook_service::EVENT_BATCH_COMMAND_FINISHED,
+                json!({
+                    "batchCommandId": batch_command_id,
+                    "status": new_status.to_string(),
+                    "executionAlias": parent_task.execution_alias,
+                    "userId": parent_task.user_id,
+                    "targetCount": child_statuses.len(),
+                    "unsuccessfulCount": failed,
+                    "completedAt": completed_at,
+                }),
+            )?;
+        }
+
         tx.commit()?;
         Ok(Some((new_status, completed_at)))
     }).await??;
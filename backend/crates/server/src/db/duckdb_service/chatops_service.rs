@@ -0,0 +1,245 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, OptionalExt, Result as DuckDbResult, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::duckdb_service::{encryption_key_service, DuckDbPool};
+use crate::db::entities::chatops_bridge;
+use crate::notifications::encryption::EncryptionService;
+use crate::web::error::AppError;
+use crate::web::models::chatops_models::{CreateChatOpsBridgeRequest, UpdateChatOpsBridgeRequest};
+
+pub const PLATFORM_TELEGRAM: &str = "telegram";
+pub const PLATFORM_SLACK: &str = "slack";
+pub const SCOPE_READ: &str = "read";
+pub const SCOPE_EXECUTE: &str = "execute";
+
+/// A script run waiting for confirmation in the chat it was requested from.
+#[derive(Debug, Clone)]
+pub struct PendingConfirmation {
+    pub script_id: i32,
+    pub vps_id: i32,
+}
+
+fn json_column<T: serde::de::DeserializeOwned>(row: &Row, column: &str) -> DuckDbResult<T> {
+    let raw: String = row.get(column)?;
+    serde_json::from_str(&raw).map_err(|e| {
+        duckdb::Error::FromSqlConversionFailure(0, duckdb::types::Type::Text, Box::new(e))
+    })
+}
+
+fn row_to_bridge_model(row: &Row) -> DuckDbResult<chatops_bridge::Model> {
+    Ok(chatops_bridge::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        name: row.get("name")?,
+        platform: row.get("platform")?,
+        allowed_chat_ids: json_column(row, "allowed_chat_ids")?,
+        scopes: json_column(row, "scopes")?,
+        allowed_script_ids: json_column(row, "allowed_script_ids")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn validate_scopes(scopes: &[String]) -> Result<(), AppError> {
+    match scopes.iter().find(|s| ![SCOPE_READ, SCOPE_EXECUTE].contains(&s.as_str())) {
+        Some(unknown) => Err(AppError::InvalidInput(format!("Unknown scope '{unknown}'"))),
+        None => Ok(()),
+    }
+}
+
+fn check_script_ownership(
+    conn: &duckdb::Connection,
+    user_id: i32,
+    script_ids: &[i32],
+) -> Result<(), AppError> {
+    for script_id in script_ids {
+        let owned = conn
+            .query_row(
+                "SELECT 1 FROM command_scripts WHERE id = ? AND user_id = ?",
+                params![script_id, user_id],
+                |_| Ok(()),
+            )
+            .optional()?;
+        if owned.is_none() {
+            return Err(AppError::InvalidInput(format!("Script {script_id} not found")));
+        }
+    }
+    Ok(())
+}
+
+/// Creates a bridge. Returns it together with the plaintext secret.
+pub async fn create_bridge(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    user_id: i32,
+    payload: CreateChatOpsBridgeRequest,
+) -> Result<(chatops_bridge::Model, String), AppError> {
+    let secret = match (payload.platform.as_str(), payload.secret) {
+        (PLATFORM_SLACK, Some(secret)) if !secret.trim().is_empty() => secret.trim().to_string(),
+        (PLATFORM_SLACK, _) => {
+            return Err(AppError::InvalidInput("Slack bridges need the app's signing secret".to_string()));
+        }
+        // Telegram secret tokens allow only A-Z, a-z, 0-9, '_' and '-'.
+        (PLATFORM_TELEGRAM, _) => Uuid::new_v4().simple().to_string(),
+        (other, _) => return Err(AppError::InvalidInput(format!("Unsupported platform '{other}'"))),
+    };
+    let scopes = payload.scopes.unwrap_or_else(|| vec![SCOPE_READ.to_string()]);
+    validate_scopes(&scopes)?;
+    let conn = pool.get()?;
+    let key_organization = encryption_key_service::user_key_organization(&conn, user_id)?;
+    let encrypted_secret = encryption_service
+        .encrypt_for(key_organization, secret.as_bytes())
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    check_script_ownership(&conn, user_id, &payload.allowed_script_ids)?;
+    let bridge = conn.query_row(
+        "INSERT INTO chatops_bridges (user_id, name, platform, secret, allowed_chat_ids, scopes, allowed_script_ids)
+         VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        params![
+            user_id,
+            payload.name,
+            payload.platform,
+            encrypted_secret,
+            serde_json::to_string(&payload.allowed_chat_ids)?,
+            serde_json::to_string(&scopes)?,
+            serde_json::to_string(&payload.allowed_script_ids)?,
+        ],
+        row_to_bridge_model,
+    )?;
+    Ok((bridge, secret))
+}
+
+pub async fn get_bridges_for_user(
+    pool: DuckDbPool,
+    user_id: i32,
+) -> Result<Vec<chatops_bridge::Model>, AppError> {
+    let conn = pool.get()?;
+    let bridges = conn
+        .prepare("SELECT * FROM chatops_bridges WHERE user_id = ? ORDER BY created_at DESC")?
+        .query_map(params![user_id], row_to_bridge_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(bridges)
+}
+
+pub async fn update_bridge(
+    pool: DuckDbPool,
+    user_id: i32,
+    id: i32,
+    payload: UpdateChatOpsBridgeRequest,
+) -> Result<chatops_bridge::Model, AppError> {
+    let conn = pool.get()?;
+    let existing = conn
+        .query_row(
+            "SELECT * FROM chatops_bridges WHERE id = ? AND user_id = ?",
+            params![id, user_id],
+            row_to_bridge_model,
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("Chat bridge not found".to_string()))?;
+
+    let scopes = payload.scopes.unwrap_or(existing.scopes);
+    validate_scopes(&scopes)?;
+    let allowed_script_ids = payload.allowed_script_ids.unwrap_or(existing.allowed_script_ids);
+    check_script_ownership(&conn, user_id, &allowed_script_ids)?;
+    let bridge = conn.query_row(
+        "UPDATE chatops_bridges
+         SET name = ?, allowed_chat_ids = ?, scopes = ?, allowed_script_ids = ?, updated_at = ?
+         WHERE id = ? AND user_id = ? RETURNING *",
+        params![
+            payload.name.unwrap_or(existing.name),
+            serde_json::to_string(&payload.allowed_chat_ids.unwrap_or(existing.allowed_chat_ids))?,
+            serde_json::to_string(&scopes)?,
+            serde_json::to_string(&allowed_script_ids)?,
+            Utc::now(),
+            id,
+            user_id,
+        ],
+        row_to_bridge_model,
+    )?;
+    Ok(bridge)
+}
+
+pub async fn delete_bridge(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute(
+        "DELETE FROM chatops_bridges WHERE id = ? AND user_id = ?",
+        params![id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("Chat bridge not found".to_string()));
+    }
+    conn.execute("DELETE FROM chatops_confirmations WHERE bridge_id = ?", params![id])?;
+    Ok(())
+}
+
+/// Loads a bridge for an inbound request together with its decrypted secret.
+pub async fn get_bridge_with_secret(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    id: i32,
+    platform: &str,
+) -> Result<Option<(chatops_bridge::Model, String)>, AppError> {
+    let conn = pool.get()?;
+    let row = conn
+        .query_row(
+            "SELECT * FROM chatops_bridges WHERE id = ? AND platform = ?",
+            params![id, platform],
+            |row| Ok((row_to_bridge_model(row)?, row.get::<_, Vec<u8>>("secret")?)),
+        )
+        .optional()?;
+    let Some((bridge, encrypted_secret)) = row else {
+        return Ok(None);
+    };
+    let secret = encryption_service
+        .decrypt(&encrypted_secret)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    let secret = String::from_utf8(secret).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    Ok(Some((bridge, secret)))
+}
+
+/// Stores a pending script run and returns the code that confirms it.
+pub async fn create_confirmation(
+    pool: DuckDbPool,
+    bridge_id: i32,
+    chat_id: &str,
+    script_id: i32,
+    vps_id: i32,
+    expires_at: DateTime<Utc>,
+) -> Result<String, AppError> {
+    let code = Uuid::new_v4().simple().to_string()[..8].to_string();
+    let conn = pool.get()?;
+    conn.execute("DELETE FROM chatops_confirmations WHERE expires_at < ?", params![Utc::now()])?;
+    conn.execute(
+        "INSERT INTO chatops_confirmations (code, bridge_id, chat_id, script_id, vps_id, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?)",
+        params![code, bridge_id, chat_id, script_id, vps_id, expires_at],
+    )?;
+    Ok(code)
+}
+
+/// Consumes a confirmation code. Codes only work once, before they expire, and
+/// in the chat that requested them.
+pub async fn take_confirmation(
+    pool: DuckDbPool,
+    bridge_id: i32,
+    chat_id: &str,
+    code: &str,
+) -> Result<Option<PendingConfirmation>, AppError> {
+    let conn = pool.get()?;
+    let pending = conn
+        .query_row(
+            "DELETE FROM chatops_confirmations
+             WHERE code = ? AND bridge_id = ? AND chat_id = ? AND expires_at >= ?
+             RETURNING script_id, vps_id",
+            params![code, bridge_id, chat_id, Utc::now()],
+            |row| {
+                Ok(PendingConfirmation {
+                    script_id: row.get(0)?,
+                    vps_id: row.get(1)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(pending)
+}
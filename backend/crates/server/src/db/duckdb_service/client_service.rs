@@ -0,0 +1,298 @@
+//! Reseller clients: external customers VPS are managed for, with per-client
+//! cost and uptime reports built from renewal pricing and status events.
+
+use chrono::{DateTime, Utc};
+use duckdb::{params, params_from_iter, Connection, OptionalExt, Result as DuckDbResult, Row};
+use std::collections::BTreeMap;
+
+use crate::db::duckdb_service::{organization_service, uptime_service, DuckDbPool};
+use crate::db::entities::client;
+use crate::web::error::AppError;
+use crate::web::models::client_models::{ClientReport, ClientRequest, ClientVpsCostEntry, CurrencyTotal};
+
+/// Average month length, for cycles given in months.
+const DAYS_PER_MONTH: f64 = 30.4375;
+
+fn row_to_client_model(row: &Row) -> DuckDbResult<client::Model> {
+    Ok(client::Model {
+        id: row.get("id")?,
+        organization_id: row.get("organization_id")?,
+        name: row.get("name")?,
+        contact_email: row.get("contact_email")?,
+        notes: row.get("notes")?,
+        markup_percent: row.get("markup_percent")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn validate(request: &ClientRequest) -> Result<&str, AppError> {
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Client name must not be empty".to_string()));
+    }
+    if !request.markup_percent.is_finite() || request.markup_percent < 0.0 {
+        return Err(AppError::InvalidInput("markupPercent must be zero or positive".to_string()));
+    }
+    Ok(name)
+}
+
+/// Length of a renewal cycle (as stored in `vps_renewal_info`) in days.
+pub fn cycle_days(cycle: &str, custom_days: Option<i32>) -> Option<f64> {
+    let months = match cycle {
+        "monthly" => 1.0,
+        "quarterly" => 3.0,
+        "semi_annually" => 6.0,
+        "annually" => 12.0,
+        "biennially" => 24.0,
+        "triennially" => 36.0,
+        "custom_days" => return custom_days.filter(|days| *days > 0).map(f64::from),
+        _ => return None,
+    };
+    Some(months * DAYS_PER_MONTH)
+}
+
+/// The share of a cycle's price that falls into a period of `period_seconds`.
+pub fn prorated_cost(price: f64, cycle_days: f64, period_seconds: i64) -> f64 {
+    let cost = price * (period_seconds as f64 / 86_400.0) / cycle_days;
+    (cost * 100.0).round() / 100.0
+}
+
+fn with_markup(cost: f64, markup_percent: f64) -> f64 {
+    (cost * (1.0 + markup_percent / 100.0) * 100.0).round() / 100.0
+}
+
+fn get_client_in_organization(conn: &Connection, user_id: i32, id: i32) -> Result<client::Model, AppError> {
+    conn.query_row(
+        &format!("SELECT * FROM clients WHERE id = ? AND {}", organization_service::org_scope("organization_id")),
+        params![id, user_id],
+        row_to_client_model,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound("Client not found".to_string()))
+}
+
+pub async fn get_clients(pool: DuckDbPool, user_id: i32) -> Result<Vec<client::Model>, AppError> {
+    let conn = pool.get()?;
+    let clients = conn
+        .prepare(&format!(
+            "SELECT * FROM clients WHERE {} ORDER BY name, id",
+            organization_service::org_scope("organization_id")
+        ))?
+        .query_map(params![user_id], row_to_client_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(clients)
+}
+
+pub async fn get_client(pool: DuckDbPool, user_id: i32, id: i32) -> Result<client::Model, AppError> {
+    let conn = pool.get()?;
+    get_client_in_organization(&conn, user_id, id)
+}
+
+pub async fn create_client(pool: DuckDbPool, user_id: i32, request: &ClientRequest) -> Result<client::Model, AppError> {
+    let name = validate(request)?;
+    let conn = pool.get()?;
+    let organization_id = organization_service::active_organization_id(&conn, user_id)?;
+    Ok(conn.query_row(
+        "INSERT INTO clients (organization_id, name, contact_email, notes, markup_percent)
+         VALUES (?, ?, ?, ?, ?) RETURNING *",
+        params![organization_id, name, request.contact_email, request.notes, request.markup_percent],
+        row_to_client_model,
+    )?)
+}
+
+pub async fn update_client(
+    pool: DuckDbPool,
+    user_id: i32,
+    id: i32,
+    request: &ClientRequest,
+) -> Result<client::Model, AppError> {
+    let name = validate(request)?;
+    let conn = pool.get()?;
+    get_client_in_organization(&conn, user_id, id)?;
+    Ok(conn.query_row(
+        "UPDATE clients SET name = ?, contact_email = ?, notes = ?, markup_percent = ?, updated_at = ?
+         WHERE id = ? RETURNING *",
+        params![name, request.contact_email, request.notes, request.markup_percent, Utc::now(), id],
+        row_to_client_model,
+    )?)
+}
+
+/// Deletes a client. Its VPS are detached and its share links revoked.
+pub async fn delete_client(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let mut conn = pool.get()?;
+    get_client_in_organization(&conn, user_id, id)?;
+    let tx = conn.transaction()?;
+    tx.execute("UPDATE vps SET client_id = NULL WHERE client_id = ?", params![id])?;
+    tx.execute("DELETE FROM share_links WHERE client_id = ?", params![id])?;
+    tx.execute("DELETE FROM clients WHERE id = ?", params![id])?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Replaces the set of VPS attached to a client.
+pub async fn set_client_vps(pool: DuckDbPool, user_id: i32, id: i32, vps_ids: &[i32]) -> Result<(), AppError> {
+    let mut conn = pool.get()?;
+    let client = get_client_in_organization(&conn, user_id, id)?;
+    if !vps_ids.is_empty() {
+        let found: i64 = conn.query_row(
+            &format!(
+                "SELECT COUNT(*) FROM vps WHERE organization_id = ? AND id IN ({})",
+                vec!["?"; vps_ids.len()].join(", ")
+            ),
+            params_from_iter(std::iter::once(&client.organization_id).chain(vps_ids.iter())),
+            |row| row.get(0),
+        )?;
+        if found != vps_ids.len() as i64 {
+            return Err(AppError::NotFound("One or more VPS were not found".to_string()));
+        }
+    }
+    let tx = conn.transaction()?;
+    tx.execute("UPDATE vps SET client_id = NULL WHERE client_id = ?", params![id])?;
+    let mut stmt = tx.prepare("UPDATE vps SET client_id = ? WHERE id = ?")?;
+    for vps_id in vps_ids {
+        stmt.execute(params![id, vps_id])?;
+    }
+    drop(stmt);
+    tx.commit()?;
+    Ok(())
+}
+
+/// Looks up a client without an ownership check, for share links.
+pub async fn get_client_by_id(pool: DuckDbPool, id: i32) -> Result<Option<client::Model>, AppError> {
+    let conn = pool.get()?;
+    Ok(conn
+        .query_row("SELECT * FROM clients WHERE id = ?", params![id], row_to_client_model)
+        .optional()?)
+}
+
+/// Cost and uptime of the client's VPS within `[start, end]`.
+/// Which VPS belong to the client is read from `pool`, the main database; only
+/// their availability comes from `reporting_pool`, which may lag behind.
+pub async fn build_client_report(
+    pool: DuckDbPool,
+    reporting_pool: DuckDbPool,
+    client: client::Model,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<ClientReport, AppError> {
+    let conn = pool.get()?;
+    let reporting_conn = reporting_pool.get()?;
+    let grace_seconds = uptime_service::load_grace_seconds(&conn)?;
+    let period_seconds = (end - start).num_seconds().max(0);
+
+    let rows = conn
+        .prepare(
+            "SELECT v.id, v.name, r.renewal_price, r.renewal_currency, r.renewal_cycle, r.renewal_cycle_custom_days
+             FROM vps v
+             LEFT JOIN vps_renewal_info r ON r.vps_id = v.id
+             WHERE v.client_id = ?
+             ORDER BY v.name, v.id",
+        )?
+        .query_map(params![client.id], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, Option<f64>>(2)?,
+                row.get::<_, Option<String>>(3)?,
+                row.get::<_, Option<String>>(4)?,
+                row.get::<_, Option<i32>>(5)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut entries = Vec::with_capacity(rows.len());
+    for (vps_id, name, price, currency, cycle, custom_days) in rows {
+        let availability = uptime_service::availability_since(&reporting_conn, vps_id, start, end, grace_seconds)?;
+        let cost = price
+            .zip(cycle.as_deref().and_then(|cycle| cycle_days(cycle, custom_days)))
+            .map(|(price, days)| prorated_cost(price, days, period_seconds));
+        entries.push(ClientVpsCostEntry {
+            vps_id,
+            name,
+            currency,
+            cost,
+            billed: cost.map(|cost| with_markup(cost, client.markup_percent)),
+            uptime_percent: availability.uptime_percent,
+            downtime_seconds: availability.downtime_seconds,
+        });
+    }
+
+    let known_uptimes: Vec<f64> = entries.iter().filter_map(|e| e.uptime_percent).collect();
+    let uptime_percent = (!known_uptimes.is_empty())
+        .then(|| known_uptimes.iter().sum::<f64>() / known_uptimes.len() as f64);
+    Ok(ClientReport {
+        totals: currency_totals(&entries),
+        client,
+        start,
+        end,
+        entries,
+        uptime_percent,
+    })
+}
+
+/// Sums costs per currency, sorted by currency code.
+fn currency_totals(entries: &[ClientVpsCostEntry]) -> Vec<CurrencyTotal> {
+    let mut totals: BTreeMap<&str, (f64, f64)> = BTreeMap::new();
+    for entry in entries {
+        if let (Some(cost), Some(billed)) = (entry.cost, entry.billed) {
+            let total = totals.entry(entry.currency.as_deref().unwrap_or("")).or_default();
+            total.0 += cost;
+            total.1 += billed;
+        }
+    }
+    let round = |value: f64| (value * 100.0).round() / 100.0;
+    totals
+        .into_iter()
+        .map(|(currency, (cost, billed))| CurrencyTotal {
+            currency: currency.to_string(),
+            cost: round(cost),
+            billed: round(billed),
+            commission: round(billed - cost),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(currency: &str, cost: Option<f64>, billed: Option<f64>) -> ClientVpsCostEntry {
+        ClientVpsCostEntry {
+            vps_id: 1,
+            name: "web".to_string(),
+            currency: Some(currency.to_string()),
+            cost,
+            billed,
+            uptime_percent: None,
+            downtime_seconds: 0,
+        }
+    }
+
+    #[test]
+    fn annual_price_is_prorated_over_the_period() {
+        let days = cycle_days("annually", None).unwrap();
+        // A full year's worth of seconds costs the full price.
+        assert_eq!(prorated_cost(120.0, days, (days * 86_400.0) as i64), 120.0);
+        assert_eq!(prorated_cost(120.0, days, (days * 86_400.0 / 12.0) as i64), 10.0);
+        assert_eq!(cycle_days("custom_days", Some(10)), Some(10.0));
+        assert_eq!(cycle_days("custom_days", None), None);
+        assert_eq!(cycle_days("weird", None), None);
+    }
+
+    #[test]
+    fn markup_and_totals_are_kept_per_currency() {
+        assert_eq!(with_markup(10.0, 25.0), 12.5);
+        let totals = currency_totals(&[
+            entry("USD", Some(10.0), Some(12.5)),
+            entry("EUR", Some(5.0), Some(6.25)),
+            entry("USD", Some(2.0), Some(2.5)),
+            entry("USD", None, None),
+        ]);
+        assert_eq!(totals.len(), 2);
+        assert_eq!(totals[0].currency, "EUR");
+        assert_eq!(totals[1].cost, 12.0);
+        assert_eq!(totals[1].billed, 15.0);
+        assert_eq!(totals[1].commission, 3.0);
+    }
+}
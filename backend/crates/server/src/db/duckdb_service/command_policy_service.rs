@@ -0,0 +1,374 @@
+//! Allow/deny policies for running commands. Batch commands are checked
+//! against the policies of each target's organization before any child task
+//! is created; a dry run reports the same decisions without running anything.
+
+use chrono::Utc;
+use duckdb::{params, Connection, OptionalExt, Result as DuckDbResult, Row};
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
+use crate::db::entities::command_policy;
+use crate::web::error::AppError;
+use crate::web::models::command_policy_models::{CreateCommandPolicyRequest, UpdateCommandPolicyRequest};
+
+pub const EFFECT_ALLOW: &str = "allow";
+pub const EFFECT_DENY: &str = "deny";
+
+fn json_column<T: serde::de::DeserializeOwned>(row: &Row, column: &str) -> DuckDbResult<T> {
+    let raw: String = row.get(column)?;
+    serde_json::from_str(&raw).map_err(|e| {
+        duckdb::Error::FromSqlConversionFailure(0, duckdb::types::Type::Text, Box::new(e))
+    })
+}
+
+fn row_to_command_policy_model(row: &Row) -> DuckDbResult<command_policy::Model> {
+    Ok(command_policy::Model {
+        id: row.get("id")?,
+        organization_id: row.get("organization_id")?,
+        name: row.get("name")?,
+        effect: row.get("effect")?,
+        user_ids: json_column(row, "user_ids")?,
+        script_ids: json_column(row, "script_ids")?,
+        group_ids: json_column(row, "group_ids")?,
+        tag_ids: json_column(row, "tag_ids")?,
+        enabled: row.get("enabled")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+/// A target VPS as policies see it.
+#[derive(Debug, Clone, Default)]
+pub struct PolicyTarget {
+    /// The VPS's group and every group above it.
+    pub group_ids: Vec<i32>,
+    pub tag_ids: Vec<i32>,
+}
+
+/// Whether a command may run on one target, and why.
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TargetDecision {
+    pub vps_id: i32,
+    pub vps_name: Option<String>,
+    pub allowed: bool,
+    /// The policy that decided, if one did.
+    pub policy_id: Option<i32>,
+    pub reason: String,
+}
+
+fn validate_effect(effect: &str) -> Result<(), AppError> {
+    if effect != EFFECT_ALLOW && effect != EFFECT_DENY {
+        return Err(AppError::InvalidInput(format!(
+            "Policy effect must be '{EFFECT_ALLOW}' or '{EFFECT_DENY}'"
+        )));
+    }
+    Ok(())
+}
+
+fn validate_name(name: &str) -> Result<&str, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Policy name must not be empty".to_string()));
+    }
+    Ok(name)
+}
+
+fn applies_to(policy: &command_policy::Model, user_id: i32, script_id: Option<i32>, target: &PolicyTarget) -> bool {
+    let user_matches = policy.user_ids.is_empty() || policy.user_ids.contains(&user_id);
+    let script_matches =
+        policy.script_ids.is_empty() || script_id.is_some_and(|id| policy.script_ids.contains(&id));
+    let target_matches = (policy.group_ids.is_empty() && policy.tag_ids.is_empty())
+        || policy.group_ids.iter().any(|id| target.group_ids.contains(id))
+        || policy.tag_ids.iter().any(|id| target.tag_ids.contains(id));
+    policy.enabled && user_matches && script_matches && target_matches
+}
+
+/// Decides one target against the policies of its organization. A matching
+/// deny wins; otherwise, once any allow policy exists, one of them has to
+/// match. Without policies everything is allowed.
+pub fn decide<'a>(
+    policies: &'a [command_policy::Model],
+    user_id: i32,
+    script_id: Option<i32>,
+    target: &PolicyTarget,
+) -> (bool, Option<&'a command_policy::Model>) {
+    let matching = |effect: &str| {
+        policies
+            .iter()
+            .find(|policy| policy.effect == effect && applies_to(policy, user_id, script_id, target))
+    };
+    if let Some(deny) = matching(EFFECT_DENY) {
+        return (false, Some(deny));
+    }
+    if let Some(allow) = matching(EFFECT_ALLOW) {
+        return (true, Some(allow));
+    }
+    let allow_list = policies.iter().any(|policy| policy.enabled && policy.effect == EFFECT_ALLOW);
+    (!allow_list, None)
+}
+
+/// Parent of every group in the organization.
+fn group_parents(conn: &Connection, organization_id: i32) -> DuckDbResult<HashMap<i32, Option<i32>>> {
+    conn.prepare("SELECT id, parent_id FROM vps_groups WHERE organization_id = ?")?
+        .query_map(params![organization_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect()
+}
+
+fn with_ancestors(parents: &HashMap<i32, Option<i32>>, group_id: Option<i32>) -> Vec<i32> {
+    let mut groups = Vec::new();
+    let mut current = group_id;
+    // Bounded by the group count in case the table holds a cycle.
+    while let Some(id) = current {
+        if groups.len() > parents.len() {
+            break;
+        }
+        groups.push(id);
+        current = parents.get(&id).copied().flatten();
+    }
+    groups
+}
+
+/// Checks a command by `user_id` (of `script_id`, or ad hoc when `None`)
+/// against every target. Targets that don't exist are denied.
+pub(crate) fn evaluate_targets(
+    conn: &Connection,
+    user_id: i32,
+    script_id: Option<i32>,
+    vps_ids: &[i32],
+) -> DuckDbResult<Vec<TargetDecision>> {
+    let mut policies: HashMap<i32, Vec<command_policy::Model>> = HashMap::new();
+    let mut parents: HashMap<i32, HashMap<i32, Option<i32>>> = HashMap::new();
+    let mut decisions = Vec::with_capacity(vps_ids.len());
+
+    for &vps_id in vps_ids {
+        let vps: Option<(String, Option<i32>, Option<i32>)> = conn
+            .query_row(
+                "SELECT name, organization_id, group_id FROM vps WHERE id = ?",
+                params![vps_id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .optional()?;
+        let Some((name, organization_id, group_id)) = vps else {
+            decisions.push(TargetDecision {
+                vps_id,
+                vps_name: None,
+                allowed: false,
+                policy_id: None,
+                reason: "VPS not found".to_string(),
+            });
+            continue;
+        };
+
+        let (org_policies, group_ids) = match organization_id {
+            Some(organization_id) => {
+                if !policies.contains_key(&organization_id) {
+                    let loaded = conn
+                        .prepare("SELECT * FROM command_policies WHERE organization_id = ? AND enabled ORDER BY id")?
+                        .query_map(params![organization_id], row_to_command_policy_model)?
+                        .collect::<DuckDbResult<Vec<_>>>()?;
+                    policies.insert(organization_id, loaded);
+                    parents.insert(organization_id, group_parents(conn, organization_id)?);
+                }
+                (&policies[&organization_id][..], with_ancestors(&parents[&organization_id], group_id))
+            }
+            None => (&[][..], Vec::new()),
+        };
+        let tag_ids = conn
+            .prepare("SELECT tag_id FROM vps_tags WHERE vps_id = ?")?
+            .query_map(params![vps_id], |row| row.get(0))?
+            .collect::<DuckDbResult<Vec<i32>>>()?;
+
+        let target = PolicyTarget { group_ids, tag_ids };
+        let (allowed, policy) = decide(org_policies, user_id, script_id, &target);
+        let reason = match (allowed, policy) {
+            (true, Some(policy)) => format!("Allowed by policy '{}'", policy.name),
+            (false, Some(policy)) => format!("Denied by policy '{}'", policy.name),
+            (true, None) => "No policy applies".to_string(),
+            (false, None) => "No allow policy matches".to_string(),
+        };
+        decisions.push(TargetDecision {
+            vps_id,
+            vps_name: Some(name),
+            allowed,
+            policy_id: policy.map(|policy| policy.id),
+            reason,
+        });
+    }
+    Ok(decisions)
+}
+
+/// Decisions for a command by `user_id` on `vps_ids`, without running it.
+pub async fn dry_run(
+    pool: DuckDbPool,
+    user_id: i32,
+    script_id: Option<i32>,
+    vps_ids: &[i32],
+) -> Result<Vec<TargetDecision>, AppError> {
+    let conn = pool.get()?;
+    Ok(evaluate_targets(&conn, user_id, script_id, vps_ids)?)
+}
+
+fn get_policy(conn: &Connection, user_id: i32, id: i32) -> Result<command_policy::Model, AppError> {
+    conn.query_row(
+        &format!(
+            "SELECT * FROM command_policies WHERE id = ? AND {}",
+            organization_service::org_scope("organization_id")
+        ),
+        params![id, user_id],
+        row_to_command_policy_model,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound("Command policy not found".to_string()))
+}
+
+pub async fn get_policies(pool: DuckDbPool, user_id: i32) -> Result<Vec<command_policy::Model>, AppError> {
+    let conn = pool.get()?;
+    let policies = conn
+        .prepare(&format!(
+            "SELECT * FROM command_policies WHERE {} ORDER BY id",
+            organization_service::org_scope("organization_id")
+        ))?
+        .query_map(params![user_id], row_to_command_policy_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(policies)
+}
+
+pub async fn create_policy(
+    pool: DuckDbPool,
+    user_id: i32,
+    request: CreateCommandPolicyRequest,
+) -> Result<command_policy::Model, AppError> {
+    let name = validate_name(&request.name)?;
+    validate_effect(&request.effect)?;
+    let conn = pool.get()?;
+    let organization_id = organization_service::active_organization_id(&conn, user_id)?;
+    Ok(conn.query_row(
+        "INSERT INTO command_policies (organization_id, name, effect, user_ids, script_ids, group_ids, tag_ids, enabled)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        params![
+            organization_id,
+            name,
+            request.effect,
+            serde_json::to_string(&request.user_ids)?,
+            serde_json::to_string(&request.script_ids)?,
+            serde_json::to_string(&request.group_ids)?,
+            serde_json::to_string(&request.tag_ids)?,
+            request.enabled.unwrap_or(true),
+        ],
+        row_to_command_policy_model,
+    )?)
+}
+
+pub async fn update_policy(
+    pool: DuckDbPool,
+    user_id: i32,
+    id: i32,
+    request: UpdateCommandPolicyRequest,
+) -> Result<command_policy::Model, AppError> {
+    let conn = pool.get()?;
+    let existing = get_policy(&conn, user_id, id)?;
+    let name = match &request.name {
+        Some(name) => validate_name(name)?.to_string(),
+        None => existing.name,
+    };
+    let effect = request.effect.unwrap_or(existing.effect);
+    validate_effect(&effect)?;
+    Ok(conn.query_row(
+        "UPDATE command_policies
+         SET name = ?, effect = ?, user_ids = ?, script_ids = ?, group_ids = ?, tag_ids = ?, enabled = ?, updated_at = ?
+         WHERE id = ? RETURNING *",
+        params![
+            name,
+            effect,
+            serde_json::to_string(&request.user_ids.unwrap_or(existing.user_ids))?,
+            serde_json::to_string(&request.script_ids.unwrap_or(existing.script_ids))?,
+            serde_json::to_string(&request.group_ids.unwrap_or(existing.group_ids))?,
+            serde_json::to_string(&request.tag_ids.unwrap_or(existing.tag_ids))?,
+            request.enabled.unwrap_or(existing.enabled),
+            Utc::now(),
+            id,
+        ],
+        row_to_command_policy_model,
+    )?)
+}
+
+pub async fn delete_policy(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute(
+        &format!(
+            "DELETE FROM command_policies WHERE id = ? AND {}",
+            organization_service::org_scope("organization_id")
+        ),
+        params![id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("Command policy not found".to_string()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy(id: i32, effect: &str) -> command_policy::Model {
+        command_policy::Model {
+            id,
+            organization_id: 1,
+            name: format!("policy {id}"),
+            effect: effect.to_string(),
+            user_ids: Vec::new(),
+            script_ids: Vec::new(),
+            group_ids: Vec::new(),
+            tag_ids: Vec::new(),
+            enabled: true,
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn target(group_ids: Vec<i32>, tag_ids: Vec<i32>) -> PolicyTarget {
+        PolicyTarget { group_ids, tag_ids }
+    }
+
+    #[test]
+    fn deny_wins_over_allow() {
+        let mut deny = policy(2, EFFECT_DENY);
+        deny.tag_ids = vec![7];
+        let policies = [policy(1, EFFECT_ALLOW), deny];
+
+        let (allowed, decided_by) = decide(&policies, 10, None, &target(vec![], vec![7]));
+        assert!(!allowed);
+        assert_eq!(decided_by.map(|p| p.id), Some(2));
+        assert!(decide(&policies, 10, None, &target(vec![], vec![8])).0);
+        assert_eq!(decide(&[], 10, None, &target(vec![], vec![])), (true, None));
+    }
+
+    #[test]
+    fn allow_policies_turn_into_an_allow_list() {
+        let mut allow = policy(1, EFFECT_ALLOW);
+        allow.user_ids = vec![10];
+        allow.script_ids = vec![3];
+        allow.group_ids = vec![5];
+        let policies = [allow];
+
+        // Group 5 is an ancestor of the VPS's group.
+        assert!(decide(&policies, 10, Some(3), &target(vec![6, 5], vec![])).0);
+        assert!(!decide(&policies, 11, Some(3), &target(vec![6, 5], vec![])).0);
+        assert!(!decide(&policies, 10, Some(4), &target(vec![6, 5], vec![])).0);
+        assert!(!decide(&policies, 10, None, &target(vec![6, 5], vec![])).0);
+        assert!(!decide(&policies, 10, Some(3), &target(vec![6], vec![])).0);
+    }
+
+    #[test]
+    fn ancestors_stop_at_the_top_and_on_cycles() {
+        let parents = HashMap::from([(1, None), (2, Some(1)), (3, Some(2))]);
+        assert_eq!(with_ancestors(&parents, Some(3)), vec![3, 2, 1]);
+        assert!(with_ancestors(&parents, None).is_empty());
+
+        let cycle = HashMap::from([(1, Some(2)), (2, Some(1))]);
+        assert!(with_ancestors(&cycle, Some(1)).len() <= 3);
+    }
+}
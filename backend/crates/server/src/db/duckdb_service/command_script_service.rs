@@ -1,5 +1,6 @@
 use crate::db::duckdb_service::DuckDbPool;
 use crate::db::entities::command_script::ScriptLanguage;
+use crate::services::script_template::{self, ScriptParameter};
 use crate::web::error::AppError;
 use chrono::{DateTime, Utc};
 use duckdb::{params, types::{FromSql, FromSqlError, FromSqlResult, ToSql, ToSqlOutput, ValueRef}, Result as DuckDbResult, Row};
@@ -16,6 +17,8 @@ pub struct CommandScript {
     pub language: ScriptLanguage,
     pub script_content: String,
     pub working_directory: String,
+    /// Values filled into `{{name}}` placeholders in `script_content`.
+    pub parameters: Vec<ScriptParameter>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -32,6 +35,10 @@ pub enum CommandScriptServiceError {
     Unauthorized,
     #[error("A script with the name '{0}' already exists.")]
     DuplicateName(String),
+    #[error("Invalid script parameters: {0}")]
+    InvalidParameters(String),
+    #[error("Serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
     #[error("Tokio join error: {0}")]
     JoinError(#[from] JoinError),
 }
@@ -39,11 +46,13 @@ pub enum CommandScriptServiceError {
 impl From<CommandScriptServiceError> for AppError {
     fn from(err: CommandScriptServiceError) -> Self {
         match err {
-            CommandScriptServiceError::DbErr(e) => AppError::DatabaseError(e.to_string()),
-            CommandScriptServiceError::PoolError(e) => AppError::DatabaseError(e.to_string()),
+            CommandScriptServiceError::DbErr(e) => e.into(),
+            CommandScriptServiceError::PoolError(e) => e.into(),
             CommandScriptServiceError::NotFound(id) => AppError::NotFound(format!("Script with ID {id} not found")),
             CommandScriptServiceError::Unauthorized => AppError::Unauthorized("You are not authorized to perform this action.".to_string()),
             CommandScriptServiceError::DuplicateName(name) => AppError::Conflict(format!("A script with the name '{name}' already exists.")),
+            CommandScriptServiceError::InvalidParameters(message) => AppError::InvalidInput(message),
+            CommandScriptServiceError::Serialization(e) => AppError::InternalServerError(e.to_string()),
             CommandScriptServiceError::JoinError(e) => AppError::InternalServerError(e.to_string()),
         }
     }
@@ -75,6 +84,13 @@ impl FromSql for ScriptLanguage {
     }
 }
 
+fn json_column<T: serde::de::DeserializeOwned>(row: &Row, column: &str) -> DuckDbResult<T> {
+    let raw: String = row.get(column)?;
+    serde_json::from_str(&raw).map_err(|e| {
+        duckdb::Error::FromSqlConversionFailure(0, duckdb::types::Type::Text, Box::new(e))
+    })
+}
+
 fn row_to_command_script(row: &Row) -> DuckDbResult<CommandScript> {
     Ok(CommandScript {
         id: row.get("id")?,
@@ -84,6 +100,7 @@ fn row_to_command_script(row: &Row) -> DuckDbResult<CommandScript> {
         language: row.get("language")?,
         script_content: row.get("script_content")?,
         working_directory: row.get("working_directory")?,
+        parameters: json_column(row, "parameters")?,
         created_at: row.get("created_at")?,
         updated_at: row.get("updated_at")?,
     })
@@ -97,7 +114,11 @@ pub async fn create_script(
     language: ScriptLanguage,
     script_content: String,
     working_directory: String,
+    parameters: Vec<ScriptParameter>,
 ) -> Result<CommandScript, CommandScriptServiceError> {
+    script_template::validate_definition(&script_content, &parameters)
+        .map_err(CommandScriptServiceError::InvalidParameters)?;
+    let parameters = serde_json::to_string(&parameters)?;
     let pool = db_pool.clone();
     let name_clone = name.clone();
     tokio::task::spawn_blocking(move || {
@@ -119,8 +140,8 @@ pub async fn create_script(
         let conn = pool.get()?;
         let now = Utc::now();
         let mut stmt = conn.prepare(
-            "INSERT INTO command_scripts (user_id, name, description, language, script_content, working_directory, created_at, updated_at)
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+            "INSERT INTO command_scripts (user_id, name, description, language, script_content, working_directory, parameters, created_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
         )?;
         let script = stmt.query_row(
             params![
@@ -130,6 +151,7 @@ pub async fn create_script(
                 language,
                 script_content,
                 working_directory,
+                parameters,
                 now,
                 now,
             ],
@@ -177,13 +199,17 @@ pub async fn update_script(
     language: ScriptLanguage,
     script_content: String,
     working_directory: String,
+    parameters: Vec<ScriptParameter>,
 ) -> Result<CommandScript, CommandScriptServiceError> {
+    script_template::validate_definition(&script_content, &parameters)
+        .map_err(CommandScriptServiceError::InvalidParameters)?;
+    let parameters = serde_json::to_string(&parameters)?;
     let pool = db_pool.clone();
     tokio::task::spawn_blocking(move || {
         let conn = pool.get()?;
         let now = Utc::now();
         let mut stmt = conn.prepare(
-            "UPDATE command_scripts SET name = ?, description = ?, language = ?, script_content = ?, working_directory = ?, updated_at = ?
+            "UPDATE command_scripts SET name = ?, description = ?, language = ?, script_content = ?, working_directory = ?, parameters = ?, updated_at = ?
              WHERE id = ? AND user_id = ? RETURNING *",
         )?;
         let script = stmt.query_row(
@@ -193,6 +219,7 @@ pub async fn update_script(
                 language,
                 script_content,
                 working_directory,
+                parameters,
                 now,
                 script_id,
                 user_id,
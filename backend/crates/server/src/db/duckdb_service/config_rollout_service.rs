@@ -0,0 +1,242 @@
+use chrono::Utc;
+use duckdb::{params, Connection, OptionalExt, Result as DuckDbResult, Row};
+use serde::Serialize;
+use std::sync::Mutex;
+
+use crate::db::duckdb_service::{json_from_row, DuckDbPool};
+use crate::db::entities::{config_rollout, config_rollout_target};
+use crate::web::error::AppError;
+
+pub const STATUS_RUNNING: &str = "running";
+pub const STATUS_COMPLETED: &str = "completed";
+pub const STATUS_ROLLED_BACK: &str = "rolled_back";
+pub const STATUS_ABORTED: &str = "aborted";
+
+pub const TARGET_WAITING: &str = "waiting";
+pub const TARGET_APPLIED: &str = "applied";
+pub const TARGET_HEALTHY: &str = "healthy";
+pub const TARGET_FAILED: &str = "failed";
+pub const TARGET_SKIPPED: &str = "skipped";
+pub const TARGET_ROLLED_BACK: &str = "rolled_back";
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigRolloutDetails {
+    #[serde(flatten)]
+    pub rollout: config_rollout::Model,
+    pub targets: Vec<config_rollout_target::Model>,
+}
+
+fn row_to_rollout_model(row: &Row) -> DuckDbResult<config_rollout::Model> {
+    Ok(config_rollout::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        status: row.get("status")?,
+        config: json_from_row(row, "config")?.unwrap_or_default(),
+        health_window_seconds: row.get("health_window_seconds")?,
+        current_stage: row.get("current_stage")?,
+        stage_count: row.get("stage_count")?,
+        error: row.get("error")?,
+        created_at: row.get("created_at")?,
+        finished_at: row.get("finished_at")?,
+    })
+}
+
+fn row_to_target_model(row: &Row) -> DuckDbResult<config_rollout_target::Model> {
+    Ok(config_rollout_target::Model {
+        rollout_id: row.get("rollout_id")?,
+        vps_id: row.get("vps_id")?,
+        stage: row.get("stage")?,
+        status: row.get("status")?,
+        error: row.get("error")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+/// Splits `vps_ids` into rollout stages: the canaries first, then batches of
+/// `batch_size` (all remaining VPS at once when 0). Explicit `canary_ids`
+/// win over `canary_count`, which takes the lowest IDs.
+pub fn plan_stages(
+    vps_ids: &[i32],
+    canary_ids: &[i32],
+    canary_count: usize,
+    batch_size: usize,
+) -> Result<Vec<Vec<i32>>, String> {
+    let mut remaining: Vec<i32> = vps_ids.to_vec();
+    remaining.sort_unstable();
+    remaining.dedup();
+
+    let canaries: Vec<i32> = if canary_ids.is_empty() {
+        remaining.iter().copied().take(canary_count.max(1)).collect()
+    } else {
+        if let Some(unknown) = canary_ids.iter().find(|id| !remaining.contains(id)) {
+            return Err(format!("Canary VPS {unknown} is not part of the rollout."));
+        }
+        canary_ids.to_vec()
+    };
+    if canaries.is_empty() {
+        return Err("There are no VPS to roll out to.".to_string());
+    }
+    remaining.retain(|id| !canaries.contains(id));
+
+    let mut stages = vec![canaries];
+    if !remaining.is_empty() {
+        let batch_size = if batch_size == 0 { remaining.len() } else { batch_size };
+        stages.extend(remaining.chunks(batch_size).map(<[i32]>::to_vec));
+    }
+    Ok(stages)
+}
+
+/// Held while checking for a running rollout and inserting a new one, since
+/// two concurrent transactions would both see none running.
+static CREATE_LOCK: Mutex<()> = Mutex::new(());
+
+fn running_rollout(conn: &Connection) -> DuckDbResult<Option<i32>> {
+    conn.query_row(
+        "SELECT id FROM config_rollouts WHERE status = ? LIMIT 1",
+        params![STATUS_RUNNING],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// The rollout that is still running, if any.
+pub async fn running_rollout_id(pool: DuckDbPool) -> Result<Option<i32>, AppError> {
+    let conn = pool.get()?;
+    Ok(running_rollout(&conn)?)
+}
+
+/// Creates a running rollout with its targets. Only one rollout may run at a
+/// time, since they all change the same global config.
+pub async fn create_rollout(
+    pool: DuckDbPool,
+    user_id: i32,
+    config: &serde_json::Value,
+    health_window_seconds: i32,
+    stages: &[Vec<i32>],
+) -> Result<ConfigRolloutDetails, AppError> {
+    let rollout_id = {
+        let _guard = CREATE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut conn = pool.get()?;
+        if let Some(running) = running_rollout(&conn)? {
+            return Err(AppError::Conflict(format!("Config rollout {running} is still running.")));
+        }
+        let tx = conn.transaction()?;
+
+        let rollout = tx.query_row(
+            "INSERT INTO config_rollouts (user_id, status, config, health_window_seconds, stage_count)
+             VALUES (?, ?, ?, ?, ?) RETURNING *",
+            params![
+                user_id,
+                STATUS_RUNNING,
+                serde_json::to_string(config)?,
+                health_window_seconds,
+                stages.len() as i32
+            ],
+            row_to_rollout_model,
+        )?;
+        let now = Utc::now();
+        for (stage, vps_ids) in stages.iter().enumerate() {
+            for vps_id in vps_ids {
+                tx.execute(
+                    "INSERT INTO config_rollout_targets (rollout_id, vps_id, stage, status, updated_at) VALUES (?, ?, ?, ?, ?)",
+                    params![rollout.id, vps_id, stage as i32, TARGET_WAITING, now],
+                )?;
+            }
+        }
+        tx.commit()?;
+        rollout.id
+    };
+    get_rollout(pool, rollout_id)
+        .await?
+        .ok_or_else(|| AppError::InternalServerError("Created rollout not found".to_string()))
+}
+
+pub async fn get_rollout(pool: DuckDbPool, id: i32) -> Result<Option<ConfigRolloutDetails>, AppError> {
+    let conn = pool.get()?;
+    let Some(rollout) = conn
+        .query_row("SELECT * FROM config_rollouts WHERE id = ?", params![id], row_to_rollout_model)
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    let targets = conn
+        .prepare("SELECT * FROM config_rollout_targets WHERE rollout_id = ? ORDER BY stage, vps_id")?
+        .query_map(params![id], row_to_target_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Some(ConfigRolloutDetails { rollout, targets }))
+}
+
+/// Rollouts without their targets, newest first.
+pub async fn list_rollouts(pool: DuckDbPool) -> Result<Vec<config_rollout::Model>, AppError> {
+    let conn = pool.get()?;
+    let rollouts = conn
+        .prepare("SELECT * FROM config_rollouts ORDER BY created_at DESC, id DESC LIMIT 50")?
+        .query_map([], row_to_rollout_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rollouts)
+}
+
+pub async fn set_current_stage(pool: DuckDbPool, id: i32, stage: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    conn.execute("UPDATE config_rollouts SET current_stage = ? WHERE id = ?", params![stage, id])?;
+    Ok(())
+}
+
+pub async fn set_target_status(
+    pool: DuckDbPool,
+    rollout_id: i32,
+    vps_id: i32,
+    status: &str,
+    error: Option<&str>,
+) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE config_rollout_targets SET status = ?, error = ?, updated_at = ? WHERE rollout_id = ? AND vps_id = ?",
+        params![status, error, Utc::now(), rollout_id, vps_id],
+    )?;
+    Ok(())
+}
+
+pub async fn finish_rollout(pool: DuckDbPool, id: i32, status: &str, error: Option<&str>) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE config_rollouts SET status = ?, error = ?, finished_at = ? WHERE id = ?",
+        params![status, error, Utc::now(), id],
+    )?;
+    Ok(())
+}
+
+/// Marks rollouts interrupted by a server restart as aborted. Agents that got
+/// the new config fall back to the unchanged global config on reconnect.
+pub async fn abort_unfinished_rollouts(pool: DuckDbPool) -> Result<usize, AppError> {
+    let conn = pool.get()?;
+    let aborted = conn.execute(
+        "UPDATE config_rollouts SET status = ?, error = ?, finished_at = ? WHERE status = ?",
+        params![STATUS_ABORTED, "Interrupted by a server restart.", Utc::now(), STATUS_RUNNING],
+    )?;
+    Ok(aborted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn canaries_come_first_then_batches() {
+        let stages = plan_stages(&[5, 1, 4, 2, 3], &[], 1, 2).unwrap();
+        assert_eq!(stages, vec![vec![1], vec![2, 3], vec![4, 5]]);
+    }
+
+    #[test]
+    fn explicit_canaries_and_single_batch() {
+        let stages = plan_stages(&[1, 2, 3, 4], &[3], 0, 0).unwrap();
+        assert_eq!(stages, vec![vec![3], vec![1, 2, 4]]);
+    }
+
+    #[test]
+    fn unknown_canary_is_rejected() {
+        assert!(plan_stages(&[1, 2], &[9], 0, 0).is_err());
+        assert!(plan_stages(&[], &[], 1, 0).is_err());
+    }
+}
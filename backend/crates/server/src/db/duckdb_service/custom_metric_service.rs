@@ -0,0 +1,126 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, Result as DuckDbResult, Row};
+use std::collections::BTreeMap;
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::web::error::AppError;
+use crate::web::models::ingest_models::{CustomMetricPoint, CustomMetricSeries};
+
+/// Alert rules reference a custom metric by name through this metric type.
+pub const CUSTOM_METRIC_TYPE_PREFIX: &str = "custom:";
+
+pub fn parse_custom_metric_type(metric_type: &str) -> Option<&str> {
+    metric_type
+        .strip_prefix(CUSTOM_METRIC_TYPE_PREFIX)
+        .filter(|name| !name.is_empty())
+}
+
+fn labels_column(row: &Row, column: &str) -> DuckDbResult<BTreeMap<String, String>> {
+    let raw: String = row.get(column)?;
+    serde_json::from_str(&raw).map_err(|e| {
+        duckdb::Error::FromSqlConversionFailure(0, duckdb::types::Type::Text, Box::new(e))
+    })
+}
+
+/// Every metric name and label set attributed to the VPS, whether pushed with
+/// an ingest key, to `/api/vps/{id}/custom-metrics` or by the agent itself.
+pub async fn list_custom_metrics(pool: DuckDbPool, vps_id: i32) -> Result<Vec<CustomMetricSeries>, AppError> {
+    let conn = pool.get()?;
+    let series = conn
+        .prepare(
+            r#"SELECT name, labels, arg_max(value, "time") AS last_value, MAX("time") AS last_seen
+               FROM generic_metrics
+               WHERE vps_id = ?
+               GROUP BY name, labels
+               ORDER BY name ASC, labels ASC"#,
+        )?
+        .query_map(params![vps_id], |row| {
+            Ok(CustomMetricSeries {
+                name: row.get("name")?,
+                labels: labels_column(row, "labels")?,
+                last_value: row.get("last_value")?,
+                last_seen: row.get("last_seen")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(series)
+}
+
+/// Samples of one metric, per label set. With an interval the samples are
+/// averaged per bucket and label set.
+pub async fn get_custom_metric_timeseries(
+    pool: DuckDbPool,
+    vps_id: i32,
+    name: &str,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    interval_seconds: Option<u32>,
+) -> Result<Vec<CustomMetricPoint>, AppError> {
+    let sql = match interval_seconds {
+        None => r#"SELECT "time", labels, value FROM generic_metrics
+                   WHERE vps_id = ? AND name = ? AND "time" >= ? AND "time" <= ?
+                   ORDER BY "time" ASC, labels ASC"#
+            .to_string(),
+        Some(interval_secs) => {
+            let interval_secs = interval_secs.max(1);
+            format!(
+                r#"
+                SELECT
+                    date_trunc('second', "time") + INTERVAL '{interval_secs} seconds' * (epoch("time") / {interval_secs}) AS "time",
+                    labels,
+                    AVG(value) AS value
+                FROM generic_metrics
+                WHERE vps_id = ? AND name = ? AND "time" >= ? AND "time" <= ?
+                GROUP BY 1, labels
+                ORDER BY 1 ASC, labels ASC
+                "#
+            )
+        }
+    };
+
+    let conn = pool.get()?;
+    let points = conn
+        .prepare(&sql)?
+        .query_map(params![vps_id, name, start_time, end_time], |row| {
+            Ok(CustomMetricPoint {
+                time: row.get("time")?,
+                labels: labels_column(row, "labels")?,
+                value: row.get("value")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(points)
+}
+
+/// Every sample of a metric in the range across all its label sets, oldest first.
+pub async fn get_custom_metric_values(
+    pool: DuckDbPool,
+    vps_id: i32,
+    name: &str,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<Vec<f64>, AppError> {
+    let conn = pool.get()?;
+    let values = conn
+        .prepare(
+            r#"SELECT value FROM generic_metrics
+               WHERE vps_id = ? AND name = ? AND "time" >= ? AND "time" <= ?
+               ORDER BY "time" ASC"#,
+        )?
+        .query_map(params![vps_id, name, start_time, end_time], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(values)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_custom_metric_types() {
+        assert_eq!(parse_custom_metric_type("custom:myapp.queue_depth"), Some("myapp.queue_depth"));
+        assert_eq!(parse_custom_metric_type("custom:"), None);
+        assert_eq!(parse_custom_metric_type("derived:3"), None);
+        assert_eq!(parse_custom_metric_type("cpu_usage_percent"), None);
+    }
+}
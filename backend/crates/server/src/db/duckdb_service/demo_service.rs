@@ -0,0 +1,65 @@
+//! Demo servers: ordinary VPS rows owned by the admin account and marked with
+//! `metadata.demo = true`, so every view shows them like real servers while
+//! still telling them apart.
+
+use chrono::Utc;
+use duckdb::{params, OptionalExt};
+use uuid::Uuid;
+
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
+use crate::web::error::AppError;
+
+const DEMO_OS_TYPES: &[&str] = &["Ubuntu 24.04", "Debian 12", "Rocky Linux 9", "Alpine 3.20"];
+const DEMO_GROUPS: &[&str] = &["Demo / Frankfurt", "Demo / Singapore", "Demo / Virginia"];
+
+/// Makes sure `count` demo servers exist and returns their ids. Returns an
+/// empty list while there is no admin account to own them yet.
+pub async fn ensure_demo_servers(pool: DuckDbPool, count: u32) -> Result<Vec<i32>, AppError> {
+    let conn = pool.get()?;
+    let Some(owner_id) = conn
+        .query_row("SELECT id FROM users WHERE role = 'admin' ORDER BY id LIMIT 1", [], |row| {
+            row.get::<_, i32>(0)
+        })
+        .optional()?
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut ids = conn
+        .prepare(
+            "SELECT id FROM vps
+             WHERE json_extract(metadata, '$.demo')::BOOLEAN IS TRUE AND archived_at IS NULL
+             ORDER BY id",
+        )?
+        .query_map([], |row| row.get::<_, i32>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let organization_id = organization_service::active_organization_id(&conn, owner_id)?;
+    let now = Utc::now();
+    for n in ids.len() as u32..count {
+        let index = n as usize;
+        let id: i32 = conn.query_row(
+            "INSERT INTO vps (user_id, organization_id, name, ip_address, os_type, agent_secret, status, metadata,
+                 \"group\", created_at, updated_at, config_status, traffic_current_cycle_rx_bytes,
+                 traffic_current_cycle_tx_bytes, last_processed_cumulative_rx, last_processed_cumulative_tx)
+             VALUES (?, ?, ?, ?, ?, ?, 'online', ?, ?, ?, ?, 'synced', 0, 0, 0, 0) RETURNING id",
+            params![
+                owner_id,
+                organization_id,
+                format!("Demo Server {:02}", n + 1),
+                // TEST-NET-3, reserved for documentation.
+                format!("203.0.113.{}", n % 254 + 1),
+                DEMO_OS_TYPES[index % DEMO_OS_TYPES.len()],
+                Uuid::new_v4().to_string(),
+                serde_json::json!({ "demo": true }).to_string(),
+                DEMO_GROUPS[index % DEMO_GROUPS.len()],
+                now,
+                now,
+            ],
+            |row| row.get(0),
+        )?;
+        ids.push(id);
+    }
+    ids.truncate(count as usize);
+    Ok(ids)
+}
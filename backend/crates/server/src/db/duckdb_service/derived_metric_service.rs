@@ -0,0 +1,313 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, OptionalExt, Result as DuckDbResult, Row};
+
+use crate::db::duckdb_service::performance_service::select_metric_source;
+use crate::db::duckdb_service::retention::RetentionPolicy;
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::derived_metric;
+use crate::services::metric_expression::{parse_expression, ColumnSource, MetricColumn};
+use crate::web::error::AppError;
+use crate::web::models::derived_metric_models::{
+    CreateDerivedMetricRequest, DerivedMetricPoint, UpdateDerivedMetricRequest,
+};
+
+/// Alert rules reference a derived metric with `metric_type = "derived:<id>"`.
+pub const DERIVED_METRIC_TYPE_PREFIX: &str = "derived:";
+
+const MAX_NAME_LEN: usize = 64;
+
+pub fn parse_derived_metric_type(metric_type: &str) -> Option<i32> {
+    metric_type
+        .strip_prefix(DERIVED_METRIC_TYPE_PREFIX)
+        .and_then(|id| id.parse().ok())
+}
+
+fn row_to_derived_metric_model(row: &Row) -> DuckDbResult<derived_metric::Model> {
+    Ok(derived_metric::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        name: row.get("name")?,
+        expression: row.get("expression")?,
+        unit: row.get("unit")?,
+        description: row.get("description")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+/// Names are identifiers so they read well in charts and alert messages, and
+/// must not shadow a collected column.
+fn validate_name(name: &str) -> Result<(), AppError> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid {
+        return Err(AppError::InvalidInput(format!(
+            "Name must start with a letter and contain only letters, digits and underscores (max {MAX_NAME_LEN} characters)."
+        )));
+    }
+    if MetricColumn::from_name(name).is_some() {
+        return Err(AppError::InvalidInput(format!(
+            "'{name}' is a built-in metric name."
+        )));
+    }
+    Ok(())
+}
+
+fn validate_expression(expression: &str) -> Result<(), AppError> {
+    parse_expression(expression)
+        .map(|_| ())
+        .map_err(|e| AppError::InvalidInput(format!("Invalid expression: {e}")))
+}
+
+fn ensure_unique_name(
+    conn: &duckdb::Connection,
+    user_id: i32,
+    name: &str,
+    exclude_id: Option<i32>,
+) -> Result<(), AppError> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM derived_metrics WHERE user_id = ? AND name = ? AND id IS DISTINCT FROM ?",
+        params![user_id, name, exclude_id],
+        |row| row.get(0),
+    )?;
+    if count > 0 {
+        return Err(AppError::Conflict(format!(
+            "A derived metric named '{name}' already exists."
+        )));
+    }
+    Ok(())
+}
+
+pub async fn get_derived_metrics_for_user(
+    pool: DuckDbPool,
+    user_id: i32,
+) -> Result<Vec<derived_metric::Model>, AppError> {
+    let conn = pool.get()?;
+    let metrics = conn
+        .prepare("SELECT * FROM derived_metrics WHERE user_id = ? ORDER BY name")?
+        .query_map(params![user_id], row_to_derived_metric_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(metrics)
+}
+
+pub async fn get_derived_metric(
+    pool: DuckDbPool,
+    user_id: i32,
+    id: i32,
+) -> Result<Option<derived_metric::Model>, AppError> {
+    let conn = pool.get()?;
+    let metric = conn
+        .query_row(
+            "SELECT * FROM derived_metrics WHERE id = ? AND user_id = ?",
+            params![id, user_id],
+            row_to_derived_metric_model,
+        )
+        .optional()?;
+    Ok(metric)
+}
+
+pub async fn create_derived_metric(
+    pool: DuckDbPool,
+    user_id: i32,
+    payload: CreateDerivedMetricRequest,
+) -> Result<derived_metric::Model, AppError> {
+    let name = payload.name.trim();
+    validate_name(name)?;
+    validate_expression(&payload.expression)?;
+
+    let conn = pool.get()?;
+    ensure_unique_name(&conn, user_id, name, None)?;
+    let metric = conn.query_row(
+        "INSERT INTO derived_metrics (user_id, name, expression, unit, description)
+         VALUES (?, ?, ?, ?, ?) RETURNING *",
+        params![
+            user_id,
+            name,
+            payload.expression.trim(),
+            payload.unit,
+            payload.description
+        ],
+        row_to_derived_metric_model,
+    )?;
+    Ok(metric)
+}
+
+pub async fn update_derived_metric(
+    pool: DuckDbPool,
+    user_id: i32,
+    id: i32,
+    payload: UpdateDerivedMetricRequest,
+) -> Result<derived_metric::Model, AppError> {
+    let conn = pool.get()?;
+    let existing = conn
+        .query_row(
+            "SELECT * FROM derived_metrics WHERE id = ? AND user_id = ?",
+            params![id, user_id],
+            row_to_derived_metric_model,
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("Derived metric not found".to_string()))?;
+
+    let name = payload.name.as_deref().map(str::trim).unwrap_or(existing.name.as_str());
+    validate_name(name)?;
+    ensure_unique_name(&conn, user_id, name, Some(id))?;
+    let expression = payload
+        .expression
+        .as_deref()
+        .map(str::trim)
+        .unwrap_or(existing.expression.as_str());
+    validate_expression(expression)?;
+    let unit = payload.unit.or(existing.unit);
+    let description = payload.description.or(existing.description);
+
+    let metric = conn.query_row(
+        "UPDATE derived_metrics SET name = ?, expression = ?, unit = ?, description = ?, updated_at = ?
+         WHERE id = ? AND user_id = ? RETURNING *",
+        params![name, expression, unit, description, Utc::now(), id, user_id],
+        row_to_derived_metric_model,
+    )?;
+    Ok(metric)
+}
+
+/// Deletes a derived metric. Alert rules that still reference it are rejected
+/// rather than left evaluating a missing metric.
+pub async fn delete_derived_metric(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let referencing: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM alert_rules WHERE metric_type = ?",
+        params![format!("{DERIVED_METRIC_TYPE_PREFIX}{id}")],
+        |row| row.get(0),
+    )?;
+    if referencing > 0 {
+        return Err(AppError::Conflict(format!(
+            "The derived metric is used by {referencing} alert rule(s)."
+        )));
+    }
+    let deleted = conn.execute(
+        "DELETE FROM derived_metrics WHERE id = ? AND user_id = ?",
+        params![id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("Derived metric not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Evaluates a derived metric for one VPS. Without an interval every raw sample is
+/// returned; with one, the expression is averaged per bucket over the same source
+/// table the regular timeseries endpoint would use for this range.
+pub async fn get_derived_metric_timeseries(
+    pool: DuckDbPool,
+    metric: &derived_metric::Model,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    interval_seconds: Option<u32>,
+    retention: &RetentionPolicy,
+) -> Result<Vec<DerivedMetricPoint>, AppError> {
+    let expr = parse_expression(&metric.expression)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid expression: {e}")))?;
+
+    let sql = match interval_seconds {
+        None => format!(
+            r#"SELECT "time", {} FROM performance_metrics
+               WHERE vps_id = ? AND "time" >= ? AND "time" <= ? ORDER BY "time" ASC"#,
+            expr.to_sql(ColumnSource::Raw)
+        ),
+        Some(interval_secs) => {
+            let interval_secs = interval_secs.max(1);
+            let (metric_source, is_aggregated) = select_metric_source(start_time, end_time, retention, Utc::now());
+            let source = if is_aggregated { ColumnSource::Summary } else { ColumnSource::Raw };
+            format!(
+                r#"
+                SELECT
+                    date_trunc('second', "time") + INTERVAL '{interval_secs} seconds' * (epoch("time") / {interval_secs}) AS time_bucket,
+                    AVG({})
+                FROM {metric_source}
+                WHERE vps_id = ? AND "time" >= ? AND "time" <= ?
+                GROUP BY time_bucket
+                ORDER BY time_bucket ASC
+                "#,
+                expr.to_sql(source)
+            )
+        }
+    };
+
+    let conn = pool.get()?;
+    let points = conn
+        .prepare(&sql)?
+        .query_map(params![vps_id, start_time, end_time], |row| {
+            Ok(DerivedMetricPoint {
+                time: row.get(0)?,
+                value: row.get(1)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(points)
+}
+
+/// Raw values of a derived metric for alert evaluation. Returns `None` if the
+/// metric no longer exists for the rule's owner.
+pub async fn get_derived_metric_values(
+    pool: DuckDbPool,
+    user_id: i32,
+    id: i32,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    retention: &RetentionPolicy,
+) -> Result<Option<(derived_metric::Model, Vec<Option<f64>>)>, AppError> {
+    let Some(metric) = get_derived_metric(pool.clone(), user_id, id).await? else {
+        return Ok(None);
+    };
+    let values = get_derived_metric_timeseries(pool, &metric, vps_id, start_time, end_time, None, retention)
+        .await?
+        .into_iter()
+        .map(|p| p.value)
+        .collect();
+    Ok(Some((metric, values)))
+}
+
+/// Rejects alert rule metric types that reference another user's (or a missing)
+/// derived metric. Built-in metric types pass through unchanged.
+pub async fn check_metric_type_ownership(
+    pool: DuckDbPool,
+    user_id: i32,
+    metric_type: &str,
+) -> Result<(), AppError> {
+    if !metric_type.starts_with(DERIVED_METRIC_TYPE_PREFIX) {
+        return Ok(());
+    }
+    let found = match parse_derived_metric_type(metric_type) {
+        Some(id) => get_derived_metric(pool, user_id, id).await?.is_some(),
+        None => false,
+    };
+    if !found {
+        return Err(AppError::InvalidInput(format!(
+            "Derived metric '{metric_type}' not found."
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_derived_metric_types() {
+        assert_eq!(parse_derived_metric_type("derived:12"), Some(12));
+        assert_eq!(parse_derived_metric_type("derived:"), None);
+        assert_eq!(parse_derived_metric_type("cpu_usage_percent"), None);
+    }
+
+    #[test]
+    fn validates_names() {
+        assert!(validate_name("memory_used_percent").is_ok());
+        assert!(validate_name("cpu_usage_percent").is_err());
+        assert!(validate_name("1st").is_err());
+        assert!(validate_name("rx+tx").is_err());
+    }
+}
@@ -0,0 +1,115 @@
+use chrono::{DateTime, Duration, Utc};
+use duckdb::{params, Result as DuckDbResult, Row};
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::disk_health_metric;
+use crate::web::error::AppError;
+
+/// Alert rules with this metric type compare the largest growth of any drive's
+/// reallocated sector count within their duration.
+pub const DISK_REALLOCATED_INCREASE_METRIC_TYPE: &str = "disk_reallocated_sectors_increase";
+/// Alert rules with this metric type compare the wear of the most worn drive.
+pub const DISK_WEAR_METRIC_TYPE: &str = "disk_wear_percent";
+/// Alert rules with this metric type compare the number of drives whose SMART
+/// self-assessment currently fails.
+pub const DISK_SMART_FAILED_METRIC_TYPE: &str = "disk_smart_failed";
+
+/// Agents re-read drives every few minutes; readings older than this belong to
+/// drives that are gone or no longer readable.
+const CURRENT_MAX_AGE_HOURS: i64 = 1;
+/// How far before a rule's window to look for the reading to compare against.
+const BASELINE_LOOKBACK_HOURS: i64 = 24;
+
+fn row_to_disk_health_metric_model(row: &Row) -> DuckDbResult<disk_health_metric::Model> {
+    Ok(disk_health_metric::Model {
+        time: row.get("time")?,
+        vps_id: row.get("vps_id")?,
+        device: row.get("device")?,
+        model: row.get("model")?,
+        serial: row.get("serial")?,
+        smart_passed: row.get("smart_passed")?,
+        reallocated_sectors: row.get("reallocated_sectors")?,
+        pending_sectors: row.get("pending_sectors")?,
+        wear_percent: row.get("wear_percent")?,
+        temperature_celsius: row.get("temperature_celsius")?,
+        power_on_hours: row.get("power_on_hours")?,
+    })
+}
+
+/// Every reading in the range, optionally limited to one device. Drives are
+/// only read every few minutes, so readings are not aggregated.
+pub async fn get_disk_health_timeseries(
+    pool: DuckDbPool,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    device: Option<String>,
+) -> Result<Vec<disk_health_metric::Model>, AppError> {
+    let device_filter = if device.is_some() { "AND device = ?" } else { "" };
+    let sql = format!(
+        r#"SELECT * FROM disk_health_metrics
+           WHERE vps_id = ? AND "time" >= ? AND "time" <= ? {device_filter}
+           ORDER BY "time" ASC, device ASC"#
+    );
+    let mut params: Vec<&dyn duckdb::ToSql> = vec![&vps_id, &start_time, &end_time];
+    if let Some(device) = device.as_ref() {
+        params.push(device);
+    }
+    let conn = pool.get()?;
+    let metrics = conn
+        .prepare(&sql)?
+        .query_map(params.as_slice(), row_to_disk_health_metric_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(metrics)
+}
+
+/// The largest growth of any drive's reallocated sectors between the last
+/// reading before `start_time` (or the first one after it) and the newest one.
+/// A swapped drive starts over, since readings are matched by serial.
+/// `None` without readings.
+pub async fn get_max_reallocated_increase(
+    pool: DuckDbPool,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<Option<i64>, AppError> {
+    let conn = pool.get()?;
+    let increase = conn.query_row(
+        r#"SELECT CAST(MAX(latest - baseline) AS BIGINT) FROM (
+               SELECT
+                   arg_max(reallocated_sectors, "time") AS latest,
+                   COALESCE(
+                       arg_max(reallocated_sectors, "time") FILTER (WHERE "time" <= ?),
+                       arg_min(reallocated_sectors, "time")
+                   ) AS baseline
+               FROM disk_health_metrics
+               WHERE vps_id = ? AND "time" >= ? AND "time" <= ? AND reallocated_sectors IS NOT NULL
+               GROUP BY device, serial
+           )"#,
+        params![start_time, vps_id, start_time - Duration::hours(BASELINE_LOOKBACK_HOURS), end_time],
+        |row| row.get(0),
+    )?;
+    Ok(increase)
+}
+
+/// The newest reading of every drive still reporting.
+pub async fn get_current_disk_health(
+    pool: DuckDbPool,
+    vps_id: i32,
+    now: DateTime<Utc>,
+) -> Result<Vec<disk_health_metric::Model>, AppError> {
+    let conn = pool.get()?;
+    let metrics = conn
+        .prepare(
+            r#"SELECT * FROM disk_health_metrics
+               WHERE vps_id = ? AND "time" >= ?
+               QUALIFY ROW_NUMBER() OVER (PARTITION BY device ORDER BY "time" DESC) = 1
+               ORDER BY device"#,
+        )?
+        .query_map(
+            params![vps_id, now - Duration::hours(CURRENT_MAX_AGE_HOURS)],
+            row_to_disk_health_metric_model,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(metrics)
+}
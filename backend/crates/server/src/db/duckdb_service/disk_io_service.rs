@@ -0,0 +1,94 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, Result as DuckDbResult, Row};
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::disk_io_metric;
+use crate::web::error::AppError;
+
+/// Alert rules with this metric type compare the worst device's await (ms) per sample.
+pub const DISK_AWAIT_METRIC_TYPE: &str = "disk_await_ms";
+
+fn row_to_disk_io_metric_model(row: &Row) -> DuckDbResult<disk_io_metric::Model> {
+    Ok(disk_io_metric::Model {
+        time: row.get("time")?,
+        vps_id: row.get("vps_id")?,
+        device: row.get("device")?,
+        read_bps: row.get("read_bps")?,
+        write_bps: row.get("write_bps")?,
+        read_iops: row.get("read_iops")?,
+        write_iops: row.get("write_iops")?,
+        await_ms: row.get("await_ms")?,
+        utilization_percent: row.get("utilization_percent")?,
+    })
+}
+
+/// Per-device samples, optionally limited to one device. With an interval the
+/// samples are averaged per bucket and device.
+pub async fn get_disk_io_timeseries(
+    pool: DuckDbPool,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    interval_seconds: Option<u32>,
+    device: Option<String>,
+) -> Result<Vec<disk_io_metric::Model>, AppError> {
+    let device_filter = if device.is_some() { "AND device = ?" } else { "" };
+    let sql = match interval_seconds {
+        None => format!(
+            r#"SELECT * FROM disk_io_metrics
+               WHERE vps_id = ? AND "time" >= ? AND "time" <= ? {device_filter}
+               ORDER BY "time" ASC, device ASC"#
+        ),
+        Some(interval_secs) => {
+            let interval_secs = interval_secs.max(1);
+            format!(
+                r#"
+                SELECT
+                    date_trunc('second', "time") + INTERVAL '{interval_secs} seconds' * (epoch("time") / {interval_secs}) AS "time",
+                    vps_id,
+                    device,
+                    CAST(AVG(read_bps) AS BIGINT) AS read_bps,
+                    CAST(AVG(write_bps) AS BIGINT) AS write_bps,
+                    AVG(read_iops) AS read_iops,
+                    AVG(write_iops) AS write_iops,
+                    AVG(await_ms) AS await_ms,
+                    AVG(utilization_percent) AS utilization_percent
+                FROM disk_io_metrics
+                WHERE vps_id = ? AND "time" >= ? AND "time" <= ? {device_filter}
+                GROUP BY 1, vps_id, device
+                ORDER BY 1 ASC, device ASC
+                "#
+            )
+        }
+    };
+
+    let mut params: Vec<&dyn duckdb::ToSql> = vec![&vps_id, &start_time, &end_time];
+    if let Some(device) = device.as_ref() {
+        params.push(device);
+    }
+    let conn = pool.get()?;
+    let metrics = conn
+        .prepare(&sql)?
+        .query_map(params.as_slice(), row_to_disk_io_metric_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(metrics)
+}
+
+/// The highest await across devices for each sample in the range, oldest first.
+pub async fn get_max_await_values(
+    pool: DuckDbPool,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<Vec<f64>, AppError> {
+    let conn = pool.get()?;
+    let values = conn
+        .prepare(
+            r#"SELECT MAX(await_ms) FROM disk_io_metrics
+               WHERE vps_id = ? AND "time" >= ? AND "time" <= ?
+               GROUP BY "time" ORDER BY "time" ASC"#,
+        )?
+        .query_map(params![vps_id, start_time, end_time], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(values)
+}
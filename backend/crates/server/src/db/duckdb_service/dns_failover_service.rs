@@ -0,0 +1,482 @@
+//! DNS failover policies: when every monitor watching a primary server has
+//! failed its last `confirm_checks` checks, the policy's record is pointed at
+//! the standby IP; once they all pass again it is reverted. Every switch (or
+//! would-be switch in dry-run mode) and every provider error is logged as an
+//! event.
+
+use chrono::{DateTime, Utc};
+use duckdb::{params, Connection, OptionalExt, Result as DuckDbResult, Row};
+use std::net::IpAddr;
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::db::duckdb_service::{encryption_key_service, organization_service, DuckDbPool};
+use crate::db::entities::{dns_failover_event, dns_failover_policy};
+use crate::notifications::encryption::EncryptionService;
+use crate::services::dns_provider::{self, DnsCredentials, DnsRecord};
+use crate::web::error::AppError;
+use crate::web::models::dns_failover_models::{CreateDnsFailoverPolicyRequest, UpdateDnsFailoverPolicyRequest};
+
+pub const TARGET_PRIMARY: &str = "primary";
+pub const TARGET_STANDBY: &str = "standby";
+pub const EVENT_FAILOVER: &str = "failover";
+pub const EVENT_REVERT: &str = "revert";
+pub const EVENT_ERROR: &str = "error";
+
+const DEFAULT_TTL: i32 = 60;
+const DEFAULT_CONFIRM_CHECKS: i32 = 3;
+const MAX_CONFIRM_CHECKS: i32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transition {
+    Failover,
+    Revert,
+}
+
+/// Decides whether to switch, given each monitor's most recent results
+/// (newest first). Only a unanimous verdict over the last `confirm_checks`
+/// results of every monitor switches; anything mixed keeps the current target.
+pub fn decide_transition(on_standby: bool, recent: &[Vec<bool>], confirm_checks: usize) -> Option<Transition> {
+    if recent.is_empty() || confirm_checks == 0 {
+        return None;
+    }
+    let unanimous = |up: bool| {
+        recent
+            .iter()
+            .all(|results| results.len() >= confirm_checks && results[..confirm_checks].iter().all(|r| *r == up))
+    };
+    match on_standby {
+        false if unanimous(false) => Some(Transition::Failover),
+        true if unanimous(true) => Some(Transition::Revert),
+        _ => None,
+    }
+}
+
+fn json_column<T: serde::de::DeserializeOwned>(row: &Row, column: &str) -> DuckDbResult<T> {
+    let raw: String = row.get(column)?;
+    serde_json::from_str(&raw).map_err(|e| {
+        duckdb::Error::FromSqlConversionFailure(0, duckdb::types::Type::Text, Box::new(e))
+    })
+}
+
+fn row_to_policy_model(row: &Row) -> DuckDbResult<dns_failover_policy::Model> {
+    Ok(dns_failover_policy::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        name: row.get("name")?,
+        provider: row.get("provider")?,
+        zone_id: row.get("zone_id")?,
+        record_name: row.get("record_name")?,
+        record_type: row.get("record_type")?,
+        ttl: row.get("ttl")?,
+        primary_ip: row.get("primary_ip")?,
+        standby_ip: row.get("standby_ip")?,
+        monitor_ids: json_column(row, "monitor_ids")?,
+        confirm_checks: row.get("confirm_checks")?,
+        dry_run: row.get("dry_run")?,
+        enabled: row.get("enabled")?,
+        active_target: row.get("active_target")?,
+        last_switched_at: row.get("last_switched_at")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn row_to_event_model(row: &Row) -> DuckDbResult<dns_failover_event::Model> {
+    Ok(dns_failover_event::Model {
+        id: row.get("id")?,
+        policy_id: row.get("policy_id")?,
+        time: row.get("time")?,
+        kind: row.get("kind")?,
+        from_ip: row.get("from_ip")?,
+        to_ip: row.get("to_ip")?,
+        dry_run: row.get("dry_run")?,
+        details: row.get("details")?,
+    })
+}
+
+/// Checks the record settings of a (possibly partially updated) policy.
+fn validate_policy(policy: &dns_failover_policy::Model) -> Result<(), AppError> {
+    if policy.name.trim().is_empty() || policy.zone_id.trim().is_empty() || policy.record_name.trim().is_empty() {
+        return Err(AppError::InvalidInput("Name, zone id and record name are required".to_string()));
+    }
+    let wants_v6 = match policy.record_type.as_str() {
+        "A" => false,
+        "AAAA" => true,
+        other => return Err(AppError::InvalidInput(format!("Unsupported record type '{other}'"))),
+    };
+    for ip in [&policy.primary_ip, &policy.standby_ip] {
+        match ip.parse::<IpAddr>() {
+            Ok(addr) if addr.is_ipv6() == wants_v6 => {}
+            _ => {
+                return Err(AppError::InvalidInput(format!(
+                    "'{ip}' is not a valid address for a {} record",
+                    policy.record_type
+                )))
+            }
+        }
+    }
+    if policy.primary_ip == policy.standby_ip {
+        return Err(AppError::InvalidInput("Primary and standby IP must differ".to_string()));
+    }
+    if !(1..=86_400).contains(&policy.ttl) {
+        return Err(AppError::InvalidInput("TTL must be between 1 and 86400 seconds".to_string()));
+    }
+    if !(1..=MAX_CONFIRM_CHECKS).contains(&policy.confirm_checks) {
+        return Err(AppError::InvalidInput(format!(
+            "confirmChecks must be between 1 and {MAX_CONFIRM_CHECKS}"
+        )));
+    }
+    if policy.monitor_ids.is_empty() {
+        return Err(AppError::InvalidInput("At least one monitor is required".to_string()));
+    }
+    Ok(())
+}
+
+fn check_monitor_ownership(conn: &Connection, user_id: i32, monitor_ids: &[i32]) -> Result<(), AppError> {
+    for monitor_id in monitor_ids {
+        let owned = conn
+            .query_row(
+                &format!("SELECT 1 FROM service_monitors WHERE id = ? AND {}", organization_service::org_scope("organization_id")),
+                params![monitor_id, user_id],
+                |_| Ok(()),
+            )
+            .optional()?;
+        if owned.is_none() {
+            return Err(AppError::InvalidInput(format!("Monitor {monitor_id} not found")));
+        }
+    }
+    Ok(())
+}
+
+fn encrypt_credentials(
+    encryption_service: &EncryptionService,
+    conn: &Connection,
+    user_id: i32,
+    credentials: &DnsCredentials,
+) -> Result<Vec<u8>, AppError> {
+    let key_organization = encryption_key_service::user_key_organization(conn, user_id)?;
+    encryption_service
+        .encrypt_for(key_organization, serde_json::to_string(credentials)?.as_bytes())
+        .map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+fn decrypt_credentials(encryption_service: &EncryptionService, encrypted: &[u8]) -> Result<DnsCredentials, AppError> {
+    let plaintext = encryption_service
+        .decrypt(encrypted)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    Ok(serde_json::from_slice(&plaintext)?)
+}
+
+pub async fn create_policy(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    user_id: i32,
+    payload: CreateDnsFailoverPolicyRequest,
+) -> Result<dns_failover_policy::Model, AppError> {
+    let now = Utc::now();
+    let policy = dns_failover_policy::Model {
+        id: 0,
+        user_id,
+        name: payload.name.trim().to_string(),
+        provider: payload.credentials.provider().to_string(),
+        zone_id: payload.zone_id.trim().to_string(),
+        record_name: payload.record_name.trim().to_string(),
+        record_type: payload.record_type.unwrap_or_else(|| "A".to_string()).to_uppercase(),
+        ttl: payload.ttl.unwrap_or(DEFAULT_TTL),
+        primary_ip: payload.primary_ip.trim().to_string(),
+        standby_ip: payload.standby_ip.trim().to_string(),
+        monitor_ids: payload.monitor_ids,
+        confirm_checks: payload.confirm_checks.unwrap_or(DEFAULT_CONFIRM_CHECKS),
+        dry_run: payload.dry_run.unwrap_or(true),
+        enabled: payload.enabled.unwrap_or(true),
+        active_target: TARGET_PRIMARY.to_string(),
+        last_switched_at: None,
+        created_at: now,
+        updated_at: now,
+    };
+    validate_policy(&policy)?;
+    let conn = pool.get()?;
+    let credentials = encrypt_credentials(&encryption_service, &conn, user_id, &payload.credentials)?;
+    check_monitor_ownership(&conn, user_id, &policy.monitor_ids)?;
+    let created = conn.query_row(
+        "INSERT INTO dns_failover_policies (user_id, name, provider, credentials, zone_id, record_name, record_type,
+             ttl, primary_ip, standby_ip, monitor_ids, confirm_checks, dry_run, enabled)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        params![
+            user_id,
+            policy.name,
+            policy.provider,
+            credentials,
+            policy.zone_id,
+            policy.record_name,
+            policy.record_type,
+            policy.ttl,
+            policy.primary_ip,
+            policy.standby_ip,
+            serde_json::to_string(&policy.monitor_ids)?,
+            policy.confirm_checks,
+            policy.dry_run,
+            policy.enabled,
+        ],
+        row_to_policy_model,
+    )?;
+    Ok(created)
+}
+
+pub async fn get_policies_for_user(pool: DuckDbPool, user_id: i32) -> Result<Vec<dns_failover_policy::Model>, AppError> {
+    let conn = pool.get()?;
+    let policies = conn
+        .prepare("SELECT * FROM dns_failover_policies WHERE user_id = ? ORDER BY name ASC")?
+        .query_map(params![user_id], row_to_policy_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(policies)
+}
+
+fn get_policy_with_conn(conn: &Connection, user_id: i32, id: i32) -> Result<dns_failover_policy::Model, AppError> {
+    conn.query_row(
+        "SELECT * FROM dns_failover_policies WHERE id = ? AND user_id = ?",
+        params![id, user_id],
+        row_to_policy_model,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound("DNS failover policy not found".to_string()))
+}
+
+/// Updates a policy. Changing the record or IPs resets it to the primary
+/// target, since the previous switch state no longer applies.
+pub async fn update_policy(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    user_id: i32,
+    id: i32,
+    payload: UpdateDnsFailoverPolicyRequest,
+) -> Result<dns_failover_policy::Model, AppError> {
+    let conn = pool.get()?;
+    let existing = get_policy_with_conn(&conn, user_id, id)?;
+    let record_changed = payload.zone_id.is_some()
+        || payload.record_name.is_some()
+        || payload.record_type.is_some()
+        || payload.primary_ip.is_some()
+        || payload.standby_ip.is_some()
+        || payload.credentials.is_some();
+    let policy = dns_failover_policy::Model {
+        name: payload.name.map(|n| n.trim().to_string()).unwrap_or(existing.name.clone()),
+        provider: payload
+            .credentials
+            .as_ref()
+            .map(|c| c.provider().to_string())
+            .unwrap_or(existing.provider.clone()),
+        zone_id: payload.zone_id.map(|z| z.trim().to_string()).unwrap_or(existing.zone_id.clone()),
+        record_name: payload.record_name.map(|r| r.trim().to_string()).unwrap_or(existing.record_name.clone()),
+        record_type: payload.record_type.map(|t| t.to_uppercase()).unwrap_or(existing.record_type.clone()),
+        ttl: payload.ttl.unwrap_or(existing.ttl),
+        primary_ip: payload.primary_ip.map(|ip| ip.trim().to_string()).unwrap_or(existing.primary_ip.clone()),
+        standby_ip: payload.standby_ip.map(|ip| ip.trim().to_string()).unwrap_or(existing.standby_ip.clone()),
+        monitor_ids: payload.monitor_ids.unwrap_or(existing.monitor_ids.clone()),
+        confirm_checks: payload.confirm_checks.unwrap_or(existing.confirm_checks),
+        dry_run: payload.dry_run.unwrap_or(existing.dry_run),
+        enabled: payload.enabled.unwrap_or(existing.enabled),
+        active_target: if record_changed { TARGET_PRIMARY.to_string() } else { existing.active_target.clone() },
+        ..existing
+    };
+    validate_policy(&policy)?;
+    check_monitor_ownership(&conn, user_id, &policy.monitor_ids)?;
+    let credentials = payload
+        .credentials
+        .as_ref()
+        .map(|c| encrypt_credentials(&encryption_service, &conn, user_id, c))
+        .transpose()?;
+
+    let updated = conn.query_row(
+        "UPDATE dns_failover_policies
+         SET name = ?, provider = ?, credentials = COALESCE(?, credentials), zone_id = ?, record_name = ?,
+             record_type = ?, ttl = ?, primary_ip = ?, standby_ip = ?, monitor_ids = ?, confirm_checks = ?,
+             dry_run = ?, enabled = ?, active_target = ?, updated_at = ?
+         WHERE id = ? AND user_id = ? RETURNING *",
+        params![
+            policy.name,
+            policy.provider,
+            credentials,
+            policy.zone_id,
+            policy.record_name,
+            policy.record_type,
+            policy.ttl,
+            policy.primary_ip,
+            policy.standby_ip,
+            serde_json::to_string(&policy.monitor_ids)?,
+            policy.confirm_checks,
+            policy.dry_run,
+            policy.enabled,
+            policy.active_target,
+            Utc::now(),
+            id,
+            user_id,
+        ],
+        row_to_policy_model,
+    )?;
+    Ok(updated)
+}
+
+pub async fn delete_policy(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute(
+        "DELETE FROM dns_failover_policies WHERE id = ? AND user_id = ?",
+        params![id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("DNS failover policy not found".to_string()));
+    }
+    conn.execute("DELETE FROM dns_failover_events WHERE policy_id = ?", params![id])?;
+    Ok(())
+}
+
+/// Events of a policy, newest first.
+pub async fn get_policy_events(
+    pool: DuckDbPool,
+    user_id: i32,
+    id: i32,
+    limit: u32,
+) -> Result<Vec<dns_failover_event::Model>, AppError> {
+    let conn = pool.get()?;
+    get_policy_with_conn(&conn, user_id, id)?;
+    let events = conn
+        .prepare("SELECT * FROM dns_failover_events WHERE policy_id = ? ORDER BY time DESC, id DESC LIMIT ?")?
+        .query_map(params![id, limit], row_to_event_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(events)
+}
+
+fn record_event(
+    conn: &Connection,
+    policy: &dns_failover_policy::Model,
+    kind: &str,
+    from_ip: &str,
+    to_ip: &str,
+    details: Option<&str>,
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO dns_failover_events (policy_id, kind, from_ip, to_ip, dry_run, details) VALUES (?, ?, ?, ?, ?, ?)",
+        params![policy.id, kind, from_ip, to_ip, policy.dry_run, details],
+    )?;
+    Ok(())
+}
+
+/// The newest `limit` results of a monitor across all agents running it,
+/// newest first. Results inside maintenance windows don't count.
+fn recent_results(conn: &Connection, monitor_id: i32, limit: usize) -> Result<Vec<bool>, AppError> {
+    let results = conn
+        .prepare(
+            "SELECT is_up FROM service_monitor_results
+             WHERE monitor_id = ? AND NOT in_maintenance
+             ORDER BY time DESC LIMIT ?",
+        )?
+        .query_map(params![monitor_id, limit as i64], |row| row.get(0))?
+        .collect::<Result<Vec<bool>, _>>()?;
+    Ok(results)
+}
+
+/// Evaluates every enabled policy and performs due switches. Returns the
+/// number of switches made (including dry-run ones).
+pub async fn run_failover_checks(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    now: DateTime<Utc>,
+) -> Result<usize, AppError> {
+    let due = {
+        let conn = pool.get()?;
+        let policies = conn
+            .prepare("SELECT * FROM dns_failover_policies WHERE enabled")?
+            .query_map([], |row| Ok((row_to_policy_model(row)?, row.get::<_, Vec<u8>>("credentials")?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut due = Vec::new();
+        for (policy, credentials) in policies {
+            let confirm_checks = policy.confirm_checks.max(1) as usize;
+            let recent = policy
+                .monitor_ids
+                .iter()
+                .map(|monitor_id| recent_results(&conn, *monitor_id, confirm_checks))
+                .collect::<Result<Vec<_>, _>>()?;
+            let on_standby = policy.active_target == TARGET_STANDBY;
+            if let Some(transition) = decide_transition(on_standby, &recent, confirm_checks) {
+                due.push((policy, credentials, transition));
+            }
+        }
+        due
+    };
+
+    let mut switched = 0;
+    for (policy, encrypted_credentials, transition) in due {
+        let (kind, target, from_ip, to_ip) = match transition {
+            Transition::Failover => (EVENT_FAILOVER, TARGET_STANDBY, &policy.primary_ip, &policy.standby_ip),
+            Transition::Revert => (EVENT_REVERT, TARGET_PRIMARY, &policy.standby_ip, &policy.primary_ip),
+        };
+        let result = if policy.dry_run {
+            Ok(())
+        } else {
+            let record = DnsRecord {
+                zone_id: &policy.zone_id,
+                name: &policy.record_name,
+                record_type: &policy.record_type,
+                ttl: policy.ttl,
+            };
+            match decrypt_credentials(&encryption_service, &encrypted_credentials) {
+                Ok(credentials) => dns_provider::update_record(&credentials, &record, to_ip).await,
+                Err(e) => Err(e),
+            }
+        };
+
+        let conn = pool.get()?;
+        match result {
+            Ok(()) => {
+                info!(
+                    policy_id = policy.id,
+                    kind,
+                    from_ip = %from_ip,
+                    to_ip = %to_ip,
+                    dry_run = policy.dry_run,
+                    "DNS failover switched record {}.",
+                    policy.record_name
+                );
+                conn.execute(
+                    "UPDATE dns_failover_policies SET active_target = ?, last_switched_at = ? WHERE id = ?",
+                    params![target, now, policy.id],
+                )?;
+                let details = policy.dry_run.then_some("Dry run: DNS was not changed.");
+                record_event(&conn, &policy, kind, from_ip, to_ip, details)?;
+                switched += 1;
+            }
+            Err(e) => {
+                // The target is left unchanged, so the switch is retried on the next check.
+                error!(policy_id = policy.id, kind, error = %e, "DNS failover switch failed.");
+                record_event(&conn, &policy, EVENT_ERROR, from_ip, to_ip, Some(&e.to_string()))?;
+            }
+        }
+    }
+    Ok(switched)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fails_over_only_when_every_monitor_confirms() {
+        let down = vec![false, false, false];
+        let up = vec![true, true, true];
+        assert_eq!(decide_transition(false, &[down.clone(), down.clone()], 3), Some(Transition::Failover));
+        assert_eq!(decide_transition(false, &[down.clone(), up.clone()], 3), None);
+        // Too few results to confirm.
+        assert_eq!(decide_transition(false, &[vec![false, false]], 3), None);
+        // Already on standby: more failures change nothing.
+        assert_eq!(decide_transition(true, &[down], 3), None);
+    }
+
+    #[test]
+    fn reverts_once_every_monitor_recovers() {
+        assert_eq!(decide_transition(true, &[vec![true, true, false]], 2), Some(Transition::Revert));
+        assert_eq!(decide_transition(true, &[vec![true, false, false]], 2), None);
+        assert_eq!(decide_transition(false, &[vec![true, true]], 2), None);
+        assert_eq!(decide_transition(true, &[], 2), None);
+    }
+}
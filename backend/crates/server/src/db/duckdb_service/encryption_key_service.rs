@@ -0,0 +1,207 @@
+//! Data key rotation for secrets encrypted with [`EncryptionService`].
+//!
+//! Rotating adds a new key version that all new writes use immediately. Rows
+//! written with older versions stay readable and are re-encrypted in small
+//! batches by a background task; a version is dropped once no row uses it.
+//!
+//! Keys are global unless rotated for one organization. Secrets belong to the
+//! organization of their row; user-owned ones to the owner's personal
+//! organization, and two-factor secrets, which belong to the account, always
+//! use the global keys.
+
+use chrono::{DateTime, Utc};
+use duckdb::{params, Connection, OptionalExt};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::notifications::encryption::{EncryptionService, MASTER_KEY_VERSION};
+use crate::web::error::AppError;
+use crate::web::models::encryption_key_models::{EncryptionKeyInfo, EncryptionKeyStatus};
+
+/// The organization that owns rows carrying a `user_id`.
+const PERSONAL_ORGANIZATION: &str = "(SELECT o.id FROM organizations o WHERE o.personal_for_user_id = t.user_id)";
+
+/// Every encrypted column as (table, id column, encrypted column, owning
+/// organization). The organization is an SQL expression over the row `t`.
+const ENCRYPTED_COLUMNS: &[(&str, &str, &str, &str)] = &[
+    ("notification_channels", "id", "config", PERSONAL_ORGANIZATION),
+    ("chatops_bridges", "id", "secret", PERSONAL_ORGANIZATION),
+    ("provider_accounts", "id", "token", PERSONAL_ORGANIZATION),
+    ("dns_failover_policies", "id", "credentials", PERSONAL_ORGANIZATION),
+    ("user_totp", "user_id", "secret", "NULL"),
+    ("webhook_subscriptions", "id", "secret", "t.organization_id"),
+];
+
+fn encryption_error(e: impl std::fmt::Display) -> AppError {
+    AppError::InternalServerError(e.to_string())
+}
+
+/// The organization whose keys encrypt the secrets `user_id` owns: their
+/// personal one.
+pub(crate) fn user_key_organization(conn: &Connection, user_id: i32) -> Result<Option<i32>, AppError> {
+    Ok(conn
+        .query_row(
+            "SELECT id FROM organizations WHERE personal_for_user_id = ?",
+            params![user_id],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+/// Installs all stored data keys. Called once at startup, before anything is
+/// decrypted.
+pub async fn load_keys(pool: DuckDbPool, encryption_service: &EncryptionService) -> Result<usize, AppError> {
+    let conn = pool.get()?;
+    let keys = conn
+        .prepare("SELECT version, organization_id, wrapped_key FROM encryption_keys ORDER BY version")?
+        .query_map([], |row| {
+            Ok((row.get::<_, u32>(0)?, row.get::<_, Option<i32>>(1)?, row.get::<_, Vec<u8>>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    for (version, organization_id, wrapped_key) in &keys {
+        encryption_service.install_key(*version, *organization_id, wrapped_key).map_err(|e| {
+            AppError::InternalServerError(format!(
+                "Failed to unwrap encryption key version {version}; is NOTIFICATION_ENCRYPTION_KEY correct? {e}"
+            ))
+        })?;
+    }
+    Ok(keys.len())
+}
+
+/// Generates and stores a new data key and makes it current, globally or for
+/// `organization_id` only. Returns the new version.
+pub async fn rotate_key(
+    pool: DuckDbPool,
+    encryption_service: &EncryptionService,
+    organization_id: Option<i32>,
+) -> Result<u32, AppError> {
+    let wrapped_key = encryption_service
+        .wrap_key(&EncryptionService::generate_key())
+        .map_err(encryption_error)?;
+    let conn = pool.get()?;
+    if let Some(id) = organization_id {
+        conn.query_row("SELECT 1 FROM organizations WHERE id = ?", params![id], |_| Ok(()))
+            .optional()?
+            .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))?;
+    }
+    let version: u32 = conn.query_row(
+        "INSERT INTO encryption_keys (version, organization_id, wrapped_key)
+         SELECT COALESCE(MAX(version), 0) + 1, ?, ? FROM encryption_keys
+         RETURNING version",
+        params![organization_id, wrapped_key],
+        |row| row.get(0),
+    )?;
+    encryption_service
+        .install_key(version, organization_id, &wrapped_key)
+        .map_err(encryption_error)?;
+    info!(version, ?organization_id, "Rotated encryption key.");
+    Ok(version)
+}
+
+/// Re-encrypts up to `batch_size` rows per table that still use an older key,
+/// then drops key versions nothing refers to any more. Returns the number of
+/// rows re-encrypted.
+pub async fn reencrypt_stale_rows(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    batch_size: usize,
+) -> Result<usize, AppError> {
+    let conn = pool.get()?;
+    let mut reencrypted = 0;
+    for (table, id_column, column, organization) in ENCRYPTED_COLUMNS {
+        let rows = conn
+            .prepare(&format!("SELECT t.{id_column}, t.{column}, {organization} FROM {table} t"))?
+            .query_map([], |row| {
+                Ok((row.get::<_, i32>(0)?, row.get::<_, Vec<u8>>(1)?, row.get::<_, Option<i32>>(2)?))
+            })?
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut updated_in_table = 0;
+        for (id, encrypted, organization_id) in rows {
+            if updated_in_table >= batch_size {
+                break;
+            }
+            let Some(fresh) = encryption_service
+                .reencrypt(organization_id, &encrypted)
+                .map_err(encryption_error)?
+            else {
+                continue;
+            };
+            // Only replace the value we read, so a concurrent update wins.
+            updated_in_table += conn.execute(
+                &format!("UPDATE {table} SET {column} = ? WHERE {id_column} = ? AND {column} = ?"),
+                params![fresh, id, encrypted],
+            )?;
+        }
+        reencrypted += updated_in_table;
+    }
+
+    let in_use = count_rows_by_version(&conn, &encryption_service)?;
+    let stored = conn
+        .prepare("SELECT version FROM encryption_keys")?
+        .query_map([], |row| row.get::<_, u32>(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    let unused = stored
+        .into_iter()
+        .filter(|v| !in_use.contains_key(v) && !encryption_service.is_current(*v));
+    for version in unused {
+        conn.execute("DELETE FROM encryption_keys WHERE version = ?", params![version])?;
+        encryption_service.remove_key(version);
+        info!(version, "Retired unused encryption key.");
+    }
+    Ok(reencrypted)
+}
+
+fn count_rows_by_version(
+    conn: &duckdb::Connection,
+    encryption_service: &EncryptionService,
+) -> Result<BTreeMap<u32, u64>, AppError> {
+    let mut counts = BTreeMap::new();
+    for (table, _, column, _) in ENCRYPTED_COLUMNS {
+        let mut stmt = conn.prepare(&format!("SELECT {column} FROM {table}"))?;
+        let values = stmt.query_map([], |row| row.get::<_, Vec<u8>>(0))?;
+        for value in values {
+            *counts.entry(encryption_service.key_version(&value?)).or_insert(0) += 1;
+        }
+    }
+    Ok(counts)
+}
+
+/// Key versions with the number of rows still encrypted under each.
+pub async fn get_key_status(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+) -> Result<EncryptionKeyStatus, AppError> {
+    let conn = pool.get()?;
+    let mut counts = count_rows_by_version(&conn, &encryption_service)?;
+    let stored = conn
+        .prepare("SELECT version, organization_id, created_at FROM encryption_keys ORDER BY version")?
+        .query_map([], |row| {
+            Ok((row.get::<_, u32>(0)?, row.get::<_, Option<i32>>(1)?, row.get::<_, DateTime<Utc>>(2)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut keys = Vec::new();
+    let master_rows = counts.remove(&MASTER_KEY_VERSION).unwrap_or(0);
+    if master_rows > 0 || stored.is_empty() {
+        keys.push(EncryptionKeyInfo {
+            version: MASTER_KEY_VERSION,
+            organization_id: None,
+            created_at: None,
+            encrypted_rows: master_rows,
+        });
+    }
+    for (version, organization_id, created_at) in stored {
+        keys.push(EncryptionKeyInfo {
+            version,
+            organization_id,
+            created_at: Some(created_at),
+            encrypted_rows: counts.remove(&version).unwrap_or(0),
+        });
+    }
+    Ok(EncryptionKeyStatus {
+        current_version: encryption_service.current_version(),
+        keys,
+    })
+}
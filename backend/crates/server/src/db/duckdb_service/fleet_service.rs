@@ -0,0 +1,141 @@
+use chrono::{Duration, Utc};
+use duckdb::params;
+use std::collections::HashMap;
+
+use crate::db::duckdb_service::performance_service::select_metric_source;
+use crate::db::duckdb_service::retention::RetentionPolicy;
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
+use crate::services::metric_expression::{parse_expression, ColumnSource, Expr};
+use crate::web::error::AppError;
+use crate::web::models::fleet_models::{FleetTopEntry, LatestUsage, TopAggregation};
+
+/// Shorthands accepted by the top-N endpoint, as metric expressions.
+const METRIC_ALIASES: [(&str, &str); 9] = [
+    ("cpu", "cpu_usage_percent"),
+    ("memory", "memory_usage_bytes / memory_total_bytes * 100"),
+    ("swap", "swap_usage_bytes / swap_total_bytes * 100"),
+    ("disk", "used_disk_space_bytes / total_disk_space_bytes * 100"),
+    ("disk_read", "disk_io_read_bps"),
+    ("disk_write", "disk_io_write_bps"),
+    ("net_rx", "network_rx_instant_bps"),
+    ("net_tx", "network_tx_instant_bps"),
+    ("net", "network_rx_instant_bps + network_tx_instant_bps"),
+];
+
+/// Resolves a metric shorthand or a plain column name. Arbitrary expressions are
+/// left to derived metrics.
+pub fn resolve_top_metric(metric: &str) -> Result<Expr, AppError> {
+    let expression = METRIC_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == metric)
+        .map(|(_, expression)| *expression)
+        .unwrap_or(metric);
+    let expr = parse_expression(expression)
+        .map_err(|_| AppError::InvalidInput(format!("Unknown metric '{metric}'.")))?;
+    if !matches!(expr, Expr::Column(_)) && expression == metric {
+        return Err(AppError::InvalidInput(format!("Unknown metric '{metric}'.")));
+    }
+    Ok(expr)
+}
+
+/// Ranks the user's VPS by the aggregate of a metric over the last `window`, in a
+/// single query. Longer windows read the same summary tables as the charts, so
+/// `Max` is then the maximum of per-bucket averages.
+pub async fn get_top_vps(
+    pool: DuckDbPool,
+    user_id: i32,
+    metric: &Expr,
+    agg: TopAggregation,
+    window: Duration,
+    limit: u32,
+    retention: &RetentionPolicy,
+) -> Result<Vec<FleetTopEntry>, AppError> {
+    let now = Utc::now();
+    let since = now - window;
+    let (metric_source, is_aggregated) = select_metric_source(since, now, retention, now);
+    let value_sql = metric.to_sql(if is_aggregated { ColumnSource::Summary } else { ColumnSource::Raw });
+    let agg_fn = match agg {
+        TopAggregation::Avg => "AVG",
+        TopAggregation::Max => "MAX",
+    };
+    let org_scope = organization_service::org_scope("v.organization_id");
+    let sql = format!(
+        r#"
+        SELECT v.id, v.name, {agg_fn}(m.value) AS value
+        FROM (
+            SELECT vps_id, {value_sql} AS value
+            FROM {metric_source}
+            WHERE "time" >= ?
+        ) m
+        JOIN vps v ON v.id = m.vps_id
+        WHERE {org_scope} AND m.value IS NOT NULL
+        GROUP BY v.id, v.name
+        ORDER BY value DESC, v.id ASC
+        LIMIT ?
+        "#
+    );
+
+    let conn = pool.get()?;
+    let entries = conn
+        .prepare(&sql)?
+        .query_map(params![since, user_id, limit], |row| {
+            Ok(FleetTopEntry {
+                vps_id: row.get(0)?,
+                vps_name: row.get(1)?,
+                value: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+/// The newest raw sample of each of the user's VPS within `max_age`, as percentages.
+pub async fn get_latest_usage(
+    pool: DuckDbPool,
+    user_id: i32,
+    max_age: Duration,
+) -> Result<HashMap<i32, LatestUsage>, AppError> {
+    let org_scope = organization_service::org_scope("v.organization_id");
+    let sql = format!(
+        r#"
+        SELECT m.vps_id,
+               arg_max(m.cpu_usage_percent, m."time"),
+               arg_max(m.memory_usage_bytes * 100.0 / NULLIF(m.memory_total_bytes, 0), m."time"),
+               arg_max(m.used_disk_space_bytes * 100.0 / NULLIF(m.total_disk_space_bytes, 0), m."time")
+        FROM performance_metrics m
+        JOIN vps v ON v.id = m.vps_id
+        WHERE m."time" >= ? AND {org_scope}
+        GROUP BY m.vps_id
+        "#
+    );
+
+    let conn = pool.get()?;
+    let usage = conn
+        .prepare(&sql)?
+        .query_map(params![Utc::now() - max_age, user_id], |row| {
+            Ok((
+                row.get(0)?,
+                LatestUsage {
+                    cpu_usage_percent: row.get(1)?,
+                    memory_usage_percent: row.get(2)?,
+                    disk_usage_percent: row.get(3)?,
+                },
+            ))
+        })?
+        .collect::<Result<HashMap<_, _>, _>>()?;
+    Ok(usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resolves_aliases_and_columns_only() {
+        assert!(resolve_top_metric("cpu").is_ok());
+        assert!(resolve_top_metric("memory").is_ok());
+        assert!(resolve_top_metric("tcp_established_connection_count").is_ok());
+        assert!(resolve_top_metric("cpu_usage_percent * 2").is_err());
+        assert!(resolve_top_metric("bogus").is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use chrono::{DateTime, Duration, Utc};
+use duckdb::{params, Connection, Result as DuckDbResult, Row};
+use std::collections::HashMap;
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::gpu_metric;
+use crate::web::error::AppError;
+use crate::web::models::websocket_models::GpuSnapshot;
+
+/// GPUs that haven't reported for this long are left out of the live snapshot.
+const LATEST_MAX_AGE_MINUTES: i64 = 10;
+
+fn row_to_gpu_metric_model(row: &Row) -> DuckDbResult<gpu_metric::Model> {
+    Ok(gpu_metric::Model {
+        time: row.get("time")?,
+        vps_id: row.get("vps_id")?,
+        gpu_index: row.get("gpu_index")?,
+        name: row.get("name")?,
+        vendor: row.get("vendor")?,
+        uuid: row.get("uuid")?,
+        utilization_percent: row.get("utilization_percent")?,
+        memory_used_bytes: row.get("memory_used_bytes")?,
+        memory_total_bytes: row.get("memory_total_bytes")?,
+        temperature_celsius: row.get("temperature_celsius")?,
+        power_draw_watts: row.get("power_draw_watts")?,
+    })
+}
+
+/// Per-GPU samples, optionally limited to one GPU. With an interval readings
+/// are averaged per bucket and memory used is the bucket's peak.
+pub async fn get_gpu_timeseries(
+    pool: DuckDbPool,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    interval_seconds: Option<u32>,
+    gpu_index: Option<i32>,
+) -> Result<Vec<gpu_metric::Model>, AppError> {
+    let gpu_filter = if gpu_index.is_some() { "AND gpu_index = ?" } else { "" };
+    let sql = match interval_seconds {
+        None => format!(
+            r#"SELECT * FROM gpu_metrics
+               WHERE vps_id = ? AND "time" >= ? AND "time" <= ? {gpu_filter}
+               ORDER BY "time" ASC, gpu_index ASC"#
+        ),
+        Some(interval_secs) => {
+            let interval_secs = interval_secs.max(1);
+            format!(
+                r#"
+                SELECT
+                    date_trunc('second', "time") + INTERVAL '{interval_secs} seconds' * (epoch("time") / {interval_secs}) AS "time",
+                    vps_id,
+                    gpu_index,
+                    MAX(name) AS name,
+                    MAX(vendor) AS vendor,
+                    MAX(uuid) AS uuid,
+                    AVG(utilization_percent) AS utilization_percent,
+                    MAX(memory_used_bytes) AS memory_used_bytes,
+                    MAX(memory_total_bytes) AS memory_total_bytes,
+                    AVG(temperature_celsius) AS temperature_celsius,
+                    AVG(power_draw_watts) AS power_draw_watts
+                FROM gpu_metrics
+                WHERE vps_id = ? AND "time" >= ? AND "time" <= ? {gpu_filter}
+                GROUP BY 1, vps_id, gpu_index
+                ORDER BY 1 ASC, gpu_index ASC
+                "#
+            )
+        }
+    };
+
+    let mut params: Vec<&dyn duckdb::ToSql> = vec![&vps_id, &start_time, &end_time];
+    if let Some(gpu_index) = gpu_index.as_ref() {
+        params.push(gpu_index);
+    }
+    let conn = pool.get()?;
+    let metrics = conn
+        .prepare(&sql)?
+        .query_map(params.as_slice(), row_to_gpu_metric_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(metrics)
+}
+
+/// The newest reading of every recently seen GPU, keyed by VPS.
+pub fn get_latest_gpus(conn: &Connection, now: DateTime<Utc>) -> Result<HashMap<i32, Vec<GpuSnapshot>>, AppError> {
+    let since = now - Duration::minutes(LATEST_MAX_AGE_MINUTES);
+    let mut latest: HashMap<i32, Vec<GpuSnapshot>> = HashMap::new();
+    let rows = conn
+        .prepare(
+            r#"SELECT * FROM gpu_metrics
+               WHERE "time" >= ?
+               QUALIFY ROW_NUMBER() OVER (PARTITION BY vps_id, gpu_index ORDER BY "time" DESC) = 1
+               ORDER BY vps_id, gpu_index"#,
+        )?
+        .query_map(params![since], row_to_gpu_metric_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    for row in rows {
+        latest.entry(row.vps_id).or_default().push(GpuSnapshot::from(row));
+    }
+    Ok(latest)
+}
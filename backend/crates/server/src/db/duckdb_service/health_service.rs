@@ -0,0 +1,319 @@
+use chrono::{DateTime, Duration, Utc};
+use duckdb::{params, Connection};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::db::duckdb_service::{settings_service, DuckDbPool};
+use crate::web::error::AppError;
+
+pub const HEALTH_SCORE_SETTING_KEY: &str = "health_score";
+
+/// Relative weight of each component. Components without data are left out
+/// and the remaining weights are rescaled.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HealthWeights {
+    pub cpu: f64,
+    pub memory: f64,
+    pub disk: f64,
+    pub alerts: f64,
+    pub freshness: f64,
+    pub monitors: f64,
+}
+
+impl Default for HealthWeights {
+    fn default() -> Self {
+        Self { cpu: 1.0, memory: 1.0, disk: 1.0, alerts: 2.0, freshness: 2.0, monitors: 1.5 }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct HealthScoreSettings {
+    pub weights: HealthWeights,
+    /// Resource usage up to this percentage scores 100...
+    pub pressure_warn_percent: f64,
+    /// ...and from this percentage on scores 0, linearly in between.
+    pub pressure_critical_percent: f64,
+    /// Unacknowledged alerts triggered within this window count as open.
+    pub alert_window_minutes: i64,
+    /// Points deducted from the alert component per open alert.
+    pub alert_penalty: f64,
+    /// Metrics up to this age score 100 for freshness...
+    pub fresh_seconds: i64,
+    /// ...and from this age on score 0.
+    pub stale_seconds: i64,
+    /// Only monitor results within this window are considered.
+    pub monitor_window_minutes: i64,
+}
+
+impl Default for HealthScoreSettings {
+    fn default() -> Self {
+        Self {
+            weights: HealthWeights::default(),
+            pressure_warn_percent: 70.0,
+            pressure_critical_percent: 95.0,
+            alert_window_minutes: 60,
+            alert_penalty: 25.0,
+            fresh_seconds: 60,
+            stale_seconds: 600,
+            monitor_window_minutes: 15,
+        }
+    }
+}
+
+/// Per-component scores (0–100); `None` where there is no data.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthComponents {
+    pub cpu: Option<f64>,
+    pub memory: Option<f64>,
+    pub disk: Option<f64>,
+    pub alerts: Option<f64>,
+    pub freshness: Option<f64>,
+    pub monitors: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthScore {
+    /// Weighted 0–100 score; `None` if no component has data.
+    pub score: Option<f64>,
+    pub components: HealthComponents,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupHealth {
+    /// `None` for VPS without a group.
+    pub group: Option<String>,
+    /// Mean of the member scores that have one.
+    pub score: Option<f64>,
+    pub vps_count: usize,
+}
+
+#[derive(Debug, Default)]
+struct RawHealthInputs {
+    latest_metric_time: Option<DateTime<Utc>>,
+    cpu_percent: Option<f64>,
+    memory_percent: Option<f64>,
+    disk_percent: Option<f64>,
+    open_alerts: i64,
+    monitors_total: i64,
+    monitors_up: i64,
+}
+
+pub async fn get_health_settings(pool: DuckDbPool) -> Result<HealthScoreSettings, AppError> {
+    let settings = settings_service::get_setting(pool, HEALTH_SCORE_SETTING_KEY)
+        .await?
+        .map(|s| serde_json::from_value(s.value))
+        .transpose()?
+        .unwrap_or_default();
+    Ok(settings)
+}
+
+pub async fn update_health_settings(pool: DuckDbPool, settings: &HealthScoreSettings) -> Result<(), AppError> {
+    let value = serde_json::to_value(settings)?;
+    settings_service::update_setting(pool, HEALTH_SCORE_SETTING_KEY, &value).await?;
+    Ok(())
+}
+
+fn percent(used: i64, total: i64) -> Option<f64> {
+    (total > 0).then(|| used as f64 * 100.0 / total as f64)
+}
+
+/// 100 at or below `warn`, 0 at or above `critical`, linear in between.
+pub fn pressure_score(usage_percent: f64, warn: f64, critical: f64) -> f64 {
+    if usage_percent <= warn {
+        100.0
+    } else if usage_percent >= critical || critical <= warn {
+        0.0
+    } else {
+        100.0 * (critical - usage_percent) / (critical - warn)
+    }
+}
+
+fn freshness_score(age_seconds: i64, fresh: i64, stale: i64) -> f64 {
+    pressure_score(age_seconds as f64, fresh as f64, stale as f64)
+}
+
+/// Weighted mean of the components that have data.
+pub fn combine(components: &HealthComponents, weights: &HealthWeights) -> Option<f64> {
+    let parts = [
+        (components.cpu, weights.cpu),
+        (components.memory, weights.memory),
+        (components.disk, weights.disk),
+        (components.alerts, weights.alerts),
+        (components.freshness, weights.freshness),
+        (components.monitors, weights.monitors),
+    ];
+    let (sum, total_weight) = parts
+        .iter()
+        .filter_map(|(score, weight)| score.map(|s| (s, weight.max(0.0))))
+        .fold((0.0, 0.0), |(sum, total), (s, w)| (sum + s * w, total + w));
+    (total_weight > 0.0).then(|| (sum / total_weight * 10.0).round() / 10.0)
+}
+
+fn score_inputs(inputs: &RawHealthInputs, settings: &HealthScoreSettings, now: DateTime<Utc>) -> HealthScore {
+    let pressure = |p: Option<f64>| {
+        p.map(|p| pressure_score(p, settings.pressure_warn_percent, settings.pressure_critical_percent))
+    };
+    let metrics_current = inputs
+        .latest_metric_time
+        .is_some_and(|t| (now - t).num_seconds() < settings.stale_seconds);
+    // Resource pressure from stale metrics says nothing about the present.
+    let components = HealthComponents {
+        cpu: pressure(inputs.cpu_percent.filter(|_| metrics_current)),
+        memory: pressure(inputs.memory_percent.filter(|_| metrics_current)),
+        disk: pressure(inputs.disk_percent.filter(|_| metrics_current)),
+        alerts: Some((100.0 - inputs.open_alerts as f64 * settings.alert_penalty).max(0.0)),
+        freshness: Some(match inputs.latest_metric_time {
+            Some(t) => freshness_score((now - t).num_seconds(), settings.fresh_seconds, settings.stale_seconds),
+            None => 0.0,
+        }),
+        monitors: (inputs.monitors_total > 0)
+            .then(|| inputs.monitors_up as f64 * 100.0 / inputs.monitors_total as f64),
+    };
+    HealthScore { score: combine(&components, &settings.weights), components }
+}
+
+/// Scores for each VPS, using an existing connection.
+pub fn compute_health_scores(
+    conn: &Connection,
+    vps_ids: &[i32],
+    now: DateTime<Utc>,
+) -> Result<HashMap<i32, HealthScore>, AppError> {
+    let settings: HealthScoreSettings = settings_service::get_setting_with_conn(conn, HEALTH_SCORE_SETTING_KEY)?
+        .map(|s| serde_json::from_value(s.value))
+        .transpose()?
+        .unwrap_or_default();
+    let mut inputs: HashMap<i32, RawHealthInputs> =
+        vps_ids.iter().map(|id| (*id, RawHealthInputs::default())).collect();
+
+    // Anything older than a day is just "stale", like a VPS without metrics.
+    let mut stmt = conn.prepare(
+        "SELECT vps_id, time, cpu_usage_percent, memory_usage_bytes, memory_total_bytes,
+                used_disk_space_bytes, total_disk_space_bytes
+         FROM latest_performance_metrics
+         WHERE time >= ?",
+    )?;
+    let mut rows = stmt.query(params![now - Duration::days(1)])?;
+    while let Some(row) = rows.next()? {
+        let vps_id: i32 = row.get(0)?;
+        if let Some(entry) = inputs.get_mut(&vps_id) {
+            entry.latest_metric_time = row.get(1)?;
+            entry.cpu_percent = row.get(2)?;
+            entry.memory_percent = percent(row.get(3)?, row.get(4)?);
+            entry.disk_percent = percent(row.get(5)?, row.get(6)?);
+        }
+    }
+
+    let mut stmt = conn.prepare(
+        "SELECT vps_id, COUNT(*)
+         FROM alert_events
+         WHERE resolve_time IS NULL AND acknowledged_at IS NULL AND trigger_time >= ?
+           AND (silenced_until IS NULL OR silenced_until < ?)
+         GROUP BY vps_id",
+    )?;
+    let mut rows = stmt.query(params![now - Duration::minutes(settings.alert_window_minutes), now])?;
+    while let Some(row) = rows.next()? {
+        let vps_id: i32 = row.get(0)?;
+        if let Some(entry) = inputs.get_mut(&vps_id) {
+            entry.open_alerts = row.get(1)?;
+        }
+    }
+
+    // The latest result of each monitor run by the VPS.
+    let mut stmt = conn.prepare(
+        "SELECT agent_id, COUNT(*), CAST(SUM(CASE WHEN is_up THEN 1 ELSE 0 END) AS BIGINT)
+         FROM (
+             SELECT agent_id, monitor_id, arg_max(is_up, time) AS is_up
+             FROM service_monitor_results
+             WHERE time >= ?
+             GROUP BY agent_id, monitor_id
+         )
+         GROUP BY agent_id",
+    )?;
+    let mut rows = stmt.query(params![now - Duration::minutes(settings.monitor_window_minutes)])?;
+    while let Some(row) = rows.next()? {
+        let vps_id: i32 = row.get(0)?;
+        if let Some(entry) = inputs.get_mut(&vps_id) {
+            entry.monitors_total = row.get(1)?;
+            entry.monitors_up = row.get(2)?;
+        }
+    }
+
+    Ok(inputs
+        .into_iter()
+        .map(|(vps_id, inputs)| (vps_id, score_inputs(&inputs, &settings, now)))
+        .collect())
+}
+
+/// Averages member scores per group, sorted by group name with ungrouped VPS first.
+pub fn group_health<'a>(members: impl IntoIterator<Item = (Option<&'a str>, Option<f64>)>) -> Vec<GroupHealth> {
+    let mut groups: BTreeMap<Option<&str>, (f64, usize, usize)> = BTreeMap::new();
+    for (group, score) in members {
+        let entry = groups.entry(group).or_default();
+        entry.2 += 1;
+        if let Some(score) = score {
+            entry.0 += score;
+            entry.1 += 1;
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(group, (sum, scored, vps_count))| GroupHealth {
+            group: group.map(str::to_string),
+            score: (scored > 0).then(|| (sum / scored as f64 * 10.0).round() / 10.0),
+            vps_count,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pressure_is_linear_between_thresholds() {
+        assert_eq!(pressure_score(50.0, 70.0, 95.0), 100.0);
+        assert_eq!(pressure_score(82.5, 70.0, 95.0), 50.0);
+        assert_eq!(pressure_score(99.0, 70.0, 95.0), 0.0);
+    }
+
+    #[test]
+    fn missing_components_are_left_out() {
+        let components = HealthComponents {
+            cpu: Some(100.0),
+            alerts: Some(50.0),
+            ..Default::default()
+        };
+        let weights = HealthWeights { cpu: 1.0, alerts: 1.0, ..Default::default() };
+        assert_eq!(combine(&components, &weights), Some(75.0));
+        assert_eq!(combine(&HealthComponents::default(), &weights), None);
+    }
+
+    #[test]
+    fn stale_metrics_only_affect_freshness() {
+        let now = Utc::now();
+        let inputs = RawHealthInputs {
+            latest_metric_time: Some(now - Duration::hours(1)),
+            cpu_percent: Some(100.0),
+            ..Default::default()
+        };
+        let health = score_inputs(&inputs, &HealthScoreSettings::default(), now);
+        assert_eq!(health.components.cpu, None);
+        assert_eq!(health.components.freshness, Some(0.0));
+    }
+
+    #[test]
+    fn groups_average_scored_members() {
+        let groups = group_health([(Some("web"), Some(80.0)), (Some("web"), Some(60.0)), (None, None)]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].group, None);
+        assert_eq!(groups[0].score, None);
+        assert_eq!(groups[1].score, Some(70.0));
+        assert_eq!(groups[1].vps_count, 2);
+    }
+}
@@ -0,0 +1,200 @@
+use chrono::{TimeZone, Utc};
+use duckdb::{params, OptionalExt, Result as DuckDbResult, Row};
+use sha2::{Digest, Sha256};
+use tracing::warn;
+
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
+use crate::db::entities::ingest_key;
+use crate::web::error::AppError;
+use crate::services::metric_ingest::validate_metric_name;
+use crate::web::models::ingest_models::IngestMetricSample;
+use nodenexus_common::agent_service::{generic_metric_value::ValueType, GenericMetric};
+
+/// Prefix of ingest keys, distinct from API tokens and public keys.
+pub const KEY_PREFIX: &str = "nni_";
+
+/// Characters of the key kept in plain text for display.
+const DISPLAY_PREFIX_LEN: usize = 12;
+
+fn json_column<T: serde::de::DeserializeOwned>(row: &Row, column: &str) -> DuckDbResult<T> {
+    let raw: String = row.get(column)?;
+    serde_json::from_str(&raw).map_err(|e| {
+        duckdb::Error::FromSqlConversionFailure(0, duckdb::types::Type::Text, Box::new(e))
+    })
+}
+
+fn row_to_ingest_key_model(row: &Row) -> DuckDbResult<ingest_key::Model> {
+    Ok(ingest_key::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        name: row.get("name")?,
+        key_prefix: row.get("key_prefix")?,
+        metric_prefixes: json_column(row, "metric_prefixes")?,
+        rate_limit_per_minute: row.get("rate_limit_per_minute")?,
+        created_at: row.get("created_at")?,
+        last_used_at: row.get("last_used_at")?,
+    })
+}
+
+fn hash_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Creates a key and returns it along with its plain text value, which is
+/// never available again.
+pub async fn create_ingest_key(
+    pool: DuckDbPool,
+    user_id: i32,
+    name: &str,
+    metric_prefixes: &[String],
+    rate_limit_per_minute: u32,
+) -> Result<(ingest_key::Model, String), AppError> {
+    let key = format!("{KEY_PREFIX}{}", hex::encode(rand::random::<[u8; 24]>()));
+    let conn = pool.get()?;
+    let model = conn.query_row(
+        "INSERT INTO ingest_keys (user_id, name, key_hash, key_prefix, metric_prefixes, rate_limit_per_minute)
+         VALUES (?, ?, ?, ?, ?, ?) RETURNING *",
+        params![
+            user_id,
+            name,
+            hash_key(&key),
+            &key[..DISPLAY_PREFIX_LEN],
+            serde_json::to_string(metric_prefixes)?,
+            rate_limit_per_minute,
+        ],
+        row_to_ingest_key_model,
+    )?;
+    Ok((model, key))
+}
+
+pub async fn get_ingest_keys_for_user(pool: DuckDbPool, user_id: i32) -> Result<Vec<ingest_key::Model>, AppError> {
+    let conn = pool.get()?;
+    let keys = conn
+        .prepare("SELECT * FROM ingest_keys WHERE user_id = ? ORDER BY created_at DESC")?
+        .query_map(params![user_id], row_to_ingest_key_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(keys)
+}
+
+pub async fn delete_ingest_key(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute("DELETE FROM ingest_keys WHERE id = ? AND user_id = ?", params![id, user_id])?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("Ingest key not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Looks up a presented key and records its use.
+pub async fn authenticate_ingest_key(pool: DuckDbPool, key: &str) -> Result<Option<ingest_key::Model>, AppError> {
+    if !key.starts_with(KEY_PREFIX) {
+        return Ok(None);
+    }
+    let conn = pool.get()?;
+    let model = conn
+        .query_row(
+            "SELECT * FROM ingest_keys WHERE key_hash = ?",
+            params![hash_key(key)],
+            row_to_ingest_key_model,
+        )
+        .optional()?;
+    if let Some(model) = &model {
+        if let Err(e) = conn.execute(
+            "UPDATE ingest_keys SET last_used_at = ? WHERE id = ?",
+            params![Utc::now(), model.id],
+        ) {
+            warn!(key_id = model.id, error = %e, "Failed to record ingest key use.");
+        }
+    }
+    Ok(model)
+}
+
+/// Writes validated samples for `user_id`. Every referenced VPS must be visible
+/// in the user's organization.
+pub async fn insert_generic_metrics(
+    pool: DuckDbPool,
+    user_id: i32,
+    samples: &[IngestMetricSample],
+) -> Result<(), AppError> {
+    let mut conn = pool.get()?;
+    let mut vps_ids: Vec<i32> = samples.iter().filter_map(|s| s.vps_id).collect();
+    vps_ids.sort_unstable();
+    vps_ids.dedup();
+    for vps_id in &vps_ids {
+        let visible = conn
+            .query_row(
+                &format!("SELECT 1 FROM vps WHERE id = ? AND {}", organization_service::org_scope("organization_id")),
+                params![vps_id, user_id],
+                |_| Ok(()),
+            )
+            .optional()?;
+        if visible.is_none() {
+            return Err(AppError::InvalidInput(format!("VPS {vps_id} not found")));
+        }
+    }
+
+    let tx = conn.transaction()?;
+    {
+        let mut appender = tx.appender("generic_metrics")?;
+        for sample in samples {
+            // Column order must match the table definition.
+            appender.append_row(params![
+                sample.timestamp.unwrap_or_else(Utc::now),
+                user_id,
+                sample.vps_id,
+                sample.name,
+                sample.value,
+                serde_json::to_string(&sample.labels)?,
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// Numeric value of a metric sent by an agent; strings and bytes have none.
+fn agent_metric_value(metric: &GenericMetric) -> Option<f64> {
+    match metric.value.as_ref()?.value_type.as_ref()? {
+        ValueType::DoubleValue(value) => value.is_finite().then_some(*value),
+        ValueType::Int64Value(value) => Some(*value as f64),
+        ValueType::BoolValue(value) => Some(if *value { 1.0 } else { 0.0 }),
+        ValueType::StringValue(_) | ValueType::BytesValue(_) => None,
+    }
+}
+
+/// Writes metrics an agent reported about itself, attributed to its VPS and
+/// owned by the VPS owner. Samples without a valid name or numeric value are
+/// skipped. Returns how many were written.
+pub async fn insert_agent_metrics(pool: DuckDbPool, vps_id: i32, metrics: &[GenericMetric]) -> Result<usize, AppError> {
+    let mut conn = pool.get()?;
+    let Some(user_id) = conn
+        .query_row("SELECT user_id FROM vps WHERE id = ?", params![vps_id], |row| row.get::<_, i32>(0))
+        .optional()?
+    else {
+        return Err(AppError::NotFound(format!("VPS {vps_id} not found")));
+    };
+
+    let tx = conn.transaction()?;
+    let mut written = 0;
+    {
+        let mut appender = tx.appender("generic_metrics")?;
+        for metric in metrics {
+            let Some(value) = agent_metric_value(metric) else {
+                continue;
+            };
+            if validate_metric_name(&metric.name).is_err() {
+                continue;
+            }
+            let time = Utc
+                .timestamp_millis_opt(metric.timestamp_unix_ms)
+                .single()
+                .unwrap_or_else(Utc::now);
+            let labels: std::collections::BTreeMap<_, _> = metric.tags.iter().collect();
+            // Column order must match the table definition.
+            appender.append_row(params![time, user_id, vps_id, metric.name, value, serde_json::to_string(&labels)?])?;
+            written += 1;
+        }
+    }
+    tx.commit()?;
+    Ok(written)
+}
@@ -0,0 +1,354 @@
+use chrono::{DateTime, TimeZone, Utc};
+use duckdb::{params, OptionalExt};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use nodenexus_common::agent_service::InventoryFacts;
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
+use crate::services::package_version::version_matches;
+use crate::web::error::AppError;
+use crate::web::models::inventory_models::{
+    InventoryChange, InventoryChangeRecord, InventorySearchHit, InventorySnapshot, PackageFact,
+    VersionOp, VpsInventoryResponse,
+};
+
+const DEFAULT_CHANGES_LIMIT: u32 = 200;
+const MAX_CHANGES_LIMIT: u32 = 2000;
+
+pub fn snapshot_from_facts(facts: InventoryFacts) -> InventorySnapshot {
+    let mut packages: Vec<PackageFact> = facts
+        .packages
+        .into_iter()
+        .map(|p| PackageFact {
+            name: p.name,
+            version: p.version,
+            arch: p.arch,
+            source_name: p.source_name,
+        })
+        .collect();
+    packages.sort_by(|a, b| (&a.name, &a.arch, &a.version).cmp(&(&b.name, &b.arch, &b.version)));
+    packages.dedup();
+
+    let mut enabled_services = facts.enabled_services;
+    enabled_services.sort();
+    enabled_services.dedup();
+
+    InventorySnapshot {
+        package_manager: facts.package_manager,
+        packages,
+        sysctl: facts.sysctl.into_iter().collect(),
+        enabled_services,
+    }
+}
+
+/// Installed versions per package, keyed by `name` or `name:arch`.
+fn package_versions(snapshot: &InventorySnapshot) -> BTreeMap<String, String> {
+    let mut versions: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for p in &snapshot.packages {
+        let key = if p.arch.is_empty() { p.name.clone() } else { format!("{}:{}", p.name, p.arch) };
+        versions.entry(key).or_default().push(&p.version);
+    }
+    versions.into_iter().map(|(k, v)| (k, v.join(", "))).collect()
+}
+
+fn diff_maps(
+    category: &str,
+    old: &BTreeMap<String, String>,
+    new: &BTreeMap<String, String>,
+    changes: &mut Vec<InventoryChange>,
+) {
+    for (name, old_value) in old {
+        match new.get(name) {
+            Some(new_value) if new_value == old_value => {}
+            new_value => changes.push(InventoryChange {
+                category: category.to_string(),
+                name: name.clone(),
+                old_value: Some(old_value.clone()),
+                new_value: new_value.cloned(),
+            }),
+        }
+    }
+    for (name, new_value) in new {
+        if !old.contains_key(name) {
+            changes.push(InventoryChange {
+                category: category.to_string(),
+                name: name.clone(),
+                old_value: None,
+                new_value: Some(new_value.clone()),
+            });
+        }
+    }
+}
+
+/// Lists what changed between two snapshots. Added entries have no old value,
+/// removed entries no new value.
+pub fn diff_snapshots(old: &InventorySnapshot, new: &InventorySnapshot) -> Vec<InventoryChange> {
+    let mut changes = Vec::new();
+    diff_maps("package", &package_versions(old), &package_versions(new), &mut changes);
+    diff_maps("sysctl", &old.sysctl, &new.sysctl, &mut changes);
+
+    let as_map = |services: &[String]| -> BTreeMap<String, String> {
+        services.iter().map(|s| (s.clone(), "enabled".to_string())).collect()
+    };
+    diff_maps(
+        "service",
+        &as_map(&old.enabled_services),
+        &as_map(&new.enabled_services),
+        &mut changes,
+    );
+    changes
+}
+
+fn compress_snapshot(snapshot: &InventorySnapshot) -> Result<Vec<u8>, AppError> {
+    let json = serde_json::to_vec(snapshot)?;
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(&json).map_err(|e| AppError::InternalServerError(format!("Failed to compress inventory: {e}")))?;
+    encoder
+        .finish()
+        .map_err(|e| AppError::InternalServerError(format!("Failed to compress inventory: {e}")))
+}
+
+fn decompress_snapshot(data: &[u8]) -> Result<InventorySnapshot, AppError> {
+    let mut json = Vec::new();
+    GzDecoder::new(data)
+        .read_to_end(&mut json)
+        .map_err(|e| AppError::InternalServerError(format!("Failed to decompress inventory: {e}")))?;
+    Ok(serde_json::from_slice(&json)?)
+}
+
+/// Stores a new inventory snapshot for a VPS and records what changed since the
+/// previous one. Returns the number of recorded changes.
+pub async fn record_inventory(
+    pool: DuckDbPool,
+    vps_id: i32,
+    facts: InventoryFacts,
+) -> Result<usize, AppError> {
+    tokio::task::spawn_blocking(move || -> Result<usize, AppError> {
+        let collected_at = Utc
+            .timestamp_millis_opt(facts.collected_at_unix_ms)
+            .single()
+            .unwrap_or_else(Utc::now);
+        let snapshot = snapshot_from_facts(facts);
+
+        let mut conn = pool.get()?;
+        let previous: Option<Vec<u8>> = conn
+            .query_row(
+                "SELECT facts_gz FROM vps_inventory WHERE vps_id = ?",
+                params![vps_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let previous = previous.map(|data| decompress_snapshot(&data)).transpose()?;
+
+        if previous.as_ref() == Some(&snapshot) {
+            conn.execute(
+                "UPDATE vps_inventory SET collected_at = ?, updated_at = ? WHERE vps_id = ?",
+                params![collected_at, Utc::now(), vps_id],
+            )?;
+            return Ok(0);
+        }
+
+        // The first snapshot of a host is its baseline, not a list of changes.
+        let changes = previous
+            .as_ref()
+            .map(|old| diff_snapshots(old, &snapshot))
+            .unwrap_or_default();
+        let packages_changed = previous.as_ref().map(|old| old.packages != snapshot.packages).unwrap_or(true);
+        let facts_gz = compress_snapshot(&snapshot)?;
+        let package_count = snapshot.packages.len() as i32;
+        let now = Utc::now();
+
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO vps_inventory (vps_id, package_manager, package_count, facts_gz, collected_at, changed_at, updated_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (vps_id) DO UPDATE SET
+                package_manager = excluded.package_manager,
+                package_count = excluded.package_count,
+                facts_gz = excluded.facts_gz,
+                collected_at = excluded.collected_at,
+                changed_at = excluded.changed_at,
+                updated_at = excluded.updated_at",
+            params![
+                vps_id,
+                snapshot.package_manager,
+                package_count,
+                facts_gz,
+                collected_at,
+                collected_at,
+                now
+            ],
+        )?;
+
+        if packages_changed {
+            tx.execute("DELETE FROM vps_inventory_packages WHERE vps_id = ?", params![vps_id])?;
+            let mut stmt = tx.prepare(
+                "INSERT INTO vps_inventory_packages (vps_id, name, arch, version, source_name) VALUES (?, ?, ?, ?, ?)",
+            )?;
+            for p in &snapshot.packages {
+                let source_name = (!p.source_name.is_empty()).then_some(p.source_name.as_str());
+                stmt.execute(params![vps_id, p.name, p.arch, p.version, source_name])?;
+            }
+        }
+
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO vps_inventory_changes (vps_id, changed_at, category, name, old_value, new_value)
+                 VALUES (?, ?, ?, ?, ?, ?)",
+            )?;
+            for change in &changes {
+                stmt.execute(params![
+                    vps_id,
+                    collected_at,
+                    change.category,
+                    change.name,
+                    change.old_value,
+                    change.new_value
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(changes.len())
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+}
+
+pub async fn get_inventory(
+    pool: DuckDbPool,
+    vps_id: i32,
+) -> Result<Option<VpsInventoryResponse>, AppError> {
+    let conn = pool.get()?;
+    let row: Option<(Vec<u8>, DateTime<Utc>, DateTime<Utc>)> = conn
+        .query_row(
+            "SELECT facts_gz, collected_at, changed_at FROM vps_inventory WHERE vps_id = ?",
+            params![vps_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )
+        .optional()?;
+
+    row.map(|(facts_gz, collected_at, changed_at)| {
+        Ok(VpsInventoryResponse {
+            vps_id,
+            collected_at,
+            changed_at,
+            snapshot: decompress_snapshot(&facts_gz)?,
+        })
+    })
+    .transpose()
+}
+
+pub async fn get_inventory_changes(
+    pool: DuckDbPool,
+    vps_id: i32,
+    limit: Option<u32>,
+) -> Result<Vec<InventoryChangeRecord>, AppError> {
+    let limit = limit.unwrap_or(DEFAULT_CHANGES_LIMIT).clamp(1, MAX_CHANGES_LIMIT);
+    let conn = pool.get()?;
+    let changes = conn
+        .prepare(
+            "SELECT id, vps_id, changed_at, category, name, old_value, new_value
+             FROM vps_inventory_changes WHERE vps_id = ? ORDER BY changed_at DESC, id DESC LIMIT ?",
+        )?
+        .query_map(params![vps_id, limit], |row| {
+            Ok(InventoryChangeRecord {
+                id: row.get(0)?,
+                vps_id: row.get(1)?,
+                changed_at: row.get(2)?,
+                change: InventoryChange {
+                    category: row.get(3)?,
+                    name: row.get(4)?,
+                    old_value: row.get(5)?,
+                    new_value: row.get(6)?,
+                },
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(changes)
+}
+
+/// Finds the user's hosts that have `package` installed, optionally restricted to
+/// versions matching `op version` (e.g. `lt 3.0.13`).
+pub async fn search_packages(
+    pool: DuckDbPool,
+    user_id: i32,
+    package: &str,
+    constraint: Option<(VersionOp, &str)>,
+) -> Result<Vec<InventorySearchHit>, AppError> {
+    let conn = pool.get()?;
+    let hits = conn
+        .prepare(&format!(
+            "SELECT p.vps_id, v.name, p.name, p.version, p.arch
+             FROM vps_inventory_packages p
+             JOIN vps v ON v.id = p.vps_id
+             WHERE {} AND lower(p.name) = lower(?)
+             ORDER BY p.vps_id, p.arch, p.version",
+            organization_service::org_scope("v.organization_id")
+        ))?
+        .query_map(params![user_id, package], |row| {
+            Ok(InventorySearchHit {
+                vps_id: row.get(0)?,
+                vps_name: row.get(1)?,
+                name: row.get(2)?,
+                version: row.get(3)?,
+                arch: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(match constraint {
+        Some((op, version)) => hits
+            .into_iter()
+            .filter(|hit| version_matches(&hit.version, op, version))
+            .collect(),
+        None => hits,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(name: &str, version: &str) -> PackageFact {
+        PackageFact {
+            name: name.to_string(),
+            version: version.to_string(),
+            arch: "amd64".to_string(),
+            source_name: String::new(),
+        }
+    }
+
+    #[test]
+    fn diff_reports_upgrades_additions_and_removals() {
+        let old = InventorySnapshot {
+            package_manager: "dpkg".to_string(),
+            packages: vec![package("curl", "7.88.1"), package("openssl", "3.0.11")],
+            sysctl: BTreeMap::from([("net.ipv4.ip_forward".to_string(), "0".to_string())]),
+            enabled_services: vec!["ssh.service".to_string()],
+        };
+        let new = InventorySnapshot {
+            package_manager: "dpkg".to_string(),
+            packages: vec![package("nginx", "1.22.1"), package("openssl", "3.0.13")],
+            sysctl: BTreeMap::from([("net.ipv4.ip_forward".to_string(), "1".to_string())]),
+            enabled_services: vec!["nginx.service".to_string(), "ssh.service".to_string()],
+        };
+
+        let changes = diff_snapshots(&old, &new);
+        let summary: Vec<(&str, &str, Option<&str>, Option<&str>)> = changes
+            .iter()
+            .map(|c| (c.category.as_str(), c.name.as_str(), c.old_value.as_deref(), c.new_value.as_deref()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("package", "curl:amd64", Some("7.88.1"), None),
+                ("package", "openssl:amd64", Some("3.0.11"), Some("3.0.13")),
+                ("package", "nginx:amd64", None, Some("1.22.1")),
+                ("sysctl", "net.ipv4.ip_forward", Some("0"), Some("1")),
+                ("service", "nginx.service", None, Some("enabled")),
+            ]
+        );
+        assert!(diff_snapshots(&new, &new).is_empty());
+    }
+}
@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use duckdb::params;
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::log_match;
+use crate::web::error::AppError;
+
+/// Alert rules with this metric type count the tailed log lines matching their
+/// `log_pattern` over their duration.
+pub const LOG_MATCHES_METRIC_TYPE: &str = "log_matches";
+
+/// Active log rules that apply to the VPS, as `(rule id, pattern)`: those for
+/// the VPS itself and the global ones of its organization.
+pub async fn get_log_rules_for_vps(pool: DuckDbPool, vps_id: i32) -> Result<Vec<(i32, String)>, AppError> {
+    let conn = pool.get()?;
+    let rules = conn
+        .prepare(
+            "SELECT id, log_pattern FROM alert_rules
+             WHERE is_active AND metric_type = ? AND log_pattern IS NOT NULL
+               AND (vps_id = ? OR (vps_id IS NULL AND organization_id = (SELECT organization_id FROM vps WHERE id = ?)))",
+        )?
+        .query_map(params![LOG_MATCHES_METRIC_TYPE, vps_id, vps_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rules)
+}
+
+pub async fn record_log_matches(pool: DuckDbPool, matches: &[log_match::Model]) -> Result<usize, AppError> {
+    if matches.is_empty() {
+        return Ok(0);
+    }
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    {
+        let mut appender = tx.appender("log_matches")?;
+        for m in matches {
+            // Column order must match the table definition.
+            appender.append_row(params![m.time, m.vps_id, m.alert_rule_id, m.source, m.line])?;
+        }
+    }
+    tx.commit()?;
+    Ok(matches.len())
+}
+
+/// The number of lines that matched the rule on the VPS since `since`, and the
+/// most recent of them.
+pub async fn count_log_matches(
+    pool: DuckDbPool,
+    rule_id: i32,
+    vps_id: i32,
+    since: DateTime<Utc>,
+) -> Result<(i64, Option<String>), AppError> {
+    let conn = pool.get()?;
+    let result = conn.query_row(
+        "SELECT COUNT(*), arg_max(source || ': ' || line, time) FROM log_matches
+         WHERE alert_rule_id = ? AND vps_id = ? AND time >= ?",
+        params![rule_id, vps_id, since],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )?;
+    Ok(result)
+}
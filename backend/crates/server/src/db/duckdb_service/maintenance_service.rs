@@ -0,0 +1,249 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, Connection, Result as DuckDbResult, Row};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::vps_maintenance_event;
+use crate::web::error::AppError;
+
+pub const ACTION_ENTER: &str = "enter";
+pub const ACTION_EXIT: &str = "exit";
+/// Actor recorded for exits made by the scheduler.
+pub const AUTO_EXIT_ACTOR: &str = "auto";
+
+/// An active maintenance period, as carried in broadcasts.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceState {
+    pub since: DateTime<Utc>,
+    /// Scheduled automatic exit, if any.
+    pub until: Option<DateTime<Utc>>,
+    pub reason: Option<String>,
+}
+
+fn row_to_maintenance_event_model(row: &Row) -> DuckDbResult<vps_maintenance_event::Model> {
+    Ok(vps_maintenance_event::Model {
+        id: row.get("id")?,
+        vps_id: row.get("vps_id")?,
+        time: row.get("time")?,
+        action: row.get("action")?,
+        reason: row.get("reason")?,
+        ends_at: row.get("ends_at")?,
+        actor: row.get("actor")?,
+    })
+}
+
+fn active_state(event: &vps_maintenance_event::Model, now: DateTime<Utc>) -> Option<MaintenanceState> {
+    let active = event.action == ACTION_ENTER && !matches!(event.ends_at, Some(ends_at) if ends_at <= now);
+    active.then(|| MaintenanceState {
+        since: event.time,
+        until: event.ends_at,
+        reason: event.reason.clone(),
+    })
+}
+
+fn latest_events(conn: &Connection) -> Result<Vec<vps_maintenance_event::Model>, AppError> {
+    let events = conn
+        .prepare(
+            "SELECT * FROM vps_maintenance_events
+             QUALIFY row_number() OVER (PARTITION BY vps_id ORDER BY time DESC, id DESC) = 1",
+        )?
+        .query_map([], row_to_maintenance_event_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(events)
+}
+
+/// VPS currently in maintenance, using an existing connection.
+pub fn get_active_maintenance(
+    conn: &Connection,
+    now: DateTime<Utc>,
+) -> Result<HashMap<i32, MaintenanceState>, AppError> {
+    Ok(latest_events(conn)?
+        .iter()
+        .filter_map(|event| active_state(event, now).map(|state| (event.vps_id, state)))
+        .collect())
+}
+
+pub async fn get_maintenance_state(pool: DuckDbPool, vps_id: i32) -> Result<Option<MaintenanceState>, AppError> {
+    let conn = pool.get()?;
+    let event = conn
+        .prepare("SELECT * FROM vps_maintenance_events WHERE vps_id = ? ORDER BY time DESC, id DESC LIMIT 1")?
+        .query_map(params![vps_id], row_to_maintenance_event_model)?
+        .next()
+        .transpose()?;
+    Ok(event.and_then(|event| active_state(&event, Utc::now())))
+}
+
+pub async fn is_in_maintenance(pool: DuckDbPool, vps_id: i32) -> Result<bool, AppError> {
+    Ok(get_maintenance_state(pool, vps_id).await?.is_some())
+}
+
+/// Puts a VPS into maintenance. Entering again while active replaces the
+/// reason and scheduled exit.
+pub async fn enter_maintenance(
+    pool: DuckDbPool,
+    vps_id: i32,
+    reason: Option<String>,
+    ends_at: Option<DateTime<Utc>>,
+    actor: &str,
+) -> Result<MaintenanceState, AppError> {
+    let now = Utc::now();
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO vps_maintenance_events (vps_id, time, action, reason, ends_at, actor) VALUES (?, ?, ?, ?, ?, ?)",
+        params![vps_id, now, ACTION_ENTER, reason, ends_at, actor],
+    )?;
+    Ok(MaintenanceState { since: now, until: ends_at, reason })
+}
+
+/// Ends maintenance for a VPS. Returns `false` if it wasn't in maintenance.
+pub async fn exit_maintenance(pool: DuckDbPool, vps_id: i32, actor: &str) -> Result<bool, AppError> {
+    if !is_in_maintenance(pool.clone(), vps_id).await? {
+        return Ok(false);
+    }
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO vps_maintenance_events (vps_id, time, action, actor) VALUES (?, ?, ?, ?)",
+        params![vps_id, Utc::now(), ACTION_EXIT, actor],
+    )?;
+    Ok(true)
+}
+
+/// Records the exit of every maintenance period whose scheduled end has
+/// passed, at its scheduled time. Returns the affected VPS IDs.
+pub async fn process_scheduled_exits(pool: DuckDbPool) -> Result<Vec<i32>, AppError> {
+    let now = Utc::now();
+    let mut conn = pool.get()?;
+    let due: Vec<(i32, DateTime<Utc>)> = latest_events(&conn)?
+        .into_iter()
+        .filter(|event| event.action == ACTION_ENTER)
+        .filter_map(|event| event.ends_at.filter(|ends_at| *ends_at <= now).map(|ends_at| (event.vps_id, ends_at)))
+        .collect();
+    if due.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let tx = conn.transaction()?;
+    for (vps_id, ends_at) in &due {
+        tx.execute(
+            "INSERT INTO vps_maintenance_events (vps_id, time, action, actor) VALUES (?, ?, ?, ?)",
+            params![vps_id, ends_at, ACTION_EXIT, AUTO_EXIT_ACTOR],
+        )?;
+    }
+    tx.commit()?;
+    Ok(due.into_iter().map(|(vps_id, _)| vps_id).collect())
+}
+
+pub async fn get_maintenance_events(
+    pool: DuckDbPool,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<Vec<vps_maintenance_event::Model>, AppError> {
+    let conn = pool.get()?;
+    let events = conn
+        .prepare(
+            "SELECT * FROM vps_maintenance_events
+             WHERE vps_id = ? AND time >= ? AND time <= ?
+             ORDER BY time DESC, id DESC",
+        )?
+        .query_map(params![vps_id, start_time, end_time], row_to_maintenance_event_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(events)
+}
+
+/// Turns an ordered event list into maintenance periods, clipped to `[start, end]`.
+pub fn maintenance_periods(
+    events: &[vps_maintenance_event::Model],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let mut periods = Vec::new();
+    let mut open: Option<(DateTime<Utc>, Option<DateTime<Utc>>)> = None;
+    let mut close = |from: DateTime<Utc>, to: DateTime<Utc>| {
+        let (from, to) = (from.max(start), to.min(end));
+        if from < to {
+            periods.push((from, to));
+        }
+    };
+    for event in events {
+        if let Some((since, ends_at)) = open.take() {
+            close(since, ends_at.map_or(event.time, |e| e.min(event.time)));
+        }
+        if event.action == ACTION_ENTER {
+            open = Some((event.time, event.ends_at));
+        }
+    }
+    if let Some((since, ends_at)) = open {
+        close(since, ends_at.unwrap_or(end));
+    }
+    periods
+}
+
+/// Maintenance periods of a VPS overlapping `[start, end]`, using an existing connection.
+pub fn get_maintenance_periods(
+    conn: &Connection,
+    vps_id: i32,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<(DateTime<Utc>, DateTime<Utc>)>, AppError> {
+    // The table is small per VPS; reading everything up to `end` keeps
+    // periods that started before the range.
+    let events = conn
+        .prepare(
+            "SELECT * FROM vps_maintenance_events WHERE vps_id = ? AND time <= ? ORDER BY time ASC, id ASC",
+        )?
+        .query_map(params![vps_id, end], row_to_maintenance_event_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(maintenance_periods(&events, start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn at(hours: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap() + Duration::hours(hours)
+    }
+
+    fn event(time: DateTime<Utc>, action: &str, ends_at: Option<DateTime<Utc>>) -> vps_maintenance_event::Model {
+        vps_maintenance_event::Model {
+            id: 0,
+            vps_id: 1,
+            time,
+            action: action.to_string(),
+            reason: None,
+            ends_at,
+            actor: None,
+        }
+    }
+
+    #[test]
+    fn periods_end_at_exit_or_schedule() {
+        let events = [
+            event(at(1), ACTION_ENTER, None),
+            event(at(2), ACTION_EXIT, None),
+            event(at(5), ACTION_ENTER, Some(at(6))),
+        ];
+        assert_eq!(
+            maintenance_periods(&events, at(0), at(10)),
+            vec![(at(1), at(2)), (at(5), at(6))]
+        );
+    }
+
+    #[test]
+    fn open_period_is_clipped_to_range() {
+        let events = [event(at(1), ACTION_ENTER, None)];
+        assert_eq!(maintenance_periods(&events, at(3), at(4)), vec![(at(3), at(4))]);
+    }
+
+    #[test]
+    fn expired_schedule_is_not_active() {
+        let enter = event(at(1), ACTION_ENTER, Some(at(2)));
+        assert!(active_state(&enter, at(1)).is_some());
+        assert!(active_state(&enter, at(3)).is_none());
+    }
+}
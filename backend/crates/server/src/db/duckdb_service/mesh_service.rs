@@ -0,0 +1,238 @@
+//! The latency mesh between agents. Every agent with a mesh interval pings the
+//! other VPS of its organization and reports `agent.mesh.rtt_ms` and
+//! `agent.mesh.loss_percent` as generic metrics tagged with `peer_vps_id`;
+//! this module picks the peers and reads the measurements back as a matrix.
+
+use chrono::{DateTime, Utc};
+use duckdb::params;
+use nodenexus_common::agent_service::MeshPeer;
+use nodenexus_common::metric_catalog::{MESH_LOSS_PERCENT, MESH_RTT_MS};
+use serde_json::Value;
+use std::net::IpAddr;
+
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
+use crate::services::geoip;
+use crate::web::error::AppError;
+use crate::web::models::network_models::{MeshLink, MeshNode, MeshPoint};
+
+/// Peers one agent pings, so a large fleet doesn't turn into a flood of pings.
+const MAX_PEERS: usize = 64;
+
+/// The address other agents ping: a public IP the agent reported, else the
+/// address it connected from.
+pub fn peer_address(metadata: Option<&Value>, ip_address: Option<&str>) -> Option<IpAddr> {
+    geoip::public_ip(metadata, ip_address).or_else(|| ip_address.and_then(|ip| ip.trim().parse().ok()))
+}
+
+/// The other VPS in the organization of `vps_id` (or of its owner, for VPS
+/// without one) that have an address.
+pub async fn get_mesh_peers(pool: DuckDbPool, vps_id: i32) -> Result<Vec<MeshPeer>, AppError> {
+    let conn = pool.get()?;
+    let rows = conn
+        .prepare(
+            r#"
+            SELECT p.id, p.ip_address, p.metadata
+            FROM vps p
+            JOIN vps s ON s.id = ?
+            WHERE p.id <> s.id
+              AND p.archived_at IS NULL
+              AND (p.organization_id = s.organization_id
+                   OR (s.organization_id IS NULL AND p.user_id = s.user_id))
+            ORDER BY p.id
+            "#,
+        )?
+        .query_map(params![vps_id], |row| {
+            let metadata: Option<String> = row.get(2)?;
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, Option<String>>(1)?,
+                metadata.and_then(|m| serde_json::from_str::<Value>(&m).ok()),
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows
+        .into_iter()
+        .filter_map(|(id, ip_address, metadata)| {
+            let address = peer_address(metadata.as_ref(), ip_address.as_deref())?;
+            Some(MeshPeer {
+                vps_id: id,
+                address: address.to_string(),
+            })
+        })
+        .take(MAX_PEERS)
+        .collect())
+}
+
+/// The VPS of the user's organization, the rows and columns of the matrix.
+pub async fn get_mesh_nodes(pool: DuckDbPool, user_id: i32) -> Result<Vec<MeshNode>, AppError> {
+    let org_scope = organization_service::org_scope("organization_id");
+    let sql = format!(
+        r#"SELECT id, name, "group", status FROM vps WHERE archived_at IS NULL AND {org_scope} ORDER BY id"#
+    );
+    let conn = pool.get()?;
+    let nodes = conn
+        .prepare(&sql)?
+        .query_map(params![user_id], |row| {
+            Ok(MeshNode {
+                vps_id: row.get(0)?,
+                name: row.get(1)?,
+                group: row.get(2)?,
+                status: row.get(3)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(nodes)
+}
+
+/// One bucket of measurements from a source towards a peer.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MeshSample {
+    pub source_vps_id: i32,
+    pub target_vps_id: i32,
+    pub time: DateTime<Utc>,
+    pub rtt_ms: Option<f64>,
+    pub loss_percent: Option<f64>,
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let (sum, count) = values.fold((0.0, 0usize), |(sum, count), value| (sum + value, count + 1));
+    (count > 0).then(|| sum / count as f64)
+}
+
+/// Folds bucketed samples, sorted by source, target and time, into one link
+/// per pair.
+pub fn build_links(samples: Vec<MeshSample>) -> Vec<MeshLink> {
+    let mut links: Vec<MeshLink> = Vec::new();
+    for sample in samples {
+        let point = MeshPoint {
+            time: sample.time,
+            rtt_ms: sample.rtt_ms,
+            loss_percent: sample.loss_percent,
+        };
+        match links.last_mut() {
+            Some(link) if link.source_vps_id == sample.source_vps_id && link.target_vps_id == sample.target_vps_id => {
+                link.history.push(point);
+            }
+            _ => links.push(MeshLink {
+                source_vps_id: sample.source_vps_id,
+                target_vps_id: sample.target_vps_id,
+                latest_rtt_ms: None,
+                latest_loss_percent: None,
+                avg_rtt_ms: None,
+                avg_loss_percent: None,
+                last_seen: sample.time,
+                history: vec![point],
+            }),
+        }
+    }
+    for link in &mut links {
+        if let Some(latest) = link.history.last() {
+            link.latest_rtt_ms = latest.rtt_ms;
+            link.latest_loss_percent = latest.loss_percent;
+            link.last_seen = latest.time;
+        }
+        link.avg_rtt_ms = mean(link.history.iter().filter_map(|p| p.rtt_ms));
+        link.avg_loss_percent = mean(link.history.iter().filter_map(|p| p.loss_percent));
+    }
+    links
+}
+
+/// Mesh measurements of the user's VPS since `since`, averaged per
+/// `bucket_seconds`.
+pub async fn get_mesh_links(
+    pool: DuckDbPool,
+    user_id: i32,
+    since: DateTime<Utc>,
+    bucket_seconds: u32,
+) -> Result<Vec<MeshLink>, AppError> {
+    let org_scope = organization_service::org_scope("v.organization_id");
+    let bucket_seconds = bucket_seconds.max(1);
+    let sql = format!(
+        r#"
+        SELECT source_vps_id, target_vps_id, bucket, rtt_ms, loss_percent
+        FROM (
+            SELECT g.vps_id AS source_vps_id,
+                   TRY_CAST(json_extract_string(g.labels, '$.peer_vps_id') AS INTEGER) AS target_vps_id,
+                   time_bucket(INTERVAL '{bucket_seconds}' SECONDS, g."time") AS bucket,
+                   AVG(g.value) FILTER (WHERE g.name = ?) AS rtt_ms,
+                   AVG(g.value) FILTER (WHERE g.name = ?) AS loss_percent
+            FROM generic_metrics g
+            JOIN vps v ON v.id = g.vps_id
+            WHERE g.name IN (?, ?) AND g."time" >= ? AND {org_scope}
+            GROUP BY ALL
+        )
+        WHERE target_vps_id IS NOT NULL
+        ORDER BY source_vps_id, target_vps_id, bucket
+        "#
+    );
+    let conn = pool.get()?;
+    let samples = conn
+        .prepare(&sql)?
+        .query_map(
+            params![MESH_RTT_MS, MESH_LOSS_PERCENT, MESH_RTT_MS, MESH_LOSS_PERCENT, since, user_id],
+            |row| {
+                Ok(MeshSample {
+                    source_vps_id: row.get(0)?,
+                    target_vps_id: row.get(1)?,
+                    time: row.get(2)?,
+                    rtt_ms: row.get(3)?,
+                    loss_percent: row.get(4)?,
+                })
+            },
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(build_links(samples))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn sample(source: i32, target: i32, minute: u32, rtt_ms: Option<f64>, loss_percent: f64) -> MeshSample {
+        MeshSample {
+            source_vps_id: source,
+            target_vps_id: target,
+            time: Utc.with_ymd_and_hms(2025, 7, 1, 12, minute, 0).unwrap(),
+            rtt_ms,
+            loss_percent: Some(loss_percent),
+        }
+    }
+
+    #[test]
+    fn peers_are_pinged_on_a_public_address_when_one_is_known() {
+        let metadata = json!({ "public_ip_addresses": ["95.216.10.7"] });
+        assert_eq!(
+            peer_address(Some(&metadata), Some("10.0.0.7")),
+            Some("95.216.10.7".parse().unwrap())
+        );
+        assert_eq!(peer_address(None, Some("10.0.0.7")), Some("10.0.0.7".parse().unwrap()));
+        assert_eq!(peer_address(None, Some("not an ip")), None);
+        assert_eq!(peer_address(None, None), None);
+    }
+
+    #[test]
+    fn samples_fold_into_one_link_per_direction() {
+        let links = build_links(vec![
+            sample(1, 2, 0, Some(10.0), 0.0),
+            sample(1, 2, 1, Some(20.0), 0.0),
+            sample(1, 2, 2, None, 100.0),
+            sample(2, 1, 0, Some(12.0), 0.0),
+        ]);
+        assert_eq!(links.len(), 2);
+
+        let forward = &links[0];
+        assert_eq!((forward.source_vps_id, forward.target_vps_id), (1, 2));
+        assert_eq!(forward.history.len(), 3);
+        assert_eq!(forward.latest_rtt_ms, None);
+        assert_eq!(forward.latest_loss_percent, Some(100.0));
+        assert_eq!(forward.avg_rtt_ms, Some(15.0));
+        assert_eq!(forward.last_seen, Utc.with_ymd_and_hms(2025, 7, 1, 12, 2, 0).unwrap());
+
+        let back = &links[1];
+        assert_eq!((back.source_vps_id, back.target_vps_id), (2, 1));
+        assert_eq!(back.latest_rtt_ms, Some(12.0));
+        assert_eq!(back.avg_loss_percent, Some(0.0));
+    }
+}
@@ -1,30 +1,81 @@
+pub mod agent_certificate_service;
+pub mod agent_release_service;
+pub mod agent_version_service;
+pub mod api_token_service;
+pub mod audit_log_service;
 pub mod alert_service;
+pub mod archive_service;
+pub mod backup_service;
+pub mod alert_correlation_service;
 pub mod alert_evaluation_service;
+pub mod alert_event_service;
 pub mod performance_service;
+pub mod process_service;
 pub mod user_service;
+pub mod two_factor_service;
 pub mod tasks;
 pub mod writer;
 pub mod vps_renewal_service;
+pub mod vps_selection_service;
 pub mod vps_service;
 pub mod vps_traffic_service;
 pub mod vps_detail_service;
+pub mod vps_group_service;
 pub mod settings_service;
+pub mod share_link_service;
+pub mod status_page_service;
 pub mod service_monitor_service;
+pub mod service_monitor_slo_service;
 pub mod batch_command_service;
+pub mod chatops_service;
+pub mod client_service;
+pub mod inventory_service;
+pub mod maintenance_service;
+pub mod mesh_service;
+pub mod provisioning_service;
+pub mod provider_service;
+pub mod public_api_key_service;
+pub mod recovery;
+pub mod retention;
+pub mod scheduled_task_service;
+pub mod command_policy_service;
 pub mod command_script_service;
+pub mod config_rollout_service;
+pub mod custom_metric_service;
+pub mod demo_service;
+pub mod derived_metric_service;
+pub mod disk_health_service;
+pub mod disk_io_service;
+pub mod dns_failover_service;
+pub mod encryption_key_service;
+pub mod fleet_service;
+pub mod gpu_service;
+pub mod health_service;
+pub mod ingest_key_service;
+pub mod log_match_service;
+pub mod network_interface_service;
+pub mod no_data_service;
 pub mod oauth_service;
+pub mod organization_service;
+pub mod temperature_service;
 pub mod theme_service;
+pub mod traffic_report_service;
+pub mod uptime_report_service;
+pub mod uptime_service;
+pub mod virtual_group_service;
+pub mod vulnerability_service;
+pub mod watchdog_service;
+pub mod webhook_service;
 
 pub mod notification_service;
-use self::writer::metrics_writer_task;
-use crate::db::entities::performance_metric;
+pub mod notification_digest_service;
+use self::writer::{metrics_writer_task, SnapshotBatch, WriterConfig};
 pub mod tag_service;
 use duckdb::{ffi, types::ValueRef, Connection, Result, Row};
 use serde_json;
 use std::{path::Path, sync::mpsc, thread};
 use tracing::{error, info};
 use axum::{
-    http::StatusCode,
     response::{IntoResponse, Response},
 };
 use thiserror::Error;
@@ -45,23 +96,36 @@ pub enum Error {
 
 impl IntoResponse for Error {
     fn into_response(self) -> Response {
-        let body = self.to_string();
-        (StatusCode::INTERNAL_SERVER_ERROR, body).into_response()
+        crate::web::error::AppError::from(self).into_response()
     }
 }
 
 pub type DuckDbPool = r2d2::Pool<duckdb::DuckdbConnectionManager>;
 
+/// Opens a read-only pool for report and export queries. `path` should be a
+/// replica of the main database (a periodic copy, or the file of a follower
+/// instance), so long scans there never hold up the metrics writer.
+///
+/// Nothing keeps the replica in sync, so it only serves bulk reads (metric
+/// history, availability). Anything that decides ownership
+/// (the `users`, `vps` and `clients` rows) or was just written is read from
+/// the main pool instead.
+pub fn open_reporting_pool(path: &str, max_size: u32) -> std::result::Result<DuckDbPool, Error> {
+    let config = duckdb::Config::default().access_mode(duckdb::AccessMode::ReadOnly)?;
+    let manager = duckdb::DuckdbConnectionManager::file_with_flags(path, config)?;
+    Ok(r2d2::Pool::builder().max_size(max_size.max(1)).build(manager)?)
+}
+
 // The service now only holds the sender part of the channel.
 // The connection is created and managed exclusively in the writer thread.
 // This struct is now cheap to clone and is Send + Sync.
 #[derive(Clone, Debug)]
 pub struct DuckDBService {
-    metric_sender: mpsc::Sender<performance_metric::Model>,
+    metric_sender: mpsc::Sender<SnapshotBatch>,
 }
 
 impl DuckDBService {
-    pub fn new(pool: DuckDbPool) -> std::result::Result<Self, Error> {
+    pub fn new(pool: DuckDbPool, writer_config: WriterConfig) -> std::result::Result<Self, Error> {
         info!("Initializing DuckDB service with connection pool.");
 
         // The connection is created here only to run initial migrations.
@@ -75,13 +139,21 @@ impl DuckDBService {
         // Spawn a dedicated OS thread for the blocking DuckDB writer task.
         // This prevents blocking the Tokio runtime.
         thread::spawn(move || {
-            metrics_writer_task(writer_pool, rx);
+            metrics_writer_task(writer_pool, rx, writer_config);
         });
 
         Ok(Self { metric_sender: tx })
     }
 
-    pub fn get_sender(&self) -> mpsc::Sender<performance_metric::Model> {
+    /// A service for degraded mode: no migrations, and metric batches are
+    /// dropped because the database can't take writes.
+    pub fn discarding() -> Self {
+        let (tx, rx) = mpsc::channel::<SnapshotBatch>();
+        thread::spawn(move || for _ in rx {});
+        Self { metric_sender: tx }
+    }
+
+    pub fn get_sender(&self) -> mpsc::Sender<SnapshotBatch> {
         self.metric_sender.clone()
     }
 
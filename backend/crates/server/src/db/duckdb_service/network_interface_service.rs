@@ -0,0 +1,118 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, Result as DuckDbResult, Row};
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::network_interface_metric;
+use crate::web::error::AppError;
+
+/// Alert rules with this metric type count carrier transitions over their duration.
+pub const NIC_LINK_FLAPS_METRIC_TYPE: &str = "nic_link_flaps";
+/// Alert rules with this metric type compare the highest errors+drops per second
+/// of any interface within their duration, so short spikes are caught.
+pub const NIC_ERROR_RATE_METRIC_TYPE: &str = "nic_errors_per_sec";
+
+fn row_to_network_interface_metric_model(row: &Row) -> DuckDbResult<network_interface_metric::Model> {
+    Ok(network_interface_metric::Model {
+        time: row.get("time")?,
+        vps_id: row.get("vps_id")?,
+        interface_name: row.get("interface_name")?,
+        rx_errors_per_sec: row.get("rx_errors_per_sec")?,
+        tx_errors_per_sec: row.get("tx_errors_per_sec")?,
+        rx_dropped_per_sec: row.get("rx_dropped_per_sec")?,
+        tx_dropped_per_sec: row.get("tx_dropped_per_sec")?,
+        collisions_per_sec: row.get("collisions_per_sec")?,
+        carrier_changes: row.get("carrier_changes")?,
+        link_up: row.get("link_up")?,
+        speed_mbps: row.get("speed_mbps")?,
+        duplex: row.get("duplex")?,
+    })
+}
+
+/// Per-interface samples, optionally limited to one interface. With an interval
+/// rates are averaged per bucket, carrier changes summed and the link counts as
+/// up only if it was up for the whole bucket.
+pub async fn get_network_interface_timeseries(
+    pool: DuckDbPool,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    interval_seconds: Option<u32>,
+    interface_name: Option<String>,
+) -> Result<Vec<network_interface_metric::Model>, AppError> {
+    let interface_filter = if interface_name.is_some() { "AND interface_name = ?" } else { "" };
+    let sql = match interval_seconds {
+        None => format!(
+            r#"SELECT * FROM network_interface_metrics
+               WHERE vps_id = ? AND "time" >= ? AND "time" <= ? {interface_filter}
+               ORDER BY "time" ASC, interface_name ASC"#
+        ),
+        Some(interval_secs) => {
+            let interval_secs = interval_secs.max(1);
+            format!(
+                r#"
+                SELECT
+                    date_trunc('second', "time") + INTERVAL '{interval_secs} seconds' * (epoch("time") / {interval_secs}) AS "time",
+                    vps_id,
+                    interface_name,
+                    AVG(rx_errors_per_sec) AS rx_errors_per_sec,
+                    AVG(tx_errors_per_sec) AS tx_errors_per_sec,
+                    AVG(rx_dropped_per_sec) AS rx_dropped_per_sec,
+                    AVG(tx_dropped_per_sec) AS tx_dropped_per_sec,
+                    AVG(collisions_per_sec) AS collisions_per_sec,
+                    CAST(SUM(carrier_changes) AS BIGINT) AS carrier_changes,
+                    BOOL_AND(link_up) AS link_up,
+                    MAX(speed_mbps) AS speed_mbps,
+                    MAX(duplex) AS duplex
+                FROM network_interface_metrics
+                WHERE vps_id = ? AND "time" >= ? AND "time" <= ? {interface_filter}
+                GROUP BY 1, vps_id, interface_name
+                ORDER BY 1 ASC, interface_name ASC
+                "#
+            )
+        }
+    };
+
+    let mut params: Vec<&dyn duckdb::ToSql> = vec![&vps_id, &start_time, &end_time];
+    if let Some(interface_name) = interface_name.as_ref() {
+        params.push(interface_name);
+    }
+    let conn = pool.get()?;
+    let metrics = conn
+        .prepare(&sql)?
+        .query_map(params.as_slice(), row_to_network_interface_metric_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(metrics)
+}
+
+pub async fn count_link_flaps(
+    pool: DuckDbPool,
+    vps_id: i32,
+    since: DateTime<Utc>,
+) -> Result<i64, AppError> {
+    let conn = pool.get()?;
+    let count = conn.query_row(
+        "SELECT CAST(COALESCE(SUM(carrier_changes), 0) AS BIGINT) FROM network_interface_metrics
+         WHERE vps_id = ? AND time >= ?",
+        params![vps_id, since],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
+
+/// The highest errors+drops per second of any interface in the range; `None` without samples.
+pub async fn get_peak_error_rate(
+    pool: DuckDbPool,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<Option<f64>, AppError> {
+    let conn = pool.get()?;
+    let peak = conn.query_row(
+        "SELECT MAX(rx_errors_per_sec + tx_errors_per_sec + rx_dropped_per_sec + tx_dropped_per_sec)
+         FROM network_interface_metrics
+         WHERE vps_id = ? AND time >= ? AND time <= ?",
+        params![vps_id, start_time, end_time],
+        |row| row.get(0),
+    )?;
+    Ok(peak)
+}
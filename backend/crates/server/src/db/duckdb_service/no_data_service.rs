@@ -0,0 +1,112 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, Connection, OptionalExt};
+
+use crate::db::duckdb_service::service_monitor_service;
+use crate::db::duckdb_service::uptime_service::STATUS_ONLINE;
+use crate::db::duckdb_service::DuckDbPool;
+use crate::web::error::AppError;
+
+/// Alert rules with this metric type fire when an online VPS delivered no
+/// performance metrics for the rule's duration.
+pub const NO_DATA_METRICS_METRIC_TYPE: &str = "no_data_metrics";
+/// Alert rules with this metric type fire when a monitor the VPS runs
+/// produced no results for the rule's duration.
+pub const NO_DATA_MONITORS_METRIC_TYPE: &str = "no_data_monitors";
+
+/// Seconds without data, counted from the later of the last data point and
+/// the moment the VPS came online; a fresh connection gets a full window.
+pub fn data_gap_seconds(
+    online_since: DateTime<Utc>,
+    last_data_at: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+) -> i64 {
+    let since = last_data_at.map_or(online_since, |last| last.max(online_since));
+    (now - since).num_seconds().max(0)
+}
+
+/// When the VPS last came online, or `None` if it is not online now.
+fn online_since(conn: &Connection, vps_id: i32) -> Result<Option<DateTime<Utc>>, AppError> {
+    let last_event: Option<(DateTime<Utc>, String)> = conn
+        .query_row(
+            "SELECT time, status FROM vps_status_events WHERE vps_id = ? ORDER BY time DESC, id DESC LIMIT 1",
+            params![vps_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    Ok(last_event.filter(|(_, status)| status == STATUS_ONLINE).map(|(time, _)| time))
+}
+
+/// Seconds since the VPS last delivered performance metrics while online, or
+/// `None` if it is not online.
+pub async fn get_metrics_gap_seconds(
+    pool: DuckDbPool,
+    vps_id: i32,
+    now: DateTime<Utc>,
+) -> Result<Option<i64>, AppError> {
+    let conn = pool.get()?;
+    let Some(online_since) = online_since(&conn, vps_id)? else {
+        return Ok(None);
+    };
+    let last_metric_at: Option<DateTime<Utc>> = conn.query_row(
+        "SELECT max(time) FROM latest_performance_metrics WHERE vps_id = ?",
+        params![vps_id],
+        |row| row.get(0),
+    )?;
+    Ok(Some(data_gap_seconds(online_since, last_metric_at, now)))
+}
+
+/// Names of the active monitors run by an online VPS that produced no result
+/// for at least `threshold_seconds`. Monitors are also given one extra check
+/// interval, so slow schedules don't fire spuriously.
+pub async fn get_silent_monitors(
+    pool: DuckDbPool,
+    vps_id: i32,
+    threshold_seconds: i64,
+    now: DateTime<Utc>,
+) -> Result<Vec<String>, AppError> {
+    let online_since = {
+        let conn = pool.get()?;
+        online_since(&conn, vps_id)?
+    };
+    let Some(online_since) = online_since else {
+        return Ok(Vec::new());
+    };
+    let monitors = service_monitor_service::get_runnable_monitors_for_vps(pool.clone(), vps_id).await?;
+
+    let conn = pool.get()?;
+    let mut silent = Vec::new();
+    for monitor in monitors {
+        let last_result_at: Option<DateTime<Utc>> = conn.query_row(
+            "SELECT max(time) FROM service_monitor_results WHERE monitor_id = ? AND agent_id = ?",
+            params![monitor.id, vps_id],
+            |row| row.get(0),
+        )?;
+        let active_since = online_since.max(monitor.updated_at);
+        let gap = data_gap_seconds(active_since, last_result_at, now);
+        if gap >= threshold_seconds + i64::from(monitor.frequency_seconds) {
+            silent.push(monitor.name);
+        }
+    }
+    Ok(silent)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn gap_counts_from_last_data_point() {
+        let now = Utc::now();
+        let gap = data_gap_seconds(now - Duration::hours(2), Some(now - Duration::minutes(10)), now);
+        assert_eq!(gap, 600);
+    }
+
+    #[test]
+    fn gap_starts_when_vps_came_online() {
+        let now = Utc::now();
+        let online_since = now - Duration::minutes(3);
+        assert_eq!(data_gap_seconds(online_since, Some(now - Duration::days(1)), now), 180);
+        assert_eq!(data_gap_seconds(online_since, None, now), 180);
+    }
+}
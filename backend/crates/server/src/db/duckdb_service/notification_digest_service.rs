@@ -0,0 +1,178 @@
+//! The queue behind digest channels. Notifications for a digest channel are
+//! stored here instead of being sent, and `notifications::digest` sends them
+//! as one message whenever the channel's schedule comes due.
+
+use chrono::{DateTime, Utc};
+use duckdb::{params, Connection, OptionalExt};
+use std::collections::HashMap;
+
+use crate::db::duckdb_service::{json_from_row, DuckDbPool};
+use crate::db::entities::notification_digest_item;
+use crate::db::enums::ChannelDelivery;
+use crate::services::cron::CronSchedule;
+use crate::web::error::AppError;
+
+pub const KIND_NOTIFICATION: &str = "notification";
+pub const KIND_RENEWAL: &str = "renewal";
+
+fn row_to_digest_item(row: &duckdb::Row<'_>) -> Result<notification_digest_item::Model, duckdb::Error> {
+    Ok(notification_digest_item::Model {
+        id: row.get("id")?,
+        channel_id: row.get("channel_id")?,
+        kind: row.get("kind")?,
+        context: json_from_row(row, "context")?
+            .and_then(|context| serde_json::from_value(context).ok())
+            .unwrap_or_default(),
+        created_at: row.get("created_at")?,
+    })
+}
+
+/// When the next digest of a channel goes out after `now`: `None` for
+/// channels that send immediately. Digest channels need a schedule with
+/// future runs.
+pub fn next_digest_at(
+    delivery: ChannelDelivery,
+    digest_schedule: Option<&str>,
+    now: DateTime<Utc>,
+) -> Result<Option<DateTime<Utc>>, AppError> {
+    if delivery == ChannelDelivery::Immediate {
+        return Ok(None);
+    }
+    let expression = digest_schedule
+        .map(str::trim)
+        .filter(|expression| !expression.is_empty())
+        .ok_or_else(|| AppError::InvalidInput("Digest channels need a digest schedule".to_string()))?;
+    let schedule = CronSchedule::parse(expression).map_err(|e| AppError::InvalidInput(e.to_string()))?;
+    schedule
+        .next_after(now)
+        .map(Some)
+        .ok_or_else(|| AppError::InvalidInput(format!("Digest schedule '{expression}' never runs")))
+}
+
+pub fn enqueue_item(
+    conn: &Connection,
+    channel_id: i32,
+    kind: &str,
+    context: &HashMap<String, String>,
+) -> Result<(), AppError> {
+    conn.execute(
+        "INSERT INTO notification_digest_items (channel_id, kind, context) VALUES (?, ?, ?)",
+        params![channel_id, kind, serde_json::to_string(context)?],
+    )?;
+    Ok(())
+}
+
+/// Queues a renewal reminder of the VPS for every digest channel of its
+/// owner and returns how many were queued.
+pub fn enqueue_renewal(
+    conn: &Connection,
+    vps_id: i32,
+    next_renewal_date: DateTime<Utc>,
+    renewal_price: Option<f64>,
+    renewal_currency: Option<&str>,
+) -> Result<usize, AppError> {
+    let owner = conn
+        .query_row("SELECT user_id, name FROM vps WHERE id = ?", params![vps_id], |row| {
+            Ok((row.get::<_, i32>(0)?, row.get::<_, String>(1)?))
+        })
+        .optional()?;
+    let Some((user_id, vps_name)) = owner else {
+        return Ok(0);
+    };
+    let mut context = HashMap::from([
+        ("vps_id".to_string(), vps_id.to_string()),
+        ("vps_name".to_string(), vps_name),
+        ("next_renewal_date".to_string(), next_renewal_date.format("%Y-%m-%d").to_string()),
+    ]);
+    if let Some(price) = renewal_price {
+        let currency = renewal_currency.unwrap_or_default();
+        context.insert("renewal_price".to_string(), format!("{price:.2} {currency}").trim_end().to_string());
+    }
+    let queued = conn.execute(
+        "INSERT INTO notification_digest_items (channel_id, kind, context)
+         SELECT id, ?, ? FROM notification_channels WHERE user_id = ? AND delivery = ?",
+        params![
+            KIND_RENEWAL,
+            serde_json::to_string(&context)?,
+            user_id,
+            ChannelDelivery::Digest.to_string()
+        ],
+    )?;
+    Ok(queued)
+}
+
+/// Digest channels whose schedule came due at `now`, with their schedules.
+pub async fn get_due_channels(pool: DuckDbPool, now: DateTime<Utc>) -> Result<Vec<(i32, Option<String>)>, AppError> {
+    let conn = pool.get()?;
+    let channels = conn
+        .prepare(
+            "SELECT id, digest_schedule FROM notification_channels
+             WHERE delivery = ? AND next_digest_at <= ? ORDER BY next_digest_at",
+        )?
+        .query_map(params![ChannelDelivery::Digest.to_string(), now], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(channels)
+}
+
+/// Moves a channel on to its next digest; `None` stops its digests.
+pub async fn advance_digest(
+    pool: DuckDbPool,
+    channel_id: i32,
+    next_digest_at: Option<DateTime<Utc>>,
+) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE notification_channels SET next_digest_at = ? WHERE id = ?",
+        params![next_digest_at, channel_id],
+    )?;
+    Ok(())
+}
+
+/// The queued items of a channel, oldest first.
+pub async fn get_items(pool: DuckDbPool, channel_id: i32) -> Result<Vec<notification_digest_item::Model>, AppError> {
+    let conn = pool.get()?;
+    let items = conn
+        .prepare("SELECT * FROM notification_digest_items WHERE channel_id = ? ORDER BY id")?
+        .query_map(params![channel_id], row_to_digest_item)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(items)
+}
+
+/// Removes the items of a channel up to `last_id` once they were sent, keeping
+/// those queued while the digest went out.
+pub async fn delete_items_through(pool: DuckDbPool, channel_id: i32, last_id: i64) -> Result<usize, AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute(
+        "DELETE FROM notification_digest_items WHERE channel_id = ? AND id <= ?",
+        params![channel_id, last_id],
+    )?;
+    Ok(deleted)
+}
+
+/// Drops the queue of a channel that was deleted or no longer sends digests.
+pub fn clear_items(conn: &Connection, channel_id: i32) -> Result<usize, AppError> {
+    let deleted = conn.execute("DELETE FROM notification_digest_items WHERE channel_id = ?", params![channel_id])?;
+    Ok(deleted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn digest_channels_need_a_schedule_with_future_runs() {
+        let now = Utc.with_ymd_and_hms(2025, 7, 1, 12, 30, 0).unwrap();
+        assert_eq!(next_digest_at(ChannelDelivery::Immediate, None, now).unwrap(), None);
+        assert_eq!(
+            next_digest_at(ChannelDelivery::Digest, Some("0 9 * * *"), now).unwrap(),
+            Some(Utc.with_ymd_and_hms(2025, 7, 2, 9, 0, 0).unwrap())
+        );
+        assert!(next_digest_at(ChannelDelivery::Digest, None, now).is_err());
+        assert!(next_digest_at(ChannelDelivery::Digest, Some(" "), now).is_err());
+        assert!(next_digest_at(ChannelDelivery::Digest, Some("0 9 * *"), now).is_err());
+        assert!(next_digest_at(ChannelDelivery::Digest, Some("0 0 31 2 *"), now).is_err());
+    }
+}
@@ -1,16 +1,36 @@
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::task;
-use duckdb::{params, Connection, Result as DuckDbResult, ToSql};
+use duckdb::types::{FromSql, FromSqlError, FromSqlResult, ToSqlOutput, ValueRef};
+use duckdb::{params, Connection, OptionalExt, Result as DuckDbResult, ToSql};
+use std::str::FromStr;
 use tracing::{error, info};
 
-use crate::db::duckdb_service::DuckDbPool;
+use crate::db::duckdb_service::notification_digest_service::{self, KIND_NOTIFICATION};
+use crate::db::duckdb_service::{encryption_key_service, json_from_row, DuckDbPool};
 use crate::db::entities::notification_channel;
+use crate::db::enums::{AlertSeverity, ChannelDelivery};
 use crate::notifications::encryption::{EncryptionService, EncryptionError};
-use crate::notifications::models::{ChannelConfig, CreateChannelRequest, ChannelResponse, UpdateChannelRequest};
+use crate::notifications::models::{
+    ChannelConfig, ChannelResponse, CreateChannelRequest, NotificationAction, TestChannelResponse, UpdateChannelRequest,
+};
 use crate::notifications::senders::{NotificationSender, SenderError, telegram::TelegramSender, webhook::WebhookSender};
+use crate::notifications::templates;
 use crate::web::error::AppError;
 
+impl ToSql for ChannelDelivery {
+    fn to_sql(&self) -> DuckDbResult<ToSqlOutput<'_>> {
+        Ok(ToSqlOutput::from(self.to_string()))
+    }
+}
+
+impl FromSql for ChannelDelivery {
+    fn column_result(value: ValueRef<'_>) -> FromSqlResult<Self> {
+        let s = value.as_str()?;
+        ChannelDelivery::from_str(s).map_err(|_| FromSqlError::InvalidType)
+    }
+}
+
 pub async fn create_channel(
     pool: DuckDbPool,
     encryption_service: Arc<EncryptionService>,
@@ -20,22 +40,34 @@ pub async fn create_channel(
     task::spawn_blocking(move || {
         let config_value: ChannelConfig = serde_json::from_value(payload.config)
             .map_err(|e| AppError::InvalidInput(e.to_string()))?;
+        templates::validate_templates(&payload.message_templates).map_err(AppError::InvalidInput)?;
+        let next_digest_at = notification_digest_service::next_digest_at(
+            payload.delivery,
+            payload.digest_schedule.as_deref(),
+            chrono::Utc::now(),
+        )?;
+        let conn = pool.get().map_err(AppError::from)?;
+        let key_organization = encryption_key_service::user_key_organization(&conn, user_id)?;
         let encrypted_config = encryption_service
-            .encrypt(&serde_json::to_vec(&config_value).unwrap())
+            .encrypt_for(key_organization, &serde_json::to_vec(&config_value).unwrap())
             .map_err(|e| AppError::InternalServerError(e.to_string()))?;
 
-        let conn = pool.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
-
         let model: notification_channel::Model = conn.query_row(
-            "INSERT INTO notification_channels (user_id, name, channel_type, config) VALUES (?, ?, ?, ?) RETURNING *",
+            "INSERT INTO notification_channels (user_id, name, channel_type, config, message_templates, severities, delivery, digest_schedule, next_digest_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
             params![
                 user_id,
                 payload.name,
                 payload.channel_type,
                 encrypted_config,
+                serde_json::to_string(&payload.message_templates)?,
+                serde_json::to_string(&payload.severities)?,
+                payload.delivery,
+                payload.digest_schedule.as_deref().map(str::trim).filter(|schedule| !schedule.is_empty()),
+                next_digest_at,
             ],
             row_to_channel_model,
-        ).map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        ).map_err(AppError::from)?;
 
         let decrypted_config_bytes = encryption_service.decrypt(&model.config)
             .map_err(|e| AppError::InternalServerError(e.to_string()))?;
@@ -49,6 +81,11 @@ pub async fn create_channel(
             name: model.name,
             channel_type: model.channel_type,
             config_params: Some(config_params_json),
+            message_templates: model.message_templates,
+            severities: model.severities,
+            delivery: model.delivery,
+            digest_schedule: model.digest_schedule,
+            next_digest_at: model.next_digest_at,
         })
     })
     .await
@@ -64,25 +101,39 @@ fn row_to_channel_model(row: &duckdb::Row<'_>) -> DuckDbResult<notification_chan
         config: row.get(4)?,
         created_at: row.get(5)?,
         updated_at: row.get(6)?,
+        message_templates: json_from_row(row, "message_templates")?
+            .and_then(|templates| serde_json::from_value(templates).ok())
+            .unwrap_or_default(),
+        severities: json_from_row(row, "severities")?
+            .and_then(|severities| serde_json::from_value(severities).ok())
+            .unwrap_or_default(),
+        delivery: row.get("delivery")?,
+        digest_schedule: row.get("digest_schedule")?,
+        next_digest_at: row.get("next_digest_at")?,
     })
 }
 
+/// Whether a channel limited to `severities` receives alerts of `severity`.
+pub fn channel_receives(severities: &[AlertSeverity], severity: AlertSeverity) -> bool {
+    severities.is_empty() || severities.contains(&severity)
+}
+
 pub async fn get_all_channels_for_user(
     pool: DuckDbPool,
     encryption_service: Arc<EncryptionService>,
     user_id: i32,
 ) -> Result<Vec<ChannelResponse>, AppError> {
     task::spawn_blocking(move || {
-        let conn = pool.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let conn = pool.get().map_err(AppError::from)?;
         let mut stmt = conn
             .prepare("SELECT * FROM notification_channels WHERE user_id = ? ORDER BY name ASC")
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            .map_err(AppError::from)?;
 
         let models = stmt
             .query_map(params![user_id], row_to_channel_model)
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?
+            .map_err(AppError::from)?
             .collect::<Result<Vec<_>, _>>()
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            .map_err(AppError::from)?;
 
         let mut channels_response = Vec::new();
         for model in models {
@@ -98,6 +149,11 @@ pub async fn get_all_channels_for_user(
                 name: model.name,
                 channel_type: model.channel_type,
                 config_params: Some(config_params_json),
+                message_templates: model.message_templates,
+                severities: model.severities,
+                delivery: model.delivery,
+                digest_schedule: model.digest_schedule,
+                next_digest_at: model.next_digest_at,
             });
         }
         Ok(channels_response)
@@ -113,7 +169,7 @@ pub async fn get_channel_by_id(
     channel_id: i32,
 ) -> Result<ChannelResponse, AppError> {
     task::spawn_blocking(move || {
-        let conn = pool.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let conn = pool.get().map_err(AppError::from)?;
         let model: notification_channel::Model = conn.query_row(
             "SELECT * FROM notification_channels WHERE id = ? AND user_id = ?",
             params![channel_id, user_id],
@@ -138,6 +194,11 @@ pub async fn get_channel_by_id(
             name: model.name,
             channel_type: model.channel_type,
             config_params: Some(config_params_json),
+            message_templates: model.message_templates,
+            severities: model.severities,
+            delivery: model.delivery,
+            digest_schedule: model.digest_schedule,
+            next_digest_at: model.next_digest_at,
         })
     })
     .await
@@ -154,7 +215,7 @@ pub async fn update_channel(
     let pool_clone = pool.clone();
     let encryption_service_clone = encryption_service.clone();
     task::spawn_blocking(move || {
-        let conn = pool_clone.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let conn = pool_clone.get().map_err(AppError::from)?;
 
         let mut set_clauses: Vec<String> = Vec::new();
         let mut params_vec: Vec<Box<dyn ToSql>> = Vec::new();
@@ -168,13 +229,53 @@ pub async fn update_channel(
         if let Some(new_config_value) = payload.config {
             let config_enum: ChannelConfig = serde_json::from_value(new_config_value)
                 .map_err(|e| AppError::InvalidInput(e.to_string()))?;
+            let key_organization = encryption_key_service::user_key_organization(&conn, user_id)?;
             encrypted_config = encryption_service_clone
-                .encrypt(&serde_json::to_vec(&config_enum).unwrap())
+                .encrypt_for(key_organization, &serde_json::to_vec(&config_enum).unwrap())
                 .map_err(|e| AppError::InternalServerError(e.to_string()))?;
             set_clauses.push("config = ?".to_string());
             params_vec.push(Box::new(encrypted_config));
         }
 
+        if let Some(message_templates) = payload.message_templates {
+            templates::validate_templates(&message_templates).map_err(AppError::InvalidInput)?;
+            set_clauses.push("message_templates = ?".to_string());
+            params_vec.push(Box::new(serde_json::to_string(&message_templates)?));
+        }
+
+        if let Some(severities) = payload.severities {
+            set_clauses.push("severities = ?".to_string());
+            params_vec.push(Box::new(serde_json::to_string(&severities)?));
+        }
+
+        if payload.delivery.is_some() || payload.digest_schedule.is_some() {
+            let (delivery, digest_schedule): (ChannelDelivery, Option<String>) = conn
+                .query_row(
+                    "SELECT delivery, digest_schedule FROM notification_channels WHERE id = ? AND user_id = ?",
+                    params![channel_id, user_id],
+                    |row| Ok((row.get(0)?, row.get(1)?)),
+                )
+                .optional()?
+                .ok_or_else(|| AppError::NotFound("Notification channel not found or not owned by user".to_string()))?;
+            let delivery = payload.delivery.unwrap_or(delivery);
+            let digest_schedule = payload
+                .digest_schedule
+                .map(|schedule| schedule.trim().to_string())
+                .or(digest_schedule)
+                .filter(|schedule| !schedule.is_empty());
+            let next_digest_at =
+                notification_digest_service::next_digest_at(delivery, digest_schedule.as_deref(), chrono::Utc::now())?;
+            set_clauses.push("delivery = ?".to_string());
+            params_vec.push(Box::new(delivery));
+            set_clauses.push("digest_schedule = ?".to_string());
+            params_vec.push(Box::new(digest_schedule));
+            set_clauses.push("next_digest_at = ?".to_string());
+            params_vec.push(Box::new(next_digest_at));
+            if delivery == ChannelDelivery::Immediate {
+                notification_digest_service::clear_items(&conn, channel_id)?;
+            }
+        }
+
         if !set_clauses.is_empty() {
             set_clauses.push("updated_at = ?".to_string());
             params_vec.push(Box::new(chrono::Utc::now()));
@@ -190,7 +291,7 @@ pub async fn update_channel(
 
             let params_slice: Vec<&dyn ToSql> = final_params.iter().map(|b| b.as_ref()).collect();
 
-            let num_updated = conn.execute(&sql, &params_slice[..]).map_err(|e| AppError::DatabaseError(e.to_string()))?;
+            let num_updated = conn.execute(&sql, &params_slice[..]).map_err(AppError::from)?;
 
             if num_updated == 0 {
                 return Err(AppError::NotFound("Notification channel not found or not owned by user".to_string()));
@@ -207,17 +308,18 @@ pub async fn update_channel(
 
 pub async fn delete_channel(pool: DuckDbPool, user_id: i32, channel_id: i32) -> Result<(), AppError> {
     task::spawn_blocking(move || {
-        let conn = pool.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        let conn = pool.get().map_err(AppError::from)?;
         let rows_affected = conn.execute(
             "DELETE FROM notification_channels WHERE id = ? AND user_id = ?",
             params![channel_id, user_id],
-        ).map_err(|e| AppError::DatabaseError(e.to_string()))?;
+        ).map_err(AppError::from)?;
 
         if rows_affected == 0 {
             Err(AppError::NotFound(
                 "Notification channel not found or not owned by user".to_string(),
             ))
         } else {
+            notification_digest_service::clear_items(&conn, channel_id)?;
             Ok(())
         }
     })
@@ -225,26 +327,157 @@ pub async fn delete_channel(pool: DuckDbPool, user_id: i32, channel_id: i32) ->
     .map_err(|e| AppError::InternalServerError(e.to_string()))?
 }
 
+/// Sends an alert of the rule, for the VPS it fired for, to those of the
+/// rule's channels that receive its severity.
 pub async fn send_notifications_for_alert_rule(
     pool: DuckDbPool,
     encryption_service: Arc<EncryptionService>,
     rule_id: i32,
+    vps_id: Option<i32>,
     alert_message: String,
+    actions: Vec<NotificationAction>,
 ) -> Result<(), AppError> {
-    
-    // Part 1: Fetch data from DB in a blocking task
-    let channels_to_notify = task::spawn_blocking(move || -> Result<Vec<(ChannelConfig, notification_channel::Model)>, AppError> {
-        let conn = pool.get().map_err(AppError::from)?;
-
-        let mut stmt = conn.prepare("SELECT channel_id FROM alert_rule_channels WHERE alert_rule_id = ?")?;
-        let channel_ids = stmt.query_map(params![rule_id], |row| row.get::<_, i32>(0))?
+    let pool_clone = pool.clone();
+    let (channels, rule, vps_name) = task::spawn_blocking(move || -> Result<(Vec<(i32, Vec<AlertSeverity>)>, Option<(String, AlertSeverity)>, Option<String>), AppError> {
+        let conn = pool_clone.get().map_err(AppError::from)?;
+        let mut stmt = conn.prepare(
+            "SELECT c.id, c.severities FROM alert_rule_channels rc
+             JOIN notification_channels c ON c.id = rc.channel_id
+             WHERE rc.alert_rule_id = ?",
+        )?;
+        let channels = stmt.query_map(params![rule_id], |row| {
+            let severities: Vec<AlertSeverity> = json_from_row(row, "severities")?
+                .and_then(|severities| serde_json::from_value(severities).ok())
+                .unwrap_or_default();
+            Ok((row.get::<_, i32>(0)?, severities))
+        })?
             .collect::<Result<Vec<_>, _>>()?;
+        let rule = conn
+            .query_row("SELECT name, severity FROM alert_rules WHERE id = ?", params![rule_id], |row| {
+                Ok((row.get(0)?, row.get(1)?))
+            })
+            .optional()?;
+        let vps_name = match vps_id {
+            Some(vps_id) => conn
+                .query_row("SELECT name FROM vps WHERE id = ?", params![vps_id], |row| row.get(0))
+                .optional()?,
+            None => None,
+        };
+        Ok((channels, rule, vps_name))
+    }).await.map_err(|e| AppError::InternalServerError(e.to_string()))??;
+
+    let severity = rule.as_ref().map(|(_, severity)| *severity).unwrap_or_default();
+    let channel_ids: Vec<i32> = channels
+        .into_iter()
+        .filter(|(_, severities)| channel_receives(severities, severity))
+        .map(|(channel_id, _)| channel_id)
+        .collect();
+    if channel_ids.is_empty() {
+        info!(rule_id = rule_id, %severity, "No notification channels for alert rule and severity.");
+        return Ok(());
+    }
+
+    let mut context = templates::base_context(&alert_message, chrono::Utc::now());
+    context.insert("rule_id".to_string(), rule_id.to_string());
+    context.insert("severity".to_string(), severity.to_string());
+    if let Some((rule_name, _)) = rule {
+        context.insert("rule_name".to_string(), rule_name);
+    }
+    if let Some(vps_id) = vps_id {
+        context.insert("vps_id".to_string(), vps_id.to_string());
+        context.insert("vps_name".to_string(), vps_name.unwrap_or_else(|| format!("VPS_ID_{vps_id}")));
+    }
+    send_notifications_with_context(pool, encryption_service, channel_ids, context, actions).await
+}
 
-        if channel_ids.is_empty() {
-            info!(rule_id = rule_id, "No notification channels linked to alert rule.");
-            return Ok(Vec::new());
+/// Ensures every channel belongs to `user_id`, so alerts can't be routed to foreign channels.
+pub fn check_channel_ownership(conn: &Connection, user_id: i32, channel_ids: &[i32]) -> Result<(), AppError> {
+    for channel_id in channel_ids {
+        let owned = conn
+            .query_row(
+                "SELECT 1 FROM notification_channels WHERE id = ? AND user_id = ?",
+                params![channel_id, user_id],
+                |_| Ok(()),
+            )
+            .optional()?;
+        if owned.is_none() {
+            return Err(AppError::InvalidInput(format!("Notification channel {channel_id} not found")));
         }
+    }
+    Ok(())
+}
+
+/// The language the owner of a channel reads notifications in.
+fn get_user_locale(conn: &Connection, user_id: i32) -> Result<String, AppError> {
+    let language: Option<String> = conn
+        .query_row("SELECT language FROM users WHERE id = ?", params![user_id], |row| row.get(0))
+        .optional()?;
+    Ok(templates::resolve_locale(language.as_deref().unwrap_or_default()).to_string())
+}
+
+/// Decrypts the given channels and sends `message` to each of them.
+/// Failures on individual channels are logged; the last one is returned.
+pub async fn send_notifications_to_channels(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    channel_ids: Vec<i32>,
+    message: String,
+) -> Result<(), AppError> {
+    let context = templates::base_context(&message, chrono::Utc::now());
+    send_notifications_with_context(pool, encryption_service, channel_ids, context, Vec::new()).await
+}
+
+/// The sender for a channel type, if it is one we can send to.
+pub fn sender_for(channel_type: &str) -> Option<Box<dyn NotificationSender + Send + Sync>> {
+    match channel_type {
+        "telegram" => Some(Box::new(TelegramSender::new())),
+        "webhook" => Some(Box::new(WebhookSender::new())),
+        _ => None,
+    }
+}
 
+/// A channel with its decrypted config and the language of its owner.
+pub async fn get_channel_for_sending(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    channel_id: i32,
+) -> Result<(ChannelConfig, notification_channel::Model, String), AppError> {
+    task::spawn_blocking(move || {
+        let conn = pool.get().map_err(AppError::from)?;
+        let model = conn
+            .query_row("SELECT * FROM notification_channels WHERE id = ?", params![channel_id], row_to_channel_model)
+            .optional()?
+            .ok_or_else(|| AppError::NotFound("Notification channel not found".to_string()))?;
+        let decrypted_bytes = encryption_service
+            .decrypt(&model.config)
+            .map_err(|e| AppError::InternalServerError(format!("Failed to decrypt channel config: {}", e)))?;
+        let config: ChannelConfig = serde_json::from_slice(&decrypted_bytes)
+            .map_err(|e| AppError::InternalServerError(format!("Failed to deserialize channel config: {}", e)))?;
+        let locale = get_user_locale(&conn, model.user_id)?;
+        Ok((config, model, locale))
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+}
+
+/// Renders each channel's template with `context` in its owner's language and
+/// sends it, attaching `actions` where the channel supports them. Digest
+/// channels queue the notification for their next digest instead.
+pub async fn send_notifications_with_context(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    channel_ids: Vec<i32>,
+    mut context: HashMap<String, String>,
+    actions: Vec<NotificationAction>,
+) -> Result<(), AppError> {
+    templates::add_actions(&mut context, &actions);
+    let queued_context = context.clone();
+
+    // Part 1: Fetch data from DB in a blocking task
+    let channels_to_notify = task::spawn_blocking(move || -> Result<Vec<(ChannelConfig, notification_channel::Model, String)>, AppError> {
+        let conn = pool.get().map_err(AppError::from)?;
+
+        let mut locales: HashMap<i32, String> = HashMap::new();
         let mut channels_to_notify = Vec::new();
         for channel_id in channel_ids {
             match conn.query_row::<notification_channel::Model, _, _>(
@@ -252,11 +485,25 @@ pub async fn send_notifications_for_alert_rule(
                 params![channel_id],
                 row_to_channel_model,
             ) {
+                Ok(model) if model.delivery == ChannelDelivery::Digest => {
+                    match notification_digest_service::enqueue_item(&conn, model.id, KIND_NOTIFICATION, &queued_context) {
+                        Ok(()) => info!(channel_id, "Queued notification for the channel's digest."),
+                        Err(e) => error!(channel_id, "Failed to queue notification for digest: {}", e),
+                    }
+                },
                 Ok(model) => {
+                    let locale = match locales.get(&model.user_id) {
+                        Some(locale) => locale.clone(),
+                        None => {
+                            let locale = get_user_locale(&conn, model.user_id)?;
+                            locales.insert(model.user_id, locale.clone());
+                            locale
+                        }
+                    };
                     match encryption_service.decrypt(&model.config) {
                         Ok(decrypted_bytes) => {
                             match serde_json::from_slice::<ChannelConfig>(&decrypted_bytes) {
-                                Ok(config) => channels_to_notify.push((config, model)),
+                                Ok(config) => channels_to_notify.push((config, model, locale)),
                                 Err(e) => error!(channel_id, "Failed to deserialize channel config: {}", e),
                             }
                         },
@@ -271,22 +518,21 @@ pub async fn send_notifications_for_alert_rule(
 
     // Part 2: Send notifications in the async context
     let mut last_error: Option<SenderError> = None;
-    let context = HashMap::new();
-
-    for (config, model) in channels_to_notify {
-        let sender: Box<dyn NotificationSender + Send + Sync> = match model.channel_type.as_str() {
-            "telegram" => Box::new(TelegramSender::new()),
-            "webhook" => Box::new(WebhookSender::new()),
-            unsupported => {
-                error!("Unsupported channel type for sending: {}", unsupported);
-                continue;
-            }
+
+    for (config, model, locale) in channels_to_notify {
+        let Some(sender) = sender_for(&model.channel_type) else {
+            error!("Unsupported channel type for sending: {}", model.channel_type);
+            continue;
         };
 
-        match sender.send(&config, &alert_message, &context).await {
-            Ok(_) => info!(channel_id = model.id, rule_id = rule_id, "Successfully sent alert notification."),
+        let message = templates::render_message(&model.message_templates, &locale, &context);
+        // Webhook body templates get the rendered message as `text`.
+        let mut sender_context = context.clone();
+        sender_context.insert("text".to_string(), message.clone());
+        match sender.send_with_actions(&config, &message, &sender_context, &actions).await {
+            Ok(_) => info!(channel_id = model.id, "Successfully sent notification."),
             Err(e) => {
-                error!(channel_id = model.id, rule_id = rule_id, error = ?e, "Failed to send alert notification.");
+                error!(channel_id = model.id, error = ?e, "Failed to send notification.");
                 last_error = Some(e);
             }
         }
@@ -299,15 +545,18 @@ pub async fn send_notifications_for_alert_rule(
     }
 }
 
+/// Renders the channel's template, or `template` when given, with a made-up
+/// alert and sends the result to the channel.
 pub async fn send_test_notification(
     pool: DuckDbPool,
     encryption_service: Arc<EncryptionService>,
     user_id: i32,
     channel_id: i32,
-    message: String,
-) -> Result<(), AppError> {
+    message: Option<String>,
+    template: Option<String>,
+) -> Result<TestChannelResponse, AppError> {
     // Part 1: Fetch channel data in a blocking task
-    let (config, model) = task::spawn_blocking(move || -> Result<(ChannelConfig, notification_channel::Model), AppError> {
+    let (config, model, locale) = task::spawn_blocking(move || -> Result<(ChannelConfig, notification_channel::Model, String), AppError> {
         let conn = pool.get().map_err(AppError::from)?;
         let model: notification_channel::Model = conn.query_row(
             "SELECT * FROM notification_channels WHERE id = ? AND user_id = ?",
@@ -325,27 +574,47 @@ pub async fn send_test_notification(
             .map_err(|e| AppError::InternalServerError(format!("Failed to decrypt channel config: {}", e)))?;
         let config: ChannelConfig = serde_json::from_slice(&decrypted_bytes)
             .map_err(|e| AppError::InternalServerError(format!("Failed to deserialize channel config: {}", e)))?;
-        
-        Ok((config, model))
+        let locale = get_user_locale(&conn, user_id)?;
+
+        Ok((config, model, locale))
     }).await.map_err(|e| AppError::InternalServerError(e.to_string()))??;
 
     // Part 2: Send notification in the async context
-    let sender: Box<dyn NotificationSender + Send + Sync> = match model.channel_type.as_str() {
-        "telegram" => Box::new(TelegramSender::new()),
-        "webhook" => Box::new(WebhookSender::new()),
-        unsupported => {
-            let err_msg = format!("Unsupported channel type for sending: {}", unsupported);
-            error!("{}", err_msg);
-            return Err(AppError::InternalServerError(err_msg));
-        }
-    };
+    let sender = sender_for(&model.channel_type).ok_or_else(|| {
+        let err_msg = format!("Unsupported channel type for sending: {}", model.channel_type);
+        error!("{}", err_msg);
+        AppError::InternalServerError(err_msg)
+    })?;
 
-    let context = HashMap::new(); // No context for test messages
-    sender.send(&config, &message, &context).await.map_err(|e| {
+    let message = message.unwrap_or_else(|| t!("notifications.test_message", locale = &locale).into_owned());
+    let mut context = templates::sample_context(&message, &locale, chrono::Utc::now());
+    let rendered = match template.as_deref().filter(|t| !t.trim().is_empty()) {
+        Some(template) => templates::render(template, &context)
+            .map_err(|e| AppError::InvalidInput(format!("Template is invalid: {e}")))?,
+        None => templates::render_message(&model.message_templates, &locale, &context),
+    };
+    context.insert("text".to_string(), rendered.clone());
+    sender.send(&config, &rendered, &context).await.map_err(|e| {
         error!(channel_id = model.id, error = ?e, "Failed to send test notification.");
         AppError::InternalServerError(e.to_string())
     })?;
 
     info!(channel_id = model.id, "Successfully sent test notification.");
-    Ok(())
-}
\ No newline at end of file
+    Ok(TestChannelResponse {
+        message,
+        rendered,
+        locale,
+    })
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn channels_receive_their_severities_or_all_when_none_are_set() {
+        assert!(channel_receives(&[], AlertSeverity::Info));
+        let critical_only = [AlertSeverity::Critical];
+        assert!(channel_receives(&critical_only, AlertSeverity::Critical));
+        assert!(!channel_receives(&critical_only, AlertSeverity::Warning));
+    }
+}
@@ -136,8 +136,8 @@ pub enum OAuthServiceError {
 impl From<OAuthServiceError> for AppError {
     fn from(err: OAuthServiceError) -> Self {
         match err {
-            OAuthServiceError::DbErr(e) => AppError::DatabaseError(e.to_string()),
-            OAuthServiceError::PoolError(e) => AppError::DatabaseError(e.to_string()),
+            OAuthServiceError::DbErr(e) => e.into(),
+            OAuthServiceError::PoolError(e) => e.into(),
             OAuthServiceError::NotFound(name) => AppError::NotFound(format!("OAuth provider '{name}' not found")),
             OAuthServiceError::JoinError(e) => AppError::InternalServerError(e.to_string()),
             OAuthServiceError::EncryptionError(e) => AppError::InternalServerError(e),
@@ -147,7 +147,7 @@ impl From<OAuthServiceError> for AppError {
             OAuthServiceError::UserNotFound => AppError::UserNotFound,
             OAuthServiceError::InvalidInput(e) => AppError::InvalidInput(e),
             OAuthServiceError::Conflict(e) => AppError::Conflict(e),
-            OAuthServiceError::UserServiceError(e) => AppError::DatabaseError(e.to_string()),
+            OAuthServiceError::UserServiceError(e) => e.into(),
         }
     }
 }
@@ -446,6 +446,7 @@ pub async fn handle_oauth_callback(
         let user_model = user_service::get_user_by_id(db_pool, user_id).await?
             .ok_or(OAuthServiceError::UserNotFound)?;
 
+        // TOTP guards password logins; OAuth logins rely on the provider's own MFA.
         let login_response = auth_service::create_jwt_for_user(&user_model, &config.jwt_secret)
             .map_err(|e| OAuthServiceError::OAuthError(e.to_string()))?;
             
@@ -0,0 +1,336 @@
+use chrono::Utc;
+use duckdb::{params, Connection, OptionalExt, Row};
+
+use super::DuckDbPool;
+use crate::db::entities::{organization, organization_member};
+use crate::web::error::AppError;
+
+pub const ROLE_OWNER: &str = "owner";
+pub const ROLE_MEMBER: &str = "member";
+
+/// Tables whose rows belong to an organization.
+const SCOPED_TABLES: &[&str] = &["vps", "tags", "service_monitors", "alert_rules"];
+
+/// SQL condition matching rows in the active organization of a user. Binds one
+/// parameter, the user id. `users.active_organization_id` always points at an
+/// organization the user belongs to (switching checks membership, and leaving
+/// resets it), so this is also the membership check.
+pub(crate) fn org_scope(column: &str) -> String {
+    format!("{column} = (SELECT active_organization_id FROM users WHERE id = ?)")
+}
+
+/// The organization new resources of `user_id` are created in.
+pub(crate) fn active_organization_id(conn: &Connection, user_id: i32) -> Result<i32, AppError> {
+    conn.query_row("SELECT active_organization_id FROM users WHERE id = ?", params![user_id], |row| {
+        row.get::<_, Option<i32>>(0)
+    })
+    .optional()?
+    .flatten()
+    .ok_or_else(|| AppError::NotFound("User has no active organization".to_string()))
+}
+
+/// Creates the personal organization of a new user and makes it active.
+pub(crate) fn create_personal_organization(conn: &Connection, user_id: i32, username: &str) -> duckdb::Result<i32> {
+    let id: i32 = conn.query_row(
+        "INSERT INTO organizations (name, personal_for_user_id) VALUES (?, ?) RETURNING id",
+        params![username, user_id],
+        |row| row.get(0),
+    )?;
+    conn.execute(
+        "INSERT INTO organization_members (organization_id, user_id, role) VALUES (?, ?, ?)",
+        params![id, user_id, ROLE_OWNER],
+    )?;
+    conn.execute("UPDATE users SET active_organization_id = ? WHERE id = ?", params![id, user_id])?;
+    Ok(id)
+}
+
+/// The organization's service monitor interval quota, if one is set.
+pub(crate) fn min_monitor_frequency_seconds(conn: &Connection, organization_id: i32) -> Result<Option<i32>, AppError> {
+    Ok(conn
+        .query_row(
+            "SELECT min_monitor_frequency_seconds FROM organizations WHERE id = ?",
+            params![organization_id],
+            |row| row.get::<_, Option<i32>>(0),
+        )
+        .optional()?
+        .flatten())
+}
+
+fn row_to_organization(row: &Row) -> duckdb::Result<organization::Model> {
+    Ok(organization::Model {
+        id: row.get("id")?,
+        name: row.get("name")?,
+        personal_for_user_id: row.get("personal_for_user_id")?,
+        min_monitor_frequency_seconds: row.get("min_monitor_frequency_seconds")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn row_to_member(row: &Row) -> duckdb::Result<organization_member::Model> {
+    Ok(organization_member::Model {
+        organization_id: row.get("organization_id")?,
+        user_id: row.get("user_id")?,
+        username: row.get("username")?,
+        role: row.get("role")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+fn validate_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() || name.len() > 255 {
+        return Err(AppError::InvalidInput("Organization name must be 1-255 characters".to_string()));
+    }
+    Ok(name.to_string())
+}
+
+/// The caller's role in the organization; NotFound if they aren't a member.
+fn member_role(conn: &Connection, organization_id: i32, user_id: i32) -> Result<String, AppError> {
+    conn.query_row(
+        "SELECT role FROM organization_members WHERE organization_id = ? AND user_id = ?",
+        params![organization_id, user_id],
+        |row| row.get(0),
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))
+}
+
+fn require_owner(conn: &Connection, organization_id: i32, user_id: i32) -> Result<(), AppError> {
+    if member_role(conn, organization_id, user_id)? == ROLE_OWNER {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden("Only organization owners can do this".to_string()))
+    }
+}
+
+fn get_organization(conn: &Connection, organization_id: i32) -> Result<organization::Model, AppError> {
+    conn.query_row("SELECT * FROM organizations WHERE id = ?", params![organization_id], row_to_organization)
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))
+}
+
+/// Moves users whose active organization is `organization_id` back to their
+/// personal one. `user_id` limits this to a single user.
+fn reset_active_organization(conn: &Connection, organization_id: i32, user_id: Option<i32>) -> duckdb::Result<()> {
+    let sql = "UPDATE users SET active_organization_id =
+                   (SELECT o.id FROM organizations o WHERE o.personal_for_user_id = users.id)
+               WHERE active_organization_id = ?";
+    match user_id {
+        Some(user_id) => conn.execute(&format!("{sql} AND id = ?"), params![organization_id, user_id])?,
+        None => conn.execute(sql, params![organization_id])?,
+    };
+    Ok(())
+}
+
+/// Organizations the user belongs to, with their role in each.
+pub async fn get_organizations_for_user(
+    pool: DuckDbPool,
+    user_id: i32,
+) -> Result<Vec<(organization::Model, String)>, AppError> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare(
+        "SELECT o.*, m.role AS member_role FROM organizations o
+         JOIN organization_members m ON m.organization_id = o.id
+         WHERE m.user_id = ?
+         ORDER BY o.personal_for_user_id IS NULL, o.name",
+    )?;
+    let organizations = stmt
+        .query_map(params![user_id], |row| Ok((row_to_organization(row)?, row.get("member_role")?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(organizations)
+}
+
+pub async fn create_organization(pool: DuckDbPool, user_id: i32, name: &str) -> Result<organization::Model, AppError> {
+    let name = validate_name(name)?;
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    let organization = tx.query_row(
+        "INSERT INTO organizations (name) VALUES (?) RETURNING *",
+        params![name],
+        row_to_organization,
+    )?;
+    tx.execute(
+        "INSERT INTO organization_members (organization_id, user_id, role) VALUES (?, ?, ?)",
+        params![organization.id, user_id, ROLE_OWNER],
+    )?;
+    tx.commit()?;
+    Ok(organization)
+}
+
+pub async fn rename_organization(
+    pool: DuckDbPool,
+    user_id: i32,
+    organization_id: i32,
+    name: &str,
+) -> Result<organization::Model, AppError> {
+    let name = validate_name(name)?;
+    let conn = pool.get()?;
+    require_owner(&conn, organization_id, user_id)?;
+    Ok(conn.query_row(
+        "UPDATE organizations SET name = ?, updated_at = ? WHERE id = ? RETURNING *",
+        params![name, Utc::now(), organization_id],
+        row_to_organization,
+    )?)
+}
+
+/// Deletes a shared organization. It must be empty: resources aren't moved
+/// between organizations implicitly.
+pub async fn delete_organization(pool: DuckDbPool, user_id: i32, organization_id: i32) -> Result<(), AppError> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    require_owner(&tx, organization_id, user_id)?;
+    if get_organization(&tx, organization_id)?.personal_for_user_id.is_some() {
+        return Err(AppError::Conflict("Personal organizations can't be deleted".to_string()));
+    }
+    for table in SCOPED_TABLES {
+        let count: i64 = tx.query_row(
+            &format!("SELECT COUNT(*) FROM {table} WHERE organization_id = ?"),
+            params![organization_id],
+            |row| row.get(0),
+        )?;
+        if count > 0 {
+            return Err(AppError::Conflict(format!(
+                "The organization still has {table}; delete them first"
+            )));
+        }
+    }
+    reset_active_organization(&tx, organization_id, None)?;
+    tx.execute("DELETE FROM organization_members WHERE organization_id = ?", params![organization_id])?;
+    tx.execute("DELETE FROM organizations WHERE id = ?", params![organization_id])?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// The org switcher: scopes the user's following requests to `organization_id`.
+pub async fn switch_active_organization(
+    pool: DuckDbPool,
+    user_id: i32,
+    organization_id: i32,
+) -> Result<organization::Model, AppError> {
+    let conn = pool.get()?;
+    member_role(&conn, organization_id, user_id)?;
+    conn.execute(
+        "UPDATE users SET active_organization_id = ? WHERE id = ?",
+        params![organization_id, user_id],
+    )?;
+    get_organization(&conn, organization_id)
+}
+
+pub async fn get_members(
+    pool: DuckDbPool,
+    user_id: i32,
+    organization_id: i32,
+) -> Result<Vec<organization_member::Model>, AppError> {
+    let conn = pool.get()?;
+    member_role(&conn, organization_id, user_id)?;
+    let mut stmt = conn.prepare(
+        "SELECT m.*, u.username FROM organization_members m
+         JOIN users u ON u.id = m.user_id
+         WHERE m.organization_id = ?
+         ORDER BY u.username",
+    )?;
+    let members = stmt
+        .query_map(params![organization_id], row_to_member)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(members)
+}
+
+/// Adds an existing user to the organization, or changes their role if they
+/// are already a member.
+pub async fn upsert_member(
+    pool: DuckDbPool,
+    user_id: i32,
+    organization_id: i32,
+    username: &str,
+    role: &str,
+) -> Result<organization_member::Model, AppError> {
+    if role != ROLE_OWNER && role != ROLE_MEMBER {
+        return Err(AppError::InvalidInput("role must be 'owner' or 'member'".to_string()));
+    }
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    require_owner(&tx, organization_id, user_id)?;
+    let organization = get_organization(&tx, organization_id)?;
+    let member_id: i32 = tx
+        .query_row("SELECT id FROM users WHERE username = ?", params![username], |row| row.get(0))
+        .optional()?
+        .ok_or_else(|| AppError::NotFound(format!("User '{username}' not found")))?;
+    if organization.personal_for_user_id.is_some() && member_id != user_id {
+        return Err(AppError::Conflict("Personal organizations can't be shared".to_string()));
+    }
+    if role == ROLE_MEMBER && member_role(&tx, organization_id, member_id).ok().as_deref() == Some(ROLE_OWNER) {
+        ensure_another_owner(&tx, organization_id, member_id)?;
+    }
+    tx.execute(
+        "INSERT INTO organization_members (organization_id, user_id, role) VALUES (?, ?, ?)
+         ON CONFLICT (organization_id, user_id) DO UPDATE SET role = excluded.role",
+        params![organization_id, member_id, role],
+    )?;
+    let member = tx.query_row(
+        "SELECT m.*, u.username FROM organization_members m
+         JOIN users u ON u.id = m.user_id
+         WHERE m.organization_id = ? AND m.user_id = ?",
+        params![organization_id, member_id],
+        row_to_member,
+    )?;
+    tx.commit()?;
+    Ok(member)
+}
+
+fn ensure_another_owner(conn: &Connection, organization_id: i32, leaving_user_id: i32) -> Result<(), AppError> {
+    let other_owners: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM organization_members WHERE organization_id = ? AND role = ? AND user_id <> ?",
+        params![organization_id, ROLE_OWNER, leaving_user_id],
+        |row| row.get(0),
+    )?;
+    if other_owners == 0 {
+        return Err(AppError::Conflict("An organization needs at least one owner".to_string()));
+    }
+    Ok(())
+}
+
+/// Removes a member. Owners can remove anyone; members can only leave.
+pub async fn remove_member(
+    pool: DuckDbPool,
+    user_id: i32,
+    organization_id: i32,
+    member_id: i32,
+) -> Result<(), AppError> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    if member_id != user_id {
+        require_owner(&tx, organization_id, user_id)?;
+    }
+    let role = member_role(&tx, organization_id, member_id)?;
+    if get_organization(&tx, organization_id)?.personal_for_user_id == Some(member_id) {
+        return Err(AppError::Conflict("Users can't leave their personal organization".to_string()));
+    }
+    if role == ROLE_OWNER {
+        ensure_another_owner(&tx, organization_id, member_id)?;
+    }
+    tx.execute(
+        "DELETE FROM organization_members WHERE organization_id = ? AND user_id = ?",
+        params![organization_id, member_id],
+    )?;
+    reset_active_organization(&tx, organization_id, Some(member_id))?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Sets the service monitor interval quota of an organization. Admin only;
+/// the caller checks the role.
+pub async fn set_monitor_quota(
+    pool: DuckDbPool,
+    organization_id: i32,
+    min_monitor_frequency_seconds: Option<i32>,
+) -> Result<organization::Model, AppError> {
+    let conn = pool.get()?;
+    conn.query_row(
+        "UPDATE organizations SET min_monitor_frequency_seconds = ?, updated_at = ? WHERE id = ? RETURNING *",
+        params![min_monitor_frequency_seconds, Utc::now(), organization_id],
+        row_to_organization,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound("Organization not found".to_string()))
+}
@@ -1,8 +1,9 @@
 use chrono::{DateTime, Duration, Utc};
-use duckdb::params;
+use duckdb::{params, Connection, Row};
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 
+use super::retention::RetentionPolicy;
 use super::Error;
 use db::duckdb_service::DuckDbPool;
 use nodenexus_common::agent_service::PerformanceSnapshotBatch;
@@ -29,78 +30,113 @@ pub struct PerformanceMetricPoint {
     pub total_disk_space_bytes: Option<f64>,
 }
 
-/// Retrieves performance metrics for a given VPS within a time range from DuckDB.
-pub async fn get_performance_metrics_for_vps(
-    pool: &DuckDbPool,
-    vps_id: i32,
+impl From<&performance_metric::Model> for PerformanceMetricPoint {
+    fn from(m: &performance_metric::Model) -> Self {
+        PerformanceMetricPoint {
+            time: m.time,
+            vps_id: m.vps_id,
+            cpu_usage_percent: Some(m.cpu_usage_percent),
+            memory_usage_bytes: Some(m.memory_usage_bytes as f64),
+            memory_total_bytes: Some(m.memory_total_bytes as f64),
+            swap_usage_bytes: Some(m.swap_usage_bytes as f64),
+            disk_io_read_bps: Some(m.disk_io_read_bps as f64),
+            disk_io_write_bps: Some(m.disk_io_write_bps as f64),
+            network_rx_instant_bps: Some(m.network_rx_instant_bps as f64),
+            network_tx_instant_bps: Some(m.network_tx_instant_bps as f64),
+            used_disk_space_bytes: Some(m.used_disk_space_bytes as f64),
+            total_disk_space_bytes: Some(m.total_disk_space_bytes as f64),
+        }
+    }
+}
+
+/// Picks the table to read aggregated metrics from for a query over `start..end`:
+/// the finest resolution suited to the range whose retention still reaches back
+/// to `start`. Returns the table name and whether it is a summary table
+/// (`avg_`/`max_` columns).
+pub fn select_metric_source(
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    retention: &RetentionPolicy,
+    now: DateTime<Utc>,
+) -> (&'static str, bool) {
+    let duration = end - start;
+    // Finest to coarsest, with the longest range each resolution is read for.
+    let sources = [
+        ("performance_metrics", false, Duration::hours(1), retention.raw_days),
+        ("performance_metrics_summary_1m", true, Duration::days(7), retention.summary_1m_days),
+        ("performance_metrics_summary_1h", true, Duration::days(30), retention.summary_1h_days),
+    ];
+    sources
+        .into_iter()
+        .find(|(_, _, max_range, days)| duration <= *max_range && RetentionPolicy::covers(*days, start, now))
+        .map(|(table, is_aggregated, _, _)| (table, is_aggregated))
+        .unwrap_or(("performance_metrics_summary_1d", true))
+}
+
+fn raw_metric_point(row: &Row) -> duckdb::Result<PerformanceMetricPoint> {
+    let m: performance_metric::Model = performance_metric::Model {
+        time: row.get(0)?,
+        vps_id: row.get(1)?,
+        cpu_usage_percent: row.get(2)?,
+        memory_usage_bytes: row.get(3)?,
+        memory_total_bytes: row.get(4)?,
+        swap_usage_bytes: row.get(5)?,
+        swap_total_bytes: row.get(6)?,
+        disk_io_read_bps: row.get(7)?,
+        disk_io_write_bps: row.get(8)?,
+        network_rx_cumulative: row.get(9)?,
+        network_tx_cumulative: row.get(10)?,
+        network_rx_instant_bps: row.get(11)?,
+        network_tx_instant_bps: row.get(12)?,
+        uptime_seconds: row.get(13)?,
+        total_processes_count: row.get(14)?,
+        running_processes_count: row.get(15)?,
+        tcp_established_connection_count: row.get(16)?,
+        total_disk_space_bytes: row.get(17)?,
+        used_disk_space_bytes: row.get(18)?,
+    };
+    Ok(PerformanceMetricPoint::from(&m))
+}
+
+fn aggregated_metric_point(row: &Row) -> duckdb::Result<PerformanceMetricPoint> {
+    Ok(PerformanceMetricPoint {
+        time: row.get(0)?,
+        vps_id: row.get(1)?,
+        cpu_usage_percent: row.get(2)?,
+        memory_usage_bytes: row.get(3)?,
+        memory_total_bytes: row.get(4).map(|v: i64| v as f64).ok(),
+        swap_usage_bytes: row.get(5)?,
+        disk_io_read_bps: row.get(6)?,
+        disk_io_write_bps: row.get(7)?,
+        network_rx_instant_bps: row.get(8)?,
+        network_tx_instant_bps: row.get(9)?,
+        used_disk_space_bytes: row.get(10)?,
+        total_disk_space_bytes: row.get(11)?,
+    })
+}
+
+/// The query for a VPS's metrics over a time range (parameters: vps_id,
+/// start, end), and whether its rows are raw `performance_metrics` rows
+/// rather than interval buckets.
+fn performance_metrics_sql(
     start_time: DateTime<Utc>,
     end_time: DateTime<Utc>,
     interval_seconds: Option<u32>,
-) -> Result<Vec<PerformanceMetricPoint>, Error> {
-    let conn = pool.get()?;
-
+    retention: &RetentionPolicy,
+) -> (String, bool) {
     // If no interval is specified, return raw data points.
-    if interval_seconds.is_none() {
+    let Some(interval_secs) = interval_seconds else {
         debug!("No interval specified, fetching raw performance_metrics from DuckDB.");
-        let mut stmt = conn.prepare(
-            "SELECT * FROM performance_metrics WHERE vps_id = ? AND time >= ? AND time <= ? ORDER BY time ASC"
-        )?;
-
-        let results = stmt.query_map(params![vps_id, start_time, end_time], |row| {
-            let m: performance_metric::Model = performance_metric::Model {
-                time: row.get(0)?,
-                vps_id: row.get(1)?,
-                cpu_usage_percent: row.get(2)?,
-                memory_usage_bytes: row.get(3)?,
-                memory_total_bytes: row.get(4)?,
-                swap_usage_bytes: row.get(5)?,
-                swap_total_bytes: row.get(6)?,
-                disk_io_read_bps: row.get(7)?,
-                disk_io_write_bps: row.get(8)?,
-                network_rx_cumulative: row.get(9)?,
-                network_tx_cumulative: row.get(10)?,
-                network_rx_instant_bps: row.get(11)?,
-                network_tx_instant_bps: row.get(12)?,
-                uptime_seconds: row.get(13)?,
-                total_processes_count: row.get(14)?,
-                running_processes_count: row.get(15)?,
-                tcp_established_connection_count: row.get(16)?,
-                total_disk_space_bytes: row.get(17)?,
-                used_disk_space_bytes: row.get(18)?,
-            };
-            Ok(PerformanceMetricPoint {
-                time: m.time,
-                vps_id: m.vps_id,
-                cpu_usage_percent: Some(m.cpu_usage_percent),
-                memory_usage_bytes: Some(m.memory_usage_bytes as f64),
-                memory_total_bytes: Some(m.memory_total_bytes as f64),
-                swap_usage_bytes: Some(m.swap_usage_bytes as f64),
-                disk_io_read_bps: Some(m.disk_io_read_bps as f64),
-                disk_io_write_bps: Some(m.disk_io_write_bps as f64),
-                network_rx_instant_bps: Some(m.network_rx_instant_bps as f64),
-                network_tx_instant_bps: Some(m.network_tx_instant_bps as f64),
-                used_disk_space_bytes: Some(m.used_disk_space_bytes as f64),
-                total_disk_space_bytes: Some(m.total_disk_space_bytes as f64),
-            })
-        })?
-        .collect::<Result<Vec<_>, _>>()?;
-
-        return Ok(results);
-    }
+        let sql = "SELECT * FROM performance_metrics WHERE vps_id = ? AND time >= ? AND time <= ? ORDER BY time ASC";
+        return (sql.to_string(), true);
+    };
 
     // If an interval is specified, proceed with aggregation.
     let duration = end_time - start_time;
-    let interval_secs = interval_seconds.unwrap().max(1);
+    let interval_secs = interval_secs.max(1);
 
-    let (metric_source, time_col, is_aggregated) = if duration <= Duration::hours(1) {
-        ("performance_metrics", "time", false)
-    } else if duration <= Duration::days(7) {
-        ("performance_metrics_summary_1m", "time", true)
-    } else if duration <= Duration::days(30) {
-        ("performance_metrics_summary_1h", "time", true)
-    } else {
-        ("performance_metrics_summary_1d", "time", true)
-    };
+    let (metric_source, is_aggregated) = select_metric_source(start_time, end_time, retention, Utc::now());
+    let time_col = "time";
     debug!(?duration, ?interval_seconds, metric_source, "Choosing DuckDB data source for performance query");
 
     let sql = if !is_aggregated {
@@ -150,26 +186,48 @@ pub async fn get_performance_metrics_for_vps(
             "#
         )
     };
+    (sql, false)
+}
 
+/// Calls `f` with each metric point of a VPS in a time range, oldest first,
+/// without collecting them; stops early when `f` returns `false`. Blocking.
+pub fn for_each_performance_metric(
+    conn: &Connection,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    interval_seconds: Option<u32>,
+    retention: &RetentionPolicy,
+    mut f: impl FnMut(PerformanceMetricPoint) -> bool,
+) -> Result<(), Error> {
+    let (sql, is_raw) = performance_metrics_sql(start_time, end_time, interval_seconds, retention);
+    let to_point: fn(&Row) -> duckdb::Result<PerformanceMetricPoint> =
+        if is_raw { raw_metric_point } else { aggregated_metric_point };
     let mut stmt = conn.prepare(&sql)?;
-    let results = stmt.query_map(params![vps_id, start_time, end_time], |row| {
-        Ok(PerformanceMetricPoint {
-            time: row.get(0)?,
-            vps_id: row.get(1)?,
-            cpu_usage_percent: row.get(2)?,
-            memory_usage_bytes: row.get(3)?,
-            memory_total_bytes: row.get(4).map(|v: i64| v as f64).ok(),
-            swap_usage_bytes: row.get(5)?,
-            disk_io_read_bps: row.get(6)?,
-            disk_io_write_bps: row.get(7)?,
-            network_rx_instant_bps: row.get(8)?,
-            network_tx_instant_bps: row.get(9)?,
-            used_disk_space_bytes: row.get(10)?,
-            total_disk_space_bytes: row.get(11)?,
-        })
-    })?
-    .collect::<Result<Vec<_>, _>>()?;
+    let mut rows = stmt.query(params![vps_id, start_time, end_time])?;
+    while let Some(row) = rows.next()? {
+        if !f(to_point(row)?) {
+            break;
+        }
+    }
+    Ok(())
+}
 
+/// Retrieves performance metrics for a given VPS within a time range from DuckDB.
+pub async fn get_performance_metrics_for_vps(
+    pool: &DuckDbPool,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    interval_seconds: Option<u32>,
+    retention: &RetentionPolicy,
+) -> Result<Vec<PerformanceMetricPoint>, Error> {
+    let conn = pool.get()?;
+    let mut results = Vec::new();
+    for_each_performance_metric(&conn, vps_id, start_time, end_time, interval_seconds, retention, |point| {
+        results.push(point);
+        true
+    })?;
     Ok(results)
 }
 
@@ -188,8 +246,8 @@ pub async fn get_latest_performance_metric_for_vps(
             network_tx_instant_bps, uptime_seconds, total_processes_count, 
             running_processes_count, tcp_established_connection_count, 
             total_disk_space_bytes, used_disk_space_bytes
-        FROM performance_metrics 
-        WHERE vps_id = ? ORDER BY time DESC LIMIT 1";
+        FROM latest_performance_metrics
+        WHERE vps_id = ?";
 
     let mut stmt = conn.prepare(sql)?;
     
@@ -245,7 +303,7 @@ pub async fn get_latest_disk_usage_summary(
 ) -> Result<Option<(i64, i64)>, Error> {
     let conn = pool.get()?;
     let mut stmt = conn.prepare(
-        "SELECT total_disk_space_bytes, used_disk_space_bytes FROM performance_metrics WHERE vps_id = ? ORDER BY time DESC LIMIT 1",
+        "SELECT total_disk_space_bytes, used_disk_space_bytes FROM latest_performance_metrics WHERE vps_id = ?",
     )?;
 
     let result = stmt.query_row(params![vps_id], |row| {
@@ -257,4 +315,39 @@ pub async fn get_latest_disk_usage_summary(
         Err(duckdb::Error::QueryReturnedNoRows) => Ok(None),
         Err(e) => Err(e.into()),
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn picks_resolution_by_range() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        let policy = RetentionPolicy::default();
+        let source = |range: Duration| select_metric_source(now - range, now, &policy, now).0;
+        assert_eq!(source(Duration::minutes(30)), "performance_metrics");
+        assert_eq!(source(Duration::days(1)), "performance_metrics_summary_1m");
+        assert_eq!(source(Duration::days(14)), "performance_metrics_summary_1h");
+        assert_eq!(source(Duration::days(90)), "performance_metrics_summary_1d");
+    }
+
+    #[test]
+    fn skips_resolutions_that_no_longer_cover_the_start() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        let policy = RetentionPolicy::default();
+        // A short range from 10 days ago: raw data is gone, 1m rollups remain.
+        let start = now - Duration::days(10);
+        assert_eq!(
+            select_metric_source(start, start + Duration::minutes(30), &policy, now).0,
+            "performance_metrics_summary_1m"
+        );
+        // Past the 1m retention, the hourly rollups take over.
+        let start = now - Duration::days(100);
+        assert_eq!(
+            select_metric_source(start, start + Duration::days(1), &policy, now).0,
+            "performance_metrics_summary_1h"
+        );
+    }
+}
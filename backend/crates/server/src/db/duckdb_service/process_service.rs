@@ -0,0 +1,101 @@
+use chrono::{DateTime, TimeZone, Utc};
+use duckdb::{params, Result as DuckDbResult, Row};
+use nodenexus_common::agent_service::ProcessSnapshot;
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::process_metric;
+use crate::web::error::AppError;
+
+/// Order of the returned process list.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProcessSort {
+    Cpu,
+    Memory,
+}
+
+impl ProcessSort {
+    fn order_by(self) -> &'static str {
+        match self {
+            ProcessSort::Cpu => "cpu_usage_percent DESC, memory_rss_bytes DESC",
+            ProcessSort::Memory => "memory_rss_bytes DESC, cpu_usage_percent DESC",
+        }
+    }
+}
+
+fn row_to_process_metric_model(row: &Row) -> DuckDbResult<process_metric::Model> {
+    Ok(process_metric::Model {
+        time: row.get("time")?,
+        vps_id: row.get("vps_id")?,
+        pid: row.get("pid")?,
+        name: row.get("name")?,
+        cpu_usage_percent: row.get("cpu_usage_percent")?,
+        memory_rss_bytes: row.get("memory_rss_bytes")?,
+        username: row.get("username")?,
+    })
+}
+
+pub async fn record_process_snapshot(
+    pool: DuckDbPool,
+    vps_id: i32,
+    snapshot: ProcessSnapshot,
+) -> Result<usize, AppError> {
+    tokio::task::spawn_blocking(move || -> Result<usize, AppError> {
+        let time = Utc
+            .timestamp_millis_opt(snapshot.timestamp_unix_ms)
+            .single()
+            .unwrap_or_else(Utc::now);
+        let mut conn = pool.get()?;
+        let tx = conn.transaction()?;
+        {
+            let mut stmt = tx.prepare(
+                "INSERT INTO process_metrics (time, vps_id, pid, name, cpu_usage_percent, memory_rss_bytes, username)
+                 VALUES (?, ?, ?, ?, ?, ?, ?)",
+            )?;
+            for process in &snapshot.processes {
+                let username = (!process.user.is_empty()).then_some(process.user.as_str());
+                stmt.execute(params![
+                    time,
+                    vps_id,
+                    process.pid as i32,
+                    process.name,
+                    f64::from(process.cpu_usage_percent),
+                    process.memory_rss_bytes as i64,
+                    username,
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(snapshot.processes.len())
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+}
+
+/// Returns the latest snapshot at or before `at` (or the latest overall).
+pub async fn get_process_snapshot(
+    pool: DuckDbPool,
+    vps_id: i32,
+    at: Option<DateTime<Utc>>,
+    sort: ProcessSort,
+) -> Result<Vec<process_metric::Model>, AppError> {
+    // Agent clocks may run ahead of the server's, so "latest" isn't bounded by now.
+    let time_filter = if at.is_some() { "AND time <= ?" } else { "" };
+    let sql = format!(
+        "SELECT * FROM process_metrics
+         WHERE vps_id = ? AND time = (
+             SELECT MAX(time) FROM process_metrics WHERE vps_id = ? {time_filter}
+         )
+         ORDER BY {}",
+        sort.order_by()
+    );
+    let mut params: Vec<&dyn duckdb::ToSql> = vec![&vps_id, &vps_id];
+    if let Some(at) = at.as_ref() {
+        params.push(at);
+    }
+    let conn = pool.get()?;
+    let processes = conn
+        .prepare(&sql)?
+        .query_map(params.as_slice(), row_to_process_metric_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(processes)
+}
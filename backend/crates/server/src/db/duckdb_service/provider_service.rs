@@ -0,0 +1,312 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, Connection, OptionalExt, Result as DuckDbResult, Row};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::duckdb_service::{encryption_key_service, DuckDbPool};
+use crate::db::entities::{provider_account, vps_power_action};
+use crate::notifications::encryption::EncryptionService;
+use crate::services::provider_power::{PowerAction, ProviderKind};
+use crate::web::error::AppError;
+use crate::web::models::provider_models::{
+    CreateProviderAccountRequest, UpdateProviderAccountRequest, VpsProviderLink,
+};
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_CONFIRMED: &str = "confirmed";
+pub const STATUS_SUCCEEDED: &str = "succeeded";
+pub const STATUS_FAILED: &str = "failed";
+pub const STATUS_EXPIRED: &str = "expired";
+
+/// Everything needed to call the provider API for a VPS.
+pub struct PowerTarget {
+    pub provider: ProviderKind,
+    pub token: String,
+    pub provider_server_id: String,
+}
+
+fn row_to_account_model(row: &Row) -> DuckDbResult<provider_account::Model> {
+    Ok(provider_account::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        name: row.get("name")?,
+        provider: row.get("provider")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn row_to_power_action_model(row: &Row) -> DuckDbResult<vps_power_action::Model> {
+    Ok(vps_power_action::Model {
+        id: row.get("id")?,
+        vps_id: row.get("vps_id")?,
+        user_id: row.get("user_id")?,
+        action: row.get("action")?,
+        status: row.get("status")?,
+        agent_online: row.get("agent_online")?,
+        requested_at: row.get("requested_at")?,
+        expires_at: row.get("expires_at")?,
+        completed_at: row.get("completed_at")?,
+        error: row.get("error")?,
+    })
+}
+
+fn encrypt_token(
+    encryption_service: &EncryptionService,
+    conn: &Connection,
+    user_id: i32,
+    token: &str,
+) -> Result<Vec<u8>, AppError> {
+    let token = token.trim();
+    if token.is_empty() {
+        return Err(AppError::InvalidInput("API token must not be empty".to_string()));
+    }
+    let key_organization = encryption_key_service::user_key_organization(conn, user_id)?;
+    encryption_service
+        .encrypt_for(key_organization, token.as_bytes())
+        .map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+pub async fn create_provider_account(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    user_id: i32,
+    payload: CreateProviderAccountRequest,
+) -> Result<provider_account::Model, AppError> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Name must not be empty".to_string()));
+    }
+    let conn = pool.get()?;
+    let token = encrypt_token(&encryption_service, &conn, user_id, &payload.token)?;
+    let account = conn.query_row(
+        "INSERT INTO provider_accounts (user_id, name, provider, token) VALUES (?, ?, ?, ?) RETURNING *",
+        params![user_id, name, payload.provider.as_str(), token],
+        row_to_account_model,
+    )?;
+    Ok(account)
+}
+
+pub async fn get_provider_accounts_for_user(
+    pool: DuckDbPool,
+    user_id: i32,
+) -> Result<Vec<provider_account::Model>, AppError> {
+    let conn = pool.get()?;
+    let accounts = conn
+        .prepare("SELECT * FROM provider_accounts WHERE user_id = ? ORDER BY name ASC")?
+        .query_map(params![user_id], row_to_account_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(accounts)
+}
+
+pub async fn update_provider_account(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    user_id: i32,
+    id: i32,
+    payload: UpdateProviderAccountRequest,
+) -> Result<provider_account::Model, AppError> {
+    let name = payload.name.as_deref().map(str::trim);
+    if name.is_some_and(str::is_empty) {
+        return Err(AppError::InvalidInput("Name must not be empty".to_string()));
+    }
+    let conn = pool.get()?;
+    let token = payload
+        .token
+        .as_deref()
+        .map(|token| encrypt_token(&encryption_service, &conn, user_id, token))
+        .transpose()?;
+    conn.query_row(
+        "UPDATE provider_accounts
+         SET name = COALESCE(?, name), token = COALESCE(?, token), updated_at = ?
+         WHERE id = ? AND user_id = ? RETURNING *",
+        params![name, token, Utc::now(), id, user_id],
+        row_to_account_model,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound("Provider account not found".to_string()))
+}
+
+/// Deletes an account along with the VPS links that use it.
+pub async fn delete_provider_account(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute(
+        "DELETE FROM provider_accounts WHERE id = ? AND user_id = ?",
+        params![id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("Provider account not found".to_string()));
+    }
+    conn.execute("DELETE FROM vps_provider_links WHERE account_id = ?", params![id])?;
+    Ok(())
+}
+
+pub async fn get_vps_provider_link(pool: DuckDbPool, vps_id: i32) -> Result<Option<VpsProviderLink>, AppError> {
+    let conn = pool.get()?;
+    let link = conn
+        .query_row(
+            "SELECT account_id, provider_server_id FROM vps_provider_links WHERE vps_id = ?",
+            params![vps_id],
+            |row| {
+                Ok(VpsProviderLink {
+                    account_id: row.get(0)?,
+                    provider_server_id: row.get(1)?,
+                })
+            },
+        )
+        .optional()?;
+    Ok(link)
+}
+
+/// Links a VPS to a server of one of the user's provider accounts.
+pub async fn set_vps_provider_link(
+    pool: DuckDbPool,
+    user_id: i32,
+    vps_id: i32,
+    link: VpsProviderLink,
+) -> Result<VpsProviderLink, AppError> {
+    let provider_server_id = link.provider_server_id.trim();
+    if provider_server_id.is_empty() {
+        return Err(AppError::InvalidInput("Provider server id must not be empty".to_string()));
+    }
+    let conn = pool.get()?;
+    let owned = conn
+        .query_row(
+            "SELECT 1 FROM provider_accounts WHERE id = ? AND user_id = ?",
+            params![link.account_id, user_id],
+            |_| Ok(()),
+        )
+        .optional()?;
+    if owned.is_none() {
+        return Err(AppError::InvalidInput(format!("Provider account {} not found", link.account_id)));
+    }
+    conn.execute(
+        "INSERT INTO vps_provider_links (vps_id, account_id, provider_server_id, updated_at)
+         VALUES (?, ?, ?, ?)
+         ON CONFLICT (vps_id) DO UPDATE SET
+             account_id = excluded.account_id,
+             provider_server_id = excluded.provider_server_id,
+             updated_at = excluded.updated_at",
+        params![vps_id, link.account_id, provider_server_id, Utc::now()],
+    )?;
+    Ok(VpsProviderLink {
+        account_id: link.account_id,
+        provider_server_id: provider_server_id.to_string(),
+    })
+}
+
+pub async fn delete_vps_provider_link(pool: DuckDbPool, vps_id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    conn.execute("DELETE FROM vps_provider_links WHERE vps_id = ?", params![vps_id])?;
+    Ok(())
+}
+
+/// Loads the provider and decrypted token for a VPS, `None` if it isn't linked.
+pub async fn get_power_target(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    vps_id: i32,
+) -> Result<Option<PowerTarget>, AppError> {
+    let conn = pool.get()?;
+    let row = conn
+        .query_row(
+            "SELECT a.provider, a.token, l.provider_server_id
+             FROM vps_provider_links l
+             JOIN provider_accounts a ON a.id = l.account_id
+             WHERE l.vps_id = ?",
+            params![vps_id],
+            |row| Ok((row.get::<_, String>(0)?, row.get::<_, Vec<u8>>(1)?, row.get::<_, String>(2)?)),
+        )
+        .optional()?;
+    let Some((provider, encrypted_token, provider_server_id)) = row else {
+        return Ok(None);
+    };
+    let provider = ProviderKind::parse(&provider)
+        .ok_or_else(|| AppError::InternalServerError(format!("Unknown provider '{provider}'")))?;
+    let token = encryption_service
+        .decrypt(&encrypted_token)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    let token = String::from_utf8(token).map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    Ok(Some(PowerTarget {
+        provider,
+        token,
+        provider_server_id,
+    }))
+}
+
+/// Records a requested power action and returns it with the code that confirms it.
+pub async fn request_power_action(
+    pool: DuckDbPool,
+    user_id: i32,
+    vps_id: i32,
+    action: PowerAction,
+    agent_online: bool,
+    expires_at: DateTime<Utc>,
+) -> Result<(vps_power_action::Model, String), AppError> {
+    let code = Uuid::new_v4().simple().to_string()[..8].to_string();
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE vps_power_actions SET status = ?, confirmation_code = NULL WHERE status = ? AND expires_at < ?",
+        params![STATUS_EXPIRED, STATUS_PENDING, Utc::now()],
+    )?;
+    let requested = conn.query_row(
+        "INSERT INTO vps_power_actions (vps_id, user_id, action, status, confirmation_code, agent_online, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        params![vps_id, user_id, action.as_str(), STATUS_PENDING, code, agent_online, expires_at],
+        row_to_power_action_model,
+    )?;
+    Ok((requested, code))
+}
+
+/// Consumes a confirmation code. Codes work once, before they expire, for the
+/// user, VPS and action they were issued for.
+pub async fn confirm_power_action(
+    pool: DuckDbPool,
+    user_id: i32,
+    vps_id: i32,
+    action: PowerAction,
+    code: &str,
+) -> Result<Option<vps_power_action::Model>, AppError> {
+    let conn = pool.get()?;
+    let confirmed = conn
+        .query_row(
+            "UPDATE vps_power_actions SET status = ?, confirmation_code = NULL
+             WHERE vps_id = ? AND user_id = ? AND action = ? AND confirmation_code = ?
+               AND status = ? AND expires_at >= ?
+             RETURNING *",
+            params![STATUS_CONFIRMED, vps_id, user_id, action.as_str(), code, STATUS_PENDING, Utc::now()],
+            row_to_power_action_model,
+        )
+        .optional()?;
+    Ok(confirmed)
+}
+
+/// Records the provider's answer to a confirmed action.
+pub async fn complete_power_action(
+    pool: DuckDbPool,
+    id: i32,
+    error: Option<String>,
+) -> Result<vps_power_action::Model, AppError> {
+    let status = if error.is_some() { STATUS_FAILED } else { STATUS_SUCCEEDED };
+    let conn = pool.get()?;
+    let completed = conn.query_row(
+        "UPDATE vps_power_actions SET status = ?, completed_at = ?, error = ? WHERE id = ? RETURNING *",
+        params![status, Utc::now(), error, id],
+        row_to_power_action_model,
+    )?;
+    Ok(completed)
+}
+
+/// Power actions of a VPS, newest first.
+pub async fn get_power_actions_for_vps(
+    pool: DuckDbPool,
+    vps_id: i32,
+    limit: u32,
+) -> Result<Vec<vps_power_action::Model>, AppError> {
+    let conn = pool.get()?;
+    let actions = conn
+        .prepare("SELECT * FROM vps_power_actions WHERE vps_id = ? ORDER BY requested_at DESC LIMIT ?")?
+        .query_map(params![vps_id, limit], row_to_power_action_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(actions)
+}
@@ -0,0 +1,301 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, OptionalExt, Result as DuckDbResult, Row};
+use uuid::Uuid;
+
+use crate::db::duckdb_service::uptime_service::STATUS_OFFLINE;
+use crate::db::duckdb_service::vps_service::row_to_vps_model;
+use crate::db::duckdb_service::{json_from_row, organization_service, vps_group_service, DuckDbPool};
+use crate::db::entities::{provisioning_token, vps};
+use crate::web::error::AppError;
+
+/// Prefix of provisioning tokens, so they are recognisable in agent configs.
+pub const TOKEN_PREFIX: &str = "npt_";
+
+fn row_to_provisioning_token_model(row: &Row) -> DuckDbResult<provisioning_token::Model> {
+    Ok(provisioning_token::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        organization_id: row.get("organization_id")?,
+        name: row.get("name")?,
+        token: row.get("token")?,
+        group: row.get("group")?,
+        is_ephemeral: row.get("is_ephemeral")?,
+        tag_ids: json_from_row(row, "tag_ids")?
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default(),
+        max_uses: row.get("max_uses")?,
+        expires_at: row.get("expires_at")?,
+        use_count: row.get("use_count")?,
+        created_at: row.get("created_at")?,
+        last_used_at: row.get("last_used_at")?,
+    })
+}
+
+/// Rules a provisioning token is created with.
+#[derive(Debug, Clone, Default)]
+pub struct ProvisioningTokenRules<'a> {
+    pub group: Option<&'a str>,
+    pub is_ephemeral: bool,
+    pub tag_ids: &'a [i32],
+    pub max_uses: Option<i32>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+pub async fn create_provisioning_token(
+    pool: DuckDbPool,
+    user_id: i32,
+    name: &str,
+    rules: ProvisioningTokenRules<'_>,
+) -> Result<provisioning_token::Model, AppError> {
+    let conn = pool.get()?;
+    if !rules.tag_ids.is_empty() {
+        let placeholders = rules.tag_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
+        let sql = format!(
+            "SELECT COUNT(DISTINCT id) FROM tags WHERE {} AND id IN ({placeholders})",
+            organization_service::org_scope("organization_id")
+        );
+        let mut params_vec: Vec<&dyn duckdb::ToSql> = vec![&user_id];
+        params_vec.extend(rules.tag_ids.iter().map(|id| id as &dyn duckdb::ToSql));
+        let owned: i64 = conn.query_row(&sql, &params_vec[..], |row| row.get(0))?;
+        let mut distinct = rules.tag_ids.to_vec();
+        distinct.sort_unstable();
+        distinct.dedup();
+        if owned != distinct.len() as i64 {
+            return Err(AppError::NotFound("Tag not found".to_string()));
+        }
+    }
+
+    let token = format!("{TOKEN_PREFIX}{}", Uuid::new_v4().simple());
+    let tag_ids = serde_json::to_string(rules.tag_ids)?;
+    let organization_id = organization_service::active_organization_id(&conn, user_id)?;
+    let model = conn.query_row(
+        r#"INSERT INTO provisioning_tokens (user_id, organization_id, name, token, "group", is_ephemeral, tag_ids, max_uses, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *"#,
+        params![
+            user_id,
+            organization_id,
+            name,
+            token,
+            rules.group,
+            rules.is_ephemeral,
+            tag_ids,
+            rules.max_uses,
+            rules.expires_at
+        ],
+        row_to_provisioning_token_model,
+    )?;
+    Ok(model)
+}
+
+pub async fn get_provisioning_tokens_for_user(
+    pool: DuckDbPool,
+    user_id: i32,
+) -> Result<Vec<provisioning_token::Model>, AppError> {
+    let conn = pool.get()?;
+    let tokens = conn
+        .prepare("SELECT * FROM provisioning_tokens WHERE user_id = ? ORDER BY created_at DESC")?
+        .query_map(params![user_id], row_to_provisioning_token_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tokens)
+}
+
+pub async fn get_provisioning_token_by_value(
+    pool: DuckDbPool,
+    token: &str,
+) -> Result<Option<provisioning_token::Model>, AppError> {
+    let conn = pool.get()?;
+    let token = conn
+        .query_row(
+            "SELECT * FROM provisioning_tokens WHERE token = ?",
+            params![token],
+            row_to_provisioning_token_model,
+        )
+        .optional()?;
+    Ok(token)
+}
+
+/// Revokes a provisioning token. VPS already registered with it keep working.
+pub async fn delete_provisioning_token(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute(
+        "DELETE FROM provisioning_tokens WHERE id = ? AND user_id = ?",
+        params![id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("Provisioning token not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Why a token can't register another VPS, if it can't.
+pub fn check_token_usable(token: &provisioning_token::Model, now: DateTime<Utc>) -> Result<(), String> {
+    if matches!(token.expires_at, Some(expires_at) if expires_at <= now) {
+        return Err("Provisioning token has expired.".to_string());
+    }
+    if matches!(token.max_uses, Some(max_uses) if token.use_count >= max_uses) {
+        return Err("Provisioning token has reached its maximum number of uses.".to_string());
+    }
+    Ok(())
+}
+
+/// Enrollment through `/api/enroll` only takes single-use tokens, so a leaked
+/// install command can't register more than one machine.
+pub fn check_enrollment_token(token: &provisioning_token::Model) -> Result<(), String> {
+    if token.max_uses != Some(1) {
+        return Err("Enrollment requires a single-use enrollment token.".to_string());
+    }
+    Ok(())
+}
+
+/// Creates a VPS for an agent presenting a provisioning token, owned by the
+/// token's user in the token's organization and carrying its group, tags and ephemeral flag. Fails with
+/// `Unauthorized` if the token is unknown, expired or used up, or isn't a
+/// single-use token when `enrollment` is set.
+pub async fn register_vps_with_token(
+    pool: DuckDbPool,
+    token: &str,
+    name: &str,
+    enrollment: bool,
+) -> Result<vps::Model, AppError> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    let now = Utc::now();
+
+    let provisioning_token = tx
+        .query_row(
+            "SELECT * FROM provisioning_tokens WHERE token = ?",
+            params![token],
+            row_to_provisioning_token_model,
+        )
+        .optional()?
+        .ok_or_else(|| AppError::Unauthorized("Invalid provisioning token.".to_string()))?;
+    if enrollment {
+        check_enrollment_token(&provisioning_token).map_err(AppError::Unauthorized)?;
+    }
+    check_token_usable(&provisioning_token, now).map_err(AppError::Unauthorized)?;
+
+    // Conditional, so two registrations racing for the last use can't both succeed.
+    let consumed = tx.execute(
+        "UPDATE provisioning_tokens SET use_count = use_count + 1, last_used_at = ?
+         WHERE id = ? AND (max_uses IS NULL OR use_count < max_uses)",
+        params![now, provisioning_token.id],
+    )?;
+    if consumed == 0 {
+        return Err(AppError::Unauthorized(
+            "Provisioning token has reached its maximum number of uses.".to_string(),
+        ));
+    }
+    let organization_id = match provisioning_token.organization_id {
+        Some(id) => id,
+        None => organization_service::active_organization_id(&tx, provisioning_token.user_id)?,
+    };
+    let mut vps_model = tx.query_row(
+        r#"INSERT INTO vps (user_id, organization_id, name, agent_secret, status, created_at, updated_at, "group", config_status, is_ephemeral)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *"#,
+        params![
+            provisioning_token.user_id,
+            organization_id,
+            name,
+            Uuid::new_v4().to_string(),
+            "pending",
+            now,
+            now,
+            provisioning_token.group,
+            "unknown",
+            provisioning_token.is_ephemeral,
+        ],
+        row_to_vps_model,
+    )?;
+    let group_id = match &provisioning_token.group {
+        Some(path) => vps_group_service::ensure_group_path(&tx, organization_id, path)?,
+        None => None,
+    };
+    if group_id.is_some() {
+        vps_group_service::assign_vps(&tx, organization_id, vps_model.id, group_id)?;
+        vps_model.group_id = group_id;
+    }
+    // Tags deleted since the token was created are skipped.
+    for tag_id in &provisioning_token.tag_ids {
+        tx.execute(
+            "INSERT INTO vps_tags (vps_id, tag_id)
+             SELECT ?, id FROM tags WHERE id = ? AND organization_id = ?
+             ON CONFLICT (vps_id, tag_id) DO NOTHING",
+            params![vps_model.id, tag_id, organization_id],
+        )?;
+    }
+    tx.commit()?;
+    Ok(vps_model)
+}
+
+/// Archives ephemeral VPS whose last status event is going offline before
+/// `offline_before`. Returns the archived VPS IDs.
+pub async fn archive_offline_ephemeral_vps(
+    pool: DuckDbPool,
+    offline_before: DateTime<Utc>,
+) -> Result<Vec<i32>, AppError> {
+    let conn = pool.get()?;
+    let archived = conn
+        .prepare(
+            "UPDATE vps SET archived_at = ?
+             WHERE is_ephemeral AND archived_at IS NULL AND id IN (
+                 SELECT vps_id FROM (
+                     SELECT vps_id, time, status FROM vps_status_events
+                     QUALIFY row_number() OVER (PARTITION BY vps_id ORDER BY time DESC, id DESC) = 1
+                 ) WHERE status = ? AND time <= ?
+             )
+             RETURNING id",
+        )?
+        .query_map(params![Utc::now(), STATUS_OFFLINE, offline_before], |row| row.get(0))?
+        .collect::<Result<Vec<i32>, _>>()?;
+    Ok(archived)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn token(max_uses: Option<i32>, use_count: i32, expires_at: Option<DateTime<Utc>>) -> provisioning_token::Model {
+        provisioning_token::Model {
+            id: 1,
+            user_id: 1,
+            organization_id: Some(1),
+            name: "fleet".to_string(),
+            token: format!("{TOKEN_PREFIX}test"),
+            group: None,
+            is_ephemeral: true,
+            tag_ids: Vec::new(),
+            max_uses,
+            expires_at,
+            use_count,
+            created_at: Utc::now(),
+            last_used_at: None,
+        }
+    }
+
+    #[test]
+    fn unlimited_token_is_usable() {
+        assert!(check_token_usable(&token(None, 1000, None), Utc::now()).is_ok());
+    }
+
+    #[test]
+    fn max_uses_is_enforced() {
+        let now = Utc::now();
+        assert!(check_token_usable(&token(Some(2), 1, None), now).is_ok());
+        assert!(check_token_usable(&token(Some(2), 2, None), now).is_err());
+    }
+
+    #[test]
+    fn enrollment_needs_a_single_use_token() {
+        assert!(check_enrollment_token(&token(Some(1), 0, None)).is_ok());
+        assert!(check_enrollment_token(&token(Some(5), 0, None)).is_err());
+        assert!(check_enrollment_token(&token(None, 0, None)).is_err());
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        let now = Utc::now();
+        assert!(check_token_usable(&token(None, 0, Some(now + Duration::hours(1))), now).is_ok());
+        assert!(check_token_usable(&token(None, 0, Some(now - Duration::hours(1))), now).is_err());
+    }
+}
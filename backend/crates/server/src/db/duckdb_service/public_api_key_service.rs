@@ -0,0 +1,79 @@
+use chrono::Utc;
+use duckdb::{params, OptionalExt, Result as DuckDbResult, Row};
+use uuid::Uuid;
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::public_api_key;
+use crate::web::error::AppError;
+
+/// Prefix of public read-only keys, distinct from full API credentials.
+pub const KEY_PREFIX: &str = "npk_";
+
+fn row_to_public_api_key_model(row: &Row) -> DuckDbResult<public_api_key::Model> {
+    Ok(public_api_key::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        name: row.get("name")?,
+        key: row.get("key")?,
+        created_at: row.get("created_at")?,
+        last_used_at: row.get("last_used_at")?,
+    })
+}
+
+pub async fn create_public_api_key(
+    pool: DuckDbPool,
+    user_id: i32,
+    name: &str,
+) -> Result<public_api_key::Model, AppError> {
+    let conn = pool.get()?;
+    let key = format!("{KEY_PREFIX}{}", Uuid::new_v4().simple());
+    let model = conn.query_row(
+        "INSERT INTO public_api_keys (user_id, name, key) VALUES (?, ?, ?) RETURNING *",
+        params![user_id, name, key],
+        row_to_public_api_key_model,
+    )?;
+    Ok(model)
+}
+
+pub async fn get_public_api_keys_for_user(
+    pool: DuckDbPool,
+    user_id: i32,
+) -> Result<Vec<public_api_key::Model>, AppError> {
+    let conn = pool.get()?;
+    let keys = conn
+        .prepare("SELECT * FROM public_api_keys WHERE user_id = ? ORDER BY created_at DESC")?
+        .query_map(params![user_id], row_to_public_api_key_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(keys)
+}
+
+pub async fn delete_public_api_key(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute(
+        "DELETE FROM public_api_keys WHERE id = ? AND user_id = ?",
+        params![id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("Public API key not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Looks up a presented key and records its use.
+pub async fn authenticate_public_api_key(
+    pool: DuckDbPool,
+    key: &str,
+) -> Result<Option<public_api_key::Model>, AppError> {
+    if !key.starts_with(KEY_PREFIX) {
+        return Ok(None);
+    }
+    let conn = pool.get()?;
+    let model = conn
+        .query_row(
+            "UPDATE public_api_keys SET last_used_at = ? WHERE key = ? RETURNING *",
+            params![Utc::now(), key],
+            row_to_public_api_key_model,
+        )
+        .optional()?;
+    Ok(model)
+}
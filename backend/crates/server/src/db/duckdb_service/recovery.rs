@@ -0,0 +1,211 @@
+//! Opening the database at startup without panicking on a bad file.
+//!
+//! The primary file is opened and checked first. If that fails because the
+//! file is corrupt, the operator can confirm a restore of the newest startup
+//! backup (`--restore-latest-backup`). Otherwise the server starts degraded:
+//! it serves the newest backup (or an empty schema) read-only, so agents can
+//! still connect and live data keeps flowing to dashboards while nothing is
+//! persisted.
+
+use chrono::Utc;
+use duckdb::{AccessMode, Config, Connection, DuckdbConnectionManager};
+use std::fs;
+use std::path::{Path, PathBuf};
+use tracing::{error, info, warn};
+
+use super::DuckDbPool;
+
+/// Startup backups kept in the backup directory.
+const STARTUP_BACKUPS_KEPT: usize = 3;
+const BACKUP_PREFIX: &str = "nodenexus-";
+const BACKUP_SUFFIX: &str = ".db";
+
+/// Tables whose scan exercises most of the storage a running server needs.
+const INTEGRITY_CHECK_TABLES: &[&str] = &["users", "vps", "alert_rules", "notification_channels"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StorageMode {
+    Healthy,
+    /// Storage is unavailable; `source` describes what is served read-only instead.
+    Degraded { reason: String, source: String },
+}
+
+impl StorageMode {
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, StorageMode::Healthy)
+    }
+}
+
+pub struct OpenedStorage {
+    pub pool: DuckDbPool,
+    pub mode: StorageMode,
+}
+
+/// DuckDB refuses to open a file another process holds open for writing.
+fn is_lock_error(message: &str) -> bool {
+    message.contains("Could not set lock") || message.contains("Conflicting lock")
+}
+
+/// Opens a file-backed pool and checks the tables can actually be read.
+fn open_checked(path: &Path, config: Config) -> Result<DuckDbPool, String> {
+    let manager = DuckdbConnectionManager::file_with_flags(path, config).map_err(|e| e.to_string())?;
+    let pool = r2d2::Pool::new(manager).map_err(|e| e.to_string())?;
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    check_integrity(&conn).map_err(|e| format!("integrity check failed: {e}"))?;
+    Ok(pool)
+}
+
+/// Reads the database metadata and scans core tables. A fresh database
+/// without tables passes; migrations create them.
+fn check_integrity(conn: &Connection) -> Result<(), duckdb::Error> {
+    conn.query_row("PRAGMA database_size", [], |_| Ok(()))?;
+    for table in INTEGRITY_CHECK_TABLES {
+        let exists: bool = conn.query_row(
+            "SELECT COUNT(*) > 0 FROM information_schema.tables WHERE table_name = ?",
+            [table],
+            |row| row.get(0),
+        )?;
+        if exists {
+            conn.query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| row.get::<_, i64>(0))?;
+        }
+    }
+    Ok(())
+}
+
+fn backup_files(backup_dir: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(backup_dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|e| e.path()))
+                .filter(|path| {
+                    path.file_name()
+                        .and_then(|name| name.to_str())
+                        .is_some_and(|name| name.starts_with(BACKUP_PREFIX) && name.ends_with(BACKUP_SUFFIX))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    // Names embed a sortable UTC timestamp.
+    files.sort();
+    files
+}
+
+/// Copies the open database into a new startup backup and prunes old ones.
+fn create_startup_backup(pool: &DuckDbPool, backup_dir: &Path) -> Result<PathBuf, String> {
+    fs::create_dir_all(backup_dir).map_err(|e| e.to_string())?;
+    let path = backup_dir.join(format!(
+        "{BACKUP_PREFIX}{}{BACKUP_SUFFIX}",
+        Utc::now().format("%Y%m%dT%H%M%SZ")
+    ));
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    let escaped = path.to_string_lossy().replace('\'', "''");
+    conn.execute_batch(&format!(
+        "ATTACH '{escaped}' AS startup_backup;
+         COPY FROM DATABASE main TO startup_backup;
+         DETACH startup_backup;"
+    ))
+    .map_err(|e| e.to_string())?;
+
+    let files = backup_files(backup_dir);
+    for old in files.iter().take(files.len().saturating_sub(STARTUP_BACKUPS_KEPT)) {
+        if let Err(e) = fs::remove_file(old) {
+            warn!(path = %old.display(), error = %e, "Failed to remove old startup backup.");
+        }
+    }
+    Ok(path)
+}
+
+/// Moves the unreadable file aside and copies the newest backup in its place.
+fn restore_latest_backup(db_path: &Path, backup: &Path) -> Result<(), String> {
+    let suffix = format!("corrupt-{}", Utc::now().format("%Y%m%dT%H%M%SZ"));
+    let quarantined = db_path.with_extension(format!("db.{suffix}"));
+    fs::rename(db_path, &quarantined).map_err(|e| format!("failed to move corrupt database aside: {e}"))?;
+    let wal = db_path.with_extension("db.wal");
+    if wal.exists() {
+        let _ = fs::rename(&wal, db_path.with_extension(format!("db.wal.{suffix}")));
+    }
+    fs::copy(backup, db_path).map_err(|e| format!("failed to copy backup: {e}"))?;
+    warn!(
+        backup = %backup.display(),
+        quarantined = %quarantined.display(),
+        "Restored the database from the latest startup backup."
+    );
+    Ok(())
+}
+
+/// An empty in-memory database with the schema, so handlers find their
+/// tables even when no backup exists.
+fn open_empty_schema() -> Result<DuckDbPool, String> {
+    let manager = DuckdbConnectionManager::memory().map_err(|e| e.to_string())?;
+    let pool = r2d2::Pool::new(manager).map_err(|e| e.to_string())?;
+    let conn = pool.get().map_err(|e| e.to_string())?;
+    super::DuckDBService::initialize_db(&conn).map_err(|e| e.to_string())?;
+    Ok(pool)
+}
+
+/// Opens the primary database, falling back as described in the module docs.
+/// Only fails if not even an in-memory database can be created.
+pub fn open_storage(db_path: &Path, backup_dir: &Path, restore_confirmed: bool) -> Result<OpenedStorage, String> {
+    let reason = match open_checked(db_path, Config::default()) {
+        Ok(pool) => {
+            match create_startup_backup(&pool, backup_dir) {
+                Ok(path) => info!(path = %path.display(), "Created startup backup."),
+                Err(e) => warn!(error = %e, "Failed to create startup backup."),
+            }
+            return Ok(OpenedStorage { pool, mode: StorageMode::Healthy });
+        }
+        Err(e) => e,
+    };
+    error!(path = %db_path.display(), error = %reason, "Failed to open the database.");
+
+    let latest_backup = backup_files(backup_dir).pop();
+    if is_lock_error(&reason) {
+        error!("The database is locked by another process; stop it or point DATA_DIR elsewhere. Starting degraded.");
+    } else if let Some(backup) = latest_backup.as_ref().filter(|_| restore_confirmed) {
+        match restore_latest_backup(db_path, backup).and_then(|_| open_checked(db_path, Config::default())) {
+            Ok(pool) => return Ok(OpenedStorage { pool, mode: StorageMode::Healthy }),
+            Err(e) => error!(error = %e, "Restoring the latest backup failed. Starting degraded."),
+        }
+    } else if let Some(backup) = &latest_backup {
+        error!(
+            backup = %backup.display(),
+            "Restart with --restore-latest-backup to replace the database with this backup. Starting degraded."
+        );
+    }
+
+    if let Some(backup) = latest_backup {
+        let read_only = Config::default().access_mode(AccessMode::ReadOnly).map_err(|e| e.to_string())?;
+        match open_checked(&backup, read_only) {
+            Ok(pool) => {
+                return Ok(OpenedStorage {
+                    pool,
+                    mode: StorageMode::Degraded {
+                        reason,
+                        source: format!("read-only backup {}", backup.display()),
+                    },
+                });
+            }
+            Err(e) => error!(backup = %backup.display(), error = %e, "Latest backup is not readable either."),
+        }
+    }
+    Ok(OpenedStorage {
+        pool: open_empty_schema()?,
+        mode: StorageMode::Degraded {
+            reason,
+            source: "empty in-memory database".to_string(),
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_lock_errors() {
+        assert!(is_lock_error(
+            "IO Error: Could not set lock on file \"data/nodenexus.db\": Conflicting lock is held"
+        ));
+        assert!(!is_lock_error("IO Error: The file is not a valid DuckDB database file!"));
+    }
+}
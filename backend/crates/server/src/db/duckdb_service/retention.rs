@@ -0,0 +1,73 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// How many days each metric resolution is kept. `0` keeps it forever.
+///
+/// Rollups are built from the next finer table, so a resolution only has data
+/// for as long as its source had when it was aggregated; the task manager runs
+/// hourly, well within any sensible raw retention.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetentionPolicy {
+    /// `performance_metrics`, the per-disk/per-interface rows and pushed
+    /// application metrics, none of which have rollups.
+    pub raw_days: u32,
+    pub summary_1m_days: u32,
+    pub summary_1h_days: u32,
+    pub summary_1d_days: u32,
+}
+
+impl Default for RetentionPolicy {
+    fn default() -> Self {
+        Self {
+            raw_days: 7,
+            summary_1m_days: 90,
+            summary_1h_days: 730,
+            summary_1d_days: 0,
+        }
+    }
+}
+
+impl RetentionPolicy {
+    /// Rows older than this are deleted, `None` when kept forever.
+    pub fn cutoff(days: u32, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        (days > 0).then(|| now - Duration::days(i64::from(days)))
+    }
+
+    /// Whether a table kept for `days` still holds data from `start`.
+    pub fn covers(days: u32, start: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        Self::cutoff(days, now).is_none_or(|cutoff| start >= cutoff)
+    }
+
+    /// Tables to clean up with their retention, in the order they are pruned.
+    pub fn tables(&self) -> [(&'static str, u32); 12] {
+        [
+            ("performance_metrics", self.raw_days),
+            ("performance_metrics_summary_1m", self.summary_1m_days),
+            ("performance_metrics_summary_1h", self.summary_1h_days),
+            ("performance_metrics_summary_1d", self.summary_1d_days),
+            ("disk_io_metrics", self.raw_days),
+            ("network_interface_metrics", self.raw_days),
+            ("gpu_metrics", self.raw_days),
+            ("disk_health_metrics", self.raw_days),
+            ("temperature_metrics", self.raw_days),
+            ("disk_usage_metrics", self.raw_days),
+            ("generic_metrics", self.raw_days),
+            ("log_matches", self.raw_days),
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn zero_days_keeps_forever() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap();
+        assert_eq!(RetentionPolicy::cutoff(0, now), None);
+        assert!(RetentionPolicy::covers(0, now - Duration::days(10_000), now));
+        assert_eq!(RetentionPolicy::cutoff(7, now), Some(now - Duration::days(7)));
+        assert!(RetentionPolicy::covers(7, now - Duration::days(7), now));
+        assert!(!RetentionPolicy::covers(7, now - Duration::days(8), now));
+    }
+}
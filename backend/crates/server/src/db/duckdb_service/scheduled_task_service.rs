@@ -0,0 +1,346 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, OptionalExt, Result as DuckDbResult, Row};
+use uuid::Uuid;
+
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
+use crate::db::entities::{scheduled_task, scheduled_task_run};
+use crate::services::cron::CronSchedule;
+use crate::web::error::AppError;
+use crate::web::models::schedule_models::{CreateScheduledTaskRequest, UpdateScheduledTaskRequest};
+
+pub const RUN_STATUS_DISPATCHED: &str = "dispatched";
+pub const RUN_STATUS_SKIPPED: &str = "skipped";
+pub const RUN_STATUS_FAILED: &str = "failed";
+
+const DEFAULT_RUNS_LIMIT: u32 = 50;
+const MAX_RUNS_LIMIT: u32 = 500;
+
+fn json_column<T: serde::de::DeserializeOwned>(row: &Row, column: &str) -> DuckDbResult<T> {
+    let raw: String = row.get(column)?;
+    serde_json::from_str(&raw).map_err(|e| {
+        duckdb::Error::FromSqlConversionFailure(0, duckdb::types::Type::Text, Box::new(e))
+    })
+}
+
+fn row_to_scheduled_task_model(row: &Row) -> DuckDbResult<scheduled_task::Model> {
+    Ok(scheduled_task::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        name: row.get("name")?,
+        cron_expression: row.get("cron_expression")?,
+        script_id: row.get("script_id")?,
+        target_vps_ids: json_column(row, "target_vps_ids")?,
+        target_tag_ids: json_column(row, "target_tag_ids")?,
+        enabled: row.get("enabled")?,
+        next_run_at: row.get("next_run_at")?,
+        last_run_at: row.get("last_run_at")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn row_to_scheduled_task_run_model(row: &Row) -> DuckDbResult<scheduled_task_run::Model> {
+    Ok(scheduled_task_run::Model {
+        id: row.get("id")?,
+        scheduled_task_id: row.get("scheduled_task_id")?,
+        scheduled_for: row.get("scheduled_for")?,
+        started_at: row.get("started_at")?,
+        status: row.get("status")?,
+        batch_command_id: row.get("batch_command_id")?,
+        target_vps_count: row.get("target_vps_count")?,
+        message: row.get("message")?,
+    })
+}
+
+fn parse_cron(expression: &str) -> Result<CronSchedule, AppError> {
+    CronSchedule::parse(expression).map_err(|e| AppError::InvalidInput(e.to_string()))
+}
+
+/// When an enabled schedule next fires after `now`; `None` while disabled.
+fn next_run_at(schedule: &CronSchedule, enabled: bool, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    enabled.then(|| schedule.next_after(now)).flatten()
+}
+
+fn ensure_unique_name(
+    conn: &duckdb::Connection,
+    user_id: i32,
+    name: &str,
+    exclude_id: Option<i32>,
+) -> Result<(), AppError> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM scheduled_tasks WHERE user_id = ? AND name = ? AND id IS DISTINCT FROM ?",
+        params![user_id, name, exclude_id],
+        |row| row.get(0),
+    )?;
+    if count > 0 {
+        return Err(AppError::Conflict(format!("A schedule named '{name}' already exists.")));
+    }
+    Ok(())
+}
+
+/// Checks the script belongs to the user and every target is visible in their organization.
+fn validate_targets(
+    conn: &duckdb::Connection,
+    user_id: i32,
+    script_id: i32,
+    vps_ids: &[i32],
+    tag_ids: &[i32],
+) -> Result<(), AppError> {
+    if vps_ids.is_empty() && tag_ids.is_empty() {
+        return Err(AppError::InvalidInput("A schedule needs at least one target VPS or tag".to_string()));
+    }
+    let script_owned = conn
+        .query_row(
+            "SELECT 1 FROM command_scripts WHERE id = ? AND user_id = ?",
+            params![script_id, user_id],
+            |_| Ok(()),
+        )
+        .optional()?;
+    if script_owned.is_none() {
+        return Err(AppError::InvalidInput(format!("Script {script_id} not found")));
+    }
+    for vps_id in vps_ids {
+        let visible = conn
+            .query_row(
+                &format!("SELECT 1 FROM vps WHERE id = ? AND {}", organization_service::org_scope("organization_id")),
+                params![vps_id, user_id],
+                |_| Ok(()),
+            )
+            .optional()?;
+        if visible.is_none() {
+            return Err(AppError::InvalidInput(format!("VPS {vps_id} not found")));
+        }
+    }
+    for tag_id in tag_ids {
+        let visible = conn
+            .query_row(
+                &format!("SELECT 1 FROM tags WHERE id = ? AND {}", organization_service::org_scope("organization_id")),
+                params![tag_id, user_id],
+                |_| Ok(()),
+            )
+            .optional()?;
+        if visible.is_none() {
+            return Err(AppError::InvalidInput(format!("Tag {tag_id} not found")));
+        }
+    }
+    Ok(())
+}
+
+pub async fn get_scheduled_tasks_for_user(
+    pool: DuckDbPool,
+    user_id: i32,
+) -> Result<Vec<scheduled_task::Model>, AppError> {
+    let conn = pool.get()?;
+    let tasks = conn
+        .prepare("SELECT * FROM scheduled_tasks WHERE user_id = ? ORDER BY name")?
+        .query_map(params![user_id], row_to_scheduled_task_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tasks)
+}
+
+pub async fn get_scheduled_task(
+    pool: DuckDbPool,
+    user_id: i32,
+    id: i32,
+) -> Result<scheduled_task::Model, AppError> {
+    let conn = pool.get()?;
+    conn.query_row(
+        "SELECT * FROM scheduled_tasks WHERE id = ? AND user_id = ?",
+        params![id, user_id],
+        row_to_scheduled_task_model,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound("Schedule not found".to_string()))
+}
+
+pub async fn create_scheduled_task(
+    pool: DuckDbPool,
+    user_id: i32,
+    payload: CreateScheduledTaskRequest,
+) -> Result<scheduled_task::Model, AppError> {
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Name cannot be empty".to_string()));
+    }
+    let schedule = parse_cron(&payload.cron_expression)?;
+    let enabled = payload.enabled.unwrap_or(true);
+
+    let conn = pool.get()?;
+    ensure_unique_name(&conn, user_id, &name, None)?;
+    validate_targets(&conn, user_id, payload.script_id, &payload.target_vps_ids, &payload.target_tag_ids)?;
+    let task = conn.query_row(
+        "INSERT INTO scheduled_tasks (user_id, name, cron_expression, script_id, target_vps_ids, target_tag_ids, enabled, next_run_at)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        params![
+            user_id,
+            name,
+            payload.cron_expression.trim(),
+            payload.script_id,
+            serde_json::to_string(&payload.target_vps_ids)?,
+            serde_json::to_string(&payload.target_tag_ids)?,
+            enabled,
+            next_run_at(&schedule, enabled, Utc::now()),
+        ],
+        row_to_scheduled_task_model,
+    )?;
+    Ok(task)
+}
+
+pub async fn update_scheduled_task(
+    pool: DuckDbPool,
+    user_id: i32,
+    id: i32,
+    payload: UpdateScheduledTaskRequest,
+) -> Result<scheduled_task::Model, AppError> {
+    let existing = get_scheduled_task(pool.clone(), user_id, id).await?;
+
+    let name = payload.name.map(|n| n.trim().to_string()).unwrap_or(existing.name);
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Name cannot be empty".to_string()));
+    }
+    let cron_expression = payload
+        .cron_expression
+        .map(|c| c.trim().to_string())
+        .unwrap_or(existing.cron_expression);
+    let schedule = parse_cron(&cron_expression)?;
+    let script_id = payload.script_id.unwrap_or(existing.script_id);
+    let target_vps_ids = payload.target_vps_ids.unwrap_or(existing.target_vps_ids);
+    let target_tag_ids = payload.target_tag_ids.unwrap_or(existing.target_tag_ids);
+    let enabled = payload.enabled.unwrap_or(existing.enabled);
+
+    let conn = pool.get()?;
+    ensure_unique_name(&conn, user_id, &name, Some(id))?;
+    validate_targets(&conn, user_id, script_id, &target_vps_ids, &target_tag_ids)?;
+    let now = Utc::now();
+    let task = conn.query_row(
+        "UPDATE scheduled_tasks
+         SET name = ?, cron_expression = ?, script_id = ?, target_vps_ids = ?, target_tag_ids = ?,
+             enabled = ?, next_run_at = ?, updated_at = ?
+         WHERE id = ? AND user_id = ? RETURNING *",
+        params![
+            name,
+            cron_expression,
+            script_id,
+            serde_json::to_string(&target_vps_ids)?,
+            serde_json::to_string(&target_tag_ids)?,
+            enabled,
+            next_run_at(&schedule, enabled, now),
+            now,
+            id,
+            user_id,
+        ],
+        row_to_scheduled_task_model,
+    )?;
+    Ok(task)
+}
+
+pub async fn delete_scheduled_task(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute(
+        "DELETE FROM scheduled_tasks WHERE id = ? AND user_id = ?",
+        params![id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("Schedule not found".to_string()));
+    }
+    conn.execute("DELETE FROM scheduled_task_runs WHERE scheduled_task_id = ?", params![id])?;
+    Ok(())
+}
+
+/// The most recent runs of a schedule, newest first.
+pub async fn get_runs_for_task(
+    pool: DuckDbPool,
+    user_id: i32,
+    id: i32,
+    limit: Option<u32>,
+) -> Result<Vec<scheduled_task_run::Model>, AppError> {
+    get_scheduled_task(pool.clone(), user_id, id).await?;
+    let limit = limit.unwrap_or(DEFAULT_RUNS_LIMIT).clamp(1, MAX_RUNS_LIMIT);
+    let conn = pool.get()?;
+    let runs = conn
+        .prepare(
+            "SELECT * FROM scheduled_task_runs WHERE scheduled_task_id = ?
+             ORDER BY scheduled_for DESC, id DESC LIMIT ?",
+        )?
+        .query_map(params![id, limit], row_to_scheduled_task_run_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(runs)
+}
+
+/// Enabled schedules whose next run is at or before `now`.
+pub async fn get_due_tasks(pool: DuckDbPool, now: DateTime<Utc>) -> Result<Vec<scheduled_task::Model>, AppError> {
+    let conn = pool.get()?;
+    let tasks = conn
+        .prepare("SELECT * FROM scheduled_tasks WHERE enabled AND next_run_at <= ? ORDER BY next_run_at")?
+        .query_map(params![now], row_to_scheduled_task_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(tasks)
+}
+
+/// Moves a schedule on to its next run after one came due at `ran_at`.
+pub async fn advance_task(
+    pool: DuckDbPool,
+    id: i32,
+    ran_at: DateTime<Utc>,
+    next_run_at: Option<DateTime<Utc>>,
+) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE scheduled_tasks SET last_run_at = ?, next_run_at = ? WHERE id = ?",
+        params![ran_at, next_run_at, id],
+    )?;
+    Ok(())
+}
+
+pub async fn record_run(
+    pool: DuckDbPool,
+    scheduled_task_id: i32,
+    scheduled_for: DateTime<Utc>,
+    status: &str,
+    batch_command_id: Option<Uuid>,
+    target_vps_count: i32,
+    message: Option<String>,
+) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    conn.execute(
+        "INSERT INTO scheduled_task_runs (scheduled_task_id, scheduled_for, started_at, status, batch_command_id, target_vps_count, message)
+         VALUES (?, ?, ?, ?, ?, ?, ?)",
+        params![scheduled_task_id, scheduled_for, Utc::now(), status, batch_command_id, target_vps_count, message],
+    )?;
+    Ok(())
+}
+
+/// The VPS a run targets: the fixed ones plus everything currently carrying one
+/// of the tags, limited to what the schedule's owner can still see.
+pub async fn resolve_target_vps_ids(pool: DuckDbPool, task: &scheduled_task::Model) -> Result<Vec<i32>, AppError> {
+    let conn = pool.get()?;
+    let vps_placeholders = vec!["?"; task.target_vps_ids.len()].join(",");
+    let tag_placeholders = vec!["?"; task.target_tag_ids.len()].join(",");
+    let mut conditions = Vec::new();
+    if !task.target_vps_ids.is_empty() {
+        conditions.push(format!("v.id IN ({vps_placeholders})"));
+    }
+    if !task.target_tag_ids.is_empty() {
+        conditions.push(format!(
+            "v.id IN (SELECT vps_id FROM vps_tags WHERE tag_id IN ({tag_placeholders}))"
+        ));
+    }
+    if conditions.is_empty() {
+        return Ok(Vec::new());
+    }
+    let sql = format!(
+        "SELECT v.id FROM vps v WHERE {} AND ({}) ORDER BY v.id",
+        organization_service::org_scope("v.organization_id"),
+        conditions.join(" OR ")
+    );
+
+    let mut params_vec: Vec<&dyn duckdb::ToSql> = vec![&task.user_id];
+    for id in task.target_vps_ids.iter().chain(&task.target_tag_ids) {
+        params_vec.push(id);
+    }
+    let ids = conn
+        .prepare(&sql)?
+        .query_map(&params_vec[..], |row| row.get(0))?
+        .collect::<Result<Vec<i32>, _>>()?;
+    Ok(ids)
+}
@@ -3,18 +3,30 @@
 //! This service provides functions for CRUD operations on service monitors,
 //! assigning them to agents/tags, and recording check results.
 
-use crate::db::duckdb_service::DuckDbPool;
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
 use crate::db::entities::{
-    service_monitor,
+    service_monitor, service_monitor_maintenance_window,
 };
 use crate::web::error::AppError;
 use crate::web::models::service_monitor_models::{
-    CreateMonitor, ServiceMonitorDetails, UpdateMonitor,
+    CreateMaintenanceWindow, CreateMonitor, DnsMonitorConfig, HeatmapCell, RegionLatencyPoint, ServiceMonitorDetails,
+    UpdateMonitor,
 };
 use chrono::{DateTime, TimeZone, Utc};
-use nodenexus_common::agent_service::{ServiceMonitorResult, ServiceMonitorTask};
+use nodenexus_common::agent_service::{PingStats, ServiceMonitorResult, ServiceMonitorTask};
 use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Alert rules with this metric type compare the days left until the
+/// certificate of an HTTPS monitor's target expires against the threshold.
+pub const CERT_EXPIRY_DAYS_METRIC_TYPE: &str = "cert_expiry_days";
+
+/// Alert rules with this metric type fire once a monitor failed `threshold`
+/// checks in a row on one agent and resolve after `recovery_threshold`
+/// successful ones; see `alerting::monitor_state`.
+pub const MONITOR_DOWN_METRIC_TYPE: &str = "monitor_down";
+
+pub const DNS_RECORD_TYPES: [&str; 5] = ["A", "AAAA", "CNAME", "MX", "TXT"];
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -22,12 +34,18 @@ pub struct ServiceMonitorPoint {
     pub time: DateTime<Utc>,
     pub monitor_id: i32,
     pub agent_id: i32,
+    /// Share of checks that were up, leaving out those during maintenance; `None`
+    /// for a bucket whose checks all fell in a maintenance window.
     pub is_up: Option<f64>,
     pub latency_ms: Option<f64>,
     pub details: Option<serde_json::Value>,
+    /// For bucketed results, true only when every check in the bucket fell in a maintenance window.
+    pub in_maintenance: Option<bool>,
+    /// Ping monitors only; averaged over the bucket for bucketed results.
+    pub packet_loss_percent: Option<f64>,
 }
 
-use duckdb::{params, params_from_iter, OptionalExt, Result as DuckDbResult, Row};
+use duckdb::{params, params_from_iter, Connection, OptionalExt, Result as DuckDbResult, Row};
 use crate::db::duckdb_service::json_from_row;
 
 // A helper function to generate `(?, ?, ...)` placeholder strings for `IN` clauses.
@@ -41,10 +59,75 @@ fn repeat_vars(count: usize) -> String {
     format!("({s})")
 }
 
+/// Shortest check interval any monitor may use. Organizations can be given a
+/// higher minimum as a quota (`organizations.min_monitor_frequency_seconds`).
+pub const MIN_FREQUENCY_SECONDS: i32 = 10;
+
+/// The shortest interval monitors of `organization_id` may run at.
+fn min_frequency_for_organization(conn: &Connection, organization_id: Option<i32>) -> Result<i32, AppError> {
+    let quota = match organization_id {
+        Some(id) => organization_service::min_monitor_frequency_seconds(conn, id)?,
+        None => None,
+    };
+    Ok(quota.unwrap_or(MIN_FREQUENCY_SECONDS).max(MIN_FREQUENCY_SECONDS))
+}
+
+/// Checks the check interval against the organization's minimum. The timeout
+/// must fit within the interval so checks of one monitor never overlap.
+fn validate_schedule(frequency_seconds: i32, timeout_seconds: i32, min_frequency_seconds: i32) -> Result<(), AppError> {
+    if frequency_seconds < min_frequency_seconds {
+        return Err(AppError::InvalidInput(format!(
+            "Check interval must be at least {min_frequency_seconds} seconds"
+        )));
+    }
+    if timeout_seconds < 1 || timeout_seconds > frequency_seconds {
+        return Err(AppError::InvalidInput(
+            "Timeout must be between 1 second and the check interval".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+/// Checks the type-specific part of a monitor's config before it reaches the agents.
+pub fn validate_monitor_config(
+    monitor_type: &str,
+    target: &str,
+    monitor_config: Option<&serde_json::Value>,
+) -> Result<(), AppError> {
+    if monitor_type != "dns" {
+        return Ok(());
+    }
+    if target.trim().is_empty() || target.contains("://") || target.contains('/') {
+        return Err(AppError::InvalidInput("DNS monitor target must be a domain name.".to_string()));
+    }
+    let config: DnsMonitorConfig = match monitor_config {
+        Some(value) if !value.is_null() => serde_json::from_value(value.clone())
+            .map_err(|e| AppError::InvalidInput(format!("Invalid DNS monitor config: {e}")))?,
+        _ => DnsMonitorConfig::default(),
+    };
+    if let Some(record_type) = &config.record_type {
+        if !DNS_RECORD_TYPES.contains(&record_type.to_ascii_uppercase().as_str()) {
+            return Err(AppError::InvalidInput(format!(
+                "Unsupported DNS record type '{record_type}'. Use one of {}.",
+                DNS_RECORD_TYPES.join(", ")
+            )));
+        }
+    }
+    if let Some(resolver) = config.resolver.as_deref().filter(|r| !r.is_empty()) {
+        if resolver.parse::<std::net::IpAddr>().is_err() && resolver.parse::<std::net::SocketAddr>().is_err() {
+            return Err(AppError::InvalidInput(format!(
+                "DNS resolver '{resolver}' must be an IP address, optionally with a port."
+            )));
+        }
+    }
+    Ok(())
+}
+
 fn row_to_monitor_model(row: &Row) -> DuckDbResult<service_monitor::Model> {
     Ok(service_monitor::Model {
         id: row.get("id")?,
         user_id: row.get("user_id")?,
+        organization_id: row.get("organization_id")?,
         name: row.get("name")?,
         monitor_type: row.get("monitor_type")?,
         target: row.get("target")?,
@@ -63,17 +146,31 @@ pub async fn create_monitor(
     user_id: i32,
     monitor_data: CreateMonitor,
 ) -> Result<service_monitor::Model, AppError> {
+    validate_monitor_config(
+        &monitor_data.monitor_type,
+        &monitor_data.target,
+        monitor_data.monitor_config.as_ref(),
+    )?;
+
     let mut conn = pool.get()?;
     let tx = conn.transaction()?;
 
     let monitor_config_str = serde_json::to_string(&monitor_data.monitor_config)?;
     let assignment_type = monitor_data.assignments.assignment_type.unwrap_or_else(|| "INCLUSIVE".to_string());
 
+    let organization_id = organization_service::active_organization_id(&tx, user_id)?;
+    validate_schedule(
+        monitor_data.frequency_seconds.unwrap_or(60),
+        monitor_data.timeout_seconds.unwrap_or(10),
+        min_frequency_for_organization(&tx, Some(organization_id))?,
+    )?;
+
     let saved_monitor: service_monitor::Model = tx.query_row(
-        "INSERT INTO service_monitors (user_id, name, monitor_type, target, frequency_seconds, timeout_seconds, is_active, monitor_config, assignment_type)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        "INSERT INTO service_monitors (user_id, organization_id, name, monitor_type, target, frequency_seconds, timeout_seconds, is_active, monitor_config, assignment_type)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
         params![
             user_id,
+            organization_id,
             monitor_data.name,
             monitor_data.monitor_type,
             monitor_data.target,
@@ -114,9 +211,12 @@ pub async fn get_monitors_with_details_by_user_id(
 ) -> Result<Vec<ServiceMonitorDetails>, AppError> {
     let conn = pool.get()?;
 
-    // 1. Fetch all monitors for the user
+    // 1. Fetch all monitors in the user's active organization
     let monitors: Vec<service_monitor::Model> = conn
-        .prepare("SELECT * FROM service_monitors WHERE user_id = ?")?
+        .prepare(&format!(
+            "SELECT * FROM service_monitors WHERE {}",
+            organization_service::org_scope("organization_id")
+        ))?
         .query_map(params![user_id], row_to_monitor_model)?
         .collect::<Result<Vec<_>, _>>()?;
 
@@ -194,6 +294,7 @@ pub async fn get_monitors_with_details_by_user_id(
             ServiceMonitorDetails {
                 id: monitor.id,
                 user_id: monitor.user_id,
+                organization_id: monitor.organization_id,
                 name: monitor.name,
                 monitor_type: monitor.monitor_type,
                 target: monitor.target,
@@ -268,6 +369,7 @@ pub async fn get_monitor_details_by_id(
     let details = ServiceMonitorDetails {
         id: monitor.id,
         user_id: monitor.user_id,
+        organization_id: monitor.organization_id,
         name: monitor.name,
         monitor_type: monitor.monitor_type,
         target: monitor.target,
@@ -306,13 +408,31 @@ pub async fn update_monitor(
 
         let tx = conn.transaction()?;
 
-        // Fetch the monitor to ensure it exists and belongs to the user
-        if let Err(duckdb::Error::QueryReturnedNoRows) = tx.query_row::<(), _, _>(
-            "SELECT 1 FROM service_monitors WHERE id = ? AND user_id = ?",
-            params![monitor_id, user_id],
-            |_| Ok(()),
-        ) {
+        // Fetch the monitor to ensure it exists and is in the user's organization
+        let Some(existing) = tx
+            .query_row(
+                &format!(
+                    "SELECT * FROM service_monitors WHERE id = ? AND {}",
+                    organization_service::org_scope("organization_id")
+                ),
+                params![monitor_id, user_id],
+                row_to_monitor_model,
+            )
+            .optional()?
+        else {
             return Err(AppError::NotFound("Monitor not found or permission denied".to_string()));
+        };
+        validate_monitor_config(
+            payload.monitor_type.as_deref().unwrap_or(&existing.monitor_type),
+            payload.target.as_deref().unwrap_or(&existing.target),
+            payload.monitor_config.as_ref().or(existing.monitor_config.as_ref()),
+        )?;
+        if payload.frequency_seconds.is_some() || payload.timeout_seconds.is_some() {
+            validate_schedule(
+                payload.frequency_seconds.unwrap_or(existing.frequency_seconds),
+                payload.timeout_seconds.unwrap_or(existing.timeout_seconds),
+                min_frequency_for_organization(&tx, existing.organization_id)?,
+            )?;
         }
 
         // Dynamically build the UPDATE statement
@@ -358,8 +478,9 @@ pub async fn update_monitor(
 
         if !set_clauses.is_empty() {
             let sql = format!(
-                "UPDATE service_monitors SET {} WHERE id = ? AND user_id = ?",
-                set_clauses.join(", ")
+                "UPDATE service_monitors SET {} WHERE id = ? AND {}",
+                set_clauses.join(", "),
+                organization_service::org_scope("organization_id")
             );
             let mut final_params: Vec<&dyn duckdb::ToSql> = params_vec.iter().map(|p| p as &dyn duckdb::ToSql).collect();
             final_params.push(&monitor_id);
@@ -418,9 +539,19 @@ pub async fn delete_monitor(
 ) -> Result<u64, AppError> {
     let conn = pool.get()?;
     let rows_affected = conn.execute(
-        "DELETE FROM service_monitors WHERE id = ? AND user_id = ?",
+        &format!(
+            "DELETE FROM service_monitors WHERE id = ? AND {}",
+            organization_service::org_scope("organization_id")
+        ),
         params![monitor_id, user_id],
     )?;
+    if rows_affected > 0 {
+        conn.execute(
+            "DELETE FROM service_monitor_maintenance_windows WHERE monitor_id = ?",
+            params![monitor_id],
+        )?;
+        super::service_monitor_slo_service::delete_slos_for_monitor(&conn, monitor_id)?;
+    }
     Ok(rows_affected as u64)
 }
 
@@ -475,15 +606,15 @@ pub async fn get_runnable_monitors_for_vps(
 ) -> Result<Vec<service_monitor::Model>, AppError> {
     let conn = pool.get()?;
 
-    let user_id: i32 = conn.query_row(
-        "SELECT user_id FROM vps WHERE id = ?",
+    let organization_id: Option<i32> = conn.query_row(
+        "SELECT organization_id FROM vps WHERE id = ?",
         params![vps_id],
         |row| row.get(0),
     )?;
 
     let all_active_monitors: Vec<service_monitor::Model> = conn
-        .prepare("SELECT * FROM service_monitors WHERE user_id = ? AND is_active = TRUE")?
-        .query_map(params![user_id], row_to_monitor_model)?
+        .prepare("SELECT * FROM service_monitors WHERE organization_id = ? AND is_active = TRUE")?
+        .query_map(params![organization_id], row_to_monitor_model)?
         .collect::<Result<Vec<_>, _>>()?;
 
     if all_active_monitors.is_empty() {
@@ -545,7 +676,16 @@ pub async fn get_tasks_for_agent(
     pool: DuckDbPool,
     vps_id: i32,
 ) -> Result<Vec<ServiceMonitorTask>, AppError> {
-    let monitors = get_runnable_monitors_for_vps(pool, vps_id).await?;
+    let monitors = get_runnable_monitors_for_vps(pool.clone(), vps_id).await?;
+    // Monitors saved before the organization's quota was raised run at the new minimum.
+    let min_frequency_seconds = {
+        let conn = pool.get()?;
+        let organization_id: Option<i32> = conn
+            .query_row("SELECT organization_id FROM vps WHERE id = ?", params![vps_id], |row| row.get(0))
+            .optional()?
+            .flatten();
+        min_frequency_for_organization(&conn, organization_id)?
+    };
     let tasks = monitors
         .into_iter()
         .map(|monitor| ServiceMonitorTask {
@@ -553,7 +693,7 @@ pub async fn get_tasks_for_agent(
             name: monitor.name,
             monitor_type: monitor.monitor_type,
             target: monitor.target,
-            frequency_seconds: monitor.frequency_seconds,
+            frequency_seconds: monitor.frequency_seconds.max(min_frequency_seconds),
             monitor_config_json: monitor
                 .monitor_config
                 .as_ref()
@@ -618,17 +758,69 @@ pub async fn get_vps_ids_for_monitor(
     }
 }
 
+/// Share of ping packets that got no reply, 0-100.
+pub fn packet_loss_percent(stats: &PingStats) -> f64 {
+    if stats.packets_sent == 0 {
+        return 100.0;
+    }
+    let lost = stats.packets_sent.saturating_sub(stats.packets_received);
+    f64::from(lost) / f64::from(stats.packets_sent) * 100.0
+}
+
+/// The `details` JSON stored and broadcast for a check result.
+pub fn result_details_json(result: &ServiceMonitorResult) -> serde_json::Value {
+    let mut details = serde_json::json!({ "message": &result.details });
+    if let Some(cert) = &result.certificate {
+        details["certificate"] = serde_json::json!({
+            "notBefore": Utc.timestamp_millis_opt(cert.not_before_unix_ms).single(),
+            "notAfter": Utc.timestamp_millis_opt(cert.not_after_unix_ms).single(),
+            "issuer": &cert.issuer,
+            "subject": &cert.subject,
+            "subjectAltNames": &cert.subject_alt_names,
+        });
+    }
+    if let Some(ping) = &result.ping {
+        details["ping"] = serde_json::json!({
+            "packetsSent": ping.packets_sent,
+            "packetsReceived": ping.packets_received,
+            "packetLossPercent": packet_loss_percent(ping),
+            "rttMinMs": ping.rtt_min_ms,
+            "rttAvgMs": ping.rtt_avg_ms,
+            "rttMaxMs": ping.rtt_max_ms,
+        });
+    }
+    details
+}
+
+/// Stores a check result. Returns whether it fell inside a maintenance window,
+/// or `None` for a replayed result that is already stored.
 pub async fn record_monitor_result(
     pool: DuckDbPool,
     agent_id: i32, // This is the vps_id
     result: &ServiceMonitorResult,
-) -> Result<(), AppError> {
+) -> Result<Option<bool>, AppError> {
     let conn = pool.get()?;
-    let details_str = serde_json::to_string(&serde_json::json!({ "message": &result.details }))?;
+    let details_str = serde_json::to_string(&result_details_json(result))?;
     let time = chrono::Utc.timestamp_millis_opt(result.timestamp_unix_ms).unwrap();
-    conn.execute(
-        "INSERT INTO service_monitor_results (time, monitor_id, agent_id, is_up, latency_ms, details)
-         VALUES (?, ?, ?, ?, ?, ?)",
+    // Agents replay results buffered during an outage, some of which may have
+    // arrived before the connection dropped.
+    if result.replayed {
+        let stored: bool = conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM service_monitor_results WHERE monitor_id = ? AND agent_id = ? AND time = ?)",
+            params![result.monitor_id, agent_id, time],
+            |row| row.get(0),
+        )?;
+        if stored {
+            return Ok(None);
+        }
+    }
+    let in_maintenance: bool = conn.query_row(
+        "INSERT INTO service_monitor_results (time, monitor_id, agent_id, is_up, latency_ms, details, packet_loss_percent, in_maintenance)
+         SELECT ?, ?, ?, ?, ?, ?, ?, EXISTS (
+             SELECT 1 FROM service_monitor_maintenance_windows
+             WHERE monitor_id = ? AND starts_at <= ? AND ends_at > ?
+         )
+         RETURNING in_maintenance",
         params![
             time,
             result.monitor_id,
@@ -636,9 +828,126 @@ pub async fn record_monitor_result(
             result.successful,
             result.response_time_ms,
             details_str,
+            result.ping.as_ref().map(packet_loss_percent),
+            result.monitor_id,
+            time,
+            time,
         ],
+        |row| row.get(0),
     )?;
-    Ok(())
+    Ok(Some(in_maintenance))
+}
+
+/// Days until the certificate recorded in a result's details expires;
+/// negative once it has expired.
+pub fn certificate_expiry_days(details: &serde_json::Value, now: DateTime<Utc>) -> Option<f64> {
+    let not_after = details.get("certificate")?.get("notAfter")?.as_str()?;
+    let not_after = DateTime::parse_from_rfc3339(not_after).ok()?.with_timezone(&Utc);
+    Some((not_after - now).num_seconds() as f64 / 86_400.0)
+}
+
+/// For each active monitor run by the VPS whose latest result carries a
+/// certificate, the monitor name and the days until that certificate expires.
+pub async fn get_certificate_expiries(
+    pool: DuckDbPool,
+    vps_id: i32,
+    now: DateTime<Utc>,
+) -> Result<Vec<(String, f64)>, AppError> {
+    let monitors = get_runnable_monitors_for_vps(pool.clone(), vps_id).await?;
+    let conn = pool.get()?;
+    let mut expiries = Vec::new();
+    for monitor in monitors.into_iter().filter(|m| matches!(m.monitor_type.as_str(), "http" | "https")) {
+        let details: Option<Option<serde_json::Value>> = conn
+            .query_row(
+                "SELECT details FROM service_monitor_results
+                 WHERE monitor_id = ? AND agent_id = ? AND details->'certificate' IS NOT NULL
+                 ORDER BY time DESC LIMIT 1",
+                params![monitor.id, vps_id],
+                |row| json_from_row(row, "details"),
+            )
+            .optional()?;
+        if let Some(days) = details.flatten().as_ref().and_then(|d| certificate_expiry_days(d, now)) {
+            expiries.push((monitor.name, days));
+        }
+    }
+    Ok(expiries)
+}
+
+/// The latest checks of one monitor on one agent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MonitorCheckRun {
+    pub monitor_id: i32,
+    pub monitor_name: String,
+    pub agent_id: i32,
+    pub vps_name: String,
+    /// Whether each check was up, oldest first.
+    pub results: Vec<bool>,
+    /// Message of the newest check.
+    pub last_message: Option<String>,
+}
+
+/// Up to `limit` latest checks since `since` of each active monitor in the
+/// organization of a `monitor_down` rule, per agent, narrowed to the rule's
+/// monitor and VPS when it has them. Checks run during maintenance don't count.
+pub async fn get_recent_checks_for_rule(
+    pool: DuckDbPool,
+    rule_id: i32,
+    since: DateTime<Utc>,
+    limit: usize,
+) -> Result<Vec<MonitorCheckRun>, AppError> {
+    let conn = pool.get()?;
+    let rows = conn
+        .prepare(
+            "SELECT monitor_id, monitor_name, agent_id, vps_name, is_up, message
+             FROM (
+                 SELECT r.monitor_id, m.name AS monitor_name, r.agent_id, v.name AS vps_name, r.is_up, r.time,
+                        json_extract_string(r.details, '$.message') AS message,
+                        ROW_NUMBER() OVER (PARTITION BY r.monitor_id, r.agent_id ORDER BY r.time DESC) AS rn
+                 FROM service_monitor_results r
+                 JOIN service_monitors m ON m.id = r.monitor_id
+                 JOIN vps v ON v.id = r.agent_id
+                 JOIN alert_rules a ON a.id = ?
+                 WHERE m.organization_id = a.organization_id
+                   AND m.is_active
+                   AND v.archived_at IS NULL
+                   AND NOT r.in_maintenance
+                   AND r.time >= ?
+                   AND (a.monitor_id IS NULL OR r.monitor_id = a.monitor_id)
+                   AND (a.vps_id IS NULL OR r.agent_id = a.vps_id)
+             )
+             WHERE rn <= ?
+             ORDER BY monitor_id, agent_id, time",
+        )?
+        .query_map(params![rule_id, since, limit as i64], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, i32>(2)?,
+                row.get::<_, String>(3)?,
+                row.get::<_, bool>(4)?,
+                row.get::<_, Option<String>>(5)?,
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut runs: Vec<MonitorCheckRun> = Vec::new();
+    for (monitor_id, monitor_name, agent_id, vps_name, is_up, message) in rows {
+        match runs.last_mut() {
+            Some(run) if run.monitor_id == monitor_id && run.agent_id == agent_id => {
+                run.results.push(is_up);
+                run.last_message = message;
+            }
+            _ => runs.push(MonitorCheckRun {
+                monitor_id,
+                monitor_name,
+                agent_id,
+                vps_name,
+                results: vec![is_up],
+                last_message: message,
+            }),
+        }
+    }
+    Ok(runs)
 }
 
 fn row_to_service_monitor_point(row: &Row) -> DuckDbResult<ServiceMonitorPoint> {
@@ -649,6 +958,8 @@ fn row_to_service_monitor_point(row: &Row) -> DuckDbResult<ServiceMonitorPoint>
         is_up: row.get("is_up")?,
         latency_ms: row.get("latency_ms")?,
         details: json_from_row(row, "details")?,
+        in_maintenance: row.get("in_maintenance")?,
+        packet_loss_percent: row.get("packet_loss_percent")?,
     })
 }
 
@@ -668,8 +979,11 @@ pub async fn get_monitor_results_by_id(
                 monitor_id,
                 agent_id,
                 AVG(latency_ms)::DOUBLE as latency_ms,
-                CAST(SUM(CASE WHEN is_up = true THEN 1.0 ELSE 0.0 END) AS REAL) / COUNT(*) as is_up,
-                NULL as details
+                CAST(SUM(CASE WHEN is_up AND NOT in_maintenance THEN 1.0 ELSE 0.0 END) AS REAL)
+                    / NULLIF(SUM(CASE WHEN in_maintenance THEN 0 ELSE 1 END), 0) as is_up,
+                NULL as details,
+                BOOL_AND(in_maintenance) as in_maintenance,
+                AVG(packet_loss_percent)::DOUBLE as packet_loss_percent
              FROM service_monitor_results
              WHERE monitor_id = ? AND time >= ? AND time <= ?
              GROUP BY 1, 2, 3
@@ -683,7 +997,7 @@ pub async fn get_monitor_results_by_id(
     } else {
         let points = conn
             .prepare(
-                "SELECT time, monitor_id, agent_id, latency_ms, CAST(CASE WHEN is_up THEN 1.0 ELSE 0.0 END AS DOUBLE) as is_up, details
+                "SELECT time, monitor_id, agent_id, latency_ms, CAST(CASE WHEN is_up THEN 1.0 ELSE 0.0 END AS DOUBLE) as is_up, details, in_maintenance, packet_loss_percent
                  FROM service_monitor_results
                  WHERE monitor_id = ? AND time >= ? AND time <= ?
                  ORDER BY time DESC",
@@ -716,8 +1030,11 @@ pub async fn get_monitor_results_by_vps_id(
                 monitor_id,
                 agent_id,
                 AVG(latency_ms)::DOUBLE as latency_ms,
-                CAST(SUM(CASE WHEN is_up = true THEN 1.0 ELSE 0.0 END) AS REAL) / COUNT(*) as is_up,
-                NULL as details
+                CAST(SUM(CASE WHEN is_up AND NOT in_maintenance THEN 1.0 ELSE 0.0 END) AS REAL)
+                    / NULLIF(SUM(CASE WHEN in_maintenance THEN 0 ELSE 1 END), 0) as is_up,
+                NULL as details,
+                BOOL_AND(in_maintenance) as in_maintenance,
+                AVG(packet_loss_percent)::DOUBLE as packet_loss_percent
              FROM service_monitor_results
              WHERE monitor_id IN {placeholders} AND agent_id = ? AND time >= ? AND time <= ?
              GROUP BY 1, 2, 3
@@ -735,7 +1052,7 @@ pub async fn get_monitor_results_by_vps_id(
         Ok(points)
     } else {
         let sql = format!(
-            "SELECT time, monitor_id, agent_id, latency_ms, CAST(CASE WHEN is_up THEN 1.0 ELSE 0.0 END AS DOUBLE) as is_up, details
+            "SELECT time, monitor_id, agent_id, latency_ms, CAST(CASE WHEN is_up THEN 1.0 ELSE 0.0 END AS DOUBLE) as is_up, details, in_maintenance, packet_loss_percent
              FROM service_monitor_results
              WHERE monitor_id IN {placeholders} AND agent_id = ? AND time >= ? AND time <= ?
              ORDER BY time DESC"
@@ -771,4 +1088,330 @@ pub async fn get_monitor_names_by_ids(
         .collect::<Result<Vec<_>, _>>()?;
     
     Ok(monitors.into_iter().collect())
-}
\ No newline at end of file
+}
+
+fn row_to_maintenance_window_model(row: &Row) -> DuckDbResult<service_monitor_maintenance_window::Model> {
+    Ok(service_monitor_maintenance_window::Model {
+        id: row.get("id")?,
+        monitor_id: row.get("monitor_id")?,
+        starts_at: row.get("starts_at")?,
+        ends_at: row.get("ends_at")?,
+        reason: row.get("reason")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub async fn get_maintenance_windows(
+    pool: DuckDbPool,
+    monitor_id: i32,
+) -> Result<Vec<service_monitor_maintenance_window::Model>, AppError> {
+    let conn = pool.get()?;
+    let windows = conn
+        .prepare("SELECT * FROM service_monitor_maintenance_windows WHERE monitor_id = ? ORDER BY starts_at DESC")?
+        .query_map(params![monitor_id], row_to_maintenance_window_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(windows)
+}
+
+/// Re-flags the results of a monitor within `[starts_at, ends_at)` against the current set of windows.
+fn reflag_results_in_range(
+    conn: &duckdb::Connection,
+    monitor_id: i32,
+    starts_at: DateTime<Utc>,
+    ends_at: DateTime<Utc>,
+) -> DuckDbResult<usize> {
+    conn.execute(
+        "UPDATE service_monitor_results r SET in_maintenance = EXISTS (
+             SELECT 1 FROM service_monitor_maintenance_windows w
+             WHERE w.monitor_id = r.monitor_id AND w.starts_at <= r.time AND w.ends_at > r.time
+         )
+         WHERE r.monitor_id = ? AND r.time >= ? AND r.time < ?",
+        params![monitor_id, starts_at, ends_at],
+    )
+}
+
+pub async fn create_maintenance_window(
+    pool: DuckDbPool,
+    monitor_id: i32,
+    payload: CreateMaintenanceWindow,
+) -> Result<service_monitor_maintenance_window::Model, AppError> {
+    if payload.ends_at <= payload.starts_at {
+        return Err(AppError::InvalidInput("endsAt must be after startsAt".to_string()));
+    }
+
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    let window = tx.query_row(
+        "INSERT INTO service_monitor_maintenance_windows (monitor_id, starts_at, ends_at, reason)
+         VALUES (?, ?, ?, ?) RETURNING *",
+        params![monitor_id, payload.starts_at, payload.ends_at, payload.reason],
+        row_to_maintenance_window_model,
+    )?;
+    // Windows may be declared retroactively, so existing results are re-flagged too.
+    reflag_results_in_range(&tx, monitor_id, window.starts_at, window.ends_at)?;
+    tx.commit()?;
+    Ok(window)
+}
+
+pub async fn delete_maintenance_window(
+    pool: DuckDbPool,
+    monitor_id: i32,
+    window_id: i32,
+) -> Result<(), AppError> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    let window = tx
+        .query_row(
+            "DELETE FROM service_monitor_maintenance_windows WHERE id = ? AND monitor_id = ? RETURNING *",
+            params![window_id, monitor_id],
+            row_to_maintenance_window_model,
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("Maintenance window not found".to_string()))?;
+    reflag_results_in_range(&tx, monitor_id, window.starts_at, window.ends_at)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Which results a heatmap aggregates.
+#[derive(Debug, Clone, Copy)]
+pub enum HeatmapScope {
+    Monitor(i32),
+    /// All monitors run by one agent.
+    Vps(i32),
+}
+
+/// Splits an hour-of-week index (0 = Monday 00:00) into day and hour.
+fn split_hour_of_week(hour_of_week: i32) -> (u8, u8) {
+    let hour_of_week = hour_of_week.rem_euclid(168);
+    ((hour_of_week / 24) as u8, (hour_of_week % 24) as u8)
+}
+
+/// Aggregates check results by hour of week, shifted by `utc_offset_minutes`.
+/// Checks during maintenance are ignored.
+pub async fn get_availability_heatmap(
+    pool: DuckDbPool,
+    scope: HeatmapScope,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    utc_offset_minutes: i32,
+) -> Result<Vec<HeatmapCell>, AppError> {
+    let (scope_column, scope_id) = match scope {
+        HeatmapScope::Monitor(id) => ("monitor_id", id),
+        HeatmapScope::Vps(id) => ("agent_id", id),
+    };
+    // The Unix epoch fell on a Thursday, 72 hours after Monday 00:00. Computing the
+    // slot arithmetically keeps it independent of the session time zone.
+    let sql = format!(
+        "SELECT
+             CAST((floor((epoch(time) + ?) / 3600) + 72) % 168 AS INTEGER) AS hour_of_week,
+             COUNT(*),
+             100.0 * SUM(CASE WHEN is_up THEN 1 ELSE 0 END) / COUNT(*),
+             AVG(latency_ms),
+             quantile_cont(latency_ms, 0.95)
+         FROM service_monitor_results
+         WHERE {scope_column} = ? AND time >= ? AND time <= ? AND NOT in_maintenance
+         GROUP BY hour_of_week
+         ORDER BY hour_of_week"
+    );
+    let offset_seconds = i64::from(utc_offset_minutes) * 60;
+
+    let conn = pool.get()?;
+    let cells = conn
+        .prepare(&sql)?
+        .query_map(params![offset_seconds, scope_id, start_time, end_time], |row| {
+            let (day_of_week, hour) = split_hour_of_week(row.get(0)?);
+            Ok(HeatmapCell {
+                day_of_week,
+                hour,
+                checks: row.get(1)?,
+                up_percent: row.get(2)?,
+                avg_latency_ms: row.get(3)?,
+                p95_latency_ms: row.get(4)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(cells)
+}
+
+/// Latency per agent for one monitor, bucketed to `interval_seconds` so the
+/// agents' series line up. Checks during maintenance are ignored.
+pub async fn get_region_latency_series(
+    pool: DuckDbPool,
+    monitor_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    interval_seconds: i64,
+) -> Result<BTreeMap<i32, Vec<RegionLatencyPoint>>, AppError> {
+    let sql = format!(
+        "SELECT
+             agent_id,
+             time_bucket(INTERVAL '{interval_seconds}' SECONDS, time) AS bucket,
+             AVG(latency_ms)::DOUBLE,
+             AVG(CASE WHEN is_up THEN 1.0 ELSE 0.0 END)::DOUBLE
+         FROM service_monitor_results
+         WHERE monitor_id = ? AND time >= ? AND time <= ? AND NOT in_maintenance
+         GROUP BY agent_id, bucket
+         ORDER BY agent_id, bucket"
+    );
+    let conn = pool.get()?;
+    let rows = conn
+        .prepare(&sql)?
+        .query_map(params![monitor_id, start_time, end_time], |row| {
+            Ok((
+                row.get::<_, i32>(0)?,
+                RegionLatencyPoint {
+                    time: row.get(1)?,
+                    latency_ms: row.get(2)?,
+                    up_ratio: row.get(3)?,
+                },
+            ))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut series: BTreeMap<i32, Vec<RegionLatencyPoint>> = BTreeMap::new();
+    for (agent_id, point) in rows {
+        series.entry(agent_id).or_default().push(point);
+    }
+    Ok(series)
+}
+
+fn median(values: &mut [f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(|a, b| a.total_cmp(b));
+    let mid = values.len() / 2;
+    Some(if values.len() % 2 == 0 {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    })
+}
+
+pub fn median_latency(points: &[RegionLatencyPoint]) -> Option<f64> {
+    median(&mut points.iter().filter_map(|p| p.latency_ms).collect::<Vec<_>>())
+}
+
+/// For every agent, the mean relative distance of its latency from the median
+/// of the *other* agents in the same bucket. Leaving the agent out keeps a
+/// single slow region from dragging the reference towards itself.
+pub fn compute_region_divergence(series: &BTreeMap<i32, Vec<RegionLatencyPoint>>) -> HashMap<i32, f64> {
+    let mut buckets: BTreeMap<DateTime<Utc>, Vec<(i32, f64)>> = BTreeMap::new();
+    for (agent_id, points) in series {
+        for point in points {
+            if let Some(latency) = point.latency_ms {
+                buckets.entry(point.time).or_default().push((*agent_id, latency));
+            }
+        }
+    }
+
+    let mut totals: HashMap<i32, (f64, u32)> = HashMap::new();
+    for samples in buckets.values().filter(|samples| samples.len() >= 2) {
+        for (agent_id, latency) in samples {
+            let mut others: Vec<f64> = samples
+                .iter()
+                .filter(|(other_id, _)| other_id != agent_id)
+                .map(|(_, l)| *l)
+                .collect();
+            match median(&mut others) {
+                Some(reference) if reference > 0.0 => {
+                    let total = totals.entry(*agent_id).or_insert((0.0, 0));
+                    total.0 += (latency - reference).abs() / reference;
+                    total.1 += 1;
+                }
+                _ => {}
+            }
+        }
+    }
+    totals
+        .into_iter()
+        .map(|(agent_id, (sum, count))| (agent_id, sum / f64::from(count)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn schedule_respects_minimum_and_timeout() {
+        assert!(validate_schedule(10, 5, MIN_FREQUENCY_SECONDS).is_ok());
+        assert!(validate_schedule(5, 5, MIN_FREQUENCY_SECONDS).is_err());
+        assert!(validate_schedule(30, 10, 60).is_err());
+        assert!(validate_schedule(30, 31, MIN_FREQUENCY_SECONDS).is_err());
+        assert!(validate_schedule(30, 0, MIN_FREQUENCY_SECONDS).is_err());
+    }
+
+    #[test]
+    fn packet_loss_is_a_percentage_of_sent_packets() {
+        let stats = |sent, received| PingStats { packets_sent: sent, packets_received: received, ..Default::default() };
+        assert_eq!(packet_loss_percent(&stats(4, 3)), 25.0);
+        assert_eq!(packet_loss_percent(&stats(4, 4)), 0.0);
+        assert_eq!(packet_loss_percent(&stats(0, 0)), 100.0);
+    }
+
+    #[test]
+    fn dns_monitor_config_is_validated() {
+        let config = serde_json::json!({ "record_type": "mx", "expected_values": ["10 mx.example.com"], "resolver": "1.1.1.1:53" });
+        assert!(validate_monitor_config("dns", "example.com", Some(&config)).is_ok());
+        assert!(validate_monitor_config("dns", "example.com", None).is_ok());
+        assert!(validate_monitor_config("dns", "https://example.com", None).is_err());
+        let bad_type = serde_json::json!({ "record_type": "SRV" });
+        assert!(validate_monitor_config("dns", "example.com", Some(&bad_type)).is_err());
+        let bad_resolver = serde_json::json!({ "resolver": "dns.google" });
+        assert!(validate_monitor_config("dns", "example.com", Some(&bad_resolver)).is_err());
+        assert!(validate_monitor_config("http", "https://example.com", Some(&bad_type)).is_ok());
+    }
+
+    #[test]
+    fn certificate_expiry_is_read_from_details() {
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        let details = serde_json::json!({
+            "message": "200 OK",
+            "certificate": { "notAfter": "2025-01-15T12:00:00Z" },
+        });
+        assert_eq!(certificate_expiry_days(&details, now), Some(14.5));
+        assert_eq!(certificate_expiry_days(&serde_json::json!({ "message": "200 OK" }), now), None);
+    }
+
+    #[test]
+    fn splits_hour_of_week() {
+        assert_eq!(split_hour_of_week(0), (0, 0));
+        assert_eq!(split_hour_of_week(26), (1, 2));
+        assert_eq!(split_hour_of_week(167), (6, 23));
+        assert_eq!(split_hour_of_week(-1), (6, 23));
+    }
+
+    fn point(minute: u32, latency_ms: f64) -> RegionLatencyPoint {
+        RegionLatencyPoint {
+            time: Utc.with_ymd_and_hms(2025, 1, 1, 0, minute, 0).unwrap(),
+            latency_ms: Some(latency_ms),
+            up_ratio: Some(1.0),
+        }
+    }
+
+    #[test]
+    fn single_slow_region_diverges() {
+        let series = BTreeMap::from([
+            (1, vec![point(0, 100.0), point(1, 110.0)]),
+            (2, vec![point(0, 105.0), point(1, 100.0)]),
+            (3, vec![point(0, 400.0), point(1, 420.0)]),
+        ]);
+        let divergence = compute_region_divergence(&series);
+        assert!(divergence[&3] > 2.0);
+        assert!(divergence[&1] < 0.1 && divergence[&2] < 0.1);
+    }
+
+    #[test]
+    fn global_slowdown_does_not_diverge() {
+        let series = BTreeMap::from([
+            (1, vec![point(0, 100.0), point(1, 400.0)]),
+            (2, vec![point(0, 100.0), point(1, 400.0)]),
+        ]);
+        let divergence = compute_region_divergence(&series);
+        assert_eq!(divergence[&1], 0.0);
+        assert_eq!(divergence[&2], 0.0);
+        assert_eq!(median_latency(&series[&1]), Some(250.0));
+    }
+}
@@ -0,0 +1,410 @@
+//! Service level objectives for service monitors.
+//!
+//! An SLO states the share of checks that must be "good" over a rolling window
+//! (e.g. 99.5% over 30 days). A check is good when it succeeded and, if the SLO
+//! has a latency threshold, answered within it. Checks recorded during a
+//! maintenance window are ignored.
+//!
+//! Alerting follows the multi-window burn-rate approach: the burn rate is the
+//! observed error ratio divided by the error ratio the objective allows. An alert
+//! fires when both a long and a short window exceed the configured rate, so that
+//! alerts trigger quickly on heavy burns but also reset quickly once the issue is gone.
+
+use crate::db::duckdb_service::notification_service::check_channel_ownership;
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::service_monitor_slo;
+use crate::web::error::AppError;
+use crate::web::models::service_monitor_models::{CreateSlo, UpdateSlo};
+use chrono::{DateTime, Duration, Utc};
+use duckdb::{params, Connection, OptionalExt, Result as DuckDbResult, Row};
+use serde::Serialize;
+
+/// Long/short window pair for fast-burn alerts (budget of a 30 day SLO gone in ~2 days).
+pub const FAST_BURN_WINDOWS: (Duration, Duration) = (Duration::hours(1), Duration::minutes(5));
+/// Long/short window pair for slow-burn alerts (budget of a 30 day SLO gone in ~5 days).
+pub const SLOW_BURN_WINDOWS: (Duration, Duration) = (Duration::hours(6), Duration::minutes(30));
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SloStatus {
+    pub total_checks: i64,
+    pub bad_checks: i64,
+    /// Share of good checks over the SLO window, in percent.
+    pub sli_percent: Option<f64>,
+    /// Share of the error budget that is left. Negative once the budget is exhausted.
+    pub budget_remaining_percent: Option<f64>,
+    pub fast_burn_rate: Option<f64>,
+    pub slow_burn_rate: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SloDetails {
+    #[serde(flatten)]
+    pub slo: service_monitor_slo::Model,
+    pub channel_ids: Vec<i32>,
+    pub status: SloStatus,
+}
+
+/// An active SLO together with what the evaluator needs to alert on it.
+#[derive(Debug, Clone)]
+pub struct SloForEvaluation {
+    pub slo: service_monitor_slo::Model,
+    pub monitor_name: String,
+    pub channel_ids: Vec<i32>,
+}
+
+/// Burn rates over both windows of a pair, `None` when a window has no checks.
+#[derive(Debug, Clone, Copy)]
+pub struct BurnRates {
+    pub long: Option<f64>,
+    pub short: Option<f64>,
+}
+
+impl BurnRates {
+    pub fn exceeds(&self, threshold: f64) -> bool {
+        matches!((self.long, self.short), (Some(long), Some(short)) if long >= threshold && short >= threshold)
+    }
+}
+
+fn row_to_slo_model(row: &Row) -> DuckDbResult<service_monitor_slo::Model> {
+    Ok(service_monitor_slo::Model {
+        id: row.get("id")?,
+        monitor_id: row.get("monitor_id")?,
+        name: row.get("name")?,
+        target_percent: row.get("target_percent")?,
+        window_days: row.get("window_days")?,
+        latency_threshold_ms: row.get("latency_threshold_ms")?,
+        fast_burn_rate: row.get("fast_burn_rate")?,
+        slow_burn_rate: row.get("slow_burn_rate")?,
+        is_active: row.get("is_active")?,
+        last_alerted_at: row.get("last_alerted_at")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+/// Observed error ratio divided by the error ratio allowed by `target_percent`.
+pub fn burn_rate(bad: i64, total: i64, target_percent: f64) -> Option<f64> {
+    if total <= 0 {
+        return None;
+    }
+    let allowed = 1.0 - target_percent / 100.0;
+    if allowed <= 0.0 {
+        return None;
+    }
+    Some((bad as f64 / total as f64) / allowed)
+}
+
+fn validate(
+    target_percent: f64,
+    window_days: i32,
+    latency_threshold_ms: Option<i32>,
+    fast_burn_rate: f64,
+    slow_burn_rate: f64,
+) -> Result<(), AppError> {
+    if !(target_percent > 0.0 && target_percent < 100.0) {
+        return Err(AppError::InvalidInput("targetPercent must be between 0 and 100 (exclusive)".to_string()));
+    }
+    if !(1..=365).contains(&window_days) {
+        return Err(AppError::InvalidInput("windowDays must be between 1 and 365".to_string()));
+    }
+    if latency_threshold_ms.is_some_and(|ms| ms <= 0) {
+        return Err(AppError::InvalidInput("latencyThresholdMs must be positive".to_string()));
+    }
+    if fast_burn_rate <= 0.0 || slow_burn_rate <= 0.0 {
+        return Err(AppError::InvalidInput("Burn rate thresholds must be positive".to_string()));
+    }
+    Ok(())
+}
+
+/// Counts `(total, bad)` checks of a monitor in `[start, end)`, ignoring maintenance.
+fn count_checks(
+    conn: &Connection,
+    slo: &service_monitor_slo::Model,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> DuckDbResult<(i64, i64)> {
+    conn.query_row(
+        "SELECT COUNT(*),
+                COUNT(*) FILTER (WHERE NOT is_up OR (? IS NOT NULL AND (latency_ms IS NULL OR latency_ms > ?)))
+         FROM service_monitor_results
+         WHERE monitor_id = ? AND time >= ? AND time < ? AND NOT in_maintenance",
+        params![slo.latency_threshold_ms, slo.latency_threshold_ms, slo.monitor_id, start, end],
+        |row| Ok((row.get(0)?, row.get(1)?)),
+    )
+}
+
+fn burn_rates_for(
+    conn: &Connection,
+    slo: &service_monitor_slo::Model,
+    (long, short): (Duration, Duration),
+    now: DateTime<Utc>,
+) -> DuckDbResult<BurnRates> {
+    let (long_total, long_bad) = count_checks(conn, slo, now - long, now)?;
+    let (short_total, short_bad) = count_checks(conn, slo, now - short, now)?;
+    Ok(BurnRates {
+        long: burn_rate(long_bad, long_total, slo.target_percent),
+        short: burn_rate(short_bad, short_total, slo.target_percent),
+    })
+}
+
+/// Returns the burn rates of the fast and slow window pairs.
+pub fn compute_burn_rates(
+    conn: &Connection,
+    slo: &service_monitor_slo::Model,
+    now: DateTime<Utc>,
+) -> DuckDbResult<(BurnRates, BurnRates)> {
+    Ok((
+        burn_rates_for(conn, slo, FAST_BURN_WINDOWS, now)?,
+        burn_rates_for(conn, slo, SLOW_BURN_WINDOWS, now)?,
+    ))
+}
+
+fn compute_status(conn: &Connection, slo: &service_monitor_slo::Model) -> DuckDbResult<SloStatus> {
+    let now = Utc::now();
+    let (total, bad) = count_checks(conn, slo, now - Duration::days(slo.window_days as i64), now)?;
+    let window_burn = burn_rate(bad, total, slo.target_percent);
+    let (fast, slow) = compute_burn_rates(conn, slo, now)?;
+    Ok(SloStatus {
+        total_checks: total,
+        bad_checks: bad,
+        sli_percent: (total > 0).then(|| (total - bad) as f64 / total as f64 * 100.0),
+        budget_remaining_percent: window_burn.map(|rate| (1.0 - rate) * 100.0),
+        fast_burn_rate: fast.long,
+        slow_burn_rate: slow.long,
+    })
+}
+
+fn get_channel_ids(conn: &Connection, slo_id: i32) -> DuckDbResult<Vec<i32>> {
+    conn.prepare("SELECT channel_id FROM service_monitor_slo_channels WHERE slo_id = ? ORDER BY channel_id")?
+        .query_map(params![slo_id], |row| row.get(0))?
+        .collect()
+}
+
+fn replace_channel_ids(conn: &Connection, slo_id: i32, channel_ids: &[i32]) -> DuckDbResult<()> {
+    conn.execute("DELETE FROM service_monitor_slo_channels WHERE slo_id = ?", params![slo_id])?;
+    let mut stmt = conn.prepare("INSERT INTO service_monitor_slo_channels (slo_id, channel_id) VALUES (?, ?) ON CONFLICT DO NOTHING")?;
+    for channel_id in channel_ids {
+        stmt.execute(params![slo_id, channel_id])?;
+    }
+    Ok(())
+}
+
+fn to_details(conn: &Connection, slo: service_monitor_slo::Model) -> DuckDbResult<SloDetails> {
+    Ok(SloDetails {
+        channel_ids: get_channel_ids(conn, slo.id)?,
+        status: compute_status(conn, &slo)?,
+        slo,
+    })
+}
+
+pub async fn get_slos_for_monitor(pool: DuckDbPool, monitor_id: i32) -> Result<Vec<SloDetails>, AppError> {
+    tokio::task::spawn_blocking(move || -> Result<_, AppError> {
+        let conn = pool.get()?;
+        let slos = conn
+            .prepare("SELECT * FROM service_monitor_slos WHERE monitor_id = ? ORDER BY id")?
+            .query_map(params![monitor_id], row_to_slo_model)?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(slos
+            .into_iter()
+            .map(|slo| to_details(&conn, slo))
+            .collect::<Result<Vec<_>, _>>()?)
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+}
+
+pub async fn create_slo(
+    pool: DuckDbPool,
+    user_id: i32,
+    monitor_id: i32,
+    payload: CreateSlo,
+) -> Result<SloDetails, AppError> {
+    let window_days = payload.window_days.unwrap_or(30);
+    let fast_burn_rate = payload.fast_burn_rate.unwrap_or(14.4);
+    let slow_burn_rate = payload.slow_burn_rate.unwrap_or(6.0);
+    validate(
+        payload.target_percent,
+        window_days,
+        payload.latency_threshold_ms,
+        fast_burn_rate,
+        slow_burn_rate,
+    )?;
+
+    tokio::task::spawn_blocking(move || -> Result<_, AppError> {
+        let mut conn = pool.get()?;
+        let channel_ids = payload.channel_ids.unwrap_or_default();
+        check_channel_ownership(&conn, user_id, &channel_ids)?;
+
+        let tx = conn.transaction()?;
+        let slo = tx.query_row(
+            "INSERT INTO service_monitor_slos
+                (monitor_id, name, target_percent, window_days, latency_threshold_ms, fast_burn_rate, slow_burn_rate, is_active)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+            params![
+                monitor_id,
+                payload.name,
+                payload.target_percent,
+                window_days,
+                payload.latency_threshold_ms,
+                fast_burn_rate,
+                slow_burn_rate,
+                payload.is_active.unwrap_or(true)
+            ],
+            row_to_slo_model,
+        )?;
+        replace_channel_ids(&tx, slo.id, &channel_ids)?;
+        tx.commit()?;
+        Ok(to_details(&conn, slo)?)
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+}
+
+pub async fn update_slo(
+    pool: DuckDbPool,
+    user_id: i32,
+    monitor_id: i32,
+    slo_id: i32,
+    payload: UpdateSlo,
+) -> Result<SloDetails, AppError> {
+    tokio::task::spawn_blocking(move || -> Result<_, AppError> {
+        let mut conn = pool.get()?;
+        let existing = conn
+            .query_row(
+                "SELECT * FROM service_monitor_slos WHERE id = ? AND monitor_id = ?",
+                params![slo_id, monitor_id],
+                row_to_slo_model,
+            )
+            .optional()?
+            .ok_or_else(|| AppError::NotFound("SLO not found".to_string()))?;
+
+        let target_percent = payload.target_percent.unwrap_or(existing.target_percent);
+        let window_days = payload.window_days.unwrap_or(existing.window_days);
+        let latency_threshold_ms = payload.latency_threshold_ms.unwrap_or(existing.latency_threshold_ms);
+        let fast_burn_rate = payload.fast_burn_rate.unwrap_or(existing.fast_burn_rate);
+        let slow_burn_rate = payload.slow_burn_rate.unwrap_or(existing.slow_burn_rate);
+        validate(target_percent, window_days, latency_threshold_ms, fast_burn_rate, slow_burn_rate)?;
+        if let Some(channel_ids) = &payload.channel_ids {
+            check_channel_ownership(&conn, user_id, channel_ids)?;
+        }
+
+        let tx = conn.transaction()?;
+        let slo = tx.query_row(
+            "UPDATE service_monitor_slos SET
+                name = ?, target_percent = ?, window_days = ?, latency_threshold_ms = ?,
+                fast_burn_rate = ?, slow_burn_rate = ?, is_active = ?, updated_at = ?
+             WHERE id = ? RETURNING *",
+            params![
+                payload.name.unwrap_or(existing.name),
+                target_percent,
+                window_days,
+                latency_threshold_ms,
+                fast_burn_rate,
+                slow_burn_rate,
+                payload.is_active.unwrap_or(existing.is_active),
+                Utc::now(),
+                slo_id
+            ],
+            row_to_slo_model,
+        )?;
+        if let Some(channel_ids) = &payload.channel_ids {
+            replace_channel_ids(&tx, slo_id, channel_ids)?;
+        }
+        tx.commit()?;
+        Ok(to_details(&conn, slo)?)
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+}
+
+pub async fn delete_slo(pool: DuckDbPool, monitor_id: i32, slo_id: i32) -> Result<(), AppError> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    let deleted = tx.execute(
+        "DELETE FROM service_monitor_slos WHERE id = ? AND monitor_id = ?",
+        params![slo_id, monitor_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("SLO not found".to_string()));
+    }
+    tx.execute("DELETE FROM service_monitor_slo_channels WHERE slo_id = ?", params![slo_id])?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Removes every SLO of a monitor. Used when the monitor itself is deleted.
+pub fn delete_slos_for_monitor(conn: &Connection, monitor_id: i32) -> DuckDbResult<()> {
+    conn.execute(
+        "DELETE FROM service_monitor_slo_channels WHERE slo_id IN (SELECT id FROM service_monitor_slos WHERE monitor_id = ?)",
+        params![monitor_id],
+    )?;
+    conn.execute("DELETE FROM service_monitor_slos WHERE monitor_id = ?", params![monitor_id])?;
+    Ok(())
+}
+
+pub async fn get_active_slos_for_evaluation(pool: DuckDbPool) -> Result<Vec<SloForEvaluation>, AppError> {
+    tokio::task::spawn_blocking(move || -> Result<_, AppError> {
+        let conn = pool.get()?;
+        let rows = conn
+            .prepare(
+                "SELECT s.*, m.name AS monitor_name FROM service_monitor_slos s
+                 JOIN service_monitors m ON m.id = s.monitor_id
+                 WHERE s.is_active AND m.is_active",
+            )?
+            .query_map([], |row| Ok((row_to_slo_model(row)?, row.get::<_, String>("monitor_name")?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(rows
+            .into_iter()
+            .map(|(slo, monitor_name)| {
+                Ok(SloForEvaluation {
+                    channel_ids: get_channel_ids(&conn, slo.id)?,
+                    slo,
+                    monitor_name,
+                })
+            })
+            .collect::<DuckDbResult<Vec<_>>>()?)
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+}
+
+pub async fn get_burn_rates(
+    pool: DuckDbPool,
+    slo: service_monitor_slo::Model,
+) -> Result<(BurnRates, BurnRates), AppError> {
+    tokio::task::spawn_blocking(move || -> Result<_, AppError> {
+        let conn = pool.get()?;
+        Ok(compute_burn_rates(&conn, &slo, Utc::now())?)
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+}
+
+pub async fn mark_slo_alerted(pool: DuckDbPool, slo_id: i32, at: DateTime<Utc>) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE service_monitor_slos SET last_alerted_at = ? WHERE id = ?",
+        params![at, slo_id],
+    )?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn burn_rate_is_error_ratio_over_allowed_ratio() {
+        // 99% target allows 1% errors; 2% observed errors burn at 2x.
+        let rate = burn_rate(2, 100, 99.0).unwrap();
+        assert!((rate - 2.0).abs() < 1e-9);
+        assert_eq!(burn_rate(0, 0, 99.0), None);
+
+        let rates = BurnRates { long: Some(15.0), short: Some(3.0) };
+        assert!(!rates.exceeds(14.4));
+        let rates = BurnRates { long: Some(15.0), short: Some(20.0) };
+        assert!(rates.exceeds(14.4));
+    }
+}
@@ -1,8 +1,8 @@
-use crate::db::duckdb_service::{json_from_row, DuckDbPool};
+use crate::db::duckdb_service::{json_from_row, organization_service, DuckDbPool};
 use crate::db::entities::setting;
 use crate::web::error::AppError;
 use chrono::Utc;
-use duckdb::{params, Row, Result as DuckDbResult};
+use duckdb::{params, Connection, OptionalExt, Row, Result as DuckDbResult};
 
 fn row_to_setting_model(row: &Row) -> DuckDbResult<setting::Model> {
     let value: Option<serde_json::Value> = json_from_row(row, "value")?;
@@ -27,6 +27,17 @@ pub async fn get_setting(
     }
 }
 
+/// Like `get_setting`, for callers that already hold a connection.
+pub fn get_setting_with_conn(
+    conn: &Connection,
+    key: &str,
+) -> Result<Option<setting::Model>, AppError> {
+    let setting = conn
+        .query_row("SELECT * FROM settings WHERE key = ?", params![key], row_to_setting_model)
+        .optional()?;
+    Ok(setting)
+}
+
 pub async fn update_setting(
     pool: DuckDbPool,
     key: &str,
@@ -57,7 +68,10 @@ pub async fn update_vps_config_override(
     let config_str = serde_json::to_string(config_override)?;
 
     let rows_affected = conn.execute(
-        "UPDATE vps SET agent_config_override = ?, updated_at = ? WHERE id = ? AND user_id = ?",
+        &format!(
+            "UPDATE vps SET agent_config_override = ?, updated_at = ? WHERE id = ? AND {}",
+            organization_service::org_scope("organization_id")
+        ),
         params![config_str, now, vps_id, user_id],
     )?;
     Ok(rows_affected as u64)
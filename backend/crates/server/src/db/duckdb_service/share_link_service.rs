@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, OptionalExt, Result as DuckDbResult, Row};
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::share_link;
+use crate::web::error::AppError;
+
+fn row_to_share_link_model(row: &Row) -> DuckDbResult<share_link::Model> {
+    Ok(share_link::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        organization_id: row.get("organization_id")?,
+        name: row.get("name")?,
+        vps_id: row.get("vps_id")?,
+        client_id: row.get("client_id")?,
+        expires_at: row.get("expires_at")?,
+        last_accessed_at: row.get("last_accessed_at")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+pub async fn create_share_link(
+    pool: DuckDbPool,
+    user_id: i32,
+    organization_id: i32,
+    name: &str,
+    vps_id: Option<i32>,
+    client_id: Option<i32>,
+    expires_at: DateTime<Utc>,
+) -> Result<share_link::Model, AppError> {
+    let conn = pool.get()?;
+    let link = conn.query_row(
+        "INSERT INTO share_links (user_id, organization_id, name, vps_id, client_id, expires_at)
+         VALUES (?, ?, ?, ?, ?, ?) RETURNING *",
+        params![user_id, organization_id, name, vps_id, client_id, expires_at],
+        row_to_share_link_model,
+    )?;
+    Ok(link)
+}
+
+pub async fn get_share_links_for_user(
+    pool: DuckDbPool,
+    user_id: i32,
+) -> Result<Vec<share_link::Model>, AppError> {
+    let conn = pool.get()?;
+    let links = conn
+        .prepare("SELECT * FROM share_links WHERE user_id = ? ORDER BY created_at DESC")?
+        .query_map(params![user_id], row_to_share_link_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(links)
+}
+
+/// Revokes a share link. Tokens issued for it stop working immediately.
+pub async fn delete_share_link(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute(
+        "DELETE FROM share_links WHERE id = ? AND user_id = ?",
+        params![id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("Share link not found".to_string()));
+    }
+    Ok(())
+}
+
+/// Returns the share link if it still exists and hasn't expired, and records the access.
+pub async fn get_active_share_link(
+    pool: DuckDbPool,
+    id: i32,
+) -> Result<Option<share_link::Model>, AppError> {
+    let conn = pool.get()?;
+    let link = conn
+        .query_row(
+            "UPDATE share_links SET last_accessed_at = ? WHERE id = ? AND expires_at > ? RETURNING *",
+            params![Utc::now(), id, Utc::now()],
+            row_to_share_link_model,
+        )
+        .optional()?;
+    Ok(link)
+}
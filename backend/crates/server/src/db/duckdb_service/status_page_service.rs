@@ -0,0 +1,440 @@
+//! Public status pages: which VPS and monitors a page shows, and the
+//! read-only view served to anonymous visitors. The view carries only names,
+//! status, uptime and latency; no addresses, targets or check details.
+
+use chrono::{DateTime, Duration, Utc};
+use duckdb::{params, params_from_iter, Connection, OptionalExt, Result as DuckDbResult, Row};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::db::duckdb_service::uptime_service::VpsUptime;
+use crate::db::duckdb_service::{json_from_row, organization_service, DuckDbPool};
+use crate::db::entities::status_page;
+use crate::web::error::AppError;
+use crate::web::models::branding_models::BrandingResponse;
+use crate::web::models::websocket_models::ServerWithDetails;
+
+pub const MAX_SLUG_LENGTH: usize = 64;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusPageInput {
+    pub slug: String,
+    pub title: String,
+    pub description: Option<String>,
+    #[serde(default)]
+    pub vps_ids: Vec<i32>,
+    #[serde(default)]
+    pub monitor_ids: Vec<i32>,
+    #[serde(default = "default_true")]
+    pub show_uptime: bool,
+    #[serde(default = "default_true")]
+    pub show_latency: bool,
+    #[serde(default = "default_true")]
+    pub is_published: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MonitorStatus {
+    Up,
+    /// Some of the agents running the monitor see it down.
+    Degraded,
+    Down,
+    /// No recent results.
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusPageServer {
+    pub id: i32,
+    pub name: String,
+    pub status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime: Option<VpsUptime>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusPageMonitor {
+    pub id: i32,
+    pub name: String,
+    pub status: MonitorStatus,
+    /// Average latency of the latest check of each agent.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latency_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub uptime: Option<VpsUptime>,
+}
+
+/// Everything a public status page shows.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StatusPageView {
+    pub slug: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub generated_at: DateTime<Utc>,
+    pub branding: BrandingResponse,
+    pub servers: Vec<StatusPageServer>,
+    pub monitors: Vec<StatusPageMonitor>,
+}
+
+fn row_to_status_page_model(row: &Row) -> DuckDbResult<status_page::Model> {
+    let ids = |col: &str| -> DuckDbResult<Vec<i32>> {
+        Ok(json_from_row(row, col)?
+            .and_then(|v| serde_json::from_value(v).ok())
+            .unwrap_or_default())
+    };
+    Ok(status_page::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        slug: row.get("slug")?,
+        title: row.get("title")?,
+        description: row.get("description")?,
+        vps_ids: ids("vps_ids")?,
+        monitor_ids: ids("monitor_ids")?,
+        show_uptime: row.get("show_uptime")?,
+        show_latency: row.get("show_latency")?,
+        is_published: row.get("is_published")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+/// Normalizes a slug to lowercase and checks it is made of letters, digits
+/// and inner hyphens.
+pub fn normalize_slug(slug: &str) -> Result<String, AppError> {
+    let slug = slug.trim().to_ascii_lowercase();
+    let valid = !slug.is_empty()
+        && slug.len() <= MAX_SLUG_LENGTH
+        && slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !slug.starts_with('-')
+        && !slug.ends_with('-');
+    if !valid {
+        return Err(AppError::InvalidInput(format!(
+            "Slug must be 1-{MAX_SLUG_LENGTH} letters, digits or inner hyphens"
+        )));
+    }
+    Ok(slug)
+}
+
+/// Up when every agent's latest result is up, down when none is.
+pub fn combine_monitor_status(latest_up: &[bool]) -> MonitorStatus {
+    match (latest_up.iter().any(|up| *up), latest_up.iter().all(|up| *up)) {
+        _ if latest_up.is_empty() => MonitorStatus::Unknown,
+        (_, true) => MonitorStatus::Up,
+        (true, false) => MonitorStatus::Degraded,
+        (false, _) => MonitorStatus::Down,
+    }
+}
+
+/// Rejects IDs of VPS or monitors the user doesn't own.
+fn check_ownership(conn: &Connection, user_id: i32, input: &StatusPageInput) -> Result<(), AppError> {
+    for (table, ids, what) in [("vps", &input.vps_ids, "VPS"), ("service_monitors", &input.monitor_ids, "Monitor")] {
+        if ids.is_empty() {
+            continue;
+        }
+        let placeholders = vec!["?"; ids.len()].join(",");
+        let owned: Vec<i32> = conn
+            .prepare(&format!("SELECT id FROM {table} WHERE user_id = ? AND id IN ({placeholders})"))?
+            .query_map(params_from_iter(std::iter::once(&user_id).chain(ids.iter())), |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?;
+        if let Some(unknown) = ids.iter().find(|id| !owned.contains(id)) {
+            return Err(AppError::NotFound(format!("{what} {unknown} not found")));
+        }
+    }
+    Ok(())
+}
+
+fn check_slug_available(conn: &Connection, slug: &str, except_id: Option<i32>) -> Result<(), AppError> {
+    let taken: Option<i32> = conn
+        .query_row(
+            "SELECT id FROM status_pages WHERE slug = ? AND id IS DISTINCT FROM ?",
+            params![slug, except_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    if taken.is_some() {
+        return Err(AppError::Conflict(format!("Slug '{slug}' is already in use")));
+    }
+    Ok(())
+}
+
+fn validate_input(input: &StatusPageInput) -> Result<String, AppError> {
+    if input.title.trim().is_empty() {
+        return Err(AppError::InvalidInput("Title must not be empty".to_string()));
+    }
+    normalize_slug(&input.slug)
+}
+
+pub async fn create_status_page(
+    pool: DuckDbPool,
+    user_id: i32,
+    input: &StatusPageInput,
+) -> Result<status_page::Model, AppError> {
+    let slug = validate_input(input)?;
+    let conn = pool.get()?;
+    check_ownership(&conn, user_id, input)?;
+    check_slug_available(&conn, &slug, None)?;
+    let page = conn.query_row(
+        "INSERT INTO status_pages (user_id, slug, title, description, vps_ids, monitor_ids, show_uptime, show_latency, is_published)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        params![
+            user_id,
+            slug,
+            input.title.trim(),
+            input.description,
+            serde_json::to_string(&input.vps_ids)?,
+            serde_json::to_string(&input.monitor_ids)?,
+            input.show_uptime,
+            input.show_latency,
+            input.is_published,
+        ],
+        row_to_status_page_model,
+    )?;
+    Ok(page)
+}
+
+pub async fn update_status_page(
+    pool: DuckDbPool,
+    user_id: i32,
+    id: i32,
+    input: &StatusPageInput,
+) -> Result<status_page::Model, AppError> {
+    let slug = validate_input(input)?;
+    let conn = pool.get()?;
+    check_ownership(&conn, user_id, input)?;
+    check_slug_available(&conn, &slug, Some(id))?;
+    let page = conn
+        .query_row(
+            "UPDATE status_pages SET slug = ?, title = ?, description = ?, vps_ids = ?, monitor_ids = ?,
+                 show_uptime = ?, show_latency = ?, is_published = ?, updated_at = ?
+             WHERE id = ? AND user_id = ? RETURNING *",
+            params![
+                slug,
+                input.title.trim(),
+                input.description,
+                serde_json::to_string(&input.vps_ids)?,
+                serde_json::to_string(&input.monitor_ids)?,
+                input.show_uptime,
+                input.show_latency,
+                input.is_published,
+                Utc::now(),
+                id,
+                user_id,
+            ],
+            row_to_status_page_model,
+        )
+        .optional()?;
+    page.ok_or_else(|| AppError::NotFound("Status page not found".to_string()))
+}
+
+pub async fn get_status_pages_for_user(pool: DuckDbPool, user_id: i32) -> Result<Vec<status_page::Model>, AppError> {
+    let conn = pool.get()?;
+    let pages = conn
+        .prepare("SELECT * FROM status_pages WHERE user_id = ? ORDER BY created_at DESC")?
+        .query_map(params![user_id], row_to_status_page_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(pages)
+}
+
+pub async fn get_status_page(pool: DuckDbPool, user_id: i32, id: i32) -> Result<Option<status_page::Model>, AppError> {
+    let conn = pool.get()?;
+    let page = conn
+        .query_row(
+            "SELECT * FROM status_pages WHERE id = ? AND user_id = ?",
+            params![id, user_id],
+            row_to_status_page_model,
+        )
+        .optional()?;
+    Ok(page)
+}
+
+/// The published page with this slug, as shown to anonymous visitors.
+pub async fn get_published_status_page(pool: DuckDbPool, slug: &str) -> Result<Option<status_page::Model>, AppError> {
+    let conn = pool.get()?;
+    let page = conn
+        .query_row(
+            "SELECT * FROM status_pages WHERE slug = ? AND is_published",
+            params![slug.to_ascii_lowercase()],
+            row_to_status_page_model,
+        )
+        .optional()?;
+    Ok(page)
+}
+
+/// Monitors shown on any of the user's published pages, in page order.
+pub async fn get_published_monitor_ids(pool: DuckDbPool, user_id: i32) -> Result<Vec<i32>, AppError> {
+    let mut ids: Vec<i32> = Vec::new();
+    for page in get_status_pages_for_user(pool, user_id).await? {
+        if !page.is_published {
+            continue;
+        }
+        for id in page.monitor_ids {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+    }
+    Ok(ids)
+}
+
+pub async fn delete_status_page(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute("DELETE FROM status_pages WHERE id = ? AND user_id = ?", params![id, user_id])?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("Status page not found".to_string()));
+    }
+    Ok(())
+}
+
+fn monitor_uptime(conn: &Connection, monitor_id: i32, now: DateTime<Utc>) -> Result<VpsUptime, AppError> {
+    let uptime = conn.query_row(
+        "SELECT
+             100.0 * avg(CASE WHEN is_up THEN 1.0 ELSE 0.0 END) FILTER (WHERE time >= ?),
+             100.0 * avg(CASE WHEN is_up THEN 1.0 ELSE 0.0 END) FILTER (WHERE time >= ?),
+             100.0 * avg(CASE WHEN is_up THEN 1.0 ELSE 0.0 END)
+         FROM service_monitor_results
+         WHERE monitor_id = ? AND time >= ? AND NOT in_maintenance",
+        params![now - Duration::hours(24), now - Duration::days(7), monitor_id, now - Duration::days(30)],
+        |row| {
+            Ok(VpsUptime {
+                uptime_24h: row.get(0)?,
+                uptime_7d: row.get(1)?,
+                uptime_30d: row.get(2)?,
+            })
+        },
+    )?;
+    Ok(uptime)
+}
+
+/// What a summary includes besides name and status.
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayOptions {
+    pub show_uptime: bool,
+    pub show_latency: bool,
+}
+
+impl From<&status_page::Model> for DisplayOptions {
+    fn from(page: &status_page::Model) -> Self {
+        Self {
+            show_uptime: page.show_uptime,
+            show_latency: page.show_latency,
+        }
+    }
+}
+
+/// The desensitized summary of a live server.
+pub fn server_summary(server: &ServerWithDetails, options: DisplayOptions) -> StatusPageServer {
+    StatusPageServer {
+        id: server.basic_info.id,
+        name: server.basic_info.name.clone(),
+        status: server.basic_info.status.clone(),
+        uptime: if options.show_uptime { server.uptime.clone() } else { None },
+    }
+}
+
+/// Status, latency and uptime of the given monitors owned by `user_id`.
+pub async fn monitor_summaries(
+    pool: DuckDbPool,
+    user_id: i32,
+    monitor_ids: &[i32],
+    options: DisplayOptions,
+    now: DateTime<Utc>,
+) -> Result<Vec<StatusPageMonitor>, AppError> {
+    if monitor_ids.is_empty() {
+        return Ok(Vec::new());
+    }
+    let conn = pool.get()?;
+    let placeholders = vec!["?"; monitor_ids.len()].join(",");
+    let monitors: Vec<(i32, String)> = conn
+        .prepare(&format!(
+            "SELECT id, name FROM service_monitors WHERE {} AND id IN ({placeholders}) ORDER BY name",
+            organization_service::org_scope("organization_id")
+        ))?
+        .query_map(
+            params_from_iter(std::iter::once(&user_id).chain(monitor_ids.iter())),
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut summaries = Vec::with_capacity(monitors.len());
+    for (id, name) in monitors {
+        let latest: Vec<(bool, Option<i32>)> = conn
+            .prepare(
+                "SELECT is_up, latency_ms FROM service_monitor_results
+                 WHERE monitor_id = ? AND time >= ?
+                 QUALIFY row_number() OVER (PARTITION BY agent_id ORDER BY time DESC) = 1",
+            )?
+            .query_map(params![id, now - Duration::hours(1)], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<Vec<_>, _>>()?;
+        let latest_up: Vec<bool> = latest.iter().map(|(up, _)| *up).collect();
+        let latencies: Vec<f64> = latest.iter().filter_map(|(_, latency)| latency.map(f64::from)).collect();
+        let latency_ms = (options.show_latency && !latencies.is_empty())
+            .then(|| latencies.iter().sum::<f64>() / latencies.len() as f64);
+        let uptime = if options.show_uptime { Some(monitor_uptime(&conn, id, now)?) } else { None };
+        summaries.push(StatusPageMonitor {
+            id,
+            name,
+            status: combine_monitor_status(&latest_up),
+            latency_ms,
+            uptime,
+        });
+    }
+    Ok(summaries)
+}
+
+/// Builds the public view of a page. Servers come from the live cache,
+/// limited to the page owner's.
+pub async fn build_status_page_view<'a>(
+    pool: DuckDbPool,
+    page: &status_page::Model,
+    servers: impl IntoIterator<Item = &'a ServerWithDetails>,
+    branding: BrandingResponse,
+    now: DateTime<Utc>,
+) -> Result<StatusPageView, AppError> {
+    let mut page_servers: Vec<StatusPageServer> = servers
+        .into_iter()
+        .filter(|s| s.basic_info.user_id == page.user_id && page.vps_ids.contains(&s.basic_info.id))
+        .map(|s| server_summary(s, page.into()))
+        .collect();
+    page_servers.sort_by_key(|s| page.vps_ids.iter().position(|id| *id == s.id));
+
+    Ok(StatusPageView {
+        slug: page.slug.clone(),
+        title: page.title.clone(),
+        description: page.description.clone(),
+        generated_at: now,
+        branding,
+        servers: page_servers,
+        monitors: monitor_summaries(pool, page.user_id, &page.monitor_ids, page.into(), now).await?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slugs_are_normalized_and_validated() {
+        assert_eq!(normalize_slug(" Main-Site ").unwrap(), "main-site");
+        assert!(normalize_slug("").is_err());
+        assert!(normalize_slug("-main").is_err());
+        assert!(normalize_slug("main site").is_err());
+        assert!(normalize_slug(&"a".repeat(MAX_SLUG_LENGTH + 1)).is_err());
+    }
+
+    #[test]
+    fn monitor_status_combines_agents() {
+        assert_eq!(combine_monitor_status(&[]), MonitorStatus::Unknown);
+        assert_eq!(combine_monitor_status(&[true, true]), MonitorStatus::Up);
+        assert_eq!(combine_monitor_status(&[true, false]), MonitorStatus::Degraded);
+        assert_eq!(combine_monitor_status(&[false]), MonitorStatus::Down);
+    }
+}
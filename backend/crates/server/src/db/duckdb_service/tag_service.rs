@@ -1,4 +1,4 @@
-use crate::db::duckdb_service::DuckDbPool;
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
 use crate::db::entities::tag;
 use crate::web::error::AppError;
 use chrono::Utc;
@@ -60,9 +60,10 @@ pub async fn create_tag(
 ) -> Result<tag::Model, AppError> {
     let conn = pool.get()?;
     let now = Utc::now();
+    let organization_id = organization_service::active_organization_id(&conn, user_id)?;
     let new_tag = conn.query_row(
-        "INSERT INTO tags (user_id, name, color, icon, url, is_visible, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
-        params![user_id, name, color, icon, url, is_visible, now, now],
+        "INSERT INTO tags (user_id, organization_id, name, color, icon, url, is_visible, created_at, updated_at) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        params![user_id, organization_id, name, color, icon, url, is_visible, now, now],
         row_to_tag_model,
     )?;
     Ok(new_tag)
@@ -73,14 +74,15 @@ pub async fn get_tags_by_user_id_with_count(
     user_id: i32,
 ) -> Result<Vec<TagWithCount>, AppError> {
     let conn = pool.get()?;
-    let mut stmt = conn.prepare(
+    let mut stmt = conn.prepare(&format!(
         "SELECT t.*, COUNT(vt.vps_id) as vps_count
          FROM tags t
          LEFT JOIN vps_tags vt ON t.id = vt.tag_id
-         WHERE t.user_id = ?
-         GROUP BY t.id, t.user_id, t.name, t.color, t.icon, t.url, t.is_visible, t.created_at, t.updated_at
+         WHERE {}
+         GROUP BY ALL
          ORDER BY t.name ASC",
-    )?;
+        organization_service::org_scope("t.organization_id")
+    ))?;
     let tags = stmt
         .query_map(params![user_id], row_to_tag_with_count)?
         .collect::<Result<Vec<_>, _>>()?;
@@ -100,14 +102,17 @@ pub async fn update_tag(
     let conn = pool.get()?;
     let now = Utc::now();
     let res = conn.query_row(
-        "UPDATE tags SET name = ?, color = ?, icon = ?, url = ?, is_visible = ?, updated_at = ? WHERE id = ? AND user_id = ? RETURNING *",
+        &format!(
+            "UPDATE tags SET name = ?, color = ?, icon = ?, url = ?, is_visible = ?, updated_at = ? WHERE id = ? AND {} RETURNING *",
+            organization_service::org_scope("organization_id")
+        ),
         params![name, color, icon, url, is_visible, now, tag_id, user_id],
         row_to_tag_model,
     );
     
     match res {
         Ok(tag) => Ok(tag),
-        Err(duckdb::Error::QueryReturnedNoRows) => Err(AppError::NotFound(format!("Tag with id {tag_id} not found for user {user_id}"))),
+        Err(duckdb::Error::QueryReturnedNoRows) => Err(AppError::NotFound(format!("Tag with id {tag_id} not found"))),
         Err(e) => Err(e.into()),
     }
 }
@@ -115,11 +120,11 @@ pub async fn update_tag(
 pub async fn delete_tag(pool: DuckDbPool, tag_id: i32, user_id: i32) -> Result<u64, AppError> {
     let conn = pool.get()?;
     let rows_affected = conn.execute(
-        "DELETE FROM tags WHERE id = ? AND user_id = ?",
+        &format!("DELETE FROM tags WHERE id = ? AND {}", organization_service::org_scope("organization_id")),
         params![tag_id, user_id],
     )?;
     if rows_affected == 0 {
-        return Err(AppError::NotFound(format!("Tag with id {tag_id} not found for user {user_id}")));
+        return Err(AppError::NotFound(format!("Tag with id {tag_id} not found")));
     }
     Ok(rows_affected as u64)
 }
@@ -184,7 +189,10 @@ pub async fn bulk_update_vps_tags(
     // Authorize
     if !vps_ids.is_empty() {
         let params_sql = vps_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
-        let sql = format!("SELECT COUNT(*) FROM vps WHERE id IN ({params_sql}) AND user_id = ?");
+        let sql = format!(
+            "SELECT COUNT(*) FROM vps WHERE id IN ({params_sql}) AND {}",
+            organization_service::org_scope("organization_id")
+        );
         
         let mut params_vec: Vec<&dyn duckdb::ToSql> = vps_ids.iter().map(|id| id as &dyn duckdb::ToSql).collect();
         params_vec.push(&user_id);
@@ -192,7 +200,7 @@ pub async fn bulk_update_vps_tags(
         let owned_vps_count: i64 = tx.query_row(&sql, &params_vec[..], |row| row.get(0))?;
 
         if owned_vps_count != vps_ids.len() as i64 {
-            return Err(AppError::Forbidden("User does not own all specified VPS".to_string()));
+            return Err(AppError::Forbidden("Not all specified VPS are in the active organization".to_string()));
         }
     }
 
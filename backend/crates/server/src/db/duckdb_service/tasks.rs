@@ -1,5 +1,15 @@
-use super::{vps_traffic_service, DuckDbPool};
-use duckdb::Connection;
+use super::{
+    archive_service,
+    backup_service::{self, BackupSchedule},
+    batch_command_service,
+    retention::RetentionPolicy,
+    traffic_report_service,
+    vps_traffic_service,
+    webhook_service,
+    DuckDbPool,
+};
+use chrono::Utc;
+use duckdb::{params, Connection};
 use std::{sync::Arc, time::Duration};
 use tokio::time;
 use tracing::{error, info, instrument};
@@ -7,13 +17,31 @@ use tracing::{error, info, instrument};
 pub struct DuckDBTaskManager {
     db_path: String,
     pool: DuckDbPool,
+    archive_dir: String,
+    event_retention_days: u32,
+    batch_output_retention_days: u32,
+    retention: RetentionPolicy,
+    backup: BackupSchedule,
 }
 
 impl DuckDBTaskManager {
-    pub fn new(db_path: &str, pool: DuckDbPool) -> Self {
+    pub fn new(
+        db_path: &str,
+        pool: DuckDbPool,
+        archive_dir: &str,
+        event_retention_days: u32,
+        batch_output_retention_days: u32,
+        retention: RetentionPolicy,
+        backup: BackupSchedule,
+    ) -> Self {
         Self {
             db_path: db_path.to_string(),
             pool,
+            archive_dir: archive_dir.to_string(),
+            event_retention_days,
+            batch_output_retention_days,
+            retention,
+            backup,
         }
     }
 
@@ -40,6 +68,52 @@ impl DuckDBTaskManager {
                 }
             });
 
+            let self_clone_for_archive = self.clone();
+            tokio::spawn(async move {
+                info!("Running scheduled event archival task...");
+                match tokio::task::spawn_blocking(move || self_clone_for_archive.perform_event_archival()).await {
+                    Ok(Ok(rows)) => info!("Event archival finished, {} rows archived.", rows),
+                    Ok(Err(e)) => error!("Error running event archival task: {:?}", e),
+                    Err(e) => error!("Error running event archival task block: {:?}", e),
+                }
+            });
+
+            let self_clone_for_outputs = self.clone();
+            tokio::spawn(async move {
+                let pool = self_clone_for_outputs.pool.clone();
+                let days = self_clone_for_outputs.batch_output_retention_days;
+                match tokio::task::spawn_blocking(move || batch_command_service::prune_expired_outputs(&pool, days)).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => error!("Error pruning batch command output: {:?}", e),
+                    Err(e) => error!("Error running batch output pruning task block: {:?}", e),
+                }
+            });
+
+            let self_clone_for_webhooks = self.clone();
+            tokio::spawn(async move {
+                let pool = self_clone_for_webhooks.pool.clone();
+                let days = self_clone_for_webhooks.event_retention_days;
+                match tokio::task::spawn_blocking(move || webhook_service::prune_deliveries(&pool, days)).await {
+                    Ok(Ok(_)) => {}
+                    Ok(Err(e)) => error!("Error pruning webhook deliveries: {:?}", e),
+                    Err(e) => error!("Error running webhook delivery pruning task block: {:?}", e),
+                }
+            });
+
+            if self.backup.interval_hours > 0 {
+                let self_clone_for_backup = self.clone();
+                tokio::spawn(async move {
+                    let backup = move || {
+                        backup_service::run_scheduled_backup(&self_clone_for_backup.pool, &self_clone_for_backup.backup)
+                    };
+                    match tokio::task::spawn_blocking(backup).await {
+                        Ok(Ok(_)) => {}
+                        Ok(Err(e)) => error!("Error running scheduled backup: {}", e),
+                        Err(e) => error!("Error running scheduled backup task block: {:?}", e),
+                    }
+                });
+            }
+
             let self_clone_for_traffic = self.clone();
             tokio::spawn(async move {
                 info!("Running scheduled DuckDB traffic reset task...");
@@ -75,6 +149,13 @@ impl DuckDBTaskManager {
         Ok(())
     }
 
+    #[instrument(skip(self), fields(archive_dir = %self.archive_dir))]
+    fn perform_event_archival(&self) -> Result<usize, super::Error> {
+        let conn = self.pool.get()?;
+        let rows = archive_service::archive_expired_rows(&conn, &self.archive_dir, self.event_retention_days)?;
+        Ok(rows)
+    }
+
     #[instrument(skip(self), fields(db_path = %self.db_path))]
     fn perform_aggregation_and_retention(&self) -> Result<(), duckdb::Error> {
         info!("Connecting to DuckDB for maintenance tasks...");
@@ -88,6 +169,7 @@ impl DuckDBTaskManager {
             self.aggregate_to_1m(&conn)?;
             self.aggregate_to_1h(&conn)?;
             self.aggregate_to_1d(&conn)?;
+            traffic_report_service::rollup_monthly_traffic(&conn, Utc::now().date_naive())?;
             info!("Data aggregation completed.");
 
             // --- Retention (Cleanup) Logic ---
@@ -255,15 +337,19 @@ impl DuckDBTaskManager {
     }
 
     fn apply_retention_policies(&self, conn: &Connection) -> Result<(), duckdb::Error> {
-        info!("Applying retention policies...");
-        // Delete raw metrics older than 24 hours
-        conn.execute("DELETE FROM performance_metrics WHERE time < now() - INTERVAL '24 hours'", [])?;
-        // Delete 1m metrics older than 7 days
-        conn.execute("DELETE FROM performance_metrics_summary_1m WHERE time < now() - INTERVAL '7 days'", [])?;
-        // Delete 1h metrics older than 30 days
-        conn.execute("DELETE FROM performance_metrics_summary_1h WHERE time < now() - INTERVAL '30 days'", [])?;
-        // Delete 1d metrics older than 365 days
-        conn.execute("DELETE FROM performance_metrics_summary_1d WHERE time < now() - INTERVAL '365 days'", [])?;
+        info!(policy = ?self.retention, "Applying retention policies...");
+        let now = Utc::now();
+        for (table, days) in self.retention.tables() {
+            let Some(cutoff) = RetentionPolicy::cutoff(days, now) else {
+                continue;
+            };
+            let deleted = conn.execute(&format!("DELETE FROM {table} WHERE time < ?"), params![cutoff])?;
+            if deleted > 0 {
+                info!(table, deleted, "Pruned expired metrics.");
+            }
+        }
+        // Process snapshots are only useful for recent troubleshooting
+        conn.execute("DELETE FROM process_metrics WHERE time < now() - INTERVAL '24 hours'", [])?;
         Ok(())
     }
-}
\ No newline at end of file
+}
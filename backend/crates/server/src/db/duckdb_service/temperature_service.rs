@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use duckdb::{params, Result as DuckDbResult, Row};
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::temperature_metric;
+use crate::web::error::AppError;
+
+/// Alert rules with this metric type compare the hottest sensor, which must
+/// satisfy the condition in every sample within their duration.
+pub const MAX_TEMPERATURE_METRIC_TYPE: &str = "temperature_celsius";
+
+fn row_to_temperature_metric_model(row: &Row) -> DuckDbResult<temperature_metric::Model> {
+    Ok(temperature_metric::Model {
+        time: row.get("time")?,
+        vps_id: row.get("vps_id")?,
+        sensor: row.get("sensor")?,
+        temperature_celsius: row.get("temperature_celsius")?,
+        critical_celsius: row.get("critical_celsius")?,
+    })
+}
+
+/// Per-sensor readings, optionally limited to one sensor. With an interval
+/// temperatures are averaged per bucket.
+pub async fn get_temperature_timeseries(
+    pool: DuckDbPool,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    interval_seconds: Option<u32>,
+    sensor: Option<String>,
+) -> Result<Vec<temperature_metric::Model>, AppError> {
+    let sensor_filter = if sensor.is_some() { "AND sensor = ?" } else { "" };
+    let sql = match interval_seconds {
+        None => format!(
+            r#"SELECT * FROM temperature_metrics
+               WHERE vps_id = ? AND "time" >= ? AND "time" <= ? {sensor_filter}
+               ORDER BY "time" ASC, sensor ASC"#
+        ),
+        Some(interval_secs) => {
+            let interval_secs = interval_secs.max(1);
+            format!(
+                r#"
+                SELECT
+                    date_trunc('second', "time") + INTERVAL '{interval_secs} seconds' * (epoch("time") / {interval_secs}) AS "time",
+                    vps_id,
+                    sensor,
+                    AVG(temperature_celsius) AS temperature_celsius,
+                    MAX(critical_celsius) AS critical_celsius
+                FROM temperature_metrics
+                WHERE vps_id = ? AND "time" >= ? AND "time" <= ? {sensor_filter}
+                GROUP BY 1, vps_id, sensor
+                ORDER BY 1 ASC, sensor ASC
+                "#
+            )
+        }
+    };
+
+    let mut params: Vec<&dyn duckdb::ToSql> = vec![&vps_id, &start_time, &end_time];
+    if let Some(sensor) = sensor.as_ref() {
+        params.push(sensor);
+    }
+    let conn = pool.get()?;
+    let metrics = conn
+        .prepare(&sql)?
+        .query_map(params.as_slice(), row_to_temperature_metric_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(metrics)
+}
+
+/// The hottest sensor for each sample in the range, oldest first.
+pub async fn get_max_temperature_values(
+    pool: DuckDbPool,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<Vec<f64>, AppError> {
+    let conn = pool.get()?;
+    let values = conn
+        .prepare(
+            r#"SELECT MAX(temperature_celsius) FROM temperature_metrics
+               WHERE vps_id = ? AND "time" >= ? AND "time" <= ?
+               GROUP BY "time" ORDER BY "time" ASC"#,
+        )?
+        .query_map(params![vps_id, start_time, end_time], |row| row.get(0))?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(values)
+}
@@ -0,0 +1,270 @@
+//! Traffic history per VPS and monthly totals per VPS, tag and group. Daily
+//! traffic is accumulated as metrics arrive (see `vps_traffic_service`); the
+//! hourly task rolls it up into `vps_monthly_traffic`.
+
+use chrono::{Datelike, NaiveDate};
+use duckdb::{params, Connection};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
+use crate::web::error::AppError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TrafficGranularity {
+    Daily,
+    Monthly,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficUsage {
+    /// The day, or the first day of the month.
+    pub period: NaiveDate,
+    pub rx_bytes: i64,
+    pub tx_bytes: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VpsTrafficTotal {
+    pub vps_id: i32,
+    pub name: String,
+    pub group: Option<String>,
+    pub traffic_limit_bytes: Option<i64>,
+    pub rx_bytes: i64,
+    pub tx_bytes: i64,
+}
+
+/// Traffic of the VPS sharing a tag or group.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficAggregate {
+    /// Tag ID; `None` for groups.
+    pub id: Option<i32>,
+    /// Tag name or group path.
+    pub name: String,
+    pub vps_count: i64,
+    pub rx_bytes: i64,
+    pub tx_bytes: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TrafficSummary {
+    pub month: NaiveDate,
+    pub rx_bytes: i64,
+    pub tx_bytes: i64,
+    /// Busiest first.
+    pub vps: Vec<VpsTrafficTotal>,
+    pub tags: Vec<TrafficAggregate>,
+    pub groups: Vec<TrafficAggregate>,
+}
+
+/// Parses a `YYYY-MM` month into its first day.
+pub fn parse_month(month: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", month.trim()), "%Y-%m-%d")
+        .map_err(|_| format!("Invalid month '{month}', expected YYYY-MM"))
+}
+
+pub fn first_of_month(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).expect("every month has a first day")
+}
+
+/// Recomputes the monthly totals of every month from `since` on. The previous
+/// month is included so traffic recorded just before midnight on its last day
+/// isn't missed.
+pub fn rollup_monthly_traffic(conn: &Connection, today: NaiveDate) -> duckdb::Result<usize> {
+    let since = first_of_month(first_of_month(today).pred_opt().unwrap_or(today));
+    conn.execute(
+        "INSERT INTO vps_monthly_traffic (vps_id, month, total_rx, total_tx)
+         SELECT vps_id, CAST(date_trunc('month', day) AS DATE), SUM(rx_bytes), SUM(tx_bytes)
+         FROM vps_daily_traffic
+         WHERE day >= ?
+         GROUP BY vps_id, CAST(date_trunc('month', day) AS DATE)
+         ON CONFLICT (vps_id, month) DO UPDATE SET
+             total_rx = excluded.total_rx,
+             total_tx = excluded.total_tx",
+        params![since],
+    )
+}
+
+/// Traffic of one VPS per day or month between `from` and `to`, inclusive.
+/// Months are matched by their first day.
+pub async fn get_vps_traffic_history(
+    pool: DuckDbPool,
+    vps_id: i32,
+    granularity: TrafficGranularity,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<TrafficUsage>, AppError> {
+    let conn = pool.get()?;
+    let (sql, from) = match granularity {
+        TrafficGranularity::Daily => (
+            "SELECT day, rx_bytes, tx_bytes FROM vps_daily_traffic
+             WHERE vps_id = ? AND day >= ? AND day <= ? ORDER BY day",
+            from,
+        ),
+        TrafficGranularity::Monthly => (
+            "SELECT month, total_rx, total_tx FROM vps_monthly_traffic
+             WHERE vps_id = ? AND month >= ? AND month <= ? ORDER BY month",
+            first_of_month(from),
+        ),
+    };
+    let usage = conn
+        .prepare(sql)?
+        .query_map(params![vps_id, from, to], |row| {
+            Ok(TrafficUsage {
+                period: row.get(0)?,
+                rx_bytes: row.get(1)?,
+                tx_bytes: row.get(2)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(usage)
+}
+
+/// Adds up the VPS totals per key; each VPS counts once per key.
+fn aggregate<K: Ord>(
+    vps: &[VpsTrafficTotal],
+    keys_of: impl Fn(&VpsTrafficTotal) -> Vec<(K, Option<i32>, String)>,
+) -> Vec<TrafficAggregate> {
+    let mut by_key: BTreeMap<K, TrafficAggregate> = BTreeMap::new();
+    for v in vps {
+        for (key, id, name) in keys_of(v) {
+            let entry = by_key.entry(key).or_insert_with(|| TrafficAggregate {
+                id,
+                name,
+                vps_count: 0,
+                rx_bytes: 0,
+                tx_bytes: 0,
+            });
+            entry.vps_count += 1;
+            entry.rx_bytes += v.rx_bytes;
+            entry.tx_bytes += v.tx_bytes;
+        }
+    }
+    let mut aggregates: Vec<_> = by_key.into_values().collect();
+    aggregates.sort_by_key(|a| std::cmp::Reverse(a.rx_bytes + a.tx_bytes));
+    aggregates
+}
+
+/// Builds the summary from per-VPS totals and the tags of each VPS.
+pub fn summarize(
+    month: NaiveDate,
+    mut vps: Vec<VpsTrafficTotal>,
+    tags: &HashMap<i32, Vec<(i32, String)>>,
+) -> TrafficSummary {
+    vps.sort_by_key(|v| std::cmp::Reverse(v.rx_bytes + v.tx_bytes));
+    let tag_totals = aggregate(&vps, |v| {
+        tags.get(&v.vps_id)
+            .into_iter()
+            .flatten()
+            .map(|(id, name)| (*id, Some(*id), name.clone()))
+            .collect()
+    });
+    let group_totals = aggregate(&vps, |v| {
+        v.group
+            .iter()
+            .filter(|g| !g.is_empty())
+            .map(|g| (g.clone(), None, g.clone()))
+            .collect()
+    });
+    TrafficSummary {
+        month,
+        rx_bytes: vps.iter().map(|v| v.rx_bytes).sum(),
+        tx_bytes: vps.iter().map(|v| v.tx_bytes).sum(),
+        vps,
+        tags: tag_totals,
+        groups: group_totals,
+    }
+}
+
+/// Monthly traffic of the user's VPS, with totals per tag and group. VPS
+/// without traffic that month are left out.
+pub async fn get_traffic_summary(pool: DuckDbPool, user_id: i32, month: NaiveDate) -> Result<TrafficSummary, AppError> {
+    let conn = pool.get()?;
+    let vps = conn
+        .prepare(&format!(
+            "SELECT v.id, v.name, v.\"group\", v.traffic_limit_bytes, m.total_rx, m.total_tx
+             FROM vps v JOIN vps_monthly_traffic m ON m.vps_id = v.id AND m.month = ?
+             WHERE {}",
+            organization_service::org_scope("v.organization_id")
+        ))?
+        .query_map(params![first_of_month(month), user_id], |row| {
+            Ok(VpsTrafficTotal {
+                vps_id: row.get(0)?,
+                name: row.get(1)?,
+                group: row.get(2)?,
+                traffic_limit_bytes: row.get(3)?,
+                rx_bytes: row.get(4)?,
+                tx_bytes: row.get(5)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut tags: HashMap<i32, Vec<(i32, String)>> = HashMap::new();
+    let mut stmt = conn.prepare(&format!(
+        "SELECT vt.vps_id, t.id, t.name FROM vps_tags vt
+         JOIN tags t ON t.id = vt.tag_id
+         JOIN vps v ON v.id = vt.vps_id
+         WHERE {}",
+        organization_service::org_scope("v.organization_id")
+    ))?;
+    let rows = stmt.query_map(params![user_id], |row| {
+        Ok((row.get::<_, i32>(0)?, row.get::<_, i32>(1)?, row.get::<_, String>(2)?))
+    })?;
+    for row in rows {
+        let (vps_id, tag_id, name) = row?;
+        tags.entry(vps_id).or_default().push((tag_id, name));
+    }
+
+    Ok(summarize(first_of_month(month), vps, &tags))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vps(vps_id: i32, group: Option<&str>, rx_bytes: i64, tx_bytes: i64) -> VpsTrafficTotal {
+        VpsTrafficTotal {
+            vps_id,
+            name: format!("vps-{vps_id}"),
+            group: group.map(str::to_string),
+            traffic_limit_bytes: None,
+            rx_bytes,
+            tx_bytes,
+        }
+    }
+
+    #[test]
+    fn parses_months() {
+        assert_eq!(parse_month("2024-06"), Ok(NaiveDate::from_ymd_opt(2024, 6, 1).unwrap()));
+        assert!(parse_month("2024-13").is_err());
+        assert!(parse_month("June").is_err());
+    }
+
+    #[test]
+    fn summarizes_by_tag_and_group() {
+        let month = NaiveDate::from_ymd_opt(2024, 6, 1).unwrap();
+        let tags = HashMap::from([
+            (1, vec![(10, "prod".to_string()), (11, "web".to_string())]),
+            (2, vec![(10, "prod".to_string())]),
+        ]);
+        let summary = summarize(
+            month,
+            vec![vps(1, Some("eu"), 100, 10), vps(2, Some("eu"), 500, 50), vps(3, None, 1, 1)],
+            &tags,
+        );
+
+        assert_eq!(summary.rx_bytes, 601);
+        assert_eq!(summary.tx_bytes, 61);
+        assert_eq!(summary.vps.iter().map(|v| v.vps_id).collect::<Vec<_>>(), vec![2, 1, 3]);
+        assert_eq!(summary.tags.len(), 2);
+        assert_eq!((summary.tags[0].id, summary.tags[0].vps_count, summary.tags[0].rx_bytes), (Some(10), 2, 600));
+        assert_eq!((summary.tags[1].name.as_str(), summary.tags[1].rx_bytes), ("web", 100));
+        assert_eq!(summary.groups.len(), 1);
+        assert_eq!((summary.groups[0].name.as_str(), summary.groups[0].tx_bytes), ("eu", 60));
+    }
+}
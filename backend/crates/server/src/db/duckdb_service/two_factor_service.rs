@@ -0,0 +1,185 @@
+//! TOTP enrollment and verification. A user enrolls by requesting a secret,
+//! then confirms with a first code, which enables 2FA and hands out the
+//! recovery codes. Until confirmed, login is unaffected.
+
+use chrono::Utc;
+use duckdb::{params, Connection, OptionalExt, Result as DuckDbResult, Row};
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::db::duckdb_service::{json_from_row, DuckDbPool};
+use crate::db::entities::user_totp;
+use crate::notifications::encryption::EncryptionService;
+use crate::services::totp;
+use crate::web::error::AppError;
+
+/// Held while recording a used TOTP step, so of two logins racing with the
+/// same code only the first one advances the step.
+static STEP_LOCK: Mutex<()> = Mutex::new(());
+
+fn row_to_user_totp(row: &Row) -> DuckDbResult<user_totp::Model> {
+    Ok(user_totp::Model {
+        user_id: row.get("user_id")?,
+        secret: row.get("secret")?,
+        enabled: row.get("enabled")?,
+        recovery_code_hashes: json_from_row(row, "recovery_code_hashes")?
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default(),
+        last_used_step: row.get("last_used_step")?,
+        created_at: row.get("created_at")?,
+        confirmed_at: row.get("confirmed_at")?,
+    })
+}
+
+fn get_user_totp(conn: &Connection, user_id: i32) -> Result<Option<user_totp::Model>, AppError> {
+    Ok(conn
+        .query_row("SELECT * FROM user_totp WHERE user_id = ?", params![user_id], row_to_user_totp)
+        .optional()?)
+}
+
+fn decrypt_secret(encryption_service: &EncryptionService, model: &user_totp::Model) -> Result<Vec<u8>, AppError> {
+    encryption_service
+        .decrypt(&model.secret)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+/// Whether 2FA is enabled, and how many recovery codes are left.
+pub async fn get_status(pool: DuckDbPool, user_id: i32) -> Result<(bool, usize), AppError> {
+    let conn = pool.get()?;
+    Ok(match get_user_totp(&conn, user_id)? {
+        Some(model) if model.enabled => (true, model.recovery_code_hashes.len()),
+        _ => (false, 0),
+    })
+}
+
+pub async fn is_enabled(pool: DuckDbPool, user_id: i32) -> Result<bool, AppError> {
+    Ok(get_status(pool, user_id).await?.0)
+}
+
+/// Starts (or restarts) enrollment and returns the new secret. Refused while
+/// 2FA is enabled; it has to be disabled first.
+pub async fn start_enrollment(
+    pool: DuckDbPool,
+    encryption_service: &EncryptionService,
+    user_id: i32,
+) -> Result<Vec<u8>, AppError> {
+    let conn = pool.get()?;
+    if get_user_totp(&conn, user_id)?.is_some_and(|model| model.enabled) {
+        return Err(AppError::Conflict("Two-factor authentication is already enabled".to_string()));
+    }
+    let secret = totp::generate_secret();
+    let encrypted = encryption_service
+        .encrypt(&secret)
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    conn.execute(
+        "INSERT INTO user_totp (user_id, secret, enabled, recovery_code_hashes, created_at) VALUES (?, ?, false, '[]', ?)
+         ON CONFLICT (user_id) DO UPDATE SET secret = excluded.secret, last_used_step = NULL, created_at = excluded.created_at",
+        params![user_id, encrypted, Utc::now()],
+    )?;
+    Ok(secret)
+}
+
+/// Enables 2FA when `code` matches the pending secret. Returns the recovery
+/// codes, which are only ever shown this once.
+pub async fn confirm_enrollment(
+    pool: DuckDbPool,
+    encryption_service: &EncryptionService,
+    user_id: i32,
+    code: &str,
+) -> Result<Vec<String>, AppError> {
+    let conn = pool.get()?;
+    let model = get_user_totp(&conn, user_id)?
+        .filter(|model| !model.enabled)
+        .ok_or_else(|| AppError::Conflict("Start enrollment before confirming it".to_string()))?;
+    let secret = decrypt_secret(encryption_service, &model)?;
+    let step = totp::verify_code(&secret, code, Utc::now().timestamp(), None)
+        .ok_or_else(|| AppError::InvalidInput("The code is not valid".to_string()))?;
+
+    let recovery_codes = totp::generate_recovery_codes();
+    let hashes: Vec<String> = recovery_codes.iter().map(|c| totp::hash_recovery_code(c)).collect();
+    conn.execute(
+        "UPDATE user_totp SET enabled = true, recovery_code_hashes = ?, last_used_step = ?, confirmed_at = ?
+         WHERE user_id = ?",
+        params![serde_json::to_string(&hashes)?, step, Utc::now(), user_id],
+    )?;
+    Ok(recovery_codes)
+}
+
+/// Checks a TOTP code or an unused recovery code for a user with 2FA
+/// enabled. Recovery codes are consumed.
+pub async fn verify(
+    pool: DuckDbPool,
+    encryption_service: &EncryptionService,
+    user_id: i32,
+    code: &str,
+) -> Result<bool, AppError> {
+    let mut conn = pool.get()?;
+    let Some(model) = get_user_totp(&conn, user_id)?.filter(|model| model.enabled) else {
+        return Ok(false);
+    };
+    let secret = decrypt_secret(encryption_service, &model)?;
+    if let Some(step) = totp::verify_code(&secret, code, Utc::now().timestamp(), model.last_used_step) {
+        let recorded = {
+            let _guard = STEP_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            conn.execute(
+                "UPDATE user_totp SET last_used_step = ?
+                 WHERE user_id = ? AND (last_used_step IS NULL OR last_used_step < ?)",
+                params![step, user_id, step],
+            )
+        };
+        return match recorded {
+            Ok(updated) => Ok(updated == 1),
+            // Best effort: replay protection must not lock users out of a
+            // read-only (degraded) database.
+            Err(e) => {
+                warn!(user_id, error = %e, "Failed to record the used TOTP step.");
+                Ok(true)
+            }
+        };
+    }
+
+    let Some(remaining) = consume_recovery_code(&mut conn, user_id, &totp::hash_recovery_code(code))? else {
+        return Ok(false);
+    };
+    warn!(user_id, remaining, "Recovery code used for two-factor login.");
+    Ok(true)
+}
+
+/// Removes a recovery code and returns how many are left. The update only
+/// applies if the codes haven't changed since they were read, so two logins
+/// racing with the same code can't both use it.
+fn consume_recovery_code(conn: &mut Connection, user_id: i32, hash: &str) -> Result<Option<usize>, AppError> {
+    let tx = conn.transaction()?;
+    let Some(stored) = tx
+        .query_row(
+            "SELECT CAST(recovery_code_hashes AS VARCHAR) FROM user_totp WHERE user_id = ? AND enabled",
+            params![user_id],
+            |row| row.get::<_, String>(0),
+        )
+        .optional()?
+    else {
+        return Ok(None);
+    };
+    let mut remaining: Vec<String> = serde_json::from_str(&stored)?;
+    let Some(index) = remaining.iter().position(|h| h == hash) else {
+        return Ok(None);
+    };
+    remaining.remove(index);
+    let updated = tx.execute(
+        "UPDATE user_totp SET recovery_code_hashes = ?
+         WHERE user_id = ? AND CAST(recovery_code_hashes AS VARCHAR) = ?",
+        params![serde_json::to_string(&remaining)?, user_id, stored],
+    )?;
+    if updated == 0 {
+        return Ok(None);
+    }
+    tx.commit()?;
+    Ok(Some(remaining.len()))
+}
+
+/// Disables 2FA. Callers check a current code (or recovery code) first.
+pub async fn disable(pool: DuckDbPool, user_id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    conn.execute("DELETE FROM user_totp WHERE user_id = ?", params![user_id])?;
+    Ok(())
+}
@@ -0,0 +1,359 @@
+//! Weekly and monthly uptime SLA reports. Reports are computed from VPS
+//! status events, service monitor results and performance metrics, and stored
+//! as snapshots so later retention cleanups don't change them.
+
+use chrono::{DateTime, Utc};
+use duckdb::{params, Connection, OptionalExt, Result as DuckDbResult, Row};
+use std::fmt::Write;
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::db::duckdb_service::notification_service::{self, check_channel_ownership};
+use crate::db::duckdb_service::{json_from_row, organization_service, uptime_service, DuckDbPool};
+use crate::notifications::encryption::EncryptionService;
+use crate::web::error::AppError;
+use crate::web::models::report_models::{
+    MonitorUptimeEntry, ReportPeriod, UptimeReport, UptimeReportData, UptimeReportSchedule, VpsUptimeEntry,
+};
+
+fn row_to_uptime_report(row: &Row) -> DuckDbResult<UptimeReport> {
+    let period: String = row.get("period")?;
+    Ok(UptimeReport {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        period: ReportPeriod::parse(&period).unwrap_or(ReportPeriod::Monthly),
+        period_start: row.get("period_start")?,
+        period_end: row.get("period_end")?,
+        generated_at: row.get("generated_at")?,
+        data: json_from_row(row, "data")?
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default(),
+    })
+}
+
+fn vps_entries(
+    conn: &Connection,
+    user_id: i32,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<VpsUptimeEntry>, AppError> {
+    let grace_seconds = uptime_service::load_grace_seconds(conn)?;
+    let vps_list: Vec<(i32, String)> = conn
+        .prepare(&format!(
+            "SELECT id, name FROM vps WHERE {} AND (archived_at IS NULL OR archived_at > ?) ORDER BY name",
+            organization_service::org_scope("organization_id")
+        ))?
+        .query_map(params![user_id, start], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut entries = Vec::with_capacity(vps_list.len());
+    for (vps_id, name) in vps_list {
+        let availability = uptime_service::availability_since(conn, vps_id, start, end, grace_seconds)?;
+        let (mean_check_latency_ms, metric_samples): (Option<f64>, i64) = conn.query_row(
+            "SELECT
+                 (SELECT avg(latency_ms) FROM service_monitor_results
+                  WHERE agent_id = ? AND time >= ? AND time < ? AND is_up),
+                 (SELECT count(*) FROM performance_metrics WHERE vps_id = ? AND time >= ? AND time < ?)",
+            params![vps_id, start, end, vps_id, start, end],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        entries.push(VpsUptimeEntry {
+            vps_id,
+            name,
+            uptime_percent: availability.uptime_percent,
+            downtime_seconds: availability.downtime_seconds,
+            outage_count: availability.outages.len(),
+            mean_check_latency_ms,
+            metric_samples,
+        });
+    }
+    Ok(entries)
+}
+
+fn monitor_entries(
+    conn: &Connection,
+    user_id: i32,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<MonitorUptimeEntry>, AppError> {
+    // Checks during maintenance windows don't count against the SLA.
+    let entries = conn
+        .prepare(
+            "SELECT m.id, m.name, m.monitor_type,
+                    count(r.time),
+                    count(r.time) FILTER (WHERE NOT r.is_up),
+                    avg(r.latency_ms) FILTER (WHERE r.is_up)
+             FROM service_monitors m
+             LEFT JOIN service_monitor_results r
+                 ON r.monitor_id = m.id AND r.time >= ? AND r.time < ? AND NOT r.in_maintenance
+             WHERE m.user_id = ?
+             GROUP BY m.id, m.name, m.monitor_type
+             ORDER BY m.name",
+        )?
+        .query_map(params![start, end, user_id], |row| {
+            let check_count: i64 = row.get(3)?;
+            let failed_check_count: i64 = row.get(4)?;
+            Ok(MonitorUptimeEntry {
+                monitor_id: row.get(0)?,
+                name: row.get(1)?,
+                monitor_type: row.get(2)?,
+                uptime_percent: (check_count > 0)
+                    .then(|| (check_count - failed_check_count) as f64 * 100.0 / check_count as f64),
+                mean_latency_ms: row.get(5)?,
+                check_count,
+                failed_check_count,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries)
+}
+
+/// Computes the report for the period containing `period_of` and stores it,
+/// replacing an earlier snapshot of the same period.
+pub async fn generate_uptime_report(
+    pool: DuckDbPool,
+    user_id: i32,
+    period: ReportPeriod,
+    period_of: DateTime<Utc>,
+) -> Result<UptimeReport, AppError> {
+    let (start, end) = period.bounds(period_of);
+    let now = Utc::now();
+    let conn = pool.get()?;
+    // A report of the running period only covers what has happened so far.
+    let data = UptimeReportData {
+        vps: vps_entries(&conn, user_id, start, end.min(now))?,
+        monitors: monitor_entries(&conn, user_id, start, end.min(now))?,
+    };
+    let report = conn.query_row(
+        "INSERT INTO uptime_reports (user_id, period, period_start, period_end, generated_at, data)
+         VALUES (?, ?, ?, ?, ?, ?)
+         ON CONFLICT (user_id, period, period_start)
+         DO UPDATE SET period_end = excluded.period_end, generated_at = excluded.generated_at, data = excluded.data
+         RETURNING *",
+        params![user_id, period.as_str(), start, end, now, serde_json::to_string(&data)?],
+        row_to_uptime_report,
+    )?;
+    Ok(report)
+}
+
+pub async fn list_uptime_reports(
+    pool: DuckDbPool,
+    user_id: i32,
+    period: Option<ReportPeriod>,
+) -> Result<Vec<UptimeReport>, AppError> {
+    let conn = pool.get()?;
+    let reports = conn
+        .prepare(
+            "SELECT * FROM uptime_reports WHERE user_id = ? AND (? IS NULL OR period = ?)
+             ORDER BY period_start DESC, period",
+        )?
+        .query_map(
+            params![user_id, period.map(|p| p.as_str()), period.map(|p| p.as_str())],
+            row_to_uptime_report,
+        )?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(reports)
+}
+
+pub async fn get_uptime_report(pool: DuckDbPool, user_id: i32, id: i32) -> Result<Option<UptimeReport>, AppError> {
+    let conn = pool.get()?;
+    let report = conn
+        .query_row(
+            "SELECT * FROM uptime_reports WHERE id = ? AND user_id = ?",
+            params![id, user_id],
+            row_to_uptime_report,
+        )
+        .optional()?;
+    Ok(report)
+}
+
+pub async fn delete_uptime_report(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute("DELETE FROM uptime_reports WHERE id = ? AND user_id = ?", params![id, user_id])?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("Uptime report not found".to_string()));
+    }
+    Ok(())
+}
+
+pub async fn get_report_schedule(pool: DuckDbPool, user_id: i32) -> Result<UptimeReportSchedule, AppError> {
+    let conn = pool.get()?;
+    let periods: Option<(bool, bool)> = conn
+        .query_row(
+            "SELECT weekly, monthly FROM uptime_report_schedules WHERE user_id = ?",
+            params![user_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let channel_ids = conn
+        .prepare("SELECT channel_id FROM uptime_report_channels WHERE user_id = ? ORDER BY channel_id")?
+        .query_map(params![user_id], |row| row.get(0))?
+        .collect::<Result<Vec<i32>, _>>()?;
+
+    let (weekly, monthly) = periods.unwrap_or_default();
+    Ok(UptimeReportSchedule { weekly, monthly, channel_ids })
+}
+
+pub async fn update_report_schedule(
+    pool: DuckDbPool,
+    user_id: i32,
+    schedule: UptimeReportSchedule,
+) -> Result<UptimeReportSchedule, AppError> {
+    let mut conn = pool.get()?;
+    check_channel_ownership(&conn, user_id, &schedule.channel_ids)?;
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO uptime_report_schedules (user_id, weekly, monthly, updated_at) VALUES (?, ?, ?, ?)
+         ON CONFLICT (user_id) DO UPDATE SET weekly = excluded.weekly, monthly = excluded.monthly, updated_at = excluded.updated_at",
+        params![user_id, schedule.weekly, schedule.monthly, Utc::now()],
+    )?;
+    tx.execute("DELETE FROM uptime_report_channels WHERE user_id = ?", params![user_id])?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO uptime_report_channels (user_id, channel_id) VALUES (?, ?) ON CONFLICT DO NOTHING",
+        )?;
+        for channel_id in &schedule.channel_ids {
+            stmt.execute(params![user_id, channel_id])?;
+        }
+    }
+    tx.commit()?;
+    drop(conn);
+
+    get_report_schedule(pool, user_id).await
+}
+
+fn format_percent(value: Option<f64>) -> String {
+    value.map_or_else(|| "n/a".to_string(), |v| format!("{v:.3}%"))
+}
+
+/// Plain-text summary of a report for notification channels.
+pub fn format_report_message(report: &UptimeReport) -> String {
+    let mut message = format!(
+        "📊 {} uptime report {} – {}\n",
+        match report.period {
+            ReportPeriod::Weekly => "Weekly",
+            ReportPeriod::Monthly => "Monthly",
+        },
+        report.period_start.format("%Y-%m-%d"),
+        (report.period_end - chrono::Duration::days(1)).format("%Y-%m-%d"),
+    );
+    if !report.data.vps.is_empty() {
+        message.push_str("\nServers:\n");
+        for vps in &report.data.vps {
+            let _ = writeln!(
+                message,
+                "• {}: {} ({} outages)",
+                vps.name,
+                format_percent(vps.uptime_percent),
+                vps.outage_count
+            );
+        }
+    }
+    if !report.data.monitors.is_empty() {
+        message.push_str("\nMonitors:\n");
+        for monitor in &report.data.monitors {
+            let latency = monitor
+                .mean_latency_ms
+                .map(|ms| format!(", {ms:.0} ms avg"))
+                .unwrap_or_default();
+            let _ = writeln!(message, "• {}: {}{}", monitor.name, format_percent(monitor.uptime_percent), latency);
+        }
+    }
+    message
+}
+
+/// Generates the reports of every schedule whose last completed period has no
+/// snapshot yet, and sends them to the schedule's channels.
+pub async fn run_scheduled_reports(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    now: DateTime<Utc>,
+) -> Result<usize, AppError> {
+    let user_ids: Vec<i32> = {
+        let conn = pool.get()?;
+        conn.prepare("SELECT user_id FROM uptime_report_schedules WHERE weekly OR monthly")?
+            .query_map([], |row| row.get(0))?
+            .collect::<Result<Vec<_>, _>>()?
+    };
+
+    let mut generated = 0;
+    for user_id in user_ids {
+        let schedule = get_report_schedule(pool.clone(), user_id).await?;
+        for period in schedule.periods() {
+            let (start, _) = period.last_completed(now);
+            let exists = {
+                let conn = pool.get()?;
+                conn.query_row(
+                    "SELECT 1 FROM uptime_reports WHERE user_id = ? AND period = ? AND period_start = ?",
+                    params![user_id, period.as_str(), start],
+                    |_| Ok(()),
+                )
+                .optional()?
+                .is_some()
+            };
+            if exists {
+                continue;
+            }
+
+            let report = generate_uptime_report(pool.clone(), user_id, period, start).await?;
+            generated += 1;
+            info!(user_id, period = period.as_str(), report_id = report.id, "Generated scheduled uptime report.");
+            if !schedule.channel_ids.is_empty() {
+                if let Err(e) = notification_service::send_notifications_to_channels(
+                    pool.clone(),
+                    encryption_service.clone(),
+                    schedule.channel_ids.clone(),
+                    format_report_message(&report),
+                )
+                .await
+                {
+                    error!(user_id, report_id = report.id, error = %e, "Failed to send uptime report.");
+                }
+            }
+        }
+    }
+    Ok(generated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn message_lists_servers_and_monitors() {
+        let report = UptimeReport {
+            id: 1,
+            user_id: 1,
+            period: ReportPeriod::Monthly,
+            period_start: Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap(),
+            period_end: Utc.with_ymd_and_hms(2025, 2, 1, 0, 0, 0).unwrap(),
+            generated_at: Utc.with_ymd_and_hms(2025, 2, 1, 0, 5, 0).unwrap(),
+            data: UptimeReportData {
+                vps: vec![VpsUptimeEntry {
+                    vps_id: 1,
+                    name: "web-1".to_string(),
+                    uptime_percent: Some(99.95),
+                    downtime_seconds: 1339,
+                    outage_count: 2,
+                    mean_check_latency_ms: None,
+                    metric_samples: 0,
+                }],
+                monitors: vec![MonitorUptimeEntry {
+                    monitor_id: 1,
+                    name: "api".to_string(),
+                    monitor_type: "http".to_string(),
+                    uptime_percent: None,
+                    mean_latency_ms: None,
+                    check_count: 0,
+                    failed_check_count: 0,
+                }],
+            },
+        };
+        let message = format_report_message(&report);
+        assert!(message.contains("Monthly uptime report 2025-01-01 – 2025-01-31"));
+        assert!(message.contains("• web-1: 99.950% (2 outages)"));
+        assert!(message.contains("• api: n/a"));
+    }
+}
@@ -0,0 +1,344 @@
+use chrono::{DateTime, Duration, Utc};
+use duckdb::{params, Connection, OptionalExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+
+use crate::db::duckdb_service::{maintenance_service, settings_service, webhook_service, DuckDbPool};
+use crate::web::error::AppError;
+
+pub const UPTIME_SETTING_KEY: &str = "uptime";
+pub const STATUS_ONLINE: &str = "online";
+pub const STATUS_OFFLINE: &str = "offline";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UptimeSettings {
+    /// Offline periods shorter than this (agent restarts, brief reconnects)
+    /// don't count as downtime.
+    pub grace_seconds: i64,
+}
+
+impl Default for UptimeSettings {
+    fn default() -> Self {
+        Self { grace_seconds: 60 }
+    }
+}
+
+/// Availability over the standard windows; `None` where nothing is known yet.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VpsUptime {
+    pub uptime_24h: Option<f64>,
+    pub uptime_7d: Option<f64>,
+    pub uptime_30d: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Outage {
+    pub start: DateTime<Utc>,
+    /// `None` while the VPS is still offline.
+    pub end: Option<DateTime<Utc>>,
+    pub duration_seconds: i64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct Availability {
+    pub uptime_percent: Option<f64>,
+    pub monitored_seconds: i64,
+    pub downtime_seconds: i64,
+    pub outages: Vec<Outage>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DailyUptime {
+    pub date: DateTime<Utc>,
+    pub uptime_percent: Option<f64>,
+    pub downtime_seconds: i64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VpsUptimeHistory {
+    pub vps_id: i32,
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub grace_seconds: i64,
+    #[serde(flatten)]
+    pub overall: Availability,
+    pub daily: Vec<DailyUptime>,
+}
+
+pub async fn get_uptime_settings(pool: DuckDbPool) -> Result<UptimeSettings, AppError> {
+    let settings = settings_service::get_setting(pool, UPTIME_SETTING_KEY)
+        .await?
+        .map(|s| serde_json::from_value(s.value))
+        .transpose()?
+        .unwrap_or_default();
+    Ok(settings)
+}
+
+pub async fn update_uptime_settings(pool: DuckDbPool, settings: &UptimeSettings) -> Result<(), AppError> {
+    let value = serde_json::to_value(settings)?;
+    settings_service::update_setting(pool, UPTIME_SETTING_KEY, &value).await?;
+    Ok(())
+}
+
+/// The configured grace period, see [`UptimeSettings::grace_seconds`].
+pub fn load_grace_seconds(conn: &Connection) -> Result<i64, AppError> {
+    let settings: UptimeSettings = settings_service::get_setting_with_conn(conn, UPTIME_SETTING_KEY)?
+        .map(|s| serde_json::from_value(s.value))
+        .transpose()?
+        .unwrap_or_default();
+    Ok(settings.grace_seconds.max(0))
+}
+
+/// Records a transition. Repeated reports of the current status are ignored,
+/// so the table only ever holds alternating events per VPS. Each transition
+/// is published as a `vps.online` or `vps.offline` webhook event.
+pub async fn record_status_event(
+    pool: DuckDbPool,
+    vps_id: i32,
+    status: &str,
+    time: DateTime<Utc>,
+) -> Result<(), AppError> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    let inserted = tx.execute(
+        "INSERT INTO vps_status_events (vps_id, time, status)
+         SELECT ?, ?, ?
+         WHERE COALESCE((SELECT status FROM vps_status_events WHERE vps_id = ? ORDER BY time DESC, id DESC LIMIT 1), '') <> ?",
+        params![vps_id, time, status, vps_id, status],
+    )?;
+    if inserted > 0 {
+        let event_type = if status == STATUS_ONLINE {
+            webhook_service::EVENT_VPS_ONLINE
+        } else {
+            webhook_service::EVENT_VPS_OFFLINE
+        };
+        webhook_service::publish_vps_event(&tx, vps_id, event_type, json!({ "status": status, "time": time }))?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// The status in effect at `start` and the transitions within `(start, end]`.
+fn load_events(
+    conn: &Connection,
+    vps_id: i32,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<(Option<bool>, Vec<(DateTime<Utc>, bool)>), AppError> {
+    let initial: Option<String> = conn
+        .query_row(
+            "SELECT status FROM vps_status_events WHERE vps_id = ? AND time <= ? ORDER BY time DESC, id DESC LIMIT 1",
+            params![vps_id, start],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let events = conn
+        .prepare(
+            "SELECT time, status FROM vps_status_events
+             WHERE vps_id = ? AND time > ? AND time <= ?
+             ORDER BY time ASC, id ASC",
+        )?
+        .query_map(params![vps_id, start, end], |row| {
+            let status: String = row.get(1)?;
+            Ok((row.get::<_, DateTime<Utc>>(0)?, status == STATUS_ONLINE))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((initial.map(|s| s == STATUS_ONLINE), events))
+}
+
+fn overlap_seconds(from: DateTime<Utc>, to: DateTime<Utc>, periods: &[(DateTime<Utc>, DateTime<Utc>)]) -> i64 {
+    periods
+        .iter()
+        .map(|(p_start, p_end)| (to.min(*p_end) - from.max(*p_start)).num_seconds().max(0))
+        .sum()
+}
+
+/// Computes availability within `[start, end]` from the status at `start` and
+/// the ordered transitions after it. Time before the first known status and
+/// time within `excluded` (maintenance) is left out of the calculation, and
+/// offline periods shorter than `grace_seconds` count as up.
+pub fn compute_availability(
+    initial: Option<bool>,
+    events: &[(DateTime<Utc>, bool)],
+    excluded: &[(DateTime<Utc>, DateTime<Utc>)],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    grace_seconds: i64,
+) -> Availability {
+    let mut monitored_seconds = 0;
+    let mut downtime_seconds = 0;
+    let mut outages = Vec::new();
+
+    let mut state = initial;
+    let mut since = start;
+    let mut close_period = |state: Option<bool>, from: DateTime<Utc>, to: DateTime<Utc>, ongoing: bool| {
+        let seconds = ((to - from).num_seconds() - overlap_seconds(from, to, excluded)).max(0);
+        match state {
+            None => {}
+            Some(true) => monitored_seconds += seconds,
+            Some(false) => {
+                monitored_seconds += seconds;
+                if seconds >= grace_seconds {
+                    downtime_seconds += seconds;
+                    outages.push(Outage {
+                        start: from,
+                        end: (!ongoing).then_some(to),
+                        duration_seconds: seconds,
+                    });
+                }
+            }
+        }
+    };
+
+    for &(time, online) in events {
+        let time = time.clamp(start, end);
+        if state == Some(online) {
+            continue;
+        }
+        close_period(state, since, time, false);
+        state = Some(online);
+        since = time;
+    }
+    close_period(state, since, end, true);
+
+    let uptime_percent = (monitored_seconds > 0).then(|| {
+        (monitored_seconds - downtime_seconds) as f64 * 100.0 / monitored_seconds as f64
+    });
+    Availability { uptime_percent, monitored_seconds, downtime_seconds, outages }
+}
+
+/// Availability of one VPS within `[start, end]`, maintenance excluded.
+pub fn availability_since(
+    conn: &Connection,
+    vps_id: i32,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    grace_seconds: i64,
+) -> Result<Availability, AppError> {
+    let (initial, events) = load_events(conn, vps_id, start, end)?;
+    let maintenance = maintenance_service::get_maintenance_periods(conn, vps_id, start, end)?;
+    Ok(compute_availability(initial, &events, &maintenance, start, end, grace_seconds))
+}
+
+/// 24h/7d/30d uptime for each VPS, using an existing connection.
+pub fn get_uptime_summaries(
+    conn: &Connection,
+    vps_ids: &[i32],
+    now: DateTime<Utc>,
+) -> Result<HashMap<i32, VpsUptime>, AppError> {
+    let grace_seconds = load_grace_seconds(conn)?;
+    let mut summaries = HashMap::with_capacity(vps_ids.len());
+    for &vps_id in vps_ids {
+        let (initial, events) = load_events(conn, vps_id, now - Duration::days(30), now)?;
+        let maintenance = maintenance_service::get_maintenance_periods(conn, vps_id, now - Duration::days(30), now)?;
+        let window = |days: i64| {
+            let start = now - Duration::days(days);
+            // The status at the window start is the last transition before it.
+            let initial = events
+                .iter()
+                .rev()
+                .find(|(time, _)| *time <= start)
+                .map(|(_, online)| *online)
+                .or(initial);
+            let events: Vec<_> = events.iter().copied().filter(|(time, _)| *time > start).collect();
+            compute_availability(initial, &events, &maintenance, start, now, grace_seconds).uptime_percent
+        };
+        summaries.insert(
+            vps_id,
+            VpsUptime { uptime_24h: window(1), uptime_7d: window(7), uptime_30d: window(30) },
+        );
+    }
+    Ok(summaries)
+}
+
+/// Availability over a range with per-day breakdown and the list of outages.
+pub async fn get_vps_uptime_history(
+    pool: DuckDbPool,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+) -> Result<VpsUptimeHistory, AppError> {
+    let conn = pool.get()?;
+    let grace_seconds = load_grace_seconds(&conn)?;
+    let overall = availability_since(&conn, vps_id, start_time, end_time, grace_seconds)?;
+
+    let mut daily = Vec::new();
+    let mut day_start = start_time;
+    while day_start < end_time {
+        let day_end = (day_start + Duration::days(1)).min(end_time);
+        let day = availability_since(&conn, vps_id, day_start, day_end, grace_seconds)?;
+        daily.push(DailyUptime {
+            date: day_start,
+            uptime_percent: day.uptime_percent,
+            downtime_seconds: day.downtime_seconds,
+        });
+        day_start = day_end;
+    }
+
+    Ok(VpsUptimeHistory { vps_id, start_time, end_time, grace_seconds, overall, daily })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(minutes: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap() + Duration::minutes(minutes)
+    }
+
+    #[test]
+    fn unknown_time_is_excluded() {
+        let result = compute_availability(None, &[(at(30), true)], &[], at(0), at(60), 60);
+        assert_eq!(result.monitored_seconds, 30 * 60);
+        assert_eq!(result.uptime_percent, Some(100.0));
+    }
+
+    #[test]
+    fn outages_count_as_downtime() {
+        let events = [(at(10), false), (at(40), true)];
+        let result = compute_availability(Some(true), &events, &[], at(0), at(60), 60);
+        assert_eq!(result.downtime_seconds, 30 * 60);
+        assert_eq!(result.uptime_percent, Some(50.0));
+        assert_eq!(result.outages.len(), 1);
+        assert_eq!(result.outages[0].end, Some(at(40)));
+    }
+
+    #[test]
+    fn short_outages_within_grace_are_ignored() {
+        let events = [(at(10), false), (at(11), true)];
+        let result = compute_availability(Some(true), &events, &[], at(0), at(60), 120);
+        assert_eq!(result.downtime_seconds, 0);
+        assert_eq!(result.uptime_percent, Some(100.0));
+    }
+
+    #[test]
+    fn ongoing_outage_has_no_end() {
+        let result = compute_availability(Some(true), &[(at(30), false)], &[], at(0), at(60), 60);
+        assert_eq!(result.outages[0].end, None);
+        assert_eq!(result.uptime_percent, Some(50.0));
+    }
+
+    #[test]
+    fn maintenance_is_excluded() {
+        let events = [(at(10), false), (at(40), true)];
+        let result = compute_availability(Some(true), &events, &[(at(10), at(40))], at(0), at(60), 60);
+        assert_eq!(result.monitored_seconds, 30 * 60);
+        assert_eq!(result.downtime_seconds, 0);
+        assert_eq!(result.uptime_percent, Some(100.0));
+    }
+
+    #[test]
+    fn no_events_means_no_data() {
+        let result = compute_availability(None, &[], &[], at(0), at(60), 60);
+        assert_eq!(result.uptime_percent, None);
+    }
+}
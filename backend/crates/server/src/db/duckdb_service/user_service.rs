@@ -1,9 +1,10 @@
-use super::Error;
+use super::{organization_service, Error};
 use crate::db::{self, entities::user};
 use crate::web::error::AppError;
+use crate::web::models::Role;
 use db::duckdb_service::DuckDbPool;
 use chrono::Utc;
-use duckdb::{params, Result as DuckDbResult};
+use duckdb::{params, Connection, OptionalExt, Result as DuckDbResult};
 use tokio::task;
 
 // Helper function to map a DuckDB row to our user model
@@ -19,6 +20,7 @@ fn row_to_user_model(row: &duckdb::Row<'_>) -> DuckDbResult<user::Model> {
         theme_mode: row.get("theme_mode")?,
         active_theme_id: row.get("active_theme_id")?,
         language: row.get("language")?,
+        active_organization_id: row.get("active_organization_id")?,
     })
 }
 
@@ -63,35 +65,84 @@ pub async fn create_user(
     password_hash: String,
 ) -> Result<user::Model, AppError> {
     task::spawn_blocking(move || {
-        let conn = pool.get()?;
-        let now = Utc::now();
-        let role = "user";
-        let password_login_disabled = false;
-        let theme_mode = "system";
-        let language = "auto";
-
-        let user_model = conn.query_row(
-            "INSERT INTO users (username, password_hash, role, password_login_disabled, created_at, updated_at, theme_mode, language) 
-             VALUES (?, ?, ?, ?, ?, ?, ?, ?) 
-             RETURNING *",
-            params![
-                username,
-                password_hash,
-                role,
-                password_login_disabled,
-                now,
-                now,
-                theme_mode,
-                language,
-            ],
-            row_to_user_model,
-        )?;
+        let mut conn = pool.get()?;
+        let tx = conn.transaction()?;
+        // The first account administers the server; later sign-ups manage their own resources.
+        let role = if has_users(&tx)? { Role::Operator } else { Role::Admin };
+        let user_model = insert_user(&tx, &username, &password_hash, role, false)?;
+        tx.commit()?;
         Ok(user_model)
     })
     .await
     .map_err(|e| AppError::InternalServerError(e.to_string()))?
 }
 
+/// Returns the user a trusted reverse proxy authenticated, creating it with
+/// `role` on first sight. Such users sign in only through the proxy, so
+/// password login is disabled. Like a regular sign-up, the very first account
+/// becomes an administrator.
+pub async fn find_or_create_trusted_user(
+    pool: DuckDbPool,
+    username: String,
+    role: Role,
+) -> Result<user::Model, AppError> {
+    task::spawn_blocking(move || {
+        let mut conn = pool.get()?;
+        let tx = conn.transaction()?;
+        let existing = tx
+            .query_row("SELECT * FROM users WHERE username = ?", params![username], row_to_user_model)
+            .optional()?;
+        if let Some(user_model) = existing {
+            return Ok(user_model);
+        }
+        let role = if has_users(&tx)? { role } else { Role::Admin };
+        // No bcrypt hash starts with '!', so this never matches a password.
+        let user_model = insert_user(&tx, &username, "!", role, true)?;
+        tx.commit()?;
+        Ok(user_model)
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+}
+
+fn has_users(conn: &Connection) -> Result<bool, AppError> {
+    let count: i64 = conn.query_row("SELECT COUNT(*) FROM users", [], |row| row.get(0))?;
+    Ok(count > 0)
+}
+
+/// Inserts a user along with their personal organization.
+fn insert_user(
+    conn: &Connection,
+    username: &str,
+    password_hash: &str,
+    role: Role,
+    password_login_disabled: bool,
+) -> Result<user::Model, AppError> {
+    let now = Utc::now();
+    let theme_mode = "system";
+    let language = "auto";
+
+    let user_model = conn.query_row(
+        "INSERT INTO users (username, password_hash, role, password_login_disabled, created_at, updated_at, theme_mode, language) 
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?) 
+         RETURNING *",
+        params![
+            username,
+            password_hash,
+            role.as_str(),
+            password_login_disabled,
+            now,
+            now,
+            theme_mode,
+            language,
+        ],
+        row_to_user_model,
+    )?;
+    let organization_id =
+        organization_service::create_personal_organization(conn, user_model.id, &user_model.username)?;
+    Ok(user::Model { active_organization_id: Some(organization_id), ..user_model })
+}
+
 pub async fn update_preference(
     pool: DuckDbPool,
     user_id: i32,
@@ -168,4 +219,42 @@ pub async fn unlink_provider(
         params![user_id, provider],
     )?;
     Ok(())
-}
\ No newline at end of file
+}
+
+/// All users, oldest first.
+pub async fn get_all_users(pool: DuckDbPool) -> Result<Vec<user::Model>, Error> {
+    let conn = pool.get()?;
+    let users = conn
+        .prepare("SELECT * FROM users ORDER BY id ASC")?
+        .query_map([], row_to_user_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(users)
+}
+
+/// Changes a user's role. The last admin cannot be demoted, so the server
+/// always has someone who can manage users.
+pub async fn update_user_role(pool: DuckDbPool, user_id: i32, role: Role) -> Result<user::Model, AppError> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    let current: Option<String> = tx
+        .query_row("SELECT role FROM users WHERE id = ?", params![user_id], |row| row.get(0))
+        .optional()?;
+    let current = current.ok_or_else(|| AppError::NotFound("User not found".to_string()))?;
+    if Role::from_db(&current) == Role::Admin && role != Role::Admin {
+        let admins: i64 = tx.query_row(
+            "SELECT COUNT(*) FROM users WHERE role = ?",
+            params![Role::Admin.as_str()],
+            |row| row.get(0),
+        )?;
+        if admins <= 1 {
+            return Err(AppError::Conflict("Cannot demote the last admin".to_string()));
+        }
+    }
+    let updated = tx.query_row(
+        "UPDATE users SET role = ?, updated_at = ? WHERE id = ? RETURNING *",
+        params![role.as_str(), Utc::now(), user_id],
+        row_to_user_model,
+    )?;
+    tx.commit()?;
+    Ok(updated)
+}
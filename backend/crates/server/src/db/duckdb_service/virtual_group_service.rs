@@ -0,0 +1,257 @@
+//! System-maintained VPS collections. Membership is derived from renewal,
+//! traffic, status and version data on every cache refresh, so clients can
+//! filter on it instead of re-deriving the rules themselves.
+
+use chrono::{DateTime, Duration, Utc};
+use duckdb::{params, Connection};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::db::duckdb_service::uptime_service::STATUS_OFFLINE;
+use crate::web::error::AppError;
+use crate::web::models::websocket_models::ServerWithDetails;
+
+pub const RENEWAL_DUE_WITHIN_DAYS: i64 = 7;
+pub const TRAFFIC_WARN_PERCENT: f64 = 90.0;
+pub const LONG_OFFLINE_HOURS: i64 = 24;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum VirtualGroup {
+    /// Next renewal is overdue or within [`RENEWAL_DUE_WITHIN_DAYS`].
+    RenewalDue,
+    /// Billed traffic this cycle is above [`TRAFFIC_WARN_PERCENT`] of the cap.
+    OverTraffic,
+    /// Offline for more than [`LONG_OFFLINE_HOURS`].
+    LongOffline,
+    /// Agent reports an older version than the server.
+    AgentOutdated,
+}
+
+impl VirtualGroup {
+    pub const ALL: [VirtualGroup; 4] = [
+        VirtualGroup::RenewalDue,
+        VirtualGroup::OverTraffic,
+        VirtualGroup::LongOffline,
+        VirtualGroup::AgentOutdated,
+    ];
+}
+
+/// The VPS IDs in one virtual group.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VirtualGroupMembers {
+    pub group: VirtualGroup,
+    pub vps_ids: Vec<i32>,
+}
+
+/// Traffic counted against the cap under a billing rule, or `None` for an
+/// unknown rule.
+pub fn billed_traffic_bytes(billing_rule: Option<&str>, rx_bytes: i64, tx_bytes: i64) -> Option<i64> {
+    match billing_rule {
+        Some("sum_in_out") => Some(rx_bytes + tx_bytes),
+        Some("out_only") => Some(tx_bytes),
+        Some("max_in_out") => Some(rx_bytes.max(tx_bytes)),
+        _ => None,
+    }
+}
+
+/// Whether an agent version is older than the latest one (see
+/// `agent_release_service::latest_agent_version`). Versions that don't parse
+/// as semver are never outdated.
+pub fn is_agent_outdated(agent_version: &str, latest_version: &str) -> bool {
+    let parse = |v: &str| semver::Version::parse(v.trim().trim_start_matches('v')).ok();
+    matches!((parse(agent_version), parse(latest_version)), (Some(agent), Some(latest)) if agent < latest)
+}
+
+/// Virtual groups a server belongs to. `offline_since` is the time of its
+/// last status event, if that event was going offline.
+pub fn classify(
+    server: &ServerWithDetails,
+    offline_since: Option<DateTime<Utc>>,
+    latest_agent_version: &str,
+    now: DateTime<Utc>,
+) -> Vec<VirtualGroup> {
+    let info = &server.basic_info;
+    let mut groups = Vec::new();
+
+    if matches!(server.next_renewal_date, Some(date) if date <= now + Duration::days(RENEWAL_DUE_WITHIN_DAYS)) {
+        groups.push(VirtualGroup::RenewalDue);
+    }
+
+    if let Some(limit) = info.traffic_limit_bytes.filter(|limit| *limit > 0) {
+        let used = billed_traffic_bytes(
+            info.traffic_billing_rule.as_deref(),
+            info.traffic_current_cycle_rx_bytes.unwrap_or(0),
+            info.traffic_current_cycle_tx_bytes.unwrap_or(0),
+        );
+        if matches!(used, Some(used) if used as f64 / limit as f64 * 100.0 > TRAFFIC_WARN_PERCENT) {
+            groups.push(VirtualGroup::OverTraffic);
+        }
+    }
+
+    if info.status == STATUS_OFFLINE
+        && matches!(offline_since, Some(since) if since <= now - Duration::hours(LONG_OFFLINE_HOURS))
+    {
+        groups.push(VirtualGroup::LongOffline);
+    }
+
+    if matches!(info.agent_version.as_deref(), Some(version) if is_agent_outdated(version, latest_agent_version)) {
+        groups.push(VirtualGroup::AgentOutdated);
+    }
+
+    groups
+}
+
+/// Groups servers by virtual group, in [`VirtualGroup::ALL`] order. Empty
+/// groups are included so clients see every group.
+pub fn group_members<'a>(servers: impl IntoIterator<Item = &'a ServerWithDetails>) -> Vec<VirtualGroupMembers> {
+    let mut members: HashMap<VirtualGroup, Vec<i32>> = HashMap::new();
+    for server in servers {
+        for group in &server.virtual_groups {
+            members.entry(*group).or_default().push(server.basic_info.id);
+        }
+    }
+    VirtualGroup::ALL
+        .into_iter()
+        .map(|group| {
+            let mut vps_ids = members.remove(&group).unwrap_or_default();
+            vps_ids.sort_unstable();
+            VirtualGroupMembers { group, vps_ids }
+        })
+        .collect()
+}
+
+/// For each VPS whose latest status event is going offline, when that happened.
+pub fn get_offline_since(conn: &Connection) -> Result<HashMap<i32, DateTime<Utc>>, AppError> {
+    let offline_since = conn
+        .prepare(
+            "SELECT vps_id, time FROM (
+                 SELECT vps_id, time, status FROM vps_status_events
+                 QUALIFY row_number() OVER (PARTITION BY vps_id ORDER BY time DESC, id DESC) = 1
+             ) WHERE status = ?",
+        )?
+        .query_map(params![STATUS_OFFLINE], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<HashMap<i32, DateTime<Utc>>, _>>()?;
+    Ok(offline_since)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::models::websocket_models::ServerBasicInfo;
+
+    fn server() -> ServerWithDetails {
+        ServerWithDetails {
+            basic_info: ServerBasicInfo {
+                id: 1,
+                user_id: 1,
+                organization_id: Some(1),
+                name: "web-1".to_string(),
+                ip_address: None,
+                status: "online".to_string(),
+                agent_version: Some("1.2.0".to_string()),
+                agent_outdated: false,
+                group: None,
+                group_id: None,
+                client_id: None,
+                tags: None,
+                config_status: "synced".to_string(),
+                last_config_update_at: None,
+                last_config_error: None,
+                traffic_limit_bytes: None,
+                traffic_billing_rule: None,
+                traffic_current_cycle_rx_bytes: None,
+                traffic_current_cycle_tx_bytes: None,
+                traffic_last_reset_at: None,
+                traffic_reset_config_type: None,
+                traffic_reset_config_value: None,
+                next_traffic_reset_at: None,
+                is_ephemeral: false,
+                country_code: None,
+                city: None,
+                asn: None,
+                as_organization: None,
+            },
+            os_type: None,
+            created_at: Utc::now(),
+            metadata: None,
+            renewal_cycle: None,
+            renewal_cycle_custom_days: None,
+            renewal_price: None,
+            renewal_currency: None,
+            next_renewal_date: None,
+            last_renewal_date: None,
+            service_start_date: None,
+            payment_method: None,
+            auto_renew_enabled: None,
+            renewal_notes: None,
+            reminder_active: None,
+            uptime: None,
+            health: None,
+            maintenance: None,
+            virtual_groups: Vec::new(),
+            gpus: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn healthy_server_has_no_groups() {
+        assert!(classify(&server(), None, "1.2.0", Utc::now()).is_empty());
+    }
+
+    #[test]
+    fn renewal_due_includes_overdue() {
+        let now = Utc::now();
+        let mut s = server();
+        s.next_renewal_date = Some(now + Duration::days(3));
+        assert_eq!(classify(&s, None, "1.2.0", now), vec![VirtualGroup::RenewalDue]);
+        s.next_renewal_date = Some(now - Duration::days(1));
+        assert_eq!(classify(&s, None, "1.2.0", now), vec![VirtualGroup::RenewalDue]);
+        s.next_renewal_date = Some(now + Duration::days(30));
+        assert!(classify(&s, None, "1.2.0", now).is_empty());
+    }
+
+    #[test]
+    fn over_traffic_follows_billing_rule() {
+        let mut s = server();
+        s.basic_info.traffic_limit_bytes = Some(100);
+        s.basic_info.traffic_current_cycle_rx_bytes = Some(50);
+        s.basic_info.traffic_current_cycle_tx_bytes = Some(45);
+        s.basic_info.traffic_billing_rule = Some("sum_in_out".to_string());
+        assert_eq!(classify(&s, None, "1.2.0", Utc::now()), vec![VirtualGroup::OverTraffic]);
+        s.basic_info.traffic_billing_rule = Some("out_only".to_string());
+        assert!(classify(&s, None, "1.2.0", Utc::now()).is_empty());
+    }
+
+    #[test]
+    fn long_offline_needs_offline_status_and_age() {
+        let now = Utc::now();
+        let mut s = server();
+        s.basic_info.status = STATUS_OFFLINE.to_string();
+        assert!(classify(&s, Some(now - Duration::hours(2)), "1.2.0", now).is_empty());
+        assert_eq!(
+            classify(&s, Some(now - Duration::hours(25)), "1.2.0", now),
+            vec![VirtualGroup::LongOffline]
+        );
+    }
+
+    #[test]
+    fn agent_version_is_compared_semantically() {
+        assert!(is_agent_outdated("1.9.0", "1.10.0"));
+        assert!(is_agent_outdated("v0.1.0", "0.1.1"));
+        assert!(!is_agent_outdated("1.10.0", "1.10.0"));
+        assert!(!is_agent_outdated("dev", "1.10.0"));
+    }
+
+    #[test]
+    fn group_members_lists_every_group() {
+        let mut s = server();
+        s.virtual_groups = vec![VirtualGroup::AgentOutdated];
+        let members = group_members([&s]);
+        assert_eq!(members.len(), VirtualGroup::ALL.len());
+        assert_eq!(members[3].vps_ids, vec![1]);
+        assert!(members[0].vps_ids.is_empty());
+    }
+}
@@ -1,15 +1,21 @@
 use std::collections::HashMap;
+use chrono::Utc;
 use duckdb::{params, Connection};
-use crate::db::duckdb_service::{json_from_row, DuckDbPool};
+use crate::db::duckdb_service::{
+    agent_release_service, gpu_service, health_service, json_from_row, maintenance_service, organization_service,
+    uptime_service, virtual_group_service, DuckDbPool,
+};
 use crate::db::entities::{vps, vps_renewal_info};
 use crate::web::error::AppError;
 use crate::web::models::websocket_models::{ServerBasicInfo, ServerWithDetails, Tag as WebsocketTag};
+use crate::services::{fleet_map, geoip};
 
 // Helper function to map a DuckDB row to a vps::Model
 fn row_to_vps_model(row: &duckdb::Row<'_>) -> Result<vps::Model, duckdb::Error> {
     Ok(vps::Model {
         id: row.get("vps_id")?,
         user_id: row.get("user_id")?,
+        organization_id: row.get("organization_id")?,
         name: row.get("name")?,
         ip_address: row.get("ip_address")?,
         os_type: row.get("os_type")?,
@@ -20,6 +26,8 @@ fn row_to_vps_model(row: &duckdb::Row<'_>) -> Result<vps::Model, duckdb::Error>
         created_at: row.get("created_at")?,
         updated_at: row.get("updated_at")?,
         group: row.get("group")?,
+        group_id: row.get("group_id")?,
+        client_id: row.get("client_id")?,
         agent_config_override: json_from_row(row, "agent_config_override")?,
         config_status: row.get("config_status")?,
         last_config_update_at: row.get("last_config_update_at")?,
@@ -34,6 +42,8 @@ fn row_to_vps_model(row: &duckdb::Row<'_>) -> Result<vps::Model, duckdb::Error>
         traffic_reset_config_type: row.get("traffic_reset_config_type")?,
         traffic_reset_config_value: row.get("traffic_reset_config_value")?,
         next_traffic_reset_at: row.get("next_traffic_reset_at")?,
+        is_ephemeral: row.get("is_ephemeral")?,
+        archived_at: row.get("archived_at")?,
     })
 }
 
@@ -82,15 +92,25 @@ fn build_server_with_details(
     vps_model: vps::Model,
     renewal_info_opt: Option<vps_renewal_info::Model>,
     tags: Option<Vec<WebsocketTag>>,
+    latest_agent_version: &str,
 ) -> ServerWithDetails {
+    let agent_outdated = vps_model
+        .agent_version
+        .as_deref()
+        .is_some_and(|version| virtual_group_service::is_agent_outdated(version, latest_agent_version));
+    let geo = geoip::from_metadata(vps_model.metadata.as_ref());
     let basic_info = ServerBasicInfo {
         id: vps_model.id,
         user_id: vps_model.user_id,
+        organization_id: vps_model.organization_id,
         name: vps_model.name,
         ip_address: vps_model.ip_address,
         status: vps_model.status,
         agent_version: vps_model.agent_version,
+        agent_outdated,
         group: vps_model.group,
+        group_id: vps_model.group_id,
+        client_id: vps_model.client_id,
         tags,
         config_status: vps_model.config_status,
         last_config_update_at: vps_model.last_config_update_at,
@@ -103,6 +123,11 @@ fn build_server_with_details(
         traffic_reset_config_type: vps_model.traffic_reset_config_type,
         traffic_reset_config_value: vps_model.traffic_reset_config_value,
         next_traffic_reset_at: vps_model.next_traffic_reset_at,
+        is_ephemeral: vps_model.is_ephemeral,
+        country_code: fleet_map::country_code(vps_model.metadata.as_ref()),
+        city: geo.as_ref().and_then(|geo| geo.city.clone()),
+        asn: geo.as_ref().and_then(|geo| geo.asn),
+        as_organization: geo.and_then(|geo| geo.as_organization),
     };
 
     ServerWithDetails {
@@ -121,12 +146,17 @@ fn build_server_with_details(
         auto_renew_enabled: renewal_info_opt.as_ref().and_then(|ri| ri.auto_renew_enabled),
         renewal_notes: renewal_info_opt.as_ref().and_then(|ri| ri.renewal_notes.clone()),
         reminder_active: renewal_info_opt.as_ref().and_then(|ri| ri.reminder_active),
+        uptime: None,
+        health: None,
+        maintenance: None,
+        virtual_groups: Vec::new(),
+        gpus: Vec::new(),
     }
 }
 
 const SELECT_VPS_WITH_DETAILS_SQL: &str = "
     SELECT
-        v.id as vps_id, v.user_id, v.name, v.ip_address, v.os_type, v.agent_secret, v.agent_version, v.status, v.metadata, v.created_at, v.updated_at, v.group, v.agent_config_override, v.config_status, v.last_config_update_at, v.last_config_error, v.traffic_limit_bytes, v.traffic_billing_rule, v.traffic_current_cycle_rx_bytes, v.traffic_current_cycle_tx_bytes, v.last_processed_cumulative_rx, v.last_processed_cumulative_tx, v.traffic_last_reset_at, v.traffic_reset_config_type, v.traffic_reset_config_value, v.next_traffic_reset_at,
+        v.id as vps_id, v.user_id, v.organization_id, v.name, v.ip_address, v.os_type, v.agent_secret, v.agent_version, v.status, v.metadata, v.created_at, v.updated_at, v.group, v.group_id, v.client_id, v.agent_config_override, v.config_status, v.last_config_update_at, v.last_config_error, v.traffic_limit_bytes, v.traffic_billing_rule, v.traffic_current_cycle_rx_bytes, v.traffic_current_cycle_tx_bytes, v.last_processed_cumulative_rx, v.last_processed_cumulative_tx, v.traffic_last_reset_at, v.traffic_reset_config_type, v.traffic_reset_config_value, v.next_traffic_reset_at, v.is_ephemeral, v.archived_at,
         ri.vps_id as ri_vps_id, ri.renewal_cycle, ri.renewal_cycle_custom_days, ri.renewal_price, ri.renewal_currency, ri.next_renewal_date, ri.last_renewal_date, ri.service_start_date, ri.payment_method, ri.auto_renew_enabled, ri.renewal_notes, ri.reminder_active, ri.last_reminder_generated_at, ri.created_at as ri_created_at, ri.updated_at as ri_updated_at,
         t.id as tag_id, t.name as tag_name, t.color as tag_color, t.icon as tag_icon, t.url as tag_url, t.is_visible as tag_is_visible
     FROM vps v
@@ -140,29 +170,62 @@ fn process_query_results(
     query: &str,
     params: &[&dyn duckdb::ToSql],
 ) -> Result<Vec<ServerWithDetails>, AppError> {
-    let mut stmt = conn.prepare(query).map_err(|e| AppError::DatabaseError(e.to_string()))?;
-    let mut rows = stmt.query(params).map_err(|e| AppError::DatabaseError(e.to_string()))?;
+    let mut stmt = conn.prepare(query).map_err(AppError::from)?;
+    let mut rows = stmt.query(params).map_err(AppError::from)?;
 
     let mut vps_map: HashMap<i32, (vps::Model, Option<vps_renewal_info::Model>, Vec<WebsocketTag>)> = HashMap::new();
 
-    while let Some(row) = rows.next().map_err(|e| AppError::DatabaseError(e.to_string()))? {
-        let vps_id = row.get("vps_id").map_err(|e| AppError::DatabaseError(e.to_string()))?;
+    while let Some(row) = rows.next().map_err(AppError::from)? {
+        let vps_id = row.get("vps_id").map_err(AppError::from)?;
         let entry = vps_map.entry(vps_id).or_insert_with_key(|_| {
             let vps_model = row_to_vps_model(row).unwrap();
             let renewal_info = row_to_renewal_info(row).unwrap();
             (vps_model, renewal_info, Vec::new())
         });
 
-        if let Some(tag) = row_to_tag(row).map_err(|e| AppError::DatabaseError(e.to_string()))? {
+        if let Some(tag) = row_to_tag(row).map_err(AppError::from)? {
             entry.2.push(tag);
         }
     }
 
+    drop(rows);
+    drop(stmt);
+    let vps_ids: Vec<i32> = vps_map.keys().copied().collect();
+    let now = Utc::now();
+    // Ephemeral VPS come and go by design, so they have no uptime/SLA figures.
+    let sla_vps_ids: Vec<i32> = vps_map
+        .values()
+        .filter(|(vps_model, _, _)| !vps_model.is_ephemeral)
+        .map(|(vps_model, _, _)| vps_model.id)
+        .collect();
+    let mut uptimes = uptime_service::get_uptime_summaries(conn, &sla_vps_ids, now)?;
+    let mut health_scores = health_service::compute_health_scores(conn, &vps_ids, now)?;
+    let mut maintenance = maintenance_service::get_active_maintenance(conn, now)?;
+    let offline_since = virtual_group_service::get_offline_since(conn)?;
+    let mut gpus = gpu_service::get_latest_gpus(conn, now)?;
+    let latest_agent_version = agent_release_service::latest_agent_version(conn)?;
+
     let mut servers_with_details = vps_map
         .into_values()
         .map(|(vps_model, renewal_info, tags)| {
             let tags_opt = if tags.is_empty() { None } else { Some(tags) };
-            build_server_with_details(vps_model, renewal_info, tags_opt)
+            let uptime = uptimes.remove(&vps_model.id);
+            let health = health_scores.remove(&vps_model.id);
+            let maintenance = maintenance.remove(&vps_model.id);
+            let server = ServerWithDetails {
+                uptime,
+                health,
+                maintenance,
+                gpus: gpus.remove(&vps_model.id).unwrap_or_default(),
+                ..build_server_with_details(vps_model, renewal_info, tags_opt, &latest_agent_version)
+            };
+            let virtual_groups = virtual_group_service::classify(
+                &server,
+                offline_since.get(&server.basic_info.id).copied(),
+                &latest_agent_version,
+                now,
+            );
+            ServerWithDetails { virtual_groups, ..server }
         })
         .collect::<Vec<_>>();
     
@@ -172,19 +235,155 @@ fn process_query_results(
 }
 
 pub async fn get_all_vps_with_details_for_cache(pool: DuckDbPool) -> Result<Vec<ServerWithDetails>, AppError> {
-    let mut conn = pool.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
-    let query = format!("{SELECT_VPS_WITH_DETAILS_SQL} ORDER BY v.id ASC");
+    let mut conn = pool.get().map_err(AppError::from)?;
+    let query = format!("{SELECT_VPS_WITH_DETAILS_SQL} WHERE v.archived_at IS NULL ORDER BY v.id ASC");
     process_query_results(&mut conn, &query, &[])
 }
 
 pub async fn get_all_vps_with_details_for_user(pool: DuckDbPool, user_id: i32) -> Result<Vec<ServerWithDetails>, AppError> {
-    let mut conn = pool.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
-    let query = format!("{SELECT_VPS_WITH_DETAILS_SQL} WHERE v.user_id = ? ORDER BY v.id ASC");
+    let mut conn = pool.get().map_err(AppError::from)?;
+    let query = format!(
+        "{SELECT_VPS_WITH_DETAILS_SQL} WHERE {} AND v.archived_at IS NULL ORDER BY v.id ASC",
+        organization_service::org_scope("v.organization_id")
+    );
     process_query_results(&mut conn, &query, params![user_id])}
 
 pub async fn get_vps_with_details_for_cache_by_id(pool: DuckDbPool, vps_id: i32) -> Result<Option<ServerWithDetails>, AppError> {
-    let mut conn = pool.get().map_err(|e| AppError::DatabaseError(e.to_string()))?;
+    let mut conn = pool.get().map_err(AppError::from)?;
     let query = format!("{SELECT_VPS_WITH_DETAILS_SQL} WHERE v.id = ? LIMIT 1");
     let mut results = process_query_results(&mut conn, &query, params![vps_id])?;
     Ok(results.pop())
-}
\ No newline at end of file
+}
+/// Columns the VPS list can be sorted by.
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum VpsSortKey {
+    Name,
+    IpAddress,
+    Status,
+    Group,
+    AgentVersion,
+    #[default]
+    CreatedAt,
+}
+
+impl VpsSortKey {
+    fn column(self) -> &'static str {
+        match self {
+            VpsSortKey::Name => "v.name",
+            VpsSortKey::IpAddress => "v.ip_address",
+            VpsSortKey::Status => "v.status",
+            VpsSortKey::Group => "v.\"group\"",
+            VpsSortKey::AgentVersion => "v.agent_version",
+            VpsSortKey::CreatedAt => "v.created_at",
+        }
+    }
+}
+
+/// Filters for a page of the VPS list of a user's active organization. `None` fields don't filter.
+#[derive(Debug, Default)]
+pub struct VpsListFilter {
+    pub status: Option<String>,
+    pub group: Option<String>,
+    /// A node of the group tree; matches its subgroups too.
+    pub group_id: Option<i32>,
+    pub tag_id: Option<i32>,
+    /// Case-insensitive substring of the name or IP address.
+    pub search: Option<String>,
+    /// Restricts the list to these VPS, e.g. the members of a virtual group.
+    pub vps_ids: Option<Vec<i32>>,
+    pub sort_by: VpsSortKey,
+    pub descending: bool,
+}
+
+/// Escapes `%`, `_` and `\` so `value` matches literally inside `LIKE ... ESCAPE '\'`.
+fn like_pattern(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+/// One page of the VPS in a user's active organization, plus the number of VPS matching the filter.
+pub async fn get_vps_page_for_user(
+    pool: DuckDbPool,
+    user_id: i32,
+    filter: &VpsListFilter,
+    page: u32,
+    per_page: u32,
+) -> Result<(Vec<vps::Model>, u64), AppError> {
+    let mut conditions = vec![organization_service::org_scope("v.organization_id")];
+    let mut values: Vec<Box<dyn duckdb::ToSql>> = vec![Box::new(user_id)];
+    if let Some(status) = &filter.status {
+        conditions.push("v.status = ?".to_string());
+        values.push(Box::new(status.clone()));
+    }
+    if let Some(group) = &filter.group {
+        conditions.push("v.\"group\" = ?".to_string());
+        values.push(Box::new(group.clone()));
+    }
+    if let Some(group_id) = filter.group_id {
+        conditions.push(
+            "v.group_id IN (
+                WITH RECURSIVE subtree(id) AS (
+                    SELECT ?::INTEGER
+                    UNION
+                    SELECT g.id FROM vps_groups g JOIN subtree s ON g.parent_id = s.id
+                )
+                SELECT id FROM subtree
+            )"
+            .to_string(),
+        );
+        values.push(Box::new(group_id));
+    }
+    if let Some(tag_id) = filter.tag_id {
+        conditions.push("EXISTS (SELECT 1 FROM vps_tags vt WHERE vt.vps_id = v.id AND vt.tag_id = ?)".to_string());
+        values.push(Box::new(tag_id));
+    }
+    if let Some(search) = filter.search.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+        conditions.push("(v.name ILIKE ? ESCAPE '\\' OR v.ip_address ILIKE ? ESCAPE '\\')".to_string());
+        let pattern = like_pattern(search);
+        values.push(Box::new(pattern.clone()));
+        values.push(Box::new(pattern));
+    }
+    if let Some(vps_ids) = &filter.vps_ids {
+        if vps_ids.is_empty() {
+            return Ok((Vec::new(), 0));
+        }
+        conditions.push(format!("v.id IN ({})", vec!["?"; vps_ids.len()].join(", ")));
+        values.extend(vps_ids.iter().map(|id| Box::new(*id) as Box<dyn duckdb::ToSql>));
+    }
+    let where_clause = conditions.join(" AND ");
+
+    let conn = pool.get()?;
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM vps v WHERE {where_clause}"),
+        duckdb::params_from_iter(values.iter()),
+        |row| row.get(0),
+    )?;
+
+    let direction = if filter.descending { "DESC" } else { "ASC" };
+    let query = format!(
+        "SELECT v.* FROM vps v WHERE {where_clause}
+         ORDER BY {} {direction} NULLS LAST, v.id {direction}
+         LIMIT ? OFFSET ?",
+        filter.sort_by.column()
+    );
+    values.push(Box::new(per_page as i64));
+    values.push(Box::new(page.saturating_sub(1) as i64 * per_page as i64));
+    let items = conn
+        .prepare(&query)?
+        .query_map(duckdb::params_from_iter(values.iter()), super::vps_service::row_to_vps_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((items, total as u64))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search_pattern_matches_wildcards_literally() {
+        assert_eq!(like_pattern("web"), "%web%");
+        assert_eq!(like_pattern("10_0%"), "%10\\_0\\%%");
+        assert_eq!(like_pattern("a\\b"), "%a\\\\b%");
+    }
+}
@@ -0,0 +1,491 @@
+//! Hierarchical VPS groups. Every VPS points at one node (`vps.group_id`)
+//! and keeps the node's display path in `vps."group"`, so filters, health
+//! summaries and clients that only know the flat string keep working.
+
+use chrono::Utc;
+use duckdb::{params, Connection, OptionalExt, Result as DuckDbResult, Row};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
+use crate::db::entities::vps_group;
+use crate::web::error::AppError;
+
+const PATH_SEPARATOR: char = '/';
+
+fn row_to_vps_group_model(row: &Row) -> DuckDbResult<vps_group::Model> {
+    Ok(vps_group::Model {
+        id: row.get("id")?,
+        organization_id: row.get("organization_id")?,
+        parent_id: row.get("parent_id")?,
+        name: row.get("name")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+/// Live figures for one VPS, rolled up into its group and every ancestor.
+#[derive(Debug, Clone, Default)]
+pub struct GroupMember {
+    pub vps_id: i32,
+    pub group_id: Option<i32>,
+    pub online: bool,
+    pub cpu_percent: Option<f64>,
+    pub memory_percent: Option<f64>,
+    pub health_score: Option<f64>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupStats {
+    /// VPS in the group and all of its descendants.
+    pub vps_count: usize,
+    pub online_count: usize,
+    pub avg_cpu_percent: Option<f64>,
+    pub avg_memory_percent: Option<f64>,
+    pub avg_health_score: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupNode {
+    pub id: i32,
+    pub parent_id: Option<i32>,
+    pub name: String,
+    pub path: String,
+    pub stats: GroupStats,
+    pub children: Vec<GroupNode>,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GroupTree {
+    pub groups: Vec<GroupNode>,
+    /// VPS that are not in any group.
+    pub ungrouped: GroupStats,
+}
+
+fn validate_name(name: &str) -> Result<&str, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Group name must not be empty".to_string()));
+    }
+    if name.contains(PATH_SEPARATOR) {
+        return Err(AppError::InvalidInput(format!("Group name must not contain '{PATH_SEPARATOR}'")));
+    }
+    Ok(name)
+}
+
+/// Path segments of a flat group string such as `eu / hetzner/rack-1`.
+fn split_path(path: &str) -> Vec<&str> {
+    path.split(PATH_SEPARATOR).map(str::trim).filter(|s| !s.is_empty()).collect()
+}
+
+fn find_child(
+    conn: &Connection,
+    organization_id: i32,
+    parent_id: Option<i32>,
+    name: &str,
+) -> Result<Option<i32>, AppError> {
+    Ok(conn
+        .query_row(
+            "SELECT id FROM vps_groups
+             WHERE organization_id = ? AND parent_id IS NOT DISTINCT FROM ? AND name = ?",
+            params![organization_id, parent_id, name],
+            |row| row.get(0),
+        )
+        .optional()?)
+}
+
+fn get_group(conn: &Connection, organization_id: i32, id: i32) -> Result<vps_group::Model, AppError> {
+    conn.query_row(
+        "SELECT * FROM vps_groups WHERE id = ? AND organization_id = ?",
+        params![id, organization_id],
+        row_to_vps_group_model,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound("VPS group not found".to_string()))
+}
+
+fn groups_in_organization(conn: &Connection, organization_id: i32) -> Result<Vec<vps_group::Model>, AppError> {
+    Ok(conn
+        .prepare("SELECT * FROM vps_groups WHERE organization_id = ? ORDER BY name, id")?
+        .query_map(params![organization_id], row_to_vps_group_model)?
+        .collect::<Result<Vec<_>, _>>()?)
+}
+
+/// Display path of every group, keyed by ID.
+fn paths_by_id(groups: &[vps_group::Model]) -> HashMap<i32, String> {
+    let by_id: HashMap<i32, &vps_group::Model> = groups.iter().map(|g| (g.id, g)).collect();
+    groups
+        .iter()
+        .map(|group| {
+            let mut names = vec![group.name.as_str()];
+            let mut parent = group.parent_id;
+            // Bounded by the group count in case the table holds a cycle.
+            while let Some(parent_group) = parent.and_then(|id| by_id.get(&id)) {
+                if names.len() > groups.len() {
+                    break;
+                }
+                names.push(parent_group.name.as_str());
+                parent = parent_group.parent_id;
+            }
+            names.reverse();
+            (group.id, names.join(&PATH_SEPARATOR.to_string()))
+        })
+        .collect()
+}
+
+/// Whether `candidate` is `group_id` or one of its descendants.
+fn is_in_subtree(groups: &[vps_group::Model], group_id: i32, candidate: i32) -> bool {
+    let parents: HashMap<i32, Option<i32>> = groups.iter().map(|g| (g.id, g.parent_id)).collect();
+    let mut current = Some(candidate);
+    for _ in 0..=groups.len() {
+        match current {
+            Some(id) if id == group_id => return true,
+            Some(id) => current = parents.get(&id).copied().flatten(),
+            None => return false,
+        }
+    }
+    false
+}
+
+/// Rewrites `vps."group"` for every grouped VPS in the organization after
+/// groups are renamed or moved.
+fn refresh_vps_paths(conn: &Connection, organization_id: i32) -> Result<(), AppError> {
+    let groups = groups_in_organization(conn, organization_id)?;
+    let mut stmt = conn.prepare(r#"UPDATE vps SET "group" = ? WHERE group_id = ? AND "group" IS DISTINCT FROM ?"#)?;
+    for (id, path) in paths_by_id(&groups) {
+        stmt.execute(params![path, id, path])?;
+    }
+    Ok(())
+}
+
+/// Finds or creates the groups along a flat path and returns the last one;
+/// `None` for an empty path. Used for clients and provisioning tokens that
+/// still send the `group` string.
+pub(crate) fn ensure_group_path(conn: &Connection, organization_id: i32, path: &str) -> Result<Option<i32>, AppError> {
+    let mut parent_id = None;
+    for name in split_path(path) {
+        parent_id = Some(match find_child(conn, organization_id, parent_id, name)? {
+            Some(id) => id,
+            None => conn.query_row(
+                "INSERT INTO vps_groups (organization_id, parent_id, name) VALUES (?, ?, ?) RETURNING id",
+                params![organization_id, parent_id, name],
+                |row| row.get(0),
+            )?,
+        });
+    }
+    Ok(parent_id)
+}
+
+/// Puts a VPS into a group (or none) and updates its display path.
+pub(crate) fn assign_vps(
+    conn: &Connection,
+    organization_id: i32,
+    vps_id: i32,
+    group_id: Option<i32>,
+) -> Result<(), AppError> {
+    let path = match group_id {
+        Some(id) => {
+            get_group(conn, organization_id, id)?;
+            paths_by_id(&groups_in_organization(conn, organization_id)?).remove(&id)
+        }
+        None => None,
+    };
+    conn.execute(
+        r#"UPDATE vps SET group_id = ?, "group" = ?, updated_at = ? WHERE id = ?"#,
+        params![group_id, path, Utc::now(), vps_id],
+    )?;
+    Ok(())
+}
+
+pub async fn create_group(
+    pool: DuckDbPool,
+    user_id: i32,
+    parent_id: Option<i32>,
+    name: &str,
+) -> Result<vps_group::Model, AppError> {
+    let name = validate_name(name)?;
+    let conn = pool.get()?;
+    let organization_id = organization_service::active_organization_id(&conn, user_id)?;
+    if let Some(parent_id) = parent_id {
+        get_group(&conn, organization_id, parent_id)?;
+    }
+    if find_child(&conn, organization_id, parent_id, name)?.is_some() {
+        return Err(AppError::Conflict(format!("A group named '{name}' already exists here")));
+    }
+    Ok(conn.query_row(
+        "INSERT INTO vps_groups (organization_id, parent_id, name) VALUES (?, ?, ?) RETURNING *",
+        params![organization_id, parent_id, name],
+        row_to_vps_group_model,
+    )?)
+}
+
+/// Renames and/or moves a group. `parent_id: Some(None)` moves it to the top level.
+pub async fn update_group(
+    pool: DuckDbPool,
+    user_id: i32,
+    id: i32,
+    name: Option<&str>,
+    parent_id: Option<Option<i32>>,
+) -> Result<vps_group::Model, AppError> {
+    let mut conn = pool.get()?;
+    let organization_id = organization_service::active_organization_id(&conn, user_id)?;
+    let existing = get_group(&conn, organization_id, id)?;
+    let name = match name {
+        Some(name) => validate_name(name)?.to_string(),
+        None => existing.name.clone(),
+    };
+    let parent_id = parent_id.unwrap_or(existing.parent_id);
+    if let Some(parent_id) = parent_id {
+        get_group(&conn, organization_id, parent_id)?;
+        if is_in_subtree(&groups_in_organization(&conn, organization_id)?, id, parent_id) {
+            return Err(AppError::InvalidInput(
+                "A group can't be moved into itself or one of its subgroups".to_string(),
+            ));
+        }
+    }
+    if find_child(&conn, organization_id, parent_id, &name)?.is_some_and(|other| other != id) {
+        return Err(AppError::Conflict(format!("A group named '{name}' already exists here")));
+    }
+
+    let tx = conn.transaction()?;
+    let updated = tx.query_row(
+        "UPDATE vps_groups SET name = ?, parent_id = ?, updated_at = ? WHERE id = ? RETURNING *",
+        params![name, parent_id, Utc::now(), id],
+        row_to_vps_group_model,
+    )?;
+    refresh_vps_paths(&tx, organization_id)?;
+    tx.commit()?;
+    Ok(updated)
+}
+
+/// Deletes a group. Its subgroups and VPS move up to its parent.
+pub async fn delete_group(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let mut conn = pool.get()?;
+    let organization_id = organization_service::active_organization_id(&conn, user_id)?;
+    let existing = get_group(&conn, organization_id, id)?;
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "UPDATE vps_groups SET parent_id = ?, updated_at = ? WHERE parent_id = ?",
+        params![existing.parent_id, Utc::now(), id],
+    )?;
+    tx.execute(
+        r#"UPDATE vps SET group_id = ?, "group" = NULL WHERE group_id = ?"#,
+        params![existing.parent_id, id],
+    )?;
+    tx.execute("DELETE FROM vps_groups WHERE id = ?", params![id])?;
+    refresh_vps_paths(&tx, organization_id)?;
+    tx.commit()?;
+    Ok(())
+}
+
+/// Groups and latest metrics of the VPS in the user's active organization.
+pub async fn get_groups_and_members(
+    pool: DuckDbPool,
+    user_id: i32,
+) -> Result<(Vec<vps_group::Model>, Vec<GroupMember>), AppError> {
+    let conn = pool.get()?;
+    let organization_id = organization_service::active_organization_id(&conn, user_id)?;
+    let groups = groups_in_organization(&conn, organization_id)?;
+    let members = conn
+        .prepare(
+            "SELECT v.id, v.group_id, v.status,
+                    m.cpu_usage_percent,
+                    CASE WHEN m.memory_total_bytes > 0
+                         THEN m.memory_usage_bytes * 100.0 / m.memory_total_bytes END
+             FROM vps v
+             LEFT JOIN latest_performance_metrics m ON m.vps_id = v.id
+             WHERE v.organization_id = ? AND v.archived_at IS NULL",
+        )?
+        .query_map(params![organization_id], |row| {
+            Ok(GroupMember {
+                vps_id: row.get(0)?,
+                group_id: row.get(1)?,
+                online: row.get::<_, String>(2)? == "online",
+                cpu_percent: row.get(3)?,
+                memory_percent: row.get(4)?,
+                health_score: None,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((groups, members))
+}
+
+/// The given groups and all of their descendants within an organization.
+/// Unknown IDs are ignored.
+pub async fn subtree_ids(pool: DuckDbPool, organization_id: i32, roots: &[i32]) -> Result<HashSet<i32>, AppError> {
+    let conn = pool.get()?;
+    let groups = groups_in_organization(&conn, organization_id)?;
+    let mut children: HashMap<i32, Vec<i32>> = HashMap::new();
+    for group in &groups {
+        if let Some(parent_id) = group.parent_id {
+            children.entry(parent_id).or_default().push(group.id);
+        }
+    }
+    let mut ids = HashSet::new();
+    let mut pending: Vec<i32> = roots
+        .iter()
+        .copied()
+        .filter(|id| groups.iter().any(|g| g.id == *id))
+        .collect();
+    while let Some(id) = pending.pop() {
+        if ids.insert(id) {
+            pending.extend(children.get(&id).into_iter().flatten().copied());
+        }
+    }
+    Ok(ids)
+}
+
+#[derive(Default)]
+struct Accumulator {
+    vps_count: usize,
+    online_count: usize,
+    cpu: (f64, usize),
+    memory: (f64, usize),
+    health: (f64, usize),
+}
+
+impl Accumulator {
+    fn add(&mut self, member: &GroupMember) {
+        self.vps_count += 1;
+        self.online_count += usize::from(member.online);
+        for (sum, value) in [
+            (&mut self.cpu, member.cpu_percent),
+            (&mut self.memory, member.memory_percent),
+            (&mut self.health, member.health_score),
+        ] {
+            if let Some(value) = value {
+                sum.0 += value;
+                sum.1 += 1;
+            }
+        }
+    }
+
+    fn stats(&self) -> GroupStats {
+        let mean = |(sum, count): (f64, usize)| (count > 0).then(|| (sum / count as f64 * 10.0).round() / 10.0);
+        GroupStats {
+            vps_count: self.vps_count,
+            online_count: self.online_count,
+            avg_cpu_percent: mean(self.cpu),
+            avg_memory_percent: mean(self.memory),
+            avg_health_score: mean(self.health),
+        }
+    }
+}
+
+/// Builds the group tree, counting each VPS in its group and every ancestor.
+pub fn build_tree(groups: &[vps_group::Model], members: &[GroupMember]) -> GroupTree {
+    let paths = paths_by_id(groups);
+    let parents: HashMap<i32, Option<i32>> = groups.iter().map(|g| (g.id, g.parent_id)).collect();
+    let mut totals: HashMap<i32, Accumulator> = HashMap::new();
+    let mut ungrouped = Accumulator::default();
+    for member in members {
+        // A VPS pointing at a deleted group counts as ungrouped.
+        let Some(group_id) = member.group_id.filter(|id| parents.contains_key(id)) else {
+            ungrouped.add(member);
+            continue;
+        };
+        let mut current = Some(group_id);
+        for _ in 0..=groups.len() {
+            let Some(id) = current else { break };
+            totals.entry(id).or_default().add(member);
+            current = parents.get(&id).copied().flatten();
+        }
+    }
+
+    let mut children: HashMap<Option<i32>, Vec<&vps_group::Model>> = HashMap::new();
+    for group in groups {
+        // Orphans (parent missing from this organization) are shown at the top level.
+        let parent = group.parent_id.filter(|id| parents.contains_key(id));
+        children.entry(parent).or_default().push(group);
+    }
+    fn build(
+        parent: Option<i32>,
+        children: &HashMap<Option<i32>, Vec<&vps_group::Model>>,
+        paths: &HashMap<i32, String>,
+        totals: &HashMap<i32, Accumulator>,
+        depth: usize,
+    ) -> Vec<GroupNode> {
+        let Some(groups) = children.get(&parent) else {
+            return Vec::new();
+        };
+        if depth > paths.len() {
+            return Vec::new();
+        }
+        groups
+            .iter()
+            .map(|group| GroupNode {
+                id: group.id,
+                parent_id: group.parent_id,
+                name: group.name.clone(),
+                path: paths.get(&group.id).cloned().unwrap_or_else(|| group.name.clone()),
+                stats: totals.get(&group.id).map(Accumulator::stats).unwrap_or_default(),
+                children: build(Some(group.id), children, paths, totals, depth + 1),
+            })
+            .collect()
+    }
+
+    GroupTree {
+        groups: build(None, &children, &paths, &totals, 0),
+        ungrouped: ungrouped.stats(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group(id: i32, parent_id: Option<i32>, name: &str) -> vps_group::Model {
+        vps_group::Model {
+            id,
+            organization_id: 1,
+            parent_id,
+            name: name.to_string(),
+            created_at: Utc::now(),
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn member(vps_id: i32, group_id: Option<i32>, online: bool, cpu: f64) -> GroupMember {
+        GroupMember { vps_id, group_id, online, cpu_percent: Some(cpu), ..Default::default() }
+    }
+
+    #[test]
+    fn paths_follow_parents() {
+        let groups = [group(1, None, "eu"), group(2, Some(1), "hetzner"), group(3, Some(2), "rack-1")];
+        let paths = paths_by_id(&groups);
+        assert_eq!(paths[&3], "eu/hetzner/rack-1");
+        assert_eq!(paths[&1], "eu");
+        assert!(is_in_subtree(&groups, 1, 3));
+        assert!(!is_in_subtree(&groups, 3, 1));
+    }
+
+    #[test]
+    fn stats_roll_up_to_ancestors() {
+        let groups = [group(1, None, "eu"), group(2, Some(1), "hetzner"), group(3, None, "us")];
+        let members = [
+            member(10, Some(2), true, 20.0),
+            member(11, Some(1), false, 40.0),
+            member(12, None, true, 5.0),
+        ];
+        let tree = build_tree(&groups, &members);
+        let eu = tree.groups.iter().find(|g| g.name == "eu").unwrap();
+        assert_eq!(eu.stats.vps_count, 2);
+        assert_eq!(eu.stats.online_count, 1);
+        assert_eq!(eu.stats.avg_cpu_percent, Some(30.0));
+        assert_eq!(eu.children[0].stats.vps_count, 1);
+        assert_eq!(eu.children[0].path, "eu/hetzner");
+        assert_eq!(tree.ungrouped.vps_count, 1);
+        let us = tree.groups.iter().find(|g| g.name == "us").unwrap();
+        assert_eq!(us.stats, GroupStats::default());
+    }
+
+    #[test]
+    fn flat_paths_are_split_on_slashes() {
+        assert_eq!(split_path(" eu / hetzner//rack-1 "), vec!["eu", "hetzner", "rack-1"]);
+        assert!(split_path("  ").is_empty());
+    }
+}
@@ -1,8 +1,9 @@
-use crate::db::duckdb_service::DuckDbPool;
+use crate::db::duckdb_service::{notification_digest_service, webhook_service, DuckDbPool};
 use crate::db::entities::vps_renewal_info;
 use crate::web::error::AppError;
 use chrono::{DateTime, Duration, Months, Timelike, Utc};
 use duckdb::{params, OptionalExt, Transaction};
+use serde_json::json;
 use tracing::{error, info, warn};
 
 #[derive(Debug, Clone, Default)]
@@ -191,18 +192,36 @@ pub async fn check_and_generate_reminders(
     let mut updated_count: u64 = 0;
 
     for vps_renewal_info_model in candidates {
-        if let Some(nrd) = vps_renewal_info_model.next_renewal_date {
-            if nrd < now {
-                continue;
-            }
-        } else {
-            continue;
-        }
+        let nrd = match vps_renewal_info_model.next_renewal_date {
+            Some(nrd) if nrd >= now => nrd,
+            _ => continue,
+        };
 
         let rows = tx.execute(
             "UPDATE vps_renewal_info SET reminder_active = TRUE, last_reminder_generated_at = ?, updated_at = ? WHERE vps_id = ?",
             params![now, now, vps_renewal_info_model.vps_id],
         )?;
+        if rows > 0 {
+            webhook_service::publish_vps_event(
+                &tx,
+                vps_renewal_info_model.vps_id,
+                webhook_service::EVENT_RENEWAL_DUE,
+                json!({
+                    "nextRenewalDate": vps_renewal_info_model.next_renewal_date,
+                    "renewalCycle": vps_renewal_info_model.renewal_cycle,
+                    "renewalPrice": vps_renewal_info_model.renewal_price,
+                    "renewalCurrency": vps_renewal_info_model.renewal_currency,
+                    "autoRenewEnabled": vps_renewal_info_model.auto_renew_enabled,
+                }),
+            )?;
+            notification_digest_service::enqueue_renewal(
+                &tx,
+                vps_renewal_info_model.vps_id,
+                nrd,
+                vps_renewal_info_model.renewal_price,
+                vps_renewal_info_model.renewal_currency.as_deref(),
+            )?;
+        }
         updated_count += rows as u64;
     }
 
@@ -0,0 +1,290 @@
+//! Batch command targets. Besides explicit VPS IDs, a batch command can
+//! target every VPS matching a selector (tag, group, status) or a saved
+//! selection. Both are resolved when the command is dispatched, so a saved
+//! selection always runs on the VPS that match it at that time.
+
+use chrono::Utc;
+use duckdb::{params, Connection, OptionalExt, Row};
+
+use crate::db::duckdb_service::{json_from_row, organization_service, DuckDbPool};
+use crate::db::entities::vps_selection;
+use crate::web::error::AppError;
+use crate::web::models::batch_command_models::{BatchTargets, ResolvedTarget, TargetSelector, VpsSelectionRequest};
+
+/// Recorded on child tasks of VPS that were targeted by ID.
+pub const EXPLICIT_TARGET: &str = "vps";
+
+fn row_to_vps_selection(row: &Row) -> duckdb::Result<vps_selection::Model> {
+    Ok(vps_selection::Model {
+        id: row.get("id")?,
+        user_id: row.get("user_id")?,
+        name: row.get("name")?,
+        target_vps_ids: json_from_row(row, "target_vps_ids")?
+            .and_then(|ids| serde_json::from_value(ids).ok())
+            .unwrap_or_default(),
+        selectors: json_from_row(row, "selectors")?
+            .and_then(|selectors| serde_json::from_value(selectors).ok())
+            .unwrap_or_default(),
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+pub fn selection_label(name: &str) -> String {
+    format!("selection:{name}")
+}
+
+/// Rejects selectors that would match every VPS or no VPS at all.
+pub fn validate_selectors(selectors: &[TargetSelector]) -> Result<(), AppError> {
+    for selector in selectors {
+        if selector.is_empty() {
+            return Err(AppError::InvalidInput(
+                "A target selector needs a tag, a group or a status".to_string(),
+            ));
+        }
+        if selector.status.as_deref().is_some_and(|status| status.trim().is_empty()) {
+            return Err(AppError::InvalidInput("A target selector's status cannot be empty".to_string()));
+        }
+    }
+    Ok(())
+}
+
+/// One target per VPS, in the order the groups and their VPS come in. A VPS
+/// matched more than once keeps the first label that matched it.
+pub fn merge_targets(groups: Vec<(String, Vec<i32>)>) -> Vec<ResolvedTarget> {
+    let mut targets: Vec<ResolvedTarget> = Vec::new();
+    for (label, vps_ids) in groups {
+        for vps_id in vps_ids {
+            if !targets.iter().any(|target| target.vps_id == vps_id) {
+                targets.push(ResolvedTarget {
+                    vps_id,
+                    matched_selector: label.clone(),
+                });
+            }
+        }
+    }
+    targets
+}
+
+/// The VPS in the user's organization matching all criteria of `selector`.
+fn select_vps_ids(conn: &Connection, user_id: i32, selector: &TargetSelector) -> Result<Vec<i32>, AppError> {
+    let mut conditions = vec![
+        organization_service::org_scope("v.organization_id"),
+        "v.archived_at IS NULL".to_string(),
+    ];
+    let mut values: Vec<Box<dyn duckdb::ToSql>> = vec![Box::new(user_id)];
+    if let Some(tag_id) = selector.tag_id {
+        conditions.push("v.id IN (SELECT vps_id FROM vps_tags WHERE tag_id = ?)".to_string());
+        values.push(Box::new(tag_id));
+    }
+    if let Some(group_id) = selector.group_id {
+        conditions.push(
+            "v.group_id IN (
+                WITH RECURSIVE subtree(id) AS (
+                    SELECT id FROM vps_groups WHERE id = ?
+                    UNION ALL
+                    SELECT g.id FROM vps_groups g JOIN subtree s ON g.parent_id = s.id
+                )
+                SELECT id FROM subtree
+            )"
+            .to_string(),
+        );
+        values.push(Box::new(group_id));
+    }
+    if let Some(status) = &selector.status {
+        conditions.push("v.status = ?".to_string());
+        values.push(Box::new(status.trim().to_string()));
+    }
+    let sql = format!("SELECT v.id FROM vps v WHERE {} ORDER BY v.id", conditions.join(" AND "));
+    let ids = conn
+        .prepare(&sql)?
+        .query_map(duckdb::params_from_iter(values.iter()), |row| row.get(0))?
+        .collect::<Result<Vec<i32>, _>>()?;
+    Ok(ids)
+}
+
+fn get_selection(conn: &Connection, user_id: i32, id: i32) -> Result<vps_selection::Model, AppError> {
+    conn.query_row(
+        "SELECT * FROM vps_selections WHERE id = ? AND user_id = ?",
+        params![id, user_id],
+        row_to_vps_selection,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound(format!("Selection {id} not found")))
+}
+
+/// Explicit IDs first, then each selector, then each saved selection.
+pub fn resolve_targets(conn: &Connection, user_id: i32, targets: &BatchTargets) -> Result<Vec<ResolvedTarget>, AppError> {
+    validate_selectors(&targets.target_selectors)?;
+    let mut groups = vec![(EXPLICIT_TARGET.to_string(), targets.target_vps_ids.clone())];
+    for selector in &targets.target_selectors {
+        groups.push((selector.label(), select_vps_ids(conn, user_id, selector)?));
+    }
+    for selection_id in &targets.selection_ids {
+        let selection = get_selection(conn, user_id, *selection_id)?;
+        let mut vps_ids = selection.target_vps_ids.clone();
+        for selector in &selection.selectors {
+            vps_ids.extend(select_vps_ids(conn, user_id, selector)?);
+        }
+        groups.push((selection_label(&selection.name), vps_ids));
+    }
+    Ok(merge_targets(groups))
+}
+
+pub async fn resolve_batch_targets(
+    pool: DuckDbPool,
+    user_id: i32,
+    targets: &BatchTargets,
+) -> Result<Vec<ResolvedTarget>, AppError> {
+    let conn = pool.get()?;
+    resolve_targets(&conn, user_id, targets)
+}
+
+fn ensure_unique_name(conn: &Connection, user_id: i32, name: &str, exclude_id: Option<i32>) -> Result<(), AppError> {
+    let count: i64 = conn.query_row(
+        "SELECT COUNT(*) FROM vps_selections WHERE user_id = ? AND name = ? AND id IS DISTINCT FROM ?",
+        params![user_id, name, exclude_id],
+        |row| row.get(0),
+    )?;
+    if count > 0 {
+        return Err(AppError::Conflict(format!("A selection named '{name}' already exists.")));
+    }
+    Ok(())
+}
+
+/// Checks the request has a name and targets, and every VPS ID is visible
+/// in the user's organization.
+fn validate_request(conn: &Connection, user_id: i32, payload: &VpsSelectionRequest) -> Result<String, AppError> {
+    let name = payload.name.trim().to_string();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Name cannot be empty".to_string()));
+    }
+    if payload.target_vps_ids.is_empty() && payload.selectors.is_empty() {
+        return Err(AppError::InvalidInput("A selection needs at least one VPS or selector".to_string()));
+    }
+    validate_selectors(&payload.selectors)?;
+    for vps_id in &payload.target_vps_ids {
+        let visible = conn
+            .query_row(
+                &format!("SELECT 1 FROM vps WHERE id = ? AND {}", organization_service::org_scope("organization_id")),
+                params![vps_id, user_id],
+                |_| Ok(()),
+            )
+            .optional()?;
+        if visible.is_none() {
+            return Err(AppError::InvalidInput(format!("VPS {vps_id} not found")));
+        }
+    }
+    Ok(name)
+}
+
+pub async fn get_selections_for_user(pool: DuckDbPool, user_id: i32) -> Result<Vec<vps_selection::Model>, AppError> {
+    let conn = pool.get()?;
+    let selections = conn
+        .prepare("SELECT * FROM vps_selections WHERE user_id = ? ORDER BY name")?
+        .query_map(params![user_id], row_to_vps_selection)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(selections)
+}
+
+pub async fn create_selection(
+    pool: DuckDbPool,
+    user_id: i32,
+    payload: VpsSelectionRequest,
+) -> Result<vps_selection::Model, AppError> {
+    let conn = pool.get()?;
+    let name = validate_request(&conn, user_id, &payload)?;
+    ensure_unique_name(&conn, user_id, &name, None)?;
+    let selection = conn.query_row(
+        "INSERT INTO vps_selections (user_id, name, target_vps_ids, selectors) VALUES (?, ?, ?, ?) RETURNING *",
+        params![
+            user_id,
+            name,
+            serde_json::to_string(&payload.target_vps_ids)?,
+            serde_json::to_string(&payload.selectors)?,
+        ],
+        row_to_vps_selection,
+    )?;
+    Ok(selection)
+}
+
+pub async fn update_selection(
+    pool: DuckDbPool,
+    user_id: i32,
+    id: i32,
+    payload: VpsSelectionRequest,
+) -> Result<vps_selection::Model, AppError> {
+    let conn = pool.get()?;
+    get_selection(&conn, user_id, id)?;
+    let name = validate_request(&conn, user_id, &payload)?;
+    ensure_unique_name(&conn, user_id, &name, Some(id))?;
+    let selection = conn.query_row(
+        "UPDATE vps_selections SET name = ?, target_vps_ids = ?, selectors = ?, updated_at = ?
+         WHERE id = ? AND user_id = ? RETURNING *",
+        params![
+            name,
+            serde_json::to_string(&payload.target_vps_ids)?,
+            serde_json::to_string(&payload.selectors)?,
+            Utc::now(),
+            id,
+            user_id,
+        ],
+        row_to_vps_selection,
+    )?;
+    Ok(selection)
+}
+
+pub async fn delete_selection(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute(
+        "DELETE FROM vps_selections WHERE id = ? AND user_id = ?",
+        params![id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::NotFound(format!("Selection {id} not found")));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn selector(tag_id: Option<i32>, status: Option<&str>) -> TargetSelector {
+        TargetSelector {
+            tag_id,
+            group_id: None,
+            status: status.map(str::to_string),
+        }
+    }
+
+    #[test]
+    fn each_vps_is_targeted_once_with_the_first_label_that_matched_it() {
+        let targets = merge_targets(vec![
+            (EXPLICIT_TARGET.to_string(), vec![3]),
+            ("tag=1".to_string(), vec![1, 3, 5]),
+            (selection_label("Web servers"), vec![5, 7]),
+        ]);
+        let labels: Vec<(i32, &str)> = targets
+            .iter()
+            .map(|target| (target.vps_id, target.matched_selector.as_str()))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![(3, "vps"), (1, "tag=1"), (5, "tag=1"), (7, "selection:Web servers")]
+        );
+    }
+
+    #[test]
+    fn selectors_need_a_criterion() {
+        assert!(validate_selectors(&[selector(Some(1), Some("online"))]).is_ok());
+        assert!(validate_selectors(&[selector(None, None)]).is_err());
+        assert!(validate_selectors(&[selector(None, Some(" "))]).is_err());
+        assert_eq!(selector(Some(1), Some("online")).label(), "tag=1,status=online");
+        let group = TargetSelector {
+            group_id: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(group.label(), "group=4");
+    }
+}
@@ -5,16 +5,17 @@ use crate::db::duckdb_service::vps_renewal_service::{
 use crate::db::entities::vps;
 use crate::web::error::AppError;
 use chrono::{DateTime, Utc};
-use crate::db::duckdb_service::DuckDbPool;
-use duckdb::{params, Row};
+use crate::db::duckdb_service::{agent_version_service, organization_service, vps_group_service, DuckDbPool};
+use duckdb::{params, OptionalExt, Row};
 use nodenexus_common::agent_service::AgentHandshake;
 use serde_json::json;
 use uuid::Uuid;
 
-fn row_to_vps_model(row: &Row) -> Result<vps::Model, duckdb::Error> {
+pub(super) fn row_to_vps_model(row: &Row) -> Result<vps::Model, duckdb::Error> {
     Ok(vps::Model {
         id: row.get("id")?,
         user_id: row.get("user_id")?,
+        organization_id: row.get("organization_id")?,
         name: row.get("name")?,
         ip_address: row.get("ip_address")?,
         os_type: row.get("os_type")?,
@@ -25,6 +26,8 @@ fn row_to_vps_model(row: &Row) -> Result<vps::Model, duckdb::Error> {
         created_at: row.get("created_at")?,
         updated_at: row.get("updated_at")?,
         group: row.get("group")?,
+        group_id: row.get("group_id")?,
+        client_id: row.get("client_id")?,
         agent_config_override: json_from_row(row, "agent_config_override")?,
         config_status: row.get("config_status")?,
         last_config_update_at: row.get("last_config_update_at")?,
@@ -39,6 +42,8 @@ fn row_to_vps_model(row: &Row) -> Result<vps::Model, duckdb::Error> {
         traffic_reset_config_type: row.get("traffic_reset_config_type")?,
         traffic_reset_config_value: row.get("traffic_reset_config_value")?,
         next_traffic_reset_at: row.get("next_traffic_reset_at")?,
+        is_ephemeral: row.get("is_ephemeral")?,
+        archived_at: row.get("archived_at")?,
     })
 }
 
@@ -51,12 +56,14 @@ pub async fn create_vps(
     let conn = pool.get()?;
     let now = Utc::now();
     let generated_agent_secret = Uuid::new_v4().to_string();
+    let organization_id = organization_service::active_organization_id(&conn, user_id)?;
 
     let id: i32 = conn.query_row(
-        "INSERT INTO vps (user_id, name, agent_secret, status, created_at, updated_at, config_status, traffic_current_cycle_rx_bytes, traffic_current_cycle_tx_bytes, last_processed_cumulative_rx, last_processed_cumulative_tx)
-         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
+        "INSERT INTO vps (user_id, organization_id, name, agent_secret, status, created_at, updated_at, config_status, traffic_current_cycle_rx_bytes, traffic_current_cycle_tx_bytes, last_processed_cumulative_rx, last_processed_cumulative_tx)
+         VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?) RETURNING id",
         params![
             user_id,
+            organization_id,
             name,
             generated_agent_secret,
             "pending",
@@ -74,6 +81,7 @@ pub async fn create_vps(
     Ok(vps::Model {
         id,
         user_id,
+        organization_id: Some(organization_id),
         name: name.to_string(),
         ip_address: None,
         os_type: None,
@@ -84,6 +92,8 @@ pub async fn create_vps(
         created_at: now,
         updated_at: now,
         group: None,
+        group_id: None,
+        client_id: None,
         agent_config_override: None,
         config_status: "unknown".to_string(),
         last_config_update_at: None,
@@ -98,6 +108,8 @@ pub async fn create_vps(
         traffic_reset_config_type: None,
         traffic_reset_config_value: None,
         next_traffic_reset_at: None,
+        is_ephemeral: false,
+        archived_at: None,
     })
 }
 
@@ -135,18 +147,31 @@ pub async fn get_vps_by_ids(
     vps_iter.collect::<Result<Vec<_>, _>>().map_err(Into::into)
 }
 
-/// Retrieves all VPS entries for a given user.
+/// Retrieves all VPS entries in the user's active organization.
 pub async fn get_vps_by_user_id(
     pool: DuckDbPool,
     user_id: i32,
 ) -> Result<Vec<vps::Model>, AppError> {
     let conn = pool.get()?;
-    let mut stmt =
-        conn.prepare("SELECT * FROM vps WHERE user_id = ? ORDER BY created_at DESC")?;
+    let mut stmt = conn.prepare(&format!(
+        "SELECT * FROM vps WHERE {} ORDER BY created_at DESC",
+        organization_service::org_scope("organization_id")
+    ))?;
     let vps_iter = stmt.query_map(params![user_id], row_to_vps_model)?;
     vps_iter.collect::<Result<Vec<_>, _>>().map_err(Into::into)
 }
 
+/// Every unarchived VPS without an agent config override, across all
+/// organizations: those that the global agent config applies to.
+pub async fn get_vps_following_global_config(pool: DuckDbPool) -> Result<Vec<vps::Model>, AppError> {
+    let conn = pool.get()?;
+    let mut stmt = conn.prepare(
+        "SELECT * FROM vps WHERE agent_config_override IS NULL AND archived_at IS NULL ORDER BY id",
+    )?;
+    let vps_iter = stmt.query_map([], row_to_vps_model)?;
+    vps_iter.collect::<Result<Vec<_>, _>>().map_err(Into::into)
+}
+
 /// Updates a VPS's editable fields.
 #[allow(clippy::too_many_arguments)]
 pub async fn update_vps(
@@ -155,6 +180,7 @@ pub async fn update_vps(
     user_id: i32, // To ensure ownership
     name_opt: Option<String>,
     group_opt: Option<String>,
+    group_id_opt: Option<i32>,
     tag_ids: Option<Vec<i32>>,
     traffic_limit_bytes_opt: Option<i64>,
     traffic_billing_rule_opt: Option<String>,
@@ -170,17 +196,14 @@ pub async fn update_vps(
     let mut renewal_info_changed = false;
 
     // Verify ownership first
-    let vps_count: i64 = conn.query_row(
-        "SELECT COUNT(*) FROM vps WHERE id = ? AND user_id = ?",
-        params![vps_id, user_id],
-        |row| row.get(0),
-    )?;
-
-    if vps_count == 0 {
-        return Err(AppError::NotFound(
-            "VPS not found or access denied".to_string(),
-        ));
-    }
+    let organization_id: i32 = conn
+        .query_row(
+            &format!("SELECT organization_id FROM vps WHERE id = ? AND {}", organization_service::org_scope("organization_id")),
+            params![vps_id, user_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("VPS not found or access denied".to_string()))?;
 
     let tx = conn.transaction()?;
 
@@ -193,11 +216,6 @@ pub async fn update_vps(
         params_vec.push(name);
         vps_table_changed = true;
     }
-    if let Some(group) = &group_opt {
-        set_clauses.push("group = ?");
-        params_vec.push(group);
-        vps_table_changed = true;
-    }
     if let Some(limit) = &traffic_limit_bytes_opt {
         set_clauses.push("traffic_limit_bytes = ?");
         params_vec.push(limit);
@@ -234,6 +252,17 @@ pub async fn update_vps(
         tx.execute(&sql, &params_vec[..])?;
     }
 
+    // A group ID wins over the flat path, which is mapped onto the tree.
+    let group_id = match (group_id_opt, &group_opt) {
+        (Some(id), _) => Some(Some(id)),
+        (None, Some(path)) => Some(vps_group_service::ensure_group_path(&tx, organization_id, path)?),
+        (None, None) => None,
+    };
+    if let Some(group_id) = group_id {
+        vps_group_service::assign_vps(&tx, organization_id, vps_id, group_id)?;
+        vps_table_changed = true;
+    }
+
     // 2. If tag_ids is provided, update the associations.
     if let Some(ids) = tag_ids {
         tags_changed = true;
@@ -370,8 +399,9 @@ pub async fn update_vps_info_on_handshake(
     };
     let merged_metadata_str = serde_json::to_string(&merged_metadata).unwrap();
 
+    agent_version_service::record_version_change(&conn, vps_id, &handshake_info.agent_version, now)?;
     let rows_affected = conn.execute(
-        "UPDATE vps SET os_type = ?, ip_address = ?, agent_version = ?, metadata = ?, status = ?, updated_at = ? WHERE id = ?",
+        "UPDATE vps SET os_type = ?, ip_address = ?, agent_version = ?, metadata = ?, status = ?, updated_at = ?, archived_at = NULL WHERE id = ?",
         params![
             os_type_str,
             first_ipv4,
@@ -385,7 +415,38 @@ pub async fn update_vps_info_on_handshake(
 
     Ok(rows_affected as u64)
 }
-/// Retrieves a list of VPS models that are owned by the specified user from a given list of IDs.
+/// Id, IP address and metadata of every VPS that isn't archived.
+pub async fn get_vps_network_info(
+    pool: DuckDbPool,
+) -> Result<Vec<(i32, Option<String>, Option<serde_json::Value>)>, AppError> {
+    let conn = pool.get()?;
+    let rows = conn
+        .prepare("SELECT id, ip_address, metadata FROM vps WHERE archived_at IS NULL ORDER BY id")?
+        .query_map([], |row| {
+            let metadata: Option<String> = row.get(2)?;
+            Ok((row.get(0)?, row.get(1)?, metadata.and_then(|m| serde_json::from_str(&m).ok())))
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(rows)
+}
+
+/// Sets one top-level key of the VPS metadata, keeping the others.
+pub async fn set_metadata_key(
+    pool: DuckDbPool,
+    vps_id: i32,
+    key: &str,
+    value: serde_json::Value,
+) -> Result<u64, AppError> {
+    let conn = pool.get()?;
+    let patch = json!({ key: value }).to_string();
+    let rows_affected = conn.execute(
+        "UPDATE vps SET metadata = json_merge_patch(COALESCE(metadata, '{}'), ?) WHERE id = ?",
+        params![patch, vps_id],
+    )?;
+    Ok(rows_affected as u64)
+}
+
+/// Retrieves the VPS from a given list of IDs that are in the user's active organization.
 pub async fn get_owned_vps_from_ids(
     pool: DuckDbPool,
     user_id: i32,
@@ -398,7 +459,8 @@ pub async fn get_owned_vps_from_ids(
     let conn = pool.get()?;
     let params_sql = vps_ids.iter().map(|_| "?").collect::<Vec<_>>().join(",");
     let sql = format!(
-        "SELECT * FROM vps WHERE user_id = ? AND id IN ({params_sql})"
+        "SELECT * FROM vps WHERE {} AND id IN ({params_sql})",
+        organization_service::org_scope("organization_id")
     );
 
     let mut params_vec: Vec<&dyn duckdb::ToSql> = Vec::new();
@@ -9,6 +9,7 @@ fn row_to_vps_model(row: &Row) -> Result<vps::Model, duckdb::Error> {
     Ok(vps::Model {
         id: row.get("id")?,
         user_id: row.get("user_id")?,
+        organization_id: row.get("organization_id")?,
         name: row.get("name")?,
         ip_address: row.get("ip_address")?,
         os_type: row.get("os_type")?,
@@ -19,6 +20,8 @@ fn row_to_vps_model(row: &Row) -> Result<vps::Model, duckdb::Error> {
         created_at: row.get("created_at")?,
         updated_at: row.get("updated_at")?,
         group: row.get("group")?,
+        group_id: row.get("group_id")?,
+        client_id: row.get("client_id")?,
         agent_config_override: super::json_from_row(row, "agent_config_override")?,
         config_status: row.get("config_status")?,
         last_config_update_at: row.get("last_config_update_at")?,
@@ -33,6 +36,8 @@ fn row_to_vps_model(row: &Row) -> Result<vps::Model, duckdb::Error> {
         traffic_reset_config_type: row.get("traffic_reset_config_type")?,
         traffic_reset_config_value: row.get("traffic_reset_config_value")?,
         next_traffic_reset_at: row.get("next_traffic_reset_at")?,
+        is_ephemeral: row.get("is_ephemeral")?,
+        archived_at: row.get("archived_at")?,
     })
 }
 
@@ -69,6 +74,16 @@ pub fn update_vps_traffic_stats_after_metric(
     current_cycle_rx += delta_rx;
     current_cycle_tx += delta_tx;
 
+    if delta_rx > 0 || delta_tx > 0 {
+        txn.execute(
+            "INSERT INTO vps_daily_traffic (vps_id, day, rx_bytes, tx_bytes) VALUES (?, ?, ?, ?)
+             ON CONFLICT (vps_id, day) DO UPDATE SET
+                 rx_bytes = vps_daily_traffic.rx_bytes + excluded.rx_bytes,
+                 tx_bytes = vps_daily_traffic.tx_bytes + excluded.tx_bytes",
+            params![vps_id, Utc::now().date_naive(), delta_rx, delta_tx],
+        )?;
+    }
+
     txn.execute(
         "UPDATE vps SET traffic_current_cycle_rx_bytes = ?, traffic_current_cycle_tx_bytes = ?, last_processed_cumulative_rx = ?, last_processed_cumulative_tx = ?, updated_at = ? WHERE id = ?",
         params![
@@ -0,0 +1,370 @@
+use chrono::{DateTime, Duration, Utc};
+use duckdb::{params, params_from_iter, OptionalExt};
+use std::collections::{HashMap, HashSet};
+
+use crate::db::duckdb_service::notification_service::check_channel_ownership;
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
+use crate::services::osv_client::FixedIn;
+use crate::web::error::AppError;
+use crate::web::models::vulnerability_models::{
+    Severity, VpsVulnerability, VulnerabilityAlertSettings, VulnerabilityListQuery,
+};
+
+/// A host with package inventory, as input for a vulnerability scan.
+pub struct ScanHost {
+    pub vps_id: i32,
+    pub user_id: i32,
+    pub vps_name: String,
+    pub distribution_id: Option<String>,
+    pub os_version: Option<String>,
+    pub packages: Vec<ScanPackage>,
+}
+
+pub struct ScanPackage {
+    pub name: String,
+    pub source_name: Option<String>,
+    pub version: String,
+}
+
+/// A vulnerability record as cached from OSV.
+pub struct VulnerabilityRecord {
+    pub id: String,
+    pub aliases: Vec<String>,
+    pub summary: String,
+    pub severity: Severity,
+    pub cvss_score: Option<f64>,
+    pub fixed_in: Vec<FixedIn>,
+    pub published_at: Option<DateTime<Utc>>,
+    pub modified_at: Option<DateTime<Utc>>,
+}
+
+pub struct CachedVulnerability {
+    pub severity: Severity,
+    pub aliases: Vec<String>,
+    pub fixed_in: Vec<FixedIn>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VulnerabilityMatch {
+    pub vulnerability_id: String,
+    pub package_name: String,
+    pub installed_version: String,
+    pub fixed_version: Option<String>,
+}
+
+fn split_aliases(aliases: &str) -> Vec<String> {
+    aliases
+        .split(',')
+        .filter(|a| !a.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+pub async fn get_scan_hosts(pool: DuckDbPool) -> Result<Vec<ScanHost>, AppError> {
+    let conn = pool.get()?;
+    let mut hosts: Vec<ScanHost> = conn
+        .prepare(
+            "SELECT id, user_id, name,
+                    json_extract_string(metadata, '$.distribution_id'),
+                    json_extract_string(metadata, '$.os_version_detail')
+             FROM vps WHERE id IN (SELECT DISTINCT vps_id FROM vps_inventory_packages)
+             ORDER BY id",
+        )?
+        .query_map([], |row| {
+            Ok(ScanHost {
+                vps_id: row.get(0)?,
+                user_id: row.get(1)?,
+                vps_name: row.get(2)?,
+                distribution_id: row.get(3)?,
+                os_version: row.get(4)?,
+                packages: Vec::new(),
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut packages_by_vps: HashMap<i32, Vec<ScanPackage>> = HashMap::new();
+    let mut stmt = conn.prepare("SELECT vps_id, name, source_name, version FROM vps_inventory_packages")?;
+    let rows = stmt.query_map([], |row| {
+        Ok((
+            row.get::<_, i32>(0)?,
+            ScanPackage {
+                name: row.get(1)?,
+                source_name: row.get(2)?,
+                version: row.get(3)?,
+            },
+        ))
+    })?;
+    for row in rows {
+        let (vps_id, package) = row?;
+        packages_by_vps.entry(vps_id).or_default().push(package);
+    }
+    for host in &mut hosts {
+        host.packages = packages_by_vps.remove(&host.vps_id).unwrap_or_default();
+    }
+    Ok(hosts)
+}
+
+/// Returns the ids from `ids` that are not cached or were fetched more than `max_age` ago.
+pub async fn get_ids_needing_refresh(
+    pool: DuckDbPool,
+    ids: Vec<String>,
+    max_age: Duration,
+) -> Result<Vec<String>, AppError> {
+    let conn = pool.get()?;
+    let fresh: HashSet<String> = conn
+        .prepare("SELECT id FROM vulnerabilities WHERE fetched_at > ?")?
+        .query_map(params![Utc::now() - max_age], |row| row.get(0))?
+        .collect::<Result<_, _>>()?;
+    Ok(ids.into_iter().filter(|id| !fresh.contains(id)).collect())
+}
+
+pub async fn upsert_vulnerabilities(
+    pool: DuckDbPool,
+    records: Vec<VulnerabilityRecord>,
+) -> Result<(), AppError> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO vulnerabilities (id, aliases, summary, severity, cvss_score, fixed_in, published_at, modified_at, fetched_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (id) DO UPDATE SET
+                aliases = excluded.aliases,
+                summary = excluded.summary,
+                severity = excluded.severity,
+                cvss_score = excluded.cvss_score,
+                fixed_in = excluded.fixed_in,
+                published_at = excluded.published_at,
+                modified_at = excluded.modified_at,
+                fetched_at = excluded.fetched_at",
+        )?;
+        let now = Utc::now();
+        for record in &records {
+            let aliases = record.aliases.join(",");
+            let fixed_in = serde_json::to_string(&record.fixed_in)?;
+            stmt.execute(params![
+                record.id,
+                aliases,
+                record.summary,
+                record.severity.as_str(),
+                record.cvss_score,
+                fixed_in,
+                record.published_at,
+                record.modified_at,
+                now
+            ])?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+pub async fn get_cached_vulnerabilities(
+    pool: DuckDbPool,
+    ids: Vec<String>,
+) -> Result<HashMap<String, CachedVulnerability>, AppError> {
+    if ids.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let conn = pool.get()?;
+    let placeholders = vec!["?"; ids.len()].join(",");
+    let sql = format!("SELECT id, severity, aliases, fixed_in FROM vulnerabilities WHERE id IN ({placeholders})");
+    let mut stmt = conn.prepare(&sql)?;
+    let rows = stmt.query_map(params_from_iter(ids.iter()), |row| {
+        Ok((
+            row.get::<_, String>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, String>(2)?,
+            row.get::<_, String>(3)?,
+        ))
+    })?;
+
+    let mut cached = HashMap::new();
+    for row in rows {
+        let (id, severity, aliases, fixed_in) = row?;
+        cached.insert(
+            id,
+            CachedVulnerability {
+                severity: Severity::parse(&severity),
+                aliases: split_aliases(&aliases),
+                fixed_in: serde_json::from_str(&fixed_in).unwrap_or_default(),
+            },
+        );
+    }
+    Ok(cached)
+}
+
+/// Replaces the vulnerability matches of a host with `matches` and returns the
+/// ones that weren't known before. `unresolved` are `(vulnerability id, package)`
+/// pairs OSV still reports but whose details couldn't be fetched; a known match
+/// among them is kept as it is.
+pub async fn sync_vps_matches(
+    pool: DuckDbPool,
+    vps_id: i32,
+    matches: Vec<VulnerabilityMatch>,
+    unresolved: Vec<(String, String)>,
+) -> Result<Vec<VulnerabilityMatch>, AppError> {
+    let mut conn = pool.get()?;
+    let tx = conn.transaction()?;
+    let known: HashSet<(String, String)> = tx
+        .prepare("SELECT vulnerability_id, package_name FROM vps_vulnerabilities WHERE vps_id = ?")?
+        .query_map(params![vps_id], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<Result<_, _>>()?;
+
+    let now = Utc::now();
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO vps_vulnerabilities (vps_id, vulnerability_id, package_name, installed_version, fixed_version, first_seen_at, last_seen_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)
+             ON CONFLICT (vps_id, vulnerability_id, package_name) DO UPDATE SET
+                installed_version = excluded.installed_version,
+                fixed_version = excluded.fixed_version,
+                last_seen_at = excluded.last_seen_at",
+        )?;
+        for m in &matches {
+            stmt.execute(params![
+                vps_id,
+                m.vulnerability_id,
+                m.package_name,
+                m.installed_version,
+                m.fixed_version,
+                now,
+                now
+            ])?;
+        }
+        let mut stmt = tx.prepare(
+            "UPDATE vps_vulnerabilities SET last_seen_at = ?
+             WHERE vps_id = ? AND vulnerability_id = ? AND package_name = ?",
+        )?;
+        for (vulnerability_id, package_name) in unresolved.iter().filter(|key| known.contains(*key)) {
+            stmt.execute(params![now, vps_id, vulnerability_id, package_name])?;
+        }
+    }
+    // Anything not seen in this scan has been fixed or uninstalled.
+    tx.execute(
+        "DELETE FROM vps_vulnerabilities WHERE vps_id = ? AND last_seen_at < ?",
+        params![vps_id, now],
+    )?;
+    tx.commit()?;
+
+    Ok(matches
+        .into_iter()
+        .filter(|m| !known.contains(&(m.vulnerability_id.clone(), m.package_name.clone())))
+        .collect())
+}
+
+pub async fn list_vulnerabilities(
+    pool: DuckDbPool,
+    user_id: i32,
+    query: VulnerabilityListQuery,
+) -> Result<Vec<VpsVulnerability>, AppError> {
+    let conn = pool.get()?;
+    let min_severity = query.min_severity.unwrap_or(Severity::Unknown);
+    let severities: Vec<&str> = Severity::ALL
+        .into_iter()
+        .filter(|s| *s >= min_severity)
+        .map(|s| s.as_str())
+        .collect();
+
+    let mut sql = format!(
+        "SELECT vv.vps_id, v.name, vv.vulnerability_id, vu.aliases, vu.summary, vu.severity, vu.cvss_score,
+                vv.package_name, vv.installed_version, vv.fixed_version, vv.first_seen_at, vv.last_seen_at
+         FROM vps_vulnerabilities vv
+         JOIN vps v ON v.id = vv.vps_id
+         JOIN vulnerabilities vu ON vu.id = vv.vulnerability_id
+         WHERE {} AND vu.severity IN ({})",
+        organization_service::org_scope("v.organization_id"),
+        vec!["?"; severities.len()].join(",")
+    );
+    let package = query.package.as_deref().map(str::trim).filter(|p| !p.is_empty());
+    let mut params_vec: Vec<&dyn duckdb::ToSql> = vec![&user_id];
+    for severity in &severities {
+        params_vec.push(severity);
+    }
+    if let Some(vps_id) = &query.vps_id {
+        sql.push_str(" AND vv.vps_id = ?");
+        params_vec.push(vps_id);
+    }
+    if let Some(package) = &package {
+        sql.push_str(" AND lower(vv.package_name) = lower(?)");
+        params_vec.push(package);
+    }
+    sql.push_str(" ORDER BY vu.cvss_score DESC NULLS LAST, vv.first_seen_at DESC, vv.vps_id");
+
+    let mut rows = conn
+        .prepare(&sql)?
+        .query_map(&params_vec[..], |row| {
+            Ok(VpsVulnerability {
+                vps_id: row.get(0)?,
+                vps_name: row.get(1)?,
+                vulnerability_id: row.get(2)?,
+                aliases: split_aliases(&row.get::<_, String>(3)?),
+                summary: row.get(4)?,
+                severity: Severity::parse(&row.get::<_, String>(5)?),
+                cvss_score: row.get(6)?,
+                package_name: row.get(7)?,
+                installed_version: row.get(8)?,
+                fixed_version: row.get(9)?,
+                first_seen_at: row.get(10)?,
+                last_seen_at: row.get(11)?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    // Records without a CVSS score still sort by their textual severity.
+    rows.sort_by(|a, b| b.severity.cmp(&a.severity));
+    Ok(rows)
+}
+
+pub async fn get_alert_settings(
+    pool: DuckDbPool,
+    user_id: i32,
+) -> Result<VulnerabilityAlertSettings, AppError> {
+    let conn = pool.get()?;
+    let min_severity: Option<String> = conn
+        .query_row(
+            "SELECT min_severity FROM vulnerability_alert_settings WHERE user_id = ?",
+            params![user_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    let channel_ids = conn
+        .prepare("SELECT channel_id FROM vulnerability_alert_channels WHERE user_id = ? ORDER BY channel_id")?
+        .query_map(params![user_id], |row| row.get(0))?
+        .collect::<Result<Vec<i32>, _>>()?;
+
+    let mut settings = VulnerabilityAlertSettings::default();
+    if let Some(min_severity) = min_severity {
+        settings.min_severity = Severity::parse(&min_severity);
+    }
+    settings.channel_ids = channel_ids;
+    Ok(settings)
+}
+
+pub async fn update_alert_settings(
+    pool: DuckDbPool,
+    user_id: i32,
+    settings: VulnerabilityAlertSettings,
+) -> Result<VulnerabilityAlertSettings, AppError> {
+    let mut conn = pool.get()?;
+    check_channel_ownership(&conn, user_id, &settings.channel_ids)?;
+
+    let tx = conn.transaction()?;
+    tx.execute(
+        "INSERT INTO vulnerability_alert_settings (user_id, min_severity, updated_at) VALUES (?, ?, ?)
+         ON CONFLICT (user_id) DO UPDATE SET min_severity = excluded.min_severity, updated_at = excluded.updated_at",
+        params![user_id, settings.min_severity.as_str(), Utc::now()],
+    )?;
+    tx.execute("DELETE FROM vulnerability_alert_channels WHERE user_id = ?", params![user_id])?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT INTO vulnerability_alert_channels (user_id, channel_id) VALUES (?, ?) ON CONFLICT DO NOTHING",
+        )?;
+        for channel_id in &settings.channel_ids {
+            stmt.execute(params![user_id, channel_id])?;
+        }
+    }
+    tx.commit()?;
+    drop(conn);
+
+    get_alert_settings(pool, user_id).await
+}
@@ -0,0 +1,90 @@
+use chrono::{DateTime, TimeZone, Utc};
+use duckdb::{params, Result as DuckDbResult, Row};
+use nodenexus_common::agent_service::{WatchdogEvent, WatchdogEventKind};
+
+use crate::db::duckdb_service::DuckDbPool;
+use crate::db::entities::watchdog_event;
+use crate::web::error::AppError;
+
+/// Alert rules with this metric type count watchdog-observed exits over their duration.
+pub const WATCHDOG_EXITS_METRIC_TYPE: &str = "watchdog_exits";
+
+pub const KIND_EXITED: &str = "exited";
+
+fn kind_to_str(kind: WatchdogEventKind) -> &'static str {
+    match kind {
+        WatchdogEventKind::ProcessExited => KIND_EXITED,
+        WatchdogEventKind::ProcessRestarted => "restarted",
+        WatchdogEventKind::RestartFailed => "restart_failed",
+        WatchdogEventKind::RestartRateLimited => "rate_limited",
+        WatchdogEventKind::ProcessRecovered => "recovered",
+        WatchdogEventKind::Unspecified => "unknown",
+    }
+}
+
+fn row_to_watchdog_event_model(row: &Row) -> DuckDbResult<watchdog_event::Model> {
+    Ok(watchdog_event::Model {
+        id: row.get("id")?,
+        vps_id: row.get("vps_id")?,
+        time: row.get("time")?,
+        target_name: row.get("target_name")?,
+        kind: row.get("kind")?,
+        details: row.get("details")?,
+    })
+}
+
+pub async fn record_watchdog_event(
+    pool: DuckDbPool,
+    vps_id: i32,
+    event: &WatchdogEvent,
+) -> Result<watchdog_event::Model, AppError> {
+    let kind = WatchdogEventKind::try_from(event.kind).unwrap_or(WatchdogEventKind::Unspecified);
+    let time = Utc
+        .timestamp_millis_opt(event.timestamp_unix_ms)
+        .single()
+        .unwrap_or_else(Utc::now);
+    let details = (!event.details.is_empty()).then_some(event.details.as_str());
+
+    let conn = pool.get()?;
+    let model = conn.query_row(
+        "INSERT INTO watchdog_events (vps_id, time, target_name, kind, details)
+         VALUES (?, ?, ?, ?, ?) RETURNING *",
+        params![vps_id, time, event.target_name, kind_to_str(kind), details],
+        row_to_watchdog_event_model,
+    )?;
+    Ok(model)
+}
+
+/// Most recent events first.
+pub async fn get_watchdog_events_for_vps(
+    pool: DuckDbPool,
+    vps_id: i32,
+    start_time: DateTime<Utc>,
+    end_time: DateTime<Utc>,
+    limit: u32,
+) -> Result<Vec<watchdog_event::Model>, AppError> {
+    let conn = pool.get()?;
+    let events = conn
+        .prepare(
+            "SELECT * FROM watchdog_events
+             WHERE vps_id = ? AND time >= ? AND time <= ?
+             ORDER BY time DESC LIMIT ?",
+        )?
+        .query_map(params![vps_id, start_time, end_time, limit], row_to_watchdog_event_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(events)
+}
+
+pub async fn count_watchdog_exits(
+    pool: DuckDbPool,
+    vps_id: i32,
+    since: DateTime<Utc>,
+) -> Result<i64, AppError> {
+    let conn = pool.get()?;
+    let count = conn.query_row(
+        "SELECT COUNT(*) FROM watchdog_events WHERE vps_id = ? AND kind = ? AND time >= ?",
+        params![vps_id, KIND_EXITED, since],
+        |row| row.get(0),
+    )?;
+    Ok(count)
+}
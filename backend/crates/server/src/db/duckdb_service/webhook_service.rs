@@ -0,0 +1,456 @@
+//! Outgoing webhooks. Services publish events into the `webhook_deliveries`
+//! outbox on the connection (usually inside the transaction) that made the
+//! change; `services::webhook_dispatcher` posts them and tracks retries.
+
+use chrono::{DateTime, Utc};
+use duckdb::{params, Connection, OptionalExt, Result as DuckDbResult, Row};
+use serde_json::{json, Value};
+use std::sync::Arc;
+use uuid::Uuid;
+
+use crate::db::duckdb_service::{organization_service, DuckDbPool};
+use crate::db::entities::{webhook_delivery, webhook_subscription};
+use crate::notifications::encryption::EncryptionService;
+use crate::web::error::AppError;
+use crate::web::models::webhook_models::{CreateWebhookRequest, UpdateWebhookRequest};
+
+pub const EVENT_VPS_ONLINE: &str = "vps.online";
+pub const EVENT_VPS_OFFLINE: &str = "vps.offline";
+pub const EVENT_BATCH_COMMAND_FINISHED: &str = "batch_command.finished";
+pub const EVENT_RENEWAL_DUE: &str = "renewal.due";
+pub const EVENT_AGENT_UPDATED: &str = "agent.updated";
+pub const EVENT_TYPES: &[&str] = &[
+    EVENT_VPS_ONLINE,
+    EVENT_VPS_OFFLINE,
+    EVENT_BATCH_COMMAND_FINISHED,
+    EVENT_RENEWAL_DUE,
+    EVENT_AGENT_UPDATED,
+];
+
+pub const STATUS_PENDING: &str = "pending";
+pub const STATUS_DELIVERED: &str = "delivered";
+pub const STATUS_DEAD: &str = "dead";
+
+const MAX_DELIVERIES_LISTED: u32 = 500;
+
+/// A delivery that is due, with where to send it.
+#[derive(Debug, Clone)]
+pub struct DueDelivery {
+    pub delivery: webhook_delivery::Model,
+    pub url: String,
+    pub encrypted_secret: Vec<u8>,
+}
+
+fn json_column<T: serde::de::DeserializeOwned>(row: &Row, column: &str) -> DuckDbResult<T> {
+    let raw: String = row.get(column)?;
+    serde_json::from_str(&raw).map_err(|e| {
+        duckdb::Error::FromSqlConversionFailure(0, duckdb::types::Type::Text, Box::new(e))
+    })
+}
+
+fn row_to_subscription_model(row: &Row) -> DuckDbResult<webhook_subscription::Model> {
+    Ok(webhook_subscription::Model {
+        id: row.get("id")?,
+        organization_id: row.get("organization_id")?,
+        user_id: row.get("user_id")?,
+        name: row.get("name")?,
+        url: row.get("url")?,
+        event_types: json_column(row, "event_types")?,
+        is_active: row.get("is_active")?,
+        created_at: row.get("created_at")?,
+        updated_at: row.get("updated_at")?,
+    })
+}
+
+fn row_to_delivery_model(row: &Row) -> DuckDbResult<webhook_delivery::Model> {
+    Ok(webhook_delivery::Model {
+        id: row.get("id")?,
+        subscription_id: row.get("subscription_id")?,
+        event_id: row.get("event_id")?,
+        event_type: row.get("event_type")?,
+        payload: json_column(row, "payload")?,
+        status: row.get("status")?,
+        attempts: row.get("attempts")?,
+        next_attempt_at: row.get("next_attempt_at")?,
+        last_attempt_at: row.get("last_attempt_at")?,
+        last_status_code: row.get("last_status_code")?,
+        last_error: row.get("last_error")?,
+        created_at: row.get("created_at")?,
+    })
+}
+
+/// Whether a subscription filtering on `event_types` receives `event_type`.
+fn subscribes_to(event_types: &[String], event_type: &str) -> bool {
+    event_types.is_empty() || event_types.iter().any(|t| t == event_type)
+}
+
+/// The body posted for an event.
+fn event_payload(event_id: Uuid, event_type: &str, time: DateTime<Utc>, data: Value) -> Value {
+    json!({
+        "id": event_id,
+        "type": event_type,
+        "createdAt": time,
+        "data": data,
+    })
+}
+
+fn validate_event_types(event_types: &[String]) -> Result<(), AppError> {
+    match event_types.iter().find(|t| !EVENT_TYPES.contains(&t.as_str())) {
+        Some(unknown) => Err(AppError::InvalidInput(format!("Unknown event type '{unknown}'"))),
+        None => Ok(()),
+    }
+}
+
+fn validate_url(url: &str) -> Result<String, AppError> {
+    let url = url.trim();
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(url.to_string()),
+        _ => Err(AppError::InvalidInput("Webhook URL must be an http(s) URL".to_string())),
+    }
+}
+
+fn validate_name(name: &str) -> Result<String, AppError> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Webhook name must not be empty".to_string()));
+    }
+    Ok(name.to_string())
+}
+
+fn encrypt_secret(
+    encryption_service: &EncryptionService,
+    organization_id: i32,
+    secret: &str,
+) -> Result<Vec<u8>, AppError> {
+    if secret.trim().is_empty() {
+        return Err(AppError::InvalidInput("Webhook secret must not be empty".to_string()));
+    }
+    encryption_service
+        .encrypt_for(Some(organization_id), secret.trim().as_bytes())
+        .map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+/// Queues `event_type` for every active subscription of the organization that
+/// wants it. Returns the number of deliveries queued.
+pub(crate) fn publish_event(
+    conn: &Connection,
+    organization_id: i32,
+    event_type: &str,
+    data: Value,
+) -> DuckDbResult<usize> {
+    let subscriptions = conn
+        .prepare("SELECT * FROM webhook_subscriptions WHERE organization_id = ? AND is_active")?
+        .query_map(params![organization_id], row_to_subscription_model)?
+        .collect::<DuckDbResult<Vec<_>>>()?;
+    let subscriptions: Vec<_> = subscriptions
+        .into_iter()
+        .filter(|s| subscribes_to(&s.event_types, event_type))
+        .collect();
+    if subscriptions.is_empty() {
+        return Ok(0);
+    }
+
+    // Every subscription gets the same event id, so receivers can deduplicate.
+    let event_id = Uuid::new_v4();
+    let now = Utc::now();
+    let payload = event_payload(event_id, event_type, now, data).to_string();
+    for subscription in &subscriptions {
+        conn.execute(
+            "INSERT INTO webhook_deliveries (subscription_id, event_id, event_type, payload, status, next_attempt_at, created_at)
+             VALUES (?, ?, ?, ?, ?, ?, ?)",
+            params![subscription.id, event_id, event_type, payload, STATUS_PENDING, now, now],
+        )?;
+    }
+    Ok(subscriptions.len())
+}
+
+/// Publishes an event about one VPS to its organization. `data` is extended
+/// with the VPS's id and name.
+pub(crate) fn publish_vps_event(
+    conn: &Connection,
+    vps_id: i32,
+    event_type: &str,
+    mut data: Value,
+) -> DuckDbResult<usize> {
+    let vps: Option<(Option<i32>, String)> = conn
+        .query_row(
+            "SELECT organization_id, name FROM vps WHERE id = ?",
+            params![vps_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )
+        .optional()?;
+    let Some((Some(organization_id), name)) = vps else {
+        return Ok(0);
+    };
+    if let Value::Object(map) = &mut data {
+        map.insert("vpsId".to_string(), json!(vps_id));
+        map.insert("vpsName".to_string(), json!(name));
+    }
+    publish_event(conn, organization_id, event_type, data)
+}
+
+fn get_subscription(conn: &Connection, user_id: i32, id: i32) -> Result<webhook_subscription::Model, AppError> {
+    conn.query_row(
+        &format!(
+            "SELECT * FROM webhook_subscriptions WHERE id = ? AND {}",
+            organization_service::org_scope("organization_id")
+        ),
+        params![id, user_id],
+        row_to_subscription_model,
+    )
+    .optional()?
+    .ok_or_else(|| AppError::NotFound("Webhook not found".to_string()))
+}
+
+pub async fn get_subscriptions(pool: DuckDbPool, user_id: i32) -> Result<Vec<webhook_subscription::Model>, AppError> {
+    let conn = pool.get()?;
+    let subscriptions = conn
+        .prepare(&format!(
+            "SELECT * FROM webhook_subscriptions WHERE {} ORDER BY id",
+            organization_service::org_scope("organization_id")
+        ))?
+        .query_map(params![user_id], row_to_subscription_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(subscriptions)
+}
+
+/// Creates a subscription. Returns it together with the plaintext secret.
+pub async fn create_subscription(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    user_id: i32,
+    request: CreateWebhookRequest,
+) -> Result<(webhook_subscription::Model, String), AppError> {
+    let name = validate_name(&request.name)?;
+    let url = validate_url(&request.url)?;
+    validate_event_types(&request.event_types)?;
+    let secret = match request.secret {
+        Some(secret) => secret.trim().to_string(),
+        None => Uuid::new_v4().simple().to_string(),
+    };
+    let conn = pool.get()?;
+    let organization_id = organization_service::active_organization_id(&conn, user_id)?;
+    let encrypted_secret = encrypt_secret(&encryption_service, organization_id, &secret)?;
+    let subscription = conn.query_row(
+        "INSERT INTO webhook_subscriptions (organization_id, user_id, name, url, secret, event_types, is_active)
+         VALUES (?, ?, ?, ?, ?, ?, ?) RETURNING *",
+        params![
+            organization_id,
+            user_id,
+            name,
+            url,
+            encrypted_secret,
+            serde_json::to_string(&request.event_types)?,
+            request.is_active.unwrap_or(true),
+        ],
+        row_to_subscription_model,
+    )?;
+    Ok((subscription, secret))
+}
+
+pub async fn update_subscription(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    user_id: i32,
+    id: i32,
+    request: UpdateWebhookRequest,
+) -> Result<webhook_subscription::Model, AppError> {
+    let conn = pool.get()?;
+    let existing = get_subscription(&conn, user_id, id)?;
+    let name = match &request.name {
+        Some(name) => validate_name(name)?,
+        None => existing.name,
+    };
+    let url = match &request.url {
+        Some(url) => validate_url(url)?,
+        None => existing.url,
+    };
+    let event_types = request.event_types.unwrap_or(existing.event_types);
+    validate_event_types(&event_types)?;
+    if let Some(secret) = &request.secret {
+        conn.execute(
+            "UPDATE webhook_subscriptions SET secret = ? WHERE id = ?",
+            params![encrypt_secret(&encryption_service, existing.organization_id, secret)?, id],
+        )?;
+    }
+    Ok(conn.query_row(
+        "UPDATE webhook_subscriptions
+         SET name = ?, url = ?, event_types = ?, is_active = ?, updated_at = ?
+         WHERE id = ? RETURNING *",
+        params![
+            name,
+            url,
+            serde_json::to_string(&event_types)?,
+            request.is_active.unwrap_or(existing.is_active),
+            Utc::now(),
+            id,
+        ],
+        row_to_subscription_model,
+    )?)
+}
+
+pub async fn delete_subscription(pool: DuckDbPool, user_id: i32, id: i32) -> Result<(), AppError> {
+    let conn = pool.get()?;
+    let deleted = conn.execute(
+        &format!(
+            "DELETE FROM webhook_subscriptions WHERE id = ? AND {}",
+            organization_service::org_scope("organization_id")
+        ),
+        params![id, user_id],
+    )?;
+    if deleted == 0 {
+        return Err(AppError::NotFound("Webhook not found".to_string()));
+    }
+    conn.execute("DELETE FROM webhook_deliveries WHERE subscription_id = ?", params![id])?;
+    Ok(())
+}
+
+/// Deliveries of a subscription, newest first.
+pub async fn get_deliveries(
+    pool: DuckDbPool,
+    user_id: i32,
+    subscription_id: i32,
+    status: Option<&str>,
+    limit: Option<u32>,
+) -> Result<Vec<webhook_delivery::Model>, AppError> {
+    if let Some(status) = status {
+        if ![STATUS_PENDING, STATUS_DELIVERED, STATUS_DEAD].contains(&status) {
+            return Err(AppError::InvalidInput(format!("Unknown delivery status '{status}'")));
+        }
+    }
+    let conn = pool.get()?;
+    get_subscription(&conn, user_id, subscription_id)?;
+    let limit = limit.unwrap_or(100).min(MAX_DELIVERIES_LISTED);
+    let deliveries = conn
+        .prepare(
+            "SELECT * FROM webhook_deliveries
+             WHERE subscription_id = ? AND (? IS NULL OR status = ?)
+             ORDER BY created_at DESC, id DESC LIMIT ?",
+        )?
+        .query_map(params![subscription_id, status, status, limit], row_to_delivery_model)?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(deliveries)
+}
+
+/// Puts a dead delivery back in the queue with a fresh set of attempts.
+pub async fn retry_delivery(
+    pool: DuckDbPool,
+    user_id: i32,
+    subscription_id: i32,
+    delivery_id: i64,
+) -> Result<webhook_delivery::Model, AppError> {
+    let conn = pool.get()?;
+    get_subscription(&conn, user_id, subscription_id)?;
+    let status: String = conn
+        .query_row(
+            "SELECT status FROM webhook_deliveries WHERE id = ? AND subscription_id = ?",
+            params![delivery_id, subscription_id],
+            |row| row.get(0),
+        )
+        .optional()?
+        .ok_or_else(|| AppError::NotFound("Webhook delivery not found".to_string()))?;
+    if status != STATUS_DEAD {
+        return Err(AppError::Conflict("Only dead deliveries can be retried".to_string()));
+    }
+    Ok(conn.query_row(
+        "UPDATE webhook_deliveries SET status = ?, attempts = 0, next_attempt_at = ? WHERE id = ? RETURNING *",
+        params![STATUS_PENDING, Utc::now(), delivery_id],
+        row_to_delivery_model,
+    )?)
+}
+
+/// Pending deliveries whose next attempt is due, oldest first. Deliveries of
+/// paused subscriptions wait until they are resumed.
+pub async fn get_due_deliveries(
+    pool: DuckDbPool,
+    now: DateTime<Utc>,
+    limit: u32,
+) -> Result<Vec<DueDelivery>, AppError> {
+    let conn = pool.get()?;
+    let due = conn
+        .prepare(
+            "SELECT d.*, s.url AS subscription_url, s.secret AS subscription_secret
+             FROM webhook_deliveries d JOIN webhook_subscriptions s ON s.id = d.subscription_id
+             WHERE d.status = ? AND d.next_attempt_at <= ? AND s.is_active
+             ORDER BY d.next_attempt_at, d.id LIMIT ?",
+        )?
+        .query_map(params![STATUS_PENDING, now, limit], |row| {
+            Ok(DueDelivery {
+                delivery: row_to_delivery_model(row)?,
+                url: row.get("subscription_url")?,
+                encrypted_secret: row.get("subscription_secret")?,
+            })
+        })?
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(due)
+}
+
+/// Records an attempt. `next_attempt_at` is `None` when the delivery
+/// succeeded or is out of attempts; `delivered` tells the two apart.
+pub async fn record_attempt(
+    pool: DuckDbPool,
+    delivery_id: i64,
+    attempted_at: DateTime<Utc>,
+    delivered: bool,
+    status_code: Option<i32>,
+    error: Option<&str>,
+    next_attempt_at: Option<DateTime<Utc>>,
+) -> Result<(), AppError> {
+    let status = match (delivered, next_attempt_at) {
+        (true, _) => STATUS_DELIVERED,
+        (false, Some(_)) => STATUS_PENDING,
+        (false, None) => STATUS_DEAD,
+    };
+    let conn = pool.get()?;
+    conn.execute(
+        "UPDATE webhook_deliveries
+         SET status = ?, attempts = attempts + 1, last_attempt_at = ?, last_status_code = ?, last_error = ?,
+             next_attempt_at = COALESCE(?, next_attempt_at)
+         WHERE id = ?",
+        params![status, attempted_at, status_code, error, next_attempt_at, delivery_id],
+    )?;
+    Ok(())
+}
+
+/// Deletes finished (delivered or dead) deliveries older than `retention_days`.
+pub fn prune_deliveries(pool: &DuckDbPool, retention_days: u32) -> Result<usize, AppError> {
+    let conn = pool.get()?;
+    let cutoff = Utc::now() - chrono::Duration::days(i64::from(retention_days));
+    Ok(conn.execute(
+        "DELETE FROM webhook_deliveries WHERE status IN (?, ?) AND created_at < ?",
+        params![STATUS_DELIVERED, STATUS_DEAD, cutoff],
+    )?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_filter_receives_every_event() {
+        assert!(subscribes_to(&[], EVENT_VPS_OFFLINE));
+        let filter = vec![EVENT_VPS_ONLINE.to_string(), EVENT_VPS_OFFLINE.to_string()];
+        assert!(subscribes_to(&filter, EVENT_VPS_OFFLINE));
+        assert!(!subscribes_to(&filter, EVENT_AGENT_UPDATED));
+    }
+
+    #[test]
+    fn payload_wraps_the_event_data() {
+        let id = Uuid::nil();
+        let time = DateTime::parse_from_rfc3339("2025-08-01T12:00:00Z").unwrap().with_timezone(&Utc);
+        let payload = event_payload(id, EVENT_RENEWAL_DUE, time, json!({ "vpsId": 3 }));
+        assert_eq!(payload["id"], json!(id.to_string()));
+        assert_eq!(payload["type"], json!("renewal.due"));
+        assert_eq!(payload["createdAt"], json!("2025-08-01T12:00:00Z"));
+        assert_eq!(payload["data"]["vpsId"], json!(3));
+    }
+
+    #[test]
+    fn rejects_unknown_event_types_and_urls() {
+        assert!(validate_event_types(&[EVENT_BATCH_COMMAND_FINISHED.to_string()]).is_ok());
+        assert!(validate_event_types(&["vps.deleted".to_string()]).is_err());
+        assert_eq!(validate_url(" https://example.com/hook ").unwrap(), "https://example.com/hook");
+        assert!(validate_url("ftp://example.com").is_err());
+        assert!(validate_url("not a url").is_err());
+    }
+}
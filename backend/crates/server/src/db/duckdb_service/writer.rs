@@ -1,18 +1,148 @@
 use crate::db::entities::performance_metric;
+use chrono::{TimeZone, Utc};
 use duckdb::{params, Connection};
-use std::{sync::mpsc, time::Duration};
-use tracing::{error, info};
+use nodenexus_common::agent_service::PerformanceSnapshot;
+use std::{
+    collections::HashSet,
+    sync::mpsc,
+    time::{Duration, Instant},
+};
+use tracing::{debug, error, info};
 
-const BATCH_SIZE: usize = 100;
-const FLUSH_INTERVAL_SECONDS: u64 = 10;
+/// One performance batch from an agent. All of its rows (the snapshot itself,
+/// disk usages, disk I/O, network interfaces, GPUs, drive health and
+/// temperatures) are written in one transaction.
+#[derive(Debug, Clone)]
+pub struct SnapshotBatch {
+    pub vps_id: i32,
+    pub snapshots: Vec<PerformanceSnapshot>,
+    /// Buffered by the agent during an outage. Its snapshots may already be
+    /// stored and are checked before writing.
+    pub replayed: bool,
+}
+
+impl SnapshotBatch {
+    /// Rows this batch adds across all metric tables.
+    fn row_count(&self) -> usize {
+        self.snapshots
+            .iter()
+            .map(|s| {
+                1 + s.disk_usages.len()
+                    + s.disk_io_stats.len()
+                    + s.network_interface_stats.len()
+                    + s.gpu_stats.len()
+                    + s.disk_health_stats.len()
+                    + s.temperature_sensor_stats.len()
+            })
+            .sum()
+    }
+
+    /// The snapshot taken last; agents normally send them in order, but
+    /// retried batches may not be.
+    fn newest(&self) -> Option<&PerformanceSnapshot> {
+        self.snapshots.iter().max_by_key(|s| s.timestamp_unix_ms)
+    }
+}
+
+/// Keeps `latest_performance_metrics` at the newest row per VPS. A batch older
+/// than the stored row (e.g. a late retry) leaves it untouched.
+const UPSERT_LATEST_SQL: &str = "
+    INSERT INTO latest_performance_metrics (
+        vps_id, time, cpu_usage_percent, memory_usage_bytes, memory_total_bytes,
+        disk_io_read_bps, disk_io_write_bps, network_rx_cumulative, network_tx_cumulative,
+        swap_usage_bytes, swap_total_bytes, uptime_seconds, total_processes_count,
+        running_processes_count, tcp_established_connection_count, network_rx_instant_bps,
+        network_tx_instant_bps, total_disk_space_bytes, used_disk_space_bytes
+    ) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+    ON CONFLICT (vps_id) DO UPDATE SET
+        time = excluded.time,
+        cpu_usage_percent = excluded.cpu_usage_percent,
+        memory_usage_bytes = excluded.memory_usage_bytes,
+        memory_total_bytes = excluded.memory_total_bytes,
+        disk_io_read_bps = excluded.disk_io_read_bps,
+        disk_io_write_bps = excluded.disk_io_write_bps,
+        network_rx_cumulative = excluded.network_rx_cumulative,
+        network_tx_cumulative = excluded.network_tx_cumulative,
+        swap_usage_bytes = excluded.swap_usage_bytes,
+        swap_total_bytes = excluded.swap_total_bytes,
+        uptime_seconds = excluded.uptime_seconds,
+        total_processes_count = excluded.total_processes_count,
+        running_processes_count = excluded.running_processes_count,
+        tcp_established_connection_count = excluded.tcp_established_connection_count,
+        network_rx_instant_bps = excluded.network_rx_instant_bps,
+        network_tx_instant_bps = excluded.network_tx_instant_bps,
+        total_disk_space_bytes = excluded.total_disk_space_bytes,
+        used_disk_space_bytes = excluded.used_disk_space_bytes
+    WHERE excluded.time >= latest_performance_metrics.time
+";
+
+#[derive(Debug, Clone, Copy)]
+pub struct WriterConfig {
+    /// Buffered rows that trigger a flush before the interval elapses.
+    pub batch_size: usize,
+    pub flush_interval: Duration,
+}
+
+/// Totals since the last summary, logged every [`STATS_LOG_INTERVAL`].
+#[derive(Debug, Default)]
+struct WriterStats {
+    flushes: u64,
+    failed_flushes: u64,
+    rows_written: usize,
+    flush_time: Duration,
+    max_flush_latency: Duration,
+}
+
+const STATS_LOG_INTERVAL: Duration = Duration::from_secs(300);
+
+impl WriterStats {
+    fn record(&mut self, rows: usize, latency: Duration, ok: bool) {
+        self.flushes += 1;
+        if ok {
+            self.rows_written += rows;
+        } else {
+            self.failed_flushes += 1;
+        }
+        self.flush_time += latency;
+        self.max_flush_latency = self.max_flush_latency.max(latency);
+    }
+
+    fn log_and_reset(&mut self) {
+        if self.flushes > 0 {
+            info!(
+                flushes = self.flushes,
+                failed_flushes = self.failed_flushes,
+                rows = self.rows_written,
+                rows_per_sec = rows_per_sec(self.rows_written, self.flush_time) as u64,
+                avg_flush_ms = (self.flush_time / self.flushes as u32).as_millis() as u64,
+                max_flush_ms = self.max_flush_latency.as_millis() as u64,
+                "DuckDB metrics writer summary."
+            );
+        }
+        *self = Self::default();
+    }
+}
+
+fn rows_per_sec(rows: usize, latency: Duration) -> f64 {
+    if latency.is_zero() {
+        0.0
+    } else {
+        rows as f64 / latency.as_secs_f64()
+    }
+}
 
 /// 后台任务，在一个专用的 OS 线程中运行。
-/// 它从队列中读取指标并将其批量写入数据库。
+/// 它从队列中读取快照批次并将其批量写入数据库。
 pub(super) fn metrics_writer_task(
     pool: super::DuckDbPool,
-    rx: mpsc::Receiver<performance_metric::Model>,
+    rx: mpsc::Receiver<SnapshotBatch>,
+    config: WriterConfig,
 ) {
-    info!("DuckDB metrics writer thread started.");
+    info!(
+        batch_size = config.batch_size,
+        flush_interval_secs = config.flush_interval.as_secs(),
+        "DuckDB metrics writer thread started."
+    );
 
     // 在这个线程中创建唯一的数据库连接。
     let mut conn = match pool.get() {
@@ -23,93 +153,358 @@ pub(super) fn metrics_writer_task(
         }
     };
 
-    let mut buffer = Vec::with_capacity(BATCH_SIZE);
-    let flush_interval = Duration::from_secs(FLUSH_INTERVAL_SECONDS);
+    let mut buffer: Vec<SnapshotBatch> = Vec::new();
+    let mut buffered_rows = 0;
+    let mut last_flush = Instant::now();
+    let mut stats = WriterStats::default();
+    let mut last_summary = Instant::now();
 
-    // Loop to receive messages with a timeout.
     loop {
-        match rx.recv_timeout(flush_interval) {
-            Ok(metric) => {
-                buffer.push(metric);
-                if buffer.len() >= BATCH_SIZE {
-                    if let Err(e) = flush_metrics_to_db(&mut conn, &mut buffer) {
-                        error!("Failed to flush metrics to DuckDB on batch size: {}", e);
-                    }
+        if last_summary.elapsed() >= STATS_LOG_INTERVAL {
+            stats.log_and_reset();
+            last_summary = Instant::now();
+        }
+        let wait = config.flush_interval.saturating_sub(last_flush.elapsed());
+        match rx.recv_timeout(wait) {
+            Ok(batch) => {
+                buffered_rows += batch.row_count();
+                buffer.push(batch);
+                if buffered_rows >= config.batch_size {
+                    flush(&mut conn, &mut buffer, &mut stats);
+                    buffered_rows = 0;
+                    last_flush = Instant::now();
                 }
             }
             Err(mpsc::RecvTimeoutError::Timeout) => {
-                // Timeout occurred, flush the buffer if it's not empty.
-                if !buffer.is_empty() {
-                    if let Err(e) = flush_metrics_to_db(&mut conn, &mut buffer) {
-                        error!("Failed to flush metrics to DuckDB on interval: {}", e);
-                    }
-                }
+                flush(&mut conn, &mut buffer, &mut stats);
+                buffered_rows = 0;
+                last_flush = Instant::now();
             }
             Err(mpsc::RecvTimeoutError::Disconnected) => {
-                // Channel has been closed.
                 info!("Metrics channel closed. Flushing remaining metrics and shutting down writer thread.");
-                if !buffer.is_empty() {
-                    if let Err(e) = flush_metrics_to_db(&mut conn, &mut buffer) {
-                        error!("Failed to flush remaining metrics to DuckDB: {}", e);
-                    }
-                }
+                flush(&mut conn, &mut buffer, &mut stats);
                 break;
             }
         }
     }
+    stats.log_and_reset();
     info!("DuckDB metrics writer thread finished.");
 }
 
-/// 将缓冲区中的指标刷新到数据库 (同步版本)
-fn flush_metrics_to_db(
-    conn: &mut Connection, // 接收可变引用以创建事务
-    buffer: &mut Vec<performance_metric::Model>,
-) -> duckdb::Result<()> {
+/// Writes and clears the buffer. A failed flush rolls back and drops the
+/// buffered batches rather than retrying rows that may be rejected again.
+fn flush(conn: &mut Connection, buffer: &mut Vec<SnapshotBatch>, stats: &mut WriterStats) {
     if buffer.is_empty() {
-        return Ok(());
+        return;
+    }
+    let started = Instant::now();
+    let stored = |vps_id: i32, timestamp_unix_ms: i64| {
+        let time = Utc.timestamp_millis_opt(timestamp_unix_ms).single();
+        conn.query_row(
+            "SELECT EXISTS (SELECT 1 FROM performance_metrics WHERE vps_id = ? AND time = ?)",
+            params![vps_id, time],
+            |row| row.get(0),
+        )
+    };
+    match drop_replayed_duplicates(buffer, stored) {
+        Ok(0) => {}
+        Ok(dropped) => debug!(dropped, "Dropped replayed snapshots that were already stored."),
+        Err(e) => error!(error = %e, "Failed to check replayed snapshots; writing them as they are."),
+    }
+    let result = write_batches(conn, buffer);
+    let latency = started.elapsed();
+    match result {
+        Ok(rows) => {
+            stats.record(rows, latency, true);
+            debug!(
+                rows,
+                batches = buffer.len(),
+                latency_ms = latency.as_millis() as u64,
+                rows_per_sec = rows_per_sec(rows, latency) as u64,
+                "Flushed metrics to DuckDB."
+            );
+        }
+        Err(e) => {
+            stats.record(0, latency, false);
+            error!(batches = buffer.len(), error = %e, "Failed to flush metrics to DuckDB; batch dropped.");
+        }
     }
+    buffer.clear();
+}
 
-    info!("Flushing {} metrics to DuckDB.", buffer.len());
+/// Removes snapshots of replayed batches that are already stored or appear
+/// earlier in `batches`, matched on (vps_id, timestamp). Returns how many were
+/// removed.
+fn drop_replayed_duplicates(
+    batches: &mut [SnapshotBatch],
+    mut is_stored: impl FnMut(i32, i64) -> duckdb::Result<bool>,
+) -> duckdb::Result<usize> {
+    let mut seen = HashSet::new();
+    let mut dropped = 0;
+    for batch in batches.iter_mut() {
+        let vps_id = batch.vps_id;
+        if !batch.replayed {
+            seen.extend(batch.snapshots.iter().map(|s| (vps_id, s.timestamp_unix_ms)));
+            continue;
+        }
+        let mut kept = Vec::with_capacity(batch.snapshots.len());
+        for snapshot in batch.snapshots.drain(..) {
+            let key = (vps_id, snapshot.timestamp_unix_ms);
+            if seen.contains(&key) || is_stored(vps_id, snapshot.timestamp_unix_ms)? {
+                dropped += 1;
+            } else {
+                seen.insert(key);
+                kept.push(snapshot);
+            }
+        }
+        batch.snapshots = kept;
+    }
+    Ok(dropped)
+}
 
+/// Appends every row of `batches` in a single transaction and returns the row count.
+fn write_batches(conn: &mut Connection, batches: &[SnapshotBatch]) -> duckdb::Result<usize> {
     let tx = conn.transaction()?;
+    let mut rows = 0;
+    {
+        let mut metrics = tx.appender("performance_metrics")?;
+        let mut disk_usages = tx.appender("disk_usage_metrics")?;
+        let mut disk_io = tx.appender("disk_io_metrics")?;
+        let mut interfaces = tx.appender("network_interface_metrics")?;
+        let mut gpus = tx.appender("gpu_metrics")?;
+        let mut disk_health = tx.appender("disk_health_metrics")?;
+        let mut temperatures = tx.appender("temperature_metrics")?;
+
+        for batch in batches {
+            let vps_id = batch.vps_id;
+            for snapshot in &batch.snapshots {
+                // Column order must match the table definitions.
+                let metric = performance_metric::Model::from_snapshot(vps_id, snapshot);
+                metrics.append_row(params![
+                    metric.time,
+                    metric.vps_id,
+                    metric.cpu_usage_percent,
+                    metric.memory_usage_bytes,
+                    metric.memory_total_bytes,
+                    metric.disk_io_read_bps,
+                    metric.disk_io_write_bps,
+                    metric.network_rx_cumulative,
+                    metric.network_tx_cumulative,
+                    metric.swap_usage_bytes,
+                    metric.swap_total_bytes,
+                    metric.uptime_seconds,
+                    metric.total_processes_count,
+                    metric.running_processes_count,
+                    metric.tcp_established_connection_count,
+                    metric.network_rx_instant_bps,
+                    metric.network_tx_instant_bps,
+                    metric.total_disk_space_bytes,
+                    metric.used_disk_space_bytes,
+                ])?;
+                rows += 1;
+
+                let time = Utc
+                    .timestamp_millis_opt(snapshot.timestamp_unix_ms)
+                    .single()
+                    .unwrap_or_else(Utc::now);
+                for usage in &snapshot.disk_usages {
+                    let fstype = (!usage.fstype.is_empty()).then_some(usage.fstype.as_str());
+                    disk_usages.append_row(params![
+                        time,
+                        vps_id,
+                        usage.mount_point,
+                        fstype,
+                        usage.used_bytes as i64,
+                        usage.total_bytes as i64,
+                    ])?;
+                    rows += 1;
+                }
+                for stats in &snapshot.disk_io_stats {
+                    disk_io.append_row(params![
+                        time,
+                        vps_id,
+                        stats.device,
+                        stats.read_bytes_per_sec as i64,
+                        stats.write_bytes_per_sec as i64,
+                        stats.read_iops,
+                        stats.write_iops,
+                        stats.await_ms,
+                        stats.utilization_percent,
+                    ])?;
+                    rows += 1;
+                }
+                for stats in &snapshot.network_interface_stats {
+                    let speed_mbps = (stats.speed_mbps > 0).then_some(stats.speed_mbps as i32);
+                    let duplex = (!stats.duplex.is_empty()).then_some(stats.duplex.as_str());
+                    interfaces.append_row(params![
+                        time,
+                        vps_id,
+                        stats.name,
+                        stats.rx_errors_per_sec,
+                        stats.tx_errors_per_sec,
+                        stats.rx_dropped_per_sec,
+                        stats.tx_dropped_per_sec,
+                        stats.collisions_per_sec,
+                        stats.carrier_changes as i64,
+                        stats.link_up,
+                        speed_mbps,
+                        duplex,
+                    ])?;
+                    rows += 1;
+                }
+                for stats in &snapshot.gpu_stats {
+                    let uuid = (!stats.uuid.is_empty()).then_some(stats.uuid.as_str());
+                    gpus.append_row(params![
+                        time,
+                        vps_id,
+                        stats.index as i32,
+                        stats.name,
+                        stats.vendor,
+                        uuid,
+                        stats.utilization_percent,
+                        stats.memory_used_bytes as i64,
+                        stats.memory_total_bytes as i64,
+                        stats.temperature_celsius,
+                        stats.power_draw_watts,
+                    ])?;
+                    rows += 1;
+                }
+                for stats in &snapshot.disk_health_stats {
+                    let serial = (!stats.serial.is_empty()).then_some(stats.serial.as_str());
+                    disk_health.append_row(params![
+                        time,
+                        vps_id,
+                        stats.device,
+                        stats.model,
+                        serial,
+                        stats.smart_passed,
+                        stats.reallocated_sectors.map(|v| v as i64),
+                        stats.pending_sectors.map(|v| v as i64),
+                        stats.wear_percent,
+                        stats.temperature_celsius,
+                        stats.power_on_hours.map(|v| v as i64),
+                    ])?;
+                    rows += 1;
+                }
+                for stats in &snapshot.temperature_sensor_stats {
+                    temperatures.append_row(params![
+                        time,
+                        vps_id,
+                        stats.label,
+                        stats.temperature_celsius,
+                        stats.critical_celsius,
+                    ])?;
+                    rows += 1;
+                }
+            }
+        }
+
+        // Appenders flush on drop but swallow errors there; flush explicitly so
+        // a bad row aborts the transaction.
+        metrics.flush()?;
+        disk_usages.flush()?;
+        disk_io.flush()?;
+        interfaces.flush()?;
+        gpus.flush()?;
+        disk_health.flush()?;
+        temperatures.flush()?;
+    }
     {
-        let mut stmt = tx.prepare(
-            "INSERT INTO performance_metrics (
-                time, vps_id, cpu_usage_percent, memory_usage_bytes, memory_total_bytes,
-                disk_io_read_bps, disk_io_write_bps, network_rx_cumulative, network_tx_cumulative,
-                swap_usage_bytes, swap_total_bytes, uptime_seconds, total_processes_count,
-                running_processes_count, tcp_established_connection_count, network_rx_instant_bps,
-                network_tx_instant_bps, total_disk_space_bytes, used_disk_space_bytes
-            ) VALUES (
-                ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?
-            )",
-        )?;
-
-        for metric in buffer.drain(..) { // 使用 drain 清空 buffer
-            stmt.execute(params![
-                metric.time,
-                metric.vps_id,
-                metric.cpu_usage_percent,
-                { metric.memory_usage_bytes },
-                { metric.memory_total_bytes },
-                { metric.disk_io_read_bps },
-                { metric.disk_io_write_bps },
-                { metric.network_rx_cumulative },
-                { metric.network_tx_cumulative },
-                { metric.swap_usage_bytes },
-                { metric.swap_total_bytes },
-                { metric.uptime_seconds },
-                { metric.total_processes_count },
-                { metric.running_processes_count },
-                { metric.tcp_established_connection_count },
-                { metric.network_rx_instant_bps },
-                { metric.network_tx_instant_bps },
-                { metric.total_disk_space_bytes },
-                { metric.used_disk_space_bytes },
+        let mut upsert_latest = tx.prepare(UPSERT_LATEST_SQL)?;
+        for batch in batches {
+            let Some(snapshot) = batch.newest() else {
+                continue;
+            };
+            let m = performance_metric::Model::from_snapshot(batch.vps_id, snapshot);
+            upsert_latest.execute(params![
+                m.vps_id,
+                m.time,
+                m.cpu_usage_percent,
+                m.memory_usage_bytes,
+                m.memory_total_bytes,
+                m.disk_io_read_bps,
+                m.disk_io_write_bps,
+                m.network_rx_cumulative,
+                m.network_tx_cumulative,
+                m.swap_usage_bytes,
+                m.swap_total_bytes,
+                m.uptime_seconds,
+                m.total_processes_count,
+                m.running_processes_count,
+                m.tcp_established_connection_count,
+                m.network_rx_instant_bps,
+                m.network_tx_instant_bps,
+                m.total_disk_space_bytes,
+                m.used_disk_space_bytes,
             ])?;
         }
     }
     tx.commit()?;
+    Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nodenexus_common::agent_service::{DiskHealthStats, DiskUsage, TemperatureSensorStats};
+
+    #[test]
+    fn row_count_covers_every_table() {
+        let snapshot = PerformanceSnapshot {
+            disk_usages: vec![DiskUsage::default(), DiskUsage::default()],
+            disk_health_stats: vec![DiskHealthStats::default()],
+            temperature_sensor_stats: vec![TemperatureSensorStats::default()],
+            ..Default::default()
+        };
+        let batch = SnapshotBatch {
+            vps_id: 1,
+            snapshots: vec![snapshot, PerformanceSnapshot::default()],
+            replayed: false,
+        };
+        assert_eq!(batch.row_count(), 6);
+    }
 
-    Ok(())
-}
\ No newline at end of file
+    #[test]
+    fn newest_snapshot_is_by_timestamp() {
+        let at = |timestamp_unix_ms| PerformanceSnapshot {
+            timestamp_unix_ms,
+            ..Default::default()
+        };
+        let batch = SnapshotBatch {
+            vps_id: 1,
+            snapshots: vec![at(2_000), at(3_000), at(1_000)],
+            replayed: false,
+        };
+        assert_eq!(batch.newest().map(|s| s.timestamp_unix_ms), Some(3_000));
+        assert!(SnapshotBatch { vps_id: 1, snapshots: Vec::new(), replayed: false }.newest().is_none());
+    }
+
+    #[test]
+    fn replayed_snapshots_are_deduplicated() {
+        let at = |timestamp_unix_ms| PerformanceSnapshot {
+            timestamp_unix_ms,
+            ..Default::default()
+        };
+        let mut batches = vec![
+            SnapshotBatch { vps_id: 1, snapshots: vec![at(3_000)], replayed: false },
+            SnapshotBatch { vps_id: 1, snapshots: vec![at(1_000), at(2_000), at(3_000)], replayed: true },
+            SnapshotBatch { vps_id: 1, snapshots: vec![at(2_000), at(4_000)], replayed: true },
+            SnapshotBatch { vps_id: 2, snapshots: vec![at(1_000)], replayed: true },
+        ];
+        // 1_000 of VPS 1 is already in the database.
+        let dropped = drop_replayed_duplicates(&mut batches, |vps_id, ts| Ok(vps_id == 1 && ts == 1_000)).unwrap();
+
+        let timestamps: Vec<Vec<i64>> = batches
+            .iter()
+            .map(|b| b.snapshots.iter().map(|s| s.timestamp_unix_ms).collect())
+            .collect();
+        assert_eq!(timestamps, vec![vec![3_000], vec![2_000], vec![4_000], vec![1_000]]);
+        assert_eq!(dropped, 3);
+    }
+
+    #[test]
+    fn rows_per_sec_handles_zero_latency() {
+        assert_eq!(rows_per_sec(100, Duration::ZERO), 0.0);
+        assert_eq!(rows_per_sec(100, Duration::from_millis(500)), 200.0);
+    }
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// Client certificate issued to the agent of a VPS. Only its fingerprint is
+/// kept; the private key goes to the agent and nowhere else.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub vps_id: i32,
+    /// Lowercase hex SHA-256 of the DER certificate.
+    pub fingerprint: String,
+    pub issued_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+}
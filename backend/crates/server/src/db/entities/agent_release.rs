@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// An agent build for one version and target triple, either uploaded to the
+/// server or hosted at `url`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub version: String,
+    /// Rust target triple, e.g. `x86_64-unknown-linux-gnu`.
+    pub target: String,
+    pub file_name: String,
+    /// `None` when the file is stored by the server.
+    pub url: Option<String>,
+    pub size_bytes: i64,
+    /// Lowercase hex SHA-256 of the file.
+    pub sha256: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// An agent reporting a different version than before on handshake.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub vps_id: i32,
+    pub time: chrono::DateTime<chrono::Utc>,
+    /// `None` for the first version seen.
+    pub previous_version: Option<String>,
+    pub version: String,
+}
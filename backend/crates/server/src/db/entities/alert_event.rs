@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
+use crate::db::enums::AlertSeverity;
+
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Model {
     pub id: i32,
     pub rule_id: i32,
@@ -8,4 +11,15 @@ pub struct Model {
     pub trigger_time: chrono::DateTime<chrono::Utc>,
     pub resolve_time: Option<chrono::DateTime<chrono::Utc>>,
     pub details: Option<String>,
+    pub acknowledged_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Where the acknowledgment came from, e.g. "notification link".
+    pub acknowledged_by: Option<String>,
+    pub silenced_until: Option<chrono::DateTime<chrono::Utc>>,
+    pub remediation_batch_command_id: Option<uuid::Uuid>,
+    /// Activity on the VPS shortly before the alert fired.
+    pub recent_activity: Option<serde_json::Value>,
+    /// The service monitor a `monitor_down` alert fired for.
+    pub monitor_id: Option<i32>,
+    /// The rule's severity when the alert fired.
+    pub severity: AlertSeverity,
 }
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::db::enums::AlertSeverity;
+
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Model {
     pub id: i32,
@@ -15,4 +17,16 @@ pub struct Model {
     pub cooldown_seconds: i32,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    pub remediation_script_id: Option<i32>,
+    pub condition_expression: Option<serde_json::Value>,
+    /// Seconds between evaluations; the server default when `None`.
+    pub evaluation_interval_seconds: Option<i32>,
+    /// Service monitor rules: the monitor to watch, every monitor when `None`.
+    pub monitor_id: Option<i32>,
+    /// Service monitor rules: consecutive successful checks that resolve the
+    /// alert; the default when `None`.
+    pub recovery_threshold: Option<i32>,
+    /// Log rules: regex the tailed log lines are matched against.
+    pub log_pattern: Option<String>,
+    pub severity: AlertSeverity,
 }
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    /// Leading characters of the token, to tell tokens apart. Only a hash of
+    /// the full token is stored.
+    pub token_prefix: String,
+    /// "read" allows safe methods only; "write" allows everything.
+    pub scopes: Vec<String>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub user_id: i32,
+    pub username: String,
+    pub organization_id: Option<i32>,
+    pub method: String,
+    /// Request path, e.g. `/api/vps/12/tags`.
+    pub route: String,
+    /// Leading path segments before the first ID, e.g. `vps` or `admin/users`.
+    pub entity_type: String,
+    pub entity_id: Option<String>,
+    pub status_code: i32,
+    /// Redacted state before the change, for handlers that report it.
+    pub before_summary: Option<serde_json::Value>,
+    /// Redacted request body.
+    pub after_summary: Option<serde_json::Value>,
+    pub ip_address: Option<String>,
+    pub request_id: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -11,4 +11,11 @@ pub struct Model {
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
     pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Correlation ID of the request that created the batch; also sent to agents.
+    pub trace_id: Option<String>,
+    /// Agents kill child commands that run longer than this.
+    pub timeout_seconds: Option<i32>,
+    /// Attempts per child task, 1 without retries.
+    pub max_attempts: i32,
+    pub retry_backoff_seconds: i32,
 }
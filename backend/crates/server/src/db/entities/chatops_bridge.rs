@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    /// "telegram" or "slack".
+    pub platform: String,
+    /// Telegram chat ids or Slack channel ids commands are accepted from.
+    pub allowed_chat_ids: Vec<String>,
+    /// "read" allows queries, "execute" allows running the allowed scripts.
+    pub scopes: Vec<String>,
+    pub allowed_script_ids: Vec<i32>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
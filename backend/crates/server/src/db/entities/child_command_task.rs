@@ -16,4 +16,12 @@ pub struct Model {
     pub updated_at: chrono::DateTime<chrono::Utc>,
     pub agent_started_at: Option<chrono::DateTime<chrono::Utc>>,
     pub agent_completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// What selected the VPS: `vps`, a selector label or `selection:<name>`.
+    pub matched_selector: Option<String>,
+    /// The wave of the batch's execution strategy the task runs in, from 0.
+    pub wave: i32,
+    /// 1 for the first run, counting up with each retry.
+    pub attempt: i32,
+    /// When a `Retrying` task runs again.
+    pub next_attempt_at: Option<chrono::DateTime<chrono::Utc>>,
 }
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub organization_id: i32,
+    pub name: String,
+    pub contact_email: Option<String>,
+    pub notes: Option<String>,
+    /// Added to the provider cost when billing the client.
+    pub markup_percent: f64,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
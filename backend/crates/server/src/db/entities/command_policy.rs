@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// Allows or denies running commands. Each list narrows what the policy
+/// applies to; an empty list matches anything.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub organization_id: i32,
+    pub name: String,
+    /// "allow" or "deny".
+    pub effect: String,
+    /// Users running the command.
+    pub user_ids: Vec<i32>,
+    /// Saved scripts; a policy listing scripts doesn't apply to ad-hoc commands.
+    pub script_ids: Vec<i32>,
+    /// Target groups, including their subgroups.
+    pub group_ids: Vec<i32>,
+    /// Target tags. A VPS matches when it is in one of the groups or has one of the tags.
+    pub tag_ids: Vec<i32>,
+    pub enabled: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::services::script_template::ScriptParameter;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 
 pub enum ScriptLanguage {
@@ -16,6 +18,7 @@ pub struct Model {
     pub language: ScriptLanguage,
     pub script_content: String,
     pub working_directory: String,
+    pub parameters: Vec<ScriptParameter>,
     pub created_at: chrono::DateTime<chrono::FixedOffset>,
     pub updated_at: chrono::DateTime<chrono::FixedOffset>,
 }
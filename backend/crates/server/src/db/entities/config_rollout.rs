@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+/// A staged global agent config change.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub user_id: i32,
+    /// "running", "completed", "rolled_back" or "aborted".
+    pub status: String,
+    /// The new global agent config, as stored in settings once the rollout completes.
+    pub config: serde_json::Value,
+    pub health_window_seconds: i32,
+    /// Zero-based stage currently being applied; stage 0 holds the canaries.
+    pub current_stage: i32,
+    pub stage_count: i32,
+    pub error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub finished_at: Option<chrono::DateTime<chrono::Utc>>,
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub rollout_id: i32,
+    pub vps_id: i32,
+    pub stage: i32,
+    /// "waiting", "applied", "healthy", "failed", "skipped" or "rolled_back".
+    pub status: String,
+    pub error: Option<String>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
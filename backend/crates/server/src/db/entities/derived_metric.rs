@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    /// Arithmetic over `performance_metrics` columns, e.g. `memory_usage_bytes / memory_total_bytes * 100`.
+    pub expression: String,
+    pub unit: Option<String>,
+    pub description: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub vps_id: i32,
+    pub device: String,
+    pub model: String,
+    pub serial: Option<String>,
+    /// Overall SMART self-assessment; `None` when the drive doesn't report one.
+    pub smart_passed: Option<bool>,
+    /// Reallocated sectors (ATA) or media errors (NVMe).
+    pub reallocated_sectors: Option<i64>,
+    pub pending_sectors: Option<i64>,
+    /// Share of the rated endurance used, 0 to 100.
+    pub wear_percent: Option<f64>,
+    pub temperature_celsius: Option<f64>,
+    pub power_on_hours: Option<i64>,
+}
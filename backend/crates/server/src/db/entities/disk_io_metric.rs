@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub vps_id: i32,
+    pub device: String,
+    pub read_bps: i64,
+    pub write_bps: i64,
+    pub read_iops: f64,
+    pub write_iops: f64,
+    /// Average time per completed request, queueing included.
+    pub await_ms: f64,
+    pub utilization_percent: f64,
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub policy_id: i32,
+    pub time: chrono::DateTime<chrono::Utc>,
+    /// "failover", "revert" or "error".
+    pub kind: String,
+    pub from_ip: Option<String>,
+    pub to_ip: Option<String>,
+    pub dry_run: bool,
+    pub details: Option<String>,
+}
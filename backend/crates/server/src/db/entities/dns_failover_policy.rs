@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// Switches a DNS record between a primary and a standby IP based on the
+/// results of `monitor_ids`. Provider credentials are stored encrypted and
+/// never returned.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    /// "cloudflare" or "route53".
+    pub provider: String,
+    /// Cloudflare zone id or Route53 hosted zone id.
+    pub zone_id: String,
+    /// Fully qualified record name, e.g. `app.example.com`.
+    pub record_name: String,
+    /// "A" or "AAAA".
+    pub record_type: String,
+    pub ttl: i32,
+    pub primary_ip: String,
+    pub standby_ip: String,
+    pub monitor_ids: Vec<i32>,
+    /// Consecutive failed (or, to revert, successful) checks of every monitor
+    /// needed before switching.
+    pub confirm_checks: i32,
+    /// Log the switches without touching DNS.
+    pub dry_run: bool,
+    pub enabled: bool,
+    /// "primary" or "standby": where the record currently points.
+    pub active_target: String,
+    pub last_switched_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub vps_id: i32,
+    pub gpu_index: i32,
+    pub name: String,
+    /// "nvidia" or "amd".
+    pub vendor: String,
+    pub uuid: Option<String>,
+    pub utilization_percent: f64,
+    pub memory_used_bytes: i64,
+    pub memory_total_bytes: i64,
+    pub temperature_celsius: Option<f64>,
+    pub power_draw_watts: Option<f64>,
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    /// Leading characters of the key, to tell keys apart. Only a hash of the
+    /// full key is stored.
+    pub key_prefix: String,
+    /// Metric name prefixes the key may write; empty allows any name.
+    pub metric_prefixes: Vec<String>,
+    /// Samples accepted per minute.
+    pub rate_limit_per_minute: i32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// A tailed log line that matched a log rule's pattern.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub vps_id: i32,
+    pub alert_rule_id: i32,
+    /// Name of the `[[log_tail]]` entry in the agent config.
+    pub source: String,
+    pub line: String,
+}
@@ -1,28 +1,70 @@
+pub mod agent_client_certificate;
+pub mod agent_release;
+pub mod agent_version_event;
+pub mod api_token;
+pub mod audit_log;
 pub mod alert_event;
 pub mod alert_rule;
 pub mod alert_rule_channel;
 pub mod batch_command_task;
+pub mod chatops_bridge;
 pub mod child_command_task;
+pub mod client;
+pub mod command_policy;
 pub mod command_script;
+pub mod config_rollout;
+pub mod config_rollout_target;
+pub mod derived_metric;
+pub mod disk_health_metric;
+pub mod disk_io_metric;
+pub mod dns_failover_event;
+pub mod dns_failover_policy;
 pub mod docker_container;
 pub mod docker_metric;
+pub mod gpu_metric;
+pub mod ingest_key;
+pub mod log_match;
+pub mod network_interface_metric;
 pub mod notification_channel;
+pub mod notification_digest_item;
 pub mod oauth2_provider;
+pub mod organization;
+pub mod organization_member;
 pub mod performance_metric;
+pub mod process_metric;
+pub mod provider_account;
+pub mod provisioning_token;
+pub mod public_api_key;
+pub mod scheduled_task;
+pub mod scheduled_task_run;
 pub mod service_monitor;
 pub mod service_monitor_agent;
+pub mod service_monitor_maintenance_window;
 pub mod service_monitor_result;
+pub mod service_monitor_slo;
 pub mod service_monitor_tag;
 pub mod setting;
+pub mod share_link;
+pub mod status_page;
 pub mod tag;
 pub mod task;
 pub mod task_run;
+pub mod temperature_metric;
 pub mod theme;
 pub mod user;
+pub mod user_totp;
 pub mod vps;
+pub mod vps_group;
 pub mod vps_monthly_traffic;
+pub mod vps_power_action;
+pub mod vps_maintenance_event;
 pub mod vps_renewal_info;
+pub mod vps_selection;
+pub mod vps_status_event;
 pub mod vps_tag;
+pub mod watchdog_event;
+pub mod webhook_delivery;
+pub mod webhook_subscription;
 pub mod user_identity_provider;
 
 // Prelude module for easy importing of all entities and their related types
@@ -30,9 +72,22 @@ pub mod prelude {
     pub use super::user::Model as UserModel;
 
     pub use super::vps::Model as VpsModel;
+    pub use super::vps_group::Model as VpsGroupModel;
 
     pub use super::performance_metric::Model as PerformanceMetricModel;
 
+    pub use super::process_metric::Model as ProcessMetricModel;
+
+    pub use super::disk_io_metric::Model as DiskIoMetricModel;
+
+    pub use super::network_interface_metric::Model as NetworkInterfaceMetricModel;
+
+    pub use super::gpu_metric::Model as GpuMetricModel;
+
+    pub use super::disk_health_metric::Model as DiskHealthMetricModel;
+
+    pub use super::temperature_metric::Model as TemperatureMetricModel;
+
     pub use super::docker_container::Model as DockerContainerModel;
 
     pub use super::docker_metric::Model as DockerMetricModel;
@@ -45,10 +100,38 @@ pub mod prelude {
 
     pub use super::alert_event::Model as AlertEventModel;
 
+    pub use super::derived_metric::Model as DerivedMetricModel;
+
+    pub use super::chatops_bridge::Model as ChatOpsBridgeModel;
+
+    pub use super::dns_failover_policy::Model as DnsFailoverPolicyModel;
+
+    pub use super::dns_failover_event::Model as DnsFailoverEventModel;
+
+    pub use super::watchdog_event::Model as WatchdogEventModel;
+
+    pub use super::vps_status_event::Model as VpsStatusEventModel;
+
+    pub use super::vps_maintenance_event::Model as VpsMaintenanceEventModel;
+
+    pub use super::agent_version_event::Model as AgentVersionEventModel;
+
+    pub use super::provisioning_token::Model as ProvisioningTokenModel;
+    pub use super::public_api_key::Model as PublicApiKeyModel;
+    pub use super::api_token::Model as ApiTokenModel;
+    pub use super::audit_log::Model as AuditLogModel;
+    pub use super::provider_account::Model as ProviderAccountModel;
+
     pub use super::vps_monthly_traffic::Model as VpsMonthlyTrafficModel;
 
+    pub use super::vps_power_action::Model as VpsPowerActionModel;
+
     pub use super::setting::Model as SettingModel;
 
+    pub use super::share_link::Model as ShareLinkModel;
+
+    pub use super::status_page::Model as StatusPageModel;
+
     pub use super::tag::Model as TagModel;
 
     pub use super::vps_tag::Model as VpsTagModel;
@@ -63,8 +146,14 @@ pub mod prelude {
 
     pub use super::child_command_task::Model as ChildCommandTaskModel;
 
+    pub use super::client::Model as ClientModel;
+
     pub use super::command_script::Model as CommandScriptModel;
 
+    pub use super::config_rollout::Model as ConfigRolloutModel;
+
+    pub use super::config_rollout_target::Model as ConfigRolloutTargetModel;
+
     pub use super::service_monitor::Model as ServiceMonitorModel;
 
     pub use super::service_monitor_agent::Model as ServiceMonitorAgentModel;
@@ -73,8 +162,16 @@ pub mod prelude {
 
     pub use super::service_monitor_result::Model as ServiceMonitorResultModel;
 
+    pub use super::service_monitor_maintenance_window::Model as ServiceMonitorMaintenanceWindowModel;
+
+    pub use super::service_monitor_slo::Model as ServiceMonitorSloModel;
+
     pub use super::oauth2_provider::Model as Oauth2ProviderModel;
 
+    pub use super::organization::Model as OrganizationModel;
+
+    pub use super::organization_member::Model as OrganizationMemberModel;
+
     pub use super::user_identity_provider::Model as UserIdentityProviderModel;
 
 }
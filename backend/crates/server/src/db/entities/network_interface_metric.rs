@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub vps_id: i32,
+    pub interface_name: String,
+    pub rx_errors_per_sec: f64,
+    pub tx_errors_per_sec: f64,
+    pub rx_dropped_per_sec: f64,
+    pub tx_dropped_per_sec: f64,
+    pub collisions_per_sec: f64,
+    /// Carrier up/down transitions since the previous sample.
+    pub carrier_changes: i64,
+    pub link_up: bool,
+    pub speed_mbps: Option<i32>,
+    pub duplex: Option<String>,
+}
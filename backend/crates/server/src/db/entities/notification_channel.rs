@@ -1,4 +1,7 @@
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+use crate::db::enums::{AlertSeverity, ChannelDelivery};
 
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Model {
@@ -9,4 +12,12 @@ pub struct Model {
     pub config: Vec<u8>,      // Encrypted JSON blob
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Message templates by locale; see `notifications::templates`.
+    pub message_templates: HashMap<String, String>,
+    /// Alert severities the channel receives; all of them when empty.
+    pub severities: Vec<AlertSeverity>,
+    pub delivery: ChannelDelivery,
+    /// Cron expression of when digests go out, for digest channels.
+    pub digest_schedule: Option<String>,
+    pub next_digest_at: Option<chrono::DateTime<chrono::Utc>>,
 }
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// A notification waiting for the next digest of its channel.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Model {
+    pub id: i64,
+    pub channel_id: i32,
+    /// `notification` or `renewal`.
+    pub kind: String,
+    /// Template variables of the notification.
+    pub context: HashMap<String, String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
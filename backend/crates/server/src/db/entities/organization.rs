@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub name: String,
+    /// Set for the organization every user gets at sign-up.
+    pub personal_for_user_id: Option<i32>,
+    /// Shortest service monitor interval allowed; the server-wide minimum when `None`.
+    pub min_monitor_frequency_seconds: Option<i32>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,12 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub organization_id: i32,
+    pub user_id: i32,
+    pub username: String,
+    /// "owner" or "member".
+    pub role: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub vps_id: i32,
+    pub pid: i32,
+    pub name: String,
+    /// Share of one CPU core; may exceed 100 on multi-core hosts.
+    pub cpu_usage_percent: f64,
+    pub memory_rss_bytes: i64,
+    pub username: Option<String>,
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+/// Cloud provider credentials. The API token is stored encrypted and never returned.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    /// "hetzner", "vultr" or "digitalocean".
+    pub provider: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub user_id: i32,
+    /// Organization registered VPS are created in.
+    pub organization_id: Option<i32>,
+    pub name: String,
+    pub token: String,
+    /// Group assigned to VPS registered with this token.
+    pub group: Option<String>,
+    /// Whether registered VPS are ephemeral.
+    pub is_ephemeral: bool,
+    /// Tags attached to VPS registered with this token.
+    pub tag_ids: Vec<i32>,
+    /// Registrations allowed in total; unlimited when `None`.
+    pub max_uses: Option<i32>,
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub use_count: i32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub last_used_at: Option<chrono::DateTime<chrono::Utc>>,
+}
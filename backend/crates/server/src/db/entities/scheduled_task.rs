@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    /// Five-field cron expression, evaluated in UTC.
+    pub cron_expression: String,
+    pub script_id: i32,
+    pub target_vps_ids: Vec<i32>,
+    /// Every VPS carrying one of these tags when the schedule runs is targeted too.
+    pub target_tag_ids: Vec<i32>,
+    pub enabled: bool,
+    /// `None` while disabled.
+    pub next_run_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_run_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub scheduled_task_id: i32,
+    pub scheduled_for: chrono::DateTime<chrono::Utc>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    /// "dispatched", "skipped" or "failed".
+    pub status: String,
+    /// The batch command started by a dispatched run; its progress is tracked there.
+    pub batch_command_id: Option<uuid::Uuid>,
+    pub target_vps_count: i32,
+    /// Why a run was skipped or failed.
+    pub message: Option<String>,
+}
@@ -4,6 +4,7 @@ use serde::{Deserialize, Serialize};
 pub struct Model {
     pub id: i32,
     pub user_id: i32,
+    pub organization_id: Option<i32>,
     pub name: String,
     pub monitor_type: String,
     pub target: String,
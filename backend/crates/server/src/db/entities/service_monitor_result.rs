@@ -9,4 +9,6 @@ pub struct Model {
     pub is_up: bool,
     pub latency_ms: Option<i32>,
     pub details: Option<serde_json::Value>,
+    pub in_maintenance: bool,
+    pub packet_loss_percent: Option<f64>,
 }
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub monitor_id: i32,
+    pub name: String,
+    pub target_percent: f64,
+    pub window_days: i32,
+    /// When set, a successful check only counts as good if it also answered within this latency.
+    pub latency_threshold_ms: Option<i32>,
+    pub fast_burn_rate: f64,
+    pub slow_burn_rate: f64,
+    pub is_active: bool,
+    pub last_alerted_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
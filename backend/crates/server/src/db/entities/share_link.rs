@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub user_id: i32,
+    /// The organization whose VPS the link shares, the creator's active one.
+    pub organization_id: Option<i32>,
+    pub name: String,
+    /// The shared VPS. `None` (without `client_id`) shares every VPS of the organization.
+    pub vps_id: Option<i32>,
+    /// Shares the VPS of one reseller client, and that client's report.
+    pub client_id: Option<i32>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub last_accessed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
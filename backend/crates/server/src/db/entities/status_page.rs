@@ -0,0 +1,20 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub user_id: i32,
+    /// URL name of the page, unique across all users.
+    pub slug: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub vps_ids: Vec<i32>,
+    pub monitor_ids: Vec<i32>,
+    pub show_uptime: bool,
+    pub show_latency: bool,
+    /// Unpublished pages are only visible to their owner.
+    pub is_published: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
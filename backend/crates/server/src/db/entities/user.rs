@@ -12,4 +12,6 @@ pub struct Model {
     pub theme_mode: String,
     pub active_theme_id: Option<i32>,
     pub language: String,
+    /// Organization the user's requests are scoped to; always one they belong to.
+    pub active_organization_id: Option<i32>,
 }
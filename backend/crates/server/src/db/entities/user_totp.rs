@@ -0,0 +1,15 @@
+/// TOTP enrollment of a user. Not serialized: it holds the encrypted secret.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Model {
+    pub user_id: i32,
+    /// Encrypted with the notification encryption service.
+    pub secret: Vec<u8>,
+    /// False until the first code is confirmed.
+    pub enabled: bool,
+    /// SHA-256 hashes of the unused recovery codes.
+    pub recovery_code_hashes: Vec<String>,
+    /// Time step of the last accepted code, to refuse replays.
+    pub last_used_step: Option<i64>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub confirmed_at: Option<chrono::DateTime<chrono::Utc>>,
+}
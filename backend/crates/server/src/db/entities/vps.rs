@@ -4,6 +4,8 @@ use serde::{Deserialize, Serialize};
 pub struct Model {
     pub id: i32,
     pub user_id: i32, // Foreign key to User
+    /// Organization the VPS belongs to; access is checked against this.
+    pub organization_id: Option<i32>,
     pub name: String,
     pub ip_address: Option<String>,
     pub os_type: Option<String>,
@@ -13,7 +15,12 @@ pub struct Model {
     pub metadata: Option<serde_json::Value>,
     pub created_at: chrono::DateTime<chrono::Utc>,
     pub updated_at: chrono::DateTime<chrono::Utc>,
+    /// Display path of the group, e.g. `eu/hetzner/rack-1`; kept in sync with `group_id`.
     pub group: Option<String>,
+    /// Node in the `vps_groups` tree.
+    pub group_id: Option<i32>,
+    /// Reseller client the VPS is managed for.
+    pub client_id: Option<i32>,
     pub agent_config_override: Option<serde_json::Value>,
     pub config_status: String,
     pub last_config_update_at: Option<chrono::DateTime<chrono::Utc>>,
@@ -28,4 +35,7 @@ pub struct Model {
     pub traffic_reset_config_type: Option<String>,
     pub traffic_reset_config_value: Option<String>,
     pub next_traffic_reset_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Registered through a provisioning token; archived after staying offline.
+    pub is_ephemeral: bool,
+    pub archived_at: Option<chrono::DateTime<chrono::Utc>>,
 }
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub vps_id: i32,
+    pub time: chrono::DateTime<chrono::Utc>,
+    /// "enter" or "exit".
+    pub action: String,
+    pub reason: Option<String>,
+    /// Scheduled automatic exit, for "enter" events.
+    pub ends_at: Option<chrono::DateTime<chrono::Utc>>,
+    /// Username that made the change; "auto" for scheduled exits.
+    pub actor: Option<String>,
+}
@@ -1,9 +1,13 @@
-use serde::{Deserialize, Serialize}; // Keep Serialize/Deserialize imports
+use serde::{Deserialize, Serialize};
 
+/// Traffic of a VPS in one calendar month (UTC), rolled up from
+/// `vps_daily_traffic`.
 #[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Model {
     pub vps_id: i32,
-    pub month: chrono::NaiveDate, // Corresponds to DATE type in SQL
+    /// First day of the month.
+    pub month: chrono::NaiveDate,
     pub total_rx: i64,
     pub total_tx: i64,
 }
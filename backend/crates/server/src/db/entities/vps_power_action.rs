@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub vps_id: i32,
+    pub user_id: i32,
+    /// "reboot", "stop" or "start".
+    pub action: String,
+    /// "pending", "succeeded", "failed" or "expired".
+    pub status: String,
+    /// Whether the agent was connected when the action was requested.
+    pub agent_online: bool,
+    pub requested_at: chrono::DateTime<chrono::Utc>,
+    pub expires_at: chrono::DateTime<chrono::Utc>,
+    pub completed_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub error: Option<String>,
+}
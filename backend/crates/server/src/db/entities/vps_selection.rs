@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use crate::web::models::batch_command_models::TargetSelector;
+
+/// A named set of batch command targets, resolved whenever it is used.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Model {
+    pub id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub target_vps_ids: Vec<i32>,
+    pub selectors: Vec<TargetSelector>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
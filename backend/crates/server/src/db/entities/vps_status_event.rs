@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub vps_id: i32,
+    pub time: chrono::DateTime<chrono::Utc>,
+    /// "online" or "offline".
+    pub status: String,
+}
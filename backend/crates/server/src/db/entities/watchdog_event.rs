@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub vps_id: i32,
+    pub time: chrono::DateTime<chrono::Utc>,
+    /// Name of the watchdog entry in the agent config.
+    pub target_name: String,
+    /// "exited", "restarted", "restart_failed", "rate_limited" or "recovered".
+    pub kind: String,
+    pub details: Option<String>,
+}
@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// One event queued for one subscription, with its delivery state.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i64,
+    pub subscription_id: i32,
+    pub event_id: uuid::Uuid,
+    pub event_type: String,
+    /// The exact body that is posted.
+    pub payload: serde_json::Value,
+    /// "pending", "delivered" or "dead".
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: chrono::DateTime<chrono::Utc>,
+    pub last_attempt_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+/// An outgoing webhook. The signing secret isn't part of the model; it is only
+/// returned when the subscription is created.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Model {
+    pub id: i32,
+    pub organization_id: i32,
+    pub user_id: i32,
+    pub name: String,
+    pub url: String,
+    /// Event types delivered to the URL; empty means all of them.
+    pub event_types: Vec<String>,
+    pub is_active: bool,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub updated_at: chrono::DateTime<chrono::Utc>,
+}
@@ -13,6 +13,9 @@ pub enum BatchCommandStatus {
     Terminating,
     Terminated,
     FailedToDispatch,
+    /// Aborted by the user; waiting for the agents to kill what's running.
+    Aborting,
+    Aborted,
 }
 
 impl BatchCommandStatus {
@@ -22,6 +25,7 @@ impl BatchCommandStatus {
             BatchCommandStatus::CompletedSuccessfully
                 | BatchCommandStatus::CompletedWithErrors
                 | BatchCommandStatus::Terminated
+                | BatchCommandStatus::Aborted
         )
     }
 }
@@ -41,6 +45,12 @@ pub enum ChildCommandStatus {
     AgentUnreachable,
     TimedOut,
     AgentError,
+    /// Never started because the batch's execution strategy stopped first.
+    Skipped,
+    /// Failed and waiting for its next attempt, see `next_attempt_at`.
+    Retrying,
+    /// Killed or never started because the batch was aborted.
+    Aborted,
 }
 
 impl ChildCommandStatus {
@@ -53,6 +63,44 @@ impl ChildCommandStatus {
                 | ChildCommandStatus::AgentUnreachable
                 | ChildCommandStatus::TimedOut
                 | ChildCommandStatus::AgentError
+                | ChildCommandStatus::Skipped
+                | ChildCommandStatus::Aborted
         )
     }
+
+    /// Failures that the batch's retry policy may try again.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            self,
+            ChildCommandStatus::CompletedWithFailure
+                | ChildCommandStatus::AgentUnreachable
+                | ChildCommandStatus::TimedOut
+                | ChildCommandStatus::AgentError
+        )
+    }
+}
+
+/// How urgent an alert is. Channels can limit themselves to some severities.
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, Display, EnumString,
+    schemars::JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum AlertSeverity {
+    Info,
+    #[default]
+    Warning,
+    Critical,
+}
+
+/// Whether a channel sends notifications as they are raised or queues them
+/// for a scheduled digest.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, Display, EnumString)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum ChannelDelivery {
+    #[default]
+    Immediate,
+    Digest,
 }
@@ -2,3 +2,4 @@ pub mod entities;
 pub mod enums;
 pub mod models;
 pub mod duckdb_service;
+pub mod storage;
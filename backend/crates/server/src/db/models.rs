@@ -1,6 +1,8 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::db::enums::AlertSeverity;
+
 /// Represents a performance metric snapshot for a VPS.
 /// Corresponds to the `performance_metrics` hypertable.
 /// Note: `time` is the hypertable's time dimension.
@@ -46,6 +48,20 @@ pub struct AlertRule {
     pub cooldown_seconds: i32, // Added
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    /// Command script offered as a remediation action on notifications.
+    pub remediation_script_id: Option<i32>,
+    /// AND/OR tree of metric conditions, replacing `metric_type`/`threshold` when set.
+    pub condition_expression: Option<serde_json::Value>,
+    /// Seconds between evaluations; the server default when `None`.
+    pub evaluation_interval_seconds: Option<i32>,
+    /// Service monitor rules: the monitor to watch, every monitor when `None`.
+    pub monitor_id: Option<i32>,
+    /// Service monitor rules: consecutive successful checks that resolve the alert.
+    pub recovery_threshold: Option<i32>,
+    /// Log rules: regex the tailed log lines are matched against.
+    pub log_pattern: Option<String>,
+    /// Decides which of the rule's channels are notified.
+    pub severity: AlertSeverity,
 }
 
 /// Represents an aggregated performance metric, typically used for time-bucketed queries.
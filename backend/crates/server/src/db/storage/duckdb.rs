@@ -0,0 +1,133 @@
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use nodenexus_common::agent_service::{AgentHandshake, ServiceMonitorResult};
+
+use super::Storage;
+use crate::db::duckdb_service::alert_correlation_service::ActivityEntry;
+use crate::db::duckdb_service::performance_service::{self, PerformanceMetricPoint};
+use crate::db::duckdb_service::retention::RetentionPolicy;
+use crate::db::duckdb_service::service_monitor_service::{self, ServiceMonitorPoint};
+use crate::db::duckdb_service::{alert_event_service, alert_service, vps_service, DuckDbPool};
+use crate::db::entities::{alert_event, alert_rule, performance_metric, vps};
+use crate::web::error::AppError;
+
+/// The default backend: everything lives in the DuckDB file.
+#[derive(Clone)]
+pub struct DuckDbStorage {
+    pool: DuckDbPool,
+}
+
+impl DuckDbStorage {
+    pub fn new(pool: DuckDbPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl Storage for DuckDbStorage {
+    async fn create_vps(&self, user_id: i32, name: &str) -> Result<vps::Model, AppError> {
+        vps_service::create_vps(self.pool.clone(), user_id, name).await
+    }
+
+    async fn get_vps_by_id(&self, vps_id: i32) -> Result<Option<vps::Model>, AppError> {
+        vps_service::get_vps_by_id(self.pool.clone(), vps_id).await
+    }
+
+    async fn get_vps_by_ids(&self, vps_ids: Vec<i32>) -> Result<Vec<vps::Model>, AppError> {
+        vps_service::get_vps_by_ids(self.pool.clone(), vps_ids).await
+    }
+
+    async fn get_vps_by_user_id(&self, user_id: i32) -> Result<Vec<vps::Model>, AppError> {
+        vps_service::get_vps_by_user_id(self.pool.clone(), user_id).await
+    }
+
+    async fn get_owned_vps_from_ids(&self, user_id: i32, vps_ids: &[i32]) -> Result<Vec<vps::Model>, AppError> {
+        vps_service::get_owned_vps_from_ids(self.pool.clone(), user_id, vps_ids).await
+    }
+
+    async fn update_vps_status(&self, vps_id: i32, status: &str) -> Result<u64, AppError> {
+        vps_service::update_vps_status(self.pool.clone(), vps_id, status).await
+    }
+
+    async fn update_vps_info_on_handshake(&self, vps_id: i32, handshake: &AgentHandshake) -> Result<u64, AppError> {
+        vps_service::update_vps_info_on_handshake(self.pool.clone(), vps_id, handshake).await
+    }
+
+    async fn delete_vps(&self, vps_id: i32) -> Result<u64, AppError> {
+        vps_service::delete_vps(self.pool.clone(), vps_id).await
+    }
+
+    async fn get_performance_metrics_for_vps(
+        &self,
+        vps_id: i32,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        interval_seconds: Option<u32>,
+        retention: &RetentionPolicy,
+    ) -> Result<Vec<PerformanceMetricPoint>, AppError> {
+        Ok(performance_service::get_performance_metrics_for_vps(
+            &self.pool,
+            vps_id,
+            start_time,
+            end_time,
+            interval_seconds,
+            retention,
+        )
+        .await?)
+    }
+
+    async fn get_latest_performance_metric_for_vps(
+        &self,
+        vps_id: i32,
+    ) -> Result<Option<performance_metric::Model>, AppError> {
+        Ok(performance_service::get_latest_performance_metric_for_vps(&self.pool, vps_id).await?)
+    }
+
+    async fn record_monitor_result(&self, vps_id: i32, result: &ServiceMonitorResult) -> Result<Option<bool>, AppError> {
+        service_monitor_service::record_monitor_result(self.pool.clone(), vps_id, result).await
+    }
+
+    async fn get_monitor_results_by_id(
+        &self,
+        monitor_id: i32,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        interval_seconds: Option<i64>,
+    ) -> Result<Vec<ServiceMonitorPoint>, AppError> {
+        service_monitor_service::get_monitor_results_by_id(self.pool.clone(), monitor_id, start_time, end_time, interval_seconds)
+            .await
+    }
+
+    async fn get_monitor_results_by_vps_id(
+        &self,
+        vps_id: i32,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        interval_seconds: Option<i64>,
+    ) -> Result<Vec<ServiceMonitorPoint>, AppError> {
+        service_monitor_service::get_monitor_results_by_vps_id(self.pool.clone(), vps_id, start_time, end_time, interval_seconds)
+            .await
+    }
+
+    async fn get_all_active_rules_for_evaluation(&self) -> Result<Vec<alert_rule::Model>, AppError> {
+        alert_service::get_all_active_rules_for_evaluation(self.pool.clone()).await
+    }
+
+    async fn update_alert_rule_last_triggered(&self, rule_id: i32, user_id: i32) -> Result<(), AppError> {
+        alert_service::update_alert_rule_last_triggered(self.pool.clone(), rule_id, user_id).await
+    }
+
+    async fn create_alert_event(
+        &self,
+        rule_id: i32,
+        vps_id: i32,
+        details: &str,
+        recent_activity: &[ActivityEntry],
+    ) -> Result<alert_event::Model, AppError> {
+        alert_event_service::create_alert_event(self.pool.clone(), rule_id, vps_id, details, recent_activity).await
+    }
+
+    async fn is_silenced(&self, rule_id: i32, vps_id: i32) -> Result<bool, AppError> {
+        alert_event_service::is_silenced(self.pool.clone(), rule_id, vps_id).await
+    }
+}
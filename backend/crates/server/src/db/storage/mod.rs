@@ -0,0 +1,106 @@
+//! Storage abstraction over the VPS, metric, monitor and alert queries that
+//! the agent stream, the alert evaluator and the main API routes make.
+//!
+//! `DuckDbStorage`, which wraps the `duckdb_service` functions, is the only
+//! backend.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use nodenexus_common::agent_service::{AgentHandshake, ServiceMonitorResult};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::alert_correlation_service::ActivityEntry;
+use crate::db::duckdb_service::performance_service::PerformanceMetricPoint;
+use crate::db::duckdb_service::retention::RetentionPolicy;
+use crate::db::duckdb_service::service_monitor_service::ServiceMonitorPoint;
+use crate::db::entities::{alert_event, alert_rule, performance_metric, vps};
+use crate::web::error::AppError;
+
+pub mod duckdb;
+
+pub use self::duckdb::DuckDbStorage;
+
+pub type SharedStorage = Arc<dyn Storage>;
+
+#[async_trait]
+pub trait Storage: Send + Sync {
+    // --- VPS ---
+
+    async fn create_vps(&self, user_id: i32, name: &str) -> Result<vps::Model, AppError>;
+
+    async fn get_vps_by_id(&self, vps_id: i32) -> Result<Option<vps::Model>, AppError>;
+
+    async fn get_vps_by_ids(&self, vps_ids: Vec<i32>) -> Result<Vec<vps::Model>, AppError>;
+
+    /// All VPS in the user's active organization.
+    async fn get_vps_by_user_id(&self, user_id: i32) -> Result<Vec<vps::Model>, AppError>;
+
+    /// Those of `vps_ids` that are in the user's active organization.
+    async fn get_owned_vps_from_ids(&self, user_id: i32, vps_ids: &[i32]) -> Result<Vec<vps::Model>, AppError>;
+
+    async fn update_vps_status(&self, vps_id: i32, status: &str) -> Result<u64, AppError>;
+
+    async fn update_vps_info_on_handshake(&self, vps_id: i32, handshake: &AgentHandshake) -> Result<u64, AppError>;
+
+    async fn delete_vps(&self, vps_id: i32) -> Result<u64, AppError>;
+
+    // --- Metrics ---
+
+    /// Metric points in a time range, oldest first, averaged over
+    /// `interval_seconds` buckets when given.
+    async fn get_performance_metrics_for_vps(
+        &self,
+        vps_id: i32,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        interval_seconds: Option<u32>,
+        retention: &RetentionPolicy,
+    ) -> Result<Vec<PerformanceMetricPoint>, AppError>;
+
+    async fn get_latest_performance_metric_for_vps(
+        &self,
+        vps_id: i32,
+    ) -> Result<Option<performance_metric::Model>, AppError>;
+
+    // --- Monitors ---
+
+    /// Stores a check result. Returns whether it fell inside a maintenance
+    /// window, or `None` for a replayed result that is already stored.
+    async fn record_monitor_result(&self, vps_id: i32, result: &ServiceMonitorResult) -> Result<Option<bool>, AppError>;
+
+    /// Results in a time range, newest first, aggregated over
+    /// `interval_seconds` buckets when given.
+    async fn get_monitor_results_by_id(
+        &self,
+        monitor_id: i32,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        interval_seconds: Option<i64>,
+    ) -> Result<Vec<ServiceMonitorPoint>, AppError>;
+
+    /// Results of the monitors the VPS currently runs.
+    async fn get_monitor_results_by_vps_id(
+        &self,
+        vps_id: i32,
+        start_time: DateTime<Utc>,
+        end_time: DateTime<Utc>,
+        interval_seconds: Option<i64>,
+    ) -> Result<Vec<ServiceMonitorPoint>, AppError>;
+
+    // --- Alerts ---
+
+    async fn get_all_active_rules_for_evaluation(&self) -> Result<Vec<alert_rule::Model>, AppError>;
+
+    async fn update_alert_rule_last_triggered(&self, rule_id: i32, user_id: i32) -> Result<(), AppError>;
+
+    async fn create_alert_event(
+        &self,
+        rule_id: i32,
+        vps_id: i32,
+        details: &str,
+        recent_activity: &[ActivityEntry],
+    ) -> Result<alert_event::Model, AppError>;
+
+    /// Whether a notification action silenced this rule for this VPS.
+    async fn is_silenced(&self, rule_id: i32, vps_id: i32) -> Result<bool, AppError>;
+}
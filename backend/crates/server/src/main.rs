@@ -17,21 +17,25 @@ i18n!("locales", fallback = "en");
 
 use nodenexus_common::agent_service::agent_communication_service_server::AgentCommunicationServiceServer;
 use crate::alerting::evaluation_service::EvaluationService; // Added EvaluationService
+use crate::alerting::log_matcher::LogMatcher;
 use crate::db::{duckdb_service};
-use crate::db::duckdb_service::{tasks::DuckDBTaskManager, DuckDBService};
+use crate::db::duckdb_service::{recovery::StorageMode, tasks::DuckDBTaskManager, writer::WriterConfig, DuckDBService};
+use crate::db::storage::{DuckDbStorage, SharedStorage};
 // use crate::db::services::{AlertService, BatchCommandManager}; // Added BatchCommandManager
 use crate::notifications::encryption::EncryptionService;
 use crate::server::agent_state::{ConnectedAgents, LiveServerDataCache}; // Added LiveServerDataCache
-use crate::server::config::ServerConfig;
+use crate::server::config::{BindAddress, ServerConfig};
 use crate::server::metric_broadcaster::MetricBroadcaster;
 use crate::server::result_broadcaster::{BatchCommandUpdateMsg, ResultBroadcaster}; // Added ResultBroadcaster
 use crate::server::service::MyAgentCommService;
 use crate::server::self_update_service::SelfUpdateService;
+use crate::server::command_dispatcher::{CommandDispatcher, PendingCommandResponses};
+use crate::server::terminal_sessions::TerminalSessions;
 use crate::server::update_service; // Added for cache population
 use crate::version::VERSION;
 use crate::web::models::websocket_models::{ServerWithDetails, WsMessage};
  
-use chrono::Utc;
+use chrono::{TimeZone, Utc};
 use clap::Parser;
 use futures_util::SinkExt;
 use std::collections::HashMap;
@@ -52,6 +56,21 @@ struct Args {
     /// Path to the configuration file
     #[arg(short, long)]
     config: Option<String>,
+    /// Generate a new encryption key for stored secrets at startup. Existing
+    /// data is re-encrypted in the background.
+    #[arg(long)]
+    rotate_encryption_key: bool,
+    /// With --rotate-encryption-key, rotate only the keys of this organization.
+    #[arg(long, requires = "rotate_encryption_key")]
+    encryption_key_organization: Option<i32>,
+    /// If the database fails its startup check, replace it with the newest
+    /// startup backup. The unreadable file is kept next to it.
+    #[arg(long)]
+    restore_latest_backup: bool,
+    /// Replace the database, agent CA and branding assets with a backup
+    /// archive before starting. What is replaced is kept next to it.
+    #[arg(long, value_name = "ARCHIVE")]
+    restore: Option<String>,
 }
 
 fn init_logging(log_dir: &str) {
@@ -140,26 +159,112 @@ async fn run_server(mut shutdown_rx: watch::Receiver<()>) -> Result<(), Box<dyn
    // --- DuckDB Setup ---
    let db_path = std::path::Path::new(&server_config.data_dir).join("nodenexus.db");
    let duckdb_path = db_path.to_str().ok_or("Invalid DB path")?;
-   let duckdb_manager = duckdb::DuckdbConnectionManager::file(duckdb_path).map_err(|e| e.to_string())?;
-   let duckdb_pool = r2d2::Pool::new(duckdb_manager).expect("Failed to create DuckDB connection pool.");
-   let duckdb_service = match DuckDBService::new(duckdb_pool.clone()) {
-       Ok(service) => {
-           info!("Successfully initialized DuckDB service.");
-           service
-       }
-       Err(e) => {
-           error!("Failed to create DuckDB service: {}", e);
-           return Err(e.into());
+   let backup_dir = std::path::Path::new(&server_config.data_dir).join("backups");
+   let key_fingerprint = duckdb_service::backup_service::key_fingerprint(&server_config.notification_encryption_key);
+   if let Some(archive) = &args.restore {
+       duckdb_service::backup_service::restore_backup(
+           std::path::Path::new(archive),
+           &db_path,
+           std::path::Path::new(&server_config.data_dir),
+           &key_fingerprint,
+       )
+       .map_err(|e| format!("Failed to restore {archive}: {e}"))?;
+   }
+   let storage = duckdb_service::recovery::open_storage(&db_path, &backup_dir, args.restore_latest_backup)?;
+   let storage_mode = storage.mode;
+   let duckdb_pool = storage.pool;
+   let duckdb_service = if let StorageMode::Degraded { reason, source } = &storage_mode {
+       error!("Storage is degraded ({}). Serving {}; API writes are refused and metrics are not stored.", reason, source);
+       DuckDBService::discarding()
+   } else {
+       let writer_config = WriterConfig {
+           batch_size: server_config.metrics_batch_size,
+           flush_interval: Duration::from_secs(server_config.metrics_flush_interval_seconds),
+       };
+       match DuckDBService::new(duckdb_pool.clone(), writer_config) {
+           Ok(service) => {
+               info!("Successfully initialized DuckDB service.");
+               service
+           }
+           Err(e) => {
+               error!("Failed to create DuckDB service: {}", e);
+               return Err(e.into());
+           }
        }
    };
    let duckdb_metric_sender = duckdb_service.get_sender();
 
+   // Archive reads go through the main pool, so set up remote archive access once here.
+   match duckdb_pool.get() {
+       Ok(conn) => {
+           if let Err(e) = duckdb_service::archive_service::prepare_archive_location(&conn, &server_config.archive_dir) {
+               warn!("Failed to prepare the archive location {}: {}", server_config.archive_dir, e);
+           }
+       }
+       Err(e) => warn!("Failed to get a connection to prepare the archive location: {}", e),
+   }
+
+   // Report and export queries use a read-only replica when one is configured.
+   let reporting_pool = match &server_config.reporting_database_path {
+       Some(path) => {
+           let pool = crate::db::duckdb_service::open_reporting_pool(path, server_config.reporting_pool_size).map_err(|e| {
+               error!("Failed to open reporting database at {}: {}", path, e);
+               e.to_string()
+           })?;
+           info!("Reporting queries will use the read-only database at {}.", path);
+           pool
+       }
+       None => duckdb_pool.clone(),
+   };
+
+   let storage: SharedStorage = Arc::new(DuckDbStorage::new(duckdb_pool.clone()));
+
+   let read_only = crate::services::read_only::ReadOnlyMode::load(duckdb_pool.clone()).await?;
+   if let Some(state) = read_only.current() {
+       warn!("Starting in read-only mode (enabled by {}): {}", state.enabled_by, state.message);
+   }
+
+   // --- Agent CA and TLS ---
+   let agent_ca = Arc::new(crate::services::agent_ca::AgentCa::load_or_create(&server_config.data_dir)?);
+   let tls_config = match (&server_config.tls_cert_path, &server_config.tls_key_path) {
+       (Some(cert_path), Some(key_path)) => Some(crate::server::tls::server_config(cert_path, key_path, &agent_ca)?),
+       _ => None,
+   };
+
+   // Rollouts don't survive a restart; their agents fall back to the unchanged global config.
+   if storage_mode.is_healthy() {
+       match crate::db::duckdb_service::config_rollout_service::abort_unfinished_rollouts(duckdb_pool.clone()).await {
+           Ok(0) => {}
+           Ok(count) => warn!("Aborted {} config rollout(s) interrupted by the restart.", count),
+           Err(e) => error!("Failed to abort interrupted config rollouts: {}", e),
+       }
+   }
+
    // --- DuckDB Background Tasks ---
-   let duckdb_task_manager = Arc::new(DuckDBTaskManager::new(duckdb_path, duckdb_pool.clone()));
+   let duckdb_task_manager = Arc::new(DuckDBTaskManager::new(
+       duckdb_path,
+       duckdb_pool.clone(),
+       &server_config.archive_dir,
+       server_config.event_retention_days,
+       server_config.batch_output_retention_days,
+       server_config.retention_policy(),
+       duckdb_service::backup_service::BackupSchedule {
+           data_dir: std::path::PathBuf::from(&server_config.data_dir),
+           interval_hours: server_config.backup_interval_hours,
+           keep: server_config.backups_kept,
+           key_fingerprint,
+       },
+   ));
    let duckdb_task_handle = tokio::spawn({
        let manager = duckdb_task_manager.clone();
        let mut shutdown_rx = shutdown_rx.clone();
+       // Archiving and retention only make sense against the real database.
+       let run_tasks = storage_mode.is_healthy();
        async move {
+            if !run_tasks {
+                let _ = shutdown_rx.changed().await;
+                return;
+            }
             tokio::select! {
                 _ = manager.run_periodic_tasks(Duration::from_secs(3600)) => {},
                 _ = shutdown_rx.changed() => {
@@ -170,7 +275,11 @@ async fn run_server(mut shutdown_rx: watch::Receiver<()>) -> Result<(), Box<dyn
    });
 
    // --- gRPC Server Setup ---
-    let addr: SocketAddr = "0.0.0.0:8080".parse()?;
+    let addr: SocketAddr = match BindAddress::parse(&server_config.bind_address)? {
+        BindAddress::Tcp(addr) => addr,
+        BindAddress::Unix(_) => return Err("bind_address must be host:port; only the admin API can use a unix socket.".into()),
+    };
+    let admin_addr = server_config.admin_bind_address.as_deref().map(BindAddress::parse).transpose()?;
     let connected_agents = ConnectedAgents::new();
 
     // --- Shared State Initialization for WebSocket and gRPC ---
@@ -203,12 +312,39 @@ async fn run_server(mut shutdown_rx: watch::Receiver<()>) -> Result<(), Box<dyn
     let key_bytes = hex::decode(&server_config.notification_encryption_key).expect("Failed to decode encryption key.");
     let encryption_service =
         Arc::new(EncryptionService::new(&key_bytes).expect("Failed to create encryption service."));
+    match duckdb_service::encryption_key_service::load_keys(duckdb_pool.clone(), &encryption_service).await {
+        Ok(count) => info!(count, current_version = encryption_service.current_version(), "Loaded encryption keys."),
+        Err(e) => {
+            error!(error = %e, "Failed to load encryption keys.");
+            return Err(e.to_string().into());
+        }
+    }
+    if args.rotate_encryption_key && !storage_mode.is_healthy() {
+        warn!("Ignoring --rotate-encryption-key while storage is degraded.");
+    } else if args.rotate_encryption_key {
+        let organization_id = args.encryption_key_organization;
+        let version =
+            duckdb_service::encryption_key_service::rotate_key(duckdb_pool.clone(), &encryption_service, organization_id)
+                .await
+                .map_err(|e| e.to_string())?;
+        info!(version, ?organization_id, "Encryption key rotated from the command line.");
+    }
     let result_broadcaster = Arc::new(ResultBroadcaster::new(batch_command_updates_tx.clone()));
+    let terminal_sessions = Arc::new(TerminalSessions::new());
+    let pending_command_responses = Arc::new(PendingCommandResponses::new());
+    let log_matcher = Arc::new(LogMatcher::default());
+    let command_dispatcher = Arc::new(CommandDispatcher::new(
+        connected_agents.clone(),
+        duckdb_pool.clone(),
+        result_broadcaster.clone(),
+        pending_command_responses.clone(),
+    ));
 
     // --- gRPC Server Setup (continued) ---
     let agent_comm_service = MyAgentCommService::new(
         connected_agents.clone(),
         duckdb_pool.clone(),
+        storage.clone(),
         live_server_data_cache.clone(),
         ws_data_broadcaster_tx.clone(),
         update_trigger_tx.clone(),
@@ -216,6 +352,10 @@ async fn run_server(mut shutdown_rx: watch::Receiver<()>) -> Result<(), Box<dyn
         duckdb_metric_sender.clone(),
         shutdown_rx.clone(),
         result_broadcaster.clone(),
+        terminal_sessions.clone(),
+        pending_command_responses.clone(),
+        log_matcher.clone(),
+        server_config.agent_cert_replaces_secret,
     );
 
     let grpc_service = AgentCommunicationServiceServer::new(agent_comm_service);
@@ -224,6 +364,7 @@ async fn run_server(mut shutdown_rx: watch::Receiver<()>) -> Result<(), Box<dyn
     let connected_agents_for_check = connected_agents.clone();
     let trigger_for_check = update_trigger_tx.clone();
     let duckdb_pool1 = duckdb_pool.clone();
+    let storage_for_check = storage.clone();
     let mut liveness_shutdown_rx = shutdown_rx.clone();
     tokio::spawn(async move {
         let mut interval = interval(Duration::from_secs(60));
@@ -247,7 +388,7 @@ async fn run_server(mut shutdown_rx: watch::Receiver<()>) -> Result<(), Box<dyn
                     for agent_id in timed_out_agent_ids {
                         if let Some(mut state) = agents_guard.agents.remove(&agent_id) {
                             warn!(vps_id = state.vps_db_id, "Agent timed out. Closing connection gracefully.");
-                            disconnected_vps_ids.push(state.vps_db_id);
+                            disconnected_vps_ids.push((state.vps_db_id, state.last_seen_ms));
                             tokio::spawn(async move {
                                 if let Err(e) = state.sender.close().await {
                                     warn!(vps_id = %state.vps_db_id, error = %e, "Error closing agent sender gracefully.");
@@ -260,8 +401,13 @@ async fn run_server(mut shutdown_rx: watch::Receiver<()>) -> Result<(), Box<dyn
                     if !disconnected_vps_ids.is_empty() {
                         warn!(count = disconnected_vps_ids.len(), "Found disconnected agents. Updating status to 'offline'.");
                         let mut needs_broadcast = false;
-                        for vps_id in disconnected_vps_ids {
-                            match duckdb_service::vps_service::update_vps_status(duckdb_pool1.clone(), vps_id, "offline").await {
+                        for (vps_id, last_seen_ms) in disconnected_vps_ids {
+                            // The agent went away when it was last heard from, not when the timeout fired.
+                            let went_offline_at = Utc.timestamp_millis_opt(last_seen_ms).single().unwrap_or_else(Utc::now);
+                            if let Err(e) = duckdb_service::uptime_service::record_status_event(duckdb_pool1.clone(), vps_id, duckdb_service::uptime_service::STATUS_OFFLINE, went_offline_at).await {
+                                error!(vps_id = vps_id, error = %e, "Failed to record offline status event.");
+                            }
+                            match storage_for_check.update_vps_status(vps_id, "offline").await {
                                 Ok(rows_affected) if rows_affected > 0 => needs_broadcast = true,
                                 Ok(_) => {}
                                 Err(e) => error!(vps_id = vps_id, error = %e, "Failed to update status to 'offline'."),
@@ -287,6 +433,8 @@ async fn run_server(mut shutdown_rx: watch::Receiver<()>) -> Result<(), Box<dyn
     let http_router = crate::web::create_axum_router(
         live_server_data_cache.clone(),
         duckdb_pool.clone(),
+        reporting_pool,
+        storage.clone(),
         ws_data_broadcaster_tx.clone(),
         public_ws_data_broadcaster_tx.clone(),
         connected_agents.clone(),
@@ -298,6 +446,13 @@ async fn run_server(mut shutdown_rx: watch::Receiver<()>) -> Result<(), Box<dyn
         metric_sender.clone(),
         duckdb_metric_sender.clone(),
         shutdown_rx.clone(),
+        terminal_sessions.clone(),
+        pending_command_responses.clone(),
+        log_matcher.clone(),
+        command_dispatcher.clone(),
+        storage_mode.clone(),
+        read_only.clone(),
+        agent_ca.clone(),
     );
 
     // --- Debounced Broadcast Task ---
@@ -334,12 +489,15 @@ async fn run_server(mut shutdown_rx: watch::Receiver<()>) -> Result<(), Box<dyn
     // --- Alert Evaluation Service Task ---
     let alert_evaluation_service = Arc::new(EvaluationService::new(
         duckdb_pool.clone(),
+        storage.clone(),
         encryption_service.clone(),
+        server_config.clone(),
+        ws_data_broadcaster_tx.clone(),
     ));
     let mut evaluation_shutdown_rx = shutdown_rx.clone();
     let evaluation_task = tokio::spawn(async move {
         tokio::select! {
-            _ = alert_evaluation_service.start_periodic_evaluation(60) => {},
+            _ = alert_evaluation_service.start_scheduled_evaluation() => {},
             _ = evaluation_shutdown_rx.changed() => {
                 info!("Alert evaluation service shutting down.");
             }
@@ -378,6 +536,365 @@ async fn run_server(mut shutdown_rx: watch::Receiver<()>) -> Result<(), Box<dyn
         }
     });
 
+    // --- GeoIP Enrichment Task ---
+    // ip-api.com's free tier allows 45 requests a minute.
+    const GEOIP_INTERVAL_SECONDS: u64 = 60;
+    const GEOIP_LOOKUPS_PER_RUN: usize = 30;
+    if let Some(geoip_resolver) = services::geoip::GeoIpResolver::from_config(&server_config)? {
+        let geoip_provider = server_config.geoip_provider;
+        let geoip_pool = duckdb_pool.clone();
+        let trigger_for_geoip = update_trigger_tx.clone();
+        let mut geoip_shutdown_rx = shutdown_rx.clone();
+        tokio::spawn(async move {
+            let mut interval = interval(Duration::from_secs(GEOIP_INTERVAL_SECONDS));
+            info!(provider = ?geoip_provider, "GeoIP enrichment task started.");
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        match services::geoip::enrich_pending(geoip_pool.clone(), &geoip_resolver, GEOIP_LOOKUPS_PER_RUN).await {
+                            Ok(0) => {}
+                            Ok(count) => {
+                                info!(count, "Stored VPS locations from GeoIP lookups.");
+                                if trigger_for_geoip.send(()).await.is_err() {
+                                    error!("Failed to send update trigger from GeoIP task.");
+                                }
+                            }
+                            Err(e) => error!(error = %e, "Error resolving VPS locations."),
+                        }
+                    },
+                    _ = geoip_shutdown_rx.changed() => {
+                        info!("GeoIP enrichment task shutting down.");
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    // --- Vulnerability Scan Task ---
+    if server_config.vulnerability_scan_interval_hours > 0 {
+        let scan_interval_seconds = u64::from(server_config.vulnerability_scan_interval_hours) * 60 * 60;
+        let scan_pool = duckdb_pool.clone();
+        let scan_encryption_service = encryption_service.clone();
+        let osv_api_url = server_config.osv_api_url.clone();
+        let mut scan_shutdown_rx = shutdown_rx.clone();
+        tokio::spawn(async move {
+            let mut interval = interval(Duration::from_secs(scan_interval_seconds));
+            info!(interval_seconds = scan_interval_seconds, "Vulnerability scan task started.");
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        if let Err(e) = services::vulnerability_scanner::run_vulnerability_scan(
+                            scan_pool.clone(),
+                            scan_encryption_service.clone(),
+                            &osv_api_url,
+                        ).await {
+                            error!(error = %e, "Vulnerability scan failed.");
+                        }
+                    },
+                    _ = scan_shutdown_rx.changed() => {
+                        info!("Vulnerability scan task shutting down.");
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    // --- Scheduled Maintenance Exit Task ---
+    let trigger_for_maintenance = update_trigger_tx.clone();
+    let pool_for_maintenance = duckdb_pool.clone();
+    const MAINTENANCE_EXIT_CHECK_INTERVAL_SECONDS: u64 = 60;
+    let mut maintenance_shutdown_rx = shutdown_rx.clone();
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(MAINTENANCE_EXIT_CHECK_INTERVAL_SECONDS));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    match duckdb_service::maintenance_service::process_scheduled_exits(pool_for_maintenance.clone()).await {
+                        Ok(vps_ids) if !vps_ids.is_empty() => {
+                            info!(?vps_ids, "Scheduled maintenance ended. Triggering state update.");
+                            if trigger_for_maintenance.send(()).await.is_err() {
+                                error!("Failed to send update trigger from maintenance exit task.");
+                            }
+                        },
+                        Ok(_) => {},
+                        Err(e) => error!(error = %e, "Error processing scheduled maintenance exits."),
+                    }
+                },
+                _ = maintenance_shutdown_rx.changed() => {
+                    info!("Maintenance exit task shutting down.");
+                    break;
+                }
+            }
+        }
+    });
+
+    // --- Ephemeral VPS Archival Task ---
+    if server_config.ephemeral_archive_after_hours > 0 {
+        let trigger_for_ephemeral = update_trigger_tx.clone();
+        let pool_for_ephemeral = duckdb_pool.clone();
+        let archive_after = chrono::Duration::hours(i64::from(server_config.ephemeral_archive_after_hours));
+        const EPHEMERAL_ARCHIVE_CHECK_INTERVAL_SECONDS: u64 = 10 * 60;
+        let mut ephemeral_shutdown_rx = shutdown_rx.clone();
+        tokio::spawn(async move {
+            let mut interval = interval(Duration::from_secs(EPHEMERAL_ARCHIVE_CHECK_INTERVAL_SECONDS));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        match duckdb_service::provisioning_service::archive_offline_ephemeral_vps(pool_for_ephemeral.clone(), Utc::now() - archive_after).await {
+                            Ok(vps_ids) if !vps_ids.is_empty() => {
+                                info!(?vps_ids, "Archived offline ephemeral VPS. Triggering state update.");
+                                if trigger_for_ephemeral.send(()).await.is_err() {
+                                    error!("Failed to send update trigger from ephemeral archival task.");
+                                }
+                            },
+                            Ok(_) => {},
+                            Err(e) => error!(error = %e, "Error archiving offline ephemeral VPS."),
+                        }
+                    },
+                    _ = ephemeral_shutdown_rx.changed() => {
+                        info!("Ephemeral archival task shutting down.");
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    // --- Scheduled Uptime Report Task ---
+    const UPTIME_REPORT_CHECK_INTERVAL_SECONDS: u64 = 60 * 60;
+    let report_pool = duckdb_pool.clone();
+    let report_encryption_service = encryption_service.clone();
+    let mut report_shutdown_rx = shutdown_rx.clone();
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(UPTIME_REPORT_CHECK_INTERVAL_SECONDS));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    match duckdb_service::uptime_report_service::run_scheduled_reports(
+                        report_pool.clone(),
+                        report_encryption_service.clone(),
+                        chrono::Utc::now(),
+                    ).await {
+                        Ok(0) => {}
+                        Ok(count) => info!(count, "Generated scheduled uptime reports."),
+                        Err(e) => error!(error = %e, "Error generating scheduled uptime reports."),
+                    }
+                },
+                _ = report_shutdown_rx.changed() => {
+                    info!("Uptime report task shutting down.");
+                    break;
+                }
+            }
+        }
+    });
+
+    // --- Notification Digest Task ---
+    const DIGEST_CHECK_INTERVAL_SECONDS: u64 = 60;
+    let digest_pool = duckdb_pool.clone();
+    let digest_encryption_service = encryption_service.clone();
+    let mut digest_shutdown_rx = shutdown_rx.clone();
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(DIGEST_CHECK_INTERVAL_SECONDS));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    match crate::notifications::digest::send_due_digests(
+                        digest_pool.clone(),
+                        digest_encryption_service.clone(),
+                        chrono::Utc::now(),
+                    ).await {
+                        Ok(0) => {}
+                        Ok(count) => debug!(count, "Processed due notification digests."),
+                        Err(e) => error!(error = %e, "Error sending notification digests."),
+                    }
+                },
+                _ = digest_shutdown_rx.changed() => {
+                    info!("Notification digest task shutting down.");
+                    break;
+                }
+            }
+        }
+    });
+
+    // --- Scheduled Command Task ---
+    const COMMAND_SCHEDULE_CHECK_INTERVAL_SECONDS: u64 = 30;
+    let schedule_pool = duckdb_pool.clone();
+    let schedule_dispatcher = command_dispatcher.clone();
+    let schedule_read_only = read_only.clone();
+    let mut schedule_shutdown_rx = shutdown_rx.clone();
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(COMMAND_SCHEDULE_CHECK_INTERVAL_SECONDS));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    match crate::services::command_scheduler::run_due_schedules(
+                        schedule_pool.clone(),
+                        &schedule_dispatcher,
+                        &schedule_read_only,
+                        chrono::Utc::now(),
+                    ).await {
+                        Ok(0) => {}
+                        Ok(count) => info!(count, "Ran scheduled commands."),
+                        Err(e) => error!(error = %e, "Error running scheduled commands."),
+                    }
+                },
+                _ = schedule_shutdown_rx.changed() => {
+                    info!("Scheduled command task shutting down.");
+                    break;
+                }
+            }
+        }
+    });
+
+    // --- Demo Mode Task ---
+    if server_config.demo_mode {
+        const DEMO_INTERVAL_SECONDS: u64 = 5;
+        warn!(
+            count = server_config.demo_agent_count,
+            volatility = server_config.demo_volatility,
+            "Demo mode is enabled: generating synthetic servers and metrics."
+        );
+        let demo_pool = duckdb_pool.clone();
+        let demo_metric_sender = metric_sender.clone();
+        let demo_duckdb_metric_sender = duckdb_metric_sender.clone();
+        let demo_trigger = update_trigger_tx.clone();
+        let demo_count = server_config.demo_agent_count;
+        let demo_volatility = server_config.demo_volatility;
+        let mut demo_shutdown_rx = shutdown_rx.clone();
+        tokio::spawn(async move {
+            let mut rng: rand::rngs::StdRng = rand::SeedableRng::from_os_rng();
+            let mut servers: Vec<services::demo_metrics::DemoServer> = Vec::new();
+            let mut interval = interval(Duration::from_secs(DEMO_INTERVAL_SECONDS));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {
+                        if servers.len() < demo_count as usize {
+                            match duckdb_service::demo_service::ensure_demo_servers(demo_pool.clone(), demo_count).await {
+                                Ok(ids) => {
+                                    servers.retain(|server| ids.contains(&server.vps_id));
+                                    for id in ids {
+                                        if !servers.iter().any(|server| server.vps_id == id) {
+                                            servers.push(services::demo_metrics::DemoServer::new(id, &mut rng));
+                                        }
+                                    }
+                                }
+                                Err(e) => error!(error = %e, "Failed to create demo servers."),
+                            }
+                        }
+                        if servers.is_empty() {
+                            continue;
+                        }
+                        let now_ms = Utc::now().timestamp_millis();
+                        for server in &mut servers {
+                            let snapshot = server.next_snapshot(&mut rng, now_ms, DEMO_INTERVAL_SECONDS, demo_volatility);
+                            let metric = db::entities::performance_metric::Model::from_snapshot(server.vps_id, &snapshot);
+                            if demo_metric_sender.send(metric).await.is_err() {
+                                error!("Failed to send demo metric to broadcaster channel.");
+                            }
+                            let write = duckdb_service::writer::SnapshotBatch { vps_id: server.vps_id, snapshots: vec![snapshot], replayed: false };
+                            if demo_duckdb_metric_sender.send(write).is_err() {
+                                error!("Failed to send demo metrics to DuckDB writer channel.");
+                            }
+                        }
+                        if demo_trigger.send(()).await.is_err() {
+                            error!("Failed to send update trigger after demo metrics.");
+                        }
+                    },
+                    _ = demo_shutdown_rx.changed() => {
+                        info!("Demo mode task shutting down.");
+                        break;
+                    }
+                }
+            }
+        });
+    }
+
+    // --- Encryption Key Re-encryption Task ---
+    const REENCRYPT_INTERVAL_SECONDS: u64 = 5 * 60;
+    const REENCRYPT_BATCH_SIZE: usize = 200;
+    let reencrypt_pool = duckdb_pool.clone();
+    let reencrypt_encryption_service = encryption_service.clone();
+    let mut reencrypt_shutdown_rx = shutdown_rx.clone();
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(REENCRYPT_INTERVAL_SECONDS));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    match duckdb_service::encryption_key_service::reencrypt_stale_rows(
+                        reencrypt_pool.clone(),
+                        reencrypt_encryption_service.clone(),
+                        REENCRYPT_BATCH_SIZE,
+                    ).await {
+                        Ok(0) => {}
+                        Ok(count) => info!(count, "Re-encrypted secrets with the current key."),
+                        Err(e) => error!(error = %e, "Error re-encrypting secrets."),
+                    }
+                },
+                _ = reencrypt_shutdown_rx.changed() => {
+                    info!("Re-encryption task shutting down.");
+                    break;
+                }
+            }
+        }
+    });
+
+    // --- Webhook Delivery Task ---
+    const WEBHOOK_DISPATCH_INTERVAL_SECONDS: u64 = 5;
+    let webhook_http = services::webhook_dispatcher::http_client()?;
+    let webhook_pool = duckdb_pool.clone();
+    let webhook_encryption_service = encryption_service.clone();
+    let mut webhook_shutdown_rx = shutdown_rx.clone();
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(WEBHOOK_DISPATCH_INTERVAL_SECONDS));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = services::webhook_dispatcher::dispatch_due(
+                        webhook_pool.clone(),
+                        webhook_encryption_service.clone(),
+                        &webhook_http,
+                    ).await {
+                        error!(error = %e, "Error sending webhook deliveries.");
+                    }
+                },
+                _ = webhook_shutdown_rx.changed() => {
+                    info!("Webhook delivery task shutting down.");
+                    break;
+                }
+            }
+        }
+    });
+
+    // --- DNS Failover Task ---
+    const DNS_FAILOVER_CHECK_INTERVAL_SECONDS: u64 = 60;
+    let failover_pool = duckdb_pool.clone();
+    let failover_encryption_service = encryption_service.clone();
+    let mut failover_shutdown_rx = shutdown_rx.clone();
+    tokio::spawn(async move {
+        let mut interval = interval(Duration::from_secs(DNS_FAILOVER_CHECK_INTERVAL_SECONDS));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {
+                    match duckdb_service::dns_failover_service::run_failover_checks(
+                        failover_pool.clone(),
+                        failover_encryption_service.clone(),
+                        chrono::Utc::now(),
+                    ).await {
+                        Ok(0) => {}
+                        Ok(count) => info!(count, "DNS failover policies switched target."),
+                        Err(e) => error!(error = %e, "Error running DNS failover checks."),
+                    }
+                },
+                _ = failover_shutdown_rx.changed() => {
+                    info!("DNS failover task shutting down.");
+                    break;
+                }
+            }
+        }
+    });
+
     // --- Automatic Renewal Processing Task ---
     let trigger_for_auto_renewal = update_trigger_tx.clone();
     const AUTO_RENEWAL_CHECK_INTERVAL_SECONDS: u64 = 6 * 60 * 60;
@@ -426,7 +943,7 @@ async fn run_server(mut shutdown_rx: watch::Receiver<()>) -> Result<(), Box<dyn
     let listener = socket.listen(1024)?;
     info!(address = %addr, "HTTP and gRPC server listening with TCP Keepalive");
 
-    let static_file_service = crate::web::create_static_file_service();
+    let static_file_service = crate::web::create_static_file_service(server_config.frontend_dir.as_deref());
 
     let app = http_router.fallback_service(tower::service_fn(
         move |req: axum::http::Request<axum::body::Body>| {
@@ -436,22 +953,85 @@ async fn run_server(mut shutdown_rx: watch::Receiver<()>) -> Result<(), Box<dyn
                 if req.headers().get("content-type").map(|v| v.as_bytes().starts_with(b"application/grpc")).unwrap_or(false) {
                     grpc_service.call(req).await.map(|res| res.map(axum::body::Body::new)).map_err(|err| match err {})
                 } else {
-                    static_file_service.call(req).await.map(|res| res.map(axum::body::Body::new)).map_err(|err| match err {})
+                    static_file_service.call(req).await
                 }
             }
         },
     ));
 
-    axum::serve(listener, app.into_make_service())
-        .with_graceful_shutdown(async move {
-            shutdown_rx.changed().await.ok();
-            info!("Graceful shutdown signal received. Axum server is shutting down.");
+    // With a separate admin listener, the main one keeps only the public surface.
+    let admin_task = admin_addr.map(|admin_addr| {
+        let admin_app = app.clone();
+        let admin_shutdown_rx = shutdown_rx.clone();
+        tokio::spawn(async move {
+            if let Err(e) = serve_admin(admin_addr, admin_app, admin_shutdown_rx).await {
+                error!(error = %e, "Admin API listener failed.");
+            }
         })
-        .await
-        .map_err(Box::new)?;
+    });
+    let app = if admin_task.is_some() {
+        app.layer(axum::middleware::from_fn(crate::web::middleware::surface::public_surface_only))
+    } else {
+        app
+    };
+    let app = app.layer(axum::middleware::from_fn_with_state(
+        server_config.clone(),
+        crate::web::middleware::client_cert::forwarded_client_certificate,
+    ));
+
+    if let Some(tls_config) = tls_config {
+        info!("Serving TLS; agents may authenticate with client certificates.");
+        crate::server::tls::serve(listener, tls_config, app, shutdown_rx).await.map_err(Box::new)?;
+    } else {
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(async move {
+                shutdown_rx.changed().await.ok();
+                info!("Graceful shutdown signal received. Axum server is shutting down.");
+            })
+            .await
+            .map_err(Box::new)?;
+    }
 
     // Wait for tasks to complete
     let _ = tokio::try_join!(debouncer_task, evaluation_task, duckdb_task_handle, self_update_task);
+    if let Some(admin_task) = admin_task {
+        let _ = admin_task.await;
+    }
  
     Ok(())
 }
+
+/// Serves the full router on the admin listener until shutdown.
+async fn serve_admin(
+    addr: BindAddress,
+    app: axum::Router,
+    mut shutdown_rx: watch::Receiver<()>,
+) -> std::io::Result<()> {
+    let shutdown = async move {
+        shutdown_rx.changed().await.ok();
+        info!("Graceful shutdown signal received. Admin API listener is shutting down.");
+    };
+    match addr {
+        BindAddress::Tcp(addr) => {
+            let listener = tokio::net::TcpListener::bind(addr).await?;
+            info!(address = %addr, "Admin API listening");
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).with_graceful_shutdown(shutdown).await
+        }
+        #[cfg(unix)]
+        BindAddress::Unix(path) => {
+            use std::os::unix::fs::FileTypeExt;
+            // A socket left behind by an unclean exit would make bind fail.
+            if std::fs::metadata(&path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                std::fs::remove_file(&path)?;
+            }
+            let listener = tokio::net::UnixListener::bind(&path)?;
+            info!(path = %path.display(), "Admin API listening on unix socket");
+            axum::serve(listener, app.into_make_service()).with_graceful_shutdown(shutdown).await
+        }
+        #[cfg(not(unix))]
+        BindAddress::Unix(_) => Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Unix sockets are not supported on this platform.",
+        )),
+    }
+}
@@ -0,0 +1,171 @@
+//! Digests: channels with `digest` delivery get the notifications queued for
+//! them since their last digest as one message whenever their schedule comes
+//! due. Nothing is sent for a period without notifications.
+//!
+//! Digests use the localized template `notifications.digest_template`, with
+//! `count`, `more` (items left out of a long digest), `since`, `time` and
+//! `items`, each with `kind`, `time`, `message` and the variables the
+//! notification had (`rule_name`, `severity`, `vps_name`, ...).
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tera::{Context, Tera};
+use tracing::{error, info, warn};
+
+use crate::db::duckdb_service::notification_digest_service::{self, KIND_RENEWAL};
+use crate::db::duckdb_service::{notification_service, DuckDbPool};
+use crate::db::entities::notification_digest_item;
+use crate::notifications::encryption::EncryptionService;
+use crate::services::cron::CronSchedule;
+use crate::web::error::AppError;
+
+/// Items listed in one digest; the rest are only counted.
+const MAX_DIGEST_ITEMS: usize = 50;
+
+fn item_time(time: DateTime<Utc>) -> String {
+    time.format("%Y-%m-%d %H:%M").to_string()
+}
+
+/// The template variables of one item, with a `message` for renewal
+/// reminders, which are queued without one.
+fn item_context(item: &notification_digest_item::Model, locale: &str) -> HashMap<String, String> {
+    let mut context = item.context.clone();
+    context.insert("kind".to_string(), item.kind.clone());
+    context.insert("time".to_string(), item_time(item.created_at));
+    if item.kind == KIND_RENEWAL {
+        let vps_name = context.get("vps_name").cloned().unwrap_or_default();
+        let date = context.get("next_renewal_date").cloned().unwrap_or_default();
+        let message = t!("notifications.digest_renewal", locale = locale, vps_name = vps_name, date = date);
+        context.insert("message".to_string(), message.into_owned());
+    }
+    context
+}
+
+/// Renders the digest of `items`, oldest first.
+pub fn render_digest(
+    items: &[notification_digest_item::Model],
+    locale: &str,
+    now: DateTime<Utc>,
+) -> Result<String, tera::Error> {
+    let mut context = Context::new();
+    context.insert("count", &items.len());
+    context.insert("more", &items.len().saturating_sub(MAX_DIGEST_ITEMS));
+    context.insert("since", &items.first().map(|item| item_time(item.created_at)).unwrap_or_default());
+    context.insert("time", &now.to_rfc3339_opts(SecondsFormat::Secs, true));
+    let listed: Vec<HashMap<String, String>> = items
+        .iter()
+        .take(MAX_DIGEST_ITEMS)
+        .map(|item| item_context(item, locale))
+        .collect();
+    context.insert("items", &listed);
+    let template = t!("notifications.digest_template", locale = locale);
+    Tera::one_off(&template, &context, false)
+}
+
+/// Sends the digest of every channel due at `now` and returns how many came
+/// due. A channel's next digest is scheduled before its digest is sent, so a
+/// failing channel doesn't retry on every tick; its items stay queued for
+/// the next one.
+pub async fn send_due_digests(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    now: DateTime<Utc>,
+) -> Result<usize, AppError> {
+    let due = notification_digest_service::get_due_channels(pool.clone(), now).await?;
+    for (channel_id, digest_schedule) in &due {
+        let next_digest_at = digest_schedule
+            .as_deref()
+            .and_then(|expression| CronSchedule::parse(expression).ok())
+            .and_then(|schedule| schedule.next_after(now));
+        if next_digest_at.is_none() {
+            warn!(channel_id, schedule = ?digest_schedule, "Digest schedule has no future runs.");
+        }
+        notification_digest_service::advance_digest(pool.clone(), *channel_id, next_digest_at).await?;
+
+        if let Err(e) = send_digest(pool.clone(), encryption_service.clone(), *channel_id, now).await {
+            error!(channel_id, error = %e, "Failed to send notification digest.");
+        }
+    }
+    Ok(due.len())
+}
+
+async fn send_digest(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    channel_id: i32,
+    now: DateTime<Utc>,
+) -> Result<(), AppError> {
+    let items = notification_digest_service::get_items(pool.clone(), channel_id).await?;
+    let Some(last_id) = items.last().map(|item| item.id) else {
+        return Ok(());
+    };
+    let (config, model, locale) =
+        notification_service::get_channel_for_sending(pool.clone(), encryption_service, channel_id).await?;
+    let sender = notification_service::sender_for(&model.channel_type).ok_or_else(|| {
+        AppError::InternalServerError(format!("Unsupported channel type for sending: {}", model.channel_type))
+    })?;
+
+    let message = render_digest(&items, &locale, now)
+        .map_err(|e| AppError::InternalServerError(format!("Failed to render digest: {e}")))?;
+    // Webhook body templates get the digest as `text`.
+    let context = HashMap::from([
+        ("text".to_string(), message.clone()),
+        ("message".to_string(), message.clone()),
+        ("count".to_string(), items.len().to_string()),
+        ("time".to_string(), now.to_rfc3339_opts(SecondsFormat::Secs, true)),
+    ]);
+    sender
+        .send(&config, &message, &context)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    notification_digest_service::delete_items_through(pool, channel_id, last_id).await?;
+    info!(channel_id, count = items.len(), "Sent notification digest.");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::duckdb_service::notification_digest_service::KIND_NOTIFICATION;
+    use chrono::TimeZone;
+
+    fn item(id: i64, kind: &str, minute: u32, context: &[(&str, &str)]) -> notification_digest_item::Model {
+        notification_digest_item::Model {
+            id,
+            channel_id: 1,
+            kind: kind.to_string(),
+            context: context.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect(),
+            created_at: Utc.with_ymd_and_hms(2025, 7, 1, 8, minute, 0).unwrap(),
+        }
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 7, 1, 9, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn digest_lists_alerts_and_renewals() {
+        let items = [
+            item(1, KIND_NOTIFICATION, 5, &[("message", "CPU above 90%"), ("severity", "critical"), ("vps_name", "web-01")]),
+            item(2, KIND_RENEWAL, 10, &[("vps_name", "db-01"), ("next_renewal_date", "2025-07-05")]),
+        ];
+        let english = render_digest(&items, "en", now()).unwrap();
+        assert!(english.contains("2 notifications since 2025-07-01 08:05"));
+        assert!(english.contains("[critical] CPU above 90% (web-01)"));
+        assert!(english.contains("db-01 renews on 2025-07-05"));
+
+        let chinese = render_digest(&items, "zh-CN", now()).unwrap();
+        assert!(chinese.contains("db-01 将于 2025-07-05 续费"));
+    }
+
+    #[test]
+    fn long_digests_only_count_the_rest() {
+        let items: Vec<_> = (0..MAX_DIGEST_ITEMS as i64 + 3)
+            .map(|id| item(id, KIND_NOTIFICATION, 0, &[("message", "Disk full")]))
+            .collect();
+        let digest = render_digest(&items, "en", now()).unwrap();
+        assert_eq!(digest.matches("Disk full").count(), MAX_DIGEST_ITEMS);
+        assert!(digest.contains("and 3 more"));
+    }
+}
@@ -2,8 +2,20 @@ use aes_gcm::{
     Aes256Gcm, Nonce,
     aead::{Aead, AeadCore, KeyInit, OsRng},
 };
+use std::collections::HashMap;
+use std::sync::RwLock;
 use thiserror::Error;
 
+/// Marks ciphertext produced with a versioned data key. Data written before key
+/// versioning (or while no data key exists) has no header and is decrypted with
+/// the master key, which is version 0.
+const VERSION_MAGIC: &[u8; 4] = b"NNK1";
+const HEADER_LEN: usize = VERSION_MAGIC.len() + 4;
+const NONCE_LEN: usize = 12;
+
+/// Key version of the master key itself.
+pub const MASTER_KEY_VERSION: u32 = 0;
+
 #[derive(Error, Debug)]
 pub enum EncryptionError {
     #[error("Encryption failed: {0}")]
@@ -12,57 +24,274 @@ pub enum EncryptionError {
     DecryptionFailed(String),
     #[error("Invalid key length")]
     InvalidKeyLength,
+    #[error("Unknown encryption key version {0}")]
+    UnknownKeyVersion(u32),
+}
+
+struct KeyRing {
+    /// Current version of the global keys.
+    current: u32,
+    /// Current version of each organization that has keys of its own.
+    organization_current: HashMap<i32, u32>,
+    keys: HashMap<u32, Aes256Gcm>,
+}
+
+impl KeyRing {
+    fn current_for(&self, organization_id: Option<i32>) -> u32 {
+        organization_id
+            .and_then(|id| self.organization_current.get(&id).copied())
+            .unwrap_or(self.current)
+    }
 }
 
-/// A service to handle symmetric encryption for notification channel configurations.
-/// Uses AES-256-GCM.
+/// A service to handle symmetric encryption for notification channel configurations
+/// and other stored secrets. Uses AES-256-GCM.
+///
+/// The configured key is the master key. Data keys are generated on rotation,
+/// stored wrapped by the master key, and loaded with [`EncryptionService::install_key`];
+/// new data is always encrypted with the newest one, older versions stay
+/// available for decryption until every row has been re-encrypted.
+///
+/// A data key is either global or belongs to one organization. Data of an
+/// organization with keys of its own is encrypted with its newest key, so its
+/// secrets can be rotated, or made unreadable by dropping its keys, without
+/// touching anyone else's. Versions are unique across all organizations, so
+/// decryption only needs the version in the header.
 pub struct EncryptionService {
     // The cipher is created from a 32-byte key.
-    cipher: Aes256Gcm,
+    master: Aes256Gcm,
+    ring: RwLock<KeyRing>,
+}
+
+fn seal(cipher: &Aes256Gcm, plaintext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng); // 96-bits; must be unique for each encryption
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|e| EncryptionError::EncryptionFailed(e.to_string()))?;
+
+    // Prepend nonce to the ciphertext. The nonce is required for decryption.
+    let mut result = nonce.to_vec();
+    result.extend_from_slice(&ciphertext);
+    Ok(result)
+}
+
+fn open(cipher: &Aes256Gcm, encrypted_data: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+    if encrypted_data.len() < NONCE_LEN {
+        return Err(EncryptionError::DecryptionFailed(
+            "Invalid encrypted data: too short to contain a nonce".to_string(),
+        ));
+    }
+
+    let (nonce_bytes, ciphertext) = encrypted_data.split_at(NONCE_LEN);
+    let nonce = Nonce::from_slice(nonce_bytes);
+
+    cipher
+        .decrypt(nonce, ciphertext)
+        .map_err(|e| EncryptionError::DecryptionFailed(e.to_string()))
+}
+
+/// Splits versioned ciphertext into its key version and nonce-prefixed payload.
+fn split_header(encrypted_data: &[u8]) -> Option<(u32, &[u8])> {
+    if encrypted_data.len() < HEADER_LEN + NONCE_LEN || !encrypted_data.starts_with(VERSION_MAGIC) {
+        return None;
+    }
+    let version = u32::from_be_bytes(encrypted_data[VERSION_MAGIC.len()..HEADER_LEN].try_into().ok()?);
+    Some((version, &encrypted_data[HEADER_LEN..]))
 }
 
 impl EncryptionService {
-    /// Creates a new EncryptionService with a 32-byte key.
+    /// Creates a new EncryptionService with a 32-byte master key.
     /// The key should be loaded securely, e.g., from an environment variable.
     pub fn new(key: &[u8]) -> Result<Self, EncryptionError> {
+        let master = Aes256Gcm::new_from_slice(key).map_err(|_e| EncryptionError::InvalidKeyLength)?;
+        let mut keys = HashMap::new();
+        keys.insert(MASTER_KEY_VERSION, master.clone());
         Ok(Self {
-            cipher: Aes256Gcm::new_from_slice(key)
-                .map_err(|_e| EncryptionError::InvalidKeyLength)?, // Renamed e to _e
+            master,
+            ring: RwLock::new(KeyRing {
+                current: MASTER_KEY_VERSION,
+                organization_current: HashMap::new(),
+                keys,
+            }),
         })
     }
 
-    /// Encrypts a plaintext byte slice.
-    /// Prepends a 12-byte (96-bit) nonce to the ciphertext.
+    /// Generates a new random 32-byte data key.
+    pub fn generate_key() -> Vec<u8> {
+        Aes256Gcm::generate_key(&mut OsRng).to_vec()
+    }
+
+    /// Encrypts a data key with the master key for storage.
+    pub fn wrap_key(&self, key: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        seal(&self.master, key)
+    }
+
+    /// Makes a data key available under `version`, unwrapping it with the master
+    /// key. The highest installed version of its scope (global, or
+    /// `organization_id`) is used for new encryptions in that scope.
+    pub fn install_key(
+        &self,
+        version: u32,
+        organization_id: Option<i32>,
+        wrapped_key: &[u8],
+    ) -> Result<(), EncryptionError> {
+        let key = open(&self.master, wrapped_key)?;
+        let cipher = Aes256Gcm::new_from_slice(&key).map_err(|_e| EncryptionError::InvalidKeyLength)?;
+        let mut ring = self.ring.write().unwrap_or_else(|e| e.into_inner());
+        ring.keys.insert(version, cipher);
+        match organization_id {
+            Some(id) => {
+                let current = ring.organization_current.entry(id).or_insert(version);
+                *current = (*current).max(version);
+            }
+            None => ring.current = ring.current.max(version),
+        }
+        Ok(())
+    }
+
+    /// Forgets a data key once nothing is encrypted with it any more. Current
+    /// keys and the master key cannot be removed.
+    pub fn remove_key(&self, version: u32) {
+        let mut ring = self.ring.write().unwrap_or_else(|e| e.into_inner());
+        if !Self::is_current_in(&ring, version) && version != MASTER_KEY_VERSION {
+            ring.keys.remove(&version);
+        }
+    }
+
+    fn is_current_in(ring: &KeyRing, version: u32) -> bool {
+        version == ring.current || ring.organization_current.values().any(|&current| current == version)
+    }
+
+    /// Whether new data of any scope is encrypted with `version`.
+    pub fn is_current(&self, version: u32) -> bool {
+        Self::is_current_in(&self.ring.read().unwrap_or_else(|e| e.into_inner()), version)
+    }
+
+    /// The key version new data without an organization key is encrypted with.
+    pub fn current_version(&self) -> u32 {
+        self.ring.read().unwrap_or_else(|e| e.into_inner()).current
+    }
+
+    /// The key version new data of `organization_id` is encrypted with.
+    pub fn current_version_for(&self, organization_id: Option<i32>) -> u32 {
+        self.ring.read().unwrap_or_else(|e| e.into_inner()).current_for(organization_id)
+    }
+
+    /// The key version an encrypted value was written with.
+    pub fn key_version(&self, encrypted_data: &[u8]) -> u32 {
+        split_header(encrypted_data).map_or(MASTER_KEY_VERSION, |(version, _)| version)
+    }
+
+    /// Encrypts a plaintext byte slice with the current global key.
+    /// Prepends a 12-byte (96-bit) nonce to the ciphertext, and a key version
+    /// header when a data key is in use.
     pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
-        let nonce = Aes256Gcm::generate_nonce(&mut OsRng); // 96-bits; must be unique for each encryption
-        let ciphertext = self
-            .cipher
-            .encrypt(&nonce, plaintext)
-            .map_err(|e| EncryptionError::EncryptionFailed(e.to_string()))?;
-
-        // Prepend nonce to the ciphertext. The nonce is required for decryption.
-        let mut result = nonce.to_vec();
-        result.extend_from_slice(&ciphertext);
+        self.encrypt_for(None, plaintext)
+    }
+
+    /// Encrypts data of `organization_id` with its current key, or with the
+    /// current global key if it has none.
+    pub fn encrypt_for(&self, organization_id: Option<i32>, plaintext: &[u8]) -> Result<Vec<u8>, EncryptionError> {
+        let ring = self.ring.read().unwrap_or_else(|e| e.into_inner());
+        let version = ring.current_for(organization_id);
+        if version == MASTER_KEY_VERSION {
+            return seal(&self.master, plaintext);
+        }
+        let cipher = ring
+            .keys
+            .get(&version)
+            .ok_or(EncryptionError::UnknownKeyVersion(version))?;
+        let mut result = VERSION_MAGIC.to_vec();
+        result.extend_from_slice(&version.to_be_bytes());
+        result.extend_from_slice(&seal(cipher, plaintext)?);
         Ok(result)
     }
 
-    /// Decrypts an encrypted byte slice.
-    /// Assumes the first 12 bytes are the nonce.
+    /// Decrypts an encrypted byte slice written with any installed key version.
     pub fn decrypt(&self, encrypted_data: &[u8]) -> Result<Vec<u8>, EncryptionError> {
-        if encrypted_data.len() < 12 {
-            return Err(EncryptionError::DecryptionFailed(
-                "Invalid encrypted data: too short to contain a nonce".to_string(),
-            ));
+        if let Some((version, payload)) = split_header(encrypted_data) {
+            let ring = self.ring.read().unwrap_or_else(|e| e.into_inner());
+            match ring.keys.get(&version) {
+                Some(cipher) => {
+                    if let Ok(plaintext) = open(cipher, payload) {
+                        return Ok(plaintext);
+                    }
+                }
+                None => return Err(EncryptionError::UnknownKeyVersion(version)),
+            }
+            // A legacy nonce that happens to start with the magic bytes.
         }
+        open(&self.master, encrypted_data)
+    }
+
+    /// Re-encrypts a value of `organization_id` with its current key. Returns
+    /// `None` if it already is.
+    pub fn reencrypt(
+        &self,
+        organization_id: Option<i32>,
+        encrypted_data: &[u8],
+    ) -> Result<Option<Vec<u8>>, EncryptionError> {
+        if self.key_version(encrypted_data) == self.current_version_for(organization_id) {
+            return Ok(None);
+        }
+        let plaintext = self.decrypt(encrypted_data)?;
+        self.encrypt_for(organization_id, &plaintext).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotated_keys_still_decrypt_older_data() {
+        let service = EncryptionService::new(&[7u8; 32]).unwrap();
+        let legacy = service.encrypt(b"legacy").unwrap();
+        assert_eq!(service.key_version(&legacy), MASTER_KEY_VERSION);
+
+        let wrapped = service.wrap_key(&EncryptionService::generate_key()).unwrap();
+        service.install_key(1, None, &wrapped).unwrap();
+        let current = service.encrypt(b"current").unwrap();
+        assert_eq!(service.key_version(&current), 1);
+        assert_eq!(service.decrypt(&legacy).unwrap(), b"legacy");
+        assert_eq!(service.decrypt(&current).unwrap(), b"current");
+
+        let migrated = service.reencrypt(None, &legacy).unwrap().unwrap();
+        assert_eq!(service.key_version(&migrated), 1);
+        assert_eq!(service.decrypt(&migrated).unwrap(), b"legacy");
+        assert!(service.reencrypt(None, &migrated).unwrap().is_none());
+    }
+
+    #[test]
+    fn unknown_key_versions_are_reported() {
+        let service = EncryptionService::new(&[7u8; 32]).unwrap();
+        let wrapped = service.wrap_key(&EncryptionService::generate_key()).unwrap();
+        service.install_key(2, None, &wrapped).unwrap();
+        let encrypted = service.encrypt(b"secret").unwrap();
+
+        let other = EncryptionService::new(&[7u8; 32]).unwrap();
+        assert!(matches!(other.decrypt(&encrypted), Err(EncryptionError::UnknownKeyVersion(2))));
+    }
 
-        let (nonce_bytes, ciphertext) = encrypted_data.split_at(12);
-        let nonce = Nonce::from_slice(nonce_bytes);
+    #[test]
+    fn organizations_with_own_keys_use_them() {
+        let service = EncryptionService::new(&[7u8; 32]).unwrap();
+        service.install_key(1, None, &service.wrap_key(&EncryptionService::generate_key()).unwrap()).unwrap();
+        service.install_key(2, Some(10), &service.wrap_key(&EncryptionService::generate_key()).unwrap()).unwrap();
 
-        let plaintext = self
-            .cipher
-            .decrypt(nonce, ciphertext)
-            .map_err(|e| EncryptionError::DecryptionFailed(e.to_string()))?;
+        assert_eq!(service.current_version(), 1);
+        assert_eq!(service.current_version_for(Some(10)), 2);
+        assert_eq!(service.current_version_for(Some(11)), 1);
+        let own = service.encrypt_for(Some(10), b"tenant").unwrap();
+        let shared = service.encrypt_for(Some(11), b"other").unwrap();
+        assert_eq!(service.key_version(&own), 2);
+        assert_eq!(service.key_version(&shared), 1);
+        assert_eq!(service.decrypt(&own).unwrap(), b"tenant");
 
-        Ok(plaintext)
+        let moved = service.reencrypt(Some(10), &shared).unwrap().unwrap();
+        assert_eq!(service.key_version(&moved), 2);
+        assert!(service.is_current(1) && service.is_current(2));
+        service.remove_key(2);
+        assert_eq!(service.decrypt(&moved).unwrap(), b"other");
     }
 }
@@ -1,3 +1,5 @@
+pub mod digest;
 pub mod encryption;
 pub mod models;
 pub mod senders;
+pub mod templates;
@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
+use chrono::{DateTime, Utc};
+
+use crate::db::enums::{AlertSeverity, ChannelDelivery};
+
 /// Represents the different types of notification channel configurations.
 /// This enum will be serialized to JSON and then encrypted before being stored in the database.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +22,16 @@ pub enum ChannelConfig {
     },
 }
 
+/// A link attached to a notification, e.g. "Acknowledge". Senders that support it
+/// render these as buttons; others expose them to their templates.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotificationAction {
+    /// Stable identifier, used as the template variable prefix (`{key}_url`).
+    pub key: String,
+    pub label: String,
+    pub url: String,
+}
+
 /// Defines the structure for a field in a channel template for the frontend.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -46,13 +60,29 @@ pub struct CreateChannelRequest {
     pub name: String,
     pub channel_type: String,      // "telegram" or "webhook"
     pub config: serde_json::Value, // The raw config JSON from the frontend
+    /// Tera templates by locale or "default"; see `notifications::templates`.
+    #[serde(default)]
+    pub message_templates: HashMap<String, String>,
+    /// Alert severities routed to the channel; empty for all of them.
+    #[serde(default)]
+    pub severities: Vec<AlertSeverity>,
+    #[serde(default)]
+    pub delivery: ChannelDelivery,
+    /// Five-field cron expression in UTC; required for digest channels.
+    pub digest_schedule: Option<String>,
 }
 
 /// API request body for updating an existing notification channel.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct UpdateChannelRequest {
     pub name: Option<String>,
     pub config: Option<serde_json::Value>,
+    /// Replaces all of the channel's templates; `{}` goes back to the default.
+    pub message_templates: Option<HashMap<String, String>>,
+    pub severities: Option<Vec<AlertSeverity>>,
+    pub delivery: Option<ChannelDelivery>,
+    pub digest_schedule: Option<String>,
 }
 
 /// API response for a single notification channel.
@@ -64,10 +94,28 @@ pub struct ChannelResponse {
     pub name: String,
     pub channel_type: String,
     pub config_params: Option<serde_json::Value>, // Added to include decrypted config
+    pub message_templates: HashMap<String, String>,
+    pub severities: Vec<AlertSeverity>,
+    pub delivery: ChannelDelivery,
+    pub digest_schedule: Option<String>,
+    /// When the next digest goes out, for digest channels.
+    pub next_digest_at: Option<DateTime<Utc>>,
 }
 
 /// API request for sending a test notification.
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TestChannelRequest {
     pub message: Option<String>,
+    /// Renders this template instead of the channel's, to try one out before saving it.
+    pub template: Option<String>,
+}
+
+/// What a test notification looked like once rendered.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TestChannelResponse {
+    pub message: String,
+    pub rendered: String,
+    pub locale: String,
 }
@@ -2,7 +2,7 @@ use async_trait::async_trait;
 use std::collections::HashMap;
 use thiserror::Error;
 
-use super::models::ChannelConfig;
+use super::models::{ChannelConfig, NotificationAction};
 
 pub mod telegram;
 pub mod webhook;
@@ -37,4 +37,14 @@ pub trait NotificationSender {
         message: &str,
         context: &HashMap<String, String>,
     ) -> Result<(), SenderError>;
+
+    /// Sends a notification with interactive actions (acknowledge, silence, ...).
+    /// Each action's URL is a signed link back to the server.
+    async fn send_with_actions(
+        &self,
+        config: &ChannelConfig,
+        message: &str,
+        context: &HashMap<String, String>,
+        actions: &[NotificationAction],
+    ) -> Result<(), SenderError>;
 }
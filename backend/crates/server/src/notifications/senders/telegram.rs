@@ -4,7 +4,7 @@ use serde::Serialize;
 use std::collections::HashMap;
 
 use super::{NotificationSender, SenderError};
-use crate::notifications::models::ChannelConfig;
+use crate::notifications::models::{ChannelConfig, NotificationAction};
 
 /// A sender for pushing notifications via the Telegram Bot API.
 pub struct TelegramSender {
@@ -49,15 +49,29 @@ struct TelegramMessage<'a> {
     chat_id: &'a str,
     text: &'a str,
     parse_mode: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reply_markup: Option<InlineKeyboardMarkup<'a>>,
 }
 
-#[async_trait]
-impl NotificationSender for TelegramSender {
-    async fn send(
+#[derive(Serialize)]
+struct InlineKeyboardMarkup<'a> {
+    inline_keyboard: Vec<Vec<InlineKeyboardButton<'a>>>,
+}
+
+/// A URL button. Callback buttons would need the bot to poll for updates, so the
+/// signed action link is opened in the browser instead.
+#[derive(Serialize)]
+struct InlineKeyboardButton<'a> {
+    text: &'a str,
+    url: &'a str,
+}
+
+impl TelegramSender {
+    async fn send_message(
         &self,
         config: &ChannelConfig,
         message: &str,
-        _context: &HashMap<String, String>, // Telegram doesn't use templating in this basic version
+        actions: &[NotificationAction],
     ) -> Result<(), SenderError> {
         let (bot_token, chat_id) = match config {
             ChannelConfig::Telegram { bot_token, chat_id } => (bot_token, chat_id),
@@ -71,10 +85,20 @@ impl NotificationSender for TelegramSender {
         let api_url = format!("https://api.telegram.org/bot{bot_token}/sendMessage");
 
         let escaped_message = self.escape_markdown_v2(message);
+        let reply_markup = (!actions.is_empty()).then(|| InlineKeyboardMarkup {
+            inline_keyboard: vec![actions
+                .iter()
+                .map(|a| InlineKeyboardButton {
+                    text: &a.label,
+                    url: &a.url,
+                })
+                .collect()],
+        });
         let payload = TelegramMessage {
             chat_id,
             text: &escaped_message,
             parse_mode: "MarkdownV2",
+            reply_markup,
         };
 
         let response = self.client.post(&api_url).json(&payload).send().await?;
@@ -93,3 +117,25 @@ impl NotificationSender for TelegramSender {
         Ok(())
     }
 }
+
+#[async_trait]
+impl NotificationSender for TelegramSender {
+    async fn send(
+        &self,
+        config: &ChannelConfig,
+        message: &str,
+        _context: &HashMap<String, String>, // Telegram doesn't use templating in this basic version
+    ) -> Result<(), SenderError> {
+        self.send_message(config, message, &[]).await
+    }
+
+    async fn send_with_actions(
+        &self,
+        config: &ChannelConfig,
+        message: &str,
+        _context: &HashMap<String, String>,
+        actions: &[NotificationAction],
+    ) -> Result<(), SenderError> {
+        self.send_message(config, message, actions).await
+    }
+}
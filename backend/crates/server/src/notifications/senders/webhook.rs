@@ -4,7 +4,7 @@ use std::collections::HashMap;
 use tera::{Context, Tera};
 
 use super::{NotificationSender, SenderError};
-use crate::notifications::models::ChannelConfig;
+use crate::notifications::models::{ChannelConfig, NotificationAction};
 
 /// A sender for pushing notifications via a custom webhook.
 pub struct WebhookSender {
@@ -105,4 +105,21 @@ impl NotificationSender for WebhookSender {
 
         Ok(())
     }
+
+    /// Webhooks can't render buttons, so each action is exposed to the body
+    /// template as `{key}_url` (e.g. `{{ acknowledge_url }}`) for Slack, Discord
+    /// or other chat integrations to link to.
+    async fn send_with_actions(
+        &self,
+        config: &ChannelConfig,
+        message: &str,
+        context: &HashMap<String, String>,
+        actions: &[NotificationAction],
+    ) -> Result<(), SenderError> {
+        let mut context = context.clone();
+        for action in actions {
+            context.insert(format!("{}_url", action.key), action.url.clone());
+        }
+        self.send(config, message, &context).await
+    }
 }
@@ -0,0 +1,171 @@
+//! Message templates. Every notification is rendered with Tera from a
+//! template the channel can override per language, or else the default one
+//! in `locales/notifications.yml`, in the language of the channel's owner.
+//!
+//! Variables: `message` (the text the notification was raised with), `time`,
+//! and for alerts `rule_id`, `rule_name`, `severity`, `vps_id` and `vps_name`,
+//! plus a `{key}_url` per action (e.g. `acknowledge_url`). Wrap those that
+//! only some notifications have in `{% if %}`.
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use std::collections::HashMap;
+use tera::{Context, Tera};
+
+use super::models::NotificationAction;
+
+/// Key of a channel's template for languages it has none for.
+pub const DEFAULT_TEMPLATE_KEY: &str = "default";
+const FALLBACK_LOCALE: &str = "en";
+
+/// The locale to render in for a user's language setting, which may be "auto".
+pub fn resolve_locale(language: &str) -> &str {
+    match language.trim() {
+        "" | "auto" => FALLBACK_LOCALE,
+        language => language,
+    }
+}
+
+/// A channel's template for `locale`: the exact language, then the language
+/// without its region (`zh` for `zh-CN`), then its default.
+pub fn select_template<'a>(templates: &'a HashMap<String, String>, locale: &str) -> Option<&'a str> {
+    let language = locale.split(['-', '_']).next().unwrap_or(locale);
+    [locale, language, DEFAULT_TEMPLATE_KEY]
+        .into_iter()
+        .find_map(|key| templates.get(key))
+        .map(String::as_str)
+        .filter(|template| !template.trim().is_empty())
+}
+
+pub fn default_template(locale: &str) -> String {
+    t!("notifications.default_template", locale = locale).into_owned()
+}
+
+/// Context of a notification raised with `message`.
+pub fn base_context(message: &str, now: DateTime<Utc>) -> HashMap<String, String> {
+    HashMap::from([
+        ("message".to_string(), message.to_string()),
+        ("time".to_string(), now.to_rfc3339_opts(SecondsFormat::Secs, true)),
+    ])
+}
+
+/// Exposes each action's link as `{key}_url`.
+pub fn add_actions(context: &mut HashMap<String, String>, actions: &[NotificationAction]) {
+    for action in actions {
+        context.insert(format!("{}_url", action.key), action.url.clone());
+    }
+}
+
+/// Made-up alert context for test notifications, so a template shows what
+/// an alert would look like.
+pub fn sample_context(message: &str, locale: &str, now: DateTime<Utc>) -> HashMap<String, String> {
+    let mut context = base_context(message, now);
+    context.extend([
+        ("rule_id".to_string(), "0".to_string()),
+        ("rule_name".to_string(), t!("notifications.sample_rule_name", locale = locale).into_owned()),
+        ("severity".to_string(), "warning".to_string()),
+        ("vps_id".to_string(), "0".to_string()),
+        ("vps_name".to_string(), "web-01".to_string()),
+    ]);
+    add_actions(
+        &mut context,
+        &[NotificationAction {
+            key: "acknowledge".to_string(),
+            label: "Acknowledge".to_string(),
+            url: "https://example.com/alert-actions/acknowledge".to_string(),
+        }],
+    );
+    context
+}
+
+pub fn render(template: &str, context: &HashMap<String, String>) -> Result<String, tera::Error> {
+    let mut tera_context = Context::new();
+    for (key, value) in context {
+        tera_context.insert(key, value);
+    }
+    Tera::one_off(template, &tera_context, false)
+}
+
+/// Renders the channel's template, falling back to the default one when it
+/// has none for `locale` or it fails to render, and to the bare message when
+/// even that fails.
+pub fn render_message(templates: &HashMap<String, String>, locale: &str, context: &HashMap<String, String>) -> String {
+    if let Some(template) = select_template(templates, locale) {
+        match render(template, context) {
+            Ok(rendered) => return rendered,
+            Err(e) => tracing::warn!(locale, error = %e, "Channel template failed to render. Using the default."),
+        }
+    }
+    render(&default_template(locale), context)
+        .unwrap_or_else(|_| context.get("message").cloned().unwrap_or_default())
+}
+
+/// Rejects templates that don't render with the sample context.
+pub fn validate_templates(templates: &HashMap<String, String>) -> Result<(), String> {
+    let context = sample_context("", FALLBACK_LOCALE, Utc::now());
+    for (locale, template) in templates {
+        render(template, &context).map_err(|e| {
+            let cause = std::error::Error::source(&e).map(|s| format!(": {s}")).unwrap_or_default();
+            format!("Template for '{locale}' is invalid: {e}{cause}")
+        })?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 7, 1, 12, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn templates_are_picked_by_language_then_default() {
+        let templates = HashMap::from([
+            ("zh".to_string(), "中文".to_string()),
+            ("default".to_string(), "fallback".to_string()),
+        ]);
+        assert_eq!(select_template(&templates, "zh-CN"), Some("中文"));
+        assert_eq!(select_template(&templates, "de"), Some("fallback"));
+        assert_eq!(select_template(&HashMap::new(), "en"), None);
+        assert_eq!(resolve_locale("auto"), "en");
+        assert_eq!(resolve_locale("zh-CN"), "zh-CN");
+    }
+
+    #[test]
+    fn default_template_is_localized() {
+        let context = sample_context("CPU above 90%", "en", now());
+        let english = render_message(&HashMap::new(), "en", &context);
+        assert!(english.starts_with("CPU above 90%"));
+        assert!(english.contains("Server: web-01"));
+        assert!(english.contains("Severity: warning"));
+        assert!(english.contains("Time: 2025-07-01T12:00:00Z"));
+
+        let chinese = render_message(&HashMap::new(), "zh-CN", &context);
+        assert!(chinese.contains("服务器：web-01"));
+
+        // Plain notifications have no rule or VPS.
+        let plain = render_message(&HashMap::new(), "en", &base_context("Report ready", now()));
+        assert!(!plain.contains("Rule:"));
+        assert!(!plain.contains("Server:"));
+    }
+
+    #[test]
+    fn channel_template_overrides_and_falls_back_when_broken() {
+        let context = sample_context("Disk full", "en", now());
+        let templates = HashMap::from([(
+            "default".to_string(),
+            "[{{ vps_name }}] {{ message }} {{ acknowledge_url }}".to_string(),
+        )]);
+        assert_eq!(
+            render_message(&templates, "en", &context),
+            "[web-01] Disk full https://example.com/alert-actions/acknowledge"
+        );
+
+        let broken = HashMap::from([("default".to_string(), "{{ missing }}".to_string())]);
+        assert!(render_message(&broken, "en", &context).starts_with("Disk full"));
+        assert!(validate_templates(&broken).is_err());
+        assert!(validate_templates(&templates).is_ok());
+    }
+}
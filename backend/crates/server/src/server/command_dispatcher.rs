@@ -1,25 +1,31 @@
+use dashmap::DashMap;
 use futures_util::SinkExt;
 use std::sync::{
     Arc,
     atomic::{AtomicU64, Ordering},
 };
-use tokio::sync::Mutex;
-use tracing::{error, info, warn};
+use std::time::Duration;
+use tokio::sync::{oneshot, Mutex};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid; // Import the SinkExt trait
 
 use crate::db;
-use crate::db::duckdb_service::DuckDbPool;
+use crate::db::duckdb_service::{vps_selection_service, DuckDbPool};
 use crate::server::agent_state::ConnectedAgents; // To get agent connections (gRPC clients)
 // AgentCommandServiceClient is not used directly here anymore as we use the existing stream sender
 use nodenexus_common::agent_service::{
     BatchAgentCommandRequest,       // Renamed and moved
     BatchTerminateCommandRequest,   // Added for termination
+    CommandRequest,
+    CommandResponse,
     CommandType as GrpcCommandType, // This is from batch_command.proto, now part of agent_service
     MessageToAgent,
     message_to_agent,
 };
 use crate::db::enums::ChildCommandStatus; // For updating task status
 use crate::server::result_broadcaster::ResultBroadcaster;
+use crate::web::error::AppError;
+use crate::web::models::batch_command_models::{BatchTargets, ResolvedTarget};
  // Streaming and AgentToServerMessage are not directly used in dispatch_command_to_agent for sending
  
  // Static atomic counter for generating unique server_message_ids for messages sent via CommandDispatcher
@@ -42,6 +48,49 @@ pub enum DispatcherError {
     DbUpdateError(String), // From batch_command_service
     #[error("Invalid VPS ID format: {0}")]
     InvalidVpsId(String),
+    #[error("Agent did not respond to request {0} in time")]
+    Timeout(String),
+}
+
+struct PendingRequest {
+    vps_id: i32,
+    response_tx: oneshot::Sender<CommandResponse>,
+}
+
+/// Matches `CommandResponse`s coming in on agent streams to the request
+/// that is waiting for them.
+#[derive(Default)]
+pub struct PendingCommandResponses {
+    pending: DashMap<String, PendingRequest>,
+}
+
+impl PendingCommandResponses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn register(&self, request_id: String, vps_id: i32) -> oneshot::Receiver<CommandResponse> {
+        let (response_tx, response_rx) = oneshot::channel();
+        self.pending.insert(request_id, PendingRequest { vps_id, response_tx });
+        response_rx
+    }
+
+    fn remove(&self, request_id: &str) {
+        self.pending.remove(request_id);
+    }
+
+    /// Hands a response to its waiting request. Responses from a VPS other
+    /// than the one the request was sent to are dropped.
+    pub fn complete(&self, vps_id: i32, response: CommandResponse) {
+        let Some((_, request)) = self
+            .pending
+            .remove_if(&response.request_id, |_, request| request.vps_id == vps_id)
+        else {
+            debug!(request_id = %response.request_id, vps_id, "Command response for unknown request. Dropping.");
+            return;
+        };
+        let _ = request.response_tx.send(response);
+    }
 }
 
 #[derive(Clone)]
@@ -49,6 +98,7 @@ pub struct CommandDispatcher {
     connected_agents: Arc<Mutex<ConnectedAgents>>,
     duckdb_pool: DuckDbPool,
     result_broadcaster: Arc<ResultBroadcaster>,
+    pending_responses: Arc<PendingCommandResponses>,
 }
 
 impl CommandDispatcher {
@@ -56,14 +106,76 @@ impl CommandDispatcher {
         connected_agents: Arc<Mutex<ConnectedAgents>>,
         duckdb_pool: DuckDbPool,
         result_broadcaster: Arc<ResultBroadcaster>,
+        pending_responses: Arc<PendingCommandResponses>,
     ) -> Self {
         Self {
             connected_agents,
             duckdb_pool,
             result_broadcaster,
+            pending_responses,
+        }
+    }
+
+    pub fn duckdb_pool(&self) -> DuckDbPool {
+        self.duckdb_pool.clone()
+    }
+
+    pub fn result_broadcaster(&self) -> Arc<ResultBroadcaster> {
+        self.result_broadcaster.clone()
+    }
+
+    /// The VPS a batch command runs on, with what selected each one. Selectors
+    /// and saved selections are resolved now, against the VPS as they are at
+    /// dispatch time.
+    pub async fn resolve_targets(
+        &self,
+        user_id: i32,
+        targets: &BatchTargets,
+    ) -> Result<Vec<ResolvedTarget>, AppError> {
+        vps_selection_service::resolve_batch_targets(self.duckdb_pool.clone(), user_id, targets).await
+    }
+
+    /// Sends a `CommandRequest` to the agent of `vps_id` and waits for its
+    /// `CommandResponse`. The request ID is assigned here.
+    pub async fn send_command_request(
+        &self,
+        vps_id: i32,
+        mut request: CommandRequest,
+        timeout: Duration,
+    ) -> Result<CommandResponse, DispatcherError> {
+        let mut sender = {
+            let agents_guard = self.connected_agents.lock().await;
+            agents_guard
+                .find_by_vps_id(vps_id)
+                .map(|state| state.sender)
+                .ok_or_else(|| DispatcherError::AgentNotFound(vps_id.to_string()))?
+        };
+
+        let request_id = Uuid::new_v4().to_string();
+        request.request_id = request_id.clone();
+        let response_rx = self.pending_responses.register(request_id.clone(), vps_id);
+
+        let message_to_agent = MessageToAgent {
+            server_message_id: NEXT_SERVER_MESSAGE_ID.fetch_add(1, Ordering::Relaxed),
+            payload: Some(message_to_agent::Payload::CommandRequest(request)),
+        };
+        if let Err(e) = sender.send(message_to_agent).await {
+            self.pending_responses.remove(&request_id);
+            return Err(DispatcherError::MpscSendError(e.to_string()));
+        }
+
+        match tokio::time::timeout(timeout, response_rx).await {
+            Ok(Ok(response)) => Ok(response),
+            Ok(Err(_)) | Err(_) => {
+                self.pending_responses.remove(&request_id);
+                Err(DispatcherError::Timeout(request_id))
+            }
         }
     }
 
+    /// Sends a child command to its agent, which kills it after
+    /// `timeout_seconds` (0 for no limit).
+    #[allow(clippy::too_many_arguments)]
     pub async fn dispatch_command_to_agent(
         &self,
         child_task_id: Uuid,
@@ -71,6 +183,8 @@ impl CommandDispatcher {
         command_content: &str,
         command_type: GrpcCommandType,
         working_directory: Option<String>,
+        timeout_seconds: u32,
+        trace_id: &str,
     ) -> Result<(), DispatcherError> {
         let agent_sender = {
             // Scope to release the lock quickly
@@ -100,6 +214,8 @@ impl CommandDispatcher {
                     r#type: command_type.into(), // Ensure GrpcCommandType is convertible to i32 if needed by proto
                     content: command_content.to_string(),
                     working_directory: working_directory.unwrap_or_default(), // Proto expects string, not Option<String>
+                    trace_id: trace_id.to_string(),
+                    timeout_seconds,
                 };
                 let message_to_agent = MessageToAgent {
                     server_message_id: NEXT_SERVER_MESSAGE_ID.fetch_add(1, Ordering::Relaxed),
@@ -123,7 +239,7 @@ impl CommandDispatcher {
                     return Err(DispatcherError::MpscSendError(e.to_string()));
                 }
 
-                info!("Successfully dispatched command to agent.");
+                info!(%child_task_id, vps_id, trace_id, "Successfully dispatched command to agent.");
                 // TODO: Spawn a task to handle the response stream (AgentToServerMessage)
                 // This task would listen on a channel associated with this agent's communication stream
                 // and process BatchCommandOutputStream and BatchCommandResult messages.
@@ -209,3 +325,33 @@ impl CommandDispatcher {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(request_id: &str) -> CommandResponse {
+        CommandResponse {
+            request_id: request_id.to_string(),
+            success: true,
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn completes_matching_request() {
+        let pending = PendingCommandResponses::new();
+        let rx = pending.register("req-1".to_string(), 7);
+        pending.complete(7, response("req-1"));
+        assert_eq!(rx.await.unwrap().request_id, "req-1");
+        assert!(pending.pending.is_empty());
+    }
+
+    #[tokio::test]
+    async fn drops_response_from_other_vps() {
+        let pending = PendingCommandResponses::new();
+        let _rx = pending.register("req-1".to_string(), 7);
+        pending.complete(8, response("req-1"));
+        assert!(pending.pending.contains_key("req-1"));
+    }
+}
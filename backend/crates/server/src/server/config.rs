@@ -1,6 +1,54 @@
+use crate::alerting::schedule::{MAX_INTERVAL_SECONDS, MIN_INTERVAL_SECONDS};
+use crate::db::duckdb_service::retention::RetentionPolicy;
 use serde::Deserialize;
 use std::fs;
-use std::path::Path;
+use std::net::{IpAddr, SocketAddr};
+use std::path::{Path, PathBuf};
+
+/// A listener address: `host:port`, or `unix:/path/to.sock`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BindAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+impl BindAddress {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let value = value.trim();
+        if let Some(path) = value.strip_prefix("unix:") {
+            if path.is_empty() {
+                return Err("Unix socket address needs a path, e.g. unix:/run/nodenexus/admin.sock".to_string());
+            }
+            return Ok(BindAddress::Unix(PathBuf::from(path)));
+        }
+        value
+            .parse()
+            .map(BindAddress::Tcp)
+            .map_err(|e| format!("Invalid bind address '{value}': {e}"))
+    }
+}
+
+impl std::fmt::Display for BindAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BindAddress::Tcp(addr) => write!(f, "{addr}"),
+            BindAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// How the public addresses of VPS are geolocated.
+#[derive(Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum GeoIpProvider {
+    /// No lookups; only the country the agent reports is known.
+    #[default]
+    None,
+    /// The ip-api.com JSON API. Addresses are sent to the service.
+    IpApi,
+    /// Local MaxMind GeoLite2/GeoIP2 databases.
+    Maxmind,
+}
 
 #[derive(Deserialize, Debug, Clone)]
 pub struct ServerConfig {
@@ -21,6 +69,179 @@ pub struct ServerConfig {
 
     #[serde(default)]
     pub is_in_container: bool,
+
+    /// When set and the directory exists, the frontend is served from disk instead of the embedded bundle.
+    #[serde(default)]
+    pub frontend_dir: Option<String>,
+
+    /// Local directory or `s3://bucket/prefix` URL that aged events are archived to.
+    #[serde(default = "default_archive_dir")]
+    pub archive_dir: String,
+
+    /// Events older than this are moved to the archive. `0` disables archival.
+    #[serde(default = "default_event_retention_days")]
+    pub event_retention_days: u32,
+
+    /// Seconds between evaluations of alert rules that don't set their own
+    /// interval, and of SLO burn rates.
+    #[serde(default = "default_alert_evaluation_interval_seconds")]
+    pub alert_evaluation_interval_seconds: u64,
+
+    /// Days the output of finished batch commands is kept. `0` keeps it forever.
+    #[serde(default = "default_batch_output_retention_days")]
+    pub batch_output_retention_days: u32,
+
+    /// Hours between vulnerability scans of the package inventory. `0` disables scanning.
+    #[serde(default = "default_vulnerability_scan_interval_hours")]
+    pub vulnerability_scan_interval_hours: u32,
+
+    /// Base URL of the OSV API used for vulnerability matching.
+    #[serde(default = "default_osv_api_url")]
+    pub osv_api_url: String,
+
+    /// Read-only DuckDB file, kept as a replica of the main database, that bulk
+    /// report and export reads run against. The server doesn't refresh it, and
+    /// ownership checks always use the main database. When unset they share the
+    /// main pool.
+    #[serde(default)]
+    pub reporting_database_path: Option<String>,
+
+    /// Maximum connections in the reporting pool.
+    #[serde(default = "default_reporting_pool_size")]
+    pub reporting_pool_size: u32,
+
+    /// Ephemeral VPS offline for this many hours are archived. `0` disables archival.
+    #[serde(default = "default_ephemeral_archive_after_hours")]
+    pub ephemeral_archive_after_hours: u32,
+
+    /// Listener for agents, the frontend and public endpoints. It also serves
+    /// the admin API unless `admin_bind_address` is set.
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,
+
+    /// Separate listener (`host:port` or `unix:/path`) for the authenticated
+    /// API. When set, the main listener only serves public endpoints.
+    #[serde(default)]
+    pub admin_bind_address: Option<String>,
+
+    /// Buffered metric rows (snapshots plus their per-disk and per-interface
+    /// rows) that make the writer flush before its interval elapses.
+    #[serde(default = "default_metrics_batch_size")]
+    pub metrics_batch_size: usize,
+
+    /// Longest time metrics wait in the writer buffer.
+    #[serde(default = "default_metrics_flush_interval_seconds")]
+    pub metrics_flush_interval_seconds: u64,
+
+    /// Days raw metrics (and per-disk/per-interface rows) are kept. `0` keeps them forever.
+    #[serde(default = "default_metrics_raw_retention_days")]
+    pub metrics_raw_retention_days: u32,
+
+    /// Days 1-minute rollups are kept. `0` keeps them forever.
+    #[serde(default = "default_metrics_1m_retention_days")]
+    pub metrics_1m_retention_days: u32,
+
+    /// Days hourly rollups are kept. `0` keeps them forever.
+    #[serde(default = "default_metrics_1h_retention_days")]
+    pub metrics_1h_retention_days: u32,
+
+    /// Days daily rollups are kept. `0` (the default) keeps them forever.
+    #[serde(default = "default_metrics_1d_retention_days")]
+    pub metrics_1d_retention_days: u32,
+
+    /// Generates synthetic demo servers and metrics instead of relying on real
+    /// agents. For evaluation, screenshots and load testing only.
+    #[serde(default)]
+    pub demo_mode: bool,
+
+    /// Number of synthetic servers in demo mode.
+    #[serde(default = "default_demo_agent_count")]
+    pub demo_agent_count: u32,
+
+    /// How jumpy synthetic metrics are, from 0 (flat) to 1 (erratic).
+    #[serde(default = "default_demo_volatility")]
+    pub demo_volatility: f64,
+
+    /// Trusts a reverse proxy that terminates SSO (Authelia, oauth2-proxy) to
+    /// name the user in `trusted_header_name`. Requests with a session or API
+    /// token still authenticate with it.
+    #[serde(default)]
+    pub trusted_header_auth: bool,
+
+    /// Header carrying the authenticated username.
+    #[serde(default = "default_trusted_header_name")]
+    pub trusted_header_name: String,
+
+    /// Role given to users provisioned on their first request through the proxy.
+    #[serde(default = "default_trusted_header_default_role")]
+    pub trusted_header_default_role: String,
+
+    /// Peers allowed to set the header. Loopback only when empty; connections
+    /// over the admin unix socket are always trusted.
+    #[serde(default)]
+    pub trusted_proxy_ips: Vec<IpAddr>,
+
+    /// Seconds of WebSocket broadcasts kept per channel, so a client that
+    /// reconnects with `?since=` gets what it missed. `0` disables the history.
+    #[serde(default = "default_ws_history_seconds")]
+    pub ws_history_seconds: u64,
+
+    /// Most broadcasts kept per channel, whatever their age.
+    #[serde(default = "default_ws_history_max_messages")]
+    pub ws_history_max_messages: usize,
+
+    /// PEM certificate chain the main listener serves TLS with. With it (and
+    /// `tls_key_path`) agents can authenticate with client certificates from
+    /// the agent CA. Leave unset behind a reverse proxy that terminates TLS.
+    #[serde(default)]
+    pub tls_cert_path: Option<String>,
+
+    /// PEM private key for `tls_cert_path`.
+    #[serde(default)]
+    pub tls_key_path: Option<String>,
+
+    /// Header in which a reverse proxy that verifies client certificates
+    /// forwards the agent's: its SHA-256 fingerprint in hex, or the URL-encoded
+    /// PEM (nginx's `$ssl_client_escaped_cert`). Only read from `trusted_proxy_ips`.
+    #[serde(default)]
+    pub client_cert_header: Option<String>,
+
+    /// Days agent client certificates are valid.
+    #[serde(default = "default_agent_cert_valid_days")]
+    pub agent_cert_valid_days: u32,
+
+    /// Accepts a VPS's client certificate instead of its agent secret rather
+    /// than in addition to it.
+    #[serde(default)]
+    pub agent_cert_replaces_secret: bool,
+
+    /// Where install scripts download the agent from: release assets are
+    /// expected at `<url>/v<version>/nodenexus-agent-v<version>-<target>`.
+    #[serde(default = "default_agent_download_base_url")]
+    pub agent_download_base_url: String,
+
+    /// Hours between scheduled backup archives. `0` disables scheduled backups.
+    #[serde(default = "default_backup_interval_hours")]
+    pub backup_interval_hours: u32,
+
+    /// Backup archives kept in `<data_dir>/backups/archives`; older ones are deleted.
+    #[serde(default = "default_backups_kept")]
+    pub backups_kept: usize,
+
+    #[serde(default)]
+    pub geoip_provider: GeoIpProvider,
+
+    /// Base URL of the ip-api.com compatible endpoint; the address is appended.
+    #[serde(default = "default_geoip_ip_api_url")]
+    pub geoip_ip_api_url: String,
+
+    /// GeoLite2-City (or GeoIP2-City) database, required for the `maxmind` provider.
+    #[serde(default)]
+    pub geoip_city_database: Option<String>,
+
+    /// Optional GeoLite2-ASN database for the `maxmind` provider.
+    #[serde(default)]
+    pub geoip_asn_database: Option<String>,
 }
 
 // Partial config for layering
@@ -32,7 +253,48 @@ struct PartialServerConfig {
     data_dir: Option<String>,
     log_dir: Option<String>,
     update_url: Option<String>,
+    #[serde(alias = "running_in_container")]
     is_in_container: Option<bool>,
+    frontend_dir: Option<String>,
+    archive_dir: Option<String>,
+    event_retention_days: Option<u32>,
+    alert_evaluation_interval_seconds: Option<u64>,
+    batch_output_retention_days: Option<u32>,
+    vulnerability_scan_interval_hours: Option<u32>,
+    osv_api_url: Option<String>,
+    reporting_database_path: Option<String>,
+    reporting_pool_size: Option<u32>,
+    ephemeral_archive_after_hours: Option<u32>,
+    bind_address: Option<String>,
+    admin_bind_address: Option<String>,
+    metrics_batch_size: Option<usize>,
+    metrics_flush_interval_seconds: Option<u64>,
+    metrics_raw_retention_days: Option<u32>,
+    metrics_1m_retention_days: Option<u32>,
+    metrics_1h_retention_days: Option<u32>,
+    metrics_1d_retention_days: Option<u32>,
+    demo_mode: Option<bool>,
+    demo_agent_count: Option<u32>,
+    demo_volatility: Option<f64>,
+    trusted_header_auth: Option<bool>,
+    trusted_header_name: Option<String>,
+    trusted_header_default_role: Option<String>,
+    /// Comma-separated, so it can be set from the environment.
+    trusted_proxy_ips: Option<String>,
+    ws_history_seconds: Option<u64>,
+    ws_history_max_messages: Option<usize>,
+    tls_cert_path: Option<String>,
+    tls_key_path: Option<String>,
+    client_cert_header: Option<String>,
+    agent_cert_valid_days: Option<u32>,
+    agent_cert_replaces_secret: Option<bool>,
+    agent_download_base_url: Option<String>,
+    backup_interval_hours: Option<u32>,
+    backups_kept: Option<usize>,
+    geoip_provider: Option<GeoIpProvider>,
+    geoip_ip_api_url: Option<String>,
+    geoip_city_database: Option<String>,
+    geoip_asn_database: Option<String>,
 }
 
 fn default_data_dir() -> String {
@@ -43,6 +305,119 @@ fn default_log_dir() -> String {
     "logs".to_string()
 }
 
+fn default_archive_dir() -> String {
+    "data/archive".to_string()
+}
+
+fn default_event_retention_days() -> u32 {
+    90
+}
+
+fn default_alert_evaluation_interval_seconds() -> u64 {
+    60
+}
+
+fn default_batch_output_retention_days() -> u32 {
+    30
+}
+
+fn default_vulnerability_scan_interval_hours() -> u32 {
+    12
+}
+
+fn default_osv_api_url() -> String {
+    "https://api.osv.dev".to_string()
+}
+
+fn default_reporting_pool_size() -> u32 {
+    4
+}
+
+fn default_ephemeral_archive_after_hours() -> u32 {
+    24
+}
+
+fn default_bind_address() -> String {
+    "0.0.0.0:8080".to_string()
+}
+
+fn default_metrics_batch_size() -> usize {
+    1000
+}
+
+fn default_metrics_flush_interval_seconds() -> u64 {
+    10
+}
+
+fn default_metrics_raw_retention_days() -> u32 {
+    RetentionPolicy::default().raw_days
+}
+
+fn default_metrics_1m_retention_days() -> u32 {
+    RetentionPolicy::default().summary_1m_days
+}
+
+fn default_metrics_1h_retention_days() -> u32 {
+    RetentionPolicy::default().summary_1h_days
+}
+
+fn default_metrics_1d_retention_days() -> u32 {
+    RetentionPolicy::default().summary_1d_days
+}
+
+fn default_demo_agent_count() -> u32 {
+    10
+}
+
+fn default_demo_volatility() -> f64 {
+    0.3
+}
+
+fn default_trusted_header_name() -> String {
+    "X-Remote-User".to_string()
+}
+
+fn default_trusted_header_default_role() -> String {
+    "viewer".to_string()
+}
+
+fn default_ws_history_seconds() -> u64 {
+    60
+}
+
+fn default_ws_history_max_messages() -> usize {
+    1000
+}
+
+fn default_agent_cert_valid_days() -> u32 {
+    365
+}
+
+fn default_backup_interval_hours() -> u32 {
+    24
+}
+
+fn default_backups_kept() -> usize {
+    7
+}
+
+fn default_geoip_ip_api_url() -> String {
+    "http://ip-api.com/json".to_string()
+}
+
+fn default_agent_download_base_url() -> String {
+    "https://github.com/moonheart/NodeNexus/releases/download".to_string()
+}
+
+fn parse_ip_list(value: &str) -> Result<Vec<IpAddr>, String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|ip| !ip.is_empty())
+        .map(|ip| ip.parse().map_err(|e| format!("Invalid IP address '{ip}' in TRUSTED_PROXY_IPS: {e}")))
+        .collect()
+}
+
 fn default_update_url() -> String {
     "https://api.github.com/repos/mjjer/nodenexus/releases/latest".to_string()
 }
@@ -53,7 +428,34 @@ fn default_notification_key() -> String {
     "000102030405060708090a0b0c0d0e0f101112131415161718191a1b1c1d1e1f".to_string()
 }
 
+/// Best-effort detection of Docker/Podman/Kubernetes when the flag isn't set explicitly.
+fn detect_container_runtime() -> bool {
+    if Path::new("/.dockerenv").exists() || Path::new("/run/.containerenv").exists() {
+        return true;
+    }
+    if std::env::var_os("KUBERNETES_SERVICE_HOST").is_some() {
+        return true;
+    }
+    fs::read_to_string("/proc/1/cgroup")
+        .map(|cgroup| {
+            ["docker", "containerd", "kubepods", "libpod"]
+                .iter()
+                .any(|marker| cgroup.contains(marker))
+        })
+        .unwrap_or(false)
+}
+
 impl ServerConfig {
+    /// Per-resolution metric retention, as used by the cleanup task and query routing.
+    pub fn retention_policy(&self) -> RetentionPolicy {
+        RetentionPolicy {
+            raw_days: self.metrics_raw_retention_days,
+            summary_1m_days: self.metrics_1m_retention_days,
+            summary_1h_days: self.metrics_1h_retention_days,
+            summary_1d_days: self.metrics_1d_retention_days,
+        }
+    }
+
     pub fn load(config_path: Option<&str>) -> Result<Self, String> {
         dotenv::dotenv().ok();
 
@@ -91,9 +493,165 @@ impl ServerConfig {
             update_url: env_config.update_url.or(file_config.update_url)
                 .unwrap_or_else(default_update_url),
             is_in_container: env_config.is_in_container.or(file_config.is_in_container)
+                .unwrap_or_else(detect_container_runtime),
+            frontend_dir: env_config.frontend_dir.or(file_config.frontend_dir)
+                .filter(|dir| !dir.is_empty()),
+            archive_dir: env_config.archive_dir.or(file_config.archive_dir)
+                .unwrap_or_else(default_archive_dir),
+            event_retention_days: env_config.event_retention_days.or(file_config.event_retention_days)
+                .unwrap_or_else(default_event_retention_days),
+            alert_evaluation_interval_seconds: env_config.alert_evaluation_interval_seconds.or(file_config.alert_evaluation_interval_seconds)
+                .unwrap_or_else(default_alert_evaluation_interval_seconds),
+            batch_output_retention_days: env_config.batch_output_retention_days.or(file_config.batch_output_retention_days)
+                .unwrap_or_else(default_batch_output_retention_days),
+            vulnerability_scan_interval_hours: env_config.vulnerability_scan_interval_hours.or(file_config.vulnerability_scan_interval_hours)
+                .unwrap_or_else(default_vulnerability_scan_interval_hours),
+            osv_api_url: env_config.osv_api_url.or(file_config.osv_api_url)
+                .filter(|url| !url.is_empty())
+                .unwrap_or_else(default_osv_api_url),
+            reporting_database_path: env_config.reporting_database_path.or(file_config.reporting_database_path)
+                .filter(|path| !path.is_empty()),
+            reporting_pool_size: env_config.reporting_pool_size.or(file_config.reporting_pool_size)
+                .unwrap_or_else(default_reporting_pool_size),
+            ephemeral_archive_after_hours: env_config.ephemeral_archive_after_hours.or(file_config.ephemeral_archive_after_hours)
+                .unwrap_or_else(default_ephemeral_archive_after_hours),
+            bind_address: env_config.bind_address.or(file_config.bind_address)
+                .filter(|addr| !addr.is_empty())
+                .unwrap_or_else(default_bind_address),
+            admin_bind_address: env_config.admin_bind_address.or(file_config.admin_bind_address)
+                .filter(|addr| !addr.is_empty()),
+            metrics_batch_size: env_config.metrics_batch_size.or(file_config.metrics_batch_size)
+                .unwrap_or_else(default_metrics_batch_size),
+            metrics_flush_interval_seconds: env_config.metrics_flush_interval_seconds.or(file_config.metrics_flush_interval_seconds)
+                .unwrap_or_else(default_metrics_flush_interval_seconds),
+            metrics_raw_retention_days: env_config.metrics_raw_retention_days.or(file_config.metrics_raw_retention_days)
+                .unwrap_or_else(default_metrics_raw_retention_days),
+            metrics_1m_retention_days: env_config.metrics_1m_retention_days.or(file_config.metrics_1m_retention_days)
+                .unwrap_or_else(default_metrics_1m_retention_days),
+            metrics_1h_retention_days: env_config.metrics_1h_retention_days.or(file_config.metrics_1h_retention_days)
+                .unwrap_or_else(default_metrics_1h_retention_days),
+            metrics_1d_retention_days: env_config.metrics_1d_retention_days.or(file_config.metrics_1d_retention_days)
+                .unwrap_or_else(default_metrics_1d_retention_days),
+            demo_mode: env_config.demo_mode.or(file_config.demo_mode).unwrap_or(false),
+            demo_agent_count: env_config.demo_agent_count.or(file_config.demo_agent_count)
+                .unwrap_or_else(default_demo_agent_count),
+            demo_volatility: env_config.demo_volatility.or(file_config.demo_volatility)
+                .unwrap_or_else(default_demo_volatility),
+            trusted_header_auth: env_config.trusted_header_auth.or(file_config.trusted_header_auth).unwrap_or(false),
+            trusted_header_name: env_config.trusted_header_name.or(file_config.trusted_header_name)
+                .filter(|name| !name.is_empty())
+                .unwrap_or_else(default_trusted_header_name),
+            trusted_header_default_role: env_config.trusted_header_default_role.or(file_config.trusted_header_default_role)
+                .filter(|role| !role.is_empty())
+                .unwrap_or_else(default_trusted_header_default_role),
+            trusted_proxy_ips: parse_ip_list(
+                &env_config.trusted_proxy_ips.or(file_config.trusted_proxy_ips).unwrap_or_default(),
+            )?,
+            ws_history_seconds: env_config.ws_history_seconds.or(file_config.ws_history_seconds)
+                .unwrap_or_else(default_ws_history_seconds),
+            ws_history_max_messages: env_config.ws_history_max_messages.or(file_config.ws_history_max_messages)
+                .unwrap_or_else(default_ws_history_max_messages),
+            tls_cert_path: env_config.tls_cert_path.or(file_config.tls_cert_path)
+                .filter(|path| !path.is_empty()),
+            tls_key_path: env_config.tls_key_path.or(file_config.tls_key_path)
+                .filter(|path| !path.is_empty()),
+            client_cert_header: env_config.client_cert_header.or(file_config.client_cert_header)
+                .filter(|name| !name.is_empty()),
+            agent_cert_valid_days: env_config.agent_cert_valid_days.or(file_config.agent_cert_valid_days)
+                .unwrap_or_else(default_agent_cert_valid_days),
+            agent_cert_replaces_secret: env_config.agent_cert_replaces_secret.or(file_config.agent_cert_replaces_secret)
                 .unwrap_or(false),
+            agent_download_base_url: env_config.agent_download_base_url.or(file_config.agent_download_base_url)
+                .filter(|url| !url.is_empty())
+                .unwrap_or_else(default_agent_download_base_url),
+            backup_interval_hours: env_config.backup_interval_hours.or(file_config.backup_interval_hours)
+                .unwrap_or_else(default_backup_interval_hours),
+            backups_kept: env_config.backups_kept.or(file_config.backups_kept)
+                .unwrap_or_else(default_backups_kept),
+            geoip_provider: env_config.geoip_provider.or(file_config.geoip_provider)
+                .unwrap_or_default(),
+            geoip_ip_api_url: env_config.geoip_ip_api_url.or(file_config.geoip_ip_api_url)
+                .filter(|url| !url.is_empty())
+                .unwrap_or_else(default_geoip_ip_api_url),
+            geoip_city_database: env_config.geoip_city_database.or(file_config.geoip_city_database)
+                .filter(|path| !path.is_empty()),
+            geoip_asn_database: env_config.geoip_asn_database.or(file_config.geoip_asn_database)
+                .filter(|path| !path.is_empty()),
         };
+        if final_config.geoip_provider == GeoIpProvider::Maxmind && final_config.geoip_city_database.is_none() {
+            return Err("GEOIP_CITY_DATABASE is required when GEOIP_PROVIDER is maxmind".to_string());
+        }
+        if final_config.backups_kept == 0 {
+            return Err("BACKUPS_KEPT must be positive".to_string());
+        }
+        if final_config.metrics_batch_size == 0 || final_config.metrics_flush_interval_seconds == 0 {
+            return Err("METRICS_BATCH_SIZE and METRICS_FLUSH_INTERVAL_SECONDS must be positive".to_string());
+        }
+        if !(0.0..=1.0).contains(&final_config.demo_volatility) {
+            return Err("DEMO_VOLATILITY must be between 0 and 1".to_string());
+        }
+        if !(MIN_INTERVAL_SECONDS..=MAX_INTERVAL_SECONDS).contains(&final_config.alert_evaluation_interval_seconds) {
+            return Err(format!(
+                "ALERT_EVALUATION_INTERVAL_SECONDS must be between {MIN_INTERVAL_SECONDS} and {MAX_INTERVAL_SECONDS}"
+            ));
+        }
+        if !matches!(final_config.trusted_header_default_role.as_str(), "viewer" | "operator" | "admin") {
+            return Err("TRUSTED_HEADER_DEFAULT_ROLE must be viewer, operator or admin".to_string());
+        }
+        if axum::http::HeaderName::from_bytes(final_config.trusted_header_name.as_bytes()).is_err() {
+            return Err(format!("TRUSTED_HEADER_NAME '{}' is not a valid header name", final_config.trusted_header_name));
+        }
+        if final_config.tls_cert_path.is_some() != final_config.tls_key_path.is_some() {
+            return Err("TLS_CERT_PATH and TLS_KEY_PATH must be set together".to_string());
+        }
+        if let Some(header) = &final_config.client_cert_header {
+            if axum::http::HeaderName::from_bytes(header.as_bytes()).is_err() {
+                return Err(format!("CLIENT_CERT_HEADER '{header}' is not a valid header name"));
+            }
+        }
+        BindAddress::parse(&final_config.bind_address)?;
+        if let Some(admin) = &final_config.admin_bind_address {
+            BindAddress::parse(admin)?;
+        }
 
         Ok(final_config)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_tcp_and_unix_bind_addresses() {
+        assert_eq!(
+            BindAddress::parse("127.0.0.1:9090"),
+            Ok(BindAddress::Tcp("127.0.0.1:9090".parse().unwrap()))
+        );
+        assert_eq!(
+            BindAddress::parse("unix:/run/nodenexus/admin.sock"),
+            Ok(BindAddress::Unix(PathBuf::from("/run/nodenexus/admin.sock")))
+        );
+        assert!(BindAddress::parse("unix:").is_err());
+        assert!(BindAddress::parse("localhost").is_err());
+    }
+
+    #[test]
+    fn parses_trusted_proxy_lists() {
+        assert_eq!(parse_ip_list(""), Ok(vec![]));
+        assert_eq!(
+            parse_ip_list("10.0.0.2, ::1"),
+            Ok(vec!["10.0.0.2".parse().unwrap(), "::1".parse().unwrap()])
+        );
+        assert!(parse_ip_list("10.0.0.0/8").is_err());
+    }
+
+    #[test]
+    fn parses_geoip_provider_names() {
+        let config: PartialServerConfig = toml::from_str("geoip_provider = \"ipapi\"").unwrap();
+        assert_eq!(config.geoip_provider, Some(GeoIpProvider::IpApi));
+        let config: PartialServerConfig = toml::from_str("geoip_provider = \"maxmind\"").unwrap();
+        assert_eq!(config.geoip_provider, Some(GeoIpProvider::Maxmind));
+        assert_eq!(GeoIpProvider::default(), GeoIpProvider::None);
+    }
+}
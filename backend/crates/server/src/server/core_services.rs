@@ -10,11 +10,18 @@ use nodenexus_common::agent_service::{
     message_to_agent::Payload as AgentPayload, message_to_server::Payload as ServerPayload, CommandStatus as GrpcCommandStatus, MessageToAgent, MessageToServer,
     OutputType as GrpcOutputType, ServerHandshakeAck,
 };
+use crate::alerting::log_matcher::LogMatcher;
+use crate::db::duckdb_service::writer::SnapshotBatch;
 use crate::db::entities::performance_metric;
 use crate::db::enums::ChildCommandStatus;
+use crate::db::storage::SharedStorage;
 use crate::db::{self};
 use crate::server::agent_state::{AgentSender, AgentState, ConnectedAgents};
 use crate::server::result_broadcaster::ResultBroadcaster;
+use crate::server::command_dispatcher::PendingCommandResponses;
+use crate::server::terminal_sessions::TerminalSessions;
+use crate::services::agent_ca::{check_agent_credentials, check_stream_binding, ClientCertificate, ExpectedCertificate};
+use crate::web::error::AppError;
 use crate::web::models::websocket_models::WsMessage;
 
 // 1. Define the generic AgentStream trait
@@ -31,16 +38,49 @@ pub trait AgentStream:
 pub struct AgentStreamContext {
     pub connected_agents: Arc<Mutex<ConnectedAgents>>,
     pub duckdb_pool: crate::db::duckdb_service::DuckDbPool,
+    pub storage: SharedStorage,
     pub ws_data_broadcaster_tx: broadcast::Sender<WsMessage>,
     pub update_trigger_tx: mpsc::Sender<()>,
     pub metric_sender: mpsc::Sender<performance_metric::Model>,
-    pub duckdb_metric_sender: std_mpsc::Sender<performance_metric::Model>,
+    pub duckdb_metric_sender: std_mpsc::Sender<SnapshotBatch>,
     pub shutdown_rx: tokio::sync::watch::Receiver<()>,
     pub result_broadcaster: Arc<ResultBroadcaster>,
+    pub terminal_sessions: Arc<TerminalSessions>,
+    pub pending_command_responses: Arc<PendingCommandResponses>,
+    pub log_matcher: Arc<LogMatcher>,
+    /// Certificate the agent presented when connecting, if any.
+    pub client_certificate: Option<ClientCertificate>,
+    pub certificate_replaces_secret: bool,
 }
 
 
 // 2. Create the new function that takes the generic stream
+/// Checks the handshake's credentials against the certificate issued to the
+/// VPS, if there is one. Returns whether a certificate was verified.
+async fn check_certificate_at_handshake(
+    context: &AgentStreamContext,
+    expected_secret: &str,
+    vps_id: i32,
+    secret: &str,
+) -> Result<bool, String> {
+    let issued = db::duckdb_service::agent_certificate_service::get_certificate(context.duckdb_pool.clone(), vps_id)
+        .await
+        .map_err(|e| format!("Database error ({e})"))?;
+    let expected = issued.as_ref().map(|certificate| ExpectedCertificate {
+        fingerprint: &certificate.fingerprint,
+        expires_at: certificate.expires_at,
+    });
+    check_agent_credentials(
+        expected_secret,
+        expected,
+        secret,
+        context.client_certificate.as_ref(),
+        context.certificate_replaces_secret,
+        Utc::now(),
+    )
+    .map_err(str::to_string)
+}
+
 pub async fn process_agent_stream<S>(
     agent_stream: S,
     agent_sender: AgentSender,
@@ -53,6 +93,8 @@ pub async fn process_agent_stream<S>(
     let mut vps_db_id: Option<i32> = None;
     let mut server_message_id_counter: u64 = 1;
     let mut handshake_completed = false;
+    // Set once the agent's client certificate has been verified for this VPS.
+    let mut certificate_vps_id: Option<i32> = None;
     let mut shutdown_rx = context.shutdown_rx.clone();
 
     loop {
@@ -65,33 +107,89 @@ pub async fn process_agent_stream<S>(
             result = agent_stream.next() => {
                 match result {
                     Some(Ok(msg_to_server)) => {
-                        let vps_db_id_from_msg = msg_to_server.vps_db_id;
+                        let mut vps_db_id_from_msg = msg_to_server.vps_db_id;
                         let agent_secret_from_msg = &msg_to_server.agent_secret;
                         let mut auth_successful_for_msg = false;
                         let mut error_message_for_ack = String::new();
+                        // Secret issued to an agent that registered with a provisioning token.
+                        let mut issued_agent_secret: Option<String> = None;
+
+                        let registration = match &msg_to_server.payload {
+                            Some(ServerPayload::AgentHandshake(handshake)) if vps_db_id_from_msg == 0 => handshake
+                                .provisioning_token
+                                .as_deref()
+                                .filter(|token| !token.is_empty())
+                                .map(|token| (token, handshake)),
+                            _ => None,
+                        };
 
-                        // Authenticate every message
-                        match db::duckdb_service::vps_service::get_vps_by_id(context.duckdb_pool.clone(), vps_db_id_from_msg).await {
-                            Ok(Some(vps_record)) => {
-                                if vps_record.agent_secret == *agent_secret_from_msg {
+                        let is_handshake = matches!(&msg_to_server.payload, Some(ServerPayload::AgentHandshake(_)));
+                        if let Err(reason) = check_stream_binding(vps_db_id, vps_db_id_from_msg, is_handshake) {
+                            error_message_for_ack = format!("Authentication failed: {reason}.");
+                            warn!(vps_id = vps_db_id_from_msg, bound_vps_id = ?vps_db_id, %reason, "Authentication failed.");
+                        } else if let Some((token, handshake)) = registration {
+                            let name = if handshake.hostname.is_empty() || handshake.hostname == "N/A" {
+                                handshake.agent_id_hint.as_str()
+                            } else {
+                                handshake.hostname.as_str()
+                            };
+                            match db::duckdb_service::provisioning_service::register_vps_with_token(context.duckdb_pool.clone(), token, name, false).await {
+                                Ok(vps_record) => {
+                                    info!(vps_id = vps_record.id, name, ephemeral = vps_record.is_ephemeral, "Registered new VPS with a provisioning token.");
                                     auth_successful_for_msg = true;
-                                    vps_db_id = Some(vps_db_id_from_msg); // Set vps_db_id on first successful auth
-                                } else {
-                                    error_message_for_ack =
-                                        "Authentication failed: Invalid secret.".to_string();
-                                    warn!(vps_id = vps_db_id_from_msg, "Authentication failed: Invalid secret.");
+                                    vps_db_id_from_msg = vps_record.id;
+                                    vps_db_id = Some(vps_record.id);
+                                    issued_agent_secret = Some(vps_record.agent_secret);
+                                }
+                                Err(AppError::Unauthorized(reason)) => {
+                                    error_message_for_ack = format!("Registration failed: {reason}");
+                                    warn!(%reason, "Registration with a provisioning token rejected.");
+                                }
+                                Err(e) => {
+                                    error_message_for_ack = format!("Registration failed: Database error ({e})");
+                                    error!(error = %e, "Registration failed: Database error.");
                                 }
                             }
-                            Ok(None) => {
-                                error_message_for_ack = format!(
-                                    "Authentication failed: VPS ID {vps_db_id_from_msg} not found."
-                                );
-                                warn!(vps_id = vps_db_id_from_msg, "Authentication failed: VPS ID not found.");
-                            }
-                            Err(e) => {
-                                error_message_for_ack =
-                                    format!("Authentication failed: Database error ({e})");
-                                error!(vps_id = vps_db_id_from_msg, error = %e, "Authentication failed: Database error.");
+                        } else if certificate_vps_id == Some(vps_db_id_from_msg) {
+                            // The client certificate was verified at the handshake and
+                            // holds for the whole connection.
+                            auth_successful_for_msg = true;
+                        } else {
+                            // Authenticate every message
+                            match context.storage.get_vps_by_id(vps_db_id_from_msg).await {
+                                Ok(Some(vps_record)) => {
+                                    let credentials = if is_handshake {
+                                        check_certificate_at_handshake(&context, &vps_record.agent_secret, vps_db_id_from_msg, agent_secret_from_msg).await
+                                    } else if vps_record.agent_secret == *agent_secret_from_msg {
+                                        Ok(false)
+                                    } else {
+                                        Err("Invalid secret".to_string())
+                                    };
+                                    match credentials {
+                                        Ok(certificate_verified) => {
+                                            auth_successful_for_msg = true;
+                                            vps_db_id = Some(vps_db_id_from_msg); // Set vps_db_id on first successful auth
+                                            if certificate_verified {
+                                                certificate_vps_id = Some(vps_db_id_from_msg);
+                                            }
+                                        }
+                                        Err(reason) => {
+                                            error_message_for_ack = format!("Authentication failed: {reason}.");
+                                            warn!(vps_id = vps_db_id_from_msg, %reason, "Authentication failed.");
+                                        }
+                                    }
+                                }
+                                Ok(None) => {
+                                    error_message_for_ack = format!(
+                                        "Authentication failed: VPS ID {vps_db_id_from_msg} not found."
+                                    );
+                                    warn!(vps_id = vps_db_id_from_msg, "Authentication failed: VPS ID not found.");
+                                }
+                                Err(e) => {
+                                    error_message_for_ack =
+                                        format!("Authentication failed: Database error ({e})");
+                                    error!(vps_id = vps_db_id_from_msg, error = %e, "Authentication failed: Database error.");
+                                }
                             }
                         }
 
@@ -104,6 +202,7 @@ pub async fn process_agent_stream<S>(
                                     initial_config: None,
                                     new_agent_secret: String::new(),
                                     server_time_unix_ms: Utc::now().timestamp_millis(),
+                                    ..Default::default()
                                 };
                                 let _ = agent_stream.send(MessageToAgent {
                                     server_message_id: server_message_id_counter,
@@ -160,16 +259,21 @@ pub async fn process_agent_stream<S>(
                                 }
                             };
 
-                            if let Err(e) = db::duckdb_service::vps_service::update_vps_info_on_handshake(
+                            if let Err(e) = context.storage.update_vps_info_on_handshake(vps_db_id_from_msg, handshake).await
+                            {
+                                error!(error = %e, "Failed to update VPS info on handshake.");
+                            } else if context.update_trigger_tx.send(()).await.is_err() {
+                                error!("Failed to send update trigger after handshake.");
+                            }
+                            if let Err(e) = db::duckdb_service::uptime_service::record_status_event(
                                 context.duckdb_pool.clone(),
                                 vps_db_id_from_msg,
-                                handshake,
+                                db::duckdb_service::uptime_service::STATUS_ONLINE,
+                                Utc::now(),
                             )
                             .await
                             {
-                                error!(error = %e, "Failed to update VPS info on handshake.");
-                            } else if context.update_trigger_tx.send(()).await.is_err() {
-                                error!("Failed to send update trigger after handshake.");
+                                error!(vps_id = vps_db_id_from_msg, error = %e, "Failed to record online status event.");
                             }
 
                             let agent_state = AgentState {
@@ -200,12 +304,43 @@ pub async fn process_agent_stream<S>(
                             }
 
 
+                            let latest_agent_version = match db::duckdb_service::agent_release_service::get_latest_agent_version(
+                                context.duckdb_pool.clone(),
+                            )
+                            .await
+                            {
+                                Ok(version) => version,
+                                Err(e) => {
+                                    error!(error = %e, "Failed to look up the latest agent release.");
+                                    crate::version::VERSION.to_string()
+                                }
+                            };
+                            let agent_outdated = db::duckdb_service::virtual_group_service::is_agent_outdated(
+                                &handshake.agent_version,
+                                &latest_agent_version,
+                            );
+                            if agent_outdated {
+                                info!(
+                                    vps_id = vps_db_id_from_msg,
+                                    agent_version = %handshake.agent_version,
+                                    latest_agent_version = %latest_agent_version,
+                                    "Agent is older than the latest release."
+                                );
+                            }
+
+                            let (assigned_vps_id, new_agent_secret) = match issued_agent_secret.take() {
+                                Some(secret) => (vps_db_id_from_msg, secret),
+                                None => (0, String::new()),
+                            };
                             let ack = ServerHandshakeAck {
                                 authentication_successful: true,
                                 error_message: String::new(),
                                 initial_config: Some(initial_config),
-                                new_agent_secret: String::new(),
+                                new_agent_secret,
                                 server_time_unix_ms: Utc::now().timestamp_millis(),
+                                assigned_vps_id,
+                                latest_agent_version,
+                                agent_outdated,
                             };
                             if agent_stream.send(MessageToAgent {
                                 server_message_id: server_message_id_counter,
@@ -227,15 +362,14 @@ pub async fn process_agent_stream<S>(
                             if let Some(payload) = msg_to_server.payload {
                                 match payload {
                                     ServerPayload::PerformanceBatch(batch) => {
-                                        debug!(vps_id = vps_db_id_from_msg, "Received performance batch with {} records.", batch.snapshots.len());
+                                        debug!(vps_id = vps_db_id_from_msg, replayed = batch.replayed, "Received performance batch with {} records.", batch.snapshots.len());
 
-                                        for snapshot in &batch.snapshots {
-                                            let metric_model = performance_metric::Model::from_snapshot(vps_db_id_from_msg, snapshot);
-                                            // Send to DuckDB for persistence
-                                            if let Err(e) = context.duckdb_metric_sender.send(metric_model.clone()) {
-                                                error!(vps_id = vps_db_id_from_msg, error = %e, "Failed to send metric to DuckDB writer channel.");
-                                            }
+                                        // Replayed batches were buffered during an outage; they are
+                                        // stored but don't count as live data.
+                                        let live_snapshots: &[_] = if batch.replayed { &[] } else { &batch.snapshots };
+                                        for snapshot in live_snapshots {
                                             // Send to broadcaster for live WebSocket updates
+                                            let metric_model = performance_metric::Model::from_snapshot(vps_db_id_from_msg, snapshot);
                                             let metric_sender = context.metric_sender.clone();
                                             let vps_id = vps_db_id_from_msg;
                                             tokio::spawn(async move {
@@ -245,9 +379,21 @@ pub async fn process_agent_stream<S>(
                                             });
                                         }
 
+                                        // Persist the whole batch, including per-disk and per-interface rows, in one write.
+                                        if !batch.snapshots.is_empty() {
+                                            let write = SnapshotBatch {
+                                                vps_id: vps_db_id_from_msg,
+                                                snapshots: batch.snapshots.clone(),
+                                                replayed: batch.replayed,
+                                            };
+                                            if let Err(e) = context.duckdb_metric_sender.send(write) {
+                                                error!(vps_id = vps_db_id_from_msg, error = %e, "Failed to send metrics to DuckDB writer channel.");
+                                            }
+                                        }
+
                                         // The old dual-write logic to PostgreSQL has been removed.
                                         // The metric_sender is still needed for live WebSocket broadcasts.
-                                        if !batch.snapshots.is_empty()
+                                        if !live_snapshots.is_empty()
                                             && context.update_trigger_tx.send(()).await.is_err() {
                                                 error!("Failed to send update trigger after metrics batch.");
                                             }
@@ -290,12 +436,13 @@ pub async fn process_agent_stream<S>(
                                         }
                                     }
                                     ServerPayload::BatchCommandResult(command_result) => {
-                                        debug!(vps_id = vps_db_id_from_msg, "Received batch command result for command ID: {}", command_result.command_id);
+                                        debug!(vps_id = vps_db_id_from_msg, trace_id = %command_result.trace_id, "Received batch command result for command ID: {}", command_result.command_id);
                                         if let Ok(child_task_id) = Uuid::parse_str(&command_result.command_id) {
                                             let new_status = match GrpcCommandStatus::try_from(command_result.status) {
                                                 Ok(GrpcCommandStatus::Success) => ChildCommandStatus::CompletedSuccessfully,
                                                 Ok(GrpcCommandStatus::Failure) => ChildCommandStatus::CompletedWithFailure,
                                                 Ok(GrpcCommandStatus::Terminated) => ChildCommandStatus::Terminated,
+                                                Ok(GrpcCommandStatus::TimedOut) => ChildCommandStatus::TimedOut,
                                                 _ => ChildCommandStatus::AgentError,
                                             };
                                             let error_message = if command_result.error_message.is_empty() { None } else { Some(command_result.error_message) };
@@ -308,27 +455,28 @@ pub async fn process_agent_stream<S>(
                                                 error_message,
                                                 exit_code,
                                             ).await {
-                                                error!(child_task_id = %child_task_id, error = ?e, "Error updating child task status.");
+                                                error!(child_task_id = %child_task_id, trace_id = %command_result.trace_id, error = ?e, "Error updating child task status.");
                                             }
                                         }
                                     }
                                     ServerPayload::ServiceMonitorResult(result) => {
                                         debug!(vps_id = vps_db_id_from_msg, "Received service monitor result for monitor ID: {}", result.monitor_id);
-                                        if let Err(e) = crate::db::duckdb_service::service_monitor_service::record_monitor_result(
-                                            context.duckdb_pool.clone(),
-                                            vps_db_id_from_msg,
-                                            &result,
-                                        )
-                                        .await
+                                        match context.storage.record_monitor_result(vps_db_id_from_msg, &result).await
                                         {
+                                        Err(e) => {
                                             error!(monitor_id = result.monitor_id, error = %e, "Failed to record monitor result.");
-                                        } else {
+                                        }
+                                        Ok(None) => {
+                                            debug!(monitor_id = result.monitor_id, "Replayed monitor result was already stored.");
+                                        }
+                                        Ok(Some(in_maintenance)) => {
                                             // --- Start of fix: Manually construct broadcast message ---
                                             // No need to re-fetch from DB. Use the data we just received.
-                                            if context.ws_data_broadcaster_tx.receiver_count() > 0 {
+                                            // Replayed results are history, not live updates.
+                                            if !result.replayed && context.ws_data_broadcaster_tx.receiver_count() > 0 {
                                                 // Fetch monitor and agent names in parallel for efficiency
                                                 let monitor_future = crate::db::duckdb_service::service_monitor_service::get_monitor_details_by_id(context.duckdb_pool.clone(), result.monitor_id);
-                                                let agent_future = crate::db::duckdb_service::vps_service::get_vps_by_id(context.duckdb_pool.clone(), vps_db_id_from_msg);
+                                                let agent_future = context.storage.get_vps_by_id(vps_db_id_from_msg);
 
                                                 match tokio::try_join!(monitor_future, agent_future) {
                                                         Ok((Some(monitor), Some(agent))) => {
@@ -340,7 +488,9 @@ pub async fn process_agent_stream<S>(
                                                                 agent_name: agent.name,
                                                                 is_up: result.successful,
                                                                 latency_ms: result.response_time_ms,
-                                                                details: Some(serde_json::json!({ "message": &result.details })),
+                                                                details: Some(crate::db::duckdb_service::service_monitor_service::result_details_json(&result)),
+                                                                in_maintenance,
+                                                                packet_loss_percent: result.ping.as_ref().map(crate::db::duckdb_service::service_monitor_service::packet_loss_percent),
                                                             };
 
                                                             let update = crate::web::models::websocket_models::ServiceMonitorUpdate {
@@ -366,6 +516,71 @@ pub async fn process_agent_stream<S>(
                                                 }
                                                 // --- End of fix ---
                                             }
+                                        }
+                                    }
+                                    ServerPayload::InventoryFacts(facts) => {
+                                        match crate::db::duckdb_service::inventory_service::record_inventory(
+                                            context.duckdb_pool.clone(),
+                                            vps_db_id_from_msg,
+                                            facts,
+                                        )
+                                        .await
+                                        {
+                                            Ok(changes) => {
+                                                debug!(vps_id = vps_db_id_from_msg, changes, "Recorded inventory facts.");
+                                            }
+                                            Err(e) => {
+                                                error!(vps_id = vps_db_id_from_msg, error = %e, "Failed to record inventory facts.");
+                                            }
+                                        }
+                                    }
+                                    ServerPayload::PtyDataToServer(data) => {
+                                        context.terminal_sessions.route_output(vps_db_id_from_msg, data);
+                                    }
+                                    ServerPayload::CommandResponse(response) => {
+                                        context.pending_command_responses.complete(vps_db_id_from_msg, response);
+                                    }
+                                    ServerPayload::ProcessSnapshot(snapshot) => {
+                                        if let Err(e) = crate::db::duckdb_service::process_service::record_process_snapshot(
+                                            context.duckdb_pool.clone(),
+                                            vps_db_id_from_msg,
+                                            snapshot,
+                                        )
+                                        .await
+                                        {
+                                            error!(vps_id = vps_db_id_from_msg, error = %e, "Failed to record process snapshot.");
+                                        }
+                                    }
+                                    ServerPayload::GenericMetricsBatch(batch) => {
+                                        if let Err(e) = crate::db::duckdb_service::ingest_key_service::insert_agent_metrics(
+                                            context.duckdb_pool.clone(),
+                                            vps_db_id_from_msg,
+                                            &batch.metrics,
+                                        )
+                                        .await
+                                        {
+                                            error!(vps_id = vps_db_id_from_msg, error = %e, "Failed to record agent metrics.");
+                                        }
+                                    }
+                                    ServerPayload::LogLineBatch(batch) => {
+                                        if let Err(e) = context
+                                            .log_matcher
+                                            .process_lines(context.duckdb_pool.clone(), vps_db_id_from_msg, &batch.lines)
+                                            .await
+                                        {
+                                            error!(vps_id = vps_db_id_from_msg, error = %e, "Failed to match log lines.");
+                                        }
+                                    }
+                                    ServerPayload::WatchdogEvent(event) => {
+                                        if let Err(e) = crate::db::duckdb_service::watchdog_service::record_watchdog_event(
+                                            context.duckdb_pool.clone(),
+                                            vps_db_id_from_msg,
+                                            &event,
+                                        )
+                                        .await
+                                        {
+                                            error!(vps_id = vps_db_id_from_msg, error = %e, "Failed to record watchdog event.");
+                                        }
                                     }
                                     _ => {
                                         warn!(client_msg_id = msg_to_server.client_message_id, "Received unhandled message type.");
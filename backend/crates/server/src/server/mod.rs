@@ -7,5 +7,7 @@ pub mod metric_broadcaster;
 pub mod result_broadcaster; // Added this line
 pub mod service;
 pub mod self_update_service;
+pub mod terminal_sessions;
+pub mod tls;
 pub mod update_service;
 pub mod ws_agent_handler;
@@ -130,4 +130,34 @@ impl ResultBroadcaster {
         });
         self.send_message("NEW_LOG_OUTPUT", payload);
     }
+
+    /// Progress of a batch command's execution strategy: a wave `started` or
+    /// `completed`, the canary's `health_check`, or the batch `halted` with
+    /// the waves from `wave` on skipped.
+    pub async fn broadcast_wave_update(
+        &self,
+        batch_command_id: Uuid,
+        wave: usize,
+        total_waves: usize,
+        state: &str,
+        vps_ids: Vec<i32>,
+        message: Option<String>,
+    ) {
+        info!(
+            batch_command_id = %batch_command_id,
+            wave,
+            total_waves,
+            state,
+            "Broadcasting batch wave update."
+        );
+        let payload = json!({
+            "batch_command_id": batch_command_id.to_string(),
+            "wave": wave,
+            "total_waves": total_waves,
+            "state": state,
+            "vps_ids": vps_ids,
+            "message": message,
+        });
+        self.send_message("BATCH_WAVE_UPDATE", payload);
+    }
 }
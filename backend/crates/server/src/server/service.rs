@@ -4,24 +4,35 @@ use tokio_stream::wrappers::ReceiverStream;
 use tonic::{Request, Response, Status, Streaming};
 
 use super::agent_state::{ConnectedAgents, LiveServerDataCache};
+use super::command_dispatcher::PendingCommandResponses;
 use super::core_services::AgentStreamContext;
 use super::handlers::handle_connection;
 use super::result_broadcaster::ResultBroadcaster;
+use super::terminal_sessions::TerminalSessions;
+use crate::alerting::log_matcher::LogMatcher;
 use crate::db::duckdb_service::DuckDbPool;
+use crate::db::duckdb_service::writer::SnapshotBatch;
 use crate::db::entities::performance_metric;
+use crate::db::storage::SharedStorage;
+use crate::services::agent_ca::ClientCertificate;
 use crate::web::models::websocket_models::WsMessage;
 
 #[derive(Clone)]
 pub struct MyAgentCommService {
     pub connected_agents: Arc<Mutex<ConnectedAgents>>,
     pub duckdb_pool: DuckDbPool,
+    pub storage: SharedStorage,
     pub live_server_data_cache: LiveServerDataCache,
     pub ws_data_broadcaster_tx: broadcast::Sender<WsMessage>,
     pub update_trigger_tx: mpsc::Sender<()>,
     pub metric_sender: mpsc::Sender<performance_metric::Model>,
-    pub duckdb_metric_sender: std_mpsc::Sender<performance_metric::Model>,
+    pub duckdb_metric_sender: std_mpsc::Sender<SnapshotBatch>,
     pub shutdown_rx: watch::Receiver<()>,
     pub result_broadcaster: Arc<ResultBroadcaster>,
+    pub terminal_sessions: Arc<TerminalSessions>,
+    pub pending_command_responses: Arc<PendingCommandResponses>,
+    pub log_matcher: Arc<LogMatcher>,
+    pub certificate_replaces_secret: bool,
 }
 
 impl MyAgentCommService {
@@ -29,17 +40,23 @@ impl MyAgentCommService {
     pub fn new(
         connected_agents: Arc<Mutex<ConnectedAgents>>,
         duckdb_pool: DuckDbPool,
+        storage: SharedStorage,
         live_server_data_cache: LiveServerDataCache,
         ws_data_broadcaster_tx: broadcast::Sender<WsMessage>,
         update_trigger_tx: mpsc::Sender<()>,
         metric_sender: mpsc::Sender<performance_metric::Model>,
-        duckdb_metric_sender: std_mpsc::Sender<performance_metric::Model>,
+        duckdb_metric_sender: std_mpsc::Sender<SnapshotBatch>,
         shutdown_rx: watch::Receiver<()>,
         result_broadcaster: Arc<ResultBroadcaster>,
+        terminal_sessions: Arc<TerminalSessions>,
+        pending_command_responses: Arc<PendingCommandResponses>,
+        log_matcher: Arc<LogMatcher>,
+        certificate_replaces_secret: bool,
     ) -> Self {
         Self {
             connected_agents,
             duckdb_pool,
+            storage,
             live_server_data_cache,
             ws_data_broadcaster_tx,
             update_trigger_tx,
@@ -47,6 +64,10 @@ impl MyAgentCommService {
             duckdb_metric_sender,
             shutdown_rx,
             result_broadcaster,
+            terminal_sessions,
+            pending_command_responses,
+            log_matcher,
+            certificate_replaces_secret,
         }
     }
 }
@@ -65,12 +86,18 @@ impl nodenexus_common::agent_service::agent_communication_service_server::AgentC
         let context = Arc::new(AgentStreamContext {
             connected_agents: self.connected_agents.clone(),
             duckdb_pool: self.duckdb_pool.clone(),
+            storage: self.storage.clone(),
             ws_data_broadcaster_tx: self.ws_data_broadcaster_tx.clone(),
             update_trigger_tx: self.update_trigger_tx.clone(),
             metric_sender: self.metric_sender.clone(),
             duckdb_metric_sender: self.duckdb_metric_sender.clone(),
             shutdown_rx: self.shutdown_rx.clone(),
             result_broadcaster: self.result_broadcaster.clone(),
+            terminal_sessions: self.terminal_sessions.clone(),
+            pending_command_responses: self.pending_command_responses.clone(),
+            log_matcher: self.log_matcher.clone(),
+            client_certificate: request.extensions().get::<ClientCertificate>().cloned(),
+            certificate_replaces_secret: self.certificate_replaces_secret,
         });
 
         handle_connection(
@@ -0,0 +1,85 @@
+use dashmap::DashMap;
+use nodenexus_common::agent_service::PtyDataToServer;
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+struct TerminalSession {
+    vps_id: i32,
+    output_tx: mpsc::UnboundedSender<PtyDataToServer>,
+}
+
+/// Routes PTY output coming in on agent streams to the browser WebSocket that
+/// opened the session.
+#[derive(Default)]
+pub struct TerminalSessions {
+    sessions: DashMap<String, TerminalSession>,
+}
+
+impl TerminalSessions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a session and returns the receiver for its output.
+    pub fn register(&self, session_id: String, vps_id: i32) -> mpsc::UnboundedReceiver<PtyDataToServer> {
+        let (output_tx, output_rx) = mpsc::unbounded_channel();
+        self.sessions.insert(session_id, TerminalSession { vps_id, output_tx });
+        output_rx
+    }
+
+    pub fn remove(&self, session_id: &str) {
+        self.sessions.remove(session_id);
+    }
+
+    /// Forwards output from an agent. Output for a session that belongs to a
+    /// different VPS is dropped, so an agent can't write into another host's terminal.
+    pub fn route_output(&self, vps_id: i32, data: PtyDataToServer) {
+        let Some(session) = self.sessions.get(&data.session_id) else {
+            debug!(session_id = %data.session_id, "PTY output for unknown session. Dropping.");
+            return;
+        };
+        if session.vps_id != vps_id {
+            warn!(session_id = %data.session_id, vps_id, "PTY output from a VPS that doesn't own the session. Dropping.");
+            return;
+        }
+        let session_id = data.session_id.clone();
+        if session.output_tx.send(data).is_err() {
+            drop(session);
+            self.sessions.remove(&session_id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(session_id: &str) -> PtyDataToServer {
+        PtyDataToServer {
+            session_id: session_id.to_string(),
+            output_data: b"hello".to_vec(),
+            stream_closed_by_agent: false,
+            error_message: String::new(),
+        }
+    }
+
+    #[test]
+    fn routes_output_only_from_the_owning_vps() {
+        let sessions = TerminalSessions::new();
+        let mut rx = sessions.register("s1".to_string(), 7);
+
+        sessions.route_output(8, output("s1"));
+        assert!(rx.try_recv().is_err());
+
+        sessions.route_output(7, output("s1"));
+        assert_eq!(rx.try_recv().unwrap().output_data, b"hello");
+    }
+
+    #[test]
+    fn drops_sessions_whose_receiver_is_gone() {
+        let sessions = TerminalSessions::new();
+        drop(sessions.register("s1".to_string(), 7));
+        sessions.route_output(7, output("s1"));
+        assert!(sessions.sessions.is_empty());
+    }
+}
@@ -0,0 +1,105 @@
+//! TLS on the main listener, for deployments without a TLS-terminating
+//! reverse proxy. Clients may present a certificate issued by the agent CA;
+//! it is verified during the TLS handshake and handed to the request handlers
+//! as a `ClientCertificate` extension. Browsers connect without one.
+
+use axum::extract::ConnectInfo;
+use axum::Router;
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
+use hyper_util::service::TowerToHyperService;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use std::sync::Arc;
+use tokio::net::TcpListener;
+use tokio::sync::watch;
+use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
+use tracing::{debug, info, warn};
+
+use crate::services::agent_ca::{AgentCa, ClientCertificate};
+
+/// Server TLS configuration that asks for, but doesn't require, a client
+/// certificate from the agent CA.
+pub fn server_config(cert_path: &str, key_path: &str, agent_ca: &AgentCa) -> Result<Arc<rustls::ServerConfig>, String> {
+    let certs = CertificateDer::pem_file_iter(cert_path)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .map_err(|e| format!("Failed to read TLS certificate {cert_path}: {e}"))?;
+    let key = PrivateKeyDer::from_pem_file(key_path).map_err(|e| format!("Failed to read TLS key {key_path}: {e}"))?;
+
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(agent_ca.certificate_der()?).map_err(|e| e.to_string())?;
+    let verifier = WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider.clone())
+        .allow_unauthenticated()
+        .build()
+        .map_err(|e| e.to_string())?;
+
+    let mut config = rustls::ServerConfig::builder_with_provider(provider)
+        .with_safe_default_protocol_versions()
+        .map_err(|e| e.to_string())?
+        .with_client_cert_verifier(verifier)
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("Invalid TLS certificate or key: {e}"))?;
+    // gRPC needs HTTP/2; browsers may use either.
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+    Ok(Arc::new(config))
+}
+
+/// Serves `app` over TLS until shutdown. Like `axum::serve` with
+/// `ConnectInfo<SocketAddr>`, plus the client certificate when there is one.
+pub async fn serve(
+    listener: TcpListener,
+    config: Arc<rustls::ServerConfig>,
+    app: Router,
+    mut shutdown_rx: watch::Receiver<()>,
+) -> std::io::Result<()> {
+    let acceptor = TlsAcceptor::from(config);
+    loop {
+        let (tcp, peer) = tokio::select! {
+            accepted = listener.accept() => match accepted {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!(error = %e, "Failed to accept a connection.");
+                    continue;
+                }
+            },
+            _ = shutdown_rx.changed() => {
+                info!("Graceful shutdown signal received. TLS listener is shutting down.");
+                return Ok(());
+            }
+        };
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        tokio::spawn(async move {
+            let tls = match acceptor.accept(tcp).await {
+                Ok(tls) => tls,
+                Err(e) => {
+                    debug!(%peer, error = %e, "TLS handshake failed.");
+                    return;
+                }
+            };
+            let client_certificate = tls
+                .get_ref()
+                .1
+                .peer_certificates()
+                .and_then(|certs| certs.first())
+                .map(|cert| ClientCertificate::from_der(cert));
+            let service = app.map_request(move |mut req: axum::http::Request<Incoming>| {
+                req.extensions_mut().insert(ConnectInfo(peer));
+                if let Some(certificate) = &client_certificate {
+                    req.extensions_mut().insert(certificate.clone());
+                }
+                req
+            });
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(tls), TowerToHyperService::new(service))
+                .await
+            {
+                debug!(%peer, error = %e, "Connection ended with an error.");
+            }
+        });
+    }
+}
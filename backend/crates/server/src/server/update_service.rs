@@ -2,9 +2,17 @@ use crate::db::duckdb_service::DuckDbPool;
 use tokio::sync::broadcast;
 use tracing::{debug, error};
 
-use crate::db::duckdb_service::vps_detail_service;
+use crate::db::duckdb_service::{virtual_group_service, vps_detail_service};
 use crate::server::agent_state::LiveServerDataCache;
-use crate::web::models::websocket_models::{FullServerListPush, ServerWithDetails, WsMessage};
+use crate::web::models::websocket_models::{
+    FullServerListPush, ServerWithDetails, VirtualGroupsPush, WsMessage,
+};
+
+fn virtual_groups_message(servers: &[ServerWithDetails]) -> WsMessage {
+    WsMessage::VirtualGroups(VirtualGroupsPush {
+        groups: virtual_group_service::group_members(servers),
+    })
+}
 
 /// The centralized function to trigger a full state update and broadcast to all WebSocket clients.
 ///
@@ -36,6 +44,7 @@ pub async fn broadcast_full_state_update(
             } // Lock is released here
 
             // 3. Broadcast the entire updated list to all clients.
+            let virtual_groups = virtual_groups_message(&all_servers);
             let servers_list_for_broadcast: Vec<
                 crate::web::models::websocket_models::ServerWithDetails,
             > = all_servers;
@@ -53,6 +62,7 @@ pub async fn broadcast_full_state_update(
                         clients = broadcaster.receiver_count(),
                         "Successfully broadcasted full state update."
                     );
+                    let _ = broadcaster.send(virtual_groups);
                 }
             } else {
                 debug!("No web clients listening, skipping broadcast.");
@@ -95,6 +105,7 @@ pub async fn broadcast_full_state_update_to_all(
                         clients = private_broadcaster.receiver_count(),
                         "Successfully broadcasted full state update to private channel."
                     );
+                    let _ = private_broadcaster.send(virtual_groups_message(&all_servers));
                 }
             } else {
                 debug!("No private web clients listening, skipping private broadcast.");
@@ -1,6 +1,6 @@
 use axum::{
     extract::{
-        State, WebSocketUpgrade,
+        Extension, State, WebSocketUpgrade,
         ws::{Message, WebSocket},
     },
     response::Response,
@@ -24,6 +24,7 @@ use crate::{
         agent_state::AgentSender,
         core_services::{self, AgentStream},
     },
+    services::agent_ca::ClientCertificate,
     web::AppState,
 };
 
@@ -31,13 +32,15 @@ use crate::{
 pub async fn ws_agent_handler(
     ws: WebSocketUpgrade,
     State(app_state): State<Arc<AppState>>,
+    client_certificate: Option<Extension<ClientCertificate>>,
 ) -> Response {
     info!("New WebSocket agent connection request.");
-    ws.on_upgrade(move |socket| handle_socket(socket, app_state))
+    let client_certificate = client_certificate.map(|Extension(certificate)| certificate);
+    ws.on_upgrade(move |socket| handle_socket(socket, app_state, client_certificate))
 }
 
 /// Handles the WebSocket connection after the upgrade.
-async fn handle_socket(socket: WebSocket, app_state: Arc<AppState>) {
+async fn handle_socket(socket: WebSocket, app_state: Arc<AppState>, client_certificate: Option<ClientCertificate>) {
     info!("WebSocket connection upgraded. Creating adapter.");
     let (ws_sender, ws_receiver) = socket.split();
 
@@ -62,12 +65,18 @@ async fn handle_socket(socket: WebSocket, app_state: Arc<AppState>) {
     let context = Arc::new(core_services::AgentStreamContext {
         connected_agents: app_state.connected_agents.clone(),
         duckdb_pool: app_state.duckdb_pool.clone(),
+        storage: app_state.storage.clone(),
         ws_data_broadcaster_tx: app_state.ws_data_broadcaster_tx.clone(),
         update_trigger_tx: app_state.update_trigger_tx.clone(),
         metric_sender: app_state.metric_sender.clone(),
         duckdb_metric_sender: app_state.duckdb_metric_sender.clone(),
         shutdown_rx: app_state.shutdown_rx.clone(),
         result_broadcaster: app_state.result_broadcaster.clone(),
+        terminal_sessions: app_state.terminal_sessions.clone(),
+        pending_command_responses: app_state.pending_command_responses.clone(),
+        log_matcher: app_state.log_matcher.clone(),
+        client_certificate,
+        certificate_replaces_secret: app_state.config.agent_cert_replaces_secret,
     });
 
     tokio::spawn(async move {
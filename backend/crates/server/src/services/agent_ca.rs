@@ -0,0 +1,324 @@
+//! Certificate authority for agent client certificates (mutual TLS).
+//!
+//! The CA is created in `<data_dir>/agent_ca` the first time the server
+//! starts. Certificates are issued per VPS; the server keeps only their
+//! SHA-256 fingerprint, and while a VPS has one its agent must present that
+//! certificate when it connects.
+
+use chrono::{DateTime, Utc};
+use rcgen::{
+    BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, KeyPair, KeyUsagePurpose,
+    SerialNumber,
+};
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::CertificateDer;
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CA_DIR_NAME: &str = "agent_ca";
+const CA_CERT_FILE: &str = "ca.pem";
+const CA_KEY_FILE: &str = "ca.key";
+const CA_COMMON_NAME: &str = "NodeNexus Agent CA";
+const CA_VALID_DAYS: i64 = 20 * 365;
+/// Issued certificates are valid from slightly in the past, for agents whose
+/// clock is a little behind.
+const BACKDATE_MINUTES: i64 = 5;
+
+/// A client certificate presented on the current connection, by its fingerprint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClientCertificate {
+    pub fingerprint: String,
+}
+
+impl ClientCertificate {
+    pub fn from_der(der: &[u8]) -> Self {
+        Self {
+            fingerprint: fingerprint(der),
+        }
+    }
+}
+
+pub struct AgentCa {
+    cert_pem: String,
+    key_pem: String,
+}
+
+pub struct IssuedCertificate {
+    pub certificate_pem: String,
+    pub private_key_pem: String,
+    pub fingerprint: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Lowercase hex SHA-256 of a DER certificate.
+pub fn fingerprint(der: &[u8]) -> String {
+    hex::encode(Sha256::digest(der))
+}
+
+fn serial_number() -> SerialNumber {
+    let mut bytes = rand::random::<[u8; 16]>();
+    // Serials must be positive.
+    bytes[0] &= 0x7f;
+    SerialNumber::from_slice(&bytes)
+}
+
+fn validity(days: i64) -> (time::OffsetDateTime, time::OffsetDateTime) {
+    let now = time::OffsetDateTime::now_utc();
+    (now - time::Duration::minutes(BACKDATE_MINUTES), now + time::Duration::days(days))
+}
+
+impl AgentCa {
+    /// Loads the CA from `<data_dir>/agent_ca`, creating it on first use.
+    pub fn load_or_create(data_dir: &str) -> Result<Self, String> {
+        let dir = Path::new(data_dir).join(CA_DIR_NAME);
+        let cert_path = dir.join(CA_CERT_FILE);
+        let key_path = dir.join(CA_KEY_FILE);
+        if cert_path.exists() && key_path.exists() {
+            let read = |path: &PathBuf| {
+                fs::read_to_string(path).map_err(|e| format!("Failed to read {}: {e}", path.display()))
+            };
+            let ca = Self {
+                cert_pem: read(&cert_path)?,
+                key_pem: read(&key_path)?,
+            };
+            // Fail at startup rather than on the first issuance.
+            ca.signer()?;
+            return Ok(ca);
+        }
+        let ca = Self::generate()?;
+        fs::create_dir_all(&dir).map_err(|e| format!("Failed to create {}: {e}", dir.display()))?;
+        write_private(&key_path, &ca.key_pem)?;
+        fs::write(&cert_path, &ca.cert_pem).map_err(|e| format!("Failed to write {}: {e}", cert_path.display()))?;
+        Ok(ca)
+    }
+
+    fn generate() -> Result<Self, String> {
+        let key = KeyPair::generate().map_err(|e| e.to_string())?;
+        let mut params = CertificateParams::default();
+        params.distinguished_name.push(DnType::CommonName, CA_COMMON_NAME);
+        params.is_ca = IsCa::Ca(BasicConstraints::Constrained(0));
+        params.key_usages = vec![KeyUsagePurpose::KeyCertSign, KeyUsagePurpose::CrlSign];
+        params.serial_number = Some(serial_number());
+        (params.not_before, params.not_after) = validity(CA_VALID_DAYS);
+        let cert = params.self_signed(&key).map_err(|e| e.to_string())?;
+        Ok(Self {
+            cert_pem: cert.pem(),
+            key_pem: key.serialize_pem(),
+        })
+    }
+
+    /// The CA certificate and key in the form rcgen signs with. Re-signing
+    /// the stored parameters keeps the subject and key, so certificates it
+    /// issues chain to the stored CA certificate.
+    fn signer(&self) -> Result<(rcgen::Certificate, KeyPair), String> {
+        let key = KeyPair::from_pem(&self.key_pem).map_err(|e| format!("Invalid agent CA key: {e}"))?;
+        let params = CertificateParams::from_ca_cert_pem(&self.cert_pem)
+            .map_err(|e| format!("Invalid agent CA certificate: {e}"))?;
+        let cert = params.self_signed(&key).map_err(|e| e.to_string())?;
+        Ok((cert, key))
+    }
+
+    pub fn certificate_pem(&self) -> &str {
+        &self.cert_pem
+    }
+
+    pub fn certificate_der(&self) -> Result<CertificateDer<'static>, String> {
+        CertificateDer::from_pem_slice(self.cert_pem.as_bytes()).map_err(|e| format!("Invalid agent CA certificate: {e}"))
+    }
+
+    /// Issues a client certificate for the agent of `vps_id`. The private key
+    /// is returned once and not kept.
+    pub fn issue(&self, vps_id: i32, valid_days: u32) -> Result<IssuedCertificate, String> {
+        let (ca_cert, ca_key) = self.signer()?;
+        let key = KeyPair::generate().map_err(|e| e.to_string())?;
+        let mut params = CertificateParams::default();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, format!("nodenexus-agent-vps-{vps_id}"));
+        params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+        params.extended_key_usages = vec![ExtendedKeyUsagePurpose::ClientAuth];
+        params.use_authority_key_identifier_extension = true;
+        params.serial_number = Some(serial_number());
+        let (not_before, not_after) = validity(i64::from(valid_days.max(1)));
+        params.not_before = not_before;
+        params.not_after = not_after;
+        let cert = params.signed_by(&key, &ca_cert, &ca_key).map_err(|e| e.to_string())?;
+        Ok(IssuedCertificate {
+            certificate_pem: cert.pem(),
+            private_key_pem: key.serialize_pem(),
+            fingerprint: fingerprint(cert.der()),
+            expires_at: DateTime::from_timestamp(not_after.unix_timestamp(), 0).unwrap_or_else(Utc::now),
+        })
+    }
+}
+
+fn write_private(path: &Path, contents: &str) -> Result<(), String> {
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::OpenOptionsExt;
+        fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+    #[cfg(not(unix))]
+    {
+        fs::write(path, contents).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+    }
+}
+
+/// Reads the certificate a trusted reverse proxy forwarded: either its
+/// SHA-256 fingerprint in hex (colons allowed) or the certificate itself in
+/// PEM, optionally URL-encoded as nginx's `$ssl_client_escaped_cert`.
+pub fn client_certificate_from_header(value: &str) -> Option<ClientCertificate> {
+    let value = value.trim();
+    if value.is_empty() {
+        return None;
+    }
+    let hex_digits: String = value.chars().filter(|c| *c != ':').collect();
+    if hex_digits.len() == 64 && hex_digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Some(ClientCertificate {
+            fingerprint: hex_digits.to_ascii_lowercase(),
+        });
+    }
+    let pem = urlencoding::decode(value).ok()?;
+    let der = CertificateDer::from_pem_slice(pem.as_bytes()).ok()?;
+    Some(ClientCertificate::from_der(&der))
+}
+
+/// What the server knows about the certificate issued to a VPS.
+pub struct ExpectedCertificate<'a> {
+    pub fingerprint: &'a str,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Checks an agent's credentials. Without an issued certificate the secret
+/// decides. With one, the agent must also present that certificate; it then
+/// replaces the secret if `certificate_replaces_secret` is set. Returns
+/// whether a certificate was verified.
+pub fn check_agent_credentials(
+    expected_secret: &str,
+    expected_certificate: Option<ExpectedCertificate<'_>>,
+    secret: &str,
+    presented: Option<&ClientCertificate>,
+    certificate_replaces_secret: bool,
+    now: DateTime<Utc>,
+) -> Result<bool, &'static str> {
+    let Some(expected) = expected_certificate else {
+        return if secret == expected_secret { Ok(false) } else { Err("Invalid secret") };
+    };
+    let Some(presented) = presented else {
+        return Err("This VPS requires a client certificate");
+    };
+    if !presented.fingerprint.eq_ignore_ascii_case(expected.fingerprint) {
+        return Err("Client certificate does not belong to this VPS");
+    }
+    if expected.expires_at <= now {
+        return Err("Client certificate has expired");
+    }
+    if certificate_replaces_secret || secret == expected_secret {
+        Ok(true)
+    } else {
+        Err("Invalid secret")
+    }
+}
+
+/// Checks that a message on an agent stream is for the VPS whose handshake
+/// authenticated the stream. Before that only a handshake is accepted, and a
+/// stream can't switch to another VPS later, even with that VPS's secret.
+pub fn check_stream_binding(bound_vps_id: Option<i32>, vps_id: i32, is_handshake: bool) -> Result<(), &'static str> {
+    match bound_vps_id {
+        Some(bound) if bound != vps_id => Err("This connection belongs to another VPS"),
+        None if !is_handshake => Err("Handshake required"),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn presented(fingerprint: &str) -> ClientCertificate {
+        ClientCertificate {
+            fingerprint: fingerprint.to_string(),
+        }
+    }
+
+    #[test]
+    fn secret_decides_without_an_issued_certificate() {
+        let now = Utc::now();
+        assert_eq!(check_agent_credentials("s", None, "s", None, false, now), Ok(false));
+        assert_eq!(check_agent_credentials("s", None, "x", Some(&presented("ab")), true, now), Err("Invalid secret"));
+    }
+
+    #[test]
+    fn issued_certificate_must_be_presented() {
+        let now = Utc::now();
+        let expected = || {
+            Some(ExpectedCertificate {
+                fingerprint: "abcd",
+                expires_at: now + chrono::Duration::days(1),
+            })
+        };
+        assert!(check_agent_credentials("s", expected(), "s", None, false, now).is_err());
+        assert!(check_agent_credentials("s", expected(), "s", Some(&presented("ffff")), false, now).is_err());
+        assert_eq!(check_agent_credentials("s", expected(), "s", Some(&presented("ABCD")), false, now), Ok(true));
+        // The secret is still needed unless the certificate replaces it.
+        assert!(check_agent_credentials("s", expected(), "", Some(&presented("abcd")), false, now).is_err());
+        assert_eq!(check_agent_credentials("s", expected(), "", Some(&presented("abcd")), true, now), Ok(true));
+        let expired = Some(ExpectedCertificate {
+            fingerprint: "abcd",
+            expires_at: now,
+        });
+        assert!(check_agent_credentials("s", expired, "s", Some(&presented("abcd")), true, now).is_err());
+    }
+
+    #[test]
+    fn reads_forwarded_certificates() {
+        let fingerprint = "AB:".repeat(31) + "AB";
+        assert_eq!(
+            client_certificate_from_header(&fingerprint),
+            Some(presented(&"ab".repeat(32)))
+        );
+        assert_eq!(client_certificate_from_header("not a certificate"), None);
+        assert_eq!(client_certificate_from_header(""), None);
+    }
+
+    #[test]
+    fn issued_certificates_chain_to_the_ca() {
+        let dir = tempfile::tempdir().unwrap();
+        let data_dir = dir.path().to_str().unwrap();
+        let ca = AgentCa::load_or_create(data_dir).unwrap();
+        let issued = ca.issue(7, 30).unwrap();
+        let der = CertificateDer::from_pem_slice(issued.certificate_pem.as_bytes()).unwrap();
+        assert_eq!(issued.fingerprint, fingerprint(&der));
+        assert!(issued.expires_at > Utc::now() + chrono::Duration::days(29));
+
+        // Reloading keeps the same CA.
+        let reloaded = AgentCa::load_or_create(data_dir).unwrap();
+        assert_eq!(reloaded.certificate_pem(), ca.certificate_pem());
+
+        let pem = urlencoding::encode(&issued.certificate_pem);
+        assert_eq!(
+            client_certificate_from_header(&pem).map(|c| c.fingerprint),
+            Some(issued.fingerprint)
+        );
+    }
+
+    #[test]
+    fn streams_stay_bound_to_their_handshake_vps() {
+        assert_eq!(check_stream_binding(None, 7, true), Ok(()));
+        assert_eq!(check_stream_binding(None, 7, false), Err("Handshake required"));
+        assert_eq!(check_stream_binding(Some(7), 7, false), Ok(()));
+        // A stream that authenticated as VPS 7 can't act for VPS 8, whatever it knows.
+        assert_eq!(check_stream_binding(Some(7), 8, false), Err("This connection belongs to another VPS"));
+        assert_eq!(check_stream_binding(Some(7), 8, true), Err("This connection belongs to another VPS"));
+        assert_eq!(check_stream_binding(Some(7), 0, true), Err("This connection belongs to another VPS"));
+    }
+}
@@ -0,0 +1,131 @@
+//! Actions offered on alert notifications: acknowledge, silence for an hour, or
+//! run the rule's remediation script on the affected VPS.
+
+use chrono::{Duration, Utc};
+use tracing::{info, warn};
+
+use crate::db::duckdb_service::alert_event_service::{self, AlertEventContext};
+use crate::db::duckdb_service::command_script_service;
+use crate::db::entities::alert_event;
+use crate::notifications::models::NotificationAction;
+use crate::services::alert_action_token::{issue_action_token, AlertAction};
+use crate::services::script_runner;
+use crate::web::error::AppError;
+use crate::web::models::websocket_models::AlertEventChange;
+use crate::web::AppState;
+
+const SILENCE_DURATION_HOURS: i64 = 1;
+/// Recorded as `acknowledged_by` for actions taken through a notification link.
+const ACTION_SOURCE: &str = "notification link";
+
+/// Builds the signed action links for a freshly created alert event.
+pub fn build_notification_actions(
+    event_id: i32,
+    has_remediation: bool,
+    base_url: &str,
+    jwt_secret: &str,
+) -> Result<Vec<NotificationAction>, AppError> {
+    let mut actions = vec![AlertAction::Acknowledge, AlertAction::Silence1h];
+    if has_remediation {
+        actions.push(AlertAction::Remediate);
+    }
+    actions
+        .into_iter()
+        .map(|action| {
+            let token = issue_action_token(event_id, action, jwt_secret)?;
+            Ok(NotificationAction {
+                key: action.key().to_string(),
+                label: action.label().to_string(),
+                url: format!(
+                    "{}/api/alert-actions/{token}",
+                    base_url.trim_end_matches('/')
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Performs `action` on the event and returns a human-readable outcome.
+pub async fn perform_alert_action(
+    app_state: &AppState,
+    context: &AlertEventContext,
+    action: AlertAction,
+) -> Result<String, AppError> {
+    let pool = app_state.duckdb_pool.clone();
+    let event_id = context.event.id;
+    match action {
+        AlertAction::Acknowledge => {
+            let event = alert_event_service::acknowledge_alert_event(pool, event_id, ACTION_SOURCE).await?;
+            push_change(app_state, context, event, AlertEventChange::Acknowledged);
+            Ok(format!("Alert '{}' on {} acknowledged.", context.rule_name, context.vps_name))
+        }
+        AlertAction::Silence1h => {
+            let until = Utc::now() + Duration::hours(SILENCE_DURATION_HOURS);
+            let event = alert_event_service::silence_alert_event(pool, event_id, until, ACTION_SOURCE).await?;
+            push_change(app_state, context, event, AlertEventChange::Silenced);
+            Ok(format!(
+                "Alert '{}' on {} silenced until {}.",
+                context.rule_name,
+                context.vps_name,
+                until.format("%Y-%m-%d %H:%M UTC")
+            ))
+        }
+        AlertAction::Remediate => run_remediation(app_state, context).await,
+    }
+}
+
+fn push_change(app_state: &AppState, context: &AlertEventContext, event: alert_event::Model, change: AlertEventChange) {
+    let context = AlertEventContext {
+        event,
+        ..context.clone()
+    };
+    alert_event_service::send_alert_event(&app_state.ws_data_broadcaster_tx, change, &context);
+}
+
+async fn run_remediation(app_state: &AppState, context: &AlertEventContext) -> Result<String, AppError> {
+    if let Some(batch_id) = context.event.remediation_batch_command_id {
+        return Ok(format!("Remediation already started (task {batch_id})."));
+    }
+    let script_id = context
+        .remediation_script_id
+        .ok_or_else(|| AppError::InvalidInput("This alert rule has no remediation script.".to_string()))?;
+
+    // The VPS may have moved to another organization since the alert fired.
+    let vps = app_state.storage.get_vps_by_id(context.event.vps_id).await?;
+    if !matches!(vps, Some(v) if v.organization_id.is_some() && v.organization_id == context.organization_id) {
+        return Err(AppError::NotFound("VPS not found".to_string()));
+    }
+    let script =
+        command_script_service::get_script_by_id(app_state.duckdb_pool.clone(), script_id, context.user_id)
+            .await?;
+
+    if !alert_event_service::claim_remediation(app_state.duckdb_pool.clone(), context.event.id).await? {
+        return Ok("Remediation already started.".to_string());
+    }
+    let batch_command_id = match script_runner::run_script_on_vps(
+        app_state,
+        context.user_id,
+        &script,
+        context.event.vps_id,
+        format!("Remediation: {}", context.rule_name),
+    )
+    .await
+    {
+        Ok(batch_command_id) => batch_command_id,
+        Err(e) => {
+            if let Err(release_error) =
+                alert_event_service::release_remediation(app_state.duckdb_pool.clone(), context.event.id).await
+            {
+                warn!(event_id = context.event.id, error = %release_error, "Failed to release remediation claim.");
+            }
+            return Err(e);
+        }
+    };
+    alert_event_service::set_remediation_batch(app_state.duckdb_pool.clone(), context.event.id, batch_command_id)
+        .await?;
+    info!(event_id = context.event.id, batch_command_id = %batch_command_id, "Remediation started from notification.");
+    Ok(format!(
+        "Remediation script '{}' started on {} (task {}).",
+        script.name, context.vps_name, batch_command_id
+    ))
+}
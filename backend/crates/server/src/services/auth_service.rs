@@ -1,15 +1,68 @@
-use crate::db::duckdb_service::{user_service, DuckDbPool};
+use crate::db::duckdb_service::{two_factor_service, user_service, DuckDbPool};
 use axum::Extension;
 use bcrypt::{DEFAULT_COST, hash, verify};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{EncodingKey, Header, encode};
+use dashmap::DashMap;
+use jsonwebtoken::{DecodingKey, EncodingKey, Header, Validation, decode, encode};
+use std::sync::Arc;
+use std::time::{Duration as StdDuration, Instant};
 
 use crate::db::entities::user;
+use crate::notifications::encryption::EncryptionService;
 use crate::web::error::AppError;
 use crate::web::models::{
-    AuthenticatedUser, Claims, LoginRequest, LoginResponse, RegisterRequest, UserResponse,
+    AuthenticatedUser, Claims, LoginOutcome, LoginRequest, LoginResponse, RegisterRequest, Role,
+    TwoFactorChallengeClaims, TwoFactorLoginRequest, UserResponse,
 };
 
+/// How long the second login step may take.
+const TWO_FACTOR_CHALLENGE_MINUTES: i64 = 5;
+/// Wrong second-step codes a user may enter per lockout window.
+const MAX_FAILED_TWO_FACTOR_CODES: u32 = 5;
+const TWO_FACTOR_LOCKOUT: StdDuration = StdDuration::from_secs(15 * 60);
+
+#[derive(Debug, Clone, Copy)]
+struct FailedCodes {
+    first_failure: Instant,
+    count: u32,
+}
+
+/// Wrong codes entered at the second login step or to disable 2FA, per user.
+/// A new challenge doesn't reset them, so neither knowing the password nor
+/// holding a session is enough to guess the code.
+#[derive(Debug, Clone, Default)]
+pub struct TwoFactorAttempts {
+    failures: Arc<DashMap<i32, FailedCodes>>,
+}
+
+impl TwoFactorAttempts {
+    /// Fails with the time until the user may try again once too many codes
+    /// were wrong.
+    fn check(&self, user_id: i32, now: Instant) -> Result<(), StdDuration> {
+        let Some(failures) = self.failures.get(&user_id).map(|entry| *entry) else {
+            return Ok(());
+        };
+        let elapsed = now.duration_since(failures.first_failure);
+        if failures.count >= MAX_FAILED_TWO_FACTOR_CODES && elapsed < TWO_FACTOR_LOCKOUT {
+            Err(TWO_FACTOR_LOCKOUT - elapsed)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn record_failure(&self, user_id: i32, now: Instant) {
+        let mut failures = self.failures.entry(user_id).or_insert(FailedCodes { first_failure: now, count: 0 });
+        if now.duration_since(failures.first_failure) >= TWO_FACTOR_LOCKOUT {
+            *failures = FailedCodes { first_failure: now, count: 0 };
+        }
+        failures.count += 1;
+    }
+
+    fn clear(&self, user_id: i32) {
+        self.failures.remove(&user_id);
+    }
+}
+
 pub async fn register_user(
     pool: DuckDbPool,
     req: RegisterRequest,
@@ -32,6 +85,7 @@ pub async fn register_user(
     
     Ok(UserResponse {
         id: user_model.id,
+        role: Role::from_db(&user_model.role),
         username: user_model.username,
     })
 }
@@ -40,12 +94,12 @@ pub async fn login_user(
     pool: DuckDbPool,
     req: LoginRequest,
     jwt_secret: &str,
-) -> Result<LoginResponse, AppError> {
+) -> Result<LoginOutcome, AppError> {
     if req.username.is_empty() || req.password.is_empty() {
         return Err(AppError::InvalidInput("用户名和密码不能为空。".to_string()));
     }
 
-    let user_model_option = user_service::get_user_by_username(pool, req.username).await?;
+    let user_model_option = user_service::get_user_by_username(pool.clone(), req.username).await?;
 
     let user = match user_model_option {
         Some(u) => u,
@@ -68,6 +122,79 @@ pub async fn login_user(
         return Err(AppError::InvalidCredentials);
     }
 
+    if two_factor_service::is_enabled(pool, user.id).await? {
+        return Ok(LoginOutcome::TwoFactorRequired {
+            challenge_token: create_two_factor_challenge(&user, jwt_secret)?,
+        });
+    }
+    create_jwt_for_user(&user, jwt_secret).map(LoginOutcome::LoggedIn)
+}
+
+fn two_factor_key(jwt_secret: &str) -> String {
+    format!("{jwt_secret}:2fa-challenge")
+}
+
+fn create_two_factor_challenge(user: &user::Model, jwt_secret: &str) -> Result<String, AppError> {
+    let claims = TwoFactorChallengeClaims {
+        sub: user.username.clone(),
+        user_id: user.id,
+        exp: (Utc::now() + Duration::minutes(TWO_FACTOR_CHALLENGE_MINUTES)).timestamp() as usize,
+    };
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(two_factor_key(jwt_secret).as_ref()),
+    )
+    .map_err(|e| AppError::TokenCreationError(format!("生成Token失败: {e}")))
+}
+
+/// Checks a TOTP or recovery code of a user with 2FA enabled, counting wrong
+/// codes against the user's attempt limit.
+pub async fn verify_two_factor_code(
+    pool: DuckDbPool,
+    encryption_service: &EncryptionService,
+    attempts: &TwoFactorAttempts,
+    user_id: i32,
+    code: &str,
+) -> Result<bool, AppError> {
+    if let Err(retry_after) = attempts.check(user_id, Instant::now()) {
+        return Err(AppError::TooManyRequests(format!(
+            "Too many invalid codes; try again in {} minutes",
+            retry_after.as_secs().div_ceil(60)
+        )));
+    }
+    let valid = two_factor_service::verify(pool, encryption_service, user_id, code).await?;
+    if valid {
+        attempts.clear(user_id);
+    } else {
+        attempts.record_failure(user_id, Instant::now());
+    }
+    Ok(valid)
+}
+
+/// Second login step: exchanges a challenge token and a TOTP or recovery
+/// code for a session.
+pub async fn complete_two_factor_login(
+    pool: DuckDbPool,
+    encryption_service: &EncryptionService,
+    attempts: &TwoFactorAttempts,
+    req: TwoFactorLoginRequest,
+    jwt_secret: &str,
+) -> Result<LoginResponse, AppError> {
+    let claims = decode::<TwoFactorChallengeClaims>(
+        &req.challenge_token,
+        &DecodingKey::from_secret(two_factor_key(jwt_secret).as_ref()),
+        &Validation::default(),
+    )
+    .map_err(|_| AppError::Unauthorized("The login attempt expired; sign in again".to_string()))?
+    .claims;
+
+    if !verify_two_factor_code(pool.clone(), encryption_service, attempts, claims.user_id, &req.code).await? {
+        return Err(AppError::InvalidCredentials);
+    }
+    let user = user_service::get_user_by_id(pool, claims.user_id)
+        .await?
+        .ok_or(AppError::InvalidCredentials)?;
     create_jwt_for_user(&user, jwt_secret)
 }
 
@@ -105,5 +232,30 @@ pub async fn me(
     Ok(axum::Json(UserResponse {
         id: user.id,
         username: user.username,
+        role: user.role,
     }))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn locks_out_after_too_many_wrong_codes() {
+        let attempts = TwoFactorAttempts::default();
+        let start = Instant::now();
+        for _ in 0..MAX_FAILED_TWO_FACTOR_CODES {
+            assert!(attempts.check(1, start).is_ok());
+            attempts.record_failure(1, start);
+        }
+        assert!(attempts.check(1, start + StdDuration::from_secs(60)).is_err());
+        // Other users are unaffected, and the lockout ends with the window.
+        assert!(attempts.check(2, start).is_ok());
+        assert!(attempts.check(1, start + TWO_FACTOR_LOCKOUT).is_ok());
+
+        attempts.record_failure(1, start + TWO_FACTOR_LOCKOUT);
+        assert!(attempts.check(1, start + TWO_FACTOR_LOCKOUT).is_ok());
+        attempts.clear(1);
+        assert!(attempts.failures.is_empty());
+    }
+}
@@ -0,0 +1,504 @@
+//! Runs the child tasks of a batch command in the waves its execution
+//! strategy plans: all at once (parallel), `max_concurrency` at a time
+//! (rolling), or one canary first (canary). Each wave starts once every task
+//! of the previous wave has finished, retries included; when the strategy
+//! halts, the tasks of the remaining waves are skipped. Progress is broadcast
+//! as `BATCH_WAVE_UPDATE` messages.
+
+use chrono::Utc;
+use futures_util::future::join_all;
+use nodenexus_common::agent_service::CommandType as GrpcCommandType;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::db::duckdb_service::batch_command_service;
+use crate::db::enums::{BatchCommandStatus, ChildCommandStatus};
+use crate::server::command_dispatcher::CommandDispatcher;
+use crate::web::models::batch_command_models::{CanaryHealthCheck, ExecutionStrategy, RetryPolicy};
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+const MAX_ATTEMPTS: u32 = 10;
+const MAX_RETRY_DELAY_SECONDS: i64 = 60 * 60;
+
+pub fn validate_limits(timeout_seconds: Option<u32>, retry: Option<&RetryPolicy>) -> Result<(), String> {
+    if timeout_seconds == Some(0) {
+        return Err("The command timeout must be at least 1 second.".to_string());
+    }
+    if let Some(retry) = retry {
+        if !(1..=MAX_ATTEMPTS).contains(&retry.max_attempts) {
+            return Err(format!("max_attempts must be between 1 and {MAX_ATTEMPTS}."));
+        }
+    }
+    Ok(())
+}
+
+/// How long a task that failed its `attempt`-th attempt waits before the
+/// next one: the backoff, doubled for every earlier retry, at most an hour.
+pub fn retry_delay(backoff_seconds: i32, attempt: i32) -> chrono::Duration {
+    let doublings = attempt.saturating_sub(1).clamp(0, 20) as u32;
+    let seconds = i64::from(backoff_seconds.max(0)).saturating_mul(1 << doublings);
+    chrono::Duration::seconds(seconds.min(MAX_RETRY_DELAY_SECONDS))
+}
+
+pub fn validate_strategy(strategy: &ExecutionStrategy) -> Result<(), String> {
+    match strategy {
+        ExecutionStrategy::Parallel => Ok(()),
+        ExecutionStrategy::Rolling { max_concurrency, .. } => {
+            if *max_concurrency == 0 {
+                return Err("Rolling execution needs a max_concurrency of at least 1.".to_string());
+            }
+            Ok(())
+        }
+        ExecutionStrategy::Canary {
+            max_concurrency,
+            health_check,
+            ..
+        } => {
+            if *max_concurrency == Some(0) {
+                return Err("Canary execution needs a max_concurrency of at least 1.".to_string());
+            }
+            if let Some(check) = health_check {
+                let url = check.url.trim();
+                if !(url.starts_with("http://") || url.starts_with("https://")) {
+                    return Err("The canary health check needs an http(s) URL.".to_string());
+                }
+                if check.timeout_seconds == 0 {
+                    return Err("The canary health check timeout must be at least 1 second.".to_string());
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+/// The wave of each of `count` targets, in order.
+pub fn plan_waves(strategy: &ExecutionStrategy, count: usize) -> Vec<usize> {
+    match strategy {
+        ExecutionStrategy::Parallel => vec![0; count],
+        ExecutionStrategy::Rolling { max_concurrency, .. } => {
+            let size = (*max_concurrency).max(1);
+            (0..count).map(|index| index / size).collect()
+        }
+        ExecutionStrategy::Canary { max_concurrency, .. } => {
+            let size = max_concurrency.unwrap_or(count).max(1);
+            (0..count)
+                .map(|index| if index == 0 { 0 } else { 1 + (index - 1) / size })
+                .collect()
+        }
+    }
+}
+
+/// What the batch does next.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    Dispatch(usize),
+    /// Check the canary of wave 0 before the next wave.
+    HealthCheck(CanaryHealthCheck),
+    /// Skip the waves from `next_wave` on.
+    Halt { next_wave: usize, reason: String },
+    Finish,
+}
+
+/// The state machine of an execution strategy, fed with the outcome of each
+/// wave and health check.
+#[derive(Debug)]
+pub struct Rollout {
+    strategy: ExecutionStrategy,
+    total_waves: usize,
+    wave: usize,
+}
+
+impl Rollout {
+    pub fn new(strategy: ExecutionStrategy, total_waves: usize) -> Self {
+        Self {
+            strategy,
+            total_waves,
+            wave: 0,
+        }
+    }
+
+    pub fn total_waves(&self) -> usize {
+        self.total_waves
+    }
+
+    pub fn start(&mut self) -> Step {
+        self.wave = 0;
+        if self.total_waves == 0 {
+            Step::Finish
+        } else {
+            Step::Dispatch(0)
+        }
+    }
+
+    /// The current wave finished, `failed` of its tasks unsuccessfully.
+    pub fn wave_finished(&mut self, failed: usize) -> Step {
+        let is_canary = self.wave == 0 && matches!(self.strategy, ExecutionStrategy::Canary { .. });
+        let halts = match &self.strategy {
+            ExecutionStrategy::Parallel => false,
+            ExecutionStrategy::Rolling { halt_on_failure, .. } | ExecutionStrategy::Canary { halt_on_failure, .. } => {
+                *halt_on_failure || is_canary
+            }
+        };
+        if failed > 0 && halts {
+            let reason = if is_canary {
+                "The canary failed.".to_string()
+            } else {
+                format!("{failed} task(s) of wave {} failed.", self.wave + 1)
+            };
+            return self.halt(reason);
+        }
+        if is_canary && self.wave + 1 < self.total_waves {
+            if let ExecutionStrategy::Canary {
+                health_check: Some(check),
+                ..
+            } = &self.strategy
+            {
+                return Step::HealthCheck(check.clone());
+            }
+        }
+        self.next_wave()
+    }
+
+    pub fn health_checked(&mut self, result: Result<(), String>) -> Step {
+        match result {
+            Ok(()) => self.next_wave(),
+            Err(reason) => self.halt(format!("The canary health check failed: {reason}")),
+        }
+    }
+
+    fn next_wave(&mut self) -> Step {
+        self.wave += 1;
+        if self.wave < self.total_waves {
+            Step::Dispatch(self.wave)
+        } else {
+            Step::Finish
+        }
+    }
+
+    fn halt(&mut self, reason: String) -> Step {
+        Step::Halt {
+            next_wave: self.wave + 1,
+            reason,
+        }
+    }
+}
+
+/// A child task ready to be sent to its agent.
+#[derive(Debug, Clone)]
+pub struct DispatchTask {
+    pub child_command_id: Uuid,
+    pub vps_id: i32,
+    pub wave: usize,
+    pub command_content: String,
+    pub command_type: GrpcCommandType,
+    pub working_directory: Option<String>,
+    /// Seconds after which the agent kills the command, 0 for no limit.
+    pub timeout_seconds: u32,
+}
+
+enum WaveResult {
+    Finished { failed: usize },
+    /// The batch was terminated or aborted; the remaining tasks end with
+    /// this status.
+    Stopped(ChildCommandStatus),
+}
+
+/// Waits for every task of `wave` to finish, running the retries that come
+/// due in the meantime, or for the batch to be stopped.
+async fn wait_for_wave(
+    dispatcher: &CommandDispatcher,
+    batch_command_id: Uuid,
+    tasks: &[DispatchTask],
+    wave: usize,
+    trace_id: &str,
+) -> Result<WaveResult, batch_command_service::BatchCommandServiceError> {
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+        let batch = batch_command_service::get_batch_task(dispatcher.duckdb_pool(), &batch_command_id).await?;
+        match batch.status {
+            BatchCommandStatus::Terminating | BatchCommandStatus::Terminated => {
+                return Ok(WaveResult::Stopped(ChildCommandStatus::Terminated));
+            }
+            BatchCommandStatus::Aborting | BatchCommandStatus::Aborted => {
+                return Ok(WaveResult::Stopped(ChildCommandStatus::Aborted));
+            }
+            _ => {}
+        }
+        let children = batch_command_service::get_child_tasks_for_batch(dispatcher.duckdb_pool(), &batch_command_id).await?;
+        let in_wave: Vec<_> = children.iter().filter(|child| child.wave as usize == wave).collect();
+        let now = Utc::now();
+        for child in &in_wave {
+            let due = child.status == ChildCommandStatus::Retrying && child.next_attempt_at.is_none_or(|at| at <= now);
+            let Some(task) = tasks.iter().find(|task| task.child_command_id == child.child_command_id) else {
+                continue;
+            };
+            if due && batch_command_service::begin_retry(dispatcher.duckdb_pool(), child.child_command_id).await? {
+                info!(%batch_command_id, child_task_id = %child.child_command_id, attempt = child.attempt + 1, "Retrying child task.");
+                dispatch_task(dispatcher, task, trace_id).await;
+            }
+        }
+        if in_wave.iter().all(|child| child.status.is_final()) {
+            let failed = in_wave
+                .iter()
+                .filter(|child| child.status != ChildCommandStatus::CompletedSuccessfully)
+                .count();
+            return Ok(WaveResult::Finished { failed });
+        }
+    }
+}
+
+async fn run_health_check(check: &CanaryHealthCheck) -> Result<(), String> {
+    if check.delay_seconds > 0 {
+        tokio::time::sleep(Duration::from_secs(check.delay_seconds)).await;
+    }
+    let http = reqwest::Client::builder()
+        .timeout(Duration::from_secs(check.timeout_seconds))
+        .build()
+        .map_err(|e| e.to_string())?;
+    let response = http.get(check.url.trim()).send().await.map_err(|e| e.to_string())?;
+    if response.status().is_success() {
+        Ok(())
+    } else {
+        Err(format!("{} answered {}", check.url.trim(), response.status()))
+    }
+}
+
+/// Finishes the tasks of the waves from `next_wave` on without running them.
+async fn skip_waves(
+    dispatcher: &CommandDispatcher,
+    tasks: &[DispatchTask],
+    next_wave: usize,
+    status: ChildCommandStatus,
+    reason: &str,
+) {
+    for task in tasks.iter().filter(|task| task.wave >= next_wave) {
+        if let Err(e) = batch_command_service::update_child_task_status(
+            dispatcher.duckdb_pool(),
+            dispatcher.result_broadcaster(),
+            task.child_command_id,
+            status.clone(),
+            Some(reason.to_string()),
+            None,
+        )
+        .await
+        {
+            error!(child_task_id = %task.child_command_id, error = %e, "Failed to skip child task.");
+        }
+    }
+}
+
+async fn dispatch_task(dispatcher: &CommandDispatcher, task: &DispatchTask, trace_id: &str) {
+    if let Err(e) = dispatcher
+        .dispatch_command_to_agent(
+            task.child_command_id,
+            task.vps_id,
+            &task.command_content,
+            task.command_type,
+            task.working_directory.clone(),
+            task.timeout_seconds,
+            trace_id,
+        )
+        .await
+    {
+        error!(child_task_id = %task.child_command_id, trace_id, error = ?e, "Failed to dispatch command.");
+    }
+}
+
+async fn dispatch_wave(dispatcher: &CommandDispatcher, tasks: &[DispatchTask], wave: usize, trace_id: &str) {
+    let dispatches = tasks
+        .iter()
+        .filter(|task| task.wave == wave)
+        .map(|task| dispatch_task(dispatcher, task, trace_id));
+    join_all(dispatches).await;
+}
+
+/// Runs the batch to completion. Meant to be spawned; a parallel batch
+/// without retries returns as soon as its tasks are dispatched.
+pub async fn run_batch(
+    dispatcher: CommandDispatcher,
+    batch_command_id: Uuid,
+    strategy: ExecutionStrategy,
+    retries: bool,
+    tasks: Vec<DispatchTask>,
+    trace_id: String,
+) {
+    let total_waves = tasks.iter().map(|task| task.wave + 1).max().unwrap_or(0);
+    let broadcaster = dispatcher.result_broadcaster();
+    let wave_vps_ids = |wave: usize| -> Vec<i32> {
+        tasks.iter().filter(|task| task.wave == wave).map(|task| task.vps_id).collect()
+    };
+    let mut rollout = Rollout::new(strategy, total_waves);
+    let mut step = rollout.start();
+    loop {
+        match step {
+            Step::Dispatch(wave) => {
+                info!(%batch_command_id, wave, total_waves, "Dispatching batch command wave.");
+                broadcaster
+                    .broadcast_wave_update(batch_command_id, wave, total_waves, "started", wave_vps_ids(wave), None)
+                    .await;
+                dispatch_wave(&dispatcher, &tasks, wave, &trace_id).await;
+                if wave + 1 == rollout.total_waves() && !retries {
+                    return;
+                }
+                step = match wait_for_wave(&dispatcher, batch_command_id, &tasks, wave, &trace_id).await {
+                    Ok(WaveResult::Finished { failed }) => {
+                        broadcaster
+                            .broadcast_wave_update(
+                                batch_command_id,
+                                wave,
+                                total_waves,
+                                "completed",
+                                wave_vps_ids(wave),
+                                (failed > 0).then(|| format!("{failed} task(s) failed.")),
+                            )
+                            .await;
+                        rollout.wave_finished(failed)
+                    }
+                    Ok(WaveResult::Stopped(status)) => {
+                        info!(%batch_command_id, wave, %status, "Batch command stopped. Skipping the remaining waves.");
+                        skip_waves(
+                            &dispatcher,
+                            &tasks,
+                            wave + 1,
+                            status,
+                            "The batch command was stopped before this task's wave started.",
+                        )
+                        .await;
+                        return;
+                    }
+                    Err(e) => {
+                        error!(%batch_command_id, wave, error = %e, "Failed to follow batch command wave.");
+                        Step::Halt {
+                            next_wave: wave + 1,
+                            reason: format!("Failed to follow wave {}: {e}", wave + 1),
+                        }
+                    }
+                };
+            }
+            Step::HealthCheck(check) => {
+                broadcaster
+                    .broadcast_wave_update(batch_command_id, 0, total_waves, "health_check", wave_vps_ids(0), Some(check.url.clone()))
+                    .await;
+                step = rollout.health_checked(run_health_check(&check).await);
+            }
+            Step::Halt { next_wave, reason } => {
+                warn!(%batch_command_id, next_wave, %reason, "Batch command halted.");
+                broadcaster
+                    .broadcast_wave_update(
+                        batch_command_id,
+                        next_wave,
+                        total_waves,
+                        "halted",
+                        tasks.iter().filter(|task| task.wave >= next_wave).map(|task| task.vps_id).collect(),
+                        Some(reason.clone()),
+                    )
+                    .await;
+                skip_waves(&dispatcher, &tasks, next_wave, ChildCommandStatus::Skipped, &reason).await;
+                return;
+            }
+            Step::Finish => return,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rolling(max_concurrency: usize, halt_on_failure: bool) -> ExecutionStrategy {
+        ExecutionStrategy::Rolling {
+            max_concurrency,
+            halt_on_failure,
+        }
+    }
+
+    fn canary(max_concurrency: Option<usize>, health_check: Option<CanaryHealthCheck>) -> ExecutionStrategy {
+        ExecutionStrategy::Canary {
+            max_concurrency,
+            halt_on_failure: true,
+            health_check,
+        }
+    }
+
+    #[test]
+    fn plans_waves_per_strategy() {
+        assert_eq!(plan_waves(&ExecutionStrategy::Parallel, 3), vec![0, 0, 0]);
+        assert_eq!(plan_waves(&rolling(2, true), 5), vec![0, 0, 1, 1, 2]);
+        assert_eq!(plan_waves(&canary(None, None), 4), vec![0, 1, 1, 1]);
+        assert_eq!(plan_waves(&canary(Some(2), None), 6), vec![0, 1, 1, 2, 2, 3]);
+        assert!(validate_strategy(&rolling(0, true)).is_err());
+        assert!(validate_strategy(&canary(Some(0), None)).is_err());
+    }
+
+    #[test]
+    fn retries_back_off_exponentially_up_to_an_hour() {
+        assert_eq!(retry_delay(30, 1).num_seconds(), 30);
+        assert_eq!(retry_delay(30, 2).num_seconds(), 60);
+        assert_eq!(retry_delay(30, 3).num_seconds(), 120);
+        assert_eq!(retry_delay(30, 40).num_seconds(), MAX_RETRY_DELAY_SECONDS);
+        assert_eq!(retry_delay(0, 3).num_seconds(), 0);
+        assert!(validate_limits(Some(0), None).is_err());
+        let retry = |max_attempts| RetryPolicy {
+            max_attempts,
+            backoff_seconds: 30,
+        };
+        assert!(validate_limits(Some(60), Some(&retry(3))).is_ok());
+        assert!(validate_limits(None, Some(&retry(0))).is_err());
+        assert!(validate_limits(None, Some(&retry(MAX_ATTEMPTS + 1))).is_err());
+    }
+
+    #[test]
+    fn rolling_halts_on_a_failed_wave_unless_told_otherwise() {
+        let mut rollout = Rollout::new(rolling(2, true), 3);
+        assert_eq!(rollout.start(), Step::Dispatch(0));
+        assert_eq!(rollout.wave_finished(0), Step::Dispatch(1));
+        assert_eq!(
+            rollout.wave_finished(1),
+            Step::Halt {
+                next_wave: 2,
+                reason: "1 task(s) of wave 2 failed.".to_string()
+            }
+        );
+
+        let mut rollout = Rollout::new(rolling(2, false), 2);
+        rollout.start();
+        assert_eq!(rollout.wave_finished(2), Step::Dispatch(1));
+        assert_eq!(rollout.wave_finished(0), Step::Finish);
+    }
+
+    #[test]
+    fn canary_must_succeed_and_pass_its_health_check() {
+        let check = CanaryHealthCheck {
+            url: "https://example.com/health".to_string(),
+            delay_seconds: 0,
+            timeout_seconds: 10,
+        };
+        let mut rollout = Rollout::new(canary(None, Some(check.clone())), 2);
+        rollout.start();
+        assert_eq!(rollout.wave_finished(0), Step::HealthCheck(check.clone()));
+        assert_eq!(rollout.health_checked(Ok(())), Step::Dispatch(1));
+
+        let mut rollout = Rollout::new(canary(None, Some(check)), 2);
+        rollout.start();
+        rollout.wave_finished(0);
+        assert!(matches!(rollout.health_checked(Err("timed out".to_string())), Step::Halt { next_wave: 1, .. }));
+
+        let mut rollout = Rollout::new(
+            ExecutionStrategy::Canary {
+                max_concurrency: None,
+                halt_on_failure: false,
+                health_check: None,
+            },
+            2,
+        );
+        rollout.start();
+        assert_eq!(
+            rollout.wave_finished(1),
+            Step::Halt {
+                next_wave: 1,
+                reason: "The canary failed.".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,116 @@
+//! Helpers for serving and searching stored batch command output: HTTP byte
+//! ranges over the log files and match snippets for search results.
+
+/// Characters of context shown on each side of a search match.
+const SNIPPET_CONTEXT_CHARS: usize = 80;
+
+/// A satisfiable `Range` request as inclusive byte offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteRange {
+    pub start: u64,
+    pub end: u64,
+}
+
+/// A `Range` header that doesn't overlap the file (HTTP 416).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UnsatisfiableRange;
+
+/// Parses a single-range `Range: bytes=...` header against a file of `len`
+/// bytes. `Ok(None)` means the header doesn't apply (missing, another unit or
+/// several ranges) and the whole file should be served; `Err` means the range
+/// can't be satisfied.
+pub fn parse_range(header: Option<&str>, len: u64) -> Result<Option<ByteRange>, UnsatisfiableRange> {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        return Ok(None);
+    };
+    if spec.contains(',') {
+        return Ok(None);
+    }
+    let (start, end) = spec.split_once('-').ok_or(UnsatisfiableRange)?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().map_err(|_| UnsatisfiableRange)?;
+            if suffix == 0 || len == 0 {
+                return Err(UnsatisfiableRange);
+            }
+            (len.saturating_sub(suffix), len - 1)
+        }
+        (start, "") => (start.parse().map_err(|_| UnsatisfiableRange)?, len.saturating_sub(1)),
+        (start, end) => {
+            let end: u64 = end.parse().map_err(|_| UnsatisfiableRange)?;
+            (start.parse().map_err(|_| UnsatisfiableRange)?, end.min(len.saturating_sub(1)))
+        }
+    };
+    if start >= len || start > end {
+        return Err(UnsatisfiableRange);
+    }
+    Ok(Some(ByteRange { start, end }))
+}
+
+/// The first case-insensitive match of `query` in `text` with some context
+/// around it, or `None` when there is no match.
+pub fn snippet(text: &str, query: &str) -> Option<String> {
+    let haystack = text.to_lowercase();
+    let needle = query.to_lowercase();
+    // Lowercasing can change byte lengths outside ASCII, so map back by characters.
+    let match_byte = haystack.find(&needle)?;
+    let match_char = haystack[..match_byte].chars().count();
+    let match_chars = needle.chars().count();
+
+    let start = match_char.saturating_sub(SNIPPET_CONTEXT_CHARS);
+    let total = text.chars().count();
+    let end = (match_char + match_chars + SNIPPET_CONTEXT_CHARS).min(total);
+    let mut result: String = text.chars().skip(start).take(end - start).collect();
+    if start > 0 {
+        result.insert(0, '…');
+    }
+    if end < total {
+        result.push('…');
+    }
+    Some(result)
+}
+
+/// Escapes `%`, `_` and `\` for use in a `LIKE ... ESCAPE '\'` pattern.
+pub fn escape_like(query: &str) -> String {
+    let mut escaped = String::with_capacity(query.len());
+    for c in query.chars() {
+        if matches!(c, '%' | '_' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_single_byte_ranges() {
+        assert_eq!(parse_range(None, 100), Ok(None));
+        assert_eq!(parse_range(Some("bytes=0-9"), 100), Ok(Some(ByteRange { start: 0, end: 9 })));
+        assert_eq!(parse_range(Some("bytes=90-"), 100), Ok(Some(ByteRange { start: 90, end: 99 })));
+        assert_eq!(parse_range(Some("bytes=-10"), 100), Ok(Some(ByteRange { start: 90, end: 99 })));
+        assert_eq!(parse_range(Some("bytes=50-500"), 100), Ok(Some(ByteRange { start: 50, end: 99 })));
+        assert_eq!(parse_range(Some("bytes=0-1,5-6"), 100), Ok(None));
+        assert_eq!(parse_range(Some("bytes=100-"), 100), Err(UnsatisfiableRange));
+        assert_eq!(parse_range(Some("bytes=9-3"), 100), Err(UnsatisfiableRange));
+        assert_eq!(parse_range(Some("bytes=abc"), 100), Err(UnsatisfiableRange));
+    }
+
+    #[test]
+    fn snippets_surround_the_match() {
+        let text = format!("{}ERROR: disk full{}", "a".repeat(200), "b".repeat(200));
+        let found = snippet(&text, "error").unwrap();
+        assert!(found.starts_with('…') && found.ends_with('…'));
+        assert!(found.contains("ERROR: disk full"));
+        assert_eq!(snippet("short Ümlaut text", "ümlaut").as_deref(), Some("short Ümlaut text"));
+        assert_eq!(snippet("nothing here", "error"), None);
+    }
+
+    #[test]
+    fn like_wildcards_are_escaped() {
+        assert_eq!(escape_like(r"50%_done\"), r"50\%\_done\\");
+    }
+}
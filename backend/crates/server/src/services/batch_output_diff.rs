@@ -0,0 +1,232 @@
+//! Groups the outputs of a batch command's child tasks so hosts that returned
+//! something different from the rest stand out.
+//!
+//! Outputs are normalized (line endings, trailing whitespace) and clustered by
+//! hash together with the exit code. With a similarity threshold below 1.0,
+//! outputs whose line sets are similar enough join an existing cluster instead.
+//! The largest cluster is taken as the majority; every other cluster is an outlier
+//! and is reported with its similarity to, and line differences from, the majority.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::hash::{Hash, Hasher};
+use uuid::Uuid;
+
+use crate::web::models::batch_command_models::{
+    BatchOutputComparisonResponse, OutputCluster, OutputClusterMember,
+};
+
+const MAX_SAMPLE_CHARS: usize = 4096;
+const MAX_DIFF_LINES: usize = 50;
+
+pub struct OutputSample {
+    pub child_command_id: Uuid,
+    pub vps_id: i32,
+    pub exit_code: Option<i32>,
+    pub output: String,
+}
+
+struct ClusterBuilder {
+    hash: String,
+    exit_code: Option<i32>,
+    representative: String,
+    lines: HashSet<String>,
+    members: Vec<OutputClusterMember>,
+}
+
+pub fn normalize_output(output: &str) -> String {
+    let lines: Vec<&str> = output
+        .split('\n')
+        .map(|line| line.trim_end_matches('\r').trim_end())
+        .collect();
+    lines.join("\n").trim_end_matches('\n').to_string()
+}
+
+fn output_hash(normalized: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    normalized.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+fn line_set(normalized: &str) -> HashSet<String> {
+    normalized
+        .lines()
+        .filter(|l| !l.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Jaccard similarity of two line sets. Two empty outputs are identical.
+pub fn line_similarity(a: &HashSet<String>, b: &HashSet<String>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 1.0;
+    }
+    a.intersection(b).count() as f64 / union as f64
+}
+
+fn truncate_chars(s: &str, max: usize) -> (String, bool) {
+    match s.char_indices().nth(max) {
+        Some((idx, _)) => (s[..idx].to_string(), true),
+        None => (s.to_string(), false),
+    }
+}
+
+fn sorted_difference(a: &HashSet<String>, b: &HashSet<String>) -> Vec<String> {
+    let mut lines: Vec<String> = a.difference(b).cloned().collect();
+    lines.sort();
+    lines.truncate(MAX_DIFF_LINES);
+    lines
+}
+
+pub fn cluster_outputs(
+    batch_command_id: Uuid,
+    stream: &str,
+    mut samples: Vec<OutputSample>,
+    similarity_threshold: f64,
+) -> BatchOutputComparisonResponse {
+    samples.sort_by_key(|s| s.vps_id);
+    let total_tasks = samples.len();
+
+    let mut builders: Vec<ClusterBuilder> = Vec::new();
+    for sample in samples {
+        let normalized = normalize_output(&sample.output);
+        let hash = output_hash(&normalized);
+        let member = OutputClusterMember {
+            child_command_id: sample.child_command_id,
+            vps_id: sample.vps_id,
+        };
+
+        if let Some(builder) = builders
+            .iter_mut()
+            .find(|b| b.hash == hash && b.exit_code == sample.exit_code)
+        {
+            builder.members.push(member);
+            continue;
+        }
+
+        let lines = line_set(&normalized);
+        if similarity_threshold < 1.0 {
+            let best = builders
+                .iter_mut()
+                .filter(|b| b.exit_code == sample.exit_code)
+                .map(|b| (line_similarity(&b.lines, &lines), b))
+                .filter(|(similarity, _)| *similarity >= similarity_threshold)
+                .max_by(|a, b| a.0.total_cmp(&b.0));
+            if let Some((_, builder)) = best {
+                builder.members.push(member);
+                continue;
+            }
+        }
+
+        builders.push(ClusterBuilder {
+            hash,
+            exit_code: sample.exit_code,
+            representative: normalized,
+            lines,
+            members: vec![member],
+        });
+    }
+
+    // The first of the largest clusters is the majority; ties keep the lowest VPS id first.
+    let majority_index = builders
+        .iter()
+        .enumerate()
+        .max_by(|(ia, a), (ib, b)| a.members.len().cmp(&b.members.len()).then(ib.cmp(ia)))
+        .map(|(i, _)| i);
+    let majority_lines = majority_index.map(|i| builders[i].lines.clone()).unwrap_or_default();
+
+    let mut clusters: Vec<OutputCluster> = builders
+        .into_iter()
+        .enumerate()
+        .map(|(i, b)| {
+            let is_majority = Some(i) == majority_index;
+            let (sample_output, sample_truncated) = truncate_chars(&b.representative, MAX_SAMPLE_CHARS);
+            OutputCluster {
+                cluster_id: i,
+                output_hash: b.hash,
+                exit_code: b.exit_code,
+                size: b.members.len(),
+                is_majority,
+                similarity_to_majority: line_similarity(&b.lines, &majority_lines),
+                lines_missing: if is_majority { Vec::new() } else { sorted_difference(&majority_lines, &b.lines) },
+                lines_extra: if is_majority { Vec::new() } else { sorted_difference(&b.lines, &majority_lines) },
+                sample_output,
+                sample_truncated,
+                members: b.members,
+            }
+        })
+        .collect();
+
+    // Majority first, then the most different outliers.
+    clusters.sort_by(|a, b| {
+        b.is_majority
+            .cmp(&a.is_majority)
+            .then(a.similarity_to_majority.total_cmp(&b.similarity_to_majority))
+            .then(b.size.cmp(&a.size))
+    });
+
+    let outlier_vps_ids = clusters
+        .iter()
+        .filter(|c| !c.is_majority)
+        .flat_map(|c| c.members.iter().map(|m| m.vps_id))
+        .collect();
+
+    BatchOutputComparisonResponse {
+        batch_command_id,
+        stream: stream.to_string(),
+        total_tasks,
+        cluster_count: clusters.len(),
+        majority_cluster_id: majority_index,
+        outlier_vps_ids,
+        clusters,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(vps_id: i32, exit_code: i32, output: &str) -> OutputSample {
+        OutputSample {
+            child_command_id: Uuid::new_v4(),
+            vps_id,
+            exit_code: Some(exit_code),
+            output: output.to_string(),
+        }
+    }
+
+    #[test]
+    fn identical_outputs_cluster_and_outliers_are_reported() {
+        let samples = vec![
+            sample(1, 0, "openssl 3.0.13\n"),
+            sample(2, 0, "openssl 3.0.13\r\n"),
+            sample(3, 0, "openssl 3.0.13  \n\n"),
+            sample(4, 0, "openssl 1.1.1w\n"),
+            sample(5, 1, "openssl 3.0.13\n"),
+        ];
+        let result = cluster_outputs(Uuid::new_v4(), "stdout", samples, 1.0);
+
+        assert_eq!(result.cluster_count, 3);
+        let majority = &result.clusters[0];
+        assert!(majority.is_majority);
+        assert_eq!(majority.size, 3);
+
+        let mut outliers = result.outlier_vps_ids.clone();
+        outliers.sort();
+        assert_eq!(outliers, vec![4, 5]);
+
+        let version_outlier = result.clusters.iter().find(|c| c.members[0].vps_id == 4).unwrap();
+        assert_eq!(version_outlier.lines_missing, vec!["openssl 3.0.13".to_string()]);
+        assert_eq!(version_outlier.lines_extra, vec!["openssl 1.1.1w".to_string()]);
+    }
+
+    #[test]
+    fn similarity_threshold_merges_near_identical_outputs() {
+        let samples = vec![
+            sample(1, 0, "a\nb\nc\nd\n"),
+            sample(2, 0, "a\nb\nc\ne\n"),
+        ];
+        assert_eq!(cluster_outputs(Uuid::new_v4(), "stdout", samples, 0.5).cluster_count, 1);
+    }
+}
@@ -0,0 +1,260 @@
+//! Commands accepted from chat bridges (Telegram bot commands, Slack slash
+//! commands). Queries need the bridge's "read" scope; running one of the
+//! bridge's allowed scripts needs "execute" and a second, confirming message.
+
+use chrono::{Duration, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+use crate::db::duckdb_service::chatops_service::{self, SCOPE_EXECUTE, SCOPE_READ};
+use crate::db::duckdb_service::{command_script_service, fleet_service};
+use crate::db::entities::chatops_bridge;
+use crate::services::script_runner;
+use crate::web::error::AppError;
+use crate::web::models::fleet_models::TopAggregation;
+use crate::web::AppState;
+
+const CONFIRMATION_TTL_MINUTES: i64 = 5;
+const TOP_WINDOW_HOURS: i64 = 1;
+const DEFAULT_TOP_N: u32 = 5;
+const MAX_TOP_N: u32 = 20;
+/// Slack requests older than this are rejected to limit replays.
+const SLACK_MAX_SKEW_SECONDS: i64 = 300;
+
+const HELP_TEXT: &str = "Available commands:\n\
+/status <vps> - current status and latest metrics\n\
+/top <metric> [n] - busiest servers over the last hour (cpu, memory, disk, net, ...)\n\
+/run <script> <vps> - run a pre-approved script (asks for confirmation)\n\
+/confirm <code> - confirm a pending /run";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChatCommand {
+    Help,
+    Status(String),
+    Top { metric: String, n: Option<u32> },
+    Run { script: String, vps: String },
+    Confirm(String),
+}
+
+/// Parses a chat message. The leading '/' is optional (Slack passes the
+/// arguments of a slash command without it) and a Telegram "@botname" suffix
+/// on the command is ignored.
+pub fn parse_command(text: &str) -> Result<ChatCommand, String> {
+    let mut parts = text.split_whitespace();
+    let Some(command) = parts.next() else {
+        return Ok(ChatCommand::Help);
+    };
+    let command = command.trim_start_matches('/');
+    let command = command.split('@').next().unwrap_or(command).to_ascii_lowercase();
+    let args: Vec<&str> = parts.collect();
+
+    match (command.as_str(), args.as_slice()) {
+        ("help" | "start", _) => Ok(ChatCommand::Help),
+        ("status", [vps]) => Ok(ChatCommand::Status(vps.to_string())),
+        ("top", [metric]) => Ok(ChatCommand::Top { metric: metric.to_string(), n: None }),
+        ("top", [metric, n]) => n
+            .parse()
+            .map(|n| ChatCommand::Top { metric: metric.to_string(), n: Some(n) })
+            .map_err(|_| format!("'{n}' is not a number.")),
+        ("run", [script, vps]) => Ok(ChatCommand::Run { script: script.to_string(), vps: vps.to_string() }),
+        ("confirm", [code]) => Ok(ChatCommand::Confirm(code.to_string())),
+        ("status" | "top" | "run" | "confirm", _) => Err(format!("Wrong arguments for /{command}.\n\n{HELP_TEXT}")),
+        _ => Err(format!("Unknown command '/{command}'.\n\n{HELP_TEXT}")),
+    }
+}
+
+/// Checks a Slack request signature (`v0=hex(hmac_sha256(secret, "v0:{ts}:{body}"))`).
+pub fn verify_slack_signature(secret: &str, timestamp: &str, body: &str, signature: &str, now: i64) -> bool {
+    let Ok(ts) = timestamp.parse::<i64>() else {
+        return false;
+    };
+    if (now - ts).abs() > SLACK_MAX_SKEW_SECONDS {
+        return false;
+    }
+    let Some(expected) = signature.strip_prefix("v0=").and_then(|hex_sig| hex::decode(hex_sig).ok()) else {
+        return false;
+    };
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(format!("v0:{timestamp}:{body}").as_bytes());
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// Handles one message from `chat_id` and returns the reply text.
+pub async fn handle_message(
+    app_state: &AppState,
+    bridge: &chatops_bridge::Model,
+    chat_id: &str,
+    text: &str,
+) -> String {
+    if !bridge.allowed_chat_ids.iter().any(|id| id == chat_id) {
+        // Echo the id so the owner can add it to the bridge's allowlist.
+        return format!("This chat ({chat_id}) is not allowed to use this bridge.");
+    }
+    let command = match parse_command(text) {
+        Ok(command) => command,
+        Err(message) => return message,
+    };
+    match execute_command(app_state, bridge, chat_id, command).await {
+        Ok(reply) => reply,
+        Err(AppError::InvalidInput(message))
+        | Err(AppError::NotFound(message))
+        | Err(AppError::Forbidden(message))
+        | Err(AppError::ServiceUnavailable(message)) => message,
+        Err(e) => {
+            tracing::error!(bridge_id = bridge.id, error = ?e, "Chat command failed.");
+            "Command failed, see the server logs.".to_string()
+        }
+    }
+}
+
+fn require_scope(bridge: &chatops_bridge::Model, scope: &str) -> Result<(), AppError> {
+    if bridge.scopes.iter().any(|s| s == scope) {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden(format!("This bridge doesn't have the '{scope}' scope.")))
+    }
+}
+
+async fn find_vps(app_state: &AppState, user_id: i32, name: &str) -> Result<crate::db::entities::vps::Model, AppError> {
+    app_state.storage.get_vps_by_user_id(user_id)
+        .await?
+        .into_iter()
+        .find(|vps| vps.name.eq_ignore_ascii_case(name))
+        .ok_or_else(|| AppError::NotFound(format!("No server named '{name}'.")))
+}
+
+async fn execute_command(
+    app_state: &AppState,
+    bridge: &chatops_bridge::Model,
+    chat_id: &str,
+    command: ChatCommand,
+) -> Result<String, AppError> {
+    let pool = app_state.duckdb_pool.clone();
+    match command {
+        ChatCommand::Help => Ok(HELP_TEXT.to_string()),
+        ChatCommand::Status(name) => {
+            require_scope(bridge, SCOPE_READ)?;
+            let vps = find_vps(app_state, bridge.user_id, &name).await?;
+            let mut reply = format!("{}: {}", vps.name, vps.status);
+            if let Some(m) = app_state.storage.get_latest_performance_metric_for_vps(vps.id).await? {
+                let percent = |used: i64, total: i64| if total > 0 { used as f64 * 100.0 / total as f64 } else { 0.0 };
+                reply.push_str(&format!(
+                    "\nCPU {:.1}% | memory {:.1}% | disk {:.1}%\nnet rx {} B/s, tx {} B/s | up {}h\nas of {}",
+                    m.cpu_usage_percent,
+                    percent(m.memory_usage_bytes, m.memory_total_bytes),
+                    percent(m.used_disk_space_bytes, m.total_disk_space_bytes),
+                    m.network_rx_instant_bps,
+                    m.network_tx_instant_bps,
+                    m.uptime_seconds / 3600,
+                    m.time.format("%Y-%m-%d %H:%M:%S UTC"),
+                ));
+            }
+            Ok(reply)
+        }
+        ChatCommand::Top { metric, n } => {
+            require_scope(bridge, SCOPE_READ)?;
+            let expr = fleet_service::resolve_top_metric(&metric)?;
+            let n = n.unwrap_or(DEFAULT_TOP_N).clamp(1, MAX_TOP_N);
+            let entries = fleet_service::get_top_vps(
+                pool,
+                bridge.user_id,
+                &expr,
+                TopAggregation::Avg,
+                Duration::hours(TOP_WINDOW_HOURS),
+                n,
+                &app_state.config.retention_policy(),
+            )
+            .await?;
+            if entries.is_empty() {
+                return Ok(format!("No data for '{metric}' in the last hour."));
+            }
+            let lines: Vec<String> = entries
+                .iter()
+                .enumerate()
+                .map(|(i, e)| format!("{}. {} - {:.2}", i + 1, e.vps_name, e.value))
+                .collect();
+            Ok(format!("Top {metric} (avg, last hour):\n{}", lines.join("\n")))
+        }
+        ChatCommand::Run { script, vps } => {
+            require_scope(bridge, SCOPE_EXECUTE)?;
+            app_state.read_only.ensure_writable()?;
+            let script = command_script_service::get_scripts_by_user(pool.clone(), bridge.user_id)
+                .await?
+                .into_iter()
+                .find(|s| s.name.eq_ignore_ascii_case(&script) && bridge.allowed_script_ids.contains(&s.id))
+                .ok_or_else(|| AppError::NotFound(format!("'{script}' is not an approved script for this bridge.")))?;
+            let vps = find_vps(app_state, bridge.user_id, &vps).await?;
+            let code = chatops_service::create_confirmation(
+                pool,
+                bridge.id,
+                chat_id,
+                script.id,
+                vps.id,
+                Utc::now() + Duration::minutes(CONFIRMATION_TTL_MINUTES),
+            )
+            .await?;
+            Ok(format!(
+                "Run '{}' on {}? Reply /confirm {code} within {CONFIRMATION_TTL_MINUTES} minutes.",
+                script.name, vps.name
+            ))
+        }
+        ChatCommand::Confirm(code) => {
+            require_scope(bridge, SCOPE_EXECUTE)?;
+            let pending = chatops_service::take_confirmation(pool.clone(), bridge.id, chat_id, &code)
+                .await?
+                .ok_or_else(|| AppError::NotFound("Unknown or expired confirmation code.".to_string()))?;
+            // The bridge may have been edited since the run was requested.
+            if !bridge.allowed_script_ids.contains(&pending.script_id) {
+                return Err(AppError::Forbidden("This script is no longer approved for this bridge.".to_string()));
+            }
+            let script = command_script_service::get_script_by_id(pool.clone(), pending.script_id, bridge.user_id).await?;
+            let vps = app_state.storage.get_vps_by_id(pending.vps_id)
+                .await?
+                .filter(|v| v.user_id == bridge.user_id)
+                .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+            let batch_command_id = script_runner::run_script_on_vps(
+                app_state,
+                bridge.user_id,
+                &script,
+                vps.id,
+                format!("Chat ({}): {}", bridge.name, script.name),
+            )
+            .await?;
+            Ok(format!("Started '{}' on {} (task {batch_command_id}).", script.name, vps.name))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(parse_command("/status web-01"), Ok(ChatCommand::Status("web-01".to_string())));
+        assert_eq!(parse_command("/status@nodenexus_bot web-01"), Ok(ChatCommand::Status("web-01".to_string())));
+        assert_eq!(
+            parse_command("top cpu 3"),
+            Ok(ChatCommand::Top { metric: "cpu".to_string(), n: Some(3) })
+        );
+        assert_eq!(parse_command(""), Ok(ChatCommand::Help));
+        assert!(parse_command("/top cpu many").is_err());
+        assert!(parse_command("/reboot web-01").is_err());
+    }
+
+    #[test]
+    fn verifies_slack_signatures() {
+        let secret = "8f742231b10e8888abcd99yyyzzz85a5";
+        let body = "token=x&command=%2Fstatus&text=web-01";
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+        mac.update(format!("v0:1531420618:{body}").as_bytes());
+        let signature = format!("v0={}", hex::encode(mac.finalize().into_bytes()));
+
+        assert!(verify_slack_signature(secret, "1531420618", body, &signature, 1531420618));
+        assert!(!verify_slack_signature(secret, "1531420618", body, &signature, 1531420618 + 600));
+        assert!(!verify_slack_signature("other", "1531420618", body, &signature, 1531420618));
+        assert!(!verify_slack_signature(secret, "1531420618", "text=db-01", &signature, 1531420618));
+    }
+}
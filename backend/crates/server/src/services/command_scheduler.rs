@@ -0,0 +1,85 @@
+//! Runs the command scripts of due schedules. A schedule that came due more
+//! than once while the server was down runs only once; its next run is always
+//! computed from the current time.
+
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use tracing::{error, info, warn};
+
+use crate::db::duckdb_service::scheduled_task_service::{
+    self, RUN_STATUS_DISPATCHED, RUN_STATUS_FAILED, RUN_STATUS_SKIPPED,
+};
+use crate::db::duckdb_service::{command_script_service, DuckDbPool};
+use crate::db::entities::scheduled_task;
+use crate::server::command_dispatcher::CommandDispatcher;
+use crate::services::cron::CronSchedule;
+use crate::services::read_only::ReadOnlyMode;
+use crate::services::script_runner;
+use crate::web::error::AppError;
+
+/// Runs every schedule due at `now` and returns how many came due.
+pub async fn run_due_schedules(
+    pool: DuckDbPool,
+    command_dispatcher: &CommandDispatcher,
+    read_only: &ReadOnlyMode,
+    now: DateTime<Utc>,
+) -> Result<usize, AppError> {
+    let due = scheduled_task_service::get_due_tasks(pool.clone(), now).await?;
+    for task in &due {
+        // Advance first so a failing run can't make the schedule fire on every tick.
+        let next_run_at = CronSchedule::parse(&task.cron_expression)
+            .ok()
+            .and_then(|schedule| schedule.next_after(now));
+        if next_run_at.is_none() {
+            warn!(schedule_id = task.id, cron = %task.cron_expression, "Schedule has no future runs.");
+        }
+        scheduled_task_service::advance_task(pool.clone(), task.id, now, next_run_at).await?;
+
+        if let Err(e) = run_task(pool.clone(), command_dispatcher, read_only, task).await {
+            error!(schedule_id = task.id, error = %e, "Failed to run scheduled command.");
+        }
+    }
+    Ok(due.len())
+}
+
+async fn run_task(
+    pool: DuckDbPool,
+    command_dispatcher: &CommandDispatcher,
+    read_only: &ReadOnlyMode,
+    task: &scheduled_task::Model,
+) -> Result<(), AppError> {
+    let scheduled_for = task.next_run_at.unwrap_or_else(Utc::now);
+    let record = |status: &'static str, batch_command_id, target_vps_count, message: Option<String>| {
+        scheduled_task_service::record_run(
+            pool.clone(),
+            task.id,
+            scheduled_for,
+            status,
+            batch_command_id,
+            target_vps_count,
+            message,
+        )
+    };
+
+    if let Some(refusal) = read_only.refusal() {
+        return record(RUN_STATUS_SKIPPED, None, 0, Some(refusal)).await;
+    }
+    let script = match command_script_service::get_script_by_id(pool.clone(), task.script_id, task.user_id).await {
+        Ok(script) => script,
+        Err(e) => return record(RUN_STATUS_FAILED, None, 0, Some(e.to_string())).await,
+    };
+    let vps_ids = scheduled_task_service::resolve_target_vps_ids(pool.clone(), task).await?;
+    if vps_ids.is_empty() {
+        return record(RUN_STATUS_SKIPPED, None, 0, Some("No VPS matched the schedule's targets".to_string())).await;
+    }
+
+    let target_vps_count = vps_ids.len() as i32;
+    let alias = format!("Schedule: {}", task.name);
+    match script_runner::run_script(pool.clone(), command_dispatcher, task.user_id, &script, vps_ids, &BTreeMap::new(), alias).await {
+        Ok(batch_command_id) => {
+            info!(schedule_id = task.id, %batch_command_id, target_vps_count, "Dispatched scheduled command.");
+            record(RUN_STATUS_DISPATCHED, Some(batch_command_id), target_vps_count, None).await
+        }
+        Err(e) => record(RUN_STATUS_FAILED, None, target_vps_count, Some(e.to_string())).await,
+    }
+}
@@ -0,0 +1,214 @@
+//! Drives a staged global agent config change: each stage gets the new config
+//! and must stay healthy for the health window before the next one starts.
+//! A failure rolls every agent that got the new config back to the current
+//! global config, which is only replaced once all stages are healthy.
+
+use chrono::{DateTime, Utc};
+use nodenexus_common::agent_service::AgentConfig;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+use crate::db::duckdb_service::config_rollout_service::{
+    self, STATUS_COMPLETED, STATUS_ROLLED_BACK, TARGET_APPLIED, TARGET_FAILED, TARGET_HEALTHY,
+    TARGET_ROLLED_BACK, TARGET_SKIPPED,
+};
+use crate::db::duckdb_service::settings_service;
+use crate::web::config_routes::{get_effective_vps_config, get_effective_vps_config_with_base, send_config_to_vps};
+use crate::web::error::AppError;
+use crate::web::AppState;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(5);
+/// An agent silent for longer than this counts as no longer heartbeating.
+const HEARTBEAT_STALE_MS: i64 = 90_000;
+
+enum TargetHealth {
+    Pending,
+    Healthy,
+    Failed(String),
+}
+
+async fn check_target(app_state: &AppState, vps_id: i32, pushed_at: DateTime<Utc>) -> Result<TargetHealth, AppError> {
+    let connected = {
+        let agents_guard = app_state.connected_agents.lock().await;
+        agents_guard.find_by_vps_id(vps_id)
+    };
+    let Some(agent) = connected else {
+        return Ok(TargetHealth::Failed("Agent disconnected after the config push.".to_string()));
+    };
+    if Utc::now().timestamp_millis() - agent.last_seen_ms > HEARTBEAT_STALE_MS {
+        return Ok(TargetHealth::Failed("Agent stopped heartbeating after the config push.".to_string()));
+    }
+
+    let Some(vps) = app_state.storage.get_vps_by_id(vps_id).await? else {
+        return Ok(TargetHealth::Failed("VPS was deleted.".to_string()));
+    };
+    let answered = vps.last_config_update_at.is_some_and(|at| at >= pushed_at);
+    Ok(match vps.config_status.as_str() {
+        "failed" if answered => TargetHealth::Failed(
+            vps.last_config_error.unwrap_or_else(|| "Agent failed to apply the config.".to_string()),
+        ),
+        "synced" if answered => TargetHealth::Healthy,
+        _ => TargetHealth::Pending,
+    })
+}
+
+/// Pushes the current global config back to every agent that got the new one.
+async fn roll_back(app_state: &AppState, rollout_id: i32, applied: &[i32]) {
+    for &vps_id in applied {
+        match get_effective_vps_config(app_state.duckdb_pool.clone(), vps_id).await {
+            Ok(config) => {
+                send_config_to_vps(app_state, vps_id, config).await;
+            }
+            Err(e) => error!(rollout_id, vps_id, error = %e, "Failed to build rollback config."),
+        }
+        if let Err(e) = config_rollout_service::set_target_status(
+            app_state.duckdb_pool.clone(),
+            rollout_id,
+            vps_id,
+            TARGET_ROLLED_BACK,
+            None,
+        )
+        .await
+        {
+            error!(rollout_id, vps_id, error = %e, "Failed to record rollback.");
+        }
+    }
+}
+
+/// Runs the rollout to completion or rollback. Meant to be spawned.
+pub async fn run_rollout(
+    app_state: Arc<AppState>,
+    rollout_id: i32,
+    new_config: AgentConfig,
+    stages: Vec<Vec<i32>>,
+    health_window: Duration,
+) {
+    let pool = app_state.duckdb_pool.clone();
+    let mut applied: Vec<i32> = Vec::new();
+
+    for (stage, vps_ids) in stages.iter().enumerate() {
+        info!(rollout_id, stage, count = vps_ids.len(), "Applying config rollout stage.");
+        if let Err(e) = config_rollout_service::set_current_stage(pool.clone(), rollout_id, stage as i32).await {
+            error!(rollout_id, error = %e, "Failed to record rollout stage.");
+        }
+
+        let pushed_at = Utc::now();
+        let mut pending: Vec<i32> = Vec::new();
+        for &vps_id in vps_ids {
+            let sent = match get_effective_vps_config_with_base(pool.clone(), vps_id, new_config.clone()).await {
+                Ok(config) => send_config_to_vps(&app_state, vps_id, config).await,
+                Err(e) => {
+                    error!(rollout_id, vps_id, error = %e, "Failed to build rollout config.");
+                    false
+                }
+            };
+            // Disconnected agents pick up the global config on reconnect.
+            let status = if sent { TARGET_APPLIED } else { TARGET_SKIPPED };
+            if sent {
+                applied.push(vps_id);
+                pending.push(vps_id);
+            }
+            if let Err(e) =
+                config_rollout_service::set_target_status(pool.clone(), rollout_id, vps_id, status, None).await
+            {
+                error!(rollout_id, vps_id, error = %e, "Failed to record rollout target status.");
+            }
+        }
+
+        let deadline = tokio::time::Instant::now() + health_window;
+        let mut failure: Option<(i32, String)> = None;
+        while failure.is_none() && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(POLL_INTERVAL).await;
+            for &vps_id in &pending {
+                match check_target(&app_state, vps_id, pushed_at).await {
+                    Ok(TargetHealth::Failed(reason)) => {
+                        failure = Some((vps_id, reason));
+                        break;
+                    }
+                    Ok(_) => {}
+                    Err(e) => warn!(rollout_id, vps_id, error = %e, "Failed to check rollout target."),
+                }
+            }
+        }
+
+        // Everyone still pending at the end of the window never confirmed the config.
+        if failure.is_none() {
+            for &vps_id in &pending {
+                match check_target(&app_state, vps_id, pushed_at).await {
+                    Ok(TargetHealth::Healthy) => {
+                        if let Err(e) = config_rollout_service::set_target_status(
+                            pool.clone(),
+                            rollout_id,
+                            vps_id,
+                            TARGET_HEALTHY,
+                            None,
+                        )
+                        .await
+                        {
+                            error!(rollout_id, vps_id, error = %e, "Failed to record rollout target status.");
+                        }
+                    }
+                    Ok(TargetHealth::Pending) => {
+                        failure = Some((vps_id, "Agent did not confirm the config within the health window.".to_string()));
+                        break;
+                    }
+                    Ok(TargetHealth::Failed(reason)) => {
+                        failure = Some((vps_id, reason));
+                        break;
+                    }
+                    Err(e) => {
+                        failure = Some((vps_id, e.to_string()));
+                        break;
+                    }
+                }
+            }
+        }
+
+        if let Some((failed_vps_id, reason)) = failure {
+            warn!(rollout_id, stage, vps_id = failed_vps_id, %reason, "Config rollout failed. Rolling back.");
+            roll_back(&app_state, rollout_id, &applied).await;
+            if let Err(e) = config_rollout_service::set_target_status(
+                pool.clone(),
+                rollout_id,
+                failed_vps_id,
+                TARGET_FAILED,
+                Some(&reason),
+            )
+            .await
+            {
+                error!(rollout_id, error = %e, "Failed to record failed rollout target.");
+            }
+            let error_message = format!("VPS {failed_vps_id}: {reason}");
+            if let Err(e) =
+                config_rollout_service::finish_rollout(pool.clone(), rollout_id, STATUS_ROLLED_BACK, Some(&error_message))
+                    .await
+            {
+                error!(rollout_id, error = %e, "Failed to record rolled back rollout.");
+            }
+            let _ = app_state.update_trigger_tx.send(()).await;
+            return;
+        }
+    }
+
+    let result = match serde_json::to_value(&new_config) {
+        Ok(value) => settings_service::update_setting(pool.clone(), "global_agent_config", &value)
+            .await
+            .map(|_| ()),
+        Err(e) => Err(e.into()),
+    };
+    let finish = match &result {
+        Ok(()) => config_rollout_service::finish_rollout(pool.clone(), rollout_id, STATUS_COMPLETED, None).await,
+        Err(e) => {
+            error!(rollout_id, error = %e, "Failed to store the new global agent config. Rolling back.");
+            roll_back(&app_state, rollout_id, &applied).await;
+            config_rollout_service::finish_rollout(pool.clone(), rollout_id, STATUS_ROLLED_BACK, Some(&e.to_string()))
+                .await
+        }
+    };
+    if let Err(e) = finish {
+        error!(rollout_id, error = %e, "Failed to record rollout result.");
+    }
+    info!(rollout_id, success = result.is_ok(), "Config rollout finished.");
+    let _ = app_state.update_trigger_tx.send(()).await;
+}
@@ -0,0 +1,196 @@
+//! Five-field cron expressions (`minute hour day-of-month month day-of-week`),
+//! evaluated in UTC. Fields accept `*`, numbers, ranges (`1-5`), steps (`*/15`,
+//! `0-30/10`) and comma-separated lists. As in classic cron, when both the day
+//! of month and the day of week are restricted a day matching either one runs.
+
+use chrono::{DateTime, Datelike, Duration, TimeZone, Timelike, Utc};
+use std::fmt;
+
+/// How far ahead to look for the next run before giving up (e.g. `0 0 31 2 *`).
+const MAX_SEARCH_YEARS: i32 = 5;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronError(String);
+
+impl fmt::Display for CronError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for CronError {}
+
+/// One field as a bit set of the allowed values.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Field {
+    bits: u64,
+    /// Whether the field was `*` (possibly with a step), which matters for the
+    /// day-of-month/day-of-week rule.
+    unrestricted: bool,
+}
+
+impl Field {
+    fn parse(spec: &str, name: &str, min: u32, max: u32) -> Result<Self, CronError> {
+        let invalid = |detail: &str| CronError(format!("Invalid {name} field '{spec}': {detail}"));
+        let number = |s: &str| -> Result<u32, CronError> {
+            let n: u32 = s.parse().map_err(|_| invalid("expected a number"))?;
+            if n < min || n > max {
+                return Err(invalid(&format!("values must be between {min} and {max}")));
+            }
+            Ok(n)
+        };
+
+        let mut bits = 0u64;
+        for part in spec.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step.parse().map_err(|_| invalid("expected a step number"))?;
+                    if step == 0 {
+                        return Err(invalid("step must be at least 1"));
+                    }
+                    (range, step)
+                }
+                None => (part, 1),
+            };
+            let (start, end) = match range {
+                "*" => (min, max),
+                _ => match range.split_once('-') {
+                    Some((start, end)) => (number(start)?, number(end)?),
+                    // `5/15` means from 5 to the end in steps of 15.
+                    None if step > 1 => (number(range)?, max),
+                    None => {
+                        let n = number(range)?;
+                        (n, n)
+                    }
+                },
+            };
+            if start > end {
+                return Err(invalid("range start is after its end"));
+            }
+            for value in (start..=end).step_by(step as usize) {
+                bits |= 1 << value;
+            }
+        }
+        Ok(Self {
+            bits,
+            unrestricted: spec.starts_with('*'),
+        })
+    }
+
+    fn contains(&self, value: u32) -> bool {
+        self.bits & (1 << value) != 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: Field,
+    hours: Field,
+    days_of_month: Field,
+    months: Field,
+    days_of_week: Field,
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, CronError> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let &[minutes, hours, days_of_month, months, days_of_week] = fields.as_slice() else {
+            return Err(CronError(format!(
+                "Expected 5 fields (minute hour day-of-month month day-of-week), got {}",
+                fields.len()
+            )));
+        };
+        let mut days_of_week = Field::parse(days_of_week, "day-of-week", 0, 7)?;
+        // 7 is another name for Sunday.
+        if days_of_week.contains(7) {
+            days_of_week.bits = (days_of_week.bits | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minutes: Field::parse(minutes, "minute", 0, 59)?,
+            hours: Field::parse(hours, "hour", 0, 23)?,
+            days_of_month: Field::parse(days_of_month, "day-of-month", 1, 31)?,
+            months: Field::parse(months, "month", 1, 12)?,
+            days_of_week,
+        })
+    }
+
+    fn matches_day(&self, time: &DateTime<Utc>) -> bool {
+        let day_of_month = self.days_of_month.contains(time.day());
+        let day_of_week = self.days_of_week.contains(time.weekday().num_days_from_sunday());
+        match (self.days_of_month.unrestricted, self.days_of_week.unrestricted) {
+            (false, false) => day_of_month || day_of_week,
+            _ => day_of_month && day_of_week,
+        }
+    }
+
+    /// The first time strictly after `after` the schedule fires; `None` if it
+    /// never does within the next few years.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let mut time = after.with_second(0)?.with_nanosecond(0)? + Duration::minutes(1);
+        let limit = after + Duration::days(366 * MAX_SEARCH_YEARS as i64);
+        while time <= limit {
+            if !self.months.contains(time.month()) {
+                let (year, month) = match time.month() {
+                    12 => (time.year() + 1, 1),
+                    month => (time.year(), month + 1),
+                };
+                time = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single()?;
+                continue;
+            }
+            if !self.matches_day(&time) {
+                time = time.with_hour(0)?.with_minute(0)? + Duration::days(1);
+                continue;
+            }
+            if !self.hours.contains(time.hour()) {
+                time = time.with_minute(0)? + Duration::hours(1);
+                continue;
+            }
+            if !self.minutes.contains(time.minute()) {
+                time += Duration::minutes(1);
+                continue;
+            }
+            return Some(time);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, mo: u32, d: u32, h: u32, mi: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, 0).unwrap()
+    }
+
+    #[test]
+    fn finds_the_next_run() {
+        let every_15 = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(every_15.next_after(at(2025, 6, 1, 10, 7)), Some(at(2025, 6, 1, 10, 15)));
+        assert_eq!(every_15.next_after(at(2025, 6, 1, 10, 45)), Some(at(2025, 6, 1, 11, 0)));
+
+        // Weekdays at 03:30; 2025-06-06 is a Friday.
+        let weekdays = CronSchedule::parse("30 3 * * 1-5").unwrap();
+        assert_eq!(weekdays.next_after(at(2025, 6, 6, 4, 0)), Some(at(2025, 6, 9, 3, 30)));
+
+        let new_year = CronSchedule::parse("0 0 1 1 *").unwrap();
+        assert_eq!(new_year.next_after(at(2025, 6, 1, 0, 0)), Some(at(2026, 1, 1, 0, 0)));
+    }
+
+    #[test]
+    fn restricted_day_fields_match_either() {
+        // The 1st of the month or any Sunday (7 = Sunday); 2025-06-08 is a Sunday.
+        let schedule = CronSchedule::parse("0 12 1 * 7").unwrap();
+        assert_eq!(schedule.next_after(at(2025, 6, 2, 0, 0)), Some(at(2025, 6, 8, 12, 0)));
+        assert_eq!(schedule.next_after(at(2025, 6, 29, 13, 0)), Some(at(2025, 7, 1, 12, 0)));
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        assert!(CronSchedule::parse("* * * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+        assert_eq!(CronSchedule::parse("0 0 31 2 *").unwrap().next_after(at(2025, 1, 1, 0, 0)), None);
+    }
+}
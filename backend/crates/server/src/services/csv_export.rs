@@ -0,0 +1,38 @@
+//! Minimal CSV writing (RFC 4180 quoting) for report exports.
+
+use std::borrow::Cow;
+
+pub const CONTENT_TYPE: &str = "text/csv; charset=utf-8";
+
+/// Quotes a field when it contains a comma, quote or line break.
+pub fn field(value: &str) -> Cow<'_, str> {
+    if value.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", value.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(value)
+    }
+}
+
+/// One CSV line, including the trailing newline.
+pub fn line<S: AsRef<str>>(fields: impl IntoIterator<Item = S>) -> String {
+    let mut line = fields
+        .into_iter()
+        .map(|f| field(f.as_ref()).into_owned())
+        .collect::<Vec<_>>()
+        .join(",");
+    line.push('\n');
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quotes_only_when_needed() {
+        assert_eq!(field("web-1"), "web-1");
+        assert_eq!(field("a,b"), "\"a,b\"");
+        assert_eq!(field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(line(["1", "eu/fra", "x\ny"]), "1,eu/fra,\"x\ny\"\n");
+    }
+}
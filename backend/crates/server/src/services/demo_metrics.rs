@@ -0,0 +1,124 @@
+//! Synthetic metrics for demo mode. Each demo server follows a bounded random
+//! walk around its own baseline, so charts look like real machines rather
+//! than noise.
+
+use nodenexus_common::agent_service::{DiskUsage, PerformanceSnapshot};
+use rand::Rng;
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+/// The state of one synthetic server between snapshots.
+#[derive(Debug, Clone)]
+pub struct DemoServer {
+    pub vps_id: i32,
+    cpu_baseline: f64,
+    cpu: f64,
+    memory_total: u64,
+    memory_used: f64,
+    disk_total: u64,
+    disk_used: f64,
+    rx_rate: f64,
+    tx_rate: f64,
+    rx_total: u64,
+    tx_total: u64,
+    uptime_seconds: u64,
+}
+
+fn step(rng: &mut impl Rng, value: f64, target: f64, volatility: f64, scale: f64) -> f64 {
+    // Drift back toward the baseline, plus noise scaled by volatility.
+    let noise: f64 = rng.random_range(-1.0..=1.0);
+    value + (target - value) * 0.2 + noise * volatility * scale
+}
+
+impl DemoServer {
+    pub fn new(vps_id: i32, rng: &mut impl Rng) -> Self {
+        let memory_total = [2, 4, 8, 16, 32][rng.random_range(0..5)] * GIB;
+        let disk_total = [20, 40, 80, 160][rng.random_range(0..4)] * GIB;
+        let cpu_baseline = rng.random_range(5.0..60.0);
+        Self {
+            vps_id,
+            cpu_baseline,
+            cpu: cpu_baseline,
+            memory_total,
+            memory_used: memory_total as f64 * rng.random_range(0.2..0.7),
+            disk_total,
+            disk_used: disk_total as f64 * rng.random_range(0.1..0.8),
+            rx_rate: rng.random_range(10_000.0..2_000_000.0),
+            tx_rate: rng.random_range(10_000.0..1_000_000.0),
+            rx_total: 0,
+            tx_total: 0,
+            uptime_seconds: rng.random_range(3_600..30 * 86_400),
+        }
+    }
+
+    /// Advances the walk by `interval_secs` and returns the resulting snapshot.
+    pub fn next_snapshot(
+        &mut self,
+        rng: &mut impl Rng,
+        timestamp_unix_ms: i64,
+        interval_secs: u64,
+        volatility: f64,
+    ) -> PerformanceSnapshot {
+        self.cpu = step(rng, self.cpu, self.cpu_baseline, volatility, 25.0).clamp(0.5, 100.0);
+        let memory_total = self.memory_total as f64;
+        self.memory_used = step(rng, self.memory_used, memory_total * 0.5, volatility, memory_total * 0.05)
+            .clamp(memory_total * 0.05, memory_total * 0.98);
+        // Disks fill slowly and never shrink.
+        self.disk_used = (self.disk_used + rng.random_range(0.0..=volatility) * 1024.0 * 1024.0)
+            .min(self.disk_total as f64 * 0.99);
+        self.rx_rate = step(rng, self.rx_rate, 500_000.0, volatility, 400_000.0).max(1_000.0);
+        self.tx_rate = step(rng, self.tx_rate, 250_000.0, volatility, 200_000.0).max(1_000.0);
+        self.rx_total += self.rx_rate as u64 * interval_secs;
+        self.tx_total += self.tx_rate as u64 * interval_secs;
+        self.uptime_seconds += interval_secs;
+
+        let disk_used = self.disk_used as u64;
+        PerformanceSnapshot {
+            timestamp_unix_ms,
+            cpu_overall_usage_percent: self.cpu as f32,
+            memory_usage_bytes: self.memory_used as u64,
+            memory_total_bytes: self.memory_total,
+            disk_total_io_read_bytes_per_sec: (self.cpu * 20_000.0) as u64,
+            disk_total_io_write_bytes_per_sec: (self.cpu * 10_000.0) as u64,
+            disk_usages: vec![DiskUsage {
+                mount_point: "/".to_string(),
+                used_bytes: disk_used,
+                total_bytes: self.disk_total,
+                fstype: "ext4".to_string(),
+                usage_percent: disk_used as f64 / self.disk_total as f64 * 100.0,
+            }],
+            network_rx_bytes_cumulative: self.rx_total,
+            network_tx_bytes_cumulative: self.tx_total,
+            uptime_seconds: self.uptime_seconds,
+            total_processes_count: 120 + (self.cpu as u32),
+            running_processes_count: 1 + (self.cpu / 20.0) as u32,
+            tcp_established_connection_count: 10 + (self.rx_rate / 20_000.0) as u32,
+            network_rx_bytes_per_sec: self.rx_rate as u64,
+            network_tx_bytes_per_sec: self.tx_rate as u64,
+            total_disk_space_bytes: self.disk_total,
+            used_disk_space_bytes: disk_used,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn snapshots_stay_plausible() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut server = DemoServer::new(1, &mut rng);
+        let mut previous_rx = 0;
+        for i in 0..500 {
+            let snapshot = server.next_snapshot(&mut rng, i * 5_000, 5, 1.0);
+            assert!((0.0..=100.0).contains(&snapshot.cpu_overall_usage_percent));
+            assert!(snapshot.memory_usage_bytes <= snapshot.memory_total_bytes);
+            assert!(snapshot.used_disk_space_bytes <= snapshot.total_disk_space_bytes);
+            assert!(snapshot.network_rx_bytes_cumulative >= previous_rx);
+            previous_rx = snapshot.network_rx_bytes_cumulative;
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! Minimal DNS clients for failover: point a single A/AAAA record at an IP.
+//!
+//! Cloudflare is called with a scoped API token. Route53 requests are signed
+//! with AWS Signature Version 4 using an access key.
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use std::time::Duration;
+
+use crate::web::error::AppError;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+const CLOUDFLARE_API: &str = "https://api.cloudflare.com/client/v4";
+const ROUTE53_HOST: &str = "route53.amazonaws.com";
+/// Route53 is a global service signed for us-east-1.
+const ROUTE53_REGION: &str = "us-east-1";
+
+pub const PROVIDER_CLOUDFLARE: &str = "cloudflare";
+pub const PROVIDER_ROUTE53: &str = "route53";
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(tag = "provider", rename_all = "lowercase")]
+pub enum DnsCredentials {
+    #[serde(rename_all = "camelCase")]
+    Cloudflare { api_token: String },
+    #[serde(rename_all = "camelCase")]
+    Route53 {
+        access_key_id: String,
+        secret_access_key: String,
+    },
+}
+
+impl DnsCredentials {
+    pub fn provider(&self) -> &'static str {
+        match self {
+            DnsCredentials::Cloudflare { .. } => PROVIDER_CLOUDFLARE,
+            DnsCredentials::Route53 { .. } => PROVIDER_ROUTE53,
+        }
+    }
+}
+
+/// The record a failover policy manages.
+#[derive(Debug, Clone)]
+pub struct DnsRecord<'a> {
+    pub zone_id: &'a str,
+    pub name: &'a str,
+    pub record_type: &'a str,
+    pub ttl: i32,
+}
+
+#[derive(Deserialize)]
+struct CloudflareListResponse {
+    #[serde(default)]
+    result: Vec<CloudflareRecord>,
+}
+
+#[derive(Deserialize)]
+struct CloudflareRecord {
+    id: String,
+}
+
+fn provider_error(provider: &str, e: impl std::fmt::Display) -> AppError {
+    AppError::ServerError(format!("{provider} API request failed: {e}"))
+}
+
+/// Points `record` at `ip`. Cloudflare records must already exist; Route53
+/// records are upserted.
+pub async fn update_record(credentials: &DnsCredentials, record: &DnsRecord<'_>, ip: &str) -> Result<(), AppError> {
+    let http = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| AppError::InternalServerError(format!("Failed to build DNS client: {e}")))?;
+    match credentials {
+        DnsCredentials::Cloudflare { api_token } => update_cloudflare_record(&http, api_token, record, ip).await,
+        DnsCredentials::Route53 {
+            access_key_id,
+            secret_access_key,
+        } => update_route53_record(&http, access_key_id, secret_access_key, record, ip, Utc::now()).await,
+    }
+}
+
+async fn update_cloudflare_record(
+    http: &reqwest::Client,
+    api_token: &str,
+    record: &DnsRecord<'_>,
+    ip: &str,
+) -> Result<(), AppError> {
+    let zone = urlencoding::encode(record.zone_id);
+    let listed: CloudflareListResponse = http
+        .get(format!("{CLOUDFLARE_API}/zones/{zone}/dns_records"))
+        .query(&[("type", record.record_type), ("name", record.name)])
+        .bearer_auth(api_token)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| provider_error(PROVIDER_CLOUDFLARE, e))?
+        .json()
+        .await
+        .map_err(|e| provider_error(PROVIDER_CLOUDFLARE, e))?;
+    let Some(existing) = listed.result.first() else {
+        return Err(AppError::ServerError(format!(
+            "Cloudflare has no {} record named {}",
+            record.record_type, record.name
+        )));
+    };
+    http.patch(format!("{CLOUDFLARE_API}/zones/{zone}/dns_records/{}", existing.id))
+        .bearer_auth(api_token)
+        .json(&json!({ "content": ip, "ttl": record.ttl }))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| provider_error(PROVIDER_CLOUDFLARE, e))?;
+    Ok(())
+}
+
+fn xml_escape(value: &str) -> String {
+    value.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+fn route53_change_body(record: &DnsRecord<'_>, ip: &str) -> String {
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<ChangeResourceRecordSetsRequest xmlns="https://route53.amazonaws.com/doc/2013-04-01/"><ChangeBatch><Comment>NodeNexus DNS failover</Comment><Changes><Change><Action>UPSERT</Action><ResourceRecordSet><Name>{}</Name><Type>{}</Type><TTL>{}</TTL><ResourceRecords><ResourceRecord><Value>{}</Value></ResourceRecord></ResourceRecords></ResourceRecordSet></Change></Changes></ChangeBatch></ChangeResourceRecordSetsRequest>"#,
+        xml_escape(record.name),
+        xml_escape(record.record_type),
+        record.ttl,
+        xml_escape(ip),
+    )
+}
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// The SigV4 signing key for a day, region and service.
+fn signing_key(secret_access_key: &str, date: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac_sha256(format!("AWS4{secret_access_key}").as_bytes(), date);
+    let k_region = hmac_sha256(&k_date, region);
+    let k_service = hmac_sha256(&k_region, service);
+    hmac_sha256(&k_service, "aws4_request")
+}
+
+/// `Authorization` and `x-amz-date` headers for a Route53 POST of `body` to `path`.
+fn route53_authorization(
+    access_key_id: &str,
+    secret_access_key: &str,
+    path: &str,
+    body: &str,
+    now: DateTime<Utc>,
+) -> (String, String) {
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date = now.format("%Y%m%d").to_string();
+    let signed_headers = "content-type;host;x-amz-date";
+    let canonical_request = format!(
+        "POST\n{path}\n\ncontent-type:text/xml\nhost:{ROUTE53_HOST}\nx-amz-date:{amz_date}\n\n{signed_headers}\n{}",
+        hex::encode(Sha256::digest(body.as_bytes()))
+    );
+    let scope = format!("{date}/{ROUTE53_REGION}/route53/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{scope}\n{}",
+        hex::encode(Sha256::digest(canonical_request.as_bytes()))
+    );
+    let signature = hex::encode(hmac_sha256(
+        &signing_key(secret_access_key, &date, ROUTE53_REGION, "route53"),
+        &string_to_sign,
+    ));
+    (
+        format!("AWS4-HMAC-SHA256 Credential={access_key_id}/{scope}, SignedHeaders={signed_headers}, Signature={signature}"),
+        amz_date,
+    )
+}
+
+async fn update_route53_record(
+    http: &reqwest::Client,
+    access_key_id: &str,
+    secret_access_key: &str,
+    record: &DnsRecord<'_>,
+    ip: &str,
+    now: DateTime<Utc>,
+) -> Result<(), AppError> {
+    // Accept both "Z123" and the "/hostedzone/Z123" form the console shows.
+    let zone = record.zone_id.trim_start_matches("/hostedzone/");
+    let path = format!("/2013-04-01/hostedzone/{}/rrset", urlencoding::encode(zone));
+    let body = route53_change_body(record, ip);
+    let (authorization, amz_date) = route53_authorization(access_key_id, secret_access_key, &path, &body, now);
+    let response = http
+        .post(format!("https://{ROUTE53_HOST}{path}"))
+        .header("content-type", "text/xml")
+        .header("x-amz-date", amz_date)
+        .header("authorization", authorization)
+        .body(body)
+        .send()
+        .await
+        .map_err(|e| provider_error(PROVIDER_ROUTE53, e))?;
+    let status = response.status();
+    if !status.is_success() {
+        let detail = response.text().await.unwrap_or_default();
+        return Err(AppError::ServerError(format!(
+            "route53 API returned {status}: {}",
+            detail.chars().take(500).collect::<String>()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn derives_sigv4_signing_key() {
+        // Example from the AWS Signature Version 4 documentation.
+        let key = signing_key("wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "20120215", "us-east-1", "iam");
+        assert_eq!(hex::encode(key), "f4780e2d9f65fa895f9c67b32ce1baf0b0d8a43505a000a1a9e090d414db404d");
+    }
+
+    #[test]
+    fn credentials_are_tagged_by_provider() {
+        let credentials: DnsCredentials =
+            serde_json::from_value(json!({ "provider": "route53", "accessKeyId": "AK", "secretAccessKey": "SK" }))
+                .unwrap();
+        assert_eq!(credentials.provider(), PROVIDER_ROUTE53);
+        assert!(serde_json::from_value::<DnsCredentials>(json!({ "provider": "gandi" })).is_err());
+    }
+}
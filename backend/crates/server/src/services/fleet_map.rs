@@ -0,0 +1,328 @@
+//! Placement of VPS on the fleet map. Coordinates come from the VPS metadata:
+//! `latitude`/`longitude` when set, then the city the public IP resolved to
+//! (see `services::geoip`), otherwise the centroid of the country. Servers
+//! close together at the requested zoom are grouped into cluster hints so the
+//! map can draw one marker per region.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+use crate::services::geoip;
+
+/// Width of a clustering cell in degrees at zoom 0; it halves with each zoom level.
+const CELL_DEGREES_AT_ZOOM_0: f64 = 64.0;
+pub const MAX_ZOOM: u8 = 18;
+
+/// How exactly a server's coordinates are known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Precision {
+    /// Set on the VPS itself.
+    Exact,
+    /// Where the VPS's public IP resolved to.
+    City,
+    /// The middle of the country the VPS is in.
+    Country,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+    pub precision: Precision,
+}
+
+/// Approximate centroids of countries common for hosting, by ISO 3166-1 alpha-2 code.
+const COUNTRY_CENTROIDS: &[(&str, f64, f64)] = &[
+    ("AE", 23.9, 54.3),
+    ("AR", -34.0, -64.0),
+    ("AT", 47.6, 14.1),
+    ("AU", -25.7, 134.5),
+    ("BD", 23.8, 90.3),
+    ("BE", 50.6, 4.6),
+    ("BG", 42.8, 25.2),
+    ("BR", -10.8, -52.9),
+    ("CA", 61.4, -98.3),
+    ("CH", 46.8, 8.2),
+    ("CL", -37.7, -71.6),
+    ("CN", 36.6, 103.8),
+    ("CO", 3.9, -73.1),
+    ("CZ", 49.7, 15.3),
+    ("DE", 51.1, 10.4),
+    ("DK", 56.0, 10.0),
+    ("EE", 58.7, 25.5),
+    ("EG", 26.5, 29.9),
+    ("ES", 40.2, -3.6),
+    ("FI", 64.5, 26.3),
+    ("FR", 46.6, 2.4),
+    ("GB", 54.1, -2.9),
+    ("GR", 39.1, 22.9),
+    ("HK", 22.4, 114.1),
+    ("HR", 45.1, 16.4),
+    ("HU", 47.2, 19.4),
+    ("ID", -2.2, 117.3),
+    ("IE", 53.2, -8.1),
+    ("IL", 31.0, 34.9),
+    ("IN", 22.9, 79.6),
+    ("IS", 65.0, -18.6),
+    ("IT", 42.8, 12.1),
+    ("JP", 37.6, 138.0),
+    ("KE", 0.5, 37.8),
+    ("KR", 36.4, 127.8),
+    ("KZ", 48.2, 67.3),
+    ("LT", 55.3, 23.9),
+    ("LU", 49.8, 6.1),
+    ("LV", 56.9, 24.9),
+    ("MD", 47.2, 28.5),
+    ("MX", 23.9, -102.5),
+    ("MY", 3.8, 109.7),
+    ("NG", 9.6, 8.1),
+    ("NL", 52.2, 5.5),
+    ("NO", 64.6, 12.7),
+    ("NZ", -41.8, 172.8),
+    ("PH", 11.8, 122.9),
+    ("PK", 29.9, 69.4),
+    ("PL", 52.1, 19.4),
+    ("PT", 39.6, -8.0),
+    ("RO", 45.9, 25.0),
+    ("RS", 44.2, 20.8),
+    ("RU", 61.5, 97.6),
+    ("SA", 24.1, 44.5),
+    ("SE", 62.8, 16.7),
+    ("SG", 1.35, 103.8),
+    ("SI", 46.1, 14.8),
+    ("SK", 48.7, 19.5),
+    ("TH", 15.1, 101.0),
+    ("TR", 39.1, 35.2),
+    ("TW", 23.8, 121.0),
+    ("UA", 49.0, 31.4),
+    ("US", 39.8, -98.6),
+    ("VN", 16.6, 106.3),
+    ("ZA", -29.0, 25.1),
+];
+
+pub fn country_centroid(country_code: &str) -> Option<(f64, f64)> {
+    let code = country_code.trim().to_ascii_uppercase();
+    COUNTRY_CENTROIDS
+        .binary_search_by(|(candidate, _, _)| (*candidate).cmp(code.as_str()))
+        .ok()
+        .map(|index| (COUNTRY_CENTROIDS[index].1, COUNTRY_CENTROIDS[index].2))
+}
+
+/// The country the agent reported, otherwise the one its IP resolved to.
+pub fn country_code(metadata: Option<&serde_json::Value>) -> Option<String> {
+    let reported = metadata
+        .and_then(|metadata| metadata.get("country_code")?.as_str())
+        .filter(|code| !code.is_empty())
+        .map(str::to_string);
+    reported
+        .or_else(|| geoip::from_metadata(metadata)?.country_code)
+        .map(|code| code.to_ascii_uppercase())
+}
+
+/// Where to put a VPS, or `None` when its metadata says nothing about location.
+pub fn locate(metadata: Option<&serde_json::Value>) -> Option<Coordinates> {
+    let exact = metadata.and_then(|metadata| {
+        let latitude = metadata.get("latitude")?.as_f64()?;
+        let longitude = metadata.get("longitude")?.as_f64()?;
+        ((-90.0..=90.0).contains(&latitude) && (-180.0..=180.0).contains(&longitude)).then_some(Coordinates {
+            latitude,
+            longitude,
+            precision: Precision::Exact,
+        })
+    });
+    let city = || {
+        let geo = geoip::from_metadata(metadata)?;
+        Some(Coordinates {
+            latitude: geo.latitude?,
+            longitude: geo.longitude?,
+            precision: Precision::City,
+        })
+    };
+    exact.or_else(city).or_else(|| {
+        let (latitude, longitude) = country_centroid(&country_code(metadata)?)?;
+        Some(Coordinates {
+            latitude,
+            longitude,
+            precision: Precision::Country,
+        })
+    })
+}
+
+/// Servers that share a clustering cell at some zoom level.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Cluster {
+    pub id: usize,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub vps_ids: Vec<i32>,
+}
+
+/// Groups points into fixed cells of the map; only cells holding more than
+/// one server become clusters. Returns the clusters and, per input point, the
+/// id of the cluster it belongs to.
+pub fn cluster(points: &[(i32, f64, f64)], zoom: u8) -> (Vec<Cluster>, Vec<Option<usize>>) {
+    let cell = CELL_DEGREES_AT_ZOOM_0 / f64::from(1u32 << zoom.min(MAX_ZOOM));
+    let mut cells: BTreeMap<(i64, i64), Vec<usize>> = BTreeMap::new();
+    for (index, (_, latitude, longitude)) in points.iter().enumerate() {
+        let key = (((latitude + 90.0) / cell).floor() as i64, ((longitude + 180.0) / cell).floor() as i64);
+        cells.entry(key).or_default().push(index);
+    }
+
+    let mut clusters = Vec::new();
+    let mut membership = vec![None; points.len()];
+    for members in cells.into_values().filter(|members| members.len() > 1) {
+        let id = clusters.len();
+        let count = members.len() as f64;
+        let (latitude, longitude) = members
+            .iter()
+            .fold((0.0, 0.0), |(lat, lon), &i| (lat + points[i].1, lon + points[i].2));
+        for &i in &members {
+            membership[i] = Some(id);
+        }
+        clusters.push(Cluster {
+            id,
+            latitude: latitude / count,
+            longitude: longitude / count,
+            vps_ids: members.iter().map(|&i| points[i].0).collect(),
+        });
+    }
+    (clusters, membership)
+}
+
+/// A located server, as `group_by_location` sees it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LocatedServer {
+    pub vps_id: i32,
+    pub online: bool,
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub coordinates: Coordinates,
+}
+
+/// Servers in the same country and city.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocationGroup {
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+    /// The mean position of the servers.
+    pub latitude: f64,
+    pub longitude: f64,
+    pub vps_ids: Vec<i32>,
+    pub online_count: usize,
+}
+
+/// Groups servers by country and city, largest groups first.
+pub fn group_by_location(servers: &[LocatedServer]) -> Vec<LocationGroup> {
+    let mut groups: BTreeMap<(Option<String>, Option<String>), Vec<&LocatedServer>> = BTreeMap::new();
+    for server in servers {
+        groups
+            .entry((server.country_code.clone(), server.city.clone()))
+            .or_default()
+            .push(server);
+    }
+    let mut groups: Vec<LocationGroup> = groups
+        .into_iter()
+        .map(|((country_code, city), members)| {
+            let count = members.len() as f64;
+            LocationGroup {
+                country_code,
+                country: members.iter().find_map(|s| s.country.clone()),
+                city,
+                latitude: members.iter().map(|s| s.coordinates.latitude).sum::<f64>() / count,
+                longitude: members.iter().map(|s| s.coordinates.longitude).sum::<f64>() / count,
+                vps_ids: members.iter().map(|s| s.vps_id).collect(),
+                online_count: members.iter().filter(|s| s.online).count(),
+            }
+        })
+        .collect();
+    groups.sort_by_key(|group| std::cmp::Reverse(group.vps_ids.len()));
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn centroid_table_is_sorted() {
+        assert!(COUNTRY_CENTROIDS.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(country_centroid("de"), Some((51.1, 10.4)));
+        assert_eq!(country_centroid("XX"), None);
+    }
+
+    #[test]
+    fn explicit_coordinates_win_over_the_country() {
+        let exact = locate(Some(&json!({"latitude": 50.1, "longitude": 8.7, "country_code": "US"}))).unwrap();
+        assert_eq!((exact.latitude, exact.longitude, exact.precision), (50.1, 8.7, Precision::Exact));
+
+        let country = locate(Some(&json!({"country_code": "sg"}))).unwrap();
+        assert_eq!(country.precision, Precision::Country);
+        assert_eq!(locate(Some(&json!({"latitude": 123.0, "longitude": 0.0}))), None);
+        assert_eq!(locate(None), None);
+    }
+
+    #[test]
+    fn resolved_city_is_used_before_the_country_centroid() {
+        let metadata = json!({
+            "country_code": "DE",
+            "geo": {
+                "ip": "88.99.1.2", "country_code": "DE", "country": "Germany", "city": "Falkenstein",
+                "latitude": 50.47, "longitude": 12.37, "asn": 24940, "as_organization": "Hetzner Online GmbH",
+                "resolved_at": "2025-08-01T00:00:00Z"
+            }
+        });
+        let city = locate(Some(&metadata)).unwrap();
+        assert_eq!((city.latitude, city.longitude, city.precision), (50.47, 12.37, Precision::City));
+
+        let resolved_only = json!({ "geo": metadata["geo"].clone() });
+        assert_eq!(country_code(Some(&resolved_only)).as_deref(), Some("DE"));
+    }
+
+    #[test]
+    fn servers_group_by_country_and_city() {
+        let server = |vps_id, city: &str, latitude, online| LocatedServer {
+            vps_id,
+            online,
+            country_code: Some("DE".to_string()),
+            country: Some("Germany".to_string()),
+            city: Some(city.to_string()),
+            coordinates: Coordinates {
+                latitude,
+                longitude: 10.0,
+                precision: Precision::City,
+            },
+        };
+        let groups = group_by_location(&[
+            server(1, "Nuremberg", 49.0, true),
+            server(2, "Falkenstein", 50.0, true),
+            server(3, "Falkenstein", 51.0, false),
+        ]);
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].city.as_deref(), Some("Falkenstein"));
+        assert_eq!(groups[0].vps_ids, vec![2, 3]);
+        assert_eq!(groups[0].online_count, 1);
+        assert_eq!(groups[0].latitude, 50.5);
+        assert_eq!(groups[1].vps_ids, vec![1]);
+    }
+
+    #[test]
+    fn nearby_servers_cluster_until_zoomed_in() {
+        // Two servers in Frankfurt, one in Amsterdam and one in Singapore.
+        let points = [(1, 50.11, 8.68), (2, 50.12, 8.69), (3, 52.37, 4.9), (4, 1.35, 103.8)];
+        let (clusters, membership) = cluster(&points, 1);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].vps_ids, vec![1, 2, 3]);
+        assert_eq!(membership, vec![Some(0), Some(0), Some(0), None]);
+
+        let (clusters, _) = cluster(&points, 8);
+        assert_eq!(clusters.len(), 1);
+        assert_eq!(clusters[0].vps_ids, vec![1, 2]);
+        assert!(cluster(&points, MAX_ZOOM).0.is_empty());
+    }
+}
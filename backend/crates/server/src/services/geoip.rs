@@ -0,0 +1,314 @@
+//! IP geolocation for VPS. A background task resolves the public address each
+//! agent reported at handshake to country, city and ASN, either through the
+//! ip-api.com JSON API or local MaxMind GeoLite2/GeoIP2 databases, and stores
+//! the result under the `geo` key of the VPS metadata. Lookups are repeated
+//! when the address changes or the result is a month old.
+
+use chrono::{DateTime, Duration, Utc};
+use maxminddb::{geoip2, MaxMindDBError, Reader};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::net::IpAddr;
+use tracing::{debug, warn};
+
+use crate::db::duckdb_service::{vps_service, DuckDbPool};
+use crate::server::config::{GeoIpProvider, ServerConfig};
+use crate::web::error::AppError;
+
+/// Metadata key the result is stored under.
+pub const METADATA_KEY: &str = "geo";
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+const REFRESH_AFTER_DAYS: i64 = 30;
+const IP_API_FIELDS: &str = "status,message,country,countryCode,city,lat,lon,as,query";
+
+/// What an address resolved to.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GeoInfo {
+    pub ip: String,
+    pub country_code: Option<String>,
+    pub country: Option<String>,
+    pub city: Option<String>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub asn: Option<u32>,
+    pub as_organization: Option<String>,
+    pub resolved_at: DateTime<Utc>,
+}
+
+/// The stored lookup result of a VPS, if any.
+pub fn from_metadata(metadata: Option<&Value>) -> Option<GeoInfo> {
+    serde_json::from_value(metadata?.get(METADATA_KEY)?.clone()).ok()
+}
+
+/// Whether an address is routable on the internet. Private, shared (CGNAT),
+/// loopback, link-local and documentation ranges can't be located.
+fn is_public(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, ..] = v4.octets();
+            !(v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_unspecified()
+                || (a == 100 && (64..128).contains(&b)))
+        }
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            !(v6.is_loopback()
+                || v6.is_unspecified()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && v6.segments()[1] == 0x0db8))
+        }
+    }
+}
+
+/// The address to locate a VPS by: the first public address the agent
+/// reported, otherwise the stored IP address if it is public.
+pub fn public_ip(metadata: Option<&Value>, ip_address: Option<&str>) -> Option<IpAddr> {
+    let reported = metadata
+        .and_then(|metadata| metadata.get("public_ip_addresses"))
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(Value::as_str);
+    reported
+        .chain(ip_address)
+        .filter_map(|ip| ip.trim().parse::<IpAddr>().ok())
+        .find(is_public)
+}
+
+/// Whether `ip` has to be (re)resolved given the stored result.
+pub fn needs_lookup(stored: Option<&GeoInfo>, ip: &IpAddr, now: DateTime<Utc>) -> bool {
+    match stored {
+        Some(stored) => stored.ip != ip.to_string() || now - stored.resolved_at > Duration::days(REFRESH_AFTER_DAYS),
+        None => true,
+    }
+}
+
+/// Splits ip-api's `as` field ("AS24940 Hetzner Online GmbH").
+fn parse_as_field(field: &str) -> (Option<u32>, Option<String>) {
+    let (number, organization) = field.trim().split_once(' ').unwrap_or((field.trim(), ""));
+    let asn = number.strip_prefix("AS").and_then(|n| n.parse().ok());
+    let organization = Some(organization.trim()).filter(|o| !o.is_empty()).map(str::to_string);
+    (asn, organization)
+}
+
+fn non_empty(value: &Value, key: &str) -> Option<String> {
+    value.get(key)?.as_str().filter(|s| !s.is_empty()).map(str::to_string)
+}
+
+/// Reads an ip-api.com response. Failed lookups (reserved ranges, quota)
+/// come back with `"status": "fail"` and resolve to nothing.
+pub fn parse_ip_api_response(ip: &IpAddr, body: &Value, now: DateTime<Utc>) -> Option<GeoInfo> {
+    if body.get("status").and_then(Value::as_str) != Some("success") {
+        return None;
+    }
+    let (asn, as_organization) = body
+        .get("as")
+        .and_then(Value::as_str)
+        .map(parse_as_field)
+        .unwrap_or((None, None));
+    Some(GeoInfo {
+        ip: ip.to_string(),
+        country_code: non_empty(body, "countryCode"),
+        country: non_empty(body, "country"),
+        city: non_empty(body, "city"),
+        latitude: body.get("lat").and_then(Value::as_f64),
+        longitude: body.get("lon").and_then(Value::as_f64),
+        asn,
+        as_organization,
+        resolved_at: now,
+    })
+}
+
+/// Where addresses are looked up.
+pub enum GeoIpResolver {
+    IpApi {
+        http: reqwest::Client,
+        base_url: String,
+    },
+    MaxMind {
+        city: Box<Reader<Vec<u8>>>,
+        asn: Option<Box<Reader<Vec<u8>>>>,
+    },
+}
+
+fn open_database(path: &str) -> Result<Box<Reader<Vec<u8>>>, String> {
+    Reader::open_readfile(path)
+        .map(Box::new)
+        .map_err(|e| format!("Failed to open GeoIP database {path}: {e}"))
+}
+
+/// Ignores addresses missing from a database; other errors are real.
+fn not_found_as_none<T>(result: Result<T, MaxMindDBError>) -> Result<Option<T>, AppError> {
+    match result {
+        Ok(record) => Ok(Some(record)),
+        Err(MaxMindDBError::AddressNotFoundError(_)) => Ok(None),
+        Err(e) => Err(AppError::InternalServerError(format!("GeoIP database lookup failed: {e}"))),
+    }
+}
+
+impl GeoIpResolver {
+    /// The configured resolver, or `None` when geolocation is off.
+    pub fn from_config(config: &ServerConfig) -> Result<Option<Self>, String> {
+        match config.geoip_provider {
+            GeoIpProvider::None => Ok(None),
+            GeoIpProvider::IpApi => {
+                let http = reqwest::Client::builder()
+                    .timeout(REQUEST_TIMEOUT)
+                    .build()
+                    .map_err(|e| format!("Failed to build GeoIP client: {e}"))?;
+                Ok(Some(GeoIpResolver::IpApi {
+                    http,
+                    base_url: config.geoip_ip_api_url.trim_end_matches('/').to_string(),
+                }))
+            }
+            GeoIpProvider::Maxmind => {
+                let city_path = config
+                    .geoip_city_database
+                    .as_deref()
+                    .ok_or_else(|| "GEOIP_CITY_DATABASE is required for the maxmind provider".to_string())?;
+                Ok(Some(GeoIpResolver::MaxMind {
+                    city: open_database(city_path)?,
+                    asn: config.geoip_asn_database.as_deref().map(open_database).transpose()?,
+                }))
+            }
+        }
+    }
+
+    pub async fn lookup(&self, ip: IpAddr, now: DateTime<Utc>) -> Result<Option<GeoInfo>, AppError> {
+        match self {
+            GeoIpResolver::IpApi { http, base_url } => {
+                let body: Value = http
+                    .get(format!("{base_url}/{ip}"))
+                    .query(&[("fields", IP_API_FIELDS)])
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| AppError::ServerError(format!("GeoIP request failed: {e}")))?
+                    .json()
+                    .await
+                    .map_err(|e| AppError::ServerError(format!("Invalid GeoIP response: {e}")))?;
+                Ok(parse_ip_api_response(&ip, &body, now))
+            }
+            GeoIpResolver::MaxMind { city, asn } => {
+                let english = |names: Option<std::collections::BTreeMap<&str, &str>>| {
+                    names.and_then(|names| names.get("en").map(|name| name.to_string()))
+                };
+                let city_record = not_found_as_none(city.lookup::<geoip2::City>(ip))?;
+                let asn_record = match asn {
+                    Some(reader) => not_found_as_none(reader.lookup::<geoip2::Asn>(ip))?,
+                    None => None,
+                };
+                if city_record.is_none() && asn_record.is_none() {
+                    return Ok(None);
+                }
+                let (country, city_name, location) = match city_record {
+                    Some(record) => (record.country, record.city, record.location),
+                    None => (None, None, None),
+                };
+                Ok(Some(GeoInfo {
+                    ip: ip.to_string(),
+                    country_code: country.as_ref().and_then(|c| c.iso_code).map(str::to_string),
+                    country: country.and_then(|c| english(c.names)),
+                    city: city_name.and_then(|c| english(c.names)),
+                    latitude: location.as_ref().and_then(|l| l.latitude),
+                    longitude: location.as_ref().and_then(|l| l.longitude),
+                    asn: asn_record.as_ref().and_then(|a| a.autonomous_system_number),
+                    as_organization: asn_record.and_then(|a| a.autonomous_system_organization.map(str::to_string)),
+                    resolved_at: now,
+                }))
+            }
+        }
+    }
+}
+
+/// Resolves up to `max_lookups` VPS whose address is new or whose result is
+/// stale. Returns the number of VPS whose location was stored.
+pub async fn enrich_pending(pool: DuckDbPool, resolver: &GeoIpResolver, max_lookups: usize) -> Result<usize, AppError> {
+    let now = Utc::now();
+    let pending: Vec<(i32, IpAddr)> = vps_service::get_vps_network_info(pool.clone())
+        .await?
+        .into_iter()
+        .filter_map(|(vps_id, ip_address, metadata)| {
+            let ip = public_ip(metadata.as_ref(), ip_address.as_deref())?;
+            needs_lookup(from_metadata(metadata.as_ref()).as_ref(), &ip, now).then_some((vps_id, ip))
+        })
+        .take(max_lookups)
+        .collect();
+
+    let mut stored = 0;
+    for (vps_id, ip) in pending {
+        match resolver.lookup(ip, now).await {
+            Ok(Some(info)) => {
+                vps_service::set_metadata_key(pool.clone(), vps_id, METADATA_KEY, serde_json::to_value(&info)?).await?;
+                stored += 1;
+            }
+            Ok(None) => debug!(vps_id, %ip, "Address has no GeoIP record."),
+            // Stop early; the remaining VPS are tried on the next run.
+            Err(e) => {
+                warn!(vps_id, %ip, error = %e, "GeoIP lookup failed.");
+                break;
+            }
+        }
+    }
+    Ok(stored)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn only_public_addresses_are_located() {
+        let metadata = json!({ "public_ip_addresses": ["10.0.0.5", "100.64.1.1", "203.0.113.9", "88.99.1.2"] });
+        assert_eq!(public_ip(Some(&metadata), None), "88.99.1.2".parse().ok());
+        assert_eq!(public_ip(None, Some("192.168.1.2")), None);
+        assert_eq!(public_ip(None, Some("2a01:4f8::1")), "2a01:4f8::1".parse().ok());
+        assert_eq!(public_ip(None, Some("fd00::1")), None);
+    }
+
+    #[test]
+    fn lookups_repeat_when_the_address_changes_or_ages() {
+        let now = Utc::now();
+        let ip: IpAddr = "88.99.1.2".parse().unwrap();
+        let stored = GeoInfo {
+            ip: ip.to_string(),
+            country_code: Some("DE".to_string()),
+            country: None,
+            city: None,
+            latitude: None,
+            longitude: None,
+            asn: None,
+            as_organization: None,
+            resolved_at: now - Duration::days(1),
+        };
+        assert!(!needs_lookup(Some(&stored), &ip, now));
+        assert!(needs_lookup(Some(&stored), &"88.99.1.3".parse().unwrap(), now));
+        assert!(needs_lookup(Some(&stored), &ip, now + Duration::days(REFRESH_AFTER_DAYS)));
+        assert!(needs_lookup(None, &ip, now));
+    }
+
+    #[test]
+    fn parses_ip_api_responses() {
+        let now = Utc::now();
+        let ip: IpAddr = "88.99.1.2".parse().unwrap();
+        let body = json!({
+            "status": "success", "country": "Germany", "countryCode": "DE", "city": "Falkenstein",
+            "lat": 50.47, "lon": 12.37, "as": "AS24940 Hetzner Online GmbH", "query": "88.99.1.2"
+        });
+        let info = parse_ip_api_response(&ip, &body, now).unwrap();
+        assert_eq!(info.country_code.as_deref(), Some("DE"));
+        assert_eq!(info.city.as_deref(), Some("Falkenstein"));
+        assert_eq!(info.asn, Some(24940));
+        assert_eq!(info.as_organization.as_deref(), Some("Hetzner Online GmbH"));
+        assert_eq!(from_metadata(Some(&json!({ "geo": info.clone() }))), Some(info));
+
+        assert!(parse_ip_api_response(&ip, &json!({ "status": "fail", "message": "reserved range" }), now).is_none());
+        assert_eq!(parse_as_field(""), (None, None));
+    }
+}
@@ -0,0 +1,428 @@
+//! One-line agent installation. The server renders an install script with
+//! everything a new machine needs baked in: the download URL of the agent
+//! build matching this server for each supported architecture, the server
+//! address, and either the agent credentials of an existing VPS or an
+//! enrollment token the script exchanges for new ones at `/api/enroll`.
+//! Downloads are checked against the SHA-256 of the registered agent release,
+//! or else against the `.sha256` file published next to the release asset.
+
+use std::str::FromStr;
+
+use crate::db::entities::command_script::ScriptLanguage;
+use crate::services::script_template::quote;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallOs {
+    Linux,
+    Macos,
+    Windows,
+}
+
+impl FromStr for InstallOs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "linux" => Ok(Self::Linux),
+            "macos" | "darwin" => Ok(Self::Macos),
+            "windows" => Ok(Self::Windows),
+            other => Err(format!("Unsupported operating system '{other}'; expected linux, macos or windows")),
+        }
+    }
+}
+
+impl InstallOs {
+    fn language(self) -> ScriptLanguage {
+        match self {
+            Self::Windows => ScriptLanguage::PowerShell,
+            Self::Linux | Self::Macos => ScriptLanguage::Shell,
+        }
+    }
+
+    /// Release targets built for the OS, by the architecture name the script
+    /// matches against (`uname -m`, or `PROCESSOR_ARCHITECTURE` on Windows).
+    fn targets(self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            Self::Linux => &[("x86_64", "x86_64-unknown-linux-gnu"), ("aarch64", "aarch64-unknown-linux-gnu")],
+            Self::Macos => &[("x86_64", "x86_64-apple-darwin"), ("arm64", "aarch64-apple-darwin")],
+            Self::Windows => &[("AMD64", "x86_64-pc-windows-msvc")],
+        }
+    }
+}
+
+/// File name of a release asset, following the naming of the release workflow.
+pub fn agent_file_name(version: &str, target: &str) -> String {
+    let extension = if target.contains("windows") { ".exe" } else { "" };
+    format!("nodenexus-agent-v{version}-{target}{extension}")
+}
+
+/// Download URL of a release asset.
+pub fn agent_download_url(base_url: &str, version: &str, target: &str) -> String {
+    format!(
+        "{}/v{version}/{}",
+        base_url.trim_end_matches('/'),
+        agent_file_name(version, target)
+    )
+}
+
+/// How the installed agent authenticates.
+pub enum InstallCredentials<'a> {
+    Vps { vps_id: i32, agent_secret: &'a str },
+    /// Exchanged for a new VPS at install time.
+    Enrollment { token: &'a str },
+}
+
+pub struct InstallScriptParams<'a> {
+    pub server_address: &'a str,
+    pub download_base_url: &'a str,
+    pub version: &'a str,
+    pub credentials: InstallCredentials<'a>,
+    /// SHA-256 of the agent binary by target, for releases registered with the server.
+    pub checksums: &'a [(String, String)],
+}
+
+/// The command that fetches and runs the enrollment install script for `os`.
+pub fn enrollment_command(server_address: &str, token: &str, os: InstallOs) -> String {
+    let server_address = server_address.trim_end_matches('/');
+    match os {
+        InstallOs::Windows => format!(
+            "powershell -ExecutionPolicy Bypass -Command \"irm '{server_address}/api/enroll/install-script?os=windows&token={token}' | iex\""
+        ),
+        InstallOs::Linux => format!("curl -fsSL '{server_address}/api/enroll/install-script?os=linux&token={token}' | sudo sh"),
+        InstallOs::Macos => format!("curl -fsSL '{server_address}/api/enroll/install-script?os=macos&token={token}' | sudo sh"),
+    }
+}
+
+pub fn render(os: InstallOs, params: &InstallScriptParams<'_>) -> String {
+    let language = os.language();
+    let q = |value: &str| quote(&language, value);
+    let (vps_id, agent_secret, token) = match params.credentials {
+        InstallCredentials::Vps { vps_id, agent_secret } => (vps_id.to_string(), agent_secret, ""),
+        InstallCredentials::Enrollment { token } => (String::new(), "", token),
+    };
+
+    let download_cases = os
+        .targets()
+        .iter()
+        .map(|(arch, target)| {
+            let url = q(&agent_download_url(params.download_base_url, params.version, target));
+            let checksum = params
+                .checksums
+                .iter()
+                .find(|(checksum_target, _)| checksum_target == target)
+                .map_or("", |(_, sha256)| sha256.as_str());
+            let checksum = q(checksum);
+            match os {
+                InstallOs::Windows => format!("    '{arch}' {{ $DownloadUrl = {url}; $ExpectedSha256 = {checksum} }}"),
+                InstallOs::Linux | InstallOs::Macos => {
+                    format!("    {arch}) DOWNLOAD_URL={url}; EXPECTED_SHA256={checksum} ;;")
+                }
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let (template, service_setup) = match os {
+        InstallOs::Linux => (SHELL_TEMPLATE, SYSTEMD_SETUP),
+        InstallOs::Macos => (SHELL_TEMPLATE, LAUNCHD_SETUP),
+        InstallOs::Windows => (POWERSHELL_TEMPLATE, ""),
+    };
+    template
+        .replace("{{service_setup}}", service_setup)
+        .replace("{{download_cases}}", &download_cases)
+        .replace("{{version}}", params.version)
+        .replace("{{server_address}}", &q(params.server_address.trim_end_matches('/')))
+        .replace("{{vps_id}}", &q(&vps_id))
+        .replace("{{agent_secret}}", &q(agent_secret))
+        .replace("{{enrollment_token}}", &q(token))
+}
+
+const SHELL_TEMPLATE: &str = r#"#!/bin/sh
+# NodeNexus agent {{version}} installer, generated by the server.
+set -e
+
+SERVER_ADDRESS={{server_address}}
+VPS_ID={{vps_id}}
+AGENT_SECRET={{agent_secret}}
+ENROLLMENT_TOKEN={{enrollment_token}}
+INSTALL_DIR=/opt/node-nexus
+CONFIG_FILE="$INSTALL_DIR/agent_config.toml"
+SERVICE_NAME=node-nexus-agent
+
+info() { echo "[INFO] $1" >&2; }
+fail() { echo "[ERROR] $1" >&2; exit 1; }
+
+[ "$(id -u)" -eq 0 ] || fail "This script must be run as root."
+command -v curl >/dev/null 2>&1 || fail "curl is required."
+
+case "$(uname -m)" in
+{{download_cases}}
+    *) fail "Unsupported architecture: $(uname -m)" ;;
+esac
+
+mkdir -p "$INSTALL_DIR"
+if [ -f "$CONFIG_FILE" ]; then
+    info "Keeping the existing configuration in $CONFIG_FILE."
+else
+    if [ -n "$ENROLLMENT_TOKEN" ]; then
+        info "Enrolling this machine with $SERVER_ADDRESS..."
+        RESPONSE=$(curl -fsS -X POST -H 'Content-Type: application/json' \
+            -d "{\"token\":\"$ENROLLMENT_TOKEN\",\"hostname\":\"$(hostname)\"}" \
+            "$SERVER_ADDRESS/api/enroll") || fail "Enrollment failed. The token may be used up or expired."
+        VPS_ID=$(echo "$RESPONSE" | sed -n 's/.*"vpsId":\([0-9]*\).*/\1/p')
+        AGENT_SECRET=$(echo "$RESPONSE" | sed -n 's/.*"agentSecret":"\([^"]*\)".*/\1/p')
+        [ -n "$VPS_ID" ] && [ -n "$AGENT_SECRET" ] || fail "Unexpected enrollment response: $RESPONSE"
+        info "Enrolled as VPS $VPS_ID."
+    fi
+    cat > "$CONFIG_FILE" <<EOF
+# Node-Nexus Agent Configuration
+server_address = "$SERVER_ADDRESS"
+vps_id = $VPS_ID
+agent_secret = "$AGENT_SECRET"
+
+log_level = "info"
+heartbeat_interval_seconds = 30
+metrics_collect_interval_seconds = 5
+metrics_upload_interval_seconds = 7
+metrics_upload_batch_max_size = 10
+data_collection_interval_seconds = 15
+generic_metrics_upload_interval_seconds = 300
+generic_metrics_upload_batch_max_size = 100
+
+[docker_monitoring]
+enabled = true
+docker_info_collect_interval_seconds = 600
+docker_info_upload_interval_seconds = 900
+EOF
+    chmod 600 "$CONFIG_FILE"
+fi
+
+info "Downloading the agent from $DOWNLOAD_URL..."
+curl -fL --progress-bar "$DOWNLOAD_URL" -o "$INSTALL_DIR/agent.new" || fail "Failed to download the agent."
+if [ -z "$EXPECTED_SHA256" ]; then
+    EXPECTED_SHA256=$(curl -fsSL "$DOWNLOAD_URL.sha256" | cut -d ' ' -f 1)
+    [ -n "$EXPECTED_SHA256" ] || fail "Failed to download the agent checksum."
+fi
+if command -v sha256sum >/dev/null 2>&1; then
+    ACTUAL_SHA256=$(sha256sum "$INSTALL_DIR/agent.new" | cut -d ' ' -f 1)
+else
+    ACTUAL_SHA256=$(shasum -a 256 "$INSTALL_DIR/agent.new" | cut -d ' ' -f 1)
+fi
+if [ "$ACTUAL_SHA256" != "$EXPECTED_SHA256" ]; then
+    rm -f "$INSTALL_DIR/agent.new"
+    fail "Checksum mismatch for the downloaded agent (expected $EXPECTED_SHA256, got $ACTUAL_SHA256)."
+fi
+chmod +x "$INSTALL_DIR/agent.new"
+mv -f "$INSTALL_DIR/agent.new" "$INSTALL_DIR/agent"
+{{service_setup}}
+info "NodeNexus agent installed."
+"#;
+
+const SYSTEMD_SETUP: &str = r#"
+cat > "/etc/systemd/system/$SERVICE_NAME.service" <<EOF
+[Unit]
+Description=Node-Nexus Agent
+After=network-online.target
+Wants=network-online.target
+
+[Service]
+Type=simple
+WorkingDirectory=$INSTALL_DIR
+ExecStart=$INSTALL_DIR/agent --config $CONFIG_FILE
+Environment="NEXUS_AGENT_SERVICE_NAME=$SERVICE_NAME"
+Restart=always
+RestartSec=5
+
+[Install]
+WantedBy=multi-user.target
+EOF
+systemctl daemon-reload
+systemctl enable "$SERVICE_NAME" >/dev/null 2>&1
+systemctl restart "$SERVICE_NAME"
+info "Check the service with: systemctl status $SERVICE_NAME""#;
+
+const LAUNCHD_SETUP: &str = r#"
+PLIST="/Library/LaunchDaemons/com.nodenexus.agent.plist"
+cat > "$PLIST" <<EOF
+<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key><string>com.nodenexus.agent</string>
+    <key>ProgramArguments</key>
+    <array><string>$INSTALL_DIR/agent</string><string>--config</string><string>$CONFIG_FILE</string></array>
+    <key>WorkingDirectory</key><string>$INSTALL_DIR</string>
+    <key>RunAtLoad</key><true/>
+    <key>KeepAlive</key><true/>
+</dict>
+</plist>
+EOF
+launchctl unload "$PLIST" >/dev/null 2>&1 || true
+launchctl load -w "$PLIST"
+info "Check the service with: launchctl list com.nodenexus.agent""#;
+
+const POWERSHELL_TEMPLATE: &str = r#"# NodeNexus agent {{version}} installer, generated by the server.
+$ErrorActionPreference = 'Stop'
+
+$ServerAddress = {{server_address}}
+$VpsId = {{vps_id}}
+$AgentSecret = {{agent_secret}}
+$EnrollmentToken = {{enrollment_token}}
+$InstallDir = 'C:\NodeNexusAgent'
+$ConfigPath = Join-Path $InstallDir 'config.toml'
+$ExePath = Join-Path $InstallDir 'agent.exe'
+$ServiceName = 'NodeNexusAgent'
+
+if (-not ([Security.Principal.WindowsPrincipal][Security.Principal.WindowsIdentity]::GetCurrent()).IsInRole([Security.Principal.WindowsBuiltInRole]::Administrator)) {
+    throw 'This script must be run as an Administrator.'
+}
+
+switch ($env:PROCESSOR_ARCHITECTURE) {
+{{download_cases}}
+    default { throw "Unsupported architecture: $env:PROCESSOR_ARCHITECTURE" }
+}
+
+New-Item -ItemType Directory -Path $InstallDir -Force | Out-Null
+if (Test-Path $ConfigPath) {
+    Write-Host "Keeping the existing configuration in $ConfigPath."
+} else {
+    if ($EnrollmentToken) {
+        Write-Host "Enrolling this machine with $ServerAddress..."
+        $Body = @{ token = $EnrollmentToken; hostname = $env:COMPUTERNAME } | ConvertTo-Json
+        $Enrollment = Invoke-RestMethod -Uri "$ServerAddress/api/enroll" -Method Post -ContentType 'application/json' -Body $Body -UseBasicParsing
+        $VpsId = $Enrollment.vpsId
+        $AgentSecret = $Enrollment.agentSecret
+        Write-Host "Enrolled as VPS $VpsId."
+    }
+    Set-Content -Path $ConfigPath -Value @"
+# Node-Nexus Agent Configuration
+server_address = "$ServerAddress"
+vps_id = $VpsId
+agent_secret = "$AgentSecret"
+
+log_level = "info"
+heartbeat_interval_seconds = 30
+metrics_collect_interval_seconds = 5
+metrics_upload_interval_seconds = 7
+metrics_upload_batch_max_size = 10
+data_collection_interval_seconds = 15
+generic_metrics_upload_interval_seconds = 300
+generic_metrics_upload_batch_max_size = 100
+
+[docker_monitoring]
+enabled = true
+docker_info_collect_interval_seconds = 600
+docker_info_upload_interval_seconds = 900
+"@
+}
+
+if (Get-Service -Name $ServiceName -ErrorAction SilentlyContinue) {
+    Stop-Service -Name $ServiceName -ErrorAction SilentlyContinue
+    sc.exe delete $ServiceName | Out-Null
+    Start-Sleep -Seconds 2
+}
+
+Write-Host "Downloading the agent from $DownloadUrl..."
+$DownloadPath = "$ExePath.new"
+Invoke-WebRequest -Uri $DownloadUrl -OutFile $DownloadPath -UseBasicParsing
+if (-not $ExpectedSha256) {
+    $ChecksumPath = "$ExePath.sha256"
+    Invoke-WebRequest -Uri "$DownloadUrl.sha256" -OutFile $ChecksumPath -UseBasicParsing
+    $ExpectedSha256 = ((Get-Content -Path $ChecksumPath -Raw).Trim() -split '\s+')[0]
+    Remove-Item -Path $ChecksumPath -Force
+}
+$ActualSha256 = (Get-FileHash -Path $DownloadPath -Algorithm SHA256).Hash
+if ($ActualSha256 -ne $ExpectedSha256) {
+    Remove-Item -Path $DownloadPath -Force
+    throw "Checksum mismatch for the downloaded agent (expected $ExpectedSha256, got $ActualSha256)."
+}
+Move-Item -Path $DownloadPath -Destination $ExePath -Force
+
+& $ExePath --install-service --config $ConfigPath
+if ($LASTEXITCODE -ne 0) {
+    throw 'Failed to install the NodeNexus agent service.'
+}
+Write-Host 'NodeNexus agent installed.'
+"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn params(credentials: InstallCredentials<'_>) -> InstallScriptParams<'_> {
+        InstallScriptParams {
+            server_address: "https://nexus.example.com/",
+            download_base_url: "https://mirror.example.com/releases/",
+            version: "1.2.3",
+            credentials,
+            checksums: &[],
+        }
+    }
+
+    #[test]
+    fn parses_os_names() {
+        assert_eq!("Linux".parse::<InstallOs>(), Ok(InstallOs::Linux));
+        assert_eq!("darwin".parse::<InstallOs>(), Ok(InstallOs::Macos));
+        assert!("plan9".parse::<InstallOs>().is_err());
+    }
+
+    #[test]
+    fn download_urls_follow_the_release_naming() {
+        assert_eq!(
+            agent_download_url("https://github.com/o/r/releases/download/", "1.2.3", "x86_64-pc-windows-msvc"),
+            "https://github.com/o/r/releases/download/v1.2.3/nodenexus-agent-v1.2.3-x86_64-pc-windows-msvc.exe"
+        );
+    }
+
+    #[test]
+    fn renders_credentials_and_urls_for_each_architecture() {
+        let script = render(
+            InstallOs::Linux,
+            &params(InstallCredentials::Vps {
+                vps_id: 42,
+                agent_secret: "it's-secret",
+            }),
+        );
+        assert!(script.contains("SERVER_ADDRESS='https://nexus.example.com'"));
+        assert!(script.contains("VPS_ID='42'"));
+        assert!(script.contains(r"AGENT_SECRET='it'\''s-secret'"));
+        assert!(script.contains("ENROLLMENT_TOKEN=''"));
+        assert!(script.contains(
+            "x86_64) DOWNLOAD_URL='https://mirror.example.com/releases/v1.2.3/nodenexus-agent-v1.2.3-x86_64-unknown-linux-gnu'"
+        ));
+        assert!(script.contains("aarch64-unknown-linux-gnu"));
+        assert!(script.contains(r#"curl -fsSL "$DOWNLOAD_URL.sha256""#));
+        assert!(script.contains("systemctl restart"));
+        assert!(!script.contains("{{"));
+    }
+
+    #[test]
+    fn renders_enrollment_for_windows() {
+        let script = render(InstallOs::Windows, &params(InstallCredentials::Enrollment { token: "npt_abc" }));
+        assert!(script.contains("$EnrollmentToken = 'npt_abc'"));
+        assert!(script.contains("$VpsId = ''"));
+        assert!(script.contains(
+            "'AMD64' { $DownloadUrl = 'https://mirror.example.com/releases/v1.2.3/nodenexus-agent-v1.2.3-x86_64-pc-windows-msvc.exe'; $ExpectedSha256 = '' }"
+        ));
+        assert!(script.contains("Get-FileHash"));
+        assert!(!script.contains("{{"));
+
+        let macos = render(InstallOs::Macos, &params(InstallCredentials::Enrollment { token: "npt_abc" }));
+        assert!(macos.contains("launchctl load"));
+        assert!(macos.contains("arm64) DOWNLOAD_URL="));
+    }
+
+    #[test]
+    fn embeds_checksums_of_registered_releases() {
+        let checksums = [("x86_64-unknown-linux-gnu".to_string(), "ab".repeat(32))];
+        let script = render(
+            InstallOs::Linux,
+            &InstallScriptParams {
+                checksums: &checksums,
+                ..params(InstallCredentials::Enrollment { token: "npt_abc" })
+            },
+        );
+        assert!(script.contains(&format!("EXPECTED_SHA256='{}' ;;", "ab".repeat(32))));
+        assert!(script.contains("aarch64) DOWNLOAD_URL='https://mirror.example.com/releases/v1.2.3/nodenexus-agent-v1.2.3-aarch64-unknown-linux-gnu'; EXPECTED_SHA256='' ;;"));
+    }
+}
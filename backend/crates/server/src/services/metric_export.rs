@@ -0,0 +1,124 @@
+//! Text formats for exporting metric timeseries, written one point at a time
+//! so exports can be streamed.
+
+use serde::Deserialize;
+use std::fmt::Write;
+
+use crate::db::duckdb_service::performance_service::PerformanceMetricPoint;
+use crate::services::csv_export;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetricExportFormat {
+    Csv,
+    /// One JSON object per line.
+    Jsonl,
+}
+
+const CSV_COLUMNS: [&str; 12] = [
+    "time",
+    "vps_id",
+    "cpu_usage_percent",
+    "memory_usage_bytes",
+    "memory_total_bytes",
+    "swap_usage_bytes",
+    "disk_io_read_bps",
+    "disk_io_write_bps",
+    "network_rx_instant_bps",
+    "network_tx_instant_bps",
+    "used_disk_space_bytes",
+    "total_disk_space_bytes",
+];
+
+impl MetricExportFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => csv_export::CONTENT_TYPE,
+            Self::Jsonl => "application/x-ndjson",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+        }
+    }
+
+    /// What goes before the first point.
+    pub fn header(self) -> String {
+        match self {
+            Self::Csv => csv_export::line(CSV_COLUMNS),
+            Self::Jsonl => String::new(),
+        }
+    }
+
+    pub fn write_point(self, out: &mut String, point: &PerformanceMetricPoint) {
+        match self {
+            Self::Csv => {
+                let value = |v: Option<f64>| v.map(|v| v.to_string()).unwrap_or_default();
+                out.push_str(&csv_export::line([
+                    point.time.to_rfc3339(),
+                    point.vps_id.to_string(),
+                    value(point.cpu_usage_percent),
+                    value(point.memory_usage_bytes),
+                    value(point.memory_total_bytes),
+                    value(point.swap_usage_bytes),
+                    value(point.disk_io_read_bps),
+                    value(point.disk_io_write_bps),
+                    value(point.network_rx_instant_bps),
+                    value(point.network_tx_instant_bps),
+                    value(point.used_disk_space_bytes),
+                    value(point.total_disk_space_bytes),
+                ]));
+            }
+            Self::Jsonl => {
+                // Serializing plain numbers and a timestamp can't fail.
+                let _ = writeln!(out, "{}", serde_json::to_string(point).unwrap_or_default());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    fn point() -> PerformanceMetricPoint {
+        PerformanceMetricPoint {
+            time: Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap(),
+            vps_id: 7,
+            cpu_usage_percent: Some(12.5),
+            memory_usage_bytes: Some(1024.0),
+            memory_total_bytes: None,
+            swap_usage_bytes: None,
+            disk_io_read_bps: None,
+            disk_io_write_bps: None,
+            network_rx_instant_bps: None,
+            network_tx_instant_bps: None,
+            used_disk_space_bytes: None,
+            total_disk_space_bytes: None,
+        }
+    }
+
+    #[test]
+    fn csv_rows_match_the_header() {
+        let format = MetricExportFormat::Csv;
+        let mut out = format.header();
+        format.write_point(&mut out, &point());
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines[0].split(',').count(), lines[1].split(',').count());
+        assert_eq!(lines[1], "2025-06-01T12:00:00+00:00,7,12.5,1024,,,,,,,,");
+    }
+
+    #[test]
+    fn jsonl_writes_one_object_per_line() {
+        let format = MetricExportFormat::Jsonl;
+        let mut out = format.header();
+        format.write_point(&mut out, &point());
+        format.write_point(&mut out, &point());
+        assert_eq!(out.lines().count(), 2);
+        assert!(out.starts_with("{\"time\":\"2025-06-01T12:00:00Z\",\"vpsId\":7,\"cpuUsagePercent\":12.5,"));
+    }
+}
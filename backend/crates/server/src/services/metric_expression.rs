@@ -0,0 +1,402 @@
+//! Arithmetic expressions over the collected performance metric columns, used by
+//! derived metrics (e.g. `memory_usage_bytes / memory_total_bytes * 100`).
+//!
+//! Expressions are parsed into an AST and compiled to SQL, so user input never
+//! reaches DuckDB verbatim: only whitelisted columns, numeric literals, `+ - * /`,
+//! parentheses and the functions `abs`, `min` and `max` are accepted. Division by
+//! zero yields NULL instead of an error.
+
+use std::fmt;
+
+const MAX_EXPRESSION_LEN: usize = 500;
+const MAX_DEPTH: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetricColumn {
+    CpuUsagePercent,
+    MemoryUsageBytes,
+    MemoryTotalBytes,
+    SwapUsageBytes,
+    SwapTotalBytes,
+    DiskIoReadBps,
+    DiskIoWriteBps,
+    NetworkRxInstantBps,
+    NetworkTxInstantBps,
+    TotalDiskSpaceBytes,
+    UsedDiskSpaceBytes,
+    UptimeSeconds,
+    TotalProcessesCount,
+    RunningProcessesCount,
+    TcpEstablishedConnectionCount,
+}
+
+impl MetricColumn {
+    pub const ALL: [MetricColumn; 15] = [
+        MetricColumn::CpuUsagePercent,
+        MetricColumn::MemoryUsageBytes,
+        MetricColumn::MemoryTotalBytes,
+        MetricColumn::SwapUsageBytes,
+        MetricColumn::SwapTotalBytes,
+        MetricColumn::DiskIoReadBps,
+        MetricColumn::DiskIoWriteBps,
+        MetricColumn::NetworkRxInstantBps,
+        MetricColumn::NetworkTxInstantBps,
+        MetricColumn::TotalDiskSpaceBytes,
+        MetricColumn::UsedDiskSpaceBytes,
+        MetricColumn::UptimeSeconds,
+        MetricColumn::TotalProcessesCount,
+        MetricColumn::RunningProcessesCount,
+        MetricColumn::TcpEstablishedConnectionCount,
+    ];
+
+    /// The identifier used in expressions, which is also the `performance_metrics` column.
+    pub fn name(&self) -> &'static str {
+        match self {
+            MetricColumn::CpuUsagePercent => "cpu_usage_percent",
+            MetricColumn::MemoryUsageBytes => "memory_usage_bytes",
+            MetricColumn::MemoryTotalBytes => "memory_total_bytes",
+            MetricColumn::SwapUsageBytes => "swap_usage_bytes",
+            MetricColumn::SwapTotalBytes => "swap_total_bytes",
+            MetricColumn::DiskIoReadBps => "disk_io_read_bps",
+            MetricColumn::DiskIoWriteBps => "disk_io_write_bps",
+            MetricColumn::NetworkRxInstantBps => "network_rx_instant_bps",
+            MetricColumn::NetworkTxInstantBps => "network_tx_instant_bps",
+            MetricColumn::TotalDiskSpaceBytes => "total_disk_space_bytes",
+            MetricColumn::UsedDiskSpaceBytes => "used_disk_space_bytes",
+            MetricColumn::UptimeSeconds => "uptime_seconds",
+            MetricColumn::TotalProcessesCount => "total_processes_count",
+            MetricColumn::RunningProcessesCount => "running_processes_count",
+            MetricColumn::TcpEstablishedConnectionCount => "tcp_established_connection_count",
+        }
+    }
+
+    /// The matching column of the `performance_metrics_summary_*` tables.
+    /// Totals and uptime use the bucket maximum, everything else the average.
+    pub fn summary_column(&self) -> String {
+        match self {
+            MetricColumn::MemoryTotalBytes | MetricColumn::SwapTotalBytes | MetricColumn::UptimeSeconds => {
+                format!("max_{}", self.name())
+            }
+            _ => format!("avg_{}", self.name()),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        MetricColumn::ALL.into_iter().find(|c| c.name() == name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Abs,
+    Min,
+    Max,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    Column(MetricColumn),
+    Neg(Box<Expr>),
+    Binary(Box<Expr>, BinaryOp, Box<Expr>),
+    Call(Function, Vec<Expr>),
+}
+
+/// Which table the compiled SQL reads from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColumnSource {
+    Raw,
+    Summary,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpressionError(pub String);
+
+impl fmt::Display for ExpressionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>, ExpressionError> {
+    let chars: Vec<char> = input.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            c if c.is_whitespace() => i += 1,
+            '+' | '-' | '*' | '/' => {
+                tokens.push(Token::Op(c));
+                i += 1;
+            }
+            '(' => {
+                tokens.push(Token::LParen);
+                i += 1;
+            }
+            ')' => {
+                tokens.push(Token::RParen);
+                i += 1;
+            }
+            ',' => {
+                tokens.push(Token::Comma);
+                i += 1;
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                    i += 1;
+                }
+                let literal: String = chars[start..i].iter().collect();
+                let value = literal
+                    .parse::<f64>()
+                    .map_err(|_| ExpressionError(format!("Invalid number '{literal}'")))?;
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let start = i;
+                while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                    i += 1;
+                }
+                tokens.push(Token::Ident(chars[start..i].iter().collect::<String>().to_ascii_lowercase()));
+            }
+            other => return Err(ExpressionError(format!("Unexpected character '{other}'"))),
+        }
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), ExpressionError> {
+        match self.next() {
+            Some(t) if t == expected => Ok(()),
+            _ => Err(ExpressionError(format!("Expected {expected:?}"))),
+        }
+    }
+
+    fn check_depth(depth: usize) -> Result<(), ExpressionError> {
+        if depth > MAX_DEPTH {
+            return Err(ExpressionError("Expression is nested too deeply".to_string()));
+        }
+        Ok(())
+    }
+
+    // expr := term (('+' | '-') term)*
+    fn expr(&mut self, depth: usize) -> Result<Expr, ExpressionError> {
+        Self::check_depth(depth)?;
+        let mut left = self.term(depth + 1)?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.pos += 1;
+            let right = self.term(depth + 1)?;
+            let op = if op == '+' { BinaryOp::Add } else { BinaryOp::Sub };
+            left = Expr::Binary(Box::new(left), op, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    // term := unary (('*' | '/') unary)*
+    fn term(&mut self, depth: usize) -> Result<Expr, ExpressionError> {
+        let mut left = self.unary(depth + 1)?;
+        while let Some(Token::Op(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.pos += 1;
+            let right = self.unary(depth + 1)?;
+            let op = if op == '*' { BinaryOp::Mul } else { BinaryOp::Div };
+            left = Expr::Binary(Box::new(left), op, Box::new(right));
+        }
+        Ok(left)
+    }
+
+    // unary := '-' unary | primary
+    fn unary(&mut self, depth: usize) -> Result<Expr, ExpressionError> {
+        Self::check_depth(depth)?;
+        if let Some(Token::Op('-')) = self.peek() {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary(depth + 1)?)));
+        }
+        self.primary(depth + 1)
+    }
+
+    // primary := number | column | function '(' expr (',' expr)* ')' | '(' expr ')'
+    fn primary(&mut self, depth: usize) -> Result<Expr, ExpressionError> {
+        match self.next() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::LParen) => {
+                let inner = self.expr(depth + 1)?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            Some(Token::Ident(name)) => {
+                let function = match name.as_str() {
+                    "abs" => Some(Function::Abs),
+                    "min" => Some(Function::Min),
+                    "max" => Some(Function::Max),
+                    _ => None,
+                };
+                match function {
+                    Some(function) => {
+                        self.expect(Token::LParen)?;
+                        let mut args = vec![self.expr(depth + 1)?];
+                        while let Some(Token::Comma) = self.peek() {
+                            self.pos += 1;
+                            args.push(self.expr(depth + 1)?);
+                        }
+                        self.expect(Token::RParen)?;
+                        let valid = match function {
+                            Function::Abs => args.len() == 1,
+                            Function::Min | Function::Max => args.len() >= 2,
+                        };
+                        if !valid {
+                            return Err(ExpressionError(format!("Wrong number of arguments for {name}()")));
+                        }
+                        Ok(Expr::Call(function, args))
+                    }
+                    None => MetricColumn::from_name(&name)
+                        .map(Expr::Column)
+                        .ok_or_else(|| ExpressionError(format!("Unknown metric '{name}'"))),
+                }
+            }
+            Some(token) => Err(ExpressionError(format!("Unexpected {token:?}"))),
+            None => Err(ExpressionError("Unexpected end of expression".to_string())),
+        }
+    }
+}
+
+pub fn parse_expression(input: &str) -> Result<Expr, ExpressionError> {
+    if input.trim().is_empty() {
+        return Err(ExpressionError("Expression must not be empty".to_string()));
+    }
+    if input.len() > MAX_EXPRESSION_LEN {
+        return Err(ExpressionError(format!(
+            "Expression must be at most {MAX_EXPRESSION_LEN} characters"
+        )));
+    }
+    let mut parser = Parser {
+        tokens: tokenize(input)?,
+        pos: 0,
+    };
+    let expr = parser.expr(0)?;
+    if let Some(token) = parser.peek() {
+        return Err(ExpressionError(format!("Unexpected {token:?}")));
+    }
+    if !expr.references_columns() {
+        return Err(ExpressionError("Expression must reference at least one metric".to_string()));
+    }
+    Ok(expr)
+}
+
+impl Expr {
+    fn references_columns(&self) -> bool {
+        match self {
+            Expr::Number(_) => false,
+            Expr::Column(_) => true,
+            Expr::Neg(inner) => inner.references_columns(),
+            Expr::Binary(left, _, right) => left.references_columns() || right.references_columns(),
+            Expr::Call(_, args) => args.iter().any(Expr::references_columns),
+        }
+    }
+
+    /// Compiles the expression to a DuckDB SQL expression yielding a DOUBLE.
+    pub fn to_sql(&self, source: ColumnSource) -> String {
+        match self {
+            Expr::Number(n) => format!("CAST({n:?} AS DOUBLE)"),
+            Expr::Column(column) => match source {
+                ColumnSource::Raw => format!("CAST({} AS DOUBLE)", column.name()),
+                ColumnSource::Summary => format!("CAST({} AS DOUBLE)", column.summary_column()),
+            },
+            Expr::Neg(inner) => format!("(-{})", inner.to_sql(source)),
+            Expr::Binary(left, op, right) => {
+                let (l, r) = (left.to_sql(source), right.to_sql(source));
+                match op {
+                    BinaryOp::Add => format!("({l} + {r})"),
+                    BinaryOp::Sub => format!("({l} - {r})"),
+                    BinaryOp::Mul => format!("({l} * {r})"),
+                    BinaryOp::Div => format!("({l} / NULLIF({r}, 0))"),
+                }
+            }
+            Expr::Call(function, args) => {
+                let args: Vec<String> = args.iter().map(|a| a.to_sql(source)).collect();
+                match function {
+                    Function::Abs => format!("abs({})", args[0]),
+                    Function::Min => format!("least({})", args.join(", ")),
+                    Function::Max => format!("greatest({})", args.join(", ")),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn compiles_expressions_to_sql() {
+        let expr = parse_expression("memory_usage_bytes / memory_total_bytes * 100").unwrap();
+        assert_eq!(
+            expr.to_sql(ColumnSource::Raw),
+            "((CAST(memory_usage_bytes AS DOUBLE) / NULLIF(CAST(memory_total_bytes AS DOUBLE), 0)) * CAST(100.0 AS DOUBLE))"
+        );
+        let expr = parse_expression("max(network_rx_instant_bps, -network_tx_instant_bps)").unwrap();
+        assert_eq!(
+            expr.to_sql(ColumnSource::Summary),
+            "greatest(CAST(avg_network_rx_instant_bps AS DOUBLE), (-CAST(avg_network_tx_instant_bps AS DOUBLE)))"
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_expressions() {
+        for input in [
+            "",
+            "42",
+            "cpu_usage_percent +",
+            "cpu_usage_percent; DROP TABLE vps",
+            "unknown_column * 2",
+            "abs(cpu_usage_percent, 1)",
+            "(cpu_usage_percent",
+        ] {
+            assert!(parse_expression(input).is_err(), "{input} should be rejected");
+        }
+    }
+
+    #[test]
+    fn columns_match_the_metric_catalog() {
+        let mut columns: Vec<_> = MetricColumn::ALL.iter().map(MetricColumn::name).collect();
+        let mut catalogued = nodenexus_common::metric_catalog::names_for(|usage| usage.derived_metric);
+        columns.sort_unstable();
+        catalogued.sort_unstable();
+        assert_eq!(columns, catalogued);
+    }
+}
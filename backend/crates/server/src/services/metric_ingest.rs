@@ -0,0 +1,181 @@
+//! Validation and rate limiting for application metrics pushed to
+//! `/api/ingest/metrics`. Limits count samples, not requests, so batching
+//! doesn't change how much a key may write.
+
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::{Duration as StdDuration, Instant};
+
+use crate::web::error::AppError;
+use crate::web::models::ingest_models::IngestMetricSample;
+
+pub const MAX_SAMPLES_PER_REQUEST: usize = 1000;
+pub const DEFAULT_RATE_LIMIT_PER_MINUTE: u32 = 6000;
+pub const MAX_RATE_LIMIT_PER_MINUTE: u32 = 600_000;
+const MAX_NAME_LEN: usize = 128;
+const MAX_LABELS: usize = 16;
+const MAX_LABEL_NAME_LEN: usize = 64;
+const MAX_LABEL_VALUE_LEN: usize = 256;
+/// Samples may be backfilled this far, e.g. after a client outage.
+const MAX_SAMPLE_AGE: Duration = Duration::hours(1);
+/// Allowance for clocks running ahead of the server's.
+const MAX_CLOCK_SKEW: Duration = Duration::minutes(5);
+const RATE_LIMIT_WINDOW: StdDuration = StdDuration::from_secs(60);
+
+/// Metric names look like `myapp.queue_depth` or `http_requests_total`.
+pub fn validate_metric_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name.len() > MAX_NAME_LEN {
+        return Err(format!("Metric names must be 1 to {MAX_NAME_LEN} characters"));
+    }
+    let mut chars = name.chars();
+    let valid_start = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    if !valid_start || !chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | ':')) {
+        return Err(format!(
+            "Invalid metric name '{name}': use letters, digits, '_', '.' and ':', starting with a letter or '_'"
+        ));
+    }
+    Ok(())
+}
+
+fn validate_label(name: &str, value: &str) -> Result<(), String> {
+    let mut chars = name.chars();
+    let valid_start = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    if name.len() > MAX_LABEL_NAME_LEN || !valid_start || !chars.all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(format!("Invalid label name '{name}'"));
+    }
+    if value.len() > MAX_LABEL_VALUE_LEN {
+        return Err(format!("Value of label '{name}' is longer than {MAX_LABEL_VALUE_LEN} bytes"));
+    }
+    Ok(())
+}
+
+/// Checks a batch against the schema and the key's allowed name prefixes, and
+/// fills in missing timestamps with `now`.
+pub fn validate_samples(
+    samples: &mut [IngestMetricSample],
+    metric_prefixes: &[String],
+    now: DateTime<Utc>,
+) -> Result<(), AppError> {
+    if samples.is_empty() {
+        return Err(AppError::InvalidInput("No metrics in the request".to_string()));
+    }
+    if samples.len() > MAX_SAMPLES_PER_REQUEST {
+        return Err(AppError::InvalidInput(format!(
+            "At most {MAX_SAMPLES_PER_REQUEST} metrics can be sent per request"
+        )));
+    }
+    for (index, sample) in samples.iter_mut().enumerate() {
+        let invalid = |detail: String| AppError::InvalidInput(format!("metrics[{index}]: {detail}"));
+        validate_metric_name(&sample.name).map_err(invalid)?;
+        if !metric_prefixes.is_empty() && !metric_prefixes.iter().any(|p| sample.name.starts_with(p.as_str())) {
+            return Err(invalid(format!("This key may not write metric '{}'", sample.name)));
+        }
+        if !sample.value.is_finite() {
+            return Err(invalid("Value must be a finite number".to_string()));
+        }
+        if sample.labels.len() > MAX_LABELS {
+            return Err(invalid(format!("At most {MAX_LABELS} labels are allowed")));
+        }
+        for (name, value) in &sample.labels {
+            validate_label(name, value).map_err(invalid)?;
+        }
+        let timestamp = *sample.timestamp.get_or_insert(now);
+        if timestamp < now - MAX_SAMPLE_AGE || timestamp > now + MAX_CLOCK_SKEW {
+            return Err(invalid(
+                "Timestamp must be within the last hour and not in the future".to_string(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    started: Instant,
+    used: u32,
+}
+
+/// Per-key sample budget in fixed one-minute windows; cheap to clone.
+#[derive(Debug, Clone, Default)]
+pub struct IngestRateLimiter {
+    windows: Arc<DashMap<i32, Window>>,
+}
+
+impl IngestRateLimiter {
+    /// Takes `samples` from the key's budget for the current minute, or fails
+    /// with the time until the next window opens.
+    pub fn acquire(&self, key_id: i32, samples: u32, per_minute: u32, now: Instant) -> Result<(), StdDuration> {
+        let mut window = self.windows.entry(key_id).or_insert(Window { started: now, used: 0 });
+        if now.duration_since(window.started) >= RATE_LIMIT_WINDOW {
+            *window = Window { started: now, used: 0 };
+        }
+        if window.used.saturating_add(samples) > per_minute {
+            return Err(RATE_LIMIT_WINDOW.saturating_sub(now.duration_since(window.started)));
+        }
+        window.used += samples;
+        Ok(())
+    }
+
+    /// Forgets a deleted key's window.
+    pub fn remove(&self, key_id: i32) {
+        self.windows.remove(&key_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn sample(name: &str, value: f64) -> IngestMetricSample {
+        IngestMetricSample {
+            name: name.to_string(),
+            value,
+            timestamp: None,
+            vps_id: None,
+            labels: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn metric_names_follow_the_schema() {
+        assert!(validate_metric_name("myapp.queue_depth").is_ok());
+        assert!(validate_metric_name("_http:requests_total").is_ok());
+        assert!(validate_metric_name("").is_err());
+        assert!(validate_metric_name("1xx").is_err());
+        assert!(validate_metric_name("has space").is_err());
+    }
+
+    #[test]
+    fn samples_are_checked_against_the_key() {
+        let now = Utc::now();
+        let prefixes = vec!["myapp.".to_string()];
+        let mut ok = vec![sample("myapp.jobs", 3.0)];
+        assert!(validate_samples(&mut ok, &prefixes, now).is_ok());
+        assert_eq!(ok[0].timestamp, Some(now));
+
+        assert!(validate_samples(&mut [sample("other.jobs", 1.0)], &prefixes, now).is_err());
+        assert!(validate_samples(&mut [sample("myapp.jobs", f64::NAN)], &prefixes, now).is_err());
+        let mut stale = sample("myapp.jobs", 1.0);
+        stale.timestamp = Some(now - Duration::hours(2));
+        assert!(validate_samples(&mut [stale], &prefixes, now).is_err());
+        let mut bad_label = sample("myapp.jobs", 1.0);
+        bad_label.labels.insert("queue-name".to_string(), "x".to_string());
+        assert!(validate_samples(&mut [bad_label], &[], now).is_err());
+    }
+
+    #[test]
+    fn rate_limit_resets_every_minute() {
+        let limiter = IngestRateLimiter::default();
+        let start = Instant::now();
+        assert!(limiter.acquire(1, 60, 100, start).is_ok());
+        assert_eq!(
+            limiter.acquire(1, 50, 100, start + StdDuration::from_secs(20)),
+            Err(StdDuration::from_secs(40))
+        );
+        // Other keys have their own budget.
+        assert!(limiter.acquire(2, 100, 100, start).is_ok());
+        assert!(limiter.acquire(1, 50, 100, start + StdDuration::from_secs(61)).is_ok());
+    }
+}
@@ -1,2 +1,31 @@
+pub mod agent_ca;
+pub mod alert_action_token;
+pub mod alert_actions;
 pub mod auth_service;
+pub mod batch_execution;
+pub mod batch_output;
+pub mod batch_output_diff;
+pub mod chatops;
+pub mod command_scheduler;
+pub mod config_rollout;
+pub mod cron;
+pub mod csv_export;
+pub mod demo_metrics;
+pub mod dns_provider;
 pub mod encryption_service;
+pub mod fleet_map;
+pub mod geoip;
+pub mod install_script;
+pub mod metric_export;
+pub mod metric_expression;
+pub mod metric_ingest;
+pub mod osv_client;
+pub mod package_version;
+pub mod provider_power;
+pub mod read_only;
+pub mod script_runner;
+pub mod script_template;
+pub mod share_token;
+pub mod totp;
+pub mod vulnerability_scanner;
+pub mod webhook_dispatcher;
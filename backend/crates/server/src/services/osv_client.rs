@@ -0,0 +1,483 @@
+//! Minimal client for the OSV vulnerability database (https://osv.dev).
+//!
+//! Installed packages are matched with the batch query endpoint, which only
+//! returns vulnerability ids; details (severity, fixed versions) are fetched
+//! separately per id and cached by the caller.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+use crate::services::package_version::compare_versions;
+use crate::web::error::AppError;
+use crate::web::models::vulnerability_models::Severity;
+
+/// Maximum number of queries OSV accepts in one batch request.
+pub const MAX_BATCH_QUERIES: usize = 1000;
+/// Follow-up requests for queries with more results than fit in one response.
+/// A batch still paginating after this is treated as failed rather than
+/// returned incomplete.
+const MAX_BATCH_PAGES: usize = 50;
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PackageQuery {
+    pub ecosystem: String,
+    pub name: String,
+    pub version: String,
+}
+
+#[derive(Serialize)]
+struct QueryBatchRequest<'a> {
+    queries: Vec<OsvQuery<'a>>,
+}
+
+#[derive(Serialize)]
+struct OsvQuery<'a> {
+    package: OsvPackageRef<'a>,
+    version: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    page_token: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+struct OsvPackageRef<'a> {
+    name: &'a str,
+    ecosystem: &'a str,
+}
+
+#[derive(Deserialize)]
+struct QueryBatchResponse {
+    #[serde(default)]
+    results: Vec<QueryBatchResult>,
+}
+
+#[derive(Deserialize)]
+struct QueryBatchResult {
+    #[serde(default)]
+    vulns: Vec<VulnerabilityRef>,
+    /// Set when the query has more results; sent back as `page_token` to get them.
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct VulnerabilityRef {
+    id: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OsvVulnerability {
+    pub id: String,
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub summary: String,
+    #[serde(default)]
+    pub details: String,
+    pub published: Option<DateTime<Utc>>,
+    pub modified: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub severity: Vec<OsvSeverity>,
+    #[serde(default)]
+    pub affected: Vec<OsvAffected>,
+    pub database_specific: Option<Value>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OsvSeverity {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub score: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OsvAffected {
+    pub package: Option<OsvPackage>,
+    #[serde(default)]
+    pub ranges: Vec<OsvRange>,
+    #[serde(default)]
+    pub severity: Vec<OsvSeverity>,
+    pub ecosystem_specific: Option<Value>,
+    pub database_specific: Option<Value>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OsvPackage {
+    pub name: String,
+    pub ecosystem: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OsvRange {
+    #[serde(default)]
+    pub events: Vec<OsvEvent>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct OsvEvent {
+    pub fixed: Option<String>,
+}
+
+pub struct OsvClient {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl OsvClient {
+    pub fn new(base_url: &str) -> Result<Self, AppError> {
+        let http = reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .map_err(|e| AppError::InternalServerError(format!("Failed to build OSV client: {e}")))?;
+        Ok(Self {
+            http,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Returns the vulnerability ids affecting each query, in query order,
+    /// following `next_page_token` until every query is complete.
+    /// At most [`MAX_BATCH_QUERIES`] queries may be passed.
+    pub async fn query_batch(&self, queries: &[PackageQuery]) -> Result<Vec<Vec<String>>, AppError> {
+        let mut ids = vec![Vec::new(); queries.len()];
+        let mut pending: Vec<(usize, Option<String>)> = (0..queries.len()).map(|i| (i, None)).collect();
+        for _ in 0..MAX_BATCH_PAGES {
+            if pending.is_empty() {
+                return Ok(ids);
+            }
+            let body = QueryBatchRequest {
+                queries: pending
+                    .iter()
+                    .map(|(i, page_token)| OsvQuery {
+                        package: OsvPackageRef {
+                            name: &queries[*i].name,
+                            ecosystem: &queries[*i].ecosystem,
+                        },
+                        version: &queries[*i].version,
+                        page_token: page_token.as_deref(),
+                    })
+                    .collect(),
+            };
+            let response: QueryBatchResponse = self
+                .http
+                .post(format!("{}/v1/querybatch", self.base_url))
+                .json(&body)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status())
+                .map_err(|e| AppError::InternalServerError(format!("OSV batch query failed: {e}")))?
+                .json()
+                .await
+                .map_err(|e| AppError::InternalServerError(format!("Invalid OSV batch response: {e}")))?;
+            pending = collect_page(&mut ids, &pending, response)?;
+        }
+        if pending.is_empty() {
+            return Ok(ids);
+        }
+        Err(AppError::InternalServerError(format!(
+            "OSV batch query still had more results after {MAX_BATCH_PAGES} pages."
+        )))
+    }
+
+    pub async fn get_vulnerability(&self, id: &str) -> Result<OsvVulnerability, AppError> {
+        self.http
+            .get(format!("{}/v1/vulns/{}", self.base_url, urlencoding::encode(id)))
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| AppError::InternalServerError(format!("Failed to fetch {id} from OSV: {e}")))?
+            .json()
+            .await
+            .map_err(|e| AppError::InternalServerError(format!("Invalid OSV record for {id}: {e}")))
+    }
+}
+
+/// Adds one response's ids to the queries that were asked, and returns the
+/// queries that have another page.
+fn collect_page(
+    ids: &mut [Vec<String>],
+    asked: &[(usize, Option<String>)],
+    response: QueryBatchResponse,
+) -> Result<Vec<(usize, Option<String>)>, AppError> {
+    if response.results.len() != asked.len() {
+        return Err(AppError::InternalServerError(format!(
+            "OSV batch response has {} results for {} queries.",
+            response.results.len(),
+            asked.len()
+        )));
+    }
+    let mut next = Vec::new();
+    for ((i, _), result) in asked.iter().zip(response.results) {
+        ids[*i].extend(result.vulns.into_iter().map(|v| v.id));
+        if let Some(token) = result.next_page_token.filter(|token| !token.is_empty()) {
+            next.push((*i, Some(token)));
+        }
+    }
+    Ok(next)
+}
+
+impl OsvVulnerability {
+    /// The severity of the record and its CVSS v3 base score, if one is available.
+    /// CVSS vectors take precedence over the textual ratings of distributions.
+    pub fn severity(&self) -> (Severity, Option<f64>) {
+        let all_scores = self
+            .severity
+            .iter()
+            .chain(self.affected.iter().flat_map(|a| a.severity.iter()));
+
+        let cvss = all_scores
+            .clone()
+            .filter(|s| s.kind == "CVSS_V3")
+            .filter_map(|s| cvss3_base_score(&s.score))
+            .max_by(|a, b| a.total_cmp(b));
+        if let Some(score) = cvss {
+            return (Severity::from_cvss_score(score), Some(score));
+        }
+
+        let label_of = |value: &Option<Value>, key: &str| -> Option<String> {
+            value.as_ref()?.get(key)?.as_str().map(str::to_string)
+        };
+        let mut labels: Vec<String> = all_scores
+            .filter(|s| !s.kind.starts_with("CVSS"))
+            .map(|s| s.score.clone())
+            .collect();
+        labels.extend(label_of(&self.database_specific, "severity"));
+        for affected in &self.affected {
+            labels.extend(label_of(&affected.ecosystem_specific, "urgency"));
+            labels.extend(label_of(&affected.ecosystem_specific, "severity"));
+            labels.extend(label_of(&affected.database_specific, "severity"));
+        }
+
+        let severity = labels
+            .iter()
+            .filter_map(|label| severity_from_label(label))
+            .max()
+            .unwrap_or(Severity::Unknown);
+        (severity, None)
+    }
+
+    /// Every version the record lists as fixing it, per package.
+    pub fn fixed_in(&self) -> Vec<FixedIn> {
+        let mut fixed_in = Vec::new();
+        for affected in &self.affected {
+            let Some(package) = &affected.package else { continue };
+            for event in affected.ranges.iter().flat_map(|r| r.events.iter()) {
+                if let Some(version) = &event.fixed {
+                    fixed_in.push(FixedIn {
+                        ecosystem: package.ecosystem.clone(),
+                        package: package.name.clone(),
+                        version: version.clone(),
+                    });
+                }
+            }
+        }
+        fixed_in
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FixedIn {
+    pub ecosystem: String,
+    pub package: String,
+    pub version: String,
+}
+
+/// The lowest fixed version above `installed` for a package, if there is one.
+/// Entries for the exact ecosystem release (`Debian:12`) win; otherwise entries for
+/// the base ecosystem (`Debian`) are used, as some records only name that.
+pub fn lowest_fixed_version(fixed_in: &[FixedIn], ecosystem: &str, package: &str, installed: &str) -> Option<String> {
+    let base = |e: &str| e.split(':').next().unwrap_or_default().to_string();
+    let for_package = || fixed_in.iter().filter(|f| f.package == package);
+    let exact: Vec<&FixedIn> = for_package().filter(|f| f.ecosystem == ecosystem).collect();
+    let candidates: Vec<&FixedIn> = if exact.is_empty() {
+        for_package().filter(|f| base(&f.ecosystem) == base(ecosystem)).collect()
+    } else {
+        exact
+    };
+    candidates
+        .into_iter()
+        .map(|f| f.version.as_str())
+        .filter(|fixed| compare_versions(installed, fixed).is_lt())
+        .min_by(|a, b| compare_versions(a, b))
+        .map(str::to_string)
+}
+
+fn severity_from_label(label: &str) -> Option<Severity> {
+    match label.trim().to_ascii_lowercase().as_str() {
+        "critical" => Some(Severity::Critical),
+        "high" | "important" => Some(Severity::High),
+        "medium" | "moderate" => Some(Severity::Medium),
+        "low" | "negligible" | "unimportant" => Some(Severity::Low),
+        _ => None,
+    }
+}
+
+/// Rounds up to one decimal as defined by the CVSS v3.1 specification.
+fn cvss_round_up(value: f64) -> f64 {
+    let int_input = (value * 100_000.0).round() as i64;
+    if int_input % 10_000 == 0 {
+        int_input as f64 / 100_000.0
+    } else {
+        ((int_input / 10_000) + 1) as f64 / 10.0
+    }
+}
+
+/// Computes the CVSS v3.x base score from a vector string such as
+/// `CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H`.
+pub fn cvss3_base_score(vector: &str) -> Option<f64> {
+    if !vector.starts_with("CVSS:3.") {
+        return None;
+    }
+    let metric = |key: &str| -> Option<&str> {
+        vector
+            .split('/')
+            .skip(1)
+            .find_map(|p| p.split_once(':').filter(|(k, _)| *k == key).map(|(_, v)| v))
+    };
+
+    let scope_changed = match metric("S")? {
+        "U" => false,
+        "C" => true,
+        _ => return None,
+    };
+    let attack_vector = match metric("AV")? {
+        "N" => 0.85,
+        "A" => 0.62,
+        "L" => 0.55,
+        "P" => 0.2,
+        _ => return None,
+    };
+    let attack_complexity = match metric("AC")? {
+        "L" => 0.77,
+        "H" => 0.44,
+        _ => return None,
+    };
+    let privileges_required = match (metric("PR")?, scope_changed) {
+        ("N", _) => 0.85,
+        ("L", false) => 0.62,
+        ("L", true) => 0.68,
+        ("H", false) => 0.27,
+        ("H", true) => 0.5,
+        _ => return None,
+    };
+    let user_interaction = match metric("UI")? {
+        "N" => 0.85,
+        "R" => 0.62,
+        _ => return None,
+    };
+    let cia = |key: &str| -> Option<f64> {
+        match metric(key)? {
+            "H" => Some(0.56),
+            "L" => Some(0.22),
+            "N" => Some(0.0),
+            _ => None,
+        }
+    };
+    let (c, i, a) = (cia("C")?, cia("I")?, cia("A")?);
+
+    let iss = 1.0 - (1.0 - c) * (1.0 - i) * (1.0 - a);
+    let impact = if scope_changed {
+        7.52 * (iss - 0.029) - 3.25 * (iss - 0.02).powi(15)
+    } else {
+        6.42 * iss
+    };
+    if impact <= 0.0 {
+        return Some(0.0);
+    }
+    let exploitability = 8.22 * attack_vector * attack_complexity * privileges_required * user_interaction;
+    let score = if scope_changed {
+        cvss_round_up((1.08 * (impact + exploitability)).min(10.0))
+    } else {
+        cvss_round_up((impact + exploitability).min(10.0))
+    };
+    Some(score)
+}
+
+/// Maps the distribution reported by an agent to the OSV ecosystem its packages
+/// belong to, e.g. `debian` + `12` -> `Debian:12`.
+pub fn osv_ecosystem(distribution_id: &str, os_version: &str) -> Option<String> {
+    let version = os_version.trim();
+    let mut numbers = version.split('.');
+    let major = numbers.next().filter(|m| !m.is_empty() && m.bytes().all(|b| b.is_ascii_digit()))?;
+    let minor = numbers.next();
+
+    match distribution_id.trim().to_ascii_lowercase().as_str() {
+        "debian" => Some(format!("Debian:{major}")),
+        "ubuntu" => {
+            let release = format!("{major}.{}", minor?);
+            let is_lts = minor == Some("04") && major.parse::<u32>().ok()? % 2 == 0;
+            Some(if is_lts { format!("Ubuntu:{release}:LTS") } else { format!("Ubuntu:{release}") })
+        }
+        "alpine" => Some(format!("Alpine:v{major}.{}", minor?)),
+        "rocky" => Some(format!("Rocky Linux:{major}")),
+        "almalinux" => Some(format!("AlmaLinux:{major}")),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn computes_cvss3_base_scores() {
+        assert_eq!(cvss3_base_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:N/S:U/C:H/I:H/A:H"), Some(9.8));
+        assert_eq!(cvss3_base_score("CVSS:3.1/AV:N/AC:L/PR:N/UI:R/S:C/C:L/I:L/A:N"), Some(6.1));
+        assert_eq!(cvss3_base_score("CVSS:3.0/AV:L/AC:L/PR:L/UI:N/S:U/C:N/I:N/A:N"), Some(0.0));
+        assert_eq!(cvss3_base_score("CVSS:4.0/AV:N/AC:L/AT:N/PR:N/UI:N"), None);
+    }
+
+    #[test]
+    fn picks_lowest_applicable_fixed_version() {
+        let fixed = |ecosystem: &str, version: &str| FixedIn {
+            ecosystem: ecosystem.to_string(),
+            package: "openssl".to_string(),
+            version: version.to_string(),
+        };
+        let fixed_in = vec![
+            fixed("Debian:11", "1.1.1n-0+deb11u5"),
+            fixed("Debian:12", "3.0.11-1~deb12u2"),
+            fixed("Debian:12", "3.0.13-1~deb12u1"),
+            fixed("Alpine:v3.19", "3.1.4-r5"),
+        ];
+        assert_eq!(
+            lowest_fixed_version(&fixed_in, "Debian:12", "openssl", "3.0.11-1~deb12u1").as_deref(),
+            Some("3.0.11-1~deb12u2")
+        );
+        assert_eq!(lowest_fixed_version(&fixed_in, "Debian:12", "openssl", "3.0.14-1"), None);
+    }
+
+    #[test]
+    fn follows_page_tokens_of_incomplete_queries() {
+        let mut ids = vec![Vec::new(); 3];
+        let first: QueryBatchResponse = serde_json::from_value(serde_json::json!({
+            "results": [
+                {"vulns": [{"id": "A-1"}], "next_page_token": "t1"},
+                {},
+                {"vulns": [{"id": "C-1"}]}
+            ]
+        }))
+        .unwrap();
+        let asked: Vec<(usize, Option<String>)> = (0..3).map(|i| (i, None)).collect();
+        let pending = collect_page(&mut ids, &asked, first).unwrap();
+        assert_eq!(pending, vec![(0, Some("t1".to_string()))]);
+
+        let second: QueryBatchResponse =
+            serde_json::from_value(serde_json::json!({"results": [{"vulns": [{"id": "A-2"}]}]})).unwrap();
+        assert!(collect_page(&mut ids, &pending, second).unwrap().is_empty());
+        assert_eq!(ids, vec![vec!["A-1".to_string(), "A-2".to_string()], vec![], vec!["C-1".to_string()]]);
+
+        let short: QueryBatchResponse = serde_json::from_value(serde_json::json!({"results": []})).unwrap();
+        assert!(collect_page(&mut ids, &pending, short).is_err());
+    }
+
+    #[test]
+    fn maps_distributions_to_osv_ecosystems() {
+        assert_eq!(osv_ecosystem("debian", "12").as_deref(), Some("Debian:12"));
+        assert_eq!(osv_ecosystem("ubuntu", "22.04").as_deref(), Some("Ubuntu:22.04:LTS"));
+        assert_eq!(osv_ecosystem("ubuntu", "23.10").as_deref(), Some("Ubuntu:23.10"));
+        assert_eq!(osv_ecosystem("alpine", "3.19.1").as_deref(), Some("Alpine:v3.19"));
+        assert_eq!(osv_ecosystem("arch", "rolling"), None);
+    }
+}
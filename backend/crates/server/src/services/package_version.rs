@@ -0,0 +1,114 @@
+//! Package version ordering following dpkg's rules, which also give the expected
+//! result for RPM and APK version strings in practice.
+//!
+//! A version is `[epoch:]upstream[-revision]`. Parts are compared alternately as
+//! non-digit runs (where `~` sorts before everything, even the end of the string,
+//! and letters sort before other characters) and numeric runs.
+
+use std::cmp::Ordering;
+
+use crate::web::models::inventory_models::VersionOp;
+
+fn char_order(c: Option<u8>) -> i32 {
+    match c {
+        None => 0,
+        Some(b'~') => -1,
+        Some(c) if c.is_ascii_digit() => 0,
+        Some(c) if c.is_ascii_alphabetic() => c as i32,
+        Some(c) => c as i32 + 256,
+    }
+}
+
+fn compare_fragment(a: &[u8], b: &[u8]) -> Ordering {
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() || j < b.len() {
+        while (i < a.len() && !a[i].is_ascii_digit()) || (j < b.len() && !b[j].is_ascii_digit()) {
+            let ac = char_order(a.get(i).copied());
+            let bc = char_order(b.get(j).copied());
+            if ac != bc {
+                return ac.cmp(&bc);
+            }
+            i += 1;
+            j += 1;
+        }
+        while i < a.len() && a[i] == b'0' {
+            i += 1;
+        }
+        while j < b.len() && b[j] == b'0' {
+            j += 1;
+        }
+        let mut first_diff = Ordering::Equal;
+        while i < a.len() && a[i].is_ascii_digit() && j < b.len() && b[j].is_ascii_digit() {
+            if first_diff == Ordering::Equal {
+                first_diff = a[i].cmp(&b[j]);
+            }
+            i += 1;
+            j += 1;
+        }
+        if i < a.len() && a[i].is_ascii_digit() {
+            return Ordering::Greater;
+        }
+        if j < b.len() && b[j].is_ascii_digit() {
+            return Ordering::Less;
+        }
+        if first_diff != Ordering::Equal {
+            return first_diff;
+        }
+    }
+    Ordering::Equal
+}
+
+fn split_version(version: &str) -> (u64, &str, &str) {
+    let (epoch, rest) = match version.split_once(':') {
+        Some((e, rest)) if !e.is_empty() && e.bytes().all(|c| c.is_ascii_digit()) => {
+            (e.parse().unwrap_or(0), rest)
+        }
+        _ => (0, version),
+    };
+    match rest.rsplit_once('-') {
+        Some((upstream, revision)) => (epoch, upstream, revision),
+        None => (epoch, rest, ""),
+    }
+}
+
+pub fn compare_versions(a: &str, b: &str) -> Ordering {
+    let (a_epoch, a_upstream, a_revision) = split_version(a.trim());
+    let (b_epoch, b_upstream, b_revision) = split_version(b.trim());
+    a_epoch
+        .cmp(&b_epoch)
+        .then_with(|| compare_fragment(a_upstream.as_bytes(), b_upstream.as_bytes()))
+        .then_with(|| compare_fragment(a_revision.as_bytes(), b_revision.as_bytes()))
+}
+
+pub fn version_matches(installed: &str, op: VersionOp, wanted: &str) -> bool {
+    let ordering = compare_versions(installed, wanted);
+    match op {
+        VersionOp::Lt => ordering == Ordering::Less,
+        VersionOp::Le => ordering != Ordering::Greater,
+        VersionOp::Eq => ordering == Ordering::Equal,
+        VersionOp::Ge => ordering != Ordering::Less,
+        VersionOp::Gt => ordering == Ordering::Greater,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_versions_like_dpkg() {
+        assert_eq!(compare_versions("1.1.1w-0+deb11u1", "3.0.2"), Ordering::Less);
+        assert_eq!(compare_versions("3.0.2-0ubuntu1.10", "3.0.2-0ubuntu1.9"), Ordering::Greater);
+        assert_eq!(compare_versions("1.0~rc1", "1.0"), Ordering::Less);
+        assert_eq!(compare_versions("1:1.0", "2.0"), Ordering::Greater);
+        assert_eq!(compare_versions("1.02", "1.2"), Ordering::Equal);
+        assert_eq!(compare_versions("1.0a", "1.0+"), Ordering::Less);
+    }
+
+    #[test]
+    fn applies_operators() {
+        assert!(version_matches("3.0.2-0ubuntu1", VersionOp::Lt, "3.0.13"));
+        assert!(version_matches("3.0.13", VersionOp::Ge, "3.0.13"));
+        assert!(!version_matches("3.0.13", VersionOp::Gt, "3.0.13"));
+    }
+}
@@ -0,0 +1,150 @@
+//! Power actions through cloud provider APIs, for VPS whose agent can't be
+//! reached. Each provider is a single authenticated POST per action.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::time::Duration;
+
+use crate::web::error::AppError;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProviderKind {
+    Hetzner,
+    Vultr,
+    #[serde(rename = "digitalocean")]
+    DigitalOcean,
+}
+
+impl ProviderKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProviderKind::Hetzner => "hetzner",
+            ProviderKind::Vultr => "vultr",
+            ProviderKind::DigitalOcean => "digitalocean",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        [ProviderKind::Hetzner, ProviderKind::Vultr, ProviderKind::DigitalOcean]
+            .into_iter()
+            .find(|p| p.as_str() == value)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PowerAction {
+    Reboot,
+    Stop,
+    Start,
+}
+
+impl PowerAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PowerAction::Reboot => "reboot",
+            PowerAction::Stop => "stop",
+            PowerAction::Start => "start",
+        }
+    }
+}
+
+/// URL and optional JSON body of the API call performing `action`.
+fn power_request(provider: ProviderKind, server_id: &str, action: PowerAction) -> (String, Option<Value>) {
+    let server_id = urlencoding::encode(server_id);
+    match provider {
+        ProviderKind::Hetzner => {
+            let name = match action {
+                PowerAction::Reboot => "reboot",
+                PowerAction::Stop => "poweroff",
+                PowerAction::Start => "poweron",
+            };
+            (format!("https://api.hetzner.cloud/v1/servers/{server_id}/actions/{name}"), None)
+        }
+        ProviderKind::Vultr => {
+            let name = match action {
+                PowerAction::Reboot => "reboot",
+                PowerAction::Stop => "halt",
+                PowerAction::Start => "start",
+            };
+            (format!("https://api.vultr.com/v2/instances/{server_id}/{name}"), None)
+        }
+        ProviderKind::DigitalOcean => {
+            let kind = match action {
+                PowerAction::Reboot => "reboot",
+                PowerAction::Stop => "power_off",
+                PowerAction::Start => "power_on",
+            };
+            (
+                format!("https://api.digitalocean.com/v2/droplets/{server_id}/actions"),
+                Some(json!({ "type": kind })),
+            )
+        }
+    }
+}
+
+/// Asks the provider to perform `action`. Providers run actions asynchronously,
+/// so success means the request was accepted, not that the server is back up.
+pub async fn execute_power_action(
+    provider: ProviderKind,
+    token: &str,
+    server_id: &str,
+    action: PowerAction,
+) -> Result<(), AppError> {
+    let http = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| AppError::InternalServerError(format!("Failed to build provider client: {e}")))?;
+    let (url, body) = power_request(provider, server_id, action);
+    let mut request = http.post(url).bearer_auth(token);
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| AppError::ServerError(format!("{} API request failed: {e}", provider.as_str())))?;
+    let status = response.status();
+    if !status.is_success() {
+        let detail = response.text().await.unwrap_or_default();
+        return Err(AppError::ServerError(format!(
+            "{} API returned {status}: {}",
+            provider.as_str(),
+            detail.chars().take(500).collect::<String>()
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_provider_requests() {
+        assert_eq!(
+            power_request(ProviderKind::Hetzner, "42", PowerAction::Stop),
+            ("https://api.hetzner.cloud/v1/servers/42/actions/poweroff".to_string(), None)
+        );
+        assert_eq!(
+            power_request(ProviderKind::Vultr, "ab-cd", PowerAction::Start),
+            ("https://api.vultr.com/v2/instances/ab-cd/start".to_string(), None)
+        );
+        assert_eq!(
+            power_request(ProviderKind::DigitalOcean, "7", PowerAction::Reboot),
+            (
+                "https://api.digitalocean.com/v2/droplets/7/actions".to_string(),
+                Some(json!({ "type": "reboot" }))
+            )
+        );
+    }
+
+    #[test]
+    fn parses_provider_names() {
+        assert_eq!(ProviderKind::parse("digitalocean"), Some(ProviderKind::DigitalOcean));
+        assert_eq!(ProviderKind::parse("aws"), None);
+    }
+}
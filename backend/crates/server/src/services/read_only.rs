@@ -0,0 +1,86 @@
+//! Instance-wide read-only mode, toggled by admins (e.g. during migrations or
+//! incident forensics). While it is on, API writes and command dispatch are
+//! refused with 503; dashboards, metrics ingestion and alerting keep running.
+//! The state is kept in `settings` so it survives restarts.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, RwLock};
+
+use crate::db::duckdb_service::{settings_service, DuckDbPool};
+use crate::web::error::AppError;
+
+pub const READ_ONLY_SETTING_KEY: &str = "read_only_mode";
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReadOnlyState {
+    /// Shown in the dashboard banner and returned with every refused request.
+    pub message: String,
+    pub enabled_by: String,
+    pub enabled_at: DateTime<Utc>,
+}
+
+/// Shared handle to the current mode; cheap to clone.
+#[derive(Debug, Clone, Default)]
+pub struct ReadOnlyMode {
+    state: Arc<RwLock<Option<ReadOnlyState>>>,
+}
+
+impl ReadOnlyMode {
+    /// Restores the mode saved before the last shutdown.
+    pub async fn load(pool: DuckDbPool) -> Result<Self, AppError> {
+        let state = settings_service::get_setting(pool, READ_ONLY_SETTING_KEY)
+            .await?
+            .and_then(|s| serde_json::from_value::<Option<ReadOnlyState>>(s.value).ok())
+            .flatten();
+        Ok(Self {
+            state: Arc::new(RwLock::new(state)),
+        })
+    }
+
+    pub fn current(&self) -> Option<ReadOnlyState> {
+        self.state.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Switches the mode and persists it. `None` turns read-only mode off.
+    pub async fn set(&self, pool: DuckDbPool, state: Option<ReadOnlyState>) -> Result<(), AppError> {
+        settings_service::update_setting(pool, READ_ONLY_SETTING_KEY, &serde_json::to_value(&state)?).await?;
+        *self.state.write().unwrap_or_else(|e| e.into_inner()) = state;
+        Ok(())
+    }
+
+    /// The reason writes are refused, if they are.
+    pub fn refusal(&self) -> Option<String> {
+        self.current().map(|state| format!("The server is in read-only mode: {}", state.message))
+    }
+
+    /// Fails with 503 while read-only mode is on.
+    pub fn ensure_writable(&self) -> Result<(), AppError> {
+        match self.refusal() {
+            Some(message) => Err(AppError::ServiceUnavailable(message)),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refusal_carries_the_banner_message() {
+        let mode = ReadOnlyMode::default();
+        assert!(mode.ensure_writable().is_ok());
+        *mode.state.write().unwrap() = Some(ReadOnlyState {
+            message: "Database migration in progress".to_string(),
+            enabled_by: "admin".to_string(),
+            enabled_at: Utc::now(),
+        });
+        assert_eq!(
+            mode.refusal().as_deref(),
+            Some("The server is in read-only mode: Database migration in progress")
+        );
+        assert!(matches!(mode.ensure_writable(), Err(AppError::ServiceUnavailable(_))));
+    }
+}
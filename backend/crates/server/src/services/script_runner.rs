@@ -0,0 +1,130 @@
+//! Runs a saved command script outside of the interactive batch command flow
+//! (notification remediation, chat commands, schedules).
+
+use nodenexus_common::agent_service::CommandType as GrpcCommandType;
+use std::collections::BTreeMap;
+use tracing::error;
+use uuid::Uuid;
+
+use crate::db::duckdb_service::command_script_service::CommandScript;
+use crate::db::duckdb_service::{batch_command_service, vps_selection_service, vps_service, DuckDbPool};
+use crate::server::command_dispatcher::CommandDispatcher;
+use crate::services::batch_execution::{self, DispatchTask};
+use crate::services::script_template::{self, TargetVps};
+use crate::web::error::AppError;
+use crate::web::models::batch_command_models::{
+    BatchTargets, CreateBatchCommandRequest, ExecutionStrategy, ResolvedTarget,
+};
+use crate::web::AppState;
+
+/// Creates a batch command for `script` on `vps_id` and dispatches it to the agent,
+/// with parameters at their defaults. Returns the batch command id, whose
+/// progress shows up with other batch commands.
+pub async fn run_script_on_vps(
+    app_state: &AppState,
+    user_id: i32,
+    script: &CommandScript,
+    vps_id: i32,
+    execution_alias: String,
+) -> Result<Uuid, AppError> {
+    app_state.read_only.ensure_writable()?;
+    run_script(
+        app_state.duckdb_pool.clone(),
+        &app_state.command_dispatcher,
+        user_id,
+        script,
+        vec![vps_id],
+        &BTreeMap::new(),
+        execution_alias,
+    )
+    .await
+}
+
+/// Creates a batch command for `script` on every VPS in `vps_ids` and dispatches
+/// it to the agents, rendered with `values` for each VPS. Callers are
+/// responsible for the read-only mode check.
+pub async fn run_script(
+    pool: DuckDbPool,
+    command_dispatcher: &CommandDispatcher,
+    user_id: i32,
+    script: &CommandScript,
+    vps_ids: Vec<i32>,
+    values: &BTreeMap<String, String>,
+    execution_alias: String,
+) -> Result<Uuid, AppError> {
+    let values = script_template::resolve_values(&script.parameters, values).map_err(AppError::InvalidInput)?;
+    let targets: BTreeMap<i32, TargetVps> = vps_service::get_vps_by_ids(pool.clone(), vps_ids.clone())
+        .await?
+        .iter()
+        .map(|vps| (vps.id, TargetVps::from(vps)))
+        .collect();
+
+    // The batch records the template, so secret values aren't stored with it.
+    let request = CreateBatchCommandRequest {
+        command_content: Some(script.script_content.clone()),
+        script_id: None,
+        working_directory: Some(script.working_directory.clone()),
+        targets: BatchTargets {
+            target_vps_ids: vps_ids.clone(),
+            ..Default::default()
+        },
+        execution_alias: Some(execution_alias),
+        strategy: ExecutionStrategy::Parallel,
+        timeout_seconds: None,
+        retry: None,
+    };
+    let resolved_targets = vps_ids
+        .into_iter()
+        .map(|vps_id| ResolvedTarget {
+            vps_id,
+            matched_selector: vps_selection_service::EXPLICIT_TARGET.to_string(),
+        })
+        .collect();
+    // Not started by an API request, so the batch gets a trace ID of its own.
+    let trace_id = Uuid::new_v4().to_string();
+    let (batch_task, child_tasks) = batch_command_service::create_batch_command(
+        pool,
+        user_id,
+        request,
+        resolved_targets,
+        Some(script.id),
+        trace_id.clone(),
+    )
+    .await?;
+
+    let mut tasks = Vec::new();
+    for child_task in child_tasks {
+        let Some(target) = targets.get(&child_task.vps_id) else {
+            error!(child_task_id = %child_task.child_command_id, vps_id = child_task.vps_id, "Script target VPS not found.");
+            continue;
+        };
+        let command = script_template::render(
+            &script.script_content,
+            &script.language,
+            &script.parameters,
+            &values,
+            target,
+            false,
+        );
+        tasks.push(DispatchTask {
+            child_command_id: child_task.child_command_id,
+            vps_id: child_task.vps_id,
+            wave: child_task.wave as usize,
+            command_content: command,
+            command_type: GrpcCommandType::AdhocCommand,
+            working_directory: Some(script.working_directory.clone()),
+            timeout_seconds: 0,
+        });
+    }
+    // Parallel, so this returns once every task is dispatched.
+    batch_execution::run_batch(
+        command_dispatcher.clone(),
+        batch_task.batch_command_id,
+        ExecutionStrategy::Parallel,
+        false,
+        tasks,
+        trace_id,
+    )
+    .await;
+    Ok(batch_task.batch_command_id)
+}
@@ -0,0 +1,354 @@
+//! Typed parameters for command scripts. Placeholders such as `{{port}}` in a
+//! script are replaced with validated values before dispatch, so one script can
+//! serve servers that differ only in a port or a path.
+//!
+//! Values are inserted as single-quoted words for the script's language (ints
+//! are inserted bare), so a placeholder must not itself be wrapped in quotes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::db::entities::command_script::ScriptLanguage;
+use crate::db::entities::vps;
+
+/// Shown instead of secret values in previews.
+pub const SECRET_MASK: &str = "********";
+
+/// Placeholders filled from the target VPS rather than by the caller.
+pub const BUILTIN_PARAMETERS: &[&str] = &["vps_id", "vps_name", "vps_ip"];
+
+const MAX_PARAMETERS: usize = 32;
+const MAX_NAME_LEN: usize = 64;
+const MAX_VALUE_LEN: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ParameterType {
+    String {
+        #[serde(default)]
+        max_length: Option<usize>,
+    },
+    Enum {
+        options: Vec<String>,
+    },
+    Int {
+        #[serde(default)]
+        min: Option<i64>,
+        #[serde(default)]
+        max: Option<i64>,
+    },
+    /// Supplied on every run and never stored or shown.
+    Secret,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScriptParameter {
+    pub name: String,
+    #[serde(flatten)]
+    pub kind: ParameterType,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Used when a run doesn't supply a value; parameters without one are required.
+    #[serde(default)]
+    pub default: Option<String>,
+}
+
+/// The VPS a script is rendered for.
+#[derive(Debug, Clone)]
+pub struct TargetVps {
+    pub id: i32,
+    pub name: String,
+    pub ip_address: Option<String>,
+}
+
+impl From<&vps::Model> for TargetVps {
+    fn from(vps: &vps::Model) -> Self {
+        TargetVps {
+            id: vps.id,
+            name: vps.name.clone(),
+            ip_address: vps.ip_address.clone(),
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Splits `content` into literal text and placeholder names. `{{` not followed
+/// by an identifier and `}}` is kept as text.
+fn segments(content: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = content;
+    while let Some(open) = rest.find("{{") {
+        let after_open = &rest[open + 2..];
+        let placeholder = after_open
+            .find("}}")
+            .map(|close| (after_open[..close].trim(), close))
+            .filter(|(name, _)| is_identifier(name));
+        match placeholder {
+            Some((name, close)) => {
+                segments.push(Segment::Text(&rest[..open]));
+                segments.push(Segment::Placeholder(name));
+                rest = &after_open[close + 2..];
+            }
+            None => {
+                segments.push(Segment::Text(&rest[..open + 2]));
+                rest = after_open;
+            }
+        }
+    }
+    segments.push(Segment::Text(rest));
+    segments
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    Placeholder(&'a str),
+}
+
+fn validate_value(parameter: &ScriptParameter, value: &str) -> Result<(), String> {
+    let name = &parameter.name;
+    if value.len() > MAX_VALUE_LEN {
+        return Err(format!("Value of '{name}' is longer than {MAX_VALUE_LEN} bytes"));
+    }
+    if value.contains('\0') {
+        return Err(format!("Value of '{name}' contains a NUL character"));
+    }
+    match &parameter.kind {
+        ParameterType::String { max_length } => {
+            if let Some(max_length) = max_length {
+                if value.chars().count() > *max_length {
+                    return Err(format!("'{name}' must be at most {max_length} characters"));
+                }
+            }
+        }
+        ParameterType::Enum { options } => {
+            if !options.iter().any(|option| option == value) {
+                return Err(format!("'{name}' must be one of: {}", options.join(", ")));
+            }
+        }
+        ParameterType::Int { min, max } => {
+            let number: i64 = value
+                .trim()
+                .parse()
+                .map_err(|_| format!("'{name}' must be an integer"))?;
+            if min.is_some_and(|min| number < min) || max.is_some_and(|max| number > max) {
+                let bound = |b: Option<i64>| b.map_or_else(|| "…".to_string(), |b| b.to_string());
+                return Err(format!("'{name}' must be between {} and {}", bound(*min), bound(*max)));
+            }
+        }
+        ParameterType::Secret => {}
+    }
+    Ok(())
+}
+
+/// Checks a script's parameter declarations and that every placeholder in
+/// `content` is declared or built in.
+pub fn validate_definition(content: &str, parameters: &[ScriptParameter]) -> Result<(), String> {
+    if parameters.len() > MAX_PARAMETERS {
+        return Err(format!("A script may declare at most {MAX_PARAMETERS} parameters"));
+    }
+    let mut names = HashSet::new();
+    for parameter in parameters {
+        let name = parameter.name.as_str();
+        if !is_identifier(name) || name.len() > MAX_NAME_LEN {
+            return Err(format!(
+                "Invalid parameter name '{name}': use letters, digits and '_', starting with a letter or '_'"
+            ));
+        }
+        if BUILTIN_PARAMETERS.contains(&name) {
+            return Err(format!("'{name}' is a built-in parameter"));
+        }
+        if !names.insert(name) {
+            return Err(format!("Parameter '{name}' is declared twice"));
+        }
+        match &parameter.kind {
+            ParameterType::Enum { options } if options.is_empty() => {
+                return Err(format!("Enum parameter '{name}' needs at least one option"));
+            }
+            ParameterType::Int { min: Some(min), max: Some(max) } if min > max => {
+                return Err(format!("Parameter '{name}' has a minimum above its maximum"));
+            }
+            ParameterType::Secret if parameter.default.is_some() => {
+                return Err(format!("Secret parameter '{name}' can't have a default"));
+            }
+            _ => {}
+        }
+        if let Some(default) = &parameter.default {
+            validate_value(parameter, default)?;
+        }
+    }
+    for segment in segments(content) {
+        if let Segment::Placeholder(name) = segment {
+            if !names.contains(name) && !BUILTIN_PARAMETERS.contains(&name) {
+                return Err(format!("Placeholder '{{{{{name}}}}}' is not a declared parameter"));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The value of every parameter for one run: supplied values, then defaults.
+pub fn resolve_values(
+    parameters: &[ScriptParameter],
+    values: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>, String> {
+    if let Some(unknown) = values.keys().find(|key| !parameters.iter().any(|p| &p.name == *key)) {
+        return Err(format!("Unknown parameter '{unknown}'"));
+    }
+    let mut resolved = BTreeMap::new();
+    for parameter in parameters {
+        let value = values
+            .get(&parameter.name)
+            .or(parameter.default.as_ref())
+            .ok_or_else(|| format!("Missing value for parameter '{}'", parameter.name))?;
+        validate_value(parameter, value)?;
+        resolved.insert(parameter.name.clone(), value.clone());
+    }
+    Ok(resolved)
+}
+
+/// Quotes `value` as a single literal word.
+pub fn quote(language: &ScriptLanguage, value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('\'');
+    for c in value.chars() {
+        match language {
+            ScriptLanguage::Shell if c == '\'' => quoted.push_str("'\\''"),
+            // PowerShell also treats typographic single quotes as quotes.
+            ScriptLanguage::PowerShell if matches!(c, '\'' | '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}') => {
+                quoted.push(c);
+                quoted.push(c);
+            }
+            _ => quoted.push(c),
+        }
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// Renders `content` for `vps` with values from [`resolve_values`]. With
+/// `mask_secrets` secret values are replaced by [`SECRET_MASK`].
+pub fn render(
+    content: &str,
+    language: &ScriptLanguage,
+    parameters: &[ScriptParameter],
+    values: &BTreeMap<String, String>,
+    vps: &TargetVps,
+    mask_secrets: bool,
+) -> String {
+    let mut rendered = String::with_capacity(content.len());
+    for segment in segments(content) {
+        let name = match segment {
+            Segment::Text(text) => {
+                rendered.push_str(text);
+                continue;
+            }
+            Segment::Placeholder(name) => name,
+        };
+        match name {
+            "vps_id" => rendered.push_str(&vps.id.to_string()),
+            "vps_name" => rendered.push_str(&quote(language, &vps.name)),
+            "vps_ip" => rendered.push_str(&quote(language, vps.ip_address.as_deref().unwrap_or_default())),
+            _ => {
+                let parameter = parameters.iter().find(|p| p.name == name);
+                let value = values.get(name).map(String::as_str).unwrap_or_default();
+                match parameter.map(|p| &p.kind) {
+                    Some(ParameterType::Int { .. }) => rendered.push_str(value.trim()),
+                    Some(ParameterType::Secret) if mask_secrets => rendered.push_str(&quote(language, SECRET_MASK)),
+                    _ => rendered.push_str(&quote(language, value)),
+                }
+            }
+        }
+    }
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parameter(name: &str, kind: ParameterType, default: Option<&str>) -> ScriptParameter {
+        ScriptParameter {
+            name: name.to_string(),
+            kind,
+            description: None,
+            default: default.map(str::to_string),
+        }
+    }
+
+    fn target() -> TargetVps {
+        TargetVps {
+            id: 7,
+            name: "web-1".to_string(),
+            ip_address: Some("10.0.0.7".to_string()),
+        }
+    }
+
+    #[test]
+    fn definitions_are_validated() {
+        let port = vec![parameter("port", ParameterType::Int { min: Some(1), max: Some(65535) }, Some("8080"))];
+        assert!(validate_definition("ss -ltn sport = :{{port}} # {{ vps_name }}", &port).is_ok());
+        assert!(validate_definition("echo {{missing}}", &port).is_err());
+        assert!(validate_definition("echo", &[port[0].clone(), port[0].clone()]).is_err());
+        assert!(validate_definition("echo", &[parameter("vps_id", ParameterType::Secret, None)]).is_err());
+        assert!(validate_definition("echo", &[parameter("token", ParameterType::Secret, Some("x"))]).is_err());
+        let bad_default = parameter("port", ParameterType::Int { min: None, max: Some(10) }, Some("80"));
+        assert!(validate_definition("echo", &[bad_default]).is_err());
+        // Braces that aren't placeholders are left alone.
+        assert!(validate_definition("awk '{{print $1}}' && echo ${{x}", &[]).is_ok());
+    }
+
+    #[test]
+    fn values_fall_back_to_defaults_and_are_checked() {
+        let parameters = vec![
+            parameter("mode", ParameterType::Enum { options: vec!["fast".into(), "safe".into()] }, Some("safe")),
+            parameter("token", ParameterType::Secret, None),
+        ];
+        let mut values = BTreeMap::from([("token".to_string(), "s3cret".to_string())]);
+        let resolved = resolve_values(&parameters, &values).unwrap();
+        assert_eq!(resolved["mode"], "safe");
+
+        values.insert("mode".to_string(), "reckless".to_string());
+        assert!(resolve_values(&parameters, &values).is_err());
+        assert!(resolve_values(&parameters, &BTreeMap::new()).is_err());
+        values.insert("mode".to_string(), "fast".to_string());
+        values.insert("other".to_string(), "1".to_string());
+        assert!(resolve_values(&parameters, &values).is_err());
+    }
+
+    #[test]
+    fn rendering_quotes_values() {
+        let parameters = vec![
+            parameter("path", ParameterType::String { max_length: None }, None),
+            parameter("port", ParameterType::Int { min: None, max: None }, None),
+            parameter("token", ParameterType::Secret, None),
+        ];
+        let values = BTreeMap::from([
+            ("path".to_string(), "/srv/it's here; rm -rf /".to_string()),
+            ("port".to_string(), "8080".to_string()),
+            ("token".to_string(), "s3cret".to_string()),
+        ]);
+        let content = "cd {{path}} && run --port {{port}} --token {{token}} --host {{vps_name}}/{{vps_id}}";
+        assert_eq!(
+            render(content, &ScriptLanguage::Shell, &parameters, &values, &target(), false),
+            "cd '/srv/it'\\''s here; rm -rf /' && run --port 8080 --token 's3cret' --host 'web-1'/7"
+        );
+        assert_eq!(
+            render("echo {{token}}", &ScriptLanguage::Shell, &parameters, &values, &target(), true),
+            "echo '********'"
+        );
+        assert_eq!(quote(&ScriptLanguage::PowerShell, "it's"), "'it''s'");
+    }
+
+    #[test]
+    fn parameters_deserialize_with_a_type_tag() {
+        let parameter: ScriptParameter =
+            serde_json::from_str(r#"{"name":"port","type":"int","min":1,"default":"22"}"#).unwrap();
+        assert_eq!(parameter.kind, ParameterType::Int { min: Some(1), max: None });
+        assert_eq!(parameter.default.as_deref(), Some("22"));
+    }
+}
@@ -0,0 +1,47 @@
+//! Signed tokens for read-only share links.
+//!
+//! Tokens are JWTs signed with a key derived from the server's JWT secret, so they
+//! can never be accepted as login tokens. They only carry the share link id; scope,
+//! expiry and revocation are always checked against the `share_links` row.
+
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+use crate::db::entities::share_link;
+use crate::web::error::AppError;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ShareClaims {
+    /// Share link id.
+    sid: i32,
+    exp: usize,
+}
+
+fn share_key(jwt_secret: &str) -> Vec<u8> {
+    format!("{jwt_secret}:share-links").into_bytes()
+}
+
+/// Issues the token for a share link. The same link always yields the same token.
+pub fn issue_share_token(link: &share_link::Model, jwt_secret: &str) -> Result<String, AppError> {
+    let claims = ShareClaims {
+        sid: link.id,
+        exp: link.expires_at.timestamp() as usize,
+    };
+    encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(&share_key(jwt_secret)),
+    )
+    .map_err(|e| AppError::TokenCreationError(e.to_string()))
+}
+
+/// Verifies the signature and expiry of a share token and returns the share link id.
+pub fn verify_share_token(token: &str, jwt_secret: &str) -> Result<i32, AppError> {
+    decode::<ShareClaims>(
+        token,
+        &DecodingKey::from_secret(&share_key(jwt_secret)),
+        &Validation::default(),
+    )
+    .map(|data| data.claims.sid)
+    .map_err(|_| AppError::Unauthorized("Invalid or expired share link".to_string()))
+}
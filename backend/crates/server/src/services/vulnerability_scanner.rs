@@ -0,0 +1,270 @@
+//! Periodic matching of the collected package inventory against OSV.
+//!
+//! Identical (ecosystem, package, version) triples are queried once for the whole
+//! fleet. Vulnerability details are cached and refreshed after a week. Matches that
+//! weren't present in the previous scan are notified to each owner's configured
+//! channels when they reach the owner's minimum severity.
+
+use chrono::Duration;
+use futures::stream::{self, StreamExt};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+use crate::db::duckdb_service::vulnerability_service::{
+    self, ScanHost, VulnerabilityMatch, VulnerabilityRecord,
+};
+use crate::db::duckdb_service::{notification_service, DuckDbPool};
+use crate::notifications::encryption::EncryptionService;
+use crate::services::osv_client::{
+    lowest_fixed_version, osv_ecosystem, OsvClient, PackageQuery, MAX_BATCH_QUERIES,
+};
+use crate::web::error::AppError;
+use crate::web::models::vulnerability_models::Severity;
+
+const DETAILS_MAX_AGE_DAYS: i64 = 7;
+const DETAIL_FETCH_CONCURRENCY: usize = 8;
+/// Caps the number of matches listed in a single notification.
+const MAX_ALERT_LINES: usize = 20;
+
+struct NewFinding {
+    vps_name: String,
+    vps_id: i32,
+    severity: Severity,
+    label: String,
+    finding: VulnerabilityMatch,
+}
+
+/// The package name OSV knows a package by. Debian and Ubuntu advisories are
+/// published per source package.
+fn query_name(ecosystem: &str, name: &str, source_name: Option<&str>) -> String {
+    if ecosystem.starts_with("Debian") || ecosystem.starts_with("Ubuntu") {
+        if let Some(source) = source_name.filter(|s| !s.is_empty()) {
+            return source.to_string();
+        }
+    }
+    name.to_string()
+}
+
+fn host_queries(host: &ScanHost) -> Option<(String, BTreeSet<PackageQuery>)> {
+    let ecosystem = osv_ecosystem(
+        host.distribution_id.as_deref().unwrap_or_default(),
+        host.os_version.as_deref().unwrap_or_default(),
+    )?;
+    let queries = host
+        .packages
+        .iter()
+        .map(|p| PackageQuery {
+            ecosystem: ecosystem.clone(),
+            name: query_name(&ecosystem, &p.name, p.source_name.as_deref()),
+            version: p.version.clone(),
+        })
+        .collect();
+    Some((ecosystem, queries))
+}
+
+/// Runs one scan over all hosts with inventory. Returns the number of new matches.
+pub async fn run_vulnerability_scan(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    osv_api_url: &str,
+) -> Result<usize, AppError> {
+    let client = OsvClient::new(osv_api_url)?;
+    let hosts = vulnerability_service::get_scan_hosts(pool.clone()).await?;
+
+    let mut per_host = Vec::new();
+    let mut unique_queries: BTreeSet<PackageQuery> = BTreeSet::new();
+    for host in hosts {
+        match host_queries(&host) {
+            Some((ecosystem, queries)) => {
+                unique_queries.extend(queries.iter().cloned());
+                per_host.push((host, ecosystem, queries));
+            }
+            None => debug!(
+                vps_id = host.vps_id,
+                distribution = ?host.distribution_id,
+                "Skipping vulnerability scan: distribution not supported by OSV."
+            ),
+        }
+    }
+    if per_host.is_empty() {
+        return Ok(0);
+    }
+
+    let unique_queries: Vec<PackageQuery> = unique_queries.into_iter().collect();
+    let mut vulns_by_query: HashMap<PackageQuery, Vec<String>> = HashMap::new();
+    for chunk in unique_queries.chunks(MAX_BATCH_QUERIES) {
+        let results = client.query_batch(chunk).await?;
+        for (query, ids) in chunk.iter().zip(results) {
+            if !ids.is_empty() {
+                vulns_by_query.insert(query.clone(), ids);
+            }
+        }
+    }
+
+    let all_ids: BTreeSet<String> = vulns_by_query.values().flatten().cloned().collect();
+    let to_fetch = vulnerability_service::get_ids_needing_refresh(
+        pool.clone(),
+        all_ids.iter().cloned().collect(),
+        Duration::days(DETAILS_MAX_AGE_DAYS),
+    )
+    .await?;
+    if !to_fetch.is_empty() {
+        info!(count = to_fetch.len(), "Fetching vulnerability details from OSV.");
+        let records: Vec<VulnerabilityRecord> = stream::iter(to_fetch)
+            .map(|id| {
+                let client = &client;
+                async move { (client.get_vulnerability(&id).await, id) }
+            })
+            .buffer_unordered(DETAIL_FETCH_CONCURRENCY)
+            .filter_map(|(result, id)| async move {
+                match result {
+                    Ok(vuln) => {
+                        let (severity, cvss_score) = vuln.severity();
+                        Some(VulnerabilityRecord {
+                            fixed_in: vuln.fixed_in(),
+                            id: vuln.id,
+                            aliases: vuln.aliases,
+                            summary: vuln.summary,
+                            severity,
+                            cvss_score,
+                            published_at: vuln.published,
+                            modified_at: vuln.modified,
+                        })
+                    }
+                    Err(e) => {
+                        warn!(vulnerability_id = %id, error = %e, "Failed to fetch vulnerability details.");
+                        None
+                    }
+                }
+            })
+            .collect()
+            .await;
+        vulnerability_service::upsert_vulnerabilities(pool.clone(), records).await?;
+    }
+    let cached =
+        vulnerability_service::get_cached_vulnerabilities(pool.clone(), all_ids.into_iter().collect()).await?;
+
+    let mut new_by_user: BTreeMap<i32, Vec<NewFinding>> = BTreeMap::new();
+    let mut new_total = 0;
+    for (host, ecosystem, queries) in per_host {
+        let mut matches: BTreeMap<(String, String), VulnerabilityMatch> = BTreeMap::new();
+        let mut unresolved = Vec::new();
+        for query in &queries {
+            let Some(ids) = vulns_by_query.get(query) else { continue };
+            for id in ids {
+                // Only vulnerabilities with cached details are recorded, so every
+                // listed match has a severity. One whose details failed to fetch
+                // is still reported, so a match recorded earlier is kept.
+                let Some(details) = cached.get(id) else {
+                    unresolved.push((id.clone(), query.name.clone()));
+                    continue;
+                };
+                matches.insert(
+                    (id.clone(), query.name.clone()),
+                    VulnerabilityMatch {
+                        vulnerability_id: id.clone(),
+                        package_name: query.name.clone(),
+                        installed_version: query.version.clone(),
+                        fixed_version: lowest_fixed_version(&details.fixed_in, &ecosystem, &query.name, &query.version),
+                    },
+                );
+            }
+        }
+
+        let new_matches =
+            vulnerability_service::sync_vps_matches(pool.clone(), host.vps_id, matches.into_values().collect(), unresolved)
+                .await?;
+        new_total += new_matches.len();
+        for finding in new_matches {
+            let Some(details) = cached.get(&finding.vulnerability_id) else { continue };
+            let label = match details.aliases.iter().find(|a| a.starts_with("CVE-")) {
+                Some(cve) if *cve != finding.vulnerability_id => format!("{} ({cve})", finding.vulnerability_id),
+                _ => finding.vulnerability_id.clone(),
+            };
+            new_by_user.entry(host.user_id).or_default().push(NewFinding {
+                vps_name: host.vps_name.clone(),
+                vps_id: host.vps_id,
+                severity: details.severity,
+                label,
+                finding,
+            });
+        }
+    }
+
+    for (user_id, findings) in new_by_user {
+        notify_user(pool.clone(), encryption_service.clone(), user_id, findings).await;
+    }
+    info!(new_matches = new_total, "Vulnerability scan finished.");
+    Ok(new_total)
+}
+
+async fn notify_user(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    user_id: i32,
+    findings: Vec<NewFinding>,
+) {
+    let settings = match vulnerability_service::get_alert_settings(pool.clone(), user_id).await {
+        Ok(settings) => settings,
+        Err(e) => {
+            warn!(user_id, error = %e, "Failed to load vulnerability alert settings.");
+            return;
+        }
+    };
+    if settings.channel_ids.is_empty() {
+        return;
+    }
+    let mut findings: Vec<NewFinding> = findings
+        .into_iter()
+        .filter(|f| f.severity >= settings.min_severity)
+        .collect();
+    if findings.is_empty() {
+        return;
+    }
+    findings.sort_by(|a, b| b.severity.cmp(&a.severity).then(a.vps_id.cmp(&b.vps_id)));
+
+    let mut lines: Vec<String> = findings
+        .iter()
+        .take(MAX_ALERT_LINES)
+        .map(|f| {
+            let fix = f
+                .finding
+                .fixed_version
+                .as_deref()
+                .map(|v| format!(", fixed in {v}"))
+                .unwrap_or_default();
+            format!(
+                "- [{}] {} on VPS '{}' (ID: {}): {} {}{}",
+                f.severity.as_str().to_uppercase(),
+                f.label,
+                f.vps_name,
+                f.vps_id,
+                f.finding.package_name,
+                f.finding.installed_version,
+                fix
+            )
+        })
+        .collect();
+    if findings.len() > MAX_ALERT_LINES {
+        lines.push(format!("... and {} more", findings.len() - MAX_ALERT_LINES));
+    }
+    let message = format!(
+        "ALERT! {} new vulnerabilit{} at or above {} severity detected:\n{}",
+        findings.len(),
+        if findings.len() == 1 { "y" } else { "ies" },
+        settings.min_severity.as_str(),
+        lines.join("\n")
+    );
+
+    if let Err(e) = notification_service::send_notifications_to_channels(
+        pool,
+        encryption_service,
+        settings.channel_ids,
+        message,
+    )
+    .await
+    {
+        warn!(user_id, error = %e, "Failed to send vulnerability notifications.");
+    }
+}
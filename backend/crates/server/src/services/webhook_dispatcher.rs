@@ -0,0 +1,157 @@
+//! Posts queued webhook deliveries. Each request carries the event type,
+//! delivery id and a timestamp, and is signed with HMAC-SHA256 over
+//! `"{timestamp}.{body}"` using the subscription's secret. A 2xx response
+//! marks the delivery as delivered; anything else is retried with exponential
+//! backoff until the attempts run out and the delivery is dead.
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::db::duckdb_service::webhook_service::{self, DueDelivery};
+use crate::db::duckdb_service::DuckDbPool;
+use crate::notifications::encryption::EncryptionService;
+use crate::web::error::AppError;
+
+pub const EVENT_HEADER: &str = "X-NodeNexus-Event";
+pub const DELIVERY_HEADER: &str = "X-NodeNexus-Delivery";
+pub const TIMESTAMP_HEADER: &str = "X-NodeNexus-Timestamp";
+pub const SIGNATURE_HEADER: &str = "X-NodeNexus-Signature";
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+/// Attempts before a delivery is given up on.
+const MAX_ATTEMPTS: i32 = 8;
+const BASE_RETRY_DELAY_SECONDS: i64 = 30;
+const MAX_RETRY_DELAY_SECONDS: i64 = 6 * 60 * 60;
+/// Deliveries sent per run.
+const BATCH_SIZE: u32 = 50;
+/// Response bodies kept as the delivery's last error.
+const MAX_ERROR_CHARS: usize = 500;
+
+/// The signature header value for `body` sent at `timestamp`.
+pub fn sign(secret: &[u8], timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(format!("{timestamp}.{body}").as_bytes());
+    format!("sha256={}", hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Delay before the next attempt after `attempts` failed ones, or `None` when
+/// the delivery is out of attempts.
+pub fn retry_delay(attempts: i32) -> Option<chrono::Duration> {
+    if attempts >= MAX_ATTEMPTS {
+        return None;
+    }
+    let exponent = attempts.saturating_sub(1).clamp(0, 20) as u32;
+    let seconds = BASE_RETRY_DELAY_SECONDS
+        .saturating_mul(2_i64.pow(exponent))
+        .min(MAX_RETRY_DELAY_SECONDS);
+    Some(chrono::Duration::seconds(seconds))
+}
+
+pub fn http_client() -> Result<reqwest::Client, AppError> {
+    reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| AppError::InternalServerError(format!("Failed to build webhook client: {e}")))
+}
+
+/// Sends one delivery. Returns the response status and, on failure, why.
+async fn send(
+    http: &reqwest::Client,
+    encryption_service: &EncryptionService,
+    due: &DueDelivery,
+    now: DateTime<Utc>,
+) -> (Option<i32>, Option<String>) {
+    let secret = match encryption_service.decrypt(&due.encrypted_secret) {
+        Ok(secret) => secret,
+        Err(e) => return (None, Some(format!("Failed to decrypt the webhook secret: {e}"))),
+    };
+    let body = due.delivery.payload.to_string();
+    let timestamp = now.timestamp();
+    let response = http
+        .post(&due.url)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .header(EVENT_HEADER, &due.delivery.event_type)
+        .header(DELIVERY_HEADER, due.delivery.event_id.to_string())
+        .header(TIMESTAMP_HEADER, timestamp.to_string())
+        .header(SIGNATURE_HEADER, sign(&secret, timestamp, &body))
+        .body(body)
+        .send()
+        .await;
+    match response {
+        Ok(response) if response.status().is_success() => (Some(i32::from(response.status().as_u16())), None),
+        Ok(response) => {
+            let status = response.status();
+            let text = response.text().await.unwrap_or_default();
+            let error = format!("HTTP {status}: {}", text.chars().take(MAX_ERROR_CHARS).collect::<String>());
+            (Some(i32::from(status.as_u16())), Some(error))
+        }
+        Err(e) => (None, Some(e.to_string())),
+    }
+}
+
+/// Sends the deliveries that are due. Returns how many were attempted.
+pub async fn dispatch_due(
+    pool: DuckDbPool,
+    encryption_service: Arc<EncryptionService>,
+    http: &reqwest::Client,
+) -> Result<usize, AppError> {
+    let now = Utc::now();
+    let due = webhook_service::get_due_deliveries(pool.clone(), now, BATCH_SIZE).await?;
+    for delivery in &due {
+        let (status_code, error) = send(http, &encryption_service, delivery, now).await;
+        let attempts = delivery.delivery.attempts + 1;
+        let next_attempt_at = match &error {
+            Some(_) => retry_delay(attempts).map(|delay| now + delay),
+            None => None,
+        };
+        if let Some(error) = &error {
+            warn!(
+                delivery_id = delivery.delivery.id,
+                subscription_id = delivery.delivery.subscription_id,
+                attempts,
+                dead = next_attempt_at.is_none(),
+                error = %error,
+                "Webhook delivery failed."
+            );
+        }
+        webhook_service::record_attempt(
+            pool.clone(),
+            delivery.delivery.id,
+            now,
+            error.is_none(),
+            status_code,
+            error.as_deref(),
+            next_attempt_at,
+        )
+        .await?;
+    }
+    Ok(due.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature_covers_timestamp_and_body() {
+        let signature = sign(b"secret", 1_700_000_000, r#"{"type":"vps.online"}"#);
+        assert!(signature.starts_with("sha256="));
+        assert_eq!(signature.len(), "sha256=".len() + 64);
+        assert_eq!(signature, sign(b"secret", 1_700_000_000, r#"{"type":"vps.online"}"#));
+        assert_ne!(signature, sign(b"secret", 1_700_000_001, r#"{"type":"vps.online"}"#));
+        assert_ne!(signature, sign(b"other", 1_700_000_000, r#"{"type":"vps.online"}"#));
+    }
+
+    #[test]
+    fn retries_back_off_until_dead() {
+        assert_eq!(retry_delay(1), Some(chrono::Duration::seconds(30)));
+        assert_eq!(retry_delay(2), Some(chrono::Duration::seconds(60)));
+        assert_eq!(retry_delay(4), Some(chrono::Duration::seconds(240)));
+        assert_eq!(retry_delay(MAX_ATTEMPTS - 1), Some(chrono::Duration::seconds(30 * 64)));
+        assert_eq!(retry_delay(MAX_ATTEMPTS), None);
+    }
+}
@@ -4,7 +4,12 @@ use axum::{
     response::{IntoResponse, Response},
 };
 use thiserror::Error;
+use tracing::error;
 
+/// The error type every service and handler returns. Variants fall into a few
+/// kinds, reported to clients as the `code` field next to `error`:
+/// `validation`, `unauthorized`, `forbidden`, `not_found`, `conflict`,
+/// `rate_limited`, `unavailable`, `storage` and `internal`.
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Invalid input: {0}")]
@@ -33,10 +38,35 @@ pub enum AppError {
     Conflict(String),
     #[error("Forbidden: {0}")]
     Forbidden(String),
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+    #[error("Too many requests: {0}")]
+    TooManyRequests(String),
+}
+
+impl AppError {
+    /// Machine-readable kind of the error, stable across message changes.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::InvalidInput(_) => "validation",
+            AppError::UserNotFound | AppError::InvalidCredentials | AppError::Unauthorized(_) => "unauthorized",
+            AppError::Forbidden(_) => "forbidden",
+            AppError::NotFound(_) => "not_found",
+            AppError::UserAlreadyExists(_) | AppError::Conflict(_) => "conflict",
+            AppError::DatabaseError(_) => "storage",
+            AppError::ServiceUnavailable(_) => "unavailable",
+            AppError::TooManyRequests(_) => "rate_limited",
+            AppError::PasswordHashingError(_)
+            | AppError::TokenCreationError(_)
+            | AppError::InternalServerError(_)
+            | AppError::ServerError(_) => "internal",
+        }
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
+        let code = self.code();
         let (status, error_message) = match self {
             AppError::InvalidInput(msg) => (StatusCode::BAD_REQUEST, msg),
             AppError::UserAlreadyExists(msg) => (StatusCode::CONFLICT, msg),
@@ -50,24 +80,40 @@ impl IntoResponse for AppError {
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Token creation error: {msg}"),
             ),
-            AppError::DatabaseError(msg) => (
-                StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Database error: {msg}"),
-            ),
+            AppError::DatabaseError(msg) => {
+                // The details can include SQL; keep them in the log only.
+                error!(error = %msg, "Database error while handling request.");
+                (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+            }
             AppError::InternalServerError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             AppError::ServerError(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
             AppError::Conflict(msg) => (StatusCode::CONFLICT, msg),
             AppError::Forbidden(msg) => (StatusCode::FORBIDDEN, msg),
+            AppError::ServiceUnavailable(msg) => (StatusCode::SERVICE_UNAVAILABLE, msg),
+            AppError::TooManyRequests(msg) => (StatusCode::TOO_MANY_REQUESTS, msg),
         };
-        (status, Json(serde_json::json!({ "error": error_message }))).into_response()
+        if status == StatusCode::INTERNAL_SERVER_ERROR && code != "storage" {
+            error!(error = %error_message, "Internal error while handling request.");
+        }
+        (status, Json(serde_json::json!({ "error": error_message, "code": code }))).into_response()
     }
 }
 
+/// DuckDB reports unique, primary key and foreign key violations with this prefix.
+fn is_constraint_violation(err: &duckdb::Error) -> bool {
+    err.to_string().contains("Constraint Error")
+}
+
 impl From<duckdb::Error> for AppError {
     fn from(err: duckdb::Error) -> Self {
-        AppError::DatabaseError(err.to_string())
+        match err {
+            // A `query_row` that matched nothing: the addressed record doesn't exist.
+            duckdb::Error::QueryReturnedNoRows => AppError::NotFound("Record not found".to_string()),
+            err if is_constraint_violation(&err) => AppError::Conflict(err.to_string()),
+            err => AppError::DatabaseError(err.to_string()),
+        }
     }
 }
 
@@ -88,6 +134,35 @@ use crate::db::duckdb_service;
 
 impl From<duckdb_service::Error> for AppError {
     fn from(err: duckdb_service::Error) -> Self {
-        AppError::DatabaseError(err.to_string())
+        match err {
+            duckdb_service::Error::DuckDB(e) => e.into(),
+            duckdb_service::Error::Pool(e) => e.into(),
+            duckdb_service::Error::NotFound(msg) => AppError::NotFound(msg),
+            duckdb_service::Error::InternalServerError => {
+                AppError::InternalServerError("Internal server error".to_string())
+            }
+            duckdb_service::Error::App(e) => e,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_rows_are_not_found() {
+        let err: AppError = duckdb::Error::QueryReturnedNoRows.into();
+        assert!(matches!(err, AppError::NotFound(_)));
+        let err: AppError = duckdb_service::Error::NotFound("VPS 1".to_string()).into();
+        assert_eq!(err.code(), "not_found");
+    }
+
+    #[test]
+    fn wrapped_app_errors_keep_their_kind() {
+        let err: AppError = duckdb_service::Error::App(AppError::Conflict("taken".to_string())).into();
+        assert!(matches!(err, AppError::Conflict(msg) if msg == "taken"));
+        let err: AppError = duckdb_service::Error::DuckDB(duckdb::Error::QueryReturnedNoRows).into();
+        assert_eq!(err.code(), "not_found");
     }
 }
@@ -0,0 +1,140 @@
+//! Serves the frontend from a directory on disk.
+//!
+//! Used instead of the embedded bundle when `FRONTEND_DIR` points to an existing
+//! directory, so a custom or hot-fixed frontend can be deployed without rebuilding.
+
+use axum::body::Body;
+use axum::http::{header, HeaderValue, Request, Response, StatusCode};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+use tracing::warn;
+
+const INDEX_FILE: &str = "index.html";
+
+/// Vite emits content-hashed file names under `assets/`, so those can be cached forever.
+fn cache_control_for(relative_path: &Path, is_html: bool) -> &'static str {
+    if is_html {
+        "no-cache"
+    } else if relative_path.starts_with("assets") {
+        "public, max-age=31536000, immutable"
+    } else {
+        "public, max-age=3600"
+    }
+}
+
+/// Maps a request path to a path relative to the frontend root, rejecting traversal.
+/// Backslashes are rejected too, as they separate components on Windows.
+fn sanitize_request_path(path: &str) -> Option<PathBuf> {
+    let decoded = urlencoding::decode(path).ok()?;
+    if decoded.contains('\\') {
+        return None;
+    }
+    let mut relative = PathBuf::new();
+    for component in Path::new(decoded.trim_start_matches('/')).components() {
+        match component {
+            Component::Normal(part) => relative.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(relative)
+}
+
+fn file_response(relative_path: &Path, contents: Vec<u8>) -> Response<Body> {
+    let mime = mime_guess::from_path(relative_path).first_or_octet_stream();
+    let is_html = mime.essence_str() == "text/html";
+    let mut response = Response::new(Body::from(contents));
+    if let Ok(value) = HeaderValue::from_str(mime.as_ref()) {
+        response.headers_mut().insert(header::CONTENT_TYPE, value);
+    }
+    response.headers_mut().insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(cache_control_for(relative_path, is_html)),
+    );
+    response
+}
+
+fn status_response(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+/// Resolves the request against `root`, falling back to `index.html` for client-side routes.
+pub async fn serve_frontend_dir<T>(root: Arc<PathBuf>, req: Request<T>) -> Response<Body> {
+    let Some(mut relative_path) = sanitize_request_path(req.uri().path()) else {
+        return status_response(StatusCode::BAD_REQUEST);
+    };
+
+    let result = tokio::task::spawn_blocking(move || {
+        let mut full_path = root.join(&relative_path);
+        if full_path.is_dir() {
+            relative_path.push(INDEX_FILE);
+            full_path.push(INDEX_FILE);
+        }
+        if !full_path.is_file() {
+            relative_path = PathBuf::from(INDEX_FILE);
+            full_path = root.join(INDEX_FILE);
+        }
+        std::fs::read(&full_path).map(|contents| (relative_path, contents))
+    })
+    .await;
+
+    match result {
+        Ok(Ok((relative_path, contents))) => file_response(&relative_path, contents),
+        Ok(Err(e)) => {
+            warn!(error = %e, "Failed to read frontend file from disk.");
+            status_response(StatusCode::NOT_FOUND)
+        }
+        Err(e) => {
+            warn!(error = %e, "Frontend file read task failed.");
+            status_response(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_normal_paths() {
+        assert_eq!(sanitize_request_path("/assets/index-abc.js"), Some(PathBuf::from("assets/index-abc.js")));
+        assert_eq!(sanitize_request_path("/./favicon.ico"), Some(PathBuf::from("favicon.ico")));
+        assert_eq!(sanitize_request_path("/my%20logo.png"), Some(PathBuf::from("my logo.png")));
+    }
+
+    #[test]
+    fn empty_path_is_the_root() {
+        assert_eq!(sanitize_request_path(""), Some(PathBuf::new()));
+        assert_eq!(sanitize_request_path("/"), Some(PathBuf::new()));
+    }
+
+    #[test]
+    fn rejects_parent_components() {
+        assert_eq!(sanitize_request_path("/../etc/passwd"), None);
+        assert_eq!(sanitize_request_path("/assets/../../etc/passwd"), None);
+        assert_eq!(sanitize_request_path(".."), None);
+    }
+
+    #[test]
+    fn rejects_encoded_parent_components() {
+        assert_eq!(sanitize_request_path("/%2e%2e/etc/passwd"), None);
+        assert_eq!(sanitize_request_path("/%2E%2E%2fetc%2fpasswd"), None);
+        assert_eq!(sanitize_request_path("/assets%2f..%2f..%2fsecret"), None);
+    }
+
+    #[test]
+    fn rejects_backslashes() {
+        assert_eq!(sanitize_request_path("/..\\..\\etc\\passwd"), None);
+        assert_eq!(sanitize_request_path("/%5c..%5cwindows"), None);
+        assert_eq!(sanitize_request_path("/C:\\Windows\\win.ini"), None);
+    }
+
+    #[test]
+    fn absolute_paths_stay_under_the_root() {
+        assert_eq!(sanitize_request_path("/etc/passwd"), Some(PathBuf::from("etc/passwd")));
+        assert_eq!(sanitize_request_path("//etc/passwd"), Some(PathBuf::from("etc/passwd")));
+        assert_eq!(sanitize_request_path("/%2fetc%2fpasswd"), Some(PathBuf::from("etc/passwd")));
+    }
+}
@@ -10,16 +10,18 @@ use futures_util::{sink::SinkExt, stream::StreamExt};
 use serde::Deserialize;
 use serde_json::json;
 use std::sync::Arc;
-use tracing::{error, info, warn};
+use tracing::{error, info, info_span, warn, Instrument};
 use uuid::Uuid;
 use nodenexus_common::agent_service::CommandType as GrpcCommandType;
 use crate::{
     db::duckdb_service::batch_command_service,
+    services::batch_execution::{self, DispatchTask},
     web::{
+        middleware::request_id::RequestId,
         models::{
             batch_command_models::CreateBatchCommandRequest, AuthenticatedUser,
         },
-        AppState,
+        AppError, AppState,
     },
 };
 
@@ -28,11 +30,16 @@ pub async fn batch_command_upgrade_handler(
     ws: WebSocketUpgrade,
     State(app_state): State<Arc<AppState>>,
     Extension(authenticated_user): Extension<AuthenticatedUser>,
-) -> impl IntoResponse {
+    Extension(request_id): Extension<RequestId>,
+) -> Result<impl IntoResponse, AppError> {
+    // Commands are sent over the socket, which the read-only middleware doesn't see.
+    app_state.read_only.ensure_writable()?;
     info!("Upgrading connection to WebSocket for batch command execution.");
-    ws.on_upgrade(move |socket| {
-        handle_socket(socket, app_state, authenticated_user)
-    })
+    // The socket outlives the upgrade request, so its span is carried over explicitly.
+    let span = info_span!("batch_command_socket", request_id = %request_id.0);
+    Ok(ws.on_upgrade(move |socket| {
+        handle_socket(socket, app_state, authenticated_user, request_id).instrument(span)
+    }))
 }
 
 // Handles the WebSocket connection after the upgrade.
@@ -40,6 +47,7 @@ async fn handle_socket(
     mut socket: WebSocket,
     app_state: Arc<AppState>,
     authenticated_user: AuthenticatedUser,
+    request_id: RequestId,
 ) {
     info!("New WebSocket connection established. Waiting for command payload.");
     let user_id = authenticated_user.id;
@@ -77,57 +85,67 @@ async fn handle_socket(
         let dispatcher = app_state.command_dispatcher.clone();
         let duckdb_pool = app_state.duckdb_pool.clone();
 
-        match batch_command_service::create_batch_command(duckdb_pool, user_id, payload.clone()).await {
+        // Saved scripts are referenced by ID; anything else counts as ad hoc for policies.
+        let script_id = payload.script_id.as_deref().and_then(|id| id.parse().ok());
+        let created = match dispatcher.resolve_targets(user_id, &payload.targets).await {
+            Ok(targets) => batch_command_service::create_batch_command(duckdb_pool, user_id, payload.clone(), targets, script_id, request_id.0.clone())
+                .await
+                .map_err(AppError::from),
+            Err(e) => Err(e),
+        };
+        match created {
             Ok((batch_task_model, child_tasks)) => {
                 let batch_id = batch_task_model.batch_command_id;
                 info!(%batch_id, "Successfully created batch command task in DB.");
 
-                // Send the created ID back to the client immediately.
+                // Send the created ID and the resolved targets back to the client immediately.
+                let targets: Vec<_> = child_tasks
+                    .iter()
+                    .map(|child| json!({ "vps_id": child.vps_id, "matched_selector": child.matched_selector }))
+                    .collect();
                 let created_msg = json!({
                     "type": "BATCH_TASK_CREATED",
-                    "payload": { "batch_command_id": batch_id }
+                    "payload": { "batch_command_id": batch_id, "targets": targets }
                 });
                 if socket.send(Message::Text(Utf8Bytes::from(created_msg.to_string()))).await.is_err() {
                      warn!("Failed to send BATCH_TASK_CREATED message to client.");
                 }
 
-                // Asynchronously dispatch commands for each child task
-                for child_task in child_tasks {
-                    let dispatcher_clone = dispatcher.clone();
-                    let payload_clone = payload.clone();
-                    tokio::spawn(async move {
-                        let command_content = payload_clone.command_content.unwrap_or_default();
-                        let command_type = if payload_clone.script_id.is_some() {
-                            GrpcCommandType::SavedScript
-                        } else {
-                            GrpcCommandType::AdhocCommand
-                        };
-                        let working_directory = payload_clone.working_directory;
-                        let effective_command_content = if command_type == GrpcCommandType::SavedScript {
-                            if command_content.is_empty() && payload_clone.script_id.is_some() {
-                                payload_clone.script_id.unwrap_or_default()
-                            } else {
-                                command_content
-                            }
-                        } else {
-                            command_content
-                        };
-
-                        let dispatch_result = dispatcher_clone
-                            .dispatch_command_to_agent(
-                                child_task.child_command_id,
-                                child_task.vps_id,
-                                &effective_command_content,
-                                command_type,
-                                working_directory,
-                            )
-                            .await;
-
-                        if let Err(e) = dispatch_result {
-                            error!(child_task_id = %child_task.child_command_id, error = ?e, "Failed to dispatch command.");
-                        }
-                    });
-                }
+                // Dispatch the child tasks in the waves of the execution strategy.
+                let command_content = payload.command_content.clone().unwrap_or_default();
+                let command_type = if payload.script_id.is_some() {
+                    GrpcCommandType::SavedScript
+                } else {
+                    GrpcCommandType::AdhocCommand
+                };
+                let effective_command_content = if command_type == GrpcCommandType::SavedScript && command_content.is_empty() {
+                    payload.script_id.clone().unwrap_or_default()
+                } else {
+                    command_content
+                };
+                let tasks = child_tasks
+                    .into_iter()
+                    .map(|child_task| DispatchTask {
+                        child_command_id: child_task.child_command_id,
+                        vps_id: child_task.vps_id,
+                        wave: child_task.wave as usize,
+                        command_content: effective_command_content.clone(),
+                        command_type,
+                        working_directory: payload.working_directory.clone(),
+                        timeout_seconds: payload.timeout_seconds.unwrap_or_default(),
+                    })
+                    .collect();
+                tokio::spawn(
+                    batch_execution::run_batch(
+                        (*dispatcher).clone(),
+                        batch_id,
+                        payload.strategy.clone(),
+                        payload.retry.as_ref().is_some_and(|retry| retry.max_attempts > 1),
+                        tasks,
+                        request_id.0.clone(),
+                    )
+                    .in_current_span(),
+                );
                 // Return the ID for the next step
                 batch_id
             }
@@ -1,2 +1,4 @@
 pub mod batch_command_upgrade_handler;
 pub mod websocket_handler;
+pub mod ws_history;
+pub mod ws_subscription;
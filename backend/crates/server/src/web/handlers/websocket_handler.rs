@@ -7,24 +7,52 @@ use axum::{
     response::IntoResponse,
 };
 use axum_extra::extract::cookie::CookieJar;
+use chrono::Utc;
 use futures_util::stream::StreamExt;
 use jsonwebtoken::{DecodingKey, Validation, decode}; // Added for JWT decoding
 use serde::Deserialize;
+use std::collections::HashSet;
 use std::sync::Arc;
 use tracing::{debug, error, info, warn};
 
+use crate::db::duckdb_service::{
+    share_link_service, status_page_service, user_service, virtual_group_service, vps_group_service,
+};
+use crate::db::entities::{share_link, status_page};
 use crate::web::AppError;
 use crate::web::AppState;
-use crate::web::models::websocket_models::{FullServerListPush, WsMessage};
-use crate::web::models::{AuthenticatedUser, Claims}; // Import Claims // For error handling
+use crate::web::routes::{share_routes, status_page_routes};
+use crate::web::models::websocket_models::{
+    FullServerListPush, ProtocolHello, ServerWithDetails, VirtualGroupsPush, WsClientMessage, WsMessage, WsResume,
+    WsSubscription, negotiate_protocol_version,
+};
+use crate::web::handlers::ws_history::WsHistory;
+use crate::web::handlers::ws_subscription::ConnectionFilter;
+use crate::web::models::{AuthenticatedUser, Claims, Role}; // Import Claims // For error handling
+
+const SHARE_RECHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+const STATUS_PAGE_REFRESH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(15);
 
 #[derive(Deserialize, Debug)]
 pub struct WebSocketAuthQuery {
-    token: Option<String>,
+    pub token: Option<String>,
+    /// Protocol version the client was written against.
+    pub protocol: Option<u32>,
+    /// Server time (Unix ms) of the last message of a previous connection.
+    pub since: Option<i64>,
+}
+
+/// Sends the `hello` message that opens every dashboard connection.
+async fn send_hello(socket: &mut WebSocket) -> bool {
+    let Ok(json_data) = serde_json::to_string(&WsMessage::Hello(ProtocolHello::current())) else {
+        error!("Failed to serialize WebSocket hello.");
+        return false;
+    };
+    socket.send(Message::Text(Utf8Bytes::from(json_data))).await.is_ok()
 }
 
 // Authenticate WebSocket connection using JWT from query parameter
-async fn authenticate_ws_connection(
+pub(crate) async fn authenticate_ws_connection(
     app_state: Arc<AppState>,
     token_option: Option<String>,
 ) -> Result<AuthenticatedUser, AppError> {
@@ -43,11 +71,17 @@ async fn authenticate_ws_connection(
         Ok(token_data) => {
             // Token is valid, extract claims
             let claims = token_data.claims;
-            // TODO: Optionally, you could re-verify user existence in DB here if strictness is required,
-            // but for WebSocket, usually a valid token is sufficient if it hasn't expired.
+            let user = user_service::get_user_by_id(app_state.duckdb_pool.clone(), claims.user_id)
+                .await?
+                .ok_or_else(|| AppError::Unauthorized("User no longer exists".to_string()))?;
             Ok(AuthenticatedUser {
                 id: claims.user_id,
                 username: claims.sub, // Assuming 'sub' is username
+                role: Role::from_db(&user.role),
+                organization_id: user
+                    .active_organization_id
+                    .ok_or_else(|| AppError::InternalServerError("User has no active organization".to_string()))?,
+                api_token_id: None,
             })
         }
         Err(e) => {
@@ -79,6 +113,10 @@ pub async fn websocket_handler(
     Query(query): Query<WebSocketAuthQuery>, // Get token from query params
     jar: CookieJar,                          // Get cookies
 ) -> impl IntoResponse {
+    if let Err(e) = negotiate_protocol_version(query.protocol) {
+        return AppError::InvalidInput(e).into_response();
+    }
+
     // Try to get token from cookie first, fallback to query param
     let token = jar
         .get("token")
@@ -93,54 +131,133 @@ pub async fn websocket_handler(
 
     info!(user_id = user.id, username = %user.username, "User authenticated for WebSocket connection.");
 
-    ws.on_upgrade(move |socket| handle_socket(socket, app_state, user))
+    let since = query.since;
+    ws.on_upgrade(move |socket| handle_socket(socket, app_state, user, since))
 }
 
-async fn handle_socket(mut socket: WebSocket, app_state: Arc<AppState>, user: AuthenticatedUser) {
-    // Changed parameter type
-    info!("WebSocket connection established.");
-
-    // 1. Send initial data snapshot
-    let initial_data_message = {
+/// Current server list and virtual groups, narrowed to one connection.
+async fn filtered_snapshot(app_state: &AppState, filter: &mut ConnectionFilter) -> Vec<WsMessage> {
+    let (servers, virtual_groups) = {
         let cache_guard = app_state.live_server_data_cache.lock().await;
-        let servers_list: Vec<crate::web::models::websocket_models::ServerWithDetails> =
-            cache_guard.values().cloned().collect();
-        WsMessage::FullServerList(FullServerListPush {
-            servers: servers_list,
-        })
+        let servers_list: Vec<ServerWithDetails> = cache_guard.values().cloned().collect();
+        let virtual_groups = WsMessage::VirtualGroups(VirtualGroupsPush {
+            groups: virtual_group_service::group_members(&servers_list),
+        });
+        (
+            WsMessage::FullServerList(FullServerListPush {
+                servers: servers_list,
+            }),
+            virtual_groups,
+        )
     };
+    // The server list goes first so the filter knows which VPS are visible.
+    [servers, virtual_groups]
+        .into_iter()
+        .filter_map(|message| filter.apply(message))
+        .collect()
+}
 
-    if let Ok(json_data) = serde_json::to_string(&initial_data_message) {
-        if socket
-            .send(Message::Text(Utf8Bytes::from(json_data)))
-            .await
-            .is_err()
-        {
-            error!("Error sending initial WebSocket data. Closing connection.");
-            return;
+/// The broadcasts a connection resuming from `since` missed, preceded by a
+/// `resumed` message, or `None` when `history` doesn't reach back that far.
+fn missed_messages(
+    history: &WsHistory,
+    since: i64,
+    mut filter: impl FnMut(WsMessage) -> Option<WsMessage>,
+) -> Option<Vec<WsMessage>> {
+    let missed: Vec<WsMessage> = history
+        .since(since, Utc::now().timestamp_millis())?
+        .into_iter()
+        .filter_map(&mut filter)
+        .collect();
+    let mut messages = vec![WsMessage::Resumed(WsResume {
+        since_ms: since,
+        message_count: missed.len(),
+    })];
+    messages.extend(missed);
+    Some(messages)
+}
+
+async fn send_ws_message(socket: &mut WebSocket, message: &WsMessage) -> bool {
+    match serde_json::to_string(message) {
+        Ok(json_data) => socket.send(Message::Text(Utf8Bytes::from(json_data))).await.is_ok(),
+        Err(e) => {
+            error!(error = %e, "Failed to serialize WebSocket message.");
+            true
         }
-        info!("Sent initial data snapshot.");
+    }
+}
+
+/// Applies a client `subscribe` message and returns the acknowledgement
+/// followed by a fresh snapshot for the new subscription.
+async fn handle_subscribe(
+    app_state: &AppState,
+    filter: &mut ConnectionFilter,
+    subscription: WsSubscription,
+) -> Vec<WsMessage> {
+    let group_ids = if subscription.group_ids.is_empty() {
+        HashSet::new()
     } else {
-        error!("Failed to serialize initial data. Closing connection.");
+        match vps_group_service::subtree_ids(
+            app_state.duckdb_pool.clone(),
+            filter.user().organization_id,
+            &subscription.group_ids,
+        )
+        .await
+        {
+            Ok(ids) => ids,
+            Err(e) => {
+                error!(error = ?e, "Failed to resolve subscribed groups.");
+                HashSet::new()
+            }
+        }
+    };
+    filter.subscribe(subscription, group_ids);
+    let mut messages = vec![WsMessage::Subscribed(filter.subscription().clone())];
+    messages.extend(filtered_snapshot(app_state, filter).await);
+    messages
+}
+
+async fn handle_socket(mut socket: WebSocket, app_state: Arc<AppState>, user: AuthenticatedUser, since: Option<i64>) {
+    info!("WebSocket connection established.");
+    if !send_hello(&mut socket).await {
+        error!("Error sending WebSocket hello. Closing connection.");
         return;
     }
 
-    // 2. Subscribe to broadcast channel for updates
+    // 1. Subscribe to broadcast channel for updates, before reading the
+    // snapshot or history so nothing falls in between.
     let mut rx = app_state.ws_data_broadcaster_tx.subscribe();
 
+    // 2. Send what a resuming client missed, or else the initial data snapshot
+    let mut filter = ConnectionFilter::new(user);
+    // The snapshot tells the filter which VPS are visible, even when the
+    // client resumes and already has it.
+    let snapshot = filtered_snapshot(&app_state, &mut filter).await;
+    let resumed = since.and_then(|since| missed_messages(&app_state.ws_history, since, |m| filter.apply(m)));
+    let resuming = resumed.is_some();
+    for message in resumed.unwrap_or(snapshot) {
+        if !send_ws_message(&mut socket, &message).await {
+            error!("Error sending initial WebSocket data. Closing connection.");
+            return;
+        }
+    }
+    if resuming {
+        info!(since, "Resumed WebSocket connection from history.");
+    } else {
+        info!("Sent initial data snapshot.");
+    }
+
     // 3. Main loop to listen for updates and client messages
     loop {
         tokio::select! {
             // Receive updates from the broadcast channel
             Ok(ws_message) = rx.recv() => {
-                if let Ok(json_data) = serde_json::to_string(&ws_message) {
-                    if socket.send(Message::Text(Utf8Bytes::from(json_data))).await.is_err() {
-                        warn!("Error sending WebSocket data update. Breaking loop.");
-                        break; // Error sending, client might have disconnected
-                    }
-                     // debug!("Sent data update via broadcast.");
-                } else {
-                    error!("Failed to serialize broadcast data.");
+                let Some(ws_message) = filter.apply(ws_message) else {
+                    continue;
+                };
+                if !send_ws_message(&mut socket, &ws_message).await {
+                    warn!("Error sending WebSocket data update. Breaking loop.");
+                    break; // Error sending, client might have disconnected
                 }
             }
             // Receive messages from the client (e.g., ping, commands)
@@ -148,9 +265,25 @@ async fn handle_socket(mut socket: WebSocket, app_state: Arc<AppState>, user: Au
                 match msg {
                     Message::Text(t) => {
                         debug!(message = ?t, "Received text message.");
-                        if t == "ping" && socket.send(Message::Text(Utf8Bytes::from("pong"))).await.is_err() {
-                            warn!("Error sending pong. Breaking loop.");
-                            break;
+                        if t == "ping" {
+                            if socket.send(Message::Text(Utf8Bytes::from("pong"))).await.is_err() {
+                                warn!("Error sending pong. Breaking loop.");
+                                break;
+                            }
+                            continue;
+                        }
+                        match serde_json::from_str::<WsClientMessage>(&t) {
+                            Ok(WsClientMessage::Subscribe(subscription)) => {
+                                let mut sent = true;
+                                for message in handle_subscribe(&app_state, &mut filter, subscription).await {
+                                    sent = sent && send_ws_message(&mut socket, &message).await;
+                                }
+                                if !sent {
+                                    warn!("Error sending subscription snapshot. Breaking loop.");
+                                    break;
+                                }
+                            }
+                            Err(e) => debug!(error = %e, "Ignoring unrecognized client message."),
                         }
                     }
                     Message::Binary(b) => {
@@ -188,56 +321,138 @@ async fn handle_socket(mut socket: WebSocket, app_state: Arc<AppState>, user: Au
 
 // --- Public WebSocket Handler ---
 
+#[derive(Deserialize, Debug)]
+pub struct PublicWebSocketQuery {
+    /// Share link token. When present only the servers covered by the link are sent.
+    share: Option<String>,
+    /// Status page slug. When present the socket only carries that page's view.
+    status_page: Option<String>,
+    /// Protocol version the client was written against.
+    protocol: Option<u32>,
+    /// Server time (Unix ms) of the last message of a previous connection.
+    since: Option<i64>,
+}
+
 #[debug_handler]
 pub async fn public_websocket_handler(
     ws: WebSocketUpgrade,
     State(app_state): State<Arc<AppState>>,
+    Query(query): Query<PublicWebSocketQuery>,
 ) -> impl IntoResponse {
     info!("Public WebSocket connection request.");
-    ws.on_upgrade(move |socket| handle_public_socket(socket, app_state))
+    if let Err(e) = negotiate_protocol_version(query.protocol) {
+        return AppError::InvalidInput(e).into_response();
+    }
+    if let Some(slug) = query.status_page {
+        return match status_page_service::get_published_status_page(app_state.duckdb_pool.clone(), &slug).await {
+            Ok(Some(page)) => ws.on_upgrade(move |socket| handle_status_page_socket(socket, app_state, page)),
+            Ok(None) => AppError::NotFound("Status page not found".to_string()).into_response(),
+            Err(e) => e.into_response(),
+        };
+    }
+    let share = match query.share {
+        Some(token) => match share_routes::resolve_share_token(&app_state, &token).await {
+            Ok(link) => Some(link),
+            Err(e) => return e.into_response(),
+        },
+        None => None,
+    };
+    let since = query.since;
+    ws.on_upgrade(move |socket| handle_public_socket(socket, app_state, share, since))
 }
 
-async fn handle_public_socket(mut socket: WebSocket, app_state: Arc<AppState>) {
-    info!("Public WebSocket connection established.");
-
-    // 1. Send initial data snapshot (desensitized)
-    let initial_data_message = {
-        let cache_guard = app_state.live_server_data_cache.lock().await;
-        let public_servers_list: Vec<crate::web::models::websocket_models::ServerWithDetails> =
-            cache_guard
-                .values()
-                .map(|s| s.desensitize()) // Use the new desensitize method
-                .collect();
-
-        WsMessage::FullServerList(FullServerListPush {
-            servers: public_servers_list,
-        })
+/// Narrows a public broadcast to the servers a share link covers.
+/// Messages other than server lists are not forwarded to shared views.
+fn filter_for_share(message: WsMessage, share: Option<&share_link::Model>) -> Option<WsMessage> {
+    let Some(link) = share else {
+        return Some(message);
     };
+    match message {
+        WsMessage::FullServerList(push) => Some(WsMessage::FullServerList(FullServerListPush {
+            servers: push
+                .servers
+                .into_iter()
+                .filter(|s| share_routes::share_includes(link, s))
+                .collect(),
+        })),
+        _ => None,
+    }
+}
 
-    if let Ok(json_data) = serde_json::to_string(&initial_data_message) {
-        if socket
-            .send(Message::Text(Utf8Bytes::from(json_data)))
-            .await
-            .is_err()
-        {
-            error!("Error sending initial public WebSocket data. Closing connection.");
-            return;
-        }
-        info!("Sent initial public data snapshot.");
-    } else {
-        error!("Failed to serialize initial public data. Closing connection.");
+async fn handle_public_socket(
+    mut socket: WebSocket,
+    app_state: Arc<AppState>,
+    share: Option<share_link::Model>,
+    since: Option<i64>,
+) {
+    info!(share_id = share.as_ref().map(|l| l.id), "Public WebSocket connection established.");
+    if !send_hello(&mut socket).await {
+        error!("Error sending public WebSocket hello. Closing connection.");
         return;
     }
 
-    // 2. Subscribe to the public broadcast channel.
+    // 1. Subscribe to the public broadcast channel.
     let mut rx = app_state.public_ws_data_broadcaster_tx.subscribe();
 
+    // 2. Send what a resuming client missed, if the history still has it.
+    if let Some(missed) = since.and_then(|since| {
+        missed_messages(&app_state.public_ws_history, since, |m| filter_for_share(m, share.as_ref()))
+    }) {
+        for message in &missed {
+            if !send_ws_message(&mut socket, message).await {
+                error!("Error sending missed public WebSocket data. Closing connection.");
+                return;
+            }
+        }
+        info!(since, "Resumed public WebSocket connection from history.");
+    } else {
+        // Otherwise send the initial data snapshot (desensitized)
+        let initial_data_message = match &share {
+            Some(link) => WsMessage::FullServerList(FullServerListPush {
+                servers: share_routes::shared_servers(&app_state, link).await,
+            }),
+            None => {
+                let cache_guard = app_state.live_server_data_cache.lock().await;
+                let public_servers_list: Vec<crate::web::models::websocket_models::ServerWithDetails> =
+                    cache_guard
+                        .values()
+                        .map(|s| s.desensitize()) // Use the new desensitize method
+                        .collect();
+
+                WsMessage::FullServerList(FullServerListPush {
+                    servers: public_servers_list,
+                })
+            }
+        };
+
+        if let Ok(json_data) = serde_json::to_string(&initial_data_message) {
+            if socket
+                .send(Message::Text(Utf8Bytes::from(json_data)))
+                .await
+                .is_err()
+            {
+                error!("Error sending initial public WebSocket data. Closing connection.");
+                return;
+            }
+            info!("Sent initial public data snapshot.");
+        } else {
+            error!("Failed to serialize initial public data. Closing connection.");
+            return;
+        }
+    }
+
+    // Shared views are re-validated periodically so revoked or expired links are cut off.
+    let mut share_check = tokio::time::interval(SHARE_RECHECK_INTERVAL);
+    share_check.tick().await;
+
     // 3. Main loop to listen for updates and client pings
     loop {
         tokio::select! {
             Ok(ws_message) = rx.recv() => {
                 // The public channel now sends FullServerList messages, just like the private one.
-                // No need to filter by message type, as the public broadcaster is dedicated.
+                let Some(ws_message) = filter_for_share(ws_message, share.as_ref()) else {
+                    continue;
+                };
                 if let Ok(json_data) = serde_json::to_string(&ws_message) {
                     if socket.send(Message::Text(Utf8Bytes::from(json_data))).await.is_err() {
                         warn!("Error sending public WebSocket data update. Breaking loop.");
@@ -262,6 +477,18 @@ async fn handle_public_socket(mut socket: WebSocket, app_state: Arc<AppState>) {
                     _ => {} // Ignore other message types
                 }
             }
+            _ = share_check.tick(), if share.is_some() => {
+                let share_id = share.as_ref().map(|l| l.id).unwrap_or_default();
+                match share_link_service::get_active_share_link(app_state.duckdb_pool.clone(), share_id).await {
+                    Ok(Some(_)) => {}
+                    Ok(None) => {
+                        info!(share_id, "Share link revoked or expired. Closing public WebSocket.");
+                        let _ = socket.send(Message::Close(None)).await;
+                        break;
+                    }
+                    Err(e) => warn!(share_id, error = %e, "Failed to re-validate share link."),
+                }
+            }
             else => {
                 info!("Public client disconnected. Breaking loop.");
                 break;
@@ -270,3 +497,62 @@ async fn handle_public_socket(mut socket: WebSocket, app_state: Arc<AppState>) {
     }
     info!("Public WebSocket connection closed.");
 }
+
+/// Pushes the view of a status page on connect and then periodically. The
+/// page is re-read each time so edits show up and unpublished pages disconnect.
+async fn handle_status_page_socket(mut socket: WebSocket, app_state: Arc<AppState>, page: status_page::Model) {
+    info!(status_page_id = page.id, "Status page WebSocket connection established.");
+    if !send_hello(&mut socket).await {
+        error!("Error sending status page WebSocket hello. Closing connection.");
+        return;
+    }
+
+    let slug = page.slug;
+    let mut refresh = tokio::time::interval(STATUS_PAGE_REFRESH_INTERVAL);
+    loop {
+        tokio::select! {
+            _ = refresh.tick() => {
+                let page = match status_page_service::get_published_status_page(app_state.duckdb_pool.clone(), &slug).await {
+                    Ok(Some(page)) => page,
+                    Ok(None) => {
+                        info!(slug, "Status page unpublished or deleted. Closing WebSocket.");
+                        let _ = socket.send(Message::Close(None)).await;
+                        break;
+                    }
+                    Err(e) => {
+                        warn!(slug, error = %e, "Failed to load status page.");
+                        continue;
+                    }
+                };
+                let view = match status_page_routes::status_page_view(&app_state, &page).await {
+                    Ok(view) => view,
+                    Err(e) => {
+                        warn!(slug, error = %e, "Failed to build status page view.");
+                        continue;
+                    }
+                };
+                let Ok(json_data) = serde_json::to_string(&WsMessage::StatusPage(view)) else {
+                    error!("Failed to serialize status page view.");
+                    continue;
+                };
+                if socket.send(Message::Text(Utf8Bytes::from(json_data))).await.is_err() {
+                    warn!("Error sending status page update. Breaking loop.");
+                    break;
+                }
+            }
+            Some(Ok(msg)) = socket.next() => {
+                match msg {
+                    Message::Ping(p) => {
+                        if socket.send(Message::Pong(p)).await.is_err() {
+                            break;
+                        }
+                    }
+                    Message::Close(_) => break,
+                    _ => {}
+                }
+            }
+            else => break,
+        }
+    }
+    info!(slug, "Status page WebSocket connection closed.");
+}
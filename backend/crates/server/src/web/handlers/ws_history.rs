@@ -0,0 +1,169 @@
+//! Short history of a WebSocket broadcast channel.
+//!
+//! A client that reconnects passes `?since=<unix ms>` (the server time of the
+//! last message it got) and is sent the broadcasts it missed instead of a full
+//! snapshot. When the history no longer reaches back that far the connection
+//! starts with a snapshot as usual.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::Utc;
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::web::models::websocket_models::WsMessage;
+
+struct Entries {
+    messages: VecDeque<(i64, WsMessage)>,
+    /// Every broadcast after this time is still in `messages`.
+    complete_from: i64,
+}
+
+pub struct WsHistory {
+    retention_ms: i64,
+    max_messages: usize,
+    entries: Mutex<Entries>,
+}
+
+impl WsHistory {
+    pub fn new(retention: Duration, max_messages: usize, now_ms: i64) -> Self {
+        Self {
+            retention_ms: i64::try_from(retention.as_millis()).unwrap_or(i64::MAX),
+            max_messages,
+            entries: Mutex::new(Entries {
+                messages: VecDeque::new(),
+                complete_from: now_ms,
+            }),
+        }
+    }
+
+    /// Creates a history of `tx` and records its broadcasts until the channel closes.
+    pub fn spawn(tx: &broadcast::Sender<WsMessage>, retention: Duration, max_messages: usize) -> Arc<Self> {
+        let history = Arc::new(Self::new(retention, max_messages, Utc::now().timestamp_millis()));
+        if !history.is_enabled() {
+            return history;
+        }
+        let mut rx = tx.subscribe();
+        let recorder = history.clone();
+        tokio::spawn(async move {
+            loop {
+                match rx.recv().await {
+                    Ok(message) => recorder.record(message, Utc::now().timestamp_millis()),
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        warn!(skipped, "WebSocket history fell behind its channel; starting over.");
+                        recorder.reset(Utc::now().timestamp_millis());
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+        history
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.retention_ms > 0 && self.max_messages > 0
+    }
+
+    pub fn record(&self, message: WsMessage, now_ms: i64) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.messages.push_back((now_ms, message));
+        let oldest_kept = now_ms.saturating_sub(self.retention_ms);
+        while let Some(&(at, _)) = entries.messages.front() {
+            if at >= oldest_kept && entries.messages.len() <= self.max_messages {
+                break;
+            }
+            entries.messages.pop_front();
+            entries.complete_from = entries.complete_from.max(at);
+        }
+    }
+
+    /// Forgets everything recorded so far, after broadcasts went unrecorded.
+    fn reset(&self, now_ms: i64) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.messages.clear();
+        entries.complete_from = now_ms;
+    }
+
+    /// Broadcasts sent after `since_ms`, or `None` when some of them are no
+    /// longer kept (or `since_ms` is in the future, meaning the client's idea
+    /// of the server time is off).
+    pub fn since(&self, since_ms: i64, now_ms: i64) -> Option<Vec<WsMessage>> {
+        if !self.is_enabled() || since_ms > now_ms || since_ms < now_ms.saturating_sub(self.retention_ms) {
+            return None;
+        }
+        let entries = self.entries.lock().unwrap();
+        if since_ms < entries.complete_from {
+            return None;
+        }
+        Some(
+            entries
+                .messages
+                .iter()
+                .filter(|(at, _)| *at > since_ms)
+                .map(|(_, message)| message.clone())
+                .collect(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::web::models::websocket_models::WsSubscription;
+
+    fn message(vps_id: i32) -> WsMessage {
+        WsMessage::Subscribed(WsSubscription {
+            vps_ids: vec![vps_id],
+            ..Default::default()
+        })
+    }
+
+    fn vps_ids(messages: Option<Vec<WsMessage>>) -> Option<Vec<i32>> {
+        messages.map(|messages| {
+            messages
+                .into_iter()
+                .map(|m| match m {
+                    WsMessage::Subscribed(s) => s.vps_ids[0],
+                    _ => unreachable!(),
+                })
+                .collect()
+        })
+    }
+
+    #[test]
+    fn returns_messages_after_since() {
+        let history = WsHistory::new(Duration::from_secs(60), 100, 1_000);
+        history.record(message(1), 2_000);
+        history.record(message(2), 3_000);
+        assert_eq!(vps_ids(history.since(1_000, 4_000)), Some(vec![1, 2]));
+        assert_eq!(vps_ids(history.since(2_000, 4_000)), Some(vec![2]));
+        assert_eq!(vps_ids(history.since(3_500, 4_000)), Some(vec![]));
+        // Before the history started, or in the future.
+        assert_eq!(vps_ids(history.since(500, 4_000)), None);
+        assert_eq!(vps_ids(history.since(5_000, 4_000)), None);
+    }
+
+    #[test]
+    fn pruned_messages_make_older_cursors_unusable() {
+        let history = WsHistory::new(Duration::from_secs(10), 2, 0);
+        history.record(message(1), 1_000);
+        history.record(message(2), 2_000);
+        history.record(message(3), 3_000);
+        assert_eq!(vps_ids(history.since(1_000, 3_000)), Some(vec![2, 3]));
+        assert_eq!(vps_ids(history.since(500, 3_000)), None);
+
+        // Past the retention nothing older than ten seconds is served.
+        history.record(message(4), 12_500);
+        assert_eq!(vps_ids(history.since(2_000, 12_500)), None);
+        assert_eq!(vps_ids(history.since(3_000, 12_500)), Some(vec![4]));
+    }
+
+    #[test]
+    fn zero_retention_disables_the_history() {
+        let history = WsHistory::new(Duration::ZERO, 100, 0);
+        assert!(!history.is_enabled());
+        assert_eq!(vps_ids(history.since(0, 0)), None);
+    }
+}
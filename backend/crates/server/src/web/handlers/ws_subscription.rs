@@ -0,0 +1,301 @@
+//! Per-connection filtering of `/ws/metrics` broadcasts.
+//!
+//! Every dashboard connection receives the same broadcast stream; a
+//! [`ConnectionFilter`] narrows it to the VPS in the user's organization and,
+//! once the client has sent a `subscribe` message, to the VPS it asked for.
+
+use std::collections::HashSet;
+
+use crate::web::models::AuthenticatedUser;
+use crate::web::models::websocket_models::{
+    FullServerListPush, PerformanceMetricBatch, ServerWithDetails, VirtualGroupsPush, WsMessage, WsSubscription,
+};
+
+pub struct ConnectionFilter {
+    user: AuthenticatedUser,
+    subscription: WsSubscription,
+    /// `subscription.group_ids` expanded to include every subgroup.
+    group_ids: HashSet<i32>,
+    /// VPS in the last server list sent; per-VPS messages are narrowed to these.
+    visible: HashSet<i32>,
+}
+
+impl ConnectionFilter {
+    pub fn new(user: AuthenticatedUser) -> Self {
+        Self {
+            user,
+            subscription: WsSubscription::default(),
+            group_ids: HashSet::new(),
+            visible: HashSet::new(),
+        }
+    }
+
+    pub fn user(&self) -> &AuthenticatedUser {
+        &self.user
+    }
+
+    pub fn subscription(&self) -> &WsSubscription {
+        &self.subscription
+    }
+
+    /// Replaces the subscription. `group_ids` must already be expanded to
+    /// subgroups. Takes effect with the next server list.
+    pub fn subscribe(&mut self, subscription: WsSubscription, group_ids: HashSet<i32>) {
+        self.subscription = subscription;
+        self.group_ids = group_ids;
+    }
+
+    fn matches(&self, server: &ServerWithDetails) -> bool {
+        let info = &server.basic_info;
+        if !self.user.can_access(info.organization_id) {
+            return false;
+        }
+        let subscription = &self.subscription;
+        if subscription.is_empty() {
+            return true;
+        }
+        subscription.vps_ids.contains(&info.id)
+            || info.group_id.is_some_and(|id| self.group_ids.contains(&id))
+            || info.group.as_deref().is_some_and(|path| {
+                subscription.groups.iter().any(|wanted| {
+                    let wanted = wanted.trim_end_matches('/');
+                    path == wanted || path.strip_prefix(wanted).is_some_and(|rest| rest.starts_with('/'))
+                })
+            })
+            || info
+                .tags
+                .iter()
+                .flatten()
+                .any(|tag| subscription.tag_ids.contains(&tag.id))
+    }
+
+    /// Narrows a message to this connection, or drops it when nothing in it
+    /// is of interest.
+    pub fn apply(&mut self, message: WsMessage) -> Option<WsMessage> {
+        match message {
+            WsMessage::FullServerList(push) => {
+                let servers: Vec<ServerWithDetails> = push.servers.into_iter().filter(|s| self.matches(s)).collect();
+                self.visible = servers.iter().map(|s| s.basic_info.id).collect();
+                Some(WsMessage::FullServerList(FullServerListPush { servers }))
+            }
+            WsMessage::PerformanceMetricBatch(batch) => {
+                let metrics: Vec<_> = batch
+                    .metrics
+                    .into_iter()
+                    .filter(|m| self.visible.contains(&m.vps_id))
+                    .collect();
+                (!metrics.is_empty()).then_some(WsMessage::PerformanceMetricBatch(PerformanceMetricBatch { metrics }))
+            }
+            WsMessage::ServiceMonitorResult(update) => self
+                .visible
+                .contains(&update.vps_id)
+                .then_some(WsMessage::ServiceMonitorResult(update)),
+            WsMessage::AlertEvent(push) => self.visible.contains(&push.vps_id).then_some(WsMessage::AlertEvent(push)),
+            WsMessage::VirtualGroups(push) => Some(WsMessage::VirtualGroups(VirtualGroupsPush {
+                groups: push
+                    .groups
+                    .into_iter()
+                    .map(|mut members| {
+                        members.vps_ids.retain(|id| self.visible.contains(id));
+                        members
+                    })
+                    .collect(),
+            })),
+            other => Some(other),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::db::enums::AlertSeverity;
+    use crate::web::models::Role;
+    use crate::web::models::websocket_models::{
+        AlertEventChange, AlertEventPush, PerformanceMetricPoint, ServerBasicInfo, Tag,
+    };
+    use chrono::Utc;
+
+    fn user() -> AuthenticatedUser {
+        AuthenticatedUser {
+            id: 1,
+            username: "alice".to_string(),
+            role: Role::Viewer,
+            organization_id: 1,
+            api_token_id: None,
+        }
+    }
+
+    fn server(id: i32, organization_id: i32, group: Option<&str>, tag_id: Option<i32>) -> ServerWithDetails {
+        ServerWithDetails {
+            basic_info: ServerBasicInfo {
+                id,
+                user_id: 1,
+                organization_id: Some(organization_id),
+                name: format!("vps-{id}"),
+                ip_address: None,
+                status: "online".to_string(),
+                agent_version: None,
+                agent_outdated: false,
+                group: group.map(str::to_string),
+                group_id: None,
+                client_id: None,
+                tags: tag_id.map(|id| {
+                    vec![Tag {
+                        id,
+                        name: "prod".to_string(),
+                        color: "#000".to_string(),
+                        icon: None,
+                        url: None,
+                        is_visible: true,
+                    }]
+                }),
+                config_status: "synced".to_string(),
+                last_config_update_at: None,
+                last_config_error: None,
+                traffic_limit_bytes: None,
+                traffic_billing_rule: None,
+                traffic_current_cycle_rx_bytes: None,
+                traffic_current_cycle_tx_bytes: None,
+                traffic_last_reset_at: None,
+                traffic_reset_config_type: None,
+                traffic_reset_config_value: None,
+                next_traffic_reset_at: None,
+                is_ephemeral: false,
+                country_code: None,
+                city: None,
+                asn: None,
+                as_organization: None,
+            },
+            os_type: None,
+            created_at: Utc::now(),
+            metadata: None,
+            renewal_cycle: None,
+            renewal_cycle_custom_days: None,
+            renewal_price: None,
+            renewal_currency: None,
+            next_renewal_date: None,
+            last_renewal_date: None,
+            service_start_date: None,
+            payment_method: None,
+            auto_renew_enabled: None,
+            renewal_notes: None,
+            reminder_active: None,
+            uptime: None,
+            health: None,
+            maintenance: None,
+            virtual_groups: Vec::new(),
+            gpus: Vec::new(),
+        }
+    }
+
+    fn visible_ids(filter: &mut ConnectionFilter, servers: Vec<ServerWithDetails>) -> Vec<i32> {
+        match filter.apply(WsMessage::FullServerList(FullServerListPush { servers })) {
+            Some(WsMessage::FullServerList(push)) => push.servers.iter().map(|s| s.basic_info.id).collect(),
+            _ => panic!("server list was dropped"),
+        }
+    }
+
+    fn fleet() -> Vec<ServerWithDetails> {
+        vec![
+            server(1, 1, Some("eu/hetzner"), None),
+            server(2, 1, Some("europe"), Some(7)),
+            server(3, 1, None, None),
+            server(4, 2, Some("eu"), None),
+        ]
+    }
+
+    #[test]
+    fn without_subscription_only_the_organization_is_filtered() {
+        let mut filter = ConnectionFilter::new(user());
+        assert_eq!(visible_ids(&mut filter, fleet()), vec![1, 2, 3]);
+    }
+
+    #[test]
+    fn subscription_criteria_are_combined() {
+        let mut filter = ConnectionFilter::new(user());
+        filter.subscribe(
+            WsSubscription {
+                groups: vec!["eu".to_string()],
+                ..Default::default()
+            },
+            HashSet::new(),
+        );
+        // "europe" is not a subgroup of "eu"; the other org's "eu" stays hidden.
+        assert_eq!(visible_ids(&mut filter, fleet()), vec![1]);
+
+        filter.subscribe(
+            WsSubscription {
+                vps_ids: vec![3],
+                tag_ids: vec![7],
+                ..Default::default()
+            },
+            HashSet::new(),
+        );
+        assert_eq!(visible_ids(&mut filter, fleet()), vec![2, 3]);
+    }
+
+    #[test]
+    fn metrics_follow_the_last_server_list() {
+        let mut filter = ConnectionFilter::new(user());
+        filter.subscribe(
+            WsSubscription {
+                vps_ids: vec![1],
+                ..Default::default()
+            },
+            HashSet::new(),
+        );
+        visible_ids(&mut filter, fleet());
+        let point = |vps_id| PerformanceMetricPoint {
+            time: Utc::now(),
+            vps_id,
+            cpu_usage_percent: Some(1.0),
+            memory_usage_bytes: None,
+            memory_total_bytes: None,
+            network_rx_instant_bps: None,
+            network_tx_instant_bps: None,
+            disk_io_read_bps: None,
+            disk_io_write_bps: None,
+            swap_usage_bytes: None,
+            swap_total_bytes: None,
+            disk_used_bytes: None,
+            disk_total_bytes: None,
+        };
+        let batch = |ids: &[i32]| {
+            WsMessage::PerformanceMetricBatch(PerformanceMetricBatch {
+                metrics: ids.iter().map(|id| point(*id)).collect(),
+            })
+        };
+        match filter.apply(batch(&[1, 2])) {
+            Some(WsMessage::PerformanceMetricBatch(b)) => assert_eq!(b.metrics.len(), 1),
+            _ => panic!("batch for a visible VPS was dropped"),
+        }
+        assert!(filter.apply(batch(&[2, 4])).is_none());
+    }
+
+    #[test]
+    fn alert_events_of_other_organizations_are_dropped() {
+        let mut filter = ConnectionFilter::new(user());
+        visible_ids(&mut filter, fleet());
+        let push = |vps_id| {
+            WsMessage::AlertEvent(AlertEventPush {
+                change: AlertEventChange::Fired,
+                id: 1,
+                rule_id: 1,
+                rule_name: "CPU".to_string(),
+                vps_id,
+                vps_name: format!("vps-{vps_id}"),
+                monitor_id: None,
+                severity: AlertSeverity::Warning,
+                trigger_time: Utc::now(),
+                resolve_time: None,
+                acknowledged_at: None,
+                acknowledged_by: None,
+                silenced_until: None,
+                details: None,
+            })
+        };
+        assert!(filter.apply(push(1)).is_some());
+        assert!(filter.apply(push(4)).is_none());
+    }
+}
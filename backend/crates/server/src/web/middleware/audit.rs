@@ -0,0 +1,218 @@
+//! Audit trail for mutating API requests. Runs outside the per-group auth
+//! layers, so the authenticated user is read from the response extensions
+//! (see [`super::auth::auth`]) and requests that never authenticated are not
+//! recorded. Handlers that load an entity before changing it can attach an
+//! [`AuditBefore`] to the response to record its previous state.
+
+use axum::{
+    body::{Body as AxumBody, Bytes},
+    extract::{ConnectInfo, State},
+    http::{header, Method, Request},
+    middleware::Next,
+    response::Response,
+};
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::db::duckdb_service::audit_log_service::{self, NewAuditEntry};
+use crate::web::middleware::auth::proxy_is_trusted;
+use crate::web::middleware::request_id::RequestId;
+use crate::web::models::AuthenticatedUser;
+use crate::web::AppState;
+
+/// Request bodies larger than this are not summarized.
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// State of an entity before a change, attached to a handler's response.
+#[derive(Debug, Clone)]
+pub struct AuditBefore(pub serde_json::Value);
+
+impl AuditBefore {
+    pub fn of(entity: &impl Serialize) -> Self {
+        Self(serde_json::to_value(entity).unwrap_or(serde_json::Value::Null))
+    }
+}
+
+fn is_audited(method: &Method, path: &str) -> bool {
+    let is_write = !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+    // Login and registration carry passwords and have no user yet.
+    is_write && path.starts_with("/api/") && !path.starts_with("/api/auth/")
+}
+
+fn is_id_segment(segment: &str) -> bool {
+    (!segment.is_empty() && segment.chars().all(|c| c.is_ascii_digit())) || Uuid::parse_str(segment).is_ok()
+}
+
+/// Splits `/api/vps/12/tags` into (`vps`, `Some("12")`): the segments before
+/// the first ID name the entity type.
+fn entity_from_path(path: &str) -> (String, Option<String>) {
+    let segments: Vec<&str> = path
+        .trim_start_matches("/api/")
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect();
+    match segments.iter().position(|s| is_id_segment(s)) {
+        Some(index) => (segments[..index].join("/"), Some(segments[index].to_string())),
+        None => (segments.join("/"), None),
+    }
+}
+
+/// The forwarded client address is only believed when the connection comes
+/// from a trusted proxy; anyone else could put an arbitrary address there.
+fn client_ip(req: &Request<AxumBody>, trusted_proxy_ips: &[IpAddr]) -> Option<String> {
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    proxy_is_trusted(peer, trusted_proxy_ips)
+        .then(|| req.headers().get("x-forwarded-for"))
+        .flatten()
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(|ip| ip.trim().to_string())
+        .filter(|ip| !ip.is_empty())
+        .or_else(|| peer.map(|ip| ip.to_string()))
+}
+
+fn is_small_json(req: &Request<AxumBody>) -> bool {
+    let headers = req.headers();
+    let is_json = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    let length = headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    is_json && length.is_some_and(|length| length <= MAX_BODY_BYTES)
+}
+
+fn body_summary(body: &Bytes) -> Option<serde_json::Value> {
+    let mut value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    audit_log_service::redact(&mut value);
+    Some(value)
+}
+
+pub async fn record_mutations(
+    State(app_state): State<Arc<AppState>>,
+    req: Request<AxumBody>,
+    next: Next,
+) -> Response {
+    if !is_audited(req.method(), req.uri().path()) {
+        return next.run(req).await;
+    }
+
+    let method = req.method().to_string();
+    let route = req.uri().path().to_string();
+    let ip_address = client_ip(&req, &app_state.config.trusted_proxy_ips);
+    let request_id = req.extensions().get::<RequestId>().map(|id| id.0.clone());
+
+    let (req, after_summary) = if is_small_json(&req) {
+        let (parts, body) = req.into_parts();
+        match axum::body::to_bytes(body, MAX_BODY_BYTES).await {
+            Ok(bytes) => {
+                let summary = body_summary(&bytes);
+                (Request::from_parts(parts, AxumBody::from(bytes)), summary)
+            }
+            Err(e) => {
+                warn!(error = %e, "Failed to read request body for the audit log.");
+                (Request::from_parts(parts, AxumBody::empty()), None)
+            }
+        }
+    } else {
+        (req, None)
+    };
+
+    let response = next.run(req).await;
+    let status = response.status();
+    let Some(user) = response.extensions().get::<AuthenticatedUser>() else {
+        return response;
+    };
+    if !status.is_success() && !status.is_redirection() {
+        return response;
+    }
+
+    let (entity_type, entity_id) = entity_from_path(&route);
+    let entry = NewAuditEntry {
+        user_id: user.id,
+        username: user.username.clone(),
+        organization_id: Some(user.organization_id),
+        method,
+        route,
+        entity_type,
+        entity_id,
+        status_code: status.as_u16(),
+        before_summary: response.extensions().get::<AuditBefore>().map(|before| {
+            let mut value = before.0.clone();
+            audit_log_service::redact(&mut value);
+            value
+        }),
+        after_summary,
+        ip_address,
+        request_id,
+    };
+    let pool = app_state.duckdb_pool.clone();
+    tokio::spawn(async move {
+        if let Err(e) = audit_log_service::record(pool, entry).await {
+            error!(error = %e, "Failed to write audit log entry.");
+        }
+    });
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entity_is_taken_from_the_path() {
+        assert_eq!(entity_from_path("/api/vps"), ("vps".to_string(), None));
+        assert_eq!(entity_from_path("/api/vps/12/tags"), ("vps".to_string(), Some("12".to_string())));
+        assert_eq!(
+            entity_from_path("/api/admin/users/5/role"),
+            ("admin/users".to_string(), Some("5".to_string()))
+        );
+        let id = "6f1c2a4e-8d3b-4f5a-9c7e-1b2d3e4f5a6b";
+        assert_eq!(
+            entity_from_path(&format!("/api/batch_commands/{id}/terminate")),
+            ("batch_commands".to_string(), Some(id.to_string()))
+        );
+    }
+
+    #[test]
+    fn only_authenticated_api_writes_are_audited() {
+        assert!(is_audited(&Method::POST, "/api/vps"));
+        assert!(is_audited(&Method::DELETE, "/api/tags/3"));
+        assert!(!is_audited(&Method::GET, "/api/vps"));
+        assert!(!is_audited(&Method::POST, "/api/auth/login"));
+    }
+
+    fn request_from(peer: &str, forwarded_for: &str) -> Request<AxumBody> {
+        let mut req = Request::builder()
+            .header("x-forwarded-for", forwarded_for)
+            .body(AxumBody::empty())
+            .unwrap();
+        req.extensions_mut().insert(ConnectInfo(peer.parse::<SocketAddr>().unwrap()));
+        req
+    }
+
+    #[test]
+    fn forwarded_ip_is_only_trusted_from_trusted_proxies() {
+        let proxy: IpAddr = "10.0.0.2".parse().unwrap();
+        assert_eq!(
+            client_ip(&request_from("10.0.0.2:4000", "203.0.113.7, 10.0.0.2"), &[proxy]).as_deref(),
+            Some("203.0.113.7")
+        );
+        assert_eq!(
+            client_ip(&request_from("198.51.100.9:4000", "203.0.113.7"), &[proxy]).as_deref(),
+            Some("198.51.100.9")
+        );
+        assert_eq!(
+            client_ip(&request_from("198.51.100.9:4000", "203.0.113.7"), &[]).as_deref(),
+            Some("198.51.100.9")
+        );
+    }
+}
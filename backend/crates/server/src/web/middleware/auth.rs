@@ -1,16 +1,21 @@
 use axum::{
     body::Body as AxumBody,
-    extract::State,
-    http::{Request, header},
+    extract::{ConnectInfo, State},
+    http::{HeaderValue, Request, header},
     middleware::Next,
     response::Response,
 };
-use axum_extra::extract::cookie::CookieJar;
+use axum_extra::extract::cookie::{Cookie, CookieJar, SameSite};
 use jsonwebtoken::{DecodingKey, Validation, decode};
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use tracing::warn;
 
-use crate::web::models::{AuthenticatedUser, Claims};
+use crate::db::duckdb_service::{api_token_service, user_service};
+use crate::db::entities::user;
+use crate::server::config::ServerConfig;
+use crate::services::auth_service;
+use crate::web::models::{AuthenticatedUser, Claims, Role};
 use crate::web::{AppState, error::AppError};
 
 pub async fn auth(
@@ -21,6 +26,10 @@ pub async fn auth(
 ) -> Result<Response, AppError> {
     let jwt_secret = &state.config.jwt_secret;
 
+    if let Some(username) = trusted_header_username(&state.config, &req) {
+        return trusted_header_auth(&state, &jar, username, req, next).await;
+    }
+
     // Try to get token from Authorization header first, then fall back to cookie
     let token = req
         .headers()
@@ -31,20 +40,167 @@ pub async fn auth(
         .or_else(|| jar.get("token").map(|c| c.value().to_string()))
         .ok_or(AppError::InvalidCredentials)?;
 
-    let token_data = decode::<Claims>(
-        &token,
-        &DecodingKey::from_secret(jwt_secret.as_ref()),
-        &Validation::default(),
-    )
-    .map_err(|e| {
-        warn!(error = ?e, "JWT decoding error during auth middleware.");
-        AppError::InvalidCredentials // Or "InvalidToken"
-    })?;
-
-    let authenticated_user = AuthenticatedUser {
-        id: token_data.claims.user_id,
-        username: token_data.claims.sub, // Assuming 'sub' is username
+    let authenticated_user = if token.starts_with(api_token_service::TOKEN_PREFIX) {
+        let api_token = api_token_service::authenticate_api_token(state.duckdb_pool.clone(), &token)
+            .await?
+            .ok_or(AppError::InvalidCredentials)?;
+        if !api_token_service::scopes_allow(&api_token.scopes, req.method()) {
+            return Err(AppError::Forbidden(
+                "This API token does not have the scope for this request".to_string(),
+            ));
+        }
+        let user = user_service::get_user_by_id(state.duckdb_pool.clone(), api_token.user_id)
+            .await?
+            .ok_or(AppError::InvalidCredentials)?;
+        authenticated_user_from(user.username.clone(), &user, Some(api_token.id))?
+    } else {
+        let token_data = decode::<Claims>(
+            &token,
+            &DecodingKey::from_secret(jwt_secret.as_ref()),
+            &Validation::default(),
+        )
+        .map_err(|e| {
+            warn!(error = ?e, "JWT decoding error during auth middleware.");
+            AppError::InvalidCredentials // Or "InvalidToken"
+        })?;
+
+        // The role is read on every request so promotions and demotions apply
+        // without waiting for the token to expire.
+        let user = user_service::get_user_by_id(state.duckdb_pool.clone(), token_data.claims.user_id)
+            .await?
+            .ok_or(AppError::InvalidCredentials)?;
+        authenticated_user_from(token_data.claims.sub, &user, None)? // Assuming 'sub' is username
     };
-    req.extensions_mut().insert(authenticated_user);
-    Ok(next.run(req).await)
+    req.extensions_mut().insert(authenticated_user.clone());
+    let mut response = next.run(req).await;
+    // Outer layers such as the audit log only see the response.
+    response.extensions_mut().insert(authenticated_user);
+    Ok(response)
+}
+
+/// Longest username accepted from the trusted header.
+const MAX_TRUSTED_USERNAME_LEN: usize = 128;
+
+/// The username a trusted reverse proxy put in the configured header, if
+/// trusted-header auth is on and the request came through such a proxy.
+fn trusted_header_username(config: &ServerConfig, req: &Request<AxumBody>) -> Option<String> {
+    if !config.trusted_header_auth {
+        return None;
+    }
+    let peer = req
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip());
+    if !proxy_is_trusted(peer, &config.trusted_proxy_ips) {
+        return None;
+    }
+    parse_trusted_username(req.headers().get(config.trusted_header_name.as_str())?)
+}
+
+/// Connections without a peer address come from the admin unix socket, which
+/// only local processes can reach.
+pub(crate) fn proxy_is_trusted(peer: Option<IpAddr>, trusted_proxy_ips: &[IpAddr]) -> bool {
+    match peer.map(|ip| ip.to_canonical()) {
+        None => true,
+        Some(ip) if trusted_proxy_ips.is_empty() => ip.is_loopback(),
+        Some(ip) => trusted_proxy_ips.iter().any(|trusted| trusted.to_canonical() == ip),
+    }
+}
+
+fn parse_trusted_username(value: &HeaderValue) -> Option<String> {
+    let username = value.to_str().ok()?.trim();
+    if username.is_empty() || username.len() > MAX_TRUSTED_USERNAME_LEN {
+        return None;
+    }
+    Some(username.to_string())
+}
+
+/// Authenticates the user named by the proxy, provisioning them on first sight.
+/// A session cookie is issued too, so the WebSocket and terminal connections,
+/// which authenticate with it, work behind the proxy.
+async fn trusted_header_auth(
+    state: &AppState,
+    jar: &CookieJar,
+    username: String,
+    mut req: Request<AxumBody>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let default_role = Role::from_db(&state.config.trusted_header_default_role);
+    let user = user_service::find_or_create_trusted_user(state.duckdb_pool.clone(), username.clone(), default_role)
+        .await?;
+    let authenticated_user = authenticated_user_from(username, &user, None)?;
+
+    let session_user_id = jar.get("token").and_then(|cookie| {
+        decode::<Claims>(
+            cookie.value(),
+            &DecodingKey::from_secret(state.config.jwt_secret.as_ref()),
+            &Validation::default(),
+        )
+        .ok()
+        .map(|data| data.claims.user_id)
+    });
+    let session_cookie = if session_user_id == Some(user.id) {
+        None
+    } else {
+        let token = auth_service::create_jwt_for_user(&user, &state.config.jwt_secret)?.token;
+        Some(
+            Cookie::build(("token", token))
+                .path("/")
+                .http_only(true)
+                .same_site(SameSite::Lax)
+                .secure(true)
+                .build(),
+        )
+    };
+
+    req.extensions_mut().insert(authenticated_user.clone());
+    let mut response = next.run(req).await;
+    response.extensions_mut().insert(authenticated_user);
+    if let Some(cookie) = session_cookie {
+        if let Ok(value) = HeaderValue::from_str(&cookie.to_string()) {
+            response.headers_mut().append(header::SET_COOKIE, value);
+        }
+    }
+    Ok(response)
+}
+
+fn authenticated_user_from(
+    username: String,
+    user: &user::Model,
+    api_token_id: Option<i32>,
+) -> Result<AuthenticatedUser, AppError> {
+    Ok(AuthenticatedUser {
+        id: user.id,
+        username,
+        role: Role::from_db(&user.role),
+        organization_id: user
+            .active_organization_id
+            .ok_or_else(|| AppError::InternalServerError("User has no active organization".to_string()))?,
+        api_token_id,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_configured_proxies_are_trusted() {
+        let loopback: IpAddr = "127.0.0.1".parse().unwrap();
+        let proxy: IpAddr = "10.0.0.2".parse().unwrap();
+        assert!(proxy_is_trusted(Some(loopback), &[]));
+        assert!(!proxy_is_trusted(Some(proxy), &[]));
+        assert!(proxy_is_trusted(Some(proxy), &[proxy]));
+        assert!(!proxy_is_trusted(Some(loopback), &[proxy]));
+        assert!(proxy_is_trusted(Some("::ffff:10.0.0.2".parse().unwrap()), &[proxy]));
+        assert!(proxy_is_trusted(None, &[proxy]));
+    }
+
+    #[test]
+    fn trusted_usernames_are_trimmed_and_bounded() {
+        assert_eq!(parse_trusted_username(&HeaderValue::from_static(" alice ")), Some("alice".to_string()));
+        assert_eq!(parse_trusted_username(&HeaderValue::from_static("  ")), None);
+        let long = HeaderValue::from_str(&"a".repeat(MAX_TRUSTED_USERNAME_LEN + 1)).unwrap();
+        assert_eq!(parse_trusted_username(&long), None);
+    }
 }
@@ -0,0 +1,41 @@
+//! Passes on the client certificate a reverse proxy verified, so the agent
+//! connection handlers see it just like one presented to the server's own TLS
+//! listener. The header is only read from trusted proxies, and a certificate
+//! from the TLS listener takes precedence.
+
+use axum::{
+    body::Body as AxumBody,
+    extract::{ConnectInfo, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+use crate::server::config::ServerConfig;
+use crate::services::agent_ca::{self, ClientCertificate};
+use crate::web::middleware::auth::proxy_is_trusted;
+
+pub async fn forwarded_client_certificate(
+    State(config): State<Arc<ServerConfig>>,
+    mut req: Request<AxumBody>,
+    next: Next,
+) -> Response {
+    if let Some(header) = &config.client_cert_header {
+        let peer = req
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let forwarded = (req.extensions().get::<ClientCertificate>().is_none()
+            && proxy_is_trusted(peer, &config.trusted_proxy_ips))
+        .then(|| req.headers().get(header.as_str()))
+        .flatten()
+        .and_then(|value| value.to_str().ok())
+        .and_then(agent_ca::client_certificate_from_header);
+        if let Some(certificate) = forwarded {
+            req.extensions_mut().insert(certificate);
+        }
+    }
+    next.run(req).await
+}
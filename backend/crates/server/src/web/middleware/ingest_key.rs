@@ -0,0 +1,38 @@
+use axum::{
+    body::Body as AxumBody,
+    extract::State,
+    http::{header, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::ingest_key_service;
+use crate::web::{AppState, error::AppError};
+
+pub const INGEST_KEY_HEADER: &str = "x-ingest-key";
+
+/// Accepts an ingest key from `Authorization: Bearer` or the `X-Ingest-Key`
+/// header and stores the key, which only grants metric writes, as a request
+/// extension.
+pub async fn ingest_key_auth(
+    State(state): State<Arc<AppState>>,
+    mut req: Request<AxumBody>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let key = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or_else(|| req.headers().get(INGEST_KEY_HEADER).and_then(|value| value.to_str().ok()))
+        .map(str::to_string)
+        .ok_or_else(|| AppError::Unauthorized("Missing ingest key".to_string()))?;
+
+    let key = ingest_key_service::authenticate_ingest_key(state.duckdb_pool.clone(), &key)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid ingest key".to_string()))?;
+
+    req.extensions_mut().insert(key);
+    Ok(next.run(req).await)
+}
@@ -1,2 +1,11 @@
+pub mod audit;
 pub mod auth;
+pub mod client_cert;
 pub mod i18n;
+pub mod ingest_key;
+pub mod public_key;
+pub mod read_only;
+pub mod request_id;
+pub mod role;
+pub mod storage;
+pub mod surface;
@@ -0,0 +1,56 @@
+use axum::{
+    body::Body as AxumBody,
+    extract::{Query, State},
+    http::Request,
+    middleware::Next,
+    response::Response,
+};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::db::duckdb_service::public_api_key_service;
+use crate::web::{AppState, error::AppError};
+
+pub const PUBLIC_KEY_HEADER: &str = "x-public-key";
+
+/// The user whose public data a request may read, set by [`public_key_auth`].
+#[derive(Debug, Clone, Copy)]
+pub struct PublicKeyOwner {
+    pub user_id: i32,
+    pub key_id: i32,
+}
+
+#[derive(Deserialize)]
+struct KeyQuery {
+    key: Option<String>,
+}
+
+/// Accepts a public read-only key from the `X-Public-Key` header or the `key`
+/// query parameter, so it also works in plain embed URLs.
+pub async fn public_key_auth(
+    State(state): State<Arc<AppState>>,
+    mut req: Request<AxumBody>,
+    next: Next,
+) -> Result<Response, AppError> {
+    let key = req
+        .headers()
+        .get(PUBLIC_KEY_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| {
+            Query::<KeyQuery>::try_from_uri(req.uri())
+                .ok()
+                .and_then(|Query(query)| query.key)
+        })
+        .ok_or_else(|| AppError::Unauthorized("Missing public API key".to_string()))?;
+
+    let key = public_api_key_service::authenticate_public_api_key(state.duckdb_pool.clone(), &key)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid public API key".to_string()))?;
+
+    req.extensions_mut().insert(PublicKeyOwner {
+        user_id: key.user_id,
+        key_id: key.id,
+    });
+    Ok(next.run(req).await)
+}
@@ -0,0 +1,63 @@
+//! Refuses API writes while the instance is in read-only mode (see
+//! [`crate::services::read_only`]). Agents report over `/ws/agent` and
+//! application metrics arrive at `/api/ingest/`, so metrics ingestion is not
+//! affected. Command dispatch over WebSockets is
+//! checked where the socket is opened.
+
+use axum::{
+    body::Body as AxumBody,
+    extract::State,
+    http::{Method, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use crate::web::{AppError, AppState};
+
+/// Path of the admin toggle, which must stay reachable to turn the mode off.
+pub const READ_ONLY_TOGGLE_PATH: &str = "/api/admin/read-only";
+/// Writes that are still allowed: signing in, the toggle itself, and backups,
+/// which only read the database.
+const WRITABLE_WHEN_READ_ONLY: &[&str] =
+    &["/api/auth/login", "/api/auth/login/2fa", READ_ONLY_TOGGLE_PATH, "/api/admin/backup"];
+/// Chat webhooks keep answering read commands; script runs are refused by
+/// [`crate::services::script_runner`] instead.
+const WRITABLE_PREFIXES_WHEN_READ_ONLY: &[&str] = &["/api/chatops/telegram/", "/api/chatops/slack/", "/api/ingest/"];
+
+fn is_refused_when_read_only(method: &Method, path: &str) -> bool {
+    let is_write = !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+    is_write
+        && path.starts_with("/api/")
+        && !WRITABLE_WHEN_READ_ONLY.contains(&path)
+        && !WRITABLE_PREFIXES_WHEN_READ_ONLY.iter().any(|prefix| path.starts_with(prefix))
+}
+
+pub async fn reject_writes_when_read_only(
+    State(app_state): State<Arc<AppState>>,
+    req: Request<AxumBody>,
+    next: Next,
+) -> Result<Response, AppError> {
+    if is_refused_when_read_only(req.method(), req.uri().path()) {
+        app_state.read_only.ensure_writable()?;
+    }
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_toggle_stays_writable() {
+        assert!(is_refused_when_read_only(&Method::PUT, "/api/vps/1"));
+        assert!(is_refused_when_read_only(&Method::POST, "/api/batch_commands"));
+        assert!(!is_refused_when_read_only(&Method::GET, "/api/vps"));
+        assert!(!is_refused_when_read_only(&Method::PUT, READ_ONLY_TOGGLE_PATH));
+        assert!(!is_refused_when_read_only(&Method::POST, "/api/auth/login"));
+        assert!(!is_refused_when_read_only(&Method::POST, "/api/admin/backup"));
+        assert!(!is_refused_when_read_only(&Method::POST, "/api/chatops/slack/4"));
+        assert!(!is_refused_when_read_only(&Method::POST, "/api/ingest/metrics"));
+        assert!(is_refused_when_read_only(&Method::POST, "/api/chatops/bridges"));
+    }
+}
@@ -0,0 +1,77 @@
+//! Correlation IDs. Every request gets an ID, taken from `X-Request-Id` when
+//! the caller supplies a sane one and generated otherwise. It is attached to
+//! the request's tracing span, echoed in the response, and carried into
+//! batch commands so their agent-side logs can be matched up.
+
+use axum::{
+    body::Body as AxumBody,
+    http::{HeaderName, HeaderValue, Request},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Instrument;
+use uuid::Uuid;
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Caller-supplied IDs end up in logs and agent messages, so they're kept short.
+const MAX_REQUEST_ID_LEN: usize = 128;
+
+/// The correlation ID of the current request, available as an extension.
+#[derive(Debug, Clone)]
+pub struct RequestId(pub String);
+
+fn accepted_request_id(value: &str) -> Option<&str> {
+    let value = value.trim();
+    let valid = !value.is_empty()
+        && value.len() <= MAX_REQUEST_ID_LEN
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+    valid.then_some(value)
+}
+
+pub async fn assign_request_id(mut req: Request<AxumBody>, next: Next) -> Response {
+    let request_id = req
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(accepted_request_id)
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    req.extensions_mut().insert(RequestId(request_id.clone()));
+
+    let span = tracing::info_span!(
+        "request",
+        request_id = %request_id,
+        method = %req.method(),
+        path = %req.uri().path(),
+    );
+    let mut response = next.run(req).instrument(span).await;
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER.clone(), value);
+    }
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_well_formed_ids() {
+        assert_eq!(accepted_request_id(" ci-run-42:step.3 "), Some("ci-run-42:step.3"));
+        assert_eq!(
+            accepted_request_id("0b7c5c1e-2f4a-4d0e-9d2b-8f6a1c3e5d7f"),
+            Some("0b7c5c1e-2f4a-4d0e-9d2b-8f6a1c3e5d7f")
+        );
+    }
+
+    #[test]
+    fn rejects_empty_long_or_unsafe_ids() {
+        assert_eq!(accepted_request_id(""), None);
+        assert_eq!(accepted_request_id("a b"), None);
+        assert_eq!(accepted_request_id("line\nbreak"), None);
+        assert_eq!(accepted_request_id(&"x".repeat(MAX_REQUEST_ID_LEN + 1)), None);
+    }
+}
@@ -0,0 +1,138 @@
+//! Role checks for route groups. These run after [`super::auth::auth`], so
+//! they must be added as an inner `route_layer` (before the auth layer).
+
+use axum::{
+    body::Body as AxumBody,
+    extract::Extension,
+    http::{Method, Request},
+    middleware::Next,
+    response::Response,
+};
+
+use crate::web::error::AppError;
+use crate::web::models::{AuthenticatedUser, Role};
+
+fn is_read_only(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// The least role allowed to make a request with `method` to a group where
+/// reads are open to everyone and writes need `write_role`.
+fn required_role(method: &Method, write_role: Role) -> Role {
+    if is_read_only(method) { Role::Viewer } else { write_role }
+}
+
+fn check(user: &AuthenticatedUser, required: Role) -> Result<(), AppError> {
+    if user.role >= required {
+        Ok(())
+    } else {
+        Err(AppError::Forbidden(format!("This action requires the {} role", required.as_str())))
+    }
+}
+
+/// Everything in the group needs an admin.
+pub async fn require_admin(
+    Extension(user): Extension<AuthenticatedUser>,
+    req: Request<AxumBody>,
+    next: Next,
+) -> Result<Response, AppError> {
+    check(&user, Role::Admin)?;
+    Ok(next.run(req).await)
+}
+
+/// Everything in the group, reads included, needs an operator.
+pub async fn require_operator(
+    Extension(user): Extension<AuthenticatedUser>,
+    req: Request<AxumBody>,
+    next: Next,
+) -> Result<Response, AppError> {
+    check(&user, Role::Operator)?;
+    Ok(next.run(req).await)
+}
+
+/// Viewers may read; changing anything needs an operator.
+pub async fn writes_require_operator(
+    Extension(user): Extension<AuthenticatedUser>,
+    req: Request<AxumBody>,
+    next: Next,
+) -> Result<Response, AppError> {
+    check(&user, required_role(req.method(), Role::Operator))?;
+    Ok(next.run(req).await)
+}
+
+/// Viewers may read; changing anything needs an admin. For instance-wide
+/// settings that apply to every organization.
+pub async fn writes_require_admin(
+    Extension(user): Extension<AuthenticatedUser>,
+    req: Request<AxumBody>,
+    next: Next,
+) -> Result<Response, AppError> {
+    check(&user, required_role(req.method(), Role::Admin))?;
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{Router, http::StatusCode, middleware::from_fn, routing::get};
+    use tower::ServiceExt;
+
+    /// Same guard as the file and install script routes of `/api/vps`.
+    async fn status_for(role: Role, uri: &str) -> StatusCode {
+        let user = AuthenticatedUser {
+            id: 1,
+            username: "user".to_string(),
+            role,
+            organization_id: 1,
+            api_token_id: None,
+        };
+        let router = Router::new()
+            .route("/{vps_id}/files/download", get(|| async { "file" }))
+            .route("/{vps_id}/install-script", get(|| async { "script" }))
+            .route_layer(from_fn(require_operator))
+            .route_layer(from_fn(writes_require_operator))
+            .layer(axum::Extension(user));
+        let request = Request::builder().uri(uri).body(AxumBody::empty()).unwrap();
+        router.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn viewers_cannot_read_host_files_or_install_scripts() {
+        for uri in ["/1/files/download?path=/etc/shadow", "/1/install-script"] {
+            assert_eq!(status_for(Role::Viewer, uri).await, StatusCode::FORBIDDEN);
+            assert_eq!(status_for(Role::Operator, uri).await, StatusCode::OK);
+        }
+    }
+
+    async fn settings_status_for(role: Role, method: Method) -> StatusCode {
+        let user = AuthenticatedUser {
+            id: 1,
+            username: "user".to_string(),
+            role,
+            organization_id: 1,
+            api_token_id: None,
+        };
+        let router = Router::new()
+            .route("/uptime", get(|| async { "settings" }).put(|| async { "saved" }))
+            .route_layer(from_fn(writes_require_admin))
+            .layer(axum::Extension(user));
+        let request = Request::builder().method(method).uri("/uptime").body(AxumBody::empty()).unwrap();
+        router.oneshot(request).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn only_admins_change_instance_settings() {
+        assert_eq!(settings_status_for(Role::Viewer, Method::GET).await, StatusCode::OK);
+        assert_eq!(settings_status_for(Role::Operator, Method::PUT).await, StatusCode::FORBIDDEN);
+        assert_eq!(settings_status_for(Role::Admin, Method::PUT).await, StatusCode::OK);
+    }
+
+    #[test]
+    fn viewers_only_read() {
+        assert_eq!(required_role(&Method::GET, Role::Operator), Role::Viewer);
+        assert_eq!(required_role(&Method::POST, Role::Operator), Role::Operator);
+        assert_eq!(required_role(&Method::DELETE, Role::Admin), Role::Admin);
+        assert!(Role::Admin > Role::Operator && Role::Operator > Role::Viewer);
+        assert_eq!(Role::from_db("user"), Role::Viewer);
+    }
+}
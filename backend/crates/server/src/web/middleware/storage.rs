@@ -0,0 +1,52 @@
+//! While storage is degraded (see [`crate::db::duckdb_service::recovery`]),
+//! reads are served from the fallback database and writes are refused with
+//! 503 instead of failing one by one deep inside a handler.
+
+use axum::{
+    body::Body as AxumBody,
+    extract::State,
+    http::{Method, Request},
+    middleware::Next,
+    response::Response,
+};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::recovery::StorageMode;
+use crate::web::{AppError, AppState};
+
+/// Writes that are still allowed because they don't touch the database.
+const WRITABLE_WHEN_DEGRADED: &[&str] = &["/api/auth/login", "/api/auth/login/2fa"];
+
+fn is_refused_when_degraded(method: &Method, path: &str) -> bool {
+    let is_write = !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS);
+    is_write && path.starts_with("/api/") && !WRITABLE_WHEN_DEGRADED.contains(&path)
+}
+
+pub async fn reject_writes_when_degraded(
+    State(app_state): State<Arc<AppState>>,
+    req: Request<AxumBody>,
+    next: Next,
+) -> Result<Response, AppError> {
+    if let StorageMode::Degraded { reason, .. } = &app_state.storage_mode {
+        if is_refused_when_degraded(req.method(), req.uri().path()) {
+            return Err(AppError::ServiceUnavailable(format!(
+                "Storage is unavailable ({reason}); the server is read-only until it is repaired"
+            )));
+        }
+    }
+    Ok(next.run(req).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_api_writes_are_refused() {
+        assert!(is_refused_when_degraded(&Method::POST, "/api/vps"));
+        assert!(is_refused_when_degraded(&Method::DELETE, "/api/alerts/3"));
+        assert!(!is_refused_when_degraded(&Method::GET, "/api/vps"));
+        assert!(!is_refused_when_degraded(&Method::POST, "/api/auth/login"));
+        assert!(!is_refused_when_degraded(&Method::GET, "/ws/metrics"));
+    }
+}
@@ -0,0 +1,72 @@
+//! When the admin API has its own listener, the main listener only serves what
+//! agents, status pages and notification links need; everything else under
+//! `/api` and `/ws` answers 404 there.
+
+use axum::{
+    body::Body as AxumBody,
+    http::{Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+/// Endpoints reachable on the public listener. Paths outside `/api` and `/ws`
+/// (the frontend bundle and agent gRPC) are always public.
+const PUBLIC_PATH_PREFIXES: &[&str] = &[
+    "/api/health",
+    "/api/ws-schema",
+    "/api/branding",
+    "/api/share",
+    "/api/public",
+    "/api/alert-actions",
+    "/api/enroll",
+    "/api/agent/download",
+    "/api/chatops/telegram",
+    "/api/chatops/slack",
+    "/ws/public",
+    "/ws/agent",
+];
+
+fn matches_prefix(path: &str, prefix: &str) -> bool {
+    path.strip_prefix(prefix)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+}
+
+pub fn is_public_path(path: &str) -> bool {
+    let is_api = ["/api", "/ws", "/login_test_simple"]
+        .iter()
+        .any(|prefix| matches_prefix(path, prefix));
+    !is_api || PUBLIC_PATH_PREFIXES.iter().any(|prefix| matches_prefix(path, prefix))
+}
+
+pub async fn public_surface_only(req: Request<AxumBody>, next: Next) -> Response {
+    if is_public_path(req.uri().path()) {
+        next.run(req).await
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn admin_api_is_not_public() {
+        assert!(is_public_path("/"));
+        assert!(is_public_path("/assets/index.js"));
+        assert!(is_public_path("/agent_service.AgentCommunicationService/EstablishCommunicationStream"));
+        assert!(is_public_path("/api/health"));
+        assert!(is_public_path("/api/share/abc"));
+        assert!(is_public_path("/api/public/status/main"));
+        assert!(is_public_path("/ws/public"));
+        assert!(is_public_path("/api/enroll/install-script"));
+        assert!(is_public_path("/api/agent/download/latest/x86_64-unknown-linux-gnu"));
+        assert!(!is_public_path("/api/agent/releases"));
+        assert!(!is_public_path("/api/vps"));
+        assert!(!is_public_path("/api/status-pages"));
+        assert!(!is_public_path("/api/auth/login"));
+        assert!(!is_public_path("/api/sharex"));
+        assert!(!is_public_path("/api/chatops/bridges"));
+        assert!(!is_public_path("/ws/metrics"));
+    }
+}
@@ -7,32 +7,44 @@ use axum::{
     routing::{get, post},
 };
 use rust_embed::RustEmbed;
+use std::convert::Infallible;
+use std::path::PathBuf;
 use std::sync::Arc;
 use tokio::sync::{Mutex, broadcast, mpsc};
+use tower::{ServiceExt, util::BoxCloneService};
+use tracing::{info, warn};
 
+use crate::alerting::log_matcher::LogMatcher;
 use crate::axum_embed::{FallbackBehavior, ServeEmbed};
 use crate::db::entities::performance_metric;
+use crate::db::storage::SharedStorage;
 use crate::notifications::encryption::EncryptionService;
 // use crate::db::duckdb_service::alert_service::AlertService;
 use crate::server::agent_state::{ConnectedAgents, LiveServerDataCache};
-use crate::server::command_dispatcher::CommandDispatcher;
+use crate::server::command_dispatcher::{CommandDispatcher, PendingCommandResponses};
 use crate::server::config::ServerConfig;
 use crate::server::result_broadcaster::{BatchCommandUpdateMsg, ResultBroadcaster};
+use crate::server::terminal_sessions::TerminalSessions;
+use crate::web::handlers::ws_history::WsHistory;
 use crate::web::models::websocket_models::WsMessage;
 use axum_extra::extract::cookie::{Cookie, SameSite};
 use tower_http::cors::{Any, CorsLayer};
-use crate::db::duckdb_service::DuckDbPool;
+use crate::db::duckdb_service::{recovery::StorageMode, DuckDbPool};
 
+use crate::services::agent_ca::AgentCa;
 use crate::services::auth_service;
+use crate::services::metric_ingest::IngestRateLimiter;
+use crate::services::read_only::ReadOnlyMode;
 use crate::web::{
     error::AppError,
     handlers::*,
-    middleware::auth,
+    middleware::{auth, role},
     models::{LoginRequest, RegisterRequest},
     routes::*,
 };
 
 pub mod error;
+pub mod frontend_dir;
 pub mod handlers;
 pub mod middleware;
 pub mod models;
@@ -42,20 +54,48 @@ pub mod routes;
 #[folder = "../../../frontend/dist"]
 pub struct Assets;
 
-pub fn create_static_file_service() -> ServeEmbed<Assets> {
-    ServeEmbed::<Assets>::with_parameters(
+pub type StaticFileService =
+    BoxCloneService<axum::http::Request<axum::body::Body>, axum::http::Response<axum::body::Body>, Infallible>;
+
+/// Serves the frontend from `frontend_dir` when it exists, otherwise from the embedded bundle.
+pub fn create_static_file_service(frontend_dir: Option<&str>) -> StaticFileService {
+    if let Some(dir) = frontend_dir {
+        let root = PathBuf::from(dir);
+        if root.is_dir() {
+            info!(frontend_dir = %dir, "Serving frontend from disk instead of the embedded bundle.");
+            let root = Arc::new(root);
+            return BoxCloneService::new(tower::service_fn(
+                move |req: axum::http::Request<axum::body::Body>| {
+                    let root = root.clone();
+                    async move { Ok::<_, Infallible>(frontend_dir::serve_frontend_dir(root, req).await) }
+                },
+            ));
+        }
+        warn!(frontend_dir = %dir, "Configured frontend directory does not exist. Falling back to the embedded bundle.");
+    }
+
+    let embedded = ServeEmbed::<Assets>::with_parameters(
         Some("index.html".to_string()),
         FallbackBehavior::Ok,
         Some("index.html".to_string()),
-    )
+    );
+    BoxCloneService::new(embedded.map_response(|res| res.map(axum::body::Body::new)))
 }
 
 #[derive(Clone)]
 pub struct AppState {
     pub duckdb_pool: DuckDbPool,
+    /// Pool for long report and export queries; the main pool unless a
+    /// read-only replica is configured.
+    pub reporting_pool: DuckDbPool,
+    /// VPS, metric, monitor and alert queries, served by the configured backend.
+    pub storage: SharedStorage,
     pub live_server_data_cache: LiveServerDataCache,
     pub ws_data_broadcaster_tx: broadcast::Sender<WsMessage>,
     pub public_ws_data_broadcaster_tx: broadcast::Sender<WsMessage>,
+    /// Recent broadcasts of the two channels above, for reconnecting clients.
+    pub ws_history: Arc<WsHistory>,
+    pub public_ws_history: Arc<WsHistory>,
     pub connected_agents: Arc<Mutex<ConnectedAgents>>,
     pub update_trigger_tx: mpsc::Sender<()>,
     pub encryption_service: Arc<EncryptionService>,
@@ -65,8 +105,21 @@ pub struct AppState {
     pub result_broadcaster: Arc<ResultBroadcaster>,
     pub config: Arc<ServerConfig>,
     pub metric_sender: mpsc::Sender<performance_metric::Model>,
-    pub duckdb_metric_sender: std::sync::mpsc::Sender<performance_metric::Model>,
+    pub duckdb_metric_sender: std::sync::mpsc::Sender<crate::db::duckdb_service::writer::SnapshotBatch>,
     pub shutdown_rx: tokio::sync::watch::Receiver<()>,
+    pub terminal_sessions: Arc<TerminalSessions>,
+    pub pending_command_responses: Arc<PendingCommandResponses>,
+    /// Matches the log lines agents send against the log rules.
+    pub log_matcher: Arc<LogMatcher>,
+    pub storage_mode: StorageMode,
+    pub read_only: ReadOnlyMode,
+    pub ingest_rate_limiter: IngestRateLimiter,
+    /// Sample budgets of `POST /api/vps/{id}/custom-metrics`, keyed by VPS id.
+    pub custom_metric_rate_limiter: IngestRateLimiter,
+    /// Wrong codes entered at the second login step.
+    pub two_factor_attempts: auth_service::TwoFactorAttempts,
+    /// Issues client certificates agents can authenticate with.
+    pub agent_ca: Arc<AgentCa>,
 }
 
 async fn register_handler(
@@ -84,8 +137,36 @@ async fn login_handler(
     Json(payload): Json<LoginRequest>,
 ) -> Result<impl IntoResponse, AppError> {
     let login_response =
-        auth_service::login_user(app_state.duckdb_pool.clone(), payload, &app_state.config.jwt_secret).await?;
+        match auth_service::login_user(app_state.duckdb_pool.clone(), payload, &app_state.config.jwt_secret).await? {
+            models::LoginOutcome::LoggedIn(login_response) => login_response,
+            models::LoginOutcome::TwoFactorRequired { challenge_token } => {
+                return Ok(Json(models::TwoFactorChallengeResponse {
+                    status: "2fa_required",
+                    challenge_token,
+                })
+                .into_response());
+            }
+        };
+    Ok(session_response(login_response))
+}
 
+async fn two_factor_login_handler(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<models::TwoFactorLoginRequest>,
+) -> Result<impl IntoResponse, AppError> {
+    let login_response = auth_service::complete_two_factor_login(
+        app_state.duckdb_pool.clone(),
+        &app_state.encryption_service,
+        &app_state.two_factor_attempts,
+        payload,
+        &app_state.config.jwt_secret,
+    )
+    .await?;
+    Ok(session_response(login_response))
+}
+
+/// The login response body with the session cookie set.
+fn session_response(login_response: models::LoginResponse) -> axum::response::Response {
     let auth_cookie = Cookie::build(("token", login_response.token.clone()))
         .path("/")
         .http_only(true)
@@ -98,12 +179,20 @@ async fn login_handler(
         axum::http::header::SET_COOKIE,
         auth_cookie.to_string().parse().unwrap(),
     );
+    response
+}
 
-    Ok(response)
+/// `OK` normally; reports the fallback being served while storage is degraded.
+async fn health_check_handler(State(app_state): State<Arc<AppState>>) -> String {
+    match &app_state.storage_mode {
+        StorageMode::Healthy => "OK".to_string(),
+        StorageMode::Degraded { reason, source } => format!("DEGRADED: {reason}; serving {source}"),
+    }
 }
 
-async fn health_check_handler() -> &'static str {
-    "OK"
+/// JSON schema of the dashboard WebSocket messages.
+async fn ws_schema_handler() -> Json<serde_json::Value> {
+    Json(models::websocket_models::ws_message_schema())
 }
 
 async fn login_test_handler() -> (axum::http::StatusCode, Json<serde_json::Value>) {
@@ -116,6 +205,8 @@ async fn login_test_handler() -> (axum::http::StatusCode, Json<serde_json::Value
 pub fn create_axum_router(
     live_server_data_cache: LiveServerDataCache,
     duckdb_pool: DuckDbPool,
+    reporting_pool: DuckDbPool,
+    storage: SharedStorage,
     ws_data_broadcaster_tx: broadcast::Sender<WsMessage>,
     public_ws_data_broadcaster_tx: broadcast::Sender<WsMessage>,
     connected_agents: Arc<Mutex<ConnectedAgents>>,
@@ -126,20 +217,29 @@ pub fn create_axum_router(
     result_broadcaster: Arc<ResultBroadcaster>,
     config: Arc<ServerConfig>,
     metric_sender: mpsc::Sender<performance_metric::Model>,
-    duckdb_metric_sender: std::sync::mpsc::Sender<performance_metric::Model>,
+    duckdb_metric_sender: std::sync::mpsc::Sender<crate::db::duckdb_service::writer::SnapshotBatch>,
     shutdown_rx: tokio::sync::watch::Receiver<()>,
+    terminal_sessions: Arc<TerminalSessions>,
+    pending_command_responses: Arc<PendingCommandResponses>,
+    log_matcher: Arc<LogMatcher>,
+    command_dispatcher: Arc<CommandDispatcher>,
+    storage_mode: StorageMode,
+    read_only: ReadOnlyMode,
+    agent_ca: Arc<AgentCa>,
 ) -> Router {
-    let command_dispatcher = Arc::new(CommandDispatcher::new(
-        connected_agents.clone(),
-        duckdb_pool.clone(),
-        result_broadcaster.clone(),
-    ));
-
+    let ws_history_retention = std::time::Duration::from_secs(config.ws_history_seconds);
+    let ws_history = WsHistory::spawn(&ws_data_broadcaster_tx, ws_history_retention, config.ws_history_max_messages);
+    let public_ws_history =
+        WsHistory::spawn(&public_ws_data_broadcaster_tx, ws_history_retention, config.ws_history_max_messages);
     let app_state = Arc::new(AppState {
         duckdb_pool,
+        reporting_pool,
+        storage,
         live_server_data_cache,
         ws_data_broadcaster_tx: ws_data_broadcaster_tx.clone(),
         public_ws_data_broadcaster_tx,
+        ws_history,
+        public_ws_history,
         connected_agents,
         update_trigger_tx,
         encryption_service,
@@ -151,6 +251,15 @@ pub fn create_axum_router(
         metric_sender,
         duckdb_metric_sender,
         shutdown_rx,
+        terminal_sessions,
+        pending_command_responses,
+        log_matcher,
+        storage_mode,
+        read_only,
+        ingest_rate_limiter: IngestRateLimiter::default(),
+        custom_metric_rate_limiter: IngestRateLimiter::default(),
+        two_factor_attempts: auth_service::TwoFactorAttempts::default(),
+        agent_ca,
     });
 
     let cors = CorsLayer::new()
@@ -162,7 +271,8 @@ pub fn create_axum_router(
             Method::DELETE,
             Method::OPTIONS,
         ])
-        .allow_headers(Any);
+        .allow_headers(Any)
+        .expose_headers([middleware::request_id::REQUEST_ID_HEADER.clone()]);
 
     Router::new()
         .route("/api/health", get(health_check_handler))
@@ -174,6 +284,7 @@ pub fn create_axum_router(
         .route("/api/auth/login_test", post(login_test_handler))
         .route("/api/auth/register", post(register_handler))
         .route("/api/auth/login", post(login_handler))
+        .route("/api/auth/login/2fa", post(two_factor_login_handler))
         .route(
             "/api/auth/me",
             get(auth_service::me).route_layer(axum_middleware::from_fn_with_state(
@@ -189,6 +300,27 @@ pub fn create_axum_router(
                 ),
             ),
         )
+        .nest("/api/branding", branding_routes::create_public_router())
+        .nest("/api/share", share_routes::create_public_router())
+        .nest(
+            "/api/public",
+            status_page_routes::create_public_router().merge(
+                public_key_routes::create_public_data_router().route_layer(
+                    axum_middleware::from_fn_with_state(app_state.clone(), middleware::public_key::public_key_auth),
+                ),
+            ),
+        )
+        .nest("/api/alert-actions", alert_action_routes::create_public_router())
+        .nest("/api/enroll", install_routes::create_enroll_router())
+        .nest("/api/agent/download", agent_release_routes::create_download_router())
+        .nest(
+            "/api/ingest",
+            ingest_routes::create_ingest_router().route_layer(axum_middleware::from_fn_with_state(
+                app_state.clone(),
+                middleware::ingest_key::ingest_key_auth,
+            )),
+        )
+        .route("/api/ws-schema", get(ws_schema_handler))
         .route("/ws/metrics", get(websocket_handler::websocket_handler))
         .route(
             "/ws/public",
@@ -198,70 +330,237 @@ pub fn create_axum_router(
             "/ws/agent",
             get(crate::server::ws_agent_handler::ws_agent_handler),
         )
+        .merge(terminal_routes::create_terminal_router())
         .nest(
             "/api/vps",
-            vps_routes::vps_router().route_layer(axum_middleware::from_fn_with_state(
-                app_state.clone(),
-                auth::auth,
-            )),
+            vps_routes::vps_router()
+                // Reading files or the install script (which embeds the agent
+                // secret) gives access to the host itself.
+                .merge(
+                    file_routes::file_router()
+                        .merge(install_routes::vps_install_router())
+                        .route_layer(axum_middleware::from_fn(role::require_operator)),
+                )
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/vps-groups",
+            vps_group_routes::create_vps_group_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
         )
         .nest(
             "/api/settings",
-            config_routes::create_settings_router().route_layer(
-                axum_middleware::from_fn_with_state(app_state.clone(), auth::auth),
-            ),
+            config_routes::create_settings_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_admin))
+                .merge(
+                    config_routes::create_rollout_router()
+                        .route_layer(axum_middleware::from_fn(role::require_admin)),
+                )
+                .nest(
+                    "/branding",
+                    branding_routes::create_settings_router()
+                        .route_layer(axum_middleware::from_fn(role::require_admin)),
+                )
+                .nest("/public-keys", public_key_routes::create_settings_router())
+                .nest("/ingest-keys", ingest_routes::create_settings_router())
+                .nest("/providers", provider_routes::create_settings_router())
+                .nest("/webhooks", webhook_routes::create_settings_router())
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .nest(
+                    "/encryption-keys",
+                    encryption_key_routes::create_settings_router()
+                        .route_layer(axum_middleware::from_fn(role::require_admin)),
+                )
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/dns-failover",
+            dns_failover_routes::create_dns_failover_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/organizations",
+            organization_routes::create_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
         )
         .nest(
             "/api/tags",
-            tag_routes::create_tags_router().route_layer(axum_middleware::from_fn_with_state(
-                app_state.clone(),
-                auth::auth,
-            )),
+            tag_routes::create_tags_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
         )
         .nest(
             "/api/admin/oauth",
-            admin_oauth_routes::create_router().route_layer(axum_middleware::from_fn_with_state(
-                app_state.clone(),
-                auth::auth,
-            )),
+            admin_oauth_routes::create_router()
+                .route_layer(axum_middleware::from_fn(role::require_admin))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/agent/releases",
+            agent_release_routes::create_management_router()
+                .route_layer(axum_middleware::from_fn(role::require_admin))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/admin/audit",
+            audit_routes::create_router()
+                .route_layer(axum_middleware::from_fn(role::require_admin))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/admin/backup",
+            backup_routes::create_router()
+                .route_layer(axum_middleware::from_fn(role::require_admin))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/admin/read-only",
+            read_only_routes::create_admin_router()
+                .route_layer(axum_middleware::from_fn(role::require_admin))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/traffic",
+            traffic_routes::create_traffic_router()
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/read-only",
+            read_only_routes::create_status_router()
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/admin/users",
+            admin_user_routes::create_router()
+                .route_layer(axum_middleware::from_fn(role::require_admin))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
         )
         .nest(
             "/api/notifications",
-            notification_routes::create_notification_router().route_layer(
-                axum_middleware::from_fn_with_state(app_state.clone(), auth::auth),
-            ),
+            notification_routes::create_notification_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
         )
         .nest(
             "/api/alerts",
-            alert_routes::create_alert_router().route_layer(axum_middleware::from_fn_with_state(
-                app_state.clone(),
-                auth::auth,
-            )),
+            alert_routes::create_alert_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/archive",
+            archive_routes::create_archive_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
         )
         .nest(
             "/api/batch_commands",
-            batch_command_routes::batch_command_routes().route_layer(
-                axum_middleware::from_fn_with_state(app_state.clone(), auth::auth),
-            ),
+            batch_command_routes::batch_command_routes()
+                .route_layer(axum_middleware::from_fn(role::require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/schedules",
+            schedule_routes::create_schedule_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
         )
         .nest(
             "/api/monitors",
-            service_monitor_routes::create_service_monitor_router().route_layer(
-                axum_middleware::from_fn_with_state(app_state.clone(), auth::auth),
+            service_monitor_routes::create_service_monitor_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/inventory",
+            inventory_routes::create_inventory_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/vulnerabilities",
+            vulnerability_routes::create_vulnerability_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/metrics",
+            metrics_routes::create_catalog_router()
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/derived-metrics",
+            derived_metric_routes::create_derived_metric_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/fleet",
+            fleet_routes::create_fleet_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/network",
+            network_routes::create_network_router()
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/chatops",
+            chatops_routes::create_public_router().merge(
+                chatops_routes::create_bridge_router()
+                    .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                    .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
             ),
         )
+        .nest(
+            "/api/provisioning-tokens",
+            provisioning_routes::create_provisioning_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/reports",
+            report_routes::create_report_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/clients",
+            client_routes::create_client_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/command-policies",
+            command_policy_routes::create_command_policy_router()
+                .route_layer(axum_middleware::from_fn(role::require_admin))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
         .nest(
             "/api/command-scripts",
-            command_script_routes::command_script_routes().route_layer(
-                axum_middleware::from_fn_with_state(app_state.clone(), auth::auth),
-            ),
+            command_script_routes::command_script_routes()
+                .route_layer(axum_middleware::from_fn(role::require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
         )
         .nest(
             "/api/user",
-            user_routes::create_user_router().route_layer(axum_middleware::from_fn_with_state(
-                app_state.clone(),
-                auth::auth,
-            )),
+            user_routes::create_user_router()
+                .nest("/shares", share_routes::create_user_share_router())
+                .merge(organization_routes::create_user_router())
+                .merge(api_token_routes::create_user_router())
+                .merge(two_factor_routes::create_user_router())
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
+        )
+        .nest(
+            "/api/status-pages",
+            status_page_routes::create_status_page_router()
+                .route_layer(axum_middleware::from_fn(role::writes_require_operator))
+                .route_layer(axum_middleware::from_fn_with_state(app_state.clone(), auth::auth)),
         )
         .nest(
             "/api", // A common prefix for theme routes
@@ -269,6 +568,19 @@ pub fn create_axum_router(
                 axum_middleware::from_fn_with_state(app_state.clone(), auth::auth),
             ),
         )
+        .layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::storage::reject_writes_when_degraded,
+        ))
+        .layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::read_only::reject_writes_when_read_only,
+        ))
+        .layer(axum_middleware::from_fn_with_state(
+            app_state.clone(),
+            middleware::audit::record_mutations,
+        ))
         .with_state(app_state.clone())
+        .layer(axum_middleware::from_fn(middleware::request_id::assign_request_id))
         .layer(cors)
 }
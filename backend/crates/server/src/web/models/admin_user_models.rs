@@ -0,0 +1,30 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::entities::user;
+use crate::web::models::Role;
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AdminUserResponse {
+    pub id: i32,
+    pub username: String,
+    pub role: Role,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<user::Model> for AdminUserResponse {
+    fn from(user: user::Model) -> Self {
+        Self {
+            id: user.id,
+            role: Role::from_db(&user.role),
+            username: user.username,
+            created_at: user.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UpdateUserRoleRequest {
+    pub role: Role,
+}
@@ -1,16 +1,36 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::db::enums::AlertSeverity;
+
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct CreateAlertRuleRequest {
     pub name: String,
     pub vps_id: Option<i32>,
+    // The three below may be omitted when `condition_expression` is given.
+    #[serde(default)]
     pub metric_type: String,
+    #[serde(default)]
     pub threshold: f64,
+    #[serde(default)]
     pub comparison_operator: String,
     pub duration_seconds: i32,
     pub notification_channel_ids: Option<Vec<i32>>,
     pub cooldown_seconds: Option<i32>, // Added
+    pub remediation_script_id: Option<i32>,
+    /// AND/OR tree of metric conditions; see `alerting::expression`.
+    pub condition_expression: Option<serde_json::Value>,
+    /// Seconds between evaluations of this rule; the server default when omitted.
+    pub evaluation_interval_seconds: Option<i32>,
+    /// `monitor_down` rules: the monitor to watch; every monitor when omitted.
+    pub monitor_id: Option<i32>,
+    /// `monitor_down` rules: consecutive successful checks that resolve the alert.
+    pub recovery_threshold: Option<i32>,
+    /// `log_matches` rules: regex the tailed log lines are matched against.
+    pub log_pattern: Option<String>,
+    #[serde(default)]
+    pub severity: AlertSeverity,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,6 +44,16 @@ pub struct UpdateAlertRuleRequest {
     pub duration_seconds: Option<i32>,
     pub notification_channel_ids: Option<Vec<i32>>,
     pub cooldown_seconds: Option<i32>, // Added
+    pub remediation_script_id: Option<i32>,
+    /// Setting an expression turns the rule into a composite rule; setting
+    /// a `metric_type` instead turns it back into a single-metric rule.
+    pub condition_expression: Option<serde_json::Value>,
+    /// `0` goes back to the server default.
+    pub evaluation_interval_seconds: Option<i32>,
+    pub monitor_id: Option<i32>,
+    pub recovery_threshold: Option<i32>,
+    pub log_pattern: Option<String>,
+    pub severity: Option<AlertSeverity>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -34,3 +64,84 @@ pub struct UpdateAlertRuleStatusRequest {
 
 // The response for get/create/update will typically be the db::models::AlertRule struct,
 // with notification_channel_ids populated by the service layer.
+
+/// A fired alert with the activity on its VPS shortly before it fired.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertEventDetail {
+    #[serde(flatten)]
+    pub event: crate::db::entities::alert_event::Model,
+    pub rule_name: String,
+    pub vps_name: String,
+    pub state: AlertEventState,
+}
+
+/// Where an alert event is in its life: firing and nobody has looked at it
+/// yet, acknowledged but still firing, or resolved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertEventState {
+    Open,
+    Acknowledged,
+    Resolved,
+}
+
+impl AlertEventState {
+    pub fn of(event: &crate::db::entities::alert_event::Model) -> Self {
+        if event.resolve_time.is_some() {
+            Self::Resolved
+        } else if event.acknowledged_at.is_some() {
+            Self::Acknowledged
+        } else {
+            Self::Open
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertEventListQuery {
+    pub vps_id: Option<i32>,
+    pub rule_id: Option<i32>,
+    pub state: Option<AlertEventState>,
+    pub severity: Option<AlertSeverity>,
+    /// Events that fired at or after this time.
+    pub since: Option<DateTime<Utc>>,
+    /// Events that fired before this time.
+    pub until: Option<DateTime<Utc>>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertEventPage {
+    pub items: Vec<AlertEventDetail>,
+    /// Events matching the filters, across all pages.
+    pub total: u64,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+/// Unresolved events of one VPS or rule.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertEventCount {
+    pub id: i32,
+    pub name: String,
+    pub open: u64,
+    pub acknowledged: u64,
+}
+
+/// Counts for an "open incidents" overview.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertEventSummary {
+    pub open: u64,
+    pub acknowledged: u64,
+    pub resolved_last_24h: u64,
+    /// VPS with unresolved events, most first.
+    pub by_vps: Vec<AlertEventCount>,
+    /// Rules with unresolved events, most first.
+    pub by_rule: Vec<AlertEventCount>,
+}
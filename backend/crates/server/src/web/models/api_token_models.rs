@@ -0,0 +1,22 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::entities::api_token;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateApiTokenRequest {
+    pub name: String,
+    /// Defaults to read-only.
+    pub scopes: Option<Vec<String>>,
+    /// Never expires when omitted.
+    pub expires_in_days: Option<u32>,
+}
+
+/// Returned once on creation; `token` can't be retrieved afterwards.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedApiTokenResponse {
+    #[serde(flatten)]
+    pub api_token: api_token::Model,
+    pub token: String,
+}
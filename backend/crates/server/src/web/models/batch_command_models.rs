@@ -22,8 +22,136 @@ pub struct CreateBatchCommandRequest {
     pub command_content: Option<String>,
     pub script_id: Option<String>,
     pub working_directory: Option<String>,
-    pub target_vps_ids: Vec<i32>, // Assuming vps_id is String, adjust if it's Uuid or i32
+    #[serde(flatten)]
+    pub targets: BatchTargets,
     pub execution_alias: Option<String>,
+    #[serde(default)]
+    pub strategy: ExecutionStrategy,
+    /// Agents kill a child command that runs longer than this.
+    #[serde(default)]
+    pub timeout_seconds: Option<u32>,
+    #[serde(default)]
+    pub retry: Option<RetryPolicy>,
+}
+
+/// Runs a failed child task again, up to `max_attempts` attempts in all.
+/// The n-th retry waits `backoff_seconds * 2^(n-1)`, at most an hour.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    #[serde(default = "default_retry_backoff")]
+    pub backoff_seconds: u32,
+}
+
+fn default_retry_backoff() -> u32 {
+    30
+}
+
+/// How the child tasks of a batch command are started. Targets run in waves;
+/// a wave starts once every task of the previous one has finished.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ExecutionStrategy {
+    /// Every target at once.
+    #[default]
+    Parallel,
+    /// At most `max_concurrency` targets per wave.
+    Rolling {
+        max_concurrency: usize,
+        /// Skip the remaining waves once a task of a wave fails.
+        #[serde(default = "default_halt_on_failure")]
+        halt_on_failure: bool,
+    },
+    /// The first target on its own, then the rest (in waves of
+    /// `max_concurrency`, if given) once it succeeded and passed the health
+    /// check. A failed canary always stops the batch.
+    Canary {
+        #[serde(default)]
+        max_concurrency: Option<usize>,
+        #[serde(default = "default_halt_on_failure")]
+        halt_on_failure: bool,
+        #[serde(default)]
+        health_check: Option<CanaryHealthCheck>,
+    },
+}
+
+fn default_halt_on_failure() -> bool {
+    true
+}
+
+/// Checked after the canary succeeded: `url` has to answer a GET with a 2xx
+/// status within `timeout_seconds`, `delay_seconds` after the canary finished.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CanaryHealthCheck {
+    pub url: String,
+    #[serde(default)]
+    pub delay_seconds: u64,
+    #[serde(default = "default_health_check_timeout")]
+    pub timeout_seconds: u64,
+}
+
+fn default_health_check_timeout() -> u64 {
+    10
+}
+
+/// The VPS a batch command runs on: explicit IDs, plus every VPS matching a
+/// selector or a saved selection when the command is dispatched.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct BatchTargets {
+    #[serde(default)]
+    pub target_vps_ids: Vec<i32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub target_selectors: Vec<TargetSelector>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub selection_ids: Vec<i32>,
+}
+
+/// VPS matching all of the criteria given, e.g. every online VPS with a tag.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct TargetSelector {
+    pub tag_id: Option<i32>,
+    /// The group or any of its subgroups.
+    pub group_id: Option<i32>,
+    /// `online`, `offline`, ...
+    pub status: Option<String>,
+}
+
+impl TargetSelector {
+    pub fn is_empty(&self) -> bool {
+        self.tag_id.is_none() && self.group_id.is_none() && self.status.is_none()
+    }
+
+    /// How the selector is recorded on the child tasks it matched, e.g.
+    /// `tag=3,status=online`.
+    pub fn label(&self) -> String {
+        let mut parts = Vec::new();
+        if let Some(tag_id) = self.tag_id {
+            parts.push(format!("tag={tag_id}"));
+        }
+        if let Some(group_id) = self.group_id {
+            parts.push(format!("group={group_id}"));
+        }
+        if let Some(status) = &self.status {
+            parts.push(format!("status={status}"));
+        }
+        parts.join(",")
+    }
+}
+
+/// A VPS a batch command runs on and what selected it.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct ResolvedTarget {
+    pub vps_id: i32,
+    pub matched_selector: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct VpsSelectionRequest {
+    pub name: String,
+    #[serde(default)]
+    pub target_vps_ids: Vec<i32>,
+    #[serde(default)]
+    pub selectors: Vec<TargetSelector>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -48,6 +176,12 @@ pub struct ChildCommandTaskDetail {
     pub agent_started_at: Option<DateTime<Utc>>,
     pub agent_completed_at: Option<DateTime<Utc>>,
     pub last_output_at: Option<DateTime<Utc>>,
+    pub matched_selector: Option<String>,
+    /// The wave of the execution strategy the task runs in, from 0.
+    pub wave: i32,
+    /// 1 for the first run, counting up with each retry.
+    pub attempt: i32,
+    pub next_attempt_at: Option<DateTime<Utc>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -62,3 +196,74 @@ pub struct BatchCommandTaskDetailResponse {
     pub updated_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
 }
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct CompareOutputsQuery {
+    /// `stdout` (default), `stderr` or `combined`.
+    pub stream: Option<String>,
+    /// Outputs whose line sets are at least this similar are grouped together.
+    /// Defaults to 1.0, i.e. only identical outputs share a cluster.
+    pub similarity_threshold: Option<f64>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct OutputClusterMember {
+    pub child_command_id: Uuid,
+    pub vps_id: i32,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct OutputCluster {
+    pub cluster_id: usize,
+    pub output_hash: String,
+    pub exit_code: Option<i32>,
+    pub size: usize,
+    pub is_majority: bool,
+    pub similarity_to_majority: f64,
+    /// Lines of the majority output that this cluster lacks.
+    pub lines_missing: Vec<String>,
+    /// Lines of this cluster that the majority output lacks.
+    pub lines_extra: Vec<String>,
+    pub sample_output: String,
+    pub sample_truncated: bool,
+    pub members: Vec<OutputClusterMember>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct BatchOutputComparisonResponse {
+    pub batch_command_id: Uuid,
+    pub stream: String,
+    pub total_tasks: usize,
+    pub cluster_count: usize,
+    pub majority_cluster_id: Option<usize>,
+    pub outlier_vps_ids: Vec<i32>,
+    pub clusters: Vec<OutputCluster>,
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ChildOutputQuery {
+    /// `stdout` (default) or `stderr`.
+    pub stream: Option<String>,
+    /// Serves the output as an attachment instead of inline text.
+    #[serde(default)]
+    pub download: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SearchOutputsQuery {
+    pub q: String,
+    pub limit: Option<u32>,
+}
+
+/// A finished child command whose output matched a search.
+#[derive(Debug, Serialize, Clone)]
+pub struct BatchOutputSearchHit {
+    pub batch_command_id: Uuid,
+    pub child_command_id: Uuid,
+    pub vps_id: i32,
+    pub execution_alias: Option<String>,
+    /// The stream the snippet comes from, `stdout` or `stderr`.
+    pub stream: String,
+    pub snippet: String,
+    pub finished_at: DateTime<Utc>,
+}
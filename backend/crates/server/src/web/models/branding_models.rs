@@ -0,0 +1,84 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct FooterLink {
+    pub label: String,
+    pub url: String,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AccentColors {
+    pub primary: Option<String>,
+    pub secondary: Option<String>,
+}
+
+/// Instance branding, persisted under the `branding` settings key.
+/// Uploaded images live on disk; only their content type and upload time are stored here.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct BrandingSettings {
+    pub title: String,
+    pub footer_links: Vec<FooterLink>,
+    pub default_language: String,
+    pub accent_colors: AccentColors,
+    pub logo_content_type: Option<String>,
+    pub logo_updated_at: Option<i64>,
+    pub favicon_content_type: Option<String>,
+    pub favicon_updated_at: Option<i64>,
+}
+
+impl Default for BrandingSettings {
+    fn default() -> Self {
+        Self {
+            title: "NodeNexus".to_string(),
+            footer_links: Vec::new(),
+            default_language: "auto".to_string(),
+            accent_colors: AccentColors::default(),
+            logo_content_type: None,
+            logo_updated_at: None,
+            favicon_content_type: None,
+            favicon_updated_at: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateBrandingRequest {
+    pub title: Option<String>,
+    pub footer_links: Option<Vec<FooterLink>>,
+    pub default_language: Option<String>,
+    pub accent_colors: Option<AccentColors>,
+}
+
+/// Public branding payload served by `/api/branding`.
+#[derive(Serialize, Debug, Clone, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct BrandingResponse {
+    pub title: String,
+    pub footer_links: Vec<FooterLink>,
+    pub default_language: String,
+    pub accent_colors: AccentColors,
+    pub logo_url: Option<String>,
+    pub favicon_url: Option<String>,
+}
+
+impl From<BrandingSettings> for BrandingResponse {
+    fn from(settings: BrandingSettings) -> Self {
+        Self {
+            logo_url: settings
+                .logo_updated_at
+                .map(|v| format!("/api/branding/logo?v={v}")),
+            favicon_url: settings
+                .favicon_updated_at
+                .map(|v| format!("/api/branding/favicon?v={v}")),
+            title: settings.title,
+            footer_links: settings.footer_links,
+            default_language: settings.default_language,
+            accent_colors: settings.accent_colors,
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::entities::chatops_bridge;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateChatOpsBridgeRequest {
+    pub name: String,
+    pub platform: String,
+    /// Required for Slack (the app's signing secret). Generated for Telegram when omitted.
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub allowed_chat_ids: Vec<String>,
+    pub scopes: Option<Vec<String>>,
+    #[serde(default)]
+    pub allowed_script_ids: Vec<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateChatOpsBridgeRequest {
+    pub name: Option<String>,
+    pub allowed_chat_ids: Option<Vec<String>>,
+    pub scopes: Option<Vec<String>>,
+    pub allowed_script_ids: Option<Vec<i32>>,
+}
+
+/// Returned on creation only; the secret isn't readable afterwards.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedChatOpsBridge {
+    #[serde(flatten)]
+    pub bridge: chatops_bridge::Model,
+    pub secret: String,
+    /// Where the platform should deliver commands.
+    pub webhook_url: String,
+}
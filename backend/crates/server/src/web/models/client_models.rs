@@ -0,0 +1,125 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::entities::client;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientRequest {
+    pub name: String,
+    pub contact_email: Option<String>,
+    pub notes: Option<String>,
+    #[serde(default)]
+    pub markup_percent: f64,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SetClientVpsRequest {
+    /// Replaces the client's VPS. VPS of another client are moved over.
+    pub vps_ids: Vec<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientReportQuery {
+    /// Defaults to the last completed calendar month.
+    pub start: Option<DateTime<Utc>>,
+    pub end: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientVpsCostEntry {
+    pub vps_id: i32,
+    pub name: String,
+    pub currency: Option<String>,
+    /// Provider cost prorated over the report period; `None` without renewal pricing.
+    pub cost: Option<f64>,
+    /// Cost plus the client's markup.
+    pub billed: Option<f64>,
+    pub uptime_percent: Option<f64>,
+    pub downtime_seconds: i64,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CurrencyTotal {
+    pub currency: String,
+    pub cost: f64,
+    pub billed: f64,
+    /// `billed - cost`.
+    pub commission: f64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientReport {
+    pub client: client::Model,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub entries: Vec<ClientVpsCostEntry>,
+    /// One total per currency; costs in different currencies are never summed.
+    pub totals: Vec<CurrencyTotal>,
+    /// Mean of the VPS uptimes that are known.
+    pub uptime_percent: Option<f64>,
+}
+
+/// What a client sees through a share link: billed amounts only, without the
+/// provider cost, markup or internal notes.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientStatement {
+    pub client_name: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub entries: Vec<ClientStatementEntry>,
+    pub totals: Vec<ClientStatementTotal>,
+    pub uptime_percent: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientStatementEntry {
+    pub vps_id: i32,
+    pub name: String,
+    pub currency: Option<String>,
+    pub amount: Option<f64>,
+    pub uptime_percent: Option<f64>,
+    pub downtime_seconds: i64,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct ClientStatementTotal {
+    pub currency: String,
+    pub amount: f64,
+}
+
+impl From<ClientReport> for ClientStatement {
+    fn from(report: ClientReport) -> Self {
+        ClientStatement {
+            client_name: report.client.name,
+            start: report.start,
+            end: report.end,
+            entries: report
+                .entries
+                .into_iter()
+                .map(|entry| ClientStatementEntry {
+                    vps_id: entry.vps_id,
+                    name: entry.name,
+                    currency: entry.currency,
+                    amount: entry.billed,
+                    uptime_percent: entry.uptime_percent,
+                    downtime_seconds: entry.downtime_seconds,
+                })
+                .collect(),
+            totals: report
+                .totals
+                .into_iter()
+                .map(|total| ClientStatementTotal { currency: total.currency, amount: total.billed })
+                .collect(),
+            uptime_percent: report.uptime_percent,
+        }
+    }
+}
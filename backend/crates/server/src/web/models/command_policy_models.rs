@@ -0,0 +1,51 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::duckdb_service::command_policy_service::TargetDecision;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateCommandPolicyRequest {
+    pub name: String,
+    /// "allow" or "deny".
+    pub effect: String,
+    #[serde(default)]
+    pub user_ids: Vec<i32>,
+    #[serde(default)]
+    pub script_ids: Vec<i32>,
+    #[serde(default)]
+    pub group_ids: Vec<i32>,
+    #[serde(default)]
+    pub tag_ids: Vec<i32>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateCommandPolicyRequest {
+    pub name: Option<String>,
+    pub effect: Option<String>,
+    pub user_ids: Option<Vec<i32>>,
+    pub script_ids: Option<Vec<i32>>,
+    pub group_ids: Option<Vec<i32>>,
+    pub tag_ids: Option<Vec<i32>>,
+    pub enabled: Option<bool>,
+}
+
+/// Body of `POST /api/command-policies/dry-run`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandPolicyDryRunRequest {
+    /// Omit for an ad-hoc command.
+    pub script_id: Option<i32>,
+    pub vps_ids: Vec<i32>,
+    /// The user who would run the command; defaults to the caller.
+    pub user_id: Option<i32>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CommandPolicyDryRunResponse {
+    pub allowed_vps_ids: Vec<i32>,
+    pub denied_vps_ids: Vec<i32>,
+    pub targets: Vec<TargetDecision>,
+}
@@ -15,6 +15,12 @@ pub struct WebAgentConfig {
     pub log_level: String,
     #[serde(default)]
     pub service_monitor_tasks: Vec<WebServiceMonitorTask>,
+    /// 0 turns the latency mesh off.
+    #[serde(default)]
+    pub mesh_ping_interval_seconds: u32,
+    /// Filled in by the server from the other VPS in the organization.
+    #[serde(default)]
+    pub mesh_peers: Vec<WebMeshPeer>,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -29,6 +35,13 @@ pub struct WebServiceMonitorTask {
     pub timeout_seconds: i32,
 }
 
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct WebMeshPeer {
+    pub vps_id: i32,
+    pub address: String,
+}
+
 impl From<nodenexus_common::agent_service::AgentConfig> for WebAgentConfig {
     fn from(proto: nodenexus_common::agent_service::AgentConfig) -> Self {
         Self {
@@ -42,6 +55,8 @@ impl From<nodenexus_common::agent_service::AgentConfig> for WebAgentConfig {
             feature_flags: proto.feature_flags,
             log_level: proto.log_level,
             service_monitor_tasks: proto.service_monitor_tasks.into_iter().map(Into::into).collect(),
+            mesh_ping_interval_seconds: proto.mesh_ping_interval_seconds,
+            mesh_peers: proto.mesh_peers.into_iter().map(Into::into).collect(),
         }
     }
 }
@@ -59,6 +74,8 @@ impl From<WebAgentConfig> for nodenexus_common::agent_service::AgentConfig {
             feature_flags: web.feature_flags,
             log_level: web.log_level,
             service_monitor_tasks: web.service_monitor_tasks.into_iter().map(Into::into).collect(),
+            mesh_ping_interval_seconds: web.mesh_ping_interval_seconds,
+            mesh_peers: web.mesh_peers.into_iter().map(Into::into).collect(),
         }
     }
 }
@@ -89,4 +106,21 @@ impl From<WebServiceMonitorTask> for nodenexus_common::agent_service::ServiceMon
             timeout_seconds: web.timeout_seconds,
         }
     }
-}
\ No newline at end of file
+}
+impl From<nodenexus_common::agent_service::MeshPeer> for WebMeshPeer {
+    fn from(proto: nodenexus_common::agent_service::MeshPeer) -> Self {
+        Self {
+            vps_id: proto.vps_id,
+            address: proto.address,
+        }
+    }
+}
+
+impl From<WebMeshPeer> for nodenexus_common::agent_service::MeshPeer {
+    fn from(web: WebMeshPeer) -> Self {
+        Self {
+            vps_id: web.vps_id,
+            address: web.address,
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateDerivedMetricRequest {
+    pub name: String,
+    pub expression: String,
+    pub unit: Option<String>,
+    pub description: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateDerivedMetricRequest {
+    pub name: Option<String>,
+    pub expression: Option<String>,
+    pub unit: Option<String>,
+    pub description: Option<String>,
+}
+
+/// Checks an expression without saving it, so the editor can report errors as the user types.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateExpressionRequest {
+    pub expression: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateExpressionResponse {
+    pub valid: bool,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DerivedMetricTimeseriesQuery {
+    pub vps_id: i32,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub interval: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct DerivedMetricPoint {
+    pub time: DateTime<Utc>,
+    /// `None` where the expression is undefined, e.g. a division by zero.
+    pub value: Option<f64>,
+}
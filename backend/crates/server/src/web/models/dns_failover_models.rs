@@ -0,0 +1,44 @@
+use serde::Deserialize;
+
+use crate::services::dns_provider::DnsCredentials;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateDnsFailoverPolicyRequest {
+    pub name: String,
+    pub credentials: DnsCredentials,
+    pub zone_id: String,
+    pub record_name: String,
+    pub record_type: Option<String>,
+    pub ttl: Option<i32>,
+    pub primary_ip: String,
+    pub standby_ip: String,
+    pub monitor_ids: Vec<i32>,
+    pub confirm_checks: Option<i32>,
+    /// Defaults to true so a new policy can be watched before it touches DNS.
+    pub dry_run: Option<bool>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateDnsFailoverPolicyRequest {
+    pub name: Option<String>,
+    /// Replaces the stored credentials when set.
+    pub credentials: Option<DnsCredentials>,
+    pub zone_id: Option<String>,
+    pub record_name: Option<String>,
+    pub record_type: Option<String>,
+    pub ttl: Option<i32>,
+    pub primary_ip: Option<String>,
+    pub standby_ip: Option<String>,
+    pub monitor_ids: Option<Vec<i32>>,
+    pub confirm_checks: Option<i32>,
+    pub dry_run: Option<bool>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DnsFailoverEventsQuery {
+    pub limit: Option<u32>,
+}
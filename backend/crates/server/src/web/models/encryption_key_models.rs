@@ -0,0 +1,29 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionKeyInfo {
+    /// Version 0 is the configured master key.
+    pub version: u32,
+    /// Set for keys rotated for a single organization.
+    pub organization_id: Option<i32>,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Rows still encrypted with this version.
+    pub encrypted_rows: u64,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct RotateKeyQuery {
+    /// Rotates the keys of this organization only, starting its own keys if
+    /// it used the global ones so far.
+    pub organization_id: Option<i32>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EncryptionKeyStatus {
+    pub current_version: u32,
+    pub keys: Vec<EncryptionKeyInfo>,
+}
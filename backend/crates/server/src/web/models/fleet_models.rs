@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::duckdb_service::health_service::{GroupHealth, HealthScore};
+use crate::services::fleet_map::{Cluster, Precision};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TopAggregation {
+    #[default]
+    Avg,
+    Max,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FleetTopQuery {
+    /// A shorthand such as `cpu` or `memory`, or any collected column name.
+    pub metric: String,
+    /// e.g. "15m", "1h", "7d". Defaults to one hour.
+    pub window: Option<String>,
+    pub n: Option<u32>,
+    #[serde(default)]
+    pub agg: TopAggregation,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct FleetTopEntry {
+    pub vps_id: i32,
+    pub vps_name: String,
+    pub value: f64,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FleetTopResponse {
+    pub metric: String,
+    pub agg: TopAggregation,
+    pub window_seconds: u32,
+    pub entries: Vec<FleetTopEntry>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VpsHealthEntry {
+    pub vps_id: i32,
+    pub vps_name: String,
+    pub group: Option<String>,
+    pub health: Option<HealthScore>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FleetHealthResponse {
+    pub servers: Vec<VpsHealthEntry>,
+    pub groups: Vec<GroupHealth>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FleetMapQuery {
+    /// Map zoom level the cluster hints are computed for (0–18). Defaults to 2.
+    pub zoom: Option<u8>,
+}
+
+#[derive(Serialize, Debug, Clone, Copy, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct LatestUsage {
+    pub cpu_usage_percent: Option<f64>,
+    pub memory_usage_percent: Option<f64>,
+    pub disk_usage_percent: Option<f64>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FleetMapServer {
+    pub vps_id: i32,
+    pub name: String,
+    pub status: String,
+    pub latitude: f64,
+    pub longitude: f64,
+    pub precision: Precision,
+    pub country_code: Option<String>,
+    pub health_score: Option<f64>,
+    #[serde(flatten)]
+    pub usage: LatestUsage,
+    /// Index into `clusters` when the server is drawn as part of a cluster.
+    pub cluster_id: Option<usize>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FleetMapResponse {
+    pub zoom: u8,
+    pub servers: Vec<FleetMapServer>,
+    pub clusters: Vec<Cluster>,
+    /// Servers without coordinates or a known country.
+    pub unlocated_vps_ids: Vec<i32>,
+}
@@ -0,0 +1,79 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::db::entities::ingest_key;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateIngestKeyRequest {
+    pub name: String,
+    /// Metric name prefixes the key may write, e.g. `["myapp."]`. Any name when omitted.
+    #[serde(default)]
+    pub metric_prefixes: Vec<String>,
+    /// Samples accepted per minute; defaults to the server-wide default.
+    pub rate_limit_per_minute: Option<u32>,
+}
+
+/// Returned once on creation; `key` can't be retrieved afterwards.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedIngestKeyResponse {
+    #[serde(flatten)]
+    pub ingest_key: ingest_key::Model,
+    pub key: String,
+}
+
+/// Body of `POST /api/ingest/metrics`.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct IngestMetricsRequest {
+    pub metrics: Vec<IngestMetricSample>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct IngestMetricSample {
+    pub name: String,
+    pub value: f64,
+    /// Defaults to the time the request is received.
+    pub timestamp: Option<DateTime<Utc>>,
+    /// Attributes the sample to one of the key owner's servers.
+    pub vps_id: Option<i32>,
+    #[serde(default)]
+    pub labels: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IngestMetricsResponse {
+    pub accepted: usize,
+}
+
+/// One label set of a custom metric on a VPS, with its latest sample.
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomMetricSeries {
+    pub name: String,
+    pub labels: BTreeMap<String, String>,
+    pub last_value: f64,
+    pub last_seen: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomMetricTimeseriesQuery {
+    pub name: String,
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    /// Averages each label set per bucket, e.g. "1m"; raw samples when omitted.
+    pub interval: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomMetricPoint {
+    pub time: DateTime<Utc>,
+    pub labels: BTreeMap<String, String>,
+    pub value: f64,
+}
@@ -0,0 +1,89 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct PackageFact {
+    pub name: String,
+    pub version: String,
+    pub arch: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub source_name: String,
+}
+
+/// The facts of one snapshot, in the form they are stored (compressed) and returned.
+/// Collections are kept sorted so two snapshots can be compared directly.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct InventorySnapshot {
+    pub package_manager: String,
+    pub packages: Vec<PackageFact>,
+    pub sysctl: BTreeMap<String, String>,
+    pub enabled_services: Vec<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VpsInventoryResponse {
+    pub vps_id: i32,
+    pub collected_at: DateTime<Utc>,
+    pub changed_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub snapshot: InventorySnapshot,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryChange {
+    /// `package`, `sysctl` or `service`.
+    pub category: String,
+    pub name: String,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InventoryChangeRecord {
+    pub id: i32,
+    pub vps_id: i32,
+    pub changed_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub change: InventoryChange,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum VersionOp {
+    Lt,
+    Le,
+    Eq,
+    Ge,
+    Gt,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InventorySearchQuery {
+    /// Exact package name, e.g. `openssl` or `libssl3`.
+    pub package: String,
+    /// Version constraint; both `op` and `version` must be given to filter by version.
+    pub op: Option<VersionOp>,
+    pub version: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct InventorySearchHit {
+    pub vps_id: i32,
+    pub vps_name: String,
+    pub name: String,
+    pub version: String,
+    pub arch: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct InventoryChangesQuery {
+    pub limit: Option<u32>,
+}
@@ -1,8 +1,28 @@
 use serde::{Deserialize, Serialize};
 
+pub mod admin_user_models;
+pub mod api_token_models;
 pub mod alert_models;
 pub mod batch_command_models;
+pub mod branding_models;
+pub mod chatops_models;
+pub mod client_models;
+pub mod command_policy_models;
+pub mod derived_metric_models;
+pub mod dns_failover_models;
+pub mod encryption_key_models;
+pub mod fleet_models;
+pub mod ingest_models;
+pub mod inventory_models;
+pub mod network_models;
+pub mod organization_models;
+pub mod provider_models;
+pub mod report_models;
+pub mod schedule_models;
 pub mod service_monitor_models;
+pub mod vps_group_models;
+pub mod vulnerability_models;
+pub mod webhook_models;
 pub mod websocket_models;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -21,6 +41,7 @@ pub struct LoginRequest {
 pub struct UserResponse {
     pub id: i32,
     pub username: String,
+    pub role: Role,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -30,6 +51,38 @@ pub struct LoginResponse {
     pub username: String,
 }
 
+/// Outcome of the password step of a login.
+#[derive(Debug)]
+pub enum LoginOutcome {
+    LoggedIn(LoginResponse),
+    /// The password was right but the account has 2FA enabled; the challenge
+    /// token is exchanged for a session at `/api/auth/login/2fa`.
+    TwoFactorRequired { challenge_token: String },
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorChallengeResponse {
+    /// Always "2fa_required".
+    pub status: &'static str,
+    pub challenge_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorLoginRequest {
+    pub challenge_token: String,
+    /// A TOTP code or a recovery code.
+    pub code: String,
+}
+
+/// Claims of a 2FA challenge token. Signed with a key derived from the JWT
+/// secret so it can never pass as a session token.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TwoFactorChallengeClaims {
+    pub sub: String,
+    pub user_id: i32,
+    pub exp: usize,
+}
+
 // JWT Claims structure
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Claims {
@@ -38,11 +91,55 @@ pub struct Claims {
     pub exp: usize, // Expiration time (timestamp)
 }
 
+/// What a user may do. Roles are ordered: each one includes everything the
+/// previous one may do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// Read-only access.
+    Viewer,
+    /// May change resources and run commands.
+    Operator,
+    /// May also manage users and server-wide settings.
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Operator => "operator",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Parses the `users.role` column. Unknown values get the least access.
+    pub fn from_db(value: &str) -> Self {
+        match value {
+            "admin" => Role::Admin,
+            "operator" => Role::Operator,
+            _ => Role::Viewer,
+        }
+    }
+}
+
 /// Struct to hold authenticated user details, to be passed as a request extension.
 #[derive(Debug, Clone)]
 pub struct AuthenticatedUser {
     pub id: i32,
     pub username: String,
+    pub role: Role,
+    /// The organization the user is working in; resources are scoped to it.
+    pub organization_id: i32,
+    /// Set when the request authenticated with a personal access token.
+    pub api_token_id: Option<i32>,
+}
+
+impl AuthenticatedUser {
+    /// Whether a resource belonging to `organization_id` is visible to the user.
+    pub fn can_access(&self, organization_id: Option<i32>) -> bool {
+        organization_id == Some(self.organization_id)
+    }
 }
 
 pub mod config_models;
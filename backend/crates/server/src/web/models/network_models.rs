@@ -0,0 +1,54 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MeshQuery {
+    /// e.g. "15m", "1h", "7d". Defaults to one hour.
+    pub window: Option<String>,
+    /// Width of a history point, e.g. "1m". Defaults to a sixtieth of the window.
+    pub bucket: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MeshNode {
+    pub vps_id: i32,
+    pub name: String,
+    pub group: Option<String>,
+    pub status: String,
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MeshPoint {
+    pub time: DateTime<Utc>,
+    /// Missing when every ping in the bucket was lost.
+    pub rtt_ms: Option<f64>,
+    pub loss_percent: Option<f64>,
+}
+
+/// What `source_vps_id` measured towards `target_vps_id`.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MeshLink {
+    pub source_vps_id: i32,
+    pub target_vps_id: i32,
+    /// The newest bucket.
+    pub latest_rtt_ms: Option<f64>,
+    pub latest_loss_percent: Option<f64>,
+    /// Over the whole window.
+    pub avg_rtt_ms: Option<f64>,
+    pub avg_loss_percent: Option<f64>,
+    pub last_seen: DateTime<Utc>,
+    pub history: Vec<MeshPoint>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MeshResponse {
+    pub window_seconds: u32,
+    pub bucket_seconds: u32,
+    pub nodes: Vec<MeshNode>,
+    pub links: Vec<MeshLink>,
+}
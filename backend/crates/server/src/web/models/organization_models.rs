@@ -0,0 +1,63 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::duckdb_service::service_monitor_service;
+use crate::db::entities::organization;
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct OrganizationResponse {
+    pub id: i32,
+    pub name: String,
+    /// The organization created for the user at sign-up; it can't be shared.
+    pub personal: bool,
+    /// The caller's role: "owner" or "member".
+    pub role: String,
+    /// Whether requests are currently scoped to this organization.
+    pub active: bool,
+    /// Shortest service monitor interval allowed in the organization.
+    pub min_monitor_frequency_seconds: i32,
+    pub created_at: DateTime<Utc>,
+}
+
+impl OrganizationResponse {
+    pub fn new(organization: organization::Model, role: String, active_organization_id: i32) -> Self {
+        Self {
+            id: organization.id,
+            name: organization.name,
+            personal: organization.personal_for_user_id.is_some(),
+            role,
+            active: organization.id == active_organization_id,
+            min_monitor_frequency_seconds: organization
+                .min_monitor_frequency_seconds
+                .unwrap_or(service_monitor_service::MIN_FREQUENCY_SECONDS)
+                .max(service_monitor_service::MIN_FREQUENCY_SECONDS),
+            created_at: organization.created_at,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct OrganizationNameRequest {
+    pub name: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UpsertMemberRequest {
+    pub username: String,
+    /// "owner" or "member" (default).
+    pub role: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SwitchOrganizationRequest {
+    pub organization_id: i32,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorQuotaRequest {
+    /// `None` clears the quota.
+    pub min_monitor_frequency_seconds: Option<i32>,
+}
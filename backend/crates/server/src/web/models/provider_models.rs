@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::entities::vps_power_action;
+use crate::services::provider_power::{PowerAction, ProviderKind};
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateProviderAccountRequest {
+    pub name: String,
+    pub provider: ProviderKind,
+    pub token: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateProviderAccountRequest {
+    pub name: Option<String>,
+    /// Replaces the stored token when set.
+    pub token: Option<String>,
+}
+
+/// The provider server behind a VPS.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct VpsProviderLink {
+    pub account_id: i32,
+    /// Hetzner server id, Vultr instance id or DigitalOcean droplet id.
+    pub provider_server_id: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerActionRequest {
+    pub action: PowerAction,
+    /// Omitted to request the action, then set to the returned code to carry it out.
+    pub confirmation_code: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct PowerActionResponse {
+    #[serde(flatten)]
+    pub action: vps_power_action::Model,
+    /// Only returned while the action awaits confirmation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub confirmation_code: Option<String>,
+}
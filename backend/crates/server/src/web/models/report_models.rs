@@ -0,0 +1,156 @@
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Length of an uptime report. Weeks start on Monday, all periods at 00:00 UTC.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportPeriod {
+    Weekly,
+    Monthly,
+}
+
+impl ReportPeriod {
+    pub const ALL: [ReportPeriod; 2] = [ReportPeriod::Weekly, ReportPeriod::Monthly];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReportPeriod::Weekly => "weekly",
+            ReportPeriod::Monthly => "monthly",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        ReportPeriod::ALL.into_iter().find(|p| p.as_str() == value)
+    }
+
+    /// Start and end of the period containing `time`.
+    pub fn bounds(&self, time: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        let date = time.date_naive();
+        let (start, end) = match self {
+            ReportPeriod::Weekly => {
+                let start = date - Duration::days(i64::from(date.weekday().num_days_from_monday()));
+                (start, start + Duration::days(7))
+            }
+            ReportPeriod::Monthly => {
+                let start = NaiveDate::from_ymd_opt(date.year(), date.month(), 1).unwrap_or(date);
+                let end = if date.month() == 12 {
+                    NaiveDate::from_ymd_opt(date.year() + 1, 1, 1)
+                } else {
+                    NaiveDate::from_ymd_opt(date.year(), date.month() + 1, 1)
+                }
+                .unwrap_or(date);
+                (start, end)
+            }
+        };
+        let midnight = |d: NaiveDate| Utc.from_utc_datetime(&d.and_hms_opt(0, 0, 0).unwrap_or_default());
+        (midnight(start), midnight(end))
+    }
+
+    /// The most recent period that has fully ended at `now`.
+    pub fn last_completed(&self, now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        let (current_start, _) = self.bounds(now);
+        self.bounds(current_start - Duration::seconds(1))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct VpsUptimeEntry {
+    pub vps_id: i32,
+    pub name: String,
+    /// `None` when the VPS reported no status during the period.
+    pub uptime_percent: Option<f64>,
+    pub downtime_seconds: i64,
+    pub outage_count: usize,
+    /// Mean latency of the service checks this VPS ran as an agent.
+    pub mean_check_latency_ms: Option<f64>,
+    /// Performance samples received, a measure of how complete the metrics are.
+    pub metric_samples: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct MonitorUptimeEntry {
+    pub monitor_id: i32,
+    pub name: String,
+    pub monitor_type: String,
+    /// Share of successful checks; `None` without checks in the period.
+    pub uptime_percent: Option<f64>,
+    pub mean_latency_ms: Option<f64>,
+    pub check_count: i64,
+    pub failed_check_count: i64,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct UptimeReportData {
+    pub vps: Vec<VpsUptimeEntry>,
+    pub monitors: Vec<MonitorUptimeEntry>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UptimeReport {
+    pub id: i32,
+    pub user_id: i32,
+    pub period: ReportPeriod,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub data: UptimeReportData,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct GenerateUptimeReportRequest {
+    pub period: ReportPeriod,
+    /// Any time within the period to report on. Defaults to the last completed period.
+    pub period_of: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct UptimeReportListQuery {
+    pub period: Option<ReportPeriod>,
+}
+
+/// Reports generated automatically once a period ends, and the channels they
+/// are sent to.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct UptimeReportSchedule {
+    pub weekly: bool,
+    pub monthly: bool,
+    pub channel_ids: Vec<i32>,
+}
+
+impl UptimeReportSchedule {
+    pub fn periods(&self) -> impl Iterator<Item = ReportPeriod> + '_ {
+        ReportPeriod::ALL.into_iter().filter(|p| match p {
+            ReportPeriod::Weekly => self.weekly,
+            ReportPeriod::Monthly => self.monthly,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(y: i32, m: u32, d: u32, h: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, m, d, h, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn weeks_start_on_monday() {
+        // 2025-01-01 is a Wednesday.
+        assert_eq!(ReportPeriod::Weekly.bounds(at(2025, 1, 1, 12)), (at(2024, 12, 30, 0), at(2025, 1, 6, 0)));
+        assert_eq!(ReportPeriod::Weekly.last_completed(at(2025, 1, 6, 0)), (at(2024, 12, 30, 0), at(2025, 1, 6, 0)));
+    }
+
+    #[test]
+    fn months_wrap_around_the_year() {
+        assert_eq!(ReportPeriod::Monthly.bounds(at(2024, 12, 31, 23)), (at(2024, 12, 1, 0), at(2025, 1, 1, 0)));
+        assert_eq!(ReportPeriod::Monthly.last_completed(at(2025, 1, 15, 0)), (at(2024, 12, 1, 0), at(2025, 1, 1, 0)));
+    }
+}
@@ -0,0 +1,32 @@
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateScheduledTaskRequest {
+    pub name: String,
+    /// Five-field cron expression evaluated in UTC, e.g. `0 3 * * 1-5`.
+    pub cron_expression: String,
+    pub script_id: i32,
+    #[serde(default)]
+    pub target_vps_ids: Vec<i32>,
+    #[serde(default)]
+    pub target_tag_ids: Vec<i32>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateScheduledTaskRequest {
+    pub name: Option<String>,
+    pub cron_expression: Option<String>,
+    pub script_id: Option<i32>,
+    pub target_vps_ids: Option<Vec<i32>>,
+    pub target_tag_ids: Option<Vec<i32>>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledTaskRunsQuery {
+    pub limit: Option<u32>,
+}
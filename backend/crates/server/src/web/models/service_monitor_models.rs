@@ -1,3 +1,4 @@
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -6,6 +7,7 @@ use serde_json::Value;
 pub struct ServiceMonitorDetails {
     pub id: i32,
     pub user_id: i32,
+    pub organization_id: Option<i32>,
     pub name: String,
     pub monitor_type: String,
     pub target: String,
@@ -49,6 +51,18 @@ pub struct CreateMonitor {
     pub assignments: MonitorAssignments,
 }
 
+/// `monitor_config` of a "dns" monitor, whose target is the name to resolve.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct DnsMonitorConfig {
+    /// A, AAAA, CNAME, MX or TXT. Defaults to A.
+    pub record_type: Option<String>,
+    /// Values the answer must contain; any answer passes when empty.
+    #[serde(default)]
+    pub expected_values: Vec<String>,
+    /// `ip` or `ip:port` of the resolver to query instead of the system one.
+    pub resolver: Option<String>,
+}
+
 // Model for updating an existing service monitor
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -62,7 +76,7 @@ pub struct UpdateMonitor {
     pub monitor_config: Option<serde_json::Value>,
     pub assignments: Option<MonitorAssignments>,
 }
-#[derive(Serialize, Debug, Clone)]
+#[derive(Serialize, Debug, Clone, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ServiceMonitorResultDetails {
     pub time: String,
@@ -73,4 +87,120 @@ pub struct ServiceMonitorResultDetails {
     pub is_up: bool,
     pub latency_ms: Option<i32>,
     pub details: Option<Value>,
+    pub in_maintenance: bool,
+    /// Only set for ping monitors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub packet_loss_percent: Option<f64>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateMaintenanceWindow {
+    pub starts_at: chrono::DateTime<chrono::Utc>,
+    pub ends_at: chrono::DateTime<chrono::Utc>,
+    pub reason: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateSlo {
+    pub name: String,
+    pub target_percent: f64,
+    pub window_days: Option<i32>,
+    pub latency_threshold_ms: Option<i32>,
+    pub fast_burn_rate: Option<f64>,
+    pub slow_burn_rate: Option<f64>,
+    pub is_active: Option<bool>,
+    pub channel_ids: Option<Vec<i32>>,
+}
+
+/// Distinguishes an absent field (`None`) from an explicit `null` (`Some(None)`).
+pub(crate) fn deserialize_nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateSlo {
+    pub name: Option<String>,
+    pub target_percent: Option<f64>,
+    pub window_days: Option<i32>,
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub latency_threshold_ms: Option<Option<i32>>,
+    pub fast_burn_rate: Option<f64>,
+    pub slow_burn_rate: Option<f64>,
+    pub is_active: Option<bool>,
+    pub channel_ids: Option<Vec<i32>>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapQuery {
+    /// Number of trailing weeks to aggregate. Defaults to 4.
+    pub weeks: Option<u32>,
+    /// Offset of the viewer's local time from UTC, so hours line up with their clock.
+    pub utc_offset_minutes: Option<i32>,
+}
+
+/// One hour-of-week slot. Only slots with at least one check are returned.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapCell {
+    /// 0 = Monday ... 6 = Sunday.
+    pub day_of_week: u8,
+    pub hour: u8,
+    pub checks: i64,
+    pub up_percent: f64,
+    pub avg_latency_ms: Option<f64>,
+    pub p95_latency_ms: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct HeatmapResponse {
+    pub start_time: chrono::DateTime<chrono::Utc>,
+    pub end_time: chrono::DateTime<chrono::Utc>,
+    pub utc_offset_minutes: i32,
+    pub cells: Vec<HeatmapCell>,
+}
+
+/// One time bucket of a single agent's checks.
+#[derive(Serialize, Debug, Clone, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct RegionLatencyPoint {
+    pub time: chrono::DateTime<chrono::Utc>,
+    pub latency_ms: Option<f64>,
+    /// Share of successful checks in the bucket, 0.0 to 1.0.
+    pub up_ratio: Option<f64>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RegionLatencySeries {
+    pub agent_id: i32,
+    pub agent_name: String,
+    pub median_latency_ms: Option<f64>,
+    /// Mean relative distance of this agent's latency from the other agents'
+    /// median across shared buckets (0.5 = typically 50% off). `None` when no
+    /// bucket had another agent to compare against.
+    pub divergence: Option<f64>,
+    pub points: Vec<RegionLatencyPoint>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct RegionComparisonResponse {
+    pub monitor_id: i32,
+    pub interval_seconds: i64,
+    /// Highest per-agent divergence. High with a single outlier means a
+    /// regional (routing) problem; a global slowdown moves all agents together
+    /// and keeps it low.
+    pub divergence_score: Option<f64>,
+    /// The agent behind `divergence_score` when it stands out from the rest.
+    pub outlier_agent_id: Option<i32>,
+    pub agents: Vec<RegionLatencySeries>,
 }
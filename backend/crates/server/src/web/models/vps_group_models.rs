@@ -0,0 +1,20 @@
+use serde::Deserialize;
+
+use crate::web::models::service_monitor_models::deserialize_nullable;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateVpsGroupRequest {
+    pub name: String,
+    /// Omit for a top-level group.
+    pub parent_id: Option<i32>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateVpsGroupRequest {
+    pub name: Option<String>,
+    /// `null` moves the group to the top level; omit to keep its parent.
+    #[serde(default, deserialize_with = "deserialize_nullable")]
+    pub parent_id: Option<Option<i32>>,
+}
@@ -0,0 +1,93 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Unknown,
+    Low,
+    Medium,
+    High,
+    Critical,
+}
+
+impl Severity {
+    pub const ALL: [Severity; 5] = [
+        Severity::Unknown,
+        Severity::Low,
+        Severity::Medium,
+        Severity::High,
+        Severity::Critical,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Unknown => "unknown",
+            Severity::Low => "low",
+            Severity::Medium => "medium",
+            Severity::High => "high",
+            Severity::Critical => "critical",
+        }
+    }
+
+    /// Parses the stored form. Unrecognized values map to `Unknown`.
+    pub fn parse(value: &str) -> Self {
+        Severity::ALL
+            .into_iter()
+            .find(|s| s.as_str().eq_ignore_ascii_case(value))
+            .unwrap_or(Severity::Unknown)
+    }
+
+    pub fn from_cvss_score(score: f64) -> Self {
+        match score {
+            s if s >= 9.0 => Severity::Critical,
+            s if s >= 7.0 => Severity::High,
+            s if s >= 4.0 => Severity::Medium,
+            _ => Severity::Low,
+        }
+    }
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VpsVulnerability {
+    pub vps_id: i32,
+    pub vps_name: String,
+    pub vulnerability_id: String,
+    pub aliases: Vec<String>,
+    pub summary: String,
+    pub severity: Severity,
+    pub cvss_score: Option<f64>,
+    pub package_name: String,
+    pub installed_version: String,
+    pub fixed_version: Option<String>,
+    pub first_seen_at: DateTime<Utc>,
+    pub last_seen_at: DateTime<Utc>,
+}
+
+#[derive(Deserialize, Debug, Clone, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct VulnerabilityListQuery {
+    /// Only return vulnerabilities at or above this severity.
+    pub min_severity: Option<Severity>,
+    pub vps_id: Option<i32>,
+    pub package: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(rename_all = "camelCase")]
+pub struct VulnerabilityAlertSettings {
+    /// New matches at or above this severity are notified.
+    pub min_severity: Severity,
+    /// No notifications are sent while this is empty.
+    pub channel_ids: Vec<i32>,
+}
+
+impl Default for VulnerabilityAlertSettings {
+    fn default() -> Self {
+        Self {
+            min_severity: Severity::Critical,
+            channel_ids: Vec::new(),
+        }
+    }
+}
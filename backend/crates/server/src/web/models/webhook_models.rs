@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+use crate::db::entities::webhook_subscription;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateWebhookRequest {
+    pub name: String,
+    pub url: String,
+    /// Generated when omitted.
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub event_types: Vec<String>,
+    pub is_active: Option<bool>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateWebhookRequest {
+    pub name: Option<String>,
+    pub url: Option<String>,
+    /// Replaces the signing secret.
+    pub secret: Option<String>,
+    pub event_types: Option<Vec<String>>,
+    pub is_active: Option<bool>,
+}
+
+/// Returned on creation only; the secret isn't readable afterwards.
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatedWebhook {
+    #[serde(flatten)]
+    pub webhook: webhook_subscription::Model,
+    pub secret: String,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDeliveryQuery {
+    /// "pending", "delivered" or "dead".
+    pub status: Option<String>,
+    pub limit: Option<u32>,
+}
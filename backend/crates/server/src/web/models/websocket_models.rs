@@ -1,10 +1,26 @@
+//! Messages pushed to dashboard clients over `/ws/metrics` and `/ws/public`.
+//!
+//! Every message is a JSON object `{"type": "<snake_case>", "data": {...}}`.
+//! The first message on a connection is always `hello`, carrying the
+//! protocol version the server speaks. The JSON schema for all messages is
+//! served at `/api/ws-schema`.
+//!
+//! Compatibility rules:
+//! - Within a protocol version, changes are additive only: new message types,
+//!   new fields, and fields that become nullable. Clients must ignore message
+//!   types and fields they don't know.
+//! - Removing or renaming a message type or field, or changing a field's type,
+//!   bumps [`WS_PROTOCOL_VERSION`]. The server keeps accepting clients down to
+//!   [`WS_MIN_PROTOCOL_VERSION`] for at least one release.
+
 use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
 use serde::Serialize;
 
 /// Represents a tag as it will be sent to the frontend via WebSocket.
 use serde::Deserialize;
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct Tag {
     pub id: i32,
@@ -15,17 +31,22 @@ pub struct Tag {
     pub is_visible: bool,
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ServerBasicInfo {
     pub id: i32,
     pub user_id: i32,
+    pub organization_id: Option<i32>,
     pub name: String,
     pub ip_address: Option<String>,
     pub status: String,
     pub agent_version: Option<String>,
+    /// Older than the latest agent release.
+    pub agent_outdated: bool,
     #[serde(rename = "group")]
     pub group: Option<String>,
+    pub group_id: Option<i32>,
+    pub client_id: Option<i32>,
     pub tags: Option<Vec<Tag>>, // Changed from Option<String>
     // Config status fields
     pub config_status: String,
@@ -40,6 +61,12 @@ pub struct ServerBasicInfo {
     pub traffic_reset_config_type: Option<String>,
     pub traffic_reset_config_value: Option<String>,
     pub next_traffic_reset_at: Option<DateTime<Utc>>,
+    pub is_ephemeral: bool,
+    // Location, from the agent's report or the GeoIP lookup
+    pub country_code: Option<String>,
+    pub city: Option<String>,
+    pub asn: Option<u32>,
+    pub as_organization: Option<String>,
 }
 
 #[derive(Serialize, Clone, Debug)]
@@ -70,7 +97,7 @@ pub struct ServerMetricsSnapshot {
     pub tcp_established_connection_count: Option<u32>,
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ServerWithDetails {
     #[serde(flatten)]
@@ -92,6 +119,47 @@ pub struct ServerWithDetails {
     pub renewal_notes: Option<String>,
     pub reminder_active: Option<bool>,
     // pub last_reminder_generated_at: Option<DateTime<Utc>>, // Decided to omit from websocket model for now, primarily backend concern
+
+    /// 24h/7d/30d availability from agent online/offline transitions.
+    pub uptime: Option<VpsUptime>,
+    /// Composite 0–100 health score, recomputed on every cache refresh.
+    pub health: Option<HealthScore>,
+    /// Set while the VPS is in maintenance mode.
+    pub maintenance: Option<MaintenanceState>,
+    /// System-maintained collections the VPS currently belongs to.
+    pub virtual_groups: Vec<VirtualGroup>,
+    /// Latest reading of each GPU; empty for hosts without GPUs.
+    pub gpus: Vec<GpuSnapshot>,
+}
+
+#[derive(Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct GpuSnapshot {
+    pub time: DateTime<Utc>,
+    pub index: i32,
+    pub name: String,
+    pub vendor: String,
+    pub utilization_percent: f64,
+    pub memory_used_bytes: i64,
+    pub memory_total_bytes: i64,
+    pub temperature_celsius: Option<f64>,
+    pub power_draw_watts: Option<f64>,
+}
+
+impl From<gpu_metric::Model> for GpuSnapshot {
+    fn from(metric: gpu_metric::Model) -> Self {
+        Self {
+            time: metric.time,
+            index: metric.gpu_index,
+            name: metric.name,
+            vendor: metric.vendor,
+            utilization_percent: metric.utilization_percent,
+            memory_used_bytes: metric.memory_used_bytes,
+            memory_total_bytes: metric.memory_total_bytes,
+            temperature_celsius: metric.temperature_celsius,
+            power_draw_watts: metric.power_draw_watts,
+        }
+    }
 }
 
 impl ServerWithDetails {
@@ -111,6 +179,10 @@ impl ServerWithDetails {
                 traffic_reset_config_type: None,
                 traffic_reset_config_value: None,
                 next_traffic_reset_at: None,
+                // Only the country is public
+                city: None,
+                asn: None,
+                as_organization: None,
                 // Clone the public fields from the original basic_info
                 ..self.basic_info.clone()
             },
@@ -127,28 +199,37 @@ impl ServerWithDetails {
             auto_renew_enabled: None,
             renewal_notes: None,
             reminder_active: None,
+            // Derived from renewal and traffic data, so private as well
+            virtual_groups: Vec::new(),
             // Clone the remaining public fields from the original ServerWithDetails
             ..self.clone()
         }
     }
 }
 
+use crate::db::duckdb_service::health_service::HealthScore;
+use crate::db::entities::gpu_metric;
+use crate::db::enums::AlertSeverity;
+use crate::db::duckdb_service::maintenance_service::MaintenanceState;
+use crate::db::duckdb_service::status_page_service::StatusPageView;
+use crate::db::duckdb_service::uptime_service::VpsUptime;
+use crate::db::duckdb_service::virtual_group_service::{VirtualGroup, VirtualGroupMembers};
 use crate::web::models::service_monitor_models::ServiceMonitorResultDetails;
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct FullServerListPush {
     pub servers: Vec<ServerWithDetails>,
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, JsonSchema)]
 pub struct ServiceMonitorUpdate {
     #[serde(flatten)]
     pub result_details: ServiceMonitorResultDetails,
     pub vps_id: i32,
 }
 
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PerformanceMetricPoint {
     pub time: DateTime<Utc>,
@@ -167,17 +248,177 @@ pub struct PerformanceMetricPoint {
 }
 
 /// Represents a batch of performance metrics from one or more VPS, to be sent over WebSocket.
-#[derive(Serialize, Clone, Debug)]
+#[derive(Serialize, Clone, Debug, JsonSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PerformanceMetricBatch {
     pub metrics: Vec<PerformanceMetricPoint>,
 }
 
-#[derive(Serialize, Clone, Debug)]
+/// Members of every virtual group, sent after each full server list on the
+/// private channel.
+#[derive(Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct VirtualGroupsPush {
+    pub groups: Vec<VirtualGroupMembers>,
+}
+
+/// Which VPS a `/ws/metrics` connection wants updates for. A VPS is included
+/// when it matches any of the given criteria; a subscription without criteria
+/// covers every VPS the user can see.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WsSubscription {
+    #[serde(default)]
+    pub vps_ids: Vec<i32>,
+    /// Group paths; a path also covers its subgroups ("eu" matches "eu/hetzner").
+    #[serde(default)]
+    pub groups: Vec<String>,
+    /// Group IDs; a group also covers its subgroups.
+    #[serde(default)]
+    pub group_ids: Vec<i32>,
+    #[serde(default)]
+    pub tag_ids: Vec<i32>,
+}
+
+impl WsSubscription {
+    pub fn is_empty(&self) -> bool {
+        self.vps_ids.is_empty() && self.groups.is_empty() && self.group_ids.is_empty() && self.tag_ids.is_empty()
+    }
+}
+
+/// Messages a dashboard client may send on `/ws/metrics`, in the same
+/// `{"type", "data"}` envelope as server messages.
+#[derive(Deserialize, Clone, Debug)]
+#[serde(tag = "type", content = "data")]
+#[serde(rename_all = "snake_case")]
+pub enum WsClientMessage {
+    /// Replaces the connection's subscription. The server answers with
+    /// `subscribed` and a fresh server list narrowed to the subscription.
+    Subscribe(WsSubscription),
+}
+
+/// Current version of the dashboard WebSocket protocol.
+pub const WS_PROTOCOL_VERSION: u32 = 1;
+/// Oldest protocol version clients may still request.
+pub const WS_MIN_PROTOCOL_VERSION: u32 = 1;
+
+/// First message on every connection.
+#[derive(Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct ProtocolHello {
+    pub protocol_version: u32,
+    pub min_protocol_version: u32,
+    pub server_version: String,
+    /// Server clock in Unix milliseconds; reconnecting clients pass their
+    /// estimate of it as `since`.
+    pub server_time_ms: i64,
+}
+
+impl ProtocolHello {
+    pub fn current() -> Self {
+        Self {
+            protocol_version: WS_PROTOCOL_VERSION,
+            min_protocol_version: WS_MIN_PROTOCOL_VERSION,
+            server_version: crate::version::VERSION.to_string(),
+            server_time_ms: Utc::now().timestamp_millis(),
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug, JsonSchema)]
 #[serde(tag = "type", content = "data")]
 #[serde(rename_all = "snake_case")]
 pub enum WsMessage {
+    Hello(ProtocolHello),
     FullServerList(FullServerListPush),
     ServiceMonitorResult(ServiceMonitorUpdate),
     PerformanceMetricBatch(PerformanceMetricBatch),
+    VirtualGroups(VirtualGroupsPush),
+    /// Acknowledges a client `subscribe` message.
+    Subscribed(WsSubscription),
+    /// Sent only on the public status page topic.
+    StatusPage(StatusPageView),
+    Resumed(WsResume),
+    AlertEvent(AlertEventPush),
+}
+
+/// Sent instead of the initial snapshot when a connection resumes from
+/// `since`; the broadcasts it missed follow.
+#[derive(Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct WsResume {
+    pub since_ms: i64,
+    pub message_count: usize,
+}
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertEventChange {
+    Fired,
+    Acknowledged,
+    Silenced,
+    Resolved,
+}
+
+/// An alert event that fired or changed state, for live incident views.
+#[derive(Serialize, Clone, Debug, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AlertEventPush {
+    pub change: AlertEventChange,
+    pub id: i32,
+    pub rule_id: i32,
+    pub rule_name: String,
+    pub vps_id: i32,
+    pub vps_name: String,
+    pub monitor_id: Option<i32>,
+    pub severity: AlertSeverity,
+    pub trigger_time: DateTime<Utc>,
+    pub resolve_time: Option<DateTime<Utc>>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub acknowledged_by: Option<String>,
+    pub silenced_until: Option<DateTime<Utc>>,
+    pub details: Option<String>,
+}
+
+/// Checks a protocol version requested by a client. Clients that don't ask
+/// for one get the current version.
+pub fn negotiate_protocol_version(requested: Option<u32>) -> Result<u32, String> {
+    match requested {
+        None => Ok(WS_PROTOCOL_VERSION),
+        Some(v) if (WS_MIN_PROTOCOL_VERSION..=WS_PROTOCOL_VERSION).contains(&v) => Ok(v),
+        Some(v) => Err(format!(
+            "Unsupported WebSocket protocol version {v}; supported versions are {WS_MIN_PROTOCOL_VERSION} to {WS_PROTOCOL_VERSION}"
+        )),
+    }
+}
+
+/// JSON schema of [`WsMessage`], annotated with the protocol versions.
+pub fn ws_message_schema() -> serde_json::Value {
+    let mut schema = serde_json::to_value(schemars::schema_for!(WsMessage)).unwrap_or_default();
+    if let Some(root) = schema.as_object_mut() {
+        root.insert("x-protocol-version".to_string(), WS_PROTOCOL_VERSION.into());
+        root.insert("x-min-protocol-version".to_string(), WS_MIN_PROTOCOL_VERSION.into());
+    }
+    schema
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn negotiation_accepts_supported_versions_only() {
+        assert_eq!(negotiate_protocol_version(None), Ok(WS_PROTOCOL_VERSION));
+        assert_eq!(negotiate_protocol_version(Some(WS_PROTOCOL_VERSION)), Ok(WS_PROTOCOL_VERSION));
+        assert!(negotiate_protocol_version(Some(WS_PROTOCOL_VERSION + 1)).is_err());
+        assert!(negotiate_protocol_version(Some(0)).is_err());
+    }
+
+    #[test]
+    fn schema_lists_every_message_type() {
+        let schema = ws_message_schema().to_string();
+        for tag in ["hello", "full_server_list", "service_monitor_result", "performance_metric_batch", "virtual_groups", "subscribed", "status_page", "resumed", "alert_event"] {
+            assert!(schema.contains(&format!("\"{tag}\"")), "missing {tag}");
+        }
+    }
 }
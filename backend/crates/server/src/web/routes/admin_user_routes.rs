@@ -0,0 +1,40 @@
+use axum::{
+    extract::{Extension, Path, State},
+    routing::{get, put},
+    Json, Router,
+};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::db::duckdb_service::user_service;
+use crate::web::models::admin_user_models::{AdminUserResponse, UpdateUserRoleRequest};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+/// User management, mounted at `/api/admin/users` behind the admin role.
+pub fn create_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_users_handler))
+        .route("/{id}/role", put(update_user_role_handler))
+}
+
+async fn list_users_handler(State(app_state): State<Arc<AppState>>) -> Result<Json<Vec<AdminUserResponse>>, AppError> {
+    let users = user_service::get_all_users(app_state.duckdb_pool.clone()).await?;
+    Ok(Json(users.into_iter().map(AdminUserResponse::from).collect()))
+}
+
+async fn update_user_role_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<UpdateUserRoleRequest>,
+) -> Result<Json<AdminUserResponse>, AppError> {
+    let updated = user_service::update_user_role(app_state.duckdb_pool.clone(), id, payload.role).await?;
+    info!(
+        admin_id = authenticated_user.id,
+        user_id = id,
+        role = payload.role.as_str(),
+        "Changed user role."
+    );
+    Ok(Json(updated.into()))
+}
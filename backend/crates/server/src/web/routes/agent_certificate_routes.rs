@@ -0,0 +1,108 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use tracing::info;
+
+use crate::db::duckdb_service::agent_certificate_service;
+use crate::db::entities::agent_client_certificate;
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct IssuedCertificateResponse {
+    pub certificate_pem: String,
+    /// Only returned here; the server doesn't keep it.
+    pub private_key_pem: String,
+    /// For agents that connect to the server directly rather than through a
+    /// proxy with a publicly trusted certificate.
+    pub ca_certificate_pem: String,
+    pub fingerprint: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Merged into the VPS router.
+pub fn agent_certificate_router() -> Router<Arc<AppState>> {
+    Router::new().route(
+        "/{vps_id}/client-certificate",
+        get(get_certificate_handler)
+            .post(issue_certificate_handler)
+            .delete(revoke_certificate_handler),
+    )
+}
+
+async fn check_vps_access(app_state: &AppState, user: &AuthenticatedUser, vps_id: i32) -> Result<(), AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+    Ok(())
+}
+
+async fn get_certificate_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+) -> Result<Json<agent_client_certificate::Model>, AppError> {
+    check_vps_access(&app_state, &authenticated_user, vps_id).await?;
+    agent_certificate_service::get_certificate(app_state.duckdb_pool.clone(), vps_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("No client certificate has been issued for this VPS".to_string()))
+}
+
+/// Issues a new certificate for the VPS's agent. Any previous certificate
+/// stops working at the agent's next connection, and from then on the agent
+/// must present this one.
+async fn issue_certificate_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+) -> Result<(StatusCode, Json<IssuedCertificateResponse>), AppError> {
+    check_vps_access(&app_state, &authenticated_user, vps_id).await?;
+    let issued = app_state
+        .agent_ca
+        .issue(vps_id, app_state.config.agent_cert_valid_days)
+        .map_err(AppError::InternalServerError)?;
+    agent_certificate_service::save_certificate(
+        app_state.duckdb_pool.clone(),
+        vps_id,
+        &issued.fingerprint,
+        issued.expires_at,
+    )
+    .await?;
+    info!(vps_id, user_id = authenticated_user.id, fingerprint = %issued.fingerprint, "Issued agent client certificate.");
+    Ok((
+        StatusCode::CREATED,
+        Json(IssuedCertificateResponse {
+            certificate_pem: issued.certificate_pem,
+            private_key_pem: issued.private_key_pem,
+            ca_certificate_pem: app_state.agent_ca.certificate_pem().to_string(),
+            fingerprint: issued.fingerprint,
+            expires_at: issued.expires_at,
+        }),
+    ))
+}
+
+/// Revokes the VPS's certificate; its agent authenticates with the secret
+/// alone again from its next connection.
+async fn revoke_certificate_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    check_vps_access(&app_state, &authenticated_user, vps_id).await?;
+    if !agent_certificate_service::delete_certificate(app_state.duckdb_pool.clone(), vps_id).await? {
+        return Err(AppError::NotFound("No client certificate has been issued for this VPS".to_string()));
+    }
+    info!(vps_id, user_id = authenticated_user.id, "Revoked agent client certificate.");
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,227 @@
+use axum::{
+    body::Bytes,
+    extract::{DefaultBodyLimit, Extension, Path, State},
+    http::{header, HeaderName, StatusCode},
+    response::{IntoResponse, Redirect, Response},
+    routing::{get, put},
+    Json, Router,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::{error, info};
+
+use crate::db::duckdb_service::agent_release_service;
+use crate::db::entities::agent_release;
+use crate::services::install_script::agent_file_name;
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+const MAX_AGENT_BINARY_BYTES: usize = 256 * 1024 * 1024;
+const CHECKSUM_HEADER: &str = "x-checksum-sha256";
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordReleaseRequest {
+    pub version: String,
+    pub target: String,
+    pub url: String,
+    pub sha256: String,
+    pub size_bytes: i64,
+}
+
+/// Admin routes, mounted at `/api/agent/releases`.
+pub fn create_management_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_releases_handler).post(record_release_handler))
+        .route(
+            "/{version}/{target}",
+            put(upload_release_handler)
+                .delete(delete_release_handler)
+                .layer(DefaultBodyLimit::max(MAX_AGENT_BINARY_BYTES)),
+        )
+}
+
+/// Unauthenticated downloads, mounted at `/api/agent/download`.
+pub fn create_download_router() -> Router<Arc<AppState>> {
+    Router::new().route("/{version}/{target}", get(download_handler))
+}
+
+/// Strips a leading `v` and checks the version is semver, so releases compare
+/// correctly against what agents report.
+fn normalize_version(version: &str) -> Result<String, AppError> {
+    let version = version.trim().trim_start_matches('v');
+    semver::Version::parse(version)
+        .map_err(|e| AppError::InvalidInput(format!("Invalid version '{version}': {e}")))?;
+    Ok(version.to_string())
+}
+
+/// Target triples end up in file paths, so only the characters they use are allowed.
+fn validate_target(target: &str) -> Result<(), AppError> {
+    let valid = !target.is_empty()
+        && target.len() <= 64
+        && target.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(AppError::InvalidInput(format!("Invalid target '{target}'")))
+    }
+}
+
+fn release_path(app_state: &AppState, release: &agent_release::Model) -> PathBuf {
+    PathBuf::from(&app_state.config.data_dir)
+        .join("agent_releases")
+        .join(&release.version)
+        .join(&release.file_name)
+}
+
+/// Adding or removing a release can change which agents are outdated.
+async fn refresh_cache(app_state: &AppState) {
+    if app_state.update_trigger_tx.send(()).await.is_err() {
+        error!("Failed to send update trigger after an agent release change.");
+    }
+}
+
+async fn list_releases_handler(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<agent_release::Model>>, AppError> {
+    Ok(Json(agent_release_service::list_releases(app_state.duckdb_pool.clone()).await?))
+}
+
+/// Stores an uploaded agent binary, replacing any earlier upload for the same
+/// version and target.
+async fn upload_release_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path((version, target)): Path<(String, String)>,
+    body: Bytes,
+) -> Result<(StatusCode, Json<agent_release::Model>), AppError> {
+    let version = normalize_version(&version)?;
+    validate_target(&target)?;
+    if body.is_empty() {
+        return Err(AppError::InvalidInput("The uploaded file is empty".to_string()));
+    }
+
+    let release = agent_release::Model {
+        file_name: agent_file_name(&version, &target),
+        url: None,
+        size_bytes: body.len() as i64,
+        sha256: hex::encode(Sha256::digest(&body)),
+        created_at: Utc::now(),
+        version,
+        target,
+    };
+    let path = release_path(&app_state, &release);
+    tokio::task::spawn_blocking(move || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, &body)
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+    .map_err(|e| AppError::InternalServerError(format!("Failed to store agent binary: {e}")))?;
+
+    let release = agent_release_service::save_release(app_state.duckdb_pool.clone(), &release).await?;
+    info!(
+        user_id = authenticated_user.id,
+        version = %release.version,
+        target = %release.target,
+        sha256 = %release.sha256,
+        "Uploaded agent release."
+    );
+    refresh_cache(&app_state).await;
+    Ok((StatusCode::CREATED, Json(release)))
+}
+
+/// Records a build hosted elsewhere; downloads redirect to its URL.
+async fn record_release_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<RecordReleaseRequest>,
+) -> Result<(StatusCode, Json<agent_release::Model>), AppError> {
+    let version = normalize_version(&payload.version)?;
+    validate_target(&payload.target)?;
+    if !(payload.url.starts_with("https://") || payload.url.starts_with("http://")) {
+        return Err(AppError::InvalidInput("URL must be http(s)".to_string()));
+    }
+    let sha256 = payload.sha256.trim().to_ascii_lowercase();
+    if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::InvalidInput("sha256 must be 64 hex characters".to_string()));
+    }
+    if payload.size_bytes <= 0 {
+        return Err(AppError::InvalidInput("sizeBytes must be positive".to_string()));
+    }
+
+    let release = agent_release::Model {
+        file_name: agent_file_name(&version, &payload.target),
+        url: Some(payload.url),
+        size_bytes: payload.size_bytes,
+        sha256,
+        created_at: Utc::now(),
+        version,
+        target: payload.target,
+    };
+    // A file uploaded earlier for the same release is superseded.
+    let _ = tokio::fs::remove_file(release_path(&app_state, &release)).await;
+    let release = agent_release_service::save_release(app_state.duckdb_pool.clone(), &release).await?;
+    info!(
+        user_id = authenticated_user.id,
+        version = %release.version,
+        target = %release.target,
+        "Recorded external agent release."
+    );
+    refresh_cache(&app_state).await;
+    Ok((StatusCode::CREATED, Json(release)))
+}
+
+async fn delete_release_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path((version, target)): Path<(String, String)>,
+) -> Result<StatusCode, AppError> {
+    let version = normalize_version(&version)?;
+    let release = agent_release_service::delete_release(app_state.duckdb_pool.clone(), &version, &target)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Agent release not found".to_string()))?;
+    if release.url.is_none() {
+        let _ = tokio::fs::remove_file(release_path(&app_state, &release)).await;
+    }
+    info!(user_id = authenticated_user.id, %version, %target, "Deleted agent release.");
+    refresh_cache(&app_state).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Serves an agent binary; `version` may be `latest`. The checksum header lets
+/// installers and self-updating agents verify what they got.
+async fn download_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path((version, target)): Path<(String, String)>,
+) -> Result<Response, AppError> {
+    let version = if version == "latest" { version } else { normalize_version(&version)? };
+    let release = agent_release_service::get_release(app_state.duckdb_pool.clone(), &version, &target)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Agent release not found".to_string()))?;
+
+    if let Some(url) = &release.url {
+        let checksum = [(HeaderName::from_static(CHECKSUM_HEADER), release.sha256.clone())];
+        return Ok((checksum, Redirect::temporary(url)).into_response());
+    }
+    let contents = tokio::fs::read(release_path(&app_state, &release))
+        .await
+        .map_err(|e| AppError::NotFound(format!("Agent binary missing on disk: {e}")))?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"{}\"", release.file_name),
+            ),
+            (HeaderName::from_static(CHECKSUM_HEADER), release.sha256),
+        ],
+        contents,
+    )
+        .into_response())
+}
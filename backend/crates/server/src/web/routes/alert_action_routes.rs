@@ -0,0 +1,74 @@
+//! Public endpoints behind the action buttons on alert notifications.
+//!
+//! Opening a link only shows a confirmation page; the action runs on the form
+//! POST, so link previews and prefetching in chat clients can't trigger it.
+
+use axum::{
+    extract::{Path, State},
+    response::Html,
+    routing::get,
+    Router,
+};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::alert_event_service::{self, AlertEventContext};
+use crate::services::alert_action_token::{verify_action_token, AlertAction};
+use crate::services::alert_actions;
+use crate::web::{AppError, AppState};
+
+pub fn create_public_router() -> Router<Arc<AppState>> {
+    Router::new().route("/{token}", get(confirm_action).post(perform_action))
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn render_page(title: &str, body: &str) -> Html<String> {
+    Html(format!(
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><meta name=\"viewport\" content=\"width=device-width, initial-scale=1\"><title>{title}</title></head>\
+         <body style=\"font-family: sans-serif; max-width: 32rem; margin: 3rem auto; padding: 0 1rem;\"><h2>{title}</h2>{body}</body></html>",
+        title = escape_html(title),
+    ))
+}
+
+async fn resolve(
+    app_state: &AppState,
+    token: &str,
+) -> Result<(AlertEventContext, AlertAction), AppError> {
+    let (event_id, action) = verify_action_token(token, &app_state.config.jwt_secret)?;
+    let context = alert_event_service::get_alert_event_context(app_state.duckdb_pool.clone(), event_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Alert event not found".to_string()))?;
+    Ok((context, action))
+}
+
+async fn confirm_action(
+    State(app_state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Result<Html<String>, AppError> {
+    let (context, action) = resolve(&app_state, &token).await?;
+    let body = format!(
+        "<p>Alert <b>{}</b> on <b>{}</b> fired at {}.</p><p>{}</p>\
+         <form method=\"post\"><button type=\"submit\" style=\"font-size: 1rem; padding: 0.5rem 1rem;\">{}</button></form>",
+        escape_html(&context.rule_name),
+        escape_html(&context.vps_name),
+        context.event.trigger_time.format("%Y-%m-%d %H:%M:%S UTC"),
+        escape_html(context.event.details.as_deref().unwrap_or_default()),
+        action.label(),
+    );
+    Ok(render_page(action.label(), &body))
+}
+
+async fn perform_action(
+    State(app_state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Result<Html<String>, AppError> {
+    let (context, action) = resolve(&app_state, &token).await?;
+    let outcome = alert_actions::perform_alert_action(&app_state, &context, action).await?;
+    Ok(render_page("Done", &format!("<p>{}</p>", escape_html(&outcome))))
+}
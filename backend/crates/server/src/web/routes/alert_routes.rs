@@ -1,22 +1,37 @@
 use crate::{
-    db::duckdb_service::alert_service,
+    alerting::expression::{ConditionExpr, COMPOSITE_METRIC_TYPE},
+    alerting::log_matcher,
+    alerting::schedule::{MAX_INTERVAL_SECONDS, MIN_INTERVAL_SECONDS},
+    db::duckdb_service::{
+        alert_event_service::{self, AlertEventContext, AlertEventFilter},
+        alert_service, command_script_service, custom_metric_service, derived_metric_service,
+        log_match_service, service_monitor_service,
+    },
+    services::metric_ingest::validate_metric_name,
     web::{
         models::alert_models::{
+            AlertEventDetail, AlertEventListQuery, AlertEventPage, AlertEventState, AlertEventSummary,
             CreateAlertRuleRequest, UpdateAlertRuleRequest, UpdateAlertRuleStatusRequest,
         },
+        models::websocket_models::AlertEventChange,
         models::AuthenticatedUser,
         AppError, AppState,
     },
 };
 use axum::{
-    extract::{Extension, Path, State},
+    extract::{Extension, Path, Query, State},
     routing::{get, post, put},
     Json, Router,
 };
+use chrono::Utc;
+use nodenexus_common::metric_catalog;
 use std::sync::Arc;
 
 use crate::db::models::AlertRule;
 
+const DEFAULT_EVENT_PAGE_SIZE: u32 = 50;
+const MAX_EVENT_PAGE_SIZE: u32 = 500;
+
 pub fn create_alert_router() -> Router<Arc<AppState>> {
     Router::new()
         .route(
@@ -30,14 +45,131 @@ pub fn create_alert_router() -> Router<Arc<AppState>> {
                 .delete(delete_alert_rule_handler),
         )
         .route("/{id}/status", put(update_alert_rule_status_handler))
+        .route("/events", get(list_alert_events_handler))
+        .route("/events/summary", get(get_alert_event_summary_handler))
+        .route("/events/{event_id}", get(get_alert_event_handler))
+        .route("/events/{event_id}/acknowledge", post(acknowledge_alert_event_handler))
+        .route("/events/{event_id}/resolve", post(resolve_alert_event_handler))
+}
+
+/// Validates a condition expression and returns it in normalized form.
+fn validate_condition_expression(expression: &serde_json::Value) -> Result<serde_json::Value, AppError> {
+    let expr = ConditionExpr::parse(expression).map_err(AppError::InvalidInput)?;
+    serde_json::to_value(expr).map_err(|e| AppError::InternalServerError(e.to_string()))
+}
+
+/// Rejects metric types that are neither in the metric catalog, a derived
+/// metric (whose ownership is checked separately) nor a custom metric name.
+fn validate_metric_type(metric_type: &str) -> Result<(), AppError> {
+    if metric_type.starts_with(derived_metric_service::DERIVED_METRIC_TYPE_PREFIX)
+        || metric_catalog::find(metric_type).is_some_and(|m| m.usage.alert_rule)
+    {
+        return Ok(());
+    }
+    if let Some(name) = custom_metric_service::parse_custom_metric_type(metric_type) {
+        return validate_metric_name(name).map_err(AppError::InvalidInput);
+    }
+    Err(AppError::InvalidInput(format!(
+        "Unsupported metricType '{metric_type}'. Supported: {}, derived:<id> or custom:<name>.",
+        metric_catalog::names_for(|usage| usage.alert_rule).join(", ")
+    )))
+}
+
+/// `0` (server default) or an interval the schedule supports.
+fn validate_evaluation_interval(seconds: Option<i32>) -> Result<(), AppError> {
+    match seconds {
+        None | Some(0) => Ok(()),
+        Some(seconds) if (MIN_INTERVAL_SECONDS..=MAX_INTERVAL_SECONDS).contains(&(seconds.max(0) as u64)) => Ok(()),
+        Some(_) => Err(AppError::InvalidInput(format!(
+            "evaluationIntervalSeconds must be between {MIN_INTERVAL_SECONDS} and {MAX_INTERVAL_SECONDS}, or 0 for the server default"
+        ))),
+    }
+}
+
+/// `log_matches` rules need a pattern, and it has to compile.
+fn validate_log_pattern(pattern: Option<&str>) -> Result<(), AppError> {
+    let pattern = pattern.ok_or_else(|| {
+        AppError::InvalidInput(format!(
+            "logPattern is required for {} rules.",
+            log_match_service::LOG_MATCHES_METRIC_TYPE
+        ))
+    })?;
+    log_matcher::compile_pattern(pattern)
+        .map(|_| ())
+        .map_err(AppError::InvalidInput)
+}
+
+/// The settings of `monitor_down` rules: a whole number of failed checks as
+/// the threshold, at least one successful check to resolve, and a monitor the
+/// user can see.
+async fn validate_monitor_rule(
+    app_state: &AppState,
+    user: &AuthenticatedUser,
+    threshold: Option<f64>,
+    monitor_id: Option<i32>,
+    recovery_threshold: Option<i32>,
+) -> Result<(), AppError> {
+    if let Some(threshold) = threshold {
+        if threshold < 1.0 || threshold.fract() != 0.0 {
+            return Err(AppError::InvalidInput(
+                "threshold of a monitor_down rule is the number of consecutive failed checks and must be a whole number of at least 1.".to_string(),
+            ));
+        }
+    }
+    if recovery_threshold.is_some_and(|n| n < 1) {
+        return Err(AppError::InvalidInput("recoveryThreshold must be at least 1.".to_string()));
+    }
+    if let Some(monitor_id) = monitor_id {
+        service_monitor_service::get_monitor_details_by_id(app_state.duckdb_pool.clone(), monitor_id)
+            .await?
+            .filter(|monitor| user.can_access(monitor.organization_id))
+            .ok_or_else(|| AppError::NotFound("Monitor not found".to_string()))?;
+    }
+    Ok(())
 }
 
 async fn create_alert_rule_handler(
     State(app_state): State<Arc<AppState>>,
     Extension(authenticated_user): Extension<AuthenticatedUser>,
-    Json(payload): Json<CreateAlertRuleRequest>,
+    Json(mut payload): Json<CreateAlertRuleRequest>,
 ) -> Result<Json<AlertRule>, AppError> {
     let user_id = authenticated_user.id;
+    validate_evaluation_interval(payload.evaluation_interval_seconds)?;
+    if let Some(expression) = &payload.condition_expression {
+        payload.condition_expression = Some(validate_condition_expression(expression)?);
+        payload.metric_type = COMPOSITE_METRIC_TYPE.to_string();
+    } else if payload.metric_type.is_empty() || payload.metric_type == COMPOSITE_METRIC_TYPE {
+        return Err(AppError::InvalidInput(
+            "Either metricType or conditionExpression is required.".to_string(),
+        ));
+    } else if payload.metric_type == service_monitor_service::MONITOR_DOWN_METRIC_TYPE {
+        validate_monitor_rule(
+            &app_state,
+            &authenticated_user,
+            Some(payload.threshold),
+            payload.monitor_id,
+            payload.recovery_threshold,
+        )
+        .await?;
+        // The threshold counts failed checks; there is nothing to compare.
+        payload.comparison_operator = ">=".to_string();
+    } else if payload.comparison_operator.is_empty() {
+        return Err(AppError::InvalidInput("comparisonOperator is required.".to_string()));
+    } else {
+        validate_metric_type(&payload.metric_type)?;
+    }
+    if payload.metric_type == log_match_service::LOG_MATCHES_METRIC_TYPE || payload.log_pattern.is_some() {
+        validate_log_pattern(payload.log_pattern.as_deref())?;
+    }
+    derived_metric_service::check_metric_type_ownership(
+        app_state.duckdb_pool.clone(),
+        user_id,
+        &payload.metric_type,
+    )
+    .await?;
+    if let Some(script_id) = payload.remediation_script_id {
+        command_script_service::get_script_by_id(app_state.duckdb_pool.clone(), script_id, user_id).await?;
+    }
     let alert_rule =
         alert_service::create_alert_rule(app_state.duckdb_pool.clone(), user_id, payload).await?;
     Ok(Json(alert_rule))
@@ -65,14 +197,159 @@ async fn get_alert_rule_handler(
     Ok(Json(alert_rule))
 }
 
+/// The event with its rule and VPS names, if the user can see its rule.
+async fn visible_alert_event(
+    app_state: &AppState,
+    user: &AuthenticatedUser,
+    event_id: i32,
+) -> Result<AlertEventContext, AppError> {
+    let context = alert_event_service::get_alert_event_context(app_state.duckdb_pool.clone(), event_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Alert event not found".to_string()))?;
+    // Visible exactly when its rule is.
+    alert_service::get_alert_rule_by_id_for_user(app_state.duckdb_pool.clone(), context.event.rule_id, user.id)
+        .await
+        .map_err(|_| AppError::NotFound("Alert event not found".to_string()))?;
+    Ok(context)
+}
+
+fn event_detail(context: AlertEventContext) -> AlertEventDetail {
+    AlertEventDetail {
+        state: AlertEventState::of(&context.event),
+        event: context.event,
+        rule_name: context.rule_name,
+        vps_name: context.vps_name,
+    }
+}
+
+/// Fired alerts of the organization, newest first.
+async fn list_alert_events_handler(
+    State(app_state): State<Arc<AppState>>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    Query(query): Query<AlertEventListQuery>,
+) -> Result<Json<AlertEventPage>, AppError> {
+    let filter = AlertEventFilter {
+        vps_id: query.vps_id,
+        rule_id: query.rule_id,
+        state: query.state,
+        severity: query.severity,
+        since: query.since,
+        until: query.until,
+    };
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(DEFAULT_EVENT_PAGE_SIZE).clamp(1, MAX_EVENT_PAGE_SIZE);
+    let (items, total) = alert_event_service::get_alert_event_page(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        &filter,
+        page,
+        per_page,
+    )
+    .await?;
+    Ok(Json(AlertEventPage {
+        items,
+        total,
+        page,
+        per_page,
+    }))
+}
+
+async fn get_alert_event_summary_handler(
+    State(app_state): State<Arc<AppState>>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+) -> Result<Json<AlertEventSummary>, AppError> {
+    let summary =
+        alert_event_service::get_alert_event_summary(app_state.duckdb_pool.clone(), authenticated_user.id, Utc::now())
+            .await?;
+    Ok(Json(summary))
+}
+
+/// A fired alert, including the recent activity captured when it fired.
+async fn get_alert_event_handler(
+    State(app_state): State<Arc<AppState>>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    Path(event_id): Path<i32>,
+) -> Result<Json<AlertEventDetail>, AppError> {
+    let context = visible_alert_event(&app_state, &authenticated_user, event_id).await?;
+    Ok(Json(event_detail(context)))
+}
+
+async fn acknowledge_alert_event_handler(
+    State(app_state): State<Arc<AppState>>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    Path(event_id): Path<i32>,
+) -> Result<Json<AlertEventDetail>, AppError> {
+    let mut context = visible_alert_event(&app_state, &authenticated_user, event_id).await?;
+    context.event = alert_event_service::acknowledge_alert_event(
+        app_state.duckdb_pool.clone(),
+        event_id,
+        &authenticated_user.username,
+    )
+    .await?;
+    alert_event_service::send_alert_event(&app_state.ws_data_broadcaster_tx, AlertEventChange::Acknowledged, &context);
+    Ok(Json(event_detail(context)))
+}
+
+/// Closes an event by hand. A `monitor_down` event whose monitor is still
+/// failing fires again on the next evaluation.
+async fn resolve_alert_event_handler(
+    State(app_state): State<Arc<AppState>>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    Path(event_id): Path<i32>,
+) -> Result<Json<AlertEventDetail>, AppError> {
+    visible_alert_event(&app_state, &authenticated_user, event_id).await?;
+    alert_event_service::resolve_alert_event(app_state.duckdb_pool.clone(), event_id).await?;
+    let context = visible_alert_event(&app_state, &authenticated_user, event_id).await?;
+    alert_event_service::send_alert_event(&app_state.ws_data_broadcaster_tx, AlertEventChange::Resolved, &context);
+    Ok(Json(event_detail(context)))
+}
+
 #[axum::debug_handler]
 async fn update_alert_rule_handler(
     State(app_state): State<Arc<AppState>>,
     Extension(authenticated_user): Extension<AuthenticatedUser>,
     Path(id): Path<i32>,
-    Json(payload): Json<UpdateAlertRuleRequest>,
+    Json(mut payload): Json<UpdateAlertRuleRequest>,
 ) -> Result<Json<AlertRule>, AppError> {
     let user_id = authenticated_user.id;
+    validate_evaluation_interval(payload.evaluation_interval_seconds)?;
+    if let Some(expression) = &payload.condition_expression {
+        payload.condition_expression = Some(validate_condition_expression(expression)?);
+        payload.metric_type = Some(COMPOSITE_METRIC_TYPE.to_string());
+    } else if payload.metric_type.as_deref() == Some(COMPOSITE_METRIC_TYPE) {
+        return Err(AppError::InvalidInput(
+            "conditionExpression is required for composite rules.".to_string(),
+        ));
+    } else if let Some(metric_type) = &payload.metric_type {
+        validate_metric_type(metric_type)?;
+    }
+    if payload.log_pattern.is_some() {
+        validate_log_pattern(payload.log_pattern.as_deref())?;
+    } else if payload.metric_type.as_deref() == Some(log_match_service::LOG_MATCHES_METRIC_TYPE) {
+        // Turning a rule into a log rule needs a pattern unless it already has one.
+        let existing = alert_service::get_alert_rule_by_id_for_user(app_state.duckdb_pool.clone(), id, user_id).await?;
+        validate_log_pattern(existing.log_pattern.as_deref())?;
+    }
+    let monitor_rule = payload.metric_type.as_deref() == Some(service_monitor_service::MONITOR_DOWN_METRIC_TYPE);
+    validate_monitor_rule(
+        &app_state,
+        &authenticated_user,
+        payload.threshold.filter(|_| monitor_rule),
+        payload.monitor_id,
+        payload.recovery_threshold,
+    )
+    .await?;
+    if let Some(metric_type) = &payload.metric_type {
+        derived_metric_service::check_metric_type_ownership(
+            app_state.duckdb_pool.clone(),
+            user_id,
+            metric_type,
+        )
+        .await?;
+    }
+    if let Some(script_id) = payload.remediation_script_id {
+        command_script_service::get_script_by_id(app_state.duckdb_pool.clone(), script_id, user_id).await?;
+    }
     let updated_rule =
         alert_service::update_alert_rule(app_state.duckdb_pool.clone(), id, user_id, payload)
             .await?;
@@ -0,0 +1,78 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    routing::{delete, get},
+    Json, Router,
+};
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::api_token_service;
+use crate::db::entities::api_token;
+use crate::web::models::api_token_models::{CreateApiTokenRequest, CreatedApiTokenResponse};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+/// Personal access token management, merged into `/api/user`.
+pub fn create_user_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/tokens", get(list_api_tokens).post(create_api_token))
+        .route("/tokens/{id}", delete(delete_api_token))
+}
+
+/// Tokens can't be used to mint or revoke tokens, so a leaked one can't
+/// outlive its own revocation.
+fn reject_api_token(authenticated_user: &AuthenticatedUser) -> Result<(), AppError> {
+    if authenticated_user.api_token_id.is_some() {
+        return Err(AppError::Forbidden("API tokens can't manage API tokens".to_string()));
+    }
+    Ok(())
+}
+
+async fn list_api_tokens(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<api_token::Model>>, AppError> {
+    let tokens =
+        api_token_service::get_api_tokens_for_user(app_state.duckdb_pool.clone(), authenticated_user.id).await?;
+    Ok(Json(tokens))
+}
+
+async fn create_api_token(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateApiTokenRequest>,
+) -> Result<(StatusCode, Json<CreatedApiTokenResponse>), AppError> {
+    reject_api_token(&authenticated_user)?;
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Name must not be empty".to_string()));
+    }
+    let scopes = payload
+        .scopes
+        .unwrap_or_else(|| vec![api_token_service::SCOPE_READ.to_string()]);
+    let expires_at = match payload.expires_in_days {
+        Some(0) => return Err(AppError::InvalidInput("expiresInDays must be at least 1".to_string())),
+        Some(days) => Some(Utc::now() + Duration::days(days as i64)),
+        None => None,
+    };
+    let (api_token, token) = api_token_service::create_api_token(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        name,
+        &scopes,
+        expires_at,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(CreatedApiTokenResponse { api_token, token })))
+}
+
+async fn delete_api_token(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    reject_api_token(&authenticated_user)?;
+    api_token_service::delete_api_token(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,75 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::db::duckdb_service::archive_service::{self, ArchivedRange};
+use crate::web::models::{AuthenticatedUser, Role};
+use crate::web::{AppError, AppState};
+
+const DEFAULT_ARCHIVE_QUERY_LIMIT: u32 = 1000;
+const MAX_ARCHIVE_QUERY_LIMIT: u32 = 10000;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ArchiveQuery {
+    pub start_time: DateTime<Utc>,
+    pub end_time: DateTime<Utc>,
+    pub limit: Option<u32>,
+}
+
+pub fn create_archive_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_archived_ranges_handler))
+        .route("/{table}", get(query_archive_handler))
+}
+
+/// Archived files span every user's rows, so only admins may list them.
+async fn list_archived_ranges_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ArchivedRange>>, AppError> {
+    if authenticated_user.role != Role::Admin {
+        return Err(AppError::Forbidden("Only admins can list archived files".to_string()));
+    }
+    let ranges = archive_service::list_archived_ranges(
+        app_state.duckdb_pool.clone(),
+        app_state.config.archive_dir.clone(),
+    )
+    .await?;
+    Ok(Json(ranges))
+}
+
+async fn query_archive_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(table): Path<String>,
+    Query(query): Query<ArchiveQuery>,
+) -> Result<Json<Vec<serde_json::Value>>, AppError> {
+    if query.start_time > query.end_time {
+        return Err(AppError::InvalidInput(
+            "startTime must be before endTime".to_string(),
+        ));
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_ARCHIVE_QUERY_LIMIT)
+        .min(MAX_ARCHIVE_QUERY_LIMIT);
+
+    let rows = archive_service::query_archived_rows(
+        app_state.duckdb_pool.clone(),
+        app_state.config.archive_dir.clone(),
+        table,
+        authenticated_user.id,
+        authenticated_user.role == Role::Admin,
+        query.start_time,
+        query.end_time,
+        limit,
+    )
+    .await?;
+    Ok(Json(rows))
+}
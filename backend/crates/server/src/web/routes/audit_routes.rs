@@ -0,0 +1,62 @@
+use axum::{
+    extract::{Query, State},
+    routing::get,
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::audit_log_service::{self, AuditLogFilter};
+use crate::db::entities::audit_log;
+use crate::web::{AppError, AppState};
+
+const DEFAULT_AUDIT_PAGE_SIZE: u32 = 50;
+const MAX_AUDIT_PAGE_SIZE: u32 = 500;
+
+/// Audit log queries, mounted at `/api/admin/audit` behind the admin role.
+pub fn create_router() -> Router<Arc<AppState>> {
+    Router::new().route("/", get(list_audit_log_handler))
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditLogQuery {
+    pub user_id: Option<i32>,
+    /// E.g. `vps`, `alerts` or `admin/users`.
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub method: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub until: Option<DateTime<Utc>>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AuditLogPage {
+    pub items: Vec<audit_log::Model>,
+    /// Entries matching the filters, across all pages.
+    pub total: u64,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+async fn list_audit_log_handler(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<AuditLogQuery>,
+) -> Result<Json<AuditLogPage>, AppError> {
+    let filter = AuditLogFilter {
+        user_id: query.user_id,
+        entity_type: query.entity_type,
+        entity_id: query.entity_id,
+        method: query.method,
+        since: query.since,
+        until: query.until,
+    };
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(DEFAULT_AUDIT_PAGE_SIZE).clamp(1, MAX_AUDIT_PAGE_SIZE);
+    let (items, total) = audit_log_service::get_audit_page(app_state.duckdb_pool.clone(), &filter, page, per_page).await?;
+    Ok(Json(AuditLogPage { items, total, page, per_page }))
+}
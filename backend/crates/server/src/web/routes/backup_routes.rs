@@ -0,0 +1,117 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Extension, Path, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use std::io::Read;
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::{error, info};
+
+use crate::db::duckdb_service::backup_service::{self, BackupArchive};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+/// Size of the chunks an archive is sent in.
+const DOWNLOAD_CHUNK_BYTES: usize = 64 * 1024;
+/// Chunks read ahead of a slow client.
+const DOWNLOAD_BUFFERED_CHUNKS: usize = 4;
+
+/// Admin routes, mounted at `/api/admin/backup`.
+pub fn create_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_backups_handler).post(create_backup_handler))
+        .route("/{file_name}", get(download_backup_handler))
+}
+
+fn data_dir(app_state: &AppState) -> PathBuf {
+    PathBuf::from(&app_state.config.data_dir)
+}
+
+/// Streams an archive from the archive dir.
+fn archive_response(app_state: &AppState, file_name: &str) -> Result<Response, AppError> {
+    let path = backup_service::archive_dir(&data_dir(app_state)).join(file_name);
+    let mut file = std::fs::File::open(&path)
+        .map_err(|e| AppError::NotFound(format!("Backup archive not found: {e}")))?;
+
+    let (tx, rx) = mpsc::channel::<Result<Bytes, std::io::Error>>(DOWNLOAD_BUFFERED_CHUNKS);
+    tokio::task::spawn_blocking(move || {
+        let mut buf = vec![0; DOWNLOAD_CHUNK_BYTES];
+        loop {
+            let chunk = match file.read(&mut buf) {
+                Ok(0) => return,
+                Ok(n) => Ok(Bytes::copy_from_slice(&buf[..n])),
+                Err(e) => Err(e),
+            };
+            let failed = chunk.is_err();
+            // A send error means the client went away.
+            if tx.blocking_send(chunk).is_err() || failed {
+                return;
+            }
+        }
+    });
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/gzip".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{file_name}\"")),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response())
+}
+
+async fn list_backups_handler(State(app_state): State<Arc<AppState>>) -> Json<Vec<BackupArchive>> {
+    let data_dir = data_dir(&app_state);
+    Json(
+        tokio::task::spawn_blocking(move || backup_service::list_backups(&data_dir))
+            .await
+            .unwrap_or_default(),
+    )
+}
+
+/// Writes a new archive, keeps it with the scheduled ones and sends it back
+/// as a download.
+async fn create_backup_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Response, AppError> {
+    if !app_state.storage_mode.is_healthy() {
+        return Err(AppError::ServiceUnavailable(
+            "Storage is degraded; there is no database to back up".to_string(),
+        ));
+    }
+    let pool = app_state.duckdb_pool.clone();
+    let data_dir = data_dir(&app_state);
+    let key_fingerprint = backup_service::key_fingerprint(&app_state.config.notification_encryption_key);
+    let keep = app_state.config.backups_kept;
+    let archive = tokio::task::spawn_blocking(move || {
+        let archive = backup_service::create_backup(&pool, &data_dir, &key_fingerprint)?;
+        backup_service::prune_backups(&data_dir, keep);
+        Ok::<_, String>(archive)
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+    .map_err(|e| {
+        error!(error = %e, "Backup failed.");
+        AppError::InternalServerError(format!("Backup failed: {e}"))
+    })?;
+
+    info!(user_id = authenticated_user.id, file = %archive.file_name, "Created backup archive on request.");
+    archive_response(&app_state, &archive.file_name)
+}
+
+async fn download_backup_handler(
+    State(app_state): State<Arc<AppState>>,
+    Path(file_name): Path<String>,
+) -> Result<Response, AppError> {
+    if backup_service::parse_archive_name(&file_name).is_none() {
+        return Err(AppError::NotFound("Backup archive not found".to_string()));
+    }
+    archive_response(&app_state, &file_name)
+}
@@ -1,33 +1,63 @@
 use axum::{
     Json,
     Router,
-    extract::{Extension, Path, State},
-    routing::{get, post},
+    body::Body,
+    extract::{Extension, Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
 };
+use bytes::Bytes;
+use futures_util::stream;
+use std::io::SeekFrom;
 use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
 use tracing::error;
 use uuid::Uuid;
 
-use crate::db::duckdb_service::batch_command_service;
+use crate::db::duckdb_service::{batch_command_service, vps_selection_service};
+use crate::db::entities::vps_selection;
+use crate::db::enums::BatchCommandStatus;
+use crate::services::batch_output::{self, ByteRange};
 use crate::web::handlers::batch_command_upgrade_handler::batch_command_upgrade_handler;
-use crate::web::models::batch_command_models::BatchCommandTaskDetailResponse;
+use crate::web::models::batch_command_models::{
+    BatchCommandTaskDetailResponse, BatchOutputComparisonResponse, BatchOutputSearchHit, BatchTargets,
+    ChildOutputQuery, CompareOutputsQuery, ResolvedTarget, SearchOutputsQuery, VpsSelectionRequest,
+};
 use crate::web::models::AuthenticatedUser;
 use crate::web::{AppState, error::AppError};
 
 pub fn batch_command_routes() -> Router<Arc<AppState>> {
     Router::<Arc<AppState>>::new()
         .route("/", get(batch_command_upgrade_handler)) // Changed to GET for WebSocket upgrade
+        .route("/search", get(search_outputs))
+        .route("/targets/preview", post(preview_targets))
+        .route("/selections", get(list_selections).post(create_selection))
+        .route(
+            "/selections/{selection_id}",
+            put(update_selection).delete(delete_selection),
+        )
         .route("/{batch_command_id}", get(get_batch_command_detail))
+        .route("/{batch_command_id}/compare", get(compare_batch_outputs))
         .route(
             "/{batch_command_id}/terminate",
             post(terminate_batch_command),
         )
+        .route("/{batch_command_id}/abort", post(abort_batch_command))
         .route(
             "/{batch_id}/tasks/{child_id}/terminate",
             post(terminate_child_command),
         ) // More granular control
+        .route(
+            "/{batch_command_id}/children/{child_id}/output",
+            get(get_child_output),
+        )
 }
 
+const OUTPUT_CHUNK_SIZE: usize = 64 * 1024;
+const DEFAULT_SEARCH_LIMIT: u32 = 50;
+const MAX_SEARCH_LIMIT: u32 = 200;
+
 #[axum::debug_handler]
 async fn get_batch_command_detail(
     Extension(authenticated_user): Extension<AuthenticatedUser>,
@@ -49,6 +79,24 @@ async fn get_batch_command_detail(
     }
 }
 
+#[axum::debug_handler]
+async fn compare_batch_outputs(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(batch_command_id): Path<Uuid>,
+    Query(query): Query<CompareOutputsQuery>,
+) -> Result<Json<BatchOutputComparisonResponse>, AppError> {
+    let comparison = batch_command_service::compare_child_outputs(
+        app_state.duckdb_pool.clone(),
+        batch_command_id,
+        authenticated_user.id,
+        query.stream.as_deref().unwrap_or("stdout"),
+        query.similarity_threshold.unwrap_or(1.0),
+    )
+    .await?;
+    Ok(Json(comparison))
+}
+
 #[axum::debug_handler]
 async fn terminate_batch_command(
     Extension(authenticated_user): Extension<AuthenticatedUser>,
@@ -87,6 +135,41 @@ async fn terminate_batch_command(
     })))
 }
 
+/// Stops a batch for good: running commands are killed, and tasks waiting
+/// for a retry or a later wave never run. Its tasks end up `Aborted`.
+#[axum::debug_handler]
+async fn abort_batch_command(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(batch_command_id): Path<Uuid>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let dispatcher = app_state.command_dispatcher.clone();
+    let child_tasks_to_abort = batch_command_service::abort_batch_command(
+        app_state.duckdb_pool.clone(),
+        batch_command_id,
+        authenticated_user.id,
+    )
+    .await?;
+    app_state
+        .result_broadcaster
+        .broadcast_batch_task_update(batch_command_id, BatchCommandStatus::Aborting.to_string(), None)
+        .await;
+
+    let signalled = child_tasks_to_abort.len();
+    tokio::spawn(async move {
+        for (child_command_id, vps_id) in child_tasks_to_abort {
+            if let Err(e) = dispatcher.terminate_command_on_agent(child_command_id, vps_id).await {
+                error!(child_task_id = %child_command_id, error = ?e, "Failed to dispatch abort to agent.");
+            }
+        }
+    });
+
+    Ok(Json(serde_json::json!({
+        "message": format!("Batch command task {batch_command_id} is being aborted."),
+        "aborted_tasks": signalled,
+    })))
+}
+
 #[axum::debug_handler]
 async fn terminate_child_command(
     Extension(authenticated_user): Extension<AuthenticatedUser>,
@@ -122,3 +205,167 @@ async fn terminate_child_command(
         "message": format!("Child command task {} marked for termination. Termination signal sent to agent.", child_id)
     })))
 }
+
+/// Serves the stored output of one child command. Supports single `Range`
+/// requests so large logs can be paged; `?download=true` makes it a file
+/// download, which doubles as a permalink to the output.
+#[axum::debug_handler]
+async fn get_child_output(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path((batch_command_id, child_id)): Path<(Uuid, Uuid)>,
+    Query(query): Query<ChildOutputQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let stream_name = query.stream.unwrap_or_else(|| "stdout".to_string());
+    let path = batch_command_service::get_child_output_path(
+        app_state.duckdb_pool.clone(),
+        batch_command_id,
+        child_id,
+        authenticated_user.id,
+        &stream_name,
+    )
+    .await?
+    .ok_or_else(|| AppError::NotFound(format!("No {stream_name} output stored for this command")))?;
+
+    let mut file = tokio::fs::File::open(&path)
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+    let len = file
+        .metadata()
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .len();
+    let range_header = headers.get(header::RANGE).and_then(|value| value.to_str().ok());
+    let range = match batch_output::parse_range(range_header, len) {
+        Ok(range) => range,
+        Err(_) => {
+            return Ok((
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                [(header::CONTENT_RANGE, format!("bytes */{len}"))],
+            )
+                .into_response());
+        }
+    };
+    let ByteRange { start, end } = range.unwrap_or(ByteRange { start: 0, end: len.saturating_sub(1) });
+    let body_len = if len == 0 { 0 } else { end - start + 1 };
+    file.seek(SeekFrom::Start(start))
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?;
+
+    // State: the open file and the bytes still to send.
+    let chunks = stream::unfold((file, body_len), |(mut file, remaining)| async move {
+        if remaining == 0 {
+            return None;
+        }
+        let mut buf = vec![0; OUTPUT_CHUNK_SIZE.min(remaining as usize)];
+        match file.read(&mut buf).await {
+            Ok(0) => None,
+            Ok(read) => {
+                buf.truncate(read);
+                Some((Ok::<_, std::io::Error>(Bytes::from(buf)), (file, remaining - read as u64)))
+            }
+            Err(e) => Some((Err(e), (file, 0))),
+        }
+    });
+
+    let disposition = if query.download {
+        format!("attachment; filename=\"{batch_command_id}-{child_id}-{stream_name}.log\"")
+    } else {
+        "inline".to_string()
+    };
+    let mut response = (
+        [
+            (header::CONTENT_TYPE, "text/plain; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+            (header::ACCEPT_RANGES, "bytes".to_string()),
+            (header::CONTENT_LENGTH, body_len.to_string()),
+        ],
+        Body::from_stream(chunks),
+    )
+        .into_response();
+    if range.is_some() {
+        *response.status_mut() = StatusCode::PARTIAL_CONTENT;
+        response.headers_mut().insert(
+            header::CONTENT_RANGE,
+            format!("bytes {start}-{end}/{len}")
+                .parse()
+                .map_err(|_| AppError::InternalServerError("Invalid Content-Range".to_string()))?,
+        );
+    }
+    Ok(response)
+}
+
+/// Searches the output of the user's finished batch commands.
+#[axum::debug_handler]
+async fn search_outputs(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<SearchOutputsQuery>,
+) -> Result<Json<Vec<BatchOutputSearchHit>>, AppError> {
+    let hits = batch_command_service::search_child_outputs(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        query.q,
+        query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT).clamp(1, MAX_SEARCH_LIMIT),
+    )
+    .await?;
+    Ok(Json(hits))
+}
+
+/// The VPS a batch command with these targets would run on right now.
+async fn preview_targets(
+    State(app_state): State<Arc<AppState>>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    Json(targets): Json<BatchTargets>,
+) -> Result<Json<Vec<ResolvedTarget>>, AppError> {
+    let targets = app_state
+        .command_dispatcher
+        .resolve_targets(authenticated_user.id, &targets)
+        .await?;
+    Ok(Json(targets))
+}
+
+async fn list_selections(
+    State(app_state): State<Arc<AppState>>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+) -> Result<Json<Vec<vps_selection::Model>>, AppError> {
+    let selections =
+        vps_selection_service::get_selections_for_user(app_state.duckdb_pool.clone(), authenticated_user.id).await?;
+    Ok(Json(selections))
+}
+
+async fn create_selection(
+    State(app_state): State<Arc<AppState>>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    Json(payload): Json<VpsSelectionRequest>,
+) -> Result<Json<vps_selection::Model>, AppError> {
+    let selection =
+        vps_selection_service::create_selection(app_state.duckdb_pool.clone(), authenticated_user.id, payload).await?;
+    Ok(Json(selection))
+}
+
+async fn update_selection(
+    State(app_state): State<Arc<AppState>>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    Path(selection_id): Path<i32>,
+    Json(payload): Json<VpsSelectionRequest>,
+) -> Result<Json<vps_selection::Model>, AppError> {
+    let selection = vps_selection_service::update_selection(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        selection_id,
+        payload,
+    )
+    .await?;
+    Ok(Json(selection))
+}
+
+async fn delete_selection(
+    State(app_state): State<Arc<AppState>>,
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    Path(selection_id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    vps_selection_service::delete_selection(app_state.duckdb_pool.clone(), authenticated_user.id, selection_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,247 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{header, HeaderMap, StatusCode},
+    response::IntoResponse,
+    routing::{get, put},
+    Json, Router,
+};
+use chrono::Utc;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::db::duckdb_service::{settings_service, DuckDbPool};
+use crate::web::models::branding_models::{BrandingResponse, BrandingSettings, UpdateBrandingRequest};
+use crate::web::{AppError, AppState};
+
+const BRANDING_SETTING_KEY: &str = "branding";
+/// Assets are served from the app's origin, so anything active in them (an
+/// SVG's scripts) must not run when one is opened directly.
+const ASSET_CONTENT_SECURITY_POLICY: &str = "default-src 'none'; style-src 'unsafe-inline'; sandbox";
+const MAX_BRANDING_IMAGE_BYTES: usize = 1024 * 1024;
+const ALLOWED_IMAGE_TYPES: &[&str] = &[
+    "image/png",
+    "image/jpeg",
+    "image/gif",
+    "image/webp",
+    "image/svg+xml",
+    "image/x-icon",
+    "image/vnd.microsoft.icon",
+];
+
+/// Unauthenticated routes, mounted at `/api/branding`.
+pub fn create_public_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(get_branding))
+        .route("/{asset}", get(get_branding_asset))
+}
+
+/// Admin routes, mounted at `/api/settings/branding`.
+pub fn create_settings_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(get_branding_settings).put(update_branding))
+        .route("/{asset}", put(upload_branding_asset).delete(delete_branding_asset))
+}
+
+pub async fn load_branding(pool: DuckDbPool) -> Result<BrandingSettings, AppError> {
+    let settings = settings_service::get_setting(pool, BRANDING_SETTING_KEY)
+        .await?
+        .map(|s| serde_json::from_value(s.value))
+        .transpose()?
+        .unwrap_or_default();
+    Ok(settings)
+}
+
+async fn save_branding(pool: DuckDbPool, settings: &BrandingSettings) -> Result<(), AppError> {
+    let value = serde_json::to_value(settings)?;
+    settings_service::update_setting(pool, BRANDING_SETTING_KEY, &value).await?;
+    Ok(())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BrandingAsset {
+    Logo,
+    Favicon,
+}
+
+impl BrandingAsset {
+    fn parse(name: &str) -> Result<Self, AppError> {
+        match name {
+            "logo" => Ok(Self::Logo),
+            "favicon" => Ok(Self::Favicon),
+            _ => Err(AppError::NotFound(format!("Unknown branding asset '{name}'"))),
+        }
+    }
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Self::Logo => "logo",
+            Self::Favicon => "favicon",
+        }
+    }
+
+    fn content_type(self, settings: &BrandingSettings) -> Option<&str> {
+        match self {
+            Self::Logo => settings.logo_content_type.as_deref(),
+            Self::Favicon => settings.favicon_content_type.as_deref(),
+        }
+    }
+
+    fn set(self, settings: &mut BrandingSettings, content_type: Option<String>) {
+        let updated_at = content_type.as_ref().map(|_| Utc::now().timestamp());
+        match self {
+            Self::Logo => {
+                settings.logo_content_type = content_type;
+                settings.logo_updated_at = updated_at;
+            }
+            Self::Favicon => {
+                settings.favicon_content_type = content_type;
+                settings.favicon_updated_at = updated_at;
+            }
+        }
+    }
+}
+
+fn asset_path(app_state: &AppState, asset: BrandingAsset) -> PathBuf {
+    PathBuf::from(&app_state.config.data_dir)
+        .join("branding")
+        .join(asset.file_name())
+}
+
+async fn get_branding(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<BrandingResponse>, AppError> {
+    let settings = load_branding(app_state.duckdb_pool.clone()).await?;
+    Ok(Json(settings.into()))
+}
+
+async fn get_branding_asset(
+    State(app_state): State<Arc<AppState>>,
+    Path(asset): Path<String>,
+) -> Result<impl IntoResponse, AppError> {
+    let asset = BrandingAsset::parse(&asset)?;
+    let settings = load_branding(app_state.duckdb_pool.clone()).await?;
+    let content_type = asset
+        .content_type(&settings)
+        .ok_or_else(|| AppError::NotFound("No custom image uploaded".to_string()))?
+        .to_string();
+
+    let path = asset_path(&app_state, asset);
+    let contents = tokio::task::spawn_blocking(move || std::fs::read(path))
+        .await
+        .map_err(|e| AppError::InternalServerError(e.to_string()))?
+        .map_err(|e| AppError::NotFound(format!("Branding image missing on disk: {e}")))?;
+
+    // `<img>` still renders an attachment; only opening the URL downloads it.
+    let disposition = if content_type == "image/svg+xml" { "attachment" } else { "inline" };
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            // The URL carries a version query parameter, so it can be cached aggressively.
+            (header::CACHE_CONTROL, "public, max-age=86400".to_string()),
+            (header::CONTENT_SECURITY_POLICY, ASSET_CONTENT_SECURITY_POLICY.to_string()),
+            (header::X_CONTENT_TYPE_OPTIONS, "nosniff".to_string()),
+            (header::CONTENT_DISPOSITION, disposition.to_string()),
+        ],
+        contents,
+    ))
+}
+
+async fn get_branding_settings(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<BrandingSettings>, AppError> {
+    Ok(Json(load_branding(app_state.duckdb_pool.clone()).await?))
+}
+
+async fn update_branding(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<UpdateBrandingRequest>,
+) -> Result<Json<BrandingResponse>, AppError> {
+    let mut settings = load_branding(app_state.duckdb_pool.clone()).await?;
+
+    if let Some(title) = payload.title {
+        let title = title.trim();
+        if title.is_empty() {
+            return Err(AppError::InvalidInput("Title cannot be empty".to_string()));
+        }
+        settings.title = title.to_string();
+    }
+    if let Some(links) = payload.footer_links {
+        if let Some(bad) = links
+            .iter()
+            .find(|l| !(l.url.starts_with("http://") || l.url.starts_with("https://") || l.url.starts_with('/')))
+        {
+            return Err(AppError::InvalidInput(format!("Invalid footer link URL: {}", bad.url)));
+        }
+        settings.footer_links = links;
+    }
+    if let Some(language) = payload.default_language {
+        settings.default_language = language;
+    }
+    if let Some(colors) = payload.accent_colors {
+        for color in [&colors.primary, &colors.secondary].into_iter().flatten() {
+            let is_hex = color.len() == 7
+                && color.starts_with('#')
+                && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+            if !is_hex {
+                return Err(AppError::InvalidInput(format!("Invalid color '{color}', expected #rrggbb")));
+            }
+        }
+        settings.accent_colors = colors;
+    }
+
+    save_branding(app_state.duckdb_pool.clone(), &settings).await?;
+    Ok(Json(settings.into()))
+}
+
+async fn upload_branding_asset(
+    State(app_state): State<Arc<AppState>>,
+    Path(asset): Path<String>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<BrandingResponse>, AppError> {
+    let asset = BrandingAsset::parse(&asset)?;
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.split(';').next().unwrap_or_default().trim().to_lowercase())
+        .unwrap_or_default();
+
+    if !ALLOWED_IMAGE_TYPES.contains(&content_type.as_str()) {
+        return Err(AppError::InvalidInput(format!("Unsupported image type '{content_type}'")));
+    }
+    if body.is_empty() || body.len() > MAX_BRANDING_IMAGE_BYTES {
+        return Err(AppError::InvalidInput(format!(
+            "Image must be between 1 byte and {MAX_BRANDING_IMAGE_BYTES} bytes"
+        )));
+    }
+
+    let path = asset_path(&app_state, asset);
+    tokio::task::spawn_blocking(move || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, &body)
+    })
+    .await
+    .map_err(|e| AppError::InternalServerError(e.to_string()))?
+    .map_err(|e| AppError::InternalServerError(format!("Failed to store branding image: {e}")))?;
+
+    let mut settings = load_branding(app_state.duckdb_pool.clone()).await?;
+    asset.set(&mut settings, Some(content_type));
+    save_branding(app_state.duckdb_pool.clone(), &settings).await?;
+    Ok(Json(settings.into()))
+}
+
+async fn delete_branding_asset(
+    State(app_state): State<Arc<AppState>>,
+    Path(asset): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let asset = BrandingAsset::parse(&asset)?;
+    let mut settings = load_branding(app_state.duckdb_pool.clone()).await?;
+    asset.set(&mut settings, None);
+    save_branding(app_state.duckdb_pool.clone(), &settings).await?;
+
+    let _ = std::fs::remove_file(asset_path(&app_state, asset));
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,213 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::HeaderMap,
+    routing::{get, post, put},
+    Json, Router,
+};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::chatops_service::{self, PLATFORM_SLACK, PLATFORM_TELEGRAM};
+use crate::db::entities::chatops_bridge;
+use crate::services::chatops;
+use crate::web::models::chatops_models::{
+    CreateChatOpsBridgeRequest, CreatedChatOpsBridge, UpdateChatOpsBridgeRequest,
+};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+const TELEGRAM_SECRET_HEADER: &str = "x-telegram-bot-api-secret-token";
+const SLACK_SIGNATURE_HEADER: &str = "x-slack-signature";
+const SLACK_TIMESTAMP_HEADER: &str = "x-slack-request-timestamp";
+
+/// Inbound webhooks, authenticated by the bridge's secret instead of a session.
+pub fn create_public_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/telegram/{bridge_id}", post(telegram_webhook))
+        .route("/slack/{bridge_id}", post(slack_webhook))
+}
+
+pub fn create_bridge_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/bridges", get(list_bridges).post(create_bridge))
+        .route("/bridges/{id}", put(update_bridge).delete(delete_bridge))
+}
+
+#[derive(Deserialize)]
+struct TelegramUpdate {
+    message: Option<TelegramMessage>,
+}
+
+#[derive(Deserialize)]
+struct TelegramMessage {
+    chat: TelegramChat,
+    text: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TelegramChat {
+    id: i64,
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+async fn load_bridge(
+    app_state: &AppState,
+    bridge_id: i32,
+    platform: &str,
+) -> Result<(chatops_bridge::Model, String), AppError> {
+    chatops_service::get_bridge_with_secret(
+        app_state.duckdb_pool.clone(),
+        app_state.encryption_service.clone(),
+        bridge_id,
+        platform,
+    )
+    .await?
+    .ok_or_else(|| AppError::NotFound("Chat bridge not found".to_string()))
+}
+
+/// Replies through the webhook response (a Bot API method call) so the server
+/// doesn't need the bot token.
+async fn telegram_webhook(
+    State(app_state): State<Arc<AppState>>,
+    Path(bridge_id): Path<i32>,
+    headers: HeaderMap,
+    Json(update): Json<TelegramUpdate>,
+) -> Result<Json<Value>, AppError> {
+    let (bridge, secret) = load_bridge(&app_state, bridge_id, PLATFORM_TELEGRAM).await?;
+    if header_str(&headers, TELEGRAM_SECRET_HEADER) != Some(secret.as_str()) {
+        return Err(AppError::Unauthorized("Invalid secret token".to_string()));
+    }
+    // Edits, joins and other updates without text are acknowledged and ignored.
+    let Some((chat_id, text)) = update
+        .message
+        .and_then(|m| m.text.map(|text| (m.chat.id, text)))
+    else {
+        return Ok(Json(json!({})));
+    };
+    if !text.starts_with('/') {
+        return Ok(Json(json!({})));
+    }
+    let reply = chatops::handle_message(&app_state, &bridge, &chat_id.to_string(), &text).await;
+    Ok(Json(json!({
+        "method": "sendMessage",
+        "chat_id": chat_id,
+        "text": reply,
+    })))
+}
+
+fn parse_form(body: &str) -> Vec<(String, String)> {
+    body.split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .map(|(key, value)| {
+            let decode = |s: &str| {
+                urlencoding::decode(&s.replace('+', " "))
+                    .map(|s| s.into_owned())
+                    .unwrap_or_default()
+            };
+            (decode(key), decode(value))
+        })
+        .collect()
+}
+
+/// Slash command endpoint. Configure the slash command (e.g. `/nodenexus`) with
+/// this URL; its text is parsed like a Telegram command.
+async fn slack_webhook(
+    State(app_state): State<Arc<AppState>>,
+    Path(bridge_id): Path<i32>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<Json<Value>, AppError> {
+    let (bridge, secret) = load_bridge(&app_state, bridge_id, PLATFORM_SLACK).await?;
+    let verified = match (
+        header_str(&headers, SLACK_TIMESTAMP_HEADER),
+        header_str(&headers, SLACK_SIGNATURE_HEADER),
+    ) {
+        (Some(timestamp), Some(signature)) => chatops::verify_slack_signature(
+            &secret,
+            timestamp,
+            &body,
+            signature,
+            chrono::Utc::now().timestamp(),
+        ),
+        _ => false,
+    };
+    if !verified {
+        return Err(AppError::Unauthorized("Invalid request signature".to_string()));
+    }
+
+    let form = parse_form(&body);
+    let field = |name: &str| {
+        form.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+            .unwrap_or_default()
+    };
+    let reply = chatops::handle_message(&app_state, &bridge, field("channel_id"), field("text")).await;
+    Ok(Json(json!({
+        "response_type": "ephemeral",
+        "text": reply,
+    })))
+}
+
+async fn list_bridges(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<chatops_bridge::Model>>, AppError> {
+    let bridges =
+        chatops_service::get_bridges_for_user(app_state.duckdb_pool.clone(), authenticated_user.id)
+            .await?;
+    Ok(Json(bridges))
+}
+
+async fn create_bridge(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateChatOpsBridgeRequest>,
+) -> Result<Json<CreatedChatOpsBridge>, AppError> {
+    let (bridge, secret) = chatops_service::create_bridge(
+        app_state.duckdb_pool.clone(),
+        app_state.encryption_service.clone(),
+        authenticated_user.id,
+        payload,
+    )
+    .await?;
+    let webhook_url = format!(
+        "{}/api/chatops/{}/{}",
+        app_state.config.frontend_url.trim_end_matches('/'),
+        bridge.platform,
+        bridge.id
+    );
+    Ok(Json(CreatedChatOpsBridge {
+        bridge,
+        secret,
+        webhook_url,
+    }))
+}
+
+async fn update_bridge(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<UpdateChatOpsBridgeRequest>,
+) -> Result<Json<chatops_bridge::Model>, AppError> {
+    let bridge = chatops_service::update_bridge(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        id,
+        payload,
+    )
+    .await?;
+    Ok(Json(bridge))
+}
+
+async fn delete_bridge(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<(), AppError> {
+    chatops_service::delete_bridge(app_state.duckdb_pool.clone(), authenticated_user.id, id).await
+}
@@ -0,0 +1,117 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    routing::{get, put},
+    Json, Router,
+};
+use chrono::{DateTime, Utc};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::client_service;
+use crate::db::entities::client;
+use crate::server::update_service;
+use crate::web::models::client_models::{ClientReport, ClientReportQuery, ClientRequest, SetClientVpsRequest};
+use crate::web::models::report_models::ReportPeriod;
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+/// Reseller clients, mounted at `/api/clients`.
+pub fn create_client_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_clients).post(create_client))
+        .route("/{id}", put(update_client).delete(delete_client))
+        .route("/{id}/vps", put(set_client_vps))
+        .route("/{id}/report", get(get_client_report))
+}
+
+/// The report period from a query, defaulting to the last completed month.
+pub fn report_range(query: &ClientReportQuery) -> Result<(DateTime<Utc>, DateTime<Utc>), AppError> {
+    let (default_start, default_end) = ReportPeriod::Monthly.last_completed(Utc::now());
+    let start = query.start.unwrap_or(default_start);
+    let end = query.end.unwrap_or(default_end);
+    if start >= end {
+        return Err(AppError::InvalidInput("start must be before end".to_string()));
+    }
+    Ok((start, end))
+}
+
+async fn list_clients(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<client::Model>>, AppError> {
+    let clients = client_service::get_clients(app_state.duckdb_pool.clone(), authenticated_user.id).await?;
+    Ok(Json(clients))
+}
+
+async fn create_client(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<ClientRequest>,
+) -> Result<(StatusCode, Json<client::Model>), AppError> {
+    let client = client_service::create_client(app_state.duckdb_pool.clone(), authenticated_user.id, &payload).await?;
+    Ok((StatusCode::CREATED, Json(client)))
+}
+
+async fn update_client(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<ClientRequest>,
+) -> Result<Json<client::Model>, AppError> {
+    let client =
+        client_service::update_client(app_state.duckdb_pool.clone(), authenticated_user.id, id, &payload).await?;
+    Ok(Json(client))
+}
+
+async fn delete_client(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    client_service::delete_client(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    update_service::broadcast_full_state_update(
+        app_state.duckdb_pool.clone(),
+        &app_state.live_server_data_cache,
+        &app_state.ws_data_broadcaster_tx,
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn set_client_vps(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<SetClientVpsRequest>,
+) -> Result<StatusCode, AppError> {
+    client_service::set_client_vps(app_state.duckdb_pool.clone(), authenticated_user.id, id, &payload.vps_ids)
+        .await?;
+    // Client share links filter the live cache by client.
+    update_service::broadcast_full_state_update(
+        app_state.duckdb_pool.clone(),
+        &app_state.live_server_data_cache,
+        &app_state.ws_data_broadcaster_tx,
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Provider cost, billed amount (cost plus markup) and uptime per VPS.
+async fn get_client_report(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Query(query): Query<ClientReportQuery>,
+) -> Result<Json<ClientReport>, AppError> {
+    let (start, end) = report_range(&query)?;
+    let client = client_service::get_client(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    let report = client_service::build_client_report(
+        app_state.duckdb_pool.clone(),
+        app_state.reporting_pool.clone(),
+        client,
+        start,
+        end,
+    )
+    .await?;
+    Ok(Json(report))
+}
@@ -0,0 +1,99 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    routing::{post, put},
+    Json, Router,
+};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::command_policy_service;
+use crate::db::entities::command_policy;
+use crate::web::models::command_policy_models::{
+    CommandPolicyDryRunRequest, CommandPolicyDryRunResponse, CreateCommandPolicyRequest, UpdateCommandPolicyRequest,
+};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+pub fn create_command_policy_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", post(create_policy_handler).get(list_policies_handler))
+        .route("/dry-run", post(dry_run_handler))
+        .route("/{id}", put(update_policy_handler).delete(delete_policy_handler))
+}
+
+async fn list_policies_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<command_policy::Model>>, AppError> {
+    let policies =
+        command_policy_service::get_policies(app_state.duckdb_pool.clone(), authenticated_user.id).await?;
+    Ok(Json(policies))
+}
+
+async fn create_policy_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateCommandPolicyRequest>,
+) -> Result<(StatusCode, Json<command_policy::Model>), AppError> {
+    let policy =
+        command_policy_service::create_policy(app_state.duckdb_pool.clone(), authenticated_user.id, payload).await?;
+    Ok((StatusCode::CREATED, Json(policy)))
+}
+
+async fn update_policy_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<UpdateCommandPolicyRequest>,
+) -> Result<Json<command_policy::Model>, AppError> {
+    let policy =
+        command_policy_service::update_policy(app_state.duckdb_pool.clone(), authenticated_user.id, id, payload)
+            .await?;
+    Ok(Json(policy))
+}
+
+async fn delete_policy_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    command_policy_service::delete_policy(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Which of the targets a command would run on, as the policies stand now.
+/// Nothing is created or dispatched.
+async fn dry_run_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CommandPolicyDryRunRequest>,
+) -> Result<Json<CommandPolicyDryRunResponse>, AppError> {
+    let mut vps_ids = payload.vps_ids;
+    vps_ids.sort_unstable();
+    vps_ids.dedup();
+    if vps_ids.is_empty() {
+        return Err(AppError::InvalidInput("At least one VPS is required".to_string()));
+    }
+    let visible = app_state.storage.get_vps_by_ids(vps_ids.clone())
+        .await?
+        .iter()
+        .filter(|vps| authenticated_user.can_access(vps.organization_id))
+        .count();
+    if visible != vps_ids.len() {
+        return Err(AppError::NotFound("VPS not found".to_string()));
+    }
+
+    let targets = command_policy_service::dry_run(
+        app_state.duckdb_pool.clone(),
+        payload.user_id.unwrap_or(authenticated_user.id),
+        payload.script_id,
+        &vps_ids,
+    )
+    .await?;
+    let (allowed, denied): (Vec<_>, Vec<_>) = targets.iter().partition(|target| target.allowed);
+    Ok(Json(CommandPolicyDryRunResponse {
+        allowed_vps_ids: allowed.iter().map(|target| target.vps_id).collect(),
+        denied_vps_ids: denied.iter().map(|target| target.vps_id).collect(),
+        targets,
+    }))
+}
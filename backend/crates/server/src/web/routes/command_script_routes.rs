@@ -3,11 +3,16 @@ use axum::{
     extract::{Extension, Path, State},
     routing::{get, post},
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::sync::Arc;
+use uuid::Uuid;
 
 use crate::db::duckdb_service::command_script_service;
 use crate::db::entities::command_script::ScriptLanguage;
+use crate::db::entities::vps;
+use crate::services::script_runner;
+use crate::services::script_template::{self, ScriptParameter, TargetVps};
 use crate::web::models::AuthenticatedUser;
 use crate::web::{AppError, AppState};
 
@@ -18,6 +23,34 @@ pub struct ScriptPayload {
     pub language: ScriptLanguage,
     pub script_content: String,
     pub working_directory: String,
+    #[serde(default)]
+    pub parameters: Vec<ScriptParameter>,
+}
+
+#[derive(Deserialize)]
+pub struct PreviewScriptRequest {
+    pub vps_id: i32,
+    #[serde(default)]
+    pub values: BTreeMap<String, String>,
+}
+
+#[derive(Serialize)]
+pub struct PreviewScriptResponse {
+    /// The command as it would be sent to the VPS, with secrets masked.
+    pub command: String,
+}
+
+#[derive(Deserialize)]
+pub struct RunScriptRequest {
+    pub vps_ids: Vec<i32>,
+    #[serde(default)]
+    pub values: BTreeMap<String, String>,
+    pub execution_alias: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct RunScriptResponse {
+    pub batch_command_id: Uuid,
 }
 
 pub fn command_script_routes() -> Router<Arc<AppState>> {
@@ -27,6 +60,26 @@ pub fn command_script_routes() -> Router<Arc<AppState>> {
             "/{id}",
             get(get_script).put(update_script).delete(delete_script),
         )
+        .route("/{id}/preview", post(preview_script))
+        .route("/{id}/run", post(run_script))
+}
+
+/// The VPS in `vps_ids`, all of which must be visible to the user.
+async fn accessible_vps(
+    app_state: &AppState,
+    user: &AuthenticatedUser,
+    vps_ids: Vec<i32>,
+) -> Result<Vec<vps::Model>, AppError> {
+    let requested = vps_ids.len();
+    let servers: Vec<vps::Model> = app_state.storage.get_vps_by_ids(vps_ids)
+        .await?
+        .into_iter()
+        .filter(|vps| user.can_access(vps.organization_id))
+        .collect();
+    if servers.len() != requested {
+        return Err(AppError::NotFound("VPS not found".to_string()));
+    }
+    Ok(servers)
 }
 
 async fn create_script(
@@ -42,6 +95,7 @@ async fn create_script(
         payload.language,
         payload.script_content,
         payload.working_directory,
+        payload.parameters,
     )
     .await?;
     Ok(Json(script))
@@ -79,11 +133,60 @@ async fn update_script(
         payload.language,
         payload.script_content,
         payload.working_directory,
+        payload.parameters,
     )
     .await?;
     Ok(Json(script))
 }
 
+async fn preview_script(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<i32>,
+    Json(payload): Json<PreviewScriptRequest>,
+) -> Result<Json<PreviewScriptResponse>, AppError> {
+    let script = command_script_service::get_script_by_id(app_state.duckdb_pool.clone(), id, user.id).await?;
+    let servers = accessible_vps(&app_state, &user, vec![payload.vps_id]).await?;
+    let values =
+        script_template::resolve_values(&script.parameters, &payload.values).map_err(AppError::InvalidInput)?;
+    let command = script_template::render(
+        &script.script_content,
+        &script.language,
+        &script.parameters,
+        &values,
+        &TargetVps::from(&servers[0]),
+        true,
+    );
+    Ok(Json(PreviewScriptResponse { command }))
+}
+
+async fn run_script(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Path(id): Path<i32>,
+    Json(mut payload): Json<RunScriptRequest>,
+) -> Result<Json<RunScriptResponse>, AppError> {
+    payload.vps_ids.sort_unstable();
+    payload.vps_ids.dedup();
+    if payload.vps_ids.is_empty() {
+        return Err(AppError::InvalidInput("At least one VPS is required".to_string()));
+    }
+    let script = command_script_service::get_script_by_id(app_state.duckdb_pool.clone(), id, user.id).await?;
+    accessible_vps(&app_state, &user, payload.vps_ids.clone()).await?;
+    let execution_alias = payload.execution_alias.unwrap_or_else(|| script.name.clone());
+    let batch_command_id = script_runner::run_script(
+        app_state.duckdb_pool.clone(),
+        &app_state.command_dispatcher,
+        user.id,
+        &script,
+        payload.vps_ids,
+        &payload.values,
+        execution_alias,
+    )
+    .await?;
+    Ok(Json(RunScriptResponse { batch_command_id }))
+}
+
 async fn delete_script(
     State(app_state): State<Arc<AppState>>,
     Extension(user): Extension<AuthenticatedUser>,
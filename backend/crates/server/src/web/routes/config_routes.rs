@@ -1,24 +1,185 @@
-use crate::db::duckdb_service::{self, settings_service, vps_service};
+use crate::db::duckdb_service::{
+    self,
+    alert_correlation_service::{self, CorrelationSettings},
+    config_rollout_service::{self, ConfigRolloutDetails},
+    health_service::{self, HealthScoreSettings},
+    settings_service,
+    uptime_service::{self, UptimeSettings},
+    vps_service,
+};
+use crate::db::entities::config_rollout;
+use crate::services::config_rollout::run_rollout;
+use crate::web::models::AuthenticatedUser;
 use crate::web::{models::config_models::WebAgentConfig, AppError, AppState};
 use axum::{
-    extract::{Path, State},
+    extract::{Extension, Path, State},
     http::StatusCode,
+    response::{IntoResponse, Response},
     routing::{get, post, put},
     Json, Router,
 };
+use serde::{Deserialize, Serialize};
 use futures_util::SinkExt;
 use nodenexus_common::agent_service::{
     message_to_agent::Payload as AgentPayload, AgentConfig, MessageToAgent, UpdateConfigRequest,
 };
 use std::sync::Arc;
+use std::time::Duration;
 use tracing::{error, warn};
 use uuid::Uuid;
 
+/// Instance-wide settings that apply to every organization, mounted at
+/// `/api/settings` behind an admin check for writes.
 pub fn create_settings_router() -> Router<Arc<AppState>> {
-    Router::new().route(
-        "/agent-config",
-        get(get_global_agent_config).put(update_global_agent_config),
+    Router::new()
+        .route(
+            "/agent-config",
+            get(get_global_agent_config).put(update_global_agent_config),
+        )
+        .route("/uptime", get(get_uptime_settings).put(update_uptime_settings))
+        .route("/health-score", get(get_health_settings).put(update_health_settings))
+        .route(
+            "/alert-correlation",
+            get(get_correlation_settings).put(update_correlation_settings),
+        )
+}
+
+/// Staged changes of the global agent config, which reach every VPS that
+/// follows it, so these are admin-only. Mounted at `/api/settings`.
+pub fn create_rollout_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/agent-config/rollouts",
+            get(list_config_rollouts).post(create_config_rollout),
+        )
+        .route("/agent-config/rollouts/{id}", get(get_config_rollout))
+}
+
+const DEFAULT_HEALTH_WINDOW_SECONDS: u32 = 120;
+const MIN_HEALTH_WINDOW_SECONDS: u32 = 30;
+const MAX_HEALTH_WINDOW_SECONDS: u32 = 3600;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateConfigRolloutRequest {
+    /// The new global agent config.
+    pub config: WebAgentConfig,
+    /// VPS that get the config first. Defaults to the `canaryCount` lowest IDs.
+    #[serde(default)]
+    pub canary_vps_ids: Vec<i32>,
+    #[serde(default = "default_canary_count")]
+    pub canary_count: usize,
+    /// VPS per stage after the canaries; all at once when 0.
+    #[serde(default)]
+    pub batch_size: usize,
+    /// How long each stage must stay healthy before the next one starts.
+    pub health_window_seconds: Option<u32>,
+    /// Only return the stages, without pushing anything.
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+fn default_canary_count() -> usize {
+    1
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RolloutPlanEntry {
+    pub vps_id: i32,
+    pub name: String,
+    /// Disconnected agents are skipped and get the config on reconnect.
+    pub connected: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConfigRolloutPlan {
+    pub stages: Vec<Vec<RolloutPlanEntry>>,
+    pub health_window_seconds: u32,
+}
+
+async fn create_config_rollout(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateConfigRolloutRequest>,
+) -> Result<Response, AppError> {
+    let health_window_seconds = payload.health_window_seconds.unwrap_or(DEFAULT_HEALTH_WINDOW_SECONDS);
+    if !(MIN_HEALTH_WINDOW_SECONDS..=MAX_HEALTH_WINDOW_SECONDS).contains(&health_window_seconds) {
+        return Err(AppError::InvalidInput(format!(
+            "healthWindowSeconds must be between {MIN_HEALTH_WINDOW_SECONDS} and {MAX_HEALTH_WINDOW_SECONDS}"
+        )));
+    }
+
+    // The global config reaches every VPS without an override, in every organization.
+    let vps_list = vps_service::get_vps_following_global_config(app_state.duckdb_pool.clone()).await?;
+    let vps_ids: Vec<i32> = vps_list.iter().map(|vps| vps.id).collect();
+    let stages = config_rollout_service::plan_stages(
+        &vps_ids,
+        &payload.canary_vps_ids,
+        payload.canary_count,
+        payload.batch_size,
+    )
+    .map_err(AppError::InvalidInput)?;
+
+    if payload.dry_run {
+        let agents_guard = app_state.connected_agents.lock().await;
+        let plan = ConfigRolloutPlan {
+            stages: stages
+                .iter()
+                .map(|stage| {
+                    stage
+                        .iter()
+                        .map(|id| RolloutPlanEntry {
+                            vps_id: *id,
+                            name: vps_list
+                                .iter()
+                                .find(|vps| vps.id == *id)
+                                .map(|vps| vps.name.clone())
+                                .unwrap_or_default(),
+                            connected: agents_guard.find_by_vps_id(*id).is_some(),
+                        })
+                        .collect()
+                })
+                .collect(),
+            health_window_seconds,
+        };
+        return Ok(Json(plan).into_response());
+    }
+
+    let new_config: AgentConfig = payload.config.into();
+    let rollout = config_rollout_service::create_rollout(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        &serde_json::to_value(&new_config)?,
+        health_window_seconds as i32,
+        &stages,
     )
+    .await?;
+    tokio::spawn(run_rollout(
+        app_state.clone(),
+        rollout.rollout.id,
+        new_config,
+        stages,
+        Duration::from_secs(u64::from(health_window_seconds)),
+    ));
+    Ok((StatusCode::ACCEPTED, Json(rollout)).into_response())
+}
+
+async fn list_config_rollouts(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<config_rollout::Model>>, AppError> {
+    Ok(Json(config_rollout_service::list_rollouts(app_state.duckdb_pool.clone()).await?))
+}
+
+async fn get_config_rollout(
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<ConfigRolloutDetails>, AppError> {
+    config_rollout_service::get_rollout(app_state.duckdb_pool.clone(), id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("Config rollout not found".to_string()))
 }
 
 pub fn create_vps_config_router() -> Router<Arc<AppState>> {
@@ -45,24 +206,85 @@ async fn update_global_agent_config(
     State(app_state): State<Arc<AppState>>,
     Json(payload): Json<WebAgentConfig>,
 ) -> Result<StatusCode, AppError> {
+    if let Some(running) = config_rollout_service::running_rollout_id(app_state.duckdb_pool.clone()).await? {
+        return Err(AppError::Conflict(format!(
+            "Config rollout {running} is still running and will replace the global agent config."
+        )));
+    }
     let proto_config: AgentConfig = payload.into();
     let value = serde_json::to_value(&proto_config)?;
 
     settings_service::update_setting(app_state.duckdb_pool.clone(), "global_agent_config", &value).await?;
 
-    let all_vps_models = vps_service::get_vps_by_user_id(app_state.duckdb_pool.clone(), 1).await?; // Assuming user_id 1 for admin
-
-    for vps_model in all_vps_models {
-        if vps_model.agent_config_override.is_none() {
-            if let Err(e) = push_config_to_vps(app_state.clone(), vps_model.id).await {
-                error!(vps_id = vps_model.id, error = ?e, "Failed to push config to VPS after global update.");
-            }
+    for vps_model in vps_service::get_vps_following_global_config(app_state.duckdb_pool.clone()).await? {
+        if let Err(e) = push_config_to_vps(app_state.clone(), vps_model.id).await {
+            error!(vps_id = vps_model.id, error = ?e, "Failed to push config to VPS after global update.");
         }
     }
 
     Ok(StatusCode::OK)
 }
 
+async fn get_uptime_settings(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<UptimeSettings>, AppError> {
+    Ok(Json(uptime_service::get_uptime_settings(app_state.duckdb_pool.clone()).await?))
+}
+
+async fn update_uptime_settings(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<UptimeSettings>,
+) -> Result<Json<UptimeSettings>, AppError> {
+    if payload.grace_seconds < 0 {
+        return Err(AppError::InvalidInput("graceSeconds must not be negative".to_string()));
+    }
+    uptime_service::update_uptime_settings(app_state.duckdb_pool.clone(), &payload).await?;
+    Ok(Json(payload))
+}
+
+async fn get_correlation_settings(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<CorrelationSettings>, AppError> {
+    Ok(Json(alert_correlation_service::get_correlation_settings(app_state.duckdb_pool.clone()).await?))
+}
+
+async fn update_correlation_settings(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CorrelationSettings>,
+) -> Result<Json<CorrelationSettings>, AppError> {
+    if !(0..=24 * 60).contains(&payload.window_minutes) {
+        return Err(AppError::InvalidInput("windowMinutes must be between 0 and 1440".to_string()));
+    }
+    alert_correlation_service::update_correlation_settings(app_state.duckdb_pool.clone(), &payload).await?;
+    Ok(Json(payload))
+}
+
+async fn get_health_settings(
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<HealthScoreSettings>, AppError> {
+    Ok(Json(health_service::get_health_settings(app_state.duckdb_pool.clone()).await?))
+}
+
+async fn update_health_settings(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<HealthScoreSettings>,
+) -> Result<Json<HealthScoreSettings>, AppError> {
+    if payload.pressure_critical_percent <= payload.pressure_warn_percent {
+        return Err(AppError::InvalidInput(
+            "pressureCriticalPercent must be greater than pressureWarnPercent".to_string(),
+        ));
+    }
+    if payload.stale_seconds <= payload.fresh_seconds {
+        return Err(AppError::InvalidInput("staleSeconds must be greater than freshSeconds".to_string()));
+    }
+    health_service::update_health_settings(app_state.duckdb_pool.clone(), &payload).await?;
+    // Rescore with the new settings on the next cache refresh.
+    if app_state.update_trigger_tx.send(()).await.is_err() {
+        warn!("Failed to send update trigger after health score settings change.");
+    }
+    Ok(Json(payload))
+}
+
 async fn update_vps_config_override(
     State(app_state): State<Arc<AppState>>,
     Path(vps_id): Path<i32>,
@@ -100,7 +322,13 @@ async fn preview_vps_config(
 
 pub async fn push_config_to_vps(app_state: Arc<AppState>, vps_id: i32) -> Result<(), AppError> {
     let effective_config = get_effective_vps_config(app_state.duckdb_pool.clone(), vps_id).await?;
+    send_config_to_vps(&app_state, vps_id, effective_config).await;
+    Ok(())
+}
 
+/// Sends a config to the VPS's agent and records the push in its
+/// `config_status`. Returns whether the config reached the agent's channel.
+pub async fn send_config_to_vps(app_state: &AppState, vps_id: i32, effective_config: AgentConfig) -> bool {
     let agent_state = {
         let agents_guard = app_state.connected_agents.lock().await;
         agents_guard.find_by_vps_id(vps_id)
@@ -124,6 +352,7 @@ pub async fn push_config_to_vps(app_state: Arc<AppState>, vps_id: i32) -> Result
             {
                 error!(vps_id = vps_id, error = ?e, "Failed to update VPS config status to pending.");
             }
+            return true;
         } else {
             let err_msg = "Failed to send config to agent (channel closed).";
             warn!(vps_id = vps_id, "{}", err_msg);
@@ -153,18 +382,32 @@ pub async fn push_config_to_vps(app_state: Arc<AppState>, vps_id: i32) -> Result
         }
     }
 
-    Ok(())
+    false
+}
+
+pub async fn get_global_config(db_pool: duckdb_service::DuckDbPool) -> Result<AgentConfig, AppError> {
+    let global_config_setting = settings_service::get_setting(db_pool, "global_agent_config")
+        .await?
+        .ok_or_else(|| AppError::NotFound("Global agent config not found.".to_string()))?;
+    Ok(serde_json::from_value(global_config_setting.value)?)
 }
 
 pub async fn get_effective_vps_config(
     db_pool: duckdb_service::DuckDbPool,
     vps_id: i32,
 ) -> Result<AgentConfig, AppError> {
-    let global_config_setting = settings_service::get_setting(db_pool.clone(), "global_agent_config")
-        .await?
-        .ok_or_else(|| AppError::NotFound("Global agent config not found.".to_string()))?;
+    let global_config = get_global_config(db_pool.clone()).await?;
+    get_effective_vps_config_with_base(db_pool, vps_id, global_config).await
+}
 
-    let mut effective_config: AgentConfig = serde_json::from_value(global_config_setting.value)?;
+/// The config a VPS gets with `base` as the global config, merged with its
+/// override and monitor tasks.
+pub async fn get_effective_vps_config_with_base(
+    db_pool: duckdb_service::DuckDbPool,
+    vps_id: i32,
+    base: AgentConfig,
+) -> Result<AgentConfig, AppError> {
+    let mut effective_config = base;
 
     let vps_model = vps_service::get_vps_by_id(db_pool.clone(), vps_id)
         .await?
@@ -205,14 +448,23 @@ pub async fn get_effective_vps_config(
         if !override_config.log_level.is_empty() {
             effective_config.log_level = override_config.log_level;
         }
+        if override_config.mesh_ping_interval_seconds > 0 {
+            effective_config.mesh_ping_interval_seconds = override_config.mesh_ping_interval_seconds;
+        }
         effective_config
             .feature_flags
             .extend(override_config.feature_flags);
     }
 
     // TODO: Migrate service_monitor_service to get tasks
-    let tasks = duckdb_service::service_monitor_service::get_tasks_for_agent(db_pool, vps_id).await?;
+    let tasks = duckdb_service::service_monitor_service::get_tasks_for_agent(db_pool.clone(), vps_id).await?;
     effective_config.service_monitor_tasks = tasks;
 
+    effective_config.mesh_peers = if effective_config.mesh_ping_interval_seconds > 0 {
+        duckdb_service::mesh_service::get_mesh_peers(db_pool, vps_id).await?
+    } else {
+        Vec::new()
+    };
+
     Ok(effective_config)
 }
@@ -0,0 +1,134 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    routing::{get, post, put},
+    Json, Router,
+};
+use chrono::Utc;
+use std::sync::Arc;
+
+use crate::db::duckdb_service::derived_metric_service;
+use crate::db::entities::derived_metric;
+use crate::services::metric_expression::{parse_expression, MetricColumn};
+use crate::web::models::derived_metric_models::{
+    CreateDerivedMetricRequest, DerivedMetricPoint, DerivedMetricTimeseriesQuery,
+    UpdateDerivedMetricRequest, ValidateExpressionRequest, ValidateExpressionResponse,
+};
+use crate::web::models::AuthenticatedUser;
+use crate::web::routes::vps_routes::parse_interval_to_seconds;
+use crate::web::{AppError, AppState};
+
+pub fn create_derived_metric_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_derived_metrics).post(create_derived_metric))
+        .route("/columns", get(list_columns))
+        .route("/validate", post(validate_expression))
+        .route("/{id}", put(update_derived_metric).delete(delete_derived_metric))
+        .route("/{id}/timeseries", get(get_derived_metric_timeseries))
+}
+
+async fn list_derived_metrics(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<derived_metric::Model>>, AppError> {
+    let metrics = derived_metric_service::get_derived_metrics_for_user(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+    )
+    .await?;
+    Ok(Json(metrics))
+}
+
+/// The column names expressions may reference.
+async fn list_columns() -> Json<Vec<&'static str>> {
+    Json(MetricColumn::ALL.iter().map(MetricColumn::name).collect())
+}
+
+async fn validate_expression(
+    Json(payload): Json<ValidateExpressionRequest>,
+) -> Json<ValidateExpressionResponse> {
+    let result = parse_expression(&payload.expression);
+    Json(ValidateExpressionResponse {
+        valid: result.is_ok(),
+        error: result.err().map(|e| e.to_string()),
+    })
+}
+
+async fn create_derived_metric(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateDerivedMetricRequest>,
+) -> Result<Json<derived_metric::Model>, AppError> {
+    let metric = derived_metric_service::create_derived_metric(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        payload,
+    )
+    .await?;
+    Ok(Json(metric))
+}
+
+async fn update_derived_metric(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<UpdateDerivedMetricRequest>,
+) -> Result<Json<derived_metric::Model>, AppError> {
+    let metric = derived_metric_service::update_derived_metric(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        id,
+        payload,
+    )
+    .await?;
+    Ok(Json(metric))
+}
+
+async fn delete_derived_metric(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<(), AppError> {
+    derived_metric_service::delete_derived_metric(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        id,
+    )
+    .await
+}
+
+async fn get_derived_metric_timeseries(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Query(query): Query<DerivedMetricTimeseriesQuery>,
+) -> Result<Json<Vec<DerivedMetricPoint>>, AppError> {
+    let user_id = authenticated_user.id;
+    let metric = derived_metric_service::get_derived_metric(app_state.duckdb_pool.clone(), user_id, id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Derived metric not found".to_string()))?;
+    let vps = app_state.storage.get_vps_by_id(query.vps_id).await?;
+    if !matches!(vps, Some(v) if authenticated_user.can_access(v.organization_id)) {
+        return Err(AppError::NotFound("VPS not found".to_string()));
+    }
+
+    let end_time = query.end_time.unwrap_or_else(Utc::now);
+    if query.start_time >= end_time {
+        return Err(AppError::InvalidInput(
+            "start_time must be before end_time".to_string(),
+        ));
+    }
+    let interval_seconds = parse_interval_to_seconds(query.interval.clone())
+        .and_then(|secs| u32::try_from(secs).ok());
+
+    let points = derived_metric_service::get_derived_metric_timeseries(
+        app_state.duckdb_pool.clone(),
+        &metric,
+        query.vps_id,
+        query.start_time,
+        end_time,
+        interval_seconds,
+        &app_state.config.retention_policy(),
+    )
+    .await?;
+    Ok(Json(points))
+}
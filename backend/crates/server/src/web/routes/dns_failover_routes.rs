@@ -0,0 +1,90 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    routing::{get, put},
+    Json, Router,
+};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::dns_failover_service;
+use crate::db::entities::{dns_failover_event, dns_failover_policy};
+use crate::web::models::dns_failover_models::{
+    CreateDnsFailoverPolicyRequest, DnsFailoverEventsQuery, UpdateDnsFailoverPolicyRequest,
+};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+const MAX_EVENTS: u32 = 200;
+
+pub fn create_dns_failover_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/policies", get(list_policies_handler).post(create_policy_handler))
+        .route("/policies/{id}", put(update_policy_handler).delete(delete_policy_handler))
+        .route("/policies/{id}/events", get(get_policy_events_handler))
+}
+
+async fn list_policies_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<dns_failover_policy::Model>>, AppError> {
+    let policies =
+        dns_failover_service::get_policies_for_user(app_state.duckdb_pool.clone(), authenticated_user.id).await?;
+    Ok(Json(policies))
+}
+
+async fn create_policy_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateDnsFailoverPolicyRequest>,
+) -> Result<(StatusCode, Json<dns_failover_policy::Model>), AppError> {
+    let policy = dns_failover_service::create_policy(
+        app_state.duckdb_pool.clone(),
+        app_state.encryption_service.clone(),
+        authenticated_user.id,
+        payload,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(policy)))
+}
+
+async fn update_policy_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<UpdateDnsFailoverPolicyRequest>,
+) -> Result<Json<dns_failover_policy::Model>, AppError> {
+    let policy = dns_failover_service::update_policy(
+        app_state.duckdb_pool.clone(),
+        app_state.encryption_service.clone(),
+        authenticated_user.id,
+        id,
+        payload,
+    )
+    .await?;
+    Ok(Json(policy))
+}
+
+async fn delete_policy_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    dns_failover_service::delete_policy(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_policy_events_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Query(query): Query<DnsFailoverEventsQuery>,
+) -> Result<Json<Vec<dns_failover_event::Model>>, AppError> {
+    let events = dns_failover_service::get_policy_events(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        id,
+        query.limit.unwrap_or(MAX_EVENTS).min(MAX_EVENTS),
+    )
+    .await?;
+    Ok(Json(events))
+}
@@ -0,0 +1,115 @@
+use axum::{
+    extract::{Extension, Path, State},
+    routing::post,
+    Json, Router,
+};
+use nodenexus_common::agent_service::{
+    command_request, command_response, docker_command_payload::DockerAction, CommandExecutionType,
+    CommandRequest, DockerCommandPayload,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::server::command_dispatcher::DispatcherError;
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+/// Docker's own default grace period before it kills a stopping container.
+const DEFAULT_STOP_TIMEOUT_SECONDS: u32 = 10;
+const MAX_STOP_TIMEOUT_SECONDS: u32 = 300;
+/// Added to the stop timeout for the agent round trip.
+const REQUEST_GRACE: Duration = Duration::from_secs(30);
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ContainerAction {
+    Start,
+    Stop,
+    Restart,
+}
+
+impl From<ContainerAction> for DockerAction {
+    fn from(action: ContainerAction) -> Self {
+        match action {
+            ContainerAction::Start => DockerAction::StartContainer,
+            ContainerAction::Stop => DockerAction::StopContainer,
+            ContainerAction::Restart => DockerAction::RestartContainer,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerActionRequest {
+    pub action: ContainerAction,
+    /// Seconds to wait for a graceful stop before the container is killed.
+    /// Ignored for `start`.
+    pub timeout_seconds: Option<u32>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ContainerActionResponse {
+    pub container_id: String,
+    /// False when the container was already in the requested state.
+    pub state_changed: bool,
+}
+
+pub fn docker_router() -> Router<Arc<AppState>> {
+    Router::new().route("/{vps_id}/docker/{container_id}/action", post(container_action_handler))
+}
+
+async fn container_action_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path((vps_id, container_id)): Path<(i32, String)>,
+    Json(payload): Json<ContainerActionRequest>,
+) -> Result<Json<ContainerActionResponse>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+
+    let stop_timeout = payload.timeout_seconds.unwrap_or(DEFAULT_STOP_TIMEOUT_SECONDS);
+    if stop_timeout > MAX_STOP_TIMEOUT_SECONDS {
+        return Err(AppError::InvalidInput(format!(
+            "timeoutSeconds must not exceed {MAX_STOP_TIMEOUT_SECONDS}"
+        )));
+    }
+    let timeout = Duration::from_secs(u64::from(stop_timeout)) + REQUEST_GRACE;
+
+    let docker_command = DockerCommandPayload {
+        action: DockerAction::from(payload.action).into(),
+        target_id: container_id,
+        arguments: HashMap::from([("timeout_seconds".to_string(), stop_timeout.to_string())]),
+    };
+    let request = CommandRequest {
+        r#type: CommandExecutionType::CmdExecTypeDockerOperation.into(),
+        payload: Some(command_request::Payload::DockerCommand(docker_command)),
+        timeout_seconds: timeout.as_secs() as u32,
+        ..Default::default()
+    };
+    let response = app_state
+        .command_dispatcher
+        .send_command_request(vps_id, request, timeout)
+        .await
+        .map_err(|e| match e {
+            DispatcherError::AgentNotFound(_) => AppError::Conflict("Agent is not connected".to_string()),
+            DispatcherError::Timeout(_) => AppError::ServerError("Agent did not respond in time".to_string()),
+            other => AppError::ServerError(other.to_string()),
+        })?;
+    if !response.success {
+        return Err(AppError::InvalidInput(response.error_message));
+    }
+    match response.result_payload {
+        Some(command_response::ResultPayload::DockerResult(result)) => Ok(Json(ContainerActionResponse {
+            container_id: result.container_id,
+            state_changed: result.state_changed,
+        })),
+        _ => Err(AppError::ServerError("Agent returned no docker result".to_string())),
+    }
+}
@@ -0,0 +1,43 @@
+use axum::{
+    extract::{Query, State},
+    routing::{get, post},
+    Json, Router,
+};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::encryption_key_service;
+use crate::web::models::encryption_key_models::{EncryptionKeyStatus, RotateKeyQuery};
+use crate::web::{AppError, AppState};
+
+/// Key rotation, mounted at `/api/settings/encryption-keys`.
+pub fn create_settings_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(get_key_status_handler))
+        .route("/rotate", post(rotate_key_handler))
+}
+
+async fn get_key_status_handler(State(app_state): State<Arc<AppState>>) -> Result<Json<EncryptionKeyStatus>, AppError> {
+    let status =
+        encryption_key_service::get_key_status(app_state.duckdb_pool.clone(), app_state.encryption_service.clone())
+            .await?;
+    Ok(Json(status))
+}
+
+/// Switches new writes to a fresh key, globally or for one organization.
+/// Existing rows are migrated in the background, so the server keeps serving
+/// throughout.
+async fn rotate_key_handler(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<RotateKeyQuery>,
+) -> Result<Json<EncryptionKeyStatus>, AppError> {
+    encryption_key_service::rotate_key(
+        app_state.duckdb_pool.clone(),
+        &app_state.encryption_service,
+        query.organization_id,
+    )
+    .await?;
+    let status =
+        encryption_key_service::get_key_status(app_state.duckdb_pool.clone(), app_state.encryption_service.clone())
+            .await?;
+    Ok(Json(status))
+}
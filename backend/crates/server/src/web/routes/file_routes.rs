@@ -0,0 +1,284 @@
+use axum::{
+    body::{Body, Bytes},
+    extract::{Extension, Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::{delete, get, put},
+    Json, Router,
+};
+use futures_util::{stream, StreamExt};
+use nodenexus_common::agent_service::{
+    command_request, command_response, file_management_operation::FileAction,
+    file_stat::ItemType, CommandExecutionType, CommandRequest, FileManagementOperation,
+    FileManagementResult, FileStat,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::server::command_dispatcher::DispatcherError;
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+const FILE_REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Chunk size for transfers in both directions; well below the gRPC message limit.
+const TRANSFER_CHUNK_SIZE: usize = 512 * 1024;
+
+#[derive(Deserialize, Debug)]
+pub struct PathQuery {
+    pub path: String,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct DeleteQuery {
+    pub path: String,
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileEntry {
+    pub name: String,
+    pub path: String,
+    /// "file", "directory", "symlink" or "other".
+    pub item_type: &'static str,
+    pub size_bytes: i64,
+    pub mode: u32,
+    pub modified_at_ms: i64,
+    pub owner: String,
+    pub group: String,
+}
+
+impl From<FileStat> for FileEntry {
+    fn from(stat: FileStat) -> Self {
+        let item_type = match stat.item_type() {
+            ItemType::File => "file",
+            ItemType::Directory => "directory",
+            ItemType::Symlink => "symlink",
+            ItemType::Other | ItemType::Unspecified => "other",
+        };
+        Self {
+            name: stat.name,
+            path: stat.path,
+            item_type,
+            size_bytes: stat.size_bytes,
+            mode: stat.mode_permissions,
+            modified_at_ms: stat.modified_time_unix_ms,
+            owner: stat.owner_user,
+            group: stat.owner_group,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct DirectoryListing {
+    pub directory: Option<FileEntry>,
+    pub entries: Vec<FileEntry>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UploadResult {
+    pub path: String,
+    pub bytes_written: i64,
+}
+
+pub fn file_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/{vps_id}/files/list", get(list_directory_handler))
+        .route("/{vps_id}/files/stat", get(stat_handler))
+        .route("/{vps_id}/files/download", get(download_handler))
+        .route("/{vps_id}/files/upload", put(upload_handler))
+        .route("/{vps_id}/files/delete", delete(delete_handler))
+}
+
+async fn authorize(app_state: &AppState, user: &AuthenticatedUser, vps_id: i32) -> Result<(), AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+    Ok(())
+}
+
+/// Runs one file operation on the agent and unwraps its result.
+async fn run_file_operation(
+    app_state: &AppState,
+    vps_id: i32,
+    operation: FileManagementOperation,
+) -> Result<FileManagementResult, AppError> {
+    let request = CommandRequest {
+        r#type: CommandExecutionType::CmdExecTypeFileManagement.into(),
+        payload: Some(command_request::Payload::FileOperation(operation)),
+        timeout_seconds: FILE_REQUEST_TIMEOUT.as_secs() as u32,
+        ..Default::default()
+    };
+    let response = app_state
+        .command_dispatcher
+        .send_command_request(vps_id, request, FILE_REQUEST_TIMEOUT)
+        .await
+        .map_err(|e| match e {
+            DispatcherError::AgentNotFound(_) => AppError::Conflict("Agent is not connected".to_string()),
+            DispatcherError::Timeout(_) => AppError::ServerError("Agent did not respond in time".to_string()),
+            other => AppError::ServerError(other.to_string()),
+        })?;
+    if !response.success {
+        return Err(AppError::InvalidInput(response.error_message));
+    }
+    match response.result_payload {
+        Some(command_response::ResultPayload::FileResult(result)) => Ok(result),
+        _ => Err(AppError::ServerError("Agent returned no file result".to_string())),
+    }
+}
+
+fn operation(action: FileAction, path: &str) -> FileManagementOperation {
+    FileManagementOperation {
+        action: action.into(),
+        path: path.to_string(),
+        ..Default::default()
+    }
+}
+
+async fn list_directory_handler(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<PathQuery>,
+) -> Result<Json<DirectoryListing>, AppError> {
+    authorize(&app_state, &user, vps_id).await?;
+    let result = run_file_operation(&app_state, vps_id, operation(FileAction::ListDirectory, &query.path)).await?;
+    Ok(Json(DirectoryListing {
+        directory: result.item_stat.map(FileEntry::from),
+        entries: result.directory_listing.into_iter().map(FileEntry::from).collect(),
+    }))
+}
+
+async fn stat_handler(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<PathQuery>,
+) -> Result<Json<FileEntry>, AppError> {
+    authorize(&app_state, &user, vps_id).await?;
+    let result = run_file_operation(&app_state, vps_id, operation(FileAction::StatItem, &query.path)).await?;
+    let stat = result
+        .item_stat
+        .ok_or_else(|| AppError::ServerError("Agent returned no file stat".to_string()))?;
+    Ok(Json(stat.into()))
+}
+
+/// Streams a file from the agent chunk by chunk.
+async fn download_handler(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<PathQuery>,
+) -> Result<Response, AppError> {
+    authorize(&app_state, &user, vps_id).await?;
+    let stat = run_file_operation(&app_state, vps_id, operation(FileAction::StatItem, &query.path))
+        .await?
+        .item_stat
+        .ok_or_else(|| AppError::ServerError("Agent returned no file stat".to_string()))?;
+    if stat.item_type() != ItemType::File {
+        return Err(AppError::InvalidInput("Only regular files can be downloaded".to_string()));
+    }
+    let file_name = stat.name.replace('"', "");
+
+    // State: next offset, or None once the last chunk has been sent.
+    let path = query.path;
+    let chunks = stream::unfold(Some(0i64), move |offset| {
+        let app_state = app_state.clone();
+        let path = path.clone();
+        async move {
+            let offset = offset?;
+            let op = FileManagementOperation {
+                offset,
+                chunk_size_request: TRANSFER_CHUNK_SIZE as u32,
+                ..operation(FileAction::GetFileChunk, &path)
+            };
+            match run_file_operation(&app_state, vps_id, op).await {
+                Ok(result) => {
+                    let next = (!result.is_eof && !result.data_chunk.is_empty())
+                        .then(|| offset + result.data_chunk.len() as i64);
+                    Some((Ok(Bytes::from(result.data_chunk)), next))
+                }
+                Err(e) => Some((Err(std::io::Error::other(e.to_string())), None)),
+            }
+        }
+    });
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/octet-stream".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{file_name}\"")),
+        ],
+        Body::from_stream(chunks),
+    )
+        .into_response())
+}
+
+/// Writes the request body to the given path on the agent, replacing any existing file.
+async fn upload_handler(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<PathQuery>,
+    body: Body,
+) -> Result<Json<UploadResult>, AppError> {
+    authorize(&app_state, &user, vps_id).await?;
+
+    let mut body = body.into_data_stream();
+    let mut buffer: Vec<u8> = Vec::with_capacity(TRANSFER_CHUNK_SIZE);
+    let mut offset = 0i64;
+    loop {
+        let next = body
+            .next()
+            .await
+            .transpose()
+            .map_err(|e| AppError::InvalidInput(format!("Failed to read upload: {e}")))?;
+        let is_last_chunk = next.is_none();
+        if let Some(bytes) = next {
+            buffer.extend_from_slice(&bytes);
+        }
+        while buffer.len() >= TRANSFER_CHUNK_SIZE || (is_last_chunk && (offset == 0 || !buffer.is_empty())) {
+            let len = buffer.len().min(TRANSFER_CHUNK_SIZE);
+            let data: Vec<u8> = buffer.drain(..len).collect();
+            let op = FileManagementOperation {
+                data_chunk: data,
+                offset,
+                is_last_chunk: is_last_chunk && buffer.is_empty(),
+                ..operation(FileAction::PutFileChunk, &query.path)
+            };
+            offset = run_file_operation(&app_state, vps_id, op).await?.bytes_written_cumulative;
+            if is_last_chunk && buffer.is_empty() {
+                break;
+            }
+        }
+        if is_last_chunk {
+            break;
+        }
+    }
+
+    Ok(Json(UploadResult { path: query.path, bytes_written: offset }))
+}
+
+async fn delete_handler(
+    Extension(user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<DeleteQuery>,
+) -> Result<Json<FileEntry>, AppError> {
+    authorize(&app_state, &user, vps_id).await?;
+    let op = FileManagementOperation {
+        recursive_delete: query.recursive,
+        ..operation(FileAction::DeleteItem, &query.path)
+    };
+    let stat = run_file_operation(&app_state, vps_id, op)
+        .await?
+        .item_stat
+        .ok_or_else(|| AppError::ServerError("Agent returned no file stat".to_string()))?;
+    Ok(Json(stat.into()))
+}
@@ -0,0 +1,181 @@
+use axum::{
+    extract::{Extension, Query, State},
+    routing::get,
+    Json, Router,
+};
+use chrono::Duration;
+use std::sync::Arc;
+
+use crate::db::duckdb_service::{fleet_service, health_service};
+use crate::services::{fleet_map, geoip};
+use crate::web::models::fleet_models::{
+    FleetHealthResponse, FleetMapQuery, FleetMapResponse, FleetMapServer, FleetTopQuery, FleetTopResponse,
+    VpsHealthEntry,
+};
+use crate::web::models::AuthenticatedUser;
+use crate::web::routes::vps_routes::parse_interval_to_seconds;
+use crate::web::{AppError, AppState};
+
+const DEFAULT_WINDOW_SECONDS: u32 = 3600;
+const DEFAULT_TOP_N: u32 = 10;
+const MAX_TOP_N: u32 = 100;
+const DEFAULT_MAP_ZOOM: u8 = 2;
+/// Samples older than this don't count as current usage on the map.
+const MAP_USAGE_MAX_AGE_SECONDS: i64 = 300;
+
+pub fn create_fleet_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/top", get(get_fleet_top))
+        .route("/health", get(get_fleet_health))
+        .route("/map", get(get_fleet_map))
+        .route("/locations", get(get_fleet_locations))
+}
+
+async fn get_fleet_top(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<FleetTopQuery>,
+) -> Result<Json<FleetTopResponse>, AppError> {
+    let metric = fleet_service::resolve_top_metric(query.metric.trim())?;
+    let window_seconds = match query.window.clone() {
+        Some(window) => parse_interval_to_seconds(Some(window.clone()))
+            .and_then(|secs| u32::try_from(secs).ok())
+            .filter(|secs| *secs > 0)
+            .ok_or_else(|| AppError::InvalidInput(format!("Invalid window '{window}'.")))?,
+        None => DEFAULT_WINDOW_SECONDS,
+    };
+    let n = query.n.unwrap_or(DEFAULT_TOP_N).clamp(1, MAX_TOP_N);
+
+    let entries = fleet_service::get_top_vps(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        &metric,
+        query.agg,
+        Duration::seconds(window_seconds as i64),
+        n,
+        &app_state.config.retention_policy(),
+    )
+    .await?;
+
+    Ok(Json(FleetTopResponse {
+        metric: query.metric,
+        agg: query.agg,
+        window_seconds,
+        entries,
+    }))
+}
+
+/// Health scores as of the last cache refresh, per VPS and per group.
+async fn get_fleet_health(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<FleetHealthResponse>, AppError> {
+    let mut servers: Vec<VpsHealthEntry> = app_state
+        .live_server_data_cache
+        .lock()
+        .await
+        .values()
+        .filter(|s| authenticated_user.can_access(s.basic_info.organization_id))
+        .map(|s| VpsHealthEntry {
+            vps_id: s.basic_info.id,
+            vps_name: s.basic_info.name.clone(),
+            group: s.basic_info.group.clone(),
+            health: s.health.clone(),
+        })
+        .collect();
+    servers.sort_by_key(|s| s.vps_id);
+
+    let groups = health_service::group_health(
+        servers
+            .iter()
+            .map(|s| (s.group.as_deref(), s.health.as_ref().and_then(|h| h.score))),
+    );
+    Ok(Json(FleetHealthResponse { servers, groups }))
+}
+
+/// Located servers with their status and current usage, plus cluster hints
+/// for the requested zoom level.
+async fn get_fleet_map(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<FleetMapQuery>,
+) -> Result<Json<FleetMapResponse>, AppError> {
+    let zoom = query.zoom.unwrap_or(DEFAULT_MAP_ZOOM).min(fleet_map::MAX_ZOOM);
+    let mut usage = fleet_service::get_latest_usage(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        Duration::seconds(MAP_USAGE_MAX_AGE_SECONDS),
+    )
+    .await?;
+
+    let mut servers = Vec::new();
+    let mut unlocated_vps_ids = Vec::new();
+    {
+        let cache = app_state.live_server_data_cache.lock().await;
+        for server in cache
+            .values()
+            .filter(|s| authenticated_user.can_access(s.basic_info.organization_id))
+        {
+            let Some(coordinates) = fleet_map::locate(server.metadata.as_ref()) else {
+                unlocated_vps_ids.push(server.basic_info.id);
+                continue;
+            };
+            servers.push(FleetMapServer {
+                vps_id: server.basic_info.id,
+                name: server.basic_info.name.clone(),
+                status: server.basic_info.status.clone(),
+                latitude: coordinates.latitude,
+                longitude: coordinates.longitude,
+                precision: coordinates.precision,
+                country_code: fleet_map::country_code(server.metadata.as_ref()),
+                health_score: server.health.as_ref().and_then(|h| h.score),
+                usage: usage.remove(&server.basic_info.id).unwrap_or_default(),
+                cluster_id: None,
+            });
+        }
+    }
+    servers.sort_by_key(|s| s.vps_id);
+    unlocated_vps_ids.sort_unstable();
+
+    let points: Vec<(i32, f64, f64)> = servers.iter().map(|s| (s.vps_id, s.latitude, s.longitude)).collect();
+    let (clusters, membership) = fleet_map::cluster(&points, zoom);
+    for (server, cluster_id) in servers.iter_mut().zip(membership) {
+        server.cluster_id = cluster_id;
+    }
+
+    Ok(Json(FleetMapResponse {
+        zoom,
+        servers,
+        clusters,
+        unlocated_vps_ids,
+    }))
+}
+
+/// Located servers grouped by country and city, largest groups first.
+async fn get_fleet_locations(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Json<Vec<fleet_map::LocationGroup>> {
+    let mut servers: Vec<fleet_map::LocatedServer> = app_state
+        .live_server_data_cache
+        .lock()
+        .await
+        .values()
+        .filter(|s| authenticated_user.can_access(s.basic_info.organization_id))
+        .filter_map(|server| {
+            let metadata = server.metadata.as_ref();
+            let coordinates = fleet_map::locate(metadata)?;
+            let geo = geoip::from_metadata(metadata);
+            Some(fleet_map::LocatedServer {
+                vps_id: server.basic_info.id,
+                online: server.basic_info.status == "online",
+                country_code: fleet_map::country_code(metadata),
+                country: geo.as_ref().and_then(|geo| geo.country.clone()),
+                city: geo.and_then(|geo| geo.city),
+                coordinates,
+            })
+        })
+        .collect();
+    servers.sort_by_key(|s| s.vps_id);
+    Json(fleet_map::group_by_location(&servers))
+}
@@ -0,0 +1,118 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::{header, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::db::duckdb_service::ingest_key_service;
+use crate::db::entities::ingest_key;
+use crate::services::metric_ingest::{
+    self, DEFAULT_RATE_LIMIT_PER_MINUTE, MAX_RATE_LIMIT_PER_MINUTE, MAX_SAMPLES_PER_REQUEST,
+};
+use crate::web::models::ingest_models::{
+    CreateIngestKeyRequest, CreatedIngestKeyResponse, IngestMetricsRequest, IngestMetricsResponse,
+};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+/// Metric pushes, mounted at `/api/ingest` behind the ingest key middleware.
+pub fn create_ingest_router() -> Router<Arc<AppState>> {
+    Router::new().route("/metrics", post(ingest_metrics))
+}
+
+/// Key management, mounted at `/api/settings/ingest-keys`.
+pub fn create_settings_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_ingest_keys).post(create_ingest_key))
+        .route("/{id}", delete(delete_ingest_key))
+}
+
+async fn ingest_metrics(
+    Extension(key): Extension<ingest_key::Model>,
+    State(app_state): State<Arc<AppState>>,
+    Json(mut payload): Json<IngestMetricsRequest>,
+) -> Result<Response, AppError> {
+    metric_ingest::validate_samples(&mut payload.metrics, &key.metric_prefixes, Utc::now())?;
+
+    let samples = payload.metrics.len() as u32;
+    let per_minute = key.rate_limit_per_minute.max(0) as u32;
+    if let Err(retry_after) = app_state.ingest_rate_limiter.acquire(key.id, samples, per_minute, Instant::now()) {
+        return Ok(too_many_samples(
+            format!("This key may send {per_minute} metrics per minute"),
+            retry_after,
+        ));
+    }
+
+    ingest_key_service::insert_generic_metrics(app_state.duckdb_pool.clone(), key.user_id, &payload.metrics).await?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(IngestMetricsResponse {
+            accepted: payload.metrics.len(),
+        }),
+    )
+        .into_response())
+}
+
+/// 429 telling the client when the next rate limit window opens.
+pub(crate) fn too_many_samples(message: String, retry_after: Duration) -> Response {
+    let mut response = AppError::TooManyRequests(message).into_response();
+    response
+        .headers_mut()
+        .insert(header::RETRY_AFTER, HeaderValue::from(retry_after.as_secs().max(1)));
+    response
+}
+
+async fn list_ingest_keys(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ingest_key::Model>>, AppError> {
+    let keys =
+        ingest_key_service::get_ingest_keys_for_user(app_state.duckdb_pool.clone(), authenticated_user.id).await?;
+    Ok(Json(keys))
+}
+
+async fn create_ingest_key(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateIngestKeyRequest>,
+) -> Result<(StatusCode, Json<CreatedIngestKeyResponse>), AppError> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Name must not be empty".to_string()));
+    }
+    for prefix in &payload.metric_prefixes {
+        if prefix.is_empty() {
+            return Err(AppError::InvalidInput("Metric prefixes must not be empty".to_string()));
+        }
+    }
+    let rate_limit = payload.rate_limit_per_minute.unwrap_or(DEFAULT_RATE_LIMIT_PER_MINUTE);
+    if !(MAX_SAMPLES_PER_REQUEST as u32..=MAX_RATE_LIMIT_PER_MINUTE).contains(&rate_limit) {
+        return Err(AppError::InvalidInput(format!(
+            "rateLimitPerMinute must be between {MAX_SAMPLES_PER_REQUEST} and {MAX_RATE_LIMIT_PER_MINUTE}"
+        )));
+    }
+    let (ingest_key, key) = ingest_key_service::create_ingest_key(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        name,
+        &payload.metric_prefixes,
+        rate_limit,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(CreatedIngestKeyResponse { ingest_key, key })))
+}
+
+async fn delete_ingest_key(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    ingest_key_service::delete_ingest_key(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    app_state.ingest_rate_limiter.remove(id);
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,160 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::db::duckdb_service::{agent_release_service, provisioning_service};
+use crate::services::install_script::{self, InstallCredentials, InstallOs, InstallScriptParams};
+use crate::version::VERSION;
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+const MAX_HOSTNAME_LEN: usize = 255;
+
+#[derive(Deserialize, Debug)]
+pub struct InstallScriptQuery {
+    /// `linux` (default), `macos` or `windows`.
+    pub os: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EnrollmentScriptQuery {
+    pub token: String,
+    pub os: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct EnrollRequest {
+    pub token: String,
+    /// Name of the new VPS.
+    #[serde(default)]
+    pub hostname: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrollResponse {
+    pub vps_id: i32,
+    pub agent_secret: String,
+    pub server_address: String,
+}
+
+/// Merged into the VPS router.
+pub fn vps_install_router() -> Router<Arc<AppState>> {
+    Router::new().route("/{vps_id}/install-script", get(vps_install_script_handler))
+}
+
+/// Mounted at `/api/enroll` without authentication; the enrollment token is
+/// the credential.
+pub fn create_enroll_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", post(enroll_handler))
+        .route("/install-script", get(enrollment_install_script_handler))
+}
+
+fn parse_os(os: Option<&str>) -> Result<InstallOs, AppError> {
+    os.unwrap_or("linux").parse().map_err(AppError::InvalidInput)
+}
+
+/// Checksums of the agent builds of this server version registered under
+/// `/api/agent/releases`, so the script doesn't have to trust the mirror's.
+async fn release_checksums(app_state: &AppState) -> Result<Vec<(String, String)>, AppError> {
+    let releases = agent_release_service::list_releases(app_state.duckdb_pool.clone()).await?;
+    Ok(releases
+        .into_iter()
+        .filter(|release| release.version == VERSION)
+        .map(|release| (release.target, release.sha256))
+        .collect())
+}
+
+fn script_response(os: InstallOs, params: &InstallScriptParams<'_>) -> Response {
+    (
+        [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+        install_script::render(os, params),
+    )
+        .into_response()
+}
+
+/// Install script with the credentials of an existing VPS.
+async fn vps_install_script_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<InstallScriptQuery>,
+) -> Result<Response, AppError> {
+    let os = parse_os(query.os.as_deref())?;
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+    let checksums = release_checksums(&app_state).await?;
+    Ok(script_response(
+        os,
+        &InstallScriptParams {
+            server_address: &app_state.config.frontend_url,
+            download_base_url: &app_state.config.agent_download_base_url,
+            version: VERSION,
+            credentials: InstallCredentials::Vps {
+                vps_id: vps.id,
+                agent_secret: &vps.agent_secret,
+            },
+            checksums: &checksums,
+        },
+    ))
+}
+
+/// Install script that enrolls the machine with a single-use enrollment token.
+/// The token is checked here so a bad one fails before anything is installed;
+/// it is only used up when the script calls [`enroll_handler`].
+async fn enrollment_install_script_handler(
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<EnrollmentScriptQuery>,
+) -> Result<Response, AppError> {
+    let os = parse_os(query.os.as_deref())?;
+    let token = provisioning_service::get_provisioning_token_by_value(app_state.duckdb_pool.clone(), &query.token)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid provisioning token.".to_string()))?;
+    provisioning_service::check_enrollment_token(&token).map_err(AppError::Unauthorized)?;
+    provisioning_service::check_token_usable(&token, Utc::now()).map_err(AppError::Unauthorized)?;
+    let checksums = release_checksums(&app_state).await?;
+    Ok(script_response(
+        os,
+        &InstallScriptParams {
+            server_address: &app_state.config.frontend_url,
+            download_base_url: &app_state.config.agent_download_base_url,
+            version: VERSION,
+            credentials: InstallCredentials::Enrollment { token: &token.token },
+            checksums: &checksums,
+        },
+    ))
+}
+
+/// Registers a new VPS with a single-use enrollment token and returns its
+/// agent credentials, for install scripts that write the agent config themselves.
+async fn enroll_handler(
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<EnrollRequest>,
+) -> Result<(StatusCode, Json<EnrollResponse>), AppError> {
+    let hostname: String = payload.hostname.trim().chars().take(MAX_HOSTNAME_LEN).collect();
+    let name = if hostname.is_empty() { "enrolled-host" } else { hostname.as_str() };
+    let vps = provisioning_service::register_vps_with_token(app_state.duckdb_pool.clone(), payload.token.trim(), name, true)
+        .await?;
+    info!(vps_id = vps.id, name, "Enrolled new VPS with a provisioning token.");
+    Ok((
+        StatusCode::CREATED,
+        Json(EnrollResponse {
+            vps_id: vps.id,
+            agent_secret: vps.agent_secret,
+            server_address: app_state.config.frontend_url.trim_end_matches('/').to_string(),
+        }),
+    ))
+}
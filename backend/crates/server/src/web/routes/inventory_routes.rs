@@ -0,0 +1,83 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    routing::get,
+    Json, Router,
+};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::inventory_service;
+use crate::web::models::inventory_models::{
+    InventoryChangeRecord, InventoryChangesQuery, InventorySearchHit, InventorySearchQuery,
+    VpsInventoryResponse,
+};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+pub fn create_inventory_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/search", get(search_inventory))
+        .route("/vps/{vps_id}", get(get_vps_inventory))
+        .route("/vps/{vps_id}/changes", get(get_vps_inventory_changes))
+}
+
+async fn ensure_vps_access(app_state: &AppState, vps_id: i32, user: &AuthenticatedUser) -> Result<(), AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id).await?;
+    if !matches!(vps, Some(v) if user.can_access(v.organization_id)) {
+        return Err(AppError::NotFound("VPS not found".to_string()));
+    }
+    Ok(())
+}
+
+async fn search_inventory(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<InventorySearchQuery>,
+) -> Result<Json<Vec<InventorySearchHit>>, AppError> {
+    let package = query.package.trim();
+    if package.is_empty() {
+        return Err(AppError::InvalidInput("package must not be empty".to_string()));
+    }
+    let constraint = match (query.op, query.version.as_deref().map(str::trim)) {
+        (Some(op), Some(version)) if !version.is_empty() => Some((op, version)),
+        (None, None) => None,
+        _ => {
+            return Err(AppError::InvalidInput(
+                "op and version must be given together".to_string(),
+            ))
+        }
+    };
+
+    let hits = inventory_service::search_packages(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        package,
+        constraint,
+    )
+    .await?;
+    Ok(Json(hits))
+}
+
+async fn get_vps_inventory(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+) -> Result<Json<VpsInventoryResponse>, AppError> {
+    ensure_vps_access(&app_state, vps_id, &authenticated_user).await?;
+    let inventory = inventory_service::get_inventory(app_state.duckdb_pool.clone(), vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("No inventory has been reported for this VPS".to_string()))?;
+    Ok(Json(inventory))
+}
+
+async fn get_vps_inventory_changes(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<InventoryChangesQuery>,
+) -> Result<Json<Vec<InventoryChangeRecord>>, AppError> {
+    ensure_vps_access(&app_state, vps_id, &authenticated_user).await?;
+    let changes =
+        inventory_service::get_inventory_changes(app_state.duckdb_pool.clone(), vps_id, query.limit)
+            .await?;
+    Ok(Json(changes))
+}
@@ -1,15 +1,37 @@
 use axum::{
     Json, Router,
-    extract::{Path, Query, State},
-    routing::get,
+    body::{Body, Bytes},
+    extract::{Extension, Path, Query, State},
+    http::{StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post},
 };
 use chrono::{DateTime, Utc};
+use nodenexus_common::metric_catalog::{self, MetricDescriptor};
 use serde::Deserialize;
 use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tracing::error;
 
 use crate::db::duckdb_service::performance_service::{self};
+use crate::db::duckdb_service::{
+    custom_metric_service, disk_health_service, disk_io_service, gpu_service, ingest_key_service,
+    network_interface_service, temperature_service,
+};
+use crate::db::entities::{disk_health_metric, disk_io_metric, gpu_metric, network_interface_metric, temperature_metric};
+use crate::services::metric_export::MetricExportFormat;
+use crate::services::metric_ingest::{self, DEFAULT_RATE_LIMIT_PER_MINUTE};
 use crate::web::AppError;
 use crate::web::AppState;
+use crate::web::models::AuthenticatedUser;
+use crate::web::models::ingest_models::{
+    CustomMetricPoint, CustomMetricSeries, CustomMetricTimeseriesQuery, IngestMetricsRequest,
+    IngestMetricsResponse,
+};
+use crate::web::routes::ingest_routes::too_many_samples;
+use crate::web::routes::vps_routes::parse_interval_to_seconds;
 
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -48,23 +70,457 @@ async fn get_vps_metrics_timeseries_handler(
         }
     });
 
-    let results = performance_service::get_performance_metrics_for_vps(
-        &app_state.duckdb_pool,
+    let results = app_state
+        .storage
+        .get_performance_metrics_for_vps(
+            vps_id,
+            params.start_time,
+            end_time,
+            interval_seconds, // Pass the parsed interval in seconds
+            &app_state.config.retention_policy(),
+        )
+        .await?;
+
+    Ok(Json(results))
+}
+
+/// Size of the chunks an export is sent in.
+const EXPORT_CHUNK_BYTES: usize = 64 * 1024;
+/// Chunks read ahead of a slow client.
+const EXPORT_BUFFERED_CHUNKS: usize = 4;
+
+#[derive(Deserialize)]
+pub struct MetricsExportQuery {
+    #[serde(alias = "startTime")]
+    pub start: DateTime<Utc>,
+    #[serde(alias = "endTime")]
+    pub end: Option<DateTime<Utc>>,
+    /// Downsampling interval, as for the timeseries; raw points when omitted.
+    pub interval: Option<String>,
+    pub format: MetricExportFormat,
+}
+
+/// Streams a VPS's metrics as CSV or JSON lines. Rows are read from DuckDB
+/// and sent as they come, so memory use doesn't grow with the range.
+async fn export_vps_metrics_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(params): Query<MetricsExportQuery>,
+) -> Result<Response, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+
+    let end = params.end.unwrap_or_else(Utc::now);
+    if params.start >= end {
+        return Err(AppError::InvalidInput("start must be before end".to_string()));
+    }
+    let interval_seconds = parse_interval_to_seconds(params.interval)
+        .and_then(|s| u32::try_from(s).ok())
+        .filter(|s| *s > 0);
+    let format = params.format;
+    let start = params.start;
+    let retention = app_state.config.retention_policy();
+    let conn = app_state.reporting_pool.get()?;
+
+    let (tx, rx) = mpsc::channel::<Result<Bytes, std::io::Error>>(EXPORT_BUFFERED_CHUNKS);
+    tokio::task::spawn_blocking(move || {
+        let mut buf = format.header();
+        let result = performance_service::for_each_performance_metric(
+            &conn,
+            vps_id,
+            start,
+            end,
+            interval_seconds,
+            &retention,
+            |point| {
+                format.write_point(&mut buf, &point);
+                if buf.len() < EXPORT_CHUNK_BYTES {
+                    return true;
+                }
+                // A send error means the client went away.
+                tx.blocking_send(Ok(Bytes::from(std::mem::take(&mut buf)))).is_ok()
+            },
+        );
+        match result {
+            Ok(()) if !buf.is_empty() => {
+                let _ = tx.blocking_send(Ok(Bytes::from(buf)));
+            }
+            Ok(()) => {}
+            Err(e) => {
+                error!(vps_id, error = %e, "Metric export failed.");
+                // Ends the response with an error, so the download shows as incomplete.
+                let _ = tx.blocking_send(Err(std::io::Error::other(e.to_string())));
+            }
+        }
+    });
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("attachment; filename=\"metrics-vps-{vps_id}.{}\"", format.extension()),
+            ),
+        ],
+        Body::from_stream(ReceiverStream::new(rx)),
+    )
+        .into_response())
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskIoQuery {
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub interval: Option<String>,
+    /// Only return this block device, e.g. "sda" or "nvme0n1".
+    pub device: Option<String>,
+}
+
+async fn get_vps_disk_io_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(params): Query<DiskIoQuery>,
+) -> Result<Json<Vec<disk_io_metric::Model>>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+
+    let end_time = params.end_time.unwrap_or_else(Utc::now);
+    if params.start_time >= end_time {
+        return Err(AppError::InvalidInput(
+            "start_time must be before end_time".to_string(),
+        ));
+    }
+    let interval_seconds = parse_interval_to_seconds(params.interval)
+        .and_then(|s| u32::try_from(s).ok())
+        .filter(|s| *s > 0);
+
+    let results = disk_io_service::get_disk_io_timeseries(
+        app_state.duckdb_pool.clone(),
         vps_id,
         params.start_time,
         end_time,
-        interval_seconds, // Pass the parsed interval in seconds
+        interval_seconds,
+        params.device,
     )
-    .await
-    .map_err(|e| AppError::DatabaseError(e.to_string()))?;
+    .await?;
+    Ok(Json(results))
+}
 
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkInterfacesQuery {
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub interval: Option<String>,
+    /// Only return this interface, e.g. "eth0".
+    pub interface: Option<String>,
+}
+
+async fn get_vps_network_interfaces_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(params): Query<NetworkInterfacesQuery>,
+) -> Result<Json<Vec<network_interface_metric::Model>>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+
+    let end_time = params.end_time.unwrap_or_else(Utc::now);
+    if params.start_time >= end_time {
+        return Err(AppError::InvalidInput(
+            "start_time must be before end_time".to_string(),
+        ));
+    }
+    let interval_seconds = parse_interval_to_seconds(params.interval)
+        .and_then(|s| u32::try_from(s).ok())
+        .filter(|s| *s > 0);
+
+    let results = network_interface_service::get_network_interface_timeseries(
+        app_state.duckdb_pool.clone(),
+        vps_id,
+        params.start_time,
+        end_time,
+        interval_seconds,
+        params.interface,
+    )
+    .await?;
     Ok(Json(results))
 }
 
-pub fn metrics_router() -> Router<Arc<AppState>> {
-    Router::new().route(
-        "/{vps_id}/metrics/timeseries",
-        get(get_vps_metrics_timeseries_handler),
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GpuMetricsQuery {
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub interval: Option<String>,
+    /// Only return this GPU, by the index the driver reports.
+    pub gpu: Option<i32>,
+}
+
+async fn get_vps_gpus_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(params): Query<GpuMetricsQuery>,
+) -> Result<Json<Vec<gpu_metric::Model>>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+
+    let end_time = params.end_time.unwrap_or_else(Utc::now);
+    if params.start_time >= end_time {
+        return Err(AppError::InvalidInput(
+            "start_time must be before end_time".to_string(),
+        ));
+    }
+    let interval_seconds = parse_interval_to_seconds(params.interval)
+        .and_then(|s| u32::try_from(s).ok())
+        .filter(|s| *s > 0);
+
+    let results = gpu_service::get_gpu_timeseries(
+        app_state.duckdb_pool.clone(),
+        vps_id,
+        params.start_time,
+        end_time,
+        interval_seconds,
+        params.gpu,
     )
+    .await?;
+    Ok(Json(results))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiskHealthQuery {
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    /// Only return this drive, e.g. `/dev/sda`.
+    pub device: Option<String>,
+}
+
+async fn get_vps_disk_health_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(params): Query<DiskHealthQuery>,
+) -> Result<Json<Vec<disk_health_metric::Model>>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+
+    let end_time = params.end_time.unwrap_or_else(Utc::now);
+    if params.start_time >= end_time {
+        return Err(AppError::InvalidInput(
+            "start_time must be before end_time".to_string(),
+        ));
+    }
+
+    let results = disk_health_service::get_disk_health_timeseries(
+        app_state.duckdb_pool.clone(),
+        vps_id,
+        params.start_time,
+        end_time,
+        params.device,
+    )
+    .await?;
+    Ok(Json(results))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TemperatureMetricsQuery {
+    pub start_time: DateTime<Utc>,
+    pub end_time: Option<DateTime<Utc>>,
+    pub interval: Option<String>,
+    /// Only return this sensor, by its label.
+    pub sensor: Option<String>,
+}
+
+async fn get_vps_temperatures_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(params): Query<TemperatureMetricsQuery>,
+) -> Result<Json<Vec<temperature_metric::Model>>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+
+    let end_time = params.end_time.unwrap_or_else(Utc::now);
+    if params.start_time >= end_time {
+        return Err(AppError::InvalidInput(
+            "start_time must be before end_time".to_string(),
+        ));
+    }
+    let interval_seconds = parse_interval_to_seconds(params.interval)
+        .and_then(|s| u32::try_from(s).ok())
+        .filter(|s| *s > 0);
+
+    let results = temperature_service::get_temperature_timeseries(
+        app_state.duckdb_pool.clone(),
+        vps_id,
+        params.start_time,
+        end_time,
+        interval_seconds,
+        params.sensor,
+    )
+    .await?;
+    Ok(Json(results))
+}
+
+/// Takes the same samples as `/api/ingest/metrics`, attributed to the VPS in the
+/// path, so scripts can push with a user's API token instead of an ingest key.
+async fn push_custom_metrics_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Json(mut payload): Json<IngestMetricsRequest>,
+) -> Result<Response, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+    if payload.metrics.iter().any(|sample| sample.vps_id.is_some_and(|id| id != vps_id)) {
+        return Err(AppError::InvalidInput(
+            "vpsId of a sample must be omitted or match the VPS in the path".to_string(),
+        ));
+    }
+    metric_ingest::validate_samples(&mut payload.metrics, &[], Utc::now())?;
+
+    let samples = payload.metrics.len() as u32;
+    if let Err(retry_after) = app_state.custom_metric_rate_limiter.acquire(
+        vps_id,
+        samples,
+        DEFAULT_RATE_LIMIT_PER_MINUTE,
+        Instant::now(),
+    ) {
+        return Ok(too_many_samples(
+            format!("A VPS may receive {DEFAULT_RATE_LIMIT_PER_MINUTE} custom metrics per minute"),
+            retry_after,
+        ));
+    }
+
+    for sample in &mut payload.metrics {
+        sample.vps_id = Some(vps_id);
+    }
+    // Owned by the VPS owner, like the metrics the agent reports.
+    ingest_key_service::insert_generic_metrics(app_state.duckdb_pool.clone(), vps.user_id, &payload.metrics).await?;
+    Ok((
+        StatusCode::ACCEPTED,
+        Json(IngestMetricsResponse {
+            accepted: payload.metrics.len(),
+        }),
+    )
+        .into_response())
+}
+
+async fn list_custom_metrics_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+) -> Result<Json<Vec<CustomMetricSeries>>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+    let series = custom_metric_service::list_custom_metrics(app_state.duckdb_pool.clone(), vps_id).await?;
+    Ok(Json(series))
+}
+
+async fn get_custom_metric_timeseries_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(params): Query<CustomMetricTimeseriesQuery>,
+) -> Result<Json<Vec<CustomMetricPoint>>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+
+    let end_time = params.end_time.unwrap_or_else(Utc::now);
+    if params.start_time >= end_time {
+        return Err(AppError::InvalidInput(
+            "start_time must be before end_time".to_string(),
+        ));
+    }
+    let interval_seconds = parse_interval_to_seconds(params.interval)
+        .and_then(|s| u32::try_from(s).ok())
+        .filter(|s| *s > 0);
+
+    let results = custom_metric_service::get_custom_metric_timeseries(
+        app_state.duckdb_pool.clone(),
+        vps_id,
+        &params.name,
+        params.start_time,
+        end_time,
+        interval_seconds,
+    )
+    .await?;
+    Ok(Json(results))
+}
+
+/// Every metric the agent collects or the server computes, with its unit and
+/// what alert rules and expressions may use it for.
+async fn get_metric_catalog_handler() -> Json<&'static [MetricDescriptor]> {
+    Json(metric_catalog::METRICS)
+}
+
+/// Routes under `/api/metrics` that aren't tied to one VPS.
+pub fn create_catalog_router() -> Router<Arc<AppState>> {
+    Router::new().route("/catalog", get(get_metric_catalog_handler))
+}
+
+pub fn metrics_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/{vps_id}/metrics/timeseries",
+            get(get_vps_metrics_timeseries_handler),
+        )
+        .route("/{vps_id}/metrics/export", get(export_vps_metrics_handler))
+        .route("/{vps_id}/metrics/disk-io", get(get_vps_disk_io_handler))
+        .route(
+            "/{vps_id}/metrics/network-interfaces",
+            get(get_vps_network_interfaces_handler),
+        )
+        .route("/{vps_id}/metrics/gpus", get(get_vps_gpus_handler))
+        .route("/{vps_id}/metrics/disk-health", get(get_vps_disk_health_handler))
+        .route("/{vps_id}/metrics/temperatures", get(get_vps_temperatures_handler))
+        .route("/{vps_id}/metrics/custom", get(list_custom_metrics_handler))
+        .route(
+            "/{vps_id}/metrics/custom/timeseries",
+            get(get_custom_metric_timeseries_handler),
+        )
+        .route("/{vps_id}/custom-metrics", post(push_custom_metrics_handler))
 }
 
@@ -1,13 +1,50 @@
 pub mod admin_oauth_routes;
+pub mod admin_user_routes;
+pub mod agent_certificate_routes;
+pub mod agent_release_routes;
+pub mod alert_action_routes;
 pub mod alert_routes;
+pub mod api_token_routes;
+pub mod archive_routes;
+pub mod audit_routes;
+pub mod backup_routes;
 pub mod batch_command_routes;
+pub mod branding_routes;
+pub mod chatops_routes;
+pub mod client_routes;
+pub mod command_policy_routes;
 pub mod command_script_routes;
 pub mod config_routes;
+pub mod derived_metric_routes;
+pub mod dns_failover_routes;
+pub mod encryption_key_routes;
+pub mod docker_routes;
+pub mod file_routes;
+pub mod fleet_routes;
+pub mod ingest_routes;
+pub mod install_routes;
+pub mod inventory_routes;
 pub mod metrics_routes;
+pub mod network_routes;
 pub mod notification_routes;
 pub mod oauth_routes;
+pub mod organization_routes;
+pub mod provider_routes;
+pub mod provisioning_routes;
+pub mod public_key_routes;
+pub mod read_only_routes;
+pub mod report_routes;
+pub mod schedule_routes;
 pub mod service_monitor_routes;
+pub mod share_routes;
+pub mod status_page_routes;
 pub mod tag_routes;
+pub mod terminal_routes;
 pub mod theme_routes;
+pub mod traffic_routes;
+pub mod two_factor_routes;
 pub mod user_routes;
+pub mod vps_group_routes;
 pub mod vps_routes;
+pub mod vulnerability_routes;
+pub mod webhook_routes;
@@ -0,0 +1,68 @@
+use axum::{
+    extract::{Extension, Query, State},
+    routing::get,
+    Json, Router,
+};
+use chrono::{Duration, Utc};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::mesh_service;
+use crate::web::models::network_models::{MeshQuery, MeshResponse};
+use crate::web::models::AuthenticatedUser;
+use crate::web::routes::vps_routes::parse_interval_to_seconds;
+use crate::web::{AppError, AppState};
+
+const DEFAULT_WINDOW_SECONDS: u32 = 3600;
+const MAX_WINDOW_SECONDS: u32 = 7 * 86400;
+/// History points per link when no bucket is given.
+const DEFAULT_POINTS: u32 = 60;
+const MIN_BUCKET_SECONDS: u32 = 60;
+/// Keeps the history of every link in the matrix to a few thousand points.
+const MAX_POINTS: u32 = 2000;
+
+pub fn create_network_router() -> Router<Arc<AppState>> {
+    Router::new().route("/mesh", get(get_mesh))
+}
+
+fn parse_seconds(value: Option<String>, name: &str) -> Result<Option<u32>, AppError> {
+    match value {
+        Some(raw) => parse_interval_to_seconds(Some(raw.clone()))
+            .and_then(|secs| u32::try_from(secs).ok())
+            .filter(|secs| *secs > 0)
+            .map(Some)
+            .ok_or_else(|| AppError::InvalidInput(format!("Invalid {name} '{raw}'."))),
+        None => Ok(None),
+    }
+}
+
+/// The latency matrix between the user's agents: one link per direction with
+/// its latest and average round-trip time and loss, and the history.
+async fn get_mesh(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<MeshQuery>,
+) -> Result<Json<MeshResponse>, AppError> {
+    let window_seconds = parse_seconds(query.window, "window")?
+        .unwrap_or(DEFAULT_WINDOW_SECONDS)
+        .min(MAX_WINDOW_SECONDS);
+    let bucket_seconds = parse_seconds(query.bucket, "bucket")?
+        .unwrap_or(window_seconds / DEFAULT_POINTS)
+        .max(MIN_BUCKET_SECONDS)
+        .max(window_seconds.div_ceil(MAX_POINTS));
+
+    let nodes = mesh_service::get_mesh_nodes(app_state.duckdb_pool.clone(), authenticated_user.id).await?;
+    let links = mesh_service::get_mesh_links(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        Utc::now() - Duration::seconds(i64::from(window_seconds)),
+        bucket_seconds,
+    )
+    .await?;
+
+    Ok(Json(MeshResponse {
+        window_seconds,
+        bucket_seconds,
+        nodes,
+        links,
+    }))
+}
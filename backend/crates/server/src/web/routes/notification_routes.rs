@@ -11,7 +11,7 @@ use crate::{
     db::duckdb_service,
     notifications::models::{
         ChannelTemplate, ChannelTemplateField, CreateChannelRequest, TestChannelRequest,
-        UpdateChannelRequest,
+        TestChannelResponse, UpdateChannelRequest,
     },
     web::{AppError, AppState, models::AuthenticatedUser},
 };
@@ -89,7 +89,7 @@ async fn get_channel_templates(
                     required: false,
                     label: "Body Template (for POST)".to_string(),
                     help_text: Some(
-                        "JSON body with Tera template variables like {{ vps_name }}."
+                        "JSON body with Tera template variables like {{ vps_name }} or {{ text }}, the rendered message."
                             .to_string(),
                     ),
                 },
@@ -178,24 +178,21 @@ async fn delete_channel(
     Ok(StatusCode::NO_CONTENT)
 }
 
-// Handler to send a test message to a channel
+// Handler to render a sample alert with the channel's template and send it
 async fn test_channel(
     State(app_state): State<Arc<AppState>>,
     Extension(authenticated_user): Extension<AuthenticatedUser>,
     Path(id): Path<i32>,
     Json(payload): Json<TestChannelRequest>,
-) -> Result<impl IntoResponse, AppError> {
-    duckdb_service::notification_service::send_test_notification(
+) -> Result<Json<TestChannelResponse>, AppError> {
+    let response = duckdb_service::notification_service::send_test_notification(
         app_state.duckdb_pool.clone(),
         app_state.encryption_service.clone(),
         authenticated_user.id,
         id,
-        payload.message.unwrap_or_else(|| "This is a test message from your monitoring system.".to_string()),
+        payload.message,
+        payload.template,
     )
     .await?;
-
-    Ok((
-        StatusCode::OK,
-        Json(serde_json::json!({"message": "Test notification sent successfully."})),
-    ))
+    Ok(Json(response))
 }
@@ -0,0 +1,171 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    routing::{delete, get, put},
+    Json, Router,
+};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::{organization_service, service_monitor_service};
+use crate::db::entities::{organization, organization_member};
+use crate::web::models::organization_models::{
+    MonitorQuotaRequest, OrganizationNameRequest, OrganizationResponse, SwitchOrganizationRequest, UpsertMemberRequest,
+};
+use crate::web::models::{AuthenticatedUser, Role};
+use crate::web::{AppError, AppState};
+
+/// Organization and membership management, mounted at `/api/organizations`.
+pub fn create_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_organizations_handler).post(create_organization_handler))
+        .route("/{id}", put(rename_organization_handler).delete(delete_organization_handler))
+        .route("/{id}/members", get(list_members_handler).post(upsert_member_handler))
+        .route("/{id}/members/{user_id}", delete(remove_member_handler))
+        .route("/{id}/quota", put(set_quota_handler))
+}
+
+/// The org switcher, merged into `/api/user` so every role can use it.
+pub fn create_user_router() -> Router<Arc<AppState>> {
+    Router::new().route("/active-organization", put(switch_organization_handler))
+}
+
+async fn list_organizations_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<OrganizationResponse>>, AppError> {
+    let organizations =
+        organization_service::get_organizations_for_user(app_state.duckdb_pool.clone(), authenticated_user.id).await?;
+    Ok(Json(
+        organizations
+            .into_iter()
+            .map(|(organization, role)| {
+                OrganizationResponse::new(organization, role, authenticated_user.organization_id)
+            })
+            .collect(),
+    ))
+}
+
+async fn create_organization_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<OrganizationNameRequest>,
+) -> Result<(StatusCode, Json<OrganizationResponse>), AppError> {
+    let organization =
+        organization_service::create_organization(app_state.duckdb_pool.clone(), authenticated_user.id, &payload.name)
+            .await?;
+    Ok((
+        StatusCode::CREATED,
+        Json(OrganizationResponse::new(
+            organization,
+            organization_service::ROLE_OWNER.to_string(),
+            authenticated_user.organization_id,
+        )),
+    ))
+}
+
+async fn rename_organization_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<OrganizationNameRequest>,
+) -> Result<Json<OrganizationResponse>, AppError> {
+    let organization = organization_service::rename_organization(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        id,
+        &payload.name,
+    )
+    .await?;
+    Ok(Json(OrganizationResponse::new(
+        organization,
+        organization_service::ROLE_OWNER.to_string(),
+        authenticated_user.organization_id,
+    )))
+}
+
+async fn delete_organization_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    organization_service::delete_organization(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_members_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<organization_member::Model>>, AppError> {
+    let members = organization_service::get_members(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    Ok(Json(members))
+}
+
+async fn upsert_member_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<UpsertMemberRequest>,
+) -> Result<Json<organization_member::Model>, AppError> {
+    let member = organization_service::upsert_member(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        id,
+        &payload.username,
+        payload.role.as_deref().unwrap_or(organization_service::ROLE_MEMBER),
+    )
+    .await?;
+    Ok(Json(member))
+}
+
+async fn remove_member_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path((id, user_id)): Path<(i32, i32)>,
+) -> Result<StatusCode, AppError> {
+    organization_service::remove_member(app_state.duckdb_pool.clone(), authenticated_user.id, id, user_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn switch_organization_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<SwitchOrganizationRequest>,
+) -> Result<Json<OrganizationResponse>, AppError> {
+    let pool = app_state.duckdb_pool.clone();
+    let organization =
+        organization_service::switch_active_organization(pool.clone(), authenticated_user.id, payload.organization_id)
+            .await?;
+    let role = organization_service::get_organizations_for_user(pool, authenticated_user.id)
+        .await?
+        .into_iter()
+        .find(|(candidate, _)| candidate.id == organization.id)
+        .map(|(_, role)| role)
+        .unwrap_or_else(|| organization_service::ROLE_MEMBER.to_string());
+    let active_organization_id = organization.id;
+    Ok(Json(OrganizationResponse::new(organization, role, active_organization_id)))
+}
+
+/// Quotas are set by server admins, not by the organization's owners.
+async fn set_quota_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<MonitorQuotaRequest>,
+) -> Result<Json<organization::Model>, AppError> {
+    if authenticated_user.role != Role::Admin {
+        return Err(AppError::Forbidden("Only admins can set organization quotas".to_string()));
+    }
+    if let Some(seconds) = payload.min_monitor_frequency_seconds {
+        if seconds < service_monitor_service::MIN_FREQUENCY_SECONDS {
+            return Err(AppError::InvalidInput(format!(
+                "The minimum interval can't be below {} seconds",
+                service_monitor_service::MIN_FREQUENCY_SECONDS
+            )));
+        }
+    }
+    let organization =
+        organization_service::set_monitor_quota(app_state.duckdb_pool.clone(), id, payload.min_monitor_frequency_seconds)
+            .await?;
+    Ok(Json(organization))
+}
@@ -0,0 +1,228 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    routing::{get, post, put},
+    Json, Router,
+};
+use chrono::{Duration, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::{info, warn};
+
+use crate::db::duckdb_service::provider_service;
+use crate::db::entities::{provider_account, vps_power_action};
+use crate::services::provider_power;
+use crate::web::models::provider_models::{
+    CreateProviderAccountRequest, PowerActionRequest, PowerActionResponse, UpdateProviderAccountRequest,
+    VpsProviderLink,
+};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+/// How long a requested power action can be confirmed.
+const CONFIRMATION_TTL_MINUTES: i64 = 5;
+const MAX_POWER_ACTIONS: u32 = 100;
+
+#[derive(Deserialize, Debug)]
+pub struct PowerActionsQuery {
+    pub limit: Option<u32>,
+}
+
+/// Provider credentials, mounted at `/api/settings/providers`.
+pub fn create_settings_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_provider_accounts).post(create_provider_account))
+        .route("/{id}", put(update_provider_account).delete(delete_provider_account))
+}
+
+/// Merged into the VPS router.
+pub fn vps_power_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route(
+            "/{vps_id}/provider",
+            get(get_vps_provider_link).put(set_vps_provider_link).delete(delete_vps_provider_link),
+        )
+        .route("/{vps_id}/power", post(power_action_handler))
+        .route("/{vps_id}/power/actions", get(get_power_actions_handler))
+}
+
+async fn check_vps_access(app_state: &AppState, user: &AuthenticatedUser, vps_id: i32) -> Result<(), AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+    Ok(())
+}
+
+async fn list_provider_accounts(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<provider_account::Model>>, AppError> {
+    let accounts =
+        provider_service::get_provider_accounts_for_user(app_state.duckdb_pool.clone(), authenticated_user.id)
+            .await?;
+    Ok(Json(accounts))
+}
+
+async fn create_provider_account(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateProviderAccountRequest>,
+) -> Result<(StatusCode, Json<provider_account::Model>), AppError> {
+    let account = provider_service::create_provider_account(
+        app_state.duckdb_pool.clone(),
+        app_state.encryption_service.clone(),
+        authenticated_user.id,
+        payload,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(account)))
+}
+
+async fn update_provider_account(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<UpdateProviderAccountRequest>,
+) -> Result<Json<provider_account::Model>, AppError> {
+    let account = provider_service::update_provider_account(
+        app_state.duckdb_pool.clone(),
+        app_state.encryption_service.clone(),
+        authenticated_user.id,
+        id,
+        payload,
+    )
+    .await?;
+    Ok(Json(account))
+}
+
+async fn delete_provider_account(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    provider_service::delete_provider_account(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_vps_provider_link(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+) -> Result<Json<VpsProviderLink>, AppError> {
+    check_vps_access(&app_state, &authenticated_user, vps_id).await?;
+    provider_service::get_vps_provider_link(app_state.duckdb_pool.clone(), vps_id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("VPS is not linked to a provider".to_string()))
+}
+
+async fn set_vps_provider_link(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Json(payload): Json<VpsProviderLink>,
+) -> Result<Json<VpsProviderLink>, AppError> {
+    check_vps_access(&app_state, &authenticated_user, vps_id).await?;
+    let link =
+        provider_service::set_vps_provider_link(app_state.duckdb_pool.clone(), authenticated_user.id, vps_id, payload)
+            .await?;
+    Ok(Json(link))
+}
+
+async fn delete_vps_provider_link(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    check_vps_access(&app_state, &authenticated_user, vps_id).await?;
+    provider_service::delete_vps_provider_link(app_state.duckdb_pool.clone(), vps_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Two-step power action: without a confirmation code the action is recorded as
+/// pending and a code is returned (202); repeating the request with that code
+/// calls the provider API.
+async fn power_action_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Json(payload): Json<PowerActionRequest>,
+) -> Result<(StatusCode, Json<PowerActionResponse>), AppError> {
+    let user_id = authenticated_user.id;
+    check_vps_access(&app_state, &authenticated_user, vps_id).await?;
+    let target =
+        provider_service::get_power_target(app_state.duckdb_pool.clone(), app_state.encryption_service.clone(), vps_id)
+            .await?
+            .ok_or_else(|| AppError::InvalidInput("VPS is not linked to a provider".to_string()))?;
+
+    let Some(code) = payload.confirmation_code else {
+        let agent_online = app_state.connected_agents.lock().await.find_by_vps_id(vps_id).is_some();
+        let (requested, code) = provider_service::request_power_action(
+            app_state.duckdb_pool.clone(),
+            user_id,
+            vps_id,
+            payload.action,
+            agent_online,
+            Utc::now() + Duration::minutes(CONFIRMATION_TTL_MINUTES),
+        )
+        .await?;
+        return Ok((
+            StatusCode::ACCEPTED,
+            Json(PowerActionResponse {
+                action: requested,
+                confirmation_code: Some(code),
+            }),
+        ));
+    };
+
+    let confirmed =
+        provider_service::confirm_power_action(app_state.duckdb_pool.clone(), user_id, vps_id, payload.action, &code)
+            .await?
+            .ok_or_else(|| AppError::Forbidden("Invalid or expired confirmation code".to_string()))?;
+    info!(
+        vps_id,
+        user_id,
+        action = payload.action.as_str(),
+        provider = target.provider.as_str(),
+        "Executing provider power action."
+    );
+    let result = provider_power::execute_power_action(
+        target.provider,
+        &target.token,
+        &target.provider_server_id,
+        payload.action,
+    )
+    .await;
+    let error = result.as_ref().err().map(|e| e.to_string());
+    let completed = provider_service::complete_power_action(app_state.duckdb_pool.clone(), confirmed.id, error).await?;
+    if let Err(e) = result {
+        warn!(vps_id, action_id = completed.id, "Provider power action failed: {}", e);
+        return Err(e);
+    }
+    Ok((
+        StatusCode::OK,
+        Json(PowerActionResponse {
+            action: completed,
+            confirmation_code: None,
+        }),
+    ))
+}
+
+async fn get_power_actions_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<PowerActionsQuery>,
+) -> Result<Json<Vec<vps_power_action::Model>>, AppError> {
+    check_vps_access(&app_state, &authenticated_user, vps_id).await?;
+    let actions = provider_service::get_power_actions_for_vps(
+        app_state.duckdb_pool.clone(),
+        vps_id,
+        query.limit.unwrap_or(MAX_POWER_ACTIONS).min(MAX_POWER_ACTIONS),
+    )
+    .await?;
+    Ok(Json(actions))
+}
@@ -0,0 +1,179 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    routing::{delete, get, post},
+    Json, Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::provisioning_service::{self, ProvisioningTokenRules};
+use crate::db::entities::provisioning_token;
+use crate::services::install_script::{enrollment_command, InstallOs};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateProvisioningTokenRequest {
+    pub name: String,
+    /// Group assigned to VPS registered with the token.
+    pub group: Option<String>,
+    /// Registered VPS are ephemeral: archived after staying offline and left
+    /// out of uptime figures. Defaults to `true`.
+    #[serde(default = "default_ephemeral")]
+    pub is_ephemeral: bool,
+    /// Tags attached to every registered VPS.
+    #[serde(default)]
+    pub tag_ids: Vec<i32>,
+    /// Total registrations allowed; unlimited when omitted.
+    pub max_uses: Option<i32>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+fn default_ephemeral() -> bool {
+    true
+}
+
+const DEFAULT_ENROLLMENT_HOURS: i64 = 24;
+const MAX_ENROLLMENT_HOURS: i64 = 24 * 30;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateEnrollmentTokenRequest {
+    /// Defaults to "Enrollment".
+    pub name: Option<String>,
+    pub group: Option<String>,
+    #[serde(default)]
+    pub tag_ids: Vec<i32>,
+    /// Defaults to 24.
+    pub expires_in_hours: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InstallCommands {
+    pub linux: String,
+    pub macos: String,
+    pub windows: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EnrollmentTokenResponse {
+    pub token: provisioning_token::Model,
+    /// One-liners that install the agent and enroll the machine.
+    pub install_commands: InstallCommands,
+}
+
+/// Mounted at `/api/provisioning-tokens`.
+pub fn create_provisioning_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_provisioning_tokens).post(create_provisioning_token))
+        .route("/enrollment", post(create_enrollment_token))
+        .route("/{id}", delete(delete_provisioning_token))
+}
+
+async fn list_provisioning_tokens(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<provisioning_token::Model>>, AppError> {
+    let tokens = provisioning_service::get_provisioning_tokens_for_user(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+    )
+    .await?;
+    Ok(Json(tokens))
+}
+
+async fn create_provisioning_token(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateProvisioningTokenRequest>,
+) -> Result<(StatusCode, Json<provisioning_token::Model>), AppError> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Name must not be empty".to_string()));
+    }
+    if matches!(payload.max_uses, Some(max_uses) if max_uses < 1) {
+        return Err(AppError::InvalidInput("maxUses must be at least 1".to_string()));
+    }
+    if matches!(payload.expires_at, Some(expires_at) if expires_at <= Utc::now()) {
+        return Err(AppError::InvalidInput("expiresAt must be in the future".to_string()));
+    }
+
+    let rules = ProvisioningTokenRules {
+        group: payload.group.as_deref().map(str::trim).filter(|g| !g.is_empty()),
+        is_ephemeral: payload.is_ephemeral,
+        tag_ids: &payload.tag_ids,
+        max_uses: payload.max_uses,
+        expires_at: payload.expires_at,
+    };
+    let token = provisioning_service::create_provisioning_token(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        name,
+        rules,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(token)))
+}
+
+/// Creates a single-use token for enrolling one machine with the install
+/// script, and the commands that run it. The VPS it registers is permanent.
+async fn create_enrollment_token(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateEnrollmentTokenRequest>,
+) -> Result<(StatusCode, Json<EnrollmentTokenResponse>), AppError> {
+    let hours = payload.expires_in_hours.unwrap_or(DEFAULT_ENROLLMENT_HOURS);
+    if !(1..=MAX_ENROLLMENT_HOURS).contains(&hours) {
+        return Err(AppError::InvalidInput(format!(
+            "expiresInHours must be between 1 and {MAX_ENROLLMENT_HOURS}"
+        )));
+    }
+    let name = payload
+        .name
+        .as_deref()
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+        .unwrap_or("Enrollment");
+    let rules = ProvisioningTokenRules {
+        group: payload.group.as_deref().map(str::trim).filter(|g| !g.is_empty()),
+        is_ephemeral: false,
+        tag_ids: &payload.tag_ids,
+        max_uses: Some(1),
+        expires_at: Some(Utc::now() + Duration::hours(hours)),
+    };
+    let token = provisioning_service::create_provisioning_token(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        name,
+        rules,
+    )
+    .await?;
+    let server_address = &app_state.config.frontend_url;
+    let install_commands = InstallCommands {
+        linux: enrollment_command(server_address, &token.token, InstallOs::Linux),
+        macos: enrollment_command(server_address, &token.token, InstallOs::Macos),
+        windows: enrollment_command(server_address, &token.token, InstallOs::Windows),
+    };
+    Ok((
+        StatusCode::CREATED,
+        Json(EnrollmentTokenResponse {
+            token,
+            install_commands,
+        }),
+    ))
+}
+
+async fn delete_provisioning_token(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    provisioning_service::delete_provisioning_token(app_state.duckdb_pool.clone(), authenticated_user.id, id)
+        .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,114 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    routing::{delete, get},
+    Json, Router,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::db::duckdb_service::public_api_key_service;
+use crate::db::duckdb_service::status_page_service::{
+    self, DisplayOptions, StatusPageMonitor, StatusPageServer,
+};
+use crate::db::entities::public_api_key;
+use crate::web::middleware::public_key::PublicKeyOwner;
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+/// Embedded views always show everything the status page data offers.
+const EMBED_OPTIONS: DisplayOptions = DisplayOptions {
+    show_uptime: true,
+    show_latency: true,
+};
+
+#[derive(Deserialize)]
+pub struct CreatePublicKeyRequest {
+    pub name: String,
+}
+
+/// Management routes, mounted at `/api/settings/public-keys`.
+pub fn create_settings_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_public_keys).post(create_public_key))
+        .route("/{id}", delete(delete_public_key))
+}
+
+/// Read-only data for public key holders, mounted at `/api/public` behind
+/// the public key middleware.
+pub fn create_public_data_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/servers", get(get_public_servers))
+        .route("/monitors", get(get_public_monitors))
+}
+
+async fn list_public_keys(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<public_api_key::Model>>, AppError> {
+    let keys =
+        public_api_key_service::get_public_api_keys_for_user(app_state.duckdb_pool.clone(), authenticated_user.id)
+            .await?;
+    Ok(Json(keys))
+}
+
+async fn create_public_key(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreatePublicKeyRequest>,
+) -> Result<(StatusCode, Json<public_api_key::Model>), AppError> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Name must not be empty".to_string()));
+    }
+    let key =
+        public_api_key_service::create_public_api_key(app_state.duckdb_pool.clone(), authenticated_user.id, name)
+            .await?;
+    Ok((StatusCode::CREATED, Json(key)))
+}
+
+async fn delete_public_key(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    public_api_key_service::delete_public_api_key(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Status and uptime of every server of the key's owner.
+async fn get_public_servers(
+    Extension(owner): Extension<PublicKeyOwner>,
+    State(app_state): State<Arc<AppState>>,
+) -> Json<Vec<StatusPageServer>> {
+    debug!(key_id = owner.key_id, "Serving public server data.");
+    let cache_guard = app_state.live_server_data_cache.lock().await;
+    let mut servers: Vec<StatusPageServer> = cache_guard
+        .values()
+        .filter(|s| s.basic_info.user_id == owner.user_id)
+        .map(|s| status_page_service::server_summary(s, EMBED_OPTIONS))
+        .collect();
+    servers.sort_by_key(|s| s.id);
+    Json(servers)
+}
+
+/// Monitors the owner shows on a published status page; others stay private.
+async fn get_public_monitors(
+    Extension(owner): Extension<PublicKeyOwner>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<StatusPageMonitor>>, AppError> {
+    debug!(key_id = owner.key_id, "Serving public monitor data.");
+    let monitor_ids =
+        status_page_service::get_published_monitor_ids(app_state.duckdb_pool.clone(), owner.user_id).await?;
+    let monitors = status_page_service::monitor_summaries(
+        app_state.duckdb_pool.clone(),
+        owner.user_id,
+        &monitor_ids,
+        EMBED_OPTIONS,
+        Utc::now(),
+    )
+    .await?;
+    Ok(Json(monitors))
+}
@@ -0,0 +1,62 @@
+use axum::{
+    extract::{Extension, State},
+    routing::get,
+    Json, Router,
+};
+use chrono::Utc;
+use serde::Deserialize;
+use std::sync::Arc;
+use tracing::warn;
+
+use crate::services::read_only::ReadOnlyState;
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+/// The current mode for the dashboard banner, mounted at `/api/read-only`.
+pub fn create_status_router() -> Router<Arc<AppState>> {
+    Router::new().route("/", get(get_read_only_mode))
+}
+
+/// The toggle, mounted at `/api/admin/read-only` behind the admin role.
+pub fn create_admin_router() -> Router<Arc<AppState>> {
+    Router::new().route("/", get(get_read_only_mode).put(set_read_only_mode))
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct SetReadOnlyModeRequest {
+    pub enabled: bool,
+    /// Banner text, e.g. "Database migration in progress". Required when enabling.
+    pub message: Option<String>,
+}
+
+async fn get_read_only_mode(State(app_state): State<Arc<AppState>>) -> Json<Option<ReadOnlyState>> {
+    Json(app_state.read_only.current())
+}
+
+async fn set_read_only_mode(
+    State(app_state): State<Arc<AppState>>,
+    Extension(user): Extension<AuthenticatedUser>,
+    Json(payload): Json<SetReadOnlyModeRequest>,
+) -> Result<Json<Option<ReadOnlyState>>, AppError> {
+    let state = if payload.enabled {
+        let message = payload.message.map(|m| m.trim().to_string()).unwrap_or_default();
+        if message.is_empty() {
+            return Err(AppError::InvalidInput("A banner message is required to enable read-only mode".to_string()));
+        }
+        Some(ReadOnlyState {
+            message,
+            enabled_by: user.username.clone(),
+            enabled_at: Utc::now(),
+        })
+    } else {
+        None
+    };
+
+    app_state.read_only.set(app_state.duckdb_pool.clone(), state.clone()).await?;
+    match &state {
+        Some(state) => warn!(user = %user.username, message = %state.message, "Read-only mode enabled."),
+        None => warn!(user = %user.username, "Read-only mode disabled."),
+    }
+    Ok(Json(state))
+}
@@ -0,0 +1,122 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use chrono::Utc;
+use std::sync::Arc;
+
+use crate::db::duckdb_service::agent_release_service;
+use crate::db::duckdb_service::agent_version_service::{self, AgentVersionReport};
+use crate::db::duckdb_service::uptime_report_service;
+use crate::web::models::report_models::{
+    GenerateUptimeReportRequest, UptimeReport, UptimeReportListQuery, UptimeReportSchedule,
+};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+/// Mounted at `/api/reports`.
+pub fn create_report_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/agent-versions", get(get_agent_version_report))
+        .route("/uptime", get(list_uptime_reports).post(generate_uptime_report))
+        .route("/uptime/schedule", get(get_uptime_report_schedule).put(update_uptime_report_schedule))
+        .route("/uptime/{id}", get(get_uptime_report).delete(delete_uptime_report))
+}
+
+/// Agent version distribution across the user's fleet, relative to the
+/// latest agent release.
+async fn get_agent_version_report(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<AgentVersionReport>, AppError> {
+    let latest_version = agent_release_service::get_latest_agent_version(app_state.duckdb_pool.clone()).await?;
+    let report = agent_version_service::get_agent_version_report(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        &latest_version,
+    )
+    .await?;
+    Ok(Json(report))
+}
+
+/// Stored reports come from the main pool, so freshly generated ones show up
+/// and ownership is never checked against a stale replica.
+async fn list_uptime_reports(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<UptimeReportListQuery>,
+) -> Result<Json<Vec<UptimeReport>>, AppError> {
+    let reports =
+        uptime_report_service::list_uptime_reports(app_state.duckdb_pool.clone(), authenticated_user.id, query.period)
+            .await?;
+    Ok(Json(reports))
+}
+
+/// Generates (or regenerates) the report of a period on demand. Reports are
+/// stored, so this uses the main pool rather than the read-only replica.
+async fn generate_uptime_report(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<GenerateUptimeReportRequest>,
+) -> Result<(StatusCode, Json<UptimeReport>), AppError> {
+    let now = Utc::now();
+    let period_of = payload
+        .period_of
+        .unwrap_or_else(|| payload.period.last_completed(now).0);
+    if period_of > now {
+        return Err(AppError::InvalidInput("periodOf must not be in the future".to_string()));
+    }
+    let report = uptime_report_service::generate_uptime_report(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        payload.period,
+        period_of,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(report)))
+}
+
+async fn get_uptime_report(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<UptimeReport>, AppError> {
+    uptime_report_service::get_uptime_report(app_state.duckdb_pool.clone(), authenticated_user.id, id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("Uptime report not found".to_string()))
+}
+
+async fn delete_uptime_report(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    uptime_report_service::delete_uptime_report(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_uptime_report_schedule(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<UptimeReportSchedule>, AppError> {
+    let schedule =
+        uptime_report_service::get_report_schedule(app_state.duckdb_pool.clone(), authenticated_user.id).await?;
+    Ok(Json(schedule))
+}
+
+async fn update_uptime_report_schedule(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<UptimeReportSchedule>,
+) -> Result<Json<UptimeReportSchedule>, AppError> {
+    let schedule = uptime_report_service::update_report_schedule(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        payload,
+    )
+    .await?;
+    Ok(Json(schedule))
+}
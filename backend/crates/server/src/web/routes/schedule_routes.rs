@@ -0,0 +1,97 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    routing::get,
+    Json, Router,
+};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::scheduled_task_service;
+use crate::db::entities::{scheduled_task, scheduled_task_run};
+use crate::web::models::schedule_models::{
+    CreateScheduledTaskRequest, ScheduledTaskRunsQuery, UpdateScheduledTaskRequest,
+};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+pub fn create_schedule_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_schedules).post(create_schedule))
+        .route("/{id}", get(get_schedule).put(update_schedule).delete(delete_schedule))
+        .route("/{id}/runs", get(list_schedule_runs))
+}
+
+async fn list_schedules(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<scheduled_task::Model>>, AppError> {
+    let tasks = scheduled_task_service::get_scheduled_tasks_for_user(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+    )
+    .await?;
+    Ok(Json(tasks))
+}
+
+async fn get_schedule(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<scheduled_task::Model>, AppError> {
+    let task =
+        scheduled_task_service::get_scheduled_task(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    Ok(Json(task))
+}
+
+async fn create_schedule(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateScheduledTaskRequest>,
+) -> Result<Json<scheduled_task::Model>, AppError> {
+    let task = scheduled_task_service::create_scheduled_task(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        payload,
+    )
+    .await?;
+    Ok(Json(task))
+}
+
+async fn update_schedule(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<UpdateScheduledTaskRequest>,
+) -> Result<Json<scheduled_task::Model>, AppError> {
+    let task = scheduled_task_service::update_scheduled_task(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        id,
+        payload,
+    )
+    .await?;
+    Ok(Json(task))
+}
+
+async fn delete_schedule(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<(), AppError> {
+    scheduled_task_service::delete_scheduled_task(app_state.duckdb_pool.clone(), authenticated_user.id, id).await
+}
+
+async fn list_schedule_runs(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Query(query): Query<ScheduledTaskRunsQuery>,
+) -> Result<Json<Vec<scheduled_task_run::Model>>, AppError> {
+    let runs = scheduled_task_service::get_runs_for_task(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        id,
+        query.limit,
+    )
+    .await?;
+    Ok(Json(runs))
+}
@@ -1,14 +1,19 @@
-use crate::db::duckdb_service::service_monitor_service;
+use crate::db::duckdb_service::service_monitor_service::{self, HeatmapScope};
+use crate::db::duckdb_service::service_monitor_slo_service::{self, SloDetails};
 use crate::web::config_routes::push_config_to_vps;
+use crate::db::entities::service_monitor_maintenance_window;
 use crate::web::models::service_monitor_models::{
-    CreateMonitor, ServiceMonitorResultDetails, UpdateMonitor,
+    CreateMaintenanceWindow, CreateMonitor, CreateSlo, HeatmapQuery, HeatmapResponse,
+    RegionComparisonResponse, RegionLatencySeries, ServiceMonitorResultDetails, UpdateMonitor,
+    UpdateSlo,
 };
+use crate::web::models::AuthenticatedUser;
 use crate::web::routes::vps_routes::{parse_interval_to_seconds, MonitorTimeseriesQuery};
 use crate::web::{AppError, AppState};
 use axum::{
-    extract::{Path, Query, State},
+    extract::{Extension, Path, Query, State},
     http::StatusCode,
-    routing::get,
+    routing::{delete, get, put},
     Json, Router,
 };
 use std::collections::HashMap;
@@ -23,30 +28,261 @@ pub fn create_service_monitor_router() -> Router<Arc<AppState>> {
             get(get_monitor).put(update_monitor).delete(delete_monitor),
         )
         .route("/{id}/results", get(get_monitor_results))
+        .route("/{id}/heatmap", get(get_monitor_heatmap))
+        .route("/{id}/regions", get(get_monitor_region_comparison))
+        .route(
+            "/{id}/maintenance",
+            get(list_maintenance_windows).post(create_maintenance_window),
+        )
+        .route(
+            "/{id}/maintenance/{window_id}",
+            delete(delete_maintenance_window),
+        )
+        .route("/{id}/slos", get(list_slos).post(create_slo))
+        .route("/{id}/slos/{slo_id}", put(update_slo).delete(delete_slo))
+}
+
+async fn ensure_monitor_access(
+    app_state: &AppState,
+    monitor_id: i32,
+    user: &AuthenticatedUser,
+) -> Result<(), AppError> {
+    let monitor = service_monitor_service::get_monitor_details_by_id(app_state.duckdb_pool.clone(), monitor_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Monitor not found".to_string()))?;
+    if !user.can_access(monitor.organization_id) {
+        return Err(AppError::NotFound("Monitor not found".to_string()));
+    }
+    Ok(())
+}
+
+const DEFAULT_HEATMAP_WEEKS: u32 = 4;
+const MAX_HEATMAP_WEEKS: u32 = 26;
+
+/// Builds an hour-of-week heatmap over the trailing weeks requested.
+pub async fn build_heatmap(
+    app_state: &AppState,
+    scope: HeatmapScope,
+    query: HeatmapQuery,
+) -> Result<HeatmapResponse, AppError> {
+    let weeks = query.weeks.unwrap_or(DEFAULT_HEATMAP_WEEKS).clamp(1, MAX_HEATMAP_WEEKS);
+    let utc_offset_minutes = query.utc_offset_minutes.unwrap_or(0);
+    if !(-14 * 60..=14 * 60).contains(&utc_offset_minutes) {
+        return Err(AppError::InvalidInput("utcOffsetMinutes is out of range".to_string()));
+    }
+    let end_time = chrono::Utc::now();
+    let start_time = end_time - chrono::Duration::weeks(weeks as i64);
+    let cells = service_monitor_service::get_availability_heatmap(
+        app_state.duckdb_pool.clone(),
+        scope,
+        start_time,
+        end_time,
+        utc_offset_minutes,
+    )
+    .await?;
+    Ok(HeatmapResponse {
+        start_time,
+        end_time,
+        utc_offset_minutes,
+        cells,
+    })
+}
+
+async fn get_monitor_heatmap(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Query(query): Query<HeatmapQuery>,
+) -> Result<Json<HeatmapResponse>, AppError> {
+    ensure_monitor_access(&app_state, id, &authenticated_user).await?;
+    let heatmap = build_heatmap(&app_state, HeatmapScope::Monitor(id), query).await?;
+    Ok(Json(heatmap))
+}
+
+/// Buckets per series when the caller doesn't pick an interval.
+const DEFAULT_REGION_BUCKETS: i64 = 120;
+/// An agent is reported as the outlier only if it is at least this far off the
+/// others and clearly further off than the next agent.
+const OUTLIER_MIN_DIVERGENCE: f64 = 0.5;
+const OUTLIER_MIN_RATIO: f64 = 2.0;
+
+/// Latency per agent side by side, with a score of how far the agents disagree.
+async fn get_monitor_region_comparison(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Query(query): Query<MonitorTimeseriesQuery>,
+) -> Result<Json<RegionComparisonResponse>, AppError> {
+    ensure_monitor_access(&app_state, id, &authenticated_user).await?;
+    if query.end_time <= query.start_time {
+        return Err(AppError::InvalidInput("endTime must be after startTime".to_string()));
+    }
+    let interval_seconds = parse_interval_to_seconds(query.interval).unwrap_or_else(|| {
+        ((query.end_time - query.start_time).num_seconds() / DEFAULT_REGION_BUCKETS).max(60)
+    });
+
+    let series = service_monitor_service::get_region_latency_series(
+        app_state.duckdb_pool.clone(),
+        id,
+        query.start_time,
+        query.end_time,
+        interval_seconds,
+    )
+    .await?;
+    let divergence = service_monitor_service::compute_region_divergence(&series);
+    let agent_ids: Vec<i32> = series.keys().copied().collect();
+    let agent_names: HashMap<i32, String> =
+        app_state.storage.get_vps_by_ids(agent_ids)
+            .await?
+            .into_iter()
+            .map(|a| (a.id, a.name))
+            .collect();
+
+    let mut ranked: Vec<(i32, f64)> = divergence.iter().map(|(id, d)| (*id, *d)).collect();
+    ranked.sort_by(|a, b| b.1.total_cmp(&a.1));
+    let divergence_score = ranked.first().map(|(_, d)| *d);
+    // With only two agents there is no majority to tell which one is off.
+    let outlier_agent_id = match ranked.as_slice() {
+        [(top_id, top), (_, second), _, ..]
+            if *top >= OUTLIER_MIN_DIVERGENCE && *top >= second * OUTLIER_MIN_RATIO =>
+        {
+            Some(*top_id)
+        }
+        _ => None,
+    };
+
+    let agents = series
+        .into_iter()
+        .map(|(agent_id, points)| RegionLatencySeries {
+            agent_id,
+            agent_name: agent_names
+                .get(&agent_id)
+                .cloned()
+                .unwrap_or_else(|| format!("Agent {agent_id}")),
+            median_latency_ms: service_monitor_service::median_latency(&points),
+            divergence: divergence.get(&agent_id).copied(),
+            points,
+        })
+        .collect();
+
+    Ok(Json(RegionComparisonResponse {
+        monitor_id: id,
+        interval_seconds,
+        divergence_score,
+        outlier_agent_id,
+        agents,
+    }))
+}
+
+async fn list_maintenance_windows(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<service_monitor_maintenance_window::Model>>, AppError> {
+    ensure_monitor_access(&app_state, id, &authenticated_user).await?;
+    let windows = service_monitor_service::get_maintenance_windows(app_state.duckdb_pool.clone(), id).await?;
+    Ok(Json(windows))
+}
+
+async fn create_maintenance_window(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreateMaintenanceWindow>,
+) -> Result<(StatusCode, Json<service_monitor_maintenance_window::Model>), AppError> {
+    ensure_monitor_access(&app_state, id, &authenticated_user).await?;
+    let window =
+        service_monitor_service::create_maintenance_window(app_state.duckdb_pool.clone(), id, payload).await?;
+    Ok((StatusCode::CREATED, Json(window)))
+}
+
+async fn delete_maintenance_window(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path((id, window_id)): Path<(i32, i32)>,
+) -> Result<StatusCode, AppError> {
+    ensure_monitor_access(&app_state, id, &authenticated_user).await?;
+    service_monitor_service::delete_maintenance_window(app_state.duckdb_pool.clone(), id, window_id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_slos(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<Vec<SloDetails>>, AppError> {
+    ensure_monitor_access(&app_state, id, &authenticated_user).await?;
+    let slos = service_monitor_slo_service::get_slos_for_monitor(app_state.duckdb_pool.clone(), id).await?;
+    Ok(Json(slos))
+}
+
+async fn create_slo(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<CreateSlo>,
+) -> Result<(StatusCode, Json<SloDetails>), AppError> {
+    ensure_monitor_access(&app_state, id, &authenticated_user).await?;
+    let slo = service_monitor_slo_service::create_slo(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        id,
+        payload,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(slo)))
+}
+
+async fn update_slo(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path((id, slo_id)): Path<(i32, i32)>,
+    Json(payload): Json<UpdateSlo>,
+) -> Result<Json<SloDetails>, AppError> {
+    ensure_monitor_access(&app_state, id, &authenticated_user).await?;
+    let slo = service_monitor_slo_service::update_slo(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        id,
+        slo_id,
+        payload,
+    )
+    .await?;
+    Ok(Json(slo))
+}
+
+async fn delete_slo(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path((id, slo_id)): Path<(i32, i32)>,
+) -> Result<StatusCode, AppError> {
+    ensure_monitor_access(&app_state, id, &authenticated_user).await?;
+    service_monitor_slo_service::delete_slo(app_state.duckdb_pool.clone(), id, slo_id).await?;
+    Ok(StatusCode::NO_CONTENT)
 }
 
 #[axum::debug_handler]
 async fn list_monitors(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
     State(app_state): State<Arc<AppState>>,
-    // TODO: Add user extraction
 ) -> Result<Json<Vec<crate::web::models::service_monitor_models::ServiceMonitorDetails>>, AppError>
 {
-    let user_id = 1; // Hardcoded user_id
-    let monitors =
-        service_monitor_service::get_monitors_with_details_by_user_id(app_state.duckdb_pool.clone(), user_id)
-            .await?;
+    let monitors = service_monitor_service::get_monitors_with_details_by_user_id(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+    )
+    .await?;
     Ok(Json(monitors))
 }
 
 #[axum::debug_handler]
 async fn create_monitor(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
     State(app_state): State<Arc<AppState>>,
-    // TODO: Add user extraction
     Json(payload): Json<CreateMonitor>,
 ) -> Result<(StatusCode, Json<serde_json::Value>), AppError> {
-    let user_id = 1; // Hardcoded user_id
     let created_monitor =
-        service_monitor_service::create_monitor(app_state.duckdb_pool.clone(), user_id, payload).await?;
+        service_monitor_service::create_monitor(app_state.duckdb_pool.clone(), authenticated_user.id, payload).await?;
 
     let affected_vps_ids =
         service_monitor_service::get_vps_ids_for_monitor(app_state.duckdb_pool.clone(), created_monitor.id)
@@ -72,26 +308,27 @@ async fn create_monitor(
 
 #[axum::debug_handler]
 async fn get_monitor(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
     State(app_state): State<Arc<AppState>>,
     Path(id): Path<i32>,
-    // TODO: Add user extraction and authorization
 ) -> Result<Json<crate::web::models::service_monitor_models::ServiceMonitorDetails>, AppError> {
     let monitor = service_monitor_service::get_monitor_details_by_id(app_state.duckdb_pool.clone(), id)
         .await?
+        .filter(|monitor| authenticated_user.can_access(monitor.organization_id))
         .ok_or_else(|| AppError::NotFound("Monitor not found".to_string()))?;
     Ok(Json(monitor))
 }
 
 #[axum::debug_handler]
 async fn update_monitor(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
     State(app_state): State<Arc<AppState>>,
     Path(id): Path<i32>,
-    // TODO: Add user extraction
     Json(payload): Json<UpdateMonitor>,
 ) -> Result<Json<crate::web::models::service_monitor_models::ServiceMonitorDetails>, AppError> {
-    let user_id = 1; // Hardcoded user_id
     let (updated_details, affected_vps_ids) =
-        service_monitor_service::update_monitor(app_state.duckdb_pool.clone(), id, user_id, payload).await?;
+        service_monitor_service::update_monitor(app_state.duckdb_pool.clone(), id, authenticated_user.id, payload)
+            .await?;
 
     for vps_id in affected_vps_ids {
         if let Err(e) = push_config_to_vps(app_state.clone(), vps_id).await {
@@ -104,17 +341,16 @@ async fn update_monitor(
 
 #[axum::debug_handler]
 async fn delete_monitor(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
     State(app_state): State<Arc<AppState>>,
     Path(id): Path<i32>,
-    // TODO: Add user extraction
 ) -> Result<StatusCode, AppError> {
-    let user_id = 1; // Hardcoded user_id
 
     let affected_vps_ids =
         service_monitor_service::get_vps_ids_for_monitor(app_state.duckdb_pool.clone(), id).await?;
 
     let delete_result =
-        service_monitor_service::delete_monitor(app_state.duckdb_pool.clone(), id, user_id).await?;
+        service_monitor_service::delete_monitor(app_state.duckdb_pool.clone(), id, authenticated_user.id).await?;
 
     if delete_result == 0 {
         return Err(AppError::NotFound(
@@ -145,14 +381,10 @@ async fn get_monitor_results(
 
     let interval_seconds = parse_interval_to_seconds(query.interval);
 
-    let points = service_monitor_service::get_monitor_results_by_id(
-        app_state.duckdb_pool.clone(),
-        id,
-        query.start_time,
-        query.end_time,
-        interval_seconds,
-    )
-    .await?;
+    let points = app_state
+        .storage
+        .get_monitor_results_by_id(id, query.start_time, query.end_time, interval_seconds)
+        .await?;
 
     if points.is_empty() {
         return Ok(Json(Vec::new()));
@@ -165,7 +397,7 @@ async fn get_monitor_results(
     // 2. Fetch all agent (VPS) models for these IDs
     // This part needs to be refactored to use the duckdb_service
     // For now, we'll have to query the vps service from duckdb
-    let agents = crate::db::duckdb_service::vps_service::get_vps_by_ids(app_state.duckdb_pool.clone(), agent_ids).await?;
+    let agents = app_state.storage.get_vps_by_ids(agent_ids).await?;
     let agent_name_map: HashMap<i32, String> =
         agents.into_iter().map(|a| (a.id, a.name)).collect();
 
@@ -187,6 +419,8 @@ async fn get_monitor_results(
                 is_up: point.is_up.is_some_and(|v| v > 0.5),
                 latency_ms: point.latency_ms.map(|f| f as i32),
                 details: point.details,
+                in_maintenance: point.in_maintenance.unwrap_or(false),
+                packet_loss_percent: point.packet_loss_percent,
             }
         })
         .collect();
@@ -0,0 +1,209 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    routing::{delete, get},
+    Json, Router,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::{client_service, share_link_service};
+use crate::db::entities::share_link;
+use crate::services::share_token;
+use crate::web::models::client_models::{ClientReportQuery, ClientStatement};
+use crate::web::models::websocket_models::ServerWithDetails;
+use crate::web::models::AuthenticatedUser;
+use crate::web::routes::client_routes;
+use crate::web::{AppError, AppState};
+
+const MAX_SHARE_LIFETIME_DAYS: i64 = 365;
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateShareLinkRequest {
+    pub name: String,
+    /// Share a single VPS. When omitted the whole dashboard is shared.
+    pub vps_id: Option<i32>,
+    /// Share the VPS and statement of one reseller client instead.
+    pub client_id: Option<i32>,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLinkResponse {
+    #[serde(flatten)]
+    pub link: share_link::Model,
+    pub token: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SharedView {
+    pub name: String,
+    pub expires_at: DateTime<Utc>,
+    pub servers: Vec<ServerWithDetails>,
+}
+
+/// Management routes, mounted at `/api/user/shares`.
+pub fn create_user_share_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_share_links).post(create_share_link))
+        .route("/{id}", delete(delete_share_link))
+}
+
+/// Unauthenticated read-only access, mounted at `/api/share`.
+pub fn create_public_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/{token}", get(get_shared_view))
+        .route("/{token}/statement", get(get_shared_statement))
+}
+
+/// Resolves a share token to its link, rejecting expired or revoked links.
+pub async fn resolve_share_token(
+    app_state: &AppState,
+    token: &str,
+) -> Result<share_link::Model, AppError> {
+    let id = share_token::verify_share_token(token, &app_state.config.jwt_secret)?;
+    share_link_service::get_active_share_link(app_state.duckdb_pool.clone(), id)
+        .await?
+        .ok_or_else(|| AppError::Unauthorized("Invalid or expired share link".to_string()))
+}
+
+/// Whether `server` still belongs to the organization the link was created in.
+fn in_link_organization(link: &share_link::Model, server: &ServerWithDetails) -> bool {
+    link.organization_id.is_some() && server.basic_info.organization_id == link.organization_id
+}
+
+/// Whether `server` is visible through `link`.
+pub fn share_includes(link: &share_link::Model, server: &ServerWithDetails) -> bool {
+    match (link.vps_id, link.client_id) {
+        (Some(vps_id), _) => server.basic_info.id == vps_id && in_link_organization(link, server),
+        (None, Some(client_id)) => server.basic_info.client_id == Some(client_id),
+        (None, None) => in_link_organization(link, server),
+    }
+}
+
+/// The desensitized servers visible through `link`, taken from the live cache.
+pub async fn shared_servers(app_state: &AppState, link: &share_link::Model) -> Vec<ServerWithDetails> {
+    let cache_guard = app_state.live_server_data_cache.lock().await;
+    let mut servers: Vec<ServerWithDetails> = cache_guard
+        .values()
+        .filter(|s| share_includes(link, s))
+        .map(|s| s.desensitize())
+        .collect();
+    servers.sort_by_key(|s| s.basic_info.id);
+    servers
+}
+
+fn to_response(link: share_link::Model, jwt_secret: &str) -> Result<ShareLinkResponse, AppError> {
+    let token = share_token::issue_share_token(&link, jwt_secret)?;
+    Ok(ShareLinkResponse { link, token })
+}
+
+async fn list_share_links(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<ShareLinkResponse>>, AppError> {
+    let links =
+        share_link_service::get_share_links_for_user(app_state.duckdb_pool.clone(), authenticated_user.id)
+            .await?;
+    let responses = links
+        .into_iter()
+        .map(|link| to_response(link, &app_state.config.jwt_secret))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Json(responses))
+}
+
+async fn create_share_link(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateShareLinkRequest>,
+) -> Result<(StatusCode, Json<ShareLinkResponse>), AppError> {
+    let name = payload.name.trim();
+    if name.is_empty() {
+        return Err(AppError::InvalidInput("Name must not be empty".to_string()));
+    }
+    let now = Utc::now();
+    if payload.expires_at <= now {
+        return Err(AppError::InvalidInput("expiresAt must be in the future".to_string()));
+    }
+    if payload.expires_at > now + Duration::days(MAX_SHARE_LIFETIME_DAYS) {
+        return Err(AppError::InvalidInput(format!(
+            "Share links can be valid for at most {MAX_SHARE_LIFETIME_DAYS} days"
+        )));
+    }
+    if payload.vps_id.is_some() && payload.client_id.is_some() {
+        return Err(AppError::InvalidInput("Share either a VPS or a client, not both".to_string()));
+    }
+    if let Some(client_id) = payload.client_id {
+        client_service::get_client(app_state.duckdb_pool.clone(), authenticated_user.id, client_id).await?;
+    }
+    if let Some(vps_id) = payload.vps_id {
+        let vps = app_state.storage.get_vps_by_id(vps_id).await?;
+        if !matches!(vps, Some(v) if authenticated_user.can_access(v.organization_id)) {
+            return Err(AppError::NotFound("VPS not found".to_string()));
+        }
+    }
+
+    let link = share_link_service::create_share_link(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        authenticated_user.organization_id,
+        name,
+        payload.vps_id,
+        payload.client_id,
+        payload.expires_at,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(to_response(link, &app_state.config.jwt_secret)?)))
+}
+
+async fn delete_share_link(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    share_link_service::delete_share_link(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_shared_view(
+    State(app_state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+) -> Result<Json<SharedView>, AppError> {
+    let link = resolve_share_token(&app_state, &token).await?;
+    let servers = shared_servers(&app_state, &link).await;
+    Ok(Json(SharedView {
+        name: link.name,
+        expires_at: link.expires_at,
+        servers,
+    }))
+}
+
+/// The client's statement for a client share link: billed amounts and uptime,
+/// without the provider cost or markup.
+async fn get_shared_statement(
+    State(app_state): State<Arc<AppState>>,
+    Path(token): Path<String>,
+    Query(query): Query<ClientReportQuery>,
+) -> Result<Json<ClientStatement>, AppError> {
+    let link = resolve_share_token(&app_state, &token).await?;
+    let client_id = link
+        .client_id
+        .ok_or_else(|| AppError::NotFound("This share link is not for a client".to_string()))?;
+    let client = client_service::get_client_by_id(app_state.duckdb_pool.clone(), client_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Client not found".to_string()))?;
+    let (start, end) = client_routes::report_range(&query)?;
+    let report = client_service::build_client_report(
+        app_state.duckdb_pool.clone(),
+        app_state.reporting_pool.clone(),
+        client,
+        start,
+        end,
+    )
+    .await?;
+    Ok(Json(report.into()))
+}
@@ -0,0 +1,118 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    routing::get,
+    Json, Router,
+};
+use chrono::Utc;
+use std::sync::Arc;
+
+use crate::db::duckdb_service::status_page_service::{self, StatusPageInput, StatusPageView};
+use crate::db::entities::status_page;
+use crate::web::routes::branding_routes::load_branding;
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+/// Management routes, mounted at `/api/status-pages`.
+pub fn create_status_page_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_status_pages).post(create_status_page))
+        .route(
+            "/{id}",
+            get(get_status_page).put(update_status_page).delete(delete_status_page),
+        )
+}
+
+/// Unauthenticated page views, mounted at `/api/public`.
+pub fn create_public_router() -> Router<Arc<AppState>> {
+    Router::new().route("/status/{slug}", get(get_public_status_page))
+}
+
+/// Builds the public view of a published page from the live cache and
+/// stored monitor results.
+pub async fn status_page_view(
+    app_state: &AppState,
+    page: &status_page::Model,
+) -> Result<StatusPageView, AppError> {
+    let servers: Vec<_> = {
+        let cache_guard = app_state.live_server_data_cache.lock().await;
+        cache_guard
+            .values()
+            .filter(|s| page.vps_ids.contains(&s.basic_info.id))
+            .cloned()
+            .collect()
+    };
+    let branding = load_branding(app_state.duckdb_pool.clone()).await?;
+    status_page_service::build_status_page_view(app_state.duckdb_pool.clone(), page, &servers, branding.into(), Utc::now())
+        .await
+}
+
+async fn list_status_pages(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<status_page::Model>>, AppError> {
+    let pages =
+        status_page_service::get_status_pages_for_user(app_state.duckdb_pool.clone(), authenticated_user.id)
+            .await?;
+    Ok(Json(pages))
+}
+
+async fn create_status_page(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<StatusPageInput>,
+) -> Result<(StatusCode, Json<status_page::Model>), AppError> {
+    let page = status_page_service::create_status_page(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        &payload,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(page)))
+}
+
+async fn get_status_page(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<Json<status_page::Model>, AppError> {
+    status_page_service::get_status_page(app_state.duckdb_pool.clone(), authenticated_user.id, id)
+        .await?
+        .map(Json)
+        .ok_or_else(|| AppError::NotFound("Status page not found".to_string()))
+}
+
+async fn update_status_page(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<StatusPageInput>,
+) -> Result<Json<status_page::Model>, AppError> {
+    let page = status_page_service::update_status_page(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        id,
+        &payload,
+    )
+    .await?;
+    Ok(Json(page))
+}
+
+async fn delete_status_page(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    status_page_service::delete_status_page(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_public_status_page(
+    State(app_state): State<Arc<AppState>>,
+    Path(slug): Path<String>,
+) -> Result<Json<StatusPageView>, AppError> {
+    let page = status_page_service::get_published_status_page(app_state.duckdb_pool.clone(), &slug)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Status page not found".to_string()))?;
+    Ok(Json(status_page_view(&app_state, &page).await?))
+}
@@ -0,0 +1,188 @@
+use axum::{
+    extract::{
+        ws::{Message, Utf8Bytes, WebSocket, WebSocketUpgrade},
+        Path, Query, State,
+    },
+    response::IntoResponse,
+    routing::get,
+    Router,
+};
+use axum_extra::extract::cookie::CookieJar;
+use futures_util::{SinkExt, StreamExt};
+use nodenexus_common::agent_service::{
+    message_to_agent, pty_data_to_agent::ControlEvent, MessageToAgent, PtyDataToAgent, PtyResize,
+    PtyStartCommand,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::server::agent_state::AgentSender;
+use crate::web::handlers::websocket_handler::authenticate_ws_connection;
+use crate::web::models::Role;
+use crate::web::{AppError, AppState};
+
+const DEFAULT_ROWS: u32 = 24;
+const DEFAULT_COLS: u32 = 80;
+
+#[derive(Deserialize, Debug)]
+pub struct TerminalQuery {
+    token: Option<String>,
+    rows: Option<u32>,
+    cols: Option<u32>,
+}
+
+/// Text frames from the browser. Binary frames are raw terminal input.
+#[derive(Deserialize, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum TerminalClientMessage {
+    Input { data: String },
+    Resize { rows: u32, cols: u32 },
+}
+
+/// Text frames to the browser. Terminal output is sent as binary frames.
+#[derive(Serialize, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+enum TerminalServerMessage {
+    Error { message: String },
+    Closed { message: String },
+}
+
+pub fn create_terminal_router() -> Router<Arc<AppState>> {
+    Router::new().route("/ws/terminal/{vps_id}", get(terminal_ws_handler))
+}
+
+async fn terminal_ws_handler(
+    ws: WebSocketUpgrade,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<TerminalQuery>,
+    jar: CookieJar,
+) -> Result<impl IntoResponse, AppError> {
+    let token = jar
+        .get("token")
+        .map(|c| c.value().to_string())
+        .or(query.token.clone());
+    let user = authenticate_ws_connection(app_state.clone(), token).await?;
+    if user.role < Role::Operator {
+        return Err(AppError::Forbidden("This action requires the operator role".to_string()));
+    }
+    app_state.read_only.ensure_writable()?;
+
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+
+    let agent_sender = app_state
+        .connected_agents
+        .lock()
+        .await
+        .find_by_vps_id(vps_id)
+        .map(|state| state.sender)
+        .ok_or_else(|| AppError::Conflict("Agent is not connected".to_string()))?;
+
+    info!(user_id = user.id, vps_id, "Opening terminal session.");
+    let size = PtyResize {
+        rows: query.rows.filter(|r| *r > 0).unwrap_or(DEFAULT_ROWS),
+        cols: query.cols.filter(|c| *c > 0).unwrap_or(DEFAULT_COLS),
+    };
+    Ok(ws.on_upgrade(move |socket| handle_terminal_socket(socket, app_state, vps_id, agent_sender, size)))
+}
+
+async fn send_to_agent(
+    sender: &mut AgentSender,
+    session_id: &str,
+    event: ControlEvent,
+) -> Result<(), tonic::Status> {
+    sender
+        .send(MessageToAgent {
+            server_message_id: chrono::Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64,
+            payload: Some(message_to_agent::Payload::PtyDataToAgent(PtyDataToAgent {
+                session_id: session_id.to_string(),
+                control_event: Some(event),
+            })),
+        })
+        .await
+}
+
+async fn send_status(socket: &mut WebSocket, message: TerminalServerMessage) {
+    if let Ok(json) = serde_json::to_string(&message) {
+        let _ = socket.send(Message::Text(Utf8Bytes::from(json))).await;
+    }
+}
+
+async fn handle_terminal_socket(
+    mut socket: WebSocket,
+    app_state: Arc<AppState>,
+    vps_id: i32,
+    mut agent_sender: AgentSender,
+    size: PtyResize,
+) {
+    let session_id = Uuid::new_v4().to_string();
+    let mut output_rx = app_state.terminal_sessions.register(session_id.clone(), vps_id);
+    let mut shutdown_rx = app_state.shutdown_rx.clone();
+
+    let start = ControlEvent::StartCommand(PtyStartCommand {
+        session_id: session_id.clone(),
+        initial_size: Some(size),
+        ..Default::default()
+    });
+    if let Err(e) = send_to_agent(&mut agent_sender, &session_id, start).await {
+        warn!(vps_id, error = %e, "Failed to start terminal session on agent.");
+        send_status(&mut socket, TerminalServerMessage::Error { message: "Agent is not reachable.".to_string() }).await;
+        app_state.terminal_sessions.remove(&session_id);
+        return;
+    }
+
+    loop {
+        tokio::select! {
+            output = output_rx.recv() => {
+                let Some(output) = output else { break };
+                if !output.output_data.is_empty()
+                    && socket.send(Message::Binary(output.output_data.into())).await.is_err()
+                {
+                    break;
+                }
+                if !output.error_message.is_empty() {
+                    send_status(&mut socket, TerminalServerMessage::Error { message: output.error_message.clone() }).await;
+                }
+                if output.stream_closed_by_agent {
+                    send_status(&mut socket, TerminalServerMessage::Closed { message: output.error_message }).await;
+                    let _ = socket.close().await;
+                    app_state.terminal_sessions.remove(&session_id);
+                    info!(vps_id, session_id = %session_id, "Terminal session closed by agent.");
+                    return;
+                }
+            }
+            message = socket.next() => {
+                let event = match message {
+                    Some(Ok(Message::Binary(data))) => ControlEvent::InputData(data.to_vec()),
+                    Some(Ok(Message::Text(text))) => match serde_json::from_str::<TerminalClientMessage>(&text) {
+                        Ok(TerminalClientMessage::Input { data }) => ControlEvent::InputData(data.into_bytes()),
+                        Ok(TerminalClientMessage::Resize { rows, cols }) => ControlEvent::ResizeEvent(PtyResize { rows, cols }),
+                        Err(e) => {
+                            warn!(error = %e, "Ignoring malformed terminal message.");
+                            continue;
+                        }
+                    },
+                    Some(Ok(_)) => continue,
+                    Some(Err(_)) | None => break,
+                };
+                if let Err(e) = send_to_agent(&mut agent_sender, &session_id, event).await {
+                    warn!(vps_id, error = %e, "Lost connection to agent during terminal session.");
+                    send_status(&mut socket, TerminalServerMessage::Error { message: "Agent connection lost.".to_string() }).await;
+                    break;
+                }
+            }
+            _ = shutdown_rx.changed() => break,
+        }
+    }
+
+    app_state.terminal_sessions.remove(&session_id);
+    let _ = send_to_agent(&mut agent_sender, &session_id, ControlEvent::CloseSignalFromServer(true)).await;
+    info!(vps_id, session_id = %session_id, "Terminal session ended.");
+}
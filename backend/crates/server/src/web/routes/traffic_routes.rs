@@ -0,0 +1,166 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use chrono::{Duration, Months, NaiveDate, Utc};
+use serde::Deserialize;
+use std::sync::Arc;
+
+use crate::db::duckdb_service::traffic_report_service::{self, TrafficGranularity, TrafficSummary, TrafficUsage};
+use crate::services::csv_export;
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+const DEFAULT_HISTORY_DAYS: i64 = 30;
+const DEFAULT_HISTORY_MONTHS: u32 = 12;
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Json,
+    Csv,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TrafficHistoryQuery {
+    pub granularity: Option<TrafficGranularity>,
+    /// `YYYY-MM-DD`, inclusive. Defaults to the last 30 days, or 12 months.
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+    #[serde(default)]
+    pub format: ReportFormat,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TrafficSummaryQuery {
+    /// `YYYY-MM`; defaults to the current month.
+    pub month: Option<String>,
+    #[serde(default)]
+    pub format: ReportFormat,
+}
+
+/// Merged into the VPS router.
+pub fn vps_traffic_router() -> Router<Arc<AppState>> {
+    Router::new().route("/{vps_id}/traffic/history", get(get_traffic_history_handler))
+}
+
+/// Mounted at `/api/traffic`.
+pub fn create_traffic_router() -> Router<Arc<AppState>> {
+    Router::new().route("/summary", get(get_traffic_summary_handler))
+}
+
+fn csv_response(file_name: &str, body: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, csv_export::CONTENT_TYPE.to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{file_name}\"")),
+        ],
+        body,
+    )
+        .into_response()
+}
+
+fn history_csv(usage: &[TrafficUsage]) -> String {
+    let mut csv = csv_export::line(["period", "rx_bytes", "tx_bytes", "total_bytes"]);
+    for u in usage {
+        csv.push_str(&csv_export::line([
+            u.period.to_string(),
+            u.rx_bytes.to_string(),
+            u.tx_bytes.to_string(),
+            (u.rx_bytes + u.tx_bytes).to_string(),
+        ]));
+    }
+    csv
+}
+
+/// One row per VPS, tag and group, told apart by the `scope` column.
+fn summary_csv(summary: &TrafficSummary) -> String {
+    let mut csv = csv_export::line(["scope", "id", "name", "vps_count", "rx_bytes", "tx_bytes", "total_bytes"]);
+    let vps_rows = summary
+        .vps
+        .iter()
+        .map(|v| ("vps", Some(v.vps_id), &v.name, 1, v.rx_bytes, v.tx_bytes));
+    let tag_rows = summary
+        .tags
+        .iter()
+        .map(|t| ("tag", t.id, &t.name, t.vps_count, t.rx_bytes, t.tx_bytes));
+    let group_rows = summary
+        .groups
+        .iter()
+        .map(|g| ("group", g.id, &g.name, g.vps_count, g.rx_bytes, g.tx_bytes));
+    for (scope, id, name, vps_count, rx, tx) in vps_rows.chain(tag_rows).chain(group_rows) {
+        csv.push_str(&csv_export::line([
+            scope.to_string(),
+            id.map(|id| id.to_string()).unwrap_or_default(),
+            name.clone(),
+            vps_count.to_string(),
+            rx.to_string(),
+            tx.to_string(),
+            (rx + tx).to_string(),
+        ]));
+    }
+    csv
+}
+
+/// Daily (default) or monthly traffic of one VPS.
+async fn get_traffic_history_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<TrafficHistoryQuery>,
+) -> Result<Response, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+
+    let granularity = query.granularity.unwrap_or(TrafficGranularity::Daily);
+    let to = query.to.unwrap_or_else(|| Utc::now().date_naive());
+    let from = match (query.from, granularity) {
+        (Some(from), _) => from,
+        (None, TrafficGranularity::Daily) => to - Duration::days(DEFAULT_HISTORY_DAYS - 1),
+        (None, TrafficGranularity::Monthly) => traffic_report_service::first_of_month(to)
+            .checked_sub_months(Months::new(DEFAULT_HISTORY_MONTHS - 1))
+            .unwrap_or(to),
+    };
+    if from > to {
+        return Err(AppError::InvalidInput("'from' must not be after 'to'".to_string()));
+    }
+
+    let usage =
+        traffic_report_service::get_vps_traffic_history(app_state.reporting_pool.clone(), vps_id, granularity, from, to)
+            .await?;
+    Ok(match query.format {
+        ReportFormat::Json => Json(usage).into_response(),
+        ReportFormat::Csv => csv_response(&format!("traffic-vps-{vps_id}-{from}-{to}.csv"), history_csv(&usage)),
+    })
+}
+
+/// Monthly traffic per VPS, tag and group across the user's organization.
+async fn get_traffic_summary_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<TrafficSummaryQuery>,
+) -> Result<Response, AppError> {
+    let month = match query.month.as_deref() {
+        Some(month) => traffic_report_service::parse_month(month).map_err(AppError::InvalidInput)?,
+        None => traffic_report_service::first_of_month(Utc::now().date_naive()),
+    };
+    // Scoped through the VPS rows, so this reads the main database rather than the replica.
+    let summary =
+        traffic_report_service::get_traffic_summary(app_state.duckdb_pool.clone(), authenticated_user.id, month)
+            .await?;
+    Ok(match query.format {
+        ReportFormat::Json => Json(summary).into_response(),
+        ReportFormat::Csv => csv_response(
+            &format!("traffic-summary-{}.csv", month.format("%Y-%m")),
+            summary_csv(&summary),
+        ),
+    })
+}
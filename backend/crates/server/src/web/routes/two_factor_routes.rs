@@ -0,0 +1,128 @@
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    routing::{get, post},
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::two_factor_service;
+use crate::services::{auth_service, totp};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+/// Issuer shown in authenticator apps.
+const TOTP_ISSUER: &str = "NodeNexus";
+
+/// TOTP management, merged into `/api/user`.
+pub fn create_user_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/2fa", get(get_status))
+        .route("/2fa/enroll", post(enroll))
+        .route("/2fa/confirm", post(confirm))
+        .route("/2fa/disable", post(disable))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct TwoFactorStatusResponse {
+    enabled: bool,
+    recovery_codes_remaining: usize,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct EnrollmentResponse {
+    /// Base32, for manual entry.
+    secret: String,
+    /// `otpauth://` URI, for QR codes.
+    provisioning_uri: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct RecoveryCodesResponse {
+    recovery_codes: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CodeRequest {
+    code: String,
+}
+
+/// 2FA protects interactive sessions; API tokens can't change it.
+fn reject_api_token(authenticated_user: &AuthenticatedUser) -> Result<(), AppError> {
+    if authenticated_user.api_token_id.is_some() {
+        return Err(AppError::Forbidden(
+            "API tokens can't manage two-factor authentication".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+async fn get_status(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<TwoFactorStatusResponse>, AppError> {
+    let (enabled, recovery_codes_remaining) =
+        two_factor_service::get_status(app_state.duckdb_pool.clone(), authenticated_user.id).await?;
+    Ok(Json(TwoFactorStatusResponse {
+        enabled,
+        recovery_codes_remaining,
+    }))
+}
+
+async fn enroll(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<EnrollmentResponse>, AppError> {
+    reject_api_token(&authenticated_user)?;
+    let secret = two_factor_service::start_enrollment(
+        app_state.duckdb_pool.clone(),
+        &app_state.encryption_service,
+        authenticated_user.id,
+    )
+    .await?;
+    Ok(Json(EnrollmentResponse {
+        secret: totp::base32_encode(&secret),
+        provisioning_uri: totp::provisioning_uri(&secret, TOTP_ISSUER, &authenticated_user.username),
+    }))
+}
+
+async fn confirm(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CodeRequest>,
+) -> Result<Json<RecoveryCodesResponse>, AppError> {
+    reject_api_token(&authenticated_user)?;
+    let recovery_codes = two_factor_service::confirm_enrollment(
+        app_state.duckdb_pool.clone(),
+        &app_state.encryption_service,
+        authenticated_user.id,
+        &payload.code,
+    )
+    .await?;
+    Ok(Json(RecoveryCodesResponse { recovery_codes }))
+}
+
+async fn disable(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CodeRequest>,
+) -> Result<StatusCode, AppError> {
+    reject_api_token(&authenticated_user)?;
+    let valid = auth_service::verify_two_factor_code(
+        app_state.duckdb_pool.clone(),
+        &app_state.encryption_service,
+        &app_state.two_factor_attempts,
+        authenticated_user.id,
+        &payload.code,
+    )
+    .await?;
+    if !valid {
+        return Err(AppError::InvalidInput("The code is not valid".to_string()));
+    }
+    two_factor_service::disable(app_state.duckdb_pool.clone(), authenticated_user.id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
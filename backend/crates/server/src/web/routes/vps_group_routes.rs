@@ -0,0 +1,96 @@
+use axum::{
+    extract::{Extension, Path, State},
+    http::StatusCode,
+    routing::{get, put},
+    Json, Router,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::db::duckdb_service::vps_group_service::{self, GroupTree};
+use crate::db::entities::vps_group;
+use crate::server::update_service;
+use crate::web::models::vps_group_models::{CreateVpsGroupRequest, UpdateVpsGroupRequest};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+pub fn create_vps_group_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(get_group_tree_handler).post(create_group_handler))
+        .route("/{id}", put(update_group_handler).delete(delete_group_handler))
+}
+
+/// The group tree of the active organization, with metrics and health
+/// aggregated over each group and its subgroups.
+async fn get_group_tree_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<GroupTree>, AppError> {
+    let (groups, mut members) =
+        vps_group_service::get_groups_and_members(app_state.duckdb_pool.clone(), authenticated_user.id).await?;
+    let health: HashMap<i32, f64> = app_state
+        .live_server_data_cache
+        .lock()
+        .await
+        .values()
+        .filter_map(|s| Some((s.basic_info.id, s.health.as_ref()?.score?)))
+        .collect();
+    for member in &mut members {
+        member.health_score = health.get(&member.vps_id).copied();
+    }
+    Ok(Json(vps_group_service::build_tree(&groups, &members)))
+}
+
+async fn create_group_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateVpsGroupRequest>,
+) -> Result<(StatusCode, Json<vps_group::Model>), AppError> {
+    let group = vps_group_service::create_group(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        payload.parent_id,
+        &payload.name,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(group)))
+}
+
+async fn update_group_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<UpdateVpsGroupRequest>,
+) -> Result<Json<vps_group::Model>, AppError> {
+    let group = vps_group_service::update_group(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        id,
+        payload.name.as_deref(),
+        payload.parent_id,
+    )
+    .await?;
+    // Group paths of the member VPS changed.
+    update_service::broadcast_full_state_update(
+        app_state.duckdb_pool.clone(),
+        &app_state.live_server_data_cache,
+        &app_state.ws_data_broadcaster_tx,
+    )
+    .await;
+    Ok(Json(group))
+}
+
+async fn delete_group_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    vps_group_service::delete_group(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    update_service::broadcast_full_state_update(
+        app_state.duckdb_pool.clone(),
+        &app_state.live_server_data_cache,
+        &app_state.ws_data_broadcaster_tx,
+    )
+    .await;
+    Ok(StatusCode::NO_CONTENT)
+}
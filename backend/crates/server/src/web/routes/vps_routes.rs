@@ -2,16 +2,24 @@ use crate::db::{
     duckdb_service::{
         tag_service as duckdb_tag_service,
         vps_renewal_service::VpsRenewalDataInput,
-        vps_service,
+        process_service::{self, ProcessSort},
+        agent_version_service, maintenance_service, uptime_service, vps_service, watchdog_service,
+        vps_detail_service::{self, VpsListFilter, VpsSortKey},
+        virtual_group_service::{self, VirtualGroup, VirtualGroupMembers},
+    },
+    entities::{
+        agent_version_event, process_metric, service_monitor, vps, vps_maintenance_event, watchdog_event,
     },
-    entities::{service_monitor, vps},
     models::PerformanceMetric as DbPerformanceMetric,
 };
 use crate::db::entities::tag;
 use crate::server::update_service;
-use crate::web::models::service_monitor_models::ServiceMonitorResultDetails;
+use crate::web::models::service_monitor_models::{
+    HeatmapQuery, HeatmapResponse, ServiceMonitorResultDetails,
+};
+use crate::web::middleware::audit::AuditBefore;
 use crate::web::models::AuthenticatedUser;
-use crate::web::{config_routes, AppError, AppState, routes::metrics_routes};
+use crate::web::{config_routes, AppError, AppState, routes::{agent_certificate_routes, docker_routes, metrics_routes, provider_routes, traffic_routes}};
 use axum::{
     extract::{Extension, Path, Query, State},
     http::StatusCode,
@@ -89,6 +97,7 @@ pub struct VpsListItemResponse {
     pub created_at: String,
     #[serde(rename = "group")]
     pub group: Option<String>,
+    pub group_id: Option<i32>,
     pub tags: Option<Vec<crate::web::models::websocket_models::Tag>>,
     pub config_status: String,
     pub last_config_update_at: Option<String>,
@@ -103,6 +112,8 @@ pub struct VpsListItemResponse {
     pub traffic_reset_config_type: Option<String>,
     pub traffic_reset_config_value: Option<String>,
     pub next_traffic_reset_at: Option<String>,
+    pub is_ephemeral: bool,
+    pub archived_at: Option<String>,
 
     // Renewal Info Fields (flattened)
     pub renewal_cycle: Option<String>,
@@ -117,6 +128,7 @@ pub struct VpsListItemResponse {
     pub renewal_notes: Option<String>,
     pub reminder_active: Option<bool>,
     // last_reminder_generated_at is likely not needed by list view, but can be added if detail view needs it
+    pub virtual_groups: Vec<VirtualGroup>,
 
     // Agent secret is only included in the detail view, not the list view.
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -139,6 +151,7 @@ impl From<crate::web::models::websocket_models::ServerWithDetails> for VpsListIt
             agent_version: details.basic_info.agent_version,
             created_at: details.created_at.to_rfc3339(),
             group: details.basic_info.group,
+            group_id: details.basic_info.group_id,
             tags: details.basic_info.tags,
             config_status: details.basic_info.config_status,
             last_config_update_at: details
@@ -160,6 +173,8 @@ impl From<crate::web::models::websocket_models::ServerWithDetails> for VpsListIt
                 .basic_info
                 .next_traffic_reset_at
                 .map(|dt| dt.to_rfc3339()),
+            is_ephemeral: details.basic_info.is_ephemeral,
+            archived_at: None, // Archived VPS are not part of the live data
 
             // Map renewal fields (assuming they will be added to ServerWithDetails or a similar joined struct)
             // For now, these will default to None or false if not present in ServerWithDetails.
@@ -175,6 +190,7 @@ impl From<crate::web::models::websocket_models::ServerWithDetails> for VpsListIt
             auto_renew_enabled: details.auto_renew_enabled,
             renewal_notes: details.renewal_notes.clone(),
             reminder_active: details.reminder_active,
+            virtual_groups: details.virtual_groups,
             agent_secret: None, // Secret is never sent in the list view or via WebSocket
         }
     }
@@ -218,7 +234,7 @@ async fn create_vps_handler(
     Json(payload): Json<CreateVpsRequest>,
 ) -> Result<(StatusCode, Json<vps::Model>), AppError> {
     let user_id = authenticated_user.id;
-    match vps_service::create_vps(app_state.duckdb_pool.clone(), user_id, &payload.name).await {
+    match app_state.storage.create_vps(user_id, &payload.name).await {
         Ok(vps_model) => {
             // After successful creation, broadcast the new state
             update_service::broadcast_full_state_update(
@@ -236,13 +252,84 @@ async fn create_vps_handler(
     }
 }
 
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VpsListQuery {
+    /// Only return VPS in this virtual group.
+    pub virtual_group: Option<VirtualGroup>,
+    pub page: Option<u32>,
+    pub per_page: Option<u32>,
+    pub sort_by: Option<VpsSortKey>,
+    /// "asc" or "desc"; defaults to descending for `createdAt`, ascending otherwise.
+    pub order: Option<String>,
+    pub status: Option<String>,
+    pub group: Option<String>,
+    /// Only return VPS in this group or one of its subgroups.
+    pub group_id: Option<i32>,
+    pub tag_id: Option<i32>,
+    /// Case-insensitive match on name or IP address.
+    pub search: Option<String>,
+}
+
+const DEFAULT_VPS_PAGE_SIZE: u32 = 50;
+const MAX_VPS_PAGE_SIZE: u32 = 500;
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct VpsListPage {
+    pub items: Vec<VpsListItemResponse>,
+    /// VPS matching the filters, across all pages.
+    pub total: u64,
+    pub page: u32,
+    pub per_page: u32,
+}
+
+/// Virtual group membership from the live cache, which is recomputed on
+/// every refresh. Archived VPS are not cached and belong to no group.
+async fn cached_virtual_groups(app_state: &AppState) -> std::collections::HashMap<i32, Vec<VirtualGroup>> {
+    let cache_guard = app_state.live_server_data_cache.lock().await;
+    cache_guard
+        .iter()
+        .map(|(id, server)| (*id, server.virtual_groups.clone()))
+        .collect()
+}
+
 async fn get_all_vps_handler(
     Extension(authenticated_user): Extension<AuthenticatedUser>,
     State(app_state): State<Arc<AppState>>,
-) -> Result<Json<Vec<VpsListItemResponse>>, AppError> {
+    Query(query): Query<VpsListQuery>,
+) -> Result<Json<VpsListPage>, AppError> {
     let user_id = authenticated_user.id;
-    let vps_list = vps_service::get_vps_by_user_id(app_state.duckdb_pool.clone(), user_id).await?;
-    
+    let mut virtual_groups = cached_virtual_groups(&app_state).await;
+    let sort_by = query.sort_by.unwrap_or_default();
+    let descending = match query.order.as_deref() {
+        None => sort_by == VpsSortKey::CreatedAt,
+        Some(order) if order.eq_ignore_ascii_case("desc") => true,
+        Some(order) if order.eq_ignore_ascii_case("asc") => false,
+        Some(order) => return Err(AppError::InvalidInput(format!("Invalid order '{order}'"))),
+    };
+    let filter = VpsListFilter {
+        status: query.status,
+        group: query.group,
+        group_id: query.group_id,
+        tag_id: query.tag_id,
+        search: query.search,
+        vps_ids: query.virtual_group.map(|group| {
+            virtual_groups
+                .iter()
+                .filter(|(_, groups)| groups.contains(&group))
+                .map(|(id, _)| *id)
+                .collect()
+        }),
+        sort_by,
+        descending,
+    };
+    let page = query.page.unwrap_or(1).max(1);
+    let per_page = query.per_page.unwrap_or(DEFAULT_VPS_PAGE_SIZE).clamp(1, MAX_VPS_PAGE_SIZE);
+    let (vps_list, total) =
+        vps_detail_service::get_vps_page_for_user(app_state.duckdb_pool.clone(), user_id, &filter, page, per_page)
+            .await?;
+
     // TODO: This is inefficient. We should join tags and renewal info in the query.
     // For now, we'll just convert the basic info.
     let response_list: Vec<VpsListItemResponse> = vps_list
@@ -257,6 +344,7 @@ async fn get_all_vps_handler(
             agent_version: vps.agent_version,
             created_at: vps.created_at.to_rfc3339(),
             group: vps.group,
+            group_id: vps.group_id,
             tags: None, // TODO
             config_status: vps.config_status,
             last_config_update_at: vps.last_config_update_at.map(|dt| dt.to_rfc3339()),
@@ -269,6 +357,8 @@ async fn get_all_vps_handler(
             traffic_reset_config_type: vps.traffic_reset_config_type,
             traffic_reset_config_value: vps.traffic_reset_config_value,
             next_traffic_reset_at: vps.next_traffic_reset_at.map(|dt| dt.to_rfc3339()),
+            is_ephemeral: vps.is_ephemeral,
+            archived_at: vps.archived_at.map(|dt| dt.to_rfc3339()),
             renewal_cycle: None, // TODO
             renewal_cycle_custom_days: None, // TODO
             renewal_price: None, // TODO
@@ -280,11 +370,31 @@ async fn get_all_vps_handler(
             auto_renew_enabled: None, // TODO
             renewal_notes: None, // TODO
             reminder_active: None, // TODO
+            virtual_groups: virtual_groups.remove(&vps.id).unwrap_or_default(),
             agent_secret: None,
         })
         .collect();
 
-    Ok(Json(response_list))
+    Ok(Json(VpsListPage {
+        items: response_list,
+        total,
+        page,
+        per_page,
+    }))
+}
+
+/// Members of every virtual group among the user's VPS.
+async fn get_virtual_groups_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<VirtualGroupMembers>>, AppError> {
+    let cache_guard = app_state.live_server_data_cache.lock().await;
+    let groups = virtual_group_service::group_members(
+        cache_guard
+            .values()
+            .filter(|server| authenticated_user.can_access(server.basic_info.organization_id)),
+    );
+    Ok(Json(groups))
 }
 
 async fn get_vps_detail_handler(
@@ -292,13 +402,11 @@ async fn get_vps_detail_handler(
     State(app_state): State<Arc<AppState>>,
     Path(vps_id): Path<i32>,
 ) -> Result<Json<VpsListItemResponse>, AppError> {
-    let user_id = authenticated_user.id;
-
-    let vps = vps_service::get_vps_by_id(app_state.duckdb_pool.clone(), vps_id)
+    let vps = app_state.storage.get_vps_by_id(vps_id)
         .await?
         .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
 
-    if vps.user_id != user_id {
+    if !authenticated_user.can_access(vps.organization_id) {
         return Err(AppError::Unauthorized("Access denied".to_string()));
     }
 
@@ -313,6 +421,7 @@ async fn get_vps_detail_handler(
         agent_version: vps.agent_version,
         created_at: vps.created_at.to_rfc3339(),
         group: vps.group,
+        group_id: vps.group_id,
         tags: None, // TODO
         config_status: vps.config_status,
         last_config_update_at: vps.last_config_update_at.map(|dt| dt.to_rfc3339()),
@@ -325,6 +434,8 @@ async fn get_vps_detail_handler(
         traffic_reset_config_type: vps.traffic_reset_config_type,
         traffic_reset_config_value: vps.traffic_reset_config_value,
         next_traffic_reset_at: vps.next_traffic_reset_at.map(|dt| dt.to_rfc3339()),
+        is_ephemeral: vps.is_ephemeral,
+        archived_at: vps.archived_at.map(|dt| dt.to_rfc3339()),
         renewal_cycle: None, // TODO
         renewal_cycle_custom_days: None, // TODO
         renewal_price: None, // TODO
@@ -336,6 +447,7 @@ async fn get_vps_detail_handler(
         auto_renew_enabled: None, // TODO
         renewal_notes: None, // TODO
         reminder_active: None, // TODO
+        virtual_groups: cached_virtual_groups(&app_state).await.remove(&vps.id).unwrap_or_default(),
         agent_secret: Some(vps.agent_secret),
     };
 
@@ -346,7 +458,10 @@ async fn get_vps_detail_handler(
 #[serde(rename_all = "camelCase")]
 pub struct UpdateVpsRequest {
     name: Option<String>,
+    /// Flat group path such as `eu/hetzner`; missing groups are created.
     group: Option<String>,
+    /// Takes precedence over `group`.
+    group_id: Option<i32>,
     tag_ids: Option<Vec<i32>>,
 
     // Traffic monitoring config fields
@@ -389,8 +504,12 @@ async fn update_vps_handler(
     State(app_state): State<Arc<AppState>>,
     Path(vps_id): Path<i32>,
     Json(payload): Json<UpdateVpsRequest>,
-) -> Result<StatusCode, AppError> {
+) -> Result<(Extension<AuditBefore>, StatusCode), AppError> {
     let user_id = authenticated_user.id;
+    let before = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .map(|vps| AuditBefore::of(&vps))
+        .unwrap_or(AuditBefore(serde_json::Value::Null));
 
     let renewal_input_opt = if payload.renewal_cycle.is_some()
         || payload.renewal_cycle_custom_days.is_some()
@@ -425,6 +544,7 @@ async fn update_vps_handler(
         user_id,
         payload.name,
         payload.group,
+        payload.group_id,
         payload.tag_ids,
         payload.traffic_limit_bytes,
         payload.traffic_billing_rule,
@@ -442,9 +562,9 @@ async fn update_vps_handler(
             &app_state.ws_data_broadcaster_tx,
         )
         .await;
-        Ok(StatusCode::OK)
+        Ok((Extension(before), StatusCode::OK))
     } else {
-        Ok(StatusCode::NOT_MODIFIED)
+        Ok((Extension(before), StatusCode::NOT_MODIFIED))
     }
 }
 
@@ -457,11 +577,10 @@ async fn add_tag_to_vps_handler(
     Path(vps_id): Path<i32>,
     Json(payload): Json<AddTagToVpsRequest>,
 ) -> Result<StatusCode, AppError> {
-    let user_id = authenticated_user.id;
-    let vps = vps_service::get_vps_by_id(app_state.duckdb_pool.clone(), vps_id)
+    let vps = app_state.storage.get_vps_by_id(vps_id)
         .await?
         .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
-    if vps.user_id != user_id {
+    if !authenticated_user.can_access(vps.organization_id) {
         return Err(AppError::Unauthorized(
             "Permission denied to VPS".to_string(),
         ));
@@ -484,11 +603,10 @@ async fn remove_tag_from_vps_handler(
     State(app_state): State<Arc<AppState>>,
     Path((vps_id, tag_id)): Path<(i32, i32)>,
 ) -> Result<StatusCode, AppError> {
-    let user_id = authenticated_user.id;
-    let vps = vps_service::get_vps_by_id(app_state.duckdb_pool.clone(), vps_id)
+    let vps = app_state.storage.get_vps_by_id(vps_id)
         .await?
         .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
-    if vps.user_id != user_id {
+    if !authenticated_user.can_access(vps.organization_id) {
         return Err(AppError::Unauthorized("Permission denied".to_string()));
     }
 
@@ -512,11 +630,10 @@ async fn get_tags_for_vps_handler(
     State(app_state): State<Arc<AppState>>,
     Path(vps_id): Path<i32>,
 ) -> Result<Json<Vec<tag::Model>>, AppError> {
-    let user_id = authenticated_user.id;
-    let vps = vps_service::get_vps_by_id(app_state.duckdb_pool.clone(), vps_id)
+    let vps = app_state.storage.get_vps_by_id(vps_id)
         .await?
         .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
-    if vps.user_id != user_id {
+    if !authenticated_user.can_access(vps.organization_id) {
         return Err(AppError::Unauthorized("Permission denied".to_string()));
     }
 
@@ -580,7 +697,7 @@ async fn bulk_trigger_update_check_handler(
     }
 
     let owned_vps_list =
-        vps_service::get_owned_vps_from_ids(app_state.duckdb_pool.clone(), user_id, &payload.vps_ids).await?;
+        app_state.storage.get_owned_vps_from_ids(user_id, &payload.vps_ids).await?;
 
     if owned_vps_list.len() != payload.vps_ids.len() {
         error!(
@@ -622,13 +739,11 @@ async fn dismiss_renewal_reminder_handler(
     State(app_state): State<Arc<AppState>>,
     Path(vps_id): Path<i32>,
 ) -> Result<StatusCode, AppError> {
-    let user_id = authenticated_user.id;
-
-    let vps = vps_service::get_vps_by_id(app_state.duckdb_pool.clone(), vps_id)
+    let vps = app_state.storage.get_vps_by_id(vps_id)
         .await?
         .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
 
-    if vps.user_id != user_id {
+    if !authenticated_user.can_access(vps.organization_id) {
         return Err(AppError::Unauthorized(
             "Permission denied to VPS".to_string(),
         ));
@@ -683,31 +798,276 @@ pub fn parse_interval_to_seconds(interval: Option<String>) -> Option<i64> {
     })
 }
 
-async fn get_vps_monitor_results_handler(
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessListQuery {
+    /// Show the snapshot taken at or before this time instead of the latest one.
+    pub at: Option<DateTime<Utc>>,
+    /// "cpu" (default) or "memory".
+    pub sort: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ProcessListResponse {
+    /// When the snapshot was taken; `None` if the agent hasn't reported processes yet.
+    pub time: Option<DateTime<Utc>>,
+    pub processes: Vec<process_metric::Model>,
+}
+
+async fn get_vps_processes_handler(
     Extension(authenticated_user): Extension<AuthenticatedUser>,
     State(app_state): State<Arc<AppState>>,
     Path(vps_id): Path<i32>,
-    Query(query): Query<MonitorTimeseriesQuery>,
-) -> Result<Json<Vec<ServiceMonitorResultDetails>>, AppError> {
-    let user_id = authenticated_user.id;
-
-    let vps = vps_service::get_vps_by_id(app_state.duckdb_pool.clone(), vps_id)
+    Query(query): Query<ProcessListQuery>,
+) -> Result<Json<ProcessListResponse>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
         .await?
         .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
-    if vps.user_id != user_id {
+    if !authenticated_user.can_access(vps.organization_id) {
         return Err(AppError::Unauthorized("Access denied".to_string()));
     }
+    let sort = match query.sort.as_deref() {
+        None | Some("cpu") => ProcessSort::Cpu,
+        Some("memory") => ProcessSort::Memory,
+        Some(other) => {
+            return Err(AppError::InvalidInput(format!("Unknown sort '{other}'. Use 'cpu' or 'memory'.")));
+        }
+    };
+    let processes =
+        process_service::get_process_snapshot(app_state.duckdb_pool.clone(), vps_id, query.at, sort).await?;
+    Ok(Json(ProcessListResponse {
+        time: processes.first().map(|p| p.time),
+        processes,
+    }))
+}
 
-    let interval_seconds = parse_interval_to_seconds(query.interval);
+const DEFAULT_WATCHDOG_EVENT_DAYS: i64 = 7;
+const MAX_WATCHDOG_EVENTS: u32 = 1000;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct WatchdogEventsQuery {
+    pub start_time: Option<DateTime<Utc>>,
+    #[serde(default = "default_end_time")]
+    pub end_time: DateTime<Utc>,
+    pub limit: Option<u32>,
+}
 
-    let points_result = crate::db::duckdb_service::service_monitor_service::get_monitor_results_by_vps_id(
+async fn get_vps_watchdog_events_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<WatchdogEventsQuery>,
+) -> Result<Json<Vec<watchdog_event::Model>>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+    let start_time = query
+        .start_time
+        .unwrap_or_else(|| query.end_time - chrono::Duration::days(DEFAULT_WATCHDOG_EVENT_DAYS));
+    let events = watchdog_service::get_watchdog_events_for_vps(
         app_state.duckdb_pool.clone(),
         vps_id,
-        query.start_time,
+        start_time,
         query.end_time,
-        interval_seconds,
+        query.limit.unwrap_or(MAX_WATCHDOG_EVENTS).min(MAX_WATCHDOG_EVENTS),
     )
-    .await;
+    .await?;
+    Ok(Json(events))
+}
+
+const DEFAULT_UPTIME_DAYS: i64 = 30;
+const MAX_UPTIME_DAYS: i64 = 366;
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct UptimeQuery {
+    pub start_time: Option<DateTime<Utc>>,
+    #[serde(default = "default_end_time")]
+    pub end_time: DateTime<Utc>,
+}
+
+async fn get_vps_uptime_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<UptimeQuery>,
+) -> Result<Json<uptime_service::VpsUptimeHistory>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+    let start_time = query
+        .start_time
+        .unwrap_or_else(|| query.end_time - chrono::Duration::days(DEFAULT_UPTIME_DAYS));
+    if start_time >= query.end_time {
+        return Err(AppError::InvalidInput("startTime must be before endTime".to_string()));
+    }
+    if query.end_time - start_time > chrono::Duration::days(MAX_UPTIME_DAYS) {
+        return Err(AppError::InvalidInput(format!(
+            "Uptime history is limited to {MAX_UPTIME_DAYS} days"
+        )));
+    }
+    let history =
+        uptime_service::get_vps_uptime_history(app_state.reporting_pool.clone(), vps_id, start_time, query.end_time)
+            .await?;
+    Ok(Json(history))
+}
+
+const MAX_MAINTENANCE_HOURS: u32 = 24 * 30;
+const DEFAULT_MAINTENANCE_EVENT_DAYS: i64 = 30;
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EnterMaintenanceRequest {
+    pub reason: Option<String>,
+    /// Leave maintenance automatically after this many hours.
+    pub duration_hours: Option<u32>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MaintenanceEventsQuery {
+    pub start_time: Option<DateTime<Utc>>,
+    #[serde(default = "default_end_time")]
+    pub end_time: DateTime<Utc>,
+}
+
+async fn check_vps_owner(app_state: &AppState, user: &AuthenticatedUser, vps_id: i32) -> Result<(), AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+    Ok(())
+}
+
+/// Agent version changes of a VPS, newest first.
+async fn get_agent_version_history_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+) -> Result<Json<Vec<agent_version_event::Model>>, AppError> {
+    check_vps_owner(&app_state, &authenticated_user, vps_id).await?;
+    let history = agent_version_service::get_version_history(app_state.duckdb_pool.clone(), vps_id).await?;
+    Ok(Json(history))
+}
+
+async fn enter_maintenance_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    payload: Option<Json<EnterMaintenanceRequest>>,
+) -> Result<Json<maintenance_service::MaintenanceState>, AppError> {
+    check_vps_owner(&app_state, &authenticated_user, vps_id).await?;
+    let payload = payload.map(|Json(p)| p).unwrap_or_default();
+    let ends_at = match payload.duration_hours {
+        Some(0) => return Err(AppError::InvalidInput("durationHours must be at least 1".to_string())),
+        Some(hours) if hours > MAX_MAINTENANCE_HOURS => {
+            return Err(AppError::InvalidInput(format!(
+                "durationHours must not exceed {MAX_MAINTENANCE_HOURS}"
+            )))
+        }
+        Some(hours) => Some(Utc::now() + chrono::Duration::hours(hours as i64)),
+        None => None,
+    };
+    let reason = payload.reason.map(|r| r.trim().to_string()).filter(|r| !r.is_empty());
+
+    let state = maintenance_service::enter_maintenance(
+        app_state.duckdb_pool.clone(),
+        vps_id,
+        reason,
+        ends_at,
+        &authenticated_user.username,
+    )
+    .await?;
+    if app_state.update_trigger_tx.send(()).await.is_err() {
+        error!("Failed to send update trigger after entering maintenance.");
+    }
+    Ok(Json(state))
+}
+
+async fn exit_maintenance_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    check_vps_owner(&app_state, &authenticated_user, vps_id).await?;
+    let exited =
+        maintenance_service::exit_maintenance(app_state.duckdb_pool.clone(), vps_id, &authenticated_user.username)
+            .await?;
+    if !exited {
+        return Err(AppError::Conflict("VPS is not in maintenance".to_string()));
+    }
+    if app_state.update_trigger_tx.send(()).await.is_err() {
+        error!("Failed to send update trigger after leaving maintenance.");
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn get_maintenance_events_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<MaintenanceEventsQuery>,
+) -> Result<Json<Vec<vps_maintenance_event::Model>>, AppError> {
+    check_vps_owner(&app_state, &authenticated_user, vps_id).await?;
+    let start_time = query
+        .start_time
+        .unwrap_or_else(|| query.end_time - chrono::Duration::days(DEFAULT_MAINTENANCE_EVENT_DAYS));
+    let events =
+        maintenance_service::get_maintenance_events(app_state.duckdb_pool.clone(), vps_id, start_time, query.end_time)
+            .await?;
+    Ok(Json(events))
+}
+
+async fn get_vps_monitor_heatmap_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<HeatmapQuery>,
+) -> Result<Json<HeatmapResponse>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+    let heatmap = crate::web::routes::service_monitor_routes::build_heatmap(
+        &app_state,
+        crate::db::duckdb_service::service_monitor_service::HeatmapScope::Vps(vps_id),
+        query,
+    )
+    .await?;
+    Ok(Json(heatmap))
+}
+
+async fn get_vps_monitor_results_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(query): Query<MonitorTimeseriesQuery>,
+) -> Result<Json<Vec<ServiceMonitorResultDetails>>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
+    if !authenticated_user.can_access(vps.organization_id) {
+        return Err(AppError::Unauthorized("Access denied".to_string()));
+    }
+
+    let interval_seconds = parse_interval_to_seconds(query.interval);
+
+    let points_result = app_state
+        .storage
+        .get_monitor_results_by_vps_id(vps_id, query.start_time, query.end_time, interval_seconds)
+        .await;
 
     let points = match points_result {
         Ok(points) => points,
@@ -753,6 +1113,8 @@ async fn get_vps_monitor_results_handler(
                 is_up: point.is_up.is_some_and(|v| v > 0.5),
                 latency_ms: point.latency_ms.map(|f| f as i32),
                 details: point.details,
+                in_maintenance: point.in_maintenance.unwrap_or(false),
+                packet_loss_percent: point.packet_loss_percent,
             }
         })
         .collect();
@@ -766,13 +1128,11 @@ async fn get_vps_monitors_handler(
     State(app_state): State<Arc<AppState>>,
     Path(vps_id): Path<i32>,
 ) -> Result<Json<Vec<service_monitor::Model>>, AppError> {
-    let user_id = authenticated_user.id;
-
-    let vps = vps_service::get_vps_by_id(app_state.duckdb_pool.clone(), vps_id)
+    let vps = app_state.storage.get_vps_by_id(vps_id)
         .await?
         .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
 
-    if vps.user_id != user_id {
+    if !authenticated_user.can_access(vps.organization_id) {
         return Err(AppError::Unauthorized("Access denied".to_string()));
     }
 
@@ -786,6 +1146,7 @@ pub fn vps_router() -> Router<Arc<AppState>> {
     Router::new()
         .route("/", post(create_vps_handler))
         .route("/", get(get_all_vps_handler))
+        .route("/virtual-groups", get(get_virtual_groups_handler))
         .route(
             "/bulk-actions/update-tags",
             post(bulk_update_vps_tags_handler),
@@ -809,6 +1170,25 @@ pub fn vps_router() -> Router<Arc<AppState>> {
             "/{vps_id}/monitor-results",
             get(get_vps_monitor_results_handler),
         )
+        .route(
+            "/{vps_id}/monitor-heatmap",
+            get(get_vps_monitor_heatmap_handler),
+        )
+        .route("/{vps_id}/processes", get(get_vps_processes_handler))
+        .route(
+            "/{vps_id}/watchdog-events",
+            get(get_vps_watchdog_events_handler),
+        )
+        .route("/{vps_id}/uptime", get(get_vps_uptime_handler))
+        .route("/{vps_id}/agent-versions", get(get_agent_version_history_handler))
+        .route(
+            "/{vps_id}/maintenance",
+            post(enter_maintenance_handler).delete(exit_maintenance_handler),
+        )
+        .route(
+            "/{vps_id}/maintenance/events",
+            get(get_maintenance_events_handler),
+        )
         .route(
             "/{vps_id}/trigger-update-check",
             post(trigger_update_check_handler),
@@ -816,6 +1196,10 @@ pub fn vps_router() -> Router<Arc<AppState>> {
         .nest("/{vps_id}/tags", vps_tags_router())
         .merge(config_routes::create_vps_config_router())
         .merge(metrics_routes::metrics_router())
+        .merge(docker_routes::docker_router())
+        .merge(provider_routes::vps_power_router())
+        .merge(agent_certificate_routes::agent_certificate_router())
+        .merge(traffic_routes::vps_traffic_router())
 }
 
 async fn trigger_update_check_handler(
@@ -823,12 +1207,10 @@ async fn trigger_update_check_handler(
     State(app_state): State<Arc<AppState>>,
     Path(vps_id): Path<i32>,
 ) -> Result<StatusCode, AppError> {
-    let user_id = authenticated_user.id;
-
-    let vps = vps_service::get_vps_by_id(app_state.duckdb_pool.clone(), vps_id)
+    let vps = app_state.storage.get_vps_by_id(vps_id)
         .await?
         .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
-    if vps.user_id != user_id {
+    if !authenticated_user.can_access(vps.organization_id) {
         return Err(AppError::Unauthorized("Access denied".to_string()));
     }
 
@@ -848,17 +1230,16 @@ async fn delete_vps_handler(
     Extension(authenticated_user): Extension<AuthenticatedUser>,
     State(app_state): State<Arc<AppState>>,
     Path(vps_id): Path<i32>,
-) -> Result<StatusCode, AppError> {
-    let user_id = authenticated_user.id;
-
-    let vps = vps_service::get_vps_by_id(app_state.duckdb_pool.clone(), vps_id)
+) -> Result<(Extension<AuditBefore>, StatusCode), AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id)
         .await?
         .ok_or_else(|| AppError::NotFound("VPS not found".to_string()))?;
-    if vps.user_id != user_id {
+    if !authenticated_user.can_access(vps.organization_id) {
         return Err(AppError::Unauthorized("Access denied".to_string()));
     }
+    let before = AuditBefore::of(&vps);
 
-    vps_service::delete_vps(app_state.duckdb_pool.clone(), vps_id).await?;
+    app_state.storage.delete_vps(vps_id).await?;
 
     update_service::broadcast_full_state_update(
         app_state.duckdb_pool.clone(),
@@ -867,5 +1248,5 @@ async fn delete_vps_handler(
     )
     .await;
 
-    Ok(StatusCode::NO_CONTENT)
+    Ok((Extension(before), StatusCode::NO_CONTENT))
 }
@@ -0,0 +1,81 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    routing::get,
+    Json, Router,
+};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::vulnerability_service;
+use crate::web::models::vulnerability_models::{
+    VpsVulnerability, VulnerabilityAlertSettings, VulnerabilityListQuery,
+};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+pub fn create_vulnerability_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_vulnerabilities))
+        .route("/vps/{vps_id}", get(list_vps_vulnerabilities))
+        .route(
+            "/alert-settings",
+            get(get_alert_settings).put(update_alert_settings),
+        )
+}
+
+async fn list_vulnerabilities(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Query(query): Query<VulnerabilityListQuery>,
+) -> Result<Json<Vec<VpsVulnerability>>, AppError> {
+    let vulnerabilities = vulnerability_service::list_vulnerabilities(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        query,
+    )
+    .await?;
+    Ok(Json(vulnerabilities))
+}
+
+async fn list_vps_vulnerabilities(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(vps_id): Path<i32>,
+    Query(mut query): Query<VulnerabilityListQuery>,
+) -> Result<Json<Vec<VpsVulnerability>>, AppError> {
+    let vps = app_state.storage.get_vps_by_id(vps_id).await?;
+    if !matches!(vps, Some(v) if authenticated_user.can_access(v.organization_id)) {
+        return Err(AppError::NotFound("VPS not found".to_string()));
+    }
+    query.vps_id = Some(vps_id);
+    let vulnerabilities = vulnerability_service::list_vulnerabilities(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        query,
+    )
+    .await?;
+    Ok(Json(vulnerabilities))
+}
+
+async fn get_alert_settings(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<VulnerabilityAlertSettings>, AppError> {
+    let settings =
+        vulnerability_service::get_alert_settings(app_state.duckdb_pool.clone(), authenticated_user.id)
+            .await?;
+    Ok(Json(settings))
+}
+
+async fn update_alert_settings(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<VulnerabilityAlertSettings>,
+) -> Result<Json<VulnerabilityAlertSettings>, AppError> {
+    let settings = vulnerability_service::update_alert_settings(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        payload,
+    )
+    .await?;
+    Ok(Json(settings))
+}
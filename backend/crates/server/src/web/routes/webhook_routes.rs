@@ -0,0 +1,108 @@
+use axum::{
+    extract::{Extension, Path, Query, State},
+    http::StatusCode,
+    routing::{get, post, put},
+    Json, Router,
+};
+use std::sync::Arc;
+
+use crate::db::duckdb_service::webhook_service;
+use crate::db::entities::{webhook_delivery, webhook_subscription};
+use crate::web::models::webhook_models::{
+    CreateWebhookRequest, CreatedWebhook, UpdateWebhookRequest, WebhookDeliveryQuery,
+};
+use crate::web::models::AuthenticatedUser;
+use crate::web::{AppError, AppState};
+
+/// Outgoing webhook subscriptions, mounted at `/api/settings/webhooks`.
+pub fn create_settings_router() -> Router<Arc<AppState>> {
+    Router::new()
+        .route("/", get(list_webhooks_handler).post(create_webhook_handler))
+        .route("/event-types", get(list_event_types_handler))
+        .route("/{id}", put(update_webhook_handler).delete(delete_webhook_handler))
+        .route("/{id}/deliveries", get(list_deliveries_handler))
+        .route("/{id}/deliveries/{delivery_id}/retry", post(retry_delivery_handler))
+}
+
+async fn list_event_types_handler() -> Json<&'static [&'static str]> {
+    Json(webhook_service::EVENT_TYPES)
+}
+
+async fn list_webhooks_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+) -> Result<Json<Vec<webhook_subscription::Model>>, AppError> {
+    let webhooks =
+        webhook_service::get_subscriptions(app_state.duckdb_pool.clone(), authenticated_user.id).await?;
+    Ok(Json(webhooks))
+}
+
+async fn create_webhook_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Json(payload): Json<CreateWebhookRequest>,
+) -> Result<(StatusCode, Json<CreatedWebhook>), AppError> {
+    let (webhook, secret) = webhook_service::create_subscription(
+        app_state.duckdb_pool.clone(),
+        app_state.encryption_service.clone(),
+        authenticated_user.id,
+        payload,
+    )
+    .await?;
+    Ok((StatusCode::CREATED, Json(CreatedWebhook { webhook, secret })))
+}
+
+async fn update_webhook_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Json(payload): Json<UpdateWebhookRequest>,
+) -> Result<Json<webhook_subscription::Model>, AppError> {
+    let webhook = webhook_service::update_subscription(
+        app_state.duckdb_pool.clone(),
+        app_state.encryption_service.clone(),
+        authenticated_user.id,
+        id,
+        payload,
+    )
+    .await?;
+    Ok(Json(webhook))
+}
+
+async fn delete_webhook_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+) -> Result<StatusCode, AppError> {
+    webhook_service::delete_subscription(app_state.duckdb_pool.clone(), authenticated_user.id, id).await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_deliveries_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path(id): Path<i32>,
+    Query(query): Query<WebhookDeliveryQuery>,
+) -> Result<Json<Vec<webhook_delivery::Model>>, AppError> {
+    let deliveries = webhook_service::get_deliveries(
+        app_state.duckdb_pool.clone(),
+        authenticated_user.id,
+        id,
+        query.status.as_deref(),
+        query.limit,
+    )
+    .await?;
+    Ok(Json(deliveries))
+}
+
+/// Requeues a dead delivery.
+async fn retry_delivery_handler(
+    Extension(authenticated_user): Extension<AuthenticatedUser>,
+    State(app_state): State<Arc<AppState>>,
+    Path((id, delivery_id)): Path<(i32, i64)>,
+) -> Result<Json<webhook_delivery::Model>, AppError> {
+    let delivery =
+        webhook_service::retry_delivery(app_state.duckdb_pool.clone(), authenticated_user.id, id, delivery_id)
+            .await?;
+    Ok(Json(delivery))
+}
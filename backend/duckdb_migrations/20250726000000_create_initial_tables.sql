@@ -419,3 +419,1021 @@ CREATE TABLE IF NOT EXISTS themes (
 CREATE INDEX IF NOT EXISTS idx_themes_user_id ON themes (user_id);
 CREATE INDEX IF NOT EXISTS idx_themes_is_official ON themes (is_official);
 
+
+-- Scheduled downtime for service monitors. Results recorded inside a window are
+-- flagged and excluded from uptime/SLA calculations.
+CREATE SEQUENCE IF NOT EXISTS service_monitor_maintenance_windows_id_seq;
+CREATE TABLE IF NOT EXISTS service_monitor_maintenance_windows (
+    id         INTEGER PRIMARY KEY DEFAULT nextval('service_monitor_maintenance_windows_id_seq'),
+    monitor_id INTEGER NOT NULL,
+    starts_at  TIMESTAMPTZ NOT NULL,
+    ends_at    TIMESTAMPTZ NOT NULL,
+    reason     TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX IF NOT EXISTS idx_service_monitor_maintenance_windows_monitor_id ON service_monitor_maintenance_windows (monitor_id);
+
+ALTER TABLE service_monitor_results ADD COLUMN IF NOT EXISTS in_maintenance BOOLEAN NOT NULL DEFAULT false;
+
+-- Success-rate / response-time objectives for service monitors, evaluated with
+-- multi-window burn-rate alerting.
+CREATE SEQUENCE IF NOT EXISTS service_monitor_slos_id_seq;
+CREATE TABLE IF NOT EXISTS service_monitor_slos (
+    id                   INTEGER PRIMARY KEY DEFAULT nextval('service_monitor_slos_id_seq'),
+    monitor_id           INTEGER NOT NULL,
+    name                 VARCHAR(255) NOT NULL,
+    target_percent       DOUBLE NOT NULL,
+    window_days          INTEGER NOT NULL DEFAULT 30,
+    latency_threshold_ms INTEGER,
+    fast_burn_rate       DOUBLE NOT NULL DEFAULT 14.4,
+    slow_burn_rate       DOUBLE NOT NULL DEFAULT 6.0,
+    is_active            BOOLEAN NOT NULL DEFAULT true,
+    last_alerted_at      TIMESTAMPTZ,
+    created_at           TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at           TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX IF NOT EXISTS idx_service_monitor_slos_monitor_id ON service_monitor_slos (monitor_id);
+
+CREATE TABLE IF NOT EXISTS service_monitor_slo_channels (
+    slo_id     INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    PRIMARY KEY (slo_id, channel_id)
+);
+
+-- Read-only share links. A NULL vps_id shares the owner's whole dashboard.
+CREATE SEQUENCE IF NOT EXISTS share_links_id_seq;
+CREATE TABLE IF NOT EXISTS share_links (
+    id               INTEGER PRIMARY KEY DEFAULT nextval('share_links_id_seq'),
+    user_id          INTEGER NOT NULL,
+    name             VARCHAR(255) NOT NULL,
+    vps_id           INTEGER,
+    expires_at       TIMESTAMPTZ NOT NULL,
+    last_accessed_at TIMESTAMPTZ,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX IF NOT EXISTS idx_share_links_user_id ON share_links (user_id);
+
+-- Host inventory facts reported by agents with the "inventory_facts" feature flag.
+-- The full snapshot is kept gzip-compressed; packages are also stored per row for fleet-wide search.
+CREATE TABLE IF NOT EXISTS vps_inventory (
+    vps_id          INTEGER PRIMARY KEY,
+    package_manager VARCHAR(32) NOT NULL,
+    package_count   INTEGER NOT NULL,
+    facts_gz        BLOB NOT NULL,
+    collected_at    TIMESTAMPTZ NOT NULL,
+    changed_at      TIMESTAMPTZ NOT NULL,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+CREATE TABLE IF NOT EXISTS vps_inventory_packages (
+    vps_id  INTEGER NOT NULL,
+    name    VARCHAR NOT NULL,
+    arch    VARCHAR NOT NULL,
+    version VARCHAR NOT NULL
+);
+
+-- Not unique: RPM hosts can have several versions of one package installed (e.g. kernels).
+CREATE INDEX IF NOT EXISTS idx_vps_inventory_packages_vps_id ON vps_inventory_packages (vps_id);
+CREATE INDEX IF NOT EXISTS idx_vps_inventory_packages_name ON vps_inventory_packages (name);
+
+-- One row per changed package, sysctl key or enabled service between two snapshots.
+CREATE SEQUENCE IF NOT EXISTS vps_inventory_changes_id_seq;
+CREATE TABLE IF NOT EXISTS vps_inventory_changes (
+    id         INTEGER PRIMARY KEY DEFAULT nextval('vps_inventory_changes_id_seq'),
+    vps_id     INTEGER NOT NULL,
+    changed_at TIMESTAMPTZ NOT NULL,
+    category   VARCHAR(16) NOT NULL, -- 'package', 'sysctl' or 'service'
+    name       VARCHAR NOT NULL,
+    old_value  VARCHAR,
+    new_value  VARCHAR
+);
+
+CREATE INDEX IF NOT EXISTS idx_vps_inventory_changes_vps_id_changed_at ON vps_inventory_changes (vps_id, changed_at);
+
+ALTER TABLE vps_inventory_packages ADD COLUMN IF NOT EXISTS source_name VARCHAR;
+
+-- Vulnerability records fetched from OSV, cached and refreshed periodically.
+CREATE TABLE IF NOT EXISTS vulnerabilities (
+    id           VARCHAR PRIMARY KEY,
+    aliases      VARCHAR NOT NULL DEFAULT '', -- comma-separated, e.g. CVE ids of a DSA
+    summary      VARCHAR NOT NULL DEFAULT '',
+    severity     VARCHAR(16) NOT NULL, -- 'critical', 'high', 'medium', 'low' or 'unknown'
+    cvss_score   DOUBLE,
+    fixed_in     VARCHAR NOT NULL DEFAULT '[]', -- JSON list of {ecosystem, package, version}
+    published_at TIMESTAMPTZ,
+    modified_at  TIMESTAMPTZ,
+    fetched_at   TIMESTAMPTZ NOT NULL
+);
+
+-- Vulnerabilities currently affecting a host's installed packages.
+CREATE TABLE IF NOT EXISTS vps_vulnerabilities (
+    vps_id            INTEGER NOT NULL,
+    vulnerability_id  VARCHAR NOT NULL,
+    package_name      VARCHAR NOT NULL,
+    installed_version VARCHAR NOT NULL,
+    fixed_version     VARCHAR,
+    first_seen_at     TIMESTAMPTZ NOT NULL,
+    last_seen_at      TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (vps_id, vulnerability_id, package_name)
+);
+
+CREATE INDEX IF NOT EXISTS idx_vps_vulnerabilities_vulnerability_id ON vps_vulnerabilities (vulnerability_id);
+
+CREATE TABLE IF NOT EXISTS vulnerability_alert_settings (
+    user_id      INTEGER PRIMARY KEY,
+    min_severity VARCHAR(16) NOT NULL DEFAULT 'critical',
+    updated_at   TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+CREATE TABLE IF NOT EXISTS vulnerability_alert_channels (
+    user_id    INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    PRIMARY KEY (user_id, channel_id)
+);
+
+-- User-defined metrics computed from performance_metrics columns at query time.
+-- Alert rules reference them with metric_type 'derived:<id>'.
+CREATE SEQUENCE IF NOT EXISTS derived_metrics_id_seq;
+CREATE TABLE IF NOT EXISTS derived_metrics (
+    id          INTEGER PRIMARY KEY DEFAULT nextval('derived_metrics_id_seq'),
+    user_id     INTEGER NOT NULL,
+    name        VARCHAR(64) NOT NULL,
+    expression  VARCHAR NOT NULL,
+    unit        VARCHAR(32),
+    description VARCHAR,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at  TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    UNIQUE (user_id, name)
+);
+
+-- Fired alert rules. Notifications carry signed action links (acknowledge,
+-- silence, remediate) that update the event from the chat client.
+CREATE SEQUENCE IF NOT EXISTS alert_events_id_seq;
+CREATE TABLE IF NOT EXISTS alert_events (
+    id                       INTEGER PRIMARY KEY DEFAULT nextval('alert_events_id_seq'),
+    rule_id                  INTEGER NOT NULL,
+    vps_id                   INTEGER NOT NULL,
+    trigger_time             TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    resolve_time             TIMESTAMPTZ,
+    details                  VARCHAR
+);
+CREATE INDEX IF NOT EXISTS idx_alert_events_rule_vps ON alert_events (rule_id, vps_id, trigger_time);
+ALTER TABLE alert_events ADD COLUMN IF NOT EXISTS acknowledged_at TIMESTAMPTZ;
+ALTER TABLE alert_events ADD COLUMN IF NOT EXISTS acknowledged_by VARCHAR;
+ALTER TABLE alert_events ADD COLUMN IF NOT EXISTS silenced_until TIMESTAMPTZ;
+ALTER TABLE alert_events ADD COLUMN IF NOT EXISTS remediation_batch_command_id UUID;
+-- Set before the remediation batch is created, so only one request starts it.
+ALTER TABLE alert_events ADD COLUMN IF NOT EXISTS remediation_claimed_at TIMESTAMPTZ;
+
+-- Optional command script offered as a "run remediation" action on notifications.
+ALTER TABLE alert_rules ADD COLUMN IF NOT EXISTS remediation_script_id INTEGER;
+
+-- Inbound chat command bridges (Telegram bot webhooks, Slack slash commands).
+-- `secret` is encrypted: the Telegram webhook secret token or the Slack signing secret.
+CREATE SEQUENCE IF NOT EXISTS chatops_bridges_id_seq;
+CREATE TABLE IF NOT EXISTS chatops_bridges (
+    id                 INTEGER PRIMARY KEY DEFAULT nextval('chatops_bridges_id_seq'),
+    user_id            INTEGER NOT NULL,
+    name               VARCHAR(255) NOT NULL,
+    platform           VARCHAR(20) NOT NULL,
+    secret             BLOB NOT NULL,
+    allowed_chat_ids   JSON NOT NULL DEFAULT '[]',
+    scopes             JSON NOT NULL DEFAULT '["read"]',
+    allowed_script_ids JSON NOT NULL DEFAULT '[]',
+    created_at         TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+CREATE INDEX IF NOT EXISTS idx_chatops_bridges_user_id ON chatops_bridges (user_id);
+
+-- Script runs requested from chat, waiting for a "/confirm <code>" reply.
+CREATE TABLE IF NOT EXISTS chatops_confirmations (
+    code       VARCHAR PRIMARY KEY,
+    bridge_id  INTEGER NOT NULL,
+    chat_id    VARCHAR NOT NULL,
+    script_id  INTEGER NOT NULL,
+    vps_id     INTEGER NOT NULL,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+-- Events reported by the agent's process watchdog (exits, restarts, rate limiting).
+CREATE SEQUENCE IF NOT EXISTS watchdog_events_id_seq;
+CREATE TABLE IF NOT EXISTS watchdog_events (
+    id          INTEGER PRIMARY KEY DEFAULT nextval('watchdog_events_id_seq'),
+    vps_id      INTEGER NOT NULL,
+    time        TIMESTAMPTZ NOT NULL,
+    target_name VARCHAR NOT NULL,
+    kind        VARCHAR(32) NOT NULL,
+    details     TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_watchdog_events_vps_id_time ON watchdog_events (vps_id, time);
+
+-- Top processes by CPU and memory, one row per process per agent snapshot.
+CREATE TABLE IF NOT EXISTS process_metrics (
+    time              TIMESTAMPTZ NOT NULL,
+    vps_id            INTEGER NOT NULL,
+    pid               INTEGER NOT NULL,
+    name              VARCHAR NOT NULL,
+    cpu_usage_percent DOUBLE NOT NULL,
+    memory_rss_bytes  BIGINT NOT NULL,
+    username          VARCHAR
+);
+CREATE INDEX IF NOT EXISTS idx_process_metrics_vps_id_time ON process_metrics (vps_id, time);
+
+-- Per block device throughput, IOPS and latency, one row per device per performance snapshot.
+CREATE TABLE IF NOT EXISTS disk_io_metrics (
+    time                TIMESTAMPTZ NOT NULL,
+    vps_id              INTEGER NOT NULL,
+    device              VARCHAR NOT NULL,
+    read_bps            BIGINT NOT NULL,
+    write_bps           BIGINT NOT NULL,
+    read_iops           DOUBLE NOT NULL,
+    write_iops          DOUBLE NOT NULL,
+    await_ms            DOUBLE NOT NULL,
+    utilization_percent DOUBLE NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_disk_io_metrics_vps_id_time ON disk_io_metrics (vps_id, time);
+
+-- Per physical network interface error/drop rates and link state, one row per interface per performance snapshot.
+CREATE TABLE IF NOT EXISTS network_interface_metrics (
+    time               TIMESTAMPTZ NOT NULL,
+    vps_id             INTEGER NOT NULL,
+    interface_name     VARCHAR NOT NULL,
+    rx_errors_per_sec  DOUBLE NOT NULL,
+    tx_errors_per_sec  DOUBLE NOT NULL,
+    rx_dropped_per_sec DOUBLE NOT NULL,
+    tx_dropped_per_sec DOUBLE NOT NULL,
+    collisions_per_sec DOUBLE NOT NULL,
+    carrier_changes    BIGINT NOT NULL,
+    link_up            BOOLEAN NOT NULL,
+    speed_mbps         INTEGER,
+    duplex             VARCHAR(8)
+);
+CREATE INDEX IF NOT EXISTS idx_network_interface_metrics_vps_id_time ON network_interface_metrics (vps_id, time);
+
+-- Agent online/offline transitions, used to compute VPS availability.
+CREATE SEQUENCE IF NOT EXISTS vps_status_events_id_seq;
+CREATE TABLE IF NOT EXISTS vps_status_events (
+    id     INTEGER PRIMARY KEY DEFAULT nextval('vps_status_events_id_seq'),
+    vps_id INTEGER NOT NULL,
+    time   TIMESTAMPTZ NOT NULL,
+    status VARCHAR(16) NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_vps_status_events_vps_id_time ON vps_status_events (vps_id, time);
+
+-- Maintenance mode timeline. A VPS is in maintenance from an 'enter' event
+-- until the next 'exit' or, if set, `ends_at` of that 'enter' event.
+CREATE SEQUENCE IF NOT EXISTS vps_maintenance_events_id_seq;
+CREATE TABLE IF NOT EXISTS vps_maintenance_events (
+    id      INTEGER PRIMARY KEY DEFAULT nextval('vps_maintenance_events_id_seq'),
+    vps_id  INTEGER NOT NULL,
+    time    TIMESTAMPTZ NOT NULL,
+    action  VARCHAR(8) NOT NULL,
+    reason  VARCHAR,
+    ends_at TIMESTAMPTZ,
+    actor   VARCHAR
+);
+CREATE INDEX IF NOT EXISTS idx_vps_maintenance_events_vps_id_time ON vps_maintenance_events (vps_id, time);
+
+-- Composite alert conditions (JSON AND/OR tree); metric_type is 'composite' when set.
+ALTER TABLE alert_rules ADD COLUMN IF NOT EXISTS condition_expression VARCHAR;
+
+-- Ephemeral VPS self-register with a provisioning token and are archived
+-- after staying offline; archived VPS are hidden from the dashboard.
+ALTER TABLE vps ADD COLUMN IF NOT EXISTS is_ephemeral BOOLEAN NOT NULL DEFAULT false;
+ALTER TABLE vps ADD COLUMN IF NOT EXISTS archived_at TIMESTAMPTZ;
+
+CREATE SEQUENCE IF NOT EXISTS provisioning_tokens_id_seq;
+CREATE TABLE IF NOT EXISTS provisioning_tokens (
+    id           INTEGER PRIMARY KEY DEFAULT nextval('provisioning_tokens_id_seq'),
+    user_id      INTEGER NOT NULL,
+    name         VARCHAR(255) NOT NULL,
+    token        VARCHAR(255) NOT NULL UNIQUE,
+    "group"      VARCHAR(255),
+    is_ephemeral BOOLEAN NOT NULL DEFAULT true,
+    use_count    INTEGER NOT NULL DEFAULT 0,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    last_used_at TIMESTAMPTZ
+);
+CREATE INDEX IF NOT EXISTS idx_provisioning_tokens_user_id ON provisioning_tokens (user_id);
+
+-- Provisioning token constraints: registrations stop after max_uses or
+-- expires_at; tag_ids (JSON array) are attached to every registered VPS.
+ALTER TABLE provisioning_tokens ADD COLUMN IF NOT EXISTS max_uses INTEGER;
+ALTER TABLE provisioning_tokens ADD COLUMN IF NOT EXISTS expires_at TIMESTAMPTZ;
+ALTER TABLE provisioning_tokens ADD COLUMN IF NOT EXISTS tag_ids VARCHAR;
+
+-- Agent version changes reported on handshake, for upgrade history and
+-- fleet version reports.
+CREATE SEQUENCE IF NOT EXISTS agent_version_events_id_seq;
+CREATE TABLE IF NOT EXISTS agent_version_events (
+    id               INTEGER PRIMARY KEY DEFAULT nextval('agent_version_events_id_seq'),
+    vps_id           INTEGER NOT NULL,
+    time             TIMESTAMPTZ NOT NULL,
+    previous_version VARCHAR(64),
+    version          VARCHAR(64) NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_agent_version_events_vps_id_time ON agent_version_events (vps_id, time);
+
+-- Staged global agent config changes: pushed stage by stage (canaries
+-- first), rolled back on agents that fail to apply or stop heartbeating.
+CREATE SEQUENCE IF NOT EXISTS config_rollouts_id_seq;
+CREATE TABLE IF NOT EXISTS config_rollouts (
+    id                    INTEGER PRIMARY KEY DEFAULT nextval('config_rollouts_id_seq'),
+    user_id               INTEGER NOT NULL,
+    status                VARCHAR(16) NOT NULL,
+    config                JSON NOT NULL,
+    health_window_seconds INTEGER NOT NULL,
+    current_stage         INTEGER NOT NULL DEFAULT 0,
+    stage_count           INTEGER NOT NULL,
+    error                 VARCHAR,
+    created_at            TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    finished_at           TIMESTAMPTZ
+);
+
+CREATE TABLE IF NOT EXISTS config_rollout_targets (
+    rollout_id INTEGER NOT NULL,
+    vps_id     INTEGER NOT NULL,
+    stage      INTEGER NOT NULL,
+    status     VARCHAR(16) NOT NULL,
+    error      VARCHAR,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    PRIMARY KEY (rollout_id, vps_id)
+);
+
+-- Packet loss of ICMP ping checks, kept as a column so timeseries buckets can average it.
+ALTER TABLE service_monitor_results ADD COLUMN IF NOT EXISTS packet_loss_percent DOUBLE;
+
+-- Public status pages. vps_ids and monitor_ids are JSON arrays of what the page shows.
+CREATE SEQUENCE IF NOT EXISTS status_pages_id_seq;
+CREATE TABLE IF NOT EXISTS status_pages (
+    id            INTEGER PRIMARY KEY DEFAULT nextval('status_pages_id_seq'),
+    user_id       INTEGER NOT NULL,
+    slug          VARCHAR(64) NOT NULL UNIQUE,
+    title         VARCHAR(255) NOT NULL,
+    description   TEXT,
+    vps_ids       JSON NOT NULL DEFAULT '[]',
+    monitor_ids   JSON NOT NULL DEFAULT '[]',
+    show_uptime   BOOLEAN NOT NULL DEFAULT true,
+    show_latency  BOOLEAN NOT NULL DEFAULT true,
+    is_published  BOOLEAN NOT NULL DEFAULT true,
+    created_at    TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at    TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+-- Per mount point disk usage, one row per mount per performance snapshot.
+-- Written by the metrics writer's appender, so the column order matters.
+CREATE TABLE IF NOT EXISTS disk_usage_metrics (
+    time        TIMESTAMPTZ NOT NULL,
+    vps_id      INTEGER NOT NULL,
+    mount_point VARCHAR NOT NULL,
+    fstype      VARCHAR,
+    used_bytes  BIGINT NOT NULL,
+    total_bytes BIGINT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_disk_usage_metrics_vps_id_time ON disk_usage_metrics (vps_id, time);
+
+-- Read-only keys for embedding desensitized status data in other dashboards.
+CREATE SEQUENCE IF NOT EXISTS public_api_keys_id_seq;
+CREATE TABLE IF NOT EXISTS public_api_keys (
+    id           INTEGER PRIMARY KEY DEFAULT nextval('public_api_keys_id_seq'),
+    user_id      INTEGER NOT NULL,
+    name         VARCHAR(255) NOT NULL,
+    key          VARCHAR(64) NOT NULL UNIQUE,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    last_used_at TIMESTAMPTZ
+);
+CREATE INDEX IF NOT EXISTS idx_public_api_keys_user_id ON public_api_keys (user_id);
+
+-- Uptime SLA report snapshots. data holds the per-VPS and per-monitor figures as JSON.
+CREATE SEQUENCE IF NOT EXISTS uptime_reports_id_seq;
+CREATE TABLE IF NOT EXISTS uptime_reports (
+    id           INTEGER PRIMARY KEY DEFAULT nextval('uptime_reports_id_seq'),
+    user_id      INTEGER NOT NULL,
+    period       VARCHAR(16) NOT NULL,
+    period_start TIMESTAMPTZ NOT NULL,
+    period_end   TIMESTAMPTZ NOT NULL,
+    generated_at TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    data         JSON NOT NULL,
+    UNIQUE (user_id, period, period_start)
+);
+
+-- Which reports are generated automatically after each period, and where they are sent.
+CREATE TABLE IF NOT EXISTS uptime_report_schedules (
+    user_id    INTEGER PRIMARY KEY,
+    weekly     BOOLEAN NOT NULL DEFAULT false,
+    monthly    BOOLEAN NOT NULL DEFAULT false,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+CREATE TABLE IF NOT EXISTS uptime_report_channels (
+    user_id    INTEGER NOT NULL,
+    channel_id INTEGER NOT NULL,
+    PRIMARY KEY (user_id, channel_id)
+);
+
+-- Cloud provider API credentials used for out-of-band power actions. token holds the encrypted API token.
+CREATE SEQUENCE IF NOT EXISTS provider_accounts_id_seq;
+CREATE TABLE IF NOT EXISTS provider_accounts (
+    id         INTEGER PRIMARY KEY DEFAULT nextval('provider_accounts_id_seq'),
+    user_id    INTEGER NOT NULL,
+    name       VARCHAR(255) NOT NULL,
+    provider   VARCHAR(32) NOT NULL,
+    token      BLOB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+CREATE INDEX IF NOT EXISTS idx_provider_accounts_user_id ON provider_accounts (user_id);
+
+-- The provider server (Hetzner server, Vultr instance, DigitalOcean droplet) behind a VPS.
+CREATE TABLE IF NOT EXISTS vps_provider_links (
+    vps_id             INTEGER PRIMARY KEY,
+    account_id         INTEGER NOT NULL,
+    provider_server_id VARCHAR(255) NOT NULL,
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+-- Audit trail of power actions. A request stays "pending" until confirmed with its code.
+CREATE SEQUENCE IF NOT EXISTS vps_power_actions_id_seq;
+CREATE TABLE IF NOT EXISTS vps_power_actions (
+    id                INTEGER PRIMARY KEY DEFAULT nextval('vps_power_actions_id_seq'),
+    vps_id            INTEGER NOT NULL,
+    user_id           INTEGER NOT NULL,
+    action            VARCHAR(16) NOT NULL,
+    status            VARCHAR(16) NOT NULL,
+    confirmation_code VARCHAR(32),
+    agent_online      BOOLEAN NOT NULL,
+    requested_at      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    expires_at        TIMESTAMPTZ NOT NULL,
+    completed_at      TIMESTAMPTZ,
+    error             TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_vps_power_actions_vps_id ON vps_power_actions (vps_id, requested_at);
+
+-- Newest performance_metrics row per VPS, upserted by the metrics writer so dashboard
+-- refreshes are point lookups rather than scans of the raw table.
+CREATE TABLE IF NOT EXISTS latest_performance_metrics (
+    vps_id                           INTEGER PRIMARY KEY,
+    time                             TIMESTAMPTZ NOT NULL,
+    cpu_usage_percent                DOUBLE NOT NULL,
+    memory_usage_bytes               BIGINT NOT NULL,
+    memory_total_bytes               BIGINT NOT NULL,
+    disk_io_read_bps                 BIGINT NOT NULL,
+    disk_io_write_bps                BIGINT NOT NULL,
+    network_rx_cumulative            BIGINT NOT NULL,
+    network_tx_cumulative            BIGINT NOT NULL,
+    swap_usage_bytes                 BIGINT NOT NULL,
+    swap_total_bytes                 BIGINT NOT NULL,
+    uptime_seconds                   BIGINT NOT NULL,
+    total_processes_count            INTEGER NOT NULL,
+    running_processes_count          INTEGER NOT NULL,
+    tcp_established_connection_count INTEGER NOT NULL,
+    network_rx_instant_bps           BIGINT NOT NULL,
+    network_tx_instant_bps           BIGINT NOT NULL,
+    total_disk_space_bytes           BIGINT NOT NULL,
+    used_disk_space_bytes            BIGINT NOT NULL
+);
+
+-- One-off backfill for databases that predate the table.
+INSERT INTO latest_performance_metrics BY NAME
+SELECT * FROM performance_metrics
+WHERE NOT EXISTS (SELECT 1 FROM latest_performance_metrics)
+QUALIFY row_number() OVER (PARTITION BY vps_id ORDER BY time DESC) = 1;
+
+-- DNS failover: point a record at a standby IP while the monitors watching the primary
+-- are down, and back once they recover. credentials holds the encrypted provider credentials.
+CREATE SEQUENCE IF NOT EXISTS dns_failover_policies_id_seq;
+CREATE TABLE IF NOT EXISTS dns_failover_policies (
+    id               INTEGER PRIMARY KEY DEFAULT nextval('dns_failover_policies_id_seq'),
+    user_id          INTEGER NOT NULL,
+    name             VARCHAR(255) NOT NULL,
+    provider         VARCHAR(32) NOT NULL,
+    credentials      BLOB NOT NULL,
+    zone_id          VARCHAR(255) NOT NULL,
+    record_name      VARCHAR(255) NOT NULL,
+    record_type      VARCHAR(8) NOT NULL DEFAULT 'A',
+    ttl              INTEGER NOT NULL DEFAULT 60,
+    primary_ip       VARCHAR(64) NOT NULL,
+    standby_ip       VARCHAR(64) NOT NULL,
+    monitor_ids      JSON NOT NULL DEFAULT '[]',
+    confirm_checks   INTEGER NOT NULL DEFAULT 3,
+    dry_run          BOOLEAN NOT NULL DEFAULT true,
+    enabled          BOOLEAN NOT NULL DEFAULT true,
+    active_target    VARCHAR(16) NOT NULL DEFAULT 'primary',
+    last_switched_at TIMESTAMPTZ,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at       TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+CREATE INDEX IF NOT EXISTS idx_dns_failover_policies_user_id ON dns_failover_policies (user_id);
+
+CREATE SEQUENCE IF NOT EXISTS dns_failover_events_id_seq;
+CREATE TABLE IF NOT EXISTS dns_failover_events (
+    id        INTEGER PRIMARY KEY DEFAULT nextval('dns_failover_events_id_seq'),
+    policy_id INTEGER NOT NULL,
+    time      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    kind      VARCHAR(16) NOT NULL,
+    from_ip   VARCHAR(64),
+    to_ip     VARCHAR(64),
+    dry_run   BOOLEAN NOT NULL,
+    details   TEXT
+);
+CREATE INDEX IF NOT EXISTS idx_dns_failover_events_policy_id_time ON dns_failover_events (policy_id, time);
+
+-- Versioned data keys for stored secrets (notification channel configs, chat
+-- bridge secrets, provider tokens, DNS credentials). Each key is wrapped by the
+-- configured NOTIFICATION_ENCRYPTION_KEY; version 0 is that master key itself.
+CREATE TABLE IF NOT EXISTS encryption_keys (
+    version     INTEGER PRIMARY KEY,
+    wrapped_key BLOB NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+-- Keys of a single organization; NULL for global keys. Versions stay unique
+-- across organizations.
+ALTER TABLE encryption_keys ADD COLUMN IF NOT EXISTS organization_id INTEGER;
+
+-- Roles: 'admin', 'operator' or 'viewer'. Accounts from before roles existed
+-- keep full control of their own resources, and the oldest account becomes the
+-- admin if there is none.
+UPDATE users SET role = 'operator' WHERE role = 'user';
+UPDATE users SET role = 'admin'
+WHERE id = (SELECT MIN(id) FROM users)
+  AND NOT EXISTS (SELECT 1 FROM users WHERE role = 'admin');
+
+-- Organizations. VPS, tags, service monitors and alert rules belong to one, and
+-- every request is scoped to the caller's active organization. Each user has a
+-- personal organization (`personal_for_user_id`) that can't be left or deleted;
+-- everything created before organizations existed moves into its owner's.
+CREATE SEQUENCE IF NOT EXISTS organizations_id_seq;
+CREATE TABLE IF NOT EXISTS organizations (
+    id                   INTEGER PRIMARY KEY DEFAULT nextval('organizations_id_seq'),
+    name                 VARCHAR(255) NOT NULL,
+    personal_for_user_id INTEGER UNIQUE,
+    created_at           TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at           TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+CREATE TABLE IF NOT EXISTS organization_members (
+    organization_id INTEGER NOT NULL,
+    user_id         INTEGER NOT NULL,
+    role            VARCHAR(16) NOT NULL DEFAULT 'member', -- 'owner' or 'member'
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    PRIMARY KEY (organization_id, user_id)
+);
+CREATE INDEX IF NOT EXISTS idx_organization_members_user_id ON organization_members (user_id);
+
+ALTER TABLE users ADD COLUMN IF NOT EXISTS active_organization_id INTEGER;
+ALTER TABLE vps ADD COLUMN IF NOT EXISTS organization_id INTEGER;
+ALTER TABLE tags ADD COLUMN IF NOT EXISTS organization_id INTEGER;
+ALTER TABLE service_monitors ADD COLUMN IF NOT EXISTS organization_id INTEGER;
+ALTER TABLE alert_rules ADD COLUMN IF NOT EXISTS organization_id INTEGER;
+ALTER TABLE provisioning_tokens ADD COLUMN IF NOT EXISTS organization_id INTEGER;
+
+INSERT INTO organizations (name, personal_for_user_id)
+SELECT username, id FROM users
+WHERE id NOT IN (SELECT personal_for_user_id FROM organizations WHERE personal_for_user_id IS NOT NULL);
+INSERT INTO organization_members (organization_id, user_id, role)
+SELECT o.id, o.personal_for_user_id, 'owner' FROM organizations o
+WHERE o.personal_for_user_id IS NOT NULL
+  AND NOT EXISTS (SELECT 1 FROM organization_members m WHERE m.organization_id = o.id AND m.user_id = o.personal_for_user_id);
+UPDATE users SET active_organization_id = (SELECT o.id FROM organizations o WHERE o.personal_for_user_id = users.id)
+WHERE active_organization_id IS NULL;
+UPDATE vps SET organization_id = (SELECT o.id FROM organizations o WHERE o.personal_for_user_id = vps.user_id)
+WHERE organization_id IS NULL;
+UPDATE tags SET organization_id = (SELECT o.id FROM organizations o WHERE o.personal_for_user_id = tags.user_id)
+WHERE organization_id IS NULL;
+UPDATE service_monitors SET organization_id = (SELECT o.id FROM organizations o WHERE o.personal_for_user_id = service_monitors.user_id)
+WHERE organization_id IS NULL;
+UPDATE alert_rules SET organization_id = (SELECT o.id FROM organizations o WHERE o.personal_for_user_id = alert_rules.user_id)
+WHERE organization_id IS NULL;
+UPDATE provisioning_tokens SET organization_id = (SELECT o.id FROM organizations o WHERE o.personal_for_user_id = provisioning_tokens.user_id)
+WHERE organization_id IS NULL;
+CREATE INDEX IF NOT EXISTS idx_vps_organization_id ON vps (organization_id);
+
+-- Personal access tokens (nn_...) for scripting the API. Only the SHA-256 of
+-- the token is stored; scopes is a JSON array of "read" / "write".
+CREATE SEQUENCE IF NOT EXISTS api_tokens_id_seq;
+CREATE TABLE IF NOT EXISTS api_tokens (
+    id           INTEGER PRIMARY KEY DEFAULT nextval('api_tokens_id_seq'),
+    user_id      INTEGER NOT NULL,
+    name         VARCHAR(255) NOT NULL,
+    token_hash   VARCHAR(64) NOT NULL UNIQUE,
+    token_prefix VARCHAR(16) NOT NULL,
+    scopes       VARCHAR NOT NULL,
+    expires_at   TIMESTAMPTZ,
+    created_at   TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    last_used_at TIMESTAMPTZ
+);
+CREATE INDEX IF NOT EXISTS idx_api_tokens_user_id ON api_tokens (user_id);
+
+-- Correlation ID of the API request that created a batch command; sent to
+-- agents with each child command so logs on both sides can be matched.
+ALTER TABLE batch_command_tasks ADD COLUMN IF NOT EXISTS trace_id VARCHAR(128);
+
+-- Quota: shortest service monitor interval allowed in the organization. NULL
+-- means the server-wide minimum.
+ALTER TABLE organizations ADD COLUMN IF NOT EXISTS min_monitor_frequency_seconds INTEGER;
+
+-- TOTP two-factor authentication. secret is encrypted like other credentials;
+-- it only takes effect once enabled (after the first code is confirmed).
+-- recovery_code_hashes is a JSON array of SHA-256 hashes of unused codes.
+CREATE TABLE IF NOT EXISTS user_totp (
+    user_id              INTEGER PRIMARY KEY,
+    secret               BLOB NOT NULL,
+    enabled              BOOLEAN NOT NULL DEFAULT false,
+    recovery_code_hashes VARCHAR NOT NULL DEFAULT '[]',
+    last_used_step       BIGINT,
+    created_at           TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    confirmed_at         TIMESTAMPTZ
+);
+
+-- Audit log of mutating API requests. before_summary/after_summary are
+-- redacted JSON snapshots, truncated; before_summary is only known for
+-- handlers that load the entity before changing it.
+CREATE SEQUENCE IF NOT EXISTS audit_log_id_seq START 1;
+CREATE TABLE IF NOT EXISTS audit_log (
+    id             INTEGER PRIMARY KEY DEFAULT nextval('audit_log_id_seq'),
+    user_id        INTEGER NOT NULL,
+    username       VARCHAR(255) NOT NULL,
+    organization_id INTEGER,
+    method         VARCHAR(16) NOT NULL,
+    route          VARCHAR NOT NULL,
+    entity_type    VARCHAR(128) NOT NULL,
+    entity_id      VARCHAR(128),
+    status_code    INTEGER NOT NULL,
+    before_summary VARCHAR,
+    after_summary  VARCHAR,
+    ip_address     VARCHAR(64),
+    request_id     VARCHAR(128),
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+CREATE INDEX IF NOT EXISTS idx_audit_log_created_at ON audit_log (created_at);
+CREATE INDEX IF NOT EXISTS idx_audit_log_user_id ON audit_log (user_id);
+
+-- Hierarchical VPS groups (e.g. region/provider/rack). vps."group" keeps the
+-- group's display path for older clients; vps.group_id is the source of truth.
+CREATE SEQUENCE IF NOT EXISTS vps_groups_id_seq START 1;
+CREATE TABLE IF NOT EXISTS vps_groups (
+    id              INTEGER PRIMARY KEY DEFAULT nextval('vps_groups_id_seq'),
+    organization_id INTEGER NOT NULL,
+    parent_id       INTEGER,
+    name            VARCHAR(255) NOT NULL,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+CREATE INDEX IF NOT EXISTS idx_vps_groups_organization_id ON vps_groups (organization_id);
+ALTER TABLE vps ADD COLUMN IF NOT EXISTS group_id INTEGER;
+
+-- Existing flat group names become top-level groups.
+INSERT INTO vps_groups (organization_id, name)
+SELECT DISTINCT v.organization_id, v."group"
+FROM vps v
+WHERE v.group_id IS NULL AND v.organization_id IS NOT NULL AND v."group" IS NOT NULL AND v."group" <> ''
+  AND NOT EXISTS (
+      SELECT 1 FROM vps_groups g
+      WHERE g.organization_id = v.organization_id AND g.parent_id IS NULL AND g.name = v."group"
+  );
+UPDATE vps SET group_id = (
+    SELECT g.id FROM vps_groups g
+    WHERE g.organization_id = vps.organization_id AND g.parent_id IS NULL AND g.name = vps."group"
+)
+WHERE group_id IS NULL AND organization_id IS NOT NULL AND "group" IS NOT NULL AND "group" <> '';
+
+-- Reseller clients: external customers VPS are managed for. markup_percent is
+-- added on top of the provider cost when billing the client.
+CREATE SEQUENCE IF NOT EXISTS clients_id_seq START 1;
+CREATE TABLE IF NOT EXISTS clients (
+    id              INTEGER PRIMARY KEY DEFAULT nextval('clients_id_seq'),
+    organization_id INTEGER NOT NULL,
+    name            VARCHAR(255) NOT NULL,
+    contact_email   VARCHAR(255),
+    notes           VARCHAR,
+    markup_percent  DOUBLE NOT NULL DEFAULT 0,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+CREATE INDEX IF NOT EXISTS idx_clients_organization_id ON clients (organization_id);
+ALTER TABLE vps ADD COLUMN IF NOT EXISTS client_id INTEGER;
+-- A share link covers one VPS, one client's VPS, or (both NULL) all VPS of the owner.
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS client_id INTEGER;
+
+-- Commands, config pushes, maintenance toggles and edits on the VPS shortly
+-- before an alert fired (JSON array), captured when the event is recorded.
+ALTER TABLE alert_events ADD COLUMN IF NOT EXISTS recent_activity VARCHAR;
+
+-- Per GPU utilization, memory, temperature and power, one row per GPU per performance snapshot.
+CREATE TABLE IF NOT EXISTS gpu_metrics (
+    time                TIMESTAMPTZ NOT NULL,
+    vps_id              INTEGER NOT NULL,
+    gpu_index           INTEGER NOT NULL,
+    name                VARCHAR NOT NULL,
+    vendor              VARCHAR(16) NOT NULL,
+    uuid                VARCHAR,
+    utilization_percent DOUBLE NOT NULL,
+    memory_used_bytes   BIGINT NOT NULL,
+    memory_total_bytes  BIGINT NOT NULL,
+    temperature_celsius DOUBLE,
+    power_draw_watts    DOUBLE
+);
+CREATE INDEX IF NOT EXISTS idx_gpu_metrics_vps_id_time ON gpu_metrics (vps_id, time);
+
+-- SMART health per drive, written whenever the agent re-reads its drives (every few minutes).
+CREATE TABLE IF NOT EXISTS disk_health_metrics (
+    time                TIMESTAMPTZ NOT NULL,
+    vps_id              INTEGER NOT NULL,
+    device              VARCHAR NOT NULL,
+    model               VARCHAR NOT NULL,
+    serial              VARCHAR,
+    smart_passed        BOOLEAN,
+    reallocated_sectors BIGINT,
+    pending_sectors     BIGINT,
+    wear_percent        DOUBLE,
+    temperature_celsius DOUBLE,
+    power_on_hours      BIGINT
+);
+CREATE INDEX IF NOT EXISTS idx_disk_health_metrics_vps_id_time ON disk_health_metrics (vps_id, time);
+
+-- Chassis, CPU and other sensor temperatures, one row per sensor per performance snapshot.
+CREATE TABLE IF NOT EXISTS temperature_metrics (
+    time                TIMESTAMPTZ NOT NULL,
+    vps_id              INTEGER NOT NULL,
+    sensor              VARCHAR NOT NULL,
+    temperature_celsius DOUBLE NOT NULL,
+    critical_celsius    DOUBLE
+);
+CREATE INDEX IF NOT EXISTS idx_temperature_metrics_vps_id_time ON temperature_metrics (vps_id, time);
+
+-- Command scripts run on a cron schedule (UTC) against fixed VPS and every VPS
+-- carrying one of the tags, resolved at each run.
+CREATE SEQUENCE IF NOT EXISTS scheduled_tasks_id_seq;
+CREATE TABLE IF NOT EXISTS scheduled_tasks (
+    id              INTEGER PRIMARY KEY DEFAULT nextval('scheduled_tasks_id_seq'),
+    user_id         INTEGER NOT NULL,
+    name            VARCHAR(255) NOT NULL,
+    cron_expression VARCHAR(128) NOT NULL,
+    script_id       INTEGER NOT NULL,
+    target_vps_ids  JSON NOT NULL DEFAULT '[]',
+    target_tag_ids  JSON NOT NULL DEFAULT '[]',
+    enabled         BOOLEAN NOT NULL DEFAULT true,
+    next_run_at     TIMESTAMPTZ,
+    last_run_at     TIMESTAMPTZ,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    UNIQUE (user_id, name)
+);
+CREATE INDEX IF NOT EXISTS idx_scheduled_tasks_next_run_at ON scheduled_tasks (next_run_at);
+
+-- One row per time a schedule came due: the batch command it started, or why it didn't.
+CREATE SEQUENCE IF NOT EXISTS scheduled_task_runs_id_seq;
+CREATE TABLE IF NOT EXISTS scheduled_task_runs (
+    id                INTEGER PRIMARY KEY DEFAULT nextval('scheduled_task_runs_id_seq'),
+    scheduled_task_id INTEGER NOT NULL,
+    scheduled_for     TIMESTAMPTZ NOT NULL,
+    started_at        TIMESTAMPTZ NOT NULL,
+    status            VARCHAR(20) NOT NULL,
+    batch_command_id  UUID,
+    target_vps_count  INTEGER NOT NULL DEFAULT 0,
+    message           VARCHAR
+);
+CREATE INDEX IF NOT EXISTS idx_scheduled_task_runs_task_id ON scheduled_task_runs (scheduled_task_id, scheduled_for);
+
+-- Keys (nni_...) for pushing application metrics to /api/ingest/metrics. Only
+-- the SHA-256 of the key is stored. metric_prefixes is a JSON array of the
+-- metric name prefixes the key may write; empty allows any name.
+CREATE SEQUENCE IF NOT EXISTS ingest_keys_id_seq;
+CREATE TABLE IF NOT EXISTS ingest_keys (
+    id                    INTEGER PRIMARY KEY DEFAULT nextval('ingest_keys_id_seq'),
+    user_id               INTEGER NOT NULL,
+    name                  VARCHAR(255) NOT NULL,
+    key_hash              VARCHAR(64) NOT NULL UNIQUE,
+    key_prefix            VARCHAR(16) NOT NULL,
+    metric_prefixes       JSON NOT NULL DEFAULT '[]',
+    rate_limit_per_minute INTEGER NOT NULL,
+    created_at            TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    last_used_at          TIMESTAMPTZ
+);
+CREATE INDEX IF NOT EXISTS idx_ingest_keys_user_id ON ingest_keys (user_id);
+
+-- Application metrics pushed through ingest keys. vps_id is set when the
+-- sample was attributed to a server; labels is a JSON object of strings.
+CREATE TABLE IF NOT EXISTS generic_metrics (
+    time    TIMESTAMPTZ NOT NULL,
+    user_id INTEGER NOT NULL,
+    vps_id  INTEGER,
+    name    VARCHAR NOT NULL,
+    value   DOUBLE NOT NULL,
+    labels  JSON NOT NULL DEFAULT '{}'
+);
+CREATE INDEX IF NOT EXISTS idx_generic_metrics_user_id_name_time ON generic_metrics (user_id, name, time);
+
+-- Searchable copy of the head of each finished child command's output. The
+-- full output stays in the log files referenced by child_command_tasks.
+CREATE TABLE IF NOT EXISTS child_command_outputs (
+    child_command_id UUID PRIMARY KEY,
+    batch_command_id UUID NOT NULL,
+    user_id          INTEGER NOT NULL,
+    vps_id           INTEGER NOT NULL,
+    stdout           VARCHAR NOT NULL DEFAULT '',
+    stderr           VARCHAR NOT NULL DEFAULT '',
+    indexed_at       TIMESTAMPTZ NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_child_command_outputs_user_id ON child_command_outputs (user_id, indexed_at);
+
+-- Typed parameters of a command script, a JSON array of {name, type, default,
+-- ...}; the script refers to them as {{name}} placeholders.
+ALTER TABLE command_scripts ADD COLUMN IF NOT EXISTS parameters JSON NOT NULL DEFAULT '[]';
+
+-- Seconds between evaluations of the rule; NULL uses ALERT_EVALUATION_INTERVAL_SECONDS.
+ALTER TABLE alert_rules ADD COLUMN IF NOT EXISTS evaluation_interval_seconds INTEGER;
+
+-- Allow/deny rules for running commands. user_ids, script_ids, group_ids and
+-- tag_ids are JSON arrays of IDs; an empty array matches anything. A VPS
+-- matches when it is in one of the groups (or a subgroup) or has one of the
+-- tags. Any matching deny wins; once an organization has an allow policy,
+-- commands need a matching one.
+CREATE SEQUENCE IF NOT EXISTS command_policies_id_seq;
+CREATE TABLE IF NOT EXISTS command_policies (
+    id              INTEGER PRIMARY KEY DEFAULT nextval('command_policies_id_seq'),
+    organization_id INTEGER NOT NULL,
+    name            VARCHAR(255) NOT NULL,
+    effect          VARCHAR(16) NOT NULL,
+    user_ids        JSON NOT NULL DEFAULT '[]',
+    script_ids      JSON NOT NULL DEFAULT '[]',
+    group_ids       JSON NOT NULL DEFAULT '[]',
+    tag_ids         JSON NOT NULL DEFAULT '[]',
+    enabled         BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+CREATE INDEX IF NOT EXISTS idx_command_policies_organization_id ON command_policies (organization_id);
+
+-- Client certificate issued to a VPS's agent (mutual TLS), by its SHA-256
+-- fingerprint. While a VPS has one, its agent must present it to connect.
+CREATE TABLE IF NOT EXISTS agent_client_certificates (
+    vps_id      INTEGER PRIMARY KEY,
+    fingerprint VARCHAR(64) NOT NULL,
+    issued_at   TIMESTAMPTZ NOT NULL,
+    expires_at  TIMESTAMPTZ NOT NULL
+);
+
+-- Agent builds the server hands out, one per version and target triple.
+-- Uploaded files live under <data_dir>/agent_releases/<version>/; `url` is set
+-- instead for artifacts hosted elsewhere, which downloads redirect to.
+CREATE TABLE IF NOT EXISTS agent_releases (
+    version     VARCHAR(64) NOT NULL,
+    target      VARCHAR(64) NOT NULL,
+    file_name   VARCHAR(255) NOT NULL,
+    url         VARCHAR,
+    size_bytes  BIGINT NOT NULL,
+    sha256      VARCHAR(64) NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (version, target)
+);
+
+-- Traffic per VPS and UTC day, accumulated from the deltas of the cumulative
+-- network counters as metrics arrive. The hourly task rolls it up into
+-- vps_monthly_traffic.
+CREATE TABLE IF NOT EXISTS vps_daily_traffic (
+    vps_id   INTEGER NOT NULL,
+    day      DATE NOT NULL,
+    rx_bytes BIGINT NOT NULL DEFAULT 0,
+    tx_bytes BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (vps_id, day)
+);
+
+-- month is the first day of the month.
+CREATE TABLE IF NOT EXISTS vps_monthly_traffic (
+    vps_id   INTEGER NOT NULL,
+    month    DATE NOT NULL,
+    total_rx BIGINT NOT NULL DEFAULT 0,
+    total_tx BIGINT NOT NULL DEFAULT 0,
+    PRIMARY KEY (vps_id, month)
+);
+
+-- Outgoing webhooks. event_types filters what is delivered; an empty list
+-- subscribes to every event. The secret signs each delivery and is encrypted.
+CREATE SEQUENCE IF NOT EXISTS webhook_subscriptions_id_seq;
+CREATE TABLE IF NOT EXISTS webhook_subscriptions (
+    id              INTEGER PRIMARY KEY DEFAULT nextval('webhook_subscriptions_id_seq'),
+    organization_id INTEGER NOT NULL,
+    user_id         INTEGER NOT NULL,
+    name            VARCHAR(255) NOT NULL,
+    url             VARCHAR NOT NULL,
+    secret          BLOB NOT NULL,
+    event_types     JSON NOT NULL DEFAULT '[]',
+    is_active       BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at      TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+-- The event outbox: one row per event and subscription, written in the same
+-- transaction as the change that raised the event. status is 'pending',
+-- 'delivered' or 'dead' (out of attempts).
+CREATE SEQUENCE IF NOT EXISTS webhook_deliveries_id_seq;
+CREATE TABLE IF NOT EXISTS webhook_deliveries (
+    id               BIGINT PRIMARY KEY DEFAULT nextval('webhook_deliveries_id_seq'),
+    subscription_id  INTEGER NOT NULL,
+    event_id         UUID NOT NULL,
+    event_type       VARCHAR(64) NOT NULL,
+    payload          JSON NOT NULL,
+    status           VARCHAR(16) NOT NULL DEFAULT 'pending',
+    attempts         INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at  TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    last_attempt_at  TIMESTAMPTZ,
+    last_status_code INTEGER,
+    last_error       VARCHAR,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_due ON webhook_deliveries (status, next_attempt_at);
+CREATE INDEX IF NOT EXISTS idx_webhook_deliveries_subscription ON webhook_deliveries (subscription_id, created_at DESC);
+
+-- Service monitor alert rules (metric_type 'monitor_down'): the monitor to
+-- watch, NULL for every monitor, and the consecutive successful checks that
+-- resolve an alert. Their events record the monitor they fired for.
+ALTER TABLE alert_rules ADD COLUMN IF NOT EXISTS monitor_id INTEGER;
+ALTER TABLE alert_rules ADD COLUMN IF NOT EXISTS recovery_threshold INTEGER;
+ALTER TABLE alert_events ADD COLUMN IF NOT EXISTS monitor_id INTEGER;
+
+-- Per-channel message templates: a JSON object of Tera templates keyed by
+-- locale ("en", "zh-CN") or "default"; empty uses the built-in template.
+ALTER TABLE notification_channels ADD COLUMN IF NOT EXISTS message_templates JSON NOT NULL DEFAULT '{}';
+
+-- Alert severities ('info', 'warning' or 'critical'). Events keep the
+-- severity their rule had when they fired. A channel only receives alerts of
+-- the severities in its JSON array; an empty array receives all of them.
+ALTER TABLE alert_rules ADD COLUMN IF NOT EXISTS severity VARCHAR(16) NOT NULL DEFAULT 'warning';
+ALTER TABLE alert_events ADD COLUMN IF NOT EXISTS severity VARCHAR(16) NOT NULL DEFAULT 'warning';
+ALTER TABLE notification_channels ADD COLUMN IF NOT EXISTS severities JSON NOT NULL DEFAULT '[]';
+
+-- Digest channels ('digest' delivery) don't send notifications as they are
+-- raised. These queue up and go out as one summary whenever digest_schedule,
+-- a five-field cron expression in UTC, comes due at next_digest_at.
+ALTER TABLE notification_channels ADD COLUMN IF NOT EXISTS delivery VARCHAR(16) NOT NULL DEFAULT 'immediate';
+ALTER TABLE notification_channels ADD COLUMN IF NOT EXISTS digest_schedule VARCHAR(128);
+ALTER TABLE notification_channels ADD COLUMN IF NOT EXISTS next_digest_at TIMESTAMPTZ;
+
+-- Notifications waiting for the next digest of their channel. kind is
+-- 'notification' or 'renewal'; context holds the template variables.
+CREATE SEQUENCE IF NOT EXISTS notification_digest_items_id_seq;
+CREATE TABLE IF NOT EXISTS notification_digest_items (
+    id         BIGINT PRIMARY KEY DEFAULT nextval('notification_digest_items_id_seq'),
+    channel_id INTEGER NOT NULL,
+    kind       VARCHAR(16) NOT NULL,
+    context    JSON NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT current_timestamp
+);
+CREATE INDEX IF NOT EXISTS idx_notification_digest_items_channel ON notification_digest_items (channel_id, id);
+
+-- Batch command targeting. Each child task records what selected its VPS:
+-- 'vps' for an explicit ID, a selector such as 'tag=3,status=online', or
+-- 'selection:<name>'. Saved selections are named, reusable sets of explicit
+-- VPS IDs and selectors (a JSON array), resolved whenever they are used.
+ALTER TABLE child_command_tasks ADD COLUMN IF NOT EXISTS matched_selector VARCHAR(255);
+
+CREATE SEQUENCE IF NOT EXISTS vps_selections_id_seq;
+CREATE TABLE IF NOT EXISTS vps_selections (
+    id             INTEGER PRIMARY KEY DEFAULT nextval('vps_selections_id_seq'),
+    user_id        INTEGER NOT NULL,
+    name           VARCHAR(255) NOT NULL,
+    target_vps_ids JSON NOT NULL DEFAULT '[]',
+    selectors      JSON NOT NULL DEFAULT '[]',
+    created_at     TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    updated_at     TIMESTAMPTZ NOT NULL DEFAULT current_timestamp,
+    UNIQUE (user_id, name)
+);
+
+-- Batch command execution strategies. Child tasks run in waves (0 first) as
+-- the batch's strategy (parallel, rolling or canary) plans them.
+ALTER TABLE child_command_tasks ADD COLUMN IF NOT EXISTS wave INTEGER DEFAULT 0;
+
+-- Batch command timeouts and retries. Agents kill child commands running
+-- longer than timeout_seconds; failed child tasks are retried until they
+-- have had max_attempts attempts, backing off exponentially from
+-- retry_backoff_seconds. 'Aborting' batches are waiting for their agents to
+-- kill what's running.
+ALTER TABLE batch_command_tasks ADD COLUMN IF NOT EXISTS timeout_seconds INTEGER;
+ALTER TABLE batch_command_tasks ADD COLUMN IF NOT EXISTS max_attempts INTEGER DEFAULT 1;
+ALTER TABLE batch_command_tasks ADD COLUMN IF NOT EXISTS retry_backoff_seconds INTEGER DEFAULT 0;
+ALTER TABLE child_command_tasks ADD COLUMN IF NOT EXISTS attempt INTEGER DEFAULT 1;
+ALTER TABLE child_command_tasks ADD COLUMN IF NOT EXISTS next_attempt_at TIMESTAMPTZ;
+
+-- Log rules: regex matched against the lines agents tail from `[[log_tail]]` files.
+ALTER TABLE alert_rules ADD COLUMN IF NOT EXISTS log_pattern VARCHAR;
+
+-- Tailed log lines that matched a log rule, counted over the rule's duration.
+CREATE TABLE IF NOT EXISTS log_matches (
+    time          TIMESTAMPTZ NOT NULL,
+    vps_id        INTEGER NOT NULL,
+    alert_rule_id INTEGER NOT NULL,
+    source        VARCHAR NOT NULL,
+    line          TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_log_matches_rule_vps_time ON log_matches (alert_rule_id, vps_id, time);
+
+-- Share links show the VPS of the organization they were created in, so
+-- they follow VPS moved between organizations instead of their creator.
+ALTER TABLE share_links ADD COLUMN IF NOT EXISTS organization_id INTEGER;
+UPDATE share_links SET organization_id = (SELECT o.id FROM organizations o WHERE o.personal_for_user_id = share_links.user_id)
+WHERE organization_id IS NULL;
@@ -3,7 +3,11 @@ import { useForm, Controller } from 'react-hook-form';
 import type { SubmitHandler } from 'react-hook-form';
 import * as alertService from '../services/alertService';
 import { getAllChannels as getAllNotificationChannels } from '../services/notificationService';
-import type { AlertRule, CreateAlertRulePayload, UpdateAlertRulePayload, VpsListItemResponse, ChannelResponse } from '../types';
+import { getMetricCatalog, type MetricDescriptor } from '../services/metricCatalogService';
+import { getMonitors } from '../services/serviceMonitorService';
+import { customMetricType, getCustomMetrics } from '../services/customMetricService';
+import { ALERT_SEVERITIES } from '../types';
+import type { AlertRule, AlertSeverity, CreateAlertRulePayload, UpdateAlertRulePayload, VpsListItemResponse, ChannelResponse, ServiceMonitor } from '../types';
 import { Button } from '@/components/ui/button';
 import { Dialog, DialogContent, DialogHeader, DialogTitle, DialogFooter, DialogDescription } from '@/components/ui/dialog';
 import { Input } from '@/components/ui/input';
@@ -21,8 +25,15 @@ type AlertRuleFormInputs = {
   durationSeconds: number;
   notificationChannelIds: number[];
   cooldownSeconds: number;
+  monitorId: string;
+  recoveryThreshold: number;
+  logPattern: string;
+  severity: AlertSeverity;
 };
 
+const MONITOR_DOWN_METRIC = 'monitor_down';
+const LOG_MATCHES_METRIC = 'log_matches';
+
 interface AlertRuleModalProps {
   isOpen: boolean;
   onOpenChange: (isOpen: boolean) => void;
@@ -37,16 +48,46 @@ const AlertRuleModal: React.FC<AlertRuleModalProps> = ({ isOpen, onOpenChange, o
     register,
     handleSubmit,
     reset,
+    watch,
     formState: { errors, isSubmitting },
   } = useForm<AlertRuleFormInputs>();
 
   const [notificationChannels, setNotificationChannels] = useState<ChannelResponse[]>([]);
+  const [alertMetrics, setAlertMetrics] = useState<MetricDescriptor[]>([]);
+  const [monitors, setMonitors] = useState<ServiceMonitor[]>([]);
+  const [customMetricNames, setCustomMetricNames] = useState<string[]>([]);
+  const isMonitorRule = watch('metricType') === MONITOR_DOWN_METRIC;
+  const isLogRule = watch('metricType') === LOG_MATCHES_METRIC;
+  const selectedVpsId = watch('vpsId');
+  const selectedMetricType = watch('metricType');
+
+  // Custom metrics are per server, so they are only offered once one is selected.
+  useEffect(() => {
+    if (!isOpen || !selectedVpsId || selectedVpsId === 'global') {
+      setCustomMetricNames([]);
+      return;
+    }
+    getCustomMetrics(parseInt(selectedVpsId, 10))
+      .then(series => setCustomMetricNames([...new Set(series.map(s => s.name))]))
+      .catch(err => console.error("Failed to fetch custom metrics", err));
+  }, [isOpen, selectedVpsId]);
+
+  const customMetricTypes = customMetricNames.map(customMetricType);
+  if (selectedMetricType?.startsWith('custom:') && !customMetricTypes.includes(selectedMetricType)) {
+    customMetricTypes.push(selectedMetricType);
+  }
 
   useEffect(() => {
     if (isOpen) {
       getAllNotificationChannels()
         .then(setNotificationChannels)
         .catch(err => console.error("Failed to fetch notification channels", err));
+      getMetricCatalog()
+        .then(metrics => setAlertMetrics(metrics.filter(m => m.usage.alertRule)))
+        .catch(err => console.error("Failed to fetch the metric catalog", err));
+      getMonitors()
+        .then(setMonitors)
+        .catch(err => console.error("Failed to fetch service monitors", err));
 
       if (rule) {
         reset({
@@ -58,6 +99,10 @@ const AlertRuleModal: React.FC<AlertRuleModalProps> = ({ isOpen, onOpenChange, o
           durationSeconds: rule.durationSeconds,
           notificationChannelIds: rule.notificationChannelIds || [],
           cooldownSeconds: rule.cooldownSeconds || 300,
+          monitorId: rule.monitorId?.toString() || 'all',
+          recoveryThreshold: rule.recoveryThreshold || 2,
+          logPattern: rule.logPattern || '',
+          severity: rule.severity || 'warning',
         });
       } else {
         reset({
@@ -69,6 +114,10 @@ const AlertRuleModal: React.FC<AlertRuleModalProps> = ({ isOpen, onOpenChange, o
           durationSeconds: 300,
           notificationChannelIds: [],
           cooldownSeconds: 300,
+          monitorId: 'all',
+          recoveryThreshold: 2,
+          logPattern: '',
+          severity: 'warning',
         });
       }
     }
@@ -76,8 +125,16 @@ const AlertRuleModal: React.FC<AlertRuleModalProps> = ({ isOpen, onOpenChange, o
 
   const onSubmit: SubmitHandler<AlertRuleFormInputs> = async (data) => {
     try {
+      const { monitorId, recoveryThreshold, logPattern, ...rest } = data;
       const payload = {
-        ...data,
+        ...rest,
+        ...(data.metricType === MONITOR_DOWN_METRIC
+          ? {
+              monitorId: monitorId === 'all' ? null : parseInt(monitorId, 10),
+              recoveryThreshold: Number(recoveryThreshold),
+            }
+          : {}),
+        ...(data.metricType === LOG_MATCHES_METRIC ? { logPattern } : {}),
         vpsId: data.vpsId === 'global' ? null : parseInt(data.vpsId, 10),
         threshold: Number(data.threshold),
         durationSeconds: Number(data.durationSeconds),
@@ -97,7 +154,6 @@ const AlertRuleModal: React.FC<AlertRuleModalProps> = ({ isOpen, onOpenChange, o
     }
   };
 
-  const metricTypes = ["cpu_usage_percent", "memory_usage_percent", "network_rx_instant_bps", "network_tx_instant_bps"];
   const comparisonOperators = [">", "<", "=", ">=", "<="];
 
   return (
@@ -116,6 +172,24 @@ const AlertRuleModal: React.FC<AlertRuleModalProps> = ({ isOpen, onOpenChange, o
             {errors.name && <p className="text-sm text-destructive">{errors.name.message}</p>}
           </div>
 
+          <div className="space-y-2">
+            <Label htmlFor="severity">Severity</Label>
+            <Controller
+              name="severity"
+              control={control}
+              render={({ field }) => (
+                <Select onValueChange={field.onChange} defaultValue={field.value} value={field.value}>
+                  <SelectTrigger>
+                    <SelectValue placeholder="Select a severity" />
+                  </SelectTrigger>
+                  <SelectContent>
+                    {ALERT_SEVERITIES.map(s => <SelectItem key={s} value={s}>{s.charAt(0).toUpperCase() + s.slice(1)}</SelectItem>)}
+                  </SelectContent>
+                </Select>
+              )}
+            />
+          </div>
+
           <div className="space-y-2">
             <Label htmlFor="vpsId">Target VPS (Optional)</Label>
             <Controller
@@ -146,16 +220,54 @@ const AlertRuleModal: React.FC<AlertRuleModalProps> = ({ isOpen, onOpenChange, o
                     <SelectValue placeholder="Select a metric type" />
                   </SelectTrigger>
                   <SelectContent>
-                    {metricTypes.map(mt => <SelectItem key={mt} value={mt}>{mt.replace(/_/g, ' ').replace(/\b\w/g, l => l.toUpperCase())}</SelectItem>)}
+                    {alertMetrics.map(m => <SelectItem key={m.name} value={m.name} title={m.description}>{m.name.replace(/_/g, ' ').replace(/\b\w/g, l => l.toUpperCase())}</SelectItem>)}
+                    {customMetricTypes.map(type => <SelectItem key={type} value={type}>{type}</SelectItem>)}
                   </SelectContent>
                 </Select>
               )}
             />
           </div>
 
+          {isMonitorRule && (
+            <div className="grid grid-cols-2 gap-4">
+              <div className="space-y-2">
+                <Label htmlFor="monitorId">Monitor</Label>
+                <Controller
+                  name="monitorId"
+                  control={control}
+                  render={({ field }) => (
+                    <Select onValueChange={field.onChange} defaultValue={field.value} value={field.value}>
+                      <SelectTrigger>
+                        <SelectValue placeholder="Select a monitor" />
+                      </SelectTrigger>
+                      <SelectContent>
+                        <SelectItem value="all">All monitors</SelectItem>
+                        {monitors.map(m => <SelectItem key={m.id} value={m.id.toString()}>{m.name}</SelectItem>)}
+                      </SelectContent>
+                    </Select>
+                  )}
+                />
+              </div>
+              <div className="space-y-2">
+                <Label htmlFor="recoveryThreshold">Successful checks to resolve</Label>
+                <Input id="recoveryThreshold" type="number" {...register('recoveryThreshold', { valueAsNumber: true, min: { value: 1, message: "Must be at least 1" } })} />
+                {errors.recoveryThreshold && <p className="text-sm text-destructive">{errors.recoveryThreshold.message}</p>}
+              </div>
+            </div>
+          )}
+
+          {isLogRule && (
+            <div className="space-y-2">
+              <Label htmlFor="logPattern">Log pattern (regex)</Label>
+              <Input id="logPattern" placeholder="Failed password for" {...register('logPattern', { required: isLogRule ? 'Log pattern is required' : false })} />
+              <p className="text-sm text-muted-foreground">Matched against the lines of the agent's [[log_tail]] files; the threshold counts matching lines within the duration.</p>
+              {errors.logPattern && <p className="text-sm text-destructive">{errors.logPattern.message}</p>}
+            </div>
+          )}
+
           <div className="grid grid-cols-2 gap-4">
             <div className="space-y-2">
-              <Label htmlFor="threshold">Threshold</Label>
+              <Label htmlFor="threshold">{isMonitorRule ? 'Failed checks to fire' : isLogRule ? 'Matching lines' : 'Threshold'}</Label>
               <Input id="threshold" type="number" {...register('threshold', { required: 'Threshold is required', valueAsNumber: true })} />
               {errors.threshold && <p className="text-sm text-destructive">{errors.threshold.message}</p>}
             </div>
@@ -210,7 +322,7 @@ const AlertRuleModal: React.FC<AlertRuleModalProps> = ({ isOpen, onOpenChange, o
                         }}
                       />
                       <Label htmlFor={channel.id.toString()} className="font-normal">
-                        {channel.name} <span className="text-muted-foreground">({channel.channelType})</span>
+                        {channel.name} <span className="text-muted-foreground">({[channel.channelType, ...(channel.severities ?? [])].join(' · ')})</span>
                       </Label>
                     </div>
                   ))}
@@ -39,7 +39,7 @@ const HistoricalMonitorChart: React.FC<HistoricalMonitorChartProps> = ({ monitor
     const dataKeys = new Set<string>();
     data.forEach(point => {
       Object.keys(point).forEach(key => {
-        if (key !== 'time') {
+        if (key !== 'time' && key !== 'inMaintenance') {
           dataKeys.add(key);
         }
       });
@@ -63,6 +63,26 @@ const HistoricalMonitorChart: React.FC<HistoricalMonitorChartProps> = ({ monitor
   // TODO: Implement downtimeAreas calculation in useMetrics hook
   const downtimeAreas: { x1: number, x2: number }[] = [];
 
+  // Runs of points where every check fell in a maintenance window.
+  const maintenanceAreas = useMemo(() => {
+    const areas: { x1: number, x2: number }[] = [];
+    let start: number | null = null;
+    let last: number | null = null;
+    for (const point of data) {
+      if (point.inMaintenance === 1) {
+        start ??= point.time;
+        last = point.time;
+      } else if (start !== null && last !== null) {
+        areas.push({ x1: start, x2: last });
+        start = null;
+      }
+    }
+    if (start !== null && last !== null) {
+      areas.push({ x1: start, x2: last });
+    }
+    return areas;
+  }, [data]);
+
   const handleLegendClick = (data: { dataKey: string }) => {
     const { dataKey } = data;
     setHiddenLines(prev => ({ ...prev, [dataKey]: !prev[dataKey] }));
@@ -168,6 +188,9 @@ const HistoricalMonitorChart: React.FC<HistoricalMonitorChartProps> = ({ monitor
                 </div>
               )}
             />
+            {maintenanceAreas.map((area, index) => (
+              <ReferenceArea key={`maintenance-${index}`} x1={area.x1} x2={area.x2} stroke="transparent" fill="hsl(var(--muted-foreground))" fillOpacity={0.15} ifOverflow="extendDomain" label={{ value: '维护', position: 'insideTop', fontSize: 11 }} />
+            ))}
             {downtimeAreas.map((area, index) => (
               <ReferenceArea key={index} x1={area.x1} x2={area.x2} stroke="transparent" fill="hsl(var(--destructive))" fillOpacity={0.15} ifOverflow="extendDomain" />
             ))}
@@ -1,8 +1,11 @@
 import React from 'react';
 import { Outlet } from 'react-router-dom';
 import Navbar from './Navbar';
+import { useBranding } from '@/hooks/useBranding';
 
 const Layout: React.FC = () => {
+  const branding = useBranding();
+
   return (
     <div className="min-h-screen flex flex-col">
       <Navbar />
@@ -10,7 +13,21 @@ const Layout: React.FC = () => {
         <Outlet />
       </main>
       <footer className="bg-background text-muted-foreground text-center p-4 text-sm mt-8 border-t">
-        © {new Date().getFullYear()} NodeNexus. All rights reserved.
+        {branding && branding.footerLinks.length > 0 && (
+          <nav className="mb-2 flex flex-wrap justify-center gap-x-4 gap-y-1">
+            {branding.footerLinks.map(link => (
+              <a
+                key={`${link.label}-${link.url}`}
+                href={link.url}
+                className="hover:text-foreground hover:underline"
+                {...(link.url.startsWith('/') ? {} : { target: '_blank', rel: 'noopener noreferrer' })}
+              >
+                {link.label}
+              </a>
+            ))}
+          </nav>
+        )}
+        © {new Date().getFullYear()} {branding?.title ?? 'NodeNexus'}. All rights reserved.
       </footer>
     </div>
   );
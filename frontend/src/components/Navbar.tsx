@@ -4,6 +4,7 @@ import { useAuthStore } from '../store/authStore';
 import { ServerIcon } from './Icons';
 import UserMenu from './UserMenu';
 import { ThemeToggle } from './ThemeToggle';
+import { useBranding } from '@/hooks/useBranding';
 import { Button, buttonVariants } from '@/components/ui/button';
 import { cn } from '@/lib/utils';
 
@@ -16,6 +17,7 @@ const navLinks = [
 
 const Navbar: React.FC = () => {
   const { isAuthenticated } = useAuthStore();
+  const branding = useBranding();
 
   return (
     <header className="sticky top-0 z-50 w-full border-b border-border/40 bg-background/95 backdrop-blur supports-[backdrop-filter]:bg-background/60">
@@ -23,8 +25,12 @@ const Navbar: React.FC = () => {
         <div className="flex items-center space-x-8">
           <div className="flex-shrink-0">
             <Link to="/" className="flex items-center space-x-2">
-              <ServerIcon className="h-8 w-8 text-primary" />
-              <span className="text-xl font-semibold text-foreground">NodeNexus</span>
+              {branding?.logoUrl ? (
+                <img src={branding.logoUrl} alt="" className="h-8 w-8 object-contain" />
+              ) : (
+                <ServerIcon className="h-8 w-8 text-primary" />
+              )}
+              <span className="text-xl font-semibold text-foreground">{branding?.title ?? 'NodeNexus'}</span>
             </Link>
           </div>
           <nav className="hidden items-center space-x-1 md:flex">
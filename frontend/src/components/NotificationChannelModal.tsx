@@ -2,12 +2,15 @@ import React, { useEffect } from 'react';
 import { useForm, Controller } from 'react-hook-form';
 import type { SubmitHandler } from 'react-hook-form';
 import { useTranslation } from 'react-i18next';
-import type { ChannelTemplate, ChannelResponse, CreateChannelRequest, UpdateChannelRequest } from '../types';
+import { ALERT_SEVERITIES } from '../types';
+import type { AlertSeverity, ChannelDelivery, ChannelTemplate, ChannelResponse, CreateChannelRequest, UpdateChannelRequest } from '../types';
 import DynamicForm from './DynamicForm';
 import { Button } from '@/components/ui/button';
 import { Dialog, DialogContent, DialogHeader, DialogTitle, DialogFooter, DialogDescription } from '@/components/ui/dialog';
 import { Input } from '@/components/ui/input';
 import { Label } from '@/components/ui/label';
+import { Textarea } from '@/components/ui/textarea';
+import { Checkbox } from '@/components/ui/checkbox';
 import { Select, SelectContent, SelectItem, SelectTrigger, SelectValue } from '@/components/ui/select';
 import { Alert, AlertDescription } from '@/components/ui/alert';
 import { RefreshCwIcon as SpinnerIcon } from '@/components/Icons';
@@ -16,8 +19,18 @@ type FormInputs = {
   name: string;
   channelType: string;
   config: Record<string, unknown>;
+  messageTemplate: string;
+  severities: AlertSeverity[];
+  delivery: ChannelDelivery;
+  digestSchedule: string;
 };
 
+/** Schedules offered for digests; any cron expression can be typed in. */
+const DIGEST_PRESETS = [
+  { key: 'daily', schedule: '0 9 * * *' },
+  { key: 'weekly', schedule: '0 9 * * 1' },
+] as const;
+
 interface NotificationChannelModalProps {
   isOpen: boolean;
   onOpenChange: (isOpen: boolean) => void;
@@ -45,6 +58,7 @@ const NotificationChannelModal: React.FC<NotificationChannelModalProps> = ({
   } = useForm<FormInputs>();
 
   const selectedChannelType = watch('channelType');
+  const delivery = watch('delivery');
   const selectedTemplate = templates.find(t => t.channelType === selectedChannelType);
 
   useEffect(() => {
@@ -61,12 +75,20 @@ const NotificationChannelModal: React.FC<NotificationChannelModalProps> = ({
           name: editingChannel.name,
           channelType: editingChannel.channelType,
           config: initialConfig,
+          messageTemplate: editingChannel.messageTemplates?.default ?? '',
+          severities: editingChannel.severities ?? [],
+          delivery: editingChannel.delivery ?? 'immediate',
+          digestSchedule: editingChannel.digestSchedule ?? DIGEST_PRESETS[0].schedule,
         });
       } else {
         reset({
           name: '',
           channelType: '',
           config: {},
+          messageTemplate: '',
+          severities: [],
+          delivery: 'immediate',
+          digestSchedule: DIGEST_PRESETS[0].schedule,
         });
       }
     }
@@ -103,10 +125,22 @@ const NotificationChannelModal: React.FC<NotificationChannelModalProps> = ({
         }
     });
 
+    // The form edits the default template; templates for specific languages are kept.
+    const messageTemplates = { ...(editingChannel?.messageTemplates ?? {}) };
+    if (data.messageTemplate.trim()) {
+      messageTemplates.default = data.messageTemplate;
+    } else {
+      delete messageTemplates.default;
+    }
+
     const submissionData: CreateChannelRequest | UpdateChannelRequest = {
       name: data.name,
       channelType: data.channelType,
       config: finalConfig,
+      messageTemplates,
+      severities: data.severities,
+      delivery: data.delivery,
+      digestSchedule: data.digestSchedule.trim() || null,
     };
 
     await onSubmit(submissionData);
@@ -173,6 +207,92 @@ const NotificationChannelModal: React.FC<NotificationChannelModalProps> = ({
             </Alert>
           ) : null}
 
+          {selectedTemplate && (
+            <div className="space-y-2">
+              <Label htmlFor="messageTemplate">{t('notificationsPage.modal.labels.messageTemplate')}</Label>
+              <Textarea id="messageTemplate" rows={4} className="font-mono text-sm" placeholder="[{{ vps_name }}] {{ message }}" {...register('messageTemplate')} />
+              <p className="text-sm text-muted-foreground">{t('notificationsPage.modal.labels.messageTemplateHelp')}</p>
+            </div>
+          )}
+
+          {selectedTemplate && (
+            <div className="space-y-2">
+              <Label>{t('notificationsPage.modal.labels.severities')}</Label>
+              <Controller
+                name="severities"
+                control={control}
+                render={({ field }) => (
+                  <div className="flex flex-wrap gap-4">
+                    {ALERT_SEVERITIES.map(severity => (
+                      <div key={severity} className="flex items-center space-x-2">
+                        <Checkbox
+                          id={`severity-${severity}`}
+                          checked={field.value?.includes(severity)}
+                          onCheckedChange={(checked) => field.onChange(
+                            checked
+                              ? [...(field.value ?? []), severity]
+                              : (field.value ?? []).filter(s => s !== severity),
+                          )}
+                        />
+                        <Label htmlFor={`severity-${severity}`} className="font-normal">
+                          {t(`notificationsPage.modal.severities.${severity}`)}
+                        </Label>
+                      </div>
+                    ))}
+                  </div>
+                )}
+              />
+              <p className="text-sm text-muted-foreground">{t('notificationsPage.modal.labels.severitiesHelp')}</p>
+            </div>
+          )}
+
+          {selectedTemplate && (
+            <div className="space-y-2">
+              <Label htmlFor="delivery">{t('notificationsPage.modal.labels.delivery')}</Label>
+              <Controller
+                name="delivery"
+                control={control}
+                render={({ field }) => (
+                  <Select onValueChange={field.onChange} value={field.value}>
+                    <SelectTrigger id="delivery">
+                      <SelectValue />
+                    </SelectTrigger>
+                    <SelectContent>
+                      <SelectItem value="immediate">{t('notificationsPage.modal.delivery.immediate')}</SelectItem>
+                      <SelectItem value="digest">{t('notificationsPage.modal.delivery.digest')}</SelectItem>
+                    </SelectContent>
+                  </Select>
+                )}
+              />
+              {delivery === 'digest' && (
+                <>
+                  <div className="flex gap-2">
+                    <Input
+                      id="digestSchedule"
+                      className="font-mono"
+                      placeholder="0 9 * * *"
+                      {...register('digestSchedule', {
+                        validate: value => delivery !== 'digest' || value.trim() !== '' || t('notificationsPage.modal.errors.digestScheduleRequired'),
+                      })}
+                    />
+                    {DIGEST_PRESETS.map(preset => (
+                      <Button
+                        key={preset.key}
+                        type="button"
+                        variant="outline"
+                        onClick={() => setValue('digestSchedule', preset.schedule)}
+                      >
+                        {t(`notificationsPage.modal.delivery.${preset.key}`)}
+                      </Button>
+                    ))}
+                  </div>
+                  {errors.digestSchedule && <p className="text-sm text-destructive">{errors.digestSchedule.message}</p>}
+                  <p className="text-sm text-muted-foreground">{t('notificationsPage.modal.labels.digestScheduleHelp')}</p>
+                </>
+              )}
+            </div>
+          )}
+
           <DialogFooter>
             <Button type="button" variant="outline" onClick={() => onOpenChange(false)}>{t('common.actions.cancel')}</Button>
             <Button type="submit" disabled={isSubmitting || !selectedTemplate}>
@@ -27,6 +27,7 @@ const ScriptFormModal: React.FC<ScriptFormModalProps> = ({ isOpen, onClose, onSa
         language: 'shell',
         script_content: '',
         working_directory: '.',
+        parameters: [],
     });
 
     useEffect(() => {
@@ -36,6 +37,7 @@ const ScriptFormModal: React.FC<ScriptFormModalProps> = ({ isOpen, onClose, onSa
             language: 'shell',
             script_content: '',
             working_directory: '.',
+            parameters: [],
         });
 
         if (initialData) {
@@ -45,6 +47,7 @@ const ScriptFormModal: React.FC<ScriptFormModalProps> = ({ isOpen, onClose, onSa
                 language: initialData.language,
                 script_content: initialData.script_content,
                 working_directory: initialData.working_directory,
+                parameters: initialData.parameters ?? [],
             });
         } else {
             setFormData(resetForm());
@@ -70,7 +70,7 @@ export const ServiceMonitorChart: React.FC<{ results: ServiceMonitorResult[] }>
         for (let i = 0; i < timePoints.length; i++) {
             const time = timePoints[i];
             const isAnyDown = Object.values(groupedByMonitorId).some(monitorResults =>
-                monitorResults.some(r => new Date(r.time).getTime() === time && !r.isUp)
+                monitorResults.some(r => new Date(r.time).getTime() === time && !r.isUp && !r.inMaintenance)
             );
 
             if (isAnyDown && !downtimeStart) {
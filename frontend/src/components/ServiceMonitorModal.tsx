@@ -24,11 +24,15 @@ import { useTranslation } from 'react-i18next';
 const httpMonitorConfigSchema = z.object({
   expected_status_codes: z.array(z.number()).optional(),
   response_body_match: z.string().optional(),
+  record_type: z.enum(['A', 'AAAA', 'CNAME', 'MX', 'TXT']).optional(),
+  expected_values: z.array(z.string()).optional(),
+  resolver: z.string().optional(),
+  packet_count: z.number().min(1).max(20).optional(),
 }).optional();
 
 const formSchema = z.object({
   name: z.string().min(1, "common.errors.validation.nameRequired"),
-  monitorType: z.enum(['http', 'ping', 'tcp']),
+  monitorType: z.enum(['http', 'ping', 'tcp', 'dns']),
   target: z.string().min(1, "Target is required"),
   frequencySeconds: z.number().min(10),
   timeoutSeconds: z.number().min(1),
@@ -102,6 +106,14 @@ const ServiceMonitorModal: React.FC<ServiceMonitorModalProps> = ({ isOpen, onClo
                 expected_status_codes: monitorToEdit.monitorConfig.expected_status_codes,
                 response_body_match: 'response_body_match' in monitorToEdit.monitorConfig ? monitorToEdit.monitorConfig.response_body_match : undefined,
               }
+            : monitorToEdit.monitorType === 'ping' && monitorToEdit.monitorConfig && 'packet_count' in monitorToEdit.monitorConfig
+            ? { packet_count: monitorToEdit.monitorConfig.packet_count }
+            : monitorToEdit.monitorType === 'dns' && monitorToEdit.monitorConfig
+            ? {
+                record_type: 'record_type' in monitorToEdit.monitorConfig ? monitorToEdit.monitorConfig.record_type : undefined,
+                expected_values: 'expected_values' in monitorToEdit.monitorConfig ? monitorToEdit.monitorConfig.expected_values : undefined,
+                resolver: 'resolver' in monitorToEdit.monitorConfig ? monitorToEdit.monitorConfig.resolver : undefined,
+              }
             : {};
         
         reset({
@@ -129,7 +141,7 @@ const ServiceMonitorModal: React.FC<ServiceMonitorModalProps> = ({ isOpen, onClo
     const monitorInput: ServiceMonitorInput = {
         ...data,
         // Handle any transformations if necessary, e.g., for monitorConfig
-        monitorConfig: data.monitorType === 'http' ? data.monitorConfig : {},
+        monitorConfig: data.monitorType === 'tcp' ? {} : data.monitorConfig,
     };
     onSave(monitorInput, monitorToEdit?.id);
   };
@@ -202,6 +214,7 @@ const ServiceMonitorModal: React.FC<ServiceMonitorModalProps> = ({ isOpen, onClo
                           <SelectItem value="http">{t('serviceMonitoring.modal.types.http')}</SelectItem>
                           <SelectItem value="ping">{t('serviceMonitoring.modal.types.ping')}</SelectItem>
                           <SelectItem value="tcp">{t('serviceMonitoring.modal.types.tcp')}</SelectItem>
+                          <SelectItem value="dns">{t('serviceMonitoring.modal.types.dns')}</SelectItem>
                         </SelectContent>
                       </Select>
                     )} />
@@ -261,6 +274,81 @@ const ServiceMonitorModal: React.FC<ServiceMonitorModalProps> = ({ isOpen, onClo
                 </div>
               )}
 
+              {watch('monitorType') === 'ping' && (
+                <div className="space-y-4 p-4 border rounded-md bg-slate-50">
+                    <h3 className="text-lg font-medium text-slate-900">{t('serviceMonitoring.modal.pingOptions')}</h3>
+                    <div className="grid gap-3">
+                        <Label>{t('serviceMonitoring.modal.packetCount')}</Label>
+                        <Controller
+                            name="monitorConfig.packet_count"
+                            control={control}
+                            render={({ field }) => (
+                                <Input
+                                    type="number"
+                                    min={1}
+                                    max={20}
+                                    placeholder="4"
+                                    value={field.value ?? ''}
+                                    onChange={e => field.onChange(e.target.value === '' ? undefined : parseInt(e.target.value, 10))}
+                                />
+                            )}
+                        />
+                    </div>
+                </div>
+              )}
+
+              {watch('monitorType') === 'dns' && (
+                <div className="space-y-4 p-4 border rounded-md bg-slate-50">
+                    <h3 className="text-lg font-medium text-slate-900">{t('serviceMonitoring.modal.dnsOptions')}</h3>
+                    <div className="grid gap-3">
+                        <Label>{t('serviceMonitoring.modal.recordType')}</Label>
+                        <Controller
+                            name="monitorConfig.record_type"
+                            control={control}
+                            render={({ field }) => (
+                                <Select onValueChange={field.onChange} value={field.value ?? 'A'}>
+                                    <SelectTrigger><SelectValue /></SelectTrigger>
+                                    <SelectContent>
+                                        {(['A', 'AAAA', 'CNAME', 'MX', 'TXT'] as const).map(rt => <SelectItem key={rt} value={rt}>{rt}</SelectItem>)}
+                                    </SelectContent>
+                                </Select>
+                            )}
+                        />
+                    </div>
+                    <div className="grid gap-3">
+                        <Label>{t('serviceMonitoring.modal.expectedValues')}</Label>
+                        <Controller
+                            name="monitorConfig.expected_values"
+                            control={control}
+                            render={({ field: { onChange, onBlur, value, name, ref } }) => (
+                                <Input
+                                    ref={ref}
+                                    name={name}
+                                    onBlur={onBlur}
+                                    placeholder={t('serviceMonitoring.modal.expectedValuesPlaceholder')}
+                                    value={Array.isArray(value) ? value.join(', ') : ''}
+                                    onChange={e => onChange(e.target.value.split(',').map(s => s.trim()).filter(s => s.length > 0))}
+                                />
+                            )}
+                        />
+                    </div>
+                    <div className="grid gap-3">
+                        <Label>{t('serviceMonitoring.modal.resolver')}</Label>
+                        <Controller
+                            name="monitorConfig.resolver"
+                            control={control}
+                            render={({ field }) => (
+                                <Input
+                                    placeholder={t('serviceMonitoring.modal.resolverPlaceholder')}
+                                    {...field}
+                                    value={field.value ?? ''}
+                                />
+                            )}
+                        />
+                    </div>
+                </div>
+              )}
+
               {/* Assignments */}
               <div className="p-4 border rounded-md bg-slate-50 space-y-4">
                 <h3 className="text-lg font-medium">{t('serviceMonitoring.modal.assignments')}</h3>
@@ -0,0 +1,145 @@
+import React, { useCallback, useEffect, useState } from 'react';
+import toast from 'react-hot-toast';
+import { useTranslation } from 'react-i18next';
+import * as twoFactorService from '../services/twoFactorService';
+import type { TwoFactorEnrollment, TwoFactorStatus } from '../services/twoFactorService';
+import { Button } from '@/components/ui/button';
+import { Input } from '@/components/ui/input';
+import { Label } from '@/components/ui/label';
+
+const errorMessage = (error: unknown, fallback: string) => {
+    const data = (error as { response?: { data?: { error?: string } } })?.response?.data;
+    return data?.error || (error instanceof Error ? error.message : fallback);
+};
+
+/** Enrollment, recovery codes and disabling of TOTP two-factor authentication. */
+const TwoFactorSettings: React.FC = () => {
+    const { t } = useTranslation();
+    const [status, setStatus] = useState<TwoFactorStatus | null>(null);
+    const [enrollment, setEnrollment] = useState<TwoFactorEnrollment | null>(null);
+    const [recoveryCodes, setRecoveryCodes] = useState<string[] | null>(null);
+    const [code, setCode] = useState('');
+    const [isSubmitting, setIsSubmitting] = useState(false);
+
+    const fetchStatus = useCallback(async () => {
+        try {
+            setStatus(await twoFactorService.getTwoFactorStatus());
+        } catch (error) {
+            toast.error(errorMessage(error, t('accountSettings.twoFactor.fetchError')));
+        }
+    }, [t]);
+
+    useEffect(() => {
+        fetchStatus();
+    }, [fetchStatus]);
+
+    const handleEnroll = async () => {
+        setIsSubmitting(true);
+        try {
+            setEnrollment(await twoFactorService.enrollTwoFactor());
+            setRecoveryCodes(null);
+            setCode('');
+        } catch (error) {
+            toast.error(errorMessage(error, t('accountSettings.twoFactor.enrollError')));
+        } finally {
+            setIsSubmitting(false);
+        }
+    };
+
+    const handleConfirm = async (e: React.FormEvent) => {
+        e.preventDefault();
+        setIsSubmitting(true);
+        try {
+            setRecoveryCodes(await twoFactorService.confirmTwoFactor(code.trim()));
+            setEnrollment(null);
+            setCode('');
+            toast.success(t('accountSettings.twoFactor.enabledSuccess'));
+            fetchStatus();
+        } catch (error) {
+            toast.error(errorMessage(error, t('accountSettings.twoFactor.invalidCode')));
+        } finally {
+            setIsSubmitting(false);
+        }
+    };
+
+    const handleDisable = async (e: React.FormEvent) => {
+        e.preventDefault();
+        setIsSubmitting(true);
+        try {
+            await twoFactorService.disableTwoFactor(code.trim());
+            setCode('');
+            setRecoveryCodes(null);
+            toast.success(t('accountSettings.twoFactor.disabledSuccess'));
+            fetchStatus();
+        } catch (error) {
+            toast.error(errorMessage(error, t('accountSettings.twoFactor.invalidCode')));
+        } finally {
+            setIsSubmitting(false);
+        }
+    };
+
+    const codeInput = (
+        <div className="space-y-2">
+            <Label htmlFor="two-factor-settings-code">{t('accountSettings.twoFactor.codeLabel')}</Label>
+            <Input
+                id="two-factor-settings-code"
+                inputMode="numeric"
+                autoComplete="one-time-code"
+                required
+                value={code}
+                onChange={(e) => setCode(e.target.value)}
+                className="w-[280px]"
+            />
+        </div>
+    );
+
+    return (
+        <div className="space-y-4">
+            <h3 className="text-lg font-medium">{t('accountSettings.twoFactor.title')}</h3>
+            {status === null ? (
+                <p className="text-muted-foreground">{t('common.status.loading')}</p>
+            ) : status.enabled ? (
+                <form onSubmit={handleDisable} className="space-y-4">
+                    <p className="text-sm text-muted-foreground">
+                        {t('accountSettings.twoFactor.enabledDescription', { count: status.recoveryCodesRemaining })}
+                    </p>
+                    {codeInput}
+                    <Button type="submit" variant="destructive" disabled={isSubmitting}>
+                        {t('accountSettings.twoFactor.disable')}
+                    </Button>
+                </form>
+            ) : enrollment ? (
+                <form onSubmit={handleConfirm} className="space-y-4">
+                    <p className="text-sm text-muted-foreground">{t('accountSettings.twoFactor.enrollDescription')}</p>
+                    <div className="space-y-1">
+                        <code className="block break-all rounded-md border bg-muted px-3 py-2 font-mono text-sm">{enrollment.secret}</code>
+                        <a href={enrollment.provisioningUri} className="text-sm underline underline-offset-4 hover:text-primary">
+                            {t('accountSettings.twoFactor.openInApp')}
+                        </a>
+                    </div>
+                    {codeInput}
+                    <div className="flex gap-2">
+                        <Button type="submit" disabled={isSubmitting}>{t('common.actions.confirm')}</Button>
+                        <Button type="button" variant="outline" onClick={() => setEnrollment(null)}>{t('common.actions.cancel')}</Button>
+                    </div>
+                </form>
+            ) : (
+                <div className="space-y-4">
+                    <p className="text-sm text-muted-foreground">{t('accountSettings.twoFactor.disabledDescription')}</p>
+                    <Button onClick={handleEnroll} disabled={isSubmitting}>{t('accountSettings.twoFactor.enable')}</Button>
+                </div>
+            )}
+            {recoveryCodes && (
+                <div className="space-y-2 rounded-md border p-4">
+                    <p className="text-sm font-medium">{t('accountSettings.twoFactor.recoveryCodesTitle')}</p>
+                    <p className="text-sm text-muted-foreground">{t('accountSettings.twoFactor.recoveryCodesDescription')}</p>
+                    <ul className="grid grid-cols-2 gap-1 font-mono text-sm">
+                        {recoveryCodes.map(recoveryCode => <li key={recoveryCode}>{recoveryCode}</li>)}
+                    </ul>
+                </div>
+            )}
+        </div>
+    );
+};
+
+export default TwoFactorSettings;
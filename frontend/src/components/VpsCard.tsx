@@ -58,7 +58,17 @@ const VpsCard: React.FC<VpsCardProps> = React.memo(({ server, activeChartTab, on
                 {server.ipAddress || t('vps.noIpAddress')}
               </div>
             </CardDescription>
-            <CardAction>
+            <CardAction className="flex gap-1">
+              {server.isEphemeral && (
+                <Badge variant="outline" className="flex-shrink-0 rounded-xl">
+                  {t('vps.ephemeral')}
+                </Badge>
+              )}
+              {server.maintenance && (
+                <Badge variant="outline" className="flex-shrink-0 rounded-xl" title={server.maintenance.reason ?? undefined}>
+                  {t('vps.maintenance')}
+                </Badge>
+              )}
               <Badge variant={statusVariant} className="flex-shrink-0 rounded-xl">
                 <StatusIcon className="w-3.5 h-3.5 mr-1" />
                 {server.status.toUpperCase()}
@@ -74,7 +74,7 @@ const UnifiedMetricChart: React.FC<UnifiedMetricChartProps> = ({
       const dataKeys = new Set<string>();
       data.forEach(point => {
         Object.keys(point).forEach(key => {
-          if (key !== 'time') {
+          if (key !== 'time' && key !== 'inMaintenance') {
             dataKeys.add(key);
           }
         });
@@ -0,0 +1,14 @@
+import { useQuery } from '@tanstack/react-query';
+import { getBranding, type Branding } from '@/services/brandingService';
+
+export const BRANDING_QUERY_KEY = ['branding'];
+
+/** The instance branding, loaded once per page load. */
+export const useBranding = (): Branding | undefined => {
+  const { data } = useQuery({
+    queryKey: BRANDING_QUERY_KEY,
+    queryFn: getBranding,
+    staleTime: Infinity,
+  });
+  return data;
+};
@@ -60,6 +60,8 @@ const transformMonitorData = (results: ServiceMonitorResult[], groupBy: 'monitor
         }
         const key = groupBy === 'agentName' ? r.agentName : r.monitorName;
         acc[time][key] = r.isUp ? r.latencyMs : null;
+        // 1 while every result at this time fell in a maintenance window.
+        acc[time].inMaintenance = acc[time].inMaintenance !== 0 && r.inMaintenance ? 1 : 0;
         return acc;
     }, {} as Record<number, ChartDataPoint>);
 
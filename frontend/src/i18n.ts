@@ -18,6 +18,12 @@ i18n
     backend: {
       loadPath: '/locales/%{lng}.json',
     },
+    detection: {
+      // The detected language isn't cached, so the instance default language
+      // applies until the user picks one in the account settings.
+      order: ['localStorage', 'navigator'],
+      caches: [],
+    },
   });
 
 export default i18n;
\ No newline at end of file
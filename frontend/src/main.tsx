@@ -6,9 +6,16 @@ import App from './App.tsx'
 import { ThemeProvider } from './components/ThemeProvider'
 import { Toaster } from 'react-hot-toast'
 import { QueryClient, QueryClientProvider } from '@tanstack/react-query'
+import { applyBranding, getBranding } from './services/brandingService'
+import { BRANDING_QUERY_KEY } from './hooks/useBranding'
 
 const queryClient = new QueryClient();
 
+queryClient
+  .fetchQuery({ queryKey: BRANDING_QUERY_KEY, queryFn: getBranding, staleTime: Infinity })
+  .then(applyBranding)
+  .catch((error) => console.warn('Failed to load instance branding', error));
+
 createRoot(document.getElementById('root')!).render(
   <StrictMode>
     <QueryClientProvider client={queryClient}>
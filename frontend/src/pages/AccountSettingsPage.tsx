@@ -8,6 +8,7 @@ import { Button } from '@/components/ui/button';
 import { Input } from '@/components/ui/input';
 import { Label } from '@/components/ui/label';
 import { Separator } from '@/components/ui/separator';
+import TwoFactorSettings from '@/components/TwoFactorSettings';
 import {
   AlertDialog,
   AlertDialogAction,
@@ -131,6 +132,7 @@ const AccountSettingsPage: React.FC = () => {
                 // After removing the item, we need to instruct i18next to re-detect the language
                 i18n.changeLanguage(undefined);
             } else {
+                localStorage.setItem('i18nextLng', lang);
                 i18n.changeLanguage(lang);
             }
             toast.success(t('accountSettings.preferences.updateLanguageSuccess'), { id: toastId });
@@ -290,6 +292,10 @@ const AccountSettingsPage: React.FC = () => {
                             </div>
                         )}
                     </div>
+
+                    <Separator />
+
+                    <TwoFactorSettings />
                 </CardContent>
             </Card>
 
@@ -265,6 +265,10 @@ const GlobalSettingsPage: React.FC = () => {
                                     <Label htmlFor="heartbeatIntervalSeconds">{t('agentSettings.labels.heartbeatInterval')}</Label>
                                     <Input id="heartbeatIntervalSeconds" name="heartbeatIntervalSeconds" type="number" value={config.heartbeatIntervalSeconds} onChange={handleInputChange} />
                                 </div>
+                                <div className="space-y-2">
+                                    <Label htmlFor="meshPingIntervalSeconds">{t('agentSettings.labels.meshPingInterval')}</Label>
+                                    <Input id="meshPingIntervalSeconds" name="meshPingIntervalSeconds" type="number" min={0} value={config.meshPingIntervalSeconds ?? 0} onChange={handleInputChange} />
+                                </div>
                             </div>
                             <div className="mt-6 flex justify-end">
                                 <Button type="submit" disabled={isSaving}>
@@ -2,7 +2,7 @@ import React, { useState, useEffect, useCallback } from 'react';
 import { useTranslation } from 'react-i18next';
 import { getAllAlertRules, deleteAlertRule, updateAlertRuleStatus } from '../services/alertService';
 import { getAllVpsListItems } from '../services/vpsService';
-import type { VpsListItemResponse, AlertRule } from '../types';
+import type { VpsListItemResponse, AlertRule, AlertSeverity } from '../types';
 import AlertRuleModal from '../components/AlertRuleModal';
 import toast from 'react-hot-toast';
 import { Button } from '@/components/ui/button';
@@ -24,6 +24,12 @@ import {
 import { Edit3, PlusCircle, Trash2 } from 'lucide-react';
 import EmptyState from '@/components/EmptyState';
 
+const SEVERITY_BADGE_VARIANTS: Record<AlertSeverity, 'secondary' | 'warning' | 'destructive'> = {
+    info: 'secondary',
+    warning: 'warning',
+    critical: 'destructive',
+};
+
 const AlertsTableSkeleton: React.FC = () => {
     const { t } = useTranslation();
     return (
@@ -183,7 +189,14 @@ const AlertsSettingsPage: React.FC = () => {
                             <TableBody>
                                 {alertRules.map(rule => (
                                     <TableRow key={rule.id}>
-                                        <TableCell className="font-medium">{rule.name}</TableCell>
+                                        <TableCell className="font-medium">
+                                            <div className="flex items-center gap-2">
+                                                {rule.name}
+                                                <Badge variant={SEVERITY_BADGE_VARIANTS[rule.severity]}>
+                                                    {t(`notificationsPage.modal.severities.${rule.severity}`)}
+                                                </Badge>
+                                            </div>
+                                        </TableCell>
                                         <TableCell>
                                             <Badge variant={rule.isActive ? 'success' : 'secondary'}>
                                                 {rule.isActive ? t('alertSettings.status.active') : t('alertSettings.status.inactive')}
@@ -1,7 +1,7 @@
 import React, { useState, useEffect, useRef } from 'react';
-import type { VpsListItemResponse, CommandScript, Tag } from '../types';
+import type { VpsListItemResponse, CommandScript, Tag, TargetSelector, VpsSelection, ExecutionStrategy } from '../types';
 import { useServerListStore } from '../store/serverListStore';
-import { connectForBatchCommand } from '../services/batchCommandService';
+import { connectForBatchCommand, abortBatchCommand, getVpsSelections, createVpsSelection, deleteVpsSelection, type ResolvedTarget } from '../services/batchCommandService';
 import { getCommandScripts, createCommandScript } from '../services/commandScriptService';
 import SaveScriptModal from '../components/SaveScriptModal';
 import Editor from '@monaco-editor/react';
@@ -43,6 +43,19 @@ const BatchCommandPage: React.FC = () => {
     const [showSaveModal, setShowSaveModal] = useState(false);
     const [scriptLanguage, setScriptLanguage] = useState('shell');
     const [editorHeight, setEditorHeight] = useState(160); // 10rem in pixels
+    // Selectors and saved selections are resolved by the server when the command is dispatched.
+    const [targetSelectors, setTargetSelectors] = useState<TargetSelector[]>([]);
+    const [selections, setSelections] = useState<VpsSelection[]>([]);
+    const [selectedSelectionIds, setSelectedSelectionIds] = useState<Set<number>>(new Set());
+    const [selectionName, setSelectionName] = useState('');
+    const [matchedSelectors, setMatchedSelectors] = useState<Record<number, string>>({});
+    const [strategyType, setStrategyType] = useState<ExecutionStrategy['type']>('parallel');
+    const [maxConcurrency, setMaxConcurrency] = useState('5');
+    const [healthCheckUrl, setHealthCheckUrl] = useState('');
+    const [timeoutSeconds, setTimeoutSeconds] = useState('');
+    const [maxAttempts, setMaxAttempts] = useState('1');
+
+    const hasTargets = selectedVps.size > 0 || targetSelectors.length > 0 || selectedSelectionIds.size > 0;
 
     const allOsTypes = [...new Set(servers.map(s => s.osType).filter((os): os is string => !!os))];
     const allTags: Tag[] = Array.from(new Map(servers.flatMap(s => s.tags || []).map(tag => [tag.id, tag])).values());
@@ -59,6 +72,26 @@ const BatchCommandPage: React.FC = () => {
         });
     };
 
+    const sameSelector = (a: TargetSelector, b: TargetSelector) =>
+        a.tag_id === b.tag_id && a.group_id === b.group_id && a.status === b.status;
+    const isSelectorActive = (selector: TargetSelector) => targetSelectors.some(s => sameSelector(s, selector));
+    const toggleSelector = (selector: TargetSelector) => {
+        setTargetSelectors(prev =>
+            prev.some(s => sameSelector(s, selector)) ? prev.filter(s => !sameSelector(s, selector)) : [...prev, selector]
+        );
+    };
+    const toggleSelection = (selectionId: number) => {
+        setSelectedSelectionIds(prev => {
+            const next = new Set(prev);
+            if (next.has(selectionId)) {
+                next.delete(selectionId);
+            } else {
+                next.add(selectionId);
+            }
+            return next;
+        });
+    };
+
     const handleSelectAll = () => setSelectedVps(new Set(servers.map(s => s.id)));
     const handleDeselectAll = () => setSelectedVps(new Set());
     const handleSelectByOs = (os: string) => {
@@ -74,8 +107,47 @@ const BatchCommandPage: React.FC = () => {
         const storedHistory = localStorage.getItem('batchCommandHistory');
         if (storedHistory) setCommandHistory(JSON.parse(storedHistory));
         loadScripts();
+        loadSelections();
     }, []);
 
+    const loadSelections = async () => {
+        try {
+            setSelections(await getVpsSelections());
+        } catch (error) {
+            console.error("Failed to load selections:", error);
+            setError(t('batchCommand.selections.loadFailed'));
+        }
+    };
+
+    const handleSaveSelection = async () => {
+        const name = selectionName.trim();
+        if (!name) return;
+        try {
+            await createVpsSelection({ name, target_vps_ids: Array.from(selectedVps), selectors: targetSelectors });
+            setSelectionName('');
+            loadSelections();
+        } catch (err) {
+            console.error("Failed to save selection:", err);
+            const axiosError = err as { response?: { data?: { error?: string } } };
+            setError(axiosError.response?.data?.error || t('batchCommand.selections.saveFailed'));
+        }
+    };
+
+    const handleDeleteSelection = async (selectionId: number) => {
+        try {
+            await deleteVpsSelection(selectionId);
+            setSelectedSelectionIds(prev => {
+                const next = new Set(prev);
+                next.delete(selectionId);
+                return next;
+            });
+            loadSelections();
+        } catch (err) {
+            console.error("Failed to delete selection:", err);
+            setError(t('batchCommand.selections.deleteFailed'));
+        }
+    };
+
     const loadScripts = async () => {
         try {
             const fetchedScripts = await getCommandScripts();
@@ -105,8 +177,21 @@ const BatchCommandPage: React.FC = () => {
         setShowHistory(false);
     };
 
+    const buildStrategy = (): ExecutionStrategy => {
+        const concurrency = Math.max(1, parseInt(maxConcurrency, 10) || 1);
+        if (strategyType === 'rolling') return { type: 'rolling', max_concurrency: concurrency };
+        if (strategyType === 'canary') {
+            return {
+                type: 'canary',
+                max_concurrency: maxConcurrency.trim() ? concurrency : undefined,
+                health_check: healthCheckUrl.trim() ? { url: healthCheckUrl.trim() } : undefined,
+            };
+        }
+        return { type: 'parallel' };
+    };
+
     const handleSendCommand = () => {
-        if (selectedVps.size === 0 || command.trim() === '') return;
+        if (!hasTargets || command.trim() === '') return;
         if (webSocketRef.current) webSocketRef.current.close();
 
         setIsLoading(true);
@@ -116,6 +201,7 @@ const BatchCommandPage: React.FC = () => {
         setAggregatedLogs([]);
         setActiveView('all');
         setActiveServersInTask(new Set(selectedVps));
+        setMatchedSelectors({});
         setCurrentBatchCommandId(null);
 
         addToHistory(command);
@@ -129,6 +215,11 @@ const BatchCommandPage: React.FC = () => {
             ws.send(JSON.stringify({
                 command_content: processedCommand,
                 target_vps_ids: Array.from(selectedVps),
+                target_selectors: targetSelectors,
+                selection_ids: Array.from(selectedSelectionIds),
+                strategy: buildStrategy(),
+                timeout_seconds: parseInt(timeoutSeconds, 10) > 0 ? parseInt(timeoutSeconds, 10) : undefined,
+                retry: parseInt(maxAttempts, 10) > 1 ? { max_attempts: parseInt(maxAttempts, 10) } : undefined,
                 working_directory: workingDirectory,
             }));
         };
@@ -160,7 +251,22 @@ const BatchCommandPage: React.FC = () => {
                     case 'BATCH_TASK_CREATED':
                         setGeneralOutput(prev => [...prev, `<span class="log-meta text-gray-500">[SYSTEM]: </span><span class="log-content">Batch command started with ID: ${payload.batch_command_id}</span>`]);
                         setCurrentBatchCommandId(payload.batch_command_id);
+                        if (Array.isArray(payload.targets)) {
+                            const targets = payload.targets as ResolvedTarget[];
+                            setActiveServersInTask(new Set(targets.map(target => target.vps_id)));
+                            setMatchedSelectors(Object.fromEntries(targets.map(target => [target.vps_id, target.matched_selector])));
+                        }
                         break;
+                    case 'BATCH_WAVE_UPDATE': {
+                        const waveText = t(`batchCommand.strategy.waveStates.${payload.state}`, {
+                            wave: payload.wave + 1,
+                            total: payload.total_waves,
+                            count: payload.vps_ids.length,
+                        });
+                        const detail = payload.message ? ` ${payload.message}` : '';
+                        setGeneralOutput(prev => [...prev, `<span class="log-meta text-gray-500">[${new Date().toLocaleTimeString()}] [WAVE]: </span><span class="log-content">${waveText}${detail}</span>`]);
+                        break;
+                    }
                     case 'NEW_LOG_OUTPUT': {
                         const formattedHtml = ansiConverter.current.toHtml(payload.log_line);
                         const formattedMessage = `<span class="log-meta text-gray-500">[${new Date(payload.timestamp).toLocaleTimeString()}] [${payload.stream_type.toUpperCase()}]: </span><span class="log-content">${formattedHtml}</span>`;
@@ -177,7 +283,7 @@ const BatchCommandPage: React.FC = () => {
                     case 'BATCH_TASK_UPDATE': {
                         const formattedMessage = `<span class="log-meta text-gray-500">[${new Date(payload.completed_at).toLocaleTimeString()}] [SYSTEM]: </span><span class="log-content">Batch command finished with status: ${payload.overall_status}.</span>`;
                         setGeneralOutput(prev => [...prev, formattedMessage]);
-                        if (["CompletedSuccessfully", "CompletedWithErrors", "Terminated", "FailedToDispatch"].includes(payload.overall_status)) {
+                        if (["CompletedSuccessfully", "CompletedWithErrors", "Terminated", "Aborted", "FailedToDispatch"].includes(payload.overall_status)) {
                             setIsLoading(false);
                             if (webSocketRef.current) webSocketRef.current.close();
                         }
@@ -217,6 +323,20 @@ const BatchCommandPage: React.FC = () => {
         webSocketRef.current.send(JSON.stringify({ type: "TERMINATE_TASK" }));
     };
 
+    const handleAbortCommand = async () => {
+        if (!currentBatchCommandId) {
+            setError(t('batchCommand.noActiveCommand'));
+            return;
+        }
+        try {
+            await abortBatchCommand(currentBatchCommandId);
+            setGeneralOutput(prev => [...prev, `<span class="log-meta text-gray-500">[SYSTEM]: </span><span class="log-content">${t('batchCommand.aborting', { batchId: currentBatchCommandId })}</span>`]);
+        } catch (err) {
+            console.error("Failed to abort batch command:", err);
+            setError(t('batchCommand.abortFailed'));
+        }
+    };
+
     const handleSaveScript = async (name: string, description: string) => {
         const processedCommand = scriptLanguage === 'shell' ? command.replace(/\r\n/g, '\n') : command;
         try {
@@ -299,6 +419,34 @@ const BatchCommandPage: React.FC = () => {
                                         </div>
                                     </div>
                                 )}
+                                <div className="mt-2">
+                                    <h4 className="text-sm font-medium text-muted-foreground mb-1" title={t('batchCommand.selectors.hint')}>{t('batchCommand.selectors.title')}</h4>
+                                    <div className="flex flex-wrap gap-2">
+                                        <Badge variant={isSelectorActive({ status: 'online' }) ? 'default' : 'outline'} onClick={() => toggleSelector({ status: 'online' })} className="cursor-pointer">{t('batchCommand.selectors.online')}</Badge>
+                                        {allTags.map(tag => (
+                                            <Badge key={tag.id} variant={isSelectorActive({ tag_id: tag.id }) ? 'default' : 'outline'} onClick={() => toggleSelector({ tag_id: tag.id })} className="cursor-pointer">
+                                                {t('batchCommand.selectors.tag', { name: tag.name })}
+                                            </Badge>
+                                        ))}
+                                    </div>
+                                </div>
+                                <div className="mt-2">
+                                    <h4 className="text-sm font-medium text-muted-foreground mb-1">{t('batchCommand.selections.title')}</h4>
+                                    {selections.length > 0 ? (
+                                        <div className="flex flex-wrap gap-2 mb-2">
+                                            {selections.map(selection => (
+                                                <Badge key={selection.id} variant={selectedSelectionIds.has(selection.id) ? 'default' : 'outline'} onClick={() => toggleSelection(selection.id)} className="cursor-pointer">
+                                                    {selection.name}
+                                                    <X className="h-3 w-3 ml-1" aria-label={t('batchCommand.selections.delete')} onClick={(e) => { e.stopPropagation(); handleDeleteSelection(selection.id); }} />
+                                                </Badge>
+                                            ))}
+                                        </div>
+                                    ) : <p className="text-xs text-muted-foreground mb-2">{t('batchCommand.selections.empty')}</p>}
+                                    <div className="flex gap-1">
+                                        <Input value={selectionName} onChange={(e) => setSelectionName(e.target.value)} placeholder={t('batchCommand.selections.namePlaceholder')} className="h-7 text-xs" />
+                                        <Button size="xxs" variant="secondary" onClick={handleSaveSelection} disabled={!selectionName.trim() || (selectedVps.size === 0 && targetSelectors.length === 0)}>{t('batchCommand.selections.save')}</Button>
+                                    </div>
+                                </div>
                             </CardContent>
                         </Card>
                         <div className="space-y-2 overflow-y-auto flex-grow">
@@ -321,6 +469,41 @@ const BatchCommandPage: React.FC = () => {
                                 <Label htmlFor="working-directory-input">{t('common.labels.workingDirectory')}</Label>
                                 <Input id="working-directory-input" value={workingDirectory} onChange={(e) => setWorkingDirectory(e.target.value)} placeholder={t('batchCommand.workingDirPlaceholder')} />
                             </div>
+                            <div className="flex flex-wrap items-end gap-2">
+                                <div>
+                                    <Label htmlFor="strategy-select">{t('batchCommand.strategy.label')}</Label>
+                                    <Select value={strategyType} onValueChange={(value) => setStrategyType(value as ExecutionStrategy['type'])}>
+                                        <SelectTrigger id="strategy-select" className="w-[160px]">
+                                            <SelectValue />
+                                        </SelectTrigger>
+                                        <SelectContent>
+                                            <SelectItem value="parallel">{t('batchCommand.strategy.parallel')}</SelectItem>
+                                            <SelectItem value="rolling">{t('batchCommand.strategy.rolling')}</SelectItem>
+                                            <SelectItem value="canary">{t('batchCommand.strategy.canary')}</SelectItem>
+                                        </SelectContent>
+                                    </Select>
+                                </div>
+                                {strategyType !== 'parallel' && (
+                                    <div>
+                                        <Label htmlFor="max-concurrency-input">{t('batchCommand.strategy.maxConcurrency')}</Label>
+                                        <Input id="max-concurrency-input" type="number" min={1} className="w-[120px]" value={maxConcurrency} onChange={(e) => setMaxConcurrency(e.target.value)} />
+                                    </div>
+                                )}
+                                <div>
+                                    <Label htmlFor="timeout-input">{t('batchCommand.timeoutSeconds')}</Label>
+                                    <Input id="timeout-input" type="number" min={1} className="w-[120px]" value={timeoutSeconds} onChange={(e) => setTimeoutSeconds(e.target.value)} placeholder={t('batchCommand.noTimeout')} />
+                                </div>
+                                <div>
+                                    <Label htmlFor="attempts-input">{t('batchCommand.maxAttempts')}</Label>
+                                    <Input id="attempts-input" type="number" min={1} max={10} className="w-[100px]" value={maxAttempts} onChange={(e) => setMaxAttempts(e.target.value)} />
+                                </div>
+                                {strategyType === 'canary' && (
+                                    <div className="flex-1 min-w-[200px]">
+                                        <Label htmlFor="health-check-input">{t('batchCommand.strategy.healthCheckUrl')}</Label>
+                                        <Input id="health-check-input" value={healthCheckUrl} onChange={(e) => setHealthCheckUrl(e.target.value)} placeholder="https://example.com/health" />
+                                    </div>
+                                )}
+                            </div>
                             <div className="min-w-0">
                                 <div className="flex justify-between items-center mb-1">
                                     <Label htmlFor="command-input">{t('batchCommand.command')}</Label>
@@ -374,11 +557,12 @@ const BatchCommandPage: React.FC = () => {
                                 )}
                             </div>
                             <div className="flex space-x-2">
-                                <Button onClick={handleSendCommand} disabled={selectedVps.size === 0 || command.trim() === '' || isLoading}>
+                                <Button onClick={handleSendCommand} disabled={!hasTargets || command.trim() === '' || isLoading}>
                                     {isLoading ? t('batchCommand.executing') : t('batchCommand.runCommand')}
                                 </Button>
                                 <Button variant="secondary" onClick={() => setShowSaveModal(true)} disabled={command.trim() === ''}>{t('batchCommand.saveAsScript')}</Button>
                                 {isLoading && currentBatchCommandId && <Button variant="destructive" onClick={handleTerminateCommand}>{t('batchCommand.terminate')}</Button>}
+                                {isLoading && currentBatchCommandId && <Button variant="outline" className="text-destructive" onClick={handleAbortCommand}>{t('batchCommand.abort')}</Button>}
                             </div>
                             {error && <div className="p-2 bg-destructive/10 text-destructive border border-destructive/20 rounded-md text-sm">{error}</div>}
                             
@@ -415,17 +599,18 @@ const BatchCommandPage: React.FC = () => {
                                                 const data = serverOutputs[vpsId];
                                                 const server = servers.find(s => s.id === vpsId);
                                                 const vpsName = server ? server.name : `VPS_ID_${vpsId}`;
+                                                const matched = matchedSelectors[vpsId] ? <span className="text-xs text-muted-foreground ml-2">[{matchedSelectors[vpsId]}]</span> : null;
                                                 if (!data) {
                                                     return (
                                                         <details key={vpsId} className="mb-2">
-                                                            <summary className="cursor-pointer font-semibold text-muted-foreground">{vpsName} - <span className="text-warning">{t('batchCommand.pending')}</span></summary>
+                                                            <summary className="cursor-pointer font-semibold text-muted-foreground">{vpsName} - <span className="text-warning">{t('batchCommand.pending')}</span>{matched}</summary>
                                                         </details>
                                                     );
                                                 }
                                                 const statusColor = data.status.toLowerCase().includes('success') || (data.exitCode === 0) ? 'text-success' : data.status.toLowerCase().includes('fail') || (typeof data.exitCode === 'number' && data.exitCode > 0) ? 'text-destructive' : 'text-warning';
                                                 return (
                                                     <details key={vpsId} className="mb-2" open>
-                                                        <summary className="cursor-pointer font-semibold">{data.name} - <span className={statusColor}>{data.status} (Exit: {data.exitCode ?? 'N/A'})</span></summary>
+                                                        <summary className="cursor-pointer font-semibold">{data.name} - <span className={statusColor}>{data.status} (Exit: {data.exitCode ?? 'N/A'})</span>{matched}</summary>
                                                         <div className="pl-4 mt-2 border-l-2 border-border">
                                                             {data.logs.map((log, index) => <div key={index} style={{ whiteSpace: 'pre-wrap' }} dangerouslySetInnerHTML={{ __html: log }} />)}
                                                         </div>
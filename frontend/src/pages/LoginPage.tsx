@@ -14,8 +14,9 @@ import { Loader2, AlertCircle } from "lucide-react";
 const LoginPage: React.FC = () => {
     const [username, setUsername] = useState('');
     const [password, setPassword] = useState('');
+    const [twoFactorCode, setTwoFactorCode] = useState('');
     const [providers, setProviders] = useState<AuthProvider[]>([]);
-    const { login, isLoading, error, isAuthenticated, clearAuthError } = useAuthStore();
+    const { login, verifyTwoFactor, twoFactorChallenge, isLoading, error, isAuthenticated, clearAuthError } = useAuthStore();
     const navigate = useNavigate();
     const [oauthError, setOauthError] = useState<string | null>(null);
 
@@ -47,8 +48,12 @@ const LoginPage: React.FC = () => {
     const handleSubmit = async (event: React.FormEvent) => {
         event.preventDefault();
         clearAuthError();
-        const credentials: LoginRequest = { username, password };
         try {
+            if (twoFactorChallenge) {
+                await verifyTwoFactor(twoFactorCode.trim());
+                return;
+            }
+            const credentials: LoginRequest = { username, password };
             await login(credentials);
         } catch (err) {
             console.error('Login failed on page:', err);
@@ -87,6 +92,22 @@ const LoginPage: React.FC = () => {
                                 autoComplete="current-password"
                             />
                         </div>
+                        {twoFactorChallenge && (
+                            <div className="space-y-2">
+                                <Label htmlFor="two-factor-code">两步验证码</Label>
+                                <Input
+                                    id="two-factor-code"
+                                    type="text"
+                                    inputMode="numeric"
+                                    placeholder="6 位验证码或恢复码"
+                                    required
+                                    autoFocus
+                                    value={twoFactorCode}
+                                    onChange={(e) => setTwoFactorCode(e.target.value)}
+                                    autoComplete="one-time-code"
+                                />
+                            </div>
+                        )}
 
                         {error && (
                             <Alert variant="destructive">
@@ -25,6 +25,7 @@ import {
     AlertDialogTitle,
 } from "@/components/ui/alert-dialog";
 import { Skeleton } from "@/components/ui/skeleton";
+import { Badge } from '@/components/ui/badge';
 import { PlusCircle, Send, Trash2, Edit } from 'lucide-react';
 import { RefreshCwIcon as SpinnerIcon } from '@/components/Icons';
 import EmptyState from '@/components/EmptyState';
@@ -118,7 +119,7 @@ const NotificationsPage: React.FC = () => {
         setTestingChannelId(id);
         const toastId = toast.loading(t('notificationsPage.status.testing'));
         try {
-            await testChannel(id, 'This is a test message from the dashboard.');
+            await testChannel(id);
             toast.success(t('notificationsPage.notifications.testSuccess'), { id: toastId });
         } catch (err) {
             console.error('Failed to send test message:', err);
@@ -182,7 +183,16 @@ const NotificationsPage: React.FC = () => {
                 <TableBody>
                     {channels.map(channel => (
                         <TableRow key={channel.id}>
-                            <TableCell className="font-medium">{channel.name}</TableCell>
+                            <TableCell className="font-medium">
+                                <div className="flex items-center gap-2">
+                                    {channel.name}
+                                    {channel.delivery === 'digest' && (
+                                        <Badge variant="secondary" title={channel.digestSchedule ?? undefined}>
+                                            {t('notificationsPage.table.digest')}
+                                        </Badge>
+                                    )}
+                                </div>
+                            </TableCell>
                             <TableCell className="capitalize text-muted-foreground">{channel.channelType}</TableCell>
                             <TableCell className="text-right space-x-1">
                                 <Button variant="ghost" size="icon" onClick={() => handleOpenEditModal(channel)}>
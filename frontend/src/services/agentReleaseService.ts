@@ -0,0 +1,63 @@
+import apiClient from './apiClient';
+
+export interface AgentRelease {
+  version: string;
+  /** Rust target triple, e.g. `x86_64-unknown-linux-gnu`. */
+  target: string;
+  fileName: string;
+  /** Set for builds hosted elsewhere; downloads redirect there. */
+  url: string | null;
+  sizeBytes: number;
+  sha256: string;
+  createdAt: string;
+}
+
+export interface RecordAgentReleasePayload {
+  version: string;
+  target: string;
+  url: string;
+  sha256: string;
+  sizeBytes: number;
+}
+
+/**
+ * Fetches all agent releases, newest version first.
+ * Corresponds to GET /api/agent/releases
+ */
+export const getAgentReleases = async (): Promise<AgentRelease[]> => {
+  const response = await apiClient.get<AgentRelease[]>('/agent/releases');
+  return response.data;
+};
+
+/**
+ * Uploads an agent binary for one version and target; the server computes its checksum.
+ * Corresponds to PUT /api/agent/releases/:version/:target
+ */
+export const uploadAgentRelease = async (version: string, target: string, file: File): Promise<AgentRelease> => {
+  const response = await apiClient.put<AgentRelease>(
+    `/agent/releases/${encodeURIComponent(version)}/${encodeURIComponent(target)}`,
+    file,
+    { headers: { 'Content-Type': 'application/octet-stream' } },
+  );
+  return response.data;
+};
+
+/**
+ * Records a build hosted elsewhere.
+ * Corresponds to POST /api/agent/releases
+ */
+export const recordAgentRelease = async (payload: RecordAgentReleasePayload): Promise<AgentRelease> => {
+  const response = await apiClient.post<AgentRelease>('/agent/releases', payload);
+  return response.data;
+};
+
+/**
+ * Corresponds to DELETE /api/agent/releases/:version/:target
+ */
+export const deleteAgentRelease = async (version: string, target: string): Promise<void> => {
+  await apiClient.delete(`/agent/releases/${encodeURIComponent(version)}/${encodeURIComponent(target)}`);
+};
+
+/** Public download URL; `version` may be `latest`. */
+export const agentDownloadUrl = (version: string, target: string): string =>
+  `/api/agent/download/${encodeURIComponent(version)}/${encodeURIComponent(target)}`;
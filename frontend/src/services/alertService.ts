@@ -1,5 +1,5 @@
 import apiClient from './apiClient';
-import type { AlertRule, CreateAlertRulePayload, UpdateAlertRulePayload } from '../types';
+import type { AlertRule, AlertSeverity, CreateAlertRulePayload, UpdateAlertRulePayload } from '../types';
 
 // Assuming your API endpoint for alert rules is /api/alerts
 
@@ -55,4 +55,149 @@ export const deleteAlertRule = async (id: number): Promise<void> => {
 export const updateAlertRuleStatus = async (id: number, isActive: boolean): Promise<AlertRule> => {
   const response = await apiClient.put<AlertRule>(`/alerts/${id}/status`, { isActive });
   return response.data;
-};
\ No newline at end of file
+};
+export interface AlertActivityEntry {
+  kind: 'command' | 'config_push' | 'maintenance' | 'change';
+  time: string;
+  summary: string;
+  actor: string | null;
+  status: string | null;
+}
+
+export type AlertEventState = 'open' | 'acknowledged' | 'resolved';
+
+export interface AlertEventDetail {
+  id: number;
+  ruleId: number;
+  vpsId: number;
+  monitorId: number | null;
+  severity: AlertSeverity;
+  ruleName: string;
+  vpsName: string;
+  state: AlertEventState;
+  triggerTime: string;
+  resolveTime: string | null;
+  details: string | null;
+  acknowledgedAt: string | null;
+  acknowledgedBy: string | null;
+  silencedUntil: string | null;
+  remediationBatchCommandId: string | null;
+  recentActivity: AlertActivityEntry[] | null;
+}
+
+export interface AlertEventListParams {
+  vpsId?: number;
+  ruleId?: number;
+  state?: AlertEventState;
+  severity?: AlertSeverity;
+  since?: string;
+  until?: string;
+  page?: number;
+  perPage?: number;
+}
+
+export interface AlertEventPage {
+  items: AlertEventDetail[];
+  total: number;
+  page: number;
+  perPage: number;
+}
+
+export interface AlertEventCount {
+  id: number;
+  name: string;
+  open: number;
+  acknowledged: number;
+}
+
+export interface AlertEventSummary {
+  open: number;
+  acknowledged: number;
+  resolvedLast24h: number;
+  byVps: AlertEventCount[];
+  byRule: AlertEventCount[];
+}
+
+// Pushed as `alert_event` on /ws/metrics when an event fires or changes state.
+export interface AlertEventPush {
+  change: 'fired' | 'acknowledged' | 'silenced' | 'resolved';
+  id: number;
+  ruleId: number;
+  ruleName: string;
+  vpsId: number;
+  vpsName: string;
+  monitorId: number | null;
+  severity: AlertSeverity;
+  triggerTime: string;
+  resolveTime: string | null;
+  acknowledgedAt: string | null;
+  acknowledgedBy: string | null;
+  silencedUntil: string | null;
+  details: string | null;
+}
+
+export interface AlertCorrelationSettings {
+  windowMinutes: number;
+}
+
+/**
+ * Fetches a fired alert together with the activity on its VPS just before it fired.
+ * Corresponds to GET /api/alerts/events/{eventId}
+ */
+export const getAlertEvent = async (eventId: number): Promise<AlertEventDetail> => {
+  const response = await apiClient.get<AlertEventDetail>(`/alerts/events/${eventId}`);
+  return response.data;
+};
+
+/**
+ * Fetches fired alerts, newest first.
+ * Corresponds to GET /api/alerts/events
+ */
+export const getAlertEvents = async (params: AlertEventListParams = {}): Promise<AlertEventPage> => {
+  const response = await apiClient.get<AlertEventPage>('/alerts/events', { params });
+  return response.data;
+};
+
+/**
+ * Counts of open, acknowledged and recently resolved alerts.
+ * Corresponds to GET /api/alerts/events/summary
+ */
+export const getAlertEventSummary = async (): Promise<AlertEventSummary> => {
+  const response = await apiClient.get<AlertEventSummary>('/alerts/events/summary');
+  return response.data;
+};
+
+/**
+ * Corresponds to POST /api/alerts/events/{eventId}/acknowledge
+ */
+export const acknowledgeAlertEvent = async (eventId: number): Promise<AlertEventDetail> => {
+  const response = await apiClient.post<AlertEventDetail>(`/alerts/events/${eventId}/acknowledge`);
+  return response.data;
+};
+
+/**
+ * Corresponds to POST /api/alerts/events/{eventId}/resolve
+ */
+export const resolveAlertEvent = async (eventId: number): Promise<AlertEventDetail> => {
+  const response = await apiClient.post<AlertEventDetail>(`/alerts/events/${eventId}/resolve`);
+  return response.data;
+};
+
+/**
+ * Corresponds to GET /api/settings/alert-correlation
+ */
+export const getAlertCorrelationSettings = async (): Promise<AlertCorrelationSettings> => {
+  const response = await apiClient.get<AlertCorrelationSettings>('/settings/alert-correlation');
+  return response.data;
+};
+
+/**
+ * Sets how many minutes of activity are attached to alerts; 0 turns it off.
+ * Corresponds to PUT /api/settings/alert-correlation
+ */
+export const updateAlertCorrelationSettings = async (
+  settings: AlertCorrelationSettings,
+): Promise<AlertCorrelationSettings> => {
+  const response = await apiClient.put<AlertCorrelationSettings>('/settings/alert-correlation', settings);
+  return response.data;
+};
@@ -0,0 +1,51 @@
+import apiClient from './apiClient';
+
+export type ApiTokenScope = 'read' | 'write';
+
+export interface ApiToken {
+  id: number;
+  userId: number;
+  name: string;
+  tokenPrefix: string;
+  scopes: ApiTokenScope[];
+  expiresAt: string | null;
+  createdAt: string;
+  lastUsedAt: string | null;
+}
+
+export interface CreateApiTokenPayload {
+  name: string;
+  scopes?: ApiTokenScope[];
+  expiresInDays?: number;
+}
+
+/** The plain text token is only returned once, on creation. */
+export interface CreatedApiToken extends ApiToken {
+  token: string;
+}
+
+/**
+ * Fetches the current user's personal access tokens.
+ * Corresponds to GET /api/user/tokens
+ */
+export const getApiTokens = async (): Promise<ApiToken[]> => {
+  const response = await apiClient.get<ApiToken[]>('/user/tokens');
+  return response.data;
+};
+
+/**
+ * Creates a personal access token for `Authorization: Bearer nn_...`.
+ * Corresponds to POST /api/user/tokens
+ */
+export const createApiToken = async (payload: CreateApiTokenPayload): Promise<CreatedApiToken> => {
+  const response = await apiClient.post<CreatedApiToken>('/user/tokens', payload);
+  return response.data;
+};
+
+/**
+ * Revokes a personal access token.
+ * Corresponds to DELETE /api/user/tokens/:id
+ */
+export const deleteApiToken = async (id: number): Promise<void> => {
+  await apiClient.delete(`/user/tokens/${id}`);
+};
@@ -0,0 +1,45 @@
+import apiClient from './apiClient';
+
+export interface AuditLogEntry {
+  id: number;
+  userId: number;
+  username: string;
+  organizationId: number | null;
+  method: string;
+  route: string;
+  entityType: string;
+  entityId: string | null;
+  statusCode: number;
+  beforeSummary: unknown | null;
+  afterSummary: unknown | null;
+  ipAddress: string | null;
+  requestId: string | null;
+  createdAt: string;
+}
+
+export interface AuditLogParams {
+  userId?: number;
+  entityType?: string;
+  entityId?: string;
+  method?: string;
+  since?: string;
+  until?: string;
+  page?: number;
+  perPage?: number;
+}
+
+export interface AuditLogPage {
+  items: AuditLogEntry[];
+  total: number;
+  page: number;
+  perPage: number;
+}
+
+/**
+ * Fetches a page of the audit log, newest first. Admin only.
+ * Corresponds to GET /api/admin/audit
+ */
+export const getAuditLog = async (params: AuditLogParams = {}): Promise<AuditLogPage> => {
+  const response = await apiClient.get<AuditLogPage>('/admin/audit', { params });
+  return response.data;
+};
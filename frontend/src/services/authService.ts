@@ -24,6 +24,17 @@ export interface LoginResponse {
     username: string;
 }
 
+/** Returned instead of a session when the account has 2FA enabled. */
+export interface TwoFactorChallengeResponse {
+    status: '2fa_required';
+    challenge_token: string;
+}
+
+export const isTwoFactorChallenge = (
+    response: LoginResponse | TwoFactorChallengeResponse,
+): response is TwoFactorChallengeResponse =>
+    'status' in response && response.status === '2fa_required';
+
 // 不再需要手动构建 URL，apiClient 会处理 baseURL
 // const ENV_API_ROOT = import.meta.env.VITE_API_BASE_URL;
 // let AUTH_API_ENDPOINT_BASE: string;
@@ -63,10 +74,10 @@ export const registerUser = async (data: RegisterRequest): Promise<UserResponse>
     }
 };
 
-export const loginUser = async (data: LoginRequest): Promise<LoginResponse> => {
+export const loginUser = async (data: LoginRequest): Promise<LoginResponse | TwoFactorChallengeResponse> => {
     console.log('authService.ts: loginUser called with', data);
     try {
-        const response = await apiClient.post<LoginResponse>('/auth/login', data);
+        const response = await apiClient.post<LoginResponse | TwoFactorChallengeResponse>('/auth/login', data);
         return response.data;
     } catch (error: unknown) {
         console.error('Login failed:', error);
@@ -90,6 +101,33 @@ export const loginUser = async (data: LoginRequest): Promise<LoginResponse> => {
     }
 };
 
+/**
+ * Exchanges a 2FA challenge token and a TOTP or recovery code for a session.
+ * Corresponds to POST /api/auth/login/2fa
+ */
+export const completeTwoFactorLogin = async (challengeToken: string, code: string): Promise<LoginResponse> => {
+    try {
+        const response = await apiClient.post<LoginResponse>('/auth/login/2fa', {
+            challenge_token: challengeToken,
+            code,
+        });
+        return response.data;
+    } catch (error: unknown) {
+        let errorMsg = '验证码错误';
+        if (axios.isAxiosError(error) && error.response?.data) {
+            const errorData = error.response.data;
+            if (typeof errorData === 'string') {
+                errorMsg = errorData;
+            } else if (errorData && typeof errorData === 'object' && 'error' in errorData && typeof errorData.error === 'string') {
+                errorMsg = errorData.error;
+            }
+        } else if (error instanceof Error) {
+            errorMsg = error.message;
+        }
+        throw new Error(errorMsg);
+    }
+};
+
 export interface AuthProvider {
     name: string;
     iconUrl: string | undefined;
@@ -0,0 +1,31 @@
+import apiClient from './apiClient';
+
+export interface BackupArchive {
+  fileName: string;
+  sizeBytes: number;
+  createdAt: string;
+}
+
+/**
+ * Fetches the stored backup archives, newest first.
+ * Corresponds to GET /api/admin/backup
+ */
+export const getBackups = async (): Promise<BackupArchive[]> => {
+  const response = await apiClient.get<BackupArchive[]>('/admin/backup');
+  return response.data;
+};
+
+/**
+ * Takes a backup now. The archive is also kept on the server.
+ * Corresponds to POST /api/admin/backup
+ */
+export const createBackup = async (): Promise<Blob> => {
+  const response = await apiClient.post<Blob>('/admin/backup', undefined, { responseType: 'blob' });
+  return response.data;
+};
+
+/**
+ * Download URL of a stored archive (GET /api/admin/backup/:fileName).
+ */
+export const getBackupDownloadUrl = (fileName: string): string =>
+  `/api/admin/backup/${encodeURIComponent(fileName)}`;
@@ -1,3 +1,6 @@
+import apiClient from './apiClient';
+import type { TargetSelector, VpsSelection } from '../types';
+
 
 // This type might need to be expanded based on the actual API response
 export interface BatchCommandResponse {
@@ -16,4 +19,144 @@ export const connectForBatchCommand = (): WebSocket => {
     // Connect to the new endpoint that handles WebSocket upgrades.
     const wsUrl = `${wsProtocol}//${window.location.host}/api/batch_commands`;
     return new WebSocket(wsUrl);
-};
\ No newline at end of file
+};
+export interface OutputClusterMember {
+    child_command_id: string;
+    vps_id: number;
+}
+
+export interface OutputCluster {
+    cluster_id: number;
+    output_hash: string;
+    exit_code: number | null;
+    size: number;
+    is_majority: boolean;
+    similarity_to_majority: number;
+    lines_missing: string[];
+    lines_extra: string[];
+    sample_output: string;
+    sample_truncated: boolean;
+    members: OutputClusterMember[];
+}
+
+export interface BatchOutputComparison {
+    batch_command_id: string;
+    stream: string;
+    total_tasks: number;
+    cluster_count: number;
+    majority_cluster_id: number | null;
+    outlier_vps_ids: number[];
+    clusters: OutputCluster[];
+}
+
+/**
+ * Groups identical (or similar) outputs of a batch command and reports the outliers.
+ */
+export const compareBatchOutputs = async (
+    batchCommandId: string,
+    stream: 'stdout' | 'stderr' | 'combined' = 'stdout',
+    similarityThreshold?: number,
+): Promise<BatchOutputComparison> => {
+    const response = await apiClient.get<BatchOutputComparison>(`/batch_commands/${batchCommandId}/compare`, {
+        params: { stream, similarity_threshold: similarityThreshold },
+    });
+    return response.data;
+};
+
+export type OutputStream = 'stdout' | 'stderr';
+
+/**
+ * Permalink to the stored output of one child command, served as a download.
+ */
+export const childOutputDownloadUrl = (batchCommandId: string, childCommandId: string, stream: OutputStream = 'stdout'): string =>
+    `/api/batch_commands/${batchCommandId}/children/${childCommandId}/output?stream=${stream}&download=true`;
+
+/**
+ * Fetches stored output of one child command. Pass `start`/`end` (inclusive byte
+ * offsets) to page through large logs.
+ */
+export const getChildOutput = async (
+    batchCommandId: string,
+    childCommandId: string,
+    stream: OutputStream = 'stdout',
+    start?: number,
+    end?: number,
+): Promise<string> => {
+    const headers = start !== undefined ? { Range: `bytes=${start}-${end ?? ''}` } : undefined;
+    const response = await apiClient.get<string>(`/batch_commands/${batchCommandId}/children/${childCommandId}/output`, {
+        params: { stream },
+        headers,
+        responseType: 'text',
+    });
+    return response.data;
+};
+
+export interface BatchOutputSearchHit {
+    batch_command_id: string;
+    child_command_id: string;
+    vps_id: number;
+    execution_alias: string | null;
+    stream: OutputStream;
+    snippet: string;
+    finished_at: string;
+}
+
+/**
+ * Searches the output of past batch commands (case-insensitive), newest first.
+ */
+export const searchBatchOutputs = async (q: string, limit?: number): Promise<BatchOutputSearchHit[]> => {
+    const response = await apiClient.get<BatchOutputSearchHit[]>('/batch_commands/search', { params: { q, limit } });
+    return response.data;
+};
+
+/**
+ * Aborts a running batch command: agents kill what's running, and tasks waiting
+ * for a retry or a later wave never run.
+ */
+export const abortBatchCommand = async (batchCommandId: string): Promise<void> => {
+    await apiClient.post(`/batch_commands/${batchCommandId}/abort`);
+};
+
+export interface BatchTargets {
+    target_vps_ids: number[];
+    target_selectors: TargetSelector[];
+    selection_ids: number[];
+}
+
+export interface ResolvedTarget {
+    vps_id: number;
+    matched_selector: string;
+}
+
+/**
+ * The VPS a batch command with these targets would run on right now.
+ */
+export const previewBatchTargets = async (targets: BatchTargets): Promise<ResolvedTarget[]> => {
+    const response = await apiClient.post<ResolvedTarget[]>('/batch_commands/targets/preview', targets);
+    return response.data;
+};
+
+export interface VpsSelectionPayload {
+    name: string;
+    target_vps_ids: number[];
+    selectors: TargetSelector[];
+}
+
+export const getVpsSelections = async (): Promise<VpsSelection[]> => {
+    const response = await apiClient.get<VpsSelection[]>('/batch_commands/selections');
+    return response.data;
+};
+
+export const createVpsSelection = async (payload: VpsSelectionPayload): Promise<VpsSelection> => {
+    const response = await apiClient.post<VpsSelection>('/batch_commands/selections', payload);
+    return response.data;
+};
+
+export const updateVpsSelection = async (id: number, payload: VpsSelectionPayload): Promise<VpsSelection> => {
+    const response = await apiClient.put<VpsSelection>(`/batch_commands/selections/${id}`, payload);
+    return response.data;
+};
+
+export const deleteVpsSelection = async (id: number): Promise<void> => {
+    await apiClient.delete(`/batch_commands/selections/${id}`);
+};
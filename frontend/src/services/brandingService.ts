@@ -0,0 +1,79 @@
+import apiClient from './apiClient';
+import i18n from '../i18n';
+
+export interface FooterLink {
+    label: string;
+    url: string;
+}
+
+export interface AccentColors {
+    primary?: string | null;
+    secondary?: string | null;
+}
+
+export interface Branding {
+    title: string;
+    footerLinks: FooterLink[];
+    defaultLanguage: string;
+    accentColors: AccentColors;
+    logoUrl?: string | null;
+    faviconUrl?: string | null;
+}
+
+export type BrandingUpdate = Partial<Pick<Branding, 'title' | 'footerLinks' | 'defaultLanguage' | 'accentColors'>>;
+
+export const getBranding = async (): Promise<Branding> => {
+    const response = await apiClient.get<Branding>('/branding');
+    return response.data;
+};
+
+export const updateBranding = async (update: BrandingUpdate): Promise<Branding> => {
+    const response = await apiClient.put<Branding>('/settings/branding', update);
+    return response.data;
+};
+
+export const uploadBrandingImage = async (asset: 'logo' | 'favicon', file: File): Promise<Branding> => {
+    const response = await apiClient.put<Branding>(`/settings/branding/${asset}`, file, {
+        headers: { 'Content-Type': file.type },
+    });
+    return response.data;
+};
+
+export const deleteBrandingImage = async (asset: 'logo' | 'favicon'): Promise<void> => {
+    await apiClient.delete(`/settings/branding/${asset}`);
+};
+
+const BRANDING_STYLE_ID = 'branding-colors';
+
+/** Applies the instance title, favicon, accent colors and default language to the current document. */
+export const applyBranding = (branding: Branding) => {
+    document.title = branding.title;
+    if (branding.faviconUrl) {
+        let link = document.querySelector<HTMLLinkElement>("link[rel='icon']");
+        if (!link) {
+            link = document.createElement('link');
+            link.rel = 'icon';
+            document.head.appendChild(link);
+        }
+        link.removeAttribute('type');
+        link.href = branding.faviconUrl;
+    }
+
+    // index.css uses these for --primary and --secondary, so custom themes still override them.
+    const declarations = [
+        branding.accentColors.primary && `--brand-primary: ${branding.accentColors.primary};`,
+        branding.accentColors.secondary && `--brand-secondary: ${branding.accentColors.secondary};`,
+    ].filter(Boolean).join(' ');
+    let style = document.getElementById(BRANDING_STYLE_ID);
+    if (!style) {
+        style = document.createElement('style');
+        style.id = BRANDING_STYLE_ID;
+        document.head.appendChild(style);
+    }
+    style.textContent = declarations ? `:root { ${declarations} }` : '';
+
+    // Only a language picked in the account settings is stored; until then the instance default applies.
+    if (branding.defaultLanguage !== 'auto' && !localStorage.getItem('i18nextLng')) {
+        i18n.changeLanguage(branding.defaultLanguage);
+    }
+};
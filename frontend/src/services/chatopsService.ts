@@ -0,0 +1,73 @@
+import apiClient from './apiClient';
+
+export type ChatOpsPlatform = 'telegram' | 'slack';
+/** `read` allows /status and /top, `execute` allows /run of the allowed scripts. */
+export type ChatOpsScope = 'read' | 'execute';
+
+export interface ChatOpsBridge {
+    id: number;
+    userId: number;
+    name: string;
+    platform: ChatOpsPlatform;
+    /** Telegram chat ids or Slack channel ids commands are accepted from. */
+    allowedChatIds: string[];
+    scopes: ChatOpsScope[];
+    allowedScriptIds: number[];
+    createdAt: string;
+    updatedAt: string;
+}
+
+export interface CreateChatOpsBridgePayload {
+    name: string;
+    platform: ChatOpsPlatform;
+    /** Slack signing secret. Generated for Telegram when omitted. */
+    secret?: string;
+    allowedChatIds: string[];
+    scopes?: ChatOpsScope[];
+    allowedScriptIds: number[];
+}
+
+export type UpdateChatOpsBridgePayload = Partial<
+    Pick<ChatOpsBridge, 'name' | 'allowedChatIds' | 'scopes' | 'allowedScriptIds'>
+>;
+
+/** Returned on creation only; the secret can't be read back later. */
+export interface CreatedChatOpsBridge extends ChatOpsBridge {
+    secret: string;
+    webhookUrl: string;
+}
+
+/**
+ * Fetches the chat bridges of the current user.
+ * Corresponds to GET /api/chatops/bridges
+ */
+export const getChatOpsBridges = async (): Promise<ChatOpsBridge[]> => {
+    const response = await apiClient.get<ChatOpsBridge[]>('/chatops/bridges');
+    return response.data;
+};
+
+/**
+ * Creates a chat bridge.
+ * Corresponds to POST /api/chatops/bridges
+ */
+export const createChatOpsBridge = async (payload: CreateChatOpsBridgePayload): Promise<CreatedChatOpsBridge> => {
+    const response = await apiClient.post<CreatedChatOpsBridge>('/chatops/bridges', payload);
+    return response.data;
+};
+
+/**
+ * Updates a chat bridge.
+ * Corresponds to PUT /api/chatops/bridges/{id}
+ */
+export const updateChatOpsBridge = async (id: number, payload: UpdateChatOpsBridgePayload): Promise<ChatOpsBridge> => {
+    const response = await apiClient.put<ChatOpsBridge>(`/chatops/bridges/${id}`, payload);
+    return response.data;
+};
+
+/**
+ * Deletes a chat bridge.
+ * Corresponds to DELETE /api/chatops/bridges/{id}
+ */
+export const deleteChatOpsBridge = async (id: number): Promise<void> => {
+    await apiClient.delete(`/chatops/bridges/${id}`);
+};
@@ -0,0 +1,101 @@
+import apiClient from './apiClient';
+
+export interface Client {
+  id: number;
+  organizationId: number;
+  name: string;
+  contactEmail: string | null;
+  notes: string | null;
+  /** Added to the provider cost when billing the client. */
+  markupPercent: number;
+  createdAt: string;
+  updatedAt: string;
+}
+
+export interface ClientPayload {
+  name: string;
+  contactEmail?: string | null;
+  notes?: string | null;
+  markupPercent?: number;
+}
+
+export interface ClientVpsCostEntry {
+  vpsId: number;
+  name: string;
+  currency: string | null;
+  cost: number | null;
+  billed: number | null;
+  uptimePercent: number | null;
+  downtimeSeconds: number;
+}
+
+export interface CurrencyTotal {
+  currency: string;
+  cost: number;
+  billed: number;
+  commission: number;
+}
+
+export interface ClientReport {
+  client: Client;
+  start: string;
+  end: string;
+  entries: ClientVpsCostEntry[];
+  totals: CurrencyTotal[];
+  uptimePercent: number | null;
+}
+
+/**
+ * Fetches the reseller clients of the active organization.
+ * Corresponds to GET /api/clients
+ */
+export const getClients = async (): Promise<Client[]> => {
+  const response = await apiClient.get<Client[]>('/clients');
+  return response.data;
+};
+
+/**
+ * Creates a client.
+ * Corresponds to POST /api/clients
+ */
+export const createClient = async (payload: ClientPayload): Promise<Client> => {
+  const response = await apiClient.post<Client>('/clients', payload);
+  return response.data;
+};
+
+/**
+ * Updates a client.
+ * Corresponds to PUT /api/clients/{id}
+ */
+export const updateClient = async (id: number, payload: ClientPayload): Promise<Client> => {
+  const response = await apiClient.put<Client>(`/clients/${id}`, payload);
+  return response.data;
+};
+
+/**
+ * Deletes a client; its VPS are detached and its share links revoked.
+ * Corresponds to DELETE /api/clients/{id}
+ */
+export const deleteClient = async (id: number): Promise<void> => {
+  await apiClient.delete(`/clients/${id}`);
+};
+
+/**
+ * Replaces the VPS attached to a client.
+ * Corresponds to PUT /api/clients/{id}/vps
+ */
+export const setClientVps = async (id: number, vpsIds: number[]): Promise<void> => {
+  await apiClient.put(`/clients/${id}/vps`, { vpsIds });
+};
+
+/**
+ * Fetches cost, billed amount and uptime per VPS. Defaults to the last completed month.
+ * Corresponds to GET /api/clients/{id}/report
+ */
+export const getClientReport = async (
+  id: number,
+  range: { start?: string; end?: string } = {},
+): Promise<ClientReport> => {
+  const response = await apiClient.get<ClientReport>(`/clients/${id}/report`, { params: range });
+  return response.data;
+};
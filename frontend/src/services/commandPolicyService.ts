@@ -0,0 +1,90 @@
+import apiClient from './apiClient';
+
+/**
+ * Allows or denies running commands. Every list narrows what the policy
+ * applies to; an empty list matches anything. A matching deny always wins.
+ */
+export interface CommandPolicy {
+    id: number;
+    organizationId: number;
+    name: string;
+    effect: 'allow' | 'deny';
+    userIds: number[];
+    scriptIds: number[];
+    /** Groups, including their subgroups. */
+    groupIds: number[];
+    tagIds: number[];
+    enabled: boolean;
+    createdAt: string;
+    updatedAt: string;
+}
+
+export interface CommandPolicyPayload {
+    name: string;
+    effect: 'allow' | 'deny';
+    userIds?: number[];
+    scriptIds?: number[];
+    groupIds?: number[];
+    tagIds?: number[];
+    enabled?: boolean;
+}
+
+export interface CommandPolicyTargetDecision {
+    vpsId: number;
+    vpsName: string | null;
+    allowed: boolean;
+    policyId: number | null;
+    reason: string;
+}
+
+export interface CommandPolicyDryRunResult {
+    allowedVpsIds: number[];
+    deniedVpsIds: number[];
+    targets: CommandPolicyTargetDecision[];
+}
+
+/**
+ * Corresponds to GET /api/command-policies
+ */
+export const getCommandPolicies = async (): Promise<CommandPolicy[]> => {
+    const response = await apiClient.get<CommandPolicy[]>('/command-policies');
+    return response.data;
+};
+
+/**
+ * Corresponds to POST /api/command-policies
+ */
+export const createCommandPolicy = async (payload: CommandPolicyPayload): Promise<CommandPolicy> => {
+    const response = await apiClient.post<CommandPolicy>('/command-policies', payload);
+    return response.data;
+};
+
+/**
+ * Corresponds to PUT /api/command-policies/{id}
+ */
+export const updateCommandPolicy = async (
+    id: number,
+    changes: Partial<CommandPolicyPayload>,
+): Promise<CommandPolicy> => {
+    const response = await apiClient.put<CommandPolicy>(`/command-policies/${id}`, changes);
+    return response.data;
+};
+
+/**
+ * Corresponds to DELETE /api/command-policies/{id}
+ */
+export const deleteCommandPolicy = async (id: number): Promise<void> => {
+    await apiClient.delete(`/command-policies/${id}`);
+};
+
+/**
+ * Corresponds to POST /api/command-policies/dry-run
+ */
+export const dryRunCommandPolicies = async (request: {
+    scriptId?: number;
+    vpsIds: number[];
+    userId?: number;
+}): Promise<CommandPolicyDryRunResult> => {
+    const response = await apiClient.post<CommandPolicyDryRunResult>('/command-policies/dry-run', request);
+    return response.data;
+};
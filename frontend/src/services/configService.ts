@@ -21,4 +21,106 @@ export const pushConfig = async (vpsId: number): Promise<void> => {
 export const previewConfig = async (vpsId: number): Promise<AgentConfig> => {
     const response = await apiClient.get<AgentConfig>(`/vps/${vpsId}/config-preview`);
     return response.data;
-};
\ No newline at end of file
+};
+export interface CreateConfigRolloutPayload {
+    config: AgentConfig;
+    /** VPS that get the config first; defaults to the `canaryCount` lowest IDs. */
+    canaryVpsIds?: number[];
+    canaryCount?: number;
+    /** VPS per stage after the canaries; all at once when 0. */
+    batchSize?: number;
+    /** How long each stage must stay healthy before the next starts (30-3600, default 120). */
+    healthWindowSeconds?: number;
+}
+
+export interface ConfigRolloutPlan {
+    stages: { vpsId: number; name: string; connected: boolean }[][];
+    healthWindowSeconds: number;
+}
+
+export type ConfigRolloutStatus = 'running' | 'completed' | 'rolled_back' | 'aborted';
+
+export interface ConfigRolloutTarget {
+    rolloutId: number;
+    vpsId: number;
+    stage: number;
+    status: 'waiting' | 'applied' | 'healthy' | 'failed' | 'skipped' | 'rolled_back';
+    error: string | null;
+    updatedAt: string;
+}
+
+export interface ConfigRollout {
+    id: number;
+    userId: number;
+    status: ConfigRolloutStatus;
+    config: AgentConfig;
+    healthWindowSeconds: number;
+    currentStage: number;
+    stageCount: number;
+    error: string | null;
+    createdAt: string;
+    finishedAt: string | null;
+    /** Only present when fetching a single rollout. */
+    targets?: ConfigRolloutTarget[];
+}
+
+/**
+ * Shows the stages a staged rollout of `payload.config` would use, without pushing anything.
+ */
+export const planConfigRollout = async (payload: CreateConfigRolloutPayload): Promise<ConfigRolloutPlan> => {
+    const response = await apiClient.post<ConfigRolloutPlan>('/settings/agent-config/rollouts', { ...payload, dryRun: true });
+    return response.data;
+};
+
+/**
+ * Starts a staged rollout: canaries first, then batches, rolling back if any agent fails or stops heartbeating.
+ * The global config is only replaced once every stage is healthy.
+ */
+export const startConfigRollout = async (payload: CreateConfigRolloutPayload): Promise<ConfigRollout> => {
+    const response = await apiClient.post<ConfigRollout>('/settings/agent-config/rollouts', payload);
+    return response.data;
+};
+
+export const getConfigRollouts = async (): Promise<ConfigRollout[]> => {
+    const response = await apiClient.get<ConfigRollout[]>('/settings/agent-config/rollouts');
+    return response.data;
+};
+
+export const getConfigRollout = async (id: number): Promise<ConfigRollout> => {
+    const response = await apiClient.get<ConfigRollout>(`/settings/agent-config/rollouts/${id}`);
+    return response.data;
+};
+
+export interface EncryptionKeyInfo {
+    /** Version 0 is the configured master key. */
+    version: number;
+    /** Set for keys rotated for a single organization. */
+    organizationId: number | null;
+    createdAt: string | null;
+    /** Rows still encrypted with this version; they are migrated in the background. */
+    encryptedRows: number;
+}
+
+export interface EncryptionKeyStatus {
+    currentVersion: number;
+    keys: EncryptionKeyInfo[];
+}
+
+/**
+ * Corresponds to GET /api/settings/encryption-keys
+ */
+export const getEncryptionKeyStatus = async (): Promise<EncryptionKeyStatus> => {
+    const response = await apiClient.get<EncryptionKeyStatus>('/settings/encryption-keys');
+    return response.data;
+};
+
+/**
+ * Rotates the global keys, or only those of `organizationId`.
+ * Corresponds to POST /api/settings/encryption-keys/rotate
+ */
+export const rotateEncryptionKey = async (organizationId?: number): Promise<EncryptionKeyStatus> => {
+    const response = await apiClient.post<EncryptionKeyStatus>('/settings/encryption-keys/rotate', null, {
+        params: organizationId === undefined ? undefined : { organizationId },
+    });
+    return response.data;
+};
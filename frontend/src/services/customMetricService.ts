@@ -0,0 +1,61 @@
+import apiClient from './apiClient';
+
+export interface CustomMetricSeries {
+    name: string;
+    labels: Record<string, string>;
+    lastValue: number;
+    lastSeen: string;
+}
+
+/** Same sample format as POST /api/ingest/metrics. */
+export interface CustomMetricSample {
+    name: string;
+    value: number;
+    /** Defaults to the time the server receives the request. */
+    timestamp?: string;
+    labels?: Record<string, string>;
+}
+
+export interface CustomMetricPoint {
+    time: string;
+    labels: Record<string, string>;
+    value: number;
+}
+
+/** Alert rules reference a custom metric by name through this metric type. */
+export const customMetricType = (name: string): string => `custom:${name}`;
+
+/**
+ * Lists the custom metric series reported for a VPS, with their latest value.
+ * Corresponds to GET /api/vps/{vpsId}/metrics/custom
+ */
+export const getCustomMetrics = async (vpsId: number): Promise<CustomMetricSeries[]> => {
+    const response = await apiClient.get<CustomMetricSeries[]>(`/vps/${vpsId}/metrics/custom`);
+    return response.data;
+};
+
+/**
+ * Fetches the samples of one custom metric, per label set.
+ * Corresponds to GET /api/vps/{vpsId}/metrics/custom/timeseries
+ */
+export const getCustomMetricTimeseries = async (
+    vpsId: number,
+    name: string,
+    startTime: string,
+    endTime?: string,
+    interval?: string,
+): Promise<CustomMetricPoint[]> => {
+    const response = await apiClient.get<CustomMetricPoint[]>(`/vps/${vpsId}/metrics/custom/timeseries`, {
+        params: { name, startTime, endTime, interval },
+    });
+    return response.data;
+};
+
+/**
+ * Pushes custom metric samples for a VPS.
+ * Corresponds to POST /api/vps/{vpsId}/custom-metrics
+ */
+export const pushCustomMetrics = async (vpsId: number, samples: CustomMetricSample[]): Promise<{ accepted: number }> => {
+    const response = await apiClient.post<{ accepted: number }>(`/vps/${vpsId}/custom-metrics`, { metrics: samples });
+    return response.data;
+};
@@ -0,0 +1,103 @@
+import apiClient from './apiClient';
+
+export interface DerivedMetric {
+    id: number;
+    userId: number;
+    name: string;
+    /** Arithmetic over collected columns, e.g. `memory_usage_bytes / memory_total_bytes * 100`. */
+    expression: string;
+    unit: string | null;
+    description: string | null;
+    createdAt: string;
+    updatedAt: string;
+}
+
+export interface DerivedMetricPayload {
+    name: string;
+    expression: string;
+    unit?: string | null;
+    description?: string | null;
+}
+
+export interface DerivedMetricPoint {
+    time: string;
+    value: number | null;
+}
+
+export interface ExpressionValidation {
+    valid: boolean;
+    error: string | null;
+}
+
+/** Alert rules reference a derived metric through this metric type. */
+export const derivedMetricType = (id: number): string => `derived:${id}`;
+
+/**
+ * Fetches the derived metrics of the current user.
+ * Corresponds to GET /api/derived-metrics
+ */
+export const getDerivedMetrics = async (): Promise<DerivedMetric[]> => {
+    const response = await apiClient.get<DerivedMetric[]>('/derived-metrics');
+    return response.data;
+};
+
+/**
+ * Lists the column names expressions may reference.
+ * Corresponds to GET /api/derived-metrics/columns
+ */
+export const getExpressionColumns = async (): Promise<string[]> => {
+    const response = await apiClient.get<string[]>('/derived-metrics/columns');
+    return response.data;
+};
+
+/**
+ * Checks an expression without saving it.
+ * Corresponds to POST /api/derived-metrics/validate
+ */
+export const validateExpression = async (expression: string): Promise<ExpressionValidation> => {
+    const response = await apiClient.post<ExpressionValidation>('/derived-metrics/validate', { expression });
+    return response.data;
+};
+
+/**
+ * Creates a derived metric.
+ * Corresponds to POST /api/derived-metrics
+ */
+export const createDerivedMetric = async (payload: DerivedMetricPayload): Promise<DerivedMetric> => {
+    const response = await apiClient.post<DerivedMetric>('/derived-metrics', payload);
+    return response.data;
+};
+
+/**
+ * Updates a derived metric.
+ * Corresponds to PUT /api/derived-metrics/{id}
+ */
+export const updateDerivedMetric = async (id: number, payload: Partial<DerivedMetricPayload>): Promise<DerivedMetric> => {
+    const response = await apiClient.put<DerivedMetric>(`/derived-metrics/${id}`, payload);
+    return response.data;
+};
+
+/**
+ * Deletes a derived metric. Fails while alert rules still use it.
+ * Corresponds to DELETE /api/derived-metrics/{id}
+ */
+export const deleteDerivedMetric = async (id: number): Promise<void> => {
+    await apiClient.delete(`/derived-metrics/${id}`);
+};
+
+/**
+ * Evaluates a derived metric for one VPS over a time range.
+ * Corresponds to GET /api/derived-metrics/{id}/timeseries
+ */
+export const getDerivedMetricTimeseries = async (
+    id: number,
+    vpsId: number,
+    startTime: string,
+    endTime?: string,
+    interval?: string,
+): Promise<DerivedMetricPoint[]> => {
+    const response = await apiClient.get<DerivedMetricPoint[]>(`/derived-metrics/${id}/timeseries`, {
+        params: { vpsId, startTime, endTime, interval },
+    });
+    return response.data;
+};
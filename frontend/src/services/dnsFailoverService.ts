@@ -0,0 +1,98 @@
+import apiClient from './apiClient';
+
+export type DnsCredentials =
+    | { provider: 'cloudflare'; apiToken: string }
+    | { provider: 'route53'; accessKeyId: string; secretAccessKey: string };
+
+/** A DNS failover policy; the provider credentials are write-only. */
+export interface DnsFailoverPolicy {
+    id: number;
+    userId: number;
+    name: string;
+    provider: DnsCredentials['provider'];
+    zoneId: string;
+    recordName: string;
+    recordType: 'A' | 'AAAA';
+    ttl: number;
+    primaryIp: string;
+    standbyIp: string;
+    monitorIds: number[];
+    /** Consecutive checks every monitor must fail (or pass) before switching. */
+    confirmChecks: number;
+    dryRun: boolean;
+    enabled: boolean;
+    activeTarget: 'primary' | 'standby';
+    lastSwitchedAt: string | null;
+    createdAt: string;
+    updatedAt: string;
+}
+
+export interface DnsFailoverEvent {
+    id: number;
+    policyId: number;
+    time: string;
+    kind: 'failover' | 'revert' | 'error';
+    fromIp: string | null;
+    toIp: string | null;
+    dryRun: boolean;
+    details: string | null;
+}
+
+export interface DnsFailoverPolicyPayload {
+    name: string;
+    credentials: DnsCredentials;
+    zoneId: string;
+    recordName: string;
+    recordType?: 'A' | 'AAAA';
+    ttl?: number;
+    primaryIp: string;
+    standbyIp: string;
+    monitorIds: number[];
+    confirmChecks?: number;
+    dryRun?: boolean;
+    enabled?: boolean;
+}
+
+/**
+ * Corresponds to GET /api/dns-failover/policies
+ */
+export const getDnsFailoverPolicies = async (): Promise<DnsFailoverPolicy[]> => {
+    const response = await apiClient.get<DnsFailoverPolicy[]>('/dns-failover/policies');
+    return response.data;
+};
+
+/**
+ * Corresponds to POST /api/dns-failover/policies
+ */
+export const createDnsFailoverPolicy = async (payload: DnsFailoverPolicyPayload): Promise<DnsFailoverPolicy> => {
+    const response = await apiClient.post<DnsFailoverPolicy>('/dns-failover/policies', payload);
+    return response.data;
+};
+
+/**
+ * Corresponds to PUT /api/dns-failover/policies/{id}
+ */
+export const updateDnsFailoverPolicy = async (
+    id: number,
+    changes: Partial<DnsFailoverPolicyPayload>,
+): Promise<DnsFailoverPolicy> => {
+    const response = await apiClient.put<DnsFailoverPolicy>(`/dns-failover/policies/${id}`, changes);
+    return response.data;
+};
+
+/**
+ * Corresponds to DELETE /api/dns-failover/policies/{id}
+ */
+export const deleteDnsFailoverPolicy = async (id: number): Promise<void> => {
+    await apiClient.delete(`/dns-failover/policies/${id}`);
+};
+
+/**
+ * Corresponds to GET /api/dns-failover/policies/{id}/events
+ */
+export const getDnsFailoverEvents = async (id: number, limit?: number): Promise<DnsFailoverEvent[]> => {
+    const response = await apiClient.get<DnsFailoverEvent[]>(`/dns-failover/policies/${id}/events`, {
+        params: { limit },
+    });
+    return response.data;
+};
@@ -0,0 +1,65 @@
+import apiClient from './apiClient';
+
+export interface FileEntry {
+    name: string;
+    path: string;
+    itemType: 'file' | 'directory' | 'symlink' | 'other';
+    sizeBytes: number;
+    /** Unix permission bits; 0 on platforms without them. */
+    mode: number;
+    modifiedAtMs: number;
+    /** Numeric uid/gid on Unix agents. */
+    owner: string;
+    group: string;
+}
+
+export interface DirectoryListing {
+    directory: FileEntry | null;
+    entries: FileEntry[];
+}
+
+/**
+ * Lists a directory on the agent of the given VPS. Directories come first.
+ */
+export const listDirectory = async (vpsId: number, path: string): Promise<DirectoryListing> => {
+    const response = await apiClient.get<DirectoryListing>(`/vps/${vpsId}/files/list`, { params: { path } });
+    return response.data;
+};
+
+/**
+ * Fetches metadata for a single file or directory. Symlinks are not followed.
+ */
+export const statFile = async (vpsId: number, path: string): Promise<FileEntry> => {
+    const response = await apiClient.get<FileEntry>(`/vps/${vpsId}/files/stat`, { params: { path } });
+    return response.data;
+};
+
+/**
+ * Downloads a regular file as a Blob.
+ */
+export const downloadFile = async (vpsId: number, path: string): Promise<Blob> => {
+    const response = await apiClient.get<Blob>(`/vps/${vpsId}/files/download`, {
+        params: { path },
+        responseType: 'blob',
+    });
+    return response.data;
+};
+
+/**
+ * Uploads a file to the given path, replacing any existing file.
+ * @returns The number of bytes written on the agent.
+ */
+export const uploadFile = async (vpsId: number, path: string, file: Blob): Promise<number> => {
+    const response = await apiClient.put<{ path: string; bytesWritten: number }>(`/vps/${vpsId}/files/upload`, file, {
+        params: { path },
+        headers: { 'Content-Type': 'application/octet-stream' },
+    });
+    return response.data.bytesWritten;
+};
+
+/**
+ * Deletes a file or directory. Non-empty directories require `recursive`.
+ */
+export const deleteFile = async (vpsId: number, path: string, recursive = false): Promise<void> => {
+    await apiClient.delete(`/vps/${vpsId}/files/delete`, { params: { path, recursive } });
+};
@@ -0,0 +1,119 @@
+import apiClient from './apiClient';
+import type { HealthScore } from '../types';
+
+/** A shorthand (cpu, memory, swap, disk, disk_read, disk_write, net_rx, net_tx, net) or a collected column name. */
+export type FleetTopMetric = string;
+
+export interface FleetTopEntry {
+    vpsId: number;
+    vpsName: string;
+    value: number;
+}
+
+export interface FleetTopResponse {
+    metric: string;
+    agg: 'avg' | 'max';
+    windowSeconds: number;
+    entries: FleetTopEntry[];
+}
+
+/**
+ * Fetches the top-N VPS by a metric aggregated over a window.
+ * Corresponds to GET /api/fleet/top
+ */
+export const getFleetTop = async (
+    metric: FleetTopMetric,
+    options: { window?: string; n?: number; agg?: 'avg' | 'max' } = {},
+): Promise<FleetTopResponse> => {
+    const response = await apiClient.get<FleetTopResponse>('/fleet/top', {
+        params: { metric, ...options },
+    });
+    return response.data;
+};
+
+export interface VpsHealthEntry {
+    vpsId: number;
+    vpsName: string;
+    group: string | null;
+    health: HealthScore | null;
+}
+
+export interface GroupHealth {
+    /** Null for VPS without a group. */
+    group: string | null;
+    score: number | null;
+    vpsCount: number;
+}
+
+export interface FleetHealthResponse {
+    servers: VpsHealthEntry[];
+    groups: GroupHealth[];
+}
+
+/**
+ * Fetches composite health scores per VPS and per group, as of the last cache refresh.
+ * Corresponds to GET /api/fleet/health
+ */
+export const getFleetHealth = async (): Promise<FleetHealthResponse> => {
+    const response = await apiClient.get<FleetHealthResponse>('/fleet/health');
+    return response.data;
+};
+
+export interface FleetMapServer {
+    vpsId: number;
+    name: string;
+    status: string;
+    latitude: number;
+    longitude: number;
+    /** `city` when placed by IP geolocation, `country` at the centroid of the reported country. */
+    precision: 'exact' | 'city' | 'country';
+    countryCode: string | null;
+    healthScore: number | null;
+    cpuUsagePercent: number | null;
+    memoryUsagePercent: number | null;
+    diskUsagePercent: number | null;
+    clusterId: number | null;
+}
+
+export interface FleetMapCluster {
+    id: number;
+    latitude: number;
+    longitude: number;
+    vpsIds: number[];
+}
+
+export interface FleetMapResponse {
+    zoom: number;
+    servers: FleetMapServer[];
+    clusters: FleetMapCluster[];
+    unlocatedVpsIds: number[];
+}
+
+/**
+ * Fetches located servers and cluster hints for a world map.
+ * Corresponds to GET /api/fleet/map
+ */
+export const getFleetMap = async (zoom?: number): Promise<FleetMapResponse> => {
+    const response = await apiClient.get<FleetMapResponse>('/fleet/map', { params: { zoom } });
+    return response.data;
+};
+
+export interface FleetLocationGroup {
+    countryCode: string | null;
+    country: string | null;
+    city: string | null;
+    /** The mean position of the servers. */
+    latitude: number;
+    longitude: number;
+    vpsIds: number[];
+    onlineCount: number;
+}
+
+/**
+ * Fetches located servers grouped by country and city, largest groups first.
+ * Corresponds to GET /api/fleet/locations
+ */
+export const getFleetLocations = async (): Promise<FleetLocationGroup[]> => {
+    const response = await apiClient.get<FleetLocationGroup[]>('/fleet/locations');
+    return response.data;
+};
@@ -0,0 +1,51 @@
+import apiClient from './apiClient';
+
+export interface IngestKey {
+  id: number;
+  userId: number;
+  name: string;
+  keyPrefix: string;
+  /** Metric name prefixes the key may write; empty allows any name. */
+  metricPrefixes: string[];
+  rateLimitPerMinute: number;
+  createdAt: string;
+  lastUsedAt: string | null;
+}
+
+export interface CreateIngestKeyPayload {
+  name: string;
+  metricPrefixes?: string[];
+  rateLimitPerMinute?: number;
+}
+
+/** The plain text key is only returned once, on creation. */
+export interface CreatedIngestKey extends IngestKey {
+  key: string;
+}
+
+/**
+ * Fetches the current user's metric ingest keys.
+ * Corresponds to GET /api/settings/ingest-keys
+ */
+export const getIngestKeys = async (): Promise<IngestKey[]> => {
+  const response = await apiClient.get<IngestKey[]>('/settings/ingest-keys');
+  return response.data;
+};
+
+/**
+ * Creates a key for pushing metrics to POST /api/ingest/metrics with
+ * `Authorization: Bearer nni_...`.
+ * Corresponds to POST /api/settings/ingest-keys
+ */
+export const createIngestKey = async (payload: CreateIngestKeyPayload): Promise<CreatedIngestKey> => {
+  const response = await apiClient.post<CreatedIngestKey>('/settings/ingest-keys', payload);
+  return response.data;
+};
+
+/**
+ * Revokes an ingest key.
+ * Corresponds to DELETE /api/settings/ingest-keys/:id
+ */
+export const deleteIngestKey = async (id: number): Promise<void> => {
+  await apiClient.delete(`/settings/ingest-keys/${id}`);
+};
@@ -0,0 +1,76 @@
+import apiClient from './apiClient';
+
+export interface PackageFact {
+    name: string;
+    version: string;
+    arch: string;
+    /** Source package (dpkg only). */
+    sourceName?: string;
+}
+
+export interface VpsInventory {
+    vpsId: number;
+    collectedAt: string;
+    changedAt: string;
+    packageManager: string;
+    packages: PackageFact[];
+    sysctl: Record<string, string>;
+    enabledServices: string[];
+}
+
+export interface InventoryChange {
+    id: number;
+    vpsId: number;
+    changedAt: string;
+    category: 'package' | 'sysctl' | 'service';
+    name: string;
+    /** Null when the entry was added. */
+    oldValue: string | null;
+    /** Null when the entry was removed. */
+    newValue: string | null;
+}
+
+export type VersionOp = 'lt' | 'le' | 'eq' | 'ge' | 'gt';
+
+export interface InventorySearchHit {
+    vpsId: number;
+    vpsName: string;
+    name: string;
+    version: string;
+    arch: string;
+}
+
+/**
+ * Finds hosts with a package installed, optionally filtered by version.
+ * Corresponds to GET /api/inventory/search
+ */
+export const searchInventory = async (
+    pkg: string,
+    op?: VersionOp,
+    version?: string,
+): Promise<InventorySearchHit[]> => {
+    const response = await apiClient.get<InventorySearchHit[]>('/inventory/search', {
+        params: { package: pkg, op, version },
+    });
+    return response.data;
+};
+
+/**
+ * Fetches the latest inventory snapshot of a VPS.
+ * Corresponds to GET /api/inventory/vps/{vpsId}
+ */
+export const getVpsInventory = async (vpsId: number): Promise<VpsInventory> => {
+    const response = await apiClient.get<VpsInventory>(`/inventory/vps/${vpsId}`);
+    return response.data;
+};
+
+/**
+ * Fetches recent inventory changes of a VPS, newest first.
+ * Corresponds to GET /api/inventory/vps/{vpsId}/changes
+ */
+export const getVpsInventoryChanges = async (vpsId: number, limit?: number): Promise<InventoryChange[]> => {
+    const response = await apiClient.get<InventoryChange[]>(`/inventory/vps/${vpsId}/changes`, {
+        params: { limit },
+    });
+    return response.data;
+};
@@ -0,0 +1,46 @@
+import apiClient from './apiClient';
+
+export type MetricUnit =
+    | 'percent'
+    | 'bytes'
+    | 'bytes_per_second'
+    | 'count'
+    | 'per_second'
+    | 'seconds'
+    | 'milliseconds'
+    | 'days'
+    | 'celsius'
+    | 'watts'
+    | 'boolean';
+
+export interface MetricDescriptor {
+    name: string;
+    description: string;
+    unit: MetricUnit;
+    type: 'gauge' | 'counter';
+    /** Where the metric comes from, e.g. `system`, `disk_health` (needs smartctl) or `server`. */
+    source: string;
+    /** Operating systems the agent collects it on; empty when it isn't collected yet. */
+    os: Array<'linux' | 'windows' | 'macos'>;
+    usage: {
+        alertRule: boolean;
+        conditionExpression: boolean;
+        derivedMetric: boolean;
+    };
+}
+
+let catalog: Promise<MetricDescriptor[]> | null = null;
+
+/** The server's metric catalog. It only changes with the server version, so it is fetched once. */
+export const getMetricCatalog = (): Promise<MetricDescriptor[]> => {
+    if (!catalog) {
+        catalog = apiClient
+            .get<MetricDescriptor[]>('/metrics/catalog')
+            .then(response => response.data)
+            .catch(error => {
+                catalog = null;
+                throw error;
+            });
+    }
+    return catalog;
+};
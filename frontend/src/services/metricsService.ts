@@ -45,4 +45,22 @@ export const getVpsMetrics = async (
     // Consider how to handle errors, e.g., re-throw or return a specific error structure
     throw error;
   }
-};
\ No newline at end of file
+};
+/**
+ * Download URL for a metric export (GET /api/vps/:vpsId/metrics/export), streamed
+ * by the server as CSV or JSON lines.
+ * @param interval Downsampling interval such as '5m'; raw points when omitted.
+ */
+export const getVpsMetricsExportUrl = (
+  vpsId: number,
+  start: string,
+  end: string,
+  format: 'csv' | 'jsonl',
+  interval?: string,
+): string => {
+  const query = new URLSearchParams({ start, end, format });
+  if (interval) {
+    query.set('interval', interval);
+  }
+  return `/api/vps/${vpsId}/metrics/export?${query}`;
+};
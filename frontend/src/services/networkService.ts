@@ -0,0 +1,43 @@
+import apiClient from './apiClient';
+
+export interface MeshNode {
+    vpsId: number;
+    name: string;
+    group: string | null;
+    status: string;
+}
+
+export interface MeshPoint {
+    time: string;
+    /** Null when every ping in the bucket was lost. */
+    rttMs: number | null;
+    lossPercent: number | null;
+}
+
+/** What `sourceVpsId` measured towards `targetVpsId`. */
+export interface MeshLink {
+    sourceVpsId: number;
+    targetVpsId: number;
+    latestRttMs: number | null;
+    latestLossPercent: number | null;
+    avgRttMs: number | null;
+    avgLossPercent: number | null;
+    lastSeen: string;
+    history: MeshPoint[];
+}
+
+export interface MeshResponse {
+    windowSeconds: number;
+    bucketSeconds: number;
+    nodes: MeshNode[];
+    links: MeshLink[];
+}
+
+/**
+ * Fetches the latency matrix between agents with per-link history.
+ * Corresponds to GET /api/network/mesh
+ */
+export const getMesh = async (options: { window?: string; bucket?: string } = {}): Promise<MeshResponse> => {
+    const response = await apiClient.get<MeshResponse>('/network/mesh', { params: options });
+    return response.data;
+};
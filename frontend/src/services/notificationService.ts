@@ -1,5 +1,5 @@
 import apiClient from './apiClient';
-import type { ChannelTemplate, ChannelResponse, CreateChannelRequest, UpdateChannelRequest } from '../types';
+import type { ChannelTemplate, ChannelResponse, CreateChannelRequest, TestChannelResponse, UpdateChannelRequest } from '../types';
 
 /**
  * Fetches all available notification channel templates from the backend.
@@ -46,11 +46,12 @@ export const deleteChannel = async (id: number): Promise<void> => {
 };
 
 /**
- * Sends a test message to a specific notification channel.
+ * Renders a sample alert with the channel's template and sends it.
  * @param id - The ID of the channel to test.
  * @param message - An optional custom message for the test.
+ * @param template - An optional template to try instead of the channel's.
  */
-export const testChannel = async (id: number, message?: string): Promise<{ message: string }> => {
-    const response = await apiClient.post<{ message: string }>(`/notifications/channels/${id}/test`, { message });
+export const testChannel = async (id: number, message?: string, template?: string): Promise<TestChannelResponse> => {
+    const response = await apiClient.post<TestChannelResponse>(`/notifications/channels/${id}/test`, { message, template });
     return response.data;
 };
\ No newline at end of file
@@ -0,0 +1,106 @@
+import apiClient from './apiClient';
+
+export type OrganizationRole = 'owner' | 'member';
+
+export interface Organization {
+  id: number;
+  name: string;
+  personal: boolean;
+  role: OrganizationRole;
+  active: boolean;
+  minMonitorFrequencySeconds: number;
+  createdAt: string;
+}
+
+export interface OrganizationMember {
+  organizationId: number;
+  userId: number;
+  username: string;
+  role: OrganizationRole;
+  createdAt: string;
+}
+
+/**
+ * Fetches the organizations the current user belongs to.
+ * Corresponds to GET /api/organizations
+ */
+export const getOrganizations = async (): Promise<Organization[]> => {
+  const response = await apiClient.get<Organization[]>('/organizations');
+  return response.data;
+};
+
+/**
+ * Creates a shared organization owned by the current user.
+ * Corresponds to POST /api/organizations
+ */
+export const createOrganization = async (name: string): Promise<Organization> => {
+  const response = await apiClient.post<Organization>('/organizations', { name });
+  return response.data;
+};
+
+/**
+ * Renames an organization. Owners only.
+ * Corresponds to PUT /api/organizations/:id
+ */
+export const renameOrganization = async (id: number, name: string): Promise<Organization> => {
+  const response = await apiClient.put<Organization>(`/organizations/${id}`, { name });
+  return response.data;
+};
+
+/**
+ * Deletes an empty shared organization. Owners only.
+ * Corresponds to DELETE /api/organizations/:id
+ */
+export const deleteOrganization = async (id: number): Promise<void> => {
+  await apiClient.delete(`/organizations/${id}`);
+};
+
+/**
+ * Switches the organization subsequent requests are scoped to.
+ * Corresponds to PUT /api/user/active-organization
+ */
+export const switchOrganization = async (organizationId: number): Promise<Organization> => {
+  const response = await apiClient.put<Organization>('/user/active-organization', { organizationId });
+  return response.data;
+};
+
+/**
+ * Fetches the members of an organization.
+ * Corresponds to GET /api/organizations/:id/members
+ */
+export const getOrganizationMembers = async (id: number): Promise<OrganizationMember[]> => {
+  const response = await apiClient.get<OrganizationMember[]>(`/organizations/${id}/members`);
+  return response.data;
+};
+
+/**
+ * Adds a user to an organization or changes their role.
+ * Corresponds to POST /api/organizations/:id/members
+ */
+export const upsertOrganizationMember = async (
+  id: number,
+  username: string,
+  role: OrganizationRole = 'member',
+): Promise<OrganizationMember> => {
+  const response = await apiClient.post<OrganizationMember>(`/organizations/${id}/members`, { username, role });
+  return response.data;
+};
+
+/**
+ * Removes a member from an organization, or leaves it when `userId` is the current user.
+ * Corresponds to DELETE /api/organizations/:id/members/:userId
+ */
+export const removeOrganizationMember = async (id: number, userId: number): Promise<void> => {
+  await apiClient.delete(`/organizations/${id}/members/${userId}`);
+};
+
+/**
+ * Sets the shortest service monitor interval allowed in an organization. Admins only.
+ * Corresponds to PUT /api/organizations/:id/quota
+ */
+export const setOrganizationMonitorQuota = async (
+  id: number,
+  minMonitorFrequencySeconds: number | null,
+): Promise<void> => {
+  await apiClient.put(`/organizations/${id}/quota`, { minMonitorFrequencySeconds });
+};
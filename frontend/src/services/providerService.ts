@@ -0,0 +1,122 @@
+import apiClient from './apiClient';
+
+export type ProviderKind = 'hetzner' | 'vultr' | 'digitalocean';
+export type PowerAction = 'reboot' | 'stop' | 'start';
+
+/** Cloud provider credentials; the API token is write-only. */
+export interface ProviderAccount {
+    id: number;
+    userId: number;
+    name: string;
+    provider: ProviderKind;
+    createdAt: string;
+    updatedAt: string;
+}
+
+export interface VpsProviderLink {
+    accountId: number;
+    /** Hetzner server id, Vultr instance id or DigitalOcean droplet id. */
+    providerServerId: string;
+}
+
+export interface VpsPowerAction {
+    id: number;
+    vpsId: number;
+    userId: number;
+    action: PowerAction;
+    status: 'pending' | 'confirmed' | 'succeeded' | 'failed' | 'expired';
+    /** Whether the agent was connected when the action was requested. */
+    agentOnline: boolean;
+    requestedAt: string;
+    expiresAt: string;
+    completedAt: string | null;
+    error: string | null;
+}
+
+export interface PowerActionResponse extends VpsPowerAction {
+    /** Only present while the action awaits confirmation. */
+    confirmationCode?: string;
+}
+
+/**
+ * Corresponds to GET /api/settings/providers
+ */
+export const getProviderAccounts = async (): Promise<ProviderAccount[]> => {
+    const response = await apiClient.get<ProviderAccount[]>('/settings/providers');
+    return response.data;
+};
+
+/**
+ * Corresponds to POST /api/settings/providers
+ */
+export const createProviderAccount = async (
+    name: string,
+    provider: ProviderKind,
+    token: string,
+): Promise<ProviderAccount> => {
+    const response = await apiClient.post<ProviderAccount>('/settings/providers', { name, provider, token });
+    return response.data;
+};
+
+/**
+ * Corresponds to PUT /api/settings/providers/{id}
+ */
+export const updateProviderAccount = async (
+    id: number,
+    changes: { name?: string; token?: string },
+): Promise<ProviderAccount> => {
+    const response = await apiClient.put<ProviderAccount>(`/settings/providers/${id}`, changes);
+    return response.data;
+};
+
+/**
+ * Corresponds to DELETE /api/settings/providers/{id}
+ */
+export const deleteProviderAccount = async (id: number): Promise<void> => {
+    await apiClient.delete(`/settings/providers/${id}`);
+};
+
+/**
+ * Corresponds to GET /api/vps/{vpsId}/provider
+ */
+export const getVpsProviderLink = async (vpsId: number): Promise<VpsProviderLink> => {
+    const response = await apiClient.get<VpsProviderLink>(`/vps/${vpsId}/provider`);
+    return response.data;
+};
+
+/**
+ * Corresponds to PUT /api/vps/{vpsId}/provider
+ */
+export const setVpsProviderLink = async (vpsId: number, link: VpsProviderLink): Promise<VpsProviderLink> => {
+    const response = await apiClient.put<VpsProviderLink>(`/vps/${vpsId}/provider`, link);
+    return response.data;
+};
+
+/**
+ * Corresponds to DELETE /api/vps/{vpsId}/provider
+ */
+export const deleteVpsProviderLink = async (vpsId: number): Promise<void> => {
+    await apiClient.delete(`/vps/${vpsId}/provider`);
+};
+
+/**
+ * Requests a power action (returns a confirmation code), or carries it out when
+ * called again with that code.
+ * Corresponds to POST /api/vps/{vpsId}/power
+ */
+export const requestPowerAction = async (
+    vpsId: number,
+    action: PowerAction,
+    confirmationCode?: string,
+): Promise<PowerActionResponse> => {
+    const response = await apiClient.post<PowerActionResponse>(`/vps/${vpsId}/power`, { action, confirmationCode });
+    return response.data;
+};
+
+/**
+ * Corresponds to GET /api/vps/{vpsId}/power/actions
+ */
+export const getPowerActions = async (vpsId: number, limit?: number): Promise<VpsPowerAction[]> => {
+    const response = await apiClient.get<VpsPowerAction[]>(`/vps/${vpsId}/power/actions`, { params: { limit } });
+    return response.data;
+};
@@ -0,0 +1,38 @@
+import apiClient from './apiClient';
+
+/** Read-only key for embedding status and uptime data in other dashboards. */
+export interface PublicApiKey {
+    id: number;
+    userId: number;
+    name: string;
+    key: string;
+    createdAt: string;
+    lastUsedAt: string | null;
+}
+
+/**
+ * Corresponds to GET /api/settings/public-keys
+ */
+export const getPublicKeys = async (): Promise<PublicApiKey[]> => {
+    const response = await apiClient.get<PublicApiKey[]>('/settings/public-keys');
+    return response.data;
+};
+
+/**
+ * Corresponds to POST /api/settings/public-keys
+ */
+export const createPublicKey = async (name: string): Promise<PublicApiKey> => {
+    const response = await apiClient.post<PublicApiKey>('/settings/public-keys', { name });
+    return response.data;
+};
+
+/**
+ * Corresponds to DELETE /api/settings/public-keys/{id}
+ */
+export const deletePublicKey = async (id: number): Promise<void> => {
+    await apiClient.delete(`/settings/public-keys/${id}`);
+};
+
+/** Embed URL for GET /api/public/{servers|monitors} with the key as a query parameter. */
+export const getPublicDataUrl = (resource: 'servers' | 'monitors', key: string): string =>
+    `${window.location.origin}/api/public/${resource}?key=${encodeURIComponent(key)}`;
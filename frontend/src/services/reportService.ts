@@ -0,0 +1,128 @@
+import apiClient from './apiClient';
+
+export interface AgentVersionEvent {
+    id: number;
+    vpsId: number;
+    time: string;
+    /** Null for the first version seen. */
+    previousVersion: string | null;
+    version: string;
+}
+
+export interface AgentVersionCount {
+    version: string;
+    vpsCount: number;
+    outdated: boolean;
+}
+
+export interface AgentVersionReport {
+    /** Agents older than this (the server's version) count as outdated. */
+    latestVersion: string;
+    totalVps: number;
+    outdatedCount: number;
+    /** VPS whose agent never reported a version. */
+    unknownCount: number;
+    /** Newest version first. */
+    versions: AgentVersionCount[];
+    /** Version changes in the last week, newest first. */
+    recentChanges: AgentVersionEvent[];
+}
+
+/**
+ * Fetches the agent version distribution across the fleet.
+ * Corresponds to GET /api/reports/agent-versions
+ */
+export const getAgentVersionReport = async (): Promise<AgentVersionReport> => {
+    const response = await apiClient.get<AgentVersionReport>('/reports/agent-versions');
+    return response.data;
+};
+
+/**
+ * Fetches the agent version changes of one VPS, newest first.
+ * Corresponds to GET /api/vps/{vpsId}/agent-versions
+ */
+export const getAgentVersionHistory = async (vpsId: number): Promise<AgentVersionEvent[]> => {
+    const response = await apiClient.get<AgentVersionEvent[]>(`/vps/${vpsId}/agent-versions`);
+    return response.data;
+};
+
+export type ReportPeriod = 'weekly' | 'monthly';
+
+export interface VpsUptimeEntry {
+    vpsId: number;
+    name: string;
+    uptimePercent: number | null;
+    downtimeSeconds: number;
+    outageCount: number;
+    /** Mean latency of the service checks this VPS ran as an agent. */
+    meanCheckLatencyMs: number | null;
+    metricSamples: number;
+}
+
+export interface MonitorUptimeEntry {
+    monitorId: number;
+    name: string;
+    monitorType: string;
+    uptimePercent: number | null;
+    meanLatencyMs: number | null;
+    checkCount: number;
+    failedCheckCount: number;
+}
+
+export interface UptimeReport {
+    id: number;
+    userId: number;
+    period: ReportPeriod;
+    periodStart: string;
+    /** Exclusive. */
+    periodEnd: string;
+    generatedAt: string;
+    vps: VpsUptimeEntry[];
+    monitors: MonitorUptimeEntry[];
+}
+
+export interface UptimeReportSchedule {
+    weekly: boolean;
+    monthly: boolean;
+    channelIds: number[];
+}
+
+/**
+ * Corresponds to GET /api/reports/uptime
+ */
+export const getUptimeReports = async (period?: ReportPeriod): Promise<UptimeReport[]> => {
+    const response = await apiClient.get<UptimeReport[]>('/reports/uptime', { params: { period } });
+    return response.data;
+};
+
+/**
+ * Generates the report of the period containing `periodOf`, or of the last completed period.
+ * Corresponds to POST /api/reports/uptime
+ */
+export const generateUptimeReport = async (period: ReportPeriod, periodOf?: string): Promise<UptimeReport> => {
+    const response = await apiClient.post<UptimeReport>('/reports/uptime', { period, periodOf });
+    return response.data;
+};
+
+/**
+ * Corresponds to DELETE /api/reports/uptime/{id}
+ */
+export const deleteUptimeReport = async (id: number): Promise<void> => {
+    await apiClient.delete(`/reports/uptime/${id}`);
+};
+
+/**
+ * Corresponds to GET /api/reports/uptime/schedule
+ */
+export const getUptimeReportSchedule = async (): Promise<UptimeReportSchedule> => {
+    const response = await apiClient.get<UptimeReportSchedule>('/reports/uptime/schedule');
+    return response.data;
+};
+
+/**
+ * Corresponds to PUT /api/reports/uptime/schedule
+ */
+export const updateUptimeReportSchedule = async (schedule: UptimeReportSchedule): Promise<UptimeReportSchedule> => {
+    const response = await apiClient.put<UptimeReportSchedule>('/reports/uptime/schedule', schedule);
+    return response.data;
+};
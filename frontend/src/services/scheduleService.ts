@@ -0,0 +1,94 @@
+import apiClient from './apiClient';
+
+export interface ScheduledTask {
+    id: number;
+    userId: number;
+    name: string;
+    /** Five-field cron expression evaluated in UTC, e.g. `0 3 * * 1-5`. */
+    cronExpression: string;
+    scriptId: number;
+    targetVpsIds: number[];
+    /** Every VPS carrying one of these tags when the schedule runs is targeted too. */
+    targetTagIds: number[];
+    enabled: boolean;
+    nextRunAt: string | null;
+    lastRunAt: string | null;
+    createdAt: string;
+    updatedAt: string;
+}
+
+export interface ScheduledTaskPayload {
+    name: string;
+    cronExpression: string;
+    scriptId: number;
+    targetVpsIds?: number[];
+    targetTagIds?: number[];
+    enabled?: boolean;
+}
+
+export type ScheduledTaskRunStatus = 'dispatched' | 'skipped' | 'failed';
+
+export interface ScheduledTaskRun {
+    id: number;
+    scheduledTaskId: number;
+    scheduledFor: string;
+    startedAt: string;
+    status: ScheduledTaskRunStatus;
+    /** The batch command a dispatched run started. */
+    batchCommandId: string | null;
+    targetVpsCount: number;
+    message: string | null;
+}
+
+/**
+ * Fetches the schedules of the current user.
+ * Corresponds to GET /api/schedules
+ */
+export const getSchedules = async (): Promise<ScheduledTask[]> => {
+    const response = await apiClient.get<ScheduledTask[]>('/schedules');
+    return response.data;
+};
+
+/**
+ * Fetches one schedule.
+ * Corresponds to GET /api/schedules/{id}
+ */
+export const getSchedule = async (id: number): Promise<ScheduledTask> => {
+    const response = await apiClient.get<ScheduledTask>(`/schedules/${id}`);
+    return response.data;
+};
+
+/**
+ * Creates a schedule.
+ * Corresponds to POST /api/schedules
+ */
+export const createSchedule = async (payload: ScheduledTaskPayload): Promise<ScheduledTask> => {
+    const response = await apiClient.post<ScheduledTask>('/schedules', payload);
+    return response.data;
+};
+
+/**
+ * Updates a schedule; omitted fields keep their value.
+ * Corresponds to PUT /api/schedules/{id}
+ */
+export const updateSchedule = async (id: number, payload: Partial<ScheduledTaskPayload>): Promise<ScheduledTask> => {
+    const response = await apiClient.put<ScheduledTask>(`/schedules/${id}`, payload);
+    return response.data;
+};
+
+/**
+ * Deletes a schedule and its run history.
+ * Corresponds to DELETE /api/schedules/{id}
+ */
+export const deleteSchedule = async (id: number): Promise<void> => {
+    await apiClient.delete(`/schedules/${id}`);
+};
+
+/**
+ * Fetches the most recent runs of a schedule, newest first.
+ * Corresponds to GET /api/schedules/{id}/runs
+ */
+export const getScheduleRuns = async (id: number, limit?: number): Promise<ScheduledTaskRun[]> => {
+    const response = await apiClient.get<ScheduledTaskRun[]>(`/schedules/${id}/runs`, { params: { limit } });
+    return response.data;
+};
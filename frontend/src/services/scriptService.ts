@@ -23,4 +23,34 @@ export const scriptService = {
     deleteScript: async (id: number): Promise<void> => {
         await apiClient.delete(`/command-scripts/${id}`);
     },
+
+    /**
+     * Renders the script for one VPS with secrets masked.
+     * Corresponds to POST /api/command-scripts/{id}/preview
+     */
+    previewScript: async (id: number, vpsId: number, values: Record<string, string>): Promise<string> => {
+        const response = await apiClient.post<{ command: string }>(`/command-scripts/${id}/preview`, {
+            vps_id: vpsId,
+            values,
+        });
+        return response.data.command;
+    },
+
+    /**
+     * Runs the script as a batch command and returns its id.
+     * Corresponds to POST /api/command-scripts/{id}/run
+     */
+    runScript: async (
+        id: number,
+        vpsIds: number[],
+        values: Record<string, string>,
+        executionAlias?: string,
+    ): Promise<string> => {
+        const response = await apiClient.post<{ batch_command_id: string }>(`/command-scripts/${id}/run`, {
+            vps_ids: vpsIds,
+            values,
+            execution_alias: executionAlias,
+        });
+        return response.data.batch_command_id;
+    },
 };
\ No newline at end of file
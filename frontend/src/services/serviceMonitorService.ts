@@ -90,4 +90,148 @@ export const getMonitorResultsByVpsId = async (
 export const getMonitorsByVpsId = async (vpsId: number | string): Promise<ServiceMonitor[]> => {
   const response = await apiClient.get(`/vps/${vpsId}/monitors`);
   return response.data;
-};
\ No newline at end of file
+};
+export interface MaintenanceWindow {
+  id: number;
+  monitorId: number;
+  startsAt: string;
+  endsAt: string;
+  reason: string | null;
+  createdAt: string;
+}
+
+export const getMaintenanceWindows = async (monitorId: number): Promise<MaintenanceWindow[]> => {
+  const response = await apiClient.get(`/monitors/${monitorId}/maintenance`);
+  return response.data;
+};
+
+export const createMaintenanceWindow = async (
+  monitorId: number,
+  window: { startsAt: string; endsAt: string; reason?: string }
+): Promise<MaintenanceWindow> => {
+  const response = await apiClient.post(`/monitors/${monitorId}/maintenance`, window);
+  return response.data;
+};
+
+export const deleteMaintenanceWindow = async (monitorId: number, windowId: number): Promise<void> => {
+  await apiClient.delete(`/monitors/${monitorId}/maintenance/${windowId}`);
+};
+
+export interface SloStatus {
+  totalChecks: number;
+  badChecks: number;
+  sliPercent: number | null;
+  budgetRemainingPercent: number | null;
+  fastBurnRate: number | null;
+  slowBurnRate: number | null;
+}
+
+export interface ServiceMonitorSlo {
+  id: number;
+  monitorId: number;
+  name: string;
+  targetPercent: number;
+  windowDays: number;
+  latencyThresholdMs: number | null;
+  fastBurnRate: number;
+  slowBurnRate: number;
+  isActive: boolean;
+  lastAlertedAt: string | null;
+  createdAt: string;
+  updatedAt: string;
+  channelIds: number[];
+  status: SloStatus;
+}
+
+export type SloInput = Partial<Pick<ServiceMonitorSlo,
+  'name' | 'targetPercent' | 'windowDays' | 'latencyThresholdMs' | 'fastBurnRate' | 'slowBurnRate' | 'isActive' | 'channelIds'>>;
+
+export const getMonitorSlos = async (monitorId: number): Promise<ServiceMonitorSlo[]> => {
+  const response = await apiClient.get(`/monitors/${monitorId}/slos`);
+  return response.data;
+};
+
+export const createMonitorSlo = async (monitorId: number, slo: SloInput): Promise<ServiceMonitorSlo> => {
+  const response = await apiClient.post(`/monitors/${monitorId}/slos`, slo);
+  return response.data;
+};
+
+export const updateMonitorSlo = async (monitorId: number, sloId: number, slo: SloInput): Promise<ServiceMonitorSlo> => {
+  const response = await apiClient.put(`/monitors/${monitorId}/slos/${sloId}`, slo);
+  return response.data;
+};
+
+export const deleteMonitorSlo = async (monitorId: number, sloId: number): Promise<void> => {
+  await apiClient.delete(`/monitors/${monitorId}/slos/${sloId}`);
+};
+
+export interface HeatmapCell {
+  /** 0 = Monday ... 6 = Sunday */
+  dayOfWeek: number;
+  hour: number;
+  checks: number;
+  upPercent: number;
+  avgLatencyMs: number | null;
+  p95LatencyMs: number | null;
+}
+
+export interface AvailabilityHeatmap {
+  startTime: string;
+  endTime: string;
+  utcOffsetMinutes: number;
+  /** Only hours with at least one check are present. */
+  cells: HeatmapCell[];
+}
+
+const localUtcOffsetMinutes = () => -new Date().getTimezoneOffset();
+
+export const getMonitorHeatmap = async (monitorId: number, weeks?: number): Promise<AvailabilityHeatmap> => {
+  const response = await apiClient.get(`/monitors/${monitorId}/heatmap`, {
+    params: { weeks, utcOffsetMinutes: localUtcOffsetMinutes() },
+  });
+  return response.data;
+};
+
+export const getVpsMonitorHeatmap = async (vpsId: number | string, weeks?: number): Promise<AvailabilityHeatmap> => {
+  const response = await apiClient.get(`/vps/${vpsId}/monitor-heatmap`, {
+    params: { weeks, utcOffsetMinutes: localUtcOffsetMinutes() },
+  });
+  return response.data;
+};
+
+export interface RegionLatencyPoint {
+  time: string;
+  latencyMs: number | null;
+  /** Share of successful checks in the bucket, 0 to 1. */
+  upRatio: number | null;
+}
+
+export interface RegionLatencySeries {
+  agentId: number;
+  agentName: string;
+  medianLatencyMs: number | null;
+  /** Mean relative distance from the other agents' median (0.5 = 50% off). */
+  divergence: number | null;
+  points: RegionLatencyPoint[];
+}
+
+export interface RegionComparison {
+  monitorId: number;
+  intervalSeconds: number;
+  /** Highest per-agent divergence; low when all regions slow down together. */
+  divergenceScore: number | null;
+  outlierAgentId: number | null;
+  agents: RegionLatencySeries[];
+}
+
+export const getMonitorRegionComparison = async (
+  monitorId: number,
+  startTime: string,
+  endTime: string,
+  interval?: string,
+): Promise<RegionComparison> => {
+  const response = await apiClient.get(`/monitors/${monitorId}/regions`, {
+    params: { startTime, endTime, interval },
+  });
+  return response.data;
+};
@@ -0,0 +1,103 @@
+import apiClient from './apiClient';
+import type { VpsListItemResponse } from '../types';
+
+export interface ShareLink {
+    id: number;
+    userId: number;
+    organizationId: number | null;
+    name: string;
+    /** Shared VPS, or null when the whole dashboard is shared. */
+    vpsId: number | null;
+    /** Shared reseller client, whose VPS and statement are visible. */
+    clientId: number | null;
+    expiresAt: string;
+    lastAccessedAt: string | null;
+    createdAt: string;
+    token: string;
+}
+
+export interface CreateShareLinkPayload {
+    name: string;
+    vpsId?: number | null;
+    clientId?: number | null;
+    expiresAt: string;
+}
+
+export interface SharedView {
+    name: string;
+    expiresAt: string;
+    servers: VpsListItemResponse[];
+}
+
+/**
+ * Fetches the share links of the current user.
+ * Corresponds to GET /api/user/shares
+ */
+export const getShareLinks = async (): Promise<ShareLink[]> => {
+    const response = await apiClient.get<ShareLink[]>('/user/shares');
+    return response.data;
+};
+
+/**
+ * Creates a read-only share link.
+ * Corresponds to POST /api/user/shares
+ */
+export const createShareLink = async (payload: CreateShareLinkPayload): Promise<ShareLink> => {
+    const response = await apiClient.post<ShareLink>('/user/shares', payload);
+    return response.data;
+};
+
+/**
+ * Revokes a share link.
+ * Corresponds to DELETE /api/user/shares/{id}
+ */
+export const revokeShareLink = async (id: number): Promise<void> => {
+    await apiClient.delete(`/user/shares/${id}`);
+};
+
+/**
+ * Fetches the servers visible through a share token. No login required.
+ * Corresponds to GET /api/share/{token}
+ */
+export const getSharedView = async (token: string): Promise<SharedView> => {
+    const response = await apiClient.get<SharedView>(`/share/${encodeURIComponent(token)}`);
+    return response.data;
+};
+
+export interface ClientStatementEntry {
+    vpsId: number;
+    name: string;
+    currency: string | null;
+    amount: number | null;
+    uptimePercent: number | null;
+    downtimeSeconds: number;
+}
+
+export interface ClientStatement {
+    clientName: string;
+    start: string;
+    end: string;
+    entries: ClientStatementEntry[];
+    totals: { currency: string; amount: number }[];
+    uptimePercent: number | null;
+}
+
+/**
+ * Fetches the statement of a client share link. No login required.
+ * Corresponds to GET /api/share/{token}/statement
+ */
+export const getSharedStatement = async (
+    token: string,
+    range: { start?: string; end?: string } = {},
+): Promise<ClientStatement> => {
+    const response = await apiClient.get<ClientStatement>(`/share/${encodeURIComponent(token)}/statement`, {
+        params: range,
+    });
+    return response.data;
+};
+
+/** WebSocket URL streaming live updates for a share token. */
+export const getSharedViewWebSocketUrl = (token: string): string => {
+    const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
+    return `${protocol}//${window.location.host}/ws/public?share=${encodeURIComponent(token)}`;
+};
@@ -0,0 +1,105 @@
+import apiClient from './apiClient';
+import type { Branding } from './brandingService';
+
+export interface UptimeSummary {
+    uptime24h: number | null;
+    uptime7d: number | null;
+    uptime30d: number | null;
+}
+
+export interface StatusPage {
+    id: number;
+    userId: number;
+    slug: string;
+    title: string;
+    description: string | null;
+    vpsIds: number[];
+    monitorIds: number[];
+    showUptime: boolean;
+    showLatency: boolean;
+    isPublished: boolean;
+    createdAt: string;
+    updatedAt: string;
+}
+
+export interface StatusPagePayload {
+    slug: string;
+    title: string;
+    description?: string | null;
+    vpsIds: number[];
+    monitorIds: number[];
+    showUptime: boolean;
+    showLatency: boolean;
+    isPublished: boolean;
+}
+
+export type StatusPageMonitorStatus = 'up' | 'degraded' | 'down' | 'unknown';
+
+export interface StatusPageView {
+    slug: string;
+    title: string;
+    description: string | null;
+    generatedAt: string;
+    /** The instance branding, for the page header and footer. */
+    branding: Branding;
+    servers: {
+        id: number;
+        name: string;
+        status: string;
+        uptime?: UptimeSummary;
+    }[];
+    monitors: {
+        id: number;
+        name: string;
+        status: StatusPageMonitorStatus;
+        latencyMs?: number;
+        uptime?: UptimeSummary;
+    }[];
+}
+
+/**
+ * Fetches the status pages of the current user.
+ * Corresponds to GET /api/status-pages
+ */
+export const getStatusPages = async (): Promise<StatusPage[]> => {
+    const response = await apiClient.get<StatusPage[]>('/status-pages');
+    return response.data;
+};
+
+/**
+ * Corresponds to POST /api/status-pages
+ */
+export const createStatusPage = async (payload: StatusPagePayload): Promise<StatusPage> => {
+    const response = await apiClient.post<StatusPage>('/status-pages', payload);
+    return response.data;
+};
+
+/**
+ * Corresponds to PUT /api/status-pages/{id}
+ */
+export const updateStatusPage = async (id: number, payload: StatusPagePayload): Promise<StatusPage> => {
+    const response = await apiClient.put<StatusPage>(`/status-pages/${id}`, payload);
+    return response.data;
+};
+
+/**
+ * Corresponds to DELETE /api/status-pages/{id}
+ */
+export const deleteStatusPage = async (id: number): Promise<void> => {
+    await apiClient.delete(`/status-pages/${id}`);
+};
+
+/**
+ * Fetches a published status page. No login required.
+ * Corresponds to GET /api/public/status/{slug}
+ */
+export const getPublicStatusPage = async (slug: string): Promise<StatusPageView> => {
+    const response = await apiClient.get<StatusPageView>(`/public/status/${encodeURIComponent(slug)}`);
+    return response.data;
+};
+
+/** WebSocket URL streaming `status_page` messages for a published page. */
+export const getStatusPageWebSocketUrl = (slug: string): string => {
+    const protocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
+    return `${protocol}//${window.location.host}/ws/public?status_page=${encodeURIComponent(slug)}`;
+};
@@ -0,0 +1,34 @@
+/** Text frames sent by the server; terminal output arrives as binary frames. */
+export type TerminalServerMessage =
+    | { type: 'error'; message: string }
+    | { type: 'closed'; message: string };
+
+/** Text frames accepted by the server; raw input may also be sent as binary frames. */
+export type TerminalClientMessage =
+    | { type: 'input'; data: string }
+    | { type: 'resize'; rows: number; cols: number };
+
+/**
+ * Opens an interactive shell on the agent of the given VPS.
+ * Authentication uses the session cookie. The caller wires the socket to a terminal emulator.
+ * @param vpsId The VPS to open the shell on.
+ * @param rows Initial terminal height.
+ * @param cols Initial terminal width.
+ * @returns A WebSocket instance with `binaryType` set to 'arraybuffer'.
+ */
+export const connectTerminal = (vpsId: number, rows: number, cols: number): WebSocket => {
+    const wsProtocol = window.location.protocol === 'https:' ? 'wss:' : 'ws:';
+    const wsUrl = `${wsProtocol}//${window.location.host}/ws/terminal/${vpsId}?rows=${rows}&cols=${cols}`;
+    const socket = new WebSocket(wsUrl);
+    socket.binaryType = 'arraybuffer';
+    return socket;
+};
+
+/**
+ * Sends a control message over an open terminal socket.
+ */
+export const sendTerminalMessage = (socket: WebSocket, message: TerminalClientMessage): void => {
+    if (socket.readyState === WebSocket.OPEN) {
+        socket.send(JSON.stringify(message));
+    }
+};
@@ -0,0 +1,76 @@
+import apiClient from './apiClient';
+
+export type TrafficGranularity = 'daily' | 'monthly';
+
+export interface TrafficUsage {
+    /** The day, or the first day of the month (YYYY-MM-DD). */
+    period: string;
+    rxBytes: number;
+    txBytes: number;
+}
+
+export interface VpsTrafficTotal {
+    vpsId: number;
+    name: string;
+    group: string | null;
+    trafficLimitBytes: number | null;
+    rxBytes: number;
+    txBytes: number;
+}
+
+export interface TrafficAggregate {
+    /** Tag ID; null for groups. */
+    id: number | null;
+    /** Tag name or group path. */
+    name: string;
+    vpsCount: number;
+    rxBytes: number;
+    txBytes: number;
+}
+
+export interface TrafficSummary {
+    month: string;
+    rxBytes: number;
+    txBytes: number;
+    vps: VpsTrafficTotal[];
+    tags: TrafficAggregate[];
+    groups: TrafficAggregate[];
+}
+
+export interface TrafficHistoryParams {
+    granularity?: TrafficGranularity;
+    /** YYYY-MM-DD, inclusive. */
+    from?: string;
+    to?: string;
+}
+
+/**
+ * Corresponds to GET /api/vps/:vpsId/traffic/history
+ */
+export const getVpsTrafficHistory = async (vpsId: number, params: TrafficHistoryParams = {}): Promise<TrafficUsage[]> => {
+    const response = await apiClient.get<TrafficUsage[]>(`/vps/${vpsId}/traffic/history`, { params });
+    return response.data;
+};
+
+/**
+ * Corresponds to GET /api/traffic/summary
+ * @param month YYYY-MM; the current month when omitted.
+ */
+export const getTrafficSummary = async (month?: string): Promise<TrafficSummary> => {
+    const response = await apiClient.get<TrafficSummary>('/traffic/summary', { params: { month } });
+    return response.data;
+};
+
+/** Download URLs for the CSV exports. */
+export const vpsTrafficHistoryCsvUrl = (vpsId: number, params: TrafficHistoryParams = {}): string => {
+    const query = new URLSearchParams({ format: 'csv' });
+    Object.entries(params).forEach(([key, value]) => {
+        if (value) query.set(key, value);
+    });
+    return `/api/vps/${vpsId}/traffic/history?${query}`;
+};
+
+export const trafficSummaryCsvUrl = (month?: string): string => {
+    const query = new URLSearchParams({ format: 'csv', ...(month ? { month } : {}) });
+    return `/api/traffic/summary?${query}`;
+};
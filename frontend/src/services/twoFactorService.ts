@@ -0,0 +1,46 @@
+import apiClient from './apiClient';
+
+export interface TwoFactorStatus {
+  enabled: boolean;
+  recoveryCodesRemaining: number;
+}
+
+export interface TwoFactorEnrollment {
+  secret: string;
+  provisioningUri: string;
+}
+
+/**
+ * Fetches whether 2FA is enabled for the current user.
+ * Corresponds to GET /api/user/2fa
+ */
+export const getTwoFactorStatus = async (): Promise<TwoFactorStatus> => {
+  const response = await apiClient.get<TwoFactorStatus>('/user/2fa');
+  return response.data;
+};
+
+/**
+ * Starts enrollment and returns a fresh secret for the authenticator app.
+ * Corresponds to POST /api/user/2fa/enroll
+ */
+export const enrollTwoFactor = async (): Promise<TwoFactorEnrollment> => {
+  const response = await apiClient.post<TwoFactorEnrollment>('/user/2fa/enroll');
+  return response.data;
+};
+
+/**
+ * Confirms enrollment with a code from the app. The recovery codes are only returned once.
+ * Corresponds to POST /api/user/2fa/confirm
+ */
+export const confirmTwoFactor = async (code: string): Promise<string[]> => {
+  const response = await apiClient.post<{ recoveryCodes: string[] }>('/user/2fa/confirm', { code });
+  return response.data.recoveryCodes;
+};
+
+/**
+ * Disables 2FA. Requires a current TOTP code or a recovery code.
+ * Corresponds to POST /api/user/2fa/disable
+ */
+export const disableTwoFactor = async (code: string): Promise<void> => {
+  await apiClient.post('/user/2fa/disable', { code });
+};
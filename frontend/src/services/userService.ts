@@ -1,8 +1,18 @@
 import apiClient from './apiClient';
 
+export type Role = 'viewer' | 'operator' | 'admin';
+
 export interface User {
     id: number;
     username: string;
+    role?: Role;
+}
+
+export interface AdminUser {
+    id: number;
+    username: string;
+    role: Role;
+    createdAt: string;
 }
 
 export interface ConnectedAccount {
@@ -42,4 +52,20 @@ export const getAvailableProviders = async (): Promise<OAuthProvider[]> => {
 export const updateUserLanguage = async (language: string): Promise<{ message: string }> => {
     const response = await apiClient.put<{ message: string }>('/user/preference', { language });
     return response.data;
-};
\ No newline at end of file
+};
+
+/**
+ * Corresponds to GET /api/admin/users
+ */
+export const getAllUsers = async (): Promise<AdminUser[]> => {
+    const response = await apiClient.get<AdminUser[]>('/admin/users');
+    return response.data;
+};
+
+/**
+ * Corresponds to PUT /api/admin/users/{id}/role
+ */
+export const updateUserRole = async (id: number, role: Role): Promise<AdminUser> => {
+    const response = await apiClient.put<AdminUser>(`/admin/users/${id}/role`, { role });
+    return response.data;
+};
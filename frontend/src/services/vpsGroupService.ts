@@ -0,0 +1,72 @@
+import apiClient from './apiClient';
+
+export interface VpsGroupStats {
+  vpsCount: number;
+  onlineCount: number;
+  avgCpuPercent: number | null;
+  avgMemoryPercent: number | null;
+  avgHealthScore: number | null;
+}
+
+export interface VpsGroupNode {
+  id: number;
+  parentId: number | null;
+  name: string;
+  /** E.g. "eu/hetzner/rack-1"; this is what the VPS `group` field shows. */
+  path: string;
+  /** Covers the group and all of its subgroups. */
+  stats: VpsGroupStats;
+  children: VpsGroupNode[];
+}
+
+export interface VpsGroupTree {
+  groups: VpsGroupNode[];
+  ungrouped: VpsGroupStats;
+}
+
+export interface VpsGroup {
+  id: number;
+  organizationId: number;
+  parentId: number | null;
+  name: string;
+  createdAt: string;
+  updatedAt: string;
+}
+
+/**
+ * Fetches the group tree with aggregated metrics and health.
+ * Corresponds to GET /api/vps-groups
+ */
+export const getVpsGroupTree = async (): Promise<VpsGroupTree> => {
+  const response = await apiClient.get<VpsGroupTree>('/vps-groups');
+  return response.data;
+};
+
+/**
+ * Creates a group, at the top level unless a parent is given.
+ * Corresponds to POST /api/vps-groups
+ */
+export const createVpsGroup = async (name: string, parentId?: number): Promise<VpsGroup> => {
+  const response = await apiClient.post<VpsGroup>('/vps-groups', { name, parentId });
+  return response.data;
+};
+
+/**
+ * Renames a group or moves it; `parentId: null` moves it to the top level.
+ * Corresponds to PUT /api/vps-groups/{id}
+ */
+export const updateVpsGroup = async (
+  id: number,
+  payload: { name?: string; parentId?: number | null },
+): Promise<VpsGroup> => {
+  const response = await apiClient.put<VpsGroup>(`/vps-groups/${id}`, payload);
+  return response.data;
+};
+
+/**
+ * Deletes a group; its subgroups and VPS move up to its parent.
+ * Corresponds to DELETE /api/vps-groups/{id}
+ */
+export const deleteVpsGroup = async (id: number): Promise<void> => {
+  await apiClient.delete(`/vps-groups/${id}`);
+};
@@ -1,6 +1,6 @@
 import apiClient from './apiClient.ts'; // Assuming you have an apiClient for making requests
 // VpsListItemResponse is the type returned by the backend for list and detail views now
-import type { Vps, VpsListItemResponse, BulkActionResponse } from '../types';
+import type { Vps, VpsListItemResponse, BulkActionResponse, MaintenanceState, ProvisioningToken, VirtualGroup, VirtualGroupMembers } from '../types';
 
 export interface CreateVpsPayload {
   name: string;
@@ -42,7 +42,10 @@ export const getVpsDetail = async (vpsId: string): Promise<VpsListItemResponse>
 
 export interface UpdateVpsPayload {
   name?: string;
+  /** Flat group path such as `eu/hetzner`; missing groups are created. */
   group?: string;
+  /** Takes precedence over `group`. */
+  groupId?: number;
   tag_ids?: number[];
   // Traffic monitoring config fields
   traffic_limit_bytes?: number | null;
@@ -77,20 +80,70 @@ export const updateVps = async (vpsId: number, payload: UpdateVpsPayload): Promi
   }
 };
 
+export type VpsSortKey = 'name' | 'ipAddress' | 'status' | 'group' | 'agentVersion' | 'createdAt';
+
+export interface VpsListParams {
+  page?: number;
+  perPage?: number;
+  sortBy?: VpsSortKey;
+  order?: 'asc' | 'desc';
+  status?: string;
+  group?: string;
+  /** Matches the group and its subgroups. */
+  groupId?: number;
+  tagId?: number;
+  /** Case-insensitive match on name or IP address. */
+  search?: string;
+  virtualGroup?: VirtualGroup;
+}
+
+export interface VpsListPage {
+  items: VpsListItemResponse[];
+  /** VPS matching the filters, across all pages. */
+  total: number;
+  page: number;
+  perPage: number;
+}
+
 /**
- * Fetches all VPS list items.
- * Useful for populating dropdowns or lists where full details are not immediately needed per item.
+ * Fetches one page of the VPS list.
+ * Corresponds to GET /api/vps
  */
-export const getAllVpsListItems = async (): Promise<VpsListItemResponse[]> => {
+export const getVpsListPage = async (params: VpsListParams = {}): Promise<VpsListPage> => {
   try {
-    const response = await apiClient.get<VpsListItemResponse[]>('/vps'); // Assuming '/vps' endpoint returns the list
+    const response = await apiClient.get<VpsListPage>('/vps', { params });
     return response.data;
   } catch (error) {
-    console.error('Error fetching all VPS list items:', error);
+    console.error('Error fetching VPS list page:', error);
     throw error;
   }
 };
 
+/**
+ * Fetches all VPS list items, page by page.
+ * Useful for populating dropdowns or lists where full details are not immediately needed per item.
+ * @param virtualGroup Only return VPS in this virtual group.
+ */
+export const getAllVpsListItems = async (virtualGroup?: VirtualGroup): Promise<VpsListItemResponse[]> => {
+  const perPage = 500;
+  const items: VpsListItemResponse[] = [];
+  for (let page = 1; ; page++) {
+    const result = await getVpsListPage({ page, perPage, virtualGroup });
+    items.push(...result.items);
+    if (result.items.length < perPage || items.length >= result.total) {
+      return items;
+    }
+  }
+};
+
+/**
+ * Fetches the members of every virtual group among the current user's VPS.
+ */
+export const getVirtualGroups = async (): Promise<VirtualGroupMembers[]> => {
+  const response = await apiClient.get<VirtualGroupMembers[]>('/vps/virtual-groups');
+  return response.data;
+};
+
 /**
  * Dismisses the active renewal reminder for a specific VPS.
  */
@@ -129,4 +182,392 @@ export const triggerAgentUpdate = async (vpsIds: number[]): Promise<BulkActionRe
     console.error('Error triggering agent update:', error);
     throw error;
   }
-};
\ No newline at end of file
+};
+export type WatchdogEventKind = 'exited' | 'restarted' | 'restart_failed' | 'rate_limited' | 'recovered';
+
+/** An event reported by the agent's process watchdog. */
+export interface WatchdogEvent {
+  id: number;
+  vpsId: number;
+  time: string;
+  targetName: string;
+  kind: WatchdogEventKind;
+  details: string | null;
+}
+
+/**
+ * Fetches recent process watchdog events for a VPS (last 7 days by default).
+ */
+export const getVpsWatchdogEvents = async (vpsId: number, startTime?: string, endTime?: string): Promise<WatchdogEvent[]> => {
+  const response = await apiClient.get<WatchdogEvent[]>(`/vps/${vpsId}/watchdog-events`, {
+    params: { startTime, endTime },
+  });
+  return response.data;
+};
+
+export interface ProcessMetric {
+  time: string;
+  vpsId: number;
+  pid: number;
+  name: string;
+  /** Share of one CPU core; may exceed 100 on multi-core hosts. */
+  cpuUsagePercent: number;
+  memoryRssBytes: number;
+  username: string | null;
+}
+
+export interface ProcessList {
+  /** When the snapshot was taken; null if the agent hasn't reported processes yet. */
+  time: string | null;
+  processes: ProcessMetric[];
+}
+
+/**
+ * Fetches the top processes by CPU and memory from the latest snapshot (or the one at `at`).
+ */
+export const getVpsProcesses = async (vpsId: number, sort: 'cpu' | 'memory' = 'cpu', at?: string): Promise<ProcessList> => {
+  const response = await apiClient.get<ProcessList>(`/vps/${vpsId}/processes`, {
+    params: { sort, at },
+  });
+  return response.data;
+};
+
+export interface DiskIoMetric {
+  time: string;
+  vpsId: number;
+  device: string;
+  readBps: number;
+  writeBps: number;
+  readIops: number;
+  writeIops: number;
+  /** Average time per completed request in milliseconds, queueing included. */
+  awaitMs: number;
+  utilizationPercent: number;
+}
+
+/**
+ * Fetches per-device disk throughput, IOPS and latency, optionally averaged per `interval` (e.g. "5m").
+ */
+export const getVpsDiskIo = async (
+  vpsId: number,
+  startTime: string,
+  endTime?: string,
+  interval?: string,
+  device?: string,
+): Promise<DiskIoMetric[]> => {
+  const response = await apiClient.get<DiskIoMetric[]>(`/vps/${vpsId}/metrics/disk-io`, {
+    params: { startTime, endTime, interval, device },
+  });
+  return response.data;
+};
+
+export interface NetworkInterfaceMetric {
+  time: string;
+  vpsId: number;
+  interfaceName: string;
+  rxErrorsPerSec: number;
+  txErrorsPerSec: number;
+  rxDroppedPerSec: number;
+  txDroppedPerSec: number;
+  collisionsPerSec: number;
+  /** Carrier up/down transitions since the previous sample (or within the bucket). */
+  carrierChanges: number;
+  linkUp: boolean;
+  speedMbps: number | null;
+  duplex: string | null;
+}
+
+/**
+ * Fetches per-interface error/drop rates and link state, optionally averaged per `interval` (e.g. "5m").
+ */
+export const getVpsNetworkInterfaces = async (
+  vpsId: number,
+  startTime: string,
+  endTime?: string,
+  interval?: string,
+  iface?: string,
+): Promise<NetworkInterfaceMetric[]> => {
+  const response = await apiClient.get<NetworkInterfaceMetric[]>(`/vps/${vpsId}/metrics/network-interfaces`, {
+    params: { startTime, endTime, interval, interface: iface },
+  });
+  return response.data;
+};
+
+export interface GpuMetric {
+  time: string;
+  vpsId: number;
+  gpuIndex: number;
+  name: string;
+  vendor: 'nvidia' | 'amd';
+  uuid: string | null;
+  utilizationPercent: number;
+  memoryUsedBytes: number;
+  memoryTotalBytes: number;
+  temperatureCelsius: number | null;
+  powerDrawWatts: number | null;
+}
+
+/**
+ * Fetches per-GPU utilization, memory, temperature and power, optionally averaged per `interval` (e.g. "5m").
+ */
+export const getVpsGpuMetrics = async (
+  vpsId: number,
+  startTime: string,
+  endTime?: string,
+  interval?: string,
+  gpu?: number,
+): Promise<GpuMetric[]> => {
+  const response = await apiClient.get<GpuMetric[]>(`/vps/${vpsId}/metrics/gpus`, {
+    params: { startTime, endTime, interval, gpu },
+  });
+  return response.data;
+};
+
+export interface DiskHealthMetric {
+  time: string;
+  vpsId: number;
+  device: string;
+  model: string;
+  serial: string | null;
+  smartPassed: boolean | null;
+  /** Reallocated sectors (ATA) or media errors (NVMe). */
+  reallocatedSectors: number | null;
+  pendingSectors: number | null;
+  /** Share of the rated endurance used, 0 to 100. */
+  wearPercent: number | null;
+  temperatureCelsius: number | null;
+  powerOnHours: number | null;
+}
+
+/**
+ * Fetches SMART readings of the VPS's drives. Agents re-read drives every few minutes.
+ */
+export const getVpsDiskHealth = async (
+  vpsId: number,
+  startTime: string,
+  endTime?: string,
+  device?: string,
+): Promise<DiskHealthMetric[]> => {
+  const response = await apiClient.get<DiskHealthMetric[]>(`/vps/${vpsId}/metrics/disk-health`, {
+    params: { startTime, endTime, device },
+  });
+  return response.data;
+};
+
+export interface TemperatureMetric {
+  time: string;
+  vpsId: number;
+  sensor: string;
+  temperatureCelsius: number;
+  criticalCelsius: number | null;
+}
+
+/**
+ * Fetches chassis, CPU and other sensor temperatures, optionally averaged per `interval` (e.g. "5m").
+ */
+export const getVpsTemperatures = async (
+  vpsId: number,
+  startTime: string,
+  endTime?: string,
+  interval?: string,
+  sensor?: string,
+): Promise<TemperatureMetric[]> => {
+  const response = await apiClient.get<TemperatureMetric[]>(`/vps/${vpsId}/metrics/temperatures`, {
+    params: { startTime, endTime, interval, sensor },
+  });
+  return response.data;
+};
+
+export interface VpsOutage {
+  start: string;
+  /** Null while the VPS is still offline. */
+  end: string | null;
+  durationSeconds: number;
+}
+
+export interface VpsUptimeHistory {
+  vpsId: number;
+  startTime: string;
+  endTime: string;
+  graceSeconds: number;
+  /** Null when no online/offline transitions are known for the range. */
+  uptimePercent: number | null;
+  monitoredSeconds: number;
+  downtimeSeconds: number;
+  outages: VpsOutage[];
+  daily: { date: string; uptimePercent: number | null; downtimeSeconds: number }[];
+}
+
+/**
+ * Fetches availability for a VPS, derived from agent online/offline transitions.
+ * Defaults to the last 30 days.
+ */
+export const getVpsUptime = async (vpsId: number, startTime?: string, endTime?: string): Promise<VpsUptimeHistory> => {
+  const response = await apiClient.get<VpsUptimeHistory>(`/vps/${vpsId}/uptime`, {
+    params: { startTime, endTime },
+  });
+  return response.data;
+};
+
+export interface MaintenanceEvent {
+  id: number;
+  vpsId: number;
+  time: string;
+  action: 'enter' | 'exit';
+  reason: string | null;
+  endsAt: string | null;
+  /** Username, or "auto" for scheduled exits. */
+  actor: string | null;
+}
+
+/**
+ * Puts a VPS into maintenance mode. Alerts for it are skipped and the time is excluded from uptime.
+ * @param durationHours Leave maintenance automatically after this many hours.
+ */
+export const enterMaintenance = async (vpsId: number, reason?: string, durationHours?: number): Promise<MaintenanceState> => {
+  const response = await apiClient.post<MaintenanceState>(`/vps/${vpsId}/maintenance`, { reason, durationHours });
+  return response.data;
+};
+
+/**
+ * Takes a VPS out of maintenance mode.
+ */
+export const exitMaintenance = async (vpsId: number): Promise<void> => {
+  await apiClient.delete(`/vps/${vpsId}/maintenance`);
+};
+
+/**
+ * Fetches maintenance enter/exit events for a VPS, newest first. Defaults to the last 30 days.
+ */
+export const getMaintenanceEvents = async (vpsId: number, startTime?: string, endTime?: string): Promise<MaintenanceEvent[]> => {
+  const response = await apiClient.get<MaintenanceEvent[]>(`/vps/${vpsId}/maintenance/events`, {
+    params: { startTime, endTime },
+  });
+  return response.data;
+};
+
+export interface CreateProvisioningTokenPayload {
+  name: string;
+  group?: string;
+  /** Defaults to true: registered VPS are archived after staying offline. */
+  isEphemeral?: boolean;
+  tagIds?: number[];
+  maxUses?: number;
+  expiresAt?: string;
+}
+
+export const getProvisioningTokens = async (): Promise<ProvisioningToken[]> => {
+  const response = await apiClient.get<ProvisioningToken[]>('/provisioning-tokens');
+  return response.data;
+};
+
+export const createProvisioningToken = async (payload: CreateProvisioningTokenPayload): Promise<ProvisioningToken> => {
+  const response = await apiClient.post<ProvisioningToken>('/provisioning-tokens', payload);
+  return response.data;
+};
+
+export interface EnrollmentToken {
+  token: ProvisioningToken;
+  /** One-liners that install the agent and register the machine as a new VPS. */
+  installCommands: { linux: string; macos: string; windows: string };
+}
+
+/**
+ * Creates a single-use token for enrolling one machine, valid for `expiresInHours` (default 24).
+ */
+export const createEnrollmentToken = async (
+  payload: { name?: string; group?: string; tagIds?: number[]; expiresInHours?: number } = {},
+): Promise<EnrollmentToken> => {
+  const response = await apiClient.post<EnrollmentToken>('/provisioning-tokens/enrollment', payload);
+  return response.data;
+};
+
+/**
+ * Fetches the install script for an existing VPS, with its agent credentials embedded.
+ */
+export const getVpsInstallScript = async (vpsId: number, os: 'linux' | 'macos' | 'windows'): Promise<string> => {
+  const response = await apiClient.get<string>(`/vps/${vpsId}/install-script`, {
+    params: { os },
+    responseType: 'text',
+  });
+  return response.data;
+};
+
+/**
+ * Revokes a provisioning token. VPS already registered with it are not affected.
+ */
+export const deleteProvisioningToken = async (id: number): Promise<void> => {
+  await apiClient.delete(`/provisioning-tokens/${id}`);
+};
+
+export type ContainerAction = 'start' | 'stop' | 'restart';
+
+export interface ContainerActionResult {
+  containerId: string;
+  /** False when the container was already in the requested state. */
+  stateChanged: boolean;
+}
+
+/**
+ * Starts, stops or restarts a Docker container through the VPS's agent.
+ * @param timeoutSeconds Grace period before a stopping container is killed. Defaults to 10.
+ */
+export const dockerContainerAction = async (
+  vpsId: number,
+  containerId: string,
+  action: ContainerAction,
+  timeoutSeconds?: number,
+): Promise<ContainerActionResult> => {
+  const response = await apiClient.post<ContainerActionResult>(
+    `/vps/${vpsId}/docker/${encodeURIComponent(containerId)}/action`,
+    { action, timeoutSeconds },
+  );
+  return response.data;
+};
+
+export interface AgentClientCertificate {
+  vpsId: number;
+  /** Lowercase hex SHA-256 of the certificate. */
+  fingerprint: string;
+  issuedAt: string;
+  expiresAt: string;
+}
+
+export interface IssuedAgentCertificate {
+  certificatePem: string;
+  /** Shown once; the server doesn't keep it. */
+  privateKeyPem: string;
+  caCertificatePem: string;
+  fingerprint: string;
+  expiresAt: string;
+}
+
+/**
+ * Fetches the client certificate issued to the VPS's agent, or null when there is none.
+ */
+export const getAgentCertificate = async (vpsId: number): Promise<AgentClientCertificate | null> => {
+  try {
+    const response = await apiClient.get<AgentClientCertificate>(`/vps/${vpsId}/client-certificate`);
+    return response.data;
+  } catch (error) {
+    if ((error as { response?: { status?: number } }).response?.status === 404) {
+      return null;
+    }
+    throw error;
+  }
+};
+
+/**
+ * Issues a new client certificate for the VPS's agent. From its next connection the agent must present it.
+ */
+export const issueAgentCertificate = async (vpsId: number): Promise<IssuedAgentCertificate> => {
+  const response = await apiClient.post<IssuedAgentCertificate>(`/vps/${vpsId}/client-certificate`);
+  return response.data;
+};
+
+/**
+ * Revokes the VPS's client certificate; the agent authenticates with its secret alone again.
+ */
+export const revokeAgentCertificate = async (vpsId: number): Promise<void> => {
+  await apiClient.delete(`/vps/${vpsId}/client-certificate`);
+};
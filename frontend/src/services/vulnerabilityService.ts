@@ -0,0 +1,67 @@
+import apiClient from './apiClient';
+
+export type Severity = 'unknown' | 'low' | 'medium' | 'high' | 'critical';
+
+export interface VpsVulnerability {
+    vpsId: number;
+    vpsName: string;
+    vulnerabilityId: string;
+    aliases: string[];
+    summary: string;
+    severity: Severity;
+    cvssScore: number | null;
+    packageName: string;
+    installedVersion: string;
+    fixedVersion: string | null;
+    firstSeenAt: string;
+    lastSeenAt: string;
+}
+
+export interface VulnerabilityFilter {
+    minSeverity?: Severity;
+    vpsId?: number;
+    package?: string;
+}
+
+export interface VulnerabilityAlertSettings {
+    minSeverity: Severity;
+    channelIds: number[];
+}
+
+/**
+ * Lists vulnerabilities affecting the current user's hosts.
+ * Corresponds to GET /api/vulnerabilities
+ */
+export const getVulnerabilities = async (filter: VulnerabilityFilter = {}): Promise<VpsVulnerability[]> => {
+    const response = await apiClient.get<VpsVulnerability[]>('/vulnerabilities', { params: filter });
+    return response.data;
+};
+
+/**
+ * Lists vulnerabilities affecting a single VPS.
+ * Corresponds to GET /api/vulnerabilities/vps/{vpsId}
+ */
+export const getVpsVulnerabilities = async (vpsId: number, minSeverity?: Severity): Promise<VpsVulnerability[]> => {
+    const response = await apiClient.get<VpsVulnerability[]>(`/vulnerabilities/vps/${vpsId}`, {
+        params: { minSeverity },
+    });
+    return response.data;
+};
+
+/**
+ * Corresponds to GET /api/vulnerabilities/alert-settings
+ */
+export const getVulnerabilityAlertSettings = async (): Promise<VulnerabilityAlertSettings> => {
+    const response = await apiClient.get<VulnerabilityAlertSettings>('/vulnerabilities/alert-settings');
+    return response.data;
+};
+
+/**
+ * Corresponds to PUT /api/vulnerabilities/alert-settings
+ */
+export const updateVulnerabilityAlertSettings = async (
+    settings: VulnerabilityAlertSettings,
+): Promise<VulnerabilityAlertSettings> => {
+    const response = await apiClient.put<VulnerabilityAlertSettings>('/vulnerabilities/alert-settings', settings);
+    return response.data;
+};
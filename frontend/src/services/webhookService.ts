@@ -0,0 +1,115 @@
+import apiClient from './apiClient';
+
+export type WebhookEventType =
+    | 'vps.online'
+    | 'vps.offline'
+    | 'batch_command.finished'
+    | 'renewal.due'
+    | 'agent.updated';
+
+export type WebhookDeliveryStatus = 'pending' | 'delivered' | 'dead';
+
+/** An outgoing webhook; the signing secret is write-only. */
+export interface WebhookSubscription {
+    id: number;
+    organizationId: number;
+    userId: number;
+    name: string;
+    url: string;
+    /** Empty means every event type. */
+    eventTypes: WebhookEventType[];
+    isActive: boolean;
+    createdAt: string;
+    updatedAt: string;
+}
+
+export interface CreatedWebhook extends WebhookSubscription {
+    /** Only returned on creation. */
+    secret: string;
+}
+
+export interface WebhookInput {
+    name: string;
+    url: string;
+    /** Generated by the server when omitted. */
+    secret?: string;
+    eventTypes: WebhookEventType[];
+    isActive?: boolean;
+}
+
+export interface WebhookDelivery {
+    id: number;
+    subscriptionId: number;
+    eventId: string;
+    eventType: WebhookEventType;
+    payload: unknown;
+    status: WebhookDeliveryStatus;
+    attempts: number;
+    nextAttemptAt: string;
+    lastAttemptAt: string | null;
+    lastStatusCode: number | null;
+    lastError: string | null;
+    createdAt: string;
+}
+
+/**
+ * Corresponds to GET /api/settings/webhooks
+ */
+export const getWebhooks = async (): Promise<WebhookSubscription[]> => {
+    const response = await apiClient.get<WebhookSubscription[]>('/settings/webhooks');
+    return response.data;
+};
+
+/**
+ * Corresponds to GET /api/settings/webhooks/event-types
+ */
+export const getWebhookEventTypes = async (): Promise<WebhookEventType[]> => {
+    const response = await apiClient.get<WebhookEventType[]>('/settings/webhooks/event-types');
+    return response.data;
+};
+
+/**
+ * Corresponds to POST /api/settings/webhooks
+ */
+export const createWebhook = async (input: WebhookInput): Promise<CreatedWebhook> => {
+    const response = await apiClient.post<CreatedWebhook>('/settings/webhooks', input);
+    return response.data;
+};
+
+/**
+ * Corresponds to PUT /api/settings/webhooks/:id
+ */
+export const updateWebhook = async (id: number, input: Partial<WebhookInput>): Promise<WebhookSubscription> => {
+    const response = await apiClient.put<WebhookSubscription>(`/settings/webhooks/${id}`, input);
+    return response.data;
+};
+
+/**
+ * Corresponds to DELETE /api/settings/webhooks/:id
+ */
+export const deleteWebhook = async (id: number): Promise<void> => {
+    await apiClient.delete(`/settings/webhooks/${id}`);
+};
+
+/**
+ * Deliveries of a webhook, newest first.
+ * Corresponds to GET /api/settings/webhooks/:id/deliveries
+ */
+export const getWebhookDeliveries = async (
+    id: number,
+    status?: WebhookDeliveryStatus,
+): Promise<WebhookDelivery[]> => {
+    const response = await apiClient.get<WebhookDelivery[]>(`/settings/webhooks/${id}/deliveries`, {
+        params: { status },
+    });
+    return response.data;
+};
+
+/**
+ * Requeues a dead delivery.
+ * Corresponds to POST /api/settings/webhooks/:id/deliveries/:deliveryId/retry
+ */
+export const retryWebhookDelivery = async (id: number, deliveryId: number): Promise<WebhookDelivery> => {
+    const response = await apiClient.post<WebhookDelivery>(`/settings/webhooks/${id}/deliveries/${deliveryId}/retry`);
+    return response.data;
+};
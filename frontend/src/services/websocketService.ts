@@ -1,11 +1,24 @@
 import { EventEmitter } from './eventEmitter';
-import type { FullServerListPushType, ServiceMonitorResult, PerformanceMetricBatch } from '../types';
+import type { FullServerListPushType, ServiceMonitorResult, PerformanceMetricBatch, VirtualGroupMembers } from '../types';
+import type { AlertEventPush } from './alertService';
 import { throttle } from 'lodash';
 
 const isSecure = window.location.protocol === 'https:';
 const defaultWsProtocol = isSecure ? 'wss://' : 'ws://';
 const WS_URL_BASE = import.meta.env.VITE_WS_BASE_URL || `${defaultWsProtocol}${window.location.host}`;
 
+// Version of the WebSocket protocol this client understands. The schema is served at /api/ws-schema.
+const WS_PROTOCOL_VERSION = 1;
+
+// Narrows the updates a /ws/metrics connection receives. A VPS is included when it
+// matches any criterion; an empty subscription covers every VPS.
+export interface WsSubscription {
+  vpsIds?: number[];
+  groups?: string[]; // Group paths, including subgroups ("eu" matches "eu/hetzner")
+  groupIds?: number[];
+  tagIds?: number[];
+}
+
 // Define the events and their payload types
 interface WebSocketEvents {
   open: void;
@@ -15,6 +28,9 @@ interface WebSocketEvents {
   full_server_list: FullServerListPushType;
   service_monitor_result: ServiceMonitorResult;
   performance_metric_batch: PerformanceMetricBatch;
+  virtual_groups: { groups: VirtualGroupMembers[] };
+  subscribed: WsSubscription;
+  alert_event: AlertEventPush;
   // Add other specific message types here
 }
 
@@ -25,6 +41,12 @@ class WebSocketService extends EventEmitter<WebSocketEvents> {
     private reconnectTimeoutId: number | null = null;
     private intentionalClose = false;
     private currentToken: string | null = null;
+    private subscription: WsSubscription | null = null;
+    // Server clock minus the local clock, from the last hello.
+    private serverClockOffset = 0;
+    // Estimated server time of the last message, sent as `since` on reconnect so the
+    // server can replay what was missed instead of sending a full snapshot.
+    private lastMessageAt: number | null = null;
 
     private throttledEmitFullServerList: (data: FullServerListPushType) => void;
 
@@ -38,10 +60,14 @@ class WebSocketService extends EventEmitter<WebSocketEvents> {
     private _getWebSocketUrl(token?: string | null): string {
         const urlPath = token ? '/ws/metrics' : '/ws/public';
         const url = new URL(urlPath, WS_URL_BASE);
+        url.searchParams.append('protocol', String(WS_PROTOCOL_VERSION));
 
         if (token) {
             url.searchParams.append('token', token);
         }
+        if (this.lastMessageAt !== null) {
+            url.searchParams.append('since', String(Math.floor(this.lastMessageAt)));
+        }
 
         return url.toString();
     }
@@ -53,6 +79,10 @@ class WebSocketService extends EventEmitter<WebSocketEvents> {
             return;
         }
 
+        if ((token || null) !== this.currentToken) {
+            // Missed updates of another session's view are no use.
+            this.lastMessageAt = null;
+        }
         this.currentToken = token || null;
         this.intentionalClose = false;
 
@@ -67,6 +97,9 @@ class WebSocketService extends EventEmitter<WebSocketEvents> {
                 clearTimeout(this.reconnectTimeoutId);
                 this.reconnectTimeoutId = null;
             }
+            if (this.subscription) {
+                this.send({ type: 'subscribe', data: this.subscription });
+            }
             this.emit('open', undefined);
         };
 
@@ -80,6 +113,10 @@ class WebSocketService extends EventEmitter<WebSocketEvents> {
             }
 
             if (parsedData && typeof parsedData === 'object') {
+                if (parsedData.type === 'hello' && typeof parsedData.data?.serverTimeMs === 'number') {
+                    this.serverClockOffset = parsedData.data.serverTimeMs - Date.now();
+                }
+                this.lastMessageAt = Date.now() + this.serverClockOffset;
                 // Case 1: Message has a 'type' field (structured messages)
                 if ('type' in parsedData) {
                     switch (parsedData.type) {
@@ -88,6 +125,14 @@ class WebSocketService extends EventEmitter<WebSocketEvents> {
                                 this.ws.send(JSON.stringify({ type: 'pong' }));
                             }
                             return;
+                        case 'hello':
+                            if (parsedData.data?.protocolVersion !== WS_PROTOCOL_VERSION) {
+                                console.warn(`WebSocketService: Server speaks protocol ${parsedData.data?.protocolVersion}, client expects ${WS_PROTOCOL_VERSION}.`);
+                            }
+                            return;
+                        case 'resumed':
+                            console.log(`WebSocketService: Resumed, ${parsedData.data?.messageCount} missed messages follow.`);
+                            return;
                         case 'connected':
                             console.log('WebSocketService: Received "connected" confirmation.');
                             return;
@@ -97,6 +142,15 @@ class WebSocketService extends EventEmitter<WebSocketEvents> {
                         case 'performance_metric_batch':
                             this.emit('performance_metric_batch', parsedData.data as PerformanceMetricBatch);
                             return;
+                        case 'subscribed':
+                            this.emit('subscribed', parsedData.data as WsSubscription);
+                            return;
+                        case 'virtual_groups':
+                            this.emit('virtual_groups', parsedData.data as { groups: VirtualGroupMembers[] });
+                            return;
+                        case 'alert_event':
+                            this.emit('alert_event', parsedData.data as AlertEventPush);
+                            return;
                         // Note: 'full_server_list' might not be used if the raw object is sent instead
                         case 'full_server_list':
                              this.throttledEmitFullServerList(parsedData.data as FullServerListPushType);
@@ -166,6 +220,18 @@ class WebSocketService extends EventEmitter<WebSocketEvents> {
             this.ws = null;
         }
         this.reconnectAttempts = 0;
+        this.lastMessageAt = null;
+    }
+
+    /**
+     * Limits this connection to the given VPS, groups or tags. The subscription is
+     * re-sent after reconnecting; pass null to receive the whole fleet again.
+     */
+    public subscribe(subscription: WsSubscription | null): void {
+        this.subscription = subscription;
+        if (this.ws && this.ws.readyState === WebSocket.OPEN) {
+            this.send({ type: 'subscribe', data: subscription ?? {} });
+        }
     }
 
     public send(message: object): void {
@@ -1,6 +1,6 @@
 import { create } from 'zustand';
 import { persist, createJSONStorage } from 'zustand/middleware';
-import { loginUser, registerUser, getMe } from '../services/authService';
+import { loginUser, registerUser, getMe, completeTwoFactorLogin, isTwoFactorChallenge } from '../services/authService';
 import type { LoginRequest, RegisterRequest, UserResponse, LoginResponse } from '../services/authService';
 import websocketService from '../services/websocketService';
 
@@ -10,7 +10,10 @@ interface AuthState {
     token: string | null;
     isLoading: boolean;
     error: string | null;
+    /** Set while a password login waits for its second factor. */
+    twoFactorChallenge: string | null;
     login: (credentials: LoginRequest) => Promise<void>;
+    verifyTwoFactor: (code: string) => Promise<void>;
     register: (userData: RegisterRequest) => Promise<void>;
     logout: () => void;
     setToken: (token: string | null) => void;
@@ -21,17 +24,23 @@ interface AuthState {
 
 export const useAuthStore = create<AuthState>()(
     persist(
-        (set) => ({
+        (set, get) => ({
             isAuthenticated: false,
             user: null,
             token: null,
             isLoading: false,
             error: null,
+            twoFactorChallenge: null,
 
             login: async (credentials: LoginRequest) => {
                 set({ isLoading: true, error: null });
                 try {
-                    const response: LoginResponse = await loginUser(credentials);
+                    const result = await loginUser(credentials);
+                    if (isTwoFactorChallenge(result)) {
+                        set({ twoFactorChallenge: result.challenge_token, isLoading: false, error: null });
+                        return;
+                    }
+                    const response: LoginResponse = result;
                     set({
                         isAuthenticated: true,
                         user: { id: response.user_id, username: response.username },
@@ -51,6 +60,31 @@ export const useAuthStore = create<AuthState>()(
                 }
             },
 
+            verifyTwoFactor: async (code: string) => {
+                const challenge = get().twoFactorChallenge;
+                if (!challenge) {
+                    return;
+                }
+                set({ isLoading: true, error: null });
+                try {
+                    const response = await completeTwoFactorLogin(challenge, code);
+                    set({
+                        isAuthenticated: true,
+                        user: { id: response.user_id, username: response.username },
+                        token: response.token,
+                        isLoading: false,
+                        error: null,
+                        twoFactorChallenge: null,
+                    });
+                    websocketService.disconnect();
+                    websocketService.connect(response.token);
+                } catch (err: unknown) {
+                    const errorMessage = err instanceof Error ? err.message : '验证时发生未知错误';
+                    set({ isLoading: false, error: errorMessage });
+                    throw err;
+                }
+            },
+
             register: async (userData: RegisterRequest) => {
                 set({ isLoading: true, error: null });
                 try {
@@ -67,7 +101,7 @@ export const useAuthStore = create<AuthState>()(
             },
 
             logout: () => {
-                set({ isAuthenticated: false, user: null, token: null, error: null });
+                set({ isAuthenticated: false, user: null, token: null, error: null, twoFactorChallenge: null });
                 // Disconnect the authenticated WS connection and reconnect to the public endpoint
                 websocketService.disconnect();
                 websocketService.connect(); // Reconnect without a token
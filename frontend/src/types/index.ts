@@ -129,6 +129,8 @@ export interface VpsListItemResponse {
   ipAddress: string | null; // camelCase
   osType: string | null;    // camelCase
   agentVersion?: string | null;
+  // Older than the latest agent release known to the server.
+  agentOutdated?: boolean;
   agentSecret?: string; // camelCase, optional as it's only in detail view
   status: ServerStatus;
   metadata: VpsMetadata | null; // Refined metadata type
@@ -136,6 +138,7 @@ export interface VpsListItemResponse {
   updatedAt: string; // camelCase
   tags?: Tag[];
   group?: string | null;
+  groupId?: number | null;
   configStatus: string;
   lastConfigUpdateAt?: string | null;
   lastConfigError?: string | null;
@@ -162,6 +165,75 @@ export interface VpsListItemResponse {
   autoRenewEnabled?: boolean | null;
   renewalNotes?: string | null;
   reminderActive?: boolean | null;
+
+  // Availability from agent online/offline transitions; null values mean no data yet.
+  uptime?: {
+    uptime24h: number | null;
+    uptime7d: number | null;
+    uptime30d: number | null;
+  } | null;
+
+  // Composite 0-100 health score; see HealthScore.
+  health?: HealthScore | null;
+
+  // Set while the VPS is in maintenance mode: alerts are skipped and the time is excluded from uptime.
+  maintenance?: MaintenanceState | null;
+
+  // Registered through a provisioning token; archived once offline for long enough, no uptime figures.
+  isEphemeral?: boolean;
+  archivedAt?: string | null;
+
+  // Location reported by the agent or resolved from its public IP; only the country is public.
+  countryCode?: string | null;
+  city?: string | null;
+  asn?: number | null;
+  asOrganization?: string | null;
+
+  // System-maintained collections this VPS belongs to, recomputed by the server.
+  virtualGroups?: VirtualGroup[];
+
+  // Latest reading of each GPU; empty for hosts without GPUs.
+  gpus?: GpuSnapshot[];
+}
+
+export interface GpuSnapshot {
+  time: string;
+  index: number;
+  name: string;
+  vendor: 'nvidia' | 'amd';
+  utilizationPercent: number;
+  memoryUsedBytes: number;
+  memoryTotalBytes: number;
+  temperatureCelsius: number | null;
+  powerDrawWatts: number | null;
+}
+
+/** renewal_due: within 7 days or overdue; over_traffic: above 90% of the cap; long_offline: offline for over 24h. */
+export type VirtualGroup = 'renewal_due' | 'over_traffic' | 'long_offline' | 'agent_outdated';
+
+export interface VirtualGroupMembers {
+  group: VirtualGroup;
+  vpsIds: number[];
+}
+
+export interface MaintenanceState {
+  since: string;
+  /** Scheduled automatic exit, if any. */
+  until: string | null;
+  reason: string | null;
+}
+
+/** Per-component scores are 0-100, or null when there is no data for them. */
+export interface HealthScore {
+  score: number | null;
+  components: {
+    cpu: number | null;
+    memory: number | null;
+    disk: number | null;
+    alerts: number | null;
+    freshness: number | null;
+    monitors: number | null;
+  };
 }
 
 /**
@@ -188,6 +260,15 @@ export interface AgentConfig {
   logLevel: string;
   heartbeatIntervalSeconds: number;
   serviceMonitorTasks: ServiceMonitorTask[];
+  /** How often agents ping their mesh peers; 0 turns the latency mesh off. */
+  meshPingIntervalSeconds?: number;
+  /** Filled in by the server. */
+  meshPeers?: MeshPeer[];
+}
+
+export interface MeshPeer {
+  vpsId: number;
+  address: string;
 }
 
 /**
@@ -245,6 +326,9 @@ export interface UpdateTagPayload {
 
 // --- Notification Channel Types ---
 
+/** Whether a channel sends notifications right away or in scheduled digests. */
+export type ChannelDelivery = 'immediate' | 'digest';
+
 /**
  * Defines the structure for a field in a channel template for the frontend.
  * This should match the `ChannelTemplateField` struct from the backend.
@@ -276,6 +360,14 @@ export interface ChannelResponse {
   name: string;
   channelType: string;
   configParams?: Record<string, unknown>; // Renamed from config and matches backend
+  /** Tera message templates keyed by locale ("en", "zh-CN") or "default". */
+  messageTemplates?: Record<string, string>;
+  /** Alert severities the channel receives; all of them when empty. */
+  severities?: AlertSeverity[];
+  delivery?: ChannelDelivery;
+  /** Five-field cron expression (UTC) of when digests go out. */
+  digestSchedule?: string | null;
+  nextDigestAt?: string | null;
 }
 
 /**
@@ -286,6 +378,10 @@ export interface CreateChannelRequest {
   name: string;
   channelType: string;
   config: Record<string, unknown>; // The raw config JSON from the frontend
+  messageTemplates?: Record<string, string>;
+  severities?: AlertSeverity[];
+  delivery?: ChannelDelivery;
+  digestSchedule?: string | null;
 }
 
 /**
@@ -295,9 +391,29 @@ export interface CreateChannelRequest {
 export interface UpdateChannelRequest {
   name?: string;
   config?: Record<string, unknown>;
+  /** Replaces all templates; {} goes back to the default. */
+  messageTemplates?: Record<string, string>;
+  severities?: AlertSeverity[];
+  delivery?: ChannelDelivery;
+  digestSchedule?: string | null;
+}
+
+/** A test notification as rendered for the channel. */
+export interface TestChannelResponse {
+  message: string;
+  rendered: string;
+  locale: string;
 }
 // --- Alert Rule Types ---
 
+export type AlertSeverity = 'info' | 'warning' | 'critical';
+export const ALERT_SEVERITIES: AlertSeverity[] = ['info', 'warning', 'critical'];
+
+/** AND/OR tree of metric conditions for composite alert rules. */
+export type AlertConditionExpression =
+  | { type: 'and' | 'or'; conditions: AlertConditionExpression[] }
+  | { type: 'metric'; metric: string; operator: string; threshold: number };
+
 export interface AlertRule {
   id: number;
   userId: number;
@@ -309,6 +425,20 @@ export interface AlertRule {
   durationSeconds: number;
   notificationChannelIds?: number[]; // To link to notification_channels table
   cooldownSeconds?: number;
+  /** Command script offered as a "Run remediation" button on notifications. */
+  remediationScriptId?: number | null;
+  /** Set for composite rules (metricType 'composite'). */
+  conditionExpression?: AlertConditionExpression | null;
+  /** Seconds between evaluations; the server default when null. */
+  evaluationIntervalSeconds?: number | null;
+  /** monitor_down rules: the watched monitor; every monitor when null. */
+  monitorId?: number | null;
+  /** monitor_down rules: consecutive successful checks that resolve the alert (default 2). */
+  recoveryThreshold?: number | null;
+  /** log_matches rules: regex the tailed log lines are matched against. */
+  logPattern?: string | null;
+  /** Only channels that receive this severity are notified. */
+  severity: AlertSeverity;
   isActive: boolean; // Added
   createdAt: string;
   updatedAt: string;
@@ -323,6 +453,17 @@ export interface CreateAlertRulePayload {
   durationSeconds: number;
   notificationChannelIds?: number[]; // Array of channel IDs
   cooldownSeconds?: number; // Added
+  remediationScriptId?: number | null;
+  /** When given, metricType, threshold and comparisonOperator are ignored. */
+  conditionExpression?: AlertConditionExpression | null;
+  /** Seconds between evaluations (10 to 86400); 0 on update goes back to the server default. */
+  evaluationIntervalSeconds?: number | null;
+  /** monitor_down rules, where threshold is the number of consecutive failed checks that fires the alert. */
+  monitorId?: number | null;
+  recoveryThreshold?: number | null;
+  /** log_matches rules, where threshold is compared with the number of matching lines within durationSeconds. */
+  logPattern?: string | null;
+  severity?: AlertSeverity;
 }
 
 export type UpdateAlertRulePayload = Partial<CreateAlertRulePayload>;
@@ -350,6 +491,8 @@ export interface VpsMetadata {
   total_disk_bytes?: number;   // uint64 in backend
   cpu_static_info?: CpuStaticInfo;
   country_code?: string; // Added for flag display
+  /** Set on synthetic servers generated in demo mode. */
+  demo?: boolean;
   // Add any other known metadata fields that might be present
   // Ensure keys match exactly what's sent from the backend (e.g., snake_case or camelCase)
   // Based on backend vps_service.rs, keys are snake_case
@@ -376,7 +519,13 @@ export interface PingMonitorConfig {
 
 export type TcpMonitorConfig = Record<string, never>;
 
-export type MonitorConfig = HttpMonitorConfig | PingMonitorConfig | TcpMonitorConfig;
+export interface DnsMonitorConfig {
+  record_type?: 'A' | 'AAAA' | 'CNAME' | 'MX' | 'TXT';
+  expected_values?: string[];
+  resolver?: string;
+}
+
+export type MonitorConfig = HttpMonitorConfig | PingMonitorConfig | TcpMonitorConfig | DnsMonitorConfig;
 
 
 /**
@@ -387,7 +536,7 @@ export interface ServiceMonitor {
   id: number;
   userId: number;
   name: string;
-  monitorType: 'http' | 'ping' | 'tcp';
+  monitorType: 'http' | 'ping' | 'tcp' | 'dns';
   target: string;
   frequencySeconds: number;
   timeoutSeconds: number;
@@ -409,7 +558,7 @@ export interface ServiceMonitor {
  */
 export interface ServiceMonitorInput {
   name: string;
-  monitorType: 'http' | 'ping' | 'tcp';
+  monitorType: 'http' | 'ping' | 'tcp' | 'dns';
   target: string;
   frequencySeconds?: number;
   timeoutSeconds?: number;
@@ -434,11 +583,36 @@ export interface ServiceMonitorResult {
   monitorName: string; // Added from the JOIN in the backend
   isUp: boolean;
   latencyMs: number | null;
+  /** Checks during a maintenance window count neither as up nor as down. */
+  inMaintenance?: boolean;
   details?: {
       status_code?: number;
       error?: string;
       message?: string;
+      certificate?: TlsCertificateInfo;
+      ping?: PingStats;
   };
+  /** Ping monitors only. */
+  packetLossPercent?: number;
+}
+
+/** Round-trip statistics of one ping check. */
+export interface PingStats {
+  packetsSent: number;
+  packetsReceived: number;
+  packetLossPercent: number;
+  rttMinMs: number;
+  rttAvgMs: number;
+  rttMaxMs: number;
+}
+
+/** Leaf certificate captured by an HTTPS monitor check. */
+export interface TlsCertificateInfo {
+  notBefore: string | null;
+  notAfter: string | null;
+  issuer: string;
+  subject: string;
+  subjectAltNames: string[];
 }
 
 // --- Batch Command & Scripting Types ---
@@ -454,6 +628,43 @@ export interface ChildCommandTaskDetail {
   agent_started_at: string | null;
   agent_completed_at: string | null;
   last_output_at: string | null;
+  /** `vps` for targets picked by ID, else the selector or saved selection that matched. */
+  matched_selector: string | null;
+  /** The wave of the batch's execution strategy the task ran in, from 0. */
+  wave: number;
+  /** 1 for the first run, counting up with each retry. */
+  attempt: number;
+  /** When a `Retrying` task runs again. */
+  next_attempt_at: string | null;
+}
+
+/** How the targets of a batch command are started. */
+export type ExecutionStrategy =
+  | { type: 'parallel' }
+  | { type: 'rolling'; max_concurrency: number; halt_on_failure?: boolean }
+  | {
+      type: 'canary';
+      max_concurrency?: number;
+      halt_on_failure?: boolean;
+      health_check?: { url: string; delay_seconds?: number; timeout_seconds?: number };
+    };
+
+/** Matches every VPS meeting all given criteria when the command is dispatched. */
+export interface TargetSelector {
+  tag_id?: number;
+  group_id?: number;
+  status?: string;
+}
+
+/** A named set of VPS IDs and selectors that batch commands can target. */
+export interface VpsSelection {
+  id: number;
+  user_id: number;
+  name: string;
+  target_vps_ids: number[];
+  selectors: TargetSelector[];
+  created_at: string;
+  updated_at: string;
 }
 
 export interface BatchCommandTaskDetailResponse {
@@ -468,6 +679,20 @@ export interface BatchCommandTaskDetailResponse {
   completed_at: string | null;
 }
 
+/** A typed value filled into `{{name}}` placeholders of a command script. */
+export type ScriptParameterType =
+    | { type: 'string'; max_length?: number | null }
+    | { type: 'enum'; options: string[] }
+    | { type: 'int'; min?: number | null; max?: number | null }
+    | { type: 'secret' };
+
+export type ScriptParameter = ScriptParameterType & {
+    name: string;
+    description?: string | null;
+    /** Parameters without a default must be given a value on every run. */
+    default?: string | null;
+};
+
 export interface CommandScript {
     id: number;
     user_id: number;
@@ -476,6 +701,7 @@ export interface CommandScript {
     language: 'shell' | 'powershell';
     script_content: string;
     working_directory: string;
+    parameters: ScriptParameter[];
     created_at: string;
     updated_at: string;
 }
@@ -484,4 +710,21 @@ export interface BulkActionResponse {
   message: string;
   successfulCount: number;
   failedCount: number;
-}
\ No newline at end of file
+}
+/** Lets agents register their own VPS on first connect (`provisioning_token` in the agent config). */
+export interface ProvisioningToken {
+  id: number;
+  userId: number;
+  name: string;
+  token: string;
+  group: string | null;
+  isEphemeral: boolean;
+  /** Tags attached to every registered VPS. */
+  tagIds: number[];
+  /** Total registrations allowed; null for unlimited. */
+  maxUses: number | null;
+  expiresAt: string | null;
+  useCount: number;
+  createdAt: string;
+  lastUsedAt: string | null;
+}
@@ -185,26 +185,13 @@ function Install-Agent {
     $exePath = Join-Path $installDir "agent.exe"
     Move-Item -Path $downloadPath -Destination $exePath -Force
 
-    # Create Service
+    # Create and start the service; the agent registers itself with the service manager.
     Write-Log "INFO" "Creating Windows service '$serviceName'..."
-    $quotedExePath = "`"$exePath`""
-    $quotedConfigPath = "`"$configPath`""
-    $binPath = "$quotedExePath --config $quotedConfigPath"
-    
-    sc.exe create $serviceName binPath= $binPath start= auto
-    sc.exe failure $serviceName reset= 86400 actions= restart/60000/restart/60000/restart/120000
-    
-    # Configure Environment Variables for the service (for updater)
-    $envPath = "HKLM:\SYSTEM\CurrentControlSet\Services\$serviceName\Environment"
-    if (-not (Test-Path -Path $envPath)) {
-        New-Item -Path $envPath -Force | Out-Null
+    & $exePath --install-service --config $configPath
+    if ($LASTEXITCODE -ne 0) {
+        Write-Log "ERROR" "Failed to install the Windows service."
+        exit 1
     }
-    Set-ItemProperty -Path $envPath -Name "NEXUS_AGENT_SERVICE_NAME" -Value $serviceName
-    Write-Log "INFO" "Service environment variables configured for auto-update."
-
-    # Start Service
-    Write-Log "INFO" "Starting service '$serviceName'..."
-    Start-Service -Name $serviceName
 
     Write-Log "SUCCESS" "Installation complete. The NodeNexus Agent is now running."
 }
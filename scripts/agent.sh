@@ -167,6 +167,21 @@ server_address = "$server_address"
 vps_id = $vps_id
 agent_secret = "$agent_secret"
 
+# Resolved server addresses are reused for this long, and kept as a fallback when DNS fails.
+# dns_cache_ttl_seconds = 300
+# Static IPs to try when the server host can't be resolved or reached.
+# server_fallback_ips = ["203.0.113.10", "2001:db8::10"]
+# More server addresses, tried in turn when the current one can't be reached.
+# server_addresses = ["https://backup.example.com:8080"]
+# Reconnect delays double from the initial value up to the maximum, with jitter.
+# reconnect_initial_delay_seconds = 5
+# reconnect_max_delay_seconds = 300
+# Client certificate for servers that require mutual TLS (issued per VPS in the dashboard).
+# client_cert_path = "/etc/nodenexus-agent/client.pem"
+# client_key_path = "/etc/nodenexus-agent/client.key"
+# CA to trust for a server with a private certificate, in addition to the system roots.
+# server_ca_cert_path = "/etc/nodenexus-agent/server-ca.pem"
+
 # Default values, can be adjusted later
 log_level = "info"
 heartbeat_interval_seconds = 30